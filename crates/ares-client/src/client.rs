@@ -0,0 +1,360 @@
+//! HTTP client for the A.R.E.S API.
+
+use crate::error::{Error, Result};
+use crate::sse::SseDecoder;
+use crate::types::*;
+use futures::{Stream, StreamExt};
+use reqwest::{Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Stream of events from [`AresClient::chat_stream`].
+pub type ChatStream = Pin<Box<dyn Stream<Item = Result<ChatStreamEvent>> + Send>>;
+
+#[derive(Debug, Default)]
+struct Session {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+}
+
+/// Typed client for an A.R.E.S server.
+///
+/// Cloning is cheap; clones share the connection pool and session, so a
+/// token obtained with [`login`](Self::login) is used by every clone.
+#[derive(Debug, Clone)]
+pub struct AresClient {
+    http: reqwest::Client,
+    base_url: String,
+    session: Arc<RwLock<Session>>,
+}
+
+/// Builder for [`AresClient`].
+#[derive(Debug)]
+pub struct AresClientBuilder {
+    base_url: String,
+    access_token: Option<String>,
+    timeout: Option<Duration>,
+    http: Option<reqwest::Client>,
+}
+
+impl AresClientBuilder {
+    /// Use an existing access token instead of logging in.
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.access_token = Some(token.into());
+        self
+    }
+
+    /// Set a timeout for non-streaming requests.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Use a preconfigured reqwest client (proxies, TLS roots, ...).
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    /// Build the client.
+    pub fn build(self) -> Result<AresClient> {
+        let base_url = self.base_url.trim_end_matches('/').to_string();
+        if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+            return Err(Error::InvalidUrl(self.base_url));
+        }
+
+        let http = match self.http {
+            Some(http) => http,
+            None => {
+                let mut builder = reqwest::Client::builder();
+                if let Some(timeout) = self.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                builder.build()?
+            }
+        };
+
+        Ok(AresClient {
+            http,
+            base_url,
+            session: Arc::new(RwLock::new(Session {
+                access_token: self.access_token,
+                refresh_token: None,
+            })),
+        })
+    }
+}
+
+impl AresClient {
+    /// Create a client for the server at `base_url` (e.g. `http://localhost:3000`).
+    pub fn new(base_url: impl Into<String>) -> Result<Self> {
+        Self::builder(base_url).build()
+    }
+
+    /// Start building a client for the server at `base_url`.
+    pub fn builder(base_url: impl Into<String>) -> AresClientBuilder {
+        AresClientBuilder {
+            base_url: base_url.into(),
+            access_token: None,
+            timeout: None,
+            http: None,
+        }
+    }
+
+    /// Base URL of the server.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Current access token, if authenticated.
+    pub fn access_token(&self) -> Option<String> {
+        self.session().access_token.clone()
+    }
+
+    /// Replace the access token used for authenticated requests.
+    pub fn set_token(&self, token: impl Into<String>) {
+        self.session_mut().access_token = Some(token.into());
+    }
+
+    // ============= Auth =============
+
+    /// Register a new account and authenticate as it.
+    pub async fn register(&self, email: &str, password: &str, name: &str) -> Result<TokenResponse> {
+        let body = RegisterRequest {
+            email,
+            password,
+            name,
+        };
+        let tokens: TokenResponse = self.send_public("/auth/register", &body).await?;
+        self.store_tokens(&tokens);
+        Ok(tokens)
+    }
+
+    /// Log in and authenticate subsequent requests.
+    pub async fn login(&self, email: &str, password: &str) -> Result<TokenResponse> {
+        let tokens: TokenResponse = self
+            .send_public("/auth/login", &LoginRequest { email, password })
+            .await?;
+        self.store_tokens(&tokens);
+        Ok(tokens)
+    }
+
+    /// Exchange the stored refresh token for a new token pair.
+    pub async fn refresh(&self) -> Result<TokenResponse> {
+        let refresh_token = self
+            .session()
+            .refresh_token
+            .clone()
+            .ok_or(Error::NotAuthenticated)?;
+        let tokens: TokenResponse = self
+            .send_public(
+                "/auth/refresh",
+                &RefreshTokenRequest {
+                    refresh_token: &refresh_token,
+                },
+            )
+            .await?;
+        self.store_tokens(&tokens);
+        Ok(tokens)
+    }
+
+    /// Invalidate the stored refresh token and forget the session.
+    pub async fn logout(&self) -> Result<()> {
+        let refresh_token = self.session().refresh_token.clone();
+        if let Some(refresh_token) = refresh_token {
+            let _: serde_json::Value = self
+                .send_public(
+                    "/auth/logout",
+                    &RefreshTokenRequest {
+                        refresh_token: &refresh_token,
+                    },
+                )
+                .await?;
+        }
+        *self.session_mut() = Session::default();
+        Ok(())
+    }
+
+    // ============= Chat =============
+
+    /// Send a chat message and wait for the full response.
+    pub async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        self.send(Method::POST, "/chat", Some(request)).await
+    }
+
+    /// Send a chat message and stream the response as it is generated.
+    ///
+    /// Dropping the stream closes the connection, which cancels the
+    /// generation on the server.
+    pub async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream> {
+        let response = self
+            .authed(Method::POST, "/chat/stream")?
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .json(request)
+            .send()
+            .await?;
+        let mut bytes = check_status(response).await?.bytes_stream();
+
+        let stream = async_stream::stream! {
+            let mut decoder = SseDecoder::default();
+            while let Some(chunk) = bytes.next().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        yield Err(Error::Http(e));
+                        return;
+                    }
+                };
+                for data in decoder.feed(&chunk) {
+                    yield serde_json::from_str::<ChatStreamEvent>(&data).map_err(Error::from);
+                }
+            }
+        };
+        Ok(Box::pin(stream))
+    }
+
+    // ============= Research =============
+
+    /// Run a deep research query.
+    pub async fn research(&self, request: &ResearchRequest) -> Result<ResearchResponse> {
+        self.send(Method::POST, "/research", Some(request)).await
+    }
+
+    // ============= RAG =============
+
+    /// Ingest a document into a collection.
+    pub async fn rag_ingest(&self, request: &RagIngestRequest) -> Result<RagIngestResponse> {
+        self.send(Method::POST, "/rag/ingest", Some(request)).await
+    }
+
+    /// Search a collection.
+    pub async fn rag_search(&self, request: &RagSearchRequest) -> Result<RagSearchResponse> {
+        self.send(Method::POST, "/rag/search", Some(request)).await
+    }
+
+    /// List the current user's collections.
+    pub async fn rag_collections(&self) -> Result<Vec<CollectionInfo>> {
+        self.send::<(), _>(Method::GET, "/rag/collections", None)
+            .await
+    }
+
+    /// Delete a collection and all of its documents.
+    pub async fn rag_delete_collection(
+        &self,
+        collection: &str,
+    ) -> Result<RagDeleteCollectionResponse> {
+        self.send(
+            Method::DELETE,
+            "/rag/collection",
+            Some(&RagDeleteCollectionRequest { collection }),
+        )
+        .await
+    }
+
+    // ============= Internals =============
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/api{}", self.base_url, path)
+    }
+
+    fn session(&self) -> std::sync::RwLockReadGuard<'_, Session> {
+        self.session.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn session_mut(&self) -> std::sync::RwLockWriteGuard<'_, Session> {
+        self.session.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn store_tokens(&self, tokens: &TokenResponse) {
+        let mut session = self.session_mut();
+        session.access_token = Some(tokens.access_token.clone());
+        session.refresh_token = Some(tokens.refresh_token.clone());
+    }
+
+    fn authed(&self, method: Method, path: &str) -> Result<RequestBuilder> {
+        let token = self.access_token().ok_or(Error::NotAuthenticated)?;
+        Ok(self.http.request(method, self.url(path)).bearer_auth(token))
+    }
+
+    async fn send<B: Serialize, T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<T> {
+        let mut request = self.authed(method, path)?;
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        decode(request.send().await?).await
+    }
+
+    async fn send_public<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T> {
+        let response = self.http.post(self.url(path)).json(body).send().await?;
+        decode(response).await
+    }
+}
+
+/// Turn a non-success response into [`Error::Api`].
+async fn check_status(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let text = response.text().await.unwrap_or_default();
+    let body: Option<serde_json::Value> = serde_json::from_str(&text).ok();
+    let field = |name: &str| {
+        body.as_ref()
+            .and_then(|b| b.get(name))
+            .and_then(|v| v.as_str())
+            .map(String::from)
+    };
+
+    Err(Error::Api {
+        status: status.as_u16(),
+        code: field("code"),
+        message: field("error").unwrap_or(text),
+    })
+}
+
+async fn decode<T: DeserializeOwned>(response: Response) -> Result<T> {
+    let bytes = check_status(response).await?.bytes().await?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_url_validation() {
+        let client = AresClient::new("http://localhost:3000/").unwrap();
+        assert_eq!(client.base_url(), "http://localhost:3000");
+        assert_eq!(client.url("/chat"), "http://localhost:3000/api/chat");
+
+        assert!(matches!(
+            AresClient::new("localhost:3000"),
+            Err(Error::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn test_requests_without_token_fail_fast() {
+        let client = AresClient::new("http://localhost:3000").unwrap();
+        assert!(matches!(
+            client.authed(Method::GET, "/memory"),
+            Err(Error::NotAuthenticated)
+        ));
+
+        client.set_token("abc");
+        assert!(client.authed(Method::GET, "/memory").is_ok());
+    }
+}
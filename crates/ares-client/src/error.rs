@@ -0,0 +1,48 @@
+//! Error types for ares-client.
+
+use thiserror::Error;
+
+/// Result type for ares-client operations.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors returned by [`AresClient`](crate::AresClient).
+#[derive(Error, Debug)]
+pub enum Error {
+    /// Transport failure (connection refused, timeout, TLS, ...).
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The server answered with a non-success status.
+    #[error("API error ({status}): {message}")]
+    Api {
+        /// HTTP status code.
+        status: u16,
+        /// Machine-readable error code (e.g. `BUDGET_EXCEEDED`), if provided.
+        code: Option<String>,
+        /// Human-readable error message.
+        message: String,
+    },
+
+    /// The server response could not be decoded.
+    #[error("Failed to decode response: {0}")]
+    Decode(#[from] serde_json::Error),
+
+    /// The base URL is not a valid HTTP(S) URL.
+    #[error("Invalid base URL: {0}")]
+    InvalidUrl(String),
+
+    /// The request requires an access token but none is set.
+    #[error("Not authenticated: call login() or set a token first")]
+    NotAuthenticated,
+}
+
+impl Error {
+    /// HTTP status of an [`Error::Api`] error.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::Api { status, .. } => Some(*status),
+            Error::Http(e) => e.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+}
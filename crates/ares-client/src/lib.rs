@@ -0,0 +1,52 @@
+//! # ares-client
+//!
+//! Typed Rust client for the [A.R.E.S](https://github.com/dirmacs/ares) API,
+//! covering authentication, chat (including SSE streaming), deep research
+//! and RAG.
+//!
+//! ## Quick Start
+//!
+//! ```rust,ignore
+//! use ares_client::{AresClient, ChatRequest, ChatStreamEvent};
+//! use futures::StreamExt;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), ares_client::Error> {
+//!     let client = AresClient::new("http://localhost:3000")?;
+//!     client.login("user@example.com", "password").await?;
+//!
+//!     // Full response
+//!     let reply = client.chat(&ChatRequest::new("What is our refund policy?")).await?;
+//!     println!("{} answered: {}", reply.agent, reply.response);
+//!
+//!     // Streamed response
+//!     let mut stream = client
+//!         .chat_stream(&ChatRequest::new("Tell me more").with_context_id(reply.context_id))
+//!         .await?;
+//!     while let Some(event) = stream.next().await {
+//!         if let ChatStreamEvent::Token { content } = event? {
+//!             print!("{}", content);
+//!         }
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+#![warn(missing_docs)]
+#![warn(clippy::all)]
+
+pub mod client;
+pub mod error;
+mod sse;
+pub mod types;
+
+// Re-exports for convenience
+pub use client::{AresClient, AresClientBuilder, ChatStream};
+pub use error::{Error, Result};
+pub use types::{
+    ChatRequest, ChatResponse, ChatStreamEvent, CollectionInfo, DocumentMetadata,
+    RagDeleteCollectionResponse, RagIngestRequest, RagIngestResponse, RagSearchRequest,
+    RagSearchResponse, RagSearchResult, ResearchRequest, ResearchResponse, Source, StreamUsage,
+    TokenResponse,
+};
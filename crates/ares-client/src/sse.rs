@@ -0,0 +1,59 @@
+//! Minimal Server-Sent Events decoder.
+//!
+//! Splits a byte stream into event `data` payloads. Comments (the server's
+//! `:keep-alive` lines) and fields other than `data` are ignored.
+
+/// Incremental SSE decoder; feed it chunks as they arrive.
+#[derive(Debug, Default)]
+pub(crate) struct SseDecoder {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    /// Consume a chunk, returning the data of every event it completes.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                // Blank line dispatches the pending event
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data
+                    .push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_split_across_chunks() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.feed(b"data: {\"a\"").is_empty());
+        assert_eq!(
+            decoder.feed(b":1}\n\ndata: two\r\n\r\n"),
+            vec!["{\"a\":1}", "two"]
+        );
+    }
+
+    #[test]
+    fn test_comments_and_multiline_data() {
+        let mut decoder = SseDecoder::default();
+        let events = decoder.feed(b":keep-alive\n\nevent: x\ndata: line1\ndata:line2\n\n");
+        assert_eq!(events, vec!["line1\nline2"]);
+    }
+}
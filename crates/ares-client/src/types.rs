@@ -0,0 +1,343 @@
+//! Request and response types of the A.R.E.S API.
+//!
+//! These mirror the JSON bodies of the server endpoints under `/api`.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// ============= Auth =============
+
+/// Access and refresh tokens returned by login, registration and refresh.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenResponse {
+    /// JWT access token for API authentication.
+    pub access_token: String,
+    /// Refresh token for obtaining new access tokens.
+    pub refresh_token: String,
+    /// Time in seconds until the access token expires.
+    pub expires_in: i64,
+}
+
+#[derive(Serialize)]
+pub(crate) struct LoginRequest<'a> {
+    pub email: &'a str,
+    pub password: &'a str,
+}
+
+#[derive(Serialize)]
+pub(crate) struct RegisterRequest<'a> {
+    pub email: &'a str,
+    pub password: &'a str,
+    pub name: &'a str,
+}
+
+#[derive(Serialize)]
+pub(crate) struct RefreshTokenRequest<'a> {
+    pub refresh_token: &'a str,
+}
+
+// ============= Chat =============
+
+/// Request payload for `/api/chat` and `/api/chat/stream`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatRequest {
+    /// The user's message.
+    pub message: String,
+    /// Agent to handle the request (e.g. `"product"`); the router decides when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_type: Option<String>,
+    /// Conversation to continue; a new one is created when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_id: Option<String>,
+}
+
+impl ChatRequest {
+    /// Create a request for `message`, letting the router pick the agent.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            agent_type: None,
+            context_id: None,
+        }
+    }
+
+    /// Send the request to a specific agent.
+    pub fn with_agent(mut self, agent: impl Into<String>) -> Self {
+        self.agent_type = Some(agent.into());
+        self
+    }
+
+    /// Continue an existing conversation.
+    pub fn with_context_id(mut self, context_id: impl Into<String>) -> Self {
+        self.context_id = Some(context_id.into());
+        self
+    }
+}
+
+/// Response from `/api/chat`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatResponse {
+    /// The agent's response text.
+    pub response: String,
+    /// The agent that handled the request.
+    pub agent: String,
+    /// Context ID for continuing this conversation.
+    pub context_id: String,
+    /// Sources used to generate the response, if any.
+    pub sources: Option<Vec<Source>>,
+}
+
+/// An event of a streamed chat response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum ChatStreamEvent {
+    /// Generation started.
+    Start {
+        /// The agent handling the request.
+        agent: String,
+        /// Conversation context ID.
+        context_id: String,
+    },
+    /// A generated chunk of text.
+    Token {
+        /// Token content.
+        content: String,
+    },
+    /// Estimated token usage so far; sent periodically and once before `Done`.
+    Usage {
+        /// Usage totals.
+        usage: StreamUsage,
+    },
+    /// Generation finished.
+    Done {
+        /// The agent that handled the request.
+        agent: String,
+        /// Conversation context ID.
+        context_id: String,
+    },
+    /// Generation failed; no further events follow.
+    Error {
+        /// Error message.
+        error: String,
+        /// Conversation context ID, if known.
+        context_id: Option<String>,
+    },
+}
+
+/// Estimated token usage and cost of a streamed response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamUsage {
+    /// Estimated prompt tokens.
+    pub prompt_tokens: u32,
+    /// Estimated completion tokens generated so far.
+    pub completion_tokens: u32,
+    /// Sum of prompt and completion tokens.
+    pub total_tokens: u32,
+    /// Estimated cost in USD, based on the server's model pricing.
+    pub estimated_cost: f64,
+}
+
+/// A source reference used in responses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Source {
+    /// Title of the source document or webpage.
+    pub title: String,
+    /// URL of the source, if available.
+    pub url: Option<String>,
+    /// Relevance score (0.0 to 1.0).
+    pub relevance_score: f32,
+}
+
+// ============= Research =============
+
+/// Request payload for `/api/research`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResearchRequest {
+    /// The research question.
+    pub query: String,
+    /// Maximum depth for recursive research (server default: 3).
+    pub depth: Option<u8>,
+    /// Maximum iterations across all agents (server default: 10).
+    pub max_iterations: Option<u8>,
+}
+
+impl ResearchRequest {
+    /// Create a research request with server-default depth and iterations.
+    pub fn new(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            depth: None,
+            max_iterations: None,
+        }
+    }
+}
+
+/// Response from `/api/research`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResearchResponse {
+    /// The compiled research findings.
+    pub findings: String,
+    /// Sources discovered during research.
+    pub sources: Vec<Source>,
+    /// Time taken in milliseconds.
+    pub duration_ms: u64,
+}
+
+// ============= RAG =============
+
+/// Request to ingest a document via `/api/rag/ingest`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagIngestRequest {
+    /// Collection to ingest into.
+    pub collection: String,
+    /// The text content to ingest.
+    pub content: String,
+    /// Document title.
+    pub title: Option<String>,
+    /// Source URL or path.
+    pub source: Option<String>,
+    /// Tags for categorization.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Chunking strategy (e.g. `"word"`, `"semantic"`).
+    pub chunking_strategy: Option<String>,
+}
+
+impl RagIngestRequest {
+    /// Create an ingest request with default chunking and no metadata.
+    pub fn new(collection: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            collection: collection.into(),
+            content: content.into(),
+            title: None,
+            source: None,
+            tags: Vec::new(),
+            chunking_strategy: None,
+        }
+    }
+}
+
+/// Response from `/api/rag/ingest`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagIngestResponse {
+    /// Number of chunks created.
+    pub chunks_created: usize,
+    /// Document IDs created.
+    pub document_ids: Vec<String>,
+    /// Collection name.
+    pub collection: String,
+}
+
+/// Request to search via `/api/rag/search`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagSearchRequest {
+    /// Collection to search.
+    pub collection: String,
+    /// The search query.
+    pub query: String,
+    /// Maximum results to return.
+    pub limit: usize,
+    /// Search strategy: `semantic`, `bm25`, `fuzzy` or `hybrid`.
+    pub strategy: Option<String>,
+    /// Minimum similarity threshold (0.0 to 1.0).
+    pub threshold: f32,
+    /// Whether to rerank results.
+    pub rerank: bool,
+    /// Reranker to use when reranking: `cross-encoder`, `cohere` or `llm`.
+    pub reranker: Option<String>,
+    /// Reranker model to use when reranking.
+    pub reranker_model: Option<String>,
+    /// Whether to skip retrieval for greetings and small talk (server default when unset).
+    pub classify_intent: Option<bool>,
+}
+
+impl RagSearchRequest {
+    /// Create a search request returning up to 10 results with the server's default strategy.
+    pub fn new(collection: impl Into<String>, query: impl Into<String>) -> Self {
+        Self {
+            collection: collection.into(),
+            query: query.into(),
+            limit: 10,
+            strategy: None,
+            threshold: 0.0,
+            rerank: false,
+            reranker: None,
+            reranker_model: None,
+            classify_intent: None,
+        }
+    }
+}
+
+/// Metadata stored with an ingested document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentMetadata {
+    /// Title of the document.
+    #[serde(default)]
+    pub title: String,
+    /// Source of the document.
+    #[serde(default)]
+    pub source: String,
+    /// When the document was ingested.
+    pub created_at: DateTime<Utc>,
+    /// Tags for categorization.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A single search result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagSearchResult {
+    /// Document ID.
+    pub id: String,
+    /// Matching text content.
+    pub content: String,
+    /// Relevance score.
+    pub score: f32,
+    /// Document metadata.
+    pub metadata: DocumentMetadata,
+}
+
+/// Response from `/api/rag/search`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagSearchResponse {
+    /// Search results.
+    pub results: Vec<RagSearchResult>,
+    /// Total number of results before limit.
+    pub total: usize,
+    /// Search strategy used.
+    pub strategy: String,
+    /// Whether reranking was applied.
+    pub reranked: bool,
+    /// Whether retrieval was skipped because the query is small talk.
+    #[serde(default)]
+    pub retrieval_skipped: bool,
+    /// Query processing time in milliseconds.
+    pub duration_ms: u64,
+}
+
+#[derive(Serialize)]
+pub(crate) struct RagDeleteCollectionRequest<'a> {
+    pub collection: &'a str,
+}
+
+/// Response from deleting a collection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagDeleteCollectionResponse {
+    /// Whether deletion was successful.
+    pub success: bool,
+    /// Collection that was deleted.
+    pub collection: String,
+    /// Number of documents deleted.
+    pub documents_deleted: usize,
+}
+
+/// A RAG collection owned by the current user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionInfo {
+    /// Collection name.
+    pub name: String,
+    /// Number of documents in the collection.
+    pub document_count: usize,
+    /// Vector dimensions.
+    pub dimensions: usize,
+}
@@ -0,0 +1,156 @@
+//! Integration tests for ares-client against a mocked A.R.E.S server.
+
+use ares_client::{AresClient, ChatRequest, ChatStreamEvent, Error};
+use futures::StreamExt;
+use serde_json::json;
+use wiremock::matchers::{body_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn logged_in_client(server: &MockServer) -> AresClient {
+    Mock::given(method("POST"))
+        .and(path("/api/auth/login"))
+        .and(body_json(
+            json!({"email": "a@example.com", "password": "secret"}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": "access-1",
+            "refresh_token": "refresh-1",
+            "expires_in": 900
+        })))
+        .mount(server)
+        .await;
+
+    let client = AresClient::new(server.uri()).unwrap();
+    client.login("a@example.com", "secret").await.unwrap();
+    client
+}
+
+#[tokio::test]
+async fn test_login_authenticates_chat() {
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/chat"))
+        .and(header("authorization", "Bearer access-1"))
+        .and(body_json(json!({"message": "hi", "agent_type": "product"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "response": "hello",
+            "agent": "Product (system)",
+            "context_id": "ctx-1",
+            "sources": null
+        })))
+        .mount(&server)
+        .await;
+
+    let reply = client
+        .chat(&ChatRequest::new("hi").with_agent("product"))
+        .await
+        .unwrap();
+    assert_eq!(reply.response, "hello");
+    assert_eq!(reply.context_id, "ctx-1");
+}
+
+#[tokio::test]
+async fn test_api_errors_are_typed() {
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/chat"))
+        .respond_with(ResponseTemplate::new(429).set_body_json(json!({
+            "error": "User daily budget exceeded",
+            "code": "BUDGET_EXCEEDED"
+        })))
+        .mount(&server)
+        .await;
+
+    match client.chat(&ChatRequest::new("hi")).await {
+        Err(Error::Api {
+            status,
+            code,
+            message,
+        }) => {
+            assert_eq!(status, 429);
+            assert_eq!(code.as_deref(), Some("BUDGET_EXCEEDED"));
+            assert_eq!(message, "User daily budget exceeded");
+        }
+        other => panic!("expected API error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_chat_stream_yields_events() {
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+
+    let body = concat!(
+        "data: {\"event\":\"start\",\"agent\":\"router (system)\",\"context_id\":\"ctx-1\"}\n\n",
+        ":keep-alive\n\n",
+        "data: {\"event\":\"token\",\"content\":\"Hel\"}\n\n",
+        "data: {\"event\":\"token\",\"content\":\"lo\"}\n\n",
+        "data: {\"event\":\"usage\",\"usage\":{\"prompt_tokens\":10,\"completion_tokens\":2,\"total_tokens\":12,\"estimated_cost\":0.0}}\n\n",
+        "data: {\"event\":\"done\",\"agent\":\"Router (system)\",\"context_id\":\"ctx-1\"}\n\n",
+    );
+    Mock::given(method("POST"))
+        .and(path("/api/chat/stream"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+        .mount(&server)
+        .await;
+
+    let events: Vec<_> = client
+        .chat_stream(&ChatRequest::new("hi"))
+        .await
+        .unwrap()
+        .map(|e| e.unwrap())
+        .collect()
+        .await;
+
+    assert_eq!(events.len(), 5);
+    let text: String = events
+        .iter()
+        .filter_map(|e| match e {
+            ChatStreamEvent::Token { content } => Some(content.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(text, "Hello");
+    assert!(matches!(
+        &events[3],
+        ChatStreamEvent::Usage { usage } if usage.total_tokens == 12
+    ));
+    assert!(matches!(events.last(), Some(ChatStreamEvent::Done { .. })));
+}
+
+#[tokio::test]
+async fn test_refresh_and_logout_use_stored_token() {
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/auth/refresh"))
+        .and(body_json(json!({"refresh_token": "refresh-1"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": "access-2",
+            "refresh_token": "refresh-2",
+            "expires_in": 900
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/auth/logout"))
+        .and(body_json(json!({"refresh_token": "refresh-2"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"message": "ok"})))
+        .mount(&server)
+        .await;
+
+    client.refresh().await.unwrap();
+    assert_eq!(client.access_token().as_deref(), Some("access-2"));
+
+    client.logout().await.unwrap();
+    assert!(client.access_token().is_none());
+    assert!(matches!(
+        client.chat(&ChatRequest::new("hi")).await,
+        Err(Error::NotAuthenticated)
+    ));
+}
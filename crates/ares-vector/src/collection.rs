@@ -4,11 +4,14 @@
 //! and distance metric.
 
 use crate::config::HnswConfig;
-use crate::distance::DistanceMetric;
-use crate::error::Result;
+use crate::distance::{self, DistanceMetric};
+use crate::error::{Error, Result};
 use crate::index::HnswIndex;
+use crate::persistence::{self, DirtySegments};
 use crate::types::{SearchResult, VectorMetadata};
+use crate::verify::Issue;
 use crate::{CollectionStats, HnswParams};
+use parking_lot::Mutex;
 use std::sync::Arc;
 
 /// A named collection of vectors.
@@ -29,6 +32,10 @@ pub struct Collection {
     index: Arc<HnswIndex>,
     /// HNSW configuration.
     hnsw_config: HnswConfig,
+    /// Segments changed since the collection was last saved.
+    dirty: Mutex<DirtySegments>,
+    /// Held while the collection is saved.
+    save_lock: tokio::sync::Mutex<()>,
 }
 
 impl Collection {
@@ -47,6 +54,8 @@ impl Collection {
             metric,
             index: Arc::new(index),
             hnsw_config,
+            dirty: Mutex::new(DirtySegments::all()),
+            save_lock: tokio::sync::Mutex::new(()),
         })
     }
 
@@ -77,6 +86,7 @@ impl Collection {
 
     /// Insert a vector.
     pub fn insert(&self, id: &str, vector: &[f32], metadata: Option<VectorMetadata>) -> Result<()> {
+        self.dirty.lock().mark(id);
         self.index.insert(id, vector, metadata)
     }
 
@@ -85,21 +95,30 @@ impl Collection {
     where
         I: IntoIterator<Item = (&'a str, &'a [f32], Option<VectorMetadata>)>,
     {
-        self.index.insert_batch(vectors)
+        self.index.insert_batch(
+            vectors
+                .into_iter()
+                .inspect(|(id, _, _)| self.dirty.lock().mark(id)),
+        )
     }
 
     /// Update a vector.
     pub fn update(&self, id: &str, vector: &[f32], metadata: Option<VectorMetadata>) -> Result<()> {
+        self.dirty.lock().mark(id);
         self.index.update(id, vector, metadata)
     }
 
     /// Delete a vector.
     pub fn delete(&self, id: &str) -> Result<bool> {
+        self.dirty.lock().mark(id);
         self.index.delete(id)
     }
 
     /// Delete multiple vectors.
     pub fn delete_batch(&self, ids: &[&str]) -> Result<usize> {
+        let mut dirty = self.dirty.lock();
+        ids.iter().for_each(|id| dirty.mark(id));
+        drop(dirty);
         self.index.delete_batch(ids)
     }
 
@@ -133,6 +152,11 @@ impl Collection {
         self.index.compact()
     }
 
+    /// Check that the index's mappings, vectors and graph agree.
+    pub fn verify(&self) -> Vec<Issue> {
+        self.index.verify()
+    }
+
     /// Get collection statistics.
     pub fn stats(&self) -> CollectionStats {
         CollectionStats {
@@ -160,6 +184,70 @@ impl Collection {
         &self.index
     }
 
+    /// Copy the collection into a new one using `metric`.
+    ///
+    /// Vectors are normalized if the new metric needs unit-length vectors.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a vector has length zero and cannot be normalized.
+    pub fn with_metric(&self, metric: DistanceMetric) -> Result<Collection> {
+        let mut vectors = self.export_all();
+        if metric.needs_normalized() {
+            for (id, vector, _) in &mut vectors {
+                if !distance::normalize(vector) {
+                    return Err(Error::InvalidVector(format!(
+                        "Vector '{}' has length zero and cannot be normalized for {}",
+                        id, metric
+                    )));
+                }
+            }
+        }
+
+        let collection = Collection::new(
+            self.name.clone(),
+            self.dimensions,
+            metric,
+            self.hnsw_config.clone(),
+        )?;
+        collection.insert_batch(
+            vectors
+                .iter()
+                .map(|(id, vector, meta)| (id.as_str(), vector.as_slice(), meta.clone())),
+        )?;
+        Ok(collection)
+    }
+
+    /// Export the vectors stored in a segment file.
+    pub(crate) fn export_segment(
+        &self,
+        segment: usize,
+    ) -> Vec<(String, Vec<f32>, Option<VectorMetadata>)> {
+        self.index
+            .export_where(|id| persistence::segment_of(id) == segment)
+    }
+
+    /// Mark every segment as changed, so the next save rewrites them all.
+    pub(crate) fn mark_all_dirty(&self) {
+        *self.dirty.lock() = DirtySegments::all();
+    }
+
+    /// Take the segments changed since the last save, marking them saved.
+    pub(crate) fn take_dirty(&self) -> DirtySegments {
+        std::mem::take(&mut *self.dirty.lock())
+    }
+
+    /// Mark segments taken with [`take_dirty`](Self::take_dirty) as changed
+    /// again, after they failed to save.
+    pub(crate) fn restore_dirty(&self, dirty: DirtySegments) {
+        self.dirty.lock().merge(dirty);
+    }
+
+    /// Lock held while the collection is saved.
+    pub(crate) fn save_lock(&self) -> &tokio::sync::Mutex<()> {
+        &self.save_lock
+    }
+
     /// Export all vectors for persistence.
     ///
     /// Returns a vector of (id, vector, metadata) tuples.
@@ -20,6 +20,10 @@ pub struct Config {
 
     /// Interval for automatic persistence in seconds.
     pub persist_interval_secs: u64,
+
+    /// Per-collection write queue configuration.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub write_queue: WriteQueueConfig,
 }
 
 impl Default for Config {
@@ -30,6 +34,7 @@ impl Default for Config {
             max_vectors: 0,
             auto_persist: false,
             persist_interval_secs: 300,
+            write_queue: WriteQueueConfig::default(),
         }
     }
 }
@@ -76,6 +81,44 @@ impl Config {
         self.persist_interval_secs = secs;
         self
     }
+
+    /// Set the per-collection write queue configuration.
+    pub fn with_write_queue(mut self, config: WriteQueueConfig) -> Self {
+        self.write_queue = config;
+        self
+    }
+}
+
+/// Per-collection write queue configuration.
+///
+/// Writes to a collection queue up behind each other so that bulk
+/// ingestion can't starve searches on the same collection. See
+/// [`crate::throttle`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct WriteQueueConfig {
+    /// Maximum number of vectors pending in a collection's queue
+    /// (0 = unlimited). Default: 10000.
+    pub max_pending: usize,
+
+    /// How long a write waits for room in a full queue before it is
+    /// rejected, in milliseconds (0 = reject at once). Default: 5000.
+    pub max_wait_ms: u64,
+
+    /// Number of vectors written at a time. Searches run between chunks.
+    /// Default: 256.
+    pub chunk_size: usize,
+}
+
+impl Default for WriteQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_pending: 10_000,
+            max_wait_ms: 5_000,
+            chunk_size: 256,
+        }
+    }
 }
 
 /// HNSW index configuration.
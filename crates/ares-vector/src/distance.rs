@@ -106,6 +106,14 @@ impl DistanceMetric {
         matches!(self, DistanceMetric::Euclidean | DistanceMetric::Manhattan)
     }
 
+    /// Returns true if this metric assumes unit-length vectors.
+    ///
+    /// The HNSW dot product distance is `1 - a·b`, which only ranks vectors
+    /// by similarity when they are normalized.
+    pub fn needs_normalized(&self) -> bool {
+        matches!(self, DistanceMetric::DotProduct)
+    }
+
     /// Get the name of this distance metric.
     pub fn name(&self) -> &'static str {
         match self {
@@ -238,6 +246,20 @@ fn dot_product(a: &[f32], b: &[f32]) -> f32 {
     sum
 }
 
+/// Scale a vector to unit length in place.
+///
+/// Returns false, leaving the vector unchanged, if it has length zero.
+pub fn normalize(vector: &mut [f32]) -> bool {
+    let norm = dot_product(vector, vector).sqrt();
+    if norm == 0.0 {
+        return false;
+    }
+    for value in vector.iter_mut() {
+        *value /= norm;
+    }
+    true
+}
+
 /// Compute Manhattan (L1) distance between two vectors.
 #[inline]
 fn manhattan_distance(a: &[f32], b: &[f32]) -> f32 {
@@ -318,6 +340,17 @@ impl HnswDistance for ManhattanDistance {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_normalize() {
+        let mut v = vec![3.0, 4.0];
+        assert!(normalize(&mut v));
+        assert_eq!(v, vec![0.6, 0.8]);
+
+        let mut zero = vec![0.0, 0.0];
+        assert!(!normalize(&mut zero));
+        assert_eq!(zero, vec![0.0, 0.0]);
+    }
+
     #[test]
     fn test_cosine_identical() {
         let a = vec![1.0, 0.0, 0.0];
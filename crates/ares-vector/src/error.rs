@@ -41,6 +41,10 @@ pub enum Error {
     #[error("Persistence error: {0}")]
     Persistence(String),
 
+    /// A collection's write queue stayed full.
+    #[error("Write queue of collection '{0}' is full")]
+    QueueFull(String),
+
     /// Configuration error.
     #[error("Configuration error: {0}")]
     Configuration(String),
@@ -7,10 +7,11 @@ use crate::config::HnswConfig;
 use crate::distance::DistanceMetric;
 use crate::error::{Error, Result};
 use crate::types::{SearchResult, VectorId, VectorMetadata};
-use anndists::dist::distances::{DistCosine, DistDot, DistL1, DistL2};
+use crate::verify::{Issue, IssueKind};
+use anndists::dist::distances::{DistCosine, DistDot, DistL1, DistL2, Distance};
 use hnsw_rs::hnsw::Hnsw;
 use parking_lot::RwLock;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use tracing::{debug, trace};
 
@@ -192,8 +193,9 @@ impl HnswIndex {
             metadata.insert(internal_id, m);
         }
 
-        // Insert into HNSW index
-        let inner = self.inner.write();
+        // Insert into HNSW index. The graph synchronizes inserts itself, so
+        // a read lock lets searches run alongside.
+        let inner = self.inner.read();
         match &*inner {
             IndexInner::Cosine(hnsw) => {
                 hnsw.insert((vector, internal_id));
@@ -272,7 +274,7 @@ impl HnswIndex {
 
         // Batch insert into HNSW
         if !batch_data.is_empty() {
-            let inner = self.inner.write();
+            let inner = self.inner.read();
             let refs: Vec<(&Vec<f32>, usize)> = batch_data.iter().map(|(v, id)| (v, *id)).collect();
 
             match &*inner {
@@ -518,6 +520,87 @@ impl HnswIndex {
         Ok(())
     }
 
+    /// Check that the ID mappings, vectors, metadata and HNSW graph agree.
+    ///
+    /// Graph points left behind by deletions and updates are expected until
+    /// the next compaction and are not reported.
+    pub fn verify(&self) -> Vec<Issue> {
+        let id_to_internal = self.id_to_internal.read();
+        let internal_to_id = self.internal_to_id.read();
+        let vectors = self.vectors.read();
+        let metadata = self.metadata.read();
+        let mut issues = Vec::new();
+
+        let mut unmapped: Vec<String> = id_to_internal
+            .iter()
+            .filter(|(id, internal_id)| internal_to_id.get(internal_id) != Some(*id))
+            .map(|(id, _)| id.clone())
+            .collect();
+        unmapped.extend(
+            internal_to_id
+                .iter()
+                .filter(|(internal_id, id)| id_to_internal.get(*id) != Some(*internal_id))
+                .map(|(_, id)| id.clone()),
+        );
+        if !unmapped.is_empty() {
+            issues.push(Issue::for_ids(
+                IssueKind::InconsistentIndex,
+                "IDs have mismatched internal mappings",
+                &unmapped,
+            ));
+        }
+
+        let missing: Vec<String> = id_to_internal
+            .iter()
+            .filter(|(_, internal_id)| !vectors.contains_key(internal_id))
+            .map(|(id, _)| id.clone())
+            .collect();
+        if !missing.is_empty() {
+            issues.push(Issue::for_ids(
+                IssueKind::InconsistentIndex,
+                "IDs have no stored vector",
+                &missing,
+            ));
+        }
+
+        let live: HashSet<usize> = id_to_internal.values().copied().collect();
+        let orphaned = vectors.keys().filter(|id| !live.contains(id)).count()
+            + metadata.keys().filter(|id| !live.contains(id)).count();
+        if orphaned > 0 {
+            issues.push(Issue::new(
+                IssueKind::InconsistentIndex,
+                format!("{} stored vectors or metadata have no ID", orphaned),
+            ));
+        }
+
+        // Every vector must be in the graph with its current value
+        let current: HashSet<usize> = {
+            let inner = self.inner.read();
+            match &*inner {
+                IndexInner::Cosine(hnsw) => graph_matches(hnsw, &vectors),
+                IndexInner::Euclidean(hnsw) => graph_matches(hnsw, &vectors),
+                IndexInner::DotProduct(hnsw) => graph_matches(hnsw, &vectors),
+                IndexInner::Manhattan(hnsw) => graph_matches(hnsw, &vectors),
+            }
+        };
+        let unindexed: Vec<String> = id_to_internal
+            .iter()
+            .filter(|(_, internal_id)| {
+                vectors.contains_key(internal_id) && !current.contains(internal_id)
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        if !unindexed.is_empty() {
+            issues.push(Issue::for_ids(
+                IssueKind::DamagedGraph,
+                "vectors are missing from the HNSW graph or outdated in it",
+                &unindexed,
+            ));
+        }
+
+        issues
+    }
+
     /// Estimate memory usage in bytes.
     pub fn memory_usage(&self) -> usize {
         let vectors = self.vectors.read();
@@ -542,12 +625,21 @@ impl HnswIndex {
     ///
     /// Returns an iterator over (id, vector, metadata) tuples.
     pub fn export_all(&self) -> Vec<(String, Vec<f32>, Option<VectorMetadata>)> {
+        self.export_where(|_| true)
+    }
+
+    /// Export the vectors whose IDs satisfy `keep`.
+    pub(crate) fn export_where(
+        &self,
+        keep: impl Fn(&str) -> bool,
+    ) -> Vec<(String, Vec<f32>, Option<VectorMetadata>)> {
         let id_to_internal = self.id_to_internal.read();
         let vectors = self.vectors.read();
         let metadata = self.metadata.read();
 
         id_to_internal
             .iter()
+            .filter(|(id, _)| keep(id))
             .filter_map(|(id, &internal_id)| {
                 let vector = vectors.get(&internal_id)?.clone();
                 let meta = metadata.get(&internal_id).cloned();
@@ -575,6 +667,29 @@ impl HnswIndex {
     }
 }
 
+/// Internal IDs whose graph point holds the stored vector.
+fn graph_matches<D>(
+    hnsw: &Hnsw<'static, f32, D>,
+    vectors: &HashMap<usize, Vec<f32>>,
+) -> HashSet<usize>
+where
+    D: Distance<f32> + Send + Sync,
+{
+    // Iterating an empty graph panics in hnsw_rs
+    if hnsw.get_nb_point() == 0 {
+        return HashSet::new();
+    }
+    hnsw.get_point_indexation()
+        .into_iter()
+        .filter(|point| {
+            vectors
+                .get(&point.get_origin_id())
+                .is_some_and(|vector| vector.as_slice() == point.get_v())
+        })
+        .map(|point| point.get_origin_id())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -643,4 +758,33 @@ mod tests {
         index.insert("vec1", &[1.0, 0.0, 0.0], None).unwrap();
         assert!(index.contains("vec1"));
     }
+
+    #[test]
+    fn test_verify_detects_damage_until_compacted() {
+        let index = HnswIndex::new(3, DistanceMetric::Cosine, default_config()).unwrap();
+        index.insert("vec1", &[1.0, 0.0, 0.0], None).unwrap();
+        index.insert("vec2", &[0.0, 1.0, 0.0], None).unwrap();
+        index.insert("vec3", &[0.0, 0.0, 1.0], None).unwrap();
+
+        // Updates and deletions leave stale graph points, which are expected
+        index.update("vec1", &[0.5, 0.5, 0.0], None).unwrap();
+        index.delete("vec3").unwrap();
+        assert!(index.verify().is_empty());
+
+        // A stored vector the graph doesn't hold, and a dangling reverse mapping
+        let vec2 = index.id_to_internal.read()["vec2"];
+        index.vectors.write().insert(vec2, vec![0.0, 0.0, 2.0]);
+        index.internal_to_id.write().insert(99, "ghost".to_string());
+
+        let kinds: Vec<IssueKind> = index.verify().iter().map(|i| i.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![IssueKind::InconsistentIndex, IssueKind::DamagedGraph]
+        );
+
+        index.compact().unwrap();
+        assert!(index.verify().is_empty());
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.get("vec2").unwrap().0, vec![0.0, 0.0, 2.0]);
+    }
 }
@@ -8,6 +8,8 @@
 //! - **Pure Rust**: No native dependencies, compiles anywhere Rust does
 //! - **HNSW Indexing**: Fast approximate nearest neighbor search
 //! - **Thread-Safe**: Designed for concurrent read/write access
+//! - **Write Throttling**: Bounded per-collection write queues keep bulk
+//!   ingestion from starving searches
 //! - **Persistence**: Optional disk-based storage with memory-mapped files
 //! - **Multiple Distance Metrics**: Cosine, Euclidean (L2), Dot Product, Manhattan (L1)
 //!
@@ -52,7 +54,7 @@
 //! │  ┌─────────────────────────────────────────────────────────┐│
 //! │  │               Persistence Layer (optional)               ││
 //! │  │  ┌─────────────┐  ┌─────────────┐  ┌─────────────────┐ ││
-//! │  │  │   Bincode   │  │  Snapshots  │  │  WAL (future)   │ ││
+//! │  │  │  Segments   │  │  Snapshots  │  │  WAL (future)   │ ││
 //! │  │  └─────────────┘  └─────────────┘  └─────────────────┘ ││
 //! │  └─────────────────────────────────────────────────────────┘│
 //! └─────────────────────────────────────────────────────────────┘
@@ -67,17 +69,22 @@ pub mod distance;
 pub mod error;
 pub mod index;
 pub mod persistence;
+pub mod throttle;
 pub mod types;
+pub mod verify;
 
 // Re-exports for convenience
 pub use collection::Collection;
 pub use config::Config;
 pub use distance::DistanceMetric;
 pub use error::{Error, Result};
+pub use throttle::WriteQueueStats;
 pub use types::{SearchResult, VectorId, VectorMetadata};
+pub use verify::{CollectionReport, Issue, IssueKind, VerifyReport};
 
 use std::path::Path;
 use std::sync::Arc;
+use throttle::WriteQueue;
 use tracing::{debug, info, instrument, warn};
 
 /// The main vector database instance.
@@ -100,6 +107,8 @@ struct VectorDbInner {
     config: Config,
     /// Async-safe concurrent hashmap from scc crate
     collections: scc::HashMap<String, Arc<Collection>>,
+    /// Write queue of each collection written to
+    write_queues: scc::HashMap<String, Arc<WriteQueue>>,
 }
 
 impl VectorDb {
@@ -126,6 +135,7 @@ impl VectorDb {
             inner: Arc::new(VectorDbInner {
                 config: config.clone(),
                 collections: scc::HashMap::new(),
+                write_queues: scc::HashMap::new(),
             }),
         };
 
@@ -204,6 +214,7 @@ impl VectorDb {
         if self.inner.collections.remove(name).is_none() {
             return Err(Error::CollectionNotFound(name.to_string()));
         }
+        self.inner.write_queues.remove(name);
 
         // Remove from disk if persistent
         if let Some(ref path) = self.inner.config.data_path {
@@ -254,8 +265,9 @@ impl VectorDb {
     ///
     /// # Errors
     ///
-    /// Returns an error if the collection doesn't exist or the vector
-    /// dimensions don't match the collection.
+    /// Returns an error if the collection doesn't exist, the vector
+    /// dimensions don't match the collection, or the collection's write queue
+    /// stays full.
     #[instrument(skip(self, vector, metadata), fields(collection, id, dim = vector.len()))]
     pub async fn insert(
         &self,
@@ -265,6 +277,8 @@ impl VectorDb {
         metadata: Option<VectorMetadata>,
     ) -> Result<()> {
         let col = self.get_collection(collection)?;
+        let queue = self.write_queue(collection);
+        let _permit = queue.admit(collection, 1).await?;
         col.insert(id, vector, metadata)?;
         debug!("Inserted vector");
         Ok(())
@@ -273,7 +287,9 @@ impl VectorDb {
     /// Insert multiple vectors into a collection.
     ///
     /// This is more efficient than calling `insert` repeatedly as it batches
-    /// the index updates.
+    /// the index updates. The batch is written in chunks of
+    /// [`WriteQueueConfig::chunk_size`](config::WriteQueueConfig::chunk_size)
+    /// vectors, letting searches run between them.
     ///
     /// # Arguments
     ///
@@ -289,7 +305,17 @@ impl VectorDb {
         I: IntoIterator<Item = (&'a str, &'a [f32], Option<VectorMetadata>)>,
     {
         let col = self.get_collection(collection)?;
-        let count = col.insert_batch(vectors)?;
+        let vectors: Vec<_> = vectors.into_iter().collect();
+        let queue = self.write_queue(collection);
+        let _permit = queue.admit(collection, vectors.len()).await?;
+
+        let chunk_size = self.inner.config.write_queue.chunk_size.max(1);
+        let mut count = 0;
+        let mut vectors = vectors.into_iter().peekable();
+        while vectors.peek().is_some() {
+            count += col.insert_batch(vectors.by_ref().take(chunk_size))?;
+            tokio::task::yield_now().await;
+        }
         debug!(count, "Inserted batch");
         Ok(count)
     }
@@ -307,6 +333,8 @@ impl VectorDb {
         metadata: Option<VectorMetadata>,
     ) -> Result<()> {
         let col = self.get_collection(collection)?;
+        let queue = self.write_queue(collection);
+        let _permit = queue.admit(collection, 1).await?;
         col.update(id, vector, metadata)?;
         Ok(())
     }
@@ -419,6 +447,16 @@ impl VectorDb {
         Ok(col.stats())
     }
 
+    /// Get the write queue metrics of a collection.
+    pub fn write_stats(&self, collection: &str) -> Result<WriteQueueStats> {
+        self.get_collection(collection)?;
+        Ok(self
+            .inner
+            .write_queues
+            .read(collection, |_, queue| queue.stats())
+            .unwrap_or_default())
+    }
+
     /// Persist the current state to disk.
     ///
     /// This is only relevant for persistent databases. For in-memory databases,
@@ -455,6 +493,89 @@ impl VectorDb {
         Ok(())
     }
 
+    /// Check the database for corruption.
+    ///
+    /// For a persistent database, `collections.json` and every listed
+    /// collection's files are checked on disk. Every loaded collection's
+    /// index is checked for ID mappings, vectors, metadata and HNSW graph
+    /// that disagree. Nothing is changed; see [`rebuild`](Self::rebuild)
+    /// for repairing what [`VerifyReport::needs_rebuild`] names.
+    #[instrument(skip(self))]
+    pub async fn verify(&self) -> Result<VerifyReport> {
+        let mut report = VerifyReport::default();
+        if let Some(ref path) = self.inner.config.data_path {
+            verify::check_files(path, &mut report).await;
+        }
+
+        let mut loaded: Vec<(String, Arc<Collection>)> = Vec::new();
+        self.inner.collections.scan(|name, collection| {
+            loaded.push((name.clone(), collection.clone()));
+        });
+        for (name, collection) in loaded {
+            let issues = collection.verify();
+            let entry = report.collection_mut(&name);
+            entry.loaded = true;
+            entry.vector_count = collection.len();
+            entry.issues.extend(issues);
+        }
+        report.collections.sort_by(|a, b| a.name.cmp(&b.name));
+
+        info!(issues = report.issue_count(), "Verified database");
+        Ok(report)
+    }
+
+    /// Rebuild a collection's index and HNSW graph from its stored vectors.
+    ///
+    /// For a persistent database the collection's files are rewritten from
+    /// the rebuilt collection, dropping entries that could not be loaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the collection doesn't exist.
+    #[instrument(skip(self))]
+    pub async fn rebuild(&self, collection: &str) -> Result<()> {
+        let col = self.get_collection(collection)?;
+        col.compact()?;
+        if let Some(ref path) = self.inner.config.data_path {
+            col.mark_all_dirty();
+            self.persist_collection(path, collection, &col).await?;
+        }
+        info!(collection, vectors = col.len(), "Rebuilt collection");
+        Ok(())
+    }
+
+    /// Switch a collection to another distance metric.
+    ///
+    /// The collection is rebuilt with the new metric, normalizing its
+    /// vectors if the metric needs unit-length vectors (dot product). For a
+    /// persistent database the collection's files are rewritten. Writes made
+    /// to the collection while it is converted are lost.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the collection doesn't exist, or if a vector has
+    /// length zero and cannot be normalized; the collection is then left
+    /// unchanged.
+    #[instrument(skip(self))]
+    pub async fn convert_metric(&self, collection: &str, metric: DistanceMetric) -> Result<()> {
+        let col = self.get_collection(collection)?;
+        if col.metric() == metric {
+            return Ok(());
+        }
+
+        let converted = Arc::new(col.with_metric(metric)?);
+        self.inner
+            .collections
+            .update(collection, |_, v| *v = converted.clone())
+            .ok_or_else(|| Error::CollectionNotFound(collection.to_string()))?;
+        if let Some(ref path) = self.inner.config.data_path {
+            self.persist_collection(path, collection, &converted)
+                .await?;
+        }
+        info!(collection, from = %col.metric(), to = %metric, "Converted collection metric");
+        Ok(())
+    }
+
     // Internal: Load collections from disk
     async fn load_collections(&self, path: &Path) -> Result<()> {
         if !path.exists() {
@@ -491,6 +612,19 @@ impl VectorDb {
         Ok(())
     }
 
+    /// The write queue of a collection, created on its first write.
+    fn write_queue(&self, collection: &str) -> Arc<WriteQueue> {
+        if let Some(queue) = self.inner.write_queues.read(collection, |_, q| q.clone()) {
+            return queue;
+        }
+        self.inner
+            .write_queues
+            .entry(collection.to_string())
+            .or_insert_with(|| Arc::new(WriteQueue::new(&self.inner.config.write_queue)))
+            .get()
+            .clone()
+    }
+
     async fn load_collection(&self, base_path: &Path, name: &str) -> Result<Collection> {
         persistence::load_collection(base_path, name).await
     }
@@ -610,4 +744,136 @@ mod tests {
             .await;
         assert!(matches!(result, Err(Error::CollectionExists(_))));
     }
+
+    #[tokio::test]
+    async fn test_verify_and_rebuild() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().to_path_buf();
+        {
+            let db = VectorDb::open(Config::persistent(&path)).await.unwrap();
+            db.create_collection("docs", 3, DistanceMetric::Cosine)
+                .await
+                .unwrap();
+            db.create_collection("notes", 3, DistanceMetric::Cosine)
+                .await
+                .unwrap();
+            db.insert("docs", "a", &[1.0, 0.0, 0.0], None)
+                .await
+                .unwrap();
+            db.persist().await.unwrap();
+            assert!(db.verify().await.unwrap().is_ok());
+        }
+
+        // A vector loading skips, an unparseable collection and a stray directory
+        let vectors = concat!(
+            r#"{"id":"a","vector":[1.0,0.0,0.0],"metadata":null}"#,
+            "\n",
+            r#"{"id":"b","vector":[1.0,0.0],"metadata":null}"#,
+        );
+        std::fs::remove_dir_all(path.join("docs/segments")).unwrap();
+        std::fs::create_dir(path.join("docs/segments")).unwrap();
+        std::fs::write(path.join("docs/segments/0000.jsonl"), vectors).unwrap();
+        std::fs::write(path.join("notes/segments/0000.jsonl"), "{").unwrap();
+        std::fs::create_dir(path.join("stray")).unwrap();
+        std::fs::copy(
+            path.join("docs/metadata.json"),
+            path.join("stray/metadata.json"),
+        )
+        .unwrap();
+
+        let db = VectorDb::open(Config::persistent(&path)).await.unwrap();
+        let report = db.verify().await.unwrap();
+        assert_eq!(report.issue_count(), 3);
+        assert_eq!(report.issues[0].kind, IssueKind::UnlistedCollection);
+        let docs = &report.collections[0];
+        assert_eq!(docs.name, "docs");
+        assert_eq!(docs.issues[0].kind, IssueKind::InvalidVector);
+        assert!(docs.issues[0].detail.contains("'b'"));
+        let notes = &report.collections[1];
+        assert!(!notes.loaded);
+        assert_eq!(notes.issues[0].kind, IssueKind::CorruptFile);
+        assert_eq!(report.needs_rebuild(), vec!["docs"]);
+
+        db.rebuild("docs").await.unwrap();
+        let report = db.verify().await.unwrap();
+        assert!(report.collections[0].is_ok());
+        assert_eq!(report.collections[0].vector_count, 1);
+        assert!(report.needs_rebuild().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_convert_metric() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().to_path_buf();
+        {
+            let db = VectorDb::open(Config::persistent(&path)).await.unwrap();
+            db.create_collection("docs", 2, DistanceMetric::Euclidean)
+                .await
+                .unwrap();
+            db.insert("docs", "a", &[3.0, 4.0], None).await.unwrap();
+            db.insert("docs", "b", &[0.0, 0.0], None).await.unwrap();
+
+            // A zero vector cannot be normalized; nothing changes
+            assert!(db
+                .convert_metric("docs", DistanceMetric::DotProduct)
+                .await
+                .is_err());
+            assert_eq!(
+                db.get_collection("docs").unwrap().metric(),
+                DistanceMetric::Euclidean
+            );
+
+            db.delete("docs", "b").await.unwrap();
+            db.insert("docs", "c", &[0.0, 2.0], None).await.unwrap();
+            db.convert_metric("docs", DistanceMetric::DotProduct)
+                .await
+                .unwrap();
+            let (vector, _) = db.get("docs", "a").await.unwrap().unwrap();
+            assert_eq!(vector, vec![0.6, 0.8]);
+
+            let results = db.search("docs", &[0.0, 1.0], 1).await.unwrap();
+            assert_eq!(results[0].id, "c");
+        }
+
+        // The new metric is persisted
+        let db = VectorDb::open(Config::persistent(&path)).await.unwrap();
+        let col = db.get_collection("docs").unwrap();
+        assert_eq!(col.metric(), DistanceMetric::DotProduct);
+        assert_eq!(col.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_write_queue() {
+        let config = Config::memory().with_write_queue(config::WriteQueueConfig {
+            max_pending: 4,
+            max_wait_ms: 0,
+            chunk_size: 3,
+        });
+        let db = VectorDb::open(config).await.unwrap();
+        db.create_collection("docs", 2, DistanceMetric::Euclidean)
+            .await
+            .unwrap();
+        assert_eq!(db.write_stats("docs").unwrap(), WriteQueueStats::default());
+
+        // Larger than the queue and the chunk size
+        let ids: Vec<String> = (0..10).map(|i| format!("v{}", i)).collect();
+        let vectors: Vec<[f32; 2]> = (0..10).map(|i| [i as f32, 0.0]).collect();
+        let batch = ids
+            .iter()
+            .zip(&vectors)
+            .map(|(id, v)| (id.as_str(), &v[..], None));
+        assert_eq!(db.insert_batch("docs", batch).await.unwrap(), 10);
+        db.insert("docs", "extra", &[0.5, 0.5], None).await.unwrap();
+        assert_eq!(db.count("docs").unwrap(), 11);
+
+        let stats = db.write_stats("docs").unwrap();
+        assert_eq!(stats.admitted, 2);
+        assert_eq!(stats.rejected, 0);
+        assert_eq!(stats.pending_vectors, 0);
+
+        assert!(matches!(
+            db.write_stats("missing"),
+            Err(Error::CollectionNotFound(_))
+        ));
+    }
 }
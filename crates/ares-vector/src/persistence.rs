@@ -1,16 +1,39 @@
 //! Persistence layer for ares-vector.
 //!
 //! This module handles saving and loading collections to/from disk.
+//!
+//! A collection's vectors are spread over [`SEGMENT_COUNT`] segment files by
+//! a hash of their IDs. Collections track which segments changed since they
+//! were last saved, and a save only rewrites those, one at a time and one
+//! vector at a time, so saving a large collection needs memory for a single
+//! segment rather than a second copy of the collection. Each segment is
+//! written to a temporary file and renamed into place, so an interrupted
+//! save leaves the previous version of the segment.
+//!
+//! Collections saved as a single `vectors.json` by earlier versions still
+//! load, and are converted to segments when next saved.
 
 use crate::collection::Collection;
 use crate::config::HnswConfig;
 use crate::distance::DistanceMetric;
 use crate::error::{Error, Result};
 use crate::types::VectorMetadata;
+use crate::verify::{Issue, IssueKind};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
 use tracing::{debug, info, warn};
 
+/// Number of segment files a collection's vectors are spread over.
+pub const SEGMENT_COUNT: usize = 64;
+
+/// Directory holding a collection's segment files.
+const SEGMENTS_DIR: &str = "segments";
+
+/// Single-file vector storage of earlier versions.
+const LEGACY_VECTORS_FILE: &str = "vectors.json";
+
 /// Collection metadata stored on disk.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct CollectionMetadata {
@@ -20,6 +43,9 @@ struct CollectionMetadata {
     hnsw_m: usize,
     hnsw_ef_construction: usize,
     hnsw_ef_search: usize,
+    /// Number of segment files; 0 for a collection stored in `vectors.json`.
+    #[serde(default)]
+    segments: usize,
 }
 
 /// Stored vector data for persistence.
@@ -30,16 +56,127 @@ pub(crate) struct StoredVectorData {
     metadata: Option<VectorMetadata>,
 }
 
+/// Segments of a collection changed since it was last saved.
+#[derive(Debug, Default)]
+pub(crate) struct DirtySegments {
+    /// Every segment, as for a collection that was never saved.
+    all: bool,
+    segments: BTreeSet<usize>,
+}
+
+impl DirtySegments {
+    /// Every segment changed.
+    pub(crate) fn all() -> Self {
+        Self {
+            all: true,
+            segments: BTreeSet::new(),
+        }
+    }
+
+    /// Record a change to the vector `id`.
+    pub(crate) fn mark(&mut self, id: &str) {
+        if !self.all {
+            self.segments.insert(segment_of(id));
+        }
+    }
+
+    /// Whether nothing changed.
+    pub(crate) fn is_clean(&self) -> bool {
+        !self.all && self.segments.is_empty()
+    }
+
+    /// Add the changes recorded in `other`.
+    pub(crate) fn merge(&mut self, other: DirtySegments) {
+        self.all |= other.all;
+        self.segments.extend(other.segments);
+    }
+
+    fn contains(&self, segment: usize) -> bool {
+        self.all || self.segments.contains(&segment)
+    }
+}
+
+/// Segment holding the vector `id`.
+///
+/// Uses FNV-1a, which unlike the standard library's hasher is stable across
+/// Rust versions.
+pub(crate) fn segment_of(id: &str) -> usize {
+    let hash = id.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    (hash % SEGMENT_COUNT as u64) as usize
+}
+
+fn segment_path(segments_path: &Path, segment: usize) -> PathBuf {
+    segments_path.join(format!("{:04}.jsonl", segment))
+}
+
 /// Save a collection to disk.
 ///
-/// Creates the following files:
+/// Writes the following files, skipping segments that haven't changed
+/// since the collection was last saved or loaded:
 /// - `{base_path}/{name}/metadata.json` - Collection metadata
-/// - `{base_path}/{name}/vectors.json` - Vector data (JSON format)
+/// - `{base_path}/{name}/segments/{n}.jsonl` - Vector data, one JSON
+///   object per line
 pub async fn save_collection(base_path: &Path, name: &str, collection: &Collection) -> Result<()> {
+    // Saves of the same collection would write the same files
+    let _saving = collection.save_lock().lock().await;
     let collection_path = base_path.join(name);
-    tokio::fs::create_dir_all(&collection_path).await?;
+    let metadata_path = collection_path.join("metadata.json");
+    let dirty = collection.take_dirty();
+    if dirty.is_clean() && metadata_path.exists() {
+        debug!(name, "Collection unchanged, skipping save");
+        return Ok(());
+    }
+
+    match write_collection(&collection_path, name, collection, &dirty).await {
+        Ok(written) => {
+            info!(name, segments = written, path = ?collection_path, "Saved collection");
+            Ok(())
+        }
+        Err(e) => {
+            // Retry the unsaved segments with the next save
+            collection.restore_dirty(dirty);
+            Err(e)
+        }
+    }
+}
+
+/// Write a collection's changed segments and then its metadata, returning
+/// the number of segments written.
+async fn write_collection(
+    collection_path: &Path,
+    name: &str,
+    collection: &Collection,
+    dirty: &DirtySegments,
+) -> Result<usize> {
+    let segments_path = collection_path.join(SEGMENTS_DIR);
+    tokio::fs::create_dir_all(&segments_path).await?;
+
+    let mut written = 0;
+    for segment in (0..SEGMENT_COUNT).filter(|&segment| dirty.contains(segment)) {
+        let path = segment_path(&segments_path, segment);
+        let vectors = collection.export_segment(segment);
+        if vectors.is_empty() {
+            remove_if_exists(&path).await?;
+        } else {
+            write_segment(&path, vectors).await?;
+        }
+        written += 1;
+        // Let searches and writes run between segments
+        tokio::task::yield_now().await;
+    }
+    if dirty.all {
+        // Every segment was just written; anything else is left over
+        let mut entries = tokio::fs::read_dir(&segments_path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let current = segment_index(&entry.path()).is_some_and(|i| i < SEGMENT_COUNT);
+            if !current {
+                remove_if_exists(&entry.path()).await?;
+            }
+        }
+    }
 
-    // Save metadata
     let metadata = CollectionMetadata {
         name: name.to_string(),
         dimensions: collection.dimensions(),
@@ -47,33 +184,118 @@ pub async fn save_collection(base_path: &Path, name: &str, collection: &Collecti
         hnsw_m: collection.hnsw_config().m,
         hnsw_ef_construction: collection.hnsw_config().ef_construction,
         hnsw_ef_search: collection.hnsw_config().ef_search,
+        segments: SEGMENT_COUNT,
     };
-
-    let metadata_path = collection_path.join("metadata.json");
     let metadata_json = serde_json::to_string_pretty(&metadata)
         .map_err(|e| Error::Persistence(format!("Failed to serialize metadata: {}", e)))?;
-    tokio::fs::write(&metadata_path, metadata_json).await?;
-
-    // Export all vectors from the collection
-    let exported = collection.export_all();
-    let vectors: Vec<StoredVectorData> = exported
-        .into_iter()
-        .map(|(id, vector, metadata)| StoredVectorData {
-            id,
-            vector,
-            metadata,
-        })
-        .collect();
-
-    let vectors_path = collection_path.join("vectors.json");
-    let vectors_json = serde_json::to_string(&vectors)
-        .map_err(|e| Error::Persistence(format!("Failed to serialize vectors: {}", e)))?;
-    tokio::fs::write(&vectors_path, vectors_json).await?;
-
-    info!(name, vectors = vectors.len(), path = ?collection_path, "Saved collection");
+    write_atomic(
+        &collection_path.join("metadata.json"),
+        metadata_json.as_bytes(),
+    )
+    .await?;
+
+    // The metadata now points at the segments
+    remove_if_exists(&collection_path.join(LEGACY_VECTORS_FILE)).await?;
+    Ok(written)
+}
+
+/// Write a segment file one vector at a time.
+async fn write_segment(
+    path: &Path,
+    vectors: Vec<(String, Vec<f32>, Option<VectorMetadata>)>,
+) -> Result<()> {
+    let temp_path = path.with_extension("jsonl.tmp");
+    let mut file = BufWriter::new(tokio::fs::File::create(&temp_path).await?);
+    let mut line = Vec::new();
+    for (id, vector, metadata) in vectors {
+        line.clear();
+        serde_json::to_writer(
+            &mut line,
+            &StoredVectorData {
+                id,
+                vector,
+                metadata,
+            },
+        )
+        .map_err(|e| Error::Persistence(format!("Failed to serialize vector: {}", e)))?;
+        line.push(b'\n');
+        file.write_all(&line).await?;
+    }
+    file.flush().await?;
+    file.into_inner().sync_all().await?;
+    tokio::fs::rename(&temp_path, path).await?;
+    Ok(())
+}
+
+/// Replace a file's contents, leaving the old contents if interrupted.
+async fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let temp_path = path.with_extension("tmp");
+    tokio::fs::write(&temp_path, contents).await?;
+    tokio::fs::rename(&temp_path, path).await?;
     Ok(())
 }
 
+async fn remove_if_exists(path: &Path) -> Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Segment number of a segment file.
+fn segment_index(path: &Path) -> Option<usize> {
+    if path.extension()? != "jsonl" {
+        return None;
+    }
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+/// A collection's segment files, in order.
+async fn segment_files(segments_path: &Path) -> std::io::Result<Vec<(usize, PathBuf)>> {
+    let mut files = Vec::new();
+    let mut entries = tokio::fs::read_dir(segments_path).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if let Some(segment) = segment_index(&entry.path()) {
+            files.push((segment, entry.path()));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Read a segment file one vector at a time.
+///
+/// Errors describe the problem, to follow the file's name.
+async fn read_segment(
+    path: &Path,
+    mut each: impl FnMut(StoredVectorData),
+) -> std::result::Result<(), String> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| format!("cannot be read: {}", e))?;
+    let mut lines = BufReader::new(file).lines();
+    let mut number = 0;
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|e| format!("cannot be read: {}", e))?
+    {
+        number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let stored = serde_json::from_str(&line)
+            .map_err(|e| format!("cannot be parsed at line {}: {}", number, e))?;
+        each(stored);
+    }
+    Ok(())
+}
+
+/// Display name of a segment file, relative to its collection.
+fn segment_name(segment: usize) -> String {
+    format!("{}/{:04}.jsonl", SEGMENTS_DIR, segment)
+}
+
 /// Load a collection from disk.
 pub async fn load_collection(base_path: &Path, name: &str) -> Result<Collection> {
     let collection_path = base_path.join(name);
@@ -111,28 +333,149 @@ pub async fn load_collection(base_path: &Path, name: &str) -> Result<Collection>
         metric,
         hnsw_config,
     )?;
-
-    // Load vectors
-    let vectors_path = collection_path.join("vectors.json");
-    if vectors_path.exists() {
-        let vectors_json = tokio::fs::read_to_string(&vectors_path).await?;
-        let vectors: Vec<StoredVectorData> = serde_json::from_str(&vectors_json)
-            .map_err(|e| Error::Persistence(format!("Failed to parse vectors: {}", e)))?;
-
-        let count = vectors.len();
-        for stored in vectors {
-            if let Err(e) = collection.insert(&stored.id, &stored.vector, stored.metadata) {
-                warn!(id = stored.id, error = %e, "Failed to load vector");
-            }
+    let insert = |stored: StoredVectorData| {
+        if let Err(e) = collection.insert(&stored.id, &stored.vector, stored.metadata) {
+            warn!(id = stored.id, error = %e, "Failed to load vector");
         }
+    };
 
-        debug!(name, count, "Loaded vectors");
+    if metadata.segments == 0 {
+        // Stored by an earlier version; the next save converts it
+        let vectors_path = collection_path.join(LEGACY_VECTORS_FILE);
+        if vectors_path.exists() {
+            let vectors_json = tokio::fs::read_to_string(&vectors_path).await?;
+            let vectors: Vec<StoredVectorData> = serde_json::from_str(&vectors_json)
+                .map_err(|e| Error::Persistence(format!("Failed to parse vectors: {}", e)))?;
+            vectors.into_iter().for_each(insert);
+        }
+    } else {
+        let segments_path = collection_path.join(SEGMENTS_DIR);
+        let files = match segment_files(&segments_path).await {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            files => files?,
+        };
+        // Vectors in the wrong segment, as after changing the segment count,
+        // are moved when the collection is next saved
+        let mut misplaced = metadata.segments != SEGMENT_COUNT;
+        for (segment, path) in files {
+            read_segment(&path, |stored| {
+                misplaced |= segment_of(&stored.id) != segment;
+                insert(stored);
+            })
+            .await
+            .map_err(|e| Error::Persistence(format!("{} {}", segment_name(segment), e)))?;
+        }
+        if !misplaced {
+            collection.take_dirty();
+        }
     }
 
+    debug!(name, count = collection.len(), "Loaded vectors");
     info!(name, dimensions = metadata.dimensions, "Loaded collection");
     Ok(collection)
 }
 
+/// Check a collection's files without loading it.
+///
+/// Reports unreadable or unparseable files, and stored vectors that loading
+/// would skip: wrong dimensions, NaN or infinite values, and duplicate IDs.
+pub(crate) async fn verify_collection_files(base_path: &Path, name: &str) -> Vec<Issue> {
+    let collection_path = base_path.join(name);
+    let mut issues = Vec::new();
+
+    let metadata_path = collection_path.join("metadata.json");
+    let metadata = match tokio::fs::read_to_string(&metadata_path).await {
+        Ok(json) => serde_json::from_str::<CollectionMetadata>(&json)
+            .map_err(|e| format!("metadata.json cannot be parsed: {}", e)),
+        Err(e) => Err(format!("metadata.json cannot be read: {}", e)),
+    };
+    let metadata = match metadata {
+        Ok(metadata) => metadata,
+        Err(detail) => {
+            issues.push(Issue::new(IssueKind::CorruptFile, detail));
+            return issues;
+        }
+    };
+    if let Err(e) = metadata.metric.parse::<DistanceMetric>() {
+        issues.push(Issue::new(
+            IssueKind::CorruptFile,
+            format!("metadata.json has an invalid metric: {}", e),
+        ));
+    }
+
+    let mut seen = HashSet::new();
+    let (mut wrong_dimensions, mut non_finite, mut duplicates) = (vec![], vec![], vec![]);
+    let mut check = |stored: StoredVectorData| {
+        if stored.vector.len() != metadata.dimensions {
+            wrong_dimensions.push(stored.id);
+        } else if stored.vector.iter().any(|v| !v.is_finite()) {
+            non_finite.push(stored.id);
+        } else if !seen.insert(stored.id.clone()) {
+            duplicates.push(stored.id);
+        }
+    };
+    let read = if metadata.segments == 0 {
+        verify_legacy_vectors(&collection_path, &mut check).await
+    } else {
+        verify_segments(&collection_path.join(SEGMENTS_DIR), &mut check).await
+    };
+    if let Err(detail) = read {
+        issues.push(Issue::new(IssueKind::CorruptFile, detail));
+        return issues;
+    }
+
+    let dimensions_problem = format!(
+        "stored vectors do not have {} dimensions",
+        metadata.dimensions
+    );
+    for (ids, problem) in [
+        (wrong_dimensions, dimensions_problem.as_str()),
+        (non_finite, "stored vectors contain NaN or infinite values"),
+        (duplicates, "stored vectors repeat an earlier ID"),
+    ] {
+        if !ids.is_empty() {
+            issues.push(Issue::for_ids(IssueKind::InvalidVector, problem, &ids));
+        }
+    }
+    issues
+}
+
+/// Pass the vectors of an earlier version's `vectors.json` to `check`.
+async fn verify_legacy_vectors(
+    collection_path: &Path,
+    check: &mut impl FnMut(StoredVectorData),
+) -> std::result::Result<(), String> {
+    let vectors_path = collection_path.join(LEGACY_VECTORS_FILE);
+    if !vectors_path.exists() {
+        return Ok(());
+    }
+    let vectors = match tokio::fs::read_to_string(&vectors_path).await {
+        Ok(json) => serde_json::from_str::<Vec<StoredVectorData>>(&json)
+            .map_err(|e| format!("vectors.json cannot be parsed: {}", e))?,
+        Err(e) => return Err(format!("vectors.json cannot be read: {}", e)),
+    };
+    vectors.into_iter().for_each(check);
+    Ok(())
+}
+
+/// Pass the vectors of every segment file to `check`.
+async fn verify_segments(
+    segments_path: &Path,
+    check: &mut impl FnMut(StoredVectorData),
+) -> std::result::Result<(), String> {
+    let files = match segment_files(segments_path).await {
+        Ok(files) => files,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("{} cannot be read: {}", SEGMENTS_DIR, e)),
+    };
+    for (segment, path) in files {
+        read_segment(&path, &mut *check)
+            .await
+            .map_err(|e| format!("{} {}", segment_name(segment), e))?;
+    }
+    Ok(())
+}
+
 /// Enhanced persistence with postcard (when serde feature is enabled).
 #[cfg(feature = "serde")]
 #[allow(dead_code)]
@@ -202,7 +545,7 @@ mod tests {
     }
 
     /// Test that vectors are actually persisted and can be retrieved after reload.
-    /// This is a regression test for the bug where vectors.json was saved empty.
+    /// This is a regression test for the bug where vector files were saved empty.
     #[tokio::test]
     async fn test_vector_persistence_regression() {
         let temp_dir = TempDir::new().unwrap();
@@ -242,16 +585,18 @@ mod tests {
             .await
             .unwrap();
 
-        // Verify vectors.json file exists and is not empty
-        let vectors_path = base_path.join("persist_test").join("vectors.json");
-        assert!(vectors_path.exists(), "vectors.json should exist");
-        let vectors_json = tokio::fs::read_to_string(&vectors_path).await.unwrap();
-        let stored_vectors: Vec<StoredVectorData> =
-            serde_json::from_str(&vectors_json).expect("vectors.json should be valid JSON");
+        // Verify the segment files exist and hold every vector
+        let segments_path = base_path.join("persist_test").join(SEGMENTS_DIR);
+        let mut stored_vectors = Vec::new();
+        for (_, path) in segment_files(&segments_path).await.unwrap() {
+            read_segment(&path, |stored| stored_vectors.push(stored))
+                .await
+                .expect("segment files should be valid JSON lines");
+        }
         assert_eq!(
             stored_vectors.len(),
             3,
-            "vectors.json should contain 3 vectors"
+            "segment files should contain 3 vectors"
         );
 
         // Load collection (simulating server restart)
@@ -323,4 +668,74 @@ mod tests {
         assert!((loaded_meta.get_float("score").unwrap() - 0.95).abs() < 0.0001);
         assert_eq!(loaded_meta.get_bool("published"), Some(true));
     }
+
+    #[tokio::test]
+    async fn test_save_rewrites_only_changed_segments() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path().to_path_buf();
+        let segments_path = base_path.join("docs").join(SEGMENTS_DIR);
+
+        let collection = Collection::new(
+            "docs".to_string(),
+            3,
+            DistanceMetric::Cosine,
+            HnswConfig::default(),
+        )
+        .unwrap();
+        let other = (0..)
+            .map(|i| format!("v{}", i))
+            .find(|id| segment_of(id) != segment_of("a"))
+            .unwrap();
+        collection.insert("a", &[1.0, 0.0, 0.0], None).unwrap();
+        save_collection(&base_path, "docs", &collection)
+            .await
+            .unwrap();
+        let first = segment_path(&segments_path, segment_of("a"));
+        assert!(first.exists());
+
+        // Unchanged segments are not written again
+        tokio::fs::remove_file(&first).await.unwrap();
+        collection.insert(&other, &[0.0, 1.0, 0.0], None).unwrap();
+        save_collection(&base_path, "docs", &collection)
+            .await
+            .unwrap();
+        assert!(!first.exists());
+        assert!(segment_path(&segments_path, segment_of(&other)).exists());
+
+        // Deleting a segment's last vector removes its file
+        collection.delete(&other).unwrap();
+        save_collection(&base_path, "docs", &collection)
+            .await
+            .unwrap();
+        assert!(!segment_path(&segments_path, segment_of(&other)).exists());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_vectors_file_is_converted() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path().to_path_buf();
+        let collection_path = base_path.join("old");
+        tokio::fs::create_dir_all(&collection_path).await.unwrap();
+        let metadata = r#"{"name":"old","dimensions":3,"metric":"cosine","hnsw_m":16,
+                           "hnsw_ef_construction":200,"hnsw_ef_search":50}"#;
+        let vectors = r#"[{"id":"a","vector":[1.0,0.0,0.0],"metadata":null},
+                          {"id":"b","vector":[0.0,1.0,0.0],"metadata":null}]"#;
+        tokio::fs::write(collection_path.join("metadata.json"), metadata)
+            .await
+            .unwrap();
+        tokio::fs::write(collection_path.join(LEGACY_VECTORS_FILE), vectors)
+            .await
+            .unwrap();
+        assert!(verify_collection_files(&base_path, "old").await.is_empty());
+
+        let loaded = load_collection(&base_path, "old").await.unwrap();
+        assert_eq!(loaded.len(), 2);
+        save_collection(&base_path, "old", &loaded).await.unwrap();
+        assert!(!collection_path.join(LEGACY_VECTORS_FILE).exists());
+
+        let reloaded = load_collection(&base_path, "old").await.unwrap();
+        assert_eq!(reloaded.len(), 2);
+        assert!(reloaded.take_dirty().is_clean());
+        assert!(verify_collection_files(&base_path, "old").await.is_empty());
+    }
 }
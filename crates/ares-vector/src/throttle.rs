@@ -0,0 +1,179 @@
+//! Write throttling.
+//!
+//! Every collection has a bounded queue of pending writes. A write takes
+//! room in the queue for each of its vectors before it is applied and frees
+//! it when done. When the queue is full, writers wait for room, up to
+//! [`WriteQueueConfig::max_wait_ms`], and are then rejected with
+//! [`Error::QueueFull`]. Admitted writes are applied in chunks of
+//! [`WriteQueueConfig::chunk_size`] vectors, yielding between chunks, so a
+//! bulk ingestion can't keep searches on the same collection waiting.
+//!
+//! [`Error::QueueFull`]: crate::Error::QueueFull
+
+use crate::config::WriteQueueConfig;
+use crate::error::{Error, Result};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Write queue metrics of a collection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WriteQueueStats {
+    /// Writes waiting for room in the queue.
+    pub queue_depth: usize,
+    /// Vectors admitted and not yet written.
+    pub pending_vectors: usize,
+    /// Writes admitted since the database was opened.
+    pub admitted: u64,
+    /// Writes rejected because the queue stayed full.
+    pub rejected: u64,
+}
+
+/// Bounded queue of the writes to one collection.
+pub(crate) struct WriteQueue {
+    /// One permit per vector that may be pending; `None` if unbounded.
+    permits: Option<Semaphore>,
+    /// Vectors the queue holds.
+    capacity: usize,
+    /// How long a write waits for room.
+    max_wait: Duration,
+    /// Writes waiting for room.
+    waiting: AtomicUsize,
+    /// Vectors admitted and not yet written.
+    pending: AtomicUsize,
+    admitted: AtomicU64,
+    rejected: AtomicU64,
+}
+
+/// Room taken in a write queue, freed on drop.
+pub(crate) struct WritePermit<'a> {
+    queue: &'a WriteQueue,
+    count: usize,
+    _permit: Option<SemaphorePermit<'a>>,
+}
+
+impl Drop for WritePermit<'_> {
+    fn drop(&mut self) {
+        self.queue.pending.fetch_sub(self.count, Ordering::Relaxed);
+    }
+}
+
+impl WriteQueue {
+    pub(crate) fn new(config: &WriteQueueConfig) -> Self {
+        // The semaphore can't hold more permits than this
+        let capacity = config.max_pending.min(Semaphore::MAX_PERMITS);
+        Self {
+            permits: (capacity > 0).then(|| Semaphore::new(capacity)),
+            capacity,
+            max_wait: Duration::from_millis(config.max_wait_ms),
+            waiting: AtomicUsize::new(0),
+            pending: AtomicUsize::new(0),
+            admitted: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// Take room for a write of `count` vectors to `collection`.
+    ///
+    /// A write larger than the queue takes the whole queue.
+    pub(crate) async fn admit(&self, collection: &str, count: usize) -> Result<WritePermit<'_>> {
+        let permit = match &self.permits {
+            None => None,
+            Some(permits) => {
+                let wanted = count.clamp(1, self.capacity) as u32;
+                let permit = match permits.try_acquire_many(wanted) {
+                    Ok(permit) => Some(permit),
+                    Err(_) if self.max_wait.is_zero() => None,
+                    Err(_) => {
+                        self.waiting.fetch_add(1, Ordering::Relaxed);
+                        let waited =
+                            tokio::time::timeout(self.max_wait, permits.acquire_many(wanted)).await;
+                        self.waiting.fetch_sub(1, Ordering::Relaxed);
+                        // The semaphore is never closed
+                        waited.ok().and_then(|permit| permit.ok())
+                    }
+                };
+                if permit.is_none() {
+                    self.rejected.fetch_add(1, Ordering::Relaxed);
+                    return Err(Error::QueueFull(collection.to_string()));
+                }
+                permit
+            }
+        };
+
+        self.admitted.fetch_add(1, Ordering::Relaxed);
+        self.pending.fetch_add(count, Ordering::Relaxed);
+        Ok(WritePermit {
+            queue: self,
+            count,
+            _permit: permit,
+        })
+    }
+
+    /// Current metrics.
+    pub(crate) fn stats(&self) -> WriteQueueStats {
+        WriteQueueStats {
+            queue_depth: self.waiting.load(Ordering::Relaxed),
+            pending_vectors: self.pending.load(Ordering::Relaxed),
+            admitted: self.admitted.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(max_pending: usize, max_wait_ms: u64) -> WriteQueue {
+        WriteQueue::new(&WriteQueueConfig {
+            max_pending,
+            max_wait_ms,
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_rejects_after_waiting() {
+        let queue = queue(10, 20);
+        let first = queue.admit("docs", 8).await.unwrap();
+        assert_eq!(queue.stats().pending_vectors, 8);
+
+        // Waits for room, then gives up
+        let err = queue.admit("docs", 5).await.err().unwrap();
+        assert!(matches!(err, Error::QueueFull(ref name) if name == "docs"));
+        assert!(queue.admit("docs", 2).await.is_ok());
+
+        drop(first);
+        // Larger than the whole queue: takes all of it
+        let bulk = queue.admit("docs", 50).await.unwrap();
+        assert_eq!(
+            queue.stats(),
+            WriteQueueStats {
+                queue_depth: 0,
+                pending_vectors: 50,
+                admitted: 3,
+                rejected: 1,
+            }
+        );
+        drop(bulk);
+        assert_eq!(queue.stats().pending_vectors, 0);
+    }
+
+    #[tokio::test]
+    async fn test_waiting_write_is_admitted_when_room_frees() {
+        let queue = queue(4, 5_000);
+        let first = queue.admit("docs", 4).await.unwrap();
+        let (second, _) = tokio::join!(queue.admit("docs", 2), async {
+            tokio::task::yield_now().await;
+            assert_eq!(queue.stats().queue_depth, 1);
+            drop(first);
+        });
+        assert!(second.is_ok());
+
+        // Unbounded queues never wait
+        let unbounded = self::queue(0, 0);
+        assert!(unbounded.admit("docs", 1_000_000).await.is_ok());
+    }
+}
@@ -0,0 +1,216 @@
+//! Integrity checks for a vector database.
+//!
+//! [`VectorDb::verify`](crate::VectorDb::verify) checks the files of a
+//! persistent database and the in-memory index of every loaded collection:
+//!
+//! - `collections.json`, and each collection's `metadata.json` and segment
+//!   files, exist and parse
+//! - stored vectors have the collection's dimensions, finite values and
+//!   unique IDs
+//! - the index's ID mappings, vectors and metadata agree
+//! - the HNSW graph holds the current copy of every vector
+//!
+//! The HNSW graph is built from the stored vectors when a collection is
+//! loaded, so a damaged graph, an inconsistent index or invalid entries in
+//! the segment files are repaired by rebuilding the collection with
+//! [`VectorDb::rebuild`](crate::VectorDb::rebuild). Unparseable files are
+//! only reported.
+
+use crate::persistence;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Number of example IDs named in an issue.
+const MAX_EXAMPLES: usize = 3;
+
+/// Kind of problem found by a verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IssueKind {
+    /// A file is missing or cannot be parsed.
+    CorruptFile,
+    /// A collection directory is not listed in `collections.json`, so it is
+    /// never loaded.
+    UnlistedCollection,
+    /// A stored vector has the wrong dimensions, a NaN or infinite value, or
+    /// a duplicate ID.
+    InvalidVector,
+    /// The index's ID mappings, vectors and metadata disagree.
+    InconsistentIndex,
+    /// The HNSW graph is missing vectors or holds outdated copies of them.
+    DamagedGraph,
+}
+
+impl IssueKind {
+    /// Whether rebuilding the collection repairs the problem.
+    pub fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            IssueKind::InvalidVector | IssueKind::InconsistentIndex | IssueKind::DamagedGraph
+        )
+    }
+}
+
+/// A problem found by a verification.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Issue {
+    /// Kind of problem.
+    pub kind: IssueKind,
+    /// Human-readable description.
+    pub detail: String,
+}
+
+impl Issue {
+    pub(crate) fn new(kind: IssueKind, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            detail: detail.into(),
+        }
+    }
+
+    /// An issue affecting `ids`, naming the first few of them.
+    pub(crate) fn for_ids(kind: IssueKind, problem: &str, ids: &[String]) -> Self {
+        let mut examples: Vec<String> = ids
+            .iter()
+            .take(MAX_EXAMPLES)
+            .map(|id| format!("'{}'", id))
+            .collect();
+        if ids.len() > MAX_EXAMPLES {
+            examples.push(format!("{} more", ids.len() - MAX_EXAMPLES));
+        }
+        Self::new(
+            kind,
+            format!("{} {}: {}", ids.len(), problem, examples.join(", ")),
+        )
+    }
+}
+
+/// Verification result for one collection.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CollectionReport {
+    /// Name of the collection.
+    pub name: String,
+    /// Whether the collection is loaded. Collections whose files could not
+    /// be loaded only have their files checked.
+    pub loaded: bool,
+    /// Number of vectors in the loaded collection.
+    pub vector_count: usize,
+    /// Problems found, in its files and then its index.
+    pub issues: Vec<Issue>,
+}
+
+impl CollectionReport {
+    /// Whether no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Whether rebuilding the collection would repair some of its problems.
+    pub fn needs_rebuild(&self) -> bool {
+        self.loaded && self.issues.iter().any(|issue| issue.kind.is_repairable())
+    }
+}
+
+/// Result of [`VectorDb::verify`](crate::VectorDb::verify).
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VerifyReport {
+    /// Problems with the database as a whole, such as `collections.json`.
+    pub issues: Vec<Issue>,
+    /// Per-collection results, sorted by name.
+    pub collections: Vec<CollectionReport>,
+}
+
+impl VerifyReport {
+    /// Whether no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty() && self.collections.iter().all(CollectionReport::is_ok)
+    }
+
+    /// Total number of problems found.
+    pub fn issue_count(&self) -> usize {
+        self.issues.len()
+            + self
+                .collections
+                .iter()
+                .map(|c| c.issues.len())
+                .sum::<usize>()
+    }
+
+    /// Names of the collections a rebuild would repair.
+    pub fn needs_rebuild(&self) -> Vec<&str> {
+        self.collections
+            .iter()
+            .filter(|c| c.needs_rebuild())
+            .map(|c| c.name.as_str())
+            .collect()
+    }
+
+    /// The report for a collection, added if it has none yet.
+    pub(crate) fn collection_mut(&mut self, name: &str) -> &mut CollectionReport {
+        let at = match self.collections.iter().position(|c| c.name == name) {
+            Some(at) => at,
+            None => {
+                self.collections.push(CollectionReport {
+                    name: name.to_string(),
+                    loaded: false,
+                    vector_count: 0,
+                    issues: Vec::new(),
+                });
+                self.collections.len() - 1
+            }
+        };
+        &mut self.collections[at]
+    }
+}
+
+/// Check the files of a persistent database into `report`.
+pub(crate) async fn check_files(base_path: &Path, report: &mut VerifyReport) {
+    let listing_path = base_path.join("collections.json");
+    let listed: Vec<String> = match tokio::fs::read_to_string(&listing_path).await {
+        Ok(data) => match serde_json::from_str(&data) {
+            Ok(names) => names,
+            Err(e) => {
+                report.issues.push(Issue::new(
+                    IssueKind::CorruptFile,
+                    format!("collections.json cannot be parsed: {}", e),
+                ));
+                Vec::new()
+            }
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            report.issues.push(Issue::new(
+                IssueKind::CorruptFile,
+                format!("collections.json cannot be read: {}", e),
+            ));
+            Vec::new()
+        }
+    };
+
+    for name in &listed {
+        let issues = persistence::verify_collection_files(base_path, name).await;
+        report.collection_mut(name).issues.extend(issues);
+    }
+
+    // Directories the loader never looks at
+    let listed: HashSet<&String> = listed.iter().collect();
+    let Ok(mut entries) = tokio::fs::read_dir(base_path).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let is_dir = entry.file_type().await.is_ok_and(|t| t.is_dir());
+        let name = entry.file_name().to_string_lossy().to_string();
+        if is_dir && !listed.contains(&name) && entry.path().join("metadata.json").exists() {
+            report.issues.push(Issue::new(
+                IssueKind::UnlistedCollection,
+                format!(
+                    "Collection directory '{}' is not listed in collections.json",
+                    name
+                ),
+            ));
+        }
+    }
+}
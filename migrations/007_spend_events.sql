@@ -0,0 +1,14 @@
+-- Spend tracking: estimated cost of every agent generation, used for budget enforcement
+CREATE TABLE IF NOT EXISTS spend_events (
+    id            TEXT             PRIMARY KEY,
+    user_id       TEXT             NOT NULL,
+    agent_name    TEXT             NOT NULL,
+    model         TEXT             NOT NULL,
+    input_tokens  BIGINT           NOT NULL DEFAULT 0,
+    output_tokens BIGINT           NOT NULL DEFAULT 0,
+    cost_usd      DOUBLE PRECISION NOT NULL DEFAULT 0,
+    created_at    BIGINT           NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_spend_events_user    ON spend_events(user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_spend_events_agent   ON spend_events(agent_name, created_at);
+CREATE INDEX IF NOT EXISTS idx_spend_events_created ON spend_events(created_at);
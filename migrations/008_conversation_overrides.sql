@@ -0,0 +1,4 @@
+-- Per-conversation overrides pinned by clients (model picker, agent, temperature)
+ALTER TABLE IF EXISTS conversations ADD COLUMN IF NOT EXISTS model       TEXT;
+ALTER TABLE IF EXISTS conversations ADD COLUMN IF NOT EXISTS temperature REAL;
+ALTER TABLE IF EXISTS conversations ADD COLUMN IF NOT EXISTS agent       TEXT;
@@ -0,0 +1,23 @@
+-- Custom agents created by users through the API (/api/agents), resolved at chat time
+CREATE TABLE IF NOT EXISTS user_agents (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    display_name TEXT,
+    description TEXT,
+    model TEXT NOT NULL,
+    system_prompt TEXT,
+    tools TEXT NOT NULL DEFAULT '[]',
+    max_tool_iterations INTEGER NOT NULL DEFAULT 10,
+    parallel_tools BOOLEAN NOT NULL DEFAULT false,
+    extra TEXT NOT NULL DEFAULT '{}',
+    is_public BOOLEAN NOT NULL DEFAULT false,
+    usage_count INTEGER NOT NULL DEFAULT 0,
+    rating_sum INTEGER NOT NULL DEFAULT 0,
+    rating_count INTEGER NOT NULL DEFAULT 0,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL,
+    UNIQUE(user_id, name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_agents_public ON user_agents(name) WHERE is_public;
@@ -0,0 +1,2 @@
+-- Sampling seed of reproducible runs (NULL when the run was not seeded)
+ALTER TABLE IF EXISTS agent_runs ADD COLUMN IF NOT EXISTS seed BIGINT;
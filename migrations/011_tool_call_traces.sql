@@ -0,0 +1,18 @@
+-- Tool calls made while generating an assistant message, in order, for debugging agent runs
+CREATE TABLE IF NOT EXISTS tool_call_traces (
+    id              TEXT    PRIMARY KEY,
+    conversation_id TEXT    NOT NULL,
+    message_id      TEXT    NOT NULL,
+    position        INTEGER NOT NULL,
+    agent           TEXT    NOT NULL,
+    iteration       INTEGER NOT NULL,
+    tool_call_id    TEXT    NOT NULL,
+    tool_name       TEXT    NOT NULL,
+    arguments       TEXT    NOT NULL DEFAULT 'null',
+    result          TEXT    NOT NULL DEFAULT 'null',
+    success         BOOLEAN NOT NULL,
+    duration_ms     BIGINT  NOT NULL DEFAULT 0,
+    error           TEXT,
+    created_at      BIGINT  NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_tool_call_traces_message ON tool_call_traces(conversation_id, message_id);
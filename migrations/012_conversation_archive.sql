@@ -0,0 +1,4 @@
+-- Conversations whose messages were moved to cold storage keep their row as a stub
+ALTER TABLE IF EXISTS conversations ADD COLUMN IF NOT EXISTS archived_at            BIGINT;
+ALTER TABLE IF EXISTS conversations ADD COLUMN IF NOT EXISTS archive_key            TEXT;
+ALTER TABLE IF EXISTS conversations ADD COLUMN IF NOT EXISTS archived_message_count INTEGER;
@@ -0,0 +1,10 @@
+-- Chat defaults users set for themselves through /api/preferences
+CREATE TABLE IF NOT EXISTS chat_preferences (
+    user_id         TEXT   PRIMARY KEY,
+    default_agent   TEXT,
+    model           TEXT,
+    temperature     REAL,
+    response_length TEXT,
+    language        TEXT,
+    updated_at      BIGINT NOT NULL
+);
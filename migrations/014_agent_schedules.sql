@@ -0,0 +1,19 @@
+-- Agents users run with a fixed prompt on a cron schedule (/api/schedules)
+CREATE TABLE IF NOT EXISTS agent_schedules (
+    id                   TEXT    PRIMARY KEY,
+    user_id              TEXT    NOT NULL,
+    name                 TEXT    NOT NULL,
+    agent                TEXT    NOT NULL,
+    prompt               TEXT    NOT NULL,
+    cron                 TEXT    NOT NULL,
+    enabled              BOOLEAN NOT NULL DEFAULT TRUE,
+    next_run_at          BIGINT,
+    last_run_at          BIGINT,
+    last_status          TEXT,
+    last_conversation_id TEXT,
+    last_error           TEXT,
+    created_at           BIGINT  NOT NULL,
+    updated_at           BIGINT  NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_agent_schedules_user ON agent_schedules(user_id);
+CREATE INDEX IF NOT EXISTS idx_agent_schedules_next_run ON agent_schedules(next_run_at);
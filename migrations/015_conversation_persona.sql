@@ -0,0 +1,2 @@
+-- Persona selected for a conversation, applied by agents that define it
+ALTER TABLE IF EXISTS conversations ADD COLUMN IF NOT EXISTS persona TEXT;
@@ -0,0 +1,14 @@
+-- Agent runs paused until a user approves their tool calls (/api/approvals)
+CREATE TABLE IF NOT EXISTS run_approvals (
+    id              TEXT   PRIMARY KEY,
+    user_id         TEXT   NOT NULL,
+    conversation_id TEXT   NOT NULL,
+    agent           TEXT   NOT NULL,
+    message         TEXT   NOT NULL,
+    state           TEXT   NOT NULL,
+    status          TEXT   NOT NULL DEFAULT 'pending',
+    reason          TEXT,
+    created_at      BIGINT NOT NULL,
+    decided_at      BIGINT
+);
+CREATE INDEX IF NOT EXISTS idx_run_approvals_user ON run_approvals(user_id, status);
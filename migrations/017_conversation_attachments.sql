@@ -0,0 +1,13 @@
+-- Files attached to conversations; their chunks live in a per-conversation
+-- vector collection (/api/conversations/{id}/attachments)
+CREATE TABLE IF NOT EXISTS conversation_attachments (
+    id              TEXT    PRIMARY KEY,
+    conversation_id TEXT    NOT NULL,
+    user_id         TEXT    NOT NULL,
+    filename        TEXT    NOT NULL,
+    size_bytes      BIGINT  NOT NULL,
+    chunks          INTEGER NOT NULL,
+    created_at      BIGINT  NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_conversation_attachments_conversation
+    ON conversation_attachments(conversation_id, created_at);
@@ -0,0 +1,12 @@
+-- Full-text search over conversation titles and message content
+DO $$
+BEGIN
+    IF to_regclass('conversations') IS NOT NULL THEN
+        CREATE INDEX IF NOT EXISTS idx_conversations_title_fts
+            ON conversations USING GIN (to_tsvector('english', COALESCE(title, '')));
+    END IF;
+    IF to_regclass('messages') IS NOT NULL THEN
+        CREATE INDEX IF NOT EXISTS idx_messages_content_fts
+            ON messages USING GIN (to_tsvector('english', content));
+    END IF;
+END $$;
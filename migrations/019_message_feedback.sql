@@ -0,0 +1,30 @@
+-- What generated each assistant message: agent, model and system prompt version
+CREATE TABLE IF NOT EXISTS message_generations (
+    message_id      TEXT    PRIMARY KEY,
+    conversation_id TEXT    NOT NULL,
+    agent           TEXT    NOT NULL,
+    model           TEXT    NOT NULL,
+    prompt_version  TEXT    NOT NULL,
+    created_at      BIGINT  NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_message_generations_conversation ON message_generations(conversation_id);
+
+-- Users' ratings of assistant messages, with a snapshot of the exchange for evaluation datasets
+CREATE TABLE IF NOT EXISTS message_feedback (
+    id              TEXT    PRIMARY KEY,
+    conversation_id TEXT    NOT NULL,
+    message_id      TEXT    NOT NULL,
+    user_id         TEXT    NOT NULL,
+    rating          TEXT    NOT NULL,  -- up, down
+    comment         TEXT,
+    agent           TEXT,
+    model           TEXT,
+    prompt_version  TEXT,
+    question        TEXT    NOT NULL,
+    answer          TEXT    NOT NULL,
+    created_at      BIGINT  NOT NULL,
+    updated_at      BIGINT  NOT NULL,
+    UNIQUE (message_id, user_id)
+);
+CREATE INDEX IF NOT EXISTS idx_message_feedback_agent ON message_feedback(agent, created_at);
+CREATE INDEX IF NOT EXISTS idx_message_feedback_created ON message_feedback(created_at);
@@ -0,0 +1,12 @@
+-- Files uploaded to /api/files; the content lives on disk or in S3 at `location`
+CREATE TABLE IF NOT EXISTS files (
+    id           TEXT    PRIMARY KEY,
+    user_id      TEXT    NOT NULL,
+    filename     TEXT    NOT NULL,
+    content_type TEXT    NOT NULL,
+    size_bytes   BIGINT  NOT NULL,
+    sha256       TEXT    NOT NULL,
+    location     TEXT    NOT NULL,
+    created_at   BIGINT  NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_files_user ON files(user_id, created_at);
@@ -0,0 +1,17 @@
+-- Progress of /api/chat tool-calling runs, kept while they run so a run cut
+-- off by a server restart can be resumed (/api/runs)
+CREATE TABLE IF NOT EXISTS run_checkpoints (
+    id              TEXT   PRIMARY KEY,
+    user_id         TEXT   NOT NULL,
+    conversation_id TEXT   NOT NULL,
+    agent           TEXT   NOT NULL,
+    message         TEXT,
+    input           TEXT   NOT NULL,
+    state           TEXT   NOT NULL,
+    status          TEXT   NOT NULL DEFAULT 'running',
+    instance        TEXT   NOT NULL,
+    created_at      BIGINT NOT NULL,
+    updated_at      BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_run_checkpoints_status ON run_checkpoints(status, updated_at);
+CREATE INDEX IF NOT EXISTS idx_run_checkpoints_user ON run_checkpoints(user_id, status);
@@ -0,0 +1,9 @@
+-- RAG collections and tag filters a conversation's retrieval is pinned to
+-- (/api/conversations/{id}/rag-scope and the /scope chat command)
+CREATE TABLE IF NOT EXISTS conversation_rag_scopes (
+    conversation_id TEXT    PRIMARY KEY,
+    user_id         TEXT    NOT NULL,
+    collections     TEXT    NOT NULL,  -- JSON array of collection names
+    tags            TEXT    NOT NULL,  -- JSON array of tags documents must carry
+    updated_at      BIGINT  NOT NULL
+);
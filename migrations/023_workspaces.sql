@@ -0,0 +1,27 @@
+-- Workspaces: teams sharing custom agents, RAG collections and memory,
+-- isolated from other workspaces (/api/workspaces, X-Workspace-Id header)
+CREATE TABLE IF NOT EXISTS workspaces (
+    id          TEXT    PRIMARY KEY,
+    name        TEXT    NOT NULL,
+    created_by  TEXT    NOT NULL,
+    created_at  BIGINT  NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS workspace_members (
+    workspace_id TEXT    NOT NULL,
+    user_id      TEXT    NOT NULL,
+    role         TEXT    NOT NULL,  -- owner, admin, member
+    created_at   BIGINT  NOT NULL,
+    PRIMARY KEY (workspace_id, user_id)
+);
+CREATE INDEX IF NOT EXISTS idx_workspace_members_user ON workspace_members(user_id);
+
+-- Workspace a conversation was started in; NULL for the user's personal space
+ALTER TABLE IF EXISTS conversations ADD COLUMN IF NOT EXISTS workspace_id TEXT;
+DO $$
+BEGIN
+    IF to_regclass('conversations') IS NOT NULL THEN
+        CREATE INDEX IF NOT EXISTS idx_conversations_workspace
+            ON conversations(user_id, workspace_id);
+    END IF;
+END $$;
@@ -0,0 +1,31 @@
+-- Projects grouping conversations, uploaded files and RAG collections, with
+-- default agent settings for their conversations (/api/projects). Owned by
+-- a user, or by a workspace's owner key and shared by its members.
+CREATE TABLE IF NOT EXISTS projects (
+    id          TEXT    PRIMARY KEY,
+    owner_id    TEXT    NOT NULL,
+    name        TEXT    NOT NULL,
+    description TEXT,
+    agent       TEXT,
+    model       TEXT,
+    temperature REAL,
+    persona     TEXT,
+    collections TEXT    NOT NULL,  -- JSON array of collection names
+    created_by  TEXT    NOT NULL,
+    created_at  BIGINT  NOT NULL,
+    updated_at  BIGINT  NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_projects_owner ON projects(owner_id, name);
+
+-- Project a file or conversation belongs to; NULL for none
+ALTER TABLE files ADD COLUMN IF NOT EXISTS project_id TEXT;
+CREATE INDEX IF NOT EXISTS idx_files_project ON files(project_id);
+
+ALTER TABLE IF EXISTS conversations ADD COLUMN IF NOT EXISTS project_id TEXT;
+DO $$
+BEGIN
+    IF to_regclass('conversations') IS NOT NULL THEN
+        CREATE INDEX IF NOT EXISTS idx_conversations_project
+            ON conversations(project_id);
+    END IF;
+END $$;
@@ -0,0 +1,30 @@
+-- Assistants-style threads (/api/threads): a thread is a conversation.
+-- Messages added to a thread wait here until a run answers them, which
+-- stores them in the conversation.
+CREATE TABLE IF NOT EXISTS thread_messages (
+    id         TEXT   PRIMARY KEY,
+    thread_id  TEXT   NOT NULL,
+    content    TEXT   NOT NULL,
+    created_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_thread_messages_thread ON thread_messages(thread_id, created_at);
+
+-- Runs of an agent on a thread
+CREATE TABLE IF NOT EXISTS thread_runs (
+    id                TEXT   PRIMARY KEY,
+    thread_id         TEXT   NOT NULL,
+    user_id           TEXT   NOT NULL,
+    assistant_id      TEXT,             -- agent; NULL when the message is routed
+    status            TEXT   NOT NULL,
+    approval_id       TEXT,             -- tool calls waiting for approval
+    message_id        TEXT,             -- the answer, once stored
+    last_error        TEXT,
+    incomplete_reason TEXT,             -- run limit that cut the answer short
+    prompt_tokens     BIGINT,
+    completion_tokens BIGINT,
+    created_at        BIGINT NOT NULL,
+    started_at        BIGINT,
+    completed_at      BIGINT
+);
+CREATE INDEX IF NOT EXISTS idx_thread_runs_thread ON thread_runs(thread_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_thread_runs_approval ON thread_runs(approval_id);
@@ -0,0 +1,8 @@
+-- Users' roles (admin, member, read_only), embedded in their JWTs and
+-- assigned through /api/admin/users/{id}/role. Users without a row are members.
+CREATE TABLE IF NOT EXISTS user_roles (
+    user_id     TEXT    PRIMARY KEY,
+    role        TEXT    NOT NULL,
+    updated_at  BIGINT  NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_user_roles_role ON user_roles(role);
@@ -0,0 +1,16 @@
+-- API keys users issue for machine clients (/api/api-keys), sent in the
+-- X-API-Key header instead of a JWT. Only the key's SHA-256 hash is kept.
+CREATE TABLE IF NOT EXISTS user_api_keys (
+    id            TEXT    PRIMARY KEY,
+    user_id       TEXT    NOT NULL,
+    name          TEXT    NOT NULL,
+    key_hash      TEXT    NOT NULL UNIQUE,
+    key_prefix    TEXT    NOT NULL,
+    scopes        TEXT    NOT NULL,  -- JSON array: chat, rag
+    request_count BIGINT  NOT NULL DEFAULT 0,
+    last_used_at  BIGINT,
+    created_at    BIGINT  NOT NULL,
+    rotated_at    BIGINT,
+    revoked_at    BIGINT
+);
+CREATE INDEX IF NOT EXISTS idx_user_api_keys_user ON user_api_keys(user_id);
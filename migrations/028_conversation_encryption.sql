@@ -0,0 +1,5 @@
+-- Conversations whose messages are encrypted with a key derived from a
+-- secret the user sends with each request and the server never stores.
+-- The salt derives the key; the check tells a wrong secret from a right one.
+ALTER TABLE conversations ADD COLUMN IF NOT EXISTS encryption_salt TEXT;
+ALTER TABLE conversations ADD COLUMN IF NOT EXISTS encryption_check TEXT;
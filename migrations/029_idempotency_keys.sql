@@ -0,0 +1,15 @@
+-- Responses to chat and research requests sent with an Idempotency-Key
+-- header, replayed when a client retries the request. A row without a
+-- status is a request still running.
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    caller       TEXT    NOT NULL,
+    key          TEXT    NOT NULL,
+    fingerprint  TEXT    NOT NULL,
+    status_code  INTEGER,
+    content_type TEXT,
+    body         BYTEA,
+    created_at   BIGINT  NOT NULL,
+    expires_at   BIGINT  NOT NULL,
+    PRIMARY KEY (caller, key)
+);
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_expires ON idempotency_keys(expires_at);
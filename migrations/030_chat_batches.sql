@@ -0,0 +1,31 @@
+-- Batches of chat messages answered by an agent (/api/chat/batch), one
+-- row per message in chat_batch_items
+CREATE TABLE IF NOT EXISTS chat_batches (
+    id           TEXT    PRIMARY KEY,
+    user_id      TEXT    NOT NULL,
+    workspace_id TEXT,
+    agent_type   TEXT,             -- NULL when messages are routed
+    status       TEXT    NOT NULL,
+    created_at   BIGINT  NOT NULL,
+    started_at   BIGINT,
+    completed_at BIGINT
+);
+CREATE INDEX IF NOT EXISTS idx_chat_batches_user ON chat_batches(user_id, created_at);
+
+CREATE TABLE IF NOT EXISTS chat_batch_items (
+    batch_id     TEXT    NOT NULL,
+    position     INTEGER NOT NULL,
+    custom_id    TEXT,
+    message      TEXT    NOT NULL,
+    context_id   TEXT,
+    seed         BIGINT,
+    status       TEXT    NOT NULL,
+    agent        TEXT,
+    response     TEXT,
+    message_id   TEXT,
+    approval_id  TEXT,
+    error        TEXT,
+    started_at   BIGINT,
+    completed_at BIGINT,
+    PRIMARY KEY (batch_id, position)
+);
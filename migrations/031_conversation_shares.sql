@@ -0,0 +1,15 @@
+-- Read-only share links to conversations (/api/conversations/{id}/share),
+-- viewed without an account at /api/shared/{token}. Only the token's SHA-256
+-- hash is kept.
+CREATE TABLE IF NOT EXISTS conversation_shares (
+    id              TEXT    PRIMARY KEY,
+    conversation_id TEXT    NOT NULL,
+    user_id         TEXT    NOT NULL,
+    token_hash      TEXT    NOT NULL UNIQUE,
+    views           BIGINT  NOT NULL DEFAULT 0,
+    created_at      BIGINT  NOT NULL,
+    expires_at      BIGINT,
+    revoked_at      BIGINT
+);
+CREATE INDEX IF NOT EXISTS idx_conversation_shares_conversation
+    ON conversation_shares(conversation_id);
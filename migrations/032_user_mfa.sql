@@ -0,0 +1,29 @@
+-- TOTP multi-factor authentication (/api/auth/mfa). A user's secret is
+-- pending until they confirm it with a first code; `required` is set by
+-- admins for users who must use MFA even when [auth.mfa] doesn't require it.
+CREATE TABLE IF NOT EXISTS user_mfa (
+    user_id     TEXT    PRIMARY KEY,
+    secret      TEXT,
+    enabled     BOOLEAN NOT NULL DEFAULT FALSE,
+    required    BOOLEAN NOT NULL DEFAULT FALSE,
+    last_step   BIGINT  NOT NULL DEFAULT 0,
+    updated_at  BIGINT  NOT NULL
+);
+
+-- Single-use recovery codes, by SHA-256 hash.
+CREATE TABLE IF NOT EXISTS mfa_recovery_codes (
+    user_id     TEXT    NOT NULL,
+    code_hash   TEXT    NOT NULL,
+    used_at     BIGINT,
+    PRIMARY KEY (user_id, code_hash)
+);
+
+-- Logins waiting for their second factor, by SHA-256 hash of the token
+-- returned after the password.
+CREATE TABLE IF NOT EXISTS mfa_challenges (
+    token_hash  TEXT    PRIMARY KEY,
+    user_id     TEXT    NOT NULL,
+    attempts    INTEGER NOT NULL DEFAULT 0,
+    expires_at  BIGINT  NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_mfa_challenges_expires ON mfa_challenges(expires_at);
@@ -0,0 +1,6 @@
+-- Devices signed in with each refresh-token session, listed at
+-- /api/auth/sessions. A session keeps its ID when its refresh token rotates.
+ALTER TABLE IF EXISTS sessions ADD COLUMN IF NOT EXISTS user_agent   TEXT;
+ALTER TABLE IF EXISTS sessions ADD COLUMN IF NOT EXISTS ip_address   TEXT;
+ALTER TABLE IF EXISTS sessions ADD COLUMN IF NOT EXISTS last_used_at BIGINT;
+CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions(user_id);
@@ -0,0 +1,24 @@
+-- Durable background jobs (scheduled agent runs, ingest jobs) run by the
+-- job workers; see src/db/jobs.rs
+CREATE TABLE IF NOT EXISTS jobs (
+    id           TEXT    PRIMARY KEY,
+    kind         TEXT    NOT NULL,
+    payload      JSONB   NOT NULL,
+    -- Identifies the work, so it is queued once while pending or running
+    dedupe_key   TEXT,
+    -- 'pending', 'running', 'succeeded' or 'dead'
+    status       TEXT    NOT NULL DEFAULT 'pending',
+    attempts     INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    run_at       BIGINT  NOT NULL,
+    locked_by    TEXT,
+    locked_until BIGINT,
+    last_error   TEXT,
+    created_at   BIGINT  NOT NULL,
+    updated_at   BIGINT  NOT NULL,
+    finished_at  BIGINT
+);
+CREATE INDEX IF NOT EXISTS idx_jobs_due ON jobs(status, run_at);
+CREATE INDEX IF NOT EXISTS idx_jobs_finished ON jobs(finished_at) WHERE finished_at IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_jobs_active_dedupe ON jobs(dedupe_key)
+    WHERE dedupe_key IS NOT NULL AND status IN ('pending', 'running');
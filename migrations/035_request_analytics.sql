@@ -0,0 +1,22 @@
+-- Per-request analytics of agent generations (chat, streaming, regenerations,
+-- resumed and scheduled runs), the data behind GET /api/admin/analytics
+CREATE TABLE IF NOT EXISTS request_analytics (
+    id            TEXT             PRIMARY KEY,
+    tenant_id     TEXT,
+    user_id       TEXT,
+    -- 'chat', 'stream', 'regenerate', 'resume' or 'schedule'
+    source        TEXT             NOT NULL,
+    agent_name    TEXT             NOT NULL,
+    model         TEXT             NOT NULL,
+    -- 'completed', 'paused', 'stopped', 'cancelled' or 'failed'
+    status        TEXT             NOT NULL,
+    latency_ms    BIGINT           NOT NULL DEFAULT 0,
+    input_tokens  BIGINT           NOT NULL DEFAULT 0,
+    output_tokens BIGINT           NOT NULL DEFAULT 0,
+    cost_usd      DOUBLE PRECISION NOT NULL DEFAULT 0,
+    error         TEXT,
+    created_at    BIGINT           NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_request_analytics_created ON request_analytics(created_at);
+CREATE INDEX IF NOT EXISTS idx_request_analytics_agent   ON request_analytics(agent_name, created_at);
+CREATE INDEX IF NOT EXISTS idx_request_analytics_tenant  ON request_analytics(tenant_id, created_at);
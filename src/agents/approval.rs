@@ -0,0 +1,160 @@
+//! Human approval of tool calls.
+//!
+//! A tool configured with `requires_approval = true` only runs once a user
+//! approves the call, whichever agent makes it; an agent configured with
+//! `requires_approval = true` needs approval for every tool call it makes.
+//!
+//! ```toml
+//! [tools.send_email]
+//! requires_approval = true
+//!
+//! [agents.operations]
+//! requires_approval = true
+//! ```
+//!
+//! Runs started through `/api/chat` have approval checkpoints: when the
+//! model asks for a call that needs approval, the agent stops before running
+//! any of that round's calls and returns a [`PausedRun`] holding everything
+//! needed to continue. The server stores it and answers with a
+//! [`PendingApproval`]; `POST /api/approvals/{id}` then resumes the run.
+//! Approved calls run as usual. Rejected calls are refused, and the model
+//! carries on without them.
+//!
+//! Runs without checkpoints, such as workflows, scheduled runs and agents
+//! using the `react` strategy, have nobody to ask, so calls that need
+//! approval are refused.
+
+use crate::llm::coordinator::ConversationMessage;
+use crate::tools::permissions::ToolRefusal;
+use crate::types::ToolCall;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A run stopped before tool calls that need a user's approval.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PausedRun {
+    /// Agent whose run paused
+    pub agent: String,
+    /// Question the run answers, for reflection on the final answer
+    pub question: String,
+    /// Tool-calling conversation so far, ending with the model's tool calls
+    pub history: Vec<ConversationMessage>,
+    /// Tool-calling round that paused, starting at 1
+    pub iteration: usize,
+    /// The round's tool calls, none of which have run
+    pub calls: Vec<ToolCall>,
+    /// IDs of the calls that need approval
+    pub pending: Vec<String>,
+}
+
+impl PausedRun {
+    /// The calls waiting for approval
+    pub fn pending_calls(&self) -> Vec<PendingToolCall> {
+        self.calls
+            .iter()
+            .filter(|call| self.pending.contains(&call.id))
+            .map(|call| PendingToolCall {
+                id: call.id.clone(),
+                name: call.name.clone(),
+                arguments: call.arguments.clone(),
+            })
+            .collect()
+    }
+}
+
+/// A user's decision on a paused run's tool calls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApprovalDecision {
+    /// Run the calls
+    Approve,
+    /// Refuse the calls, with the user's reason if they gave one
+    Reject(Option<String>),
+}
+
+impl ApprovalDecision {
+    /// Refusal returned to the model for a call this decision doesn't allow
+    ///
+    /// `None` means no decision could be asked for.
+    pub fn refusal(decision: Option<&Self>, tool: &str) -> Option<ToolRefusal> {
+        let reason = match decision {
+            Some(ApprovalDecision::Approve) => return None,
+            Some(ApprovalDecision::Reject(reason)) => match reason {
+                Some(reason) => format!("The user rejected the call to '{}': {}", tool, reason),
+                None => format!("The user rejected the call to '{}'", tool),
+            },
+            None => format!(
+                "Tool '{}' requires a user's approval, which this run cannot ask for",
+                tool
+            ),
+        };
+        Some(ToolRefusal::approval(tool, reason))
+    }
+}
+
+/// A tool call waiting for a user's approval.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PendingToolCall {
+    /// Tool call ID from the model
+    pub id: String,
+    /// Name of the tool
+    pub name: String,
+    /// Arguments the tool would be called with
+    pub arguments: serde_json::Value,
+}
+
+/// A run waiting for a user to approve its tool calls.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PendingApproval {
+    /// Approval ID, for `POST /api/approvals/{id}`
+    pub id: String,
+    /// Conversation the run answers in
+    pub conversation_id: String,
+    /// Agent waiting for approval
+    pub agent: String,
+    /// Calls that need approval
+    pub tool_calls: Vec<PendingToolCall>,
+    /// When the run paused (Unix timestamp)
+    pub created_at: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::permissions::PermissionRule;
+    use serde_json::json;
+
+    #[test]
+    fn test_pending_calls_and_refusals() {
+        let call = |id: &str, name: &str| ToolCall {
+            id: id.to_string(),
+            name: name.to_string(),
+            arguments: json!({"to": "ops@example.com"}),
+        };
+        let paused = PausedRun {
+            agent: "operations".to_string(),
+            question: "Email the report".to_string(),
+            history: Vec::new(),
+            iteration: 1,
+            calls: vec![call("c1", "calculator"), call("c2", "send_email")],
+            pending: vec!["c2".to_string()],
+        };
+        let pending = paused.pending_calls();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].name, "send_email");
+
+        assert!(
+            ApprovalDecision::refusal(Some(&ApprovalDecision::Approve), "send_email").is_none()
+        );
+        let refusal = ApprovalDecision::refusal(
+            Some(&ApprovalDecision::Reject(Some(
+                "Wrong recipient".to_string(),
+            ))),
+            "send_email",
+        )
+        .unwrap();
+        assert_eq!(refusal.rule, PermissionRule::RequiresApproval);
+        assert!(refusal.reason.ends_with("Wrong recipient"));
+        let refusal = ApprovalDecision::refusal(None, "send_email").unwrap();
+        assert!(refusal.reason.contains("cannot ask"));
+    }
+}
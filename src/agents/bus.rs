@@ -0,0 +1,293 @@
+//! Agent message bus.
+//!
+//! Each workflow run has an [`AgentBus`] its agents share. An agent
+//! configured to publish adds directive lines to its answer, which are
+//! removed from it and published as typed events:
+//!
+//! ```text
+//! PUBLISH: {"kind": "warning", "topic": "pricing", "content": "The 2023 price list is outdated"}
+//! ```
+//!
+//! Agents that subscribe to a kind are shown the events of that kind other
+//! agents published earlier in the run, so agents can coordinate without
+//! calling each other directly:
+//!
+//! ```toml
+//! [agents.research.bus]
+//! publish = ["finding", "warning"]
+//!
+//! [agents.writer.bus]
+//! subscribe = ["finding", "warning"]
+//! topics = ["pricing"]
+//! ```
+//!
+//! Code running a workflow can also follow the events live with
+//! [`AgentBus::subscribe`].
+
+use crate::utils::toml_config::{AgentBusConfig, BusEventKind};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+/// Prefix of the directive line an agent adds to publish an event.
+pub const PUBLISH_PREFIX: &str = "PUBLISH:";
+
+/// Maximum number of events a bus keeps; the oldest are dropped first.
+pub const MAX_EVENTS: usize = 100;
+
+/// Number of events a live subscriber can lag behind before missing some.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// An event an agent published on the bus.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BusEvent {
+    /// Kind of event: finding, warning or artifact
+    #[schema(value_type = String)]
+    pub kind: BusEventKind,
+    /// What the event is about, for subscribers filtering by topic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    /// Agent that published the event
+    pub from: String,
+    /// The event's text
+    pub content: String,
+    /// Structured payload of the event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    /// Unix timestamp when the event was published
+    pub timestamp: i64,
+}
+
+impl BusEvent {
+    /// Whether an agent subscribed with `config` is shown this event.
+    pub fn matches(&self, config: &AgentBusConfig) -> bool {
+        config.subscribe.contains(&self.kind)
+            && (config.topics.is_empty()
+                || self
+                    .topic
+                    .as_ref()
+                    .is_some_and(|topic| config.topics.contains(topic)))
+    }
+}
+
+/// In-process publish/subscribe bus shared by the agents of a workflow run.
+#[derive(Debug)]
+pub struct AgentBus {
+    events: Mutex<VecDeque<BusEvent>>,
+    sender: broadcast::Sender<BusEvent>,
+}
+
+impl AgentBus {
+    /// Create an empty bus.
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            events: Mutex::new(VecDeque::new()),
+            sender,
+        }
+    }
+
+    /// Publish an event to the bus and its live subscribers.
+    pub fn publish(&self, event: BusEvent) {
+        {
+            let mut events = self.events.lock();
+            if events.len() == MAX_EVENTS {
+                events.pop_front();
+            }
+            events.push_back(event.clone());
+        }
+        // No live subscribers is fine: the event is kept for later agents
+        let _ = self.sender.send(event);
+    }
+
+    /// Receive events as they are published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<BusEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Events published so far, oldest first.
+    pub fn events(&self) -> Vec<BusEvent> {
+        self.events.lock().iter().cloned().collect()
+    }
+
+    /// Events published so far that `agent`, subscribed with `config`, is
+    /// shown: matching events from other agents, oldest first.
+    pub fn events_for(&self, agent: &str, config: &AgentBusConfig) -> Vec<BusEvent> {
+        self.events
+            .lock()
+            .iter()
+            .filter(|event| event.from != agent && event.matches(config))
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for AgentBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Directive as written by the model.
+#[derive(Deserialize)]
+struct Directive {
+    kind: BusEventKind,
+    #[serde(default)]
+    topic: Option<String>,
+    content: String,
+    #[serde(default)]
+    data: Option<serde_json::Value>,
+}
+
+/// System prompt section telling an agent how to publish events.
+pub fn instructions(kinds: &[BusEventKind]) -> String {
+    format!(
+        "You can share events with the other agents working on this task by adding lines of the form:\n\
+         {} {{\"kind\": \"<kind>\", \"topic\": \"<optional topic>\", \"content\": \"<what to share>\"}}\n\
+         These lines are removed from your answer. Kinds you can publish: {}",
+        PUBLISH_PREFIX,
+        kinds
+            .iter()
+            .map(BusEventKind::as_str)
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// System prompt section showing an agent the events published for it, if any.
+pub fn render(events: &[BusEvent]) -> Option<String> {
+    if events.is_empty() {
+        return None;
+    }
+    let lines = events
+        .iter()
+        .map(|event| {
+            let mut line = format!("- [{}] {}", event.kind.as_str(), event.from);
+            if let Some(topic) = &event.topic {
+                line.push_str(&format!(" on {}", topic));
+            }
+            line.push_str(&format!(": {}", event.content));
+            if let Some(data) = &event.data {
+                line.push_str(&format!(" {}", data));
+            }
+            line
+        })
+        .collect::<Vec<_>>();
+    Some(format!(
+        "Events published by other agents working on this task:\n{}",
+        lines.join("\n")
+    ))
+}
+
+/// Remove publish directives from an agent's output.
+///
+/// Returns the output without the directive lines, and the events of the
+/// kinds in `allowed`. Directives of other kinds are dropped; lines that are
+/// not valid directives are left in the output.
+pub fn take_events(from: &str, output: &str, allowed: &[BusEventKind]) -> (String, Vec<BusEvent>) {
+    let mut events = Vec::new();
+    let mut kept = Vec::new();
+    let mut stripped = false;
+    for line in output.lines() {
+        let directive = line
+            .trim()
+            .strip_prefix(PUBLISH_PREFIX)
+            .and_then(|directive| serde_json::from_str::<Directive>(directive.trim()).ok());
+        let Some(directive) = directive else {
+            kept.push(line);
+            continue;
+        };
+        stripped = true;
+        if !allowed.contains(&directive.kind) {
+            tracing::warn!(
+                "Agent '{}' may not publish {} events; dropping it",
+                from,
+                directive.kind.as_str()
+            );
+            continue;
+        }
+        events.push(BusEvent {
+            kind: directive.kind,
+            topic: directive.topic.filter(|topic| !topic.is_empty()),
+            from: from.to_string(),
+            content: directive.content,
+            data: directive.data,
+            timestamp: chrono::Utc::now().timestamp(),
+        });
+    }
+    if !stripped {
+        return (output.to_string(), events);
+    }
+    (kept.join("\n").trim().to_string(), events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: BusEventKind, from: &str, topic: Option<&str>) -> BusEvent {
+        BusEvent {
+            kind,
+            topic: topic.map(str::to_string),
+            from: from.to_string(),
+            content: "content".to_string(),
+            data: None,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_take_events_strips_directives() {
+        let output = "The answer is 42.\n\
+            PUBLISH: {\"kind\": \"finding\", \"topic\": \"math\", \"content\": \"It is 42\", \"data\": {\"n\": 42}}\n\
+            PUBLISH: {\"kind\": \"warning\", \"content\": \"Not allowed\"}\n\
+            PUBLISH: not json";
+
+        let (output, events) = take_events("research", output, &[BusEventKind::Finding]);
+        assert_eq!(output, "The answer is 42.\nPUBLISH: not json");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, BusEventKind::Finding);
+        assert_eq!(events[0].topic.as_deref(), Some("math"));
+        assert_eq!(events[0].from, "research");
+        assert_eq!(events[0].data, Some(serde_json::json!({"n": 42})));
+
+        let (unchanged, events) = take_events("research", "No events here.", &[]);
+        assert_eq!(unchanged, "No events here.");
+        assert!(events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_bus_filters_subscriptions() {
+        let bus = AgentBus::new();
+        let mut live = bus.subscribe();
+        bus.publish(event(BusEventKind::Finding, "research", Some("pricing")));
+        bus.publish(event(BusEventKind::Warning, "research", None));
+        bus.publish(event(BusEventKind::Finding, "writer", Some("pricing")));
+        bus.publish(event(BusEventKind::Artifact, "research", Some("pricing")));
+
+        assert_eq!(live.recv().await.unwrap().kind, BusEventKind::Finding);
+        assert_eq!(bus.events().len(), 4);
+
+        let config = AgentBusConfig {
+            subscribe: vec![BusEventKind::Finding, BusEventKind::Warning],
+            ..Default::default()
+        };
+        let shown = bus.events_for("writer", &config);
+        assert_eq!(shown.len(), 2);
+        assert!(shown.iter().all(|e| e.from == "research"));
+
+        let config = AgentBusConfig {
+            topics: vec!["pricing".to_string()],
+            ..config
+        };
+        let shown = bus.events_for("writer", &config);
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].kind, BusEventKind::Finding);
+
+        let section = render(&shown).unwrap();
+        assert!(section.contains("- [finding] research on pricing: content"));
+        assert!(render(&[]).is_none());
+    }
+}
@@ -0,0 +1,100 @@
+//! Checkpoints of tool-calling runs.
+//!
+//! A run with a [`RunJournal`] saves its progress before each round of tool
+//! calls and again once the round's results are in. If the server stops
+//! mid-run, the last checkpoint holds everything needed to carry on, so the
+//! run can be resumed instead of leaving its conversation half-answered.
+//!
+//! Checkpoints use the shape of a [`PausedRun`]. A checkpoint taken before a
+//! round lists the round's calls, which may or may not have run by the time
+//! the server stopped. They are never run again: a resumed run gives the
+//! model an error result for each of them and carries on from there.
+//!
+//! Runs started through `/api/chat` keep a journal. When a server finds a
+//! run whose server went away, it marks the run interrupted and tells the
+//! user in the conversation; `POST /api/runs/{id}/resume` then resumes it.
+//! See [`crate::db::checkpoints`].
+
+use crate::agents::approval::PausedRun;
+use crate::llm::coordinator::ConversationMessage;
+use crate::types::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Where a run saves its checkpoints.
+#[async_trait]
+pub trait RunJournal: Send + Sync {
+    /// Save a run's progress, replacing its previous checkpoint
+    async fn save(&self, checkpoint: &PausedRun) -> Result<()>;
+}
+
+/// A checkpointed run ready to carry on after an interruption
+///
+/// Each call of the round that was running gets an error result instead of
+/// running again, since it may already have had its effect.
+pub fn interrupted(mut checkpoint: PausedRun) -> PausedRun {
+    for call in checkpoint.calls.drain(..) {
+        let result = serde_json::json!({
+            "error": format!(
+                "The call to '{}' was interrupted by a server restart; it may or may not have completed",
+                call.name
+            )
+        });
+        checkpoint
+            .history
+            .push(ConversationMessage::tool_result(&call.id, &result));
+    }
+    checkpoint.pending.clear();
+    checkpoint
+}
+
+/// A run cut off by a server restart, which the user can resume.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct InterruptedRun {
+    /// Run ID, used to resume the run
+    pub id: String,
+    /// Conversation the run answers in
+    pub conversation_id: String,
+    /// Agent that was running
+    pub agent: String,
+    /// When the run started (Unix timestamp)
+    pub created_at: i64,
+    /// When the run last made progress (Unix timestamp)
+    pub updated_at: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::coordinator::MessageRole;
+    use crate::types::ToolCall;
+
+    #[test]
+    fn test_interrupted_round_is_not_rerun() {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "send_email".to_string(),
+            arguments: serde_json::json!({"to": "bob@example.com"}),
+        };
+        let checkpoint = PausedRun {
+            agent: "operations".to_string(),
+            question: "Email Bob".to_string(),
+            history: vec![
+                ConversationMessage::user("Email Bob"),
+                ConversationMessage::assistant("", vec![call.clone()]),
+            ],
+            iteration: 1,
+            calls: vec![call],
+            pending: Vec::new(),
+        };
+
+        let resumed = interrupted(checkpoint);
+        assert!(resumed.calls.is_empty());
+        assert_eq!(resumed.iteration, 1);
+        let last = resumed.history.last().unwrap();
+        assert_eq!(last.role, MessageRole::Tool);
+        assert_eq!(last.tool_call_id.as_deref(), Some("call_1"));
+        assert!(last.content.contains("interrupted"));
+    }
+}
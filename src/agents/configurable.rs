@@ -4,13 +4,56 @@
 //! It replaces the hardcoded agent implementations with a flexible,
 //! configuration-driven approach.
 
-use crate::agents::Agent;
-use crate::llm::LLMClient;
+use crate::agents::approval::{ApprovalDecision, PausedRun};
+use crate::agents::bus;
+use crate::agents::checkpoint::RunJournal;
+use crate::agents::context::ContextBudget;
+use crate::agents::handoff::{self, Handoff};
+use crate::agents::hooks::{AgentHook, AgentHooks};
+use crate::agents::knowledge::{self, KnowledgeBase};
+use crate::agents::limits::{LimitExceeded, RunLimits, RunMeter};
+use crate::agents::memory;
+use crate::agents::react::{self, ReactReply, ReactStep};
+use crate::agents::reflection::{self, Reflection};
+use crate::agents::structured::{self, OutputSchema};
+use crate::agents::{Agent, AgentEvent, AgentEventStream};
+use crate::llm::coordinator::{ConversationMessage, MessageRole, ToolCallRecord};
+use crate::llm::{GuardrailPipeline, LLMClient, LLMResponse};
+use crate::memory::estimate_tokens;
+use crate::rag::attachments;
+use crate::rag::batcher::BatchEmbedder;
+use crate::tools::permissions::{self, ToolPermissions};
 use crate::tools::registry::ToolRegistry;
-use crate::types::{AgentContext, AgentType, Result, ToolDefinition};
-use crate::utils::toml_config::AgentConfig;
+use crate::types::{AgentContext, AgentType, AppError, Result, Source, ToolCall, ToolDefinition};
+use crate::utils::toml_config::{
+    AgentBusConfig, AgentConfig, AgentMemoryConfig, AgentRagConfig, AgentStrategy,
+};
 use async_trait::async_trait;
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Timeout for a single tool call in a streamed run
+const TOOL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Output of [`ConfigurableAgent::execute_traced`].
+#[derive(Debug, Clone, Default)]
+pub struct AgentTrace {
+    /// Final response
+    pub response: String,
+    /// ReAct steps taken, in order (empty for the `direct` strategy)
+    pub steps: Vec<ReactStep>,
+    /// Tool calls made, in order
+    pub tool_calls: Vec<ToolCallRecord>,
+    /// The limit that stopped the run early, if one did
+    pub limit_exceeded: Option<LimitExceeded>,
+    /// The run, if it paused for a user's approval of its tool calls. The
+    /// response is then empty.
+    pub paused: Option<PausedRun>,
+    /// Documents of the knowledge passages injected into the prompt
+    pub sources: Vec<Source>,
+}
 
 /// A configurable agent that derives its behavior from TOML configuration
 pub struct ConfigurableAgent {
@@ -30,6 +73,42 @@ pub struct ConfigurableAgent {
     max_tool_iterations: usize,
     /// Whether to execute tools in parallel
     parallel_tools: bool,
+    /// Guardrails applied to input and output (if any)
+    guardrails: Option<Arc<GuardrailPipeline>>,
+    /// Agents this agent may hand the conversation off to
+    handoffs: Vec<String>,
+    /// How the agent works towards an answer
+    strategy: AgentStrategy,
+    /// JSON Schema the agent's output must conform to
+    output_schema: Option<serde_json::Value>,
+    /// Middleware hooks run around generation and tool calls
+    hooks: AgentHooks,
+    /// How the user's stored memory is injected into the prompt
+    memory: AgentMemoryConfig,
+    /// Embeds messages and facts to rank memory by relevance
+    memory_embedder: Option<Arc<dyn BatchEmbedder>>,
+    /// RAG collection whose passages are injected each turn
+    rag: Option<AgentRagConfig>,
+    /// Searches the collection named by `rag`
+    knowledge: Option<Arc<dyn KnowledgeBase>>,
+    /// Limits checked before each tool call
+    tool_permissions: ToolPermissions,
+    /// Sampling seed the agent's LLM client was created with
+    seed: Option<u32>,
+    /// Prompt size limit of the agent's model, if it declares one
+    context_budget: Option<ContextBudget>,
+    /// Critique and revision of draft answers, if enabled
+    reflection: Option<Reflection>,
+    /// Resource limits of a single run
+    limits: RunLimits,
+    /// Whether every tool call needs a user's approval
+    requires_approval: bool,
+    /// Whether runs pause for approval instead of refusing such calls
+    approval_checkpoints: bool,
+    /// Events the agent publishes to and reads from a workflow's message bus
+    bus: AgentBusConfig,
+    /// Where tool-calling runs save their checkpoints, if anywhere
+    journal: Option<Arc<dyn RunJournal>>,
 }
 
 impl ConfigurableAgent {
@@ -62,6 +141,24 @@ impl ConfigurableAgent {
             allowed_tools: config.tools.clone(),
             max_tool_iterations: config.max_tool_iterations,
             parallel_tools: config.parallel_tools,
+            guardrails: None,
+            handoffs: config.handoffs.clone(),
+            strategy: config.strategy,
+            output_schema: config.output_schema.clone(),
+            hooks: AgentHooks::new(),
+            memory: config.memory.clone(),
+            memory_embedder: None,
+            rag: config.rag.clone(),
+            knowledge: None,
+            tool_permissions: ToolPermissions::new(&config.tool_permissions),
+            seed: None,
+            context_budget: None,
+            reflection: None,
+            limits: RunLimits::new(config.limits),
+            requires_approval: config.requires_approval,
+            approval_checkpoints: false,
+            bus: config.bus.clone(),
+            journal: None,
         }
     }
 
@@ -86,7 +183,182 @@ impl ConfigurableAgent {
             allowed_tools,
             max_tool_iterations,
             parallel_tools,
+            guardrails: None,
+            handoffs: Vec::new(),
+            strategy: AgentStrategy::Direct,
+            output_schema: None,
+            hooks: AgentHooks::new(),
+            memory: Default::default(),
+            memory_embedder: None,
+            rag: None,
+            knowledge: None,
+            tool_permissions: ToolPermissions::default(),
+            seed: None,
+            context_budget: None,
+            reflection: None,
+            limits: RunLimits::default(),
+            requires_approval: false,
+            approval_checkpoints: false,
+            bus: AgentBusConfig::default(),
+            journal: None,
+        }
+    }
+
+    /// Attach a guardrail pipeline to run around every generation
+    pub fn with_guardrails(mut self, guardrails: Arc<GuardrailPipeline>) -> Self {
+        self.guardrails = Some(guardrails);
+        self
+    }
+
+    /// Set the agents this agent may hand the conversation off to
+    pub fn with_handoffs(mut self, handoffs: Vec<String>) -> Self {
+        self.handoffs = handoffs;
+        self
+    }
+
+    /// Set how the agent works towards an answer
+    pub fn with_strategy(mut self, strategy: AgentStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Get how the agent works towards an answer
+    pub fn strategy(&self) -> AgentStrategy {
+        self.strategy
+    }
+
+    /// Require the agent's output to conform to a JSON Schema
+    pub fn with_output_schema(mut self, schema: serde_json::Value) -> Self {
+        self.output_schema = Some(schema);
+        self
+    }
+
+    /// Get the JSON Schema the agent's output must conform to
+    pub fn output_schema(&self) -> Option<&serde_json::Value> {
+        self.output_schema.as_ref()
+    }
+
+    /// Set the middleware hooks run around generation and tool calls
+    pub fn with_hooks(mut self, hooks: AgentHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Add a middleware hook; hooks run in the order they were added
+    pub fn with_hook(mut self, hook: Arc<dyn AgentHook>) -> Self {
+        self.hooks.register(hook);
+        self
+    }
+
+    /// Get the middleware hooks run around generation and tool calls
+    pub fn hooks(&self) -> &AgentHooks {
+        &self.hooks
+    }
+
+    /// Set how the user's stored memory is injected into the prompt
+    pub fn with_memory(mut self, memory: AgentMemoryConfig) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Rank memory facts by embedding similarity instead of word overlap
+    pub fn with_memory_embedder(mut self, embedder: Arc<dyn BatchEmbedder>) -> Self {
+        self.memory_embedder = Some(embedder);
+        self
+    }
+
+    /// Set the RAG collection whose passages are injected each turn
+    pub fn with_rag(mut self, rag: AgentRagConfig) -> Self {
+        self.rag = Some(rag);
+        self
+    }
+
+    /// Search the agent's RAG collection with `knowledge`; without one no
+    /// passages are injected
+    pub fn with_knowledge(mut self, knowledge: Arc<dyn KnowledgeBase>) -> Self {
+        self.knowledge = Some(knowledge);
+        self
+    }
+
+    /// Record the sampling seed the agent's LLM client was created with
+    pub fn with_seed(mut self, seed: Option<u32>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Fit prompts into a context window, keeping the whole conversation
+    /// history instead of the last five messages
+    pub fn with_context_budget(mut self, budget: ContextBudget) -> Self {
+        self.context_budget = Some(budget);
+        self
+    }
+
+    /// Critique and revise draft answers before returning them
+    ///
+    /// Not applied to agents with an output schema, whose replies are
+    /// validated instead.
+    pub fn with_reflection(mut self, reflection: Reflection) -> Self {
+        self.reflection = Some(reflection);
+        self
+    }
+
+    /// Limit the resources each run may use
+    pub fn with_limits(mut self, limits: RunLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Pause runs at tool calls that need a user's approval, instead of
+    /// refusing the calls
+    ///
+    /// Only the tool-calling loop pauses; ReAct agents still refuse.
+    pub fn with_approval_checkpoints(mut self, enabled: bool) -> Self {
+        self.approval_checkpoints = enabled;
+        self
+    }
+
+    /// Whether runs pause for approval
+    pub fn approval_checkpoints(&self) -> bool {
+        self.approval_checkpoints
+    }
+
+    /// Save the progress of tool-calling runs to `journal`, so a run cut off
+    /// by a restart can be resumed
+    ///
+    /// Only the tool-calling loop saves checkpoints.
+    pub fn with_journal(mut self, journal: Arc<dyn RunJournal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Where tool-calling runs save their checkpoints, if anywhere
+    pub fn journal(&self) -> Option<Arc<dyn RunJournal>> {
+        self.journal.clone()
+    }
+
+    /// Sampling seed of the agent's LLM client, if fixed
+    pub fn seed(&self) -> Option<u32> {
+        self.seed
+    }
+
+    /// Get how the user's stored memory is injected into the prompt
+    pub fn memory(&self) -> &AgentMemoryConfig {
+        &self.memory
+    }
+
+    /// Get the agents this agent may hand the conversation off to
+    pub fn handoffs(&self) -> &[String] {
+        &self.handoffs
+    }
+
+    /// Parse a handoff directive from this agent's output, if it made one
+    ///
+    /// Only agents in this agent's handoff allow-list are accepted.
+    pub fn take_handoff(&self, output: &str) -> Option<Handoff> {
+        if self.handoffs.is_empty() {
+            return None;
         }
+        handoff::parse(&self.name, output, &self.handoffs)
     }
 
     /// Convert agent name to AgentType
@@ -150,6 +422,11 @@ Handle employee info, policies, and benefits."#
         !self.allowed_tools.is_empty() && self.tool_registry.is_some()
     }
 
+    /// Get the guardrail pipeline (if any)
+    pub fn guardrails(&self) -> Option<&Arc<GuardrailPipeline>> {
+        self.guardrails.as_ref()
+    }
+
     /// Get the tool registry (if any)
     pub fn tool_registry(&self) -> Option<&Arc<ToolRegistry>> {
         self.tool_registry.as_ref()
@@ -184,16 +461,63 @@ Handle employee info, policies, and benefits."#
                 .map(|r| r.is_enabled(tool_name))
                 .unwrap_or(false)
     }
+
+    /// Check if a user must approve a call before it runs
+    pub fn needs_approval(&self, registry: &ToolRegistry, call: &ToolCall) -> bool {
+        self.requires_approval || registry.requires_approval(&call.name)
+    }
 }
 
-#[async_trait]
-impl Agent for ConfigurableAgent {
-    async fn execute(&self, input: &str, context: &AgentContext) -> Result<String> {
+impl ConfigurableAgent {
+    /// Build the prompt for a run: guardrailed input, memory, knowledge
+    /// passages, recent history and hooks
+    ///
+    /// Returns the prompt with the documents of the injected passages.
+    async fn prepare_messages(
+        &self,
+        input: &str,
+        context: &AgentContext,
+    ) -> Result<(Vec<(String, String)>, Vec<Source>)> {
+        // Run input guardrails (redaction may rewrite the input)
+        let input = match &self.guardrails {
+            Some(guardrails) => guardrails.process_input(input).await?,
+            None => input.to_string(),
+        };
+
         // Build context with conversation history if available
         let mut messages = vec![("system".to_string(), self.system_prompt.clone())];
 
+        // Tell the agent which agents it may hand off to
+        if !self.handoffs.is_empty() {
+            messages.push(("system".to_string(), handoff::instructions(&self.handoffs)));
+        }
+
+        // Tell the agent how to publish events and show it those it subscribes to
+        if let Some(bus) = &context.bus {
+            if !self.bus.publish.is_empty() {
+                messages.push(("system".to_string(), bus::instructions(&self.bus.publish)));
+            }
+            if !self.bus.subscribe.is_empty() {
+                let events = bus.events_for(&self.name, &self.bus);
+                if let Some(section) = bus::render(&events) {
+                    messages.push(("system".to_string(), section));
+                }
+            }
+        }
+
         // Add user memory if available
-        if let Some(memory) = &context.user_memory {
+        if let Some(user_memory) = context.user_memory.as_ref().filter(|_| self.memory.enabled) {
+            let facts = memory::select_facts(
+                user_memory,
+                &self.memory,
+                &input,
+                self.memory_embedder.as_deref(),
+            )
+            .await;
+            if let Some(section) = memory::render(user_memory, &facts) {
+                messages.push(("system".to_string(), section));
+            }
+        } else if let Some(memory) = &context.user_memory {
             let memory_context = format!(
                 "User preferences: {}",
                 memory
@@ -206,8 +530,30 @@ impl Agent for ConfigurableAgent {
             messages.push(("system".to_string(), memory_context));
         }
 
-        // Add recent conversation history (last 5 messages)
-        for msg in context.conversation_history.iter().rev().take(5).rev() {
+        // Ask for the user's preferred answer length and language
+        if let Some(instructions) = context.preferences.instructions() {
+            messages.push(("system".to_string(), instructions));
+        }
+
+        // Add the knowledge passages most relevant to the message
+        let mut sources = Vec::new();
+        if let (Some(rag), Some(knowledge)) = (&self.rag, &self.knowledge) {
+            let passages =
+                knowledge::retrieve(knowledge.as_ref(), rag, &context.owner(), &input).await;
+            if let Some(section) = knowledge::render(&passages) {
+                messages.push(("system".to_string(), section));
+            }
+            sources = attachments::sources(&passages);
+        }
+
+        // Add conversation history: all of it when the prompt is budgeted,
+        // otherwise the last 5 messages
+        let recent = match self.context_budget {
+            Some(_) => context.conversation_history.len(),
+            None => 5,
+        };
+        let history_start = messages.len();
+        for msg in context.conversation_history.iter().rev().take(recent).rev() {
             let role = match msg.role {
                 crate::types::MessageRole::User => "user",
                 crate::types::MessageRole::Assistant => "assistant",
@@ -216,9 +562,935 @@ impl Agent for ConfigurableAgent {
             messages.push((role.to_string(), msg.content.clone()));
         }
 
-        messages.push(("user".to_string(), input.to_string()));
+        let history = history_start..messages.len();
+
+        messages.push(("user".to_string(), input));
+
+        if let Some(budget) = &self.context_budget {
+            budget
+                .fit(&mut messages, history, self.llm.as_ref())
+                .await?;
+        }
+
+        context
+            .hooks
+            .before_llm(context, &self.name, &mut messages)
+            .await?;
+        self.hooks
+            .before_generation(context, &self.name, &mut messages)
+            .await?;
+
+        Ok((messages, sources))
+    }
+
+    /// Publish bus events, then run `after_generation` hooks and output
+    /// guardrails on the complete response
+    async fn finish_output(&self, mut output: String, context: &AgentContext) -> Result<String> {
+        if let Some(bus) = context
+            .bus
+            .as_ref()
+            .filter(|_| !self.bus.publish.is_empty())
+        {
+            let (stripped, events) = bus::take_events(&self.name, &output, &self.bus.publish);
+            for event in events {
+                bus.publish(event);
+            }
+            output = stripped;
+        }
+        self.hooks
+            .after_generation(context, &self.name, &mut output)
+            .await?;
+        match &self.guardrails {
+            Some(guardrails) => guardrails.process_output(&output).await,
+            None => Ok(output),
+        }
+    }
+
+    /// Generate a reply to `messages`, metered against the run's limits
+    ///
+    /// Returns `None` once a limit is reached.
+    async fn generate(
+        &self,
+        meter: &mut RunMeter,
+        messages: &[(String, String)],
+    ) -> Result<Option<String>> {
+        if !meter.allow_llm() {
+            return Ok(None);
+        }
+        let output = meter
+            .timed(self.llm.generate_with_history(messages))
+            .await?;
+        if let Some(output) = &output {
+            meter.record_llm(prompt_tokens(messages), estimate_tokens(output));
+        }
+        Ok(output)
+    }
+
+    /// Generate a reply that may call `tools`, metered against the run's limits
+    ///
+    /// Returns `None` once a limit is reached.
+    async fn generate_with_tools(
+        &self,
+        meter: &mut RunMeter,
+        history: &[ConversationMessage],
+        tools: &[ToolDefinition],
+    ) -> Result<Option<LLMResponse>> {
+        if !meter.allow_llm() {
+            return Ok(None);
+        }
+        let response = meter
+            .timed(self.llm.generate_with_tools_and_history(history, tools))
+            .await?;
+        if let Some(response) = &response {
+            let (input, output) = match &response.usage {
+                Some(usage) => (
+                    usage.prompt_tokens as usize,
+                    usage.completion_tokens as usize,
+                ),
+                None => (
+                    history.iter().map(|m| estimate_tokens(&m.content)).sum(),
+                    estimate_tokens(&response.content),
+                ),
+            };
+            meter.record_llm(input, output);
+        }
+        Ok(response)
+    }
+
+    /// Have the judge critique a draft, metered against the run's limits
+    ///
+    /// Returns `None` when the draft is approved or a limit is reached.
+    async fn critique(
+        &self,
+        reflection: &Reflection,
+        meter: &mut RunMeter,
+        question: &str,
+        draft: &str,
+    ) -> Result<Option<String>> {
+        if !meter.allow_llm() {
+            return Ok(None);
+        }
+        let Some(critique) = meter
+            .timed(reflection.critique(self.llm.as_ref(), question, draft))
+            .await?
+        else {
+            return Ok(None);
+        };
+        meter.record_llm(
+            estimate_tokens(question) + estimate_tokens(draft),
+            critique.as_deref().map_or(1, estimate_tokens),
+        );
+        Ok(critique)
+    }
+
+    /// Critique and revise a draft until the judge approves it or the
+    /// reflection rounds run out
+    ///
+    /// `messages` is the prompt the draft answers; each revision is appended
+    /// to it, and `extract` gets the answer text out of it.
+    async fn reflect(
+        &self,
+        meter: &mut RunMeter,
+        messages: &mut Vec<(String, String)>,
+        question: &str,
+        mut draft: String,
+        extract: fn(&str) -> String,
+    ) -> Result<String> {
+        let Some(reflection) = &self.reflection else {
+            return Ok(draft);
+        };
+        for round in 1..=reflection.max_rounds() {
+            let Some(critique) = self.critique(reflection, meter, question, &draft).await? else {
+                break;
+            };
+            tracing::debug!(
+                "Agent '{}' revising its answer (round {}): {}",
+                self.name,
+                round,
+                critique
+            );
+            // A ReAct reply holding the draft may already end the prompt
+            if !matches!(messages.last(), Some((role, _)) if role == "assistant") {
+                messages.push(("assistant".to_string(), draft.clone()));
+            }
+            messages.push(("user".to_string(), reflection::revision_request(&critique)));
+            let Some(output) = self.generate(meter, messages).await? else {
+                break;
+            };
+            draft = extract(&output);
+            messages.push(("assistant".to_string(), output));
+        }
+        Ok(draft)
+    }
+
+    /// [`reflect`](Self::reflect) for a tool-calling conversation
+    ///
+    /// Revisions are generated without tools, so they rework the answer with
+    /// the tool results already gathered.
+    async fn reflect_with_tools(
+        &self,
+        meter: &mut RunMeter,
+        history: &mut Vec<ConversationMessage>,
+        question: &str,
+        mut draft: String,
+    ) -> Result<String> {
+        let Some(reflection) = &self.reflection else {
+            return Ok(draft);
+        };
+        for round in 1..=reflection.max_rounds() {
+            let Some(critique) = self.critique(reflection, meter, question, &draft).await? else {
+                break;
+            };
+            tracing::debug!(
+                "Agent '{}' revising its answer (round {}): {}",
+                self.name,
+                round,
+                critique
+            );
+            // Out of tool iterations, the draft is not the last message yet
+            if !matches!(history.last(), Some(m) if m.role == MessageRole::Assistant) {
+                history.push(ConversationMessage::assistant(&draft, Vec::new()));
+            }
+            history.push(ConversationMessage::user(reflection::revision_request(
+                &critique,
+            )));
+            let Some(response) = self.generate_with_tools(meter, history, &[]).await? else {
+                break;
+            };
+            history.push(ConversationMessage::assistant(
+                &response.content,
+                Vec::new(),
+            ));
+            if !response.content.is_empty() {
+                draft = response.content;
+            }
+        }
+        Ok(draft)
+    }
+
+    /// Compile the agent's output schema, if it has one
+    fn compiled_output_schema(&self) -> Result<Option<OutputSchema>> {
+        self.output_schema
+            .clone()
+            .map(OutputSchema::new)
+            .transpose()
+    }
+
+    /// Generate until the output conforms to the agent's output schema
+    ///
+    /// Replies failing validation are returned to the model with the
+    /// validation errors, up to [`structured::MAX_ATTEMPTS`] generations.
+    /// There is no partial output to fall back on, so reaching a run limit
+    /// is an error.
+    async fn generate_structured(
+        &self,
+        schema: &OutputSchema,
+        mut messages: Vec<(String, String)>,
+        context: &AgentContext,
+    ) -> Result<String> {
+        let at = messages.len().saturating_sub(1);
+        messages.insert(at, ("system".to_string(), schema.instructions()));
+
+        let mut meter = self.limits.start();
+        let mut errors = String::new();
+        for _ in 0..structured::MAX_ATTEMPTS {
+            let output = if meter.allow_llm() {
+                meter
+                    .timed(self.llm.generate_structured(&messages, schema.schema()))
+                    .await?
+            } else {
+                None
+            };
+            let Some(output) = output else {
+                return Err(self.budget_exceeded(meter.finish()));
+            };
+            meter.record_llm(prompt_tokens(&messages), estimate_tokens(&output));
+            match schema.validate(&output) {
+                Ok(value) => return self.finish_output(value.to_string(), context).await,
+                Err(e) => {
+                    messages.push(("assistant".to_string(), output));
+                    messages.push(("user".to_string(), structured::retry_request(&e)));
+                    errors = e;
+                }
+            }
+        }
+
+        Err(AppError::LLM(format!(
+            "Agent '{}' output did not match its output schema after {} attempts: {}",
+            self.name,
+            structured::MAX_ATTEMPTS,
+            errors
+        )))
+    }
+
+    /// Error for a run stopped by a limit that has no partial output
+    fn budget_exceeded(&self, exceeded: Option<LimitExceeded>) -> AppError {
+        let reason = exceeded.map_or_else(|| "a run limit was reached".to_string(), |e| e.reason);
+        AppError::BudgetExceeded(format!("Agent '{}' stopped: {}", self.name, reason))
+    }
+
+    /// Run `before_tool` hooks on the model's tool calls
+    async fn prepare_tool_calls(
+        &self,
+        calls: &[ToolCall],
+        context: &AgentContext,
+    ) -> Result<Vec<ToolCall>> {
+        let mut calls = calls.to_vec();
+        for call in &mut calls {
+            self.hooks.before_tool(context, &self.name, call).await?;
+        }
+        Ok(calls)
+    }
+
+    /// Run the request's and the agent's hooks on a finished tool call
+    async fn finish_tool(&self, record: &mut ToolCallRecord, context: &AgentContext) -> Result<()> {
+        context.hooks.tool_result(context, record).await?;
+        self.hooks.after_tool(context, &self.name, record).await
+    }
+
+    /// Estimated cost of a finished tool call, for `max_cost`
+    fn tool_cost(&self, registry: &ToolRegistry, record: &ToolCallRecord) -> f64 {
+        permissions::call_access(registry, &record.name, &record.arguments).cost
+    }
+
+    /// Run a single tool call, turning failures into an unsuccessful record
+    ///
+    /// `decision` is the user's decision on this call, if it was paused for
+    /// approval; other calls that need approval are refused.
+    async fn run_tool(
+        &self,
+        registry: &ToolRegistry,
+        call: &ToolCall,
+        iteration: usize,
+        context: &AgentContext,
+        timeout: Duration,
+        decision: Option<&ApprovalDecision>,
+    ) -> ToolCallRecord {
+        let start = Instant::now();
+        if self.can_use_tool(&call.name) {
+            let refusal = context
+                .tool_profile
+                .as_ref()
+                .and_then(|profile| profile.check(registry, call))
+                .or_else(|| self.tool_permissions.check(registry, call))
+                .or_else(|| {
+                    (decision.is_some() || self.needs_approval(registry, call))
+                        .then(|| ApprovalDecision::refusal(decision, &call.name))
+                        .flatten()
+                });
+            if let Some(refusal) = refusal {
+                tracing::info!(
+                    "Agent '{}' refused tool call '{}': {}",
+                    self.name,
+                    call.name,
+                    refusal.reason
+                );
+                return ToolCallRecord {
+                    id: call.id.clone(),
+                    name: call.name.clone(),
+                    arguments: call.arguments.clone(),
+                    result: refusal.to_value(),
+                    success: false,
+                    duration_ms: start.elapsed().as_millis() as u64,
+                    error: Some(refusal.reason),
+                    iteration,
+                };
+            }
+        }
+        let result = if self.can_use_tool(&call.name) {
+            tokio::time::timeout(
+                timeout,
+                registry.execute(&call.name, call.arguments.clone()),
+            )
+            .await
+            .unwrap_or_else(|_| {
+                Err(crate::types::AppError::External(
+                    "Tool execution timed out".to_string(),
+                ))
+            })
+        } else {
+            Err(crate::types::AppError::InvalidInput(format!(
+                "Tool '{}' is not available to agent '{}'",
+                call.name, self.name
+            )))
+        };
+
+        let (result, success, error) = match result {
+            Ok(value) => (value, true, None),
+            Err(e) => (
+                serde_json::json!({"error": e.to_string()}),
+                false,
+                Some(e.to_string()),
+            ),
+        };
+        ToolCallRecord {
+            id: call.id.clone(),
+            name: call.name.clone(),
+            arguments: call.arguments.clone(),
+            result,
+            success,
+            duration_ms: start.elapsed().as_millis() as u64,
+            error,
+            iteration,
+        }
+    }
+
+    /// Run a round of tool calls, in parallel if the agent is configured to
+    ///
+    /// `approval` holds the user's decision and the IDs of the calls it
+    /// applies to, for a round that paused for approval.
+    async fn run_tools(
+        &self,
+        registry: &ToolRegistry,
+        calls: &[ToolCall],
+        iteration: usize,
+        context: &AgentContext,
+        timeout: Duration,
+        approval: Option<(&ApprovalDecision, &[String])>,
+    ) -> Vec<ToolCallRecord> {
+        let decision = |call: &ToolCall| {
+            approval
+                .filter(|(_, pending)| pending.contains(&call.id))
+                .map(|(decision, _)| decision)
+        };
+        if self.parallel_tools {
+            futures::future::join_all(calls.iter().map(|call| {
+                self.run_tool(registry, call, iteration, context, timeout, decision(call))
+            }))
+            .await
+        } else {
+            let mut records = Vec::with_capacity(calls.len());
+            for call in calls {
+                records.push(
+                    self.run_tool(registry, call, iteration, context, timeout, decision(call))
+                        .await,
+                );
+            }
+            records
+        }
+    }
+
+    /// Tool-calling loop emitting an event around every tool call
+    fn tool_event_stream<'a>(
+        &'a self,
+        registry: &'a ToolRegistry,
+        messages: Vec<(String, String)>,
+        context: &'a AgentContext,
+    ) -> AgentEventStream<'a> {
+        let question = reflection::question(&messages).to_string();
+        let history = messages
+            .into_iter()
+            .map(|(role, content)| ConversationMessage::from_role_content(&role, content))
+            .collect();
+        self.tool_loop(registry, question, history, None, context)
+    }
+
+    /// Tool-calling loop continuing from `history`
+    ///
+    /// A resumed run first runs its paused round of tool calls as the user
+    /// decided. The loop pauses again at the next round with a call that
+    /// needs approval, if the agent has approval checkpoints.
+    fn tool_loop<'a>(
+        &'a self,
+        registry: &'a ToolRegistry,
+        question: String,
+        mut history: Vec<ConversationMessage>,
+        resume: Option<(PausedRun, ApprovalDecision)>,
+        context: &'a AgentContext,
+    ) -> AgentEventStream<'a> {
+        Box::pin(async_stream::try_stream! {
+            let tools = self.get_filtered_tool_definitions();
+            let mut meter = self.limits.start();
+            let mut content = String::new();
+            let mut iteration = resume.as_ref().map_or(0, |(paused, _)| paused.iteration);
+            let mut round = resume
+                .map(|(paused, decision)| (paused.calls, Some((decision, paused.pending))));
+
+            loop {
+                if let Some((calls, approval)) = round.take() {
+                    self.checkpoint(&question, &history, iteration, &calls).await;
+                    let timeout = meter.time_left().map_or(TOOL_TIMEOUT, |left| left.min(TOOL_TIMEOUT));
+                    for call in &calls {
+                        yield AgentEvent::ToolCallStarted {
+                            id: call.id.clone(),
+                            name: call.name.clone(),
+                            arguments: call.arguments.clone(),
+                        };
+                    }
+                    let records = self
+                        .run_tools(
+                            registry,
+                            &calls,
+                            iteration,
+                            context,
+                            timeout,
+                            approval
+                                .as_ref()
+                                .map(|(decision, pending)| (decision, pending.as_slice())),
+                        )
+                        .await;
+                    for mut record in records {
+                        meter.record_tool(self.tool_cost(registry, &record));
+                        self.finish_tool(&mut record, context).await?;
+                        history.push(ConversationMessage::tool_result(&record.id, &record.result));
+                        yield AgentEvent::ToolCallFinished(record);
+                    }
+                    self.checkpoint(&question, &history, iteration, &[]).await;
+                }
+
+                iteration += 1;
+                if iteration > self.max_tool_iterations.max(1) {
+                    break;
+                }
+                let Some(response) = self.generate_with_tools(&mut meter, &history, &tools).await?
+                else {
+                    break;
+                };
+                history.push(ConversationMessage::assistant(
+                    &response.content,
+                    response.tool_calls.clone(),
+                ));
+                content = response.content;
+                if response.tool_calls.is_empty() || !meter.allow_tools(response.tool_calls.len()) {
+                    break;
+                }
+
+                let calls = self.prepare_tool_calls(&response.tool_calls, context).await?;
+                let pending: Vec<String> = calls
+                    .iter()
+                    .filter(|call| {
+                        self.can_use_tool(&call.name) && self.needs_approval(registry, call)
+                    })
+                    .map(|call| call.id.clone())
+                    .collect();
+                if self.approval_checkpoints && !pending.is_empty() {
+                    yield AgentEvent::ApprovalRequired(PausedRun {
+                        agent: self.name.clone(),
+                        question,
+                        history,
+                        iteration,
+                        calls,
+                        pending,
+                    });
+                    return;
+                }
+                round = Some((calls, None));
+            }
+
+            let content = self
+                .reflect_with_tools(&mut meter, &mut history, &question, content)
+                .await?;
+            if let Some(exceeded) = meter.finish() {
+                yield AgentEvent::LimitExceeded(exceeded);
+            }
+            let response = self.finish_output(content, context).await?;
+            if !response.is_empty() {
+                yield AgentEvent::Token { delta: response.clone() };
+            }
+            yield AgentEvent::Final { response, sources: Vec::new() };
+        })
+    }
+
+    /// Save a tool-calling run's progress to the agent's journal, if it keeps
+    /// one, before running `calls`
+    ///
+    /// A failed save is logged; the run carries on without it.
+    async fn checkpoint(
+        &self,
+        question: &str,
+        history: &[ConversationMessage],
+        iteration: usize,
+        calls: &[ToolCall],
+    ) {
+        let Some(journal) = &self.journal else {
+            return;
+        };
+        let checkpoint = PausedRun {
+            agent: self.name.clone(),
+            question: question.to_string(),
+            history: history.to_vec(),
+            iteration,
+            calls: calls.to_vec(),
+            pending: Vec::new(),
+        };
+        if let Err(e) = journal.save(&checkpoint).await {
+            tracing::warn!("Failed to checkpoint run of agent '{}': {}", self.name, e);
+        }
+    }
+
+    /// ReAct loop emitting an event for every Thought/Action/Observation step
+    fn react_event_stream<'a>(
+        &'a self,
+        mut messages: Vec<(String, String)>,
+        context: &'a AgentContext,
+    ) -> AgentEventStream<'a> {
+        Box::pin(async_stream::try_stream! {
+            let tools = if self.has_tools() {
+                self.get_filtered_tool_definitions()
+            } else {
+                Vec::new()
+            };
+            messages.insert(1, ("system".to_string(), react::instructions(&tools)));
+            let question = reflection::question(&messages).to_string();
+
+            let mut meter = self.limits.start();
+            let mut answer = None;
+            let mut last_thought = String::new();
+            for step in 0..self.max_tool_iterations.max(1) {
+                let Some(output) = self.generate(&mut meter, &messages).await? else {
+                    break;
+                };
+                messages.push((
+                    "assistant".to_string(),
+                    react::strip_observation(&output).to_string(),
+                ));
+
+                let (thought, action, input) = match react::parse(&output) {
+                    ReactReply::Finish { thought, answer: text } => {
+                        yield AgentEvent::ReactStep(ReactStep {
+                            thought,
+                            action: None,
+                            action_input: None,
+                            observation: None,
+                        });
+                        answer = Some(text);
+                        break;
+                    }
+                    ReactReply::Act { thought, action, input } => (thought, action, input),
+                };
+                last_thought.clone_from(&thought);
+                if !meter.allow_tools(1) {
+                    break;
+                }
+
+                let mut call = ToolCall {
+                    id: format!("react-{}", step + 1),
+                    name: action,
+                    arguments: input,
+                };
+                self.hooks.before_tool(context, &self.name, &mut call).await?;
+                let observation = match self.tool_registry.as_deref() {
+                    Some(registry) => {
+                        yield AgentEvent::ToolCallStarted {
+                            id: call.id.clone(),
+                            name: call.name.clone(),
+                            arguments: call.arguments.clone(),
+                        };
+                        let timeout = meter
+                            .time_left()
+                            .map_or(TOOL_TIMEOUT, |left| left.min(TOOL_TIMEOUT));
+                        let mut record = self
+                            .run_tool(registry, &call, step + 1, context, timeout, None)
+                            .await;
+                        meter.record_tool(self.tool_cost(registry, &record));
+                        self.finish_tool(&mut record, context).await?;
+                        let observation = record.result.to_string();
+                        yield AgentEvent::ToolCallFinished(record);
+                        observation
+                    }
+                    None => format!("Tool '{}' is not available to agent '{}'", call.name, self.name),
+                };
+                messages.push(("user".to_string(), react::observation(&observation)));
+
+                yield AgentEvent::ReactStep(ReactStep {
+                    thought,
+                    action: Some(call.name),
+                    action_input: Some(call.arguments),
+                    observation: Some(observation),
+                });
+            }
+
+            // Out of steps: ask for an answer with what the agent has so far.
+            // A run stopped by a limit ends with its latest thought instead.
+            let answer = match answer {
+                Some(answer) => answer,
+                None if meter.exceeded().is_some() => last_thought,
+                None => {
+                    messages.push(("user".to_string(), react::final_answer_request()));
+                    match self.generate(&mut meter, &messages).await? {
+                        Some(output) => react_answer(&output),
+                        None => last_thought,
+                    }
+                }
+            };
+            let answer = self
+                .reflect(&mut meter, &mut messages, &question, answer, react_answer)
+                .await?;
+            if let Some(exceeded) = meter.finish() {
+                yield AgentEvent::LimitExceeded(exceeded);
+            }
+
+            let response = self.finish_output(answer, context).await?;
+            if !response.is_empty() {
+                yield AgentEvent::Token { delta: response.clone() };
+            }
+            yield AgentEvent::Final { response, sources: Vec::new() };
+        })
+    }
+
+    /// Run the agent, returning its response with the ReAct steps it took
+    /// and the tool calls it made
+    ///
+    /// Agents using the `direct` strategy take no steps. Those with tools and
+    /// no output schema run the same tool-calling loop as
+    /// [`execute_stream`](Agent::execute_stream).
+    pub async fn execute_traced(&self, input: &str, context: &AgentContext) -> Result<AgentTrace> {
+        let registry = self
+            .tool_registry
+            .as_deref()
+            .filter(|_| self.has_tools() && self.output_schema.is_none());
+        let (messages, sources) = self.prepare_messages(input, context).await?;
+        let events = if !self.strategy.is_direct() {
+            self.react_event_stream(messages, context)
+        } else if let Some(registry) = registry {
+            self.tool_event_stream(registry, messages, context)
+        } else {
+            let (response, limit_exceeded) = self.generate_direct(messages, context).await?;
+            return Ok(AgentTrace {
+                response,
+                limit_exceeded,
+                sources,
+                ..Default::default()
+            });
+        };
+
+        let mut trace = collect_trace(events).await?;
+        trace.sources = sources;
+
+        // The final answer must still match the output schema
+        if let Some(schema) = self.compiled_output_schema()? {
+            if trace.limit_exceeded.is_some() {
+                return Err(self.budget_exceeded(trace.limit_exceeded));
+            }
+            trace.response = schema
+                .validate(&trace.response)
+                .map_err(|e| {
+                    AppError::LLM(format!(
+                        "Agent '{}' output did not match its output schema: {}",
+                        self.name, e
+                    ))
+                })?
+                .to_string();
+        }
+        Ok(trace)
+    }
+
+    /// Continue a run that paused for approval of its tool calls
+    ///
+    /// The paused round of calls runs as the user decided, then the
+    /// tool-calling loop carries on where it stopped.
+    pub async fn resume_traced(
+        &self,
+        paused: PausedRun,
+        decision: ApprovalDecision,
+        context: &AgentContext,
+    ) -> Result<AgentTrace> {
+        let registry = self
+            .tool_registry
+            .as_deref()
+            .filter(|_| self.has_tools())
+            .ok_or_else(|| {
+                AppError::InvalidInput(format!("Agent '{}' no longer has tools", self.name))
+            })?;
+        let question = paused.question.clone();
+        let history = paused.history.clone();
+        let events = self.tool_loop(
+            registry,
+            question,
+            history,
+            Some((paused, decision)),
+            context,
+        );
+        collect_trace(events).await
+    }
+
+    /// Answer with a single generation, revised by reflection
+    ///
+    /// Returns the answer with the limit that cut the run short, if one did.
+    async fn generate_direct(
+        &self,
+        mut messages: Vec<(String, String)>,
+        context: &AgentContext,
+    ) -> Result<(String, Option<LimitExceeded>)> {
+        if let Some(schema) = self.compiled_output_schema()? {
+            let output = self.generate_structured(&schema, messages, context).await?;
+            return Ok((output, None));
+        }
+        let mut meter = self.limits.start();
+        let output = self
+            .generate(&mut meter, &messages)
+            .await?
+            .unwrap_or_default();
+        let question = reflection::question(&messages).to_string();
+        let output = self
+            .reflect(&mut meter, &mut messages, &question, output, str::to_string)
+            .await?;
+        let output = self.finish_output(output, context).await?;
+        Ok((output, meter.finish()))
+    }
+
+    /// Run the agent and parse its output as JSON
+    ///
+    /// Intended for agents with an output schema, whose output is validated
+    /// JSON.
+    pub async fn execute_json(
+        &self,
+        input: &str,
+        context: &AgentContext,
+    ) -> Result<serde_json::Value> {
+        let output = self.execute(input, context).await?;
+        serde_json::from_str(structured::extract_json(&output))
+            .map_err(|e| AppError::LLM(format!("Agent '{}' did not return JSON: {}", self.name, e)))
+    }
+
+    /// Run the agent and deserialize its output into `T`
+    ///
+    /// `T` should match the agent's output schema so workflows can consume
+    /// the result directly.
+    pub async fn execute_typed<T: DeserializeOwned>(
+        &self,
+        input: &str,
+        context: &AgentContext,
+    ) -> Result<T> {
+        let value = self.execute_json(input, context).await?;
+        serde_json::from_value(value).map_err(|e| {
+            AppError::LLM(format!(
+                "Agent '{}' output is not a valid {}: {}",
+                self.name,
+                std::any::type_name::<T>(),
+                e
+            ))
+        })
+    }
+}
+
+/// Collect a run's events into its trace
+async fn collect_trace(mut events: AgentEventStream<'_>) -> Result<AgentTrace> {
+    let mut trace = AgentTrace::default();
+    while let Some(event) = events.next().await {
+        match event? {
+            AgentEvent::ReactStep(step) => trace.steps.push(step),
+            AgentEvent::ToolCallFinished(record) => trace.tool_calls.push(record),
+            AgentEvent::LimitExceeded(exceeded) => trace.limit_exceeded = Some(exceeded),
+            AgentEvent::ApprovalRequired(paused) => trace.paused = Some(paused),
+            AgentEvent::Final { response, .. } => trace.response = response,
+            _ => {}
+        }
+    }
+    Ok(trace)
+}
+
+/// Attach the documents of a run's knowledge passages to its final event
+fn with_sources(events: AgentEventStream<'_>, sources: Vec<Source>) -> AgentEventStream<'_> {
+    if sources.is_empty() {
+        return events;
+    }
+    Box::pin(events.map(move |event| match event {
+        Ok(AgentEvent::Final { response, .. }) => Ok(AgentEvent::Final {
+            response,
+            sources: sources.clone(),
+        }),
+        event => event,
+    }))
+}
+
+/// Estimated tokens in a prompt
+fn prompt_tokens(messages: &[(String, String)]) -> usize {
+    messages
+        .iter()
+        .map(|(_, content)| estimate_tokens(content))
+        .sum()
+}
+
+/// Answer text of a ReAct reply, whether or not it finished properly
+fn react_answer(output: &str) -> String {
+    match react::parse(output) {
+        ReactReply::Finish { answer, .. } => answer,
+        ReactReply::Act { .. } => react::strip_observation(output).to_string(),
+    }
+}
+
+#[async_trait]
+impl Agent for ConfigurableAgent {
+    async fn execute(&self, input: &str, context: &AgentContext) -> Result<String> {
+        if !self.strategy.is_direct() {
+            return Ok(self.execute_traced(input, context).await?.response);
+        }
+        let (messages, _) = self.prepare_messages(input, context).await?;
+        Ok(self.generate_direct(messages, context).await?.0)
+    }
+
+    async fn execute_stream<'a>(
+        &'a self,
+        input: &'a str,
+        context: &'a AgentContext,
+    ) -> Result<AgentEventStream<'a>> {
+        let (messages, sources) = self.prepare_messages(input, context).await?;
+
+        if !self.strategy.is_direct() {
+            return Ok(with_sources(
+                self.react_event_stream(messages, context),
+                sources,
+            ));
+        }
+
+        // Structured output is only known to be valid once complete
+        if let Some(schema) = self.compiled_output_schema()? {
+            let response = self.generate_structured(&schema, messages, context).await?;
+            return Ok(Box::pin(futures::stream::iter([
+                Ok(AgentEvent::Token {
+                    delta: response.clone(),
+                }),
+                Ok(AgentEvent::Final { response, sources }),
+            ])));
+        }
+
+        if let Some(registry) = self.tool_registry.as_deref().filter(|_| self.has_tools()) {
+            return Ok(with_sources(
+                self.tool_event_stream(registry, messages, context),
+                sources,
+            ));
+        }
+
+        // A reflected answer is only final once the judge is done with it
+        if self.reflection.is_some() {
+            let (response, exceeded) = self.generate_direct(messages, context).await?;
+            let events = exceeded.map(AgentEvent::LimitExceeded).into_iter().chain([
+                AgentEvent::Token {
+                    delta: response.clone(),
+                },
+                AgentEvent::Final { response, sources },
+            ]);
+            return Ok(Box::pin(futures::stream::iter(events.map(Ok))));
+        }
 
-        self.llm.generate_with_history(&messages).await
+        // Output guardrails only see the complete response, so nothing they
+        // might redact or block is streamed before they have
+        let live = self.guardrails.is_none();
+        let mut meter = self.limits.start();
+        let mut tokens = self.llm.stream_with_history(&messages).await?;
+        Ok(Box::pin(async_stream::try_stream! {
+            let mut output = String::new();
+            while let Some(delta) = meter.timed(async { Ok(tokens.next().await) }).await? {
+                let Some(delta) = delta else {
+                    break;
+                };
+                let delta = delta?;
+                output.push_str(&delta);
+                if live {
+                    yield AgentEvent::Token { delta };
+                }
+            }
+            meter.record_llm(prompt_tokens(&messages), estimate_tokens(&output));
+            if let Some(exceeded) = meter.finish() {
+                yield AgentEvent::LimitExceeded(exceeded);
+            }
+            let response = self.finish_output(output, context).await?;
+            if !live && !response.is_empty() {
+                yield AgentEvent::Token { delta: response.clone() };
+            }
+            yield AgentEvent::Final { response, sources };
+        }))
     }
 
     fn system_prompt(&self) -> String {
@@ -343,6 +1615,20 @@ mod tests {
             tools: vec!["calculator".to_string(), "web_search".to_string()],
             max_tool_iterations: 5,
             parallel_tools: false,
+            guardrail_policy: None,
+            handoffs: Vec::new(),
+            strategy: Default::default(),
+            output_schema: None,
+            memory: Default::default(),
+            tool_permissions: Default::default(),
+            answer_cache: Default::default(),
+            reflection: Default::default(),
+            limits: Default::default(),
+            personas: Default::default(),
+            requires_approval: false,
+            bus: Default::default(),
+            parameter_limits: Default::default(),
+            rag: None,
             extra: HashMap::new(),
         };
 
@@ -435,6 +1721,20 @@ mod tests {
             tools: vec!["calculator".to_string()],
             max_tool_iterations: 5,
             parallel_tools: false,
+            guardrail_policy: None,
+            handoffs: Vec::new(),
+            strategy: Default::default(),
+            output_schema: None,
+            memory: Default::default(),
+            tool_permissions: Default::default(),
+            answer_cache: Default::default(),
+            reflection: Default::default(),
+            limits: Default::default(),
+            personas: Default::default(),
+            requires_approval: false,
+            bus: Default::default(),
+            parameter_limits: Default::default(),
+            rag: None,
             extra: HashMap::new(),
         };
 
@@ -448,6 +1748,20 @@ mod tests {
             tools: vec![],
             max_tool_iterations: 5,
             parallel_tools: false,
+            guardrail_policy: None,
+            handoffs: Vec::new(),
+            strategy: Default::default(),
+            output_schema: None,
+            memory: Default::default(),
+            tool_permissions: Default::default(),
+            answer_cache: Default::default(),
+            reflection: Default::default(),
+            limits: Default::default(),
+            personas: Default::default(),
+            requires_approval: false,
+            bus: Default::default(),
+            parameter_limits: Default::default(),
+            rag: None,
             extra: HashMap::new(),
         };
 
@@ -459,4 +1773,663 @@ mod tests {
         );
         assert!(!agent_empty.has_tools()); // Empty tools list
     }
+
+    /// Streams "Hel", "lo" and asks for one calculator call before answering
+    struct ScriptedLLM {
+        tool_rounds: std::sync::atomic::AtomicUsize,
+        /// Replies to `generate_with_history`, in order ("Hello" once exhausted)
+        replies: parking_lot::Mutex<std::collections::VecDeque<String>>,
+    }
+
+    #[async_trait]
+    impl LLMClient for ScriptedLLM {
+        async fn generate(&self, _: &str) -> Result<String> {
+            Ok("Hello".to_string())
+        }
+        async fn generate_with_system(&self, _: &str, _: &str) -> Result<String> {
+            Ok("Hello".to_string())
+        }
+        async fn generate_with_history(&self, _: &[(String, String)]) -> Result<String> {
+            Ok(self
+                .replies
+                .lock()
+                .pop_front()
+                .unwrap_or_else(|| "Hello".to_string()))
+        }
+        async fn generate_with_tools(
+            &self,
+            _: &str,
+            _: &[ToolDefinition],
+        ) -> Result<crate::llm::LLMResponse> {
+            unimplemented!()
+        }
+        async fn stream(
+            &self,
+            _: &str,
+        ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+            unimplemented!()
+        }
+        async fn stream_with_system(
+            &self,
+            _: &str,
+            _: &str,
+        ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+            unimplemented!()
+        }
+        async fn stream_with_history(
+            &self,
+            _: &[(String, String)],
+        ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+            Ok(Box::new(futures::stream::iter(vec![
+                Ok("Hel".to_string()),
+                Ok("lo".to_string()),
+            ])))
+        }
+        fn model_name(&self) -> &str {
+            "scripted"
+        }
+        async fn generate_with_tools_and_history(
+            &self,
+            _: &[crate::llm::coordinator::ConversationMessage],
+            _: &[ToolDefinition],
+        ) -> Result<crate::llm::LLMResponse> {
+            let round = self
+                .tool_rounds
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let tool_calls = if round == 0 {
+                vec![ToolCall {
+                    id: "call-1".to_string(),
+                    name: "calculator".to_string(),
+                    arguments: serde_json::json!({"operation": "add", "a": 2, "b": 3}),
+                }]
+            } else {
+                vec![]
+            };
+            Ok(crate::llm::LLMResponse {
+                content: if round == 0 { "" } else { "It is 5" }.to_string(),
+                tool_calls,
+                finish_reason: "stop".to_string(),
+                usage: None,
+            })
+        }
+    }
+
+    fn scripted_agent(
+        tools: Vec<String>,
+        registry: Option<Arc<ToolRegistry>>,
+    ) -> ConfigurableAgent {
+        let config = AgentConfig {
+            model: "default".to_string(),
+            system_prompt: None,
+            tools,
+            max_tool_iterations: 5,
+            parallel_tools: false,
+            guardrail_policy: None,
+            handoffs: Vec::new(),
+            strategy: Default::default(),
+            output_schema: None,
+            memory: Default::default(),
+            tool_permissions: Default::default(),
+            answer_cache: Default::default(),
+            reflection: Default::default(),
+            limits: Default::default(),
+            personas: Default::default(),
+            requires_approval: false,
+            bus: Default::default(),
+            parameter_limits: Default::default(),
+            rag: None,
+            extra: std::collections::HashMap::new(),
+        };
+        let llm = ScriptedLLM {
+            tool_rounds: std::sync::atomic::AtomicUsize::new(0),
+            replies: Default::default(),
+        };
+        ConfigurableAgent::new("product", &config, Box::new(llm), registry)
+    }
+
+    fn test_context() -> AgentContext {
+        AgentContext {
+            user_id: "user".to_string(),
+            session_id: "session".to_string(),
+            conversation_history: vec![],
+            user_memory: None,
+            cancellation: Default::default(),
+            hooks: Default::default(),
+            preferences: Default::default(),
+            tool_profile: None,
+            bus: None,
+            workspace_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_stream_emits_tokens_then_final() {
+        let agent = scripted_agent(vec![], None);
+        let context = test_context();
+
+        let events: Vec<AgentEvent> = agent
+            .execute_stream("hi", &context)
+            .await
+            .unwrap()
+            .map(|e| e.unwrap())
+            .collect()
+            .await;
+
+        assert!(matches!(&events[0], AgentEvent::Token { delta } if delta == "Hel"));
+        assert!(matches!(&events[1], AgentEvent::Token { delta } if delta == "lo"));
+        assert!(matches!(&events[2], AgentEvent::Final { response, .. } if response == "Hello"));
+        assert_eq!(events.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_execute_stream_emits_only_guardrailed_output() {
+        let redactor = crate::llm::guardrails::PiiRedactor::new()
+            .with_pattern("greeting", "Hello")
+            .unwrap();
+        let guardrails = GuardrailPipeline::new().with_guardrail(Arc::new(redactor));
+        let agent = scripted_agent(vec![], None).with_guardrails(Arc::new(guardrails));
+        let context = test_context();
+
+        let events: Vec<AgentEvent> = agent
+            .execute_stream("hi", &context)
+            .await
+            .unwrap()
+            .map(|e| e.unwrap())
+            .collect()
+            .await;
+
+        assert!(
+            matches!(&events[0], AgentEvent::Token { delta } if delta == "[REDACTED_GREETING]")
+        );
+        assert!(
+            matches!(&events[1], AgentEvent::Final { response, .. } if response == "[REDACTED_GREETING]")
+        );
+        assert_eq!(events.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_execute_stream_reports_tool_calls() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(crate::tools::calculator::Calculator));
+        let agent = scripted_agent(vec!["calculator".to_string()], Some(Arc::new(registry)));
+        let context = test_context();
+
+        let events: Vec<AgentEvent> = agent
+            .execute_stream("what is 2 + 3?", &context)
+            .await
+            .unwrap()
+            .map(|e| e.unwrap())
+            .collect()
+            .await;
+
+        assert!(
+            matches!(&events[0], AgentEvent::ToolCallStarted { name, .. } if name == "calculator")
+        );
+        match &events[1] {
+            AgentEvent::ToolCallFinished(record) => {
+                assert!(record.success);
+                assert_eq!(record.result["result"], 5.0);
+            }
+            other => panic!("expected ToolCallFinished, got {:?}", other),
+        }
+        assert!(matches!(&events[2], AgentEvent::Token { delta } if delta == "It is 5"));
+        assert!(matches!(&events[3], AgentEvent::Final { response, .. } if response == "It is 5"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_traced_records_tool_calls() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(crate::tools::calculator::Calculator));
+        let agent = scripted_agent(vec!["calculator".to_string()], Some(Arc::new(registry)));
+
+        let trace = agent
+            .execute_traced("what is 2 + 3?", &test_context())
+            .await
+            .unwrap();
+
+        assert_eq!(trace.response, "It is 5");
+        assert!(trace.steps.is_empty());
+        assert_eq!(trace.tool_calls.len(), 1);
+        let record = &trace.tool_calls[0];
+        assert_eq!(record.name, "calculator");
+        assert_eq!(record.arguments["a"], 2);
+        assert_eq!(record.result["result"], 5.0);
+        assert_eq!(record.iteration, 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_limit_stops_with_partial_output() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(crate::tools::calculator::Calculator));
+        let limits = RunLimits::new(crate::utils::toml_config::RunLimitsConfig {
+            max_llm_calls: Some(1),
+            ..Default::default()
+        });
+        let agent = scripted_agent(vec!["calculator".to_string()], Some(Arc::new(registry)))
+            .with_limits(limits);
+
+        let trace = agent
+            .execute_traced("what is 2 + 3?", &test_context())
+            .await
+            .unwrap();
+
+        // The tool call from the first generation still runs
+        assert_eq!(trace.tool_calls.len(), 1);
+        assert_eq!(trace.response, "");
+        let exceeded = trace.limit_exceeded.unwrap();
+        assert_eq!(exceeded.limit, crate::agents::limits::RunLimit::MaxLlmCalls);
+        assert_eq!(exceeded.usage.llm_calls, 1);
+        assert_eq!(exceeded.usage.tool_calls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_approval_checkpoint_pauses_and_resumes() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(crate::tools::calculator::Calculator));
+        let registry = Arc::new(registry);
+        let mut agent = scripted_agent(vec!["calculator".to_string()], Some(registry.clone()))
+            .with_approval_checkpoints(true);
+        agent.requires_approval = true;
+
+        let trace = agent
+            .execute_traced("what is 2 + 3?", &test_context())
+            .await
+            .unwrap();
+        // Paused before the call ran
+        assert!(trace.tool_calls.is_empty());
+        let paused = trace.paused.unwrap();
+        assert_eq!(paused.pending, vec!["call-1".to_string()]);
+        assert_eq!(paused.iteration, 1);
+
+        let resumed = agent
+            .resume_traced(paused.clone(), ApprovalDecision::Approve, &test_context())
+            .await
+            .unwrap();
+        assert!(resumed.paused.is_none());
+        assert_eq!(resumed.response, "It is 5");
+        assert!(resumed.tool_calls[0].success);
+
+        // A rejected call is refused and the model answers without it
+        let agent = scripted_agent(vec!["calculator".to_string()], Some(registry))
+            .with_approval_checkpoints(true);
+        let rejected = agent
+            .resume_traced(paused, ApprovalDecision::Reject(None), &test_context())
+            .await
+            .unwrap();
+        assert!(!rejected.tool_calls[0].success);
+        assert!(rejected.tool_calls[0]
+            .result
+            .to_string()
+            .contains("rejected"));
+    }
+
+    /// Rewrites calculator input, redacts tool results and tags the output
+    struct Rewrite;
+
+    #[async_trait]
+    impl AgentHook for Rewrite {
+        fn name(&self) -> &str {
+            "rewrite"
+        }
+
+        async fn before_generation(
+            &self,
+            _ctx: &AgentContext,
+            _agent: &str,
+            messages: &mut Vec<(String, String)>,
+        ) -> Result<()> {
+            if let Some((_, content)) = messages.last_mut() {
+                *content = content.to_uppercase();
+            }
+            Ok(())
+        }
+
+        async fn before_tool(
+            &self,
+            _ctx: &AgentContext,
+            _agent: &str,
+            call: &mut ToolCall,
+        ) -> Result<()> {
+            call.arguments["a"] = serde_json::json!(20);
+            Ok(())
+        }
+
+        async fn after_tool(
+            &self,
+            _ctx: &AgentContext,
+            _agent: &str,
+            record: &mut ToolCallRecord,
+        ) -> Result<()> {
+            record.result["checked"] = serde_json::json!(true);
+            Ok(())
+        }
+
+        async fn after_generation(
+            &self,
+            _ctx: &AgentContext,
+            agent: &str,
+            output: &mut String,
+        ) -> Result<()> {
+            output.push_str(&format!(" [{}]", agent));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_agent_hooks_run_around_generation_and_tools() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(crate::tools::calculator::Calculator));
+        let agent = scripted_agent(vec!["calculator".to_string()], Some(Arc::new(registry)))
+            .with_hook(Arc::new(Rewrite));
+        let context = test_context();
+
+        let (messages, _) = agent.prepare_messages("hi", &context).await.unwrap();
+        assert_eq!(messages.last().unwrap().1, "HI");
+
+        let events: Vec<AgentEvent> = agent
+            .execute_stream("what is 2 + 3?", &context)
+            .await
+            .unwrap()
+            .map(|e| e.unwrap())
+            .collect()
+            .await;
+
+        assert!(matches!(
+            &events[0],
+            AgentEvent::ToolCallStarted { arguments, .. } if arguments["a"] == 20
+        ));
+        match &events[1] {
+            AgentEvent::ToolCallFinished(record) => {
+                assert_eq!(record.result["result"], 23.0);
+                assert_eq!(record.result["checked"], true);
+            }
+            other => panic!("expected ToolCallFinished, got {:?}", other),
+        }
+        assert!(
+            matches!(&events[3], AgentEvent::Final { response, .. } if response == "It is 5 [product]")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_react_strategy_records_steps() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(crate::tools::calculator::Calculator));
+        let config = AgentConfig {
+            model: "default".to_string(),
+            system_prompt: None,
+            tools: vec!["calculator".to_string()],
+            max_tool_iterations: 5,
+            parallel_tools: false,
+            guardrail_policy: None,
+            handoffs: Vec::new(),
+            strategy: AgentStrategy::React,
+            output_schema: None,
+            memory: Default::default(),
+            tool_permissions: Default::default(),
+            answer_cache: Default::default(),
+            reflection: Default::default(),
+            limits: Default::default(),
+            personas: Default::default(),
+            requires_approval: false,
+            bus: Default::default(),
+            parameter_limits: Default::default(),
+            rag: None,
+            extra: std::collections::HashMap::new(),
+        };
+        let llm = ScriptedLLM {
+            tool_rounds: std::sync::atomic::AtomicUsize::new(0),
+            replies: parking_lot::Mutex::new(
+                [
+                    "Thought: I need to add.\nAction: calculator\nAction Input: {\"operation\": \"add\", \"a\": 2, \"b\": 3}",
+                    "Thought: I know the answer.\nFinal Answer: It is 5",
+                ]
+                .map(String::from)
+                .into(),
+            ),
+        };
+        let agent =
+            ConfigurableAgent::new("product", &config, Box::new(llm), Some(Arc::new(registry)));
+
+        let trace = agent
+            .execute_traced("what is 2 + 3?", &test_context())
+            .await
+            .unwrap();
+
+        assert_eq!(trace.response, "It is 5");
+        let steps = &trace.steps;
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].thought, "I need to add.");
+        assert_eq!(steps[0].action.as_deref(), Some("calculator"));
+        assert!(steps[0].observation.as_deref().unwrap().contains('5'));
+        assert_eq!(steps[1].thought, "I know the answer.");
+        assert!(steps[1].action.is_none());
+        assert_eq!(trace.tool_calls.len(), 1);
+        assert_eq!(trace.tool_calls[0].name, "calculator");
+        assert_eq!(trace.tool_calls[0].iteration, 1);
+    }
+
+    #[tokio::test]
+    async fn test_output_schema_retries_until_valid() {
+        #[derive(serde::Deserialize)]
+        struct Label {
+            label: String,
+        }
+
+        let llm = ScriptedLLM {
+            tool_rounds: std::sync::atomic::AtomicUsize::new(0),
+            replies: parking_lot::Mutex::new(
+                ["It's a bug", "```json\n{\"label\": \"bug\"}\n```"]
+                    .map(String::from)
+                    .into(),
+            ),
+        };
+        let agent = ConfigurableAgent::new(
+            "product",
+            &AgentConfig {
+                model: "default".to_string(),
+                system_prompt: None,
+                tools: vec![],
+                max_tool_iterations: 5,
+                parallel_tools: false,
+                guardrail_policy: None,
+                handoffs: Vec::new(),
+                strategy: Default::default(),
+                output_schema: None,
+                memory: Default::default(),
+                tool_permissions: Default::default(),
+                answer_cache: Default::default(),
+                reflection: Default::default(),
+                limits: Default::default(),
+                personas: Default::default(),
+                requires_approval: false,
+                bus: Default::default(),
+                parameter_limits: Default::default(),
+                rag: None,
+                extra: std::collections::HashMap::new(),
+            },
+            Box::new(llm),
+            None,
+        )
+        .with_output_schema(serde_json::json!({
+            "type": "object",
+            "required": ["label"],
+            "properties": {"label": {"type": "string"}}
+        }));
+
+        let label: Label = agent
+            .execute_typed("classify this", &test_context())
+            .await
+            .unwrap();
+        assert_eq!(label.label, "bug");
+
+        // Replies keep failing validation once the script runs out
+        let err = agent.execute("classify this", &test_context()).await;
+        assert!(matches!(err, Err(AppError::LLM(_))));
+    }
+
+    #[tokio::test]
+    async fn test_reflection_revises_until_approved() {
+        let scripted = |replies: &[&str]| ScriptedLLM {
+            tool_rounds: std::sync::atomic::AtomicUsize::new(0),
+            replies: parking_lot::Mutex::new(replies.iter().map(|r| r.to_string()).collect()),
+        };
+
+        // The agent's own model drafts, critiques and revises
+        let agent = scripted_agent(vec![], None).with_reflection(Reflection::new(3));
+        let agent = ConfigurableAgent {
+            llm: Box::new(scripted(&["Draft", "- Too short", "Revised", "APPROVED"])),
+            ..agent
+        };
+        let response = agent.execute("Explain", &test_context()).await.unwrap();
+        assert_eq!(response, "Revised");
+
+        // A judge that approves leaves the draft alone, even when streaming
+        let agent = ConfigurableAgent {
+            llm: Box::new(scripted(&["Draft"])),
+            ..scripted_agent(vec![], None)
+        }
+        .with_reflection(Reflection::new(2).with_judge(Box::new(scripted(&["APPROVED"]))));
+        let events: Vec<AgentEvent> = agent
+            .execute_stream("Explain", &test_context())
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        assert!(matches!(events.as_slice(), [
+            AgentEvent::Token { delta },
+            AgentEvent::Final { response, .. },
+        ] if delta == "Draft" && response == "Draft"));
+    }
+
+    #[tokio::test]
+    async fn test_bus_events_published_and_shown_to_subscribers() {
+        use crate::utils::toml_config::BusEventKind;
+
+        let reply = "Prices rose 5%.\n\
+            PUBLISH: {\"kind\": \"finding\", \"topic\": \"pricing\", \"content\": \"Prices rose 5%\"}";
+        let mut researcher = ConfigurableAgent {
+            llm: Box::new(ScriptedLLM {
+                tool_rounds: std::sync::atomic::AtomicUsize::new(0),
+                replies: parking_lot::Mutex::new([reply.to_string()].into()),
+            }),
+            ..scripted_agent(vec![], None)
+        };
+        researcher.name = "research".to_string();
+        researcher.bus.publish = vec![BusEventKind::Finding];
+        let mut writer = scripted_agent(vec![], None);
+        writer.bus.subscribe = vec![BusEventKind::Finding];
+
+        // Without a bus the directive is left alone
+        let response = researcher
+            .execute("Prices?", &test_context())
+            .await
+            .unwrap();
+        assert!(response.contains("PUBLISH:"));
+
+        let bus = Arc::new(bus::AgentBus::new());
+        let context = AgentContext {
+            bus: Some(bus.clone()),
+            workspace_id: None,
+            ..test_context()
+        };
+        researcher.llm = Box::new(ScriptedLLM {
+            tool_rounds: std::sync::atomic::AtomicUsize::new(0),
+            replies: parking_lot::Mutex::new([reply.to_string()].into()),
+        });
+        let response = researcher.execute("Prices?", &context).await.unwrap();
+        assert_eq!(response, "Prices rose 5%.");
+        assert_eq!(bus.events().len(), 1);
+        assert_eq!(bus.events()[0].from, "research");
+
+        let (messages, _) = writer
+            .prepare_messages("Write it up", &context)
+            .await
+            .unwrap();
+        assert!(messages
+            .iter()
+            .any(|(_, content)| content.contains("[finding] research on pricing: Prices rose 5%")));
+    }
+
+    /// Knowledge base with one handbook passage, recording who searched
+    #[derive(Default)]
+    struct Handbook {
+        owners: parking_lot::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl KnowledgeBase for Handbook {
+        async fn search(
+            &self,
+            owner: &str,
+            collection: &str,
+            _query: &str,
+            _top_k: usize,
+            _min_score: f32,
+        ) -> Result<Vec<crate::rag::attachments::Passage>> {
+            assert_eq!(collection, "handbook");
+            self.owners.lock().push(owner.to_string());
+            Ok(vec![crate::rag::attachments::Passage {
+                filename: "leave.md".to_string(),
+                content: "Staff get 25 days of leave.".to_string(),
+                score: 0.9,
+            }])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rag_passages_injected_with_citations_and_sources() {
+        let handbook = Arc::new(Handbook::default());
+        let agent = scripted_agent(vec![], None)
+            .with_rag(AgentRagConfig {
+                collection: "handbook".to_string(),
+                top_k: 4,
+                min_score: 0.0,
+            })
+            .with_knowledge(handbook.clone());
+        let context = test_context();
+
+        let (messages, sources) = agent
+            .prepare_messages("How much leave do I get?", &context)
+            .await
+            .unwrap();
+        assert!(messages.iter().any(|(role, content)| role == "system"
+            && content.contains("[1] leave.md\nStaff get 25 days of leave.")));
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].title, "leave.md");
+
+        let trace = agent
+            .execute_traced("How much leave do I get?", &context)
+            .await
+            .unwrap();
+        assert_eq!(trace.sources.len(), 1);
+        assert_eq!(trace.sources[0].title, "leave.md");
+
+        // Streamed runs carry them on their final event
+        let events: Vec<AgentEvent> = agent
+            .execute_stream("How much leave do I get?", &context)
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        assert!(matches!(
+            events.last(),
+            Some(AgentEvent::Final { sources, .. })
+                if sources.len() == 1 && sources[0].title == "leave.md"
+        ));
+        assert_eq!(*handbook.owners.lock(), vec!["user", "user", "user"]);
+
+        // Without a knowledge base nothing is retrieved
+        let (_, sources) = scripted_agent(vec![], None)
+            .with_rag(AgentRagConfig {
+                collection: "handbook".to_string(),
+                top_k: 4,
+                min_score: 0.0,
+            })
+            .prepare_messages("How much leave do I get?", &context)
+            .await
+            .unwrap();
+        assert!(sources.is_empty());
+    }
 }
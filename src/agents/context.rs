@@ -0,0 +1,332 @@
+//! Context window budgeting.
+//!
+//! Agents whose model declares a `context_window` measure every prompt
+//! before sending it: system prompt, memory and other context, conversation
+//! history and the new message, with the model's `max_tokens` kept free for
+//! the reply. A prompt that doesn't fit loses its oldest conversation turns,
+//! which are replaced by a short summary unless `summarize_overflow = false`.
+//! Context that still doesn't fit is cut short, so a long conversation never
+//! fails at the provider.
+//!
+//! ```toml
+//! [models.balanced]
+//! provider = "ollama-local"
+//! model = "ministral-3:3b"
+//! max_tokens = 512
+//! context_window = 8192
+//! ```
+//!
+//! Token counts are estimated with [`estimate_tokens`].
+
+use crate::llm::LLMClient;
+use crate::memory::estimate_tokens;
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::ModelConfig;
+use std::ops::Range;
+
+/// Tokens counted per message for role markers and separators
+const MESSAGE_OVERHEAD: usize = 4;
+
+/// Largest summary of dropped turns, in tokens
+const MAX_SUMMARY_TOKENS: usize = 512;
+
+/// Summaries with less room than this are not worth generating
+const MIN_SUMMARY_TOKENS: usize = 32;
+
+/// Appended to context that was cut short
+const TRUNCATION_MARKER: &str = "\n[truncated]";
+
+/// Instructions for summarizing dropped turns
+const SUMMARY_INSTRUCTIONS: &str = "Summarize this earlier part of a conversation in a few \
+sentences. Keep names, numbers, decisions and open questions; leave out pleasantries.";
+
+/// Prompt size limit derived from a model's context window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextBudget {
+    window: usize,
+    reserve: usize,
+    summarize: bool,
+}
+
+impl ContextBudget {
+    /// Budget for a `window`-token context, keeping `reserve` tokens free
+    /// for the reply
+    pub fn new(window: usize, reserve: usize) -> Self {
+        Self {
+            window,
+            reserve,
+            summarize: true,
+        }
+    }
+
+    /// Budget for a model, if it declares a context window
+    pub fn from_model(config: &ModelConfig) -> Option<Self> {
+        let window = config.context_window?;
+        Some(
+            Self::new(window as usize, config.max_tokens as usize)
+                .with_summaries(config.summarize_overflow),
+        )
+    }
+
+    /// Whether dropped turns are summarized (default: true)
+    pub fn with_summaries(mut self, summarize: bool) -> Self {
+        self.summarize = summarize;
+        self
+    }
+
+    /// Tokens available for the prompt
+    pub fn prompt_tokens(&self) -> usize {
+        self.window.saturating_sub(self.reserve)
+    }
+
+    /// Fit a prompt into the budget
+    ///
+    /// `history` is the range of `messages` holding earlier conversation
+    /// turns, oldest first. The first message (the system prompt) and the
+    /// last (the new message) are never shortened; when they alone exceed
+    /// the budget the request is refused.
+    pub async fn fit(
+        &self,
+        messages: &mut Vec<(String, String)>,
+        history: Range<usize>,
+        llm: &dyn LLMClient,
+    ) -> Result<()> {
+        let budget = self.prompt_tokens();
+        if total_tokens(messages) <= budget {
+            return Ok(());
+        }
+
+        // Drop the oldest turns, leaving room for their summary
+        let allowance = if self.summarize {
+            (budget / 8).min(MAX_SUMMARY_TOKENS)
+        } else {
+            0
+        };
+        let mut dropped = Vec::new();
+        while dropped.len() < history.len() && total_tokens(messages) > budget - allowance {
+            dropped.push(messages.remove(history.start));
+        }
+
+        let room = budget.saturating_sub(total_tokens(messages) + MESSAGE_OVERHEAD);
+        if self.summarize && !dropped.is_empty() && room >= MIN_SUMMARY_TOKENS {
+            match summarize(&dropped, budget, llm).await {
+                Ok(summary) if !summary.trim().is_empty() => {
+                    let summary =
+                        format!("Summary of the earlier conversation:\n{}", summary.trim());
+                    messages.insert(
+                        history.start,
+                        ("system".to_string(), truncate(&summary, room)),
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Dropping earlier turns without a summary: {}", e),
+            }
+        }
+        if !dropped.is_empty() {
+            tracing::debug!(
+                "Dropped {} earlier turns to fit a {}-token context window",
+                dropped.len(),
+                self.window
+            );
+        }
+
+        // Cut the longest remaining context until the prompt fits
+        while total_tokens(messages) > budget {
+            let excess = total_tokens(messages) - budget;
+            let longest = (1..messages.len().saturating_sub(1))
+                .filter(|&i| !messages[i].1.is_empty())
+                .max_by_key(|&i| estimate_tokens(&messages[i].1));
+            let Some(i) = longest else {
+                return Err(AppError::InvalidInput(format!(
+                    "Message is too long for the model's context window ({} tokens with the \
+                     system prompt, {} available)",
+                    total_tokens(messages),
+                    budget
+                )));
+            };
+            let keep = estimate_tokens(&messages[i].1).saturating_sub(excess);
+            messages[i].1 = truncate(&messages[i].1, keep);
+        }
+        Ok(())
+    }
+}
+
+/// Estimated tokens of a prompt
+fn total_tokens(messages: &[(String, String)]) -> usize {
+    messages
+        .iter()
+        .map(|(_, content)| estimate_tokens(content) + MESSAGE_OVERHEAD)
+        .sum()
+}
+
+/// Summarize dropped turns with the agent's model
+async fn summarize(
+    turns: &[(String, String)],
+    budget: usize,
+    llm: &dyn LLMClient,
+) -> Result<String> {
+    let transcript = turns
+        .iter()
+        .map(|(role, content)| format!("{}: {}", role, content))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    // The summary request must fit the context window too
+    let room = budget.saturating_sub(estimate_tokens(SUMMARY_INSTRUCTIONS) + 2 * MESSAGE_OVERHEAD);
+    llm.generate_with_history(&[
+        ("system".to_string(), SUMMARY_INSTRUCTIONS.to_string()),
+        ("user".to_string(), truncate(&transcript, room)),
+    ])
+    .await
+}
+
+/// Shorten text to about `tokens` tokens, marker included
+///
+/// Returns an empty string when not even the marker fits.
+fn truncate(text: &str, tokens: usize) -> String {
+    if estimate_tokens(text) <= tokens {
+        return text.to_string();
+    }
+    let Some(bytes) = (tokens * 4).checked_sub(TRUNCATION_MARKER.len()) else {
+        return String::new();
+    };
+    let mut end = bytes.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", &text[..end], TRUNCATION_MARKER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::LLMResponse;
+    use crate::types::ToolDefinition;
+    use async_trait::async_trait;
+
+    /// Summarizes anything as a fixed sentence, or fails
+    struct Summarizer(Option<&'static str>);
+
+    #[async_trait]
+    impl LLMClient for Summarizer {
+        async fn generate(&self, _: &str) -> Result<String> {
+            unimplemented!()
+        }
+        async fn generate_with_system(&self, _: &str, _: &str) -> Result<String> {
+            unimplemented!()
+        }
+        async fn generate_with_history(&self, _: &[(String, String)]) -> Result<String> {
+            self.0
+                .map(str::to_string)
+                .ok_or_else(|| AppError::LLM("unavailable".to_string()))
+        }
+        async fn generate_with_tools(&self, _: &str, _: &[ToolDefinition]) -> Result<LLMResponse> {
+            unimplemented!()
+        }
+        async fn generate_with_tools_and_history(
+            &self,
+            _: &[crate::llm::coordinator::ConversationMessage],
+            _: &[ToolDefinition],
+        ) -> Result<LLMResponse> {
+            unimplemented!()
+        }
+        async fn stream(
+            &self,
+            _: &str,
+        ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+            unimplemented!()
+        }
+        async fn stream_with_system(
+            &self,
+            _: &str,
+            _: &str,
+        ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+            unimplemented!()
+        }
+        async fn stream_with_history(
+            &self,
+            _: &[(String, String)],
+        ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+            unimplemented!()
+        }
+        fn model_name(&self) -> &str {
+            "summarizer"
+        }
+    }
+
+    /// System prompt, ten 100-token turns and a new message
+    fn conversation() -> Vec<(String, String)> {
+        let mut messages = vec![("system".to_string(), "You are helpful.".to_string())];
+        for i in 0..10 {
+            let role = if i % 2 == 0 { "user" } else { "assistant" };
+            messages.push((role.to_string(), format!("{:<400}", i)));
+        }
+        messages.push(("user".to_string(), "And now?".to_string()));
+        messages
+    }
+
+    #[tokio::test]
+    async fn test_fit_summarizes_oldest_turns() {
+        let budget = ContextBudget::new(1000, 200);
+        let mut messages = conversation();
+        budget
+            .fit(
+                &mut messages,
+                1..11,
+                &Summarizer(Some("They talked about numbers.")),
+            )
+            .await
+            .unwrap();
+
+        assert!(total_tokens(&messages) <= 800);
+        assert_eq!(messages[0].1, "You are helpful.");
+        assert!(messages[1].1.ends_with("They talked about numbers."));
+        // The most recent turns are kept whole
+        assert_eq!(messages[messages.len() - 2].1.trim(), "9");
+        assert_eq!(messages.last().unwrap().1, "And now?");
+    }
+
+    #[tokio::test]
+    async fn test_fit_drops_turns_when_summary_fails() {
+        let budget = ContextBudget::new(1000, 200);
+        let mut messages = conversation();
+        budget
+            .fit(&mut messages, 1..11, &Summarizer(None))
+            .await
+            .unwrap();
+
+        assert!(total_tokens(&messages) <= 800);
+        assert!(messages.iter().all(|(_, c)| !c.starts_with("Summary")));
+        assert_eq!(messages.len(), 2 + 6);
+
+        // Prompts that fit are left alone
+        let mut fitting = conversation();
+        ContextBudget::new(10_000, 200)
+            .fit(&mut fitting, 1..11, &Summarizer(None))
+            .await
+            .unwrap();
+        assert_eq!(fitting, conversation());
+    }
+
+    #[tokio::test]
+    async fn test_fit_truncates_context_and_refuses_oversized_messages() {
+        let budget = ContextBudget::new(300, 100).with_summaries(false);
+        let mut messages = vec![
+            ("system".to_string(), "You are helpful.".to_string()),
+            ("system".to_string(), "x".repeat(2000)),
+            ("user".to_string(), "Hi".to_string()),
+        ];
+        budget
+            .fit(&mut messages, 2..2, &Summarizer(None))
+            .await
+            .unwrap();
+        assert!(total_tokens(&messages) <= 200);
+        assert!(messages[1].1.ends_with(TRUNCATION_MARKER));
+
+        let mut messages = vec![
+            ("system".to_string(), "You are helpful.".to_string()),
+            ("user".to_string(), "y".repeat(2000)),
+        ];
+        let err = budget.fit(&mut messages, 1..1, &Summarizer(None)).await;
+        assert!(matches!(err, Err(AppError::InvalidInput(_))));
+    }
+}
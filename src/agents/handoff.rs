@@ -0,0 +1,145 @@
+//! Agent handoffs.
+//!
+//! An agent configured with `handoffs = ["billing", ...]` is told it may
+//! transfer the conversation to one of those agents. It does so by replying
+//! with a single directive line instead of an answer:
+//!
+//! ```text
+//! HANDOFF: {"agent": "billing", "reason": "Refund request", "summary": "User wants a refund for order 42"}
+//! ```
+//!
+//! [`AgentRegistry::execute_with_handoffs`](crate::agents::AgentRegistry::execute_with_handoffs)
+//! follows these directives, running the target agent on the original message
+//! together with the reason and summary, up to [`MAX_HANDOFFS`] times per
+//! request.
+
+use crate::agents::approval::PausedRun;
+use crate::agents::limits::LimitExceeded;
+use crate::agents::react::ReactStep;
+use crate::llm::coordinator::ToolCallRecord;
+use crate::types::Source;
+use serde::{Deserialize, Serialize};
+
+/// Prefix of the directive line an agent replies with to hand off.
+pub const HANDOFF_PREFIX: &str = "HANDOFF:";
+
+/// Maximum number of handoffs followed for a single request.
+pub const MAX_HANDOFFS: usize = 3;
+
+/// A transfer of the conversation from one agent to another.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Handoff {
+    /// Agent that handed the conversation off
+    pub from: String,
+    /// Agent the conversation was handed to
+    pub to: String,
+    /// Why the conversation was handed off
+    pub reason: String,
+    /// Summary of the conversation so far for the receiving agent
+    pub summary: String,
+}
+
+/// Result of running an agent with handoffs followed.
+#[derive(Debug, Clone)]
+pub struct HandoffOutcome {
+    /// Response of the agent that finally answered
+    pub response: String,
+    /// Name of the agent that finally answered
+    pub agent: String,
+    /// Handoffs that happened, in order
+    pub handoffs: Vec<Handoff>,
+    /// ReAct steps taken by the agents involved, in order
+    pub trace: Vec<ReactStep>,
+    /// Tool calls made by the agents involved, with the agent that made
+    /// each, in order
+    pub tool_calls: Vec<(String, ToolCallRecord)>,
+    /// Limit that stopped the answering agent's run early, if one did
+    pub limit_exceeded: Option<LimitExceeded>,
+    /// The answering agent's run, if it paused for a user's approval of its
+    /// tool calls. The response is then empty.
+    pub paused: Option<PausedRun>,
+    /// Documents of the knowledge passages the answering agent was given
+    pub sources: Vec<Source>,
+}
+
+/// Directive as written by the model.
+#[derive(Deserialize)]
+struct Directive {
+    agent: String,
+    #[serde(default)]
+    reason: String,
+    #[serde(default)]
+    summary: String,
+}
+
+/// System prompt section telling an agent which agents it may hand off to.
+pub fn instructions(targets: &[String]) -> String {
+    format!(
+        "If another agent is better suited to handle this conversation, do not answer it yourself. \
+         Instead reply with exactly one line:\n\
+         {} {{\"agent\": \"<name>\", \"reason\": \"<why>\", \"summary\": \"<what the user needs so far>\"}}\n\
+         Agents you can hand off to: {}",
+        HANDOFF_PREFIX,
+        targets.join(", ")
+    )
+}
+
+/// Parse a handoff directive from an agent's output.
+///
+/// Only targets in `allowed` are accepted; anything else is treated as a
+/// regular answer.
+pub fn parse(from: &str, output: &str, allowed: &[String]) -> Option<Handoff> {
+    let directive = output
+        .lines()
+        .map(str::trim)
+        .find_map(|line| line.strip_prefix(HANDOFF_PREFIX))?;
+    let directive: Directive = serde_json::from_str(directive.trim()).ok()?;
+
+    allowed
+        .iter()
+        .any(|a| a == &directive.agent)
+        .then(|| Handoff {
+            from: from.to_string(),
+            to: directive.agent,
+            reason: directive.reason,
+            summary: directive.summary,
+        })
+}
+
+/// Input for the receiving agent: the handoff context followed by the user's message.
+pub fn handoff_input(handoff: &Handoff, input: &str) -> String {
+    format!(
+        "[Conversation handed off from the {} agent. Reason: {}]\n[Summary: {}]\n\n{}",
+        handoff.from, handoff.reason, handoff.summary, input
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_handoff_directive() {
+        let allowed = vec!["billing".to_string()];
+        let output =
+            "HANDOFF: {\"agent\": \"billing\", \"reason\": \"refund\", \"summary\": \"order 42\"}";
+
+        let handoff = parse("sales", output, &allowed).unwrap();
+        assert_eq!(handoff.from, "sales");
+        assert_eq!(handoff.to, "billing");
+        assert_eq!(handoff.reason, "refund");
+        assert_eq!(handoff.summary, "order 42");
+
+        let input = handoff_input(&handoff, "I want my money back");
+        assert!(input.contains("from the sales agent. Reason: refund"));
+        assert!(input.ends_with("I want my money back"));
+    }
+
+    #[test]
+    fn test_parse_rejects_unlisted_or_malformed() {
+        let allowed = vec!["billing".to_string()];
+        assert!(parse("sales", "HANDOFF: {\"agent\": \"hr\"}", &allowed).is_none());
+        assert!(parse("sales", "HANDOFF: billing", &allowed).is_none());
+        assert!(parse("sales", "Here is your answer.", &allowed).is_none());
+    }
+}
@@ -0,0 +1,190 @@
+//! Agent middleware hooks.
+//!
+//! Agent hooks inject behavior into every run of a
+//! [`ConfigurableAgent`](crate::agents::ConfigurableAgent) (input rewriting,
+//! tool result filtering, output moderation) without forking it. Register
+//! implementations of [`AgentHook`] with
+//! [`AgentRegistry::register_hook`](crate::agents::AgentRegistry::register_hook);
+//! every agent the registry creates runs them.
+//!
+//! Hooks run in registration order at four points of a run:
+//!
+//! | Hook | When | May modify |
+//! |------|------|------------|
+//! | `before_generation` | Once the prompt is built, before the first LLM call | The prompt messages |
+//! | `before_tool` | Before each tool call runs | The tool name and arguments |
+//! | `after_tool` | After each tool call, before its result is sent back to the model | The tool result |
+//! | `after_generation` | On the final output, before output guardrails | The output text |
+//!
+//! Returning an error from a hook aborts the run with that error. Agent hooks
+//! run after the request's [`ConversationHook`](crate::hooks::ConversationHook)s
+//! at the matching points.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use ares::agents::hooks::AgentHook;
+//! use ares::types::{AgentContext, AppError, Result, ToolCall};
+//!
+//! struct NoShell;
+//!
+//! #[async_trait::async_trait]
+//! impl AgentHook for NoShell {
+//!     fn name(&self) -> &str {
+//!         "no-shell"
+//!     }
+//!
+//!     async fn before_tool(&self, _ctx: &AgentContext, agent: &str, call: &mut ToolCall) -> Result<()> {
+//!         if call.name == "shell" {
+//!             return Err(AppError::Guardrail(format!("{} may not run shell commands", agent)));
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! agent_registry.register_hook(Arc::new(NoShell));
+//! ```
+
+use crate::llm::coordinator::ToolCallRecord;
+use crate::types::{AgentContext, Result, ToolCall};
+use async_trait::async_trait;
+use std::fmt;
+use std::sync::Arc;
+
+/// Custom logic invoked at fixed points of an agent run.
+///
+/// Every method has a no-op default, so implementations only override the
+/// points they care about.
+#[async_trait]
+pub trait AgentHook: Send + Sync {
+    /// Hook name, used in logs.
+    fn name(&self) -> &str;
+
+    /// Called with the `(role, content)` prompt messages before `agent` generates.
+    async fn before_generation(
+        &self,
+        _ctx: &AgentContext,
+        _agent: &str,
+        _messages: &mut Vec<(String, String)>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called with `agent`'s final output before output guardrails run.
+    async fn after_generation(
+        &self,
+        _ctx: &AgentContext,
+        _agent: &str,
+        _output: &mut String,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called before `agent` runs a tool call.
+    async fn before_tool(
+        &self,
+        _ctx: &AgentContext,
+        _agent: &str,
+        _call: &mut ToolCall,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after `agent` ran a tool call, before the result reaches the model.
+    async fn after_tool(
+        &self,
+        _ctx: &AgentContext,
+        _agent: &str,
+        _record: &mut ToolCallRecord,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// An ordered set of [`AgentHook`]s.
+#[derive(Clone, Default)]
+pub struct AgentHooks {
+    hooks: Vec<Arc<dyn AgentHook>>,
+}
+
+impl AgentHooks {
+    /// Create an empty hook set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a hook; hooks run in the order they were registered
+    pub fn register(&mut self, hook: Arc<dyn AgentHook>) {
+        self.hooks.push(hook);
+    }
+
+    /// Number of registered hooks
+    pub fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    /// Whether no hooks are registered
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Run every `before_generation` hook
+    pub async fn before_generation(
+        &self,
+        ctx: &AgentContext,
+        agent: &str,
+        messages: &mut Vec<(String, String)>,
+    ) -> Result<()> {
+        for hook in &self.hooks {
+            hook.before_generation(ctx, agent, messages).await?;
+        }
+        Ok(())
+    }
+
+    /// Run every `after_generation` hook
+    pub async fn after_generation(
+        &self,
+        ctx: &AgentContext,
+        agent: &str,
+        output: &mut String,
+    ) -> Result<()> {
+        for hook in &self.hooks {
+            hook.after_generation(ctx, agent, output).await?;
+        }
+        Ok(())
+    }
+
+    /// Run every `before_tool` hook
+    pub async fn before_tool(
+        &self,
+        ctx: &AgentContext,
+        agent: &str,
+        call: &mut ToolCall,
+    ) -> Result<()> {
+        for hook in &self.hooks {
+            hook.before_tool(ctx, agent, call).await?;
+        }
+        Ok(())
+    }
+
+    /// Run every `after_tool` hook
+    pub async fn after_tool(
+        &self,
+        ctx: &AgentContext,
+        agent: &str,
+        record: &mut ToolCallRecord,
+    ) -> Result<()> {
+        for hook in &self.hooks {
+            hook.after_tool(ctx, agent, record).await?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for AgentHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.hooks.iter().map(|h| h.name()))
+            .finish()
+    }
+}
@@ -0,0 +1,153 @@
+//! Knowledge retrieval for RAG-augmented agents.
+//!
+//! Agents with a `rag` section search one of the requesting user's RAG
+//! collections with every message. The most relevant passages are added to
+//! the system prompt, numbered so the answer can cite them as `[1]`, `[2]`,
+//! and the documents they come from become the response's sources.
+//!
+//! ```toml
+//! [agents.support]
+//! model = "balanced"
+//! rag = { collection = "handbook", top_k = 5, min_score = 0.3 }
+//! ```
+
+use crate::rag::attachments::Passage;
+use crate::types::Result;
+use crate::utils::toml_config::AgentRagConfig;
+use async_trait::async_trait;
+
+/// Searches users' RAG collections for passages to inject.
+#[async_trait]
+pub trait KnowledgeBase: Send + Sync {
+    /// The passages of `owner`'s `collection` most relevant to `query`, best
+    /// first, at most `top_k` and none scoring below `min_score`
+    ///
+    /// A collection the owner doesn't have yields no passages.
+    async fn search(
+        &self,
+        owner: &str,
+        collection: &str,
+        query: &str,
+        top_k: usize,
+        min_score: f32,
+    ) -> Result<Vec<Passage>>;
+}
+
+/// Retrieve the passages to inject for a message
+///
+/// Returns no passages when retrieval fails, so an unavailable vector store
+/// never blocks a turn.
+pub async fn retrieve(
+    knowledge: &dyn KnowledgeBase,
+    config: &AgentRagConfig,
+    owner: &str,
+    message: &str,
+) -> Vec<Passage> {
+    if config.top_k == 0 {
+        return Vec::new();
+    }
+    match knowledge
+        .search(
+            owner,
+            &config.collection,
+            message,
+            config.top_k,
+            config.min_score,
+        )
+        .await
+    {
+        Ok(passages) => passages,
+        Err(e) => {
+            tracing::warn!(
+                collection = %config.collection,
+                "Answering without knowledge passages: {}",
+                e
+            );
+            Vec::new()
+        }
+    }
+}
+
+/// System prompt section presenting the passages with citation markers
+///
+/// Returns `None` when there are no passages.
+pub fn render(passages: &[Passage]) -> Option<String> {
+    if passages.is_empty() {
+        return None;
+    }
+    let passages = passages
+        .iter()
+        .enumerate()
+        .map(|(i, p)| format!("[{}] {}\n{}", i + 1, p.filename, p.content))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Some(format!(
+        "Answer using the following numbered passages from the knowledge base where they \
+         are relevant. Cite the passages you use with their numbers in square brackets, \
+         like [1]. Say so if they don't contain the answer.\n\n{}",
+        passages
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AppError;
+
+    /// Knowledge base returning fixed passages, or failing
+    struct FixedKnowledge(Option<Vec<Passage>>);
+
+    #[async_trait]
+    impl KnowledgeBase for FixedKnowledge {
+        async fn search(
+            &self,
+            _owner: &str,
+            _collection: &str,
+            _query: &str,
+            top_k: usize,
+            _min_score: f32,
+        ) -> Result<Vec<Passage>> {
+            let mut passages = self
+                .0
+                .clone()
+                .ok_or_else(|| AppError::External("vector store down".to_string()))?;
+            passages.truncate(top_k);
+            Ok(passages)
+        }
+    }
+
+    fn passage(filename: &str, content: &str) -> Passage {
+        Passage {
+            filename: filename.to_string(),
+            content: content.to_string(),
+            score: 0.8,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_and_render_numbered_passages() {
+        let config = AgentRagConfig {
+            collection: "handbook".to_string(),
+            top_k: 2,
+            min_score: 0.0,
+        };
+        let knowledge = FixedKnowledge(Some(vec![
+            passage("leave.md", "Staff get 25 days of leave."),
+            passage("expenses.md", "Receipts are needed over 20 EUR."),
+            passage("travel.md", "Book trains over flights."),
+        ]));
+
+        let passages = retrieve(&knowledge, &config, "user-1", "How much leave?").await;
+        assert_eq!(passages.len(), 2);
+        let section = render(&passages).unwrap();
+        assert!(section.contains("[1] leave.md\nStaff get 25 days of leave."));
+        assert!(section.contains("[2] expenses.md"));
+        assert!(!section.contains("travel.md"));
+
+        let failing = FixedKnowledge(None);
+        assert!(retrieve(&failing, &config, "user-1", "How much leave?")
+            .await
+            .is_empty());
+        assert!(render(&[]).is_none());
+    }
+}
@@ -0,0 +1,310 @@
+//! Per-run resource limits.
+//!
+//! Agents can cap a single run's wall-clock time, tool calls, LLM
+//! generations and estimated spend. [`ConfigurableAgent`] checks the limits
+//! before every generation and batch of tool calls, and cuts off a
+//! generation that outlives the run's time. A run that reaches a limit stops
+//! there and returns the output it has so far, together with a
+//! [`LimitExceeded`] describing which limit was reached.
+//!
+//! ```toml
+//! [agents.research.limits]
+//! timeout_secs = 120
+//! max_tool_calls = 20
+//! max_llm_calls = 10
+//! max_cost = 0.25
+//! ```
+//!
+//! [`ConfigurableAgent`]: crate::agents::configurable::ConfigurableAgent
+
+use crate::types::Result;
+use crate::utils::toml_config::{ModelPricing, RunLimitsConfig};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// A limit a run can reach.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunLimit {
+    /// `timeout_secs`
+    Timeout,
+    /// `max_tool_calls`
+    MaxToolCalls,
+    /// `max_llm_calls`
+    MaxLlmCalls,
+    /// `max_cost`
+    MaxCost,
+}
+
+/// Resources a run used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RunUsage {
+    /// Wall-clock time so far, in milliseconds
+    pub elapsed_ms: u64,
+    /// Tool calls made
+    pub tool_calls: usize,
+    /// LLM generations made
+    pub llm_calls: usize,
+    /// Estimated spend in USD
+    pub cost: f64,
+}
+
+/// A run stopped by one of its limits.
+///
+/// The run's response holds the output produced before it stopped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LimitExceeded {
+    /// Limit that was reached
+    pub limit: RunLimit,
+    /// Human-readable explanation
+    pub reason: String,
+    /// Resources used when the run stopped
+    pub usage: RunUsage,
+}
+
+/// An agent's run limits, with the price of its model's tokens.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunLimits {
+    config: RunLimitsConfig,
+    pricing: ModelPricing,
+}
+
+impl RunLimits {
+    /// Limits from an agent's `limits`
+    pub fn new(config: RunLimitsConfig) -> Self {
+        Self {
+            config,
+            pricing: ModelPricing::default(),
+        }
+    }
+
+    /// Price generations at the agent's model pricing, for `max_cost`
+    pub fn with_pricing(mut self, pricing: ModelPricing) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// Whether no limit is set
+    pub fn is_unlimited(&self) -> bool {
+        self.config.is_unlimited()
+    }
+
+    /// Start metering a run
+    pub fn start(&self) -> RunMeter {
+        RunMeter {
+            limits: *self,
+            started: Instant::now(),
+            usage: RunUsage::default(),
+            exceeded: None,
+        }
+    }
+}
+
+/// Resources used by one run, checked against the run's limits.
+///
+/// The first limit reached is kept; once one is, every further check fails.
+#[derive(Debug)]
+pub struct RunMeter {
+    limits: RunLimits,
+    started: Instant,
+    usage: RunUsage,
+    exceeded: Option<LimitExceeded>,
+}
+
+impl RunMeter {
+    /// Time left before `timeout_secs`, if the run has a timeout
+    pub fn time_left(&self) -> Option<Duration> {
+        self.limits
+            .config
+            .timeout_secs
+            .map(|secs| Duration::from_secs(secs).saturating_sub(self.started.elapsed()))
+    }
+
+    /// Stop the run at `limit`, keeping the first limit reached
+    fn exceed(&mut self, limit: RunLimit) -> bool {
+        if self.exceeded.is_none() {
+            let config = &self.limits.config;
+            let reason = match limit {
+                RunLimit::Timeout => format!(
+                    "The run took longer than {}s",
+                    config.timeout_secs.unwrap_or_default()
+                ),
+                RunLimit::MaxToolCalls => format!(
+                    "The run reached its limit of {} tool calls",
+                    config.max_tool_calls.unwrap_or_default()
+                ),
+                RunLimit::MaxLlmCalls => format!(
+                    "The run reached its limit of {} LLM calls",
+                    config.max_llm_calls.unwrap_or_default()
+                ),
+                RunLimit::MaxCost => format!(
+                    "The run reached its spend limit of ${:.4}",
+                    config.max_cost.unwrap_or_default()
+                ),
+            };
+            self.exceeded = Some(LimitExceeded {
+                limit,
+                reason,
+                usage: self.usage(),
+            });
+        }
+        false
+    }
+
+    /// Check the limits every step shares: a stopped run, time and spend
+    fn allow_step(&mut self) -> bool {
+        if self.exceeded.is_some() {
+            return false;
+        }
+        if self.time_left().is_some_and(|left| left.is_zero()) {
+            return self.exceed(RunLimit::Timeout);
+        }
+        if let Some(max_cost) = self.limits.config.max_cost {
+            if self.usage.cost >= max_cost {
+                return self.exceed(RunLimit::MaxCost);
+            }
+        }
+        true
+    }
+
+    /// Whether the run may make another LLM call
+    pub fn allow_llm(&mut self) -> bool {
+        if !self.allow_step() {
+            return false;
+        }
+        match self.limits.config.max_llm_calls {
+            Some(max) if self.usage.llm_calls >= max => self.exceed(RunLimit::MaxLlmCalls),
+            _ => true,
+        }
+    }
+
+    /// Whether the run may make `count` more tool calls
+    pub fn allow_tools(&mut self, count: usize) -> bool {
+        if !self.allow_step() {
+            return false;
+        }
+        match self.limits.config.max_tool_calls {
+            Some(max) if self.usage.tool_calls + count > max => self.exceed(RunLimit::MaxToolCalls),
+            _ => true,
+        }
+    }
+
+    /// Count an LLM call with its estimated token usage
+    pub fn record_llm(&mut self, input_tokens: usize, output_tokens: usize) {
+        let pricing = &self.limits.pricing;
+        self.usage.llm_calls += 1;
+        self.usage.cost += (input_tokens as f64 / 1000.0) * pricing.input_per_1k
+            + (output_tokens as f64 / 1000.0) * pricing.output_per_1k;
+    }
+
+    /// Count a tool call with its configured cost
+    pub fn record_tool(&mut self, cost: f64) {
+        self.usage.tool_calls += 1;
+        self.usage.cost += cost;
+    }
+
+    /// Await a step, giving up when the run's time is up
+    ///
+    /// Returns `None` if the time ran out first.
+    pub async fn timed<T>(&mut self, step: impl Future<Output = Result<T>>) -> Result<Option<T>> {
+        let Some(remaining) = self.time_left() else {
+            return step.await.map(Some);
+        };
+        match tokio::time::timeout(remaining, step).await {
+            Ok(output) => output.map(Some),
+            Err(_) => {
+                self.exceed(RunLimit::Timeout);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Resources used so far
+    pub fn usage(&self) -> RunUsage {
+        RunUsage {
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            ..self.usage
+        }
+    }
+
+    /// The limit that stopped the run, if one did
+    pub fn exceeded(&self) -> Option<&LimitExceeded> {
+        self.exceeded.as_ref()
+    }
+
+    /// Finish metering, returning the limit that stopped the run
+    pub fn finish(self) -> Option<LimitExceeded> {
+        self.exceeded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meter_stops_at_first_limit() {
+        let limits = RunLimits::new(RunLimitsConfig {
+            max_llm_calls: Some(2),
+            max_tool_calls: Some(3),
+            max_cost: Some(0.01),
+            ..Default::default()
+        })
+        .with_pricing(ModelPricing {
+            input_per_1k: 0.001,
+            output_per_1k: 0.002,
+        });
+        let mut meter = limits.start();
+
+        assert!(meter.allow_llm());
+        meter.record_llm(1000, 1000);
+        assert!(meter.allow_tools(2));
+        meter.record_tool(0.0);
+        meter.record_tool(0.0);
+        assert!(meter.allow_llm());
+        meter.record_llm(100, 100);
+
+        // A batch that would go over the limit is refused as a whole
+        assert!(!meter.allow_tools(2));
+        assert!(!meter.allow_llm());
+        let exceeded = meter.finish().unwrap();
+        assert_eq!(exceeded.limit, RunLimit::MaxToolCalls);
+        assert_eq!(exceeded.usage.llm_calls, 2);
+        assert_eq!(exceeded.usage.tool_calls, 2);
+
+        let mut meter = limits.start();
+        meter.record_llm(0, 0);
+        meter.record_tool(0.02);
+        assert!(!meter.allow_llm());
+        assert_eq!(meter.exceeded().unwrap().limit, RunLimit::MaxCost);
+
+        let mut meter = RunLimits::default().start();
+        assert!(meter.allow_llm() && meter.allow_tools(1000));
+        assert!(meter.finish().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_timed_step_is_cut_off() {
+        // With no time left, a step still finishes if it is ready at once
+        let limits = RunLimits::new(RunLimitsConfig {
+            timeout_secs: Some(0),
+            ..Default::default()
+        });
+        let mut meter = limits.start();
+        let output = meter.timed(async { Ok(1) }).await.unwrap();
+        assert_eq!(output, Some(1));
+
+        let output = meter
+            .timed(async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(2)
+            })
+            .await
+            .unwrap();
+        assert_eq!(output, None);
+        assert_eq!(meter.exceeded().unwrap().limit, RunLimit::Timeout);
+        assert!(!meter.allow_llm());
+    }
+}
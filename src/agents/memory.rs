@@ -0,0 +1,274 @@
+//! User memory injection.
+//!
+//! Agents with `memory.enabled` get the user's stored preferences and a
+//! selection of their memory facts added to the system prompt every turn.
+//! Facts are chosen by the agent's [`MemoryStrategy`]: the most relevant to
+//! the message (embedding similarity when the registry has an embedder,
+//! word overlap otherwise), the most recent, or the most confident.
+//!
+//! ```toml
+//! [agents.assistant]
+//! model = "balanced"
+//! memory = { enabled = true, max_facts = 8, strategy = "relevant" }
+//! ```
+
+use crate::rag::batcher::BatchEmbedder;
+use crate::types::{MemoryFact, UserMemory};
+use crate::utils::toml_config::{AgentMemoryConfig, MemoryStrategy};
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashSet;
+
+/// Select the facts to inject for a message, best first
+///
+/// Falls back to word overlap when embedding fails, so a flaky embedding
+/// provider never blocks a turn.
+pub async fn select_facts<'a>(
+    memory: &'a UserMemory,
+    config: &AgentMemoryConfig,
+    message: &str,
+    embedder: Option<&dyn BatchEmbedder>,
+) -> Vec<&'a MemoryFact> {
+    let mut facts: Vec<&MemoryFact> = memory.facts.iter().collect();
+    if facts.len() <= config.max_facts && config.strategy == MemoryStrategy::Relevant {
+        // Everything fits; skip the embedding round-trip
+        facts.sort_by(|a, b| by_confidence(a, b));
+        return facts;
+    }
+
+    match config.strategy {
+        MemoryStrategy::Recent => facts.sort_by_key(|fact| Reverse(fact.updated_at)),
+        MemoryStrategy::Confident => facts.sort_by(|a, b| by_confidence(a, b)),
+        MemoryStrategy::Relevant => {
+            let scores = match embedder {
+                Some(embedder) => match embedding_scores(&facts, message, embedder).await {
+                    Ok(scores) => scores,
+                    Err(e) => {
+                        tracing::warn!("Falling back to word overlap for memory facts: {}", e);
+                        overlap_scores(&facts, message)
+                    }
+                },
+                None => overlap_scores(&facts, message),
+            };
+            let mut scored: Vec<(f32, &MemoryFact)> = scores.into_iter().zip(facts).collect();
+            scored.sort_by(|(a_score, a), (b_score, b)| {
+                b_score
+                    .partial_cmp(a_score)
+                    .unwrap_or(Ordering::Equal)
+                    .then_with(|| by_confidence(a, b))
+            });
+            facts = scored.into_iter().map(|(_, fact)| fact).collect();
+        }
+    }
+    facts.truncate(config.max_facts);
+    facts
+}
+
+/// System prompt section listing the user's preferences and selected facts
+///
+/// Returns `None` when there is nothing to inject.
+pub fn render(memory: &UserMemory, facts: &[&MemoryFact]) -> Option<String> {
+    if memory.preferences.is_empty() && facts.is_empty() {
+        return None;
+    }
+
+    let mut section = String::from(
+        "What you know about this user. Use it where it helps, and don't recite it unprompted.",
+    );
+    if !memory.preferences.is_empty() {
+        section.push_str("\n\nPreferences:");
+        for preference in &memory.preferences {
+            section.push_str(&format!(
+                "\n- {} / {}: {}",
+                preference.category, preference.key, preference.value
+            ));
+        }
+    }
+    if !facts.is_empty() {
+        section.push_str("\n\nFacts:");
+        for fact in facts {
+            section.push_str(&format!(
+                "\n- {} / {}: {}",
+                fact.category, fact.fact_key, fact.fact_value
+            ));
+        }
+    }
+    Some(section)
+}
+
+/// Higher confidence first, then more recently updated
+fn by_confidence(a: &MemoryFact, b: &MemoryFact) -> Ordering {
+    b.confidence
+        .partial_cmp(&a.confidence)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| b.updated_at.cmp(&a.updated_at))
+}
+
+/// Text a fact is matched against
+fn fact_text(fact: &MemoryFact) -> String {
+    format!("{} {}: {}", fact.category, fact.fact_key, fact.fact_value)
+}
+
+/// Cosine similarity of each fact to the message
+async fn embedding_scores(
+    facts: &[&MemoryFact],
+    message: &str,
+    embedder: &dyn BatchEmbedder,
+) -> crate::types::Result<Vec<f32>> {
+    let mut texts = vec![message.to_string()];
+    texts.extend(facts.iter().map(|fact| fact_text(fact)));
+    let vectors = embedder.embed_batch(texts).await?;
+    let Some((query, facts)) = vectors.split_first() else {
+        return Ok(Vec::new());
+    };
+    Ok(facts.iter().map(|fact| cosine(query, fact)).collect())
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 {
+        0.0
+    } else {
+        dot / denominator
+    }
+}
+
+/// Share of each fact's words that appear in the message
+fn overlap_scores(facts: &[&MemoryFact], message: &str) -> Vec<f32> {
+    let message_words = words(message);
+    facts
+        .iter()
+        .map(|fact| {
+            let fact_words = words(&fact_text(fact));
+            if fact_words.is_empty() {
+                return 0.0;
+            }
+            let shared = fact_words.intersection(&message_words).count();
+            shared as f32 / fact_words.len() as f32
+        })
+        .collect()
+}
+
+/// Lowercased words of three or more characters
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Preference, Result};
+    use async_trait::async_trait;
+    use chrono::{Duration, Utc};
+
+    fn fact(key: &str, value: &str, confidence: f32, age_days: i64) -> MemoryFact {
+        let at = Utc::now() - Duration::days(age_days);
+        MemoryFact {
+            id: key.to_string(),
+            user_id: "user".to_string(),
+            category: "personal".to_string(),
+            fact_key: key.to_string(),
+            fact_value: value.to_string(),
+            confidence,
+            created_at: at,
+            updated_at: at,
+        }
+    }
+
+    fn memory() -> UserMemory {
+        UserMemory {
+            user_id: "user".to_string(),
+            preferences: vec![Preference {
+                category: "communication".to_string(),
+                key: "tone".to_string(),
+                value: "concise".to_string(),
+                confidence: 0.9,
+            }],
+            facts: vec![
+                fact("pet", "has a dog called Rex", 0.6, 1),
+                fact("employer", "works at Acme on the billing team", 0.9, 30),
+                fact("city", "lives in Lisbon", 0.8, 10),
+            ],
+        }
+    }
+
+    fn config(strategy: MemoryStrategy, max_facts: usize) -> AgentMemoryConfig {
+        AgentMemoryConfig {
+            enabled: true,
+            max_facts,
+            strategy,
+        }
+    }
+
+    fn keys(facts: &[&MemoryFact]) -> Vec<String> {
+        facts.iter().map(|f| f.fact_key.clone()).collect()
+    }
+
+    /// Embeds texts mentioning a dog close to each other
+    struct DogEmbedder;
+
+    #[async_trait]
+    impl BatchEmbedder for DogEmbedder {
+        async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|t| {
+                    if t.contains("dog") || t.contains("puppy") {
+                        vec![1.0, 0.0]
+                    } else {
+                        vec![0.0, 1.0]
+                    }
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_select_facts_by_strategy() {
+        let memory = memory();
+
+        let recent = select_facts(&memory, &config(MemoryStrategy::Recent, 2), "", None).await;
+        assert_eq!(keys(&recent), vec!["pet", "city"]);
+
+        let confident =
+            select_facts(&memory, &config(MemoryStrategy::Confident, 1), "", None).await;
+        assert_eq!(keys(&confident), vec!["employer"]);
+
+        let relevant = select_facts(
+            &memory,
+            &config(MemoryStrategy::Relevant, 1),
+            "Which billing plan fits my team?",
+            None,
+        )
+        .await;
+        assert_eq!(keys(&relevant), vec!["employer"]);
+
+        let embedded = select_facts(
+            &memory,
+            &config(MemoryStrategy::Relevant, 1),
+            "Any tips for my puppy?",
+            Some(&DogEmbedder),
+        )
+        .await;
+        assert_eq!(keys(&embedded), vec!["pet"]);
+    }
+
+    #[test]
+    fn test_render() {
+        let memory = memory();
+        let section = render(&memory, &[&memory.facts[2]]).unwrap();
+        assert!(section.contains("- communication / tone: concise"));
+        assert!(section.contains("Facts:\n- personal / city: lives in Lisbon"));
+
+        let empty = UserMemory {
+            user_id: "user".to_string(),
+            preferences: Vec::new(),
+            facts: Vec::new(),
+        };
+        assert!(render(&empty, &[]).is_none());
+    }
+}
@@ -4,7 +4,10 @@
 //!
 //! - **Agent Trait** - Base trait that all agents implement
 //! - **ConfigurableAgent** - Dynamic agent created from TOML/TOON configuration
-//! - **AgentRegistry** - Registry for creating and managing agent instances
+//! - **AgentRegistry** - Registry for creating and managing agent instances,
+//!   including custom agents implemented in code or by plugins
+//! - **AgentHook** - Middleware run around an agent's generation and tool calls
+//! - **AgentBus** - Events agents publish to each other during a workflow run
 //! - **Router** - Routes requests to appropriate specialized agents
 //! - **Orchestrator** - Coordinates multi-step agent workflows
 //!
@@ -26,33 +29,151 @@
 //!
 //! // Execute with context
 //! let response = agent.execute("Help me with my order", &context).await?;
+//!
+//! // Or follow the run as it happens
+//! let mut events = agent.execute_stream("Help me with my order", &context).await?;
+//! while let Some(event) = events.next().await {
+//!     match event? {
+//!         AgentEvent::Token { delta } => print!("{}", delta),
+//!         AgentEvent::ToolCallStarted { name, .. } => println!("[calling {}]", name),
+//!         AgentEvent::ToolCallFinished(record) => println!("[{} done]", record.name),
+//!         AgentEvent::Final { response, .. } => println!("\n{}", response),
+//!     }
+//! }
 //! ```
 
+/// Human approval of tool calls.
+pub mod approval;
+/// Typed events agents publish to each other during a workflow run.
+pub mod bus;
+/// Checkpoints of tool-calling runs, for runs cut off by a restart.
+pub mod checkpoint;
 pub mod configurable;
+/// Fitting prompts into a model's context window.
+pub mod context;
+/// Transfer of a conversation between agents.
+pub mod handoff;
+/// Middleware hooks around agent generation and tool calls.
+pub mod hooks;
+/// RAG collection passages injected into agent prompts.
+pub mod knowledge;
+/// Per-run resource limits.
+pub mod limits;
+/// User memory injection into agent prompts.
+pub mod memory;
 /// Multi-agent orchestration for complex tasks.
 pub mod orchestrator;
+/// Agents provided by external plugin processes.
+pub mod plugin;
+/// ReAct (Thought/Action/Observation) planning loop.
+pub mod react;
+/// Critique and revision of draft answers.
+pub mod reflection;
+/// Agents hosted by other systems, called over A2A.
+pub mod remote;
 pub mod registry;
 /// Request routing to specialized agents.
 pub mod router;
+/// Agent runs on a cron schedule.
+pub mod scheduler;
+/// JSON Schema enforcement for agent output.
+pub mod structured;
 /// Per-tenant agent creation from DB-stored configs.
 pub mod tenant_agent;
 
-use crate::types::{AgentContext, AgentType, Result};
+use crate::llm::coordinator::ToolCallRecord;
+use crate::types::{AgentContext, AgentType, Result, Source};
 use async_trait::async_trait;
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
 
 // Re-export commonly used types
-pub use configurable::ConfigurableAgent;
+pub use bus::{AgentBus, BusEvent};
+pub use configurable::{AgentTrace, ConfigurableAgent};
+pub use handoff::{Handoff, HandoffOutcome};
+pub use hooks::{AgentHook, AgentHooks};
+pub use react::ReactStep;
 pub use registry::{AgentRegistry, AgentRegistryBuilder};
 
+/// Progress event emitted while an agent runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentEvent {
+    /// A chunk of response text.
+    Token {
+        /// Text generated since the previous token event
+        delta: String,
+    },
+    /// The agent is about to run a tool.
+    ToolCallStarted {
+        /// Tool call ID from the model
+        id: String,
+        /// Name of the tool
+        name: String,
+        /// Arguments the tool is called with
+        arguments: serde_json::Value,
+    },
+    /// A tool finished running (successfully or not).
+    ToolCallFinished(ToolCallRecord),
+    /// A ReAct step finished (agents with `strategy = "react"`).
+    ReactStep(react::ReactStep),
+    /// The run reached one of its limits and stopped early. The final
+    /// response holds the output produced so far.
+    LimitExceeded(limits::LimitExceeded),
+    /// The run paused before tool calls that need a user's approval. Ends
+    /// the stream in place of [`AgentEvent::Final`].
+    ApprovalRequired(approval::PausedRun),
+    /// The complete response, after output guardrails. The last event,
+    /// unless the run paused for approval.
+    Final {
+        /// Final response text
+        response: String,
+        /// Documents of the knowledge passages the response could cite
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        sources: Vec<Source>,
+    },
+}
+
+/// Stream of events produced by [`Agent::execute_stream`].
+pub type AgentEventStream<'a> = Pin<Box<dyn Stream<Item = Result<AgentEvent>> + Send + 'a>>;
+
 /// Base trait for all agents
 #[async_trait]
 pub trait Agent: Send + Sync {
     /// Execute the agent with given input and context
     async fn execute(&self, input: &str, context: &AgentContext) -> Result<String>;
 
+    /// Execute the agent, streaming tokens and tool activity as they happen.
+    ///
+    /// The stream ends with an [`AgentEvent::Final`] carrying the complete
+    /// response. The default implementation runs [`execute`](Agent::execute)
+    /// and emits the result as a single token followed by the final event.
+    async fn execute_stream<'a>(
+        &'a self,
+        input: &'a str,
+        context: &'a AgentContext,
+    ) -> Result<AgentEventStream<'a>> {
+        let response = self.execute(input, context).await?;
+        Ok(Box::pin(futures::stream::iter([
+            Ok(AgentEvent::Token {
+                delta: response.clone(),
+            }),
+            Ok(AgentEvent::Final {
+                response,
+                sources: Vec::new(),
+            }),
+        ])))
+    }
+
     /// Get the agent's system prompt
     fn system_prompt(&self) -> String;
 
     /// Get the agent type
     fn agent_type(&self) -> AgentType;
+
+    /// What the agent handles, for agent lists and routing
+    fn description(&self) -> String {
+        String::new()
+    }
 }
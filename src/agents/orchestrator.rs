@@ -87,6 +87,9 @@ Only respond with valid JSON."#,
         task: &str,
         context: &AgentContext,
     ) -> Result<String> {
+        if let Some(agent) = self.agent_registry.custom_agent(agent_name) {
+            return agent.execute(task, context).await;
+        }
         // Create agent from registry (handles model and tool configuration)
         let agent = self.agent_registry.create_agent(agent_name).await?;
         agent.execute(task, context).await
@@ -0,0 +1,228 @@
+//! Agents provided by external plugin processes.
+//!
+//! A plugin is a program that answers as an agent, written in any language.
+//! Each `*.toon` file in `plugins_dir` (`config/plugins` by default) describes
+//! one, and is loaded at startup:
+//!
+//! ```toon
+//! name: summarizer
+//! description: Summarizes long documents and threads
+//! command: python3
+//! args[1]: plugins/summarizer.py
+//! timeout_secs: 60
+//! ```
+//!
+//! The plugin's agent is registered as a custom agent (see
+//! [`AgentRegistry::register_custom`](crate::agents::AgentRegistry::register_custom)),
+//! so it can be chatted with, routed to and used in workflows like any other.
+//!
+//! ## Protocol
+//!
+//! Each run starts the command and writes one JSON-RPC 2.0 request to its
+//! stdin, then closes it:
+//!
+//! ```json
+//! {"jsonrpc": "2.0", "id": 1, "method": "execute",
+//!  "params": {"input": "...", "user_id": "...", "session_id": "...", "history": [...]}}
+//! ```
+//!
+//! The plugin answers with a JSON-RPC response as the last line of its
+//! stdout, then exits:
+//!
+//! ```json
+//! {"jsonrpc": "2.0", "id": 1, "result": {"response": "..."}}
+//! ```
+//!
+//! or `{"jsonrpc": "2.0", "id": 1, "error": {"code": -32000, "message": "..."}}`
+//! to fail the run. Anything the plugin writes to stderr is logged.
+
+use crate::agents::Agent;
+use crate::types::{AgentContext, AgentType, AppError, Result};
+use crate::utils::toon_config::{load_plugins, ToonPluginConfig};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// An agent run by an external plugin process.
+pub struct PluginAgent {
+    config: ToonPluginConfig,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    #[serde(default)]
+    result: Option<ExecuteResult>,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct ExecuteResult {
+    response: String,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl PluginAgent {
+    /// Create an agent running the plugin described by `config`
+    pub fn new(config: ToonPluginConfig) -> Self {
+        Self { config }
+    }
+
+    /// Name of the plugin's agent
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Load the enabled plugins described in `dir`
+    ///
+    /// A missing directory has no plugins; a plugin file that fails to
+    /// parse is logged and skipped.
+    pub fn discover(dir: &Path) -> Vec<PluginAgent> {
+        let configs = match load_plugins(dir) {
+            Ok(configs) => configs,
+            Err(e) => {
+                tracing::warn!("Failed to load agent plugins from {:?}: {}", dir, e);
+                return Vec::new();
+            }
+        };
+        let mut plugins: Vec<PluginAgent> = configs
+            .into_values()
+            .filter(|config| config.enabled)
+            .map(PluginAgent::new)
+            .collect();
+        plugins.sort_by(|a, b| a.name().cmp(b.name()));
+        plugins
+    }
+
+    /// Run the plugin with one request, returning its stdout
+    async fn call(&self, request: &serde_json::Value) -> Result<Vec<u8>> {
+        let plugin_err = |e: std::io::Error| {
+            AppError::External(format!(
+                "Failed to run plugin agent '{}': {}",
+                self.config.name, e
+            ))
+        };
+        let mut child = tokio::process::Command::new(&self.config.command)
+            .args(&self.config.args)
+            .envs(&self.config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(plugin_err)?;
+        // Dropping stdin once the request is written closes it
+        if let Some(mut stdin) = child.stdin.take() {
+            let line = format!("{}\n", request);
+            if let Err(e) = stdin.write_all(line.as_bytes()).await {
+                tracing::debug!("Plugin agent '{}' stopped reading: {}", self.config.name, e);
+            }
+        }
+
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let output = tokio::time::timeout(timeout, child.wait_with_output())
+            .await
+            .map_err(|_| {
+                AppError::External(format!(
+                    "Plugin agent '{}' timed out after {}s",
+                    self.config.name, self.config.timeout_secs
+                ))
+            })?
+            .map_err(plugin_err)?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !stderr.trim().is_empty() {
+            tracing::debug!(plugin = %self.config.name, "{}", stderr.trim());
+        }
+        if !output.status.success() {
+            return Err(AppError::External(format!(
+                "Plugin agent '{}' exited with {}",
+                self.config.name, output.status
+            )));
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// The response in a plugin's output: its last non-empty line
+fn parse_response(name: &str, stdout: &[u8]) -> Result<String> {
+    let stdout = String::from_utf8_lossy(stdout);
+    let line = stdout
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .ok_or_else(|| AppError::External(format!("Plugin agent '{}' gave no response", name)))?;
+    let response: RpcResponse = serde_json::from_str(line).map_err(|e| {
+        AppError::External(format!(
+            "Plugin agent '{}' gave an invalid response: {}",
+            name, e
+        ))
+    })?;
+    match (response.result, response.error) {
+        (_, Some(error)) => Err(AppError::External(format!(
+            "Plugin agent '{}' failed ({}): {}",
+            name, error.code, error.message
+        ))),
+        (Some(result), None) => Ok(result.response),
+        (None, None) => Err(AppError::External(format!(
+            "Plugin agent '{}' gave a response without a result",
+            name
+        ))),
+    }
+}
+
+#[async_trait]
+impl Agent for PluginAgent {
+    async fn execute(&self, input: &str, context: &AgentContext) -> Result<String> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "execute",
+            "params": {
+                "input": input,
+                "user_id": context.user_id,
+                "session_id": context.session_id,
+                "history": context.conversation_history,
+            },
+        });
+        let stdout = self.call(&request).await?;
+        parse_response(&self.config.name, &stdout)
+    }
+
+    fn system_prompt(&self) -> String {
+        String::new()
+    }
+
+    fn agent_type(&self) -> AgentType {
+        AgentType::Custom(self.config.name.clone())
+    }
+
+    fn description(&self) -> String {
+        self.config.description.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let stdout = b"loading model...\n{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{\"response\":\"Done\"}}\n\n";
+        assert_eq!(parse_response("summarizer", stdout).unwrap(), "Done");
+
+        let stdout = br#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"no input"}}"#;
+        let err = parse_response("summarizer", stdout).unwrap_err();
+        assert!(err.to_string().contains("no input"));
+
+        assert!(parse_response("summarizer", b"").is_err());
+        assert!(parse_response("summarizer", b"not json").is_err());
+    }
+}
@@ -0,0 +1,203 @@
+//! ReAct planning loop.
+//!
+//! Agents configured with `strategy = "react"` reason in explicit steps. On
+//! each step the model replies with a thought and either an action:
+//!
+//! ```text
+//! Thought: I need the current exchange rate.
+//! Action: web_search
+//! Action Input: {"query": "EUR to USD exchange rate"}
+//! ```
+//!
+//! which is run as a tool call and answered with an `Observation:` message,
+//! or a final answer:
+//!
+//! ```text
+//! Thought: I have everything I need.
+//! Final Answer: 100 EUR is about 108 USD.
+//! ```
+//!
+//! Every step is recorded as a [`ReactStep`] so the reasoning can be
+//! inspected in the response trace.
+
+use crate::types::ToolDefinition;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+const THOUGHT: &str = "Thought:";
+const ACTION: &str = "Action:";
+const ACTION_INPUT: &str = "Action Input:";
+const OBSERVATION: &str = "Observation:";
+const FINAL_ANSWER: &str = "Final Answer:";
+
+/// One Thought/Action/Observation step of a ReAct run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReactStep {
+    /// The model's reasoning for this step
+    pub thought: String,
+    /// Tool the model chose to run, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    /// Arguments the tool was run with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_input: Option<serde_json::Value>,
+    /// Result of the tool, as shown to the model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub observation: Option<String>,
+}
+
+/// A parsed model reply.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReactReply {
+    /// Run a tool and continue with its observation.
+    Act {
+        /// Reasoning leading to the action
+        thought: String,
+        /// Tool name
+        action: String,
+        /// Tool arguments
+        input: serde_json::Value,
+    },
+    /// Stop and answer the user.
+    Finish {
+        /// Final reasoning
+        thought: String,
+        /// Answer for the user
+        answer: String,
+    },
+}
+
+/// System prompt section describing the ReAct format and the available tools.
+pub fn instructions(tools: &[ToolDefinition]) -> String {
+    let tools = if tools.is_empty() {
+        "None. Reason step by step without actions and give a Final Answer.".to_string()
+    } else {
+        tools
+            .iter()
+            .map(|t| {
+                format!(
+                    "- {}: {} Arguments: {}",
+                    t.name, t.description, t.parameters
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    format!(
+        "Work through the request step by step. On each turn reply in exactly this format:\n\
+         {THOUGHT} <your reasoning about what to do next>\n\
+         {ACTION} <tool name>\n\
+         {ACTION_INPUT} <JSON arguments for the tool>\n\n\
+         You will then receive an \"{OBSERVATION}\" message with the tool's result. \
+         When you know the answer, reply instead with:\n\
+         {THOUGHT} <your final reasoning>\n\
+         {FINAL_ANSWER} <the answer for the user>\n\n\
+         Available tools:\n{tools}"
+    )
+}
+
+/// Message returning a tool's result to the model.
+pub fn observation(result: &str) -> String {
+    format!("{OBSERVATION} {result}")
+}
+
+/// Message asking for an answer once the step budget is used up.
+pub fn final_answer_request() -> String {
+    format!("You have used all available steps. Reply now with your {FINAL_ANSWER}")
+}
+
+/// Cut a reply at any observation the model invented for itself.
+pub fn strip_observation(output: &str) -> &str {
+    output
+        .find(&format!("\n{OBSERVATION}"))
+        .map_or(output, |i| &output[..i])
+        .trim()
+}
+
+/// Parse a model reply.
+///
+/// Replies that follow neither format are treated as the final answer.
+pub fn parse(output: &str) -> ReactReply {
+    let output = strip_observation(output);
+
+    if let Some(i) = output.find(FINAL_ANSWER) {
+        return ReactReply::Finish {
+            thought: thought(&output[..i]),
+            answer: output[i + FINAL_ANSWER.len()..].trim().to_string(),
+        };
+    }
+
+    if let Some(i) = output.find(ACTION) {
+        let rest = &output[i + ACTION.len()..];
+        let (action, input) = match rest.find(ACTION_INPUT) {
+            Some(j) => (&rest[..j], rest[j + ACTION_INPUT.len()..].trim()),
+            None => (rest, ""),
+        };
+        let action = action.trim().to_string();
+        if !action.is_empty() {
+            let input = if input.is_empty() {
+                serde_json::json!({})
+            } else {
+                serde_json::from_str(input)
+                    .unwrap_or_else(|_| serde_json::Value::String(input.to_string()))
+            };
+            return ReactReply::Act {
+                thought: thought(&output[..i]),
+                action,
+                input,
+            };
+        }
+    }
+
+    ReactReply::Finish {
+        thought: String::new(),
+        answer: output.to_string(),
+    }
+}
+
+fn thought(text: &str) -> String {
+    let text = text.trim();
+    text.strip_prefix(THOUGHT)
+        .unwrap_or(text)
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_action_ignores_invented_observation() {
+        let output = "Thought: I should add.\nAction: calculator\nAction Input: {\"expression\": \"2+2\"}\nObservation: 4";
+        assert_eq!(
+            parse(output),
+            ReactReply::Act {
+                thought: "I should add.".to_string(),
+                action: "calculator".to_string(),
+                input: serde_json::json!({"expression": "2+2"}),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_final_answer_and_free_text() {
+        let output = "Thought: Done.\nFinal Answer: It is 4.\nSee you.";
+        assert_eq!(
+            parse(output),
+            ReactReply::Finish {
+                thought: "Done.".to_string(),
+                answer: "It is 4.\nSee you.".to_string(),
+            }
+        );
+
+        assert_eq!(
+            parse("Just an answer"),
+            ReactReply::Finish {
+                thought: String::new(),
+                answer: "Just an answer".to_string(),
+            }
+        );
+    }
+}
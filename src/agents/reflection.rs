@@ -0,0 +1,167 @@
+//! Self-reflection on agent answers.
+//!
+//! Agents with `reflection` enabled have each draft answer critiqued before
+//! it is returned. A judge (the agent's own model, or a cheaper
+//! `judge_model`) reviews the draft against the user's message and either
+//! approves it or lists what to fix; the agent then revises the draft. This
+//! repeats until the judge approves or `max_rounds` critiques have been made.
+//!
+//! ```toml
+//! [agents.writer]
+//! model = "powerful"
+//! reflection = { enabled = true, max_rounds = 2, judge_model = "fast" }
+//! ```
+//!
+//! With `judge` naming one of the `[judges]`, drafts are scored against its
+//! rubric instead: a draft passes once it reaches the judge's pass score,
+//! and is otherwise revised by the judge's scores and feedback.
+
+use crate::llm::{Judge, LLMClient};
+use crate::types::Result;
+
+/// Reply a judge gives when a draft needs no changes.
+pub const APPROVED: &str = "APPROVED";
+
+/// Instructions for the judge reviewing a draft.
+const CRITIQUE_PROMPT: &str = "You review answers written by an AI assistant. \
+Check the answer against the user's message for factual errors, missing or \
+unanswered parts, unsupported claims, and unclear wording. If the answer needs \
+no changes, reply with exactly APPROVED. Otherwise list the problems concisely, \
+one per line, without rewriting the answer.";
+
+/// An agent's reflection settings, with the judge that writes critiques.
+pub struct Reflection {
+    max_rounds: usize,
+    judge: Option<Box<dyn LLMClient>>,
+    scorer: Option<Judge>,
+}
+
+impl Reflection {
+    /// Reflect for up to `max_rounds` rounds, judged by the agent's own model
+    pub fn new(max_rounds: usize) -> Self {
+        Self {
+            max_rounds: max_rounds.max(1),
+            judge: None,
+            scorer: None,
+        }
+    }
+
+    /// Have a different model write the critiques
+    pub fn with_judge(mut self, judge: Box<dyn LLMClient>) -> Self {
+        self.judge = Some(judge);
+        self
+    }
+
+    /// Score drafts with a rubric judge instead of asking for approval
+    pub fn with_scoring(mut self, scorer: Judge) -> Self {
+        self.scorer = Some(scorer);
+        self
+    }
+
+    /// Most critique/revise rounds per answer
+    pub fn max_rounds(&self) -> usize {
+        self.max_rounds
+    }
+
+    /// Critique a draft answer to `question`
+    ///
+    /// Uses the scoring judge if one is set, then the judge model, otherwise
+    /// `llm`. Returns `None` when the draft is approved.
+    pub async fn critique(
+        &self,
+        llm: &dyn LLMClient,
+        question: &str,
+        draft: &str,
+    ) -> Result<Option<String>> {
+        if let Some(scorer) = &self.scorer {
+            let judgment = scorer.judge(question, draft).await?;
+            return Ok((!judgment.passed).then(|| judgment.critique()));
+        }
+        let judge = self.judge.as_deref().unwrap_or(llm);
+        let output = judge
+            .generate_with_history(&critique_messages(question, draft))
+            .await?;
+        Ok(parse_critique(&output))
+    }
+}
+
+/// Prompt asking the judge to review `draft` as an answer to `question`.
+pub fn critique_messages(question: &str, draft: &str) -> Vec<(String, String)> {
+    vec![
+        ("system".to_string(), CRITIQUE_PROMPT.to_string()),
+        (
+            "user".to_string(),
+            format!(
+                "User's message:\n{}\n\nAnswer to review:\n{}",
+                question, draft
+            ),
+        ),
+    ]
+}
+
+/// Read a judge's reply: `None` if it approves the draft, otherwise the critique.
+///
+/// An empty reply counts as approval, so a judge that says nothing never
+/// triggers a revision.
+pub fn parse_critique(output: &str) -> Option<String> {
+    let output = output.trim();
+    let verdict = output.trim_matches(|c: char| !c.is_alphanumeric());
+    if verdict.is_empty() || verdict.eq_ignore_ascii_case(APPROVED) {
+        None
+    } else {
+        Some(output.to_string())
+    }
+}
+
+/// Message asking the agent to revise its last answer to address a critique.
+pub fn revision_request(critique: &str) -> String {
+    format!(
+        "A reviewer found these problems with your answer:\n{}\n\
+         Reply with the complete revised answer only.",
+        critique
+    )
+}
+
+/// The user's message a prompt answers: its last `user` message.
+pub fn question(messages: &[(String, String)]) -> &str {
+    messages
+        .iter()
+        .rev()
+        .find(|(role, _)| role == "user")
+        .map_or("", |(_, content)| content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_critique() {
+        assert_eq!(parse_critique("APPROVED"), None);
+        assert_eq!(parse_critique("  **Approved.**\n"), None);
+        assert_eq!(parse_critique(""), None);
+        assert_eq!(
+            parse_critique("- The date is wrong\n"),
+            Some("- The date is wrong".to_string())
+        );
+        assert!(parse_critique("Not approved: the date is wrong").is_some());
+    }
+
+    #[test]
+    fn test_critique_messages_and_question() {
+        let messages = vec![
+            ("system".to_string(), "Be helpful".to_string()),
+            ("user".to_string(), "When was Rust 1.0?".to_string()),
+            ("assistant".to_string(), "2015".to_string()),
+        ];
+        assert_eq!(question(&messages), "When was Rust 1.0?");
+        assert_eq!(question(&[]), "");
+
+        let prompt = critique_messages("When was Rust 1.0?", "2014");
+        assert_eq!(prompt[0].0, "system");
+        assert!(prompt[0].1.contains(APPROVED));
+        assert!(prompt[1].1.contains("When was Rust 1.0?"));
+        assert!(prompt[1].1.ends_with("2014"));
+        assert!(revision_request("Wrong year").contains("Wrong year"));
+    }
+}
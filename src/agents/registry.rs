@@ -10,12 +10,30 @@
 //! 2. TOON config (`config/agents/*.toon`) is checked second
 //!
 //! This allows TOML to override TOON configs for specific deployments.
+//!
+//! ## Custom Agents
+//!
+//! Agents implemented in code, or by plugins (see [`crate::agents::plugin`]),
+//! are registered with [`AgentRegistry::register_custom`]. A custom agent
+//! takes the place of a configured agent of the same name, and appears in
+//! chat routing, workflows and the agent list alongside the others.
 
+use crate::agents::approval::{ApprovalDecision, PausedRun};
 use crate::agents::configurable::ConfigurableAgent;
-use crate::llm::ProviderRegistry;
+use crate::agents::context::ContextBudget;
+use crate::agents::handoff::{self, HandoffOutcome, MAX_HANDOFFS};
+use crate::agents::hooks::{AgentHook, AgentHooks};
+use crate::agents::knowledge::KnowledgeBase;
+use crate::agents::limits::RunLimits;
+use crate::agents::reflection::Reflection;
+use crate::agents::Agent;
+use crate::llm::{GuardrailPipeline, Judge, ProviderRegistry, Sampling};
+use crate::rag::batcher::BatchEmbedder;
 use crate::tools::registry::ToolRegistry;
-use crate::types::{AgentType, AppError, Result};
-use crate::utils::toml_config::{AgentConfig, AresConfig};
+use crate::types::{AgentContext, AgentType, AppError, Result};
+use crate::utils::toml_config::{
+    AgentConfig, AgentStrategy, AresConfig, GuardrailsConfig, JudgeConfig, ModelPricing,
+};
 use crate::utils::toon_config::{DynamicConfigManager, ToonAgentConfig};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -33,6 +51,20 @@ pub struct AgentRegistry {
     tool_registry: Arc<ToolRegistry>,
     /// Optional TOON-based dynamic config manager for hot-reloadable agents
     dynamic_config: Option<Arc<DynamicConfigManager>>,
+    /// Guardrail policies available to agents
+    guardrails: GuardrailsConfig,
+    /// Middleware hooks attached to every agent created
+    hooks: AgentHooks,
+    /// Embedder for ranking memory facts by relevance
+    memory_embedder: Option<Arc<dyn BatchEmbedder>>,
+    /// Searches the RAG collections of agents with a `rag` section
+    knowledge: Option<Arc<dyn KnowledgeBase>>,
+    /// Token pricing keyed by model name, for agents' `max_cost`
+    pricing: HashMap<String, ModelPricing>,
+    /// Judges available to agents' reflection, keyed by name
+    judges: HashMap<String, JudgeConfig>,
+    /// Agents implemented in code or by plugins, keyed by name
+    custom: HashMap<String, Arc<dyn Agent>>,
 }
 
 impl AgentRegistry {
@@ -43,6 +75,13 @@ impl AgentRegistry {
             provider_registry,
             tool_registry,
             dynamic_config: None,
+            guardrails: GuardrailsConfig::default(),
+            hooks: AgentHooks::new(),
+            memory_embedder: None,
+            knowledge: None,
+            pricing: HashMap::new(),
+            judges: HashMap::new(),
+            custom: HashMap::new(),
         }
     }
 
@@ -57,6 +96,13 @@ impl AgentRegistry {
             provider_registry,
             tool_registry,
             dynamic_config: None,
+            guardrails: config.guardrails.clone(),
+            hooks: AgentHooks::new(),
+            memory_embedder: None,
+            knowledge: None,
+            pricing: config.budgets.pricing.clone(),
+            judges: config.judges.clone(),
+            custom: HashMap::new(),
         }
     }
 
@@ -72,6 +118,13 @@ impl AgentRegistry {
             provider_registry,
             tool_registry,
             dynamic_config: Some(dynamic_config),
+            guardrails: config.guardrails.clone(),
+            hooks: AgentHooks::new(),
+            memory_embedder: None,
+            knowledge: None,
+            pricing: config.budgets.pricing.clone(),
+            judges: config.judges.clone(),
+            custom: HashMap::new(),
         }
     }
 
@@ -80,11 +133,69 @@ impl AgentRegistry {
         self.dynamic_config = Some(dynamic_config);
     }
 
+    /// Set the guardrail policies available to agents
+    pub fn set_guardrails(&mut self, guardrails: GuardrailsConfig) {
+        self.guardrails = guardrails;
+    }
+
+    /// Register a middleware hook run by every agent this registry creates
+    ///
+    /// Hooks run in registration order; agents already created are unaffected.
+    pub fn register_hook(&mut self, hook: Arc<dyn AgentHook>) {
+        self.hooks.register(hook);
+    }
+
+    /// Get the middleware hooks attached to created agents
+    pub fn hooks(&self) -> &AgentHooks {
+        &self.hooks
+    }
+
+    /// Set the embedder agents use to rank memory facts by relevance
+    pub fn set_memory_embedder(&mut self, embedder: Arc<dyn BatchEmbedder>) {
+        self.memory_embedder = Some(embedder);
+    }
+
+    /// Get the embedder agents use to rank memory facts by relevance
+    pub fn memory_embedder(&self) -> Option<&Arc<dyn BatchEmbedder>> {
+        self.memory_embedder.as_ref()
+    }
+
+    /// Set what agents with a `rag` section search for knowledge passages
+    pub fn set_knowledge(&mut self, knowledge: Arc<dyn KnowledgeBase>) {
+        self.knowledge = Some(knowledge);
+    }
+
+    /// Get what agents with a `rag` section search for knowledge passages
+    pub fn knowledge(&self) -> Option<&Arc<dyn KnowledgeBase>> {
+        self.knowledge.as_ref()
+    }
+
     /// Register an agent configuration
     pub fn register(&mut self, name: &str, config: AgentConfig) {
         self.configs.insert(name.to_string(), config);
     }
 
+    /// Register an agent implemented in code, named by its agent type
+    ///
+    /// It replaces any custom agent of the same name, and takes the place of
+    /// a configured one.
+    pub fn register_custom(&mut self, agent: Arc<dyn Agent>) {
+        let name = agent.agent_type().as_str().to_string();
+        self.custom.insert(name, agent);
+    }
+
+    /// Get a custom agent by name
+    pub fn custom_agent(&self, name: &str) -> Option<Arc<dyn Agent>> {
+        self.custom.get(name).cloned()
+    }
+
+    /// Get all custom agents, sorted by name
+    pub fn custom_agents(&self) -> Vec<Arc<dyn Agent>> {
+        let mut agents: Vec<Arc<dyn Agent>> = self.custom.values().cloned().collect();
+        agents.sort_by(|a, b| a.agent_type().as_str().cmp(b.agent_type().as_str()));
+        agents
+    }
+
     /// Get an agent configuration by name (TOML only)
     ///
     /// Note: For lookups that include TOON, use `get_config_any` instead.
@@ -92,6 +203,16 @@ impl AgentRegistry {
         self.configs.get(name)
     }
 
+    /// Get an agent configuration by name from TOML or TOON config
+    ///
+    /// TOML takes precedence, as in [`create_agent`](Self::create_agent).
+    pub fn get_config_any(&self, name: &str) -> Option<AgentConfig> {
+        self.get_config(name).cloned().or_else(|| {
+            self.get_toon_config(name)
+                .map(|toon| Self::toon_to_agent_config(&toon))
+        })
+    }
+
     /// Get TOON agent config by name
     pub fn get_toon_config(&self, name: &str) -> Option<ToonAgentConfig> {
         self.dynamic_config.as_ref().and_then(|dc| dc.agent(name))
@@ -110,7 +231,7 @@ impl AgentRegistry {
             .unwrap_or(false)
     }
 
-    /// Get all agent names (from both TOML and TOON)
+    /// Get all agent names (from TOML, TOON and custom agents)
     pub fn agent_names(&self) -> Vec<String> {
         let mut names: Vec<String> = self.configs.keys().cloned().collect();
 
@@ -123,12 +244,18 @@ impl AgentRegistry {
             }
         }
 
+        for name in self.custom.keys() {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+
         names
     }
 
-    /// Check if an agent exists (in either TOML or TOON config)
+    /// Check if an agent exists (in TOML or TOON config, or as a custom agent)
     pub fn has_agent(&self, name: &str) -> bool {
-        self.has_toml_agent(name) || self.has_toon_agent(name)
+        self.has_toml_agent(name) || self.has_toon_agent(name) || self.custom.contains_key(name)
     }
 
     /// Convert ToonAgentConfig to AgentConfig for unified handling
@@ -139,6 +266,20 @@ impl AgentRegistry {
             tools: toon.tools.clone(),
             max_tool_iterations: toon.max_tool_iterations,
             parallel_tools: toon.parallel_tools,
+            guardrail_policy: toon.guardrail_policy.clone(),
+            handoffs: toon.handoffs.clone(),
+            strategy: toon.strategy,
+            output_schema: toon.output_schema.clone(),
+            memory: toon.memory.clone(),
+            tool_permissions: toon.tool_permissions.clone(),
+            answer_cache: toon.answer_cache.clone(),
+            reflection: toon.reflection.clone(),
+            limits: toon.limits,
+            personas: toon.personas.clone(),
+            requires_approval: toon.requires_approval,
+            bus: toon.bus.clone(),
+            parameter_limits: toon.parameter_limits,
+            rag: toon.rag.clone(),
             // Convert serde_json::Value to toml::Value
             // For extra fields we just convert to string representation
             extra: toon
@@ -174,15 +315,17 @@ impl AgentRegistry {
     /// 1. TOML config (`ares.toml` [agents.*])
     /// 2. TOON config (`config/agents/*.toon`)
     pub async fn create_agent(&self, name: &str) -> Result<ConfigurableAgent> {
-        // First check TOML config
+        let config = self.resolve_config(name)?;
+        self.create_agent_from_config(name, &config).await
+    }
+
+    /// Find an agent's configuration, checking TOML before TOON
+    fn resolve_config(&self, name: &str) -> Result<AgentConfig> {
         if let Some(config) = self.get_config(name) {
-            return self.create_agent_from_config(name, config).await;
+            return Ok(config.clone());
         }
-
-        // Then check TOON config
         if let Some(toon_config) = self.get_toon_config(name) {
-            let config = Self::toon_to_agent_config(&toon_config);
-            return self.create_agent_from_config(name, &config).await;
+            return Ok(Self::toon_to_agent_config(&toon_config));
         }
 
         Err(AppError::Configuration(format!(
@@ -196,11 +339,40 @@ impl AgentRegistry {
         &self,
         name: &str,
         config: &AgentConfig,
+    ) -> Result<ConfigurableAgent> {
+        self.create_agent_from_config_with_temperature(name, config, None)
+            .await
+    }
+
+    /// Create an agent from an AgentConfig, optionally overriding its model's temperature
+    pub async fn create_agent_from_config_with_temperature(
+        &self,
+        name: &str,
+        config: &AgentConfig,
+        temperature: Option<f32>,
+    ) -> Result<ConfigurableAgent> {
+        let sampling = Sampling {
+            temperature,
+            ..Sampling::default()
+        };
+        self.create_agent_from_config_with_sampling(name, config, sampling)
+            .await
+    }
+
+    /// Create an agent from an AgentConfig, optionally overriding its model's
+    /// sampling parameters and fixing its sampling seed
+    ///
+    /// A seeded agent passes the seed on to the agents it hands off to.
+    pub async fn create_agent_from_config_with_sampling(
+        &self,
+        name: &str,
+        config: &AgentConfig,
+        sampling: Sampling,
     ) -> Result<ConfigurableAgent> {
         // Create the LLM client for this agent's model
         let llm = self
             .provider_registry
-            .create_client_for_model(&config.model)
+            .create_client_for_model_with_sampling(&config.model, sampling)
             .await?;
 
         // Create a filtered tool registry with only the tools this agent can use
@@ -210,12 +382,208 @@ impl AgentRegistry {
             Some(Arc::clone(&self.tool_registry))
         };
 
-        Ok(ConfigurableAgent::new(
-            name,
-            config,
-            llm,
-            agent_tool_registry,
-        ))
+        let mut agent = ConfigurableAgent::new(name, config, llm, agent_tool_registry)
+            .with_hooks(self.hooks.clone())
+            .with_seed(sampling.seed);
+        if let Some(embedder) = &self.memory_embedder {
+            agent = agent.with_memory_embedder(Arc::clone(embedder));
+        }
+        if let Some(knowledge) = &self.knowledge {
+            agent = agent.with_knowledge(Arc::clone(knowledge));
+        }
+        let budget = self
+            .provider_registry
+            .get_model(&config.model)
+            .and_then(ContextBudget::from_model);
+        if let Some(budget) = budget {
+            agent = agent.with_context_budget(budget);
+        }
+        // Structured output is validated rather than reflected on
+        if config.reflection.enabled && config.output_schema.is_none() {
+            let mut reflection = Reflection::new(config.reflection.max_rounds);
+            if let Some(name) = &config.reflection.judge {
+                let judge = self.judges.get(name).ok_or_else(|| {
+                    AppError::Configuration(format!("Judge '{}' not found", name))
+                })?;
+                reflection = reflection
+                    .with_scoring(Judge::from_config(judge, &self.provider_registry).await?);
+            } else if let Some(model) = &config.reflection.judge_model {
+                reflection = reflection.with_judge(
+                    self.provider_registry
+                        .create_client_for_model(model)
+                        .await?,
+                );
+            }
+            agent = agent.with_reflection(reflection);
+        }
+        if let Some(pricing) = self.pricing.get(&config.model) {
+            agent = agent.with_limits(RunLimits::new(config.limits).with_pricing(*pricing));
+        }
+
+        match self.build_guardrails(name, config).await? {
+            Some(guardrails) => Ok(agent.with_guardrails(guardrails)),
+            None => Ok(agent),
+        }
+    }
+
+    /// Resolve the guardrail policy name for an agent
+    ///
+    /// Precedence: the explicit config, then the agent's TOML/TOON definition
+    /// (configs passed in from the database don't carry a policy), then
+    /// `guardrails.default_policy`.
+    pub fn guardrail_policy_for(&self, name: &str, config: &AgentConfig) -> Option<String> {
+        config
+            .guardrail_policy
+            .clone()
+            .or_else(|| {
+                self.configs
+                    .get(name)
+                    .and_then(|c| c.guardrail_policy.clone())
+            })
+            .or_else(|| self.get_toon_config(name).and_then(|c| c.guardrail_policy))
+            .or_else(|| self.guardrails.default_policy.clone())
+    }
+
+    /// Build the guardrail pipeline for an agent, if it has a policy
+    pub async fn build_guardrails(
+        &self,
+        name: &str,
+        config: &AgentConfig,
+    ) -> Result<Option<Arc<GuardrailPipeline>>> {
+        let Some(policy_name) = self.guardrail_policy_for(name, config) else {
+            return Ok(None);
+        };
+
+        let policy = self.guardrails.policies.get(&policy_name).ok_or_else(|| {
+            AppError::Configuration(format!(
+                "Guardrail policy '{}' for agent '{}' does not exist",
+                policy_name, name
+            ))
+        })?;
+
+        let judge = match (&policy.judge_model, policy.blocked_topics.is_empty()) {
+            (Some(model), false) => Some(Arc::from(
+                self.provider_registry
+                    .create_client_for_model(model)
+                    .await?,
+            )),
+            _ => None,
+        };
+
+        let pipeline = GuardrailPipeline::from_policy(policy, judge)?;
+        if pipeline.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Arc::new(pipeline)))
+    }
+
+    /// Run an agent, following any handoffs it makes to other registered agents
+    ///
+    /// Each receiving agent gets the original input together with the handoff
+    /// reason and summary. After [`MAX_HANDOFFS`] transfers the receiving agent
+    /// can no longer hand off.
+    pub async fn execute_with_handoffs(
+        &self,
+        agent: ConfigurableAgent,
+        input: &str,
+        context: &AgentContext,
+    ) -> Result<HandoffOutcome> {
+        self.run_with_handoffs(agent, input, None, context).await
+    }
+
+    /// Continue a run that paused for approval, following any handoffs the
+    /// agent makes afterwards
+    ///
+    /// `agent` is the paused agent and `input` the original input of the
+    /// run it was part of.
+    pub async fn resume_with_handoffs(
+        &self,
+        agent: ConfigurableAgent,
+        input: &str,
+        paused: PausedRun,
+        decision: ApprovalDecision,
+        context: &AgentContext,
+    ) -> Result<HandoffOutcome> {
+        self.run_with_handoffs(agent, input, Some((paused, decision)), context)
+            .await
+    }
+
+    async fn run_with_handoffs(
+        &self,
+        agent: ConfigurableAgent,
+        input: &str,
+        mut resume: Option<(PausedRun, ApprovalDecision)>,
+        context: &AgentContext,
+    ) -> Result<HandoffOutcome> {
+        let mut agent = agent;
+        let mut agent_input = input.to_string();
+        let mut handoffs = Vec::new();
+        let mut trace = Vec::new();
+        let mut tool_calls = Vec::new();
+
+        loop {
+            let run = match resume.take() {
+                Some((paused, decision)) => agent.resume_traced(paused, decision, context).await?,
+                None => agent.execute_traced(&agent_input, context).await?,
+            };
+            trace.extend(run.steps);
+            tool_calls.extend(
+                run.tool_calls
+                    .into_iter()
+                    .map(|record| (agent.name().to_string(), record)),
+            );
+            // A run stopped by a limit answers with what it has
+            let next = match (&run.limit_exceeded, &run.paused) {
+                (None, None) => agent.take_handoff(&run.response),
+                _ => None,
+            };
+            let Some(next) = next else {
+                return Ok(HandoffOutcome {
+                    response: run.response,
+                    agent: agent.name().to_string(),
+                    handoffs,
+                    trace,
+                    tool_calls,
+                    limit_exceeded: run.limit_exceeded,
+                    paused: run.paused,
+                    sources: run.sources,
+                });
+            };
+
+            if !self.has_agent(&next.to) {
+                return Err(AppError::Configuration(format!(
+                    "Agent '{}' handed off to '{}', which is not a registered agent",
+                    next.from, next.to
+                )));
+            }
+            tracing::info!(
+                "Agent '{}' handed off to '{}': {}",
+                next.from,
+                next.to,
+                next.reason
+            );
+
+            let config = self.resolve_config(&next.to)?;
+            let checkpoints = agent.approval_checkpoints();
+            let journal = agent.journal();
+            agent = self
+                .create_agent_from_config_with_sampling(
+                    &next.to,
+                    &config,
+                    Sampling::seeded(agent.seed()),
+                )
+                .await?
+                .with_approval_checkpoints(checkpoints);
+            if let Some(journal) = journal {
+                agent = agent.with_journal(journal);
+            }
+            if handoffs.len() + 1 >= MAX_HANDOFFS {
+                agent = agent.with_handoffs(Vec::new());
+            }
+            agent_input = handoff::handoff_input(&next, input);
+            handoffs.push(next);
+        }
     }
 
     /// Create an agent instance for a specific AgentType
@@ -251,6 +619,40 @@ impl AgentRegistry {
             .unwrap_or_default()
     }
 
+    /// Get the agents an agent may hand off to (checks both TOML and TOON)
+    pub fn get_agent_handoffs(&self, name: &str) -> Vec<String> {
+        // Check TOML first
+        if let Some(config) = self.configs.get(name) {
+            return config.handoffs.clone();
+        }
+        // Check TOON
+        self.get_toon_config(name)
+            .map(|c| c.handoffs)
+            .unwrap_or_default()
+    }
+
+    /// Get the strategy of an agent (checks both TOML and TOON)
+    pub fn get_agent_strategy(&self, name: &str) -> AgentStrategy {
+        // Check TOML first
+        if let Some(config) = self.configs.get(name) {
+            return config.strategy;
+        }
+        // Check TOON
+        self.get_toon_config(name)
+            .map(|c| c.strategy)
+            .unwrap_or_default()
+    }
+
+    /// Get the output schema of an agent (checks both TOML and TOON)
+    pub fn get_agent_output_schema(&self, name: &str) -> Option<serde_json::Value> {
+        // Check TOML first
+        if let Some(config) = self.configs.get(name) {
+            return config.output_schema.clone();
+        }
+        // Check TOON
+        self.get_toon_config(name).and_then(|c| c.output_schema)
+    }
+
     /// Get the system prompt for an agent (checks both TOML and TOON)
     pub fn get_agent_system_prompt(&self, name: &str) -> Option<String> {
         // Check TOML first
@@ -268,6 +670,13 @@ pub struct AgentRegistryBuilder {
     provider_registry: Option<Arc<ProviderRegistry>>,
     tool_registry: Option<Arc<ToolRegistry>>,
     dynamic_config: Option<Arc<DynamicConfigManager>>,
+    guardrails: GuardrailsConfig,
+    hooks: AgentHooks,
+    memory_embedder: Option<Arc<dyn BatchEmbedder>>,
+    knowledge: Option<Arc<dyn KnowledgeBase>>,
+    pricing: HashMap<String, ModelPricing>,
+    judges: HashMap<String, JudgeConfig>,
+    custom: HashMap<String, Arc<dyn Agent>>,
 }
 
 impl AgentRegistryBuilder {
@@ -278,6 +687,13 @@ impl AgentRegistryBuilder {
             provider_registry: None,
             tool_registry: None,
             dynamic_config: None,
+            guardrails: GuardrailsConfig::default(),
+            hooks: AgentHooks::new(),
+            memory_embedder: None,
+            knowledge: None,
+            pricing: HashMap::new(),
+            judges: HashMap::new(),
+            custom: HashMap::new(),
         }
     }
 
@@ -299,15 +715,61 @@ impl AgentRegistryBuilder {
         self
     }
 
+    /// Set the guardrail policies available to agents
+    pub fn with_guardrails(mut self, guardrails: GuardrailsConfig) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+
+    /// Register a middleware hook run by every agent
+    pub fn with_hook(mut self, hook: Arc<dyn AgentHook>) -> Self {
+        self.hooks.register(hook);
+        self
+    }
+
+    /// Set the embedder agents use to rank memory facts by relevance
+    pub fn with_memory_embedder(mut self, embedder: Arc<dyn BatchEmbedder>) -> Self {
+        self.memory_embedder = Some(embedder);
+        self
+    }
+
+    /// Set what agents with a `rag` section search for knowledge passages
+    pub fn with_knowledge(mut self, knowledge: Arc<dyn KnowledgeBase>) -> Self {
+        self.knowledge = Some(knowledge);
+        self
+    }
+
+    /// Set the token pricing, keyed by model name, used for agents' `max_cost`
+    pub fn with_pricing(mut self, pricing: HashMap<String, ModelPricing>) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// Set the judges, keyed by name, available to agents' reflection
+    pub fn with_judges(mut self, judges: HashMap<String, JudgeConfig>) -> Self {
+        self.judges = judges;
+        self
+    }
+
     /// Add an agent configuration
     pub fn with_agent(mut self, name: &str, config: AgentConfig) -> Self {
         self.configs.insert(name.to_string(), config);
         self
     }
 
+    /// Add an agent implemented in code, named by its agent type
+    pub fn with_custom_agent(mut self, agent: Arc<dyn Agent>) -> Self {
+        let name = agent.agent_type().as_str().to_string();
+        self.custom.insert(name, agent);
+        self
+    }
+
     /// Load agent configurations from TOML config
     pub fn from_config(mut self, config: &AresConfig) -> Self {
         self.configs = config.agents.clone();
+        self.guardrails = config.guardrails.clone();
+        self.pricing = config.budgets.pricing.clone();
+        self.judges = config.judges.clone();
         self
     }
 
@@ -326,6 +788,13 @@ impl AgentRegistryBuilder {
             provider_registry,
             tool_registry,
             dynamic_config: self.dynamic_config,
+            guardrails: self.guardrails,
+            hooks: self.hooks,
+            memory_embedder: self.memory_embedder,
+            knowledge: self.knowledge,
+            pricing: self.pricing,
+            judges: self.judges,
+            custom: self.custom,
         })
     }
 }
@@ -361,6 +830,9 @@ mod tests {
                 top_p: None,
                 frequency_penalty: None,
                 presence_penalty: None,
+                context_window: None,
+                summarize_overflow: true,
+                max_continuations: 2,
             },
         );
         Arc::new(registry)
@@ -374,6 +846,7 @@ mod tests {
         assert_eq!(AgentRegistry::type_to_name(&AgentType::Invoice), "invoice");
         assert_eq!(AgentRegistry::type_to_name(&AgentType::Sales), "sales");
         assert_eq!(AgentRegistry::type_to_name(&AgentType::Finance), "finance");
+        assert_eq!(AgentRegistry::type_to_name(&AgentType::Debate), "debate");
         assert_eq!(
             AgentRegistry::type_to_name(&AgentType::Orchestrator),
             "orchestrator"
@@ -392,6 +865,20 @@ mod tests {
             tools: vec![],
             max_tool_iterations: 5,
             parallel_tools: false,
+            guardrail_policy: None,
+            handoffs: Vec::new(),
+            strategy: Default::default(),
+            output_schema: None,
+            memory: Default::default(),
+            tool_permissions: Default::default(),
+            answer_cache: Default::default(),
+            reflection: Default::default(),
+            limits: Default::default(),
+            personas: Default::default(),
+            requires_approval: false,
+            bus: Default::default(),
+            parameter_limits: Default::default(),
+            rag: None,
             extra: HashMap::new(),
         };
 
@@ -417,6 +904,20 @@ mod tests {
                 tools: vec![],
                 max_tool_iterations: 10,
                 parallel_tools: false,
+                guardrail_policy: None,
+                handoffs: Vec::new(),
+                strategy: Default::default(),
+                output_schema: None,
+                memory: Default::default(),
+                tool_permissions: Default::default(),
+                answer_cache: Default::default(),
+                reflection: Default::default(),
+                limits: Default::default(),
+                personas: Default::default(),
+                requires_approval: false,
+                bus: Default::default(),
+                parameter_limits: Default::default(),
+                rag: None,
                 extra: HashMap::new(),
             },
         );
@@ -429,6 +930,20 @@ mod tests {
                 tools: vec![],
                 max_tool_iterations: 10,
                 parallel_tools: false,
+                guardrail_policy: None,
+                handoffs: Vec::new(),
+                strategy: Default::default(),
+                output_schema: None,
+                memory: Default::default(),
+                tool_permissions: Default::default(),
+                answer_cache: Default::default(),
+                reflection: Default::default(),
+                limits: Default::default(),
+                personas: Default::default(),
+                requires_approval: false,
+                bus: Default::default(),
+                parameter_limits: Default::default(),
+                rag: None,
                 extra: HashMap::new(),
             },
         );
@@ -453,6 +968,20 @@ mod tests {
                 tools: vec![],
                 max_tool_iterations: 10,
                 parallel_tools: false,
+                guardrail_policy: None,
+                handoffs: Vec::new(),
+                strategy: Default::default(),
+                output_schema: None,
+                memory: Default::default(),
+                tool_permissions: Default::default(),
+                answer_cache: Default::default(),
+                reflection: Default::default(),
+                limits: Default::default(),
+                personas: Default::default(),
+                requires_approval: false,
+                bus: Default::default(),
+                parameter_limits: Default::default(),
+                rag: None,
                 extra: HashMap::new(),
             },
         );
@@ -478,6 +1007,20 @@ mod tests {
                 tools: vec!["calculator".to_string(), "web_search".to_string()],
                 max_tool_iterations: 10,
                 parallel_tools: false,
+                guardrail_policy: None,
+                handoffs: Vec::new(),
+                strategy: Default::default(),
+                output_schema: None,
+                memory: Default::default(),
+                tool_permissions: Default::default(),
+                answer_cache: Default::default(),
+                reflection: Default::default(),
+                limits: Default::default(),
+                personas: Default::default(),
+                requires_approval: false,
+                bus: Default::default(),
+                parameter_limits: Default::default(),
+                rag: None,
                 extra: HashMap::new(),
             },
         );
@@ -490,6 +1033,20 @@ mod tests {
                 tools: vec![],
                 max_tool_iterations: 10,
                 parallel_tools: false,
+                guardrail_policy: None,
+                handoffs: Vec::new(),
+                strategy: Default::default(),
+                output_schema: None,
+                memory: Default::default(),
+                tool_permissions: Default::default(),
+                answer_cache: Default::default(),
+                reflection: Default::default(),
+                limits: Default::default(),
+                personas: Default::default(),
+                requires_approval: false,
+                bus: Default::default(),
+                parameter_limits: Default::default(),
+                rag: None,
                 extra: HashMap::new(),
             },
         );
@@ -525,6 +1082,20 @@ mod tests {
                     tools: vec![],
                     max_tool_iterations: 5,
                     parallel_tools: false,
+                    guardrail_policy: None,
+                    handoffs: Vec::new(),
+                    strategy: Default::default(),
+                    output_schema: None,
+                    memory: Default::default(),
+                    tool_permissions: Default::default(),
+                    answer_cache: Default::default(),
+                    reflection: Default::default(),
+                    limits: Default::default(),
+                    personas: Default::default(),
+                    requires_approval: false,
+                    bus: Default::default(),
+                    parameter_limits: Default::default(),
+                    rag: None,
                     extra: HashMap::new(),
                 },
             )
@@ -0,0 +1,773 @@
+//! Agents hosted by other systems, called over the A2A protocol.
+//!
+//! Each entry of `[remote_agents]` is an agent served by another A2A server,
+//! registered at startup under its key:
+//!
+//! ```toml
+//! [remote_agents.billing]
+//! url = "https://billing.example.com/a2a"
+//! description = "Answers questions about invoices and refunds"
+//! api_key_env = "BILLING_AGENT_KEY"
+//! streaming = true
+//! ```
+//!
+//! The agent is registered as a custom agent (see
+//! [`AgentRegistry::register_custom`](crate::agents::AgentRegistry::register_custom)),
+//! so it can be chatted with, routed to and used in workflows like any other,
+//! including as a debate panelist or fallback agent run alongside local ones.
+//!
+//! ## Protocol
+//!
+//! A run sends the input as a text message with the JSON-RPC method
+//! `message/send`. The agent answers with a message, or with a task that is
+//! polled with `tasks/get` until it ends. The response is the text of the
+//! task's artifacts (`data` parts are rendered as JSON), or of its status
+//! message when it has none:
+//!
+//! - `completed`: the run succeeds.
+//! - `input-required`, `auth-required`: the run succeeds with the agent's
+//!   question, and the next run of the same session continues the task.
+//! - `failed`, `canceled`, `rejected`: the run fails.
+//!
+//! With `streaming = true`, streamed runs use `message/stream` instead, and
+//! the text of each artifact update is streamed as it arrives. A stream that
+//! ends before the task does falls back to polling.
+//!
+//! With `service_token_scopes`, each request also carries a service token
+//! (see [`crate::auth::service_tokens`]) for the agent's name, the user of
+//! the run and those scopes, so the agent can check who calls it without a
+//! shared key.
+//!
+//! Each session keeps the agent's `contextId`, so the remote agent sees one
+//! conversation. Requests that fail to connect or get a 429 or 5xx status
+//! are retried `retries` times with exponential backoff, and a run gives up
+//! after `timeout_secs`, polling included.
+
+use crate::agents::{Agent, AgentEvent, AgentEventStream};
+use crate::auth::service_tokens::ServiceTokens;
+use crate::types::{AgentContext, AgentType, AppError, Result};
+use crate::utils::toml_config::{AresConfig, RemoteAgentConfig};
+use async_trait::async_trait;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Sessions whose remote context is remembered, per agent
+const MAX_SESSIONS: usize = 1024;
+
+/// Delay before the first retry, doubled for each following one
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Delay between polls of a running task
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An agent served by a remote A2A server.
+pub struct RemoteAgent {
+    name: String,
+    config: RemoteAgentConfig,
+    client: reqwest::Client,
+    service_tokens: Option<Arc<ServiceTokens>>,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+/// What a session continues on the remote side
+#[derive(Debug, Clone, Default)]
+struct Session {
+    context_id: Option<String>,
+    /// Task waiting for the user's input
+    task_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    #[serde(default)]
+    result: Option<Reply>,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// A result of `message/send` or `tasks/get`, or a `message/stream` event
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+enum Reply {
+    Message(A2aMessage),
+    Task(Task),
+    StatusUpdate(StatusUpdate),
+    ArtifactUpdate(ArtifactUpdate),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct A2aMessage {
+    #[serde(default)]
+    parts: Vec<Part>,
+    #[serde(default)]
+    context_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+enum Part {
+    Text {
+        text: String,
+    },
+    Data {
+        data: Value,
+    },
+    /// Files aren't part of the response text
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Task {
+    id: String,
+    #[serde(default)]
+    context_id: Option<String>,
+    status: TaskStatus,
+    #[serde(default)]
+    artifacts: Vec<Artifact>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TaskStatus {
+    state: TaskState,
+    #[serde(default)]
+    message: Option<A2aMessage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum TaskState {
+    Submitted,
+    Working,
+    InputRequired,
+    AuthRequired,
+    Completed,
+    Canceled,
+    Failed,
+    Rejected,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize)]
+struct Artifact {
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StatusUpdate {
+    task_id: String,
+    #[serde(default)]
+    context_id: Option<String>,
+    status: TaskStatus,
+    #[serde(default, rename = "final")]
+    is_final: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactUpdate {
+    task_id: String,
+    #[serde(default)]
+    context_id: Option<String>,
+    artifact: Artifact,
+    #[serde(default)]
+    append: bool,
+}
+
+/// Text of `parts`, one paragraph per part
+fn text(parts: &[Part]) -> String {
+    parts
+        .iter()
+        .filter_map(|part| match part {
+            Part::Text { text } => Some(text.clone()),
+            Part::Data { data } => Some(data.to_string()),
+            Part::Other => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Progress of a run, built up from the agent's replies
+#[derive(Debug, Default)]
+struct Run {
+    response: String,
+    context_id: Option<String>,
+    task_id: Option<String>,
+    state: Option<TaskState>,
+    done: bool,
+}
+
+impl Run {
+    /// Apply a reply of agent `name`, returning the text it adds to the
+    /// response
+    fn apply(&mut self, name: &str, reply: Reply) -> Result<String> {
+        match reply {
+            Reply::Message(message) => {
+                self.context_id = message.context_id.or(self.context_id.take());
+                self.done = true;
+                Ok(self.append(text(&message.parts), "\n"))
+            }
+            Reply::Task(task) => {
+                self.context_id = task.context_id.or(self.context_id.take());
+                self.task_id = Some(task.id);
+                let artifacts: Vec<String> =
+                    task.artifacts.iter().map(|a| text(&a.parts)).collect();
+                let artifacts = artifacts.join("\n");
+                // Polled tasks repeat the artifacts seen so far
+                let delta = match artifacts.strip_prefix(self.response.as_str()) {
+                    Some(delta) => delta.to_string(),
+                    None if artifacts.is_empty() => String::new(),
+                    None => artifacts.clone(),
+                };
+                if !artifacts.is_empty() {
+                    self.response = artifacts;
+                }
+                let status = self.status(name, task.status)?;
+                Ok(delta + &status)
+            }
+            Reply::StatusUpdate(update) => {
+                self.context_id = update.context_id.or(self.context_id.take());
+                self.task_id = Some(update.task_id);
+                let delta = self.status(name, update.status)?;
+                self.done |= update.is_final;
+                Ok(delta)
+            }
+            Reply::ArtifactUpdate(update) => {
+                self.context_id = update.context_id.or(self.context_id.take());
+                self.task_id = Some(update.task_id);
+                let separator = if update.append { "" } else { "\n" };
+                Ok(self.append(text(&update.artifact.parts), separator))
+            }
+        }
+    }
+
+    /// Apply a task status, returning the text it adds to the response
+    fn status(&mut self, name: &str, status: TaskStatus) -> Result<String> {
+        self.state = Some(status.state);
+        let message = status.message.map(|m| text(&m.parts)).unwrap_or_default();
+        match status.state {
+            TaskState::Completed => {
+                self.done = true;
+                // The status message only stands in for missing artifacts
+                match self.response.is_empty() {
+                    true => Ok(self.append(message, "\n")),
+                    false => Ok(String::new()),
+                }
+            }
+            TaskState::InputRequired | TaskState::AuthRequired => {
+                self.done = true;
+                Ok(self.append(message, "\n\n"))
+            }
+            TaskState::Failed | TaskState::Canceled | TaskState::Rejected => {
+                let state = match status.state {
+                    TaskState::Failed => "failed",
+                    TaskState::Canceled => "was canceled",
+                    _ => "was rejected",
+                };
+                Err(AppError::External(match message.is_empty() {
+                    true => format!("Task of remote agent '{}' {}", name, state),
+                    false => format!("Task of remote agent '{}' {}: {}", name, state, message),
+                }))
+            }
+            TaskState::Submitted | TaskState::Working | TaskState::Unknown => Ok(String::new()),
+        }
+    }
+
+    /// Append `text` to the response, after `separator` unless it's the
+    /// first text, returning what was appended
+    fn append(&mut self, text: String, separator: &str) -> String {
+        if text.is_empty() {
+            return text;
+        }
+        let delta = match self.response.is_empty() {
+            true => text,
+            false => format!("{}{}", separator, text),
+        };
+        self.response.push_str(&delta);
+        delta
+    }
+
+    /// What the session continues with the next run
+    fn session(&self) -> Session {
+        let waiting = matches!(
+            self.state,
+            Some(TaskState::InputRequired | TaskState::AuthRequired)
+        );
+        Session {
+            context_id: self.context_id.clone(),
+            task_id: self.task_id.clone().filter(|_| waiting),
+        }
+    }
+}
+
+/// A JSON-RPC request
+fn rpc_request(method: &str, params: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": uuid::Uuid::new_v4().to_string(),
+        "method": method,
+        "params": params,
+    })
+}
+
+/// The reply in a JSON-RPC response of agent `name`
+fn parse_reply(name: &str, body: &str) -> Result<Reply> {
+    let response: RpcResponse = serde_json::from_str(body).map_err(|e| {
+        AppError::External(format!(
+            "Remote agent '{}' gave an invalid response: {}",
+            name, e
+        ))
+    })?;
+    match (response.result, response.error) {
+        (_, Some(error)) => Err(AppError::External(format!(
+            "Remote agent '{}' failed ({}): {}",
+            name, error.code, error.message
+        ))),
+        (Some(reply), None) => Ok(reply),
+        (None, None) => Err(AppError::External(format!(
+            "Remote agent '{}' gave a response without a result",
+            name
+        ))),
+    }
+}
+
+impl RemoteAgent {
+    /// Create the agent `name`, served at the endpoint of `config`
+    pub fn new(name: impl Into<String>, config: RemoteAgentConfig) -> Self {
+        Self {
+            name: name.into(),
+            config,
+            client: reqwest::Client::new(),
+            service_tokens: None,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sign the service tokens of `service_token_scopes` with `tokens`
+    pub fn with_service_tokens(mut self, tokens: Arc<ServiceTokens>) -> Self {
+        self.service_tokens = Some(tokens);
+        self
+    }
+
+    /// Name of the agent
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The agents of `[remote_agents]`, sorted by name
+    pub fn from_config(
+        config: &AresConfig,
+        service_tokens: &Arc<ServiceTokens>,
+    ) -> Vec<RemoteAgent> {
+        let mut agents: Vec<RemoteAgent> = config
+            .remote_agents
+            .iter()
+            .map(|(name, remote)| {
+                RemoteAgent::new(name, remote.clone())
+                    .with_service_tokens(Arc::clone(service_tokens))
+            })
+            .collect();
+        agents.sort_by(|a, b| a.name().cmp(b.name()));
+        agents
+    }
+
+    fn timeout_error(&self) -> AppError {
+        AppError::External(format!(
+            "Remote agent '{}' timed out after {}s",
+            self.name, self.config.timeout_secs
+        ))
+    }
+
+    /// Key sent with each request, if the agent needs one
+    fn api_key(&self) -> Result<Option<String>> {
+        let Some(env) = &self.config.api_key_env else {
+            return Ok(None);
+        };
+        std::env::var(env).map(Some).map_err(|_| {
+            AppError::Configuration(format!(
+                "Environment variable '{}' for remote agent '{}' is not set",
+                env, self.name
+            ))
+        })
+    }
+
+    /// Service token for a request made for `user_id`, if the agent takes one
+    fn service_token(&self, user_id: &str) -> Result<Option<String>> {
+        if self.config.service_token_scopes.is_empty() {
+            return Ok(None);
+        }
+        let tokens = self.service_tokens.as_ref().ok_or_else(|| {
+            AppError::Configuration(format!(
+                "Remote agent '{}' needs service tokens, but none are set up",
+                self.name
+            ))
+        })?;
+        tokens
+            .mint(&self.name, &self.config.service_token_scopes, Some(user_id))
+            .map(Some)
+    }
+
+    /// Send a JSON-RPC request for `user_id`, retrying transient failures
+    async fn send(
+        &self,
+        request: &Value,
+        stream: bool,
+        user_id: &str,
+    ) -> Result<reqwest::Response> {
+        let api_key = self.api_key()?;
+        let mut attempt = 0;
+        loop {
+            let mut builder = self.client.post(&self.config.url).json(request);
+            if stream {
+                builder = builder.header(reqwest::header::ACCEPT, "text/event-stream");
+            }
+            if let Some(key) = &api_key {
+                builder = match &self.config.api_key_header {
+                    Some(header) => builder.header(header.as_str(), key),
+                    None => builder.bearer_auth(key),
+                };
+            }
+            // Minted per attempt, so retries don't send an expiring token
+            if let Some(token) = self.service_token(user_id)? {
+                builder = builder.bearer_auth(token);
+            }
+            let result = builder.send().await;
+            let retryable = match &result {
+                Ok(response) => {
+                    response.status() == StatusCode::TOO_MANY_REQUESTS
+                        || response.status().is_server_error()
+                }
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+            if retryable && attempt < self.config.retries {
+                let delay = RETRY_DELAY * 2u32.pow(attempt);
+                tracing::debug!(
+                    "Retrying remote agent '{}' in {:?} (attempt {})",
+                    self.name,
+                    delay,
+                    attempt + 1
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                continue;
+            }
+
+            let response = result.map_err(|e| {
+                AppError::External(format!(
+                    "Failed to reach remote agent '{}': {}",
+                    self.name, e
+                ))
+            })?;
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                return Err(AppError::External(format!(
+                    "Remote agent '{}' returned {}: {}",
+                    self.name, status, body
+                )));
+            }
+            return Ok(response);
+        }
+    }
+
+    /// Call a JSON-RPC method for `user_id`, returning its reply
+    async fn call(&self, method: &str, params: Value, user_id: &str) -> Result<Reply> {
+        let response = self
+            .send(&rpc_request(method, params), false, user_id)
+            .await?;
+        let body = response.text().await.map_err(|e| {
+            AppError::External(format!(
+                "Failed to read the response of remote agent '{}': {}",
+                self.name, e
+            ))
+        })?;
+        parse_reply(&self.name, &body)
+    }
+
+    /// Parameters sending `input` in the session of `context`
+    fn send_params(&self, input: &str, context: &AgentContext) -> Value {
+        let session = self
+            .sessions
+            .lock()
+            .get(&context.session_id)
+            .cloned()
+            .unwrap_or_default();
+        let mut message = json!({
+            "kind": "message",
+            "role": "user",
+            "messageId": uuid::Uuid::new_v4().to_string(),
+            "parts": [{"kind": "text", "text": input}],
+        });
+        if let Some(context_id) = session.context_id {
+            message["contextId"] = json!(context_id);
+        }
+        if let Some(task_id) = session.task_id {
+            message["taskId"] = json!(task_id);
+        }
+        json!({
+            "message": message,
+            "configuration": {
+                "blocking": true,
+                "acceptedOutputModes": ["text/plain", "application/json"],
+            },
+        })
+    }
+
+    /// Poll the run's task until it ends
+    async fn poll(&self, run: &mut Run, user_id: &str) -> Result<()> {
+        while !run.done {
+            let Some(task_id) = run.task_id.clone() else {
+                break;
+            };
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let reply = self
+                .call("tasks/get", json!({"id": task_id}), user_id)
+                .await?;
+            run.apply(&self.name, reply)?;
+        }
+        Ok(())
+    }
+
+    /// Remember what the session continues with
+    fn remember(&self, session_id: &str, run: &Run) {
+        let mut sessions = self.sessions.lock();
+        if sessions.len() >= MAX_SESSIONS && !sessions.contains_key(session_id) {
+            if let Some(oldest) = sessions.keys().next().cloned() {
+                sessions.remove(&oldest);
+            }
+        }
+        sessions.insert(session_id.to_string(), run.session());
+    }
+
+    async fn run(&self, input: &str, context: &AgentContext) -> Result<String> {
+        let reply = self
+            .call(
+                "message/send",
+                self.send_params(input, context),
+                &context.user_id,
+            )
+            .await?;
+        let mut run = Run::default();
+        run.apply(&self.name, reply)?;
+        self.poll(&mut run, &context.user_id).await?;
+        self.remember(&context.session_id, &run);
+        Ok(run.response)
+    }
+}
+
+#[async_trait]
+impl Agent for RemoteAgent {
+    async fn execute(&self, input: &str, context: &AgentContext) -> Result<String> {
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        tokio::time::timeout(timeout, self.run(input, context))
+            .await
+            .map_err(|_| self.timeout_error())?
+    }
+
+    async fn execute_stream<'a>(
+        &'a self,
+        input: &'a str,
+        context: &'a AgentContext,
+    ) -> Result<AgentEventStream<'a>> {
+        if !self.config.streaming {
+            let response = self.execute(input, context).await?;
+            return Ok(Box::pin(futures::stream::iter([
+                Ok(AgentEvent::Token {
+                    delta: response.clone(),
+                }),
+                Ok(AgentEvent::Final {
+                    response,
+                    sources: Vec::new(),
+                }),
+            ])));
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(self.config.timeout_secs);
+        let request = rpc_request("message/stream", self.send_params(input, context));
+        let response =
+            tokio::time::timeout_at(deadline, self.send(&request, true, &context.user_id))
+                .await
+                .map_err(|_| self.timeout_error())??;
+
+        let events = async_stream::stream! {
+            let mut bytes = response.bytes_stream();
+            let mut buffer = String::new();
+            let mut run = Run::default();
+            while !run.done {
+                let chunk = match tokio::time::timeout_at(deadline, bytes.next()).await {
+                    Ok(Some(Ok(chunk))) => chunk,
+                    Ok(None) => break,
+                    Ok(Some(Err(e))) => {
+                        yield Err(AppError::External(format!(
+                            "Stream of remote agent '{}' failed: {}",
+                            self.name, e
+                        )));
+                        return;
+                    }
+                    Err(_) => {
+                        yield Err(self.timeout_error());
+                        return;
+                    }
+                };
+                buffer.push_str(&String::from_utf8_lossy(&chunk));
+
+                while let Some(pos) = buffer.find('\n') {
+                    let line: String = buffer.drain(..=pos).collect();
+                    let Some(data) = line.trim().strip_prefix("data:") else {
+                        continue;
+                    };
+                    let delta = parse_reply(&self.name, data.trim())
+                        .and_then(|reply| run.apply(&self.name, reply));
+                    match delta {
+                        Ok(delta) if delta.is_empty() => {}
+                        Ok(delta) => yield Ok(AgentEvent::Token { delta }),
+                        Err(e) => {
+                            yield Err(e);
+                            return;
+                        }
+                    }
+                }
+            }
+
+            // The stream closed before the task ended
+            let response = run.response.clone();
+            match tokio::time::timeout_at(deadline, self.poll(&mut run, &context.user_id)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    yield Err(e);
+                    return;
+                }
+                Err(_) => {
+                    yield Err(self.timeout_error());
+                    return;
+                }
+            }
+            if let Some(delta) = run.response.strip_prefix(response.as_str()) {
+                if !delta.is_empty() {
+                    yield Ok(AgentEvent::Token { delta: delta.to_string() });
+                }
+            }
+            self.remember(&context.session_id, &run);
+            yield Ok(AgentEvent::Final { response: run.response, sources: Vec::new() });
+        };
+        Ok(Box::pin(events))
+    }
+
+    fn system_prompt(&self) -> String {
+        String::new()
+    }
+
+    fn agent_type(&self) -> AgentType {
+        AgentType::Custom(self.name.clone())
+    }
+
+    fn description(&self) -> String {
+        self.config.description.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(result: Value) -> Reply {
+        let body = json!({"jsonrpc": "2.0", "id": "1", "result": result}).to_string();
+        parse_reply("billing", &body).unwrap()
+    }
+
+    #[test]
+    fn test_task_replies() {
+        let mut run = Run::default();
+        let working = reply(json!({
+            "kind": "task", "id": "t1", "contextId": "c1",
+            "status": {"state": "working"},
+            "artifacts": [{"parts": [{"kind": "text", "text": "Refund"}]}],
+        }));
+        assert_eq!(run.apply("billing", working).unwrap(), "Refund");
+        assert!(!run.done);
+
+        let completed = reply(json!({
+            "kind": "task", "id": "t1", "contextId": "c1",
+            "status": {"state": "completed"},
+            "artifacts": [
+                {"parts": [{"kind": "text", "text": "Refund"}]},
+                {"parts": [{"kind": "data", "data": {"amount": 12}}, {"kind": "file", "file": {}}]},
+            ],
+        }));
+        assert_eq!(
+            run.apply("billing", completed).unwrap(),
+            "\n{\"amount\":12}"
+        );
+        assert_eq!(run.response, "Refund\n{\"amount\":12}");
+        assert!(run.done);
+        assert_eq!(run.session().context_id.as_deref(), Some("c1"));
+        assert_eq!(run.session().task_id, None);
+
+        let mut run = Run::default();
+        let question = reply(json!({
+            "kind": "task", "id": "t2",
+            "status": {"state": "input-required", "message": {
+                "kind": "message", "role": "agent", "messageId": "m1",
+                "parts": [{"kind": "text", "text": "Which invoice?"}],
+            }},
+        }));
+        assert_eq!(run.apply("billing", question).unwrap(), "Which invoice?");
+        assert_eq!(run.session().task_id.as_deref(), Some("t2"));
+
+        let failed = reply(json!({"kind": "task", "id": "t3", "status": {"state": "failed"}}));
+        let err = Run::default().apply("billing", failed).unwrap_err();
+        assert!(err.to_string().contains("failed"));
+
+        let body =
+            r#"{"jsonrpc":"2.0","id":"1","error":{"code":-32001,"message":"Task not found"}}"#;
+        assert!(parse_reply("billing", body).is_err());
+    }
+
+    #[test]
+    fn test_stream_events() {
+        let mut run = Run::default();
+        let events = [
+            json!({"kind": "task", "id": "t1", "contextId": "c1", "status": {"state": "submitted"}}),
+            json!({"kind": "artifact-update", "taskId": "t1", "contextId": "c1",
+                   "artifact": {"artifactId": "a1", "parts": [{"kind": "text", "text": "Your "}]}}),
+            json!({"kind": "artifact-update", "taskId": "t1", "contextId": "c1", "append": true,
+                   "artifact": {"artifactId": "a1", "parts": [{"kind": "text", "text": "refund"}]}}),
+            json!({"kind": "status-update", "taskId": "t1", "contextId": "c1",
+                   "status": {"state": "completed"}, "final": true}),
+        ];
+        let deltas: Vec<String> = events
+            .into_iter()
+            .map(|event| run.apply("billing", reply(event)).unwrap())
+            .collect();
+        assert_eq!(deltas, ["", "Your ", "refund", ""]);
+        assert_eq!(run.response, "Your refund");
+        assert!(run.done);
+
+        let mut run = Run::default();
+        let message = reply(json!({
+            "kind": "message", "role": "agent", "messageId": "m1", "contextId": "c2",
+            "parts": [{"kind": "text", "text": "Paid"}],
+        }));
+        assert_eq!(run.apply("billing", message).unwrap(), "Paid");
+        assert!(run.done);
+        assert_eq!(run.session().context_id.as_deref(), Some("c2"));
+    }
+}
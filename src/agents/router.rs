@@ -4,6 +4,7 @@ use crate::{
     types::{AgentContext, AgentType, Result},
 };
 use async_trait::async_trait;
+use std::sync::Arc;
 
 /// Valid agent names for routing
 const VALID_AGENTS: &[&str] = &[
@@ -22,12 +23,43 @@ const VALID_AGENTS: &[&str] = &[
 /// specialized agent is best suited to handle them.
 pub struct RouterAgent {
     llm: Box<dyn LLMClient>,
+    /// Names and descriptions of custom agents to route to as well
+    custom: Vec<(String, String)>,
 }
 
 impl RouterAgent {
     /// Creates a new RouterAgent with the given LLM client.
     pub fn new(llm: Box<dyn LLMClient>) -> Self {
-        Self { llm }
+        Self {
+            llm,
+            custom: Vec::new(),
+        }
+    }
+
+    /// Also route to these custom agents, as described by themselves
+    pub fn with_custom_agents(mut self, agents: &[Arc<dyn Agent>]) -> Self {
+        self.custom = agents
+            .iter()
+            .map(|agent| (agent.agent_type().as_str().to_string(), agent.description()))
+            .collect();
+        self
+    }
+
+    /// Find one of `names` chosen in LLM output
+    ///
+    /// Unlike built-in agents, a custom agent's name must appear as a whole
+    /// word, so that a short name doesn't match inside another.
+    pub fn parse_custom_choice(output: &str, names: &[&str]) -> Option<String> {
+        let trimmed = output.trim().to_lowercase();
+        trimmed
+            .split(|c: char| c.is_whitespace() || c == ':' || c == ',' || c == '.')
+            .chain(std::iter::once(trimmed.as_str()))
+            .find_map(|word| {
+                names
+                    .iter()
+                    .find(|name| name.to_lowercase() == word.trim())
+                    .map(|name| name.to_string())
+            })
     }
 
     /// Parse routing decision from LLM output
@@ -69,6 +101,12 @@ impl RouterAgent {
         let system_prompt = self.system_prompt();
         let response = self.llm.generate_with_system(&system_prompt, query).await?;
 
+        // Custom agents are matched first, as they may replace built-in ones
+        let custom: Vec<&str> = self.custom.iter().map(|(name, _)| name.as_str()).collect();
+        if let Some(name) = Self::parse_custom_choice(&response, &custom) {
+            return Ok(AgentType::Custom(name));
+        }
+
         // Parse the response with robust matching
         let agent_name = Self::parse_routing_decision(&response);
 
@@ -101,7 +139,13 @@ impl Agent for RouterAgent {
     }
 
     fn system_prompt(&self) -> String {
-        r#"You are a routing agent that classifies user queries and routes them to the appropriate specialized agent.
+        let custom: String = self
+            .custom
+            .iter()
+            .map(|(name, description)| format!("- {}: {}\n", name, description))
+            .collect();
+        format!(
+            r#"You are a routing agent that classifies user queries and routes them to the appropriate specialized agent.
 
 Available agents:
 - product: Product information, recommendations, catalog queries
@@ -110,7 +154,7 @@ Available agents:
 - finance: Financial reports, budgets, expense analysis
 - hr: Human resources, employee information, policies
 - orchestrator: Complex queries requiring multiple agents or research
-
+{}
 Analyze the user's query and respond with ONLY the agent name (lowercase, one word).
 Examples:
 - "What products do we have?" → product
@@ -118,10 +162,36 @@ Examples:
 - "What's our hiring policy?" → hr
 - "Create a comprehensive market analysis" → orchestrator
 
-Respond with ONLY the agent name, nothing else."#.to_string()
+Respond with ONLY the agent name, nothing else."#,
+            custom
+        )
     }
 
     fn agent_type(&self) -> AgentType {
         AgentType::Router
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_custom_choice() {
+        let names = ["summarizer", "legal-review"];
+        assert_eq!(
+            RouterAgent::parse_custom_choice(" Summarizer\n", &names).as_deref(),
+            Some("summarizer")
+        );
+        assert_eq!(
+            RouterAgent::parse_custom_choice("Route to: legal-review.", &names).as_deref(),
+            Some("legal-review")
+        );
+        // Names only match as whole words
+        assert_eq!(
+            RouterAgent::parse_custom_choice("summarizers", &names),
+            None
+        );
+        assert_eq!(RouterAgent::parse_custom_choice("product", &names), None);
+    }
+}
@@ -0,0 +1,456 @@
+//! Scheduled agent runs.
+//!
+//! Agents can run with a fixed prompt on a cron schedule, e.g. to summarize
+//! yesterday's ingested documents every morning. Schedules come from
+//! `[schedules]` in `ares.toml` or are created by users through
+//! `/api/schedules`. Each run is stored as a new conversation owned by the
+//! schedule's user, holding the prompt and the agent's answer.
+//!
+//! ```toml
+//! [schedules.morning-digest]
+//! agent = "research"
+//! prompt = "Summarize the documents ingested yesterday."
+//! cron = "0 7 * * *"
+//! ```
+//!
+//! Cron expressions are evaluated in UTC and take five fields (minute, hour,
+//! day of month, month, day of week) or six with a leading seconds field.
+
+use crate::api::handlers::user_agents::resolve_agent;
+use crate::api::maintenance;
+use crate::db::analytics::{self, RequestEvent};
+use crate::db::{agent_runs, jobs, locks, schedules, spend};
+use crate::memory::estimate_tokens;
+use crate::tools::permissions::ToolProfile;
+use crate::types::{AgentContext, AppError, MessageRole, Result, ToolCallTrace};
+use crate::AppState;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// How often the scheduler looks for due runs
+pub const SCHEDULER_TICK: Duration = Duration::from_secs(15);
+
+/// Stored schedules started per tick at most
+const DUE_BATCH_SIZE: i64 = 50;
+
+/// Next run of a schedule from `ares.toml`.
+struct ConfigRun {
+    /// Expression `next` was computed from, to notice reloaded changes
+    expression: String,
+    cron: Option<CronSchedule>,
+    next: Option<DateTime<Utc>>,
+}
+
+impl ConfigRun {
+    /// First run after `now`
+    fn new(expression: &str, now: DateTime<Utc>) -> Self {
+        let cron = CronSchedule::parse(expression).ok();
+        let next = cron.as_ref().and_then(|cron| cron.next_after(now));
+        Self {
+            expression: expression.to_string(),
+            cron,
+            next,
+        }
+    }
+}
+
+/// A parsed cron expression.
+#[derive(Debug, Clone)]
+pub struct CronSchedule(croner::Cron);
+
+impl CronSchedule {
+    /// Parse a cron expression, with or without a seconds field
+    pub fn parse(expression: &str) -> Result<Self> {
+        let mut cron = croner::Cron::new(expression.trim());
+        cron.with_seconds_optional();
+        cron.parse().map(Self).map_err(|e| {
+            AppError::InvalidInput(format!("Invalid cron expression '{}': {}", expression, e))
+        })
+    }
+
+    /// First run strictly after `time`, if the expression ever matches again
+    pub fn next_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.0.find_next_occurrence(&time, false).ok()
+    }
+
+    /// Shortest gap between the next few runs after `time`
+    ///
+    /// Used to refuse schedules that would run too often.
+    pub fn min_interval(&self, time: DateTime<Utc>) -> Option<chrono::Duration> {
+        let runs: Vec<DateTime<Utc>> =
+            std::iter::successors(self.next_after(time), |run| self.next_after(*run))
+                .take(4)
+                .collect();
+        runs.windows(2).map(|pair| pair[1] - pair[0]).min()
+    }
+}
+
+/// Run an agent on a prompt and store the run as a new conversation
+///
+/// The agent is resolved for `user_id` like in chat (their own agents, then
+/// community and system agents), budgets are enforced, and the run is
+/// recorded in agent runs and spend. Returns the conversation ID. Fails
+/// while the server is in maintenance mode.
+pub async fn run_agent(
+    state: &AppState,
+    user_id: &str,
+    agent_name: &str,
+    prompt: &str,
+    title: &str,
+) -> Result<String> {
+    maintenance::ensure_available(state)?;
+    let (config, _) = resolve_agent(state, user_id, agent_name.to_string()).await?;
+    let budgets = state.config_manager.config().budgets.clone();
+    spend::enforce_budgets(state.tenant_db.pool(), &budgets, user_id, agent_name).await?;
+    let agent = state
+        .agent_registry
+        .create_agent_from_config(agent_name, &config)
+        .await?;
+
+    // Roles may list users by email; config schedules can run as users that
+    // don't exist, who get the default role
+    let email = state
+        .db
+        .get_user_by_id(user_id)
+        .await?
+        .map(|user| user.email)
+        .unwrap_or_default();
+    let tool_profile = ToolProfile::for_user(&state.config_manager.config(), user_id, &email);
+
+    let conversation_id = Uuid::new_v4().to_string();
+    let context = AgentContext {
+        user_id: user_id.to_string(),
+        session_id: conversation_id.clone(),
+        conversation_history: Vec::new(),
+        user_memory: None,
+        cancellation: Default::default(),
+        hooks: state.hooks.clone(),
+        preferences: Default::default(),
+        tool_profile,
+        bus: None,
+        workspace_id: None,
+    };
+
+    let start = Instant::now();
+    let outcome = state
+        .agent_registry
+        .execute_with_handoffs(agent, prompt, &context)
+        .await;
+    let input_tokens = estimate_tokens(prompt) as i64;
+    let output_tokens = outcome
+        .as_ref()
+        .map_or(0, |outcome| estimate_tokens(&outcome.response) as i64);
+    let error = outcome.as_ref().err().map(|e| e.to_string());
+    let status = if error.is_some() {
+        "failed"
+    } else {
+        "completed"
+    };
+    if let Err(e) = agent_runs::insert_agent_run(
+        state.tenant_db.pool(),
+        "system",
+        agent_name,
+        Some(user_id),
+        status,
+        input_tokens,
+        output_tokens,
+        start.elapsed().as_millis() as i64,
+        error.as_deref(),
+        None,
+    )
+    .await
+    {
+        tracing::warn!("Failed to record scheduled run of {}: {}", agent_name, e);
+    }
+    let cost = budgets.estimate_cost(&config.model, input_tokens as u64, output_tokens as u64);
+    analytics::record_in_background(
+        state.tenant_db.pool(),
+        RequestEvent {
+            tenant_id: None,
+            user_id: Some(user_id.to_string()),
+            source: "schedule",
+            agent_name: agent_name.to_string(),
+            model: config.model.clone(),
+            status,
+            latency_ms: start.elapsed().as_millis() as i64,
+            input_tokens,
+            output_tokens,
+            cost_usd: if error.is_some() { 0.0 } else { cost },
+            error,
+        },
+    );
+    let outcome = outcome?;
+
+    if let Err(e) = spend::record_spend(
+        state.tenant_db.pool(),
+        user_id,
+        agent_name,
+        &config.model,
+        input_tokens,
+        output_tokens,
+        cost,
+    )
+    .await
+    {
+        tracing::warn!("Failed to record spend for {}: {}", user_id, e);
+    }
+
+    state
+        .db
+        .create_conversation(&conversation_id, user_id, Some(title), None)
+        .await?;
+    let reply_id = Uuid::new_v4().to_string();
+    for (id, role, content) in [
+        (Uuid::new_v4().to_string(), MessageRole::User, prompt),
+        (reply_id.clone(), MessageRole::Assistant, &outcome.response),
+    ] {
+        state
+            .db
+            .add_message(&id, &conversation_id, role, content)
+            .await?;
+    }
+    let traces: Vec<ToolCallTrace> = outcome
+        .tool_calls
+        .into_iter()
+        .map(|(agent, record)| ToolCallTrace::new(agent, record))
+        .collect();
+    if let Err(e) = state
+        .db
+        .store_tool_call_traces(&conversation_id, &reply_id, &traces)
+        .await
+    {
+        tracing::warn!("Failed to store tool call trace of scheduled run: {}", e);
+    }
+
+    Ok(conversation_id)
+}
+
+/// Title of the conversation a scheduled run is stored in
+pub fn run_title(schedule: &str, at: DateTime<Utc>) -> String {
+    format!("{} ({})", schedule, at.format("%Y-%m-%d %H:%M UTC"))
+}
+
+/// Queue every due schedule's run, every [`SCHEDULER_TICK`].
+///
+/// Runs are queued as [`AGENT_RUN`](crate::api::jobs::AGENT_RUN) jobs, so
+/// a run survives a restart and failed runs are retried. Stored schedules
+/// are claimed in the transaction that queues the run, so each run happens
+/// once. Schedules from `ares.toml` are tracked by every server, starting
+/// from the next match after the server starts or the schedule changes,
+/// and each run is queued once; hot-reloaded changes apply on the next
+/// tick. With several servers, only the [leader](locks::is_leader) queues
+/// runs; the others keep tracking, ready to take over.
+pub fn spawn_scheduler(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SCHEDULER_TICK);
+        let mut config_runs: HashMap<String, ConfigRun> = HashMap::new();
+        loop {
+            interval.tick().await;
+            let now = Utc::now();
+
+            let config = state.config_manager.config();
+            config_runs.retain(|name, _| config.schedules.get(name).is_some_and(|s| s.enabled));
+            for (name, schedule) in config.schedules.iter().filter(|(_, s)| s.enabled) {
+                let run = config_runs
+                    .entry(name.clone())
+                    .or_insert_with(|| ConfigRun::new(&schedule.cron, now));
+                if run.expression != schedule.cron {
+                    *run = ConfigRun::new(&schedule.cron, now);
+                }
+                let Some(due) = run.next.filter(|due| *due <= now) else {
+                    continue;
+                };
+                run.next = run.cron.as_ref().and_then(|cron| cron.next_after(now));
+                if !locks::is_leader() {
+                    continue;
+                }
+
+                // A new leader may notice a run the last one queued; it is
+                // queued once
+                let run = ScheduledRun {
+                    schedule_id: None,
+                    schedule: name.clone(),
+                    user_id: schedule.user_id.clone(),
+                    agent: schedule.agent.clone(),
+                    prompt: schedule.prompt.clone(),
+                    due_at: due.timestamp(),
+                };
+                let queued = jobs::enqueue_once(
+                    state.tenant_db.pool(),
+                    crate::api::jobs::AGENT_RUN,
+                    &run.payload(),
+                    &run.dedupe_key(),
+                    config.jobs.max_attempts,
+                    now.timestamp(),
+                )
+                .await;
+                if let Err(e) = queued {
+                    tracing::warn!("Failed to queue run of schedule {}: {}", name, e);
+                }
+            }
+
+            if !locks::is_leader() {
+                continue;
+            }
+            let due = match schedules::due_schedules(
+                state.tenant_db.pool(),
+                now.timestamp(),
+                DUE_BATCH_SIZE,
+            )
+            .await
+            {
+                Ok(due) => due,
+                Err(e) => {
+                    tracing::warn!("Failed to list due schedules: {}", e);
+                    continue;
+                }
+            };
+            for schedule in due {
+                let Some(due_at) = schedule.next_run_at else {
+                    continue;
+                };
+                let next = CronSchedule::parse(&schedule.cron)
+                    .ok()
+                    .and_then(|cron| cron.next_after(now))
+                    .map(|next| next.timestamp());
+                // Claim the run and queue it together, so a claimed run is
+                // never lost to a restart
+                let run = ScheduledRun {
+                    schedule_id: Some(schedule.id.clone()),
+                    schedule: schedule.name.clone(),
+                    user_id: schedule.user_id,
+                    agent: schedule.agent,
+                    prompt: schedule.prompt,
+                    due_at,
+                };
+                if let Err(e) = queue_stored_run(&state, &run, next, config.jobs.max_attempts).await
+                {
+                    tracing::warn!("Failed to queue run of schedule {}: {}", schedule.id, e);
+                }
+            }
+            crate::api::jobs::wake();
+        }
+    });
+}
+
+/// Claim a stored schedule's due run and queue it in one transaction.
+///
+/// Nothing is queued if another server claimed the run first.
+async fn queue_stored_run(
+    state: &AppState,
+    run: &ScheduledRun,
+    next: Option<i64>,
+    max_attempts: u32,
+) -> Result<()> {
+    let failed = |e: sqlx::Error| AppError::Database(format!("Failed to queue run: {}", e));
+    let id = run.schedule_id.as_deref().unwrap_or_default();
+    let mut tx = state.tenant_db.pool().begin().await.map_err(failed)?;
+    if !schedules::claim_run(&mut *tx, id, run.due_at, next).await? {
+        return Ok(());
+    }
+    jobs::enqueue(
+        &mut *tx,
+        crate::api::jobs::AGENT_RUN,
+        &run.payload(),
+        Some(&run.dedupe_key()),
+        max_attempts,
+        Utc::now().timestamp(),
+    )
+    .await?;
+    tx.commit().await.map_err(failed)
+}
+
+/// A scheduled run queued as a background job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledRun {
+    /// Stored schedule the run belongs to; `None` for schedules from `ares.toml`
+    pub schedule_id: Option<String>,
+    /// Schedule name, used in the title of the run's conversation
+    pub schedule: String,
+    /// User whose conversation receives the run
+    pub user_id: String,
+    /// Agent that runs
+    pub agent: String,
+    /// Message the agent is given
+    pub prompt: String,
+    /// When the run was due (Unix timestamp)
+    pub due_at: i64,
+}
+
+impl ScheduledRun {
+    /// Job payload
+    fn payload(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    /// Key the run is queued under, so each run is queued once
+    fn dedupe_key(&self) -> String {
+        match &self.schedule_id {
+            Some(id) => format!("schedule:{}:{}", id, self.due_at),
+            None => format!("config-schedule:{}:{}", self.schedule, self.due_at),
+        }
+    }
+}
+
+/// Run a queued scheduled run.
+///
+/// The outcome of every attempt is recorded on stored schedules; failed
+/// attempts are returned so the job is retried.
+pub async fn run_scheduled(state: &AppState, run: ScheduledRun) -> Result<()> {
+    let started = Utc::now();
+    let due = DateTime::from_timestamp(run.due_at, 0).unwrap_or(started);
+    let title = run_title(&run.schedule, due);
+    let outcome = run_agent(state, &run.user_id, &run.agent, &run.prompt, &title).await;
+
+    if let Some(id) = &run.schedule_id {
+        if let Err(e) =
+            schedules::record_run(state.tenant_db.pool(), id, started.timestamp(), &outcome).await
+        {
+            tracing::warn!("Failed to record run of schedule {}: {}", id, e);
+        }
+    }
+    let conversation_id = outcome?;
+    tracing::info!(
+        "Schedule {} stored its run in {}",
+        run.schedule,
+        conversation_id
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_cron_schedule_next_after() {
+        let cron = CronSchedule::parse("0 7 * * *").unwrap();
+        let now = Utc.with_ymd_and_hms(2026, 3, 1, 9, 30, 0).unwrap();
+        assert_eq!(
+            cron.next_after(now),
+            Some(Utc.with_ymd_and_hms(2026, 3, 2, 7, 0, 0).unwrap())
+        );
+        assert_eq!(cron.min_interval(now), Some(chrono::Duration::days(1)));
+
+        // A leading seconds field is accepted
+        let cron = CronSchedule::parse("30 */5 * * * *").unwrap();
+        assert_eq!(
+            cron.next_after(now),
+            Some(Utc.with_ymd_and_hms(2026, 3, 1, 9, 30, 30).unwrap())
+        );
+        assert_eq!(cron.min_interval(now), Some(chrono::Duration::minutes(5)));
+
+        assert!(CronSchedule::parse("every morning").is_err());
+        assert!(CronSchedule::parse("61 * * * *").is_err());
+    }
+
+    #[test]
+    fn test_run_title() {
+        let at = Utc.with_ymd_and_hms(2026, 3, 1, 7, 0, 0).unwrap();
+        assert_eq!(run_title("digest", at), "digest (2026-03-01 07:00 UTC)");
+    }
+}
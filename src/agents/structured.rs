@@ -0,0 +1,135 @@
+//! Structured agent output.
+//!
+//! Agents configured with an `output_schema` answer in JSON. The schema is
+//! described to the model, passed to providers with native structured output,
+//! and every reply is validated against it. A reply that fails validation is
+//! sent back to the model with the validation errors, up to
+//! [`MAX_ATTEMPTS`] generations in total.
+//!
+//! ```toml
+//! [agents.classifier]
+//! model = "fast"
+//! output_schema = { type = "object", required = ["label"], properties = { label = { type = "string", enum = ["bug", "feature"] } } }
+//! ```
+
+use crate::types::{AppError, Result};
+use serde_json::Value;
+
+/// Generations per request before giving up on a schema-conforming reply.
+pub const MAX_ATTEMPTS: usize = 3;
+
+/// Compiled output schema.
+pub struct OutputSchema {
+    schema: Value,
+    validator: jsonschema::Validator,
+}
+
+impl OutputSchema {
+    /// Compile a JSON Schema
+    pub fn new(schema: Value) -> Result<Self> {
+        let validator = jsonschema::validator_for(&schema)
+            .map_err(|e| AppError::Configuration(format!("Invalid output schema: {}", e)))?;
+        Ok(Self { schema, validator })
+    }
+
+    /// Get the JSON Schema
+    pub fn schema(&self) -> &Value {
+        &self.schema
+    }
+
+    /// System prompt section describing the required output.
+    pub fn instructions(&self) -> String {
+        format!(
+            "Respond only with a JSON value that conforms to this JSON Schema. \
+             Do not add any text before or after the JSON.\n{}",
+            self.schema
+        )
+    }
+
+    /// Parse and validate a model reply.
+    ///
+    /// On failure returns a description of what is wrong, suitable for
+    /// sending back to the model.
+    pub fn validate(&self, output: &str) -> std::result::Result<Value, String> {
+        let value: Value = serde_json::from_str(extract_json(output))
+            .map_err(|e| format!("The reply is not valid JSON: {}", e))?;
+
+        let errors: Vec<String> = self
+            .validator
+            .iter_errors(&value)
+            .map(|e| {
+                let path = e.instance_path.to_string();
+                if path.is_empty() {
+                    e.to_string()
+                } else {
+                    format!("{}: {}", path, e)
+                }
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(value)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+/// Message asking the model to correct a reply that failed validation.
+pub fn retry_request(errors: &str) -> String {
+    format!(
+        "Your reply does not match the required JSON Schema:\n{}\n\
+         Reply again with only the corrected JSON.",
+        errors
+    )
+}
+
+/// Strip Markdown code fences and surrounding text from a JSON reply.
+pub fn extract_json(output: &str) -> &str {
+    let output = output.trim();
+    let output = output
+        .strip_prefix("```json")
+        .or_else(|| output.strip_prefix("```"))
+        .and_then(|rest| rest.trim_end().strip_suffix("```"))
+        .map_or(output, str::trim);
+
+    // Keep the outermost object or array if the model wrapped it in prose
+    match (output.find(['{', '[']), output.rfind(['}', ']'])) {
+        (Some(start), Some(end)) if start < end => &output[start..=end],
+        _ => output,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extract_json_from_fenced_reply() {
+        assert_eq!(extract_json("```json\n{\"a\": 1}\n```"), "{\"a\": 1}");
+        assert_eq!(extract_json("Here you go: [1, 2]."), "[1, 2]");
+        assert_eq!(extract_json("42"), "42");
+    }
+
+    #[test]
+    fn test_validate_reports_schema_errors() {
+        let schema = OutputSchema::new(json!({
+            "type": "object",
+            "required": ["label"],
+            "properties": {"label": {"enum": ["bug", "feature"]}}
+        }))
+        .unwrap();
+
+        assert_eq!(
+            schema.validate("{\"label\": \"bug\"}").unwrap(),
+            json!({"label": "bug"})
+        );
+        assert!(schema
+            .validate("{\"label\": \"question\"}")
+            .unwrap_err()
+            .contains("/label"));
+        assert!(schema.validate("{}").is_err());
+        assert!(schema.validate("not json").is_err());
+        assert!(OutputSchema::new(json!({"type": 5})).is_err());
+    }
+}
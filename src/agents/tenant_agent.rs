@@ -15,6 +15,25 @@ fn json_to_agent_config(json: &serde_json::Value) -> AgentConfig {
             .unwrap_or_default(),
         max_tool_iterations: json["max_tool_iterations"].as_u64().unwrap_or(5) as usize,
         parallel_tools: json["parallel_tools"].as_bool().unwrap_or(false),
+        guardrail_policy: json["guardrail_policy"].as_str().map(|s| s.to_string()),
+        handoffs: json["handoffs"]
+            .as_array()
+            .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
+            .unwrap_or_default(),
+        strategy: serde_json::from_value(json["strategy"].clone()).unwrap_or_default(),
+        output_schema: json.get("output_schema").filter(|v| !v.is_null()).cloned(),
+        memory: serde_json::from_value(json["memory"].clone()).unwrap_or_default(),
+        tool_permissions: serde_json::from_value(json["tool_permissions"].clone())
+            .unwrap_or_default(),
+        answer_cache: serde_json::from_value(json["answer_cache"].clone()).unwrap_or_default(),
+        reflection: serde_json::from_value(json["reflection"].clone()).unwrap_or_default(),
+        limits: serde_json::from_value(json["limits"].clone()).unwrap_or_default(),
+        personas: serde_json::from_value(json["personas"].clone()).unwrap_or_default(),
+        requires_approval: json["requires_approval"].as_bool().unwrap_or(false),
+        bus: serde_json::from_value(json["bus"].clone()).unwrap_or_default(),
+        parameter_limits: serde_json::from_value(json["parameter_limits"].clone())
+            .unwrap_or_default(),
+        rag: serde_json::from_value(json["rag"].clone()).unwrap_or_default(),
         extra: HashMap::new(),
     }
 }
@@ -0,0 +1,82 @@
+//! Encrypted conversations: messages the server can't read at rest.
+//!
+//! A chat request starting a conversation with an `X-Conversation-Secret`
+//! header encrypts it: its messages are stored sealed with a key derived
+//! from the secret (see [`crate::db::conversation_keys`]), which the server
+//! never stores. Every later request reading or continuing it must send the
+//! same secret; without it, or with a wrong one, it fails with a 403.
+//! Agents get the decrypted history in memory for the run.
+//!
+//! This protects chats from someone with database access only. The server
+//! still sees the plaintext while answering, and so do the model
+//! providers. To keep content out of other tables, encrypted conversations
+//! are never auto-titled, cached, checkpointed or given tool-call traces,
+//! and feedback on them keeps only the rating and comment.
+
+use crate::db::conversation_keys::{self, ConversationKey};
+use crate::types::{AppError, Result};
+use crate::AppState;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+
+/// Header carrying the secret of an encrypted conversation
+pub const HEADER: &str = "x-conversation-secret";
+
+/// The conversation secret a request sent, if any.
+#[derive(Clone, Default)]
+pub struct ConversationSecret(Option<String>);
+
+impl std::fmt::Debug for ConversationSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secret = self.0.as_ref().map(|_| "<redacted>");
+        f.debug_tuple("ConversationSecret").field(&secret).finish()
+    }
+}
+
+impl ConversationSecret {
+    /// No secret, for requests that can't send one
+    pub fn none() -> Self {
+        Self(None)
+    }
+
+    /// The key of a conversation, for reading it: `None` if it isn't
+    /// encrypted.
+    pub async fn unlock(
+        &self,
+        state: &AppState,
+        conversation_id: &str,
+    ) -> Result<Option<ConversationKey>> {
+        conversation_keys::unlock(state.tenant_db.pool(), conversation_id, self.0.as_deref()).await
+    }
+
+    /// The key of a conversation, for adding to it: a secret sent for a
+    /// conversation without messages encrypts it.
+    pub async fn unlock_or_encrypt(
+        &self,
+        state: &AppState,
+        conversation_id: &str,
+    ) -> Result<Option<ConversationKey>> {
+        let pool = state.tenant_db.pool();
+        match (self.unlock(state, conversation_id).await?, &self.0) {
+            (Some(key), _) => Ok(Some(key)),
+            (None, Some(secret)) => Ok(Some(
+                conversation_keys::encrypt(pool, conversation_id, secret).await?,
+            )),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ConversationSecret {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self> {
+        let Some(header) = parts.headers.get(HEADER) else {
+            return Ok(Self::none());
+        };
+        let secret = header.to_str().map_err(|_| {
+            AppError::InvalidInput("Invalid X-Conversation-Secret header".to_string())
+        })?;
+        Ok(Self((!secret.is_empty()).then(|| secret.to_string())))
+    }
+}
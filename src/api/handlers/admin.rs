@@ -7,23 +7,38 @@ use crate::db::tenant_agents::{
     update_tenant_agent as db_update_tenant_agent,
 };
 use crate::db::agent_runs;
+use crate::db::analytics;
 use crate::db::alerts as db_alerts;
+use crate::api::maintenance::MaintenanceStatus;
 use crate::db::audit_log;
+use crate::db::jobs;
+use crate::db::feedback::{self, ExportCursor, FeedbackFilter, MessageFeedback, Rating};
+use crate::llm::canary::CanaryReport;
 use crate::llm::provider_registry::ModelInfo;
 use crate::models::{Tenant, TenantTier};
-use crate::types::{AppError, Result};
+use crate::auth::jwt::AuthService;
+use crate::db::mfa;
+use crate::db::roles::{self, UserRole};
+use crate::types::{AppError, Result, Role};
 use crate::AppState;
 use axum::{
+    body::Body,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
     Json,
 };
+use futures::Stream;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
 
+/// Admin routes accept the `X-Admin-Secret` header, or the JWT of a user
+/// with the admin role.
 pub async fn admin_middleware(
+    auth_service: Arc<AuthService>,
     req: axum::extract::Request,
     next: Next,
 ) -> Response {
@@ -36,9 +51,25 @@ pub async fn admin_middleware(
 
     match (admin_secret, header_secret) {
         (Some(expected), Some(given)) if expected == given => {
+            return next.run(req).await;
+        }
+        _ => {}
+    }
+
+    let claims = req
+        .headers()
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .and_then(|token| auth_service.verify_token(token).ok());
+    match claims {
+        Some(claims) if claims.role.allows(Role::Admin) => {
+            let mut req = req;
+            req.extensions_mut().insert(claims);
             next.run(req).await
         }
-        _ => {
+        Some(_) => AppError::Forbidden("This requires the admin role".to_string()).into_response(),
+        None => {
             Response::builder()
                 .status(StatusCode::UNAUTHORIZED)
                 .header("Content-Type", "application/json")
@@ -370,6 +401,338 @@ pub async fn list_models_handler(
     Ok(Json(state.provider_registry.list_models()))
 }
 
+/// Per-arm metrics of every model canary since the server started
+pub async fn list_canaries_handler(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<CanaryReport>>> {
+    Ok(Json(state.provider_registry.canary_reports()))
+}
+
+// =============================================================================
+// Maintenance Mode
+// =============================================================================
+
+/// Runtime maintenance switch
+#[derive(Debug, Deserialize)]
+pub struct MaintenanceRequest {
+    /// Whether to reject new generations
+    pub enabled: bool,
+    /// Message returned to rejected requests
+    pub message: Option<String>,
+}
+
+fn maintenance_status(state: &AppState) -> MaintenanceStatus {
+    state.maintenance.status(
+        &state.config_manager.config().maintenance,
+        state.generations.len(),
+    )
+}
+
+/// Whether the server is in maintenance mode, and the generations still running
+pub async fn get_maintenance(State(state): State<AppState>) -> Result<Json<MaintenanceStatus>> {
+    Ok(Json(maintenance_status(&state)))
+}
+
+/// Switch maintenance mode on or off, overriding `[maintenance]`
+pub async fn set_maintenance(
+    State(state): State<AppState>,
+    Json(req): Json<MaintenanceRequest>,
+) -> Result<Json<MaintenanceStatus>> {
+    state.maintenance.set(req.enabled, req.message);
+    let status = maintenance_status(&state);
+    tracing::warn!(
+        "Maintenance mode switched {} by admin ({} generations in flight)",
+        if status.enabled { "on" } else { "off" },
+        status.in_flight
+    );
+
+    let pool = state.tenant_db.pool().clone();
+    let action = if status.enabled { "enable_maintenance" } else { "disable_maintenance" };
+    let details = serde_json::json!({ "message": status.message }).to_string();
+    tokio::spawn(async move {
+        let _ = audit_log::log_admin_action(
+            &pool, action, "server", "maintenance", Some(&details), None,
+        ).await;
+    });
+
+    Ok(Json(status))
+}
+
+/// Clear the runtime switch, going back to `[maintenance]`
+pub async fn clear_maintenance(State(state): State<AppState>) -> Result<Json<MaintenanceStatus>> {
+    state.maintenance.clear();
+
+    let pool = state.tenant_db.pool().clone();
+    tokio::spawn(async move {
+        let _ = audit_log::log_admin_action(
+            &pool, "clear_maintenance", "server", "maintenance", None, None,
+        ).await;
+    });
+
+    Ok(Json(maintenance_status(&state)))
+}
+
+// =============================================================================
+// Request Analytics
+// =============================================================================
+
+/// Window and filters of the analytics report
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsQuery {
+    /// Days covered, counting today (default 30, at most 90)
+    pub days: Option<i64>,
+    /// Only requests made in this tenant
+    pub tenant_id: Option<String>,
+    /// Agents listed in `top_agents` at most (default 10, at most 100)
+    pub limit: Option<i64>,
+}
+
+/// Usage of agent generations over a window of days
+#[derive(Debug, Serialize)]
+pub struct AnalyticsReport {
+    /// Start of the window (Unix timestamp)
+    pub since: i64,
+    /// Usage per UTC day, oldest first
+    pub daily: Vec<analytics::DailyUsage>,
+    /// Agents with the most requests
+    pub top_agents: Vec<analytics::AgentUsage>,
+    /// Agents and models with failed requests, highest error rate first
+    pub error_rates: Vec<analytics::ErrorRate>,
+}
+
+/// Daily usage, top agents and error rates of agent generations
+pub async fn get_analytics(
+    State(state): State<AppState>,
+    Query(q): Query<AnalyticsQuery>,
+) -> Result<Json<AnalyticsReport>> {
+    let days = q.days.unwrap_or(30).clamp(1, 90);
+    let limit = q.limit.unwrap_or(10).clamp(1, 100);
+    let now = chrono::Utc::now().timestamp();
+    let since = crate::db::spend::day_start(now) - (days - 1) * 86400;
+    let tenant_id = q.tenant_id.as_deref();
+
+    let pool = state.tenant_db.read_pool();
+    let (daily, top_agents, error_rates) = tokio::try_join!(
+        analytics::daily_usage(pool, since, tenant_id),
+        analytics::top_agents(pool, since, tenant_id, limit),
+        analytics::error_rates(pool, since, tenant_id),
+    )?;
+
+    Ok(Json(AnalyticsReport {
+        since,
+        daily,
+        top_agents,
+        error_rates,
+    }))
+}
+
+// =============================================================================
+// Background Jobs
+// =============================================================================
+
+/// Filters of the job list
+#[derive(Debug, Deserialize)]
+pub struct JobsQuery {
+    /// `pending`, `running`, `succeeded` or `dead`
+    pub status: Option<String>,
+    /// e.g. `agent_run` or `ingest`
+    pub kind: Option<String>,
+    /// Jobs returned at most (default 50, at most 200)
+    pub limit: Option<i64>,
+}
+
+/// Queued, running, succeeded and dead-lettered jobs, newest first
+pub async fn list_jobs(
+    State(state): State<AppState>,
+    Query(q): Query<JobsQuery>,
+) -> Result<Json<Vec<jobs::Job>>> {
+    if let Some(status) = &q.status {
+        if ![jobs::PENDING, jobs::RUNNING, jobs::SUCCEEDED, jobs::DEAD].contains(&status.as_str()) {
+            return Err(AppError::InvalidInput(format!("Unknown job status '{}'", status)));
+        }
+    }
+    let limit = q.limit.unwrap_or(50).clamp(1, 200);
+    let jobs = jobs::list_jobs(
+        state.tenant_db.read_pool(),
+        q.status.as_deref(),
+        q.kind.as_deref(),
+        limit,
+    ).await?;
+    Ok(Json(jobs))
+}
+
+/// Queue a dead-lettered job again with a fresh set of attempts
+pub async fn retry_job(
+    State(state): State<AppState>,
+    Path(job_id): Path<String>,
+) -> Result<Json<jobs::Job>> {
+    let pool = state.tenant_db.pool();
+    let job = jobs::get_job(pool, &job_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Job '{}' not found", job_id)))?;
+    if job.status != jobs::DEAD {
+        return Err(AppError::InvalidInput(format!(
+            "Job '{}' is {}; only dead jobs can be retried",
+            job_id, job.status
+        )));
+    }
+    if !jobs::retry_job(pool, &job_id, chrono::Utc::now().timestamp()).await? {
+        return Err(AppError::InvalidInput(format!(
+            "The work of job '{}' is already queued again",
+            job_id
+        )));
+    }
+    crate::api::jobs::wake();
+
+    let audit_pool = pool.clone();
+    let audited_id = job_id.clone();
+    tokio::spawn(async move {
+        let _ = audit_log::log_admin_action(
+            &audit_pool, "retry_job", "job", &audited_id, None, None,
+        ).await;
+    });
+
+    let job = jobs::get_job(pool, &job_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Job '{}' not found", job_id)))?;
+    Ok(Json(job))
+}
+
+/// Delete a job that is not running
+pub async fn delete_job(
+    State(state): State<AppState>,
+    Path(job_id): Path<String>,
+) -> Result<StatusCode> {
+    if !jobs::delete_job(state.tenant_db.pool(), &job_id).await? {
+        return Err(AppError::NotFound(format!(
+            "Job '{}' not found or still running",
+            job_id
+        )));
+    }
+
+    let pool = state.tenant_db.pool().clone();
+    tokio::spawn(async move {
+        let _ = audit_log::log_admin_action(
+            &pool, "delete_job", "job", &job_id, None, None,
+        ).await;
+    });
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// =============================================================================
+// Message Feedback
+// =============================================================================
+
+/// Filters and paging of the feedback list
+#[derive(Debug, Deserialize)]
+pub struct FeedbackQuery {
+    /// Only feedback with this rating
+    pub rating: Option<Rating>,
+    /// Only feedback on messages generated by this agent
+    pub agent: Option<String>,
+    /// Feedback returned at most (default 50, max 200)
+    pub limit: Option<i64>,
+    /// Feedback skipped before the first one returned
+    pub offset: Option<i64>,
+}
+
+/// Users' feedback on assistant messages, newest first
+pub async fn list_feedback_handler(
+    State(state): State<AppState>,
+    Query(q): Query<FeedbackQuery>,
+) -> Result<Json<Vec<MessageFeedback>>> {
+    let filter = FeedbackFilter {
+        rating: q.rating,
+        agent: q.agent,
+        limit: q.limit.unwrap_or(50).min(200),
+        offset: q.offset.unwrap_or(0),
+    };
+    let feedback = feedback::list_feedback(state.tenant_db.pool(), &filter).await?;
+    Ok(Json(feedback))
+}
+
+/// Feedback read per query while exporting
+const FEEDBACK_EXPORT_PAGE: i64 = 500;
+
+/// Which feedback to export
+#[derive(Debug, Deserialize)]
+pub struct FeedbackExportQuery {
+    /// Only feedback with this rating
+    pub rating: Option<Rating>,
+    /// Only feedback on messages generated by this agent
+    pub agent: Option<String>,
+}
+
+/// Export feedback as JSON Lines, newest first, to seed evaluation datasets
+///
+/// Each line is one [`MessageFeedback`]: the rated question and answer with
+/// the rating, comment, and the agent, model and prompt version behind the
+/// answer. The body is streamed a page at a time.
+pub async fn export_feedback_handler(
+    State(state): State<AppState>,
+    Query(q): Query<FeedbackExportQuery>,
+) -> Result<Response> {
+    let pool = state.tenant_db.pool().clone();
+    let filter = FeedbackFilter {
+        rating: q.rating,
+        agent: q.agent,
+        limit: FEEDBACK_EXPORT_PAGE,
+        offset: 0,
+    };
+    let pages = feedback_export_pages(FEEDBACK_EXPORT_PAGE, move |after| {
+        let pool = pool.clone();
+        let filter = filter.clone();
+        async move { feedback::export_feedback(&pool, &filter, after.as_ref()).await }
+    });
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"feedback.jsonl\"",
+            ),
+        ],
+        Body::from_stream(pages),
+    )
+        .into_response())
+}
+
+/// JSON Lines chunks of a feedback export, one per page read through
+/// `fetch_page` from the cursor after the previous page
+fn feedback_export_pages<F, Fut>(
+    page_size: i64,
+    mut fetch_page: F,
+) -> impl Stream<Item = Result<String>>
+where
+    F: FnMut(Option<ExportCursor>) -> Fut,
+    Fut: Future<Output = Result<Vec<MessageFeedback>>>,
+{
+    async_stream::try_stream! {
+        let mut after = None;
+        loop {
+            let page = fetch_page(after.take()).await?;
+            let mut chunk = String::new();
+            for item in &page {
+                let line = serde_json::to_string(item)
+                    .map_err(|e| AppError::Internal(format!("Failed to encode feedback: {}", e)))?;
+                chunk.push_str(&line);
+                chunk.push('\n');
+            }
+            if !chunk.is_empty() {
+                yield chunk;
+            }
+            match page.last() {
+                Some(last) if page.len() as i64 >= page_size => {
+                    after = Some(ExportCursor::after(last));
+                }
+                _ => break,
+            }
+        }
+    }
+}
+
 // =============================================================================
 // Alerts
 // =============================================================================
@@ -421,6 +784,125 @@ pub async fn resolve_alert(
     Ok(StatusCode::OK)
 }
 
+// =============================================================================
+// User Roles
+// =============================================================================
+
+/// Role to assign to a user
+#[derive(Debug, Deserialize)]
+pub struct RoleRequest {
+    /// `admin`, `member` or `read_only`
+    pub role: Role,
+}
+
+/// List users with their roles
+pub async fn list_users(State(state): State<AppState>) -> Result<Json<Vec<UserRole>>> {
+    Ok(Json(roles::list_users(state.tenant_db.pool()).await?))
+}
+
+/// Assign a role to a user; it is embedded in their next token, at the
+/// latest when their access token is refreshed
+pub async fn set_user_role(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+    Json(req): Json<RoleRequest>,
+) -> Result<Json<UserRole>> {
+    let user = state
+        .db
+        .get_user_by_id(&user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {} not found", user_id)))?;
+
+    let pool = state.tenant_db.pool();
+    let current = roles::get_role(pool, &user_id).await?;
+    if current == Role::Admin && req.role != Role::Admin && roles::count_admins(pool).await? <= 1 {
+        return Err(AppError::InvalidInput(
+            "Can't remove the role of the last admin".to_string(),
+        ));
+    }
+    roles::set_role(pool, &user_id, req.role, chrono::Utc::now().timestamp()).await?;
+
+    let pool = pool.clone();
+    let details = serde_json::json!({ "from": current, "to": req.role }).to_string();
+    let uid = user_id.clone();
+    tokio::spawn(async move {
+        let _ = audit_log::log_admin_action(
+            &pool, "set_user_role", "user", &uid, Some(&details), None,
+        ).await;
+    });
+
+    Ok(Json(UserRole {
+        id: user.id,
+        email: user.email,
+        name: user.name,
+        role: req.role,
+    }))
+}
+
+// =============================================================================
+// User MFA
+// =============================================================================
+
+/// Whether a user must use MFA
+#[derive(Debug, Deserialize)]
+pub struct MfaRequirementRequest {
+    /// Whether the user must set up and use MFA
+    pub required: bool,
+}
+
+/// Require MFA of a user, or stop requiring it; a user who must and hasn't
+/// set it up enrolls at their next login
+pub async fn set_user_mfa(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+    Json(req): Json<MfaRequirementRequest>,
+) -> Result<StatusCode> {
+    state
+        .db
+        .get_user_by_id(&user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {} not found", user_id)))?;
+
+    let pool = state.tenant_db.pool();
+    mfa::set_required(pool, &user_id, req.required, chrono::Utc::now().timestamp()).await?;
+
+    let pool = pool.clone();
+    let details = serde_json::json!({ "required": req.required }).to_string();
+    tokio::spawn(async move {
+        let _ = audit_log::log_admin_action(
+            &pool, "set_user_mfa", "user", &user_id, Some(&details), None,
+        ).await;
+    });
+
+    Ok(StatusCode::OK)
+}
+
+/// Reset a user's MFA, for a user who lost their authenticator and recovery
+/// codes; they sign in with their password alone, or enroll again if MFA is
+/// required
+pub async fn reset_user_mfa(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<StatusCode> {
+    state
+        .db
+        .get_user_by_id(&user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {} not found", user_id)))?;
+
+    let pool = state.tenant_db.pool();
+    mfa::disable(pool, &user_id, chrono::Utc::now().timestamp()).await?;
+
+    let pool = pool.clone();
+    tokio::spawn(async move {
+        let _ = audit_log::log_admin_action(
+            &pool, "reset_user_mfa", "user", &user_id, None, None,
+        ).await;
+    });
+
+    Ok(StatusCode::OK)
+}
+
 // =============================================================================
 // Audit Log
 // =============================================================================
@@ -437,7 +919,7 @@ pub async fn list_audit_log(
 ) -> Result<Json<Vec<audit_log::AuditLogEntry>>> {
     let limit = q.limit.unwrap_or(50).min(200);
     let offset = q.offset.unwrap_or(0);
-    let entries = audit_log::list_audit_log(state.tenant_db.pool(), limit, offset).await?;
+    let entries = audit_log::list_audit_log(state.tenant_db.read_pool(), limit, offset).await?;
     Ok(Json(entries))
 }
 
@@ -556,3 +1038,104 @@ pub async fn get_platform_stats(
     let stats = agent_runs::get_platform_stats(state.tenant_db.pool()).await?;
     Ok(Json(stats))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn feedback(id: &str, created_at: i64) -> MessageFeedback {
+        MessageFeedback {
+            id: id.to_string(),
+            conversation_id: "c1".to_string(),
+            message_id: format!("m-{}", id),
+            user_id: "alice".to_string(),
+            rating: "down".to_string(),
+            comment: Some("Wrong order number".to_string()),
+            agent: Some("support".to_string()),
+            model: Some("fast".to_string()),
+            prompt_version: Some("v2".to_string()),
+            question: "Where is my order?".to_string(),
+            answer: "It shipped.".to_string(),
+            created_at,
+            updated_at: created_at,
+        }
+    }
+
+    #[test]
+    fn test_feedback_export_query_filters() {
+        let uri = "/admin/feedback/export?rating=down&agent=support".parse().unwrap();
+        let Query(q) = Query::<FeedbackExportQuery>::try_from_uri(&uri).unwrap();
+        assert_eq!(q.rating, Some(Rating::Down));
+        assert_eq!(q.agent.as_deref(), Some("support"));
+
+        let uri = "/admin/feedback/export".parse().unwrap();
+        let Query(q) = Query::<FeedbackExportQuery>::try_from_uri(&uri).unwrap();
+        assert_eq!(q.rating, None);
+        assert_eq!(q.agent, None);
+
+        let uri = "/admin/feedback/export?rating=meh".parse().unwrap();
+        assert!(Query::<FeedbackExportQuery>::try_from_uri(&uri).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_feedback_export_lines_survive_concurrent_feedback() {
+        // Two feedback share a timestamp, so pages must break ties by ID
+        let store = Arc::new(std::sync::Mutex::new(vec![
+            feedback("a", 100),
+            feedback("b", 200),
+            feedback("c", 200),
+            feedback("d", 300),
+            feedback("e", 400),
+        ]));
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let pages = feedback_export_pages(2, {
+            let store = store.clone();
+            let calls = calls.clone();
+            move |after: Option<ExportCursor>| {
+                let mut rows = store.lock().unwrap();
+                if calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 1 {
+                    // Feedback given while the export runs
+                    rows.push(feedback("f", 500));
+                }
+                let mut page: Vec<_> = rows
+                    .iter()
+                    .filter(|f| {
+                        after.as_ref().is_none_or(|c| {
+                            (f.created_at, f.id.as_str()) < (c.created_at, c.id.as_str())
+                        })
+                    })
+                    .cloned()
+                    .collect();
+                page.sort_by(|x, y| (y.created_at, &y.id).cmp(&(x.created_at, &x.id)));
+                page.truncate(2);
+                async move { Ok(page) }
+            }
+        });
+        let body: String = pages
+            .map(|chunk| chunk.unwrap())
+            .collect::<Vec<_>>()
+            .await
+            .concat();
+
+        assert!(body.ends_with('\n'));
+        let lines: Vec<serde_json::Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let ids: Vec<_> = lines.iter().map(|l| l["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, ["e", "d", "c", "b", "a"]);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        let line = &lines[0];
+        assert_eq!(line["question"], "Where is my order?");
+        assert_eq!(line["answer"], "It shipped.");
+        assert_eq!(line["rating"], "down");
+        assert_eq!(line["comment"], "Wrong order number");
+        assert_eq!(line["agent"], "support");
+        assert_eq!(line["model"], "fast");
+        assert_eq!(line["prompt_version"], "v2");
+        assert_eq!(line["created_at"], 400);
+    }
+}
@@ -1,12 +1,13 @@
-//! Built-in agent listing handler.
+//! Agent listing handler: built-in agents, then custom agents.
 
 use crate::{types::AgentType, AppState};
 use axum::{extract::State, Json};
 use serde::Serialize;
 
-/// Lists all available built-in agents.
-pub async fn list_agents(State(_state): State<AppState>) -> Json<Vec<AgentInfo>> {
-    Json(vec![
+/// Lists all available built-in agents, then custom agents registered in
+/// code or by plugins.
+pub async fn list_agents(State(state): State<AppState>) -> Json<Vec<AgentInfo>> {
+    let mut agents = vec![
         AgentInfo {
             agent_type: AgentType::Product,
             name: "Product Agent".to_string(),
@@ -32,7 +33,19 @@ pub async fn list_agents(State(_state): State<AppState>) -> Json<Vec<AgentInfo>>
             name: "HR Agent".to_string(),
             description: "Manages human resources queries".to_string(),
         },
-    ])
+    ];
+    agents.extend(
+        state
+            .agent_registry
+            .custom_agents()
+            .iter()
+            .map(|agent| AgentInfo {
+                agent_type: agent.agent_type(),
+                name: agent.agent_type().as_str().to_string(),
+                description: agent.description(),
+            }),
+    );
+    Json(agents)
 }
 
 /// Information about an available agent.
@@ -0,0 +1,550 @@
+//! AG-UI protocol endpoint.
+//!
+//! [AG-UI](https://docs.ag-ui.com) is an open protocol between agent
+//! backends and frontends. `POST /api/agui` takes AG-UI's `RunAgentInput`
+//! and answers with a Server-Sent Events stream of AG-UI events, so AG-UI
+//! clients (CopilotKit, `@ag-ui/client`'s `HttpAgent`) render ARES agent
+//! runs without bespoke code. `POST /api/agui/{agent}` runs a given agent
+//! instead of routing the message.
+//!
+//! The input's `threadId` is the ARES conversation, created on first use,
+//! and its last message, which must be the user's, is answered exactly as
+//! `POST /api/chat` would: the conversation keeps its own history, so
+//! earlier messages sent by the client are ignored. A run's events are:
+//!
+//! 1. `RUN_STARTED`, then `STATE_SNAPSHOT` with the client's state.
+//! 2. `TOOL_CALL_START`, `TOOL_CALL_ARGS`, `TOOL_CALL_END` and
+//!    `TOOL_CALL_RESULT` for each tool call, as soon as it has run.
+//! 3. `TEXT_MESSAGE_START`, `TEXT_MESSAGE_CONTENT` and `TEXT_MESSAGE_END`
+//!    with the answer, once it is complete.
+//! 4. `STATE_DELTA` adding `/ares`: the agent that answered, the stored
+//!    message's ID, sources and any limit the run hit.
+//! 5. `RUN_FINISHED`, or `RUN_ERROR` if the run failed.
+//!
+//! A run paused for tool call approval sends a `CUSTOM` event named
+//! `approval_required` with the pending approval instead of a message;
+//! approve it with `/api/approvals`. Frontend tools and context sent in the
+//! input aren't used, since ARES agents only call their own tools.
+
+use crate::{
+    api::{
+        encryption::ConversationSecret, handlers::chat::answer, handlers::threads::check_assistant,
+        maintenance, workspace::ActiveWorkspace,
+    },
+    auth::middleware::AuthUser,
+    hooks::ConversationHook,
+    llm::{cancellation::CancellationToken, coordinator::ToolCallRecord},
+    models::TenantContext,
+    types::{AgentContext, AgentType, AppError, ChatRequest, ChatResponse, Result},
+    AppState,
+};
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, State},
+    response::sse::{Event, KeepAlive, Sse},
+    Extension, Json,
+};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// AG-UI `RunAgentInput`: a request to run an agent on a thread.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RunAgentInput {
+    /// Thread the run belongs to; the ARES conversation ID
+    pub thread_id: String,
+    /// ID of this run, echoed in its events
+    pub run_id: String,
+    /// Frontend state, sent back in `STATE_SNAPSHOT`
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub state: Value,
+    /// Messages of the thread; the last one is answered
+    #[serde(default)]
+    pub messages: Vec<AgUiMessage>,
+    /// Frontend tools (not used)
+    #[serde(default)]
+    #[schema(value_type = Vec<Object>)]
+    pub tools: Vec<Value>,
+    /// Frontend context (not used)
+    #[serde(default)]
+    #[schema(value_type = Vec<Object>)]
+    pub context: Vec<Value>,
+    /// ARES options of the run
+    #[serde(default)]
+    pub forwarded_props: ForwardedProps,
+}
+
+/// A message of an AG-UI thread.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AgUiMessage {
+    /// Message ID
+    #[serde(default)]
+    pub id: Option<String>,
+    /// "user", "assistant", "system", "developer" or "tool"
+    pub role: String,
+    /// Text, or a list of content parts whose `text` parts are used
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub content: Value,
+}
+
+/// ARES options passed in AG-UI's `forwardedProps`.
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ForwardedProps {
+    /// Agent to answer (default: routed)
+    #[serde(default)]
+    pub agent_type: Option<AgentType>,
+    /// Persona of the agent to answer in
+    #[serde(default)]
+    pub persona: Option<String>,
+    /// Sampling seed for a reproducible run
+    #[serde(default)]
+    pub seed: Option<u32>,
+}
+
+/// An AG-UI event, serialized with its `type`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "SCREAMING_SNAKE_CASE",
+    rename_all_fields = "camelCase"
+)]
+pub enum AgUiEvent {
+    /// The run started
+    RunStarted {
+        /// Thread of the run
+        thread_id: String,
+        /// The run
+        run_id: String,
+    },
+    /// The run finished
+    RunFinished {
+        /// Thread of the run
+        thread_id: String,
+        /// The run
+        run_id: String,
+    },
+    /// The run failed
+    RunError {
+        /// What went wrong
+        message: String,
+        /// Error code, as in REST API errors
+        #[serde(skip_serializing_if = "Option::is_none")]
+        code: Option<String>,
+    },
+    /// A message starts
+    TextMessageStart {
+        /// The message
+        message_id: String,
+        /// Always "assistant"
+        role: String,
+    },
+    /// Text of a message
+    TextMessageContent {
+        /// The message
+        message_id: String,
+        /// Text added to the message
+        delta: String,
+    },
+    /// A message is complete
+    TextMessageEnd {
+        /// The message
+        message_id: String,
+    },
+    /// A tool call starts
+    ToolCallStart {
+        /// The tool call
+        tool_call_id: String,
+        /// Tool called
+        tool_call_name: String,
+    },
+    /// Arguments of a tool call, as JSON text
+    ToolCallArgs {
+        /// The tool call
+        tool_call_id: String,
+        /// Text added to the arguments
+        delta: String,
+    },
+    /// A tool call's arguments are complete
+    ToolCallEnd {
+        /// The tool call
+        tool_call_id: String,
+    },
+    /// What a tool call returned
+    ToolCallResult {
+        /// ID of the tool message holding the result
+        message_id: String,
+        /// The tool call
+        tool_call_id: String,
+        /// Result, as JSON text
+        content: String,
+        /// Always "tool"
+        role: String,
+    },
+    /// The whole state
+    StateSnapshot {
+        /// The state
+        snapshot: Value,
+    },
+    /// Changes to the state, as JSON Patch operations
+    StateDelta {
+        /// The operations
+        delta: Vec<Value>,
+    },
+    /// An ARES event without an AG-UI equivalent
+    Custom {
+        /// Event name
+        name: String,
+        /// Event data
+        value: Value,
+    },
+}
+
+impl AgUiEvent {
+    fn sse(&self) -> std::result::Result<Event, Infallible> {
+        Ok(Event::default().data(serde_json::to_string(self).unwrap_or_default()))
+    }
+}
+
+/// Sends an AG-UI client the tool calls of its run as they complete
+struct ToolCallEvents {
+    events: mpsc::UnboundedSender<AgUiEvent>,
+}
+
+#[async_trait]
+impl ConversationHook for ToolCallEvents {
+    fn name(&self) -> &str {
+        "agui-tool-calls"
+    }
+
+    async fn on_tool_result(&self, _ctx: &AgentContext, record: &mut ToolCallRecord) -> Result<()> {
+        for event in tool_call_events(record) {
+            // The client is gone if this fails; the run goes on
+            let _ = self.events.send(event);
+        }
+        Ok(())
+    }
+}
+
+/// The events reporting a tool call
+fn tool_call_events(record: &ToolCallRecord) -> Vec<AgUiEvent> {
+    let tool_call_id = match record.id.is_empty() {
+        true => Uuid::new_v4().to_string(),
+        false => record.id.clone(),
+    };
+    let result = match &record.error {
+        Some(error) if record.result.is_null() => json!({ "error": error }),
+        _ => record.result.clone(),
+    };
+    vec![
+        AgUiEvent::ToolCallStart {
+            tool_call_id: tool_call_id.clone(),
+            tool_call_name: record.name.clone(),
+        },
+        AgUiEvent::ToolCallArgs {
+            tool_call_id: tool_call_id.clone(),
+            delta: record.arguments.to_string(),
+        },
+        AgUiEvent::ToolCallEnd {
+            tool_call_id: tool_call_id.clone(),
+        },
+        AgUiEvent::ToolCallResult {
+            message_id: Uuid::new_v4().to_string(),
+            tool_call_id,
+            content: result.to_string(),
+            role: "tool".to_string(),
+        },
+    ]
+}
+
+/// The text of the last message, which must be the user's
+fn user_message(messages: &[AgUiMessage]) -> Result<String> {
+    let last = messages
+        .last()
+        .ok_or_else(|| AppError::InvalidInput("messages must not be empty".to_string()))?;
+    if last.role != "user" {
+        return Err(AppError::InvalidInput(format!(
+            "The last message must be the user's, not a '{}' message",
+            last.role
+        )));
+    }
+    let text = match &last.content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter(|part| part["type"] == "text")
+            .filter_map(|part| part["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    };
+    if text.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "The last message has no text".to_string(),
+        ));
+    }
+    Ok(text)
+}
+
+/// The events reporting an answer, before `RUN_FINISHED`
+fn answer_events(response: ChatResponse) -> Vec<AgUiEvent> {
+    let mut events = Vec::new();
+    match &response.approval {
+        Some(approval) => events.push(AgUiEvent::Custom {
+            name: "approval_required".to_string(),
+            value: serde_json::to_value(approval).unwrap_or_default(),
+        }),
+        None => {
+            let message_id = response
+                .message_id
+                .clone()
+                .unwrap_or_else(|| Uuid::new_v4().to_string());
+            events.push(AgUiEvent::TextMessageStart {
+                message_id: message_id.clone(),
+                role: "assistant".to_string(),
+            });
+            // AG-UI doesn't allow empty content events
+            if !response.response.is_empty() {
+                events.push(AgUiEvent::TextMessageContent {
+                    message_id: message_id.clone(),
+                    delta: response.response.clone(),
+                });
+            }
+            events.push(AgUiEvent::TextMessageEnd { message_id });
+        }
+    }
+    events.push(AgUiEvent::StateDelta {
+        delta: vec![json!({
+            "op": "add",
+            "path": "/ares",
+            "value": {
+                "agent": response.agent,
+                "conversationId": response.context_id,
+                "messageId": response.message_id,
+                "sources": response.sources,
+                "cached": response.cached,
+                "limitExceeded": response.limit_exceeded,
+            },
+        })],
+    });
+    events
+}
+
+/// Run an agent for an AG-UI client.
+///
+/// Streams AG-UI events as Server-Sent Events; see the module docs for
+/// their order.
+#[utoipa::path(
+    post,
+    path = "/api/agui",
+    request_body = RunAgentInput,
+    responses(
+        (status = 200, description = "Stream of AG-UI events"),
+        (status = 400, description = "No user message to answer"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Encrypted conversation without its secret, or a wrong one"),
+        (status = 503, description = "Server in maintenance mode")
+    ),
+    tag = "chat",
+    security(("bearer" = []))
+)]
+pub async fn run(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    secret: ConversationSecret,
+    tenant_ctx: Option<Extension<TenantContext>>,
+    Json(input): Json<RunAgentInput>,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    run_agent(state, claims, workspace, secret, tenant_ctx, None, input).await
+}
+
+/// Run a given agent for an AG-UI client.
+#[utoipa::path(
+    post,
+    path = "/api/agui/{agent}",
+    params(("agent" = String, Path, description = "Agent to run")),
+    request_body = RunAgentInput,
+    responses(
+        (status = 200, description = "Stream of AG-UI events"),
+        (status = 400, description = "No user message to answer"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Agent not found"),
+        (status = 503, description = "Server in maintenance mode")
+    ),
+    tag = "chat",
+    security(("bearer" = []))
+)]
+pub async fn run_named(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    secret: ConversationSecret,
+    tenant_ctx: Option<Extension<TenantContext>>,
+    Path(agent): Path<String>,
+    Json(input): Json<RunAgentInput>,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    check_assistant(&state, &workspace.owner(&claims.sub), &Some(agent.clone())).await?;
+    let agent = AgentType::from_string(&agent);
+    run_agent(
+        state,
+        claims,
+        workspace,
+        secret,
+        tenant_ctx,
+        Some(agent),
+        input,
+    )
+    .await
+}
+
+async fn run_agent(
+    state: AppState,
+    claims: crate::types::Claims,
+    workspace: ActiveWorkspace,
+    secret: ConversationSecret,
+    tenant_ctx: Option<Extension<TenantContext>>,
+    agent: Option<AgentType>,
+    input: RunAgentInput,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    maintenance::ensure_available(&state)?;
+    if input.thread_id.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "threadId must not be empty".to_string(),
+        ));
+    }
+    let message = user_message(&input.messages)?;
+    let payload = ChatRequest {
+        message,
+        agent_type: agent.or(input.forwarded_props.agent_type),
+        context_id: Some(input.thread_id.clone()),
+        seed: input.forwarded_props.seed,
+        persona: input.forwarded_props.persona,
+        file_ids: Vec::new(),
+        project_id: None,
+        parameters: Default::default(),
+    };
+    let snapshot = match input.state {
+        Value::Object(state) => Value::Object(state),
+        _ => json!({}),
+    };
+    let tenant_id = tenant_ctx.map(|Extension(tc)| tc.tenant_id);
+
+    // The run reports its tool calls through a hook of its own
+    let (events_tx, mut events) = mpsc::unbounded_channel();
+    let mut hooks = (*state.hooks).clone();
+    hooks.register(Arc::new(ToolCallEvents { events: events_tx }));
+    let mut run_state = state;
+    run_state.hooks = Arc::new(hooks);
+
+    let (thread_id, run_id) = (input.thread_id, input.run_id);
+    let stream = async_stream::stream! {
+        yield AgUiEvent::RunStarted { thread_id: thread_id.clone(), run_id: run_id.clone() }.sse();
+        yield AgUiEvent::StateSnapshot { snapshot }.sse();
+
+        // Cancelled when the SSE stream is dropped (client disconnect)
+        let cancellation = CancellationToken::new();
+        let _cancel_on_drop = cancellation.clone().drop_guard();
+        let run = answer(&run_state, &claims, &workspace, &secret, tenant_id, payload, cancellation);
+        tokio::pin!(run);
+        let result = loop {
+            let event = tokio::select! {
+                biased;
+                Some(event) = events.recv() => event,
+                result = &mut run => break result,
+            };
+            yield event.sse();
+        };
+        while let Ok(event) = events.try_recv() {
+            yield event.sse();
+        }
+
+        match result {
+            Ok(answer) => {
+                for event in answer_events(answer.response) {
+                    yield event.sse();
+                }
+                yield AgUiEvent::RunFinished { thread_id, run_id }.sse();
+            }
+            Err(e) => {
+                let code = serde_json::to_value(e.code())
+                    .ok()
+                    .and_then(|code| code.as_str().map(str::to_string));
+                yield AgUiEvent::RunError { message: e.to_string(), code }.sse();
+            }
+        }
+    };
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_follow_the_protocol() {
+        let event = AgUiEvent::ToolCallStart {
+            tool_call_id: "call_1".to_string(),
+            tool_call_name: "calculator".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({"type": "TOOL_CALL_START", "toolCallId": "call_1", "toolCallName": "calculator"})
+        );
+
+        let record = ToolCallRecord {
+            id: "call_1".to_string(),
+            name: "calculator".to_string(),
+            arguments: json!({"expression": "2+2"}),
+            result: json!(4),
+            success: true,
+            duration_ms: 3,
+            error: None,
+            iteration: 1,
+        };
+        let events = serde_json::to_value(tool_call_events(&record)).unwrap();
+        let types: Vec<&str> = events
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "TOOL_CALL_START",
+                "TOOL_CALL_ARGS",
+                "TOOL_CALL_END",
+                "TOOL_CALL_RESULT"
+            ]
+        );
+        assert_eq!(events[1]["delta"], r#"{"expression":"2+2"}"#);
+        assert_eq!(events[3]["content"], "4");
+    }
+
+    #[test]
+    fn test_last_message_is_answered() {
+        let messages: Vec<AgUiMessage> = serde_json::from_value(json!([
+            {"id": "1", "role": "user", "content": "Hi"},
+            {"id": "2", "role": "assistant", "content": "Hello!"},
+            {"id": "3", "role": "user", "content": [
+                {"type": "text", "text": "What's in"},
+                {"type": "binary", "mimeType": "image/png", "data": "..."},
+                {"type": "text", "text": "this image?"}
+            ]}
+        ]))
+        .unwrap();
+        assert_eq!(user_message(&messages).unwrap(), "What's in\nthis image?");
+        assert!(user_message(&messages[..2]).is_err());
+        assert!(user_message(&[]).is_err());
+    }
+}
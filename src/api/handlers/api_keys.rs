@@ -0,0 +1,182 @@
+//! API key handlers.
+//!
+//! Users issue API keys so services can call ARES without the login flow:
+//! a request with the key in the `X-API-Key` header acts as the user, on
+//! the routes the key's scopes cover (see [`crate::db::user_api_keys`]).
+//! Keys are managed with a JWT only; a key can't manage keys.
+
+use crate::{
+    auth::middleware::AuthUser,
+    db::user_api_keys::{self, ApiKeyScope, UserApiKey},
+    types::{AppError, Result},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Longest accepted key name
+const MAX_KEY_NAME_LEN: usize = 100;
+
+/// Request to issue an API key.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateApiKeyRequest {
+    /// What the key is for, e.g. the service using it
+    pub name: String,
+    /// Routes the key may call: `chat`, `rag` or both
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+/// An API key with its secret, shown only when issued or rotated.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IssuedApiKey {
+    /// The key
+    #[serde(flatten)]
+    pub key: UserApiKey,
+    /// The secret to send in the `X-API-Key` header
+    pub secret: String,
+}
+
+/// One of the user's API keys
+async fn load_key(state: &AppState, id: &str, user_id: &str) -> Result<UserApiKey> {
+    user_api_keys::get_key(state.tenant_db.pool(), id, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("API key {} not found", id)))
+}
+
+/// List the user's API keys, with their usage.
+#[utoipa::path(
+    get,
+    path = "/api/api-keys",
+    responses(
+        (status = 200, description = "API keys, newest first", body = Vec<UserApiKey>),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "api-keys",
+    security(("bearer" = []))
+)]
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Vec<UserApiKey>>> {
+    Ok(Json(
+        user_api_keys::list_keys(state.tenant_db.pool(), &claims.sub).await?,
+    ))
+}
+
+/// Issue an API key.
+///
+/// The secret is only returned here; store it, it can't be shown again.
+#[utoipa::path(
+    post,
+    path = "/api/api-keys",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 201, description = "API key issued", body = IssuedApiKey),
+        (status = 400, description = "Invalid name, or no scopes"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "api-keys",
+    security(("bearer" = []))
+)]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<IssuedApiKey>)> {
+    let name = payload.name.trim();
+    if name.is_empty() || name.len() > MAX_KEY_NAME_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "API key name must be 1-{} characters",
+            MAX_KEY_NAME_LEN
+        )));
+    }
+    let mut scopes = Vec::new();
+    for scope in payload.scopes {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    if scopes.is_empty() {
+        return Err(AppError::InvalidInput(
+            "An API key needs at least one scope".to_string(),
+        ));
+    }
+
+    let (key_hash, key_prefix, secret) = user_api_keys::generate_key();
+    let key = UserApiKey {
+        id: Uuid::new_v4().to_string(),
+        user_id: claims.sub,
+        name: name.to_string(),
+        key_prefix,
+        scopes,
+        request_count: 0,
+        last_used_at: None,
+        created_at: Utc::now().timestamp(),
+        rotated_at: None,
+    };
+    user_api_keys::insert_key(state.tenant_db.pool(), &key, &key_hash).await?;
+
+    Ok((StatusCode::CREATED, Json(IssuedApiKey { key, secret })))
+}
+
+/// Replace an API key's secret, keeping its name, scopes and usage.
+///
+/// The old secret stops working at once.
+#[utoipa::path(
+    post,
+    path = "/api/api-keys/{id}/rotate",
+    params(("id" = String, Path, description = "API key ID")),
+    responses(
+        (status = 200, description = "API key rotated", body = IssuedApiKey),
+        (status = 404, description = "API key not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "api-keys",
+    security(("bearer" = []))
+)]
+pub async fn rotate_api_key(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<IssuedApiKey>> {
+    let (key_hash, key_prefix, secret) = user_api_keys::generate_key();
+    let now = Utc::now().timestamp();
+    let pool = state.tenant_db.pool();
+    if !user_api_keys::rotate_key(pool, &id, &claims.sub, &key_hash, &key_prefix, now).await? {
+        return Err(AppError::NotFound(format!("API key {} not found", id)));
+    }
+    let key = load_key(&state, &id, &claims.sub).await?;
+    Ok(Json(IssuedApiKey { key, secret }))
+}
+
+/// Revoke an API key.
+#[utoipa::path(
+    delete,
+    path = "/api/api-keys/{id}",
+    params(("id" = String, Path, description = "API key ID")),
+    responses(
+        (status = 204, description = "API key revoked"),
+        (status = 404, description = "API key not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "api-keys",
+    security(("bearer" = []))
+)]
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    let now = Utc::now().timestamp();
+    if !user_api_keys::revoke_key(state.tenant_db.pool(), &id, &claims.sub, now).await? {
+        return Err(AppError::NotFound(format!("API key {} not found", id)));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,129 @@
+//! Tool call approval handlers.
+//!
+//! A chat run reaching a tool call that needs approval pauses, and its
+//! response carries the approval it waits on. The user lists the runs
+//! waiting on them and approves or rejects each one, which resumes the run.
+//! See [`crate::agents::approval`].
+
+use crate::{
+    agents::approval::{ApprovalDecision, PendingApproval},
+    api::{
+        encryption::ConversationSecret,
+        handlers::{chat::resume_run, threads::approval_decided},
+    },
+    auth::middleware::AuthUser,
+    db::approvals,
+    models::TenantContext,
+    types::{AppError, ChatResponse, Result},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+/// A user's decision on a paused run's tool calls.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ApprovalRequest {
+    /// Whether to run the calls; rejected calls are refused and the agent
+    /// carries on without them
+    pub approve: bool,
+    /// Why the calls were rejected, passed on to the agent
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// List the runs waiting for the user's approval.
+#[utoipa::path(
+    get,
+    path = "/api/approvals",
+    responses(
+        (status = 200, description = "Pending approvals, oldest first", body = Vec<PendingApproval>),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "approvals",
+    security(("bearer" = []))
+)]
+pub async fn list_approvals(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Vec<PendingApproval>>> {
+    let pending = approvals::list_pending(state.tenant_db.pool(), &claims.sub).await?;
+    Ok(Json(
+        pending
+            .iter()
+            .map(approvals::RunApproval::pending)
+            .collect::<Result<_>>()?,
+    ))
+}
+
+/// Approve or reject a paused run's tool calls and resume the run.
+///
+/// Answers with the resumed run's response, which may wait on another
+/// approval.
+#[utoipa::path(
+    post,
+    path = "/api/approvals/{id}",
+    params(("id" = String, Path, description = "Approval ID")),
+    request_body = ApprovalRequest,
+    responses(
+        (status = 200, description = "Run resumed", body = ChatResponse),
+        (status = 400, description = "Approval already decided"),
+        (status = 404, description = "Approval not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "approvals",
+    security(("bearer" = []))
+)]
+pub async fn decide_approval(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    secret: ConversationSecret,
+    tenant_ctx: Option<Extension<TenantContext>>,
+    Path(id): Path<String>,
+    Json(payload): Json<ApprovalRequest>,
+) -> Result<Json<ChatResponse>> {
+    let pool = state.tenant_db.pool();
+    let approval = approvals::get_approval(pool, &id, &claims.sub)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Approval '{}' not found", id)))?;
+
+    let reason = payload
+        .reason
+        .map(|reason| reason.trim().to_string())
+        .filter(|reason| !reason.is_empty());
+    let (status, decision) = match payload.approve {
+        true => (approvals::APPROVED, ApprovalDecision::Approve),
+        false => (
+            approvals::REJECTED,
+            ApprovalDecision::Reject(reason.clone()),
+        ),
+    };
+    // Only the first decision resumes the run
+    if !approvals::decide(pool, &id, status, reason.as_deref(), Utc::now().timestamp()).await? {
+        return Err(AppError::InvalidInput(format!(
+            "Approval '{}' was already decided",
+            id
+        )));
+    }
+
+    let tenant_id = tenant_ctx
+        .map(|Extension(tc)| tc.tenant_id)
+        .unwrap_or_else(|| "system".to_string());
+    let response = resume_run(
+        &state,
+        &claims,
+        &secret,
+        tenant_id,
+        &approval.conversation_id,
+        &approval.message,
+        approval.paused()?,
+        decision,
+    )
+    .await;
+    approval_decided(&state, &id, &response).await;
+    Ok(Json(response?))
+}
@@ -1,27 +1,112 @@
 use crate::{
-    db::traits::DatabaseClient,
+    api::handlers::mfa::{self, MfaChallenge},
+    auth::cookies,
+    db::{
+        roles,
+        sessions::SessionClient,
+        traits::DatabaseClient,
+    },
     types::{AppError, LoginRequest, RegisterRequest, Result, TokenResponse},
     AppState,
 };
-use axum::{extract::State, Json};
-use serde::Deserialize;
+use axum::{extract::State, http::HeaderMap, Json};
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
 /// Request payload for refreshing an access token
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct RefreshTokenRequest {
-    /// The refresh token issued during login or registration
+    /// The refresh token issued during login or registration. Leave empty
+    /// to use the refresh cookie, with the `X-CSRF-Token` header.
+    #[serde(default)]
     pub refresh_token: String,
 }
 
+/// Respond with new tokens, moving the refresh token into cookies when
+/// `[server.security] cookie_refresh_tokens` is on.
+fn issue(state: &AppState, mut tokens: TokenResponse) -> (HeaderMap, Json<TokenResponse>) {
+    if !state
+        .config_manager
+        .config()
+        .server
+        .security
+        .cookie_refresh_tokens
+    {
+        return (HeaderMap::new(), Json(tokens));
+    }
+    let headers =
+        cookies::session_cookies(&tokens.refresh_token, state.auth_service.refresh_expiry());
+    tokens.refresh_token.clear();
+    (headers, Json(tokens))
+}
+
+/// Response to a login or registration: the session's tokens, or a
+/// challenge for the second factor when the user has MFA.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    /// The login is complete
+    Tokens(TokenResponse),
+    /// The login needs a second factor first
+    Mfa(MfaChallenge),
+}
+
+/// Start a session for a user whose credentials were checked
+pub(crate) async fn start_session(
+    state: &AppState,
+    user_id: &str,
+    email: &str,
+    client: &SessionClient,
+) -> Result<(HeaderMap, Json<TokenResponse>)> {
+    // Generate tokens carrying the user's role
+    let pool = state.tenant_db.pool();
+    let role = roles::get_role(pool, user_id).await?;
+    let tokens = state
+        .auth_service
+        .generate_tokens(user_id, email, role)?;
+
+    // Store refresh token in a session for the device
+    let token_hash = state.auth_service.hash_token(&tokens.refresh_token);
+    let session_id = Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().timestamp();
+    state
+        .sessions
+        .create(
+            &session_id,
+            user_id,
+            &token_hash,
+            now + tokens.expires_in,
+            client,
+            now,
+        )
+        .await?;
+
+    Ok(issue(state, tokens))
+}
+
+/// Challenge a user with the right password for their second factor, or
+/// start their session
+async fn complete_login(
+    state: &AppState,
+    user_id: &str,
+    email: &str,
+    client: &SessionClient,
+) -> Result<(HeaderMap, Json<LoginResponse>)> {
+    if let Some(challenge) = mfa::login_challenge(state, user_id).await? {
+        return Ok((HeaderMap::new(), Json(LoginResponse::Mfa(challenge))));
+    }
+    let (headers, Json(tokens)) = start_session(state, user_id, email, client).await?;
+    Ok((headers, Json(LoginResponse::Tokens(tokens))))
+}
+
 /// Register a new user
 #[utoipa::path(
     post,
     path = "/api/auth/register",
     request_body = RegisterRequest,
     responses(
-        (status = 200, description = "User registered successfully", body = TokenResponse),
+        (status = 200, description = "User registered successfully, or MFA enrollment required", body = LoginResponse),
         (status = 400, description = "Invalid input"),
         (status = 409, description = "User already exists")
     ),
@@ -29,8 +114,9 @@ pub struct RefreshTokenRequest {
 )]
 pub async fn register(
     State(state): State<AppState>,
+    client: SessionClient,
     Json(payload): Json<RegisterRequest>,
-) -> Result<Json<TokenResponse>> {
+) -> Result<(HeaderMap, Json<LoginResponse>)> {
     // Validate input
     if payload.email.is_empty() || payload.password.len() < 8 {
         return Err(AppError::InvalidInput(
@@ -58,25 +144,8 @@ pub async fn register(
         .create_user(&user_id, &payload.email, &password_hash, &payload.name)
         .await?;
 
-    // Generate tokens
-    let tokens = state
-        .auth_service
-        .generate_tokens(&user_id, &payload.email)?;
-
-    // Store refresh token
-    let token_hash = state.auth_service.hash_token(&tokens.refresh_token);
-    let session_id = Uuid::new_v4().to_string();
-    state
-        .db
-        .create_session(
-            &session_id,
-            &user_id,
-            &token_hash,
-            chrono::Utc::now().timestamp() + tokens.expires_in,
-        )
-        .await?;
-
-    Ok(Json(tokens))
+    // New users are members; with MFA required, they enroll first
+    complete_login(&state, &user_id, &payload.email, &client).await
 }
 
 /// Login with email and password
@@ -85,15 +154,16 @@ pub async fn register(
     path = "/api/auth/login",
     request_body = LoginRequest,
     responses(
-        (status = 200, description = "Login successful", body = TokenResponse),
+        (status = 200, description = "Login successful, or a second factor required", body = LoginResponse),
         (status = 401, description = "Invalid credentials")
     ),
     tag = "auth"
 )]
 pub async fn login(
     State(state): State<AppState>,
+    client: SessionClient,
     Json(payload): Json<LoginRequest>,
-) -> Result<Json<TokenResponse>> {
+) -> Result<(HeaderMap, Json<LoginResponse>)> {
     // Get user
     let user = state
         .db
@@ -109,29 +179,15 @@ pub async fn login(
         return Err(AppError::Auth("Invalid credentials".to_string()));
     }
 
-    // Generate tokens
-    let tokens = state.auth_service.generate_tokens(&user.id, &user.email)?;
-
-    // Store refresh token
-    let token_hash = state.auth_service.hash_token(&tokens.refresh_token);
-    let session_id = Uuid::new_v4().to_string();
-    state
-        .db
-        .create_session(
-            &session_id,
-            &user.id,
-            &token_hash,
-            chrono::Utc::now().timestamp() + tokens.expires_in,
-        )
-        .await?;
-
-    Ok(Json(tokens))
+    complete_login(&state, &user.id, &user.email, &client).await
 }
 
 /// Request payload for logout
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct LogoutRequest {
-    /// The refresh token to invalidate
+    /// The refresh token to invalidate. Leave empty to use the refresh
+    /// cookie, with the `X-CSRF-Token` header.
+    #[serde(default)]
     pub refresh_token: String,
 }
 
@@ -149,27 +205,31 @@ pub struct LogoutResponse {
     request_body = LogoutRequest,
     responses(
         (status = 200, description = "Logout successful", body = LogoutResponse),
-        (status = 401, description = "Invalid token")
+        (status = 401, description = "Invalid token"),
+        (status = 403, description = "Refresh cookie sent without a valid CSRF token")
     ),
     tag = "auth"
 )]
 pub async fn logout(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<LogoutRequest>,
-) -> Result<Json<LogoutResponse>> {
+) -> Result<(HeaderMap, Json<LogoutResponse>)> {
+    let refresh_token = cookies::refresh_token(&headers, &payload.refresh_token)?;
+
     // Hash the refresh token and delete the session
-    let token_hash = state.auth_service.hash_token(&payload.refresh_token);
+    let token_hash = state.auth_service.hash_token(refresh_token);
 
     // Attempt to delete the session - we don't error if it doesn't exist
     // (token may already be expired/revoked, which is fine for logout)
-    state
-        .db
-        .delete_session_by_token_hash(&token_hash)
-        .await?;
+    state.sessions.end(&token_hash).await?;
 
-    Ok(Json(LogoutResponse {
-        message: "Logged out successfully".to_string(),
-    }))
+    Ok((
+        cookies::cleared_cookies(),
+        Json(LogoutResponse {
+            message: "Logged out successfully".to_string(),
+        }),
+    ))
 }
 
 /// Refresh access token
@@ -179,24 +239,28 @@ pub async fn logout(
     request_body = RefreshTokenRequest,
     responses(
         (status = 200, description = "Token refreshed successfully", body = TokenResponse),
-        (status = 401, description = "Invalid or expired refresh token")
+        (status = 401, description = "Invalid or expired refresh token"),
+        (status = 403, description = "Refresh cookie sent without a valid CSRF token")
     ),
     tag = "auth"
 )]
 pub async fn refresh_token(
     State(state): State<AppState>,
+    headers: HeaderMap,
+    client: SessionClient,
     Json(payload): Json<RefreshTokenRequest>,
-) -> Result<Json<TokenResponse>> {
-    let refresh_token = &payload.refresh_token;
+) -> Result<(HeaderMap, Json<TokenResponse>)> {
+    let refresh_token = cookies::refresh_token(&headers, &payload.refresh_token)?;
 
     // Verify refresh token JWT signature and expiry
     let claims = state.auth_service.verify_token(refresh_token)?;
 
-    // Hash the refresh token and validate it exists in the database
+    // Hash the refresh token and validate it belongs to a live session
     let token_hash = state.auth_service.hash_token(refresh_token);
+    let now = chrono::Utc::now().timestamp();
     let user_id = state
-        .db
-        .validate_session(&token_hash)
+        .sessions
+        .user_of(&token_hash, now)
         .await?
         .ok_or_else(|| AppError::Auth("Refresh token has been revoked or expired".to_string()))?;
 
@@ -205,29 +269,45 @@ pub async fn refresh_token(
         return Err(AppError::Auth("Token mismatch".to_string()));
     }
 
-    // Invalidate the old refresh token (one-time use)
-    state
-        .db
-        .delete_session_by_token_hash(&token_hash)
-        .await?;
-
-    // Generate new tokens
+    // Generate new tokens, picking up a changed role
+    let pool = state.tenant_db.pool();
+    let role = roles::get_role(pool, &claims.sub).await?;
     let tokens = state
         .auth_service
-        .generate_tokens(&claims.sub, &claims.email)?;
+        .generate_tokens(&claims.sub, &claims.email, role)?;
 
-    // Store the new refresh token in a new session
+    // Swap the session's refresh token for the new one, so the old one
+    // can't be used again and the session keeps its ID
     let new_token_hash = state.auth_service.hash_token(&tokens.refresh_token);
-    let session_id = Uuid::new_v4().to_string();
-    state
-        .db
-        .create_session(
-            &session_id,
-            &claims.sub,
+    if !state
+        .sessions
+        .rotate(
+            &token_hash,
             &new_token_hash,
-            chrono::Utc::now().timestamp() + tokens.expires_in,
+            now + tokens.expires_in,
+            &client,
+            now,
         )
-        .await?;
+        .await?
+    {
+        return Err(AppError::Auth(
+            "Refresh token has been revoked or expired".to_string(),
+        ));
+    }
 
-    Ok(Json(tokens))
+    Ok(issue(&state, tokens))
+}
+
+/// Public key verifying the service tokens ARES sends to the systems it
+/// calls, as a JWK set
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    responses(
+        (status = 200, description = "JWK set with the Ed25519 verification key")
+    ),
+    tag = "auth"
+)]
+pub async fn jwks(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(state.service_tokens.jwks())
 }
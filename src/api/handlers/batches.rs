@@ -0,0 +1,621 @@
+//! Batch chat: many messages answered by an agent at once.
+//!
+//! `POST /api/chat/batch` takes up to `[batch] max_items` messages, for
+//! offline evaluation or bulk processing, and answers each exactly as `POST
+//! /api/chat` would, `[batch] concurrency` at a time. Each message is
+//! answered in its own new conversation unless it names one with
+//! `context_id`.
+//!
+//! Batches of up to `[batch] inline_items` messages are answered before the
+//! request returns. Larger ones, or any sent with `"background": true`, are
+//! accepted with a 202 and run in the background: poll `GET
+//! /api/chat/batch/{id}` until the batch is `completed` or `cancelled`.
+//!
+//! A failed message doesn't stop the batch; it is reported `failed` with
+//! its error, and the batch's counts tell how many of each outcome there
+//! were.
+
+use crate::{
+    api::{
+        encryption::ConversationSecret,
+        handlers::{chat::answer, threads::check_assistant},
+        maintenance,
+        workspace::ActiveWorkspace,
+    },
+    auth::middleware::AuthUser,
+    db::batches::{self, BatchItem, ChatBatch},
+    llm::cancellation::CancellationToken,
+    models::TenantContext,
+    types::{AgentType, AppError, ChatRequest, Claims, GenerationParameters, Result},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::Utc;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// A batch of messages to answer.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchChatRequest {
+    /// Agent answering every message; without one each is routed as in
+    /// `POST /api/chat`
+    #[serde(default)]
+    pub agent_type: Option<AgentType>,
+    /// Messages to answer, at most `[batch] max_items`
+    pub items: Vec<BatchItemRequest>,
+    /// Generation parameters for every message, within the agent's limits
+    #[serde(default)]
+    pub parameters: GenerationParameters,
+    /// Run in the background even when small enough to answer inline
+    #[serde(default)]
+    pub background: bool,
+}
+
+/// A message of a batch.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchItemRequest {
+    /// The client's ID for the message, returned with its answer
+    #[serde(default)]
+    pub custom_id: Option<String>,
+    /// The message
+    pub message: String,
+    /// Conversation to answer in (default: a new one)
+    #[serde(default)]
+    pub context_id: Option<String>,
+    /// Sampling seed for a reproducible answer
+    #[serde(default)]
+    pub seed: Option<u32>,
+}
+
+/// A batch and the outcome of its messages.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Batch {
+    /// Batch ID
+    pub id: String,
+    /// `queued`, `in_progress`, `cancelling`, `cancelled` or `completed`
+    pub status: String,
+    /// Agent answering; `None` when messages are routed
+    pub agent_type: Option<String>,
+    /// How many messages have each outcome
+    pub counts: BatchCounts,
+    /// When the batch was created (Unix timestamp)
+    pub created_at: i64,
+    /// When its first message started (Unix timestamp)
+    pub started_at: Option<i64>,
+    /// When the batch finished (Unix timestamp)
+    pub completed_at: Option<i64>,
+    /// The messages, in the order they were sent
+    pub items: Vec<BatchItemResult>,
+}
+
+/// How many messages of a batch have each outcome.
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct BatchCounts {
+    /// Messages in the batch
+    pub total: usize,
+    /// Messages answered
+    pub completed: usize,
+    /// Messages whose answer failed
+    pub failed: usize,
+    /// Messages not answered because the batch was cancelled
+    pub cancelled: usize,
+    /// Messages waiting for their tool calls to be approved
+    pub requires_action: usize,
+}
+
+/// A message of a batch and its outcome.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchItemResult {
+    /// Position of the message in the batch, from 0
+    pub index: usize,
+    /// The client's ID for the message
+    pub custom_id: Option<String>,
+    /// `queued`, `in_progress`, `completed`, `failed`, `cancelled` or
+    /// `requires_action`
+    pub status: String,
+    /// Conversation the message is answered in
+    pub context_id: Option<String>,
+    /// Agent that answered
+    pub agent: Option<String>,
+    /// The answer
+    pub response: Option<String>,
+    /// ID of the stored answer
+    pub message_id: Option<String>,
+    /// Approval to decide with `POST /api/approvals/{id}` when the answer
+    /// waits for its tool calls to be approved
+    pub approval_id: Option<String>,
+    /// Why the answer failed
+    pub error: Option<String>,
+}
+
+impl Batch {
+    fn new(batch: ChatBatch, items: Vec<BatchItem>) -> Self {
+        let mut counts = BatchCounts {
+            total: items.len(),
+            ..Default::default()
+        };
+        for item in &items {
+            match item.status.as_str() {
+                batches::COMPLETED => counts.completed += 1,
+                batches::FAILED => counts.failed += 1,
+                batches::CANCELLED => counts.cancelled += 1,
+                batches::REQUIRES_ACTION => counts.requires_action += 1,
+                _ => {}
+            }
+        }
+        Batch {
+            id: batch.id,
+            status: batch.status,
+            agent_type: batch.agent_type,
+            counts,
+            created_at: batch.created_at,
+            started_at: batch.started_at,
+            completed_at: batch.completed_at,
+            items: items
+                .into_iter()
+                .map(|item| BatchItemResult {
+                    index: item.position as usize,
+                    custom_id: item.custom_id,
+                    status: item.status,
+                    context_id: item.context_id,
+                    agent: item.agent,
+                    response: item.response,
+                    message_id: item.message_id,
+                    approval_id: item.approval_id,
+                    error: item.error,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Answer a batch of messages.
+///
+/// Small batches are answered before the request returns (200); larger
+/// ones run in the background (202) and are polled with `GET
+/// /api/chat/batch/{id}`.
+#[utoipa::path(
+    post,
+    path = "/api/chat/batch",
+    request_body = BatchChatRequest,
+    responses(
+        (status = 200, description = "Batch answered", body = Batch),
+        (status = 202, description = "Batch running in the background", body = Batch),
+        (status = 400, description = "No messages, too many, or an invalid one"),
+        (status = 404, description = "Agent not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 503, description = "Server in maintenance mode")
+    ),
+    tag = "chat",
+    security(("bearer" = []))
+)]
+pub async fn create_batch(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    tenant_ctx: Option<Extension<TenantContext>>,
+    Json(payload): Json<BatchChatRequest>,
+) -> Result<Response> {
+    maintenance::ensure_available(&state)?;
+    let config = state.config_manager.config().batch.clone();
+    validate(&payload, config.max_items)?;
+    let agent_type = payload.agent_type.map(|agent| agent.as_str().to_string());
+    check_assistant(&state, &workspace.owner(&claims.sub), &agent_type).await?;
+
+    let now = Utc::now().timestamp();
+    let batch = ChatBatch {
+        id: Uuid::new_v4().to_string(),
+        user_id: claims.sub.clone(),
+        workspace_id: workspace.id().map(str::to_string),
+        agent_type,
+        status: batches::QUEUED.to_string(),
+        created_at: now,
+        started_at: None,
+        completed_at: None,
+    };
+    let items: Vec<BatchItem> = payload
+        .items
+        .into_iter()
+        .enumerate()
+        .map(|(position, item)| BatchItem {
+            batch_id: batch.id.clone(),
+            position: position as i32,
+            custom_id: item.custom_id,
+            message: item.message,
+            // Known up front, so even a failed message names its conversation
+            context_id: Some(
+                item.context_id
+                    .unwrap_or_else(|| Uuid::new_v4().to_string()),
+            ),
+            seed: item.seed.map(i64::from),
+            status: batches::QUEUED.to_string(),
+            agent: None,
+            response: None,
+            message_id: None,
+            approval_id: None,
+            error: None,
+            started_at: None,
+            completed_at: None,
+        })
+        .collect();
+    batches::insert_batch(state.tenant_db.pool(), &batch, &items).await?;
+
+    let inline = !payload.background && items.len() <= config.inline_items;
+    let queued = Batch::new(batch.clone(), items.clone());
+    let cancellation = CancellationToken::new();
+    let task = tokio::spawn(run_batch(
+        state.clone(),
+        claims,
+        workspace,
+        tenant_ctx.map(|Extension(tc)| tc.tenant_id),
+        batch,
+        items,
+        payload.parameters,
+        config.concurrency,
+        cancellation.clone(),
+    ));
+    if !inline {
+        return Ok((StatusCode::ACCEPTED, Json(queued)).into_response());
+    }
+
+    // Cancelled when Axum drops this handler (client disconnect); the task
+    // still records which messages were answered
+    let _cancel_on_drop = cancellation.drop_guard();
+    let (batch, items) = task
+        .await
+        .map_err(|e| AppError::Internal(format!("Batch failed: {}", e)))?;
+    Ok(Json(Batch::new(batch, items)).into_response())
+}
+
+/// Check a batch's size and messages
+fn validate(payload: &BatchChatRequest, max_items: usize) -> Result<()> {
+    if payload.items.is_empty() {
+        return Err(AppError::InvalidInput(
+            "A batch needs at least one message".to_string(),
+        ));
+    }
+    if payload.items.len() > max_items {
+        return Err(AppError::InvalidInput(format!(
+            "A batch can have at most {} messages, got {}",
+            max_items,
+            payload.items.len()
+        )));
+    }
+    let mut conversations = HashSet::new();
+    for (index, item) in payload.items.iter().enumerate() {
+        if item.message.trim().is_empty() {
+            return Err(AppError::InvalidInput(format!(
+                "Message {} of the batch is empty",
+                index
+            )));
+        }
+        // Messages answered at once mustn't interleave in one conversation
+        if let Some(context_id) = &item.context_id {
+            if !conversations.insert(context_id) {
+                return Err(AppError::InvalidInput(format!(
+                    "Conversation {} appears more than once in the batch",
+                    context_id
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Answer a batch's messages, `concurrency` at a time, recording each
+/// outcome. Returns the batch and its messages in their final status.
+#[allow(clippy::too_many_arguments)]
+async fn run_batch(
+    state: AppState,
+    claims: Claims,
+    workspace: ActiveWorkspace,
+    tenant_id: Option<String>,
+    mut batch: ChatBatch,
+    items: Vec<BatchItem>,
+    parameters: GenerationParameters,
+    concurrency: usize,
+    cancellation: CancellationToken,
+) -> (ChatBatch, Vec<BatchItem>) {
+    let pool = state.tenant_db.pool();
+    // Registered so the batch can be cancelled, and shutdown waits for it
+    let _generation = state.generations.register(
+        &generation_key(&batch.id),
+        &claims.sub,
+        cancellation.clone(),
+    );
+    batch.status = batches::IN_PROGRESS.to_string();
+    batch.started_at = Some(Utc::now().timestamp());
+    save_batch(pool, &batch).await;
+
+    let agent_type = batch.agent_type.as_deref().map(AgentType::from_string);
+    let mut items: Vec<BatchItem> = futures::stream::iter(items)
+        .map(|item| {
+            answer_item(
+                &state,
+                &claims,
+                &workspace,
+                tenant_id.clone(),
+                agent_type.clone(),
+                parameters,
+                item,
+                &cancellation,
+            )
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+    items.sort_by_key(|item| item.position);
+
+    let cancelled = items.iter().any(|item| item.status == batches::CANCELLED);
+    batch.status = match cancelled {
+        true => batches::CANCELLED,
+        false => batches::COMPLETED,
+    }
+    .to_string();
+    batch.completed_at = Some(Utc::now().timestamp());
+    save_batch(pool, &batch).await;
+    (batch, items)
+}
+
+/// Answer one message of a batch as `POST /api/chat` does
+#[allow(clippy::too_many_arguments)]
+async fn answer_item(
+    state: &AppState,
+    claims: &Claims,
+    workspace: &ActiveWorkspace,
+    tenant_id: Option<String>,
+    agent_type: Option<AgentType>,
+    parameters: GenerationParameters,
+    mut item: BatchItem,
+    cancellation: &CancellationToken,
+) -> BatchItem {
+    let pool = state.tenant_db.pool();
+    if cancellation.is_cancelled() {
+        item.status = batches::CANCELLED.to_string();
+        item.completed_at = Some(Utc::now().timestamp());
+        save_item(pool, &item).await;
+        return item;
+    }
+    item.status = batches::IN_PROGRESS.to_string();
+    item.started_at = Some(Utc::now().timestamp());
+    save_item(pool, &item).await;
+
+    let payload = ChatRequest {
+        message: item.message.clone(),
+        agent_type,
+        context_id: item.context_id.clone(),
+        seed: item.seed.map(|seed| seed as u32),
+        persona: None,
+        file_ids: Vec::new(),
+        project_id: None,
+        parameters,
+    };
+    let result = answer(
+        state,
+        claims,
+        workspace,
+        &ConversationSecret::none(),
+        tenant_id,
+        payload,
+        cancellation.child_token(),
+    )
+    .await;
+    let now = Utc::now().timestamp();
+    match result {
+        Ok(answer) => {
+            let response = answer.response;
+            item.agent = Some(response.agent);
+            match response.approval {
+                Some(approval) => {
+                    item.status = batches::REQUIRES_ACTION.to_string();
+                    item.approval_id = Some(approval.id);
+                }
+                None => {
+                    item.status = batches::COMPLETED.to_string();
+                    item.response = Some(response.response);
+                    item.message_id = response.message_id;
+                    item.completed_at = Some(now);
+                }
+            }
+        }
+        Err(AppError::Cancelled(_)) => {
+            item.status = batches::CANCELLED.to_string();
+            item.completed_at = Some(now);
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Message {} of batch {} failed: {}",
+                item.position,
+                item.batch_id,
+                e
+            );
+            item.status = batches::FAILED.to_string();
+            item.error = Some(e.to_string());
+            item.completed_at = Some(now);
+        }
+    }
+    save_item(pool, &item).await;
+    item
+}
+
+/// Get a batch, e.g. to poll its progress.
+#[utoipa::path(
+    get,
+    path = "/api/chat/batch/{id}",
+    params(("id" = String, Path, description = "Batch ID")),
+    responses(
+        (status = 200, description = "Batch", body = Batch),
+        (status = 404, description = "Batch not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "chat",
+    security(("bearer" = []))
+)]
+pub async fn get_batch(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+) -> Result<Json<Batch>> {
+    let batch = load_batch(&state, &claims, &workspace, &id).await?;
+    let items = batches::list_items(state.tenant_db.pool(), &id).await?;
+    Ok(Json(Batch::new(batch, items)))
+}
+
+/// Cancel a running batch.
+///
+/// Messages being answered stop and are `cancelled`, as are those not
+/// started; answered messages are kept. The batch is `cancelling` until
+/// its messages have stopped, then `cancelled`.
+#[utoipa::path(
+    post,
+    path = "/api/chat/batch/{id}/cancel",
+    params(("id" = String, Path, description = "Batch ID")),
+    responses(
+        (status = 200, description = "Batch cancelling", body = Batch),
+        (status = 400, description = "Batch is not running"),
+        (status = 404, description = "Batch not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "chat",
+    security(("bearer" = []))
+)]
+pub async fn cancel_batch(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+) -> Result<Json<Batch>> {
+    let batch = load_batch(&state, &claims, &workspace, &id).await?;
+    let pool = state.tenant_db.pool();
+    if !batches::request_cancel(pool, &batch.id).await? {
+        return Err(AppError::InvalidInput(format!(
+            "Batch {} is {} and can't be cancelled",
+            batch.id, batch.status
+        )));
+    }
+    // The batch's own task records it cancelled once its messages have stopped
+    state.generations.stop(&generation_key(&id), &claims.sub);
+    let batch = load_batch(&state, &claims, &workspace, &id).await?;
+    let items = batches::list_items(pool, &id).await?;
+    Ok(Json(Batch::new(batch, items)))
+}
+
+/// One of the user's batches in the active workspace
+async fn load_batch(
+    state: &AppState,
+    claims: &Claims,
+    workspace: &ActiveWorkspace,
+    id: &str,
+) -> Result<ChatBatch> {
+    batches::get_batch(state.tenant_db.pool(), id, &claims.sub, workspace.id())
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Batch {} not found", id)))
+}
+
+/// Key a batch is registered under in the active generations
+fn generation_key(batch_id: &str) -> String {
+    format!("batch:{}", batch_id)
+}
+
+/// Save a batch's status, logging failures
+async fn save_batch(pool: &sqlx::PgPool, batch: &ChatBatch) {
+    if let Err(e) = batches::update_batch(pool, batch).await {
+        tracing::warn!("Failed to save batch {}: {}", batch.id, e);
+    }
+}
+
+/// Save a message's outcome, logging failures
+async fn save_item(pool: &sqlx::PgPool, item: &BatchItem) {
+    if let Err(e) = batches::update_item(pool, item).await {
+        tracing::warn!(
+            "Failed to save message {} of batch {}: {}",
+            item.position,
+            item.batch_id,
+            e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(items: &[(&str, Option<&str>)]) -> BatchChatRequest {
+        BatchChatRequest {
+            agent_type: None,
+            items: items
+                .iter()
+                .map(|(message, context_id)| BatchItemRequest {
+                    custom_id: None,
+                    message: message.to_string(),
+                    context_id: context_id.map(str::to_string),
+                    seed: None,
+                })
+                .collect(),
+            parameters: Default::default(),
+            background: false,
+        }
+    }
+
+    #[test]
+    fn test_batches_are_validated() {
+        assert!(validate(&request(&[("a", None), ("b", None)]), 2).is_ok());
+        assert!(validate(&request(&[]), 2).is_err());
+        assert!(validate(&request(&[("a", None), ("b", None), ("c", None)]), 2).is_err());
+        assert!(validate(&request(&[("a", None), (" ", None)]), 2).is_err());
+        // Two messages can't share a conversation
+        assert!(validate(&request(&[("a", Some("c1")), ("b", Some("c1"))]), 2).is_err());
+    }
+
+    #[test]
+    fn test_counts_report_partial_failures() {
+        let now = 0;
+        let batch = ChatBatch {
+            id: "b1".to_string(),
+            user_id: "alice".to_string(),
+            workspace_id: None,
+            agent_type: None,
+            status: batches::COMPLETED.to_string(),
+            created_at: now,
+            started_at: Some(now),
+            completed_at: Some(now),
+        };
+        let item = |position: i32, status: &str| BatchItem {
+            batch_id: "b1".to_string(),
+            position,
+            custom_id: None,
+            message: "hi".to_string(),
+            context_id: None,
+            seed: None,
+            status: status.to_string(),
+            agent: None,
+            response: None,
+            message_id: None,
+            approval_id: None,
+            error: None,
+            started_at: None,
+            completed_at: None,
+        };
+        let batch = Batch::new(
+            batch,
+            vec![
+                item(0, batches::COMPLETED),
+                item(1, batches::FAILED),
+                item(2, batches::COMPLETED),
+            ],
+        );
+        assert_eq!(batch.counts.total, 3);
+        assert_eq!(batch.counts.completed, 2);
+        assert_eq!(batch.counts.failed, 1);
+        assert_eq!(batch.items[1].index, 1);
+    }
+}
@@ -1,19 +1,65 @@
 
 
+#[cfg(feature = "ares-vector")]
+use crate::api::handlers::rag::answer_cache;
 use crate::{
-    agents::{registry::AgentRegistry, router::RouterAgent, Agent},
-    api::handlers::user_agents::resolve_agent,
+    agents::{
+        approval::{ApprovalDecision, PausedRun},
+        knowledge,
+        memory as agent_memory,
+        registry::AgentRegistry,
+        router::RouterAgent,
+        Agent, HandoffOutcome,
+    },
+    api::{
+        handlers::{
+            conversations::{
+                conversation_overrides, conversation_passages, ensure_conversation,
+                restore_archived, run_scope_command, title_in_background,
+            },
+            files::attach_files,
+            user_agents::resolve_agent,
+        },
+        encryption::ConversationSecret,
+        maintenance,
+        workspace::ActiveWorkspace,
+    },
     auth::middleware::AuthUser,
-    db::agent_runs,
+    db::{
+        agent_runs,
+        analytics::{self, RequestEvent},
+        approvals,
+        checkpoints::CheckpointJournal,
+        conversation_keys,
+        feedback::{self, Generation},
+        spend, workspaces,
+    },
+    llm::{
+        cancellation::{run_cancellable, CancellationToken},
+        Sampling,
+    },
     memory::estimate_tokens,
+    rag::{
+        answer_cache::{AnswerCache, CachedAnswer},
+        attachments, scope as rag_scope,
+    },
+    tools::permissions::ToolProfile,
     types::{
-        AgentContext, AgentType, AppError, ChatRequest, ChatResponse, MessageRole, Result,
-        UserMemory,
+        AgentContext, AgentType, AppError, ChatPreferences, ChatRequest, ChatResponse,
+        Claims, ConversationOverrides, GenerationParameters,
+        MessageRole, RegenerateRequest, Result, Source, ToolCallTrace, UserMemory,
     },
-    utils::toml_config::AgentConfig,
+    utils::toml_config::{AgentConfig, BudgetsConfig},
+    workflows::{debate::DEBATE_WORKFLOW, WorkflowEngine},
     AppState,
 };
-use axum::{extract::State, response::Response, Extension, Json};
+use axum::{
+    extract::{Path, State},
+    response::Response,
+    Extension, Json,
+};
+use chrono::Utc;
+use std::sync::Arc;
 use uuid::Uuid;
 
 /// Chat with the AI assistant
@@ -24,7 +70,9 @@ use uuid::Uuid;
     responses(
         (status = 200, description = "Chat response", body = ChatResponse),
         (status = 400, description = "Invalid input"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Encrypted conversation without its secret, or a wrong one"),
+        (status = 503, description = "Server in maintenance mode")
     ),
     tag = "chat",
     security(("bearer" = []))
@@ -32,37 +80,128 @@ use uuid::Uuid;
 pub async fn chat(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    secret: ConversationSecret,
     tenant_ctx: Option<Extension<crate::models::TenantContext>>,
     Json(payload): Json<ChatRequest>,
 ) -> Result<Response> {
+    maintenance::ensure_available(&state)?;
+
+    // Cancelled when Axum drops this handler (client disconnect), aborting the generation
+    let cancellation = CancellationToken::new();
+    let _cancel_on_drop = cancellation.clone().drop_guard();
+
+    let tenant_id = tenant_ctx.map(|Extension(tc)| tc.tenant_id);
+    let answer = answer(
+        &state,
+        &claims,
+        &workspace,
+        &secret,
+        tenant_id,
+        payload,
+        cancellation,
+    )
+    .await?;
+    let mut response = Json(answer.response).into_response();
+    if let Some((input_tokens, output_tokens)) = answer.tokens {
+        response.headers_mut().insert(
+            axum::http::HeaderName::from_static("x-input-tokens"),
+            axum::http::HeaderValue::from(input_tokens),
+        );
+        response.headers_mut().insert(
+            axum::http::HeaderName::from_static("x-output-tokens"),
+            axum::http::HeaderValue::from(output_tokens),
+        );
+    }
+
+    Ok(response)
+}
+
+/// A chat message's answer
+pub(crate) struct Answer {
+    /// The response returned to the client
+    pub response: ChatResponse,
+    /// Estimated input and output tokens, when an agent generated the answer
+    pub tokens: Option<(u32, u32)>,
+}
+
+/// Answer a chat message as `POST /api/chat` does, storing the exchange in
+/// the conversation
+///
+/// The run stops when `cancellation` is cancelled, or when the user stops
+/// it with `POST /api/chat/{context_id}/stop`. A `secret` unlocks an
+/// encrypted conversation, or encrypts a new one.
+pub(crate) async fn answer(
+    state: &AppState,
+    claims: &Claims,
+    workspace: &ActiveWorkspace,
+    secret: &ConversationSecret,
+    tenant_id: Option<String>,
+    mut payload: ChatRequest,
+    cancellation: CancellationToken,
+) -> Result<Answer> {
     // Get or create conversation
     let context_id = payload
         .context_id
         .unwrap_or_else(|| Uuid::new_v4().to_string());
 
-    // Check if conversation exists, create if not
-    if !state.db.conversation_exists(&context_id).await? {
-        state
-            .db
-            .create_conversation(&context_id, &claims.sub, None)
-            .await?;
+    ensure_conversation(
+        state,
+        &context_id,
+        &claims.sub,
+        workspace,
+        payload.project_id.as_deref(),
+    )
+    .await?;
+    let key = secret.unlock_or_encrypt(state, &context_id).await?;
+    attach_files(
+        state,
+        &context_id,
+        &claims.sub,
+        workspace,
+        &payload.file_ids,
+    )
+    .await?;
+    // A "/scope" command sets the conversation's RAG scope instead of asking an agent
+    if let Some(command) = rag_scope::parse_command(&payload.message) {
+        let reply =
+            run_scope_command(state, &context_id, &claims.sub, workspace, command).await?;
+        return Ok(Answer {
+            response: ChatResponse {
+                response: reply,
+                agent: "scope".to_string(),
+                context_id,
+                sources: None,
+                trace: None,
+                cached: false,
+                cached_at: None,
+                seed: None,
+                message_id: None,
+                limit_exceeded: None,
+                approval: None,
+            },
+            tokens: None,
+        });
     }
-    let history = state.db.get_conversation_history(&context_id).await?;
+    // Allow POST /api/chat/{context_id}/stop to cancel this run
+    let _generation = state
+        .generations
+        .register(&context_id, &claims.sub, cancellation.clone());
+    // The user's preferences fill in whatever the conversation and its
+    // project don't pin
+    let preferences = state.db.get_chat_preferences(&claims.sub).await?;
+    let conversation = state.db.get_conversation(&context_id).await?;
+    let mut overrides = preferences.apply(conversation_overrides(state, &conversation).await?);
+    restore_archived(state, &context_id).await?;
+    let history = conversation_keys::open_messages(
+        key.as_ref(),
+        state.db.get_conversation_history(&context_id).await?,
+    )?;
     // Compute history token estimate in the same pass (before clone into AgentContext)
     let history_input_tokens: usize = history.iter().map(|m| estimate_tokens(&m.content)).sum();
 
     // Load user memory
-    let memory_facts = state.db.get_user_memory(&claims.sub).await?;
-    let preferences = state.db.get_user_preferences(&claims.sub).await?;
-    let user_memory = if !memory_facts.is_empty() || !preferences.is_empty() {
-        Some(UserMemory {
-            user_id: claims.sub.clone(),
-            preferences,
-            facts: memory_facts,
-        })
-    } else {
-        None
-    };
+    let user_memory = load_user_memory(state, &claims.sub, &workspace.owner(&claims.sub)).await?;
 
     // Build agent context
     let agent_context = AgentContext {
@@ -70,100 +209,582 @@ pub async fn chat(
         session_id: context_id.clone(),
         conversation_history: history.clone(),
         user_memory,
+        cancellation,
+        hooks: state.hooks.clone(),
+        preferences,
+        tool_profile: ToolProfile::for_user(
+            &state.config_manager.config(),
+            &claims.sub,
+            &claims.email,
+        ),
+        bus: None,
+        workspace_id: workspace.id().map(str::to_string),
     };
 
+    // Let hooks inspect or rewrite the message before routing
+    agent_context
+        .hooks
+        .message_received(&agent_context, &mut payload.message)
+        .await?;
+
     // Route to appropriate agent
-    let agent_type = if let Some(at) = payload.agent_type {
+    let agent_type = if let Some(at) = requested_agent(payload.agent_type, &overrides) {
         at
     } else {
-        // Get router model from config, or use default
-        let config = state.config_manager.config();
-        let router_model = config
-            .get_agent("router")
-            .map(|a| a.model.as_str())
-            .unwrap_or("fast");
-
-        let router_llm = match state
-            .provider_registry
-            .create_client_for_model(router_model)
+        route_message(state, &payload.message, &agent_context, payload.seed).await?
+    };
+
+    let agent_name_for_run = AgentRegistry::type_to_name(&agent_type).to_string();
+
+    if let Some(persona) = payload.persona.take() {
+        let (config, _) =
+            resolve_agent(state, &agent_context.owner(), agent_name_for_run.clone()).await?;
+        pin_persona(state, &context_id, &config, &persona).await?;
+        overrides.persona = Some(persona);
+    }
+    // Parameters set for this message must be within the agent's limits
+    if !payload.parameters.is_empty() {
+        let (config, _) =
+            resolve_agent(state, &agent_context.owner(), agent_name_for_run.clone()).await?;
+        payload
+            .parameters
+            .check(&config.parameter_limits)
+            .map_err(AppError::InvalidInput)?;
+    }
+
+    // A conversation with attached files or a RAG scope is answered from
+    // their passages
+    let passages = conversation_passages(state, &context_id, &payload.message).await;
+
+    // Serve an earlier answer to a similar question without generating,
+    // unless the request asks for a seeded run or its own parameters, the
+    // answer depends on the conversation's documents, or the conversation
+    // is encrypted and mustn't leave its answers in the cache
+    let cache_turn = match payload.seed {
+        Some(_) => None,
+        None if !payload.parameters.is_empty() || !passages.is_empty() || key.is_some() => None,
+        None => {
+            lookup_answer_cache(
+                state,
+                &agent_name_for_run,
+                &payload.message,
+                &agent_context,
+                &overrides,
+            )
             .await
-        {
-            Ok(client) => client,
-            Err(_) => state.llm_factory.create_default().await?,
+        }
+    };
+    if let Some((_, Some(hit))) = &cache_turn {
+        let mut response = ChatResponse {
+            response: hit.answer.clone(),
+            agent: format!("{:?} (cached)", agent_type),
+            context_id: context_id.clone(),
+            sources: (!hit.sources.is_empty()).then(|| hit.sources.clone()),
+            trace: None,
+            cached: true,
+            cached_at: Some(hit.cached_at),
+            seed: None,
+            message_id: None,
+            limit_exceeded: None,
+            approval: None,
         };
+        agent_context
+            .hooks
+            .response(&agent_context, &agent_name_for_run, &mut response.response)
+            .await?;
+        let resp_id = Uuid::new_v4().to_string();
+        for (id, role, content) in [
+            (
+                Uuid::new_v4().to_string(),
+                MessageRole::User,
+                &payload.message,
+            ),
+            (resp_id.clone(), MessageRole::Assistant, &response.response),
+        ] {
+            state
+                .db
+                .add_message(&id, &context_id, role, content)
+                .await?;
+        }
+        response.message_id = Some(resp_id);
+        if history.is_empty() {
+            title_in_background(state, &context_id, &payload.message, &response.response);
+        }
+        return Ok(Answer {
+            response,
+            tokens: None,
+        });
+    }
 
-        let router = RouterAgent::new(router_llm);
-        router.route(&payload.message, &agent_context).await?
-    };
+    // Refuse the request up front if any applicable spend budget is exhausted
+    let budgets = state.config_manager.config().budgets.clone();
+    spend::enforce_budgets(
+        state.tenant_db.pool(),
+        &budgets,
+        &claims.sub,
+        &agent_name_for_run,
+    )
+    .await?;
 
     // Execute agent with timing
-    let agent_name_for_run = AgentRegistry::type_to_name(&agent_type).to_string();
     let start = std::time::Instant::now();
-    let response = execute_agent(agent_type, &payload.message, &agent_context, &state).await?;
+    let input = match passages.is_empty() {
+        true => payload.message.clone(),
+        false => attachments::prompt(&passages, &payload.message),
+    };
+    let (mut response, generation, tool_calls) = match execute_agent(
+        agent_type,
+        &input,
+        &agent_context,
+        &overrides,
+        request_sampling(&payload.parameters, payload.seed),
+        true,
+        // Checkpoints would keep an encrypted conversation's run in plaintext
+        key.is_none().then(|| {
+            CheckpointJournal::new(
+                state.tenant_db.pool().clone(),
+                &claims.sub,
+                &context_id,
+                Some(&payload.message),
+                &input,
+            )
+        }),
+        state,
+    )
+    .await
+    {
+        Ok(result) => result,
+        Err(e @ AppError::Cancelled(_)) => {
+            record_failed_generation(
+                state,
+                tenant_id,
+                &claims.sub,
+                "chat",
+                &agent_name_for_run,
+                overrides.model.clone(),
+                &e,
+                start,
+            );
+            // Stopped before any output; keep the user's turn in the history
+            let msg_id = Uuid::new_v4().to_string();
+            let content = conversation_keys::seal(key.as_ref(), &payload.message)?;
+            if let Err(err) = state
+                .db
+                .add_message(&msg_id, &context_id, MessageRole::User, &content)
+                .await
+            {
+                tracing::error!(
+                    "Failed to store user message in conversation {}: {}",
+                    context_id,
+                    err
+                );
+            }
+            return Err(e);
+        }
+        Err(e) => {
+            record_failed_generation(
+                state,
+                tenant_id,
+                &claims.sub,
+                "chat",
+                &agent_name_for_run,
+                overrides.model.clone(),
+                &e,
+                start,
+            );
+            return Err(e);
+        }
+    };
+    // Report the passages' files along with any documents the agent retrieved itself
+    if !passages.is_empty() {
+        let mut sources = attachments::sources(&passages);
+        sources.extend(response.sources.take().unwrap_or_default());
+        response.sources = Some(sources);
+    }
+    // A run cut short by a limit has only a partial answer to cache, and a
+    // paused run none yet
+    let paused = response.approval.is_some();
+    if let Some((turn, None)) = cache_turn.filter(|_| response.limit_exceeded.is_none() && !paused)
+    {
+        turn.store(&payload.message, &response).await;
+    }
+    if !paused {
+        agent_context
+            .hooks
+            .response(&agent_context, &agent_name_for_run, &mut response.response)
+            .await?;
+    }
     let duration_ms = start.elapsed().as_millis() as i64;
 
-    // Store messages in conversation
+    // Store messages in conversation; a paused run's answer is stored once
+    // the user decides
     let msg_id = Uuid::new_v4().to_string();
-    state
-        .db
-        .add_message(&msg_id, &context_id, MessageRole::User, &payload.message)
-        .await?;
-
-    let resp_id = Uuid::new_v4().to_string();
     state
         .db
         .add_message(
-            &resp_id,
+            &msg_id,
             &context_id,
-            MessageRole::Assistant,
-            &response.response,
+            MessageRole::User,
+            &conversation_keys::seal(key.as_ref(), &payload.message)?,
         )
         .await?;
 
+    if !paused {
+        let resp_id = Uuid::new_v4().to_string();
+        state
+            .db
+            .add_message(
+                &resp_id,
+                &context_id,
+                MessageRole::Assistant,
+                &conversation_keys::seal(key.as_ref(), &response.response)?,
+            )
+            .await?;
+        if key.is_none() {
+            store_tool_calls(state, &context_id, &resp_id, &tool_calls).await;
+        }
+        store_generation(state, &context_id, &resp_id, &generation).await;
+        response.message_id = Some(resp_id);
+        if history.is_empty() {
+            title_in_background(state, &context_id, &payload.message, &response.response);
+        }
+    }
+
     // Estimate token counts using the shared heuristic (~4 chars/token).
     // Input includes full context: conversation history + current message.
     // Real counts require Agent::execute() → TokenUsage (tracked as future work).
     let input_tokens = (history_input_tokens + estimate_tokens(&payload.message)) as u32;
     let output_tokens = estimate_tokens(&response.response) as u32;
 
-    // Record agent run (fire-and-forget)
+    // Record agent run and estimated spend (fire-and-forget)
     {
         let pool = state.tenant_db.pool().clone();
         let agent_name = agent_name_for_run;
         let user_id = claims.sub.clone();
-        let tenant_id_for_run = tenant_ctx
-            .map(|Extension(tc)| tc.tenant_id.clone())
+        let model = generation.model.clone();
+        let tenant_id_for_run = tenant_id
+            .clone()
             .unwrap_or_else(|| "system".to_string());
         let itok = input_tokens as i64;
         let otok = output_tokens as i64;
+        let seed = payload.seed;
+        let status = if paused { "paused" } else { "completed" };
+        let cost = budgets.estimate_cost(&model, itok as u64, otok as u64);
+        analytics::record_in_background(
+            &pool,
+            RequestEvent {
+                tenant_id,
+                user_id: Some(user_id.clone()),
+                source: "chat",
+                agent_name: agent_name.clone(),
+                model: model.clone(),
+                status,
+                latency_ms: duration_ms,
+                input_tokens: itok,
+                output_tokens: otok,
+                cost_usd: cost,
+                error: None,
+            },
+        );
         tokio::spawn(async move {
             let _ = agent_runs::insert_agent_run(
                 &pool, &tenant_id_for_run, &agent_name, Some(&user_id),
-                "completed", itok, otok, duration_ms, None,
+                status, itok, otok, duration_ms, None, seed,
             ).await;
+            if let Err(e) =
+                spend::record_spend(&pool, &user_id, &agent_name, &model, itok, otok, cost).await
+            {
+                tracing::warn!("Failed to record spend for {}: {}", user_id, e);
+            }
         });
     }
 
-    let body = Json(response);
-    let mut response = body.into_response();
-    response.headers_mut().insert(
-        axum::http::HeaderName::from_static("x-input-tokens"),
-        axum::http::HeaderValue::from(input_tokens),
-    );
-    response.headers_mut().insert(
-        axum::http::HeaderName::from_static("x-output-tokens"),
-        axum::http::HeaderValue::from(output_tokens),
+    Ok(Answer {
+        response,
+        tokens: Some((input_tokens, output_tokens)),
+    })
+}
+
+/// Load a user's stored memory facts and preferences, if they have any
+///
+/// Facts are stored under `owner`: the user, or the workspace they act in,
+/// whose members share its facts.
+async fn load_user_memory(
+    state: &AppState,
+    user_id: &str,
+    owner: &str,
+) -> Result<Option<UserMemory>> {
+    let memory_facts = state.db.get_user_memory(owner).await?;
+    let preferences = state.db.get_user_preferences(user_id).await?;
+    if memory_facts.is_empty() && preferences.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(UserMemory {
+        user_id: user_id.to_string(),
+        preferences,
+        facts: memory_facts,
+    }))
+}
+
+/// A question looked up in an agent's answer cache.
+struct AnswerCacheTurn {
+    cache: AnswerCache,
+    collection: String,
+    embedding: Vec<f32>,
+}
+
+impl AnswerCacheTurn {
+    /// Store the generated answer for later similar questions
+    async fn store(self, question: &str, response: &ChatResponse) {
+        let sources = response.sources.clone().unwrap_or_default();
+        if let Err(e) = self
+            .cache
+            .store(
+                &self.collection,
+                self.embedding,
+                question,
+                &response.response,
+                sources,
+            )
+            .await
+        {
+            tracing::warn!("Failed to cache answer: {}", e);
+        }
+    }
+}
+
+/// Look up a question in the agent's answer cache, if the agent caches answers
+///
+/// Only the first message of a conversation without a pinned model or
+/// temperature, from a user without answer length or language preferences,
+/// is eligible, since other answers depend on the conversation or the user.
+/// Cache failures are logged and treated as a disabled cache.
+async fn lookup_answer_cache(
+    state: &AppState,
+    agent_name: &str,
+    message: &str,
+    context: &AgentContext,
+    overrides: &ConversationOverrides,
+) -> Option<(AnswerCacheTurn, Option<CachedAnswer>)> {
+    if !context.conversation_history.is_empty()
+        || overrides.model.is_some()
+        || overrides.temperature.is_some()
+        || overrides.persona.is_some()
+        || context.preferences.instructions().is_some()
+    {
+        return None;
+    }
+    let config = match resolve_agent(state, &context.owner(), agent_name.to_string()).await {
+        Ok((agent, _)) if agent.answer_cache.enabled => agent.answer_cache,
+        _ => return None,
+    };
+
+    let lookup = async {
+        let cache = answer_cache(&state.config_manager.config()).await?;
+        // Workspaces' agents of the same name are different agents
+        let agent_key = match context.workspace_id {
+            Some(_) => format!("{}/{}", context.owner(), agent_name),
+            None => agent_name.to_string(),
+        };
+        let collection = cache.collection(&agent_key, &context.user_id, &config);
+        let embedding = cache.embed(message).await?;
+        let hit = cache.lookup(&collection, &embedding, &config).await?;
+        Ok::<_, AppError>((
+            AnswerCacheTurn {
+                cache,
+                collection,
+                embedding,
+            },
+            hit,
+        ))
+    };
+    match lookup.await {
+        Ok(turn) => Some(turn),
+        Err(e) => {
+            tracing::warn!("Answer cache unavailable for agent {}: {}", agent_name, e);
+            None
+        }
+    }
+}
+
+/// Answer caching stores answers in the embedded vector database.
+#[cfg(not(feature = "ares-vector"))]
+async fn answer_cache(_config: &crate::utils::toml_config::AresConfig) -> Result<AnswerCache> {
+    Err(AppError::Configuration(
+        "Answer caching requires the `ares-vector` feature".to_string(),
+    ))
+}
+
+/// Sampling of a run with a request's parameters and seed
+fn request_sampling(parameters: &GenerationParameters, seed: Option<u32>) -> Sampling {
+    Sampling {
+        temperature: parameters.temperature,
+        max_tokens: parameters.max_tokens,
+        top_p: parameters.top_p,
+        seed,
+    }
+}
+
+/// Pick an agent for a message with the router agent
+async fn route_message(
+    state: &AppState,
+    message: &str,
+    context: &AgentContext,
+    seed: Option<u32>,
+) -> Result<AgentType> {
+    // Get router model from config, or use default
+    let config = state.config_manager.config();
+    let router_model = config
+        .get_agent("router")
+        .map(|a| a.model.as_str())
+        .unwrap_or("fast");
+
+    let router_llm = match state
+        .provider_registry
+        .create_client_for_model_with_sampling(router_model, Sampling::seeded(seed))
+        .await
+    {
+        Ok(client) => client,
+        Err(_) => state.llm_factory.create_default().await?,
+    };
+
+    let router =
+        RouterAgent::new(router_llm).with_custom_agents(&state.agent_registry.custom_agents());
+    run_cancellable(&context.cancellation, router.route(message, context)).await
+}
+
+/// Check a persona selected by a request is one of the agent's, and keep it
+/// for the rest of the conversation
+async fn pin_persona(
+    state: &AppState,
+    context_id: &str,
+    config: &AgentConfig,
+    persona: &str,
+) -> Result<()> {
+    config
+        .check_persona(persona)
+        .map_err(AppError::InvalidInput)?;
+    let mut pinned = state.db.get_conversation(context_id).await?.overrides();
+    pinned.persona = Some(persona.to_string());
+    state
+        .db
+        .update_conversation_overrides(context_id, &pinned)
+        .await
+}
+
+/// Keep the tool calls made for an assistant message for its trace
+///
+/// A failure is logged rather than failing a request whose reply is already
+/// stored.
+async fn store_tool_calls(
+    state: &AppState,
+    context_id: &str,
+    message_id: &str,
+    tool_calls: &[ToolCallTrace],
+) {
+    if let Err(e) = state
+        .db
+        .store_tool_call_traces(context_id, message_id, tool_calls)
+        .await
+    {
+        tracing::warn!(
+            "Failed to store tool call trace for message {}: {}",
+            message_id,
+            e
+        );
+    }
+}
+
+/// Record what generated an assistant message, logging failures
+async fn store_generation(
+    state: &AppState,
+    context_id: &str,
+    message_id: &str,
+    generation: &Generation,
+) {
+    if let Err(e) = feedback::record_generation(
+        state.tenant_db.pool(),
+        context_id,
+        message_id,
+        generation,
+        Utc::now().timestamp(),
+    )
+    .await
+    {
+        tracing::warn!(
+            "Failed to record generation of message {}: {}",
+            message_id,
+            e
+        );
+    }
+}
+
+/// Record the analytics of a generation that ended in `error` without an
+/// answer, with the agent's own model unless the conversation pins `model`
+#[allow(clippy::too_many_arguments)]
+fn record_failed_generation(
+    state: &AppState,
+    tenant_id: Option<String>,
+    user_id: &str,
+    source: &'static str,
+    agent: &str,
+    model: Option<String>,
+    error: &AppError,
+    start: std::time::Instant,
+) {
+    let model =
+        model.unwrap_or_else(|| agent_generation(state, agent, "unknown".to_string()).model);
+    analytics::record_in_background(
+        state.tenant_db.pool(),
+        RequestEvent {
+            tenant_id,
+            user_id: Some(user_id.to_string()),
+            source,
+            agent_name: agent.to_string(),
+            model,
+            status: match error {
+                AppError::Cancelled(_) => analytics::CANCELLED,
+                _ => analytics::FAILED,
+            },
+            latency_ms: start.elapsed().as_millis() as i64,
+            error: Some(error.to_string()),
+            ..Default::default()
+        },
     );
+}
 
-    Ok(response)
+/// What generates the answers of an agent running with its own configured
+/// model (`fallback_model` if it has none), as after a handoff
+fn agent_generation(state: &AppState, agent: &str, fallback_model: String) -> Generation {
+    let config = state.agent_registry.get_config_any(agent);
+    Generation {
+        agent: agent.to_string(),
+        prompt_version: config
+            .as_ref()
+            .map(AgentConfig::prompt_version)
+            .unwrap_or_default(),
+        model: config.map(|c| c.model).unwrap_or(fallback_model),
+    }
 }
 
+/// Run an agent, returning its response, what generated it (the answering
+/// agent, its model and prompt version) and the tool calls made along the way
+///
+/// With `approvals`, a run reaching a tool call that needs the user's
+/// approval pauses and answers with the approval it waits on. With a
+/// `journal`, tool-calling runs save checkpoints to resume from if the server
+/// stops mid-run.
+#[allow(clippy::too_many_arguments)]
 async fn execute_agent(
     agent_type: AgentType,
     message: &str,
     context: &AgentContext,
+    overrides: &ConversationOverrides,
+    sampling: Sampling,
+    approvals: bool,
+    journal: Option<CheckpointJournal>,
     state: &AppState,
-) -> Result<ChatResponse> {
+) -> Result<(ChatResponse, Generation, Vec<ToolCallTrace>)> {
     // Get agent name from type
     let agent_name = AgentRegistry::type_to_name(&agent_type);
 
@@ -173,34 +794,663 @@ async fn execute_agent(
         ));
     }
 
+    if agent_type == AgentType::Debate {
+        return execute_debate(message, context, state).await;
+    }
+
+    // Agents registered in code or by plugins have no configuration to resolve
+    if let Some(agent) = state.agent_registry.custom_agent(agent_name) {
+        return execute_custom(agent, message, context).await;
+    }
+
     // Resolve agent using the 3-tier hierarchy (User -> Community -> System)
-    let (user_agent, source) = resolve_agent(state, &context.user_id, agent_name.to_string()).await?;
-
-    // Convert UserAgent to AgentConfig for the registry
-    let config = AgentConfig {
-        model: user_agent.model.clone(),
-        system_prompt: user_agent.system_prompt.clone(),
-        tools: user_agent.tools_vec(),
-        max_tool_iterations: user_agent.max_tool_iterations as usize,
-        parallel_tools: user_agent.parallel_tools,
-        extra: std::collections::HashMap::new(),
-    };
+    let (config, source) =
+        resolve_run_config(state, &context.owner(), agent_name, overrides).await?;
+
+    let sampling = conversation_sampling(sampling, overrides);
 
     // Create agent from registry using the resolved config
-    let agent = state
+    let mut agent = state
+        .agent_registry
+        .create_agent_from_config_with_sampling(agent_name, &config, sampling)
+        .await?
+        .with_approval_checkpoints(approvals);
+    if let Some(journal) = journal {
+        agent = agent.with_journal(Arc::new(journal));
+    }
+
+    // Execute the agent and any agents it hands off to, aborting if the request is cancelled
+    let outcome = run_cancellable(
+        &context.cancellation,
+        state
+            .agent_registry
+            .execute_with_handoffs(agent, message, context),
+    )
+    .await?;
+
+    // After a handoff the receiving agent answered with its own configured model
+    let (agent_label, generation) = match outcome.handoffs.last() {
+        Some(last) => (
+            format!("{} (handoff from {})", last.to, agent_name),
+            agent_generation(state, &last.to, config.model),
+        ),
+        None => (
+            format!("{:?} ({})", agent_type, source),
+            Generation {
+                agent: agent_name.to_string(),
+                prompt_version: config.prompt_version(),
+                model: config.model,
+            },
+        ),
+    };
+
+    let (response, tool_calls) =
+        run_response(state, context, message, agent_label, sampling.seed, outcome).await?;
+    Ok((response, generation, tool_calls))
+}
+
+/// Continue a run that paused, for approval with the user's decision or at
+/// a checkpoint, answering `input` in a conversation
+///
+/// Stores the run's answer in its conversation, unless it pauses again for
+/// another approval. The run is recorded under `tenant_id`; `secret`
+/// unlocks an encrypted conversation.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn resume_run(
+    state: &AppState,
+    claims: &Claims,
+    secret: &ConversationSecret,
+    tenant_id: String,
+    conversation_id: &str,
+    input: &str,
+    paused: PausedRun,
+    decision: ApprovalDecision,
+) -> Result<ChatResponse> {
+    let context_id = conversation_id.to_string();
+    let cancellation = CancellationToken::new();
+    // Allow POST /api/chat/{context_id}/stop to cancel this run
+    let _generation = state
+        .generations
+        .register(&context_id, &claims.sub, cancellation.clone());
+    let preferences = state.db.get_chat_preferences(&claims.sub).await?;
+    let conversation = state.db.get_conversation(&context_id).await?;
+    let overrides = preferences.apply(conversation_overrides(state, &conversation).await?);
+    let key = secret.unlock(state, &context_id).await?;
+    restore_archived(state, &context_id).await?;
+    let history = conversation_keys::open_messages(
+        key.as_ref(),
+        state.db.get_conversation_history(&context_id).await?,
+    )?;
+    let history_input_tokens: usize = history.iter().map(|m| estimate_tokens(&m.content)).sum();
+
+    // The run continues in the workspace its conversation belongs to
+    let owner = match &conversation.workspace_id {
+        Some(id) => workspaces::owner_key(id),
+        None => claims.sub.clone(),
+    };
+    let agent_context = AgentContext {
+        user_id: claims.sub.clone(),
+        session_id: context_id.clone(),
+        conversation_history: history,
+        user_memory: load_user_memory(state, &claims.sub, &owner).await?,
+        cancellation,
+        hooks: state.hooks.clone(),
+        preferences,
+        tool_profile: ToolProfile::for_user(
+            &state.config_manager.config(),
+            &claims.sub,
+            &claims.email,
+        ),
+        bus: None,
+        workspace_id: conversation.workspace_id,
+    };
+
+    let budgets = state.config_manager.config().budgets.clone();
+    spend::enforce_budgets(state.tenant_db.pool(), &budgets, &claims.sub, &paused.agent).await?;
+
+    let start = std::time::Instant::now();
+    let agent_name = paused.agent.clone();
+    let (config, source) = resolve_run_config(state, &owner, &agent_name, &overrides).await?;
+    let mut agent = state
         .agent_registry
-        .create_agent_from_config(agent_name, &config)
+        .create_agent_from_config_with_sampling(
+            &agent_name,
+            &config,
+            Sampling {
+                temperature: overrides.temperature,
+                ..Sampling::default()
+            },
+        )
+        .await?
+        .with_approval_checkpoints(true);
+    if key.is_none() {
+        agent = agent.with_journal(Arc::new(CheckpointJournal::new(
+            state.tenant_db.pool().clone(),
+            &claims.sub,
+            &context_id,
+            None,
+            input,
+        )));
+    }
+    let outcome = run_cancellable(
+        &agent_context.cancellation,
+        state
+            .agent_registry
+            .resume_with_handoffs(agent, input, paused, decision, &agent_context),
+    )
+    .await?;
+
+    let (agent_label, generation) = match outcome.handoffs.last() {
+        Some(last) => (
+            format!("{} (handoff from {})", last.to, agent_name),
+            agent_generation(state, &last.to, config.model),
+        ),
+        None => (
+            format!("{} ({})", agent_name, source),
+            Generation {
+                agent: agent_name.clone(),
+                prompt_version: config.prompt_version(),
+                model: config.model,
+            },
+        ),
+    };
+    let model = generation.model.clone();
+    let (mut response, tool_calls) =
+        run_response(state, &agent_context, input, agent_label, None, outcome).await?;
+    let paused = response.approval.is_some();
+    if !paused {
+        agent_context
+            .hooks
+            .response(&agent_context, &agent_name, &mut response.response)
+            .await?;
+        let resp_id = Uuid::new_v4().to_string();
+        state
+            .db
+            .add_message(
+                &resp_id,
+                &context_id,
+                MessageRole::Assistant,
+                &conversation_keys::seal(key.as_ref(), &response.response)?,
+            )
+            .await?;
+        if key.is_none() {
+            store_tool_calls(state, &context_id, &resp_id, &tool_calls).await;
+        }
+        store_generation(state, &context_id, &resp_id, &generation).await;
+        response.message_id = Some(resp_id);
+    }
+
+    // Record the resumed run and its estimated spend (fire-and-forget)
+    let pool = state.tenant_db.pool().clone();
+    let user_id = claims.sub.clone();
+    let itok = history_input_tokens as i64;
+    let otok = estimate_tokens(&response.response) as i64;
+    let duration_ms = start.elapsed().as_millis() as i64;
+    let status = if paused { "paused" } else { "completed" };
+    let cost = budgets.estimate_cost(&model, itok as u64, otok as u64);
+    analytics::record_in_background(
+        &pool,
+        RequestEvent {
+            tenant_id: Some(tenant_id.clone()),
+            user_id: Some(user_id.clone()),
+            source: "resume",
+            agent_name: agent_name.clone(),
+            model: model.clone(),
+            status,
+            latency_ms: duration_ms,
+            input_tokens: itok,
+            output_tokens: otok,
+            cost_usd: cost,
+            error: None,
+        },
+    );
+    tokio::spawn(async move {
+        let _ = agent_runs::insert_agent_run(
+            &pool,
+            &tenant_id,
+            &agent_name,
+            Some(&user_id),
+            status,
+            itok,
+            otok,
+            duration_ms,
+            None,
+            None,
+        )
+        .await;
+        if let Err(e) =
+            spend::record_spend(&pool, &user_id, &agent_name, &model, itok, otok, cost).await
+        {
+            tracing::warn!("Failed to record spend for {}: {}", user_id, e);
+        }
+    });
+
+    Ok(response)
+}
+
+/// Resolve the agent a conversation runs, among `owner`'s custom agents and
+/// the configured ones, with the model and persona pinned on the
+/// conversation applied
+async fn resolve_run_config(
+    state: &AppState,
+    owner: &str,
+    agent_name: &str,
+    overrides: &ConversationOverrides,
+) -> Result<(AgentConfig, String)> {
+    let (mut config, source) = resolve_agent(state, owner, agent_name.to_string()).await?;
+    apply_overrides(&mut config, overrides);
+    Ok((config, source))
+}
+
+/// Apply what is pinned on a conversation to an agent's config
+///
+/// A pinned model takes precedence over the agent's own, and a pinned
+/// persona applies to agents that define it.
+fn apply_overrides(config: &mut AgentConfig, overrides: &ConversationOverrides) {
+    if let Some(model) = &overrides.model {
+        config.model = model.clone();
+    }
+    if let Some(persona) = &overrides.persona {
+        config.apply_persona(persona);
+    }
+}
+
+/// Agent a message asks for: the request's, or else the one pinned on the
+/// conversation
+///
+/// `None` leaves the choice to the router.
+fn requested_agent(
+    requested: Option<AgentType>,
+    overrides: &ConversationOverrides,
+) -> Option<AgentType> {
+    requested.or_else(|| overrides.agent.as_deref().map(AgentType::from_string))
+}
+
+/// A temperature pinned on the conversation applies unless the request sets one
+fn conversation_sampling(sampling: Sampling, overrides: &ConversationOverrides) -> Sampling {
+    Sampling {
+        temperature: sampling.temperature.or(overrides.temperature),
+        ..sampling
+    }
+}
+
+/// Chat response for an agent run, with the tool calls it made
+///
+/// A run that paused for approval is stored until the user decides, and
+/// answers with the approval it waits on.
+async fn run_response(
+    state: &AppState,
+    context: &AgentContext,
+    message: &str,
+    agent_label: String,
+    seed: Option<u32>,
+    outcome: HandoffOutcome,
+) -> Result<(ChatResponse, Vec<ToolCallTrace>)> {
+    let approval = match &outcome.paused {
+        Some(paused) => Some(
+            approvals::insert_approval(
+                state.tenant_db.pool(),
+                &Uuid::new_v4().to_string(),
+                &context.user_id,
+                &context.session_id,
+                message,
+                paused,
+                Utc::now().timestamp(),
+            )
+            .await?
+            .pending()?,
+        ),
+        None => None,
+    };
+
+    Ok((
+        ChatResponse {
+            response: outcome.response,
+            agent: agent_label,
+            context_id: context.session_id.clone(),
+            sources: (!outcome.sources.is_empty()).then_some(outcome.sources),
+            trace: (!outcome.trace.is_empty()).then_some(outcome.trace),
+            cached: false,
+            cached_at: None,
+            seed,
+            message_id: None,
+            limit_exceeded: outcome.limit_exceeded,
+            approval,
+        },
+        outcome
+            .tool_calls
+            .into_iter()
+            .map(|(agent, record)| ToolCallTrace::new(agent, record))
+            .collect(),
+    ))
+}
+
+/// Run the debate workflow, returning the judge's final answer and what
+/// generated it
+///
+/// Conversation overrides and seeds don't apply; each agent in the debate
+/// uses its own configured model.
+async fn execute_debate(
+    message: &str,
+    context: &AgentContext,
+    state: &AppState,
+) -> Result<(ChatResponse, Generation, Vec<ToolCallTrace>)> {
+    let engine = WorkflowEngine::new(state.clone());
+    let workflow = engine
+        .get_workflow_config(DEBATE_WORKFLOW)
+        .filter(|workflow| workflow.debate.is_some())
+        .ok_or_else(|| {
+            AppError::InvalidInput(format!(
+                "No debate is configured: add a [workflows.{}] section with a debate panel",
+                DEBATE_WORKFLOW
+            ))
+        })?;
+
+    let output = engine
+        .execute_workflow(DEBATE_WORKFLOW, message, context)
         .await?;
+    let generation = agent_generation(state, &workflow.entry_agent, String::new());
 
-    // Execute the agent
-    let response = agent.execute(message, context).await?;
+    Ok((
+        ChatResponse {
+            response: output.final_response,
+            agent: format!("Debate ({})", output.agents_used.join(", ")),
+            context_id: context.session_id.clone(),
+            sources: None,
+            trace: None,
+            cached: false,
+            cached_at: None,
+            seed: None,
+            message_id: None,
+            limit_exceeded: None,
+            approval: None,
+        },
+        generation,
+        Vec::new(),
+    ))
+}
 
-    Ok(ChatResponse {
-        response,
-        agent: format!("{:?} ({})", agent_type, source),
-        context_id: context.session_id.clone(),
-        sources: None,
-    })
+/// Run a custom agent, registered in code or by a plugin
+///
+/// The agent answers as it is: conversation overrides, sampling and
+/// approvals apply only to configured agents.
+async fn execute_custom(
+    agent: Arc<dyn Agent>,
+    message: &str,
+    context: &AgentContext,
+) -> Result<(ChatResponse, Generation, Vec<ToolCallTrace>)> {
+    let response = run_cancellable(&context.cancellation, agent.execute(message, context)).await?;
+    let name = agent.agent_type().as_str().to_string();
+
+    Ok((
+        ChatResponse {
+            response,
+            agent: format!("{} (custom)", name),
+            context_id: context.session_id.clone(),
+            sources: None,
+            trace: None,
+            cached: false,
+            cached_at: None,
+            seed: None,
+            message_id: None,
+            limit_exceeded: None,
+            approval: None,
+        },
+        Generation {
+            agent: name,
+            model: String::new(),
+            prompt_version: String::new(),
+        },
+        Vec::new(),
+    ))
+}
+
+/// Stop the in-flight generation in a conversation
+///
+/// Cancels the active `/api/chat` or `/api/chat/stream` run for the
+/// conversation. A stopped stream stores the response generated so far and
+/// ends with a "stopped" event.
+#[utoipa::path(
+    post,
+    path = "/api/chat/{context_id}/stop",
+    params(
+        ("context_id" = String, Path, description = "Conversation ID")
+    ),
+    responses(
+        (status = 200, description = "Generation stopped"),
+        (status = 404, description = "No generation in progress for this conversation"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "chat",
+    security(("bearer" = []))
+)]
+pub async fn stop_generation(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(context_id): Path<String>,
+) -> Result<Json<serde_json::Value>> {
+    if !state.generations.stop(&context_id, &claims.sub) {
+        return Err(AppError::NotFound(format!(
+            "No generation in progress for conversation {}",
+            context_id
+        )));
+    }
+
+    Ok(Json(serde_json::json!({"stopped": true, "context_id": context_id})))
+}
+
+/// Regenerate the last assistant message in a conversation
+///
+/// Re-runs the agent on the last user message, optionally steered by
+/// `feedback`. The feedback and the previous answer are only sent to the
+/// agent; the stored history keeps the original user message, with the old
+/// answer replaced by the new one (unless `draft` is set).
+#[utoipa::path(
+    post,
+    path = "/api/chat/{context_id}/regenerate",
+    params(
+        ("context_id" = String, Path, description = "Conversation ID")
+    ),
+    request_body = RegenerateRequest,
+    responses(
+        (status = 200, description = "Regenerated response", body = ChatResponse),
+        (status = 400, description = "Conversation does not end with an assistant reply"),
+        (status = 404, description = "Conversation not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Encrypted conversation without its secret, or a wrong one"),
+        (status = 503, description = "Server in maintenance mode")
+    ),
+    tag = "chat",
+    security(("bearer" = []))
+)]
+pub async fn regenerate(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    secret: ConversationSecret,
+    tenant_ctx: Option<Extension<crate::models::TenantContext>>,
+    Path(context_id): Path<String>,
+    Json(payload): Json<RegenerateRequest>,
+) -> Result<Json<ChatResponse>> {
+    maintenance::ensure_available(&state)?;
+
+    let cancellation = CancellationToken::new();
+    let _cancel_on_drop = cancellation.clone().drop_guard();
+
+    let tenant_id = tenant_ctx.map(|Extension(tc)| tc.tenant_id);
+    let conversation = state.db.get_conversation(&context_id).await?;
+    if !conversation.belongs_to(&claims.sub, workspace.id()) {
+        return Err(AppError::Auth(
+            "Not authorized to access this conversation".to_string(),
+        ));
+    }
+    let preferences = state.db.get_chat_preferences(&claims.sub).await?;
+    let overrides = preferences.apply(conversation_overrides(&state, &conversation).await?);
+
+    // The conversation must end with a user message followed by the reply to replace
+    let key = secret.unlock(&state, &context_id).await?;
+    restore_archived(&state, &context_id).await?;
+    let mut history = conversation_keys::open_messages(
+        key.as_ref(),
+        state.db.get_conversation_history(&context_id).await?,
+    )?;
+    let (previous_id, previous) = match history.pop() {
+        Some(msg) if matches!(msg.role, MessageRole::Assistant) => (msg.id, msg.content),
+        _ => {
+            return Err(AppError::InvalidInput(
+                "The last message in this conversation is not an assistant reply".to_string(),
+            ))
+        }
+    };
+    let user_message = match history.pop() {
+        Some(msg) if matches!(msg.role, MessageRole::User) => msg.content,
+        _ => {
+            return Err(AppError::InvalidInput(
+                "No user message to regenerate a reply for".to_string(),
+            ))
+        }
+    };
+
+    let _generation = state
+        .generations
+        .register(&context_id, &claims.sub, cancellation.clone());
+    let agent_context = AgentContext {
+        user_id: claims.sub.clone(),
+        session_id: context_id.clone(),
+        conversation_history: history,
+        user_memory: load_user_memory(&state, &claims.sub, &workspace.owner(&claims.sub)).await?,
+        cancellation,
+        hooks: state.hooks.clone(),
+        preferences,
+        tool_profile: ToolProfile::for_user(
+            &state.config_manager.config(),
+            &claims.sub,
+            &claims.email,
+        ),
+        bus: None,
+        workspace_id: workspace.id().map(str::to_string),
+    };
+
+    let agent_type = match requested_agent(payload.agent_type, &overrides) {
+        Some(at) => at,
+        None => route_message(&state, &user_message, &agent_context, payload.seed).await?,
+    };
+
+    let agent_name = AgentRegistry::type_to_name(&agent_type).to_string();
+    let budgets = state.config_manager.config().budgets.clone();
+    spend::enforce_budgets(state.tenant_db.pool(), &budgets, &claims.sub, &agent_name).await?;
+
+    let mut prompt = regeneration_prompt(&user_message, &previous, payload.feedback.as_deref());
+    let passages = conversation_passages(&state, &context_id, &user_message).await;
+    if !passages.is_empty() {
+        prompt = format!("{}\n\n{}", attachments::context(&passages), prompt);
+    }
+    let start = std::time::Instant::now();
+    let (mut response, generation, tool_calls) = match execute_agent(
+        agent_type,
+        &prompt,
+        &agent_context,
+        &overrides,
+        Sampling::seeded(payload.seed),
+        false,
+        None,
+        &state,
+    )
+    .await
+    {
+        Ok(result) => result,
+        Err(e) => {
+            record_failed_generation(
+                &state,
+                None,
+                &claims.sub,
+                "regenerate",
+                &agent_name,
+                overrides.model.clone(),
+                &e,
+                start,
+            );
+            return Err(e);
+        }
+    };
+    let duration_ms = start.elapsed().as_millis() as i64;
+    agent_context
+        .hooks
+        .response(&agent_context, &agent_name, &mut response.response)
+        .await?;
+    // Report the passages' files along with any documents the agent retrieved itself
+    if !passages.is_empty() {
+        let mut sources = attachments::sources(&passages);
+        sources.extend(response.sources.take().unwrap_or_default());
+        response.sources = Some(sources);
+    }
+
+    if !payload.draft {
+        state
+            .db
+            .update_last_assistant_message(
+                &context_id,
+                &conversation_keys::seal(key.as_ref(), &response.response)?,
+            )
+            .await?;
+        if key.is_none() {
+            store_tool_calls(&state, &context_id, &previous_id, &tool_calls).await;
+        }
+        store_generation(&state, &context_id, &previous_id, &generation).await;
+        response.message_id = Some(previous_id);
+    }
+
+    // Record the regeneration and its estimated spend (fire-and-forget)
+    {
+        let pool = state.tenant_db.pool().clone();
+        let model = generation.model.clone();
+        let user_id = claims.sub.clone();
+        let itok = estimate_tokens(&prompt) as i64;
+        let otok = estimate_tokens(&response.response) as i64;
+        let cost = budgets.estimate_cost(&model, itok as u64, otok as u64);
+        analytics::record_in_background(
+            &pool,
+            RequestEvent {
+                tenant_id,
+                user_id: Some(user_id.clone()),
+                source: "regenerate",
+                agent_name: agent_name.clone(),
+                model: model.clone(),
+                status: analytics::COMPLETED,
+                latency_ms: duration_ms,
+                input_tokens: itok,
+                output_tokens: otok,
+                cost_usd: cost,
+                error: None,
+            },
+        );
+        tokio::spawn(async move {
+            if let Err(e) =
+                spend::record_spend(&pool, &user_id, &agent_name, &model, itok, otok, cost).await
+            {
+                tracing::warn!("Failed to record spend for {}: {}", user_id, e);
+            }
+        });
+    }
+
+    Ok(Json(response))
+}
+
+/// Build the agent input for a regeneration, keeping feedback out of the stored history
+fn regeneration_prompt(user_message: &str, previous: &str, feedback: Option<&str>) -> String {
+    let feedback = feedback.map(str::trim).filter(|f| !f.is_empty());
+    format!(
+        "{}\n\n[A previous answer to this message was:\n{}\n]\n{}",
+        user_message,
+        previous,
+        match feedback {
+            Some(feedback) => format!(
+                "Write a new answer that takes this feedback into account: {}",
+                feedback
+            ),
+            None => "Write a different, improved answer.".to_string(),
+        }
+    )
 }
 
 /// Get user memory
@@ -217,8 +1467,12 @@ async fn execute_agent(
 pub async fn get_user_memory(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
 ) -> Result<Json<UserMemory>> {
-    let facts = state.db.get_user_memory(&claims.sub).await?;
+    let facts = state
+        .db
+        .get_user_memory(&workspace.owner(&claims.sub))
+        .await?;
     let preferences = state.db.get_user_preferences(&claims.sub).await?;
 
     Ok(Json(UserMemory {
@@ -231,12 +1485,12 @@ pub async fn get_user_memory(
 /// Streaming chat response event
 #[derive(serde::Serialize)]
 pub struct StreamEvent {
-    /// Event type: "start", "token", "done", "error"
+    /// Event type: "start", "token", "usage", "done", "stopped", "error"
     pub event: String,
     /// Token content (for "token" events)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
-    /// Agent type that handled the request (for "start" and "done" events)
+    /// Agent type that handled the request (for "start", "done" and "stopped" events)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub agent: Option<String>,
     /// Context ID for the conversation
@@ -245,8 +1499,60 @@ pub struct StreamEvent {
     /// Error message (for "error" events)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Token usage so far (for "usage" events)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<StreamUsage>,
+    /// Documents of the passages the response drew on (for "done" and
+    /// "stopped" events)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources: Option<Vec<Source>>,
+}
+
+/// Estimated token usage and cost of a streamed response so far
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct StreamUsage {
+    /// Estimated prompt tokens
+    pub prompt_tokens: u32,
+    /// Estimated completion tokens generated so far
+    pub completion_tokens: u32,
+    /// Sum of prompt and completion tokens
+    pub total_tokens: u32,
+    /// Estimated cost in USD, using the configured model pricing
+    pub estimated_cost: f64,
+}
+
+impl StreamUsage {
+    fn estimate(budgets: &BudgetsConfig, model: &str, prompt: &str, response: &str) -> Self {
+        let prompt_tokens = estimate_tokens(prompt) as u32;
+        let completion_tokens = estimate_tokens(response) as u32;
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            estimated_cost: budgets.estimate_cost(
+                model,
+                prompt_tokens as u64,
+                completion_tokens as u64,
+            ),
+        }
+    }
+
+    fn event(self) -> StreamEvent {
+        StreamEvent {
+            event: "usage".to_string(),
+            content: None,
+            agent: None,
+            context_id: None,
+            error: None,
+            usage: Some(self),
+            sources: None,
+        }
+    }
 }
 
+/// Number of streamed tokens between "usage" events
+const USAGE_EVENT_INTERVAL: usize = 20;
+
 /// Stream a chat response using Server-Sent Events
 #[utoipa::path(
     post,
@@ -255,7 +1561,9 @@ pub struct StreamEvent {
     responses(
         (status = 200, description = "Streaming chat response"),
         (status = 400, description = "Invalid input"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Encrypted conversation without its secret, or a wrong one"),
+        (status = 503, description = "Server in maintenance mode")
     ),
     tag = "chat",
     security(("bearer" = []))
@@ -263,45 +1571,133 @@ pub struct StreamEvent {
 pub async fn chat_stream(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    secret: ConversationSecret,
+    tenant_ctx: Option<Extension<crate::models::TenantContext>>,
     Json(payload): Json<ChatRequest>,
-) -> axum::response::Sse<
-    impl futures::Stream<
-        Item = std::result::Result<axum::response::sse::Event, std::convert::Infallible>,
+) -> Result<
+    axum::response::Sse<
+        impl futures::Stream<
+            Item = std::result::Result<axum::response::sse::Event, std::convert::Infallible>,
+        >,
     >,
 > {
     use axum::response::sse::{Event, Sse};
 
+    maintenance::ensure_available(&state)?;
+
     // Get or create conversation
     let context_id = payload
         .context_id
         .clone()
         .unwrap_or_else(|| Uuid::new_v4().to_string());
+    // Set up before streaming starts, so a conversation of another space or
+    // a bad file is reported as an error
+    ensure_conversation(
+        &state,
+        &context_id,
+        &claims.sub,
+        &workspace,
+        payload.project_id.as_deref(),
+    )
+    .await?;
+    let key = secret.unlock_or_encrypt(&state, &context_id).await?;
+    attach_files(
+        &state,
+        &context_id,
+        &claims.sub,
+        &workspace,
+        &payload.file_ids,
+    )
+    .await?;
+    // A "/scope" command is answered directly instead of by an agent
+    let scope_reply = match rag_scope::parse_command(&payload.message) {
+        Some(command) => {
+            Some(run_scope_command(&state, &context_id, &claims.sub, &workspace, command).await?)
+        }
+        None => None,
+    };
 
     // Clone values we need for the async stream
     let state_clone = state.clone();
     let claims_clone = claims.clone();
-    let message = payload.message.clone();
+    let mut message = payload.message.clone();
     let agent_type_req = payload.agent_type;
+    let persona_req = payload.persona.clone();
+    let seed = payload.seed;
+    let parameters = payload.parameters;
     let context_id_clone = context_id.clone();
+    let owner = workspace.owner(&claims.sub);
+    let workspace_id = workspace.id().map(str::to_string);
+    let tenant_id = tenant_ctx.map(|Extension(tc)| tc.tenant_id);
 
     let stream = async_stream::stream! {
-        // Setup conversation
-        if !state_clone.db.conversation_exists(&context_id_clone).await.unwrap_or(false) {
-            if let Err(e) = state_clone
-                .db
-                .create_conversation(&context_id_clone, &claims_clone.sub, None)
-                .await {
-                tracing::warn!("Failed to create conversation {}: {}", context_id_clone, e);
+        if let Some(reply) = scope_reply {
+            for (event, content) in [("token", Some(reply)), ("done", None)] {
+                let event = StreamEvent {
+                    event: event.to_string(),
+                    content,
+                    agent: (event == "done").then(|| "scope".to_string()),
+                    context_id: Some(context_id_clone.clone()),
+                    error: None,
+                    usage: None,
+                    sources: None,
+                };
+                yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
             }
+            return;
         }
 
+        // Cancelled when the SSE stream is dropped (client disconnect)
+        let cancellation = CancellationToken::new();
+        let _cancel_on_drop = cancellation.clone().drop_guard();
+        // Allow POST /api/chat/{context_id}/stop to cancel this run
+        let _generation = state_clone
+            .generations
+            .register(&context_id_clone, &claims_clone.sub, cancellation.clone());
+
+        let chat_preferences = state_clone.db.get_chat_preferences(&claims_clone.sub).await.unwrap_or_else(|e| {
+            tracing::warn!("Failed to get chat preferences for {}: {}", claims_clone.sub, e);
+            ChatPreferences::default()
+        });
+        let pinned = match state_clone.db.get_conversation(&context_id_clone).await {
+            Ok(conversation) => conversation_overrides(&state_clone, &conversation).await,
+            Err(e) => Err(e),
+        };
+        let overrides = match pinned {
+            Ok(overrides) => chat_preferences.apply(overrides),
+            Err(e) => {
+                tracing::warn!("Failed to get conversation overrides for {}: {}", context_id_clone, e);
+                chat_preferences.apply(ConversationOverrides::default())
+            }
+        };
+
+        if let Err(e) = restore_archived(&state_clone, &context_id_clone).await {
+            tracing::warn!("Failed to restore archived conversation {}: {}", context_id_clone, e);
+        }
         let history = state_clone.db.get_conversation_history(&context_id_clone).await.unwrap_or_else(|e| {
             tracing::warn!("Failed to get conversation history for {}: {}", context_id_clone, e);
             vec![]
         });
+        let history = match conversation_keys::open_messages(key.as_ref(), history) {
+            Ok(history) => history,
+            Err(e) => {
+                let event = StreamEvent {
+                    event: "error".to_string(),
+                    content: None,
+                    agent: None,
+                    context_id: Some(context_id_clone.clone()),
+                    error: Some(e.to_string()),
+                    usage: None,
+                    sources: None,
+                };
+                yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+                return;
+            }
+        };
 
         // Load user memory
-        let memory_facts = state_clone.db.get_user_memory(&claims_clone.sub).await.unwrap_or_else(|e| {
+        let memory_facts = state_clone.db.get_user_memory(&owner).await.unwrap_or_else(|e| {
             tracing::warn!("Failed to get user memory for {}: {}", claims_clone.sub, e);
             vec![]
         });
@@ -325,10 +1721,35 @@ pub async fn chat_stream(
             session_id: context_id_clone.clone(),
             conversation_history: history,
             user_memory,
+            cancellation: cancellation.clone(),
+            hooks: state_clone.hooks.clone(),
+            preferences: chat_preferences,
+            tool_profile: ToolProfile::for_user(
+                &state_clone.config_manager.config(),
+                &claims_clone.sub,
+                &claims_clone.email,
+            ),
+            bus: None,
+            workspace_id: workspace_id.clone(),
         };
 
+        // Let hooks inspect or rewrite the message before routing
+        if let Err(e) = agent_context.hooks.message_received(&agent_context, &mut message).await {
+            let event = StreamEvent {
+                event: "error".to_string(),
+                content: None,
+                agent: None,
+                context_id: Some(context_id_clone.clone()),
+                error: Some(e.to_string()),
+                usage: None,
+                sources: None,
+            };
+            yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+            return;
+        }
+
         // Route to appropriate agent
-        let agent_type = if let Some(at) = agent_type_req {
+        let agent_type = if let Some(at) = requested_agent(agent_type_req, &overrides) {
             at
         } else {
             let config = state_clone.config_manager.config();
@@ -339,7 +1760,7 @@ pub async fn chat_stream(
 
             let router_llm = match state_clone
                 .provider_registry
-                .create_client_for_model(router_model)
+                .create_client_for_model_with_sampling(router_model, Sampling::seeded(seed))
                 .await
             {
                 Ok(client) => client,
@@ -352,6 +1773,8 @@ pub async fn chat_stream(
                             agent: None,
                             context_id: Some(context_id_clone.clone()),
                             error: Some(format!("Failed to create LLM client: {}", e)),
+                            usage: None,
+                            sources: None,
                         };
                         yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
                         return;
@@ -369,6 +1792,8 @@ pub async fn chat_stream(
                         agent: None,
                         context_id: Some(context_id_clone.clone()),
                         error: Some(format!("Router failed: {}", e)),
+                        usage: None,
+                        sources: None,
                     };
                     yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
                     return;
@@ -376,21 +1801,76 @@ pub async fn chat_stream(
             }
         };
 
-        // Send start event
+        // A debate answers only once the judge has decided, and a custom
+        // agent all at once
+        if state_clone.agent_registry.custom_agent(agent_type.as_str()).is_some() {
+            let event = StreamEvent {
+                event: "error".to_string(),
+                content: None,
+                agent: None,
+                context_id: Some(context_id_clone.clone()),
+                error: Some(format!(
+                    "Custom agent '{}' cannot be streamed; use /api/chat",
+                    agent_type
+                )),
+                usage: None,
+                sources: None,
+            };
+            yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+            return;
+        }
+        if agent_type == AgentType::Debate {
+            let event = StreamEvent {
+                event: "error".to_string(),
+                content: None,
+                agent: None,
+                context_id: Some(context_id_clone.clone()),
+                error: Some("Debates cannot be streamed; use /api/chat".to_string()),
+                usage: None,
+                sources: None,
+            };
+            yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+            return;
+        }
+
+        // Refuse the request if any applicable spend budget is exhausted
         let agent_name = AgentRegistry::type_to_name(&agent_type);
+        let budgets = state_clone.config_manager.config().budgets.clone();
+        if let Err(e) = spend::enforce_budgets(
+            state_clone.tenant_db.pool(),
+            &budgets,
+            &claims_clone.sub,
+            agent_name,
+        ).await {
+            let event = StreamEvent {
+                event: "error".to_string(),
+                content: None,
+                agent: None,
+                context_id: Some(context_id_clone.clone()),
+                error: Some(e.to_string()),
+                usage: None,
+                sources: None,
+            };
+            yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+            return;
+        }
+
+        // Send start event
         let start_event = StreamEvent {
             event: "start".to_string(),
             content: None,
             agent: Some(format!("{} (system)", agent_type)),
             context_id: Some(context_id_clone.clone()),
             error: None,
+            usage: None,
+            sources: None,
         };
         yield Ok(Event::default().data(serde_json::to_string(&start_event).unwrap_or_default()));
 
         // Resolve agent using hierarchy
-        let (user_agent, source) = match crate::api::handlers::user_agents::resolve_agent(
+        let (mut agent_config, source) = match crate::api::handlers::user_agents::resolve_agent(
             &state_clone,
-            &claims_clone.sub,
+            &owner,
             agent_name.to_string(),
         ).await {
             Ok(r) => r,
@@ -401,16 +1881,54 @@ pub async fn chat_stream(
                     agent: None,
                     context_id: Some(context_id_clone.clone()),
                     error: Some(format!("Failed to resolve agent: {}", e)),
+                    usage: None,
+                    sources: None,
                 };
                 yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
                 return;
             }
         };
 
-        // Get LLM client for streaming
+        // A requested persona must be one of the agent's; the conversation keeps it
+        if let Some(persona) = &persona_req {
+            if let Err(e) = pin_persona(&state_clone, &context_id_clone, &agent_config, persona).await {
+                let event = StreamEvent {
+                    event: "error".to_string(),
+                    content: None,
+                    agent: None,
+                    context_id: Some(context_id_clone.clone()),
+                    error: Some(e.to_string()),
+                    usage: None,
+                    sources: None,
+                };
+                yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+                return;
+            }
+        }
+        if let Some(persona) = persona_req.as_ref().or(overrides.persona.as_ref()) {
+            agent_config.apply_persona(persona);
+        }
+        // Parameters set for this message must be within the agent's limits
+        if let Err(e) = parameters.check(&agent_config.parameter_limits) {
+            let event = StreamEvent {
+                event: "error".to_string(),
+                content: None,
+                agent: None,
+                context_id: Some(context_id_clone.clone()),
+                error: Some(e),
+                usage: None,
+                sources: None,
+            };
+            yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+            return;
+        }
+
+        // Get LLM client for streaming, honoring any model pinned on the conversation
+        let model = overrides.model.clone().unwrap_or_else(|| agent_config.model.clone());
+        let sampling = conversation_sampling(request_sampling(&parameters, seed), &overrides);
         let llm = match state_clone
             .provider_registry
-            .create_client_for_model(&user_agent.model)
+            .create_client_for_model_with_sampling(&model, sampling)
             .await
         {
             Ok(c) => c,
@@ -423,6 +1941,8 @@ pub async fn chat_stream(
                         agent: None,
                         context_id: Some(context_id_clone.clone()),
                         error: Some(format!("Failed to create LLM: {}", e)),
+                        usage: None,
+                        sources: None,
                     };
                     yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
                     return;
@@ -430,39 +1950,178 @@ pub async fn chat_stream(
             },
         };
 
+        let generation = Generation {
+            agent: agent_name.to_string(),
+            model: model.clone(),
+            prompt_version: agent_config.prompt_version(),
+        };
+
+        // The agent's guardrails check the message before the model sees it
+        // (redaction may rewrite it) and the response before the client does
+        let guardrails = match state_clone.agent_registry.build_guardrails(agent_name, &agent_config).await {
+            Ok(guardrails) => guardrails,
+            Err(e) => {
+                let event = StreamEvent {
+                    event: "error".to_string(),
+                    content: None,
+                    agent: None,
+                    context_id: Some(context_id_clone.clone()),
+                    error: Some(e.to_string()),
+                    usage: None,
+                    sources: None,
+                };
+                yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+                return;
+            }
+        };
+        let input = match &guardrails {
+            Some(guardrails) => match guardrails.process_input(&message).await {
+                Ok(input) => input,
+                Err(e) => {
+                    let event = StreamEvent {
+                        event: "error".to_string(),
+                        content: None,
+                        agent: None,
+                        context_id: Some(context_id_clone.clone()),
+                        error: Some(e.to_string()),
+                        usage: None,
+                        sources: None,
+                    };
+                    yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+                    return;
+                }
+            },
+            None => message.clone(),
+        };
+
         // Build the prompt with system message and history
-        let system_prompt = user_agent.system_prompt.unwrap_or_else(|| "You are a helpful assistant.".to_string());
-        let full_prompt = format!(
-            "{}\n\nUser: {}\nAssistant:",
-            system_prompt,
-            message
-        );
+        let system_prompt = agent_config.system_prompt.unwrap_or_else(|| "You are a helpful assistant.".to_string());
+        let mut prompt_messages = vec![("system".to_string(), system_prompt)];
+        // Add the user's memory, its facts chosen for the message
+        if let Some(user_memory) = agent_context.user_memory.as_ref().filter(|_| agent_config.memory.enabled) {
+            let facts = agent_memory::select_facts(
+                user_memory,
+                &agent_config.memory,
+                &input,
+                state_clone.agent_registry.memory_embedder().map(|e| e.as_ref()),
+            )
+            .await;
+            if let Some(section) = agent_memory::render(user_memory, &facts) {
+                prompt_messages.push(("system".to_string(), section));
+            }
+        } else if let Some(memory) = &agent_context.user_memory {
+            let memory_context = format!(
+                "User preferences: {}",
+                memory
+                    .preferences
+                    .iter()
+                    .map(|p| format!("{}: {}", p.key, p.value))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            prompt_messages.push(("system".to_string(), memory_context));
+        }
+        if let Some(instructions) = agent_context.preferences.instructions() {
+            prompt_messages.push(("system".to_string(), instructions));
+        }
+        // An agent with a `rag` section answers from its knowledge passages
+        let knowledge_passages = match (&agent_config.rag, state_clone.agent_registry.knowledge()) {
+            (Some(rag), Some(kb)) => {
+                knowledge::retrieve(kb.as_ref(), rag, &agent_context.owner(), &input).await
+            }
+            _ => Vec::new(),
+        };
+        if let Some(section) = knowledge::render(&knowledge_passages) {
+            prompt_messages.push(("system".to_string(), section));
+        }
+        // A conversation with attached files or a RAG scope is answered from
+        // their passages
+        let passages = conversation_passages(&state_clone, &context_id_clone, &input).await;
+        if !passages.is_empty() {
+            prompt_messages.push(("system".to_string(), attachments::context(&passages)));
+        }
+        let mut sources = attachments::sources(&passages);
+        sources.extend(attachments::sources(&knowledge_passages));
+        prompt_messages.push(("user".to_string(), input));
+        if let Err(e) = agent_context.hooks.before_llm(&agent_context, agent_name, &mut prompt_messages).await {
+            let event = StreamEvent {
+                event: "error".to_string(),
+                content: None,
+                agent: None,
+                context_id: Some(context_id_clone.clone()),
+                error: Some(e.to_string()),
+                usage: None,
+                sources: None,
+            };
+            yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+            return;
+        }
+        let mut full_prompt = String::new();
+        for (role, content) in &prompt_messages {
+            match role.as_str() {
+                "system" => full_prompt.push_str(&format!("{}\n\n", content)),
+                "assistant" => full_prompt.push_str(&format!("Assistant: {}\n", content)),
+                _ => full_prompt.push_str(&format!("User: {}\n", content)),
+            }
+        }
+        full_prompt.push_str("Assistant:");
 
-        // Stream tokens
+        // Stream tokens until the stream ends or the run is stopped
         use futures::StreamExt;
         let mut full_response = String::new();
-        match llm.stream(&full_prompt).await {
+        let mut stopped = false;
+        let mut streamed_tokens = 0usize;
+        let start = std::time::Instant::now();
+        match run_cancellable(&cancellation, llm.stream(&full_prompt)).await {
             Ok(mut token_stream) => {
-                while let Some(token_result) = token_stream.next().await {
+                loop {
+                    let next = tokio::select! {
+                        biased;
+                        _ = cancellation.cancelled() => None,
+                        next = token_stream.next() => Some(next),
+                    };
+                    let token_result = match next {
+                        Some(Some(token_result)) => token_result,
+                        Some(None) => break,
+                        None => {
+                            stopped = true;
+                            break;
+                        }
+                    };
                     match token_result {
                         Ok(token) => {
                             full_response.push_str(&token);
-                            let event = StreamEvent {
-                                event: "token".to_string(),
-                                content: Some(token),
-                                agent: None,
-                                context_id: None,
-                                error: None,
-                            };
-                            yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+                            // A guarded response is sent once its checks pass
+                            if guardrails.is_none() {
+                                let event = StreamEvent {
+                                    event: "token".to_string(),
+                                    content: Some(token),
+                                    agent: None,
+                                    context_id: None,
+                                    error: None,
+                                    usage: None,
+                                    sources: None,
+                                };
+                                yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+                            }
+
+                            // Periodically report usage so clients can show live meters
+                            streamed_tokens += 1;
+                            if streamed_tokens.is_multiple_of(USAGE_EVENT_INTERVAL) {
+                                let event = StreamUsage::estimate(&budgets, &model, &full_prompt, &full_response).event();
+                                yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+                            }
                         }
                         Err(e) => {
+                            record_failed_generation(&state_clone, None, &claims_clone.sub, "stream", agent_name, Some(model.clone()), &e, start);
                             let event = StreamEvent {
                                 event: "error".to_string(),
                                 content: None,
                                 agent: None,
                                 context_id: Some(context_id_clone.clone()),
                                 error: Some(format!("Stream error: {}", e)),
+                                usage: None,
+                                sources: None,
                             };
                             yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
                             return;
@@ -470,51 +2129,241 @@ pub async fn chat_stream(
                     }
                 }
             }
+            Err(AppError::Cancelled(_)) => stopped = true,
             Err(e) => {
+                record_failed_generation(&state_clone, None, &claims_clone.sub, "stream", agent_name, Some(model.clone()), &e, start);
                 let event = StreamEvent {
                     event: "error".to_string(),
                     content: None,
                     agent: None,
                     context_id: Some(context_id_clone.clone()),
                     error: Some(format!("Failed to start stream: {}", e)),
+                    usage: None,
+                    sources: None,
                 };
                 yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
                 return;
             }
         }
 
+        // Hooks see the complete response; tokens already sent are unaffected
+        if let Err(e) = agent_context.hooks.response(&agent_context, agent_name, &mut full_response).await {
+            let event = StreamEvent {
+                event: "error".to_string(),
+                content: None,
+                agent: None,
+                context_id: Some(context_id_clone.clone()),
+                error: Some(e.to_string()),
+                usage: None,
+                sources: None,
+            };
+            yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+            return;
+        }
+        if let Some(guardrails) = &guardrails {
+            full_response = match guardrails.process_output(&full_response).await {
+                Ok(output) => output,
+                Err(e) => {
+                    let event = StreamEvent {
+                        event: "error".to_string(),
+                        content: None,
+                        agent: None,
+                        context_id: Some(context_id_clone.clone()),
+                        error: Some(e.to_string()),
+                        usage: None,
+                        sources: None,
+                    };
+                    yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+                    return;
+                }
+            };
+            if !full_response.is_empty() {
+                let event = StreamEvent {
+                    event: "token".to_string(),
+                    content: Some(full_response.clone()),
+                    agent: None,
+                    context_id: None,
+                    error: None,
+                    usage: None,
+                    sources: None,
+                };
+                yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+            }
+        }
+
         // Store messages in conversation
         let msg_id = Uuid::new_v4().to_string();
-        if let Err(e) = state_clone
-            .db
-            .add_message(&msg_id, &context_id_clone, MessageRole::User, &message)
-            .await {
+        let stored = match conversation_keys::seal(key.as_ref(), &message) {
+            Ok(content) => state_clone
+                .db
+                .add_message(&msg_id, &context_id_clone, MessageRole::User, &content)
+                .await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = stored {
             tracing::error!("Failed to store user message in conversation {}: {}", context_id_clone, e);
         }
 
-        let resp_id = Uuid::new_v4().to_string();
-        if let Err(e) = state_clone
-            .db
-            .add_message(&resp_id, &context_id_clone, MessageRole::Assistant, &full_response)
-            .await {
-            tracing::error!("Failed to store assistant message in conversation {}: {}", context_id_clone, e);
+        // A stopped run keeps whatever was generated before the stop
+        if !(stopped && full_response.is_empty()) {
+            let resp_id = Uuid::new_v4().to_string();
+            let stored = match conversation_keys::seal(key.as_ref(), &full_response) {
+                Ok(content) => state_clone
+                    .db
+                    .add_message(&resp_id, &context_id_clone, MessageRole::Assistant, &content)
+                    .await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = stored {
+                tracing::error!("Failed to store assistant message in conversation {}: {}", context_id_clone, e);
+            }
+            store_generation(&state_clone, &context_id_clone, &resp_id, &generation).await;
+            if !stopped && agent_context.conversation_history.is_empty() {
+                title_in_background(&state_clone, &context_id_clone, &message, &full_response);
+            }
         }
 
-        // Send done event
+        // Record the request and its estimated spend
+        let usage = StreamUsage::estimate(&budgets, &model, &full_prompt, &full_response);
+        analytics::record_in_background(state_clone.tenant_db.pool(), RequestEvent {
+            tenant_id,
+            user_id: Some(claims_clone.sub.clone()),
+            source: "stream",
+            agent_name: agent_name.to_string(),
+            model: model.clone(),
+            status: if stopped { analytics::STOPPED } else { analytics::COMPLETED },
+            latency_ms: start.elapsed().as_millis() as i64,
+            input_tokens: usage.prompt_tokens as i64,
+            output_tokens: usage.completion_tokens as i64,
+            cost_usd: usage.estimated_cost,
+            error: None,
+        });
+        if let Err(e) = spend::record_spend(
+            state_clone.tenant_db.pool(),
+            &claims_clone.sub,
+            agent_name,
+            &model,
+            usage.prompt_tokens as i64,
+            usage.completion_tokens as i64,
+            usage.estimated_cost,
+        ).await {
+            tracing::warn!("Failed to record spend for {}: {}", claims_clone.sub, e);
+        }
+
+        // Send the final usage for the whole response
+        let usage_event = usage.event();
+        yield Ok(Event::default().data(serde_json::to_string(&usage_event).unwrap_or_default()));
+
+        // Send done event ("stopped" if the run was interrupted)
         let done_event = StreamEvent {
-            event: "done".to_string(),
+            event: if stopped { "stopped" } else { "done" }.to_string(),
             content: None,
             agent: Some(format!("{:?} ({})", agent_type, source)),
             context_id: Some(context_id_clone),
             error: None,
+            usage: None,
+            sources: (!sources.is_empty()).then_some(sources),
         };
         yield Ok(Event::default().data(serde_json::to_string(&done_event).unwrap_or_default()));
     };
 
-    Sse::new(stream).keep_alive(
+    Ok(Sse::new(stream).keep_alive(
         axum::response::sse::KeepAlive::new()
             .interval(std::time::Duration::from_secs(15))
             .text("keep-alive"),
-    )
+    ))
 }
 use axum::response::IntoResponse;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversation_overrides_win_over_agent_defaults() {
+        let mut config: AgentConfig = toml::from_str(
+            r#"
+            model = "fast"
+            system_prompt = "You are helpful."
+
+            [personas.formal]
+            prompt = "Answer formally."
+            "#,
+        )
+        .unwrap();
+        let overrides = ConversationOverrides {
+            model: Some("powerful".to_string()),
+            temperature: Some(0.2),
+            agent: Some("research".to_string()),
+            persona: Some("formal".to_string()),
+        };
+
+        apply_overrides(&mut config, &overrides);
+        assert_eq!(config.model, "powerful");
+        assert_eq!(
+            config.system_prompt.as_deref(),
+            Some("You are helpful.\n\nAnswer formally.")
+        );
+        assert_eq!(
+            requested_agent(None, &overrides),
+            Some(AgentType::from_string("research"))
+        );
+        let sampling = conversation_sampling(Sampling::default(), &overrides);
+        assert_eq!(sampling.temperature, Some(0.2));
+
+        // What the request asks for still wins over the conversation
+        assert_eq!(
+            requested_agent(Some(AgentType::Product), &overrides),
+            Some(AgentType::Product)
+        );
+        let sampling = conversation_sampling(
+            Sampling {
+                temperature: Some(0.9),
+                ..Default::default()
+            },
+            &overrides,
+        );
+        assert_eq!(sampling.temperature, Some(0.9));
+
+        // Nothing pinned keeps the agent's defaults
+        let mut config: AgentConfig = toml::from_str(r#"model = "fast""#).unwrap();
+        apply_overrides(&mut config, &ConversationOverrides::default());
+        assert_eq!(config.model, "fast");
+        assert_eq!(requested_agent(None, &ConversationOverrides::default()), None);
+    }
+
+    #[test]
+    fn test_regeneration_prompt_threads_feedback() {
+        let prompt = regeneration_prompt("What is Rust?", "A language.", Some(" more formal "));
+        assert!(prompt.starts_with("What is Rust?"));
+        assert!(prompt.contains("A language."));
+        assert!(prompt.ends_with("into account: more formal"));
+
+        let prompt = regeneration_prompt("What is Rust?", "A language.", Some("  "));
+        assert!(prompt.ends_with("Write a different, improved answer."));
+    }
+
+    #[test]
+    fn test_stream_usage_estimates_cost_from_pricing() {
+        let mut budgets = BudgetsConfig::default();
+        budgets.pricing.insert(
+            "balanced".to_string(),
+            crate::utils::toml_config::ModelPricing {
+                input_per_1k: 1.0,
+                output_per_1k: 2.0,
+            },
+        );
+
+        let prompt = "a".repeat(4000);
+        let usage = StreamUsage::estimate(&budgets, "balanced", &prompt, &"b".repeat(2000));
+        assert_eq!(usage.prompt_tokens, 1000);
+        assert_eq!(usage.completion_tokens, 500);
+        assert_eq!(usage.total_tokens, 1500);
+        assert!((usage.estimated_cost - 2.0).abs() < 1e-9);
+
+        let event = serde_json::to_value(usage.event()).unwrap();
+        assert_eq!(event["event"], "usage");
+        assert_eq!(event["usage"]["completion_tokens"], 500);
+        assert!(event.get("content").is_none());
+    }
+}
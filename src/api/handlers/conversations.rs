@@ -1,19 +1,48 @@
 //! Conversation management handlers.
 //!
-//! This module provides CRUD operations for user conversations.
+//! This module provides CRUD operations and full-text search for user
+//! conversations, feedback on their messages, the files attached to them
+//! for document Q&A (see [`crate::rag::attachments`]), the RAG collections
+//! their retrieval is pinned to (see [`crate::rag::scope`]), the project
+//! they belong to (see [`crate::db::projects`]), and their automatic
+//! titling.
 
+#[cfg(feature = "ares-vector")]
+use crate::api::handlers::rag::{
+    check_scope_collections, conversation_attachments, scoped_passages,
+};
 use crate::{
+    api::{
+        encryption::ConversationSecret,
+        handlers::{projects::load_project, user_agents::resolve_agent},
+        workspace::ActiveWorkspace,
+    },
     auth::middleware::AuthUser,
-    db::postgres::Conversation,
-    types::{AppError, Result},
+    db::{
+        archive,
+        attachments::{self, Attachment},
+        conversation_keys,
+        feedback::{self, MessageFeedback, Rating},
+        postgres::Conversation,
+        projects, rag_scopes, shares, threads,
+        traits::{ConversationFilter, ConversationSort},
+    },
+    rag::{
+        attachments::{Passage, MAX_ATTACHMENTS, MAX_ATTACHMENT_BYTES, PASSAGES_PER_MESSAGE},
+        scope::{self as rag_scope, ScopeCommand},
+    },
+    types::{AppError, ConversationOverrides, MessageRole, RagScope, Result, ToolCallTrace},
     AppState,
 };
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::StatusCode,
     Json,
 };
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
+use uuid::Uuid;
 
 /// Conversation summary returned in list endpoints.
 #[derive(Debug, Serialize, ToSchema)]
@@ -28,6 +57,11 @@ pub struct ConversationSummary {
     pub created_at: String,
     /// RFC3339 formatted last update timestamp
     pub updated_at: String,
+    /// Whether the messages are in cold storage; opening the conversation
+    /// restores them
+    pub archived: bool,
+    /// Project the conversation belongs to, if any
+    pub project_id: Option<String>,
 }
 
 impl From<Conversation> for ConversationSummary {
@@ -38,6 +72,8 @@ impl From<Conversation> for ConversationSummary {
             message_count: c.message_count,
             created_at: c.created_at,
             updated_at: c.updated_at,
+            archived: false,
+            project_id: c.project_id,
         }
     }
 }
@@ -51,6 +87,13 @@ pub struct ConversationDetails {
     pub title: Option<String>,
     /// Messages in the conversation, ordered by time
     pub messages: Vec<ConversationMessage>,
+    /// Model, temperature and agent pinned on this conversation
+    pub overrides: ConversationOverrides,
+    /// Project the conversation belongs to, if any
+    pub project_id: Option<String>,
+    /// Whether messages are stored encrypted; reading them takes the
+    /// conversation's secret in the `X-Conversation-Secret` header
+    pub encrypted: bool,
     /// RFC3339 formatted creation timestamp
     pub created_at: String,
     /// RFC3339 formatted last update timestamp
@@ -70,6 +113,36 @@ pub struct ConversationMessage {
     pub created_at: String,
 }
 
+/// Tool calls made while generating an assistant message.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessageTrace {
+    /// Conversation identifier
+    pub conversation_id: String,
+    /// Message identifier
+    pub message_id: String,
+    /// Tool calls in the order they were made, across any agents the
+    /// conversation was handed off to
+    pub tool_calls: Vec<ToolCallTrace>,
+}
+
+/// Feedback on an assistant message.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MessageFeedbackRequest {
+    /// Thumbs "up" or "down"
+    pub rating: Rating,
+    /// Optional free-text comment
+    pub comment: Option<String>,
+}
+
+/// A file to attach to a conversation.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AttachmentRequest {
+    /// File name, shown as the source of passages from the file
+    pub filename: String,
+    /// The file's text content
+    pub content: String,
+}
+
 /// Request to update a conversation.
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateConversationRequest {
@@ -77,12 +150,52 @@ pub struct UpdateConversationRequest {
     pub title: Option<String>,
 }
 
-/// List all conversations for the authenticated user.
+/// Request to move a conversation or file into a project.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProjectAssignment {
+    /// Project to move into, or `null` to move out of any project
+    pub project_id: Option<String>,
+}
+
+/// Search, sorting and pagination of listed conversations.
+#[derive(Debug, Deserialize)]
+pub struct ListConversationsQuery {
+    /// Full-text query matched against titles and message content
+    pub q: Option<String>,
+    /// Sort order (default: relevance when searching, updated otherwise)
+    pub sort: Option<ConversationSort>,
+    /// "asc" or "desc" (default: desc, except asc for titles)
+    pub order: Option<String>,
+    /// Maximum conversations returned (default: all, at most 200)
+    pub limit: Option<u32>,
+    /// Conversations skipped before the first one returned (default: 0)
+    pub offset: Option<u32>,
+    /// Only list the conversations of this project
+    pub project_id: Option<String>,
+}
+
+/// Largest page of conversations returned
+const MAX_CONVERSATIONS_PAGE: u32 = 200;
+
+/// List the authenticated user's conversations.
+///
+/// With `q`, only conversations whose title or messages match the query
+/// are listed, best match first. Archived conversations match on their
+/// title only.
 #[utoipa::path(
     get,
     path = "/api/conversations",
+    params(
+        ("q" = Option<String>, Query, description = "Full-text query matched against titles and message content"),
+        ("sort" = Option<ConversationSort>, Query, description = "updated, created, title or relevance (default: relevance when searching, updated otherwise)"),
+        ("order" = Option<String>, Query, description = "asc or desc (default: desc, except asc for titles)"),
+        ("limit" = Option<u32>, Query, description = "Maximum conversations returned (default: all, at most 200)"),
+        ("offset" = Option<u32>, Query, description = "Conversations skipped before the first one returned (default: 0)"),
+        ("project_id" = Option<String>, Query, description = "Only list the conversations of this project")
+    ),
     responses(
         (status = 200, description = "List of conversations", body = Vec<ConversationSummary>),
+        (status = 400, description = "Invalid order"),
         (status = 401, description = "Unauthorized")
     ),
     tag = "conversations",
@@ -91,12 +204,41 @@ pub struct UpdateConversationRequest {
 pub async fn list_conversations(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Query(query): Query<ListConversationsQuery>,
 ) -> Result<Json<Vec<ConversationSummary>>> {
-    let conversations = state.db.get_user_conversations(&claims.sub).await?;
+    let descending = match query.order.as_deref() {
+        None => None,
+        Some("asc") => Some(false),
+        Some("desc") => Some(true),
+        Some(other) => {
+            return Err(AppError::InvalidInput(format!(
+                "order must be 'asc' or 'desc', not '{}'",
+                other
+            )))
+        }
+    };
+    let searching = query.q.as_deref().is_some_and(|q| !q.trim().is_empty());
+    let filter = ConversationFilter {
+        sort: query.sort.unwrap_or(match searching {
+            true => ConversationSort::Relevance,
+            false => ConversationSort::Updated,
+        }),
+        query: query.q,
+        descending,
+        limit: query.limit.map(|limit| limit.min(MAX_CONVERSATIONS_PAGE)),
+        offset: query.offset.unwrap_or(0),
+        workspace_id: workspace.id().map(str::to_string),
+        project_id: query.project_id,
+    };
+    let conversations = state
+        .db
+        .search_user_conversations(&claims.sub, &filter)
+        .await?;
 
     let summaries: Vec<ConversationSummary> = conversations
         .into_iter()
-        .map(|c| ConversationSummary { id: c.id, title: Some(c.title), message_count: c.message_count, created_at: c.created_at, updated_at: c.updated_at })
+        .map(|c| ConversationSummary { id: c.id, title: Some(c.title), message_count: c.message_count, created_at: c.created_at, updated_at: c.updated_at, archived: c.archived, project_id: c.project_id })
         .collect();
 
     Ok(Json(summaries))
@@ -111,6 +253,7 @@ pub async fn list_conversations(
     ),
     responses(
         (status = 200, description = "Conversation details", body = ConversationDetails),
+        (status = 403, description = "Encrypted conversation without its secret, or a wrong one"),
         (status = 404, description = "Conversation not found"),
         (status = 401, description = "Unauthorized")
     ),
@@ -120,39 +263,248 @@ pub async fn list_conversations(
 pub async fn get_conversation(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    secret: ConversationSecret,
     Path(id): Path<String>,
 ) -> Result<Json<ConversationDetails>> {
     // Verify conversation belongs to user
     let conversation = state.db.get_conversation(&id).await?;
 
-    if conversation.user_id != claims.sub {
+    if !conversation.belongs_to(&claims.sub, workspace.id()) {
         return Err(AppError::Auth(
             "Not authorized to access this conversation".to_string(),
         ));
     }
 
-    let messages = state.db.get_conversation_history(&id).await?;
+    let key = secret.unlock(&state, &id).await?;
+    restore_archived(&state, &id).await?;
+    let messages =
+        conversation_keys::open_messages(key.as_ref(), state.db.get_conversation_history(&id).await?)?;
 
     let message_details: Vec<ConversationMessage> = messages
         .into_iter()
-        .enumerate()
-        .map(|(idx, msg)| ConversationMessage {
-            id: format!("{}-{}", id, idx), // Generate a pseudo-ID from conversation_id and index
+        .map(|msg| ConversationMessage {
+            id: msg.id,
             role: format!("{:?}", msg.role).to_lowercase(),
             content: msg.content,
             created_at: msg.timestamp.to_rfc3339(),
         })
         .collect();
 
+    let overrides = conversation.overrides();
+
     Ok(Json(ConversationDetails {
         id: conversation.id,
         title: conversation.title,
         messages: message_details,
+        overrides,
+        project_id: conversation.project_id,
+        encrypted: conversation.encrypted,
         created_at: conversation.created_at,
         updated_at: conversation.updated_at,
     }))
 }
 
+/// Get the tool-call trace of a message.
+///
+/// Lists every tool call the agent made while generating the message, with
+/// its arguments, result, duration and tool-calling round. Messages generated
+/// without tools, user messages and streamed replies have an empty trace.
+#[utoipa::path(
+    get,
+    path = "/api/conversations/{id}/messages/{mid}/trace",
+    params(
+        ("id" = String, Path, description = "Conversation ID"),
+        ("mid" = String, Path, description = "Message ID")
+    ),
+    responses(
+        (status = 200, description = "Tool-call trace", body = MessageTrace),
+        (status = 404, description = "Conversation or message not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "conversations",
+    security(("bearer" = []))
+)]
+pub async fn get_message_trace(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path((id, mid)): Path<(String, String)>,
+) -> Result<Json<MessageTrace>> {
+    // Verify conversation belongs to user
+    let conversation = state.db.get_conversation(&id).await?;
+
+    if !conversation.belongs_to(&claims.sub, workspace.id()) {
+        return Err(AppError::Auth(
+            "Not authorized to access this conversation".to_string(),
+        ));
+    }
+
+    restore_archived(&state, &id).await?;
+    let messages = state.db.get_conversation_history(&id).await?;
+    if !messages.iter().any(|msg| msg.id == mid) {
+        return Err(AppError::NotFound("Message not found".to_string()));
+    }
+
+    let tool_calls = state.db.get_tool_call_traces(&id, &mid).await?;
+
+    Ok(Json(MessageTrace {
+        conversation_id: id,
+        message_id: mid,
+        tool_calls,
+    }))
+}
+
+/// Longest feedback comment accepted, in characters
+const MAX_FEEDBACK_COMMENT_CHARS: usize = 4000;
+
+/// Rate an assistant message.
+///
+/// Stores a thumbs up or down and an optional comment, with the agent,
+/// model and system prompt version that generated the message and a copy of
+/// the exchange, for building evaluation datasets. Rating a message again
+/// replaces the earlier feedback.
+#[utoipa::path(
+    post,
+    path = "/api/conversations/{id}/messages/{mid}/feedback",
+    params(
+        ("id" = String, Path, description = "Conversation ID"),
+        ("mid" = String, Path, description = "Message ID")
+    ),
+    request_body = MessageFeedbackRequest,
+    responses(
+        (status = 200, description = "Feedback stored", body = MessageFeedback),
+        (status = 400, description = "Not an assistant message, or comment too long"),
+        (status = 404, description = "Conversation or message not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "conversations",
+    security(("bearer" = []))
+)]
+pub async fn submit_message_feedback(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path((id, mid)): Path<(String, String)>,
+    Json(payload): Json<MessageFeedbackRequest>,
+) -> Result<Json<MessageFeedback>> {
+    Ok(Json(
+        rate_message(&state, claims.sub, &workspace, id, mid, payload).await?,
+    ))
+}
+
+/// Rate an assistant message by its ID alone.
+///
+/// The same as rating it through its conversation.
+#[utoipa::path(
+    post,
+    path = "/api/messages/{id}/feedback",
+    params(("id" = String, Path, description = "Message ID")),
+    request_body = MessageFeedbackRequest,
+    responses(
+        (status = 200, description = "Feedback stored", body = MessageFeedback),
+        (status = 400, description = "Not an assistant message, or comment too long"),
+        (status = 404, description = "Message not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "conversations",
+    security(("bearer" = []))
+)]
+pub async fn submit_feedback(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(mid): Path<String>,
+    Json(payload): Json<MessageFeedbackRequest>,
+) -> Result<Json<MessageFeedback>> {
+    let id = feedback::conversation_of_message(state.tenant_db.pool(), &mid)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Message not found".to_string()))?;
+    Ok(Json(
+        rate_message(&state, claims.sub, &workspace, id, mid, payload).await?,
+    ))
+}
+
+/// Store a user's feedback on message `mid` of conversation `id`
+async fn rate_message(
+    state: &AppState,
+    user_id: String,
+    workspace: &ActiveWorkspace,
+    id: String,
+    mid: String,
+    payload: MessageFeedbackRequest,
+) -> Result<MessageFeedback> {
+    // Verify conversation belongs to user
+    let conversation = state.db.get_conversation(&id).await?;
+
+    if !conversation.belongs_to(&user_id, workspace.id()) {
+        return Err(AppError::Auth(
+            "Not authorized to access this conversation".to_string(),
+        ));
+    }
+
+    let comment = payload
+        .comment
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty());
+    if comment
+        .as_ref()
+        .is_some_and(|c| c.chars().count() > MAX_FEEDBACK_COMMENT_CHARS)
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Comments are limited to {} characters",
+            MAX_FEEDBACK_COMMENT_CHARS
+        )));
+    }
+
+    restore_archived(state, &id).await?;
+    let messages = state.db.get_conversation_history(&id).await?;
+    let position = messages
+        .iter()
+        .position(|msg| msg.id == mid)
+        .ok_or_else(|| AppError::NotFound("Message not found".to_string()))?;
+    if !matches!(messages[position].role, MessageRole::Assistant) {
+        return Err(AppError::InvalidInput(
+            "Only assistant messages can be rated".to_string(),
+        ));
+    }
+    // An encrypted conversation's exchange stays out of the feedback
+    let question = messages[..position]
+        .iter()
+        .rev()
+        .find(|msg| matches!(msg.role, MessageRole::User))
+        .filter(|_| !conversation.encrypted)
+        .map(|msg| msg.content.clone())
+        .unwrap_or_default();
+    let answer = match conversation.encrypted {
+        true => String::new(),
+        false => messages[position].content.clone(),
+    };
+
+    let pool = state.tenant_db.pool();
+    let generation = feedback::get_generation(pool, &id, &mid).await?;
+    let now = Utc::now().timestamp();
+    feedback::upsert_feedback(
+        pool,
+        &MessageFeedback {
+            id: Uuid::new_v4().to_string(),
+            conversation_id: id,
+            message_id: mid,
+            user_id,
+            rating: payload.rating.as_str().to_string(),
+            comment,
+            agent: generation.as_ref().map(|g| g.agent.clone()),
+            model: generation.as_ref().map(|g| g.model.clone()),
+            prompt_version: generation.map(|g| g.prompt_version),
+            question,
+            answer,
+            created_at: now,
+            updated_at: now,
+        },
+    )
+    .await
+}
+
 /// Update a conversation (e.g., change title).
 #[utoipa::path(
     put,
@@ -172,13 +524,14 @@ pub async fn get_conversation(
 pub async fn update_conversation(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
     Path(id): Path<String>,
     Json(payload): Json<UpdateConversationRequest>,
 ) -> Result<Json<serde_json::Value>> {
     // Verify conversation belongs to user
     let conversation = state.db.get_conversation(&id).await?;
 
-    if conversation.user_id != claims.sub {
+    if !conversation.belongs_to(&claims.sub, workspace.id()) {
         return Err(AppError::Auth(
             "Not authorized to modify this conversation".to_string(),
         ));
@@ -192,6 +545,125 @@ pub async fn update_conversation(
     Ok(Json(serde_json::json!({"success": true})))
 }
 
+/// Pin a model, temperature and/or agent on a conversation.
+///
+/// Subsequent chat messages in this conversation use these values unless the
+/// request names an agent explicitly. Send `null` for a field to clear it.
+#[utoipa::path(
+    put,
+    path = "/api/conversations/{id}/overrides",
+    params(
+        ("id" = String, Path, description = "Conversation ID")
+    ),
+    request_body = ConversationOverrides,
+    responses(
+        (status = 200, description = "Overrides updated", body = ConversationOverrides),
+        (status = 400, description = "Unknown model, agent or persona, or temperature out of range"),
+        (status = 404, description = "Conversation not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "conversations",
+    security(("bearer" = []))
+)]
+pub async fn update_conversation_overrides(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+    Json(payload): Json<ConversationOverrides>,
+) -> Result<Json<ConversationOverrides>> {
+    // Verify conversation belongs to user
+    let conversation = state.db.get_conversation(&id).await?;
+
+    if !conversation.belongs_to(&claims.sub, workspace.id()) {
+        return Err(AppError::Auth(
+            "Not authorized to modify this conversation".to_string(),
+        ));
+    }
+
+    check_overrides(&state, &workspace.owner(&claims.sub), &payload).await?;
+    state.db.update_conversation_overrides(&id, &payload).await?;
+
+    Ok(Json(payload))
+}
+
+/// Check that the model, agent and persona of overrides exist and the
+/// temperature is in range, resolving agents among `owner`'s
+pub(crate) async fn check_overrides(
+    state: &AppState,
+    owner: &str,
+    overrides: &ConversationOverrides,
+) -> Result<()> {
+    if let Some(model) = overrides.model.as_deref() {
+        if !state.provider_registry.has_model(model) {
+            return Err(AppError::InvalidInput(format!("Unknown model: {}", model)));
+        }
+    }
+    if let Some(temperature) = overrides.temperature {
+        if !(0.0..=2.0).contains(&temperature) {
+            return Err(AppError::InvalidInput(
+                "temperature must be between 0.0 and 2.0".to_string(),
+            ));
+        }
+    }
+    if let Some(agent) = overrides.agent.as_deref() {
+        let Ok((config, _)) = resolve_agent(state, owner, agent.to_string()).await else {
+            return Err(AppError::InvalidInput(format!("Unknown agent: {}", agent)));
+        };
+        // Without a pinned agent, any agent defining the persona uses it
+        if let Some(persona) = overrides.persona.as_deref() {
+            config
+                .check_persona(persona)
+                .map_err(AppError::InvalidInput)?;
+        }
+    }
+    Ok(())
+}
+
+/// Move a conversation into one of the space's projects, or out of any.
+///
+/// The conversation then uses the project's default agent settings and
+/// collections.
+#[utoipa::path(
+    put,
+    path = "/api/conversations/{id}/project",
+    params(
+        ("id" = String, Path, description = "Conversation ID")
+    ),
+    request_body = ProjectAssignment,
+    responses(
+        (status = 200, description = "Conversation moved", body = ProjectAssignment),
+        (status = 404, description = "Conversation or project not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "conversations",
+    security(("bearer" = []))
+)]
+pub async fn update_conversation_project(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+    Json(payload): Json<ProjectAssignment>,
+) -> Result<Json<ProjectAssignment>> {
+    // Verify conversation belongs to user
+    let conversation = state.db.get_conversation(&id).await?;
+
+    if !conversation.belongs_to(&claims.sub, workspace.id()) {
+        return Err(AppError::Auth(
+            "Not authorized to modify this conversation".to_string(),
+        ));
+    }
+
+    if let Some(project_id) = payload.project_id.as_deref() {
+        load_project(&state, &workspace.owner(&claims.sub), project_id).await?;
+    }
+    projects::set_conversation_project(state.tenant_db.pool(), &id, payload.project_id.as_deref())
+        .await?;
+
+    Ok(Json(payload))
+}
+
 /// Delete a conversation and all its messages.
 #[utoipa::path(
     delete,
@@ -210,18 +682,744 @@ pub async fn update_conversation(
 pub async fn delete_conversation(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
     Path(id): Path<String>,
 ) -> Result<axum::http::StatusCode> {
     // Verify conversation belongs to user
     let conversation = state.db.get_conversation(&id).await?;
 
-    if conversation.user_id != claims.sub {
+    if !conversation.belongs_to(&claims.sub, workspace.id()) {
         return Err(AppError::Auth(
             "Not authorized to delete this conversation".to_string(),
         ));
     }
 
-    state.db.delete_conversation(&id).await?;
+    remove_conversation(&state, &id).await?;
 
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
+
+/// Delete a conversation with its messages, archive, attachments and
+/// everything else kept about it.
+pub(crate) async fn remove_conversation(state: &AppState, id: &str) -> Result<()> {
+    let config = state.config_manager.config();
+    archive::discard_archive(state.tenant_db.pool(), &config.archive, id).await;
+    discard_attachments(state, id).await;
+    if let Err(e) = rag_scopes::delete_scope(state.tenant_db.pool(), id).await {
+        tracing::warn!("Failed to delete RAG scope of conversation {}: {}", id, e);
+    }
+    if let Err(e) = feedback::delete_conversation_generations(state.tenant_db.pool(), id).await {
+        tracing::warn!(
+            "Failed to delete message generations of conversation {}: {}",
+            id,
+            e
+        );
+    }
+    if let Err(e) = threads::delete_thread(state.tenant_db.pool(), id).await {
+        tracing::warn!("Failed to delete thread runs of conversation {}: {}", id, e);
+    }
+    if let Err(e) = shares::delete_conversation_shares(state.tenant_db.pool(), id).await {
+        tracing::warn!("Failed to delete share links of conversation {}: {}", id, e);
+    }
+    state.db.delete_conversation(id).await
+}
+
+/// Move a conversation's messages to cold storage.
+///
+/// The messages and their tool-call traces are written to the `[archive]`
+/// location and removed from the database; the conversation stays listed
+/// with `archived: true`. Opening or continuing it restores the messages.
+#[utoipa::path(
+    post,
+    path = "/api/conversations/{id}/archive",
+    params(
+        ("id" = String, Path, description = "Conversation ID")
+    ),
+    responses(
+        (status = 200, description = "Conversation archived", body = archive::ArchivedConversation),
+        (status = 400, description = "Conversation already archived or empty"),
+        (status = 404, description = "Conversation not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "conversations",
+    security(("bearer" = []))
+)]
+pub async fn archive_conversation(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+) -> Result<Json<archive::ArchivedConversation>> {
+    // Verify conversation belongs to user
+    let conversation = state.db.get_conversation(&id).await?;
+
+    if !conversation.belongs_to(&claims.sub, workspace.id()) {
+        return Err(AppError::Auth(
+            "Not authorized to modify this conversation".to_string(),
+        ));
+    }
+
+    let config = state.config_manager.config();
+    let archived =
+        archive::archive_conversation(state.tenant_db.pool(), &config.archive, &id).await?;
+
+    Ok(Json(archived))
+}
+
+/// Create a conversation for the user in the space they act in, and in
+/// `project_id` if given, unless it exists already.
+///
+/// An existing conversation must be the user's, from the same space; it
+/// stays in its project.
+pub(crate) async fn ensure_conversation(
+    state: &AppState,
+    conversation_id: &str,
+    user_id: &str,
+    workspace: &ActiveWorkspace,
+    project_id: Option<&str>,
+) -> Result<()> {
+    if state.db.conversation_exists(conversation_id).await? {
+        let conversation = state.db.get_conversation(conversation_id).await?;
+        if !conversation.belongs_to(user_id, workspace.id()) {
+            return Err(AppError::Auth(
+                "Not authorized to modify this conversation".to_string(),
+            ));
+        }
+        return Ok(());
+    }
+    if let Some(project_id) = project_id {
+        load_project(state, &workspace.owner(user_id), project_id).await?;
+    }
+    state
+        .db
+        .create_conversation(conversation_id, user_id, None, workspace.id())
+        .await?;
+    if project_id.is_some() {
+        projects::set_conversation_project(state.tenant_db.pool(), conversation_id, project_id)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Model, temperature, agent and persona a conversation runs with, before
+/// the user's preferences: its own, then its project's defaults
+pub(crate) async fn conversation_overrides(
+    state: &AppState,
+    conversation: &Conversation,
+) -> Result<ConversationOverrides> {
+    let overrides = conversation.overrides();
+    if conversation.project_id.is_none() {
+        return Ok(overrides);
+    }
+    Ok(
+        match projects::conversation_project(state.tenant_db.pool(), &conversation.id).await? {
+            Some(project) => project.apply(overrides),
+            None => overrides,
+        },
+    )
+}
+
+/// Restore a conversation's messages from cold storage if it was archived
+pub(crate) async fn restore_archived(state: &AppState, id: &str) -> Result<()> {
+    let config = state.config_manager.config();
+    archive::restore_conversation(state.tenant_db.pool(), &config.archive, id).await?;
+    Ok(())
+}
+
+/// Attach a file to a conversation.
+///
+/// The file is chunked and embedded into a collection of the conversation's
+/// own. From then on, every message in the conversation is answered from the
+/// passages of its attached files most relevant to it. A conversation that
+/// doesn't exist yet is created.
+#[utoipa::path(
+    post,
+    path = "/api/conversations/{id}/attachments",
+    params(
+        ("id" = String, Path, description = "Conversation ID")
+    ),
+    request_body = AttachmentRequest,
+    responses(
+        (status = 201, description = "File attached", body = Attachment),
+        (status = 400, description = "Empty or too large file, or too many attachments"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "conversations",
+    security(("bearer" = []))
+)]
+pub async fn attach_file(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+    Json(payload): Json<AttachmentRequest>,
+) -> Result<(StatusCode, Json<Attachment>)> {
+    let attachment = attach_text(
+        &state,
+        &id,
+        &claims.sub,
+        &workspace,
+        &Uuid::new_v4().to_string(),
+        &payload.filename,
+        &payload.content,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(attachment)))
+}
+
+/// Attach text to a conversation as `attachment_id`, creating the
+/// conversation if it doesn't exist yet.
+pub(crate) async fn attach_text(
+    state: &AppState,
+    conversation_id: &str,
+    user_id: &str,
+    workspace: &ActiveWorkspace,
+    attachment_id: &str,
+    filename: &str,
+    content: &str,
+) -> Result<Attachment> {
+    let filename = filename.trim();
+    if filename.is_empty() {
+        return Err(AppError::InvalidInput("filename is required".to_string()));
+    }
+    if content.len() > MAX_ATTACHMENT_BYTES {
+        return Err(AppError::InvalidInput(format!(
+            "Attachments are limited to {} bytes",
+            MAX_ATTACHMENT_BYTES
+        )));
+    }
+
+    ensure_conversation(state, conversation_id, user_id, workspace, None).await?;
+
+    let pool = state.tenant_db.pool();
+    let attached = attachments::list_attachments(pool, conversation_id).await?;
+    if attached.len() >= MAX_ATTACHMENTS {
+        return Err(AppError::InvalidInput(format!(
+            "A conversation can have at most {} attachments",
+            MAX_ATTACHMENTS
+        )));
+    }
+
+    let store = conversation_attachments(&state.config_manager.config()).await?;
+    let chunks = store
+        .add(conversation_id, attachment_id, filename, content)
+        .await?;
+    let attachment = Attachment {
+        id: attachment_id.to_string(),
+        conversation_id: conversation_id.to_string(),
+        user_id: user_id.to_string(),
+        filename: filename.to_string(),
+        size_bytes: content.len() as i64,
+        chunks: chunks as i32,
+        created_at: Utc::now().timestamp(),
+    };
+    attachments::insert_attachment(pool, &attachment).await?;
+    Ok(attachment)
+}
+
+/// List the files attached to a conversation.
+#[utoipa::path(
+    get,
+    path = "/api/conversations/{id}/attachments",
+    params(
+        ("id" = String, Path, description = "Conversation ID")
+    ),
+    responses(
+        (status = 200, description = "Attached files, oldest first", body = Vec<Attachment>),
+        (status = 404, description = "Conversation not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "conversations",
+    security(("bearer" = []))
+)]
+pub async fn list_attachments(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<Attachment>>> {
+    // Verify conversation belongs to user
+    let conversation = state.db.get_conversation(&id).await?;
+
+    if !conversation.belongs_to(&claims.sub, workspace.id()) {
+        return Err(AppError::Auth(
+            "Not authorized to access this conversation".to_string(),
+        ));
+    }
+
+    Ok(Json(
+        attachments::list_attachments(state.tenant_db.pool(), &id).await?,
+    ))
+}
+
+/// Remove a file from a conversation.
+///
+/// Removing the last file returns the conversation to regular chat.
+#[utoipa::path(
+    delete,
+    path = "/api/conversations/{id}/attachments/{attachment_id}",
+    params(
+        ("id" = String, Path, description = "Conversation ID"),
+        ("attachment_id" = String, Path, description = "Attachment ID")
+    ),
+    responses(
+        (status = 204, description = "Attachment removed"),
+        (status = 404, description = "Conversation or attachment not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "conversations",
+    security(("bearer" = []))
+)]
+pub async fn delete_attachment(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path((id, attachment_id)): Path<(String, String)>,
+) -> Result<StatusCode> {
+    // Verify conversation belongs to user
+    let conversation = state.db.get_conversation(&id).await?;
+
+    if !conversation.belongs_to(&claims.sub, workspace.id()) {
+        return Err(AppError::Auth(
+            "Not authorized to modify this conversation".to_string(),
+        ));
+    }
+
+    let pool = state.tenant_db.pool();
+    let attachment = attachments::get_attachment(pool, &attachment_id, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Attachment not found".to_string()))?;
+    let store = conversation_attachments(&state.config_manager.config()).await?;
+    if attachments::list_attachments(pool, &id).await?.len() == 1 {
+        store.delete(&id).await?;
+    } else {
+        store
+            .remove(&id, &attachment.id, attachment.chunks as usize)
+            .await?;
+    }
+    attachments::delete_attachment(pool, &attachment.id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Get the RAG collections a conversation's retrieval is pinned to.
+///
+/// A conversation without a scope has no collections.
+#[utoipa::path(
+    get,
+    path = "/api/conversations/{id}/rag-scope",
+    params(
+        ("id" = String, Path, description = "Conversation ID")
+    ),
+    responses(
+        (status = 200, description = "The conversation's scope", body = RagScope),
+        (status = 404, description = "Conversation not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "conversations",
+    security(("bearer" = []))
+)]
+pub async fn get_rag_scope(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+) -> Result<Json<RagScope>> {
+    // Verify conversation belongs to user
+    let conversation = state.db.get_conversation(&id).await?;
+
+    if !conversation.belongs_to(&claims.sub, workspace.id()) {
+        return Err(AppError::Auth(
+            "Not authorized to access this conversation".to_string(),
+        ));
+    }
+
+    let stored = rag_scopes::get_scope(state.tenant_db.pool(), &id).await?;
+    Ok(Json(stored.map(|s| s.scope).unwrap_or_default()))
+}
+
+/// Pin a conversation's retrieval to some of the user's RAG collections.
+///
+/// Subsequent messages are answered from passages of these collections
+/// only, narrowed to documents carrying every given tag. Attached files
+/// take precedence over the scope.
+#[utoipa::path(
+    put,
+    path = "/api/conversations/{id}/rag-scope",
+    params(
+        ("id" = String, Path, description = "Conversation ID")
+    ),
+    request_body = RagScope,
+    responses(
+        (status = 200, description = "Scope updated", body = RagScope),
+        (status = 400, description = "No collections, or too many"),
+        (status = 404, description = "Conversation or collection not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "conversations",
+    security(("bearer" = []))
+)]
+pub async fn update_rag_scope(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+    Json(payload): Json<RagScope>,
+) -> Result<Json<RagScope>> {
+    // Verify conversation belongs to user
+    let conversation = state.db.get_conversation(&id).await?;
+
+    if !conversation.belongs_to(&claims.sub, workspace.id()) {
+        return Err(AppError::Auth(
+            "Not authorized to modify this conversation".to_string(),
+        ));
+    }
+
+    let scope = rag_scope::normalize(payload);
+    pin_rag_scope(&state, &id, &workspace.owner(&claims.sub), &scope).await?;
+    Ok(Json(scope))
+}
+
+/// Remove a conversation's RAG scope.
+#[utoipa::path(
+    delete,
+    path = "/api/conversations/{id}/rag-scope",
+    params(
+        ("id" = String, Path, description = "Conversation ID")
+    ),
+    responses(
+        (status = 204, description = "Scope removed"),
+        (status = 404, description = "Conversation not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "conversations",
+    security(("bearer" = []))
+)]
+pub async fn delete_rag_scope(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    // Verify conversation belongs to user
+    let conversation = state.db.get_conversation(&id).await?;
+
+    if !conversation.belongs_to(&claims.sub, workspace.id()) {
+        return Err(AppError::Auth(
+            "Not authorized to modify this conversation".to_string(),
+        ));
+    }
+
+    rag_scopes::delete_scope(state.tenant_db.pool(), &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Check and store a conversation's scope, naming collections of `owner`
+async fn pin_rag_scope(
+    state: &AppState,
+    conversation_id: &str,
+    owner: &str,
+    scope: &RagScope,
+) -> Result<()> {
+    if scope.collections.is_empty() {
+        return Err(AppError::InvalidInput(
+            "A RAG scope needs at least one collection".to_string(),
+        ));
+    }
+    check_collections(state, owner, &scope.collections).await?;
+    rag_scopes::set_scope(state.tenant_db.pool(), conversation_id, owner, scope).await
+}
+
+/// Check that `owner` has each of up to [`rag_scope::MAX_COLLECTIONS`]
+/// collections
+pub(crate) async fn check_collections(
+    state: &AppState,
+    owner: &str,
+    collections: &[String],
+) -> Result<()> {
+    if collections.len() > rag_scope::MAX_COLLECTIONS {
+        return Err(AppError::InvalidInput(format!(
+            "At most {} collections can be searched",
+            rag_scope::MAX_COLLECTIONS
+        )));
+    }
+    let scope = RagScope {
+        collections: collections.to_vec(),
+        tags: Vec::new(),
+    };
+    check_scope_collections(&state.config_manager.config(), owner, &scope).await
+}
+
+/// Run a `/scope` command sent as a chat message, returning the reply.
+///
+/// Creates the conversation if it doesn't exist yet.
+pub(crate) async fn run_scope_command(
+    state: &AppState,
+    conversation_id: &str,
+    user_id: &str,
+    workspace: &ActiveWorkspace,
+    command: ScopeCommand,
+) -> Result<String> {
+    ensure_conversation(state, conversation_id, user_id, workspace, None).await?;
+
+    let pool = state.tenant_db.pool();
+    match command {
+        ScopeCommand::Show => {
+            let stored = rag_scopes::get_scope(pool, conversation_id).await?;
+            Ok(rag_scope::describe(stored.as_ref().map(|s| &s.scope)))
+        }
+        ScopeCommand::Set(scope) => {
+            let owner = workspace.owner(user_id);
+            pin_rag_scope(state, conversation_id, &owner, &scope).await?;
+            Ok(rag_scope::describe(Some(&scope)))
+        }
+        ScopeCommand::Clear => {
+            rag_scopes::delete_scope(pool, conversation_id).await?;
+            Ok(rag_scope::describe(None))
+        }
+    }
+}
+
+/// Passages relevant to a message from a conversation's attached files or,
+/// without attachments, from the collections it is scoped to or, without a
+/// scope, from its project's collections.
+///
+/// Conversations with none of these have none. Failures are logged and treated
+/// as no passages, so the message is answered as regular chat.
+pub(crate) async fn conversation_passages(
+    state: &AppState,
+    conversation_id: &str,
+    message: &str,
+) -> Vec<Passage> {
+    let search = async {
+        let pool = state.tenant_db.pool();
+        let config = state.config_manager.config();
+        if attachments::has_attachments(pool, conversation_id).await? {
+            return conversation_attachments(&config)
+                .await?
+                .search(conversation_id, message, PASSAGES_PER_MESSAGE)
+                .await;
+        }
+        match rag_scopes::get_scope(pool, conversation_id).await? {
+            Some(stored) => {
+                scoped_passages(
+                    &config,
+                    &stored.user_id,
+                    &stored.scope,
+                    message,
+                    PASSAGES_PER_MESSAGE,
+                )
+                .await
+            }
+            None => match projects::conversation_project(pool, conversation_id).await? {
+                Some(project) if !project.collections.is_empty() => {
+                    let scope = RagScope {
+                        collections: project.collections,
+                        tags: Vec::new(),
+                    };
+                    scoped_passages(
+                        &config,
+                        &project.owner_id,
+                        &scope,
+                        message,
+                        PASSAGES_PER_MESSAGE,
+                    )
+                    .await
+                }
+                _ => Ok(Vec::new()),
+            },
+        }
+    };
+    search.await.unwrap_or_else(|e: AppError| {
+        tracing::warn!(
+            "Documents of conversation {} unavailable: {}",
+            conversation_id,
+            e
+        );
+        Vec::new()
+    })
+}
+
+/// Delete a conversation's attachments and their collection, logging failures
+async fn discard_attachments(state: &AppState, conversation_id: &str) {
+    let pool = state.tenant_db.pool();
+    match attachments::delete_conversation_attachments(pool, conversation_id).await {
+        Ok(0) => {}
+        Ok(_) => {
+            let deleted = async {
+                conversation_attachments(&state.config_manager.config())
+                    .await?
+                    .delete(conversation_id)
+                    .await
+            };
+            if let Err(e) = deleted.await {
+                tracing::warn!(
+                    "Failed to delete attachments collection of conversation {}: {}",
+                    conversation_id,
+                    e
+                );
+            }
+        }
+        Err(e) => tracing::warn!(
+            "Failed to delete attachments of conversation {}: {}",
+            conversation_id,
+            e
+        ),
+    }
+}
+
+/// Characters of the first message and answer a title is written from
+const TITLE_SOURCE_CHARS: usize = 1000;
+
+/// Longest title kept, in characters
+const MAX_TITLE_CHARS: usize = 80;
+
+/// Title a conversation from its first exchange, in the background.
+///
+/// Does nothing when titling is disabled or the conversation already has a
+/// title. Failures are logged; the conversation stays untitled.
+pub(crate) fn title_in_background(
+    state: &AppState,
+    conversation_id: &str,
+    message: &str,
+    answer: &str,
+) {
+    let config = state.config_manager.config();
+    if !config.titles.enabled {
+        return;
+    }
+    let model = config.titles.model.clone().unwrap_or_else(|| {
+        config
+            .get_agent("router")
+            .map(|a| a.model.clone())
+            .unwrap_or_else(|| "fast".to_string())
+    });
+    let state = state.clone();
+    let conversation_id = conversation_id.to_string();
+    let prompt = title_prompt(message, answer);
+    tokio::spawn(async move {
+        let titled = async {
+            let conversation = state.db.get_conversation(&conversation_id).await?;
+            // A title would give away what an encrypted conversation is about
+            if conversation.encrypted || conversation.title.is_some_and(|t| !t.trim().is_empty()) {
+                return Ok(());
+            }
+            let llm = state
+                .provider_registry
+                .create_client_for_model(&model)
+                .await?;
+            let raw = llm
+                .generate_with_system(TITLE_SYSTEM_PROMPT, &prompt)
+                .await?;
+            let Some(title) = clean_title(&raw) else {
+                return Ok(());
+            };
+            // The user may have named the conversation in the meantime
+            let conversation = state.db.get_conversation(&conversation_id).await?;
+            if conversation.title.is_some_and(|t| !t.trim().is_empty()) {
+                return Ok(());
+            }
+            state
+                .db
+                .update_conversation_title(&conversation_id, Some(&title))
+                .await
+        };
+        if let Err(e) = titled.await {
+            tracing::warn!("Failed to title conversation {}: {}", conversation_id, e);
+        }
+    });
+}
+
+const TITLE_SYSTEM_PROMPT: &str = "You name conversations. Reply with a title of at most six \
+     words for the conversation below, in the language of the user's message, with no quotes \
+     and no trailing punctuation.";
+
+/// Prompt giving the title model the start of a conversation
+fn title_prompt(message: &str, answer: &str) -> String {
+    let excerpt = |text: &str| text.chars().take(TITLE_SOURCE_CHARS).collect::<String>();
+    format!(
+        "User: {}\n\nAssistant: {}",
+        excerpt(message),
+        excerpt(answer)
+    )
+}
+
+/// The title in a model's reply, without quotes, labels or trailing
+/// punctuation
+fn clean_title(raw: &str) -> Option<String> {
+    let line = raw.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let line = line
+        .strip_prefix("Title:")
+        .or_else(|| line.strip_prefix("title:"))
+        .unwrap_or(line);
+    let quotes: &[char] = &['"', '\'', '*', '`', '\u{201c}', '\u{201d}'];
+    let title = line
+        .trim()
+        .trim_matches(quotes)
+        .trim_end_matches(['.', '!', ':', ';', ','])
+        .trim();
+    if title.is_empty() {
+        return None;
+    }
+    Some(match title.char_indices().nth(MAX_TITLE_CHARS) {
+        Some((end, _)) => format!("{}...", title[..end].trim_end()),
+        None => title.to_string(),
+    })
+}
+
+/// Attachments are stored in the embedded vector database.
+#[cfg(not(feature = "ares-vector"))]
+async fn conversation_attachments(
+    _config: &crate::utils::toml_config::AresConfig,
+) -> Result<crate::rag::attachments::ConversationAttachments> {
+    Err(AppError::Configuration(
+        "Attachments require the `ares-vector` feature".to_string(),
+    ))
+}
+
+/// RAG collections are stored in the embedded vector database.
+#[cfg(not(feature = "ares-vector"))]
+async fn scoped_passages(
+    _config: &crate::utils::toml_config::AresConfig,
+    _user_id: &str,
+    _scope: &RagScope,
+    _query: &str,
+    _limit: usize,
+) -> Result<Vec<Passage>> {
+    Err(AppError::Configuration(
+        "RAG scopes require the `ares-vector` feature".to_string(),
+    ))
+}
+
+#[cfg(not(feature = "ares-vector"))]
+async fn check_scope_collections(
+    _config: &crate::utils::toml_config::AresConfig,
+    _user_id: &str,
+    _scope: &RagScope,
+) -> Result<()> {
+    Err(AppError::Configuration(
+        "RAG scopes require the `ares-vector` feature".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_title() {
+        assert_eq!(
+            clean_title("\n\"Planning a Trip to Lisbon.\"\n").as_deref(),
+            Some("Planning a Trip to Lisbon")
+        );
+        assert_eq!(
+            clean_title("Title: **Rust lifetimes**").as_deref(),
+            Some("Rust lifetimes")
+        );
+        assert_eq!(clean_title("  \n\"\"").as_deref(), None);
+
+        let long = clean_title(&"a".repeat(100)).unwrap();
+        assert!(long.ends_with("..."));
+        assert_eq!(long.chars().count(), MAX_TITLE_CHARS + 3);
+    }
+}
@@ -0,0 +1,611 @@
+//! File upload handlers.
+//!
+//! Files are uploaded once with `POST /api/files` and then referenced by ID:
+//! in chat requests (`file_ids`), which attach them to the conversation, and
+//! in RAG ingestion (`file_id`), which ingests their text. Uploads are
+//! checked against the `[files]` size and type limits, sniffed so their
+//! content matches their declared type, and optionally passed through a
+//! virus scanner before anything is stored (see
+//! [`crate::utils::toml_config::FilesConfig`]). A file can belong to a
+//! project (see [`crate::db::projects`]).
+
+use crate::{
+    api::{
+        handlers::{
+            conversations::{attach_text, ProjectAssignment},
+            projects::load_project,
+        },
+        workspace::ActiveWorkspace,
+    },
+    auth::middleware::AuthUser,
+    db::{
+        attachments::{self, Attachment},
+        files::{self, StoredFile},
+        projects,
+    },
+    rag::{
+        connectors::object_text,
+        loaders::{DocumentLoader, DocxLoader},
+    },
+    types::{AppError, Result},
+    utils::toml_config::FilesConfig,
+    AppState,
+};
+use axum::{
+    body::Body,
+    extract::{Multipart, Path, Query, State},
+    http::{header, StatusCode},
+    response::Response,
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+/// Project to upload a file into.
+#[derive(Debug, Deserialize)]
+pub struct UploadFileQuery {
+    /// Project the file belongs to
+    pub project_id: Option<String>,
+}
+
+/// Pagination and filter of listed files.
+#[derive(Debug, Deserialize)]
+pub struct ListFilesQuery {
+    /// Maximum files returned (default: 50, at most 200)
+    pub limit: Option<u32>,
+    /// Files skipped before the first one returned (default: 0)
+    pub offset: Option<u32>,
+    /// Only list the files of this project
+    pub project_id: Option<String>,
+}
+
+/// Largest page of files returned
+const MAX_FILES_PAGE: u32 = 200;
+
+/// Content type of Word documents
+const DOCX: &str = "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+
+/// Upload a file.
+///
+/// The request is `multipart/form-data` with the file in a part named
+/// `file`. Its content type is taken from the part, or guessed from the
+/// file name when the part has none or `application/octet-stream`, or else
+/// sniffed from the content (see [`crate::rag::loaders`]).
+#[utoipa::path(
+    post,
+    path = "/api/files",
+    params(
+        ("project_id" = Option<String>, Query, description = "Project to upload the file into")
+    ),
+    request_body(content_type = "multipart/form-data", description = "The file, in a part named `file`"),
+    responses(
+        (status = 201, description = "File stored", body = StoredFile),
+        (status = 400, description = "Missing, empty, too large, disallowed or rejected file"),
+        (status = 404, description = "Project not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "files",
+    security(("bearer" = []))
+)]
+pub async fn upload_file(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Query(query): Query<UploadFileQuery>,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<StoredFile>)> {
+    if let Some(project_id) = &query.project_id {
+        load_project(&state, &workspace.owner(&claims.sub), project_id).await?;
+    }
+    let config = state.config_manager.config();
+    let limits = &config.files;
+
+    let mut field = loop {
+        match multipart.next_field().await.map_err(invalid_multipart)? {
+            Some(field) if field.name() == Some("file") => break field,
+            Some(_) => continue,
+            None => {
+                return Err(AppError::InvalidInput(
+                    "Expected the file in a multipart part named 'file'".to_string(),
+                ))
+            }
+        }
+    };
+    let filename = field
+        .file_name()
+        .map(|name| name.rsplit(['/', '\\']).next().unwrap_or(name).trim())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| AppError::InvalidInput("The file part needs a filename".to_string()))?
+        .to_string();
+    let declared = match field.content_type() {
+        Some(declared) if declared != "application/octet-stream" => Some(
+            declared
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase(),
+        ),
+        _ => guess_content_type(&filename).map(String::from),
+    };
+    if let Some(content_type) = &declared {
+        check_allowed(limits, content_type)?;
+    }
+
+    // Read in chunks so an oversized upload is cut off at the limit
+    let mut bytes = Vec::new();
+    while let Some(chunk) = field.chunk().await.map_err(invalid_multipart)? {
+        if bytes.len() + chunk.len() > limits.max_bytes {
+            return Err(AppError::InvalidInput(format!(
+                "Files are limited to {} bytes",
+                limits.max_bytes
+            )));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    if bytes.is_empty() {
+        return Err(AppError::InvalidInput(format!("{} is empty", filename)));
+    }
+
+    // Sniff the type of files that neither declare one nor have a known
+    // extension
+    let content_type = match declared {
+        Some(content_type) => content_type,
+        None => {
+            let sniffed = state.loaders.media_type(&filename, None, &bytes);
+            let content_type = sniffed.ok_or_else(|| {
+                AppError::InvalidInput(format!("Can't tell the content type of {}", filename))
+            })?;
+            check_allowed(limits, content_type)?;
+            content_type.to_string()
+        }
+    };
+
+    check_content(&filename, &content_type, &bytes)?;
+    scan(limits, &filename, &bytes).await?;
+
+    let id = Uuid::new_v4().to_string();
+    let sha256 = hex::encode(Sha256::digest(&bytes));
+    let size_bytes = bytes.len() as i64;
+    let location = files::write_content(limits, &id, bytes).await?;
+    let file = StoredFile {
+        id,
+        user_id: claims.sub,
+        filename,
+        content_type,
+        size_bytes,
+        sha256,
+        location,
+        project_id: query.project_id,
+        created_at: Utc::now().timestamp(),
+    };
+    if let Err(e) = files::insert_file(state.tenant_db.pool(), &file).await {
+        discard_content(limits, &file).await;
+        return Err(e);
+    }
+
+    tracing::info!(
+        file_id = %file.id,
+        content_type = %file.content_type,
+        size_bytes = file.size_bytes,
+        "File uploaded"
+    );
+    Ok((StatusCode::CREATED, Json(file)))
+}
+
+/// List the authenticated user's files, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/files",
+    params(
+        ("limit" = Option<u32>, Query, description = "Maximum files returned (default: 50, at most 200)"),
+        ("offset" = Option<u32>, Query, description = "Files skipped before the first one returned (default: 0)"),
+        ("project_id" = Option<String>, Query, description = "Only list the files of this project")
+    ),
+    responses(
+        (status = 200, description = "Uploaded files", body = Vec<StoredFile>),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "files",
+    security(("bearer" = []))
+)]
+pub async fn list_files(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Query(query): Query<ListFilesQuery>,
+) -> Result<Json<Vec<StoredFile>>> {
+    let limit = query.limit.unwrap_or(50).min(MAX_FILES_PAGE);
+    Ok(Json(
+        files::list_files(
+            state.tenant_db.pool(),
+            &claims.sub,
+            query.project_id.as_deref(),
+            limit as i64,
+            query.offset.unwrap_or(0) as i64,
+        )
+        .await?,
+    ))
+}
+
+/// Get an uploaded file's metadata.
+#[utoipa::path(
+    get,
+    path = "/api/files/{id}",
+    params(
+        ("id" = String, Path, description = "File ID")
+    ),
+    responses(
+        (status = 200, description = "File metadata", body = StoredFile),
+        (status = 404, description = "File not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "files",
+    security(("bearer" = []))
+)]
+pub async fn get_file(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<StoredFile>> {
+    Ok(Json(user_file(&state, &id, &claims.sub).await?))
+}
+
+/// Download an uploaded file.
+///
+/// Always served as an attachment with its stored content type, so the
+/// browser never renders or sniffs it.
+#[utoipa::path(
+    get,
+    path = "/api/files/{id}/content",
+    params(
+        ("id" = String, Path, description = "File ID")
+    ),
+    responses(
+        (status = 200, description = "File content"),
+        (status = 404, description = "File not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "files",
+    security(("bearer" = []))
+)]
+pub async fn download_file(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<String>,
+) -> Result<Response> {
+    let file = user_file(&state, &id, &claims.sub).await?;
+    let bytes = files::read_content(&state.config_manager.config().files, &file).await?;
+    Response::builder()
+        .header(header::CONTENT_TYPE, &file.content_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"{}\"",
+                header_filename(&file.filename)
+            ),
+        )
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .body(Body::from(bytes))
+        .map_err(|e| AppError::Internal(format!("Failed to build response: {}", e)))
+}
+
+/// Delete an uploaded file.
+///
+/// Conversations the file was attached to keep their copy of its text.
+#[utoipa::path(
+    delete,
+    path = "/api/files/{id}",
+    params(
+        ("id" = String, Path, description = "File ID")
+    ),
+    responses(
+        (status = 204, description = "File deleted"),
+        (status = 404, description = "File not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "files",
+    security(("bearer" = []))
+)]
+pub async fn delete_file(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    let file = user_file(&state, &id, &claims.sub).await?;
+    files::delete_file(state.tenant_db.pool(), &file.id).await?;
+    discard_content(&state.config_manager.config().files, &file).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Move an uploaded file into a project, or out of any with a null
+/// `project_id`.
+#[utoipa::path(
+    put,
+    path = "/api/files/{id}/project",
+    params(
+        ("id" = String, Path, description = "File ID")
+    ),
+    request_body = ProjectAssignment,
+    responses(
+        (status = 200, description = "File moved", body = ProjectAssignment),
+        (status = 404, description = "File or project not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "files",
+    security(("bearer" = []))
+)]
+pub async fn update_file_project(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+    Json(payload): Json<ProjectAssignment>,
+) -> Result<Json<ProjectAssignment>> {
+    let file = user_file(&state, &id, &claims.sub).await?;
+    if let Some(project_id) = &payload.project_id {
+        load_project(&state, &workspace.owner(&claims.sub), project_id).await?;
+    }
+    projects::set_file_project(
+        state.tenant_db.pool(),
+        &file.id,
+        payload.project_id.as_deref(),
+    )
+    .await?;
+    Ok(Json(payload))
+}
+
+/// The text of one of a user's files, for chat and RAG ingestion.
+///
+/// The text is extracted with the file's loader (see
+/// [`crate::rag::loaders`]).
+pub(crate) async fn file_text(state: &AppState, id: &str, user_id: &str) -> Result<FileText> {
+    let file = user_file(state, id, user_id).await?;
+    let bytes = files::read_content(&state.config_manager.config().files, &file).await?;
+    let loaders = Arc::clone(&state.loaders);
+    let (filename, content_type) = (file.filename.clone(), file.content_type.clone());
+    let document =
+        tokio::task::spawn_blocking(move || loaders.load(&filename, Some(&content_type), &bytes))
+            .await
+            .map_err(|e| AppError::Internal(format!("File loader failed: {}", e)))??;
+    Ok(FileText {
+        file,
+        text: document.text,
+        title: document.title,
+    })
+}
+
+/// A stored file, its text and the title found in it
+pub(crate) struct FileText {
+    pub file: StoredFile,
+    pub text: String,
+    pub title: Option<String>,
+}
+
+/// Attach uploaded files to a conversation, skipping those already attached.
+///
+/// A file's attachment ID is derived from the conversation and the file, so
+/// referencing a file again in the same conversation doesn't attach it twice.
+pub(crate) async fn attach_files(
+    state: &AppState,
+    conversation_id: &str,
+    user_id: &str,
+    workspace: &ActiveWorkspace,
+    file_ids: &[String],
+) -> Result<Vec<Attachment>> {
+    let mut attached = Vec::new();
+    for file_id in file_ids {
+        let attachment_id = file_attachment_id(conversation_id, file_id);
+        let pool = state.tenant_db.pool();
+        if attachments::get_attachment(pool, &attachment_id, conversation_id)
+            .await?
+            .is_some()
+        {
+            continue;
+        }
+        let FileText { file, text, .. } = file_text(state, file_id, user_id).await?;
+        attached.push(
+            attach_text(
+                state,
+                conversation_id,
+                user_id,
+                workspace,
+                &attachment_id,
+                &file.filename,
+                &text,
+            )
+            .await?,
+        );
+    }
+    Ok(attached)
+}
+
+/// Attachment ID of a file in a conversation
+fn file_attachment_id(conversation_id: &str, file_id: &str) -> String {
+    let digest = Sha256::digest(format!("{}\n{}", conversation_id, file_id));
+    format!("file_{}", &hex::encode(digest)[..32])
+}
+
+/// Look up one of a user's files
+async fn user_file(state: &AppState, id: &str, user_id: &str) -> Result<StoredFile> {
+    files::get_file(state.tenant_db.pool(), id, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("File {} not found", id)))
+}
+
+/// Delete a file's content, logging failures
+async fn discard_content(config: &FilesConfig, file: &StoredFile) {
+    if let Err(e) = files::delete_content(config, file).await {
+        tracing::warn!("Failed to delete content of file {}: {}", file.id, e);
+    }
+}
+
+fn invalid_multipart(e: axum::extract::multipart::MultipartError) -> AppError {
+    AppError::InvalidInput(format!("Invalid multipart upload: {}", e))
+}
+
+/// Content type of a file name's extension, for parts that don't declare one
+fn guess_content_type(filename: &str) -> Option<&'static str> {
+    let (_, extension) = filename.rsplit_once('.')?;
+    Some(match extension.to_ascii_lowercase().as_str() {
+        "txt" | "log" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        "pdf" => "application/pdf",
+        "docx" => DOCX,
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        _ => return None,
+    })
+}
+
+/// Reject content types the `[files]` limits don't accept
+fn check_allowed(limits: &FilesConfig, content_type: &str) -> Result<()> {
+    if !limits.allowed_types.iter().any(|t| t == content_type) {
+        return Err(AppError::InvalidInput(format!(
+            "Files of type {} are not accepted",
+            content_type
+        )));
+    }
+    Ok(())
+}
+
+/// Reject executables, and content that doesn't match its declared type
+fn check_content(filename: &str, content_type: &str, bytes: &[u8]) -> Result<()> {
+    const EXECUTABLE_MAGIC: &[&[u8]] = &[
+        b"MZ",
+        b"\x7fELF",
+        b"#!",
+        b"\xfe\xed\xfa\xce",
+        b"\xfe\xed\xfa\xcf",
+        b"\xce\xfa\xed\xfe",
+        b"\xcf\xfa\xed\xfe",
+        b"\xca\xfe\xba\xbe",
+    ];
+    if EXECUTABLE_MAGIC
+        .iter()
+        .any(|magic| bytes.starts_with(magic))
+    {
+        return Err(AppError::InvalidInput(format!(
+            "{} looks like an executable",
+            filename
+        )));
+    }
+
+    let matches = match content_type {
+        "application/pdf" => bytes.starts_with(b"%PDF-"),
+        DOCX => DocxLoader.sniff(bytes),
+        "image/png" => bytes.starts_with(b"\x89PNG\r\n\x1a\n"),
+        "image/jpeg" => bytes.starts_with(b"\xff\xd8\xff"),
+        "application/json" => serde_json::from_slice::<serde_json::Value>(bytes).is_ok(),
+        t if t.starts_with("text/") => object_text(bytes.to_vec()).is_some(),
+        _ => true,
+    };
+    if !matches {
+        return Err(AppError::InvalidInput(format!(
+            "The content of {} is not {}",
+            filename, content_type
+        )));
+    }
+    Ok(())
+}
+
+/// Pipe an upload to the configured virus scanner, rejecting it unless the
+/// scanner exits with status 0
+async fn scan(config: &FilesConfig, filename: &str, bytes: &[u8]) -> Result<()> {
+    let Some(command) = &config.scan_command else {
+        return Ok(());
+    };
+    let scanner_err = |e: std::io::Error| {
+        AppError::External(format!("Failed to run virus scanner '{}': {}", command, e))
+    };
+    let mut child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(scanner_err)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A scanner may exit as soon as it has seen enough, closing stdin
+        if let Err(e) = stdin.write_all(bytes).await {
+            tracing::debug!("Virus scanner stopped reading {}: {}", filename, e);
+        }
+    }
+    let output = child.wait_with_output().await.map_err(scanner_err)?;
+    if !output.status.success() {
+        tracing::warn!(
+            filename = %filename,
+            status = %output.status,
+            output = %String::from_utf8_lossy(&output.stdout).trim(),
+            "Upload rejected by virus scanner"
+        );
+        return Err(AppError::InvalidInput(format!(
+            "{} was rejected by the virus scanner",
+            filename
+        )));
+    }
+    Ok(())
+}
+
+/// A file name safe to quote in a `Content-Disposition` header
+fn header_filename(filename: &str) -> String {
+    filename
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c.is_ascii_graphic() || c == ' ' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_content() {
+        assert!(check_content("a.md", "text/markdown", b"# Notes").is_ok());
+        assert!(check_content("a.pdf", "application/pdf", b"%PDF-1.7\n").is_ok());
+        assert!(check_content("a.png", "image/png", b"\x89PNG\r\n\x1a\n....").is_ok());
+        assert!(check_content("a.json", "application/json", br#"{"a": 1}"#).is_ok());
+
+        // Executables are rejected whatever they claim to be
+        assert!(check_content("a.txt", "text/plain", b"MZ\x90\x00").is_err());
+        assert!(check_content("run.txt", "text/plain", b"#!/bin/sh\nrm -rf /").is_err());
+        assert!(check_content("a.pdf", "application/pdf", b"\x7fELF\x02").is_err());
+        // Content must match the declared type
+        assert!(check_content("a.pdf", "application/pdf", b"hello").is_err());
+        assert!(check_content("a.txt", "text/plain", b"a\0b").is_err());
+        assert!(check_content("a.json", "application/json", b"{").is_err());
+        assert!(check_content("a.docx", DOCX, b"PK\x03\x04not a document").is_err());
+    }
+
+    #[test]
+    fn test_guess_content_type_and_header_filename() {
+        assert_eq!(guess_content_type("Notes.MD"), Some("text/markdown"));
+        assert_eq!(guess_content_type("Q3.docx"), Some(DOCX));
+        assert_eq!(guess_content_type("setup.exe"), None);
+        assert_eq!(guess_content_type("README"), None);
+        assert_eq!(header_filename("q3 \"final\"\r\n.pdf"), "q3 _final___.pdf");
+    }
+
+    #[tokio::test]
+    async fn test_scan_command_exit_status() {
+        let mut config = FilesConfig::default();
+        assert!(scan(&config, "a.txt", b"hello").await.is_ok());
+
+        config.scan_command = Some("grep -q EICAR && exit 1 || exit 0".to_string());
+        assert!(scan(&config, "a.txt", b"hello").await.is_ok());
+        assert!(scan(&config, "a.txt", b"xx EICAR xx").await.is_err());
+    }
+}
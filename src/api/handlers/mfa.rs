@@ -0,0 +1,439 @@
+//! Multi-factor authentication with authenticator apps (TOTP).
+//!
+//! A signed-in user turns MFA on by calling `POST /api/auth/mfa/enroll`,
+//! adding the returned secret to an authenticator app (the `otpauth_uri` is
+//! the QR code payload), and confirming a first code with
+//! `POST /api/auth/mfa/confirm`. Confirmation returns ten single-use
+//! recovery codes, shown only then.
+//!
+//! From then on, `POST /api/auth/login` answers a correct password with a
+//! challenge instead of tokens:
+//!
+//! ```json
+//! {"mfa_token": "...", "mfa": "verify", "expires_in": 300}
+//! ```
+//!
+//! and `POST /api/auth/mfa/verify` with the `mfa_token` and a code from the
+//! app, or a recovery code, completes the login. A challenge allows five
+//! wrong codes before the password must be entered again.
+//!
+//! When MFA is required (`[auth.mfa] required`, or by an admin for a single
+//! user) and the user hasn't set it up, the challenge is `"mfa": "enroll"`:
+//! the user enrolls and confirms with the `mfa_token` in place of a JWT,
+//! and confirming signs them in. Required MFA can't be turned off by its
+//! user.
+
+use crate::{
+    api::handlers::auth::start_session,
+    auth::{middleware::AuthUser, totp},
+    db::{
+        mfa::{self, UserMfa},
+        sessions::SessionClient,
+    },
+    types::{AppError, Result, TokenResponse},
+    AppState,
+};
+use axum::{
+    extract::State,
+    http::{header, HeaderMap},
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// What a login challenge needs next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MfaStep {
+    /// A code from the user's authenticator app, at `/api/auth/mfa/verify`
+    Verify,
+    /// Setting up MFA, which is required, at `/api/auth/mfa/enroll`
+    Enroll,
+}
+
+/// A login waiting for its second factor.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MfaChallenge {
+    /// Token identifying the login in the next step
+    pub mfa_token: String,
+    /// What the login needs next
+    pub mfa: MfaStep,
+    /// Seconds until the token expires
+    pub expires_in: i64,
+}
+
+/// A user's MFA status.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MfaStatus {
+    /// Whether sign-ins need a code
+    pub enabled: bool,
+    /// Whether the user must use MFA
+    pub required: bool,
+    /// Unused recovery codes
+    pub recovery_codes_left: i64,
+}
+
+/// Request acting for a login's challenge or, without `mfa_token`, for the
+/// user of the bearer JWT.
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct EnrollRequest {
+    /// Token of a login challenged to enroll
+    #[serde(default)]
+    pub mfa_token: Option<String>,
+}
+
+/// A new TOTP secret, to add to an authenticator app.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MfaEnrollment {
+    /// Secret, base32-encoded, for entering by hand
+    pub secret: String,
+    /// `otpauth://` URI to show as a QR code
+    pub otpauth_uri: String,
+}
+
+/// Request confirming enrollment with a first code.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ConfirmRequest {
+    /// Token of a login challenged to enroll; omit when signed in
+    #[serde(default)]
+    pub mfa_token: Option<String>,
+    /// Code from the authenticator app
+    pub code: String,
+}
+
+/// MFA turned on.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MfaConfirmation {
+    /// Single-use recovery codes; they can't be shown again
+    pub recovery_codes: Vec<String>,
+    /// Tokens of the completed login, when confirmed with an `mfa_token`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<TokenResponse>,
+}
+
+/// Second step of a login.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct VerifyRequest {
+    /// Token returned by the login
+    pub mfa_token: String,
+    /// Code from the authenticator app, or a recovery code
+    pub code: String,
+}
+
+/// Request proving the user holds their second factor.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CodeRequest {
+    /// Code from the authenticator app, or a recovery code
+    pub code: String,
+}
+
+/// New recovery codes, replacing the previous ones.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecoveryCodes {
+    /// Single-use recovery codes; they can't be shown again
+    pub recovery_codes: Vec<String>,
+}
+
+/// Whether MFA is required of a user
+fn required(state: &AppState, mfa: &UserMfa) -> bool {
+    mfa.required || state.config_manager.config().auth.mfa.required
+}
+
+/// The challenge a login with the right password must pass, if any.
+pub(crate) async fn login_challenge(
+    state: &AppState,
+    user_id: &str,
+) -> Result<Option<MfaChallenge>> {
+    let pool = state.tenant_db.pool();
+    let user_mfa = mfa::get(pool, user_id).await?;
+    let step = if user_mfa.enabled {
+        MfaStep::Verify
+    } else if required(state, &user_mfa) {
+        MfaStep::Enroll
+    } else {
+        return Ok(None);
+    };
+    let ttl = state.config_manager.config().auth.mfa.challenge_ttl_secs;
+    let mfa_token = mfa::create_challenge(pool, user_id, Utc::now().timestamp(), ttl).await?;
+    Ok(Some(MfaChallenge {
+        mfa_token,
+        mfa: step,
+        expires_in: ttl,
+    }))
+}
+
+/// The user a request acts for: the holder of a login's `mfa_token`, or
+/// else the user of the bearer JWT. Attempts at a challenge are counted
+/// when `attempt` is set.
+async fn acting_user(
+    state: &AppState,
+    headers: &HeaderMap,
+    mfa_token: Option<&str>,
+    attempt: bool,
+) -> Result<String> {
+    if let Some(token) = mfa_token {
+        let now = Utc::now().timestamp();
+        return mfa::challenge_user(state.tenant_db.pool(), token, now, attempt)
+            .await?
+            .ok_or_else(|| AppError::Auth("MFA token is invalid or expired".to_string()));
+    }
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| AppError::Auth("Unauthorized".to_string()))?;
+    Ok(state.auth_service.verify_token(token)?.sub)
+}
+
+/// Check a code from the authenticator app, or a recovery code, using it up
+async fn check_code(state: &AppState, user_id: &str, user_mfa: &UserMfa, code: &str) -> Result<()> {
+    let pool = state.tenant_db.pool();
+    let now = Utc::now().timestamp();
+    let valid = if totp::is_totp_code(code) {
+        let step = user_mfa
+            .secret
+            .as_deref()
+            .and_then(|secret| totp::verify(secret, code, now, user_mfa.last_step));
+        match step {
+            Some(step) => mfa::use_step(pool, user_id, step).await?,
+            None => false,
+        }
+    } else {
+        mfa::use_recovery_code(pool, user_id, code, now).await?
+    };
+    match valid {
+        true => Ok(()),
+        false => Err(AppError::Auth("Invalid MFA code".to_string())),
+    }
+}
+
+/// The user's MFA settings, which must be on
+async fn enabled_mfa(state: &AppState, user_id: &str) -> Result<UserMfa> {
+    let user_mfa = mfa::get(state.tenant_db.pool(), user_id).await?;
+    if !user_mfa.enabled {
+        return Err(AppError::InvalidInput("MFA is not enabled".to_string()));
+    }
+    Ok(user_mfa)
+}
+
+/// Get the user's MFA status
+#[utoipa::path(
+    get,
+    path = "/api/auth/mfa",
+    responses(
+        (status = 200, description = "MFA status", body = MfaStatus),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "auth",
+    security(("bearer" = []))
+)]
+pub async fn get_status(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<MfaStatus>> {
+    let pool = state.tenant_db.pool();
+    let user_mfa = mfa::get(pool, &claims.sub).await?;
+    Ok(Json(MfaStatus {
+        enabled: user_mfa.enabled,
+        required: required(&state, &user_mfa),
+        recovery_codes_left: mfa::count_recovery_codes(pool, &claims.sub).await?,
+    }))
+}
+
+/// Start setting up MFA with a new secret
+///
+/// Acts for the bearer JWT's user, or for a login challenged to enroll when
+/// `mfa_token` is given. The secret takes effect once confirmed.
+#[utoipa::path(
+    post,
+    path = "/api/auth/mfa/enroll",
+    request_body = EnrollRequest,
+    responses(
+        (status = 200, description = "New secret", body = MfaEnrollment),
+        (status = 400, description = "MFA is already enabled"),
+        (status = 401, description = "Unauthorized, or invalid MFA token")
+    ),
+    tag = "auth"
+)]
+pub async fn enroll(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Option<Json<EnrollRequest>>,
+) -> Result<Json<MfaEnrollment>> {
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let user_id = acting_user(&state, &headers, payload.mfa_token.as_deref(), false).await?;
+    let pool = state.tenant_db.pool();
+    if mfa::get(pool, &user_id).await?.enabled {
+        return Err(AppError::InvalidInput("MFA is already enabled".to_string()));
+    }
+    let user = state
+        .db
+        .get_user_by_id(&user_id)
+        .await?
+        .ok_or_else(|| AppError::Auth("User not found".to_string()))?;
+
+    let secret = totp::generate_secret();
+    mfa::set_pending_secret(pool, &user_id, &secret, Utc::now().timestamp()).await?;
+    let issuer = state.config_manager.config().auth.mfa.issuer.clone();
+    Ok(Json(MfaEnrollment {
+        otpauth_uri: totp::otpauth_uri(&issuer, &user.email, &secret),
+        secret,
+    }))
+}
+
+/// Turn MFA on by confirming a first code
+///
+/// With an `mfa_token`, this also completes the login and returns its
+/// tokens.
+#[utoipa::path(
+    post,
+    path = "/api/auth/mfa/confirm",
+    request_body = ConfirmRequest,
+    responses(
+        (status = 200, description = "MFA enabled", body = MfaConfirmation),
+        (status = 400, description = "No enrollment started, or MFA already enabled"),
+        (status = 401, description = "Unauthorized, or invalid code or MFA token")
+    ),
+    tag = "auth"
+)]
+pub async fn confirm(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    client: SessionClient,
+    Json(payload): Json<ConfirmRequest>,
+) -> Result<(HeaderMap, Json<MfaConfirmation>)> {
+    let mfa_token = payload.mfa_token.as_deref();
+    let user_id = acting_user(&state, &headers, mfa_token, true).await?;
+    let pool = state.tenant_db.pool();
+    let user_mfa = mfa::get(pool, &user_id).await?;
+    if user_mfa.enabled {
+        return Err(AppError::InvalidInput("MFA is already enabled".to_string()));
+    }
+    let secret = user_mfa
+        .secret
+        .ok_or_else(|| AppError::InvalidInput("Start enrollment first".to_string()))?;
+    let now = Utc::now().timestamp();
+    let step = totp::verify(&secret, &payload.code, now, 0)
+        .ok_or_else(|| AppError::Auth("Invalid MFA code".to_string()))?;
+
+    let recovery_codes = totp::generate_recovery_codes();
+    mfa::enable(pool, &user_id, step, &recovery_codes, now).await?;
+
+    let Some(token) = mfa_token else {
+        return Ok((
+            HeaderMap::new(),
+            Json(MfaConfirmation {
+                recovery_codes,
+                tokens: None,
+            }),
+        ));
+    };
+    mfa::delete_challenge(pool, token).await?;
+    let (headers, Json(tokens)) = sign_in(&state, &user_id, &client).await?;
+    Ok((
+        headers,
+        Json(MfaConfirmation {
+            recovery_codes,
+            tokens: Some(tokens),
+        }),
+    ))
+}
+
+/// Complete a login with a code from the authenticator app or a recovery
+/// code
+#[utoipa::path(
+    post,
+    path = "/api/auth/mfa/verify",
+    request_body = VerifyRequest,
+    responses(
+        (status = 200, description = "Login successful", body = TokenResponse),
+        (status = 400, description = "MFA is not enabled"),
+        (status = 401, description = "Invalid code or MFA token")
+    ),
+    tag = "auth"
+)]
+pub async fn verify(
+    State(state): State<AppState>,
+    client: SessionClient,
+    Json(payload): Json<VerifyRequest>,
+) -> Result<(HeaderMap, Json<TokenResponse>)> {
+    let user_id = acting_user(&state, &HeaderMap::new(), Some(&payload.mfa_token), true).await?;
+    let user_mfa = enabled_mfa(&state, &user_id).await?;
+    check_code(&state, &user_id, &user_mfa, &payload.code).await?;
+    mfa::delete_challenge(state.tenant_db.pool(), &payload.mfa_token).await?;
+    sign_in(&state, &user_id, &client).await
+}
+
+/// Start a session for a user who passed their challenge
+async fn sign_in(
+    state: &AppState,
+    user_id: &str,
+    client: &SessionClient,
+) -> Result<(HeaderMap, Json<TokenResponse>)> {
+    let user = state
+        .db
+        .get_user_by_id(user_id)
+        .await?
+        .ok_or_else(|| AppError::Auth("User not found".to_string()))?;
+    start_session(state, &user.id, &user.email, client).await
+}
+
+/// Turn MFA off
+#[utoipa::path(
+    post,
+    path = "/api/auth/mfa/disable",
+    request_body = CodeRequest,
+    responses(
+        (status = 200, description = "MFA disabled", body = MfaStatus),
+        (status = 400, description = "MFA is not enabled"),
+        (status = 401, description = "Unauthorized, or invalid code"),
+        (status = 403, description = "MFA is required")
+    ),
+    tag = "auth",
+    security(("bearer" = []))
+)]
+pub async fn disable(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Json(payload): Json<CodeRequest>,
+) -> Result<Json<MfaStatus>> {
+    let user_mfa = enabled_mfa(&state, &claims.sub).await?;
+    if required(&state, &user_mfa) {
+        return Err(AppError::Forbidden(
+            "MFA is required and can't be turned off".to_string(),
+        ));
+    }
+    check_code(&state, &claims.sub, &user_mfa, &payload.code).await?;
+    mfa::disable(state.tenant_db.pool(), &claims.sub, Utc::now().timestamp()).await?;
+    Ok(Json(MfaStatus {
+        enabled: false,
+        required: false,
+        recovery_codes_left: 0,
+    }))
+}
+
+/// Replace the recovery codes with new ones
+#[utoipa::path(
+    post,
+    path = "/api/auth/mfa/recovery-codes",
+    request_body = CodeRequest,
+    responses(
+        (status = 200, description = "New recovery codes", body = RecoveryCodes),
+        (status = 400, description = "MFA is not enabled"),
+        (status = 401, description = "Unauthorized, or invalid code")
+    ),
+    tag = "auth",
+    security(("bearer" = []))
+)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Json(payload): Json<CodeRequest>,
+) -> Result<Json<RecoveryCodes>> {
+    let user_mfa = enabled_mfa(&state, &claims.sub).await?;
+    check_code(&state, &claims.sub, &user_mfa, &payload.code).await?;
+    let recovery_codes = totp::generate_recovery_codes();
+    mfa::replace_recovery_codes(state.tenant_db.pool(), &claims.sub, &recovery_codes).await?;
+    Ok(Json(RecoveryCodes { recovery_codes }))
+}
@@ -4,21 +4,49 @@
 
 /// Agent listing and info handlers.
 pub mod agents;
+/// AG-UI protocol endpoint for agent frontends.
+pub mod agui;
 /// Admin tenant management handlers.
 pub mod admin;
+/// User API key handlers.
+pub mod api_keys;
+/// Tool call approval handlers.
+pub mod approvals;
 /// Authentication handlers (login, register).
 pub mod auth;
+/// Batch chat handlers.
+pub mod batches;
 /// Chat and streaming handlers.
 pub mod chat;
 /// Conversation CRUD handlers.
 pub mod conversations;
+/// File upload handlers.
+pub mod files;
+/// Multi-factor authentication handlers.
+pub mod mfa;
+/// User chat preference handlers.
+pub mod preferences;
+/// Project handlers.
+pub mod projects;
+/// Scheduled agent run handlers.
+pub mod schedules;
+/// Signed-in device (session) handlers.
+pub mod sessions;
+/// Conversation share link handlers.
+pub mod shares;
 /// RAG (document ingestion/search) handlers.
-/// Requires the `local-embeddings` feature (for ONNX-based embeddings) and
-/// `ares-vector` feature (for the embedded vector database).
-#[cfg(all(feature = "local-embeddings", feature = "ares-vector"))]
+/// Requires the `ares-vector` feature (for the embedded vector database), and either
+/// the `local-embeddings` feature or a remote `[rag] embedding_provider`.
+#[cfg(feature = "ares-vector")]
 pub mod rag;
 /// Research coordination handlers.
 pub mod research;
+/// Interrupted run handlers.
+pub mod runs;
+/// Assistants-style thread and run handlers.
+pub mod threads;
+/// Spend and budget usage handlers.
+pub mod usage;
 /// User-created agent management handlers.
 pub mod user_agents;
 /// V1 API key-authenticated tenant-scoped handlers.
@@ -27,3 +55,5 @@ pub mod v1;
 pub mod deploy;
 /// Workflow execution handlers.
 pub mod workflows;
+/// Workspace and membership handlers.
+pub mod workspaces;
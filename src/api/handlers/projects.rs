@@ -0,0 +1,227 @@
+//! Project handlers.
+//!
+//! A project groups a space's conversations, uploaded files and RAG
+//! collections, with default agent settings for its conversations (see
+//! [`crate::db::projects`]). Conversations are started in a project with
+//! `project_id` in the chat request, or moved with
+//! `PUT /api/conversations/{id}/project`; files are uploaded into one with
+//! `POST /api/files?project_id=...`, or moved with
+//! `PUT /api/files/{id}/project`. In a workspace, projects are shared by
+//! its members.
+
+use crate::{
+    api::{
+        handlers::conversations::{check_collections, check_overrides},
+        workspace::ActiveWorkspace,
+    },
+    auth::middleware::AuthUser,
+    db::projects::{self, Project},
+    rag::scope as rag_scope,
+    types::{AppError, ConversationOverrides, RagScope, Result},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Longest accepted project name
+const MAX_PROJECT_NAME_LEN: usize = 100;
+
+/// Request to create or replace a project.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ProjectRequest {
+    /// Display name
+    pub name: String,
+    /// What the project is about
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Agent, model, temperature and persona for the project's
+    /// conversations, where a conversation doesn't pin its own
+    #[serde(default)]
+    pub defaults: ConversationOverrides,
+    /// RAG collections to answer the project's conversations from
+    #[serde(default)]
+    pub collections: Vec<String>,
+}
+
+/// Load one of `owner`'s projects.
+pub(crate) async fn load_project(state: &AppState, owner: &str, id: &str) -> Result<Project> {
+    projects::get_project(state.tenant_db.pool(), id, owner)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Project {} not found", id)))
+}
+
+/// Check a project request and apply it to `project`.
+async fn apply(state: &AppState, project: &mut Project, payload: ProjectRequest) -> Result<()> {
+    let name = payload.name.trim();
+    if name.is_empty() || name.len() > MAX_PROJECT_NAME_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "Project name must be 1-{} characters",
+            MAX_PROJECT_NAME_LEN
+        )));
+    }
+    check_overrides(state, &project.owner_id, &payload.defaults).await?;
+    let collections = rag_scope::normalize(RagScope {
+        collections: payload.collections,
+        tags: Vec::new(),
+    })
+    .collections;
+    if !collections.is_empty() {
+        check_collections(state, &project.owner_id, &collections).await?;
+    }
+
+    project.name = name.to_string();
+    project.description = payload
+        .description
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty());
+    project.defaults = payload.defaults;
+    project.collections = collections;
+    project.updated_at = Utc::now().timestamp();
+    Ok(())
+}
+
+/// List the projects of the space the user acts in.
+#[utoipa::path(
+    get,
+    path = "/api/projects",
+    responses(
+        (status = 200, description = "Projects, by name", body = Vec<Project>),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "projects",
+    security(("bearer" = []))
+)]
+pub async fn list_projects(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+) -> Result<Json<Vec<Project>>> {
+    Ok(Json(
+        projects::list_projects(state.tenant_db.pool(), &workspace.owner(&claims.sub)).await?,
+    ))
+}
+
+/// Create a project.
+#[utoipa::path(
+    post,
+    path = "/api/projects",
+    request_body = ProjectRequest,
+    responses(
+        (status = 201, description = "Project created", body = Project),
+        (status = 400, description = "Invalid name, unknown model, agent or persona, or too many collections"),
+        (status = 404, description = "Collection not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "projects",
+    security(("bearer" = []))
+)]
+pub async fn create_project(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Json(payload): Json<ProjectRequest>,
+) -> Result<(StatusCode, Json<Project>)> {
+    let now = Utc::now().timestamp();
+    let mut project = Project {
+        id: Uuid::new_v4().to_string(),
+        owner_id: workspace.owner(&claims.sub),
+        name: String::new(),
+        description: None,
+        defaults: ConversationOverrides::default(),
+        collections: Vec::new(),
+        created_by: claims.sub,
+        created_at: now,
+        updated_at: now,
+    };
+    apply(&state, &mut project, payload).await?;
+    projects::insert_project(state.tenant_db.pool(), &project).await?;
+
+    Ok((StatusCode::CREATED, Json(project)))
+}
+
+/// Get a project.
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}",
+    params(("id" = String, Path, description = "Project ID")),
+    responses(
+        (status = 200, description = "Project", body = Project),
+        (status = 404, description = "Project not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "projects",
+    security(("bearer" = []))
+)]
+pub async fn get_project(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+) -> Result<Json<Project>> {
+    Ok(Json(
+        load_project(&state, &workspace.owner(&claims.sub), &id).await?,
+    ))
+}
+
+/// Replace a project's name, description, defaults and collections.
+#[utoipa::path(
+    put,
+    path = "/api/projects/{id}",
+    params(("id" = String, Path, description = "Project ID")),
+    request_body = ProjectRequest,
+    responses(
+        (status = 200, description = "Project updated", body = Project),
+        (status = 400, description = "Invalid name, unknown model, agent or persona, or too many collections"),
+        (status = 404, description = "Project or collection not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "projects",
+    security(("bearer" = []))
+)]
+pub async fn update_project(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+    Json(payload): Json<ProjectRequest>,
+) -> Result<Json<Project>> {
+    let mut project = load_project(&state, &workspace.owner(&claims.sub), &id).await?;
+    apply(&state, &mut project, payload).await?;
+    projects::update_project(state.tenant_db.pool(), &project).await?;
+    Ok(Json(project))
+}
+
+/// Delete a project.
+///
+/// Its conversations and files are kept, outside any project.
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{id}",
+    params(("id" = String, Path, description = "Project ID")),
+    responses(
+        (status = 204, description = "Project deleted"),
+        (status = 404, description = "Project not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "projects",
+    security(("bearer" = []))
+)]
+pub async fn delete_project(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    let owner = workspace.owner(&claims.sub);
+    if !projects::delete_project(state.tenant_db.pool(), &id, &owner).await? {
+        return Err(AppError::NotFound(format!("Project {} not found", id)));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -2,30 +2,80 @@
 //!
 //! Provides endpoints for:
 //! - Document ingestion with chunking
+//! - Background ingestion jobs over S3/GCS bucket prefixes and Notion/Confluence
+//!   workspaces, optionally re-synced on a schedule
 //! - Multi-strategy search (semantic, BM25, fuzzy, hybrid)
-//! - Collection management
-
+//! - Collection management and per-collection settings
+//!
+//! Embeddings come from the remote `[rag] embedding_provider` when one is
+//! configured, otherwise from the local model (`local-embeddings` feature).
+//!
+//! Each collection stores its own chunking, embedding model and search
+//! defaults (see [`CollectionSettings`]). The embedding model is recorded on
+//! first ingest; later ingests, searches and settings updates are checked
+//! against it so vectors from different models never share a collection.
+//!
+//! The vector store rejects vectors whose dimensions differ from their
+//! collection's, which happens when the embedding model or provider changes
+//! under an existing collection. Such collections are reported at startup,
+//! and `POST /api/rag/collections/{collection}/reembed` migrates them to the
+//! new model.
+
+#[cfg(feature = "local-embeddings")]
+use crate::rag::embeddings::{EmbeddingModelType, EmbeddingService};
 use crate::{
+    agents::knowledge::KnowledgeBase,
+    api::{
+        handlers::files::{file_text, FileText},
+        workspace::ActiveWorkspace,
+    },
     auth::middleware::AuthUser,
-    db::{AresVectorStore, VectorStore},
+    db::{jobs, locks, VectorStore, VectorStoreProvider},
+    llm::cancellation::CancellationToken,
     rag::{
+        answer_cache::AnswerCache,
+        attachments::{ConversationAttachments, Passage},
+        batcher::{BatchConfig, EmbeddingBatcher},
         chunker::{ChunkingStrategy, TextChunker},
-        embeddings::{EmbeddingModelType, EmbeddingService},
-        reranker::{Reranker, RerankerConfig, RerankerModelType},
-        search::{HybridWeights, SearchEngine, SearchStrategy},
+        connectors::{create_source, KeyFilter, SourceDocument, SourceLocation, SourceScheme},
+        feedback::{self, FeedbackStore},
+        ingest_jobs::{
+            run_job, IngestJob, IngestJobStatus, IngestJobStore, IngestSink, IngestedObject,
+        },
+        intent,
+        parents::{self, ParentContext, ParentStore},
+        pipeline::{self, Retriever},
+        remote_embeddings::RemoteEmbedder,
+        reranker::{create_reranker, Reranker, RerankerKind},
+        scope as rag_scope,
+        search::{HybridWeights, SearchRequest, SearchStrategy},
     },
     types::{
-        AppError, Document, DocumentMetadata, RagDeleteCollectionRequest,
-        RagDeleteCollectionResponse, RagIngestRequest, RagIngestResponse, RagSearchRequest,
+        AppError, CollectionSettings, Document, DocumentMetadata, RagChunkFeedbackRequest,
+        RagChunkFeedbackResponse, RagCollectionSettingsResponse, RagDeleteCollectionRequest,
+        RagDeleteCollectionResponse, RagFeedbackReportResponse, RagFlaggedChunk,
+        RagGitHubWebhookResponse, RagIngestJobRequest, RagIngestJobResponse, RagIngestRequest,
+        RagIngestResponse, RagReembedRequest, RagReembedResponse, RagScope, RagSearchRequest,
         RagSearchResponse, RagSearchResult, Result,
     },
-    AppState,
+    utils::toml_config::{AresConfig, RagConfig},
+    AppState, AresConfigManager,
+};
+use async_trait::async_trait;
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
 };
-use axum::{extract::State, Json};
 use chrono::Utc;
-use std::sync::Arc;
-use std::time::Instant;
-use tokio::sync::OnceCell;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OnceCell};
 use uuid::Uuid;
 
 // ============================================================================
@@ -49,44 +99,397 @@ fn extract_user_collection(user_id: &str, scoped_name: &str) -> Option<String> {
 // Shared RAG Services
 // ============================================================================
 
-/// Global embedding service (lazy initialized).
-static EMBEDDING_SERVICE: OnceCell<Arc<EmbeddingService>> = OnceCell::const_new();
+/// Embedding batchers keyed by provider and model (lazy initialized).
+/// Coalesces concurrent ingest and query embeddings into batched model calls.
+static EMBEDDING_BATCHERS: LazyLock<Mutex<HashMap<String, EmbeddingBatcher>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 
-/// Get or create the embedding service.
-async fn get_embedding_service() -> Result<Arc<EmbeddingService>> {
-    EMBEDDING_SERVICE
-        .get_or_try_init(|| async {
-            let service = EmbeddingService::with_model(EmbeddingModelType::default())
-                .map_err(|e| AppError::Internal(format!("Failed to init embeddings: {}", e)))?;
-            Ok::<_, AppError>(Arc::new(service))
-        })
-        .await
-        .cloned()
+/// Output dimensions of embedding models, keyed like [`EMBEDDING_BATCHERS`].
+static EMBEDDING_DIMENSIONS: LazyLock<Mutex<HashMap<String, usize>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Text embedded to find out a model's output dimensions
+const DIMENSION_PROBE: &str = "dimension probe";
+
+/// Key of an embedding model under the configured provider.
+fn embedder_key(config: &AresConfig, model: &str) -> String {
+    let provider = config.rag.embedding_provider.as_deref();
+    format!("{}:{}", provider.unwrap_or("local"), model)
+}
+
+/// Get or create the embedding batcher for a model, using the configured
+/// provider and batch settings.
+async fn get_embedding_batcher(config: &AresConfig, model: &str) -> Result<EmbeddingBatcher> {
+    let provider = config.rag.embedding_provider.as_deref();
+    let key = embedder_key(config, model);
+
+    let mut batchers = EMBEDDING_BATCHERS.lock().await;
+    if let Some(batcher) = batchers.get(&key) {
+        return Ok(batcher.clone());
+    }
+
+    let batch_config = BatchConfig::from_rag_config(&config.rag);
+    let batcher = match provider {
+        Some(name) => {
+            let provider = config.get_provider(name).ok_or_else(|| {
+                AppError::Configuration(format!(
+                    "RAG embedding provider '{}' is not defined in [providers]",
+                    name
+                ))
+            })?;
+            let embedder = RemoteEmbedder::from_provider(provider, model)?;
+            EmbeddingBatcher::new(Arc::new(embedder), batch_config)
+        }
+        None => local_embedding_batcher(model, batch_config)?,
+    };
+    batchers.insert(key, batcher.clone());
+    Ok(batcher)
+}
+
+/// Output dimensions of an embedding model, found by embedding a probe
+/// text once per provider and model.
+async fn embedding_dimensions(config: &AresConfig, model: &str) -> Result<usize> {
+    let key = embedder_key(config, model);
+    if let Some(dimensions) = EMBEDDING_DIMENSIONS.lock().await.get(&key) {
+        return Ok(*dimensions);
+    }
+    let batcher = get_embedding_batcher(config, model).await?;
+    let dimensions = batcher.embed(DIMENSION_PROBE).await?.len();
+    EMBEDDING_DIMENSIONS.lock().await.insert(key, dimensions);
+    Ok(dimensions)
+}
+
+/// Create a batcher over a local embedding model.
+#[cfg(feature = "local-embeddings")]
+fn local_embedding_batcher(model: &str, config: BatchConfig) -> Result<EmbeddingBatcher> {
+    let model: EmbeddingModelType = model.parse()?;
+    let service = EmbeddingService::with_model(model)
+        .map_err(|e| AppError::Internal(format!("Failed to init embeddings: {}", e)))?;
+    Ok(EmbeddingBatcher::new(Arc::new(service), config))
+}
+
+/// Without local embeddings a remote provider must be configured.
+#[cfg(not(feature = "local-embeddings"))]
+fn local_embedding_batcher(_model: &str, _config: BatchConfig) -> Result<EmbeddingBatcher> {
+    Err(AppError::Configuration(
+        "No embedding provider: set [rag] embedding_provider or build with the \
+         `local-embeddings` feature"
+            .to_string(),
+    ))
 }
 
 /// Global vector store (lazy initialized).
-/// Uses a Mutex to allow late initialization with config-driven path.
-static VECTOR_STORE: OnceCell<Arc<AresVectorStore>> = OnceCell::const_new();
+/// Uses a OnceCell to allow late initialization with the configured backend.
+static VECTOR_STORE: OnceCell<Arc<dyn VectorStore>> = OnceCell::const_new();
 
-/// Get or create the vector store with the configured path.
-/// The path is read from config on first initialization.
-async fn get_vector_store(vector_path: &str) -> Result<Arc<AresVectorStore>> {
+/// Get or create the vector store selected by `[rag] vector_store`.
+/// The backend is read from config on first initialization.
+async fn get_vector_store(config: &RagConfig) -> Result<Arc<dyn VectorStore>> {
     VECTOR_STORE
         .get_or_try_init(|| async {
-            let store = AresVectorStore::new(Some(vector_path.to_string())).await?;
-            Ok::<_, AppError>(Arc::new(store))
+            let store = VectorStoreProvider::from_rag_config(config)?
+                .create_store()
+                .await?;
+            Ok::<_, AppError>(Arc::from(store))
         })
         .await
         .cloned()
 }
 
+/// Write the shared vector store to disk, if it was opened.
+pub(crate) async fn persist_vector_store() -> Result<()> {
+    match VECTOR_STORE.get() {
+        Some(store) => store.persist().await,
+        None => Ok(()),
+    }
+}
+
+/// Number of collections in the shared vector store, opening it if needed.
+pub(crate) async fn vector_store_collections(config: &AresConfig) -> Result<usize> {
+    let store = get_vector_store(&config.rag).await?;
+    Ok(store.list_collections().await?.len())
+}
+
+/// Answer cache over the shared vector store, embedding questions with the
+/// default RAG embedding model.
+pub(crate) async fn answer_cache(config: &AresConfig) -> Result<AnswerCache> {
+    let store = get_vector_store(&config.rag).await?;
+    let embedder = get_embedding_batcher(config, &config.rag.embedding_model).await?;
+    Ok(AnswerCache::new(
+        store,
+        embedder,
+        config.rag.embedding_model.clone(),
+    ))
+}
+
+/// Attached files of conversations, stored in the shared vector store and
+/// embedded with the default RAG embedding model.
+pub(crate) async fn conversation_attachments(
+    config: &AresConfig,
+) -> Result<ConversationAttachments> {
+    let store = get_vector_store(&config.rag).await?;
+    let embedder = get_embedding_batcher(config, &config.rag.embedding_model).await?;
+    Ok(ConversationAttachments::new(store, embedder))
+}
+
+/// Passages of a conversation's scoped collections most relevant to
+/// `query`, best first.
+///
+/// Each collection is searched with its own embedding model and threshold;
+/// collections the user no longer has are skipped.
+pub(crate) async fn scoped_passages(
+    config: &AresConfig,
+    user_id: &str,
+    scope: &RagScope,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<Passage>> {
+    let store = get_vector_store(&config.rag).await?;
+    // Fetch extra when the tag filter drops some
+    let candidates = match scope.tags.is_empty() {
+        true => limit,
+        false => limit * 4,
+    };
+    let mut passages = Vec::new();
+    for collection in &scope.collections {
+        let scoped_collection = user_scoped_collection(user_id, collection);
+        if !store.collection_exists(&scoped_collection).await? {
+            tracing::debug!(
+                user_id = %user_id,
+                collection = %collection,
+                "Scoped collection not found"
+            );
+            continue;
+        }
+        let settings = load_settings(store.as_ref(), &scoped_collection).await?;
+        let batcher =
+            get_embedding_batcher(config, &effective_embedding_model(&settings, config)).await?;
+        let embedding = batcher.embed(query).await?;
+        let threshold = settings.search_threshold.unwrap_or(0.0);
+        let results: Vec<_> = store
+            .search(&scoped_collection, &embedding, candidates, threshold)
+            .await?
+            .into_iter()
+            .filter(|r| rag_scope::matches(scope, &r.document.metadata.tags))
+            .collect();
+        // Give the model the parent sections of matching chunks, once each
+        let results = with_parent_context(
+            config,
+            &scoped_collection,
+            &settings,
+            results,
+            |r| r.document.id.as_str(),
+            None,
+        )
+        .await;
+        passages.extend(results.into_iter().map(|(r, context)| Passage {
+            filename: match r.document.metadata.title.is_empty() {
+                true => collection.clone(),
+                false => r.document.metadata.title,
+            },
+            content: context.unwrap_or(r.document.content),
+            score: r.score,
+        }));
+    }
+    passages.sort_by(|a, b| b.score.total_cmp(&a.score));
+    passages.truncate(limit);
+    Ok(passages)
+}
+
+/// Check a user has every collection a scope names.
+pub(crate) async fn check_scope_collections(
+    config: &AresConfig,
+    user_id: &str,
+    scope: &RagScope,
+) -> Result<()> {
+    let store = get_vector_store(&config.rag).await?;
+    for collection in &scope.collections {
+        let scoped_collection = user_scoped_collection(user_id, collection);
+        if !store.collection_exists(&scoped_collection).await? {
+            return Err(AppError::NotFound(format!(
+                "Collection '{}' not found",
+                collection
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Users' RAG collections, searched by agents with a `rag` section (see
+/// [`crate::agents::knowledge`]).
+pub struct CollectionKnowledge {
+    config_manager: Arc<AresConfigManager>,
+}
+
+impl CollectionKnowledge {
+    /// Search the vector store configured by `config_manager`
+    pub fn new(config_manager: Arc<AresConfigManager>) -> Self {
+        Self { config_manager }
+    }
+}
+
+#[async_trait]
+impl KnowledgeBase for CollectionKnowledge {
+    async fn search(
+        &self,
+        owner: &str,
+        collection: &str,
+        query: &str,
+        top_k: usize,
+        min_score: f32,
+    ) -> Result<Vec<Passage>> {
+        let config = self.config_manager.config();
+        let scope = RagScope {
+            collections: vec![collection.to_string()],
+            tags: Vec::new(),
+        };
+        let mut passages = scoped_passages(&config, owner, &scope, query, top_k).await?;
+        passages.retain(|p| p.score >= min_score);
+        Ok(passages)
+    }
+}
+
+/// Load a collection's settings, or the defaults if none are stored.
+async fn load_settings(store: &dyn VectorStore, collection: &str) -> Result<CollectionSettings> {
+    Ok(store
+        .collection_settings(collection)
+        .await?
+        .unwrap_or_default())
+}
+
+/// Pair a collection's matching chunks, best first, with their parent
+/// context, dropping chunks whose context a better chunk already brings.
+///
+/// Chunks without parent sections (the collection stores none, or their
+/// document predates them) have no context. Failing to read parent sections
+/// falls back to the chunks alone.
+async fn with_parent_context<T>(
+    config: &AresConfig,
+    scoped_collection: &str,
+    settings: &CollectionSettings,
+    hits: Vec<T>,
+    chunk_id: impl Fn(&T) -> &str,
+    window: Option<usize>,
+) -> Vec<(T, Option<String>)> {
+    if settings.parent_chunk_size.is_none() {
+        return hits.into_iter().map(|hit| (hit, None)).collect();
+    }
+    let window = window.or(settings.parent_window).unwrap_or(0);
+    let ids: Vec<&str> = hits.iter().map(&chunk_id).collect();
+    let contexts = match parent_store(config)
+        .contexts(scoped_collection, &ids, window)
+        .await
+    {
+        Ok(contexts) => contexts,
+        Err(e) => {
+            tracing::warn!(collection = %scoped_collection, "Ignoring parent sections: {}", e);
+            return hits.into_iter().map(|hit| (hit, None)).collect();
+        }
+    };
+    let mut seen = HashSet::new();
+    hits.into_iter()
+        .zip(contexts)
+        .filter_map(|(hit, context)| match context {
+            Some(ParentContext {
+                document,
+                section,
+                content,
+            }) => seen
+                .insert((document, section))
+                .then_some((hit, Some(content))),
+            None => Some((hit, None)),
+        })
+        .collect()
+}
+
+/// Parent section files live next to the vector data.
+fn parent_store(config: &AresConfig) -> ParentStore {
+    ParentStore::new(std::path::PathBuf::from(&config.rag.vector_path).join("parents"))
+}
+
+/// Embedding model in effect for a collection.
+fn effective_embedding_model(settings: &CollectionSettings, config: &AresConfig) -> String {
+    settings
+        .embedding_model
+        .clone()
+        .unwrap_or_else(|| config.rag.embedding_model.clone())
+}
+
+/// Build the chunker for a strategy, using the collection's size and overlap
+/// when set.
+fn chunker_for(strategy: ChunkingStrategy, settings: &CollectionSettings) -> TextChunker {
+    match strategy {
+        ChunkingStrategy::Word => TextChunker::with_word_chunking(
+            settings.chunk_size.unwrap_or(200),
+            settings.chunk_overlap.unwrap_or(50),
+        ),
+        ChunkingStrategy::Semantic => {
+            TextChunker::with_semantic_chunking(settings.chunk_size.unwrap_or(500))
+        }
+        ChunkingStrategy::Character => TextChunker::with_character_chunking(
+            settings.chunk_size.unwrap_or(500),
+            settings.chunk_overlap.unwrap_or(100),
+        ),
+        ChunkingStrategy::Code => {
+            TextChunker::with_code_chunking(settings.chunk_size.unwrap_or(1500))
+        }
+    }
+}
+
+/// Check that settings hold valid strategy names and chunk sizes.
+fn validate_settings(settings: &CollectionSettings) -> Result<()> {
+    if let Some(strategy) = &settings.chunking_strategy {
+        strategy.parse::<ChunkingStrategy>()?;
+    }
+    if let Some(strategy) = &settings.search_strategy {
+        strategy.parse::<SearchStrategy>()?;
+    }
+    if settings.chunk_size == Some(0) {
+        return Err(AppError::InvalidInput(
+            "chunk_size must be greater than 0".into(),
+        ));
+    }
+    if let (Some(size), Some(overlap)) = (settings.chunk_size, settings.chunk_overlap) {
+        if overlap >= size {
+            return Err(AppError::InvalidInput(
+                "chunk_overlap must be smaller than chunk_size".into(),
+            ));
+        }
+    }
+    if settings.parent_chunk_size == Some(0) {
+        return Err(AppError::InvalidInput(
+            "parent_chunk_size must be greater than 0".into(),
+        ));
+    }
+    if let Some(threshold) = settings.search_threshold {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(AppError::InvalidInput(
+                "search_threshold must be between 0.0 and 1.0".into(),
+            ));
+        }
+    }
+    if let Some(weight) = settings.feedback_weight {
+        if !(0.0..=1.0).contains(&weight) {
+            return Err(AppError::InvalidInput(
+                "feedback_weight must be between 0.0 and 1.0".into(),
+            ));
+        }
+    }
+    Ok(())
+}
+
 // ============================================================================
 // Ingest Endpoint
 // ============================================================================
 
 /// Ingest a document into the RAG system.
 ///
-/// Chunks the document and stores embeddings for later retrieval.
+/// Chunks the document and stores embeddings for later retrieval, using the
+/// collection's chunking settings and embedding model. With `file_id`, the
+/// text of an uploaded file is ingested: PDF, DOCX, HTML, Markdown, CSV or
+/// plain text, read by its loader (see [`crate::rag::loaders`]). It is
+/// titled with the title found in the file, or else its file name, unless
+/// a title is given.
 #[utoipa::path(
     post,
     path = "/api/rag/ingest",
@@ -103,7 +506,8 @@ async fn get_vector_store(vector_path: &str) -> Result<Arc<AresVectorStore>> {
 pub async fn ingest(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
-    Json(payload): Json<RagIngestRequest>,
+    workspace: ActiveWorkspace,
+    Json(mut payload): Json<RagIngestRequest>,
 ) -> Result<Json<RagIngestResponse>> {
     let start = Instant::now();
 
@@ -111,91 +515,155 @@ pub async fn ingest(
     if payload.collection.is_empty() {
         return Err(AppError::InvalidInput("Collection name required".into()));
     }
+    if let Some(file_id) = &payload.file_id {
+        if !payload.content.is_empty() {
+            return Err(AppError::InvalidInput(
+                "Set either content or file_id, not both".into(),
+            ));
+        }
+        let FileText { file, text, title } = file_text(&state, file_id, &claims.sub).await?;
+        payload.title.get_or_insert(title.unwrap_or(file.filename));
+        payload.content = text;
+    }
     if payload.content.is_empty() {
         return Err(AppError::InvalidInput("Content required".into()));
     }
 
     // Scope collection to user for isolation
-    let scoped_collection = user_scoped_collection(&claims.sub, &payload.collection);
+    let scoped_collection =
+        user_scoped_collection(&workspace.owner(&claims.sub), &payload.collection);
+
+    let config = state.config_manager.config();
+    let vector_store = get_vector_store(&config.rag).await?;
+    let document_ids = ingest_text(
+        &config,
+        vector_store.as_ref(),
+        &scoped_collection,
+        &payload.collection,
+        &payload.content,
+        payload.chunking_strategy.as_deref(),
+        DocumentMetadata {
+            title: payload.title.clone().unwrap_or_default(),
+            source: payload.source.clone().unwrap_or_default(),
+            created_at: Utc::now(),
+            tags: payload.tags.clone(),
+        },
+        &Uuid::new_v4().to_string(),
+    )
+    .await?;
+
+    if document_ids.is_empty() {
+        return Err(AppError::InvalidInput("Content too small to chunk".into()));
+    }
 
-    // Get services
-    let embedding_service = get_embedding_service().await?;
-    let vector_path = &state.config_manager.config().rag.vector_path;
-    let vector_store = get_vector_store(vector_path).await?;
+    tracing::info!(
+        user_id = %claims.sub,
+        collection = %payload.collection,
+        scoped_collection = %scoped_collection,
+        chunks = document_ids.len(),
+        duration_ms = start.elapsed().as_millis() as u64,
+        "Document ingested"
+    );
 
-    // Parse chunking strategy
-    let strategy: ChunkingStrategy = payload
-        .chunking_strategy
-        .as_ref()
+    Ok(Json(RagIngestResponse {
+        chunks_created: document_ids.len(),
+        document_ids,
+        collection: payload.collection, // Return user-facing name, not scoped
+    }))
+}
+
+/// Chunk, embed and store a document, returning the IDs of its chunks.
+///
+/// Chunk `i` is stored as `{id_prefix}_{i}`, replacing any chunk with that
+/// ID. When the collection has a `parent_chunk_size`, the content is split
+/// into parent sections first, which replace the document's earlier ones.
+/// Returns no IDs when the content yields no chunks.
+#[allow(clippy::too_many_arguments)]
+async fn ingest_text(
+    config: &AresConfig,
+    vector_store: &dyn VectorStore,
+    scoped_collection: &str,
+    collection: &str,
+    content: &str,
+    chunking_strategy: Option<&str>,
+    metadata: DocumentMetadata,
+    id_prefix: &str,
+) -> Result<Vec<String>> {
+    let mut settings = load_settings(vector_store, scoped_collection).await?;
+    let embedding_model = effective_embedding_model(&settings, config);
+    let batcher = get_embedding_batcher(config, &embedding_model).await?;
+
+    // Parse chunking strategy: request, then collection setting
+    let strategy: ChunkingStrategy = chunking_strategy
+        .or(settings.chunking_strategy.as_deref())
         .map(|s| s.parse())
         .transpose()?
         .unwrap_or_default();
 
     // Create chunker
-    let chunker = match strategy {
-        ChunkingStrategy::Word => TextChunker::with_word_chunking(200, 50),
-        ChunkingStrategy::Semantic => TextChunker::with_semantic_chunking(500),
-        ChunkingStrategy::Character => TextChunker::with_character_chunking(500, 100),
-    };
+    let chunker = chunker_for(strategy, &settings);
 
-    // Chunk the content
-    let chunks = chunker.chunk_with_metadata(&payload.content);
+    // Chunk the content, within parent sections if the collection has them
+    let (chunks, sections) = match settings.parent_chunk_size {
+        Some(parent_size) => parents::split(content, parent_size, &chunker),
+        None => (chunker.chunk(content), Vec::new()),
+    };
 
     if chunks.is_empty() {
-        return Err(AppError::InvalidInput("Content too small to chunk".into()));
+        return Ok(Vec::new());
     }
 
-    // Ensure collection exists
-    let dimensions = embedding_service.dimensions();
-    if !vector_store.collection_exists(&scoped_collection).await? {
+    // Generate embeddings for each chunk
+    let embeddings = batcher.embed_many(chunks.clone()).await?;
+
+    // Ensure collection exists, sized to the embedding model's output
+    let dimensions = embeddings.first().map(Vec::len).unwrap_or_default();
+    if vector_store.collection_exists(scoped_collection).await? {
+        let stats = vector_store.collection_stats(scoped_collection).await?;
+        if stats.dimensions != dimensions {
+            return Err(AppError::InvalidInput(format!(
+                "Embedding model '{}' produces {} dimensions but collection '{}' has {}; \
+                 re-embed it with POST /api/rag/collections/{}/reembed",
+                embedding_model, dimensions, collection, stats.dimensions, collection
+            )));
+        }
+    } else {
         vector_store
-            .create_collection(&scoped_collection, dimensions)
+            .create_collection(scoped_collection, dimensions)
             .await?;
     }
 
-    // Generate embeddings for each chunk
-    let chunk_texts: Vec<String> = chunks.iter().map(|c| c.content.clone()).collect();
-    let embeddings = embedding_service.embed_texts(&chunk_texts).await?;
+    // Pin the embedding model so later searches and ingests use the same one
+    if settings.embedding_model.is_none() {
+        settings.embedding_model = Some(embedding_model);
+        vector_store
+            .set_collection_settings(scoped_collection, &settings)
+            .await?;
+    }
 
     // Create documents
-    let base_id = Uuid::new_v4().to_string();
     let mut documents = Vec::with_capacity(chunks.len());
     let mut document_ids = Vec::with_capacity(chunks.len());
 
-    for (i, (chunk, embedding)) in chunks.iter().zip(embeddings.into_iter()).enumerate() {
-        let doc_id = format!("{}_{}", base_id, i);
+    for (i, (chunk, embedding)) in chunks.into_iter().zip(embeddings).enumerate() {
+        let doc_id = format!("{}_{}", id_prefix, i);
         document_ids.push(doc_id.clone());
 
         documents.push(Document {
             id: doc_id,
-            content: chunk.content.clone(),
-            metadata: DocumentMetadata {
-                title: payload.title.clone().unwrap_or_default(),
-                source: payload.source.clone().unwrap_or_default(),
-                created_at: Utc::now(),
-                tags: payload.tags.clone(),
-            },
+            content: chunk,
+            metadata: metadata.clone(),
             embedding: Some(embedding),
         });
     }
 
     // Upsert to vector store
-    let count = vector_store.upsert(&scoped_collection, &documents).await?;
-
-    tracing::info!(
-        user_id = %claims.sub,
-        collection = %payload.collection,
-        scoped_collection = %scoped_collection,
-        chunks = count,
-        duration_ms = start.elapsed().as_millis() as u64,
-        "Document ingested"
-    );
-
-    Ok(Json(RagIngestResponse {
-        chunks_created: count,
-        document_ids,
-        collection: payload.collection, // Return user-facing name, not scoped
-    }))
+    vector_store.upsert(scoped_collection, &documents).await?;
+    pipeline::invalidate(scoped_collection);
+    parent_store(config)
+        .save(scoped_collection, id_prefix, &sections)
+        .await?;
+    Ok(document_ids)
 }
 
 // ============================================================================
@@ -205,6 +673,7 @@ pub async fn ingest(
 /// Search the RAG system.
 ///
 /// Supports multiple search strategies: semantic, BM25, fuzzy, and hybrid.
+/// Options not given in the request fall back to the collection's settings.
 #[utoipa::path(
     post,
     path = "/api/rag/search",
@@ -222,6 +691,7 @@ pub async fn ingest(
 pub async fn search(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
     Json(payload): Json<RagSearchRequest>,
 ) -> Result<Json<RagSearchResponse>> {
     let start = Instant::now();
@@ -235,12 +705,12 @@ pub async fn search(
     }
 
     // Scope collection to user for isolation
-    let scoped_collection = user_scoped_collection(&claims.sub, &payload.collection);
+    let scoped_collection =
+        user_scoped_collection(&workspace.owner(&claims.sub), &payload.collection);
 
     // Get services
-    let embedding_service = get_embedding_service().await?;
-    let vector_path = &state.config_manager.config().rag.vector_path;
-    let vector_store = get_vector_store(vector_path).await?;
+    let config = state.config_manager.config();
+    let vector_store = get_vector_store(&config.rag).await?;
 
     // Check collection exists
     if !vector_store.collection_exists(&scoped_collection).await? {
@@ -250,138 +720,124 @@ pub async fn search(
         )));
     }
 
-    // Parse search strategy
+    // Greetings and small talk need no documents; skip the embedding round-trip
+    let classify_intent = payload
+        .classify_intent
+        .unwrap_or(config.rag.intent_classification);
+    if classify_intent {
+        let intent = intent::classify(&payload.query);
+        if !intent.needs_retrieval() {
+            tracing::debug!(
+                user_id = %claims.sub,
+                collection = %payload.collection,
+                ?intent,
+                "Search skipped"
+            );
+            return Ok(Json(RagSearchResponse {
+                results: Vec::new(),
+                total: 0,
+                strategy: "none".to_string(),
+                reranked: false,
+                retrieval_skipped: true,
+                duration_ms: start.elapsed().as_millis() as u64,
+            }));
+        }
+    }
+
+    // Queries must be embedded with the collection's model
+    let settings = load_settings(vector_store.as_ref(), &scoped_collection).await?;
+    let batcher =
+        get_embedding_batcher(&config, &effective_embedding_model(&settings, &config)).await?;
+
+    // Parse search strategy: request, then collection setting
     let strategy: SearchStrategy = payload
         .strategy
         .as_ref()
+        .or(settings.search_strategy.as_ref())
         .map(|s| s.parse())
         .transpose()?
         .unwrap_or(SearchStrategy::Semantic);
+    let limit = payload.limit.or(settings.search_limit).unwrap_or(10);
+    let threshold = payload
+        .threshold
+        .or(settings.search_threshold)
+        .unwrap_or(0.0);
+    let rerank_results = payload
+        .rerank
+        .or(settings.rerank)
+        .unwrap_or(config.rag.rerank_enabled);
 
     // Generate query embedding
-    let query_embedding = embedding_service.embed_text(&payload.query).await?;
-
-    // Perform vector search
-    let vector_results = vector_store
-        .search(
-            &scoped_collection,
-            &query_embedding,
-            payload.limit * 2, // Fetch extra for filtering/reranking
-            payload.threshold,
-        )
-        .await?;
-
-    // Apply additional search strategies if needed
-    let mut results: Vec<RagSearchResult> = match strategy {
-        SearchStrategy::Semantic => {
-            // Pure semantic search - already done
-            vector_results
-                .iter()
-                .take(payload.limit)
-                .map(|r| RagSearchResult {
-                    id: r.document.id.clone(),
-                    content: r.document.content.clone(),
-                    score: r.score,
-                    metadata: r.document.metadata.clone(),
-                })
-                .collect()
-        }
-        SearchStrategy::Bm25 | SearchStrategy::Fuzzy | SearchStrategy::Hybrid => {
-            // For BM25, fuzzy, or hybrid, we need to build an index over the results
-            let mut search_engine = SearchEngine::new();
-
-            // Index the vector search results as Document structs
-            for r in &vector_results {
-                search_engine.index_document(&r.document);
+    let query_embedding = batcher.embed(&payload.query).await?;
+
+    // Boost or demote chunks by their feedback
+    let mut retriever = Retriever::new(vector_store.clone());
+    let feedback_weight = settings
+        .feedback_weight
+        .unwrap_or(config.rag.feedback_weight);
+    if feedback_weight > 0.0 {
+        match feedback_store(&config).load(&scoped_collection).await {
+            Ok(chunk_feedback) => {
+                retriever = retriever.with_feedback(chunk_feedback, feedback_weight)
+            }
+            Err(e) => {
+                tracing::warn!(collection = %payload.collection, "Ignoring chunk feedback: {}", e)
             }
-
-            // Get strategy-specific results
-            let strategy_results = match strategy {
-                SearchStrategy::Bm25 => search_engine.search_bm25(&payload.query, payload.limit),
-                SearchStrategy::Fuzzy => search_engine.search_fuzzy(&payload.query, payload.limit),
-                SearchStrategy::Hybrid => {
-                    // Combine semantic and BM25 using hybrid search
-                    let semantic_scores: Vec<_> = vector_results
-                        .iter()
-                        .map(|r| (r.document.id.clone(), r.score))
-                        .collect();
-                    let weights = HybridWeights::default();
-                    search_engine.search_hybrid(
-                        &payload.query,
-                        &semantic_scores,
-                        &weights,
-                        payload.limit,
-                    )
-                }
-                _ => vec![], // Already handled above
-            };
-
-            // Map back to full documents
-            strategy_results
-                .iter()
-                .filter_map(|(id, score)| {
-                    vector_results
-                        .iter()
-                        .find(|r| r.document.id == *id)
-                        .map(|r| RagSearchResult {
-                            id: r.document.id.clone(),
-                            content: r.document.content.clone(),
-                            score: *score,
-                            metadata: r.document.metadata.clone(),
-                        })
-                })
-                .collect()
         }
-    };
-
-    // Apply reranking if requested
-    let reranked = if payload.rerank && !results.is_empty() {
-        // Parse reranker model
-        let model_type: RerankerModelType = payload
-            .reranker_model
-            .as_ref()
-            .map(|s| s.parse())
-            .transpose()?
-            .unwrap_or_default();
-
-        // Create reranker with config
-        let config = RerankerConfig {
-            model: model_type,
-            ..Default::default()
-        };
-        let reranker = Reranker::new(config);
-
-        // Prepare results for reranking: (id, content, score)
-        let rerank_input: Vec<_> = results
-            .iter()
-            .map(|r| (r.id.clone(), r.content.clone(), r.score))
-            .collect();
-
-        // Rerank results
-        let reranked_results = reranker
-            .rerank(&payload.query, &rerank_input, Some(payload.limit))
-            .await
-            .map_err(|e| AppError::Internal(format!("Reranking failed: {}", e)))?;
+    }
+    if rerank_results {
+        retriever = retriever.with_reranker(reranker(&state, &config, &payload).await?);
+    }
 
-        // Convert to RagSearchResult
-        results = reranked_results
-            .into_iter()
-            .filter_map(|rr| {
-                results
-                    .iter()
-                    .find(|r| r.id == rr.id)
-                    .map(|r| RagSearchResult {
-                        id: r.id.clone(),
-                        content: r.content.clone(),
-                        score: rr.final_score,
-                        metadata: r.metadata.clone(),
-                    })
-            })
-            .collect();
-        true
-    } else {
-        false
+    // Run the strategies and rank every candidate
+    let request = SearchRequest {
+        query: payload.query.clone(),
+        strategy,
+        top_k: limit,
+        min_score: threshold,
+        rerank: rerank_results,
+        collection: scoped_collection.clone(),
+        hybrid_weights: HybridWeights::default(),
     };
+    let candidates = retriever.candidates(&request, &query_embedding).await?;
+
+    // Keep the best chunk of each parent section, then cut and rerank
+    let candidates = with_parent_context(
+        &config,
+        &scoped_collection,
+        &settings,
+        candidates,
+        |r| r.id.as_str(),
+        payload.parent_window,
+    )
+    .await;
+    let mut contexts = HashMap::new();
+    let candidates: Vec<_> = candidates
+        .into_iter()
+        .map(|(result, context)| {
+            if let Some(context) = context {
+                contexts.insert(result.id.clone(), context);
+            }
+            result
+        })
+        .collect();
+    let reranked = rerank_results && !candidates.is_empty();
+    let results: Vec<RagSearchResult> = retriever
+        .rerank(&request, candidates)
+        .await?
+        .into_iter()
+        .map(|r| RagSearchResult {
+            context: contexts.remove(&r.id),
+            metadata: r
+                .metadata
+                .and_then(|metadata| serde_json::from_value(metadata).ok())
+                .unwrap_or_default(),
+            id: r.id,
+            content: r.content,
+            score: r.score,
+            sources: r.sources,
+        })
+        .collect();
 
     let total = results.len();
     let strategy_name = format!("{:?}", strategy).to_lowercase();
@@ -401,10 +857,33 @@ pub async fn search(
         total,
         strategy: strategy_name,
         reranked,
+        retrieval_skipped: false,
         duration_ms: start.elapsed().as_millis() as u64,
     }))
 }
 
+/// The reranker a search asks for, else the configured one.
+async fn reranker(
+    state: &AppState,
+    config: &AresConfig,
+    payload: &RagSearchRequest,
+) -> Result<Arc<dyn Reranker>> {
+    let kind: RerankerKind = payload
+        .reranker
+        .as_ref()
+        .map(|s| s.parse())
+        .transpose()?
+        .unwrap_or(config.rag.reranker);
+
+    create_reranker(
+        config,
+        &state.provider_registry,
+        kind,
+        payload.reranker_model.as_deref(),
+    )
+    .await
+}
+
 // ============================================================================
 // Delete Collection Endpoint
 // ============================================================================
@@ -427,6 +906,7 @@ pub async fn search(
 pub async fn delete_collection(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
     Json(payload): Json<RagDeleteCollectionRequest>,
 ) -> Result<Json<RagDeleteCollectionResponse>> {
     // Validate input
@@ -435,10 +915,10 @@ pub async fn delete_collection(
     }
 
     // Scope collection to user for isolation
-    let scoped_collection = user_scoped_collection(&claims.sub, &payload.collection);
+    let scoped_collection =
+        user_scoped_collection(&workspace.owner(&claims.sub), &payload.collection);
 
-    let vector_path = &state.config_manager.config().rag.vector_path;
-    let vector_store = get_vector_store(vector_path).await?;
+    let vector_store = get_vector_store(&state.config_manager.config().rag).await?;
 
     // Check collection exists
     if !vector_store.collection_exists(&scoped_collection).await? {
@@ -454,6 +934,13 @@ pub async fn delete_collection(
 
     // Delete the collection
     vector_store.delete_collection(&scoped_collection).await?;
+    pipeline::invalidate(&scoped_collection);
+    feedback_store(&state.config_manager.config())
+        .clear(&scoped_collection)
+        .await?;
+    parent_store(&state.config_manager.config())
+        .clear(&scoped_collection)
+        .await?;
 
     tracing::info!(
         user_id = %claims.sub,
@@ -488,16 +975,16 @@ pub async fn delete_collection(
 pub async fn list_collections(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
 ) -> Result<Json<Vec<crate::db::CollectionInfo>>> {
-    let vector_path = &state.config_manager.config().rag.vector_path;
-    let vector_store = get_vector_store(vector_path).await?;
+    let vector_store = get_vector_store(&state.config_manager.config().rag).await?;
     let all_collections = vector_store.list_collections().await?;
 
     // Filter to only collections belonging to this user and unscope names
     let user_collections: Vec<_> = all_collections
         .into_iter()
         .filter_map(|mut info| {
-            extract_user_collection(&claims.sub, &info.name).map(|user_name| {
+            extract_user_collection(&workspace.owner(&claims.sub), &info.name).map(|user_name| {
                 info.name = user_name;
                 info
             })
@@ -507,28 +994,1198 @@ pub async fn list_collections(
     Ok(Json(user_collections))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_default_search_strategy() {
-        let strategy: SearchStrategy = "semantic".parse().unwrap();
-        assert_eq!(strategy, SearchStrategy::Semantic);
+// ============================================================================
+// Collection Settings Endpoints
+// ============================================================================
 
-        let strategy: SearchStrategy = "bm25".parse().unwrap();
-        assert_eq!(strategy, SearchStrategy::Bm25);
+/// Get a collection's RAG settings.
+#[utoipa::path(
+    get,
+    path = "/api/rag/collections/{collection}/settings",
+    params(("collection" = String, Path, description = "Collection name")),
+    responses(
+        (status = 200, description = "Collection settings", body = RagCollectionSettingsResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "rag",
+    security(("bearer" = []))
+)]
+pub async fn get_collection_settings(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(collection): Path<String>,
+) -> Result<Json<RagCollectionSettingsResponse>> {
+    let scoped_collection = user_scoped_collection(&workspace.owner(&claims.sub), &collection);
+
+    let config = state.config_manager.config();
+    let vector_store = get_vector_store(&config.rag).await?;
+    let settings = load_settings(vector_store.as_ref(), &scoped_collection).await?;
+
+    Ok(Json(RagCollectionSettingsResponse {
+        collection,
+        embedding_model: effective_embedding_model(&settings, &config),
+        settings,
+    }))
+}
 
-        let strategy: SearchStrategy = "hybrid".parse().unwrap();
-        assert_eq!(strategy, SearchStrategy::Hybrid);
+/// Replace a collection's RAG settings.
+///
+/// Settings can be stored before the first ingest. Once a collection holds
+/// documents its embedding model can only be changed by re-embedding it
+/// (`POST /api/rag/collections/{collection}/reembed`).
+#[utoipa::path(
+    put,
+    path = "/api/rag/collections/{collection}/settings",
+    params(("collection" = String, Path, description = "Collection name")),
+    request_body = CollectionSettings,
+    responses(
+        (status = 200, description = "Collection settings updated", body = RagCollectionSettingsResponse),
+        (status = 400, description = "Invalid settings or embedding model change"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "rag",
+    security(("bearer" = []))
+)]
+pub async fn update_collection_settings(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(collection): Path<String>,
+    Json(mut settings): Json<CollectionSettings>,
+) -> Result<Json<RagCollectionSettingsResponse>> {
+    if collection.is_empty() {
+        return Err(AppError::InvalidInput("Collection name required".into()));
+    }
+    validate_settings(&settings)?;
+
+    let scoped_collection = user_scoped_collection(&workspace.owner(&claims.sub), &collection);
+
+    let config = state.config_manager.config();
+    let vector_store = get_vector_store(&config.rag).await?;
+
+    // Existing vectors were embedded with the current model; keep it
+    if vector_store.collection_exists(&scoped_collection).await?
+        && vector_store.count(&scoped_collection).await? > 0
+    {
+        let current = load_settings(vector_store.as_ref(), &scoped_collection).await?;
+        let current_model = effective_embedding_model(&current, &config);
+        match &settings.embedding_model {
+            Some(model) if *model != current_model => {
+                return Err(AppError::InvalidInput(format!(
+                    "Collection '{}' holds documents embedded with '{}'; re-embed it with \
+                     POST /api/rag/collections/{}/reembed to switch to '{}'",
+                    collection, current_model, collection, model
+                )));
+            }
+            Some(_) => {}
+            None => settings.embedding_model = Some(current_model),
+        }
     }
 
-    #[test]
-    fn test_default_chunking_strategy() {
-        let strategy: ChunkingStrategy = "word".parse().unwrap();
-        assert_eq!(strategy, ChunkingStrategy::Word);
+    vector_store
+        .set_collection_settings(&scoped_collection, &settings)
+        .await?;
 
-        let strategy: ChunkingStrategy = "semantic".parse().unwrap();
-        assert_eq!(strategy, ChunkingStrategy::Semantic);
+    tracing::info!(
+        user_id = %claims.sub,
+        collection = %collection,
+        "Collection settings updated"
+    );
+
+    Ok(Json(RagCollectionSettingsResponse {
+        collection,
+        embedding_model: effective_embedding_model(&settings, &config),
+        settings,
+    }))
+}
+
+// ============================================================================
+// Embedding Migration
+// ============================================================================
+
+/// Re-embed a collection's documents with another embedding model.
+///
+/// The migration path after the embedding model or provider changes: every
+/// stored chunk is embedded again with `embedding_model` (default: the
+/// configured `[rag] embedding_model`), and the collection is rebuilt with
+/// the new model's dimensions and pinned to it. Chunk IDs, metadata,
+/// settings and feedback are kept. The collection is left unchanged if
+/// embedding fails. A collection is re-embedded by one request at a time,
+/// across all servers sharing the database.
+#[utoipa::path(
+    post,
+    path = "/api/rag/collections/{collection}/reembed",
+    params(("collection" = String, Path, description = "Collection name")),
+    request_body = RagReembedRequest,
+    responses(
+        (status = 200, description = "Collection re-embedded", body = RagReembedResponse),
+        (status = 400, description = "Collection is already being re-embedded"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Collection not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "rag",
+    security(("bearer" = []))
+)]
+pub async fn reembed_collection(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(collection): Path<String>,
+    Json(payload): Json<RagReembedRequest>,
+) -> Result<Json<RagReembedResponse>> {
+    let start = Instant::now();
+    let scoped_collection = user_scoped_collection(&workspace.owner(&claims.sub), &collection);
+
+    let config = state.config_manager.config();
+    let vector_store = get_vector_store(&config.rag).await?;
+    if !vector_store.collection_exists(&scoped_collection).await? {
+        return Err(AppError::NotFound(format!(
+            "Collection '{}' not found",
+            collection
+        )));
+    }
+
+    let lock = locks::try_lock(
+        state.tenant_db.pool(),
+        &format!("reembed:{}", scoped_collection),
+    )
+    .await?
+    .ok_or_else(|| {
+        AppError::InvalidInput(format!(
+            "Collection '{}' is already being re-embedded",
+            collection
+        ))
+    })?;
+
+    let mut settings = load_settings(vector_store.as_ref(), &scoped_collection).await?;
+    let previous_embedding_model = effective_embedding_model(&settings, &config);
+    let previous_dimensions = vector_store
+        .collection_stats(&scoped_collection)
+        .await?
+        .dimensions;
+    let embedding_model = payload
+        .embedding_model
+        .filter(|model| !model.trim().is_empty())
+        .unwrap_or_else(|| config.rag.embedding_model.clone());
+
+    // Embed everything before touching the collection
+    let mut documents = vector_store.documents(&scoped_collection).await?;
+    let batcher = get_embedding_batcher(&config, &embedding_model).await?;
+    let embeddings = batcher
+        .embed_many(documents.iter().map(|doc| doc.content.clone()).collect())
+        .await?;
+    let dimensions = match embeddings.first() {
+        Some(embedding) => embedding.len(),
+        None => embedding_dimensions(&config, &embedding_model).await?,
+    };
+    for (document, embedding) in documents.iter_mut().zip(embeddings) {
+        document.embedding = Some(embedding);
+    }
+
+    // Rebuild the collection with the new dimensions
+    vector_store.delete_collection(&scoped_collection).await?;
+    vector_store
+        .create_collection(&scoped_collection, dimensions)
+        .await?;
+    settings.embedding_model = Some(embedding_model.clone());
+    vector_store
+        .set_collection_settings(&scoped_collection, &settings)
+        .await?;
+    vector_store.upsert(&scoped_collection, &documents).await?;
+    if let Err(e) = lock.release().await {
+        tracing::warn!("{}", e);
+    }
+
+    tracing::info!(
+        user_id = %claims.sub,
+        collection = %collection,
+        from = %previous_embedding_model,
+        to = %embedding_model,
+        documents = documents.len(),
+        duration_ms = start.elapsed().as_millis() as u64,
+        "Collection re-embedded"
+    );
+
+    Ok(Json(RagReembedResponse {
+        collection,
+        embedding_model,
+        previous_embedding_model,
+        dimensions,
+        previous_dimensions,
+        documents_reembedded: documents.len(),
+    }))
+}
+
+/// Check every collection against its embedding model's output dimensions.
+///
+/// Runs once in the background at startup: detects the dimensions of the
+/// configured embedding model, then warns about each collection whose
+/// vectors have other dimensions than its model produces. The vector store
+/// rejects ingests and searches on such collections until they are
+/// re-embedded with `POST /api/rag/collections/{collection}/reembed`.
+pub fn spawn_embedding_check(config_manager: Arc<AresConfigManager>) {
+    tokio::spawn(async move {
+        let config = config_manager.config();
+        if let Err(e) = check_embedding_dimensions(&config).await {
+            tracing::warn!("Failed to check embedding dimensions: {}", e);
+        }
+    });
+}
+
+async fn check_embedding_dimensions(config: &AresConfig) -> Result<()> {
+    let dimensions = embedding_dimensions(config, &config.rag.embedding_model).await?;
+    tracing::info!(
+        model = %config.rag.embedding_model,
+        dimensions,
+        "Embedding model dimensions detected"
+    );
+
+    let vector_store = get_vector_store(&config.rag).await?;
+    for info in vector_store.list_collections().await? {
+        let settings = load_settings(vector_store.as_ref(), &info.name).await?;
+        let model = effective_embedding_model(&settings, config);
+        let expected = match embedding_dimensions(config, &model).await {
+            Ok(expected) => expected,
+            Err(e) => {
+                tracing::warn!(
+                    collection = %info.name,
+                    model = %model,
+                    "Failed to detect embedding dimensions: {}",
+                    e
+                );
+                continue;
+            }
+        };
+        if info.dimensions != expected {
+            tracing::warn!(
+                collection = %info.name,
+                model = %model,
+                collection_dimensions = info.dimensions,
+                model_dimensions = expected,
+                "Collection dimensions don't match its embedding model; ingests and searches \
+                 are rejected until it is re-embedded"
+            );
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Chunk Feedback Endpoints
+// ============================================================================
+
+/// Feedback files live next to the vector data.
+fn feedback_store(config: &AresConfig) -> FeedbackStore {
+    FeedbackStore::new(std::path::PathBuf::from(&config.rag.vector_path).join("feedback"))
+}
+
+/// Vote a retrieved chunk up or down.
+///
+/// Votes boost or demote the chunk in later searches of the collection,
+/// scaled by the collection's `feedback_weight` (default: `[rag]
+/// feedback_weight`).
+#[utoipa::path(
+    post,
+    path = "/api/rag/feedback",
+    request_body = RagChunkFeedbackRequest,
+    responses(
+        (status = 200, description = "Feedback recorded", body = RagChunkFeedbackResponse),
+        (status = 400, description = "Invalid request"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Collection or chunk not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "rag",
+    security(("bearer" = []))
+)]
+pub async fn chunk_feedback(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Json(payload): Json<RagChunkFeedbackRequest>,
+) -> Result<Json<RagChunkFeedbackResponse>> {
+    if payload.collection.is_empty() {
+        return Err(AppError::InvalidInput("Collection name required".into()));
+    }
+    if payload.chunk_id.is_empty() {
+        return Err(AppError::InvalidInput("Chunk ID required".into()));
+    }
+
+    let scoped_collection =
+        user_scoped_collection(&workspace.owner(&claims.sub), &payload.collection);
+    let config = state.config_manager.config();
+    let vector_store = get_vector_store(&config.rag).await?;
+    if !vector_store.collection_exists(&scoped_collection).await?
+        || vector_store
+            .get(&scoped_collection, &payload.chunk_id)
+            .await?
+            .is_none()
+    {
+        return Err(AppError::NotFound(format!(
+            "Chunk '{}' not found in collection '{}'",
+            payload.chunk_id, payload.collection
+        )));
+    }
+
+    let chunk = feedback_store(&config)
+        .record(
+            &scoped_collection,
+            &payload.chunk_id,
+            payload.vote,
+            payload.query.as_deref(),
+        )
+        .await?;
+
+    tracing::info!(
+        user_id = %claims.sub,
+        collection = %payload.collection,
+        chunk_id = %payload.chunk_id,
+        vote = ?payload.vote,
+        "Chunk feedback recorded"
+    );
+
+    Ok(Json(RagChunkFeedbackResponse {
+        chunk_id: payload.chunk_id,
+        up: chunk.up,
+        down: chunk.down,
+        score: chunk.score(),
+    }))
+}
+
+/// Thresholds for the feedback report.
+#[derive(Debug, Deserialize)]
+pub struct FeedbackReportQuery {
+    /// Votes a chunk needs to be reported (default: 3)
+    pub min_votes: Option<u32>,
+    /// Highest feedback score reported (default: -0.3)
+    pub max_score: Option<f32>,
+    /// Maximum chunks returned (default: 50)
+    pub limit: Option<usize>,
+}
+
+/// List a collection's chunks with consistently poor feedback.
+///
+/// Chunks with at least `min_votes` votes and a score at or below
+/// `max_score` are returned worst first, with their content and the
+/// queries they were voted down for, so they can be fixed or removed.
+#[utoipa::path(
+    get,
+    path = "/api/rag/collections/{collection}/feedback",
+    params(
+        ("collection" = String, Path, description = "Collection name"),
+        ("min_votes" = Option<u32>, Query, description = "Votes a chunk needs to be reported (default: 3)"),
+        ("max_score" = Option<f32>, Query, description = "Highest feedback score reported (default: -0.3)"),
+        ("limit" = Option<usize>, Query, description = "Maximum chunks returned (default: 50)")
+    ),
+    responses(
+        (status = 200, description = "Poorly rated chunks", body = RagFeedbackReportResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Collection not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "rag",
+    security(("bearer" = []))
+)]
+pub async fn feedback_report(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(collection): Path<String>,
+    Query(query): Query<FeedbackReportQuery>,
+) -> Result<Json<RagFeedbackReportResponse>> {
+    let scoped_collection = user_scoped_collection(&workspace.owner(&claims.sub), &collection);
+    let config = state.config_manager.config();
+    let vector_store = get_vector_store(&config.rag).await?;
+    if !vector_store.collection_exists(&scoped_collection).await? {
+        return Err(AppError::NotFound(format!(
+            "Collection '{}' not found",
+            collection
+        )));
+    }
+    let chunk_feedback = feedback_store(&config).load(&scoped_collection).await?;
+
+    let mut chunks = Vec::new();
+    for (chunk_id, votes) in feedback::poorly_rated(
+        &chunk_feedback,
+        query.min_votes.unwrap_or(3),
+        query.max_score.unwrap_or(-0.3),
+    )
+    .into_iter()
+    .take(query.limit.unwrap_or(50))
+    {
+        let document = vector_store.get(&scoped_collection, chunk_id).await?;
+        chunks.push(RagFlaggedChunk {
+            chunk_id: chunk_id.to_string(),
+            up: votes.up,
+            down: votes.down,
+            score: votes.score(),
+            down_queries: votes.down_queries.clone(),
+            content: document.as_ref().map(|d| d.content.clone()),
+            metadata: document.map(|d| d.metadata),
+        });
+    }
+
+    Ok(Json(RagFeedbackReportResponse { collection, chunks }))
+}
+
+// ============================================================================
+// Ingestion Job Endpoints
+// ============================================================================
+
+/// Cancellation tokens of the jobs running in this process, by job ID.
+static RUNNING_INGEST_JOBS: LazyLock<parking_lot::Mutex<HashMap<String, CancellationToken>>> =
+    LazyLock::new(Default::default);
+
+/// Job checkpoints live next to the vector data.
+fn ingest_job_store(config: &AresConfig) -> IngestJobStore {
+    IngestJobStore::new(std::path::PathBuf::from(&config.rag.vector_path).join("ingest_jobs"))
+}
+
+/// Shortest allowed sync interval, so scheduled jobs cannot hammer a source.
+const MIN_SYNC_INTERVAL_SECS: u64 = 300;
+
+/// How often the scheduler looks for jobs due to re-sync.
+const INGEST_SCHEDULE_TICK: Duration = Duration::from_secs(60);
+
+/// Ingests job objects into a user's collection.
+struct CollectionSink {
+    config: Arc<AresConfig>,
+    vector_store: Arc<dyn VectorStore>,
+    scoped_collection: String,
+}
+
+#[async_trait]
+impl IngestSink for CollectionSink {
+    async fn ingest(
+        &self,
+        job: &IngestJob,
+        key: &str,
+        document: SourceDocument,
+        previous: Option<&IngestedObject>,
+    ) -> Result<usize> {
+        let id_prefix = job.document_prefix(key);
+        let mut tags = job.tags.clone();
+        tags.extend(document.tags);
+        let metadata = DocumentMetadata {
+            title: document.title,
+            source: document.url,
+            created_at: Utc::now(),
+            tags,
+        };
+        let ids = ingest_text(
+            &self.config,
+            self.vector_store.as_ref(),
+            &self.scoped_collection,
+            &job.collection,
+            &document.text,
+            job.chunking_strategy.as_deref(),
+            metadata,
+            &id_prefix,
+        )
+        .await?;
+
+        // Chunks share IDs across versions; drop the ones the new version no
+        // longer has, and the feedback given on the old content
+        if let Some(previous) = previous {
+            feedback_store(&self.config)
+                .forget(&self.scoped_collection, &format!("{}_", id_prefix))
+                .await?;
+            let stale: Vec<String> = (ids.len()..previous.chunks)
+                .map(|i| format!("{}_{}", id_prefix, i))
+                .collect();
+            if !stale.is_empty() {
+                self.vector_store
+                    .delete(&self.scoped_collection, &stale)
+                    .await?;
+                pipeline::invalidate(&self.scoped_collection);
+            }
+        }
+        Ok(ids.len())
+    }
+
+    async fn remove(&self, job: &IngestJob, key: &str, ingested: &IngestedObject) -> Result<()> {
+        let id_prefix = job.document_prefix(key);
+        let ids: Vec<String> = (0..ingested.chunks)
+            .map(|i| format!("{}_{}", id_prefix, i))
+            .collect();
+        self.vector_store
+            .delete(&self.scoped_collection, &ids)
+            .await?;
+        pipeline::invalidate(&self.scoped_collection);
+        parent_store(&self.config)
+            .remove(&self.scoped_collection, &id_prefix)
+            .await?;
+        feedback_store(&self.config)
+            .forget(&self.scoped_collection, &format!("{}_", id_prefix))
+            .await
+    }
+}
+
+/// Build the response for a job.
+///
+/// A job checkpointed as running that this process is not running was cut
+/// off by a restart, and is reported as interrupted.
+fn ingest_job_response(job: IngestJob) -> RagIngestJobResponse {
+    let status = if job.status == IngestJobStatus::Running
+        && !RUNNING_INGEST_JOBS.lock().contains_key(&job.id)
+    {
+        IngestJobStatus::Interrupted
+    } else {
+        job.status
+    };
+    RagIngestJobResponse {
+        id: job.id,
+        collection: job.collection,
+        source: job.source,
+        glob: job.glob,
+        sync_interval_secs: job.sync_interval_secs,
+        status,
+        objects_ingested: job.objects_ingested,
+        objects_unchanged: job.objects_unchanged,
+        objects_skipped: job.objects_skipped,
+        objects_failed: job.objects_failed,
+        objects_removed: job.objects_removed,
+        chunks_created: job.chunks_created,
+        errors: job.errors,
+        created_at: job.created_at,
+        updated_at: job.updated_at,
+    }
+}
+
+/// Load one of the user's jobs.
+async fn load_ingest_job(store: &IngestJobStore, user_id: &str, id: &str) -> Result<IngestJob> {
+    store
+        .load(id)
+        .await?
+        .filter(|job| job.user_id == user_id)
+        .ok_or_else(|| AppError::NotFound(format!("Ingest job '{}' not found", id)))
+}
+
+/// Key of the queue job running an ingest job
+fn ingest_dedupe_key(id: &str) -> String {
+    format!("ingest:{}", id)
+}
+
+/// Checkpoint a job as queued and queue it for the job workers.
+///
+/// Queueing a job that is already queued changes nothing. Queued jobs
+/// survive restarts; a job cut off by one is picked up again
+/// once its lease runs out, continuing from its checkpoint.
+async fn queue_ingest_job(state: &AppState, mut job: IngestJob) -> Result<IngestJob> {
+    let config = state.config_manager.config();
+    let location: SourceLocation = job.source.parse()?;
+    KeyFilter::new(&location.prefix, job.glob.as_deref())?;
+    create_source(&config.rag, &location)?;
+    if RUNNING_INGEST_JOBS.lock().contains_key(&job.id) {
+        return Err(AppError::InvalidInput(format!(
+            "Ingest job '{}' is already running",
+            job.id
+        )));
+    }
+
+    // Save before responding so the job can be polled straight away
+    job.status = IngestJobStatus::Queued;
+    ingest_job_store(&config).save(&job).await?;
+    jobs::enqueue(
+        state.tenant_db.pool(),
+        crate::api::jobs::INGEST,
+        &serde_json::json!({ "job_id": job.id }),
+        Some(&ingest_dedupe_key(&job.id)),
+        config.jobs.max_attempts,
+        Utc::now().timestamp(),
+    )
+    .await?;
+    crate::api::jobs::wake();
+    Ok(job)
+}
+
+/// Run a queued ingest job from its checkpoint until it stops.
+///
+/// Called by the job workers. A job that stops on a source error is
+/// returned as failed, so the worker retries it; cancelled jobs are not run.
+pub async fn run_queued_ingest_job(state: &AppState, id: &str) -> Result<()> {
+    let config = state.config_manager.config();
+    let store = ingest_job_store(&config);
+    let Some(mut job) = store.load(id).await? else {
+        return Err(AppError::NotFound(format!("Ingest job '{}' not found", id)));
+    };
+    if job.status == IngestJobStatus::Cancelled {
+        return Ok(());
+    }
+    let prepared = async {
+        let location: SourceLocation = job.source.parse()?;
+        let source = create_source(&config.rag, &location)?;
+        let filter = KeyFilter::new(&location.prefix, job.glob.as_deref())?;
+        let vector_store = get_vector_store(&config.rag).await?;
+        Ok::<_, AppError>((source, filter, vector_store))
+    };
+    let (source, filter, vector_store) = match prepared.await {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            // Report the failure on the job instead of leaving it queued
+            job.status = IngestJobStatus::Failed;
+            job.errors.push(e.to_string());
+            store.save(&job).await?;
+            return Err(e);
+        }
+    };
+    let sink = CollectionSink {
+        vector_store,
+        scoped_collection: user_scoped_collection(&job.user_id, &job.collection),
+        config: Arc::clone(&config),
+    };
+
+    let token = CancellationToken::new();
+    {
+        let mut running = RUNNING_INGEST_JOBS.lock();
+        if running.contains_key(&job.id) {
+            return Err(AppError::InvalidInput(format!(
+                "Ingest job '{}' is already running",
+                job.id
+            )));
+        }
+        running.insert(job.id.clone(), token.clone());
+    }
+
+    let start = Instant::now();
+    let outcome = run_job(&mut job, source.as_ref(), &filter, &sink, &store, &token).await;
+    RUNNING_INGEST_JOBS.lock().remove(&job.id);
+    outcome?;
+
+    tracing::info!(
+        job_id = %job.id,
+        source = %job.source,
+        collection = %job.collection,
+        status = ?job.status,
+        objects = job.objects_ingested,
+        chunks = job.chunks_created,
+        duration_ms = start.elapsed().as_millis() as u64,
+        "Ingest job stopped"
+    );
+    if job.status == IngestJobStatus::Failed {
+        return Err(AppError::External(
+            job.errors
+                .last()
+                .cloned()
+                .unwrap_or_else(|| format!("Ingest job '{}' failed", job.id)),
+        ));
+    }
+    Ok(())
+}
+
+/// Re-run jobs with a sync interval once the interval has passed.
+///
+/// Every minute, jobs that are not queued or running and whose last run
+/// ended at least `sync_interval_secs` ago are queued again: completed jobs
+/// start a new pass over their source, others continue from their
+/// checkpoint. Cancelled jobs stay stopped until resumed by hand. Only the
+/// leader queues them, when several servers share the database.
+pub fn spawn_ingest_scheduler(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(INGEST_SCHEDULE_TICK);
+        loop {
+            interval.tick().await;
+            if !locks::is_leader() {
+                continue;
+            }
+            let config = state.config_manager.config();
+            let jobs = match ingest_job_store(&config).list_all().await {
+                Ok(jobs) => jobs,
+                Err(e) => {
+                    tracing::warn!("Failed to list ingest jobs for scheduling: {}", e);
+                    continue;
+                }
+            };
+
+            let now = Utc::now();
+            for job in jobs {
+                let Some(interval_secs) = job.sync_interval_secs else {
+                    continue;
+                };
+                if matches!(
+                    job.status,
+                    IngestJobStatus::Cancelled | IngestJobStatus::Queued
+                ) || RUNNING_INGEST_JOBS.lock().contains_key(&job.id)
+                    || job.updated_at + chrono::Duration::seconds(interval_secs as i64) > now
+                {
+                    continue;
+                }
+
+                let job_id = job.id.clone();
+                match queue_ingest_job(&state, job).await {
+                    Ok(_) => tracing::info!(job_id = %job_id, "Scheduled ingest job sync queued"),
+                    Err(e) => {
+                        tracing::warn!(job_id = %job_id, "Failed to queue scheduled ingest job: {}", e)
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Start ingesting a bucket prefix or workspace in the background.
+///
+/// Lists the objects under an `s3://` or `gs://` prefix or a `github://`
+/// repository and ingests every text object matching the glob, or the pages
+/// of a `notion://` workspace or database or a `confluence://` space.
+/// Progress is checkpointed, so a job that stops can be resumed, and
+/// re-running it only re-ingests changed objects. With `sync_interval_secs`
+/// the job re-runs on that schedule. Repositories default to the `code`
+/// chunking strategy.
+#[utoipa::path(
+    post,
+    path = "/api/rag/ingest/jobs",
+    request_body = RagIngestJobRequest,
+    responses(
+        (status = 200, description = "Ingest job queued", body = RagIngestJobResponse),
+        (status = 400, description = "Invalid request"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "rag",
+    security(("bearer" = []))
+)]
+pub async fn start_ingest_job(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Json(payload): Json<RagIngestJobRequest>,
+) -> Result<Json<RagIngestJobResponse>> {
+    if payload.collection.is_empty() {
+        return Err(AppError::InvalidInput("Collection name required".into()));
+    }
+    if let Some(strategy) = &payload.chunking_strategy {
+        strategy.parse::<ChunkingStrategy>()?;
+    }
+    let location: SourceLocation = payload.source.parse()?;
+    if payload.glob.is_some() && !location.scheme.has_prefix() {
+        return Err(AppError::InvalidInput(format!(
+            "Globs only apply to bucket and repository sources, not '{}'",
+            payload.source
+        )));
+    }
+    if payload
+        .sync_interval_secs
+        .is_some_and(|secs| secs < MIN_SYNC_INTERVAL_SECS)
+    {
+        return Err(AppError::InvalidInput(format!(
+            "sync_interval_secs must be at least {}",
+            MIN_SYNC_INTERVAL_SECS
+        )));
+    }
+
+    let config = state.config_manager.config();
+    create_source(&config.rag, &location)?;
+
+    let mut job = IngestJob::new(
+        &workspace.owner(&claims.sub),
+        &payload.collection,
+        &location.to_string(),
+    );
+    job.glob = payload.glob;
+    job.chunking_strategy = payload.chunking_strategy.or_else(|| {
+        (location.scheme == SourceScheme::GitHub).then(|| ChunkingStrategy::Code.to_string())
+    });
+    job.tags = payload.tags;
+    job.sync_interval_secs = payload.sync_interval_secs;
+
+    let job = queue_ingest_job(&state, job).await?;
+
+    tracing::info!(
+        user_id = %claims.sub,
+        job_id = %job.id,
+        source = %job.source,
+        collection = %job.collection,
+        "Ingest job queued"
+    );
+
+    Ok(Json(ingest_job_response(job)))
+}
+
+/// List the user's ingestion jobs, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/rag/ingest/jobs",
+    responses(
+        (status = 200, description = "Ingest jobs listed", body = Vec<RagIngestJobResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "rag",
+    security(("bearer" = []))
+)]
+pub async fn list_ingest_jobs(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+) -> Result<Json<Vec<RagIngestJobResponse>>> {
+    let store = ingest_job_store(&state.config_manager.config());
+    let jobs = store.list(&workspace.owner(&claims.sub)).await?;
+    Ok(Json(jobs.into_iter().map(ingest_job_response).collect()))
+}
+
+/// Get an ingestion job's progress.
+#[utoipa::path(
+    get,
+    path = "/api/rag/ingest/jobs/{id}",
+    params(("id" = String, Path, description = "Ingest job ID")),
+    responses(
+        (status = 200, description = "Ingest job", body = RagIngestJobResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Ingest job not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "rag",
+    security(("bearer" = []))
+)]
+pub async fn get_ingest_job(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+) -> Result<Json<RagIngestJobResponse>> {
+    let store = ingest_job_store(&state.config_manager.config());
+    let job = load_ingest_job(&store, &workspace.owner(&claims.sub), &id).await?;
+    Ok(Json(ingest_job_response(job)))
+}
+
+/// Resume an ingestion job from its checkpoint.
+///
+/// A failed, cancelled or interrupted job continues where it stopped; a
+/// completed job lists its source again and ingests new and changed objects.
+#[utoipa::path(
+    post,
+    path = "/api/rag/ingest/jobs/{id}/resume",
+    params(("id" = String, Path, description = "Ingest job ID")),
+    responses(
+        (status = 200, description = "Ingest job queued", body = RagIngestJobResponse),
+        (status = 400, description = "Job is already running"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Ingest job not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "rag",
+    security(("bearer" = []))
+)]
+pub async fn resume_ingest_job(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+) -> Result<Json<RagIngestJobResponse>> {
+    let config = state.config_manager.config();
+    let job = load_ingest_job(
+        &ingest_job_store(&config),
+        &workspace.owner(&claims.sub),
+        &id,
+    )
+    .await?;
+
+    let job = queue_ingest_job(&state, job).await?;
+
+    tracing::info!(
+        user_id = %claims.sub,
+        job_id = %job.id,
+        "Ingest job resumed"
+    );
+
+    Ok(Json(ingest_job_response(job)))
+}
+
+/// Cancel a queued or running ingestion job.
+///
+/// A queued job is dropped from the queue; a running job stops after the
+/// object in progress. Either can be resumed later.
+#[utoipa::path(
+    post,
+    path = "/api/rag/ingest/jobs/{id}/cancel",
+    params(("id" = String, Path, description = "Ingest job ID")),
+    responses(
+        (status = 200, description = "Cancellation requested", body = RagIngestJobResponse),
+        (status = 400, description = "Job is not running"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Ingest job not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "rag",
+    security(("bearer" = []))
+)]
+pub async fn cancel_ingest_job(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+) -> Result<Json<RagIngestJobResponse>> {
+    let store = ingest_job_store(&state.config_manager.config());
+    let mut job = load_ingest_job(&store, &workspace.owner(&claims.sub), &id).await?;
+
+    let token = RUNNING_INGEST_JOBS.lock().get(&id).cloned();
+    if let Some(token) = token {
+        token.cancel();
+    } else if jobs::cancel_pending(state.tenant_db.pool(), &ingest_dedupe_key(&id)).await? {
+        // Dropped from the queue before a worker started it
+        job.status = IngestJobStatus::Cancelled;
+        store.save(&job).await?;
+    } else {
+        return Err(AppError::InvalidInput(format!(
+            "Ingest job '{}' is not running",
+            id
+        )));
+    }
+
+    tracing::info!(user_id = %claims.sub, job_id = %id, "Ingest job cancellation requested");
+
+    Ok(Json(ingest_job_response(job)))
+}
+
+/// Whether a GitHub webhook body carries a valid `X-Hub-Signature-256`
+fn verify_github_signature(secret: &str, body: &[u8], signature: Option<&str>) -> bool {
+    let Some(signature) = signature.and_then(|s| s.strip_prefix("sha256=")) else {
+        return false;
+    };
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body);
+    // Constant-time comparison
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Whether a push to `git_ref` updates the ref a `github://` job tracks
+///
+/// Jobs without an `@ref` track the repository's default branch.
+fn push_matches_job(
+    location: &SourceLocation,
+    repository: &str,
+    git_ref: &str,
+    default_branch: &str,
+) -> bool {
+    if location.scheme != SourceScheme::GitHub {
+        return false;
+    }
+    let (job_repository, job_ref) = match location.bucket.split_once('@') {
+        Some((job_repository, job_ref)) => (job_repository, job_ref),
+        None => (location.bucket.as_str(), default_branch),
+    };
+    job_repository.eq_ignore_ascii_case(repository)
+        && (git_ref == format!("refs/heads/{}", job_ref)
+            || git_ref == format!("refs/tags/{}", job_ref))
+}
+
+/// Re-index repositories on GitHub push events.
+///
+/// Configure a webhook on the repository with content type
+/// `application/json` and the secret in `rag.github.webhook_secret_env`.
+/// Every `github://` ingest job tracking the pushed branch or tag that is
+/// not running or cancelled is resumed; since files are versioned by blob
+/// SHA, only files the push changed are re-ingested and deleted files are
+/// removed. Deliveries are rejected while the secret is unset.
+#[utoipa::path(
+    post,
+    path = "/api/rag/github/webhook",
+    request_body = serde_json::Value,
+    responses(
+        (status = 200, description = "Delivery handled", body = RagGitHubWebhookResponse),
+        (status = 400, description = "Invalid payload"),
+        (status = 401, description = "Invalid signature"),
+        (status = 500, description = "Webhook secret not configured")
+    ),
+    tag = "rag"
+)]
+pub async fn github_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<RagGitHubWebhookResponse>> {
+    let config = state.config_manager.config();
+    let secret = std::env::var(&config.rag.github.webhook_secret_env)
+        .ok()
+        .filter(|secret| !secret.is_empty())
+        .ok_or_else(|| {
+            AppError::Configuration(format!(
+                "GitHub webhook secret variable {} is not set",
+                config.rag.github.webhook_secret_env
+            ))
+        })?;
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    if !verify_github_signature(&secret, &body, header("x-hub-signature-256")) {
+        return Err(AppError::Auth("Invalid webhook signature".into()));
+    }
+
+    let event = header("x-github-event").unwrap_or_default().to_string();
+    let mut triggered_jobs = Vec::new();
+    if event != "push" {
+        return Ok(Json(RagGitHubWebhookResponse {
+            event,
+            triggered_jobs,
+        }));
+    }
+
+    let payload: serde_json::Value = serde_json::from_slice(&body)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid push payload: {}", e)))?;
+    let (Some(repository), Some(git_ref)) = (
+        payload["repository"]["full_name"].as_str(),
+        payload["ref"].as_str(),
+    ) else {
+        return Err(AppError::InvalidInput(
+            "Push payload is missing repository.full_name or ref".into(),
+        ));
+    };
+    let default_branch = payload["repository"]["default_branch"]
+        .as_str()
+        .unwrap_or("main");
+
+    for job in ingest_job_store(&config).list_all().await? {
+        let Ok(location) = job.source.parse::<SourceLocation>() else {
+            continue;
+        };
+        if job.status == IngestJobStatus::Cancelled
+            || RUNNING_INGEST_JOBS.lock().contains_key(&job.id)
+            || !push_matches_job(&location, repository, git_ref, default_branch)
+        {
+            continue;
+        }
+
+        let job_id = job.id.clone();
+        match queue_ingest_job(&state, job).await {
+            Ok(_) => triggered_jobs.push(job_id),
+            Err(e) => {
+                tracing::warn!(job_id = %job_id, "Failed to queue ingest job for push: {}", e)
+            }
+        }
+    }
+
+    tracing::info!(
+        repository = %repository,
+        git_ref = %git_ref,
+        jobs = triggered_jobs.len(),
+        "GitHub push re-indexing started"
+    );
+
+    Ok(Json(RagGitHubWebhookResponse {
+        event,
+        triggered_jobs,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_github_signature() {
+        // Example from GitHub's webhook validation docs
+        let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        assert!(verify_github_signature(
+            "It's a Secret to Everybody",
+            b"Hello, World!",
+            Some(signature)
+        ));
+        assert!(!verify_github_signature(
+            "another secret",
+            b"Hello, World!",
+            Some(signature)
+        ));
+        assert!(!verify_github_signature(
+            "It's a Secret to Everybody",
+            b"Hello, World!",
+            None
+        ));
+    }
+
+    #[test]
+    fn test_push_matches_job() {
+        let default: SourceLocation = "github://dirmacs/ares/src/".parse().unwrap();
+        assert!(push_matches_job(
+            &default,
+            "dirmacs/ares",
+            "refs/heads/main",
+            "main"
+        ));
+        assert!(!push_matches_job(
+            &default,
+            "dirmacs/ares",
+            "refs/heads/dev",
+            "main"
+        ));
+        assert!(!push_matches_job(
+            &default,
+            "dirmacs/other",
+            "refs/heads/main",
+            "main"
+        ));
+
+        let tracked: SourceLocation = "github://dirmacs/ares@release-1.x".parse().unwrap();
+        assert!(push_matches_job(
+            &tracked,
+            "Dirmacs/Ares",
+            "refs/heads/release-1.x",
+            "main"
+        ));
+        assert!(!push_matches_job(
+            &tracked,
+            "dirmacs/ares",
+            "refs/heads/main",
+            "main"
+        ));
+
+        let bucket: SourceLocation = "s3://dirmacs/ares".parse().unwrap();
+        assert!(!push_matches_job(
+            &bucket,
+            "dirmacs/ares",
+            "refs/heads/main",
+            "main"
+        ));
+    }
+
+    #[test]
+    fn test_default_search_strategy() {
+        let strategy: SearchStrategy = "semantic".parse().unwrap();
+        assert_eq!(strategy, SearchStrategy::Semantic);
+
+        let strategy: SearchStrategy = "bm25".parse().unwrap();
+        assert_eq!(strategy, SearchStrategy::Bm25);
+
+        let strategy: SearchStrategy = "hybrid".parse().unwrap();
+        assert_eq!(strategy, SearchStrategy::Hybrid);
+    }
+
+    #[test]
+    fn test_default_chunking_strategy() {
+        let strategy: ChunkingStrategy = "word".parse().unwrap();
+        assert_eq!(strategy, ChunkingStrategy::Word);
+
+        let strategy: ChunkingStrategy = "semantic".parse().unwrap();
+        assert_eq!(strategy, ChunkingStrategy::Semantic);
+    }
+
+    #[test]
+    fn test_validate_collection_settings() {
+        let mut settings = CollectionSettings {
+            chunking_strategy: Some("word".to_string()),
+            chunk_size: Some(100),
+            chunk_overlap: Some(20),
+            search_strategy: Some("hybrid".to_string()),
+            search_threshold: Some(0.5),
+            ..Default::default()
+        };
+        assert!(validate_settings(&settings).is_ok());
+
+        settings.chunk_overlap = Some(100);
+        assert!(validate_settings(&settings).is_err());
+
+        settings.chunk_overlap = None;
+        settings.search_strategy = Some("nope".to_string());
+        assert!(validate_settings(&settings).is_err());
+
+        settings.search_strategy = None;
+        settings.feedback_weight = Some(1.5);
+        assert!(validate_settings(&settings).is_err());
     }
 }
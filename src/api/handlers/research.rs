@@ -1,5 +1,7 @@
 use crate::{
+    api::maintenance,
     auth::middleware::AuthUser,
+    llm::Judge,
     research::coordinator::ResearchCoordinator,
     types::{ResearchRequest, ResearchResponse, Result},
     AppState,
@@ -15,7 +17,8 @@ use std::time::Instant;
     responses(
         (status = 200, description = "Research completed", body = ResearchResponse),
         (status = 400, description = "Invalid input"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 503, description = "Server in maintenance mode")
     ),
     tag = "research",
     security(("bearer" = []))
@@ -25,6 +28,7 @@ pub async fn deep_research(
     AuthUser(_claims): AuthUser,
     Json(payload): Json<ResearchRequest>,
 ) -> Result<Json<ResearchResponse>> {
+    maintenance::ensure_available(&state)?;
     let start = Instant::now();
 
     // Get research workflow config
@@ -57,10 +61,20 @@ pub async fn deep_research(
         Err(_) => state.llm_factory.create_default().await?,
     };
 
-    let coordinator = ResearchCoordinator::new(llm_client, depth, max_iterations);
+    let mut coordinator = ResearchCoordinator::new(llm_client, depth, max_iterations);
+
+    // The research workflow's judge runs the critique pass
+    let judge = config
+        .get_workflow("research")
+        .and_then(|workflow| workflow.judge.as_ref())
+        .and_then(|name| config.judges.get(name));
+    if let Some(judge) = judge {
+        coordinator =
+            coordinator.with_judge(Judge::from_config(judge, &state.provider_registry).await?);
+    }
 
     // Execute research
-    let (findings, sources) = coordinator.research(&payload.query).await?;
+    let (findings, sources, judgment) = coordinator.research(&payload.query).await?;
 
     let duration = start.elapsed();
 
@@ -68,5 +82,6 @@ pub async fn deep_research(
         findings,
         sources,
         duration_ms: duration.as_millis() as u64,
+        judgment,
     }))
 }
@@ -0,0 +1,210 @@
+//! Interrupted run handlers.
+//!
+//! A chat run cut off by a server restart is marked interrupted by the next
+//! server to notice, which tells the user in the conversation. The user
+//! lists their interrupted runs, and resumes each one from its last
+//! checkpoint or dismisses it. See [`crate::agents::checkpoint`].
+
+use crate::{
+    agents::{
+        approval::ApprovalDecision,
+        checkpoint::{self, InterruptedRun},
+    },
+    api::{encryption::ConversationSecret, handlers::chat::resume_run, maintenance},
+    auth::middleware::AuthUser,
+    db::{
+        checkpoints::{self, RunCheckpoint},
+        threads,
+    },
+    models::TenantContext,
+    types::{AppError, ChatResponse, MessageRole, Result},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+/// Message stored in a conversation whose run was interrupted
+const INTERRUPTED_NOTICE: &str = "This answer was interrupted by a server restart before it \
+    finished. You can resume it from where it stopped, or send your message again.";
+
+/// List the user's runs interrupted by a server restart.
+#[utoipa::path(
+    get,
+    path = "/api/runs/interrupted",
+    responses(
+        (status = 200, description = "Interrupted runs, oldest first", body = Vec<InterruptedRun>),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "runs",
+    security(("bearer" = []))
+)]
+pub async fn list_interrupted_runs(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Vec<InterruptedRun>>> {
+    let runs = checkpoints::list_interrupted(state.tenant_db.pool(), &claims.sub).await?;
+    Ok(Json(runs.iter().map(RunCheckpoint::interrupted).collect()))
+}
+
+/// Resume an interrupted run from its last checkpoint.
+///
+/// Tool calls that were running when the server stopped are not run again;
+/// the agent is told they were interrupted. Answers with the resumed run's
+/// response, which is added to the conversation.
+#[utoipa::path(
+    post,
+    path = "/api/runs/{id}/resume",
+    params(("id" = String, Path, description = "Run ID")),
+    responses(
+        (status = 200, description = "Run resumed", body = ChatResponse),
+        (status = 404, description = "Interrupted run not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 503, description = "Server in maintenance mode")
+    ),
+    tag = "runs",
+    security(("bearer" = []))
+)]
+pub async fn resume_interrupted_run(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    tenant_ctx: Option<Extension<TenantContext>>,
+    Path(id): Path<String>,
+) -> Result<Json<ChatResponse>> {
+    maintenance::ensure_available(&state)?;
+    // Taking the run first means it only resumes once
+    let run = checkpoints::take_interrupted(state.tenant_db.pool(), &id, &claims.sub)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Interrupted run '{}' not found", id)))?;
+
+    let tenant_id = tenant_ctx
+        .map(|Extension(tc)| tc.tenant_id)
+        .unwrap_or_else(|| "system".to_string());
+    Ok(Json(
+        resume_run(
+            &state,
+            &claims,
+            &ConversationSecret::none(),
+            tenant_id,
+            &run.conversation_id,
+            &run.input,
+            checkpoint::interrupted(run.checkpoint()?),
+            ApprovalDecision::Approve,
+        )
+        .await?,
+    ))
+}
+
+/// Dismiss an interrupted run without resuming it.
+#[utoipa::path(
+    delete,
+    path = "/api/runs/{id}",
+    params(("id" = String, Path, description = "Run ID")),
+    responses(
+        (status = 200, description = "Run dismissed"),
+        (status = 404, description = "Interrupted run not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "runs",
+    security(("bearer" = []))
+)]
+pub async fn dismiss_interrupted_run(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>> {
+    checkpoints::take_interrupted(state.tenant_db.pool(), &id, &claims.sub)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Interrupted run '{}' not found", id)))?;
+    Ok(Json(serde_json::json!({"dismissed": true, "id": id})))
+}
+
+/// Keep this server's runs marked as running, and find runs whose server
+/// went away
+///
+/// Every [`checkpoints::HEARTBEAT_INTERVAL`] the server stamps the
+/// checkpoints of its own runs, then marks those no server has stamped for
+/// [`checkpoints::STALE_AFTER_SECS`] interrupted. The conversation of each
+/// interrupted run gets the user's message, if it didn't have it yet, and a
+/// reply saying the answer was interrupted.
+pub fn spawn_interruption_check(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(checkpoints::HEARTBEAT_INTERVAL);
+        loop {
+            interval.tick().await;
+            let pool = state.tenant_db.pool();
+            let now = Utc::now().timestamp();
+            if let Err(e) = checkpoints::heartbeat(pool, now).await {
+                tracing::warn!("{}", e);
+            }
+            let stale_before = now - checkpoints::STALE_AFTER_SECS;
+            match checkpoints::mark_interrupted(pool, stale_before).await {
+                Ok(interrupted) => report_interrupted(&state, &interrupted, now).await,
+                Err(e) => tracing::warn!("{}", e),
+            }
+        }
+    });
+}
+
+/// Mark the runs this server still has going interrupted, when it shuts
+/// down before they end, returning how many there were
+///
+/// Their users can resume them at once, without waiting for the runs to go
+/// stale.
+pub async fn interrupt_own_runs(state: &AppState) -> Result<usize> {
+    let interrupted = checkpoints::interrupt_own(state.tenant_db.pool()).await?;
+    report_interrupted(state, &interrupted, Utc::now().timestamp()).await;
+    Ok(interrupted.len())
+}
+
+/// Fail the thread runs of interrupted runs and tell their users
+async fn report_interrupted(state: &AppState, interrupted: &[RunCheckpoint], now: i64) {
+    let pool = state.tenant_db.pool();
+    for run in interrupted {
+        tracing::info!(
+            "Run {} of agent '{}' in conversation {} was interrupted",
+            run.id,
+            run.agent,
+            run.conversation_id
+        );
+        let error = "The run was interrupted by a server restart";
+        if let Err(e) = threads::fail_active_runs(pool, &run.conversation_id, error, now).await {
+            tracing::warn!("{}", e);
+        }
+        if let Err(e) = notify_interrupted(state, run).await {
+            tracing::warn!(
+                "Failed to report interrupted run {} in conversation {}: {}",
+                run.id,
+                run.conversation_id,
+                e
+            );
+        }
+    }
+}
+
+/// Tell the user in the conversation that a run was interrupted
+async fn notify_interrupted(state: &AppState, run: &RunCheckpoint) -> Result<()> {
+    if let Some(message) = &run.message {
+        state
+            .db
+            .add_message(
+                &Uuid::new_v4().to_string(),
+                &run.conversation_id,
+                MessageRole::User,
+                message,
+            )
+            .await?;
+    }
+    state
+        .db
+        .add_message(
+            &Uuid::new_v4().to_string(),
+            &run.conversation_id,
+            MessageRole::Assistant,
+            INTERRUPTED_NOTICE,
+        )
+        .await
+}
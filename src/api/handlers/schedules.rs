@@ -0,0 +1,372 @@
+//! Scheduled agent run handlers.
+//!
+//! Users schedule agents to run with a fixed prompt on a cron schedule. The
+//! scheduler in [`crate::agents::scheduler`] stores each run as a new
+//! conversation owned by the user.
+
+use crate::{
+    agents::scheduler::{self, CronSchedule},
+    api::handlers::user_agents::resolve_agent,
+    auth::middleware::AuthUser,
+    db::schedules::{self, AgentSchedule},
+    types::{AppError, Result},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Schedules a user may have at most
+const MAX_SCHEDULES_PER_USER: i64 = 20;
+
+/// Shortest allowed time between two runs of a schedule
+const MIN_SCHEDULE_INTERVAL_SECS: i64 = 300;
+
+/// Longest accepted schedule name
+const MAX_SCHEDULE_NAME_LEN: usize = 100;
+
+/// Request to create or replace a schedule.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ScheduleRequest {
+    /// Name, used in the titles of the run conversations
+    pub name: String,
+    /// Agent to run: one of the user's own, a community or a system agent
+    pub agent: String,
+    /// Message the agent is given on every run
+    pub prompt: String,
+    /// Cron expression in UTC, e.g. "0 7 * * 1-5" for 07:00 on weekdays
+    pub cron: String,
+    /// Whether the schedule runs (default: true)
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Result of running a schedule by hand.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScheduleRunResponse {
+    /// Conversation holding the prompt and the agent's answer
+    pub conversation_id: String,
+}
+
+/// Check a schedule request, returning its parsed cron expression.
+async fn validate(
+    state: &AppState,
+    user_id: &str,
+    payload: &ScheduleRequest,
+) -> Result<CronSchedule> {
+    let name = payload.name.trim();
+    if name.is_empty() || name.len() > MAX_SCHEDULE_NAME_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "Schedule name must be 1-{} characters",
+            MAX_SCHEDULE_NAME_LEN
+        )));
+    }
+    if payload.prompt.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "Prompt must not be empty".to_string(),
+        ));
+    }
+    // The router only dispatches to other agents
+    if payload.agent.eq_ignore_ascii_case("router")
+        || resolve_agent(state, user_id, payload.agent.clone())
+            .await
+            .is_err()
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Unknown agent: {}",
+            payload.agent
+        )));
+    }
+
+    let cron = CronSchedule::parse(&payload.cron)?;
+    let now = Utc::now();
+    if cron.next_after(now).is_none() {
+        return Err(AppError::InvalidInput(format!(
+            "Cron expression '{}' never matches",
+            payload.cron
+        )));
+    }
+    if cron
+        .min_interval(now)
+        .is_some_and(|interval| interval.num_seconds() < MIN_SCHEDULE_INTERVAL_SECS)
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Schedules may run at most once every {} seconds",
+            MIN_SCHEDULE_INTERVAL_SECS
+        )));
+    }
+    Ok(cron)
+}
+
+/// Load one of the user's schedules.
+async fn load_schedule(state: &AppState, user_id: &str, id: &str) -> Result<AgentSchedule> {
+    schedules::get_schedule(state.tenant_db.pool(), id, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Schedule '{}' not found", id)))
+}
+
+/// Apply a request to a schedule, computing its next run.
+fn apply(schedule: &mut AgentSchedule, payload: ScheduleRequest, cron: &CronSchedule) {
+    let now = Utc::now();
+    schedule.name = payload.name.trim().to_string();
+    schedule.agent = payload.agent;
+    schedule.prompt = payload.prompt;
+    schedule.cron = payload.cron.trim().to_string();
+    schedule.enabled = payload.enabled;
+    schedule.next_run_at = payload
+        .enabled
+        .then(|| cron.next_after(now))
+        .flatten()
+        .map(|next| next.timestamp());
+    schedule.updated_at = now.timestamp();
+}
+
+/// List the user's schedules.
+#[utoipa::path(
+    get,
+    path = "/api/schedules",
+    responses(
+        (status = 200, description = "Schedules", body = Vec<AgentSchedule>),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "schedules",
+    security(("bearer" = []))
+)]
+pub async fn list_schedules(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Vec<AgentSchedule>>> {
+    Ok(Json(
+        schedules::list_schedules(state.tenant_db.pool(), &claims.sub).await?,
+    ))
+}
+
+/// Schedule an agent run.
+#[utoipa::path(
+    post,
+    path = "/api/schedules",
+    request_body = ScheduleRequest,
+    responses(
+        (status = 201, description = "Schedule created", body = AgentSchedule),
+        (status = 400, description = "Unknown agent, invalid cron expression, runs too often, or too many schedules"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "schedules",
+    security(("bearer" = []))
+)]
+pub async fn create_schedule(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Json(payload): Json<ScheduleRequest>,
+) -> Result<(StatusCode, Json<AgentSchedule>)> {
+    let cron = validate(&state, &claims.sub, &payload).await?;
+    if schedules::count_schedules(state.tenant_db.pool(), &claims.sub).await?
+        >= MAX_SCHEDULES_PER_USER
+    {
+        return Err(AppError::InvalidInput(format!(
+            "A user may have at most {} schedules",
+            MAX_SCHEDULES_PER_USER
+        )));
+    }
+
+    let now = Utc::now().timestamp();
+    let mut schedule = AgentSchedule {
+        id: Uuid::new_v4().to_string(),
+        user_id: claims.sub,
+        name: String::new(),
+        agent: String::new(),
+        prompt: String::new(),
+        cron: String::new(),
+        enabled: true,
+        next_run_at: None,
+        last_run_at: None,
+        last_status: None,
+        last_conversation_id: None,
+        last_error: None,
+        created_at: now,
+        updated_at: now,
+    };
+    apply(&mut schedule, payload, &cron);
+    schedules::insert_schedule(state.tenant_db.pool(), &schedule).await?;
+
+    Ok((StatusCode::CREATED, Json(schedule)))
+}
+
+/// Get one of the user's schedules, with the outcome of its latest run.
+#[utoipa::path(
+    get,
+    path = "/api/schedules/{id}",
+    params(("id" = String, Path, description = "Schedule ID")),
+    responses(
+        (status = 200, description = "Schedule", body = AgentSchedule),
+        (status = 404, description = "Schedule not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "schedules",
+    security(("bearer" = []))
+)]
+pub async fn get_schedule(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<AgentSchedule>> {
+    Ok(Json(load_schedule(&state, &claims.sub, &id).await?))
+}
+
+/// Replace one of the user's schedules.
+///
+/// The next run is computed from the new cron expression.
+#[utoipa::path(
+    put,
+    path = "/api/schedules/{id}",
+    params(("id" = String, Path, description = "Schedule ID")),
+    request_body = ScheduleRequest,
+    responses(
+        (status = 200, description = "Schedule updated", body = AgentSchedule),
+        (status = 400, description = "Unknown agent, invalid cron expression, or runs too often"),
+        (status = 404, description = "Schedule not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "schedules",
+    security(("bearer" = []))
+)]
+pub async fn update_schedule(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<String>,
+    Json(payload): Json<ScheduleRequest>,
+) -> Result<Json<AgentSchedule>> {
+    let mut schedule = load_schedule(&state, &claims.sub, &id).await?;
+    let cron = validate(&state, &claims.sub, &payload).await?;
+    apply(&mut schedule, payload, &cron);
+    schedules::update_schedule(state.tenant_db.pool(), &schedule).await?;
+    Ok(Json(schedule))
+}
+
+/// Delete one of the user's schedules.
+///
+/// Conversations from earlier runs are kept.
+#[utoipa::path(
+    delete,
+    path = "/api/schedules/{id}",
+    params(("id" = String, Path, description = "Schedule ID")),
+    responses(
+        (status = 204, description = "Schedule deleted"),
+        (status = 404, description = "Schedule not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "schedules",
+    security(("bearer" = []))
+)]
+pub async fn delete_schedule(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    if !schedules::delete_schedule(state.tenant_db.pool(), &id, &claims.sub).await? {
+        return Err(AppError::NotFound(format!("Schedule '{}' not found", id)));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Run one of the user's schedules now.
+///
+/// Waits for the agent's answer. The run is stored and recorded like a
+/// scheduled one; the next scheduled run is unchanged.
+#[utoipa::path(
+    post,
+    path = "/api/schedules/{id}/run",
+    params(("id" = String, Path, description = "Schedule ID")),
+    responses(
+        (status = 200, description = "Run completed", body = ScheduleRunResponse),
+        (status = 404, description = "Schedule not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 503, description = "Server in maintenance mode")
+    ),
+    tag = "schedules",
+    security(("bearer" = []))
+)]
+pub async fn run_schedule(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<ScheduleRunResponse>> {
+    let schedule = load_schedule(&state, &claims.sub, &id).await?;
+    let started = Utc::now();
+    let outcome = scheduler::run_agent(
+        &state,
+        &schedule.user_id,
+        &schedule.agent,
+        &schedule.prompt,
+        &scheduler::run_title(&schedule.name, started),
+    )
+    .await;
+    schedules::record_run(
+        state.tenant_db.pool(),
+        &schedule.id,
+        started.timestamp(),
+        &outcome,
+    )
+    .await?;
+
+    Ok(Json(ScheduleRunResponse {
+        conversation_id: outcome?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(enabled: bool) -> ScheduleRequest {
+        ScheduleRequest {
+            name: " Morning digest ".to_string(),
+            agent: "research".to_string(),
+            prompt: "Summarize the documents ingested yesterday.".to_string(),
+            cron: "0 7 * * *".to_string(),
+            enabled,
+        }
+    }
+
+    #[test]
+    fn test_apply_computes_next_run() {
+        let cron = CronSchedule::parse("0 7 * * *").unwrap();
+        let mut schedule = AgentSchedule {
+            id: "s1".to_string(),
+            user_id: "u1".to_string(),
+            name: String::new(),
+            agent: String::new(),
+            prompt: String::new(),
+            cron: String::new(),
+            enabled: true,
+            next_run_at: None,
+            last_run_at: None,
+            last_status: None,
+            last_conversation_id: None,
+            last_error: None,
+            created_at: 0,
+            updated_at: 0,
+        };
+
+        apply(&mut schedule, request(true), &cron);
+        assert_eq!(schedule.name, "Morning digest");
+        let next = schedule.next_run_at.unwrap();
+        assert!(next > Utc::now().timestamp());
+        assert_eq!(next % 86_400, 7 * 3600);
+
+        // Disabled schedules have no next run
+        apply(&mut schedule, request(false), &cron);
+        assert!(!schedule.enabled);
+        assert_eq!(schedule.next_run_at, None);
+    }
+}
@@ -0,0 +1,151 @@
+//! Signed-in devices.
+//!
+//! `GET /api/auth/sessions` lists the devices signed in to the user's
+//! account, one session per login, with when it logged in, when it last
+//! refreshed its tokens and its user agent and IP address.
+//! `DELETE /api/auth/sessions/{id}` revokes one: its refresh token stops
+//! working at once, and its access token at the latest when it expires
+//! (`[auth] jwt_access_expiry`).
+
+use crate::{
+    auth::middleware::AuthUser,
+    db::sessions::{Session, SessionClient, SessionStore},
+    types::{AppError, Result},
+    AppState,
+};
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, Path, State},
+    http::{header, request::Parts, StatusCode},
+    Json,
+};
+use chrono::Utc;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+impl<S> FromRequestParts<S> for SessionClient
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        Ok(SessionClient {
+            user_agent: parts
+                .headers
+                .get(header::USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            // Behind trusted proxies, the client's address (see
+            // `crate::middleware::client_ip`)
+            ip_address: parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_canonical().to_string()),
+        })
+    }
+}
+
+/// List the devices signed in to the user's account
+#[utoipa::path(
+    get,
+    path = "/api/auth/sessions",
+    responses(
+        (status = 200, description = "Live sessions, most recently used first", body = Vec<Session>),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "auth",
+    security(("bearer" = []))
+)]
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Vec<Session>>> {
+    let now = Utc::now().timestamp();
+    Ok(Json(state.sessions.list(&claims.sub, now).await?))
+}
+
+/// Sign a device out; its refresh token stops working at once
+#[utoipa::path(
+    delete,
+    path = "/api/auth/sessions/{id}",
+    params(("id" = String, Path, description = "Session ID")),
+    responses(
+        (status = 204, description = "Session revoked"),
+        (status = 404, description = "Session not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "auth",
+    security(("bearer" = []))
+)]
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    revoke(&state.sessions, &claims.sub, &id).await
+}
+
+/// Revoke one of `user_id`'s sessions; other users' sessions are not found
+async fn revoke(sessions: &SessionStore, user_id: &str, id: &str) -> Result<StatusCode> {
+    if !sessions.revoke(id, user_id).await? {
+        return Err(AppError::NotFound(format!("Session {} not found", id)));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// These tests need PostgreSQL with the ARES schema at `DATABASE_URL`; run
+// them with `cargo test -- --ignored`.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PostgresClient;
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_revoking_another_users_session_is_not_found() {
+        let db = PostgresClient::new_memory().await.unwrap();
+        let (alice, bob) = (
+            uuid::Uuid::new_v4().to_string(),
+            uuid::Uuid::new_v4().to_string(),
+        );
+        for user in [&alice, &bob] {
+            db.create_user(user, &format!("{}@example.com", user), "hash", "Test User")
+                .await
+                .unwrap();
+        }
+        let sessions = SessionStore::Postgres(db.pool);
+        let now = Utc::now().timestamp();
+        let id = uuid::Uuid::new_v4().to_string();
+        let token_hash = uuid::Uuid::new_v4().to_string();
+        sessions
+            .create(
+                &id,
+                &alice,
+                &token_hash,
+                now + 3600,
+                &SessionClient::default(),
+                now,
+            )
+            .await
+            .unwrap();
+
+        let err = revoke(&sessions, &bob, &id).await.unwrap_err();
+        assert!(matches!(err, AppError::NotFound(_)));
+        assert_eq!(
+            sessions.user_of(&token_hash, now).await.unwrap(),
+            Some(alice.clone())
+        );
+
+        assert_eq!(
+            revoke(&sessions, &alice, &id).await.unwrap(),
+            StatusCode::NO_CONTENT
+        );
+        assert!(matches!(
+            revoke(&sessions, &alice, &id).await.unwrap_err(),
+            AppError::NotFound(_)
+        ));
+    }
+}
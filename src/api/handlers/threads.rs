@@ -0,0 +1,1248 @@
+//! Assistants-style threads, messages and runs.
+//!
+//! A compatibility layer for clients built against the OpenAI Assistants
+//! API, mapped onto ARES conversations and agent runs:
+//!
+//! - A thread is a conversation, with the same ID.
+//! - An assistant is an agent, named by `assistant_id`; without one the
+//!   message is routed as in `POST /api/chat`.
+//! - Messages added to a thread wait until a run answers them. The run
+//!   sends them to the agent as one chat message, exactly as `POST
+//!   /api/chat` would, which stores the exchange in the conversation.
+//! - Runs execute in the background: poll `GET
+//!   /api/threads/{id}/runs/{run_id}`, or create the run with `"stream":
+//!   true` to receive its events. The answer arrives as a single message
+//!   delta once the agent has finished.
+//! - A run whose tool calls need approval (see [`crate::agents::approval`])
+//!   requires action until the user decides with `POST /api/approvals/{id}`.
+//!
+//! Only text messages are supported; the Assistants API's instructions,
+//! tools, files and metadata are not.
+
+use crate::{
+    api::{
+        encryption::ConversationSecret,
+        handlers::{
+            chat::answer,
+            conversations::{ensure_conversation, remove_conversation, restore_archived},
+            user_agents::resolve_agent,
+        },
+        maintenance,
+        workspace::ActiveWorkspace,
+    },
+    auth::middleware::AuthUser,
+    db::{
+        postgres::Conversation,
+        threads::{self, PendingMessage, ThreadRun},
+    },
+    llm::cancellation::CancellationToken,
+    models::TenantContext,
+    types::{AgentType, AppError, ChatRequest, ChatResponse, Claims, MessageRole, Result},
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Messages or runs listed by default
+const DEFAULT_PAGE: usize = 20;
+
+/// Most messages or runs listed at once
+const MAX_PAGE: usize = 100;
+
+/// A thread: an ARES conversation.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Thread {
+    /// Thread ID, the conversation's
+    pub id: String,
+    /// Always `thread`
+    pub object: &'static str,
+    /// When the thread was created (Unix timestamp)
+    pub created_at: i64,
+}
+
+impl From<&Conversation> for Thread {
+    fn from(conversation: &Conversation) -> Self {
+        Thread {
+            id: conversation.id.clone(),
+            object: "thread",
+            created_at: conversation.created_at.parse().unwrap_or_default(),
+        }
+    }
+}
+
+/// Confirmation that a thread was deleted.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ThreadDeleted {
+    /// ID of the deleted thread
+    pub id: String,
+    /// Always `thread.deleted`
+    pub object: &'static str,
+    /// Always true
+    pub deleted: bool,
+}
+
+/// Content of a message to add: text, or a list of text parts.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum MessageInput {
+    /// Plain text
+    Text(String),
+    /// Text parts, joined by blank lines
+    Parts(Vec<TextPart>),
+}
+
+/// A text part of a message.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TextPart {
+    /// Always `text`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The text
+    pub text: String,
+}
+
+impl MessageInput {
+    fn text(self) -> Result<String> {
+        let text = match self {
+            MessageInput::Text(text) => text,
+            MessageInput::Parts(parts) => {
+                if let Some(part) = parts.iter().find(|part| part.kind != "text") {
+                    return Err(AppError::InvalidInput(format!(
+                        "Message content of type '{}' is not supported",
+                        part.kind
+                    )));
+                }
+                parts
+                    .into_iter()
+                    .map(|part| part.text)
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            }
+        };
+        if text.trim().is_empty() {
+            return Err(AppError::InvalidInput(
+                "Message content is empty".to_string(),
+            ));
+        }
+        Ok(text)
+    }
+}
+
+/// Request to add a message to a thread.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateMessageRequest {
+    /// Always `user`; answers come from runs
+    pub role: String,
+    /// Message content
+    pub content: MessageInput,
+}
+
+impl CreateMessageRequest {
+    fn text(self) -> Result<String> {
+        if self.role != "user" {
+            return Err(AppError::InvalidInput(
+                "Only user messages can be added to a thread".to_string(),
+            ));
+        }
+        self.content.text()
+    }
+}
+
+/// Request to create a thread.
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct CreateThreadRequest {
+    /// Messages to start the thread with
+    #[serde(default)]
+    pub messages: Vec<CreateMessageRequest>,
+}
+
+/// A message of a thread.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ThreadMessage {
+    /// Message ID
+    pub id: String,
+    /// Always `thread.message`
+    pub object: &'static str,
+    /// When the message was added (Unix timestamp)
+    pub created_at: i64,
+    /// Thread the message belongs to
+    pub thread_id: String,
+    /// `completed`, or `in_progress` while a streamed answer is sent
+    pub status: &'static str,
+    /// `user` or `assistant`
+    pub role: &'static str,
+    /// The message's text
+    pub content: Vec<MessageContent>,
+    /// Agent that wrote an answer, if a run named one
+    pub assistant_id: Option<String>,
+    /// Run that wrote an answer
+    pub run_id: Option<String>,
+}
+
+/// A text content part of a message.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MessageContent {
+    /// Always `text`
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// The text
+    pub text: MessageText,
+}
+
+/// Text of a message.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MessageText {
+    /// The text
+    pub value: String,
+    /// Always empty
+    pub annotations: Vec<serde_json::Value>,
+}
+
+impl ThreadMessage {
+    fn new(id: String, thread_id: &str, role: &'static str, text: String, created_at: i64) -> Self {
+        ThreadMessage {
+            id,
+            object: "thread.message",
+            created_at,
+            thread_id: thread_id.to_string(),
+            status: "completed",
+            role,
+            content: vec![MessageContent {
+                kind: "text",
+                text: MessageText {
+                    value: text,
+                    annotations: Vec::new(),
+                },
+            }],
+            assistant_id: None,
+            run_id: None,
+        }
+    }
+}
+
+/// A page of a thread's messages.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessageList {
+    /// Always `list`
+    pub object: &'static str,
+    /// The messages
+    pub data: Vec<ThreadMessage>,
+    /// ID of the first message of the page
+    pub first_id: Option<String>,
+    /// ID of the last message of the page, for `after`
+    pub last_id: Option<String>,
+    /// Whether more messages follow the page
+    pub has_more: bool,
+}
+
+/// Request to run an agent on a thread.
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct CreateRunRequest {
+    /// Agent to answer; the message is routed when omitted
+    #[serde(default)]
+    pub assistant_id: Option<String>,
+    /// Messages to add to the thread before the run
+    #[serde(default)]
+    pub additional_messages: Vec<CreateMessageRequest>,
+    /// Stream the run's events instead of returning the queued run
+    #[serde(default)]
+    pub stream: bool,
+}
+
+/// Request to create a thread and run an agent on it.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateThreadAndRunRequest {
+    /// Agent to answer; the message is routed when omitted
+    #[serde(default)]
+    pub assistant_id: Option<String>,
+    /// The thread to create
+    #[serde(default)]
+    pub thread: CreateThreadRequest,
+    /// Stream the run's events instead of returning the queued run
+    #[serde(default)]
+    pub stream: bool,
+}
+
+/// A run of an agent on a thread.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Run {
+    /// Run ID
+    pub id: String,
+    /// Always `thread.run`
+    pub object: &'static str,
+    /// When the run was created (Unix timestamp)
+    pub created_at: i64,
+    /// Thread the run answers in
+    pub thread_id: String,
+    /// Agent answering; `None` when the message is routed
+    pub assistant_id: Option<String>,
+    /// `queued`, `in_progress`, `requires_action`, `cancelling`,
+    /// `cancelled`, `failed`, `completed` or `incomplete`
+    pub status: String,
+    /// What the run waits for when it requires action
+    pub required_action: Option<RequiredAction>,
+    /// Why the run failed
+    pub last_error: Option<RunError>,
+    /// Why the run is incomplete
+    pub incomplete_details: Option<IncompleteDetails>,
+    /// When the agent started answering (Unix timestamp)
+    pub started_at: Option<i64>,
+    /// When the run was cancelled (Unix timestamp)
+    pub cancelled_at: Option<i64>,
+    /// When the run failed (Unix timestamp)
+    pub failed_at: Option<i64>,
+    /// When the run completed (Unix timestamp)
+    pub completed_at: Option<i64>,
+    /// Estimated tokens, once the agent has answered
+    pub usage: Option<RunTokens>,
+}
+
+/// Tool calls a run waits for the user to approve.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RequiredAction {
+    /// Always `approve_tool_calls`
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Approval to decide with `POST /api/approvals/{id}`
+    pub approval_id: String,
+}
+
+/// Why a run failed.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RunError {
+    /// Always `server_error`
+    pub code: &'static str,
+    /// The error
+    pub message: String,
+}
+
+/// Why a run is incomplete.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct IncompleteDetails {
+    /// Run limit that cut the answer short, e.g. `max_tool_calls`
+    pub reason: String,
+}
+
+/// Estimated tokens of a run.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RunTokens {
+    /// Input tokens, including the conversation history
+    pub prompt_tokens: i64,
+    /// Output tokens
+    pub completion_tokens: i64,
+    /// Input and output tokens
+    pub total_tokens: i64,
+}
+
+impl From<ThreadRun> for Run {
+    fn from(run: ThreadRun) -> Self {
+        let ended_as = |status: &str| run.completed_at.filter(|_| run.status == status);
+        Run {
+            object: "thread.run",
+            created_at: run.created_at,
+            required_action: run
+                .approval_id
+                .clone()
+                .filter(|_| run.status == threads::REQUIRES_ACTION)
+                .map(|approval_id| RequiredAction {
+                    kind: "approve_tool_calls",
+                    approval_id,
+                }),
+            last_error: run.last_error.clone().map(|message| RunError {
+                code: "server_error",
+                message,
+            }),
+            incomplete_details: run
+                .incomplete_reason
+                .clone()
+                .map(|reason| IncompleteDetails { reason }),
+            started_at: run.started_at,
+            cancelled_at: ended_as(threads::CANCELLED),
+            failed_at: ended_as(threads::FAILED),
+            completed_at: ended_as(threads::COMPLETED).or(ended_as(threads::INCOMPLETE)),
+            usage: run.prompt_tokens.zip(run.completion_tokens).map(
+                |(prompt_tokens, completion_tokens)| RunTokens {
+                    prompt_tokens,
+                    completion_tokens,
+                    total_tokens: prompt_tokens + completion_tokens,
+                },
+            ),
+            id: run.id,
+            thread_id: run.thread_id,
+            assistant_id: run.assistant_id,
+            status: run.status,
+        }
+    }
+}
+
+/// A page of a thread's runs.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RunList {
+    /// Always `list`
+    pub object: &'static str,
+    /// The runs
+    pub data: Vec<Run>,
+    /// ID of the first run of the page
+    pub first_id: Option<String>,
+    /// ID of the last run of the page, for `after`
+    pub last_id: Option<String>,
+    /// Whether more runs follow the page
+    pub has_more: bool,
+}
+
+/// Pagination of listed messages and runs.
+#[derive(Debug, Deserialize)]
+pub struct ListQuery {
+    /// Most items returned (default: 20, at most 100)
+    pub limit: Option<usize>,
+    /// `asc` or `desc` (default) by creation time
+    pub order: Option<String>,
+    /// Only list items after this one
+    pub after: Option<String>,
+    /// Only list items before this one
+    pub before: Option<String>,
+}
+
+impl ListQuery {
+    /// Select the page of `items`, oldest first, returning it and whether
+    /// more items follow it
+    fn page<T>(&self, mut items: Vec<T>, id: impl Fn(&T) -> &str) -> Result<(Vec<T>, bool)> {
+        match self.order.as_deref() {
+            None | Some("desc") => items.reverse(),
+            Some("asc") => {}
+            Some(order) => {
+                return Err(AppError::InvalidInput(format!(
+                    "Invalid order '{}': expected 'asc' or 'desc'",
+                    order
+                )))
+            }
+        }
+        let position = |cursor: &str| {
+            items
+                .iter()
+                .position(|item| id(item) == cursor)
+                .ok_or_else(|| AppError::InvalidInput(format!("Unknown cursor '{}'", cursor)))
+        };
+        let end = match &self.before {
+            Some(before) => position(before)?,
+            None => items.len(),
+        };
+        let start = match &self.after {
+            Some(after) => position(after)? + 1,
+            None => 0,
+        };
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE).clamp(1, MAX_PAGE);
+        let has_more = end.saturating_sub(start) > limit;
+        let page = items
+            .into_iter()
+            .take(end)
+            .skip(start)
+            .take(limit)
+            .collect();
+        Ok((page, has_more))
+    }
+}
+
+/// One of the user's threads, from the space they act in
+async fn user_thread(
+    state: &AppState,
+    id: &str,
+    user_id: &str,
+    workspace: &ActiveWorkspace,
+) -> Result<Conversation> {
+    let conversation = state
+        .db
+        .get_conversation(id)
+        .await
+        .map_err(|_| AppError::NotFound(format!("Thread {} not found", id)))?;
+    if !conversation.belongs_to(user_id, workspace.id()) {
+        return Err(AppError::Auth(
+            "Not authorized to access this thread".to_string(),
+        ));
+    }
+    // The Assistants API has no way to send a conversation secret
+    if conversation.encrypted {
+        return Err(AppError::Forbidden(format!(
+            "Thread {} is an encrypted conversation; use /api/conversations",
+            id
+        )));
+    }
+    Ok(conversation)
+}
+
+/// Add messages to a thread, to be answered by its next run
+async fn add_messages(
+    state: &AppState,
+    thread_id: &str,
+    messages: Vec<String>,
+) -> Result<Vec<ThreadMessage>> {
+    let mut added = Vec::new();
+    for text in messages {
+        let message = PendingMessage {
+            id: Uuid::new_v4().to_string(),
+            thread_id: thread_id.to_string(),
+            content: text,
+            created_at: Utc::now().timestamp(),
+        };
+        threads::add_message(state.tenant_db.pool(), &message).await?;
+        added.push(ThreadMessage::new(
+            message.id,
+            thread_id,
+            "user",
+            message.content,
+            message.created_at,
+        ));
+    }
+    Ok(added)
+}
+
+/// A thread's messages, oldest first: those stored in the conversation,
+/// then those waiting for a run
+async fn thread_messages(state: &AppState, thread_id: &str) -> Result<Vec<ThreadMessage>> {
+    restore_archived(state, thread_id).await?;
+    let pool = state.tenant_db.pool();
+    let runs: HashMap<String, ThreadRun> = threads::list_runs(pool, thread_id)
+        .await?
+        .into_iter()
+        .filter_map(|run| Some((run.message_id.clone()?, run)))
+        .collect();
+
+    let mut messages = Vec::new();
+    for message in state.db.get_conversation_history(thread_id).await? {
+        let role = match message.role {
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+            _ => continue,
+        };
+        let mut stored = ThreadMessage::new(
+            message.id,
+            thread_id,
+            role,
+            message.content,
+            message.timestamp.timestamp(),
+        );
+        if let Some(run) = runs.get(&stored.id) {
+            stored.run_id = Some(run.id.clone());
+            stored.assistant_id = run.assistant_id.clone();
+        }
+        messages.push(stored);
+    }
+    for pending in threads::pending_messages(pool, thread_id).await? {
+        messages.push(ThreadMessage::new(
+            pending.id,
+            thread_id,
+            "user",
+            pending.content,
+            pending.created_at,
+        ));
+    }
+    Ok(messages)
+}
+
+/// Create a thread.
+#[utoipa::path(
+    post,
+    path = "/api/threads",
+    request_body = CreateThreadRequest,
+    responses(
+        (status = 200, description = "Thread created", body = Thread),
+        (status = 400, description = "Invalid message"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "threads",
+    security(("bearer" = []))
+)]
+pub async fn create_thread(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Json(payload): Json<CreateThreadRequest>,
+) -> Result<Json<Thread>> {
+    Ok(Json(
+        new_thread(&state, &claims.sub, &workspace, payload).await?,
+    ))
+}
+
+/// Create a thread with its first messages
+async fn new_thread(
+    state: &AppState,
+    user_id: &str,
+    workspace: &ActiveWorkspace,
+    payload: CreateThreadRequest,
+) -> Result<Thread> {
+    let messages = payload
+        .messages
+        .into_iter()
+        .map(CreateMessageRequest::text)
+        .collect::<Result<Vec<_>>>()?;
+    let id = Uuid::new_v4().to_string();
+    ensure_conversation(state, &id, user_id, workspace, None).await?;
+    add_messages(state, &id, messages).await?;
+    Ok(Thread::from(&state.db.get_conversation(&id).await?))
+}
+
+/// Get a thread.
+#[utoipa::path(
+    get,
+    path = "/api/threads/{id}",
+    params(("id" = String, Path, description = "Thread ID")),
+    responses(
+        (status = 200, description = "Thread", body = Thread),
+        (status = 404, description = "Thread not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "threads",
+    security(("bearer" = []))
+)]
+pub async fn get_thread(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+) -> Result<Json<Thread>> {
+    let conversation = user_thread(&state, &id, &claims.sub, &workspace).await?;
+    Ok(Json(Thread::from(&conversation)))
+}
+
+/// Delete a thread and its conversation.
+#[utoipa::path(
+    delete,
+    path = "/api/threads/{id}",
+    params(("id" = String, Path, description = "Thread ID")),
+    responses(
+        (status = 200, description = "Thread deleted", body = ThreadDeleted),
+        (status = 404, description = "Thread not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "threads",
+    security(("bearer" = []))
+)]
+pub async fn delete_thread(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+) -> Result<Json<ThreadDeleted>> {
+    user_thread(&state, &id, &claims.sub, &workspace).await?;
+    remove_conversation(&state, &id).await?;
+    Ok(Json(ThreadDeleted {
+        id,
+        object: "thread.deleted",
+        deleted: true,
+    }))
+}
+
+/// Add a user message to a thread, to be answered by its next run.
+#[utoipa::path(
+    post,
+    path = "/api/threads/{id}/messages",
+    params(("id" = String, Path, description = "Thread ID")),
+    request_body = CreateMessageRequest,
+    responses(
+        (status = 200, description = "Message added", body = ThreadMessage),
+        (status = 400, description = "Not a user message, or no text"),
+        (status = 404, description = "Thread not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "threads",
+    security(("bearer" = []))
+)]
+pub async fn create_message(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+    Json(payload): Json<CreateMessageRequest>,
+) -> Result<Json<ThreadMessage>> {
+    user_thread(&state, &id, &claims.sub, &workspace).await?;
+    let mut added = add_messages(&state, &id, vec![payload.text()?]).await?;
+    Ok(Json(added.remove(0)))
+}
+
+/// List a thread's messages, newest first unless `order=asc`.
+#[utoipa::path(
+    get,
+    path = "/api/threads/{id}/messages",
+    params(
+        ("id" = String, Path, description = "Thread ID"),
+        ("limit" = Option<usize>, Query, description = "Most messages returned (default: 20, at most 100)"),
+        ("order" = Option<String>, Query, description = "`asc` or `desc` (default) by creation time"),
+        ("after" = Option<String>, Query, description = "Only list messages after this one"),
+        ("before" = Option<String>, Query, description = "Only list messages before this one")
+    ),
+    responses(
+        (status = 200, description = "Messages", body = MessageList),
+        (status = 400, description = "Invalid order or cursor"),
+        (status = 404, description = "Thread not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "threads",
+    security(("bearer" = []))
+)]
+pub async fn list_messages(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+    Query(query): Query<ListQuery>,
+) -> Result<Json<MessageList>> {
+    user_thread(&state, &id, &claims.sub, &workspace).await?;
+    let messages = thread_messages(&state, &id).await?;
+    let (data, has_more) = query.page(messages, |message| &message.id)?;
+    Ok(Json(MessageList {
+        object: "list",
+        first_id: data.first().map(|message| message.id.clone()),
+        last_id: data.last().map(|message| message.id.clone()),
+        data,
+        has_more,
+    }))
+}
+
+/// Run an agent on a thread's new messages.
+///
+/// Returns the queued run, or with `"stream": true` a `text/event-stream`
+/// of its events: `thread.run.created`, `thread.run.queued`,
+/// `thread.run.in_progress`, then `thread.message.created`,
+/// `thread.message.delta` and `thread.message.completed` for the answer,
+/// the run's final status (e.g. `thread.run.completed`) and `done`.
+#[utoipa::path(
+    post,
+    path = "/api/threads/{id}/runs",
+    params(("id" = String, Path, description = "Thread ID")),
+    request_body = CreateRunRequest,
+    responses(
+        (status = 200, description = "Run queued, or its events", body = Run),
+        (status = 400, description = "No new messages, or a run is already active"),
+        (status = 404, description = "Thread or agent not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 503, description = "Server in maintenance mode")
+    ),
+    tag = "threads",
+    security(("bearer" = []))
+)]
+pub async fn create_run(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    tenant_ctx: Option<Extension<TenantContext>>,
+    Path(id): Path<String>,
+    Json(payload): Json<CreateRunRequest>,
+) -> Result<Response> {
+    maintenance::ensure_available(&state)?;
+    user_thread(&state, &id, &claims.sub, &workspace).await?;
+    let messages = payload
+        .additional_messages
+        .into_iter()
+        .map(CreateMessageRequest::text)
+        .collect::<Result<Vec<_>>>()?;
+
+    let tenant_id = tenant_ctx.map(|Extension(tc)| tc.tenant_id);
+    let (run, task) = start_run(
+        &state,
+        &claims,
+        &workspace,
+        tenant_id,
+        &id,
+        payload.assistant_id,
+        messages,
+    )
+    .await?;
+    Ok(match payload.stream {
+        true => run_events(state, run, task).into_response(),
+        false => Json(Run::from(run)).into_response(),
+    })
+}
+
+/// Create a thread and run an agent on it.
+#[utoipa::path(
+    post,
+    path = "/api/threads/runs",
+    request_body = CreateThreadAndRunRequest,
+    responses(
+        (status = 200, description = "Run queued, or its events", body = Run),
+        (status = 400, description = "No messages, or an invalid one"),
+        (status = 404, description = "Agent not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 503, description = "Server in maintenance mode")
+    ),
+    tag = "threads",
+    security(("bearer" = []))
+)]
+pub async fn create_thread_and_run(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    tenant_ctx: Option<Extension<TenantContext>>,
+    Json(payload): Json<CreateThreadAndRunRequest>,
+) -> Result<Response> {
+    maintenance::ensure_available(&state)?;
+    if payload.thread.messages.is_empty() {
+        return Err(AppError::InvalidInput(
+            "The thread needs a message to answer".to_string(),
+        ));
+    }
+    check_assistant(&state, &workspace.owner(&claims.sub), &payload.assistant_id).await?;
+    let thread = new_thread(&state, &claims.sub, &workspace, payload.thread).await?;
+
+    let tenant_id = tenant_ctx.map(|Extension(tc)| tc.tenant_id);
+    let (run, task) = start_run(
+        &state,
+        &claims,
+        &workspace,
+        tenant_id,
+        &thread.id,
+        payload.assistant_id,
+        Vec::new(),
+    )
+    .await?;
+    Ok(match payload.stream {
+        true => run_events(state, run, task).into_response(),
+        false => Json(Run::from(run)).into_response(),
+    })
+}
+
+/// List a thread's runs, newest first unless `order=asc`.
+#[utoipa::path(
+    get,
+    path = "/api/threads/{id}/runs",
+    params(
+        ("id" = String, Path, description = "Thread ID"),
+        ("limit" = Option<usize>, Query, description = "Most runs returned (default: 20, at most 100)"),
+        ("order" = Option<String>, Query, description = "`asc` or `desc` (default) by creation time"),
+        ("after" = Option<String>, Query, description = "Only list runs after this one"),
+        ("before" = Option<String>, Query, description = "Only list runs before this one")
+    ),
+    responses(
+        (status = 200, description = "Runs", body = RunList),
+        (status = 400, description = "Invalid order or cursor"),
+        (status = 404, description = "Thread not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "threads",
+    security(("bearer" = []))
+)]
+pub async fn list_runs(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(id): Path<String>,
+    Query(query): Query<ListQuery>,
+) -> Result<Json<RunList>> {
+    user_thread(&state, &id, &claims.sub, &workspace).await?;
+    let runs = threads::list_runs(state.tenant_db.pool(), &id).await?;
+    let (runs, has_more) = query.page(runs, |run| &run.id)?;
+    let data: Vec<Run> = runs.into_iter().map(Run::from).collect();
+    Ok(Json(RunList {
+        object: "list",
+        first_id: data.first().map(|run| run.id.clone()),
+        last_id: data.last().map(|run| run.id.clone()),
+        data,
+        has_more,
+    }))
+}
+
+/// Get a run, e.g. to poll its status.
+#[utoipa::path(
+    get,
+    path = "/api/threads/{id}/runs/{run_id}",
+    params(
+        ("id" = String, Path, description = "Thread ID"),
+        ("run_id" = String, Path, description = "Run ID")
+    ),
+    responses(
+        (status = 200, description = "Run", body = Run),
+        (status = 404, description = "Thread or run not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "threads",
+    security(("bearer" = []))
+)]
+pub async fn get_run(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path((id, run_id)): Path<(String, String)>,
+) -> Result<Json<Run>> {
+    user_thread(&state, &id, &claims.sub, &workspace).await?;
+    Ok(Json(Run::from(load_run(&state, &id, &run_id).await?)))
+}
+
+/// Cancel a running run.
+///
+/// The run is `cancelling` until its agent stops, then `cancelled`. The
+/// thread's messages it was answering are kept in the conversation.
+#[utoipa::path(
+    post,
+    path = "/api/threads/{id}/runs/{run_id}/cancel",
+    params(
+        ("id" = String, Path, description = "Thread ID"),
+        ("run_id" = String, Path, description = "Run ID")
+    ),
+    responses(
+        (status = 200, description = "Run cancelling", body = Run),
+        (status = 400, description = "Run is not running"),
+        (status = 404, description = "Thread or run not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "threads",
+    security(("bearer" = []))
+)]
+pub async fn cancel_run(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path((id, run_id)): Path<(String, String)>,
+) -> Result<Json<Run>> {
+    user_thread(&state, &id, &claims.sub, &workspace).await?;
+    let run = load_run(&state, &id, &run_id).await?;
+    if !threads::request_cancel(state.tenant_db.pool(), &run.id).await? {
+        return Err(AppError::InvalidInput(format!(
+            "Run {} is {} and can't be cancelled",
+            run.id, run.status
+        )));
+    }
+    // The run's own task records it cancelled once the agent has stopped
+    state.generations.stop(&id, &claims.sub);
+    Ok(Json(Run::from(load_run(&state, &id, &run_id).await?)))
+}
+
+/// One of a thread's runs
+async fn load_run(state: &AppState, thread_id: &str, id: &str) -> Result<ThreadRun> {
+    threads::get_run(state.tenant_db.pool(), id, thread_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Run {} not found", id)))
+}
+
+/// Check that a run's agent exists; built-in agents and routing always do
+pub(crate) async fn check_assistant(
+    state: &AppState,
+    owner: &str,
+    assistant_id: &Option<String>,
+) -> Result<()> {
+    if let Some(name) = assistant_id {
+        if let AgentType::Custom(name) = AgentType::from_string(name) {
+            resolve_agent(state, owner, name).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Queue a run on a thread's new messages and start it in the background
+///
+/// The task resolves to the run in its final status.
+async fn start_run(
+    state: &AppState,
+    claims: &Claims,
+    workspace: &ActiveWorkspace,
+    tenant_id: Option<String>,
+    thread_id: &str,
+    assistant_id: Option<String>,
+    messages: Vec<String>,
+) -> Result<(ThreadRun, JoinHandle<ThreadRun>)> {
+    let pool = state.tenant_db.pool();
+    if let Some(active) = threads::active_run(pool, thread_id).await? {
+        return Err(AppError::InvalidInput(format!(
+            "Thread {} already has an active run {}",
+            thread_id, active.id
+        )));
+    }
+    check_assistant(state, &workspace.owner(&claims.sub), &assistant_id).await?;
+    add_messages(state, thread_id, messages).await?;
+    let pending = threads::pending_messages(pool, thread_id).await?;
+    if pending.is_empty() {
+        return Err(AppError::InvalidInput(format!(
+            "Thread {} has no new messages to answer",
+            thread_id
+        )));
+    }
+
+    let run = ThreadRun::new(
+        &Uuid::new_v4().to_string(),
+        thread_id,
+        &claims.sub,
+        assistant_id,
+        Utc::now().timestamp(),
+    );
+    threads::insert_run(pool, &run).await?;
+    let task = tokio::spawn(execute_run(
+        state.clone(),
+        claims.clone(),
+        workspace.clone(),
+        tenant_id,
+        run.clone(),
+        pending,
+    ));
+    Ok((run, task))
+}
+
+/// Answer a thread's pending messages, recording the run's outcome
+async fn execute_run(
+    state: AppState,
+    claims: Claims,
+    workspace: ActiveWorkspace,
+    tenant_id: Option<String>,
+    mut run: ThreadRun,
+    pending: Vec<PendingMessage>,
+) -> ThreadRun {
+    let pool = state.tenant_db.pool();
+    let cancellation = CancellationToken::new();
+    // Registered before anything else so the run can be cancelled right away
+    let _generation = state
+        .generations
+        .register(&run.thread_id, &claims.sub, cancellation.clone());
+    run.status = threads::IN_PROGRESS.to_string();
+    run.started_at = Some(Utc::now().timestamp());
+    save_run(pool, &run).await;
+
+    let payload = ChatRequest {
+        message: pending
+            .iter()
+            .map(|message| message.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        agent_type: run.assistant_id.as_deref().map(AgentType::from_string),
+        context_id: Some(run.thread_id.clone()),
+        seed: None,
+        persona: None,
+        file_ids: Vec::new(),
+        project_id: None,
+        parameters: Default::default(),
+    };
+    let ids: Vec<String> = pending.into_iter().map(|message| message.id).collect();
+    let now = Utc::now().timestamp();
+    match answer(
+        &state,
+        &claims,
+        &workspace,
+        &ConversationSecret::none(),
+        tenant_id,
+        payload,
+        cancellation,
+    )
+    .await
+    {
+        Ok(answer) => {
+            if let Some((input_tokens, output_tokens)) = answer.tokens {
+                run.prompt_tokens = Some(input_tokens.into());
+                run.completion_tokens = Some(output_tokens.into());
+            }
+            finish_run(&mut run, &answer.response, now);
+            discard_pending(pool, &run, &ids).await;
+        }
+        // A stopped run keeps the user's messages in the conversation
+        Err(AppError::Cancelled(_)) => {
+            run.status = threads::CANCELLED.to_string();
+            run.completed_at = Some(now);
+            discard_pending(pool, &run, &ids).await;
+        }
+        // Messages of a failed run wait for the next one
+        Err(e) => {
+            tracing::warn!("Run {} on thread {} failed: {}", run.id, run.thread_id, e);
+            run.status = threads::FAILED.to_string();
+            run.last_error = Some(e.to_string());
+            run.completed_at = Some(now);
+        }
+    }
+    save_run(pool, &run).await;
+    run
+}
+
+/// Record the answer of a run: stored, cut short, or waiting for approval
+fn finish_run(run: &mut ThreadRun, response: &ChatResponse, now: i64) {
+    if let Some(approval) = &response.approval {
+        run.status = threads::REQUIRES_ACTION.to_string();
+        run.approval_id = Some(approval.id.clone());
+        return;
+    }
+    run.message_id = response.message_id.clone();
+    run.completed_at = Some(now);
+    match &response.limit_exceeded {
+        Some(exceeded) => {
+            run.status = threads::INCOMPLETE.to_string();
+            run.incomplete_reason = serde_json::to_value(exceeded.limit)
+                .ok()
+                .and_then(|limit| limit.as_str().map(str::to_string));
+        }
+        None => run.status = threads::COMPLETED.to_string(),
+    }
+}
+
+/// Record the outcome of a thread's run resumed once the user decided on
+/// its tool calls with `POST /api/approvals/{id}`
+pub(crate) async fn approval_decided(
+    state: &AppState,
+    approval_id: &str,
+    response: &Result<ChatResponse>,
+) {
+    let pool = state.tenant_db.pool();
+    let mut run = match threads::run_awaiting(pool, approval_id).await {
+        Ok(Some(run)) => run,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("{}", e);
+            return;
+        }
+    };
+    let now = Utc::now().timestamp();
+    match response {
+        Ok(response) => {
+            run.approval_id = None;
+            finish_run(&mut run, response, now);
+        }
+        Err(e) => {
+            run.status = threads::FAILED.to_string();
+            run.last_error = Some(e.to_string());
+            run.completed_at = Some(now);
+        }
+    }
+    save_run(pool, &run).await;
+}
+
+/// Remove the pending messages a run stored in the conversation, logging failures
+async fn discard_pending(pool: &sqlx::PgPool, run: &ThreadRun, ids: &[String]) {
+    if let Err(e) = threads::remove_messages(pool, ids).await {
+        tracing::warn!(
+            "Failed to remove messages answered by run {}: {}",
+            run.id,
+            e
+        );
+    }
+}
+
+/// Save a run's status, logging failures
+async fn save_run(pool: &sqlx::PgPool, run: &ThreadRun) {
+    if let Err(e) = threads::update_run(pool, run).await {
+        tracing::warn!("Failed to save run {}: {}", run.id, e);
+    }
+}
+
+/// Server-sent events of a run, until it reaches its final status
+fn run_events(
+    state: AppState,
+    run: ThreadRun,
+    task: JoinHandle<ThreadRun>,
+) -> Sse<impl futures::Stream<Item = std::result::Result<Event, std::convert::Infallible>>> {
+    let stream = async_stream::stream! {
+        let queued = Run::from(run.clone());
+        yield Ok(run_event("thread.run.created", &queued));
+        yield Ok(run_event("thread.run.queued", &queued));
+        let mut started = queued.clone();
+        started.status = threads::IN_PROGRESS.to_string();
+        yield Ok(run_event("thread.run.in_progress", &started));
+
+        let run = match task.await {
+            Ok(run) => run,
+            Err(e) => {
+                let mut failed = run;
+                failed.status = threads::FAILED.to_string();
+                failed.last_error = Some(format!("Run task failed: {}", e));
+                failed
+            }
+        };
+        let answer = match &run.message_id {
+            Some(id) => thread_messages(&state, &run.thread_id)
+                .await
+                .ok()
+                .and_then(|messages| messages.into_iter().find(|message| &message.id == id)),
+            None => None,
+        };
+        if let Some(message) = answer {
+            let text = message
+                .content
+                .first()
+                .map(|content| content.text.value.clone())
+                .unwrap_or_default();
+            let created = ThreadMessage {
+                status: "in_progress",
+                content: Vec::new(),
+                ..message.clone()
+            };
+            yield Ok(run_event("thread.message.created", &created));
+            yield Ok(run_event("thread.message.in_progress", &created));
+            let delta = serde_json::json!({
+                "id": message.id,
+                "object": "thread.message.delta",
+                "delta": {
+                    "content": [{"index": 0, "type": "text", "text": {"value": text}}]
+                }
+            });
+            yield Ok(run_event("thread.message.delta", &delta));
+            yield Ok(run_event("thread.message.completed", &message));
+        }
+        let status = format!("thread.run.{}", run.status);
+        yield Ok(run_event(&status, &Run::from(run)));
+        yield Ok(Event::default().event("done").data("[DONE]"));
+    };
+
+    Sse::new(stream).keep_alive(
+        KeepAlive::new()
+            .interval(std::time::Duration::from_secs(15))
+            .text("keep-alive"),
+    )
+}
+
+fn run_event(name: &str, data: &impl Serialize) -> Event {
+    Event::default()
+        .event(name)
+        .data(serde_json::to_string(data).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(order: Option<&str>, after: Option<&str>, limit: Option<usize>) -> ListQuery {
+        ListQuery {
+            limit,
+            order: order.map(str::to_string),
+            after: after.map(str::to_string),
+            before: None,
+        }
+    }
+
+    #[test]
+    fn test_pages_are_newest_first_by_default() {
+        let items = vec!["a", "b", "c", "d"];
+        let (page, has_more) = query(None, None, Some(2))
+            .page(items.clone(), |i| i)
+            .unwrap();
+        assert_eq!(page, vec!["d", "c"]);
+        assert!(has_more);
+
+        let (page, has_more) = query(None, Some("c"), Some(2))
+            .page(items.clone(), |i| i)
+            .unwrap();
+        assert_eq!(page, vec!["b", "a"]);
+        assert!(!has_more);
+
+        let (page, _) = query(Some("asc"), Some("a"), None)
+            .page(items, |i| i)
+            .unwrap();
+        assert_eq!(page, vec!["b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_message_content_parts_must_be_text() {
+        let content: MessageInput = serde_json::from_str(
+            r#"[{"type": "text", "text": "Hello"}, {"type": "text", "text": "there"}]"#,
+        )
+        .unwrap();
+        assert_eq!(content.text().unwrap(), "Hello\n\nthere");
+
+        let content: MessageInput =
+            serde_json::from_str(r#"[{"type": "image_url", "text": ""}]"#).unwrap();
+        assert!(content.text().is_err());
+    }
+}
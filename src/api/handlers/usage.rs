@@ -0,0 +1,102 @@
+use crate::{
+    auth::middleware::AuthUser,
+    db::spend::{self, SpendTotals},
+    types::Result,
+    utils::toml_config::BudgetLimit,
+    AppState,
+};
+use axum::{extract::State, Json};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Spend against a budget scope for the current UTC day and month.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BudgetUsage {
+    /// Estimated spend today in USD
+    pub daily_usd: f64,
+    /// Estimated spend this month in USD
+    pub monthly_usd: f64,
+    /// Daily limit in USD, if any
+    pub daily_limit_usd: Option<f64>,
+    /// Monthly limit in USD, if any
+    pub monthly_limit_usd: Option<f64>,
+}
+
+impl BudgetUsage {
+    fn new(totals: SpendTotals, limit: BudgetLimit) -> Self {
+        Self {
+            daily_usd: totals.daily_usd,
+            monthly_usd: totals.monthly_usd,
+            daily_limit_usd: limit.daily_usd,
+            monthly_limit_usd: limit.monthly_usd,
+        }
+    }
+}
+
+/// Month-to-date usage of a single agent by the current user.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AgentUsage {
+    /// Agent name
+    pub agent_name: String,
+    /// Estimated input tokens this month
+    pub input_tokens: i64,
+    /// Estimated output tokens this month
+    pub output_tokens: i64,
+    /// Estimated spend this month in USD
+    pub cost_usd: f64,
+}
+
+/// Usage and budget report for the authenticated user.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UsageReport {
+    /// User the report is for
+    pub user_id: String,
+    /// Whether budgets are currently enforced
+    pub budgets_enabled: bool,
+    /// The user's own spend and limits
+    pub user: BudgetUsage,
+    /// Platform-wide spend and limits
+    pub global: BudgetUsage,
+    /// The user's spend broken down by agent
+    pub agents: Vec<AgentUsage>,
+}
+
+/// Get estimated spend and budget limits for the current user
+#[utoipa::path(
+    get,
+    path = "/api/usage",
+    responses(
+        (status = 200, description = "Usage report", body = UsageReport),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "usage",
+    security(("bearer" = []))
+)]
+pub async fn get_usage(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<UsageReport>> {
+    let budgets = state.config_manager.config().budgets.clone();
+    let pool = state.tenant_db.pool();
+
+    let user_totals = spend::get_spend_totals(pool, Some(&claims.sub), None).await?;
+    let global_totals = spend::get_spend_totals(pool, None, None).await?;
+    let agents = spend::get_user_agent_spend(pool, &claims.sub)
+        .await?
+        .into_iter()
+        .map(|a| AgentUsage {
+            agent_name: a.agent_name,
+            input_tokens: a.input_tokens,
+            output_tokens: a.output_tokens,
+            cost_usd: a.cost_usd,
+        })
+        .collect();
+
+    Ok(Json(UsageReport {
+        budgets_enabled: budgets.enabled,
+        user: BudgetUsage::new(user_totals, budgets.user_limit(&claims.sub)),
+        global: BudgetUsage::new(global_totals, budgets.global),
+        user_id: claims.sub,
+        agents,
+    }))
+}
@@ -1,26 +1,65 @@
+//! User-defined agent handlers.
+//!
+//! Users create, update and delete their own agents (model, system prompt,
+//! tools) through the API. Agents are stored in the database and
+//! instantiated per request through the
+//! [`AgentRegistry`](crate::agents::registry::AgentRegistry), so they can be
+//! used straight away without editing config files or restarting.
+//!
+//! Agent names resolve in three tiers: the user's own agents, then public
+//! community agents, then the system agents from TOML/TOON config. In a
+//! workspace, "own" agents are the workspace's, shared by its members.
+
 use crate::{
-    db::traits::DatabaseClient,
+    api::workspace::ActiveWorkspace,
+    auth::middleware::AuthUser,
     db::postgres::UserAgent,
     types::{AppError, Result},
+    utils::toml_config::{AgentConfig, ParameterLimitsConfig, MAX_REFLECTION_ROUNDS},
+    utils::toon_config::ToonAgentConfig,
     AppState,
 };
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// Longest accepted agent name
+const MAX_AGENT_NAME_LEN: usize = 64;
 
-#[derive(Debug, Deserialize)]
+/// Upper bound for an agent's tool calling iterations
+const MAX_TOOL_ITERATIONS: i32 = 50;
+
+/// Request to create a user agent.
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateUserAgentReq {
+    /// Unique agent name: letters, digits, hyphens and underscores
     pub name: String,
+    /// Human-readable name
     pub display_name: Option<String>,
+    /// What the agent does
     pub description: Option<String>,
+    /// Model name from the configured models
     pub model: String,
+    /// Instructions defining the agent's behavior
     pub system_prompt: Option<String>,
+    /// Tools the agent may call
+    #[serde(default)]
     pub tools: Vec<String>,
+    /// Maximum tool calling iterations (default: 10)
     #[serde(default = "default_max_iterations")]
     pub max_tool_iterations: i32,
+    /// Whether to execute multiple tool calls in parallel
     #[serde(default)]
     pub parallel_tools: bool,
+    /// Whether other users can use the agent by name
     #[serde(default)]
     pub is_public: bool,
+    /// Additional agent-specific settings
     #[serde(default)]
     pub extra: HashMap<String, serde_json::Value>,
 }
@@ -29,27 +68,68 @@ fn default_max_iterations() -> i32 {
     10
 }
 
-#[derive(Debug, Serialize)]
+/// Request to update a user agent; omitted fields are left unchanged.
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct UpdateUserAgentReq {
+    /// Human-readable name
+    pub display_name: Option<String>,
+    /// What the agent does
+    pub description: Option<String>,
+    /// Model name from the configured models
+    pub model: Option<String>,
+    /// Instructions defining the agent's behavior
+    pub system_prompt: Option<String>,
+    /// Tools the agent may call
+    pub tools: Option<Vec<String>>,
+    /// Maximum tool calling iterations
+    pub max_tool_iterations: Option<i32>,
+    /// Whether to execute multiple tool calls in parallel
+    pub parallel_tools: Option<bool>,
+    /// Whether other users can use the agent by name
+    pub is_public: Option<bool>,
+    /// Additional agent-specific settings
+    pub extra: Option<HashMap<String, serde_json::Value>>,
+}
+
+/// A user agent.
+#[derive(Debug, Serialize, ToSchema)]
 pub struct UserAgentResponse {
+    /// Agent ID
     pub id: String,
+    /// Agent name
     pub name: String,
+    /// Human-readable name
     pub display_name: Option<String>,
+    /// What the agent does
     pub description: Option<String>,
+    /// Model name
     pub model: String,
+    /// Instructions defining the agent's behavior
     pub system_prompt: Option<String>,
+    /// Tools the agent may call
     pub tools: Vec<String>,
+    /// Maximum tool calling iterations
     pub max_tool_iterations: i32,
+    /// Whether multiple tool calls run in parallel
     pub parallel_tools: bool,
+    /// Whether other users can use the agent by name
     pub is_public: bool,
+    /// Additional agent-specific settings
+    pub extra: HashMap<String, serde_json::Value>,
+    /// Number of times the agent was used
     pub usage_count: i32,
+    /// Average user rating
     pub average_rating: Option<f32>,
+    /// Creation time (Unix seconds)
     pub created_at: i64,
+    /// Last update time (Unix seconds)
     pub updated_at: i64,
 }
 
 impl From<UserAgent> for UserAgentResponse {
     fn from(agent: UserAgent) -> Self {
         let tools = agent.tools_vec();
+        let extra = agent.extra_map();
         let rating = agent.average_rating();
         Self {
             id: agent.id,
@@ -62,6 +142,7 @@ impl From<UserAgent> for UserAgentResponse {
             max_tool_iterations: agent.max_tool_iterations,
             parallel_tools: agent.parallel_tools,
             is_public: agent.is_public,
+            extra,
             usage_count: agent.usage_count,
             average_rating: rating,
             created_at: agent.created_at,
@@ -70,27 +151,543 @@ impl From<UserAgent> for UserAgentResponse {
     }
 }
 
+/// Resolve an agent name to its configuration
+///
+/// Checks the custom agents of `user_id` (a user, or a workspace's owner
+/// key, see [`ActiveWorkspace::owner`]), then public community agents, then
+/// the system agents from TOML/TOON config. Returns the configuration and the
+/// tier it came from: `"user"`, `"community"` or `"system"`.
 pub async fn resolve_agent(
     state: &AppState,
     user_id: &str,
     agent_name: String,
-) -> Result<(UserAgent, String)> {
+) -> Result<(AgentConfig, String)> {
     if let Some(agent) = state.db.get_user_agent_by_name(user_id, &agent_name).await? {
-        return Ok((agent, "user".to_string()));
+        return Ok((agent.to_agent_config(), "user".to_string()));
     }
-    
+
     if let Some(agent) = state.db.get_public_agent_by_name(&agent_name).await? {
-        return Ok((agent, "community".to_string()));
+        return Ok((agent.to_agent_config(), "community".to_string()));
+    }
+
+    if let Some(config) = state.agent_registry.get_config_any(&agent_name) {
+        return Ok((config, "system".to_string()));
+    }
+
+    Err(AppError::NotFound(format!("Agent '{}' not found", agent_name)))
+}
+
+/// Check that a name can identify an agent.
+fn validate_name(name: &str) -> Result<()> {
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if name.is_empty() || name.len() > MAX_AGENT_NAME_LEN || !valid_chars {
+        return Err(AppError::InvalidInput(format!(
+            "Agent name must be 1-{} letters, digits, hyphens or underscores",
+            MAX_AGENT_NAME_LEN
+        )));
+    }
+    // The router only dispatches to other agents
+    if name.eq_ignore_ascii_case("router") {
+        return Err(AppError::InvalidInput(
+            "Agent name 'router' is reserved".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Check that an agent's model and tools exist, so it can be instantiated.
+fn validate_agent(state: &AppState, agent: &UserAgent) -> Result<()> {
+    if !state.provider_registry.has_model(&agent.model) {
+        return Err(AppError::InvalidInput(format!(
+            "Unknown model: {}",
+            agent.model
+        )));
+    }
+    if let Some(tool) = agent
+        .tools_vec()
+        .into_iter()
+        .find(|tool| !state.tool_registry.has_tool(tool))
+    {
+        return Err(AppError::InvalidInput(format!("Unknown tool: {}", tool)));
+    }
+    let config = agent.to_agent_config();
+    for (tool, permission) in &config.tool_permissions {
+        if let Some(e) = permission
+            .allowed_paths
+            .iter()
+            .find_map(|pattern| globset::Glob::new(pattern).err())
+        {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid allowed_paths pattern for tool '{}': {}",
+                tool, e
+            )));
+        }
+    }
+    if !(0.0..=1.0).contains(&config.answer_cache.similarity_threshold) {
+        return Err(AppError::InvalidInput(
+            "answer_cache.similarity_threshold must be between 0.0 and 1.0".to_string(),
+        ));
+    }
+    if !(1..=MAX_REFLECTION_ROUNDS).contains(&config.reflection.max_rounds) {
+        return Err(AppError::InvalidInput(format!(
+            "reflection.max_rounds must be between 1 and {}",
+            MAX_REFLECTION_ROUNDS
+        )));
+    }
+    if let Some(judge) = config
+        .reflection
+        .judge_model
+        .as_deref()
+        .filter(|judge| !state.provider_registry.has_model(judge))
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Unknown reflection judge model: {}",
+            judge
+        )));
+    }
+    if let Some(judge) = config
+        .reflection
+        .judge
+        .as_deref()
+        .filter(|judge| !state.config_manager.config().judges.contains_key(*judge))
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Unknown reflection judge: {}",
+            judge
+        )));
+    }
+    config.limits.validate().map_err(AppError::InvalidInput)?;
+    config.validate_personas().map_err(AppError::InvalidInput)?;
+    config
+        .parameter_limits
+        .validate()
+        .map_err(AppError::InvalidInput)?;
+    if !(1..=MAX_TOOL_ITERATIONS).contains(&agent.max_tool_iterations) {
+        return Err(AppError::InvalidInput(format!(
+            "max_tool_iterations must be between 1 and {}",
+            MAX_TOOL_ITERATIONS
+        )));
+    }
+    Ok(())
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize agent: {}", e)))
+}
+
+/// Load one of the user's own agents.
+async fn load_agent(state: &AppState, user_id: &str, name: &str) -> Result<UserAgent> {
+    state
+        .db
+        .get_user_agent_by_name(user_id, name)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Agent '{}' not found", name)))
+}
+
+/// Validate and store a new agent for a user.
+async fn insert_agent(
+    state: &AppState,
+    user_id: &str,
+    payload: CreateUserAgentReq,
+) -> Result<UserAgent> {
+    validate_name(&payload.name)?;
+    if state
+        .db
+        .get_user_agent_by_name(user_id, &payload.name)
+        .await?
+        .is_some()
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Agent '{}' already exists",
+            payload.name
+        )));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let agent = UserAgent {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: user_id.to_string(),
+        name: payload.name,
+        display_name: payload.display_name,
+        description: payload.description,
+        model: payload.model,
+        system_prompt: payload.system_prompt,
+        tools: to_json(&payload.tools)?,
+        max_tool_iterations: payload.max_tool_iterations,
+        parallel_tools: payload.parallel_tools,
+        extra: to_json(&payload.extra)?,
+        is_public: payload.is_public,
+        usage_count: 0,
+        rating_sum: 0,
+        rating_count: 0,
+        created_at: now,
+        updated_at: now,
+    };
+    validate_agent(state, &agent)?;
+
+    state.db.create_user_agent(&agent).await?;
+
+    tracing::info!(user_id = %user_id, agent = %agent.name, model = %agent.model, "User agent created");
+
+    Ok(agent)
+}
+
+/// List the user's own agents.
+#[utoipa::path(
+    get,
+    path = "/api/user/agents",
+    responses(
+        (status = 200, description = "User agents", body = Vec<UserAgentResponse>),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "agents",
+    security(("bearer" = []))
+)]
+pub async fn list_agents(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+) -> Result<Json<Vec<UserAgentResponse>>> {
+    let agents = state
+        .db
+        .list_user_agents(&workspace.owner(&claims.sub))
+        .await?;
+    Ok(Json(agents.into_iter().map(Into::into).collect()))
+}
+
+/// Create a custom agent.
+///
+/// Also served at `POST /api/agents`. The agent can be used by name in chat
+/// (`agent_type`) as soon as it is created.
+#[utoipa::path(
+    post,
+    path = "/api/user/agents",
+    request_body = CreateUserAgentReq,
+    responses(
+        (status = 201, description = "Agent created", body = UserAgentResponse),
+        (status = 400, description = "Invalid name, unknown model or tool, or name taken"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "agents",
+    security(("bearer" = []))
+)]
+pub async fn create_agent(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Json(payload): Json<CreateUserAgentReq>,
+) -> Result<(StatusCode, Json<UserAgentResponse>)> {
+    let agent = insert_agent(&state, &workspace.owner(&claims.sub), payload).await?;
+    Ok((StatusCode::CREATED, Json(agent.into())))
+}
+
+/// Get one of the user's agents.
+#[utoipa::path(
+    get,
+    path = "/api/user/agents/{name}",
+    params(("name" = String, Path, description = "Agent name")),
+    responses(
+        (status = 200, description = "User agent", body = UserAgentResponse),
+        (status = 404, description = "Agent not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "agents",
+    security(("bearer" = []))
+)]
+pub async fn get_agent(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(name): Path<String>,
+) -> Result<Json<UserAgentResponse>> {
+    let agent = load_agent(&state, &workspace.owner(&claims.sub), &name).await?;
+    Ok(Json(agent.into()))
+}
+
+/// Get the bounds on the generation parameters a chat request may set for
+/// an agent.
+///
+/// Resolves the name like chat requests do: the user's own agents, then
+/// community agents, then system agents. Parameters missing from the
+/// response can't be set.
+#[utoipa::path(
+    get,
+    path = "/api/agents/{name}/parameters",
+    params(("name" = String, Path, description = "Agent name")),
+    responses(
+        (status = 200, description = "Parameter bounds", body = ParameterLimitsConfig),
+        (status = 404, description = "Agent not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "agents",
+    security(("bearer" = []))
+)]
+pub async fn get_parameter_limits(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(name): Path<String>,
+) -> Result<Json<ParameterLimitsConfig>> {
+    let (config, _) = resolve_agent(&state, &workspace.owner(&claims.sub), name).await?;
+    Ok(Json(config.parameter_limits))
+}
+
+/// Update one of the user's agents.
+///
+/// Takes effect on the next request that uses the agent.
+#[utoipa::path(
+    put,
+    path = "/api/user/agents/{name}",
+    params(("name" = String, Path, description = "Agent name")),
+    request_body = UpdateUserAgentReq,
+    responses(
+        (status = 200, description = "Agent updated", body = UserAgentResponse),
+        (status = 400, description = "Unknown model or tool"),
+        (status = 404, description = "Agent not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "agents",
+    security(("bearer" = []))
+)]
+pub async fn update_agent(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(name): Path<String>,
+    Json(payload): Json<UpdateUserAgentReq>,
+) -> Result<Json<UserAgentResponse>> {
+    let mut agent = load_agent(&state, &workspace.owner(&claims.sub), &name).await?;
+
+    if let Some(display_name) = payload.display_name {
+        agent.display_name = Some(display_name);
+    }
+    if let Some(description) = payload.description {
+        agent.description = Some(description);
+    }
+    if let Some(model) = payload.model {
+        agent.model = model;
+    }
+    if let Some(system_prompt) = payload.system_prompt {
+        agent.system_prompt = Some(system_prompt);
+    }
+    if let Some(tools) = payload.tools {
+        agent.tools = to_json(&tools)?;
+    }
+    if let Some(max_tool_iterations) = payload.max_tool_iterations {
+        agent.max_tool_iterations = max_tool_iterations;
+    }
+    if let Some(parallel_tools) = payload.parallel_tools {
+        agent.parallel_tools = parallel_tools;
+    }
+    if let Some(is_public) = payload.is_public {
+        agent.is_public = is_public;
+    }
+    if let Some(extra) = payload.extra {
+        agent.extra = to_json(&extra)?;
+    }
+    validate_agent(&state, &agent)?;
+
+    agent.updated_at = chrono::Utc::now().timestamp();
+    state.db.update_user_agent(&agent).await?;
+
+    tracing::info!(user_id = %claims.sub, agent = %agent.name, "User agent updated");
+
+    Ok(Json(agent.into()))
+}
+
+/// Delete one of the user's agents.
+#[utoipa::path(
+    delete,
+    path = "/api/user/agents/{name}",
+    params(("name" = String, Path, description = "Agent name")),
+    responses(
+        (status = 204, description = "Agent deleted"),
+        (status = 404, description = "Agent not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "agents",
+    security(("bearer" = []))
+)]
+pub async fn delete_agent(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(name): Path<String>,
+) -> Result<StatusCode> {
+    let agent = load_agent(&state, &workspace.owner(&claims.sub), &name).await?;
+
+    if !state
+        .db
+        .delete_user_agent(&agent.id, &workspace.owner(&claims.sub))
+        .await?
+    {
+        return Err(AppError::NotFound(format!("Agent '{}' not found", name)));
     }
 
-    Err(AppError::NotFound("Not implemented".into()))
+    tracing::info!(user_id = %claims.sub, agent = %name, "User agent deleted");
+
+    Ok(StatusCode::NO_CONTENT)
 }
 
-// Dummy stubs to fix routing
-pub async fn list_agents() {}
-pub async fn create_agent() {}
-pub async fn import_agent_toon() {}
-pub async fn get_agent() {}
-pub async fn update_agent() {}
-pub async fn delete_agent() {}
-pub async fn export_agent_toon() {}
+/// Create an agent from a TOON agent definition.
+#[utoipa::path(
+    post,
+    path = "/api/user/agents/import",
+    request_body(content = String, description = "TOON agent definition", content_type = "text/plain"),
+    responses(
+        (status = 201, description = "Agent created", body = UserAgentResponse),
+        (status = 400, description = "Invalid TOON, name, model or tool"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "agents",
+    security(("bearer" = []))
+)]
+pub async fn import_agent_toon(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    body: String,
+) -> Result<(StatusCode, Json<UserAgentResponse>)> {
+    let mut toon = ToonAgentConfig::from_toon(&body)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid TOON agent definition: {}", e)))?;
+    // Stored agents keep their memory settings with the other extras
+    if toon.memory.enabled {
+        let memory = serde_json::to_value(&toon.memory)
+            .map_err(|e| AppError::Internal(format!("Failed to encode memory settings: {}", e)))?;
+        toon.extra.insert("memory".to_string(), memory);
+    }
+    if !toon.tool_permissions.is_empty() {
+        let permissions = serde_json::to_value(&toon.tool_permissions)
+            .map_err(|e| AppError::Internal(format!("Failed to encode tool permissions: {}", e)))?;
+        toon.extra
+            .insert("tool_permissions".to_string(), permissions);
+    }
+    if toon.answer_cache.enabled {
+        let answer_cache = serde_json::to_value(&toon.answer_cache).map_err(|e| {
+            AppError::Internal(format!("Failed to encode answer cache settings: {}", e))
+        })?;
+        toon.extra.insert("answer_cache".to_string(), answer_cache);
+    }
+    if toon.reflection.enabled {
+        let reflection = serde_json::to_value(&toon.reflection).map_err(|e| {
+            AppError::Internal(format!("Failed to encode reflection settings: {}", e))
+        })?;
+        toon.extra.insert("reflection".to_string(), reflection);
+    }
+    if !toon.limits.is_unlimited() {
+        let limits = serde_json::to_value(toon.limits)
+            .map_err(|e| AppError::Internal(format!("Failed to encode run limits: {}", e)))?;
+        toon.extra.insert("limits".to_string(), limits);
+    }
+    if !toon.personas.is_empty() {
+        let personas = serde_json::to_value(&toon.personas)
+            .map_err(|e| AppError::Internal(format!("Failed to encode personas: {}", e)))?;
+        toon.extra.insert("personas".to_string(), personas);
+    }
+    if toon.requires_approval {
+        toon.extra
+            .insert("requires_approval".to_string(), serde_json::Value::Bool(true));
+    }
+    if !toon.bus.is_disabled() {
+        let bus = serde_json::to_value(&toon.bus)
+            .map_err(|e| AppError::Internal(format!("Failed to encode bus settings: {}", e)))?;
+        toon.extra.insert("bus".to_string(), bus);
+    }
+    if !toon.parameter_limits.is_unset() {
+        let limits = serde_json::to_value(toon.parameter_limits)
+            .map_err(|e| AppError::Internal(format!("Failed to encode parameter limits: {}", e)))?;
+        toon.extra.insert("parameter_limits".to_string(), limits);
+    }
+    if let Some(rag) = &toon.rag {
+        let rag = serde_json::to_value(rag)
+            .map_err(|e| AppError::Internal(format!("Failed to encode RAG settings: {}", e)))?;
+        toon.extra.insert("rag".to_string(), rag);
+    }
+
+    let payload = CreateUserAgentReq {
+        name: toon.name,
+        display_name: None,
+        description: None,
+        model: toon.model,
+        system_prompt: toon.system_prompt,
+        tools: toon.tools,
+        max_tool_iterations: i32::try_from(toon.max_tool_iterations).unwrap_or(i32::MAX),
+        parallel_tools: toon.parallel_tools,
+        is_public: false,
+        extra: toon.extra,
+    };
+    let agent = insert_agent(&state, &workspace.owner(&claims.sub), payload).await?;
+    Ok((StatusCode::CREATED, Json(agent.into())))
+}
+
+/// Export one of the user's agents as a TOON agent definition.
+#[utoipa::path(
+    get,
+    path = "/api/user/agents/{name}/export",
+    params(("name" = String, Path, description = "Agent name")),
+    responses(
+        (status = 200, description = "TOON agent definition", body = String, content_type = "text/plain"),
+        (status = 404, description = "Agent not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "agents",
+    security(("bearer" = []))
+)]
+pub async fn export_agent_toon(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
+    Path(name): Path<String>,
+) -> Result<String> {
+    let agent = load_agent(&state, &workspace.owner(&claims.sub), &name).await?;
+
+    let mut toon = ToonAgentConfig::new(&agent.name, &agent.model).with_tools(agent.tools_vec());
+    toon.system_prompt = agent.system_prompt.clone();
+    toon.max_tool_iterations = agent.max_tool_iterations.max(0) as usize;
+    toon.parallel_tools = agent.parallel_tools;
+    let config = agent.to_agent_config();
+    toon.memory = config.memory;
+    toon.tool_permissions = config.tool_permissions;
+    toon.answer_cache = config.answer_cache;
+    toon.reflection = config.reflection;
+    toon.limits = config.limits;
+    toon.personas = config.personas;
+    toon.requires_approval = config.requires_approval;
+    toon.bus = config.bus;
+    toon.parameter_limits = config.parameter_limits;
+    toon.rag = config.rag;
+    toon.extra = agent.extra_map();
+    toon.extra.remove("memory");
+    toon.extra.remove("tool_permissions");
+    toon.extra.remove("answer_cache");
+    toon.extra.remove("reflection");
+    toon.extra.remove("limits");
+    toon.extra.remove("personas");
+    toon.extra.remove("requires_approval");
+    toon.extra.remove("bus");
+    toon.extra.remove("parameter_limits");
+    toon.extra.remove("rag");
+
+    toon.to_toon()
+        .map_err(|e| AppError::Internal(format!("Failed to encode agent as TOON: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("code-reviewer").is_ok());
+        assert!(validate_name("support_bot_2").is_ok());
+
+        assert!(validate_name("").is_err());
+        assert!(validate_name("has space").is_err());
+        assert!(validate_name("../etc").is_err());
+        assert!(validate_name(&"a".repeat(MAX_AGENT_NAME_LEN + 1)).is_err());
+        assert!(validate_name("Router").is_err());
+    }
+}
@@ -267,7 +267,10 @@ pub async fn list_agent_runs(
         id: r.id,
         agent_id: r.agent_name,
         status: r.status,
-        input: serde_json::json!({"tokens": r.input_tokens}),
+        input: match r.seed {
+            Some(seed) => serde_json::json!({"tokens": r.input_tokens, "seed": seed}),
+            None => serde_json::json!({"tokens": r.input_tokens}),
+        },
         output: Some(serde_json::json!({"tokens": r.output_tokens})),
         error: r.error,
         started_at: ts_to_dt(r.created_at),
@@ -3,15 +3,20 @@
 //! Handles HTTP requests for executing declarative workflows defined in ares.toml.
 
 use crate::{
+    api::{maintenance, workspace::ActiveWorkspace},
     auth::middleware::AuthUser,
-    types::{AgentContext, Result, WorkflowRequest},
-    workflows::{WorkflowEngine, WorkflowOutput},
+    llm::cancellation::CancellationToken,
+    tools::permissions::ToolProfile,
+    types::{AgentContext, AppError, Result, WorkflowRequest},
+    utils::toml_config::WorkflowConfig,
+    workflows::{GraphFormat, WorkflowEngine, WorkflowGraph, WorkflowOutput},
     AppState,
 };
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     Json,
 };
+use serde::Deserialize;
 use uuid::Uuid;
 
 /// Execute a workflow by name
@@ -26,6 +31,7 @@ use uuid::Uuid;
         (status = 200, description = "Workflow executed successfully", body = WorkflowOutput),
         (status = 400, description = "Invalid input"),
         (status = 401, description = "Unauthorized"),
+        (status = 503, description = "Server in maintenance mode"),
         (status = 404, description = "Workflow not found")
     ),
     params(
@@ -37,9 +43,12 @@ use uuid::Uuid;
 pub async fn execute_workflow(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
+    workspace: ActiveWorkspace,
     Path(workflow_name): Path<String>,
-    Json(payload): Json<WorkflowRequest>,
+    Json(mut payload): Json<WorkflowRequest>,
 ) -> Result<Json<WorkflowOutput>> {
+    maintenance::ensure_available(&state)?;
+
     // Create workflow engine
     let workflow_engine = WorkflowEngine::new(state.clone());
 
@@ -51,22 +60,121 @@ pub async fn execute_workflow(
         )));
     }
 
+    // Cancelled when Axum drops this handler (client disconnect)
+    let cancellation = CancellationToken::new();
+    let _cancel_on_drop = cancellation.clone().drop_guard();
+
     // Create agent context
     let context = AgentContext {
         user_id: claims.sub.clone(),
         session_id: Uuid::new_v4().to_string(),
         conversation_history: vec![],
         user_memory: None,
+        cancellation,
+        hooks: state.hooks.clone(),
+        preferences: Default::default(),
+        tool_profile: ToolProfile::for_user(
+            &state.config_manager.config(),
+            &claims.sub,
+            &claims.email,
+        ),
+        bus: None,
+        workspace_id: workspace.id().map(str::to_string),
     };
 
+    context
+        .hooks
+        .message_received(&context, &mut payload.query)
+        .await?;
+
     // Execute the workflow
-    let output = workflow_engine
+    let mut output = workflow_engine
         .execute_workflow(&workflow_name, &payload.query, &context)
         .await?;
 
+    context
+        .hooks
+        .response(&context, &workflow_name, &mut output.final_response)
+        .await?;
+
     Ok(Json(output))
 }
 
+/// Format of a rendered workflow graph.
+#[derive(Debug, Deserialize)]
+pub struct GraphQuery {
+    /// `mermaid` (default) or `dot`
+    #[serde(default)]
+    pub format: GraphFormat,
+}
+
+/// Render a workflow definition as a graph
+///
+/// Nodes are the agents a request can pass through and edges the ways it
+/// can take between them, as a Mermaid flowchart or a Graphviz digraph.
+#[utoipa::path(
+    get,
+    path = "/api/workflows/{workflow_name}/graph",
+    params(
+        ("workflow_name" = String, Path, description = "Name of the workflow"),
+        ("format" = Option<String>, Query, description = "mermaid (default) or dot")
+    ),
+    responses(
+        (status = 200, description = "Workflow graph", body = String, content_type = "text/plain"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Workflow not found")
+    ),
+    tag = "workflows",
+    security(("bearer" = []))
+)]
+pub async fn workflow_graph(
+    State(state): State<AppState>,
+    AuthUser(_claims): AuthUser,
+    Path(workflow_name): Path<String>,
+    Query(query): Query<GraphQuery>,
+) -> Result<String> {
+    let workflow = find_workflow(&state, &workflow_name)?;
+    Ok(WorkflowGraph::definition(&workflow).render(query.format))
+}
+
+/// Render a completed workflow run as a graph
+///
+/// Takes the output of `POST /api/workflows/{workflow_name}` and renders the
+/// steps the run took, annotated with their durations, with edges labelled
+/// by the output each step passed on.
+#[utoipa::path(
+    post,
+    path = "/api/workflows/{workflow_name}/graph",
+    request_body = WorkflowOutput,
+    params(
+        ("workflow_name" = String, Path, description = "Name of the workflow that ran"),
+        ("format" = Option<String>, Query, description = "mermaid (default) or dot")
+    ),
+    responses(
+        (status = 200, description = "Run graph", body = String, content_type = "text/plain"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Workflow not found")
+    ),
+    tag = "workflows",
+    security(("bearer" = []))
+)]
+pub async fn run_graph(
+    State(state): State<AppState>,
+    AuthUser(_claims): AuthUser,
+    Path(workflow_name): Path<String>,
+    Query(query): Query<GraphQuery>,
+    Json(output): Json<WorkflowOutput>,
+) -> Result<String> {
+    let workflow = find_workflow(&state, &workflow_name)?;
+    Ok(WorkflowGraph::run(&workflow, &output).render(query.format))
+}
+
+fn find_workflow(state: &AppState, workflow_name: &str) -> Result<WorkflowConfig> {
+    WorkflowEngine::new(state.clone())
+        .get_workflow_config(workflow_name)
+        .ok_or_else(|| AppError::NotFound(format!("Workflow '{}' not found", workflow_name)))
+}
+
 /// List available workflows
 ///
 /// Returns a list of workflow names that are defined in the configuration.
@@ -0,0 +1,266 @@
+//! Workspace handlers.
+//!
+//! Users create workspaces and add each other as members. What a workspace
+//! shares and isolates is described in [`crate::api::workspace`]; these
+//! handlers only manage workspaces and their membership.
+
+use crate::{
+    auth::middleware::AuthUser,
+    db::workspaces::{self, Workspace, WorkspaceMember, WorkspaceRole},
+    types::{AppError, Result},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Longest accepted workspace name
+const MAX_WORKSPACE_NAME_LEN: usize = 100;
+
+/// Request to create a workspace.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateWorkspaceRequest {
+    /// Display name
+    pub name: String,
+}
+
+/// Request to add a member to a workspace, or change their role.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddMemberRequest {
+    /// Email of the user to add
+    pub email: String,
+    /// Role to give them: "admin" or "member" (default)
+    #[serde(default = "default_role")]
+    pub role: WorkspaceRole,
+}
+
+fn default_role() -> WorkspaceRole {
+    WorkspaceRole::Member
+}
+
+/// A workspace with its members.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WorkspaceDetails {
+    /// The workspace
+    #[serde(flatten)]
+    pub workspace: Workspace,
+    /// Its members, in the order they joined
+    pub members: Vec<WorkspaceMember>,
+}
+
+/// The requesting user's role in a workspace; non-members get a 404.
+async fn require_member(state: &AppState, id: &str, user_id: &str) -> Result<WorkspaceRole> {
+    workspaces::member_role(state.tenant_db.pool(), id, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Workspace {} not found", id)))
+}
+
+/// Create a workspace, with the user as its owner.
+#[utoipa::path(
+    post,
+    path = "/api/workspaces",
+    request_body = CreateWorkspaceRequest,
+    responses(
+        (status = 201, description = "Workspace created", body = Workspace),
+        (status = 400, description = "Invalid name"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "workspaces",
+    security(("bearer" = []))
+)]
+pub async fn create_workspace(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Json(payload): Json<CreateWorkspaceRequest>,
+) -> Result<(StatusCode, Json<Workspace>)> {
+    let name = payload.name.trim();
+    if name.is_empty() || name.len() > MAX_WORKSPACE_NAME_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "Workspace name must be 1-{} characters",
+            MAX_WORKSPACE_NAME_LEN
+        )));
+    }
+
+    let workspace = Workspace {
+        id: Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        created_by: claims.sub,
+        created_at: Utc::now().timestamp(),
+    };
+    workspaces::create_workspace(state.tenant_db.pool(), &workspace).await?;
+    tracing::info!(user_id = %workspace.created_by, workspace_id = %workspace.id, "Workspace created");
+
+    Ok((StatusCode::CREATED, Json(workspace)))
+}
+
+/// List the workspaces the user belongs to.
+#[utoipa::path(
+    get,
+    path = "/api/workspaces",
+    responses(
+        (status = 200, description = "Workspaces", body = Vec<Workspace>),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "workspaces",
+    security(("bearer" = []))
+)]
+pub async fn list_workspaces(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+) -> Result<Json<Vec<Workspace>>> {
+    Ok(Json(
+        workspaces::list_user_workspaces(state.tenant_db.pool(), &claims.sub).await?,
+    ))
+}
+
+/// Get a workspace the user belongs to, with its members.
+#[utoipa::path(
+    get,
+    path = "/api/workspaces/{id}",
+    params(("id" = String, Path, description = "Workspace ID")),
+    responses(
+        (status = 200, description = "Workspace", body = WorkspaceDetails),
+        (status = 404, description = "Workspace not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "workspaces",
+    security(("bearer" = []))
+)]
+pub async fn get_workspace(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<WorkspaceDetails>> {
+    require_member(&state, &id, &claims.sub).await?;
+    let pool = state.tenant_db.pool();
+    let workspace = workspaces::get_workspace(pool, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Workspace {} not found", id)))?;
+    let members = workspaces::list_members(pool, &id).await?;
+    Ok(Json(WorkspaceDetails { workspace, members }))
+}
+
+/// Add a user to a workspace, or change a member's role.
+///
+/// Only the workspace's owner and admins manage members, and a workspace
+/// has exactly one owner: the role can't be given or taken away.
+#[utoipa::path(
+    post,
+    path = "/api/workspaces/{id}/members",
+    params(("id" = String, Path, description = "Workspace ID")),
+    request_body = AddMemberRequest,
+    responses(
+        (status = 200, description = "Member added or updated", body = WorkspaceMember),
+        (status = 400, description = "Role can't be assigned"),
+        (status = 401, description = "Unauthorized, or not an owner or admin"),
+        (status = 404, description = "Workspace or user not found")
+    ),
+    tag = "workspaces",
+    security(("bearer" = []))
+)]
+pub async fn add_member(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<String>,
+    Json(payload): Json<AddMemberRequest>,
+) -> Result<Json<WorkspaceMember>> {
+    let role = require_member(&state, &id, &claims.sub).await?;
+    if !role.manages_members() {
+        return Err(AppError::Auth(
+            "Only workspace owners and admins can manage members".to_string(),
+        ));
+    }
+    if payload.role == WorkspaceRole::Owner {
+        return Err(AppError::InvalidInput(
+            "A workspace has only one owner".to_string(),
+        ));
+    }
+    let user = state
+        .db
+        .get_user_by_email(payload.email.trim())
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {} not found", payload.email)))?;
+
+    let pool = state.tenant_db.pool();
+    if workspaces::member_role(pool, &id, &user.id).await? == Some(WorkspaceRole::Owner) {
+        return Err(AppError::InvalidInput(
+            "The workspace owner's role can't be changed".to_string(),
+        ));
+    }
+    let member = WorkspaceMember {
+        workspace_id: id,
+        user_id: user.id,
+        role: payload.role.as_str().to_string(),
+        created_at: Utc::now().timestamp(),
+    };
+    workspaces::upsert_member(pool, &member).await?;
+    tracing::info!(
+        user_id = %claims.sub,
+        workspace_id = %member.workspace_id,
+        member = %member.user_id,
+        role = %member.role,
+        "Workspace member added"
+    );
+
+    Ok(Json(member))
+}
+
+/// Remove a member from a workspace.
+///
+/// Owners and admins remove members; any member can remove themselves to
+/// leave. The owner can't be removed.
+#[utoipa::path(
+    delete,
+    path = "/api/workspaces/{id}/members/{user_id}",
+    params(
+        ("id" = String, Path, description = "Workspace ID"),
+        ("user_id" = String, Path, description = "ID of the member to remove")
+    ),
+    responses(
+        (status = 204, description = "Member removed"),
+        (status = 400, description = "The owner can't be removed"),
+        (status = 401, description = "Unauthorized, or not an owner or admin"),
+        (status = 404, description = "Workspace or member not found")
+    ),
+    tag = "workspaces",
+    security(("bearer" = []))
+)]
+pub async fn remove_member(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path((id, user_id)): Path<(String, String)>,
+) -> Result<StatusCode> {
+    let role = require_member(&state, &id, &claims.sub).await?;
+    if user_id != claims.sub && !role.manages_members() {
+        return Err(AppError::Auth(
+            "Only workspace owners and admins can manage members".to_string(),
+        ));
+    }
+
+    let pool = state.tenant_db.pool();
+    match workspaces::member_role(pool, &id, &user_id).await? {
+        None => Err(AppError::NotFound(format!(
+            "User {} is not a member of this workspace",
+            user_id
+        ))),
+        Some(WorkspaceRole::Owner) => Err(AppError::InvalidInput(
+            "The workspace owner can't be removed".to_string(),
+        )),
+        Some(_) => {
+            workspaces::remove_member(pool, &id, &user_id).await?;
+            tracing::info!(
+                user_id = %claims.sub,
+                workspace_id = %id,
+                member = %user_id,
+                "Workspace member removed"
+            );
+            Ok(StatusCode::NO_CONTENT)
+        }
+    }
+}
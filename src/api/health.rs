@@ -0,0 +1,362 @@
+//! Liveness and readiness probes, for Kubernetes and load balancers.
+//!
+//! `GET /health/live` (also served at `GET /health`) answers as long as the
+//! process serves requests, without looking at its dependencies, so an
+//! orchestrator only restarts a server that is stuck. `GET /health/ready`
+//! checks what a request needs and answers 503 unless all of it is up:
+//!
+//! - `database`: PostgreSQL answers a query.
+//! - `llm`: at least one configured provider is reachable. Ollama must list
+//!   its models, hosted APIs must list theirs with the configured key, and
+//!   llama.cpp must find its GGUF file. Providers are checked at most once
+//!   per `[health] provider_cache_secs`, since hosted APIs count the calls.
+//! - `vector_store`: the RAG vector store opens (skipped without the
+//!   `ares-vector` feature).
+//! - `warmup`: the startup model warm-up is done (see [`crate::llm::warmup`]).
+//!
+//! Each check gets `[health] timeout_ms` and reports how it went:
+//!
+//! ```json
+//! {
+//!   "status": "not_ready",
+//!   "checks": {
+//!     "database": {"status": "up", "latency_ms": 2},
+//!     "llm": {"status": "up", "latency_ms": 41, "components": {
+//!       "local": {"status": "up", "latency_ms": 12},
+//!       "openai": {"status": "down", "latency_ms": 41, "error": "HTTP 401 Unauthorized"}
+//!     }},
+//!     "vector_store": {"status": "down", "latency_ms": 0, "error": "..."},
+//!     "warmup": {"status": "up"}
+//!   }
+//! }
+//! ```
+
+use crate::llm::Provider;
+use crate::utils::toml_config::{AresConfig, ProviderConfig};
+use crate::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Anthropic's model list, checked for Anthropic providers
+const ANTHROPIC_MODELS_URL: &str = "https://api.anthropic.com/v1/models";
+
+/// Outcome of a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    /// The dependency works
+    Up,
+    /// The dependency failed, or didn't answer in time
+    Down,
+    /// The dependency isn't used by this server
+    Skipped,
+}
+
+/// A dependency check.
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    /// Outcome
+    pub status: CheckStatus,
+    /// Time taken, in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Why the dependency is down
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Checks of its parts, by name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub components: BTreeMap<String, Check>,
+}
+
+impl Check {
+    fn new(status: CheckStatus) -> Self {
+        Self {
+            status,
+            latency_ms: None,
+            error: None,
+            components: BTreeMap::new(),
+        }
+    }
+
+    fn down(error: impl Into<String>) -> Self {
+        Self {
+            error: Some(error.into()),
+            ..Self::new(CheckStatus::Down)
+        }
+    }
+
+    /// Whether the check doesn't keep the server from being ready
+    pub fn passed(&self) -> bool {
+        self.status != CheckStatus::Down
+    }
+}
+
+/// Readiness of the server, as reported by `GET /health/ready`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Readiness {
+    /// "ready" when every check passed, "not_ready" otherwise
+    pub status: &'static str,
+    /// Checks, by dependency
+    pub checks: BTreeMap<&'static str, Check>,
+}
+
+/// Cached checks of the LLM providers.
+///
+/// Cheap to clone; all clones share the same cache.
+#[derive(Clone, Default)]
+pub struct Health {
+    providers: Arc<RwLock<Option<ProviderChecks>>>,
+}
+
+struct ProviderChecks {
+    checked_at: Instant,
+    checks: BTreeMap<String, Check>,
+}
+
+impl Health {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check every dependency, concurrently.
+    pub async fn readiness(&self, state: &AppState) -> Readiness {
+        let config = state.config_manager.config();
+        let timeout = Duration::from_millis(config.health.timeout_ms);
+        let (database, llm, vector_store) = tokio::join!(
+            timed(timeout, check_database(state)),
+            self.check_llm(&config, timeout),
+            check_vector_store(&config, timeout),
+        );
+        let warmup = match state.warmup.is_ready() {
+            true => Check::new(CheckStatus::Up),
+            false => Check::down("Models are warming up"),
+        };
+
+        let checks = BTreeMap::from([
+            ("database", database),
+            ("llm", llm),
+            ("vector_store", vector_store),
+            ("warmup", warmup),
+        ]);
+        let status = match checks.values().all(Check::passed) {
+            true => "ready",
+            false => "not_ready",
+        };
+        Readiness { status, checks }
+    }
+
+    /// Check the providers, up when any of them is
+    async fn check_llm(&self, config: &AresConfig, timeout: Duration) -> Check {
+        if config.providers.is_empty() {
+            return Check::down("No LLM provider is configured");
+        }
+        let started = Instant::now();
+        let providers = self.providers(config, timeout).await;
+        let mut check = match providers.values().any(|c| c.status == CheckStatus::Up) {
+            true => Check::new(CheckStatus::Up),
+            false => Check::down("No LLM provider is reachable"),
+        };
+        check.latency_ms = Some(started.elapsed().as_millis() as u64);
+        check.components = providers;
+        check
+    }
+
+    /// Checks of every provider, from the cache while it's fresh
+    async fn providers(&self, config: &AresConfig, timeout: Duration) -> BTreeMap<String, Check> {
+        let max_age = Duration::from_secs(config.health.provider_cache_secs);
+        if let Some(cached) = &*self.providers.read() {
+            if cached.checked_at.elapsed() < max_age
+                && cached.checks.len() == config.providers.len()
+            {
+                return cached.checks.clone();
+            }
+        }
+
+        let client = reqwest::Client::new();
+        let checks = futures::future::join_all(config.providers.iter().map(|(name, provider)| {
+            let client = &client;
+            async move {
+                (
+                    name.clone(),
+                    timed(timeout, check_provider(client, provider)).await,
+                )
+            }
+        }))
+        .await;
+        let providers: BTreeMap<String, Check> = checks.into_iter().collect();
+        *self.providers.write() = Some(ProviderChecks {
+            checked_at: Instant::now(),
+            checks: providers.clone(),
+        });
+        providers
+    }
+}
+
+/// Run `check`, giving up after `timeout`
+async fn timed<F>(timeout: Duration, check: F) -> Check
+where
+    F: Future<Output = std::result::Result<(), String>>,
+{
+    let started = Instant::now();
+    let result = tokio::time::timeout(timeout, check)
+        .await
+        .unwrap_or_else(|_| Err(format!("Timed out after {} ms", timeout.as_millis())));
+    let mut check = match result {
+        Ok(()) => Check::new(CheckStatus::Up),
+        Err(error) => Check::down(error),
+    };
+    check.latency_ms = Some(started.elapsed().as_millis() as u64);
+    check
+}
+
+async fn check_database(state: &AppState) -> std::result::Result<(), String> {
+    sqlx::query("SELECT 1")
+        .execute(state.tenant_db.pool())
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "ares-vector")]
+async fn check_vector_store(config: &AresConfig, timeout: Duration) -> Check {
+    timed(timeout, async {
+        crate::api::handlers::rag::vector_store_collections(config)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    })
+    .await
+}
+
+#[cfg(not(feature = "ares-vector"))]
+async fn check_vector_store(_config: &AresConfig, _timeout: Duration) -> Check {
+    Check::new(CheckStatus::Skipped)
+}
+
+/// Whether a provider can answer, without generating anything
+async fn check_provider(
+    client: &reqwest::Client,
+    provider: &ProviderConfig,
+) -> std::result::Result<(), String> {
+    // Fails when the provider's feature is off, its API key is missing or
+    // its managed GGUF model hasn't been downloaded
+    Provider::from_config(provider, None).map_err(|e| e.to_string())?;
+    match provider {
+        ProviderConfig::Ollama { base_url, .. } => {
+            let url = format!("{}/api/tags", base_url.trim_end_matches('/'));
+            reachable(client.get(url)).await
+        }
+        ProviderConfig::OpenAI {
+            api_key_env,
+            api_base,
+            ..
+        }
+        | ProviderConfig::Mistral {
+            api_key_env,
+            api_base,
+            ..
+        }
+        | ProviderConfig::Cohere {
+            api_key_env,
+            api_base,
+            ..
+        } => {
+            let url = format!("{}/models", api_base.trim_end_matches('/'));
+            let api_key = std::env::var(api_key_env).unwrap_or_default();
+            reachable(client.get(url).bearer_auth(api_key)).await
+        }
+        ProviderConfig::Anthropic { api_key_env, .. } => {
+            let api_key = std::env::var(api_key_env).unwrap_or_default();
+            let request = client
+                .get(ANTHROPIC_MODELS_URL)
+                .header("x-api-key", api_key)
+                .header("anthropic-version", "2023-06-01");
+            reachable(request).await
+        }
+        ProviderConfig::LlamaCpp {
+            model_path,
+            model: None,
+            ..
+        } => match std::path::Path::new(model_path).is_file() {
+            true => Ok(()),
+            false => Err(format!("Model file '{}' not found", model_path)),
+        },
+        ProviderConfig::LlamaCpp { .. } | ProviderConfig::Mock { .. } => Ok(()),
+    }
+}
+
+/// Send `request`, failing unless it succeeds
+async fn reachable(request: reqwest::RequestBuilder) -> std::result::Result<(), String> {
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    match response.status().is_success() {
+        true => Ok(()),
+        false => Err(format!("HTTP {}", response.status())),
+    }
+}
+
+/// Liveness probe: 200 as long as the server answers requests.
+pub async fn live() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "status": "alive",
+        "version": env!("CARGO_PKG_VERSION"),
+    }))
+}
+
+/// Readiness probe: 503 unless every dependency check passes.
+pub async fn ready(State(state): State<AppState>) -> (StatusCode, Json<Readiness>) {
+    let readiness = state.health.readiness(&state).await;
+    let code = match readiness.status {
+        "ready" => StatusCode::OK,
+        _ => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (code, Json(readiness))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_checks_time_out() {
+        let check = timed(Duration::from_millis(10), async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        })
+        .await;
+        assert_eq!(check.status, CheckStatus::Down);
+        assert_eq!(check.error.as_deref(), Some("Timed out after 10 ms"));
+
+        let check = timed(Duration::from_millis(10), async {
+            Err("refused".to_string())
+        })
+        .await;
+        assert!(!check.passed());
+        assert!(timed(Duration::from_secs(1), async { Ok(()) })
+            .await
+            .passed());
+        assert!(Check::new(CheckStatus::Skipped).passed());
+    }
+
+    #[tokio::test]
+    async fn test_local_providers() {
+        let client = reqwest::Client::new();
+        let mock = ProviderConfig::Mock {
+            default_model: "mock".to_string(),
+            latency_ms: 0,
+        };
+        assert!(check_provider(&client, &mock).await.is_ok());
+
+        let openai = ProviderConfig::OpenAI {
+            api_key_env: "ARES_TEST_HEALTH_UNSET_KEY".to_string(),
+            api_base: "http://127.0.0.1:9/v1".to_string(),
+            default_model: "gpt-4o".to_string(),
+        };
+        assert!(check_provider(&client, &openai).await.is_err());
+    }
+}
@@ -0,0 +1,242 @@
+//! Workers of the durable background job queue.
+//!
+//! Scheduled agent runs and ingest jobs are queued in the `jobs` table (see
+//! [`crate::db::jobs`]) instead of running in a detached tokio task, so
+//! they survive restarts. Every server runs a worker loop that claims due
+//! jobs, up to `[jobs] concurrency` at once, and renews each job's lease
+//! while it runs. When a server stops mid-job, the job's lease runs out and
+//! a worker claims it again.
+//!
+//! A failed attempt is retried after `retry_base_secs`, doubling on every
+//! further failure up to `retry_max_secs`. After `max_attempts` the job is
+//! dead-lettered: it stays in the table with its last error until retried
+//! or deleted through `/api/admin/jobs`.
+//!
+//! ```toml
+//! [jobs]
+//! concurrency = 4
+//! max_attempts = 5
+//! ```
+
+use crate::db::jobs::{self, Job};
+use crate::db::locks;
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::JobsConfig;
+use crate::AppState;
+use chrono::Utc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// A scheduled agent run; the payload is a
+/// [`ScheduledRun`](crate::agents::scheduler::ScheduledRun)
+pub const AGENT_RUN: &str = "agent_run";
+
+/// An ingest job run from its checkpoint; the payload holds its `job_id`
+pub const INGEST: &str = "ingest";
+
+/// How often succeeded jobs past their retention are deleted
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Wakes this server's worker loop before its next poll
+static WAKE: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// Have this server's workers look for due jobs now.
+///
+/// Call after queueing a job due straight away, so it starts without
+/// waiting for the next poll.
+pub fn wake() {
+    WAKE.notify_one();
+}
+
+/// Delay before retrying a job whose `attempt`th attempt failed.
+pub fn retry_delay(config: &JobsConfig, attempt: u32) -> u64 {
+    let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+    config
+        .retry_base_secs
+        .saturating_mul(factor)
+        .min(config.retry_max_secs)
+}
+
+/// Start this server's worker loop.
+pub fn spawn_workers(state: AppState) {
+    let worker = format!("worker-{}", uuid::Uuid::new_v4());
+    let active = Arc::new(AtomicUsize::new(0));
+    tokio::spawn(async move {
+        let mut last_purge: Option<Instant> = None;
+        loop {
+            let config = state.config_manager.config().jobs.clone();
+            let free = config
+                .concurrency
+                .saturating_sub(active.load(Ordering::SeqCst));
+            if free > 0 {
+                let now = Utc::now().timestamp();
+                let claimed = jobs::claim(
+                    state.tenant_db.pool(),
+                    &worker,
+                    now,
+                    now + config.lease_secs as i64,
+                    free as i64,
+                )
+                .await;
+                match claimed {
+                    Ok(claimed) => {
+                        for job in claimed {
+                            active.fetch_add(1, Ordering::SeqCst);
+                            let (state, worker, active) =
+                                (state.clone(), worker.clone(), Arc::clone(&active));
+                            tokio::spawn(async move {
+                                run(&state, &worker, job).await;
+                                active.fetch_sub(1, Ordering::SeqCst);
+                                wake();
+                            });
+                        }
+                    }
+                    Err(e) => tracing::warn!("Failed to claim jobs: {}", e),
+                }
+            }
+
+            if locks::is_leader() && last_purge.is_none_or(|at| at.elapsed() >= PURGE_INTERVAL) {
+                last_purge = Some(Instant::now());
+                let before = Utc::now().timestamp() - config.retention_days as i64 * 86_400;
+                match jobs::purge_succeeded(state.tenant_db.pool(), before).await {
+                    Ok(0) => {}
+                    Ok(n) => tracing::debug!("Purged {} succeeded jobs", n),
+                    Err(e) => tracing::warn!("Job purge failed: {}", e),
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(config.poll_interval_secs)) => {}
+                _ = WAKE.notified() => {}
+            }
+        }
+    });
+}
+
+/// Run a claimed job, renewing its lease, and record the outcome.
+async fn run(state: &AppState, worker: &str, job: Job) {
+    let config = state.config_manager.config().jobs.clone();
+    let pool = state.tenant_db.pool();
+
+    // A job claimed again after its last attempt was cut off
+    if job.attempts > job.max_attempts {
+        let now = Utc::now().timestamp();
+        let error = "The server stopped during the last attempt";
+        if let Err(e) = jobs::fail(pool, &job.id, worker, error, now, None).await {
+            tracing::warn!(job_id = %job.id, "Failed to dead-letter job: {}", e);
+        }
+        tracing::warn!(job_id = %job.id, kind = %job.kind, "Job dead-lettered: {}", error);
+        return;
+    }
+
+    let start = Instant::now();
+    let work = execute(state, &job);
+    tokio::pin!(work);
+    let mut renew = tokio::time::interval(Duration::from_secs(config.lease_secs).div_f32(3.0));
+    renew.tick().await;
+    let mut leased = true;
+    let outcome = loop {
+        tokio::select! {
+            outcome = &mut work => break outcome,
+            _ = renew.tick(), if leased => {
+                let until = Utc::now().timestamp() + config.lease_secs as i64;
+                match jobs::extend_lease(pool, &job.id, worker, until).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        // Another worker claimed it; let this attempt finish
+                        tracing::warn!(job_id = %job.id, "Lost the lease on a running job");
+                        leased = false;
+                    }
+                    Err(e) => tracing::warn!(job_id = %job.id, "Failed to renew job lease: {}", e),
+                }
+            }
+        }
+    };
+
+    let now = Utc::now().timestamp();
+    let duration_ms = start.elapsed().as_millis() as u64;
+    let recorded = match &outcome {
+        Ok(()) => {
+            tracing::info!(job_id = %job.id, kind = %job.kind, duration_ms, "Job succeeded");
+            jobs::complete(pool, &job.id, worker, now).await
+        }
+        Err(e) => {
+            let attempts = job.attempts.max(1) as u32;
+            let retry_at = (job.attempts < job.max_attempts)
+                .then(|| now + retry_delay(&config, attempts) as i64);
+            match retry_at {
+                Some(retry_at) => tracing::warn!(
+                    job_id = %job.id,
+                    kind = %job.kind,
+                    attempt = attempts,
+                    retry_in_secs = retry_at - now,
+                    "Job failed: {}",
+                    e
+                ),
+                None => tracing::error!(
+                    job_id = %job.id,
+                    kind = %job.kind,
+                    attempts,
+                    "Job dead-lettered: {}",
+                    e
+                ),
+            }
+            jobs::fail(pool, &job.id, worker, &e.to_string(), now, retry_at).await
+        }
+    };
+    if let Err(e) = recorded {
+        tracing::warn!(job_id = %job.id, "Failed to record job outcome: {}", e);
+    }
+}
+
+/// Run a job with the handler of its kind.
+async fn execute(state: &AppState, job: &Job) -> Result<()> {
+    match job.kind.as_str() {
+        AGENT_RUN => {
+            let run = serde_json::from_value(job.payload.clone())
+                .map_err(|e| AppError::InvalidInput(format!("Invalid agent run job: {}", e)))?;
+            crate::agents::scheduler::run_scheduled(state, run).await
+        }
+        INGEST => {
+            let job_id = job.payload["job_id"]
+                .as_str()
+                .ok_or_else(|| AppError::InvalidInput("Ingest job has no job_id".to_string()))?;
+            #[cfg(feature = "ares-vector")]
+            {
+                crate::api::handlers::rag::run_queued_ingest_job(state, job_id).await
+            }
+            #[cfg(not(feature = "ares-vector"))]
+            {
+                Err(AppError::Configuration(format!(
+                    "Ingest job '{}' needs the `ares-vector` feature",
+                    job_id
+                )))
+            }
+        }
+        kind => Err(AppError::InvalidInput(format!(
+            "Unknown job kind '{}'",
+            kind
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        let config = JobsConfig {
+            retry_base_secs: 30,
+            retry_max_secs: 600,
+            ..Default::default()
+        };
+        assert_eq!(retry_delay(&config, 1), 30);
+        assert_eq!(retry_delay(&config, 2), 60);
+        assert_eq!(retry_delay(&config, 4), 240);
+        assert_eq!(retry_delay(&config, 6), 600);
+        assert_eq!(retry_delay(&config, 200), 600);
+    }
+}
@@ -0,0 +1,157 @@
+//! Maintenance mode: a system-wide switch that stops new generations.
+//!
+//! While the server is in maintenance mode, requests that would start new
+//! LLM work (chat, regenerate, research, workflows, agent and scheduled
+//! runs) are rejected with a 503 and a message for the user:
+//!
+//! ```json
+//! {"error": "A.R.E.S is down for maintenance. Please try again in a few minutes.", "code": "SERVICE_UNAVAILABLE"}
+//! ```
+//!
+//! Generations already in progress are left to finish, and health, auth,
+//! admin and read-only endpoints stay up, so a deploy can wait for
+//! [`MaintenanceStatus::in_flight`] to reach zero before restarting.
+//!
+//! The mode is enabled by `[maintenance] enabled = true` in `ares.toml`
+//! (hot-reloaded), or at runtime with `PUT /api/admin/maintenance`, which
+//! overrides the configuration on that server until
+//! `DELETE /api/admin/maintenance` clears it.
+
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::MaintenanceConfig;
+use crate::AppState;
+use chrono::Utc;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Message returned to rejected requests when none is configured
+pub const DEFAULT_MESSAGE: &str =
+    "A.R.E.S is down for maintenance. Please try again in a few minutes.";
+
+/// Message returned to requests rejected while models warm up
+pub const WARMUP_MESSAGE: &str = "A.R.E.S is loading its models. Please try again in a minute.";
+
+/// The runtime maintenance switch, overriding `[maintenance]` while set.
+///
+/// Cheap to clone; all clones share the same switch.
+#[derive(Clone, Default)]
+pub struct Maintenance {
+    switch: Arc<RwLock<Option<Switch>>>,
+}
+
+#[derive(Clone)]
+struct Switch {
+    enabled: bool,
+    message: Option<String>,
+    since: i64,
+}
+
+/// Whether the server is in maintenance mode, and why.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MaintenanceStatus {
+    /// Whether new generations are rejected
+    pub enabled: bool,
+    /// Message returned to rejected requests
+    pub message: String,
+    /// "admin" when switched at runtime, "config" otherwise
+    pub source: &'static str,
+    /// When the runtime switch was set (Unix timestamp)
+    pub since: Option<i64>,
+    /// Generations still in progress
+    pub in_flight: usize,
+}
+
+impl Maintenance {
+    /// Create a switch that follows the configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Turn maintenance mode on or off, overriding the configuration.
+    pub fn set(&self, enabled: bool, message: Option<String>) {
+        *self.switch.write() = Some(Switch {
+            enabled,
+            message,
+            since: Utc::now().timestamp(),
+        });
+    }
+
+    /// Clear the runtime switch, going back to the configuration.
+    pub fn clear(&self) {
+        *self.switch.write() = None;
+    }
+
+    /// The current mode, given the `[maintenance]` configuration and the
+    /// number of generations in progress.
+    pub fn status(&self, config: &MaintenanceConfig, in_flight: usize) -> MaintenanceStatus {
+        let (enabled, message, source, since) = match &*self.switch.read() {
+            Some(switch) => (
+                switch.enabled,
+                switch.message.clone(),
+                "admin",
+                Some(switch.since),
+            ),
+            None => (config.enabled, config.message.clone(), "config", None),
+        };
+        MaintenanceStatus {
+            enabled,
+            message: message
+                .filter(|m| !m.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_MESSAGE.to_string()),
+            source,
+            since,
+            in_flight,
+        }
+    }
+}
+
+/// Reject the request if the server is in maintenance mode, or still
+/// warming up its models behind the `[warmup]` gate.
+///
+/// Called by every handler that starts new LLM work.
+pub fn ensure_available(state: &AppState) -> Result<()> {
+    let config = state.config_manager.config();
+    let status = state.maintenance.status(&config.maintenance, 0);
+    if status.enabled {
+        return Err(AppError::Unavailable(status.message));
+    }
+    if config.warmup.gate && !state.warmup.is_ready() {
+        return Err(AppError::Unavailable(WARMUP_MESSAGE.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_switch_overrides_config() {
+        let maintenance = Maintenance::new();
+        let config = MaintenanceConfig {
+            enabled: true,
+            message: Some("Back at 14:00 UTC".to_string()),
+        };
+
+        let status = maintenance.status(&config, 2);
+        assert!(status.enabled);
+        assert_eq!(
+            (status.message.as_str(), status.source),
+            ("Back at 14:00 UTC", "config")
+        );
+        assert_eq!(status.in_flight, 2);
+
+        maintenance.set(false, None);
+        assert!(!maintenance.status(&config, 0).enabled);
+
+        maintenance.set(true, Some("  ".to_string()));
+        let status = maintenance.status(&MaintenanceConfig::default(), 0);
+        assert_eq!((status.enabled, status.source), (true, "admin"));
+        assert_eq!(status.message, DEFAULT_MESSAGE);
+        assert!(status.since.is_some());
+
+        maintenance.clear();
+        assert!(!maintenance.status(&MaintenanceConfig::default(), 0).enabled);
+    }
+}
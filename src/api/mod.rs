@@ -5,7 +5,14 @@
 //! # Module Structure
 //!
 //! - [`api::handlers`](crate::api::handlers) - Request handlers for each endpoint
+//! - [`api::health`](crate::api::health) - Liveness and readiness probes
+//! - [`api::jobs`](crate::api::jobs) - Durable background job workers
 //! - [`api::routes`](crate::api::routes) - Route definitions and router configuration
+//! - [`api::encryption`](crate::api::encryption) - Encrypted conversations
+//! - [`api::maintenance`](crate::api::maintenance) - Maintenance mode switch
+//! - [`api::shutdown`](crate::api::shutdown) - Graceful shutdown and draining
+//! - [`api::tls`](crate::api::tls) - TLS termination
+//! - [`api::workspace`](crate::api::workspace) - The workspace a request acts in
 //!
 //! # API Endpoints
 //!
@@ -44,7 +51,21 @@
 //! When the `swagger-ui` feature is enabled, interactive API documentation
 //! is available at `/swagger-ui/`.
 
+/// Encrypted conversations and their secrets.
+pub mod encryption;
 /// Request and response handlers for all API endpoints.
 pub mod handlers;
+/// Liveness and readiness probes.
+pub mod health;
+/// Workers of the durable background job queue.
+pub mod jobs;
+/// Maintenance mode, which stops new generations.
+pub mod maintenance;
 /// Router configuration and route definitions.
 pub mod routes;
+/// Graceful shutdown, draining requests and runs.
+pub mod shutdown;
+/// TLS termination with rustls.
+pub mod tls;
+/// Workspaces, and the one a request acts in.
+pub mod workspace;
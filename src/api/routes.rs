@@ -1,9 +1,10 @@
 use crate::auth::jwt::AuthService;
 use crate::db::tenants::TenantDb;
+use crate::types::Role;
 use crate::AppState;
 
 use axum::{
-    extract::Request,
+    extract::{DefaultBodyLimit, Request},
     middleware::{self, Next},
     routing::{delete, get, post, put},
     Router,
@@ -19,6 +20,10 @@ use crate::api::handlers::deploy;
 pub fn create_router(auth_service: Arc<AuthService>, tenant_db: Arc<TenantDb>) -> Router<AppState> {
     // Clone for v1 routes (API key auth)
     let tenant_db_for_v1 = tenant_db.clone();
+    // Clone for API key authentication of protected routes
+    let tenant_db_for_auth = tenant_db.clone();
+    // Clone for admin routes, which also accept admins' JWTs
+    let admin_auth_service = auth_service.clone();
 
     let public_routes = Router::new()
         // Public routes (no auth required)
@@ -29,31 +34,41 @@ pub fn create_router(auth_service: Arc<AuthService>, tenant_db: Arc<TenantDb>) -
             post(crate::api::handlers::auth::refresh_token),
         )
         .route("/auth/logout", post(crate::api::handlers::auth::logout))
-        .route("/agents", get(crate::api::handlers::agents::list_agents));
+        // MFA steps act for a login's challenge token, or for a JWT
+        .route("/auth/mfa/enroll", post(crate::api::handlers::mfa::enroll))
+        .route("/auth/mfa/confirm", post(crate::api::handlers::mfa::confirm))
+        .route("/auth/mfa/verify", post(crate::api::handlers::mfa::verify))
+        .route("/agents", get(crate::api::handlers::agents::list_agents))
+        // Shared conversations are opened by their link's token
+        .route(
+            "/shared/{token}",
+            get(crate::api::handlers::shares::view_shared),
+        );
 
-    #[allow(unused_mut)]
-    let mut protected_routes = Router::new()
-        // Protected routes (auth required)
-        .route("/chat", post(crate::api::handlers::chat::chat))
+    // GitHub push webhooks authenticate with their signature instead of a JWT
+    #[cfg(feature = "ares-vector")]
+    let public_routes = public_routes.route(
+        "/rag/github/webhook",
+        post(crate::api::handlers::rag::github_webhook),
+    );
+
+    // User agent routes (POST /agents sits beside the public agent listing).
+    // Read-only users can read agents but not create, change or delete them.
+    let agent_routes = Router::new()
         .route(
-            "/chat/stream",
-            post(crate::api::handlers::chat::chat_stream),
+            "/agents",
+            post(crate::api::handlers::user_agents::create_agent),
         )
         .route(
-            "/research",
-            post(crate::api::handlers::research::deep_research),
-        )
-        .route("/memory", get(crate::api::handlers::chat::get_user_memory))
-        // Workflow routes
-        .route(
-            "/workflows",
-            get(crate::api::handlers::workflows::list_workflows),
+            "/agents/{name}",
+            get(crate::api::handlers::user_agents::get_agent)
+                .put(crate::api::handlers::user_agents::update_agent)
+                .delete(crate::api::handlers::user_agents::delete_agent),
         )
         .route(
-            "/workflows/{workflow_name}",
-            post(crate::api::handlers::workflows::execute_workflow),
+            "/agents/{name}/parameters",
+            get(crate::api::handlers::user_agents::get_parameter_limits),
         )
-        // User agent routes
         .route(
             "/user/agents",
             get(crate::api::handlers::user_agents::list_agents)
@@ -73,6 +88,134 @@ pub fn create_router(auth_service: Arc<AuthService>, tenant_db: Arc<TenantDb>) -
             "/user/agents/{name}/export",
             get(crate::api::handlers::user_agents::export_agent_toon),
         )
+        .route_layer(middleware::from_fn(|req, next| {
+            crate::auth::middleware::require_role_to_change(Role::Member, req, next)
+        }));
+
+    #[allow(unused_mut)]
+    let mut protected_routes = Router::new()
+        // Protected routes (auth required)
+        .route("/chat", post(crate::api::handlers::chat::chat))
+        .route(
+            "/chat/stream",
+            post(crate::api::handlers::chat::chat_stream),
+        )
+        .route(
+            "/chat/{context_id}/stop",
+            post(crate::api::handlers::chat::stop_generation),
+        )
+        .route(
+            "/chat/{context_id}/regenerate",
+            post(crate::api::handlers::chat::regenerate),
+        )
+        .route(
+            "/chat/batch",
+            post(crate::api::handlers::batches::create_batch),
+        )
+        // AG-UI event streams for agent frontends
+        .route("/agui", post(crate::api::handlers::agui::run))
+        .route("/agui/{agent}", post(crate::api::handlers::agui::run_named))
+        .route(
+            "/chat/batch/{id}",
+            get(crate::api::handlers::batches::get_batch),
+        )
+        .route(
+            "/chat/batch/{id}/cancel",
+            post(crate::api::handlers::batches::cancel_batch),
+        )
+        .route(
+            "/research",
+            post(crate::api::handlers::research::deep_research),
+        )
+        .route("/memory", get(crate::api::handlers::chat::get_user_memory))
+        .route(
+            "/preferences",
+            get(crate::api::handlers::preferences::get_preferences)
+                .put(crate::api::handlers::preferences::update_preferences),
+        )
+        .route("/usage", get(crate::api::handlers::usage::get_usage))
+        // Signed-in devices
+        .route(
+            "/auth/sessions",
+            get(crate::api::handlers::sessions::list_sessions),
+        )
+        .route(
+            "/auth/sessions/{id}",
+            delete(crate::api::handlers::sessions::revoke_session),
+        )
+        // MFA routes
+        .route("/auth/mfa", get(crate::api::handlers::mfa::get_status))
+        .route("/auth/mfa/disable", post(crate::api::handlers::mfa::disable))
+        .route(
+            "/auth/mfa/recovery-codes",
+            post(crate::api::handlers::mfa::regenerate_recovery_codes),
+        )
+        // API key routes
+        .route(
+            "/api-keys",
+            get(crate::api::handlers::api_keys::list_api_keys)
+                .post(crate::api::handlers::api_keys::create_api_key),
+        )
+        .route(
+            "/api-keys/{id}",
+            delete(crate::api::handlers::api_keys::revoke_api_key),
+        )
+        .route(
+            "/api-keys/{id}/rotate",
+            post(crate::api::handlers::api_keys::rotate_api_key),
+        )
+        // Schedule routes
+        .route(
+            "/schedules",
+            get(crate::api::handlers::schedules::list_schedules)
+                .post(crate::api::handlers::schedules::create_schedule),
+        )
+        .route(
+            "/schedules/{id}",
+            get(crate::api::handlers::schedules::get_schedule)
+                .put(crate::api::handlers::schedules::update_schedule)
+                .delete(crate::api::handlers::schedules::delete_schedule),
+        )
+        .route(
+            "/schedules/{id}/run",
+            post(crate::api::handlers::schedules::run_schedule),
+        )
+        // Approval routes
+        .route(
+            "/approvals",
+            get(crate::api::handlers::approvals::list_approvals),
+        )
+        .route(
+            "/approvals/{id}",
+            post(crate::api::handlers::approvals::decide_approval),
+        )
+        // Interrupted run routes
+        .route(
+            "/runs/interrupted",
+            get(crate::api::handlers::runs::list_interrupted_runs),
+        )
+        .route(
+            "/runs/{id}",
+            delete(crate::api::handlers::runs::dismiss_interrupted_run),
+        )
+        .route(
+            "/runs/{id}/resume",
+            post(crate::api::handlers::runs::resume_interrupted_run),
+        )
+        // Workflow routes
+        .route(
+            "/workflows",
+            get(crate::api::handlers::workflows::list_workflows),
+        )
+        .route(
+            "/workflows/{workflow_name}",
+            post(crate::api::handlers::workflows::execute_workflow),
+        )
+        .route(
+            "/workflows/{workflow_name}/graph",
+            get(crate::api::handlers::workflows::workflow_graph)
+                .post(crate::api::handlers::workflows::run_graph),
+        )
         // Conversation routes
         .route(
             "/conversations",
@@ -83,26 +226,198 @@ pub fn create_router(auth_service: Arc<AuthService>, tenant_db: Arc<TenantDb>) -
             get(crate::api::handlers::conversations::get_conversation)
                 .put(crate::api::handlers::conversations::update_conversation)
                 .delete(crate::api::handlers::conversations::delete_conversation),
-        );
+        )
+        .route(
+            "/conversations/{id}/overrides",
+            put(crate::api::handlers::conversations::update_conversation_overrides),
+        )
+        .route(
+            "/conversations/{id}/messages/{mid}/trace",
+            get(crate::api::handlers::conversations::get_message_trace),
+        )
+        .route(
+            "/conversations/{id}/messages/{mid}/feedback",
+            post(crate::api::handlers::conversations::submit_message_feedback),
+        )
+        .route(
+            "/messages/{id}/feedback",
+            post(crate::api::handlers::conversations::submit_feedback),
+        )
+        .route(
+            "/conversations/{id}/archive",
+            post(crate::api::handlers::conversations::archive_conversation),
+        )
+        .route(
+            "/conversations/{id}/attachments",
+            post(crate::api::handlers::conversations::attach_file)
+                .get(crate::api::handlers::conversations::list_attachments),
+        )
+        .route(
+            "/conversations/{id}/attachments/{attachment_id}",
+            delete(crate::api::handlers::conversations::delete_attachment),
+        )
+        .route(
+            "/conversations/{id}/rag-scope",
+            get(crate::api::handlers::conversations::get_rag_scope)
+                .put(crate::api::handlers::conversations::update_rag_scope)
+                .delete(crate::api::handlers::conversations::delete_rag_scope),
+        )
+        .route(
+            "/conversations/{id}/project",
+            put(crate::api::handlers::conversations::update_conversation_project),
+        )
+        .route(
+            "/conversations/{id}/share",
+            post(crate::api::handlers::shares::create_share),
+        )
+        .route(
+            "/conversations/{id}/shares",
+            get(crate::api::handlers::shares::list_shares),
+        )
+        .route(
+            "/conversations/{id}/shares/{share_id}",
+            delete(crate::api::handlers::shares::revoke_share),
+        )
+        .route(
+            "/files",
+            // Uploads are streamed and held to `[files] max_bytes` instead
+            post(crate::api::handlers::files::upload_file)
+                .layer(DefaultBodyLimit::disable())
+                .get(crate::api::handlers::files::list_files),
+        )
+        .route(
+            "/files/{id}",
+            get(crate::api::handlers::files::get_file)
+                .delete(crate::api::handlers::files::delete_file),
+        )
+        .route(
+            "/files/{id}/content",
+            get(crate::api::handlers::files::download_file),
+        )
+        .route(
+            "/files/{id}/project",
+            put(crate::api::handlers::files::update_file_project),
+        )
+        .route(
+            "/projects",
+            get(crate::api::handlers::projects::list_projects)
+                .post(crate::api::handlers::projects::create_project),
+        )
+        .route(
+            "/projects/{id}",
+            get(crate::api::handlers::projects::get_project)
+                .put(crate::api::handlers::projects::update_project)
+                .delete(crate::api::handlers::projects::delete_project),
+        )
+        .route(
+            "/threads",
+            post(crate::api::handlers::threads::create_thread),
+        )
+        .route(
+            "/threads/runs",
+            post(crate::api::handlers::threads::create_thread_and_run),
+        )
+        .route(
+            "/threads/{id}",
+            get(crate::api::handlers::threads::get_thread)
+                .delete(crate::api::handlers::threads::delete_thread),
+        )
+        .route(
+            "/threads/{id}/messages",
+            get(crate::api::handlers::threads::list_messages)
+                .post(crate::api::handlers::threads::create_message),
+        )
+        .route(
+            "/threads/{id}/runs",
+            get(crate::api::handlers::threads::list_runs)
+                .post(crate::api::handlers::threads::create_run),
+        )
+        .route(
+            "/threads/{id}/runs/{run_id}",
+            get(crate::api::handlers::threads::get_run),
+        )
+        .route(
+            "/threads/{id}/runs/{run_id}/cancel",
+            post(crate::api::handlers::threads::cancel_run),
+        )
+        .route(
+            "/workspaces",
+            get(crate::api::handlers::workspaces::list_workspaces)
+                .post(crate::api::handlers::workspaces::create_workspace),
+        )
+        .route(
+            "/workspaces/{id}",
+            get(crate::api::handlers::workspaces::get_workspace),
+        )
+        .route(
+            "/workspaces/{id}/members",
+            post(crate::api::handlers::workspaces::add_member),
+        )
+        .route(
+            "/workspaces/{id}/members/{user_id}",
+            delete(crate::api::handlers::workspaces::remove_member),
+        )
+        .merge(agent_routes);
 
-    // RAG routes (requires local-embeddings feature for ONNX-based embeddings and ares-vector for vector storage)
-    #[cfg(all(feature = "local-embeddings", feature = "ares-vector"))]
+    // RAG routes (requires ares-vector for vector storage; embeddings are local or remote)
+    #[cfg(feature = "ares-vector")]
     {
-        protected_routes = protected_routes
+        // Read-only users can list collections but not manage them
+        let rag_management_routes = Router::new()
             .route("/rag/ingest", post(crate::api::handlers::rag::ingest))
-            .route("/rag/search", post(crate::api::handlers::rag::search))
+            .route(
+                "/rag/ingest/jobs",
+                get(crate::api::handlers::rag::list_ingest_jobs)
+                    .post(crate::api::handlers::rag::start_ingest_job),
+            )
+            .route(
+                "/rag/ingest/jobs/{id}",
+                get(crate::api::handlers::rag::get_ingest_job),
+            )
+            .route(
+                "/rag/ingest/jobs/{id}/resume",
+                post(crate::api::handlers::rag::resume_ingest_job),
+            )
+            .route(
+                "/rag/ingest/jobs/{id}/cancel",
+                post(crate::api::handlers::rag::cancel_ingest_job),
+            )
             .route(
                 "/rag/collection",
                 delete(crate::api::handlers::rag::delete_collection),
             )
+            .route(
+                "/rag/collections/{collection}/settings",
+                get(crate::api::handlers::rag::get_collection_settings)
+                    .put(crate::api::handlers::rag::update_collection_settings),
+            )
+            .route(
+                "/rag/collections/{collection}/reembed",
+                post(crate::api::handlers::rag::reembed_collection),
+            )
+            .route_layer(middleware::from_fn(|req, next| {
+                crate::auth::middleware::require_role_to_change(Role::Member, req, next)
+            }));
+
+        protected_routes = protected_routes
+            .route("/rag/search", post(crate::api::handlers::rag::search))
             .route(
                 "/rag/collections",
                 get(crate::api::handlers::rag::list_collections),
-            );
+            )
+            .route(
+                "/rag/feedback",
+                post(crate::api::handlers::rag::chunk_feedback),
+            )
+            .route(
+                "/rag/collections/{collection}/feedback",
+                get(crate::api::handlers::rag::feedback_report),
+            )
+            .merge(rag_management_routes);
     }
 
     // Layer order: last added = outermost = runs first.
-    // Request flow: jwt_auth → inject_tenant_db → track_usage → handler → track_usage (reads response)
+    // Request flow: authenticate (API key or JWT) → inject_tenant_db → track_usage → handler → track_usage (reads response)
     let protected_routes = protected_routes
         // Innermost: wraps handler, reads tenant info from extensions, records token usage from response headers
         .layer(middleware::from_fn(crate::middleware::usage::track_usage))
@@ -114,9 +429,14 @@ pub fn create_router(auth_service: Arc<AuthService>, tenant_db: Arc<TenantDb>) -
                 next.run(req).await
             }
         }))
-        // Outermost: validates JWT, rejects unauthorized requests early
+        // Outermost: validates the API key or JWT, rejects unauthorized requests early
         .layer(middleware::from_fn(move |req, next| {
-            crate::auth::middleware::auth_middleware(auth_service.clone(), req, next)
+            crate::auth::middleware::authenticate(
+                auth_service.clone(),
+                tenant_db_for_auth.clone(),
+                req,
+                next,
+            )
         }));
 
     // Admin routes (protected by X-Admin-Secret header)
@@ -168,6 +488,24 @@ pub fn create_router(auth_service: Arc<AuthService>, tenant_db: Arc<TenantDb>) -
             "/admin/models",
             get(crate::api::handlers::admin::list_models_handler),
         )
+        .route(
+            "/admin/canaries",
+            get(crate::api::handlers::admin::list_canaries_handler),
+        )
+        .route(
+            "/admin/feedback",
+            get(crate::api::handlers::admin::list_feedback_handler),
+        )
+        .route(
+            "/admin/feedback/export",
+            get(crate::api::handlers::admin::export_feedback_handler),
+        )
+        .route(
+            "/admin/maintenance",
+            get(crate::api::handlers::admin::get_maintenance)
+                .put(crate::api::handlers::admin::set_maintenance)
+                .delete(crate::api::handlers::admin::clear_maintenance),
+        )
         // Alerts
         .route(
             "/admin/alerts",
@@ -201,6 +539,38 @@ pub fn create_router(auth_service: Arc<AuthService>, tenant_db: Arc<TenantDb>) -
             "/admin/agents",
             get(crate::api::handlers::admin::list_all_agents_handler),
         )
+        // User roles
+        .route(
+            "/admin/users",
+            get(crate::api::handlers::admin::list_users),
+        )
+        .route(
+            "/admin/users/{user_id}/role",
+            put(crate::api::handlers::admin::set_user_role),
+        )
+        .route(
+            "/admin/users/{user_id}/mfa",
+            put(crate::api::handlers::admin::set_user_mfa)
+                .delete(crate::api::handlers::admin::reset_user_mfa),
+        )
+        // Background jobs and dead letters
+        .route(
+            "/admin/jobs",
+            get(crate::api::handlers::admin::list_jobs),
+        )
+        .route(
+            "/admin/jobs/{job_id}",
+            delete(crate::api::handlers::admin::delete_job),
+        )
+        .route(
+            "/admin/jobs/{job_id}/retry",
+            post(crate::api::handlers::admin::retry_job),
+        )
+        // Request analytics for dashboards
+        .route(
+            "/admin/analytics",
+            get(crate::api::handlers::admin::get_analytics),
+        )
         // Platform stats
         .route(
             "/admin/stats",
@@ -227,9 +597,9 @@ pub fn create_router(auth_service: Arc<AuthService>, tenant_db: Arc<TenantDb>) -
             "/admin/services/{service_name}/logs",
             get(deploy::get_service_logs),
         )
-        .layer(middleware::from_fn(
-            crate::api::handlers::admin::admin_middleware,
-        ));
+        .layer(middleware::from_fn(move |req, next| {
+            crate::api::handlers::admin::admin_middleware(admin_auth_service.clone(), req, next)
+        }));
 
     // External API: authenticated via API key (for client apps, CLI, MCP)
     // Client-specific business logic lives in the client's own portal backend, not here.
@@ -237,6 +607,8 @@ pub fn create_router(auth_service: Arc<AuthService>, tenant_db: Arc<TenantDb>) -
     let v1_routes = Router::new()
         .route("/chat", post(crate::api::handlers::chat::chat))
         .route("/chat/stream", post(crate::api::handlers::chat::chat_stream))
+        .route("/chat/{context_id}/stop", post(crate::api::handlers::chat::stop_generation))
+        .route("/chat/{context_id}/regenerate", post(crate::api::handlers::chat::regenerate))
         .route("/agents", get(crate::api::handlers::v1::list_agents))
         .route("/agents/{name}", get(crate::api::handlers::v1::get_agent))
         .route("/agents/{name}/run", post(crate::api::handlers::v1::run_agent))
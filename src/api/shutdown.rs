@@ -0,0 +1,154 @@
+//! Graceful shutdown: stop taking work, drain what is running, save state.
+//!
+//! On SIGTERM or Ctrl+C the server:
+//!
+//! 1. stops accepting connections, and puts itself in maintenance mode so
+//!    requests on connections already open don't start new generations;
+//! 2. waits for open requests, including streamed answers, and for agent
+//!    runs going on in the background, up to `[server] shutdown_timeout_secs`;
+//! 3. writes the vector store to disk, and marks the runs still going at the
+//!    deadline interrupted, so their users can resume them on the next
+//!    server (see [`crate::api::handlers::runs`]).
+//!
+//! BM25 and fuzzy indexes are built per search and have nothing to flush.
+
+use crate::llm::cancellation::ActiveGenerations;
+use crate::AppState;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+/// Message returned to requests for new generations while shutting down
+pub const SHUTDOWN_MESSAGE: &str = "A.R.E.S is restarting. Please try again in a moment.";
+
+/// How often draining checks for runs still going
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Wait for Ctrl+C (SIGINT) or, on Unix, SIGTERM.
+pub async fn signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {
+            tracing::info!("Received Ctrl+C, initiating graceful shutdown...");
+        }
+        _ = terminate => {
+            tracing::info!("Received SIGTERM, initiating graceful shutdown...");
+        }
+    }
+}
+
+/// Run `server` until `shutdown` is cancelled, then shut down gracefully
+/// within `timeout`.
+///
+/// `server` must stop accepting connections once `shutdown` is cancelled,
+/// like `axum::serve(..).with_graceful_shutdown(shutdown.cancelled_owned())`.
+pub async fn serve_until_shutdown(
+    server: impl Future<Output = std::io::Result<()>>,
+    shutdown: CancellationToken,
+    state: &AppState,
+    timeout: Duration,
+) -> std::io::Result<()> {
+    let mut server = std::pin::pin!(server);
+    tokio::select! {
+        result = &mut server => return result,
+        _ = shutdown.cancelled() => {}
+    }
+
+    begin(state);
+    let deadline = Instant::now() + timeout;
+    tracing::info!(
+        "Draining requests and runs for up to {}s",
+        timeout.as_secs()
+    );
+    if tokio::time::timeout_at(deadline, &mut server)
+        .await
+        .is_err()
+    {
+        tracing::warn!("Requests still open at the shutdown deadline are being closed");
+    }
+    if !drain(&state.generations, deadline).await {
+        tracing::warn!(
+            "{} generations still running at the shutdown deadline",
+            state.generations.len()
+        );
+    }
+    persist(state).await;
+    Ok(())
+}
+
+/// Stop starting new generations.
+pub fn begin(state: &AppState) {
+    state
+        .maintenance
+        .set(true, Some(SHUTDOWN_MESSAGE.to_string()));
+}
+
+/// Wait until no generation is running, or `deadline` passes.
+///
+/// Returns whether every generation finished.
+pub async fn drain(generations: &ActiveGenerations, deadline: Instant) -> bool {
+    loop {
+        if generations.is_empty() {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep_until((Instant::now() + DRAIN_POLL_INTERVAL).min(deadline)).await;
+    }
+}
+
+/// Save what would be lost on exit: the vector store, and the checkpoints
+/// of runs that didn't finish.
+pub async fn persist(state: &AppState) {
+    #[cfg(feature = "ares-vector")]
+    match crate::api::handlers::rag::persist_vector_store().await {
+        Ok(()) => tracing::info!("Vector store written to disk"),
+        Err(e) => tracing::error!("Failed to write the vector store to disk: {}", e),
+    }
+    match crate::api::handlers::runs::interrupt_own_runs(state).await {
+        Ok(0) => {}
+        Ok(count) => tracing::warn!("{} runs were interrupted by the shutdown", count),
+        Err(e) => tracing::error!("Failed to mark unfinished runs interrupted: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::cancellation::CancellationToken;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_drain_waits_for_generations_until_the_deadline() {
+        let generations = ActiveGenerations::new();
+        assert!(drain(&generations, Instant::now()).await);
+
+        let guard = generations.register("conv-1", "alice", CancellationToken::new());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        assert!(!drain(&generations, deadline).await);
+        assert!(Instant::now() >= deadline);
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            drop(guard);
+        });
+        assert!(drain(&generations, Instant::now() + Duration::from_secs(5)).await);
+    }
+}
@@ -0,0 +1,177 @@
+//! TLS termination.
+//!
+//! With `[server.security.tls]` in `ares.toml`, the server speaks HTTPS
+//! itself, with rustls, instead of relying on a reverse proxy:
+//!
+//! ```toml
+//! [server.security.tls]
+//! cert_path = "/etc/ares/tls/fullchain.pem"
+//! key_path = "/etc/ares/tls/privkey.pem"
+//! ```
+//!
+//! Certificates are read at startup; restart the server to pick up renewed
+//! ones. Handshakes run off the accept loop, so a slow or stalled client
+//! doesn't hold up the others.
+
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::TlsConfig;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+/// Time a client has to complete its handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Handshaken connections waiting for the server to take them
+const ACCEPT_BACKLOG: usize = 128;
+
+/// A listener accepting TLS connections, for [`axum::serve`].
+pub struct TlsListener {
+    incoming: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    /// Listen on `addr` with the certificate and key of `config`.
+    pub async fn bind(addr: &str, config: &TlsConfig) -> Result<Self> {
+        let acceptor = TlsAcceptor::from(Arc::new(server_config(config)?));
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to bind {}: {}", addr, e)))?;
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| AppError::Internal(format!("Failed to bind {}: {}", addr, e)))?;
+
+        let (tx, incoming) = mpsc::channel(ACCEPT_BACKLOG);
+        tokio::spawn(accept_loop(listener, acceptor, tx));
+        Ok(Self {
+            incoming,
+            local_addr,
+        })
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.incoming.recv().await {
+            Some(connection) => connection,
+            // The accept loop only stops once this listener is dropped
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+/// Accept connections and hand them over once handshaken, until the
+/// listener is dropped.
+async fn accept_loop(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    tx: mpsc::Sender<(TlsStream<TcpStream>, SocketAddr)>,
+) {
+    loop {
+        let (stream, remote_addr) = tokio::select! {
+            _ = tx.closed() => return,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    // Usually out of file descriptors; give it a moment
+                    tracing::warn!("Failed to accept connection: {}", e);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    continue;
+                }
+            },
+        };
+
+        let acceptor = acceptor.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    let _ = tx.send((stream, remote_addr)).await;
+                }
+                Ok(Err(e)) => tracing::debug!("TLS handshake with {} failed: {}", remote_addr, e),
+                Err(_) => tracing::debug!("TLS handshake with {} timed out", remote_addr),
+            }
+        });
+    }
+}
+
+/// The rustls configuration for `config`'s certificate and key.
+fn server_config(config: &TlsConfig) -> Result<ServerConfig> {
+    let certs = load_certs(&config.cert_path)?;
+    let key = load_key(&config.key_path)?;
+
+    let provider = Arc::new(tokio_rustls::rustls::crypto::ring::default_provider());
+    let mut server_config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(|e| AppError::Configuration(format!("Invalid TLS certificate or key: {}", e)))?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(server_config)
+}
+
+fn open(path: &str) -> Result<BufReader<std::fs::File>> {
+    std::fs::File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| AppError::Configuration(format!("Failed to open {}: {}", path, e)))
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let certs = rustls_pemfile::certs(&mut open(path)?)
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(|e| AppError::Configuration(format!("Failed to read {}: {}", path, e)))?;
+    if certs.is_empty() {
+        return Err(AppError::Configuration(format!(
+            "No certificates found in {}",
+            path
+        )));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &str) -> Result<PrivateKeyDer<'static>> {
+    rustls_pemfile::private_key(&mut open(path)?)
+        .map_err(|e| AppError::Configuration(format!("Failed to read {}: {}", path, e)))?
+        .ok_or_else(|| AppError::Configuration(format!("No private key found in {}", path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_or_empty_pem_files_are_configuration_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let empty = dir.path().join("empty.pem");
+        std::fs::write(&empty, "not a certificate\n").unwrap();
+        let empty = empty.to_str().unwrap().to_string();
+
+        for (cert_path, key_path) in [
+            ("/nonexistent/cert.pem".to_string(), empty.clone()),
+            (empty.clone(), empty.clone()),
+        ] {
+            let config = TlsConfig {
+                cert_path,
+                key_path,
+            };
+            assert!(matches!(
+                server_config(&config),
+                Err(AppError::Configuration(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Workspaces: teams served from one deployment, isolated from each other.
+//!
+//! Users belong to any number of workspaces (managed with
+//! `/api/workspaces`). A request acts in one by naming it in the
+//! `X-Workspace-Id` header; without the header it acts in the user's
+//! personal space. In a workspace:
+//!
+//! - Conversations are still the user's own, but belong to the space they
+//!   were started in. Lists and searches show only the current space's,
+//!   and a conversation can't be read, changed or continued from another.
+//! - Custom agents, RAG collections (with their ingest jobs and scopes) and
+//!   memory are shared by the workspace's members, and stored under the
+//!   workspace's [owner key](ActiveWorkspace::owner) instead of the user's,
+//!   so nothing of one space is visible from another.
+//!
+//! Naming a workspace the user isn't a member of fails with a 404, as if
+//! it didn't exist.
+
+use crate::db::workspaces::{self, owner_key, WorkspaceRole};
+use crate::types::{AppError, Claims};
+use crate::AppState;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+
+/// Header naming the workspace a request acts in
+pub const HEADER: &str = "x-workspace-id";
+
+/// The space a request acts in: a workspace the user belongs to, or their
+/// personal space.
+///
+/// Use in handler signatures alongside [`AuthUser`](crate::auth::middleware::AuthUser):
+/// ```ignore
+/// async fn handler(AuthUser(claims): AuthUser, workspace: ActiveWorkspace) -> Result<...> {
+///     let owner = workspace.owner(&claims.sub);
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActiveWorkspace(Option<(String, WorkspaceRole)>);
+
+impl ActiveWorkspace {
+    /// The user's personal space
+    pub fn personal() -> Self {
+        Self(None)
+    }
+
+    /// A workspace the user has `role` in
+    pub fn new(id: impl Into<String>, role: WorkspaceRole) -> Self {
+        Self(Some((id.into(), role)))
+    }
+
+    /// ID of the workspace, or `None` for the personal space
+    pub fn id(&self) -> Option<&str> {
+        self.0.as_ref().map(|(id, _)| id.as_str())
+    }
+
+    /// The user's role in the workspace, or `None` for the personal space
+    pub fn role(&self) -> Option<WorkspaceRole> {
+        self.0.as_ref().map(|(_, role)| *role)
+    }
+
+    /// Key that shared data (custom agents, RAG collections, memory) is
+    /// stored under: the user's ID in their personal space, the
+    /// workspace's [`owner_key`](workspaces::owner_key) in a workspace.
+    pub fn owner(&self, user_id: &str) -> String {
+        match self.id() {
+            Some(id) => owner_key(id),
+            None => user_id.to_string(),
+        }
+    }
+}
+
+impl FromRequestParts<AppState> for ActiveWorkspace {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Some(header) = parts.headers.get(HEADER) else {
+            return Ok(Self::personal());
+        };
+        let id = header
+            .to_str()
+            .map(str::trim)
+            .map_err(|_| AppError::InvalidInput("Invalid X-Workspace-Id header".to_string()))?;
+        if id.is_empty() {
+            return Ok(Self::personal());
+        }
+        let claims = parts
+            .extensions
+            .get::<Claims>()
+            .ok_or_else(|| AppError::Auth("Unauthorized".to_string()))?;
+        let role = workspaces::member_role(state.tenant_db.pool(), id, &claims.sub)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Workspace {} not found", id)))?;
+        Ok(Self::new(id, role))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_owner_is_the_workspace_in_a_workspace() {
+        assert_eq!(ActiveWorkspace::personal().owner("user-1"), "user-1");
+        let workspace = ActiveWorkspace::new("ws-1", WorkspaceRole::Member);
+        assert_eq!(workspace.owner("user-1"), "workspace_ws-1");
+        assert_eq!(workspace.owner("user-2"), workspace.owner("user-1"));
+        assert_eq!(workspace.id(), Some("ws-1"));
+    }
+}
@@ -0,0 +1,155 @@
+//! Cookie-based refresh tokens for browser clients.
+//!
+//! With `cookie_refresh_tokens` in `[server.security]`, login and
+//! registration put the refresh token in an `HttpOnly` cookie scoped to
+//! `/api/auth`, out of reach of page scripts, instead of the response body.
+//! Refresh and logout then read it from the cookie.
+//!
+//! Since browsers send cookies on cross-site requests too, a request using
+//! the cookie must also prove it comes from the page: it sends the value of
+//! the readable `ares_csrf` cookie, set alongside, in an `X-CSRF-Token`
+//! header (double-submit). Requests passing the refresh token in their body
+//! don't rely on cookies and need no CSRF token.
+
+use crate::types::{AppError, Result};
+use axum::http::{header, HeaderMap, HeaderValue};
+
+/// Cookie holding the refresh token
+pub const REFRESH_COOKIE: &str = "ares_refresh";
+
+/// Cookie holding the CSRF token, readable by the UI
+pub const CSRF_COOKIE: &str = "ares_csrf";
+
+/// Header echoing the CSRF cookie
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Value of the cookie `name` in a request, if present and not empty
+pub fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+        .filter(|value| !value.is_empty())
+}
+
+/// `Set-Cookie` headers for a new refresh token valid `max_age` seconds,
+/// with a fresh CSRF token.
+pub fn session_cookies(refresh_token: &str, max_age: i64) -> HeaderMap {
+    let bytes: Vec<u8> = (0..32).map(|_| rand::random::<u8>()).collect();
+    let csrf = hex::encode(bytes);
+    set_cookies(refresh_token, &csrf, max_age)
+}
+
+/// `Set-Cookie` headers removing the session cookies
+pub fn cleared_cookies() -> HeaderMap {
+    set_cookies("", "", 0)
+}
+
+fn set_cookies(refresh_token: &str, csrf: &str, max_age: i64) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for cookie in [
+        format!(
+            "{}={}; Max-Age={}; Path=/api/auth; HttpOnly; Secure; SameSite=Strict",
+            REFRESH_COOKIE, refresh_token, max_age
+        ),
+        format!(
+            "{}={}; Max-Age={}; Path=/; Secure; SameSite=Strict",
+            CSRF_COOKIE, csrf, max_age
+        ),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(&cookie) {
+            headers.append(header::SET_COOKIE, value);
+        }
+    }
+    headers
+}
+
+/// The refresh token of a request: `body` if not empty, or else the
+/// refresh cookie, once the CSRF token is checked.
+pub fn refresh_token<'a>(headers: &'a HeaderMap, body: &'a str) -> Result<&'a str> {
+    if !body.is_empty() {
+        return Ok(body);
+    }
+    let token = cookie(headers, REFRESH_COOKIE)
+        .ok_or_else(|| AppError::Auth("Missing refresh token".to_string()))?;
+    verify_csrf(headers)?;
+    Ok(token)
+}
+
+/// Check that the `X-CSRF-Token` header matches the CSRF cookie.
+pub fn verify_csrf(headers: &HeaderMap) -> Result<()> {
+    let expected = cookie(headers, CSRF_COOKIE);
+    let sent = headers
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok());
+    match (expected, sent) {
+        (Some(expected), Some(sent)) if constant_time_eq(expected, sent) => Ok(()),
+        _ => Err(AppError::Forbidden(
+            "Missing or invalid CSRF token".to_string(),
+        )),
+    }
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(cookies: &str, csrf: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_str(cookies).unwrap());
+        if let Some(csrf) = csrf {
+            headers.insert(CSRF_HEADER, HeaderValue::from_str(csrf).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_cookie_refresh_token_requires_matching_csrf_token() {
+        let cookies = "theme=dark; ares_refresh=rt-1; ares_csrf=abc123";
+
+        let headers = request(cookies, Some("abc123"));
+        assert_eq!(refresh_token(&headers, "").unwrap(), "rt-1");
+
+        for csrf in [None, Some("abc124"), Some("abc")] {
+            let headers = request(cookies, csrf);
+            assert!(matches!(
+                refresh_token(&headers, ""),
+                Err(AppError::Forbidden(_))
+            ));
+        }
+
+        // A token in the body doesn't use the cookies
+        let headers = request(cookies, None);
+        assert_eq!(refresh_token(&headers, "rt-body").unwrap(), "rt-body");
+        assert!(matches!(
+            refresh_token(&HeaderMap::new(), ""),
+            Err(AppError::Auth(_))
+        ));
+    }
+
+    #[test]
+    fn test_session_cookies() {
+        let headers = session_cookies("rt-1", 3600);
+        let cookies: Vec<&str> = headers
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|v| v.to_str().unwrap())
+            .collect();
+        assert_eq!(cookies.len(), 2);
+        assert!(cookies[0].starts_with("ares_refresh=rt-1; Max-Age=3600; Path=/api/auth; HttpOnly"));
+        assert!(cookies[1].starts_with("ares_csrf="));
+        assert!(!cookies[1].contains("HttpOnly"));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::types::{AppError, Claims, Result, TokenResponse};
+use crate::types::{AppError, Claims, Result, Role, TokenResponse};
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
     Argon2,
@@ -79,10 +79,10 @@ impl AuthService {
             .is_ok())
     }
 
-    /// Generates access and refresh tokens for a user.
-    pub fn generate_tokens(&self, user_id: &str, email: &str) -> Result<TokenResponse> {
-        let access_token = self.generate_access_token(user_id, email)?;
-        let refresh_token = self.generate_refresh_token(user_id, email)?;
+    /// Generates access and refresh tokens for a user, embedding their role.
+    pub fn generate_tokens(&self, user_id: &str, email: &str, role: Role) -> Result<TokenResponse> {
+        let access_token = self.generate_access_token(user_id, email, role)?;
+        let refresh_token = self.generate_refresh_token(user_id, email, role)?;
 
         Ok(TokenResponse {
             access_token,
@@ -91,10 +91,11 @@ impl AuthService {
         })
     }
 
-    fn generate_access_token(&self, user_id: &str, email: &str) -> Result<String> {
+    fn generate_access_token(&self, user_id: &str, email: &str, role: Role) -> Result<String> {
         let claims = Claims {
             sub: user_id.to_string(),
             email: email.to_string(),
+            role,
             exp: (Utc::now() + Duration::seconds(self.access_expiry)).timestamp() as usize,
             iat: Utc::now().timestamp() as usize,
         };
@@ -107,10 +108,11 @@ impl AuthService {
         .map_err(|e| AppError::Auth(format!("Failed to generate token: {}", e)))
     }
 
-    fn generate_refresh_token(&self, user_id: &str, email: &str) -> Result<String> {
+    fn generate_refresh_token(&self, user_id: &str, email: &str, role: Role) -> Result<String> {
         let claims = Claims {
             sub: user_id.to_string(),
             email: email.to_string(),
+            role,
             exp: (Utc::now() + Duration::seconds(self.refresh_expiry)).timestamp() as usize,
             iat: Utc::now().timestamp() as usize,
         };
@@ -145,6 +147,11 @@ impl AuthService {
         .map_err(|e| AppError::Auth(format!("Invalid token: {}", e)))
     }
 
+    /// Refresh token validity in seconds.
+    pub fn refresh_expiry(&self) -> i64 {
+        self.refresh_expiry
+    }
+
     /// Hashes a token using SHA256 for secure storage.
     pub fn hash_token(&self, token: &str) -> String {
         use sha2::{Digest, Sha256};
@@ -224,7 +231,7 @@ mod tests {
         let email = "test@example.com";
 
         let tokens = service
-            .generate_tokens(user_id, email)
+            .generate_tokens(user_id, email, Role::Member)
             .expect("should generate tokens");
 
         assert!(
@@ -254,7 +261,7 @@ mod tests {
         let email = "user@test.com";
 
         let tokens = service
-            .generate_tokens(user_id, email)
+            .generate_tokens(user_id, email, Role::ReadOnly)
             .expect("should generate tokens");
         let claims = service
             .verify_token(&tokens.access_token)
@@ -262,6 +269,7 @@ mod tests {
 
         assert_eq!(claims.sub, user_id, "subject should match user_id");
         assert_eq!(claims.email, email, "email should match");
+        assert_eq!(claims.role, Role::ReadOnly, "role should match");
     }
 
     #[test]
@@ -281,7 +289,7 @@ mod tests {
             AuthService::new("secret-two-that-is-32-chars-long".to_string(), 900, 604800);
 
         let tokens = service1
-            .generate_tokens("user-789", "test@example.com")
+            .generate_tokens("user-789", "test@example.com", Role::Member)
             .expect("should generate");
         let result = service2.verify_token(&tokens.access_token);
 
@@ -324,7 +332,7 @@ mod tests {
     fn test_claims_expiration() {
         let service = create_test_service();
         let tokens = service
-            .generate_tokens("user", "user@example.com")
+            .generate_tokens("user", "user@example.com", Role::Member)
             .expect("should generate");
         let claims = service
             .verify_token(&tokens.access_token)
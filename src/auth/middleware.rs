@@ -1,6 +1,12 @@
 use crate::auth::jwt::AuthService;
-use crate::types::Claims;
-use axum::{extract::Request, http::StatusCode, middleware::Next, response::Response};
+use crate::db::{tenants::TenantDb, user_api_keys};
+use crate::types::{AppError, Claims, Role};
+use axum::{
+    extract::Request,
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
 use std::sync::Arc;
 
 /// Axum middleware that validates JWT tokens from the Authorization header.
@@ -34,6 +40,87 @@ pub async fn auth_middleware(auth_service: Arc<AuthService>, req: Request, next:
         .unwrap()
 }
 
+/// Axum middleware that authenticates a user's API key from the `X-API-Key`
+/// header, or else a JWT as [`auth_middleware`] does.
+///
+/// A key acts as its user, with their current role, on the routes its
+/// scopes cover; other routes, including key management, are `403
+/// Forbidden`. Each request is counted in the key's usage. Handlers read
+/// the user with [`AuthUser`] either way.
+pub async fn authenticate(
+    auth_service: Arc<AuthService>,
+    tenant_db: Arc<TenantDb>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(secret) = req
+        .headers()
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+    else {
+        return auth_middleware(auth_service, req, next).await;
+    };
+
+    let owner = match user_api_keys::authenticate(tenant_db.pool(), &secret).await {
+        Ok(Some(owner)) => owner,
+        Ok(None) => return AppError::Auth("Invalid API key".to_string()).into_response(),
+        Err(e) => return e.into_response(),
+    };
+    let path = req.uri().path();
+    let path = path.strip_prefix("/api").unwrap_or(path);
+    if !owner.key.scopes.iter().any(|scope| scope.covers(path)) {
+        return AppError::Forbidden(format!(
+            "API key '{}' is not allowed to call {}",
+            owner.key.name, path
+        ))
+        .into_response();
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let pool = tenant_db.pool().clone();
+    let key_id = owner.key.id.clone();
+    tokio::spawn(async move {
+        if let Err(e) = user_api_keys::record_use(&pool, &key_id, now).await {
+            tracing::warn!("{}", e);
+        }
+    });
+
+    let mut req = req;
+    req.extensions_mut().insert(Claims {
+        sub: owner.key.user_id,
+        email: owner.email,
+        role: owner.role,
+        exp: now as usize,
+        iat: now as usize,
+    });
+    next.run(req).await
+}
+
+/// Axum middleware that rejects users whose role doesn't allow `required`.
+///
+/// Runs inside [`auth_middleware`], reading the `Claims` it injected; roles
+/// are those embedded in the JWT when it was issued. Rejected requests get
+/// `403 Forbidden`.
+pub async fn require_role(required: Role, req: Request, next: Next) -> Response {
+    let role = req.extensions().get::<Claims>().map(|claims| claims.role);
+    match role {
+        Some(role) if role.allows(required) => next.run(req).await,
+        Some(_) => AppError::Forbidden(format!("This requires the {} role", required.as_str()))
+            .into_response(),
+        None => AppError::Auth("Unauthorized".to_string()).into_response(),
+    }
+}
+
+/// Like [`require_role`], but only for requests that change something:
+/// `GET` and `HEAD` requests are allowed for every role.
+pub async fn require_role_to_change(required: Role, req: Request, next: Next) -> Response {
+    if matches!(*req.method(), Method::GET | Method::HEAD) {
+        return next.run(req).await;
+    }
+    require_role(required, req, next).await
+}
+
 // Extractor for claims
 use axum::extract::FromRequestParts;
 use axum::http::request::Parts;
@@ -104,6 +191,49 @@ mod tests {
             }))
     }
 
+    fn create_role_app(role: Role) -> Router {
+        Router::new()
+            .route("/managed", get(protected_handler).post(protected_handler))
+            .route_layer(axum::middleware::from_fn(|req, next| {
+                require_role_to_change(Role::Member, req, next)
+            }))
+            .layer(axum::middleware::from_fn(
+                move |mut req: Request<Body>, next: Next| {
+                    req.extensions_mut().insert(Claims {
+                        sub: "user-123".to_string(),
+                        email: "test@example.com".to_string(),
+                        role,
+                        exp: 0,
+                        iat: 0,
+                    });
+                    next.run(req)
+                },
+            ))
+    }
+
+    #[tokio::test]
+    async fn test_read_only_users_can_read_but_not_change() {
+        let request = |method: &str| {
+            Request::builder()
+                .method(method)
+                .uri("/managed")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let app = create_role_app(Role::ReadOnly);
+        let response = app.clone().oneshot(request("GET")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.oneshot(request("POST")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = create_role_app(Role::Admin)
+            .oneshot(request("POST"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
     #[tokio::test]
     async fn test_middleware_no_auth_header() {
         let auth_service = create_test_auth_service();
@@ -145,7 +275,7 @@ mod tests {
     async fn test_middleware_valid_token() {
         let auth_service = create_test_auth_service();
         let tokens = auth_service
-            .generate_tokens("user-123", "test@example.com")
+            .generate_tokens("user-123", "test@example.com", Role::Member)
             .expect("should generate tokens");
 
         let app = create_test_app(auth_service);
@@ -215,7 +345,7 @@ mod tests {
             0, // Zero leeway for strict expiration checking
         ));
         let tokens = auth_service
-            .generate_tokens("user-123", "test@example.com")
+            .generate_tokens("user-123", "test@example.com", Role::Member)
             .expect("should generate tokens");
 
         // Wait for token to expire
@@ -246,7 +376,7 @@ mod tests {
             604800,
         ));
         let tokens = auth_service_a
-            .generate_tokens("user-123", "test@example.com")
+            .generate_tokens("user-123", "test@example.com", Role::Member)
             .expect("should generate tokens");
 
         // Try to verify with different secret
@@ -275,7 +405,7 @@ mod tests {
     async fn test_middleware_lowercase_bearer() {
         let auth_service = create_test_auth_service();
         let tokens = auth_service
-            .generate_tokens("user-123", "test@example.com")
+            .generate_tokens("user-123", "test@example.com", Role::Member)
             .expect("should generate tokens");
 
         let app = create_test_app(auth_service);
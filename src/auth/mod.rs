@@ -7,6 +7,7 @@
 //!
 //! - [`auth::jwt`](crate::auth::jwt) - JWT token encoding, decoding, and claims
 //! - [`auth::middleware`](crate::auth::middleware) - Axum layers and extractors for authentication
+//! - [`auth::cookies`](crate::auth::cookies) - Cookie-based refresh tokens with CSRF protection
 //!
 //! # Security Features
 //!
@@ -57,7 +58,13 @@
 //! jwt_expiry_hours = 24           # Token validity duration
 //! ```
 
+/// Refresh token cookies and CSRF checks for browser clients.
+pub mod cookies;
 /// JWT token generation, validation, and password hashing services.
 pub mod jwt;
 /// Authentication middleware and extractors for protected routes.
 pub mod middleware;
+/// Short-lived, scoped tokens for calls to MCP servers and remote agents.
+pub mod service_tokens;
+/// TOTP codes and recovery codes for multi-factor authentication.
+pub mod totp;
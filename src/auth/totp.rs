@@ -0,0 +1,200 @@
+//! Time-based one-time passwords (RFC 6238) and recovery codes.
+//!
+//! Codes are the 6-digit HMAC-SHA1 codes of 30-second steps that
+//! authenticator apps generate from a secret shared as base32. A code is
+//! accepted one step early or late, to allow for clock drift.
+
+use ring::hmac;
+
+/// Seconds per step
+const STEP_SECS: i64 = 30;
+
+/// Digits per code
+const DIGITS: u32 = 6;
+
+/// Steps before and after the current one whose codes are accepted
+const DRIFT_STEPS: i64 = 1;
+
+/// Bytes of a generated secret (160 bits, as RFC 4226 recommends)
+const SECRET_BYTES: usize = 20;
+
+/// Recovery codes generated at a time
+pub const RECOVERY_CODES: usize = 10;
+
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Generate a secret, base32-encoded
+pub fn generate_secret() -> String {
+    let bytes: Vec<u8> = (0..SECRET_BYTES).map(|_| rand::random::<u8>()).collect();
+    base32_encode(&bytes)
+}
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    out
+}
+
+/// Decode base32, ignoring case, spaces and padding
+fn base32_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = BASE32
+            .iter()
+            .position(|&b| b as char == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// Step of Unix time `now`
+pub fn step(now: i64) -> i64 {
+    now.div_euclid(STEP_SECS)
+}
+
+/// Code of `step` for the decoded `secret`
+fn code_at(secret: &[u8], step: i64) -> u32 {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret);
+    let digest = hmac::sign(&key, &step.to_be_bytes());
+    let digest = digest.as_ref();
+    // Dynamic truncation (RFC 4226, section 5.3)
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    value % 10u32.pow(DIGITS)
+}
+
+/// Check `code` against `secret` (base32) at Unix time `now`, returning the
+/// step it matched
+///
+/// Codes of steps up to `last_step` were used already and don't match, so a
+/// code can't be replayed.
+pub fn verify(secret: &str, code: &str, now: i64, last_step: i64) -> Option<i64> {
+    if !is_totp_code(code) {
+        return None;
+    }
+    let code: u32 = code.trim().parse().ok()?;
+    let secret = base32_decode(secret)?;
+    let current = step(now);
+    (current - DRIFT_STEPS..=current + DRIFT_STEPS)
+        .filter(|&step| step > last_step)
+        .find(|&step| code_at(&secret, step) == code)
+}
+
+/// Whether `code` looks like an authenticator code rather than a recovery
+/// code
+pub fn is_totp_code(code: &str) -> bool {
+    let code = code.trim();
+    code.len() == DIGITS as usize && code.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// `otpauth://` URI that authenticator apps import, usually from a QR code
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(issuer),
+        percent_encode(account),
+        secret,
+        percent_encode(issuer),
+        DIGITS,
+        STEP_SECS
+    )
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Generate a set of single-use recovery codes, such as `k7m2q-x9wpa`
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODES)
+        .map(|_| {
+            let bytes: Vec<u8> = (0..7).map(|_| rand::random::<u8>()).collect();
+            let code = base32_encode(&bytes)[..10].to_ascii_lowercase();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}
+
+/// A recovery code as stored: lowercase, without spaces or dashes
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc_6238_codes() {
+        // The SHA-1 secret of RFC 6238's test vectors, truncated to 6 digits
+        let secret = base32_encode(b"12345678901234567890");
+        assert_eq!(secret, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert_eq!(
+            base32_decode(&secret.to_lowercase()).unwrap(),
+            b"12345678901234567890"
+        );
+        for (now, code) in [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (2000000000, "279037"),
+        ] {
+            assert_eq!(verify(&secret, code, now, 0), Some(step(now)));
+        }
+        // One step of drift is allowed, and used steps are refused
+        assert_eq!(
+            verify(&secret, "081804", 1111111109 + 30, 0),
+            Some(37037036)
+        );
+        assert_eq!(verify(&secret, "081804", 1111111109 + 90, 0), None);
+        assert_eq!(verify(&secret, "081804", 1111111109, 37037036), None);
+        assert_eq!(verify(&secret, "81804", 1111111109, 0), None);
+    }
+
+    #[test]
+    fn test_enrollment_payloads() {
+        let secret = generate_secret();
+        assert_eq!(secret.len(), 32);
+        assert_eq!(
+            otpauth_uri("ARES", "ada@example.com", "ABC"),
+            "otpauth://totp/ARES:ada%40example.com?secret=ABC&issuer=ARES&algorithm=SHA1&digits=6&period=30"
+        );
+
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODES);
+        assert_eq!(codes[0].len(), 11);
+        assert!(!is_totp_code(&codes[0]));
+        assert_eq!(normalize_recovery_code(" K7M2Q-X9WPA "), "k7m2qx9wpa");
+    }
+}
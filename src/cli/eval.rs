@@ -0,0 +1,114 @@
+//! Eval command implementation
+//!
+//! Runs eval suites (see [`crate::eval`]) against a running server,
+//! printing each case's outcome, and writes a JUnit report for CI.
+
+use super::output::Output;
+use crate::eval::{junit, EvalRunner, Suite, SuiteReport};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Configuration for the eval run command
+pub struct EvalConfig {
+    /// Suite files, or directories of them
+    pub suites: Vec<PathBuf>,
+    /// Base URL of the server under test
+    pub target: String,
+    /// User API key sent in `X-API-Key`
+    pub api_key: Option<String>,
+    /// JWT sent as a bearer token
+    pub token: Option<String>,
+    /// Where to write the JUnit report
+    pub junit: Option<PathBuf>,
+    /// Per-request timeout
+    pub timeout: Duration,
+}
+
+/// The suite files named by `paths`: files as given, and the `.toml` files
+/// of directories, sorted
+fn suite_files(paths: &[PathBuf]) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let mut found: Vec<PathBuf> = std::fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        found.sort();
+        files.extend(found);
+    }
+    Ok(files)
+}
+
+fn load(path: &Path, output: &Output) -> Result<Suite, Box<dyn std::error::Error>> {
+    Suite::load(path).map_err(|e| {
+        output.error(&e.to_string());
+        e.into()
+    })
+}
+
+/// Run the suites, printing each case, and fail if any case failed
+pub async fn run(
+    config: EvalConfig,
+    output: &Output,
+) -> Result<Vec<SuiteReport>, Box<dyn std::error::Error>> {
+    let files = suite_files(&config.suites)?;
+    if files.is_empty() {
+        output.error("No eval suites found");
+        return Err("Nothing to run".into());
+    }
+    // Load every suite first, so a typo doesn't surface after a long run
+    let suites = files
+        .iter()
+        .map(|path| load(path, output))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut runner = EvalRunner::new(&config.target, config.timeout)?;
+    if let Some(key) = &config.api_key {
+        runner = runner.with_api_key(key);
+    }
+    if let Some(token) = &config.token {
+        runner = runner.with_token(token);
+    }
+
+    let mut reports = Vec::with_capacity(suites.len());
+    for suite in &suites {
+        output.header(&format!(
+            "Suite '{}' ({} cases)",
+            suite.name,
+            suite.cases.len()
+        ));
+        let report = runner.run(suite).await;
+        for case in &report.cases {
+            let elapsed = format!("{:.1} s", case.elapsed.as_secs_f64());
+            if case.passed() {
+                output.success(&format!("{} ({})", case.name, elapsed));
+                continue;
+            }
+            output.error(&format!("{} ({})", case.name, elapsed));
+            for failure in case.failures.iter().chain(&case.error) {
+                output.list_item(failure);
+            }
+        }
+        reports.push(report);
+    }
+
+    if let Some(path) = &config.junit {
+        std::fs::write(path, junit::report(&reports))?;
+        output.newline();
+        output.info(&format!("JUnit report written to {}", path.display()));
+    }
+
+    let cases: usize = reports.iter().map(|r| r.cases.len()).sum();
+    let failed: usize = reports.iter().map(|r| r.failures() + r.errors()).sum();
+    output.newline();
+    if failed > 0 {
+        output.error(&format!("{} of {} cases failed", failed, cases));
+        return Err("Eval failed".into());
+    }
+    output.complete(&format!("All {} cases passed", cases));
+    Ok(reports)
+}
@@ -3,6 +3,7 @@
 //! Scaffolds a new A.R.E.S project with all necessary configuration files.
 
 use super::output::Output;
+use super::templates::ProjectTemplate;
 use std::fs;
 use std::path::Path;
 
@@ -32,11 +33,80 @@ pub struct InitConfig {
     pub host: String,
     /// Port for the server
     pub port: u16,
+    /// Project template to scaffold, if any
+    pub template: Option<ProjectTemplate>,
+    /// Model to use for the model profiles (defaults per provider)
+    pub model: Option<String>,
+    /// Base URL of the Ollama server
+    pub ollama_url: String,
+    /// Optional features to enable
+    pub features: InitFeatures,
+}
+
+/// Optional features toggled by the init wizard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitFeatures {
+    /// Enable the web_search tool
+    pub web_search: bool,
+    /// Enable the calculator tool
+    pub calculator: bool,
+    /// Include the RAG configuration section
+    pub rag: bool,
+}
+
+impl Default for InitFeatures {
+    fn default() -> Self {
+        Self {
+            web_search: true,
+            calculator: true,
+            rag: true,
+        }
+    }
+}
+
+impl InitConfig {
+    /// Provider referenced by the generated model profiles
+    fn model_provider(&self) -> &'static str {
+        if self.provider == "openai" {
+            "openai"
+        } else {
+            "ollama-local"
+        }
+    }
+
+    /// Model used by the generated model profiles
+    fn model_name(&self) -> &str {
+        match self.model {
+            Some(ref model) => model,
+            None if self.provider == "openai" => "gpt-4o-mini",
+            None => "ministral-3:3b",
+        }
+    }
+
+    /// Tools enabled by the feature toggles
+    fn enabled_tools(&self) -> Vec<&'static str> {
+        let mut tools = Vec::new();
+        if self.features.calculator {
+            tools.push("calculator");
+        }
+        if self.features.web_search {
+            tools.push("web_search");
+        }
+        tools
+    }
+
+    /// Agents the router can dispatch to, with descriptions
+    fn routable_agents(&self) -> Vec<(&'static str, &'static str)> {
+        let mut agents = vec![("orchestrator", "General purpose agent for complex queries")];
+        if let Some(template) = self.template {
+            agents.extend(template.agents().iter().map(|a| (a.name, a.description)));
+        }
+        agents
+    }
 }
 
 /// Run the init command
 pub fn run(config: InitConfig, output: &Output) -> InitResult {
-    output.banner();
     output.header("Initializing A.R.E.S Project");
 
     let base_path = &config.path;
@@ -105,12 +175,18 @@ pub fn run(config: InitConfig, output: &Output) -> InitResult {
         create_agent_files(base_path, &config, output);
 
         // Tools
-        create_tool_files(base_path, output);
+        create_tool_files(base_path, &config, output);
 
         // Workflows
         create_workflow_files(base_path, output);
     }
 
+    // Template agents and workflows are scaffolded even with --no-examples
+    if let Some(template) = config.template {
+        output.subheader(&format!("Applying template: {}", template));
+        create_template_files(base_path, template, &config, output);
+    }
+
     // Create .gitignore if it doesn't exist
     let gitignore_path = base_path.join(".gitignore");
     if !gitignore_path.exists() {
@@ -132,10 +208,13 @@ pub fn run(config: InitConfig, output: &Output) -> InitResult {
     output.command("# Edit .env and set JWT_SECRET (min 32 chars) and API_KEY");
     output.newline();
 
-    if config.provider == "ollama" {
+    if config.provider != "openai" {
         output.info("2. Start Ollama (if not running):");
         output.command("ollama serve");
-        output.command("ollama pull ministral-3:3b  # or your preferred model");
+        output.command(&format!(
+            "ollama pull {}  # or your preferred model",
+            config.model_name()
+        ));
         output.newline();
     }
 
@@ -173,7 +252,7 @@ default_model = "gpt-4o-mini"
         r#"# Ollama - Local inference (default)
 [providers.ollama-local]
 type = "ollama"
-base_url = "http://localhost:11434"
+base_url = "{ollama_url}"
 default_model = "ministral-3:3b"
 
 # OpenAI API (set OPENAI_API_KEY in .env)
@@ -188,21 +267,40 @@ default_model = "gpt-4o-mini"
         r#"# Ollama - Local inference (no API key required)
 [providers.ollama-local]
 type = "ollama"
-base_url = "http://localhost:11434"
+base_url = "{ollama_url}"
 default_model = "ministral-3:3b"
 "#
     };
-
-    let model_provider = if config.provider == "openai" {
-        "openai"
-    } else {
-        "ollama-local"
-    };
-
-    let model_name = if config.provider == "openai" {
-        "gpt-4o-mini"
+    let provider_section = provider_section.replace("{ollama_url}", &config.ollama_url);
+
+    let model_provider = config.model_provider();
+    let model_name = config.model_name();
+
+    let router_agents: String = config
+        .routable_agents()
+        .iter()
+        .map(|(name, description)| format!("- {}: {}\n", name, description))
+        .collect();
+
+    let tools = config.enabled_tools();
+    let orchestrator_tools = tools
+        .iter()
+        .map(|t| format!("\"{}\"", t))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let rag_section = if config.features.rag {
+        r#"
+# =============================================================================
+# RAG Configuration
+# =============================================================================
+[rag]
+embedding_model = "BAAI/bge-small-en-v1.5"
+chunk_size = 1000
+chunk_overlap = 200
+"#
     } else {
-        "ministral-3:3b"
+        ""
     };
 
     format!(
@@ -269,12 +367,12 @@ max_tokens = 1024
 # Tools Configuration
 # =============================================================================
 [tools.calculator]
-enabled = true
+enabled = {calculator_enabled}
 description = "Performs basic arithmetic operations (+, -, *, /)"
 timeout_secs = 10
 
 [tools.web_search]
-enabled = true
+enabled = {web_search_enabled}
 description = "Search the web using DuckDuckGo (no API key required)"
 timeout_secs = 30
 
@@ -290,14 +388,13 @@ system_prompt = """
 You are a routing agent that classifies user queries.
 
 Available agents:
-- orchestrator: General purpose agent for complex queries
-
+{router_agents}
 Respond with ONLY the agent name (one word, lowercase).
 """
 
 [agents.orchestrator]
 model = "powerful"
-tools = ["calculator", "web_search"]
+tools = [{orchestrator_tools}]
 max_tool_iterations = 10
 parallel_tools = false
 system_prompt = """
@@ -320,15 +417,7 @@ entry_agent = "router"
 fallback_agent = "orchestrator"
 max_depth = 3
 max_iterations = 5
-
-# =============================================================================
-# RAG Configuration
-# =============================================================================
-[rag]
-embedding_model = "BAAI/bge-small-en-v1.5"
-chunk_size = 1000
-chunk_overlap = 200
-
+{rag_section}
 # =============================================================================
 # Dynamic Configuration Paths (TOON Files)
 # =============================================================================
@@ -346,6 +435,11 @@ watch_interval_ms = 1000
         provider_section = provider_section,
         model_provider = model_provider,
         model_name = model_name,
+        router_agents = router_agents,
+        orchestrator_tools = orchestrator_tools,
+        calculator_enabled = config.features.calculator,
+        web_search_enabled = config.features.web_search,
+        rag_section = rag_section,
     )
 }
 
@@ -408,17 +502,8 @@ Thumbs.db
 }
 
 fn create_model_files(base_path: &Path, config: &InitConfig, output: &Output) {
-    let model_provider = if config.provider == "openai" {
-        "openai"
-    } else {
-        "ollama-local"
-    };
-
-    let model_name = if config.provider == "openai" {
-        "gpt-4o-mini"
-    } else {
-        "ministral-3:3b"
-    };
+    let model_provider = config.model_provider();
+    let model_name = config.model_name();
 
     let models = [
         (
@@ -473,27 +558,52 @@ max_tokens: 1024
 }
 
 fn create_agent_files(base_path: &Path, config: &InitConfig, output: &Output) {
+    let template_agents: String = config
+        .template
+        .map(|t| {
+            t.agents()
+                .iter()
+                .map(|a| format!("\\n- {}: {}", a.name, a.description))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let tools = config.enabled_tools();
+    let orchestrator_tools = if tools.is_empty() {
+        "tools[0]:\n".to_string()
+    } else {
+        tools
+            .iter()
+            .enumerate()
+            .map(|(i, t)| format!("tools[{}]: {}\n", i, t))
+            .collect()
+    };
+
     let agents = [
         (
             "router.toon",
-            r#"name: router
+            format!(
+                r#"name: router
 model: fast
 max_tool_iterations: 1
 parallel_tools: false
 tools[0]:
-system_prompt: "You are a routing agent that classifies user queries and routes them to the appropriate specialized agent.\n\nAvailable agents:\n- orchestrator: Complex queries requiring multiple steps or research\n\nAnalyze the user's query and respond with ONLY the agent name (lowercase, one word)."
-"#.to_string(),
+system_prompt: "You are a routing agent that classifies user queries and routes them to the appropriate specialized agent.\n\nAvailable agents:\n- orchestrator: Complex queries requiring multiple steps or research{template_agents}\n\nAnalyze the user's query and respond with ONLY the agent name (lowercase, one word)."
+"#,
+                template_agents = template_agents
+            ),
         ),
         (
             "orchestrator.toon",
-            r#"name: orchestrator
+            format!(
+                r#"name: orchestrator
 model: powerful
 max_tool_iterations: 10
 parallel_tools: false
-tools[0]: calculator
-tools[1]: web_search
-system_prompt: "You are an orchestrator agent for complex queries.\n\nCapabilities:\n- Break down complex requests\n- Perform web searches\n- Execute calculations\n- Synthesize information\n\nProvide comprehensive, well-structured answers."
-"#.to_string(),
+{orchestrator_tools}system_prompt: "You are an orchestrator agent for complex queries.\n\nCapabilities:\n- Break down complex requests\n- Perform web searches\n- Execute calculations\n- Synthesize information\n\nProvide comprehensive, well-structured answers."
+"#,
+                orchestrator_tools = orchestrator_tools
+            ),
         ),
     ];
 
@@ -507,23 +617,68 @@ system_prompt: "You are an orchestrator agent for complex queries.\n\nCapabiliti
     }
 }
 
-fn create_tool_files(base_path: &Path, output: &Output) {
+fn create_template_files(
+    base_path: &Path,
+    template: ProjectTemplate,
+    config: &InitConfig,
+    output: &Output,
+) {
+    for agent in template.agents() {
+        let filename = format!("{}.toon", agent.name);
+        let path = base_path.join("config/agents").join(&filename);
+        let result = agent
+            .to_config()
+            .to_toon()
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                write_file(&path, &content, config.force).map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(()) => output.created("agent", &format!("config/agents/{}", filename)),
+            Err(e) => output.warning(&format!("Failed to create {}: {}", filename, e)),
+        }
+    }
+
+    for workflow in template.workflows() {
+        let filename = format!("{}.toon", workflow.name);
+        let path = base_path.join("config/workflows").join(&filename);
+        let result = workflow
+            .to_config()
+            .to_toon()
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                write_file(&path, &content, config.force).map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(()) => output.created("workflow", &format!("config/workflows/{}", filename)),
+            Err(e) => output.warning(&format!("Failed to create {}: {}", filename, e)),
+        }
+    }
+}
+
+fn create_tool_files(base_path: &Path, config: &InitConfig, output: &Output) {
     let tools = [
         (
             "calculator.toon",
-            r#"name: calculator
-enabled: true
+            format!(
+                r#"name: calculator
+enabled: {}
 description: Performs basic arithmetic operations (+, -, *, /)
 timeout_secs: 10
 "#,
+                config.features.calculator
+            ),
         ),
         (
             "web_search.toon",
-            r#"name: web_search
-enabled: true
+            format!(
+                r#"name: web_search
+enabled: {}
 description: Search the web using DuckDuckGo (no API key required)
 timeout_secs: 30
 "#,
+                config.features.web_search
+            ),
         ),
     ];
 
@@ -584,6 +739,10 @@ mod tests {
             provider: "ollama".to_string(),
             host: "127.0.0.1".to_string(),
             port: 3000,
+            template: None,
+            model: None,
+            ollama_url: "http://localhost:11434".to_string(),
+            features: InitFeatures::default(),
         }
     }
 
@@ -597,6 +756,10 @@ mod tests {
             provider: "ollama".to_string(),
             host: "127.0.0.1".to_string(),
             port: 3000,
+            template: None,
+            model: None,
+            ollama_url: "http://localhost:11434".to_string(),
+            features: InitFeatures::default(),
         };
 
         assert_eq!(config.path, std::path::PathBuf::from("/tmp/test"));
@@ -639,6 +802,10 @@ mod tests {
             provider: "ollama".to_string(),
             host: "127.0.0.1".to_string(),
             port: 3000,
+            template: None,
+            model: None,
+            ollama_url: "http://localhost:11434".to_string(),
+            features: InitFeatures::default(),
         };
 
         let content = generate_ares_toml(&config);
@@ -660,6 +827,10 @@ mod tests {
             provider: "openai".to_string(),
             host: "0.0.0.0".to_string(),
             port: 8080,
+            template: None,
+            model: None,
+            ollama_url: "http://localhost:11434".to_string(),
+            features: InitFeatures::default(),
         };
 
         let content = generate_ares_toml(&config);
@@ -680,6 +851,10 @@ mod tests {
             provider: "both".to_string(),
             host: "127.0.0.1".to_string(),
             port: 3000,
+            template: None,
+            model: None,
+            ollama_url: "http://localhost:11434".to_string(),
+            features: InitFeatures::default(),
         };
 
         let content = generate_ares_toml(&config);
@@ -790,6 +965,10 @@ mod tests {
             provider: "ollama".to_string(),
             host: "127.0.0.1".to_string(),
             port: 3000,
+            template: None,
+            model: None,
+            ollama_url: "http://localhost:11434".to_string(),
+            features: InitFeatures::default(),
         };
         let output = Output::no_color();
 
@@ -841,6 +1020,10 @@ mod tests {
             provider: "ollama".to_string(),
             host: "127.0.0.1".to_string(),
             port: 3000,
+            template: None,
+            model: None,
+            ollama_url: "http://localhost:11434".to_string(),
+            features: InitFeatures::default(),
         };
         let output = Output::no_color();
 
@@ -857,4 +1040,51 @@ mod tests {
         assert!(content.contains("[server]"));
         assert!(!content.contains("existing"));
     }
+
+    #[test]
+    fn test_run_with_template_scaffolds_agents_and_workflows() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let mut config = create_test_config(&temp_dir);
+        config.template = Some(ProjectTemplate::ResearchAssistant);
+        config.model = Some("qwen3:8b".to_string());
+        let output = Output::no_color();
+
+        assert!(matches!(run(config, &output), InitResult::Success));
+
+        let agents = temp_dir.path().join("config/agents");
+        assert!(agents.join("researcher.toon").exists());
+        assert!(agents.join("synthesizer.toon").exists());
+        assert!(temp_dir
+            .path()
+            .join("config/workflows/deep-research.toon")
+            .exists());
+
+        let toml = fs::read_to_string(temp_dir.path().join("ares.toml")).expect("Failed to read");
+        assert!(toml.contains("- researcher: "));
+        assert!(toml.contains("model = \"qwen3:8b\""));
+        let router = fs::read_to_string(agents.join("router.toon")).expect("Failed to read");
+        assert!(router.contains("- synthesizer: "));
+    }
+
+    #[test]
+    fn test_generate_ares_toml_feature_toggles() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let mut config = create_test_config(&temp_dir);
+        config.features = InitFeatures {
+            web_search: false,
+            calculator: true,
+            rag: false,
+        };
+        config.ollama_url = "http://gpu-box:11434".to_string();
+
+        let content = generate_ares_toml(&config);
+        let parsed: crate::utils::toml_config::AresConfig =
+            toml::from_str(&content).expect("generated ares.toml must parse");
+
+        assert!(!parsed.tools["web_search"].enabled);
+        assert!(parsed.tools["calculator"].enabled);
+        assert_eq!(parsed.agents["orchestrator"].tools, vec!["calculator"]);
+        assert!(!content.contains("[rag]"));
+        assert!(content.contains("base_url = \"http://gpu-box:11434\""));
+    }
 }
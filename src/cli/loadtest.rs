@@ -0,0 +1,270 @@
+//! Loadtest command implementation
+//!
+//! Replays the user messages of an LLM call log (see
+//! [`crate::llm::call_log`]) against a running server's `/api/chat`, with
+//! a fixed number of requests in flight, and reports latency percentiles
+//! and error rates. Whether the target answers with real providers or the
+//! mock provider is up to its configuration.
+
+use super::output::Output;
+use crate::llm::call_log::CallRecord;
+use futures::StreamExt;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Configuration for the loadtest command
+pub struct LoadTestConfig {
+    /// LLM call log to replay
+    pub log: PathBuf,
+    /// Base URL of the server under test
+    pub target: String,
+    /// Requests in flight at once
+    pub concurrency: usize,
+    /// Requests to send, cycling through the recorded messages
+    pub requests: Option<usize>,
+    /// User API key sent in `X-API-Key`
+    pub api_key: Option<String>,
+    /// JWT sent as a bearer token
+    pub token: Option<String>,
+    /// Agent to send the messages to
+    pub agent: Option<String>,
+    /// Per-request timeout
+    pub timeout: Duration,
+}
+
+/// Outcome of one replayed request
+struct Sample {
+    latency: Duration,
+    /// HTTP status, or `None` when no response arrived
+    status: Option<u16>,
+}
+
+impl Sample {
+    fn is_error(&self) -> bool {
+        !matches!(self.status, Some(200..=299))
+    }
+}
+
+/// Results of a load test
+#[derive(Debug)]
+pub struct LoadTestReport {
+    /// Requests sent
+    pub requests: usize,
+    /// Requests without a successful response
+    pub errors: usize,
+    /// Response count per outcome: an HTTP status, or `network error`
+    pub outcomes: BTreeMap<String, usize>,
+    /// Median latency
+    pub p50: Duration,
+    /// 95th percentile latency
+    pub p95: Duration,
+    /// 99th percentile latency
+    pub p99: Duration,
+    /// Wall-clock time of the whole run
+    pub elapsed: Duration,
+}
+
+impl LoadTestReport {
+    fn new(samples: &[Sample], elapsed: Duration) -> Self {
+        let mut latencies: Vec<Duration> = samples.iter().map(|s| s.latency).collect();
+        latencies.sort();
+        let mut outcomes = BTreeMap::new();
+        for sample in samples {
+            let outcome = match sample.status {
+                Some(status) => status.to_string(),
+                None => "network error".to_string(),
+            };
+            *outcomes.entry(outcome).or_insert(0) += 1;
+        }
+        Self {
+            requests: samples.len(),
+            errors: samples.iter().filter(|s| s.is_error()).count(),
+            outcomes,
+            p50: percentile(&latencies, 50.0),
+            p95: percentile(&latencies, 95.0),
+            p99: percentile(&latencies, 99.0),
+            elapsed,
+        }
+    }
+
+    /// Share of requests that failed, from 0 to 1
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / self.requests as f64
+    }
+
+    /// Requests completed per second
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        self.requests as f64 / secs
+    }
+}
+
+/// Nearest-rank percentile of sorted latencies
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// The chat messages of a call log, in order.
+///
+/// One chat message makes several LLM calls (routing, the agent, each tool
+/// round), all ending in the same user message, so repeats are dropped.
+fn recorded_messages(records: &[CallRecord]) -> Vec<String> {
+    let mut messages: Vec<String> = Vec::new();
+    for message in records.iter().filter_map(CallRecord::user_message) {
+        if !message.trim().is_empty() && messages.last().map(String::as_str) != Some(message) {
+            messages.push(message.to_string());
+        }
+    }
+    messages
+}
+
+/// Send one chat message and time it
+async fn send(
+    client: &reqwest::Client,
+    url: &str,
+    config: &LoadTestConfig,
+    message: &str,
+) -> Sample {
+    let mut body = json!({ "message": message });
+    if let Some(agent) = &config.agent {
+        body["agent_type"] = json!(agent);
+    }
+    let mut request = client.post(url).json(&body);
+    if let Some(key) = &config.api_key {
+        request = request.header("X-API-Key", key);
+    }
+    if let Some(token) = &config.token {
+        request = request.bearer_auth(token);
+    }
+
+    let started = Instant::now();
+    let status = match request.send().await {
+        // Read the body so the latency covers the whole answer
+        Ok(response) => {
+            let status = response.status().as_u16();
+            response.bytes().await.ok().map(|_| status)
+        }
+        Err(_) => None,
+    };
+    Sample {
+        latency: started.elapsed(),
+        status,
+    }
+}
+
+/// Run the load test, printing its progress and report
+pub async fn run(
+    config: LoadTestConfig,
+    output: &Output,
+) -> Result<LoadTestReport, Box<dyn std::error::Error>> {
+    let records = crate::llm::call_log::read_records(&config.log)?;
+    let messages = recorded_messages(&records);
+    if messages.is_empty() {
+        output.error(&format!(
+            "No chat messages recorded in {}",
+            config.log.display()
+        ));
+        output.hint("Set llm_call_log in [server] and send the server some traffic first");
+        return Err("Nothing to replay".into());
+    }
+
+    let total = config.requests.unwrap_or(messages.len());
+    let concurrency = config.concurrency.max(1);
+    let url = format!("{}/api/chat", config.target.trim_end_matches('/'));
+    let client = reqwest::Client::builder().timeout(config.timeout).build()?;
+
+    output.header(&format!("Load testing {}", url));
+    output.kv("Recorded messages", &messages.len().to_string());
+    output.kv("Requests", &total.to_string());
+    output.kv("Concurrency", &concurrency.to_string());
+    output.newline();
+
+    let started = Instant::now();
+    let samples: Vec<Sample> = futures::stream::iter(0..total)
+        .map(|i| send(&client, &url, &config, &messages[i % messages.len()]))
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+    let report = LoadTestReport::new(&samples, started.elapsed());
+
+    let ms = |d: Duration| format!("{:.1} ms", d.as_secs_f64() * 1000.0);
+    output.subheader("Latency");
+    output.kv("p50", &ms(report.p50));
+    output.kv("p95", &ms(report.p95));
+    output.kv("p99", &ms(report.p99));
+    output.subheader("Results");
+    output.kv(
+        "Errors",
+        &format!(
+            "{} of {} ({:.1}%)",
+            report.errors,
+            report.requests,
+            report.error_rate() * 100.0
+        ),
+    );
+    output.kv("Throughput", &format!("{:.2} req/s", report.throughput()));
+    output.kv("Elapsed", &format!("{:.1} s", report.elapsed.as_secs_f64()));
+    output.table_header(&["Outcome", "Count"]);
+    for (outcome, count) in &report.outcomes {
+        output.table_row(&[outcome, &count.to_string()]);
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::coordinator::ConversationMessage;
+
+    fn record(user: &str) -> CallRecord {
+        CallRecord {
+            timestamp: 0,
+            model: "mock".to_string(),
+            stream: false,
+            messages: vec![
+                ConversationMessage::system("Route the message"),
+                ConversationMessage::user(user),
+            ],
+            tools: vec![],
+            response_chars: 0,
+            tool_calls: 0,
+        }
+    }
+
+    #[test]
+    fn test_repeated_calls_for_one_message_are_replayed_once() {
+        let records = [record("hi"), record("hi"), record("bye"), record("hi")];
+        assert_eq!(recorded_messages(&records), ["hi", "bye", "hi"]);
+    }
+
+    #[test]
+    fn test_report_percentiles_and_error_rate() {
+        let samples: Vec<Sample> = (1..=100)
+            .map(|ms| Sample {
+                latency: Duration::from_millis(ms),
+                status: Some(if ms % 10 == 0 { 500 } else { 200 }),
+            })
+            .collect();
+        let report = LoadTestReport::new(&samples, Duration::from_secs(2));
+        assert_eq!(report.p50, Duration::from_millis(50));
+        assert_eq!(report.p95, Duration::from_millis(95));
+        assert_eq!(report.p99, Duration::from_millis(99));
+        assert_eq!(report.errors, 10);
+        assert_eq!(report.outcomes["500"], 10);
+        assert!((report.error_rate() - 0.1).abs() < f64::EPSILON);
+        assert!((report.throughput() - 50.0).abs() < f64::EPSILON);
+    }
+}
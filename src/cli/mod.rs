@@ -3,11 +3,16 @@
 //! Provides command-line interface parsing and handling for the ares-server binary.
 //! Uses clap for argument parsing and owo-colors for colored terminal output.
 
+pub mod eval;
 pub mod init;
+pub mod loadtest;
 pub mod output;
+pub mod templates;
+pub mod wizard;
 
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
+use templates::ProjectTemplate;
 
 /// A.R.E.S - Agentic Retrieval Enhanced Server
 ///
@@ -23,9 +28,12 @@ use std::path::PathBuf;
                   tool calling, RAG (Retrieval Augmented Generation), and MCP integration.\n\n\
                   Run without arguments to start the server, or use 'init' to scaffold a new project.",
     after_help = "EXAMPLES:\n    \
-                  ares-server init              # Scaffold a new A.R.E.S project\n    \
+                  ares-server init              # Scaffold a new A.R.E.S project (interactive)\n    \
+                  ares-server init --yes --template research-assistant\n    \
                   ares-server init --minimal    # Scaffold with minimal configuration\n    \
                   ares-server                   # Start the server (requires ares.toml)\n    \
+                  ares-server loadtest llm_calls.jsonl --concurrency 20\n    \
+                  ares-server eval run evals/ --junit eval-report.xml\n    \
                   ares-server --config my.toml  # Use a custom config file"
 )]
 pub struct Cli {
@@ -56,7 +64,9 @@ pub enum Commands {
     /// Initialize a new A.R.E.S project with configuration files
     ///
     /// Creates ares.toml and the config/ directory structure with
-    /// all necessary files for running an A.R.E.S server.
+    /// all necessary files for running an A.R.E.S server. When run in a
+    /// terminal, an interactive wizard asks for the provider, model,
+    /// template and features; pass --yes to use the flags as given.
     Init {
         /// Directory to initialize (defaults to current directory)
         #[arg(default_value = ".")]
@@ -85,6 +95,34 @@ pub enum Commands {
         /// Port for the server
         #[arg(long, default_value = "3000")]
         port: u16,
+
+        /// Project template to scaffold (rag-chatbot, research-assistant, coding-agent)
+        #[arg(short, long)]
+        template: Option<ProjectTemplate>,
+
+        /// Model to use for the fast/balanced/powerful model profiles
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Base URL of the Ollama server
+        #[arg(long, default_value = "http://localhost:11434")]
+        ollama_url: String,
+
+        /// Disable the web_search tool
+        #[arg(long)]
+        no_web_search: bool,
+
+        /// Disable the calculator tool
+        #[arg(long)]
+        no_calculator: bool,
+
+        /// Omit the RAG configuration section
+        #[arg(long)]
+        no_rag: bool,
+
+        /// Skip the interactive wizard and use the flags as given
+        #[arg(short, long)]
+        yes: bool,
     },
 
     /// Show configuration information
@@ -101,6 +139,71 @@ pub enum Commands {
     /// Manage agents
     #[command(subcommand)]
     Agent(AgentCommands),
+
+    /// Manage GGUF models for the llamacpp provider
+    ///
+    /// Models are downloaded from Hugging Face into the models directory
+    /// (ARES_MODELS_DIR, default ./models) and can then be referenced from
+    /// ares.toml as `model = "owner/repo/file.gguf"`.
+    #[command(subcommand)]
+    Models(ModelCommands),
+
+    /// Replay recorded chat traffic against a server and report latencies
+    ///
+    /// Sends the user messages of an LLM call log (written by a server with
+    /// `llm_call_log` set in [server]) to the target's /api/chat, and reports
+    /// p50/p95/p99 latency and error rates. Point it at a server configured
+    /// with a `mock` provider to test ARES itself, or with real providers to
+    /// test end to end.
+    Loadtest {
+        /// LLM call log to replay
+        log: PathBuf,
+
+        /// Base URL of the server under test
+        #[arg(long, default_value = "http://127.0.0.1:3000")]
+        target: String,
+
+        /// Requests in flight at once
+        #[arg(long, default_value = "10")]
+        concurrency: usize,
+
+        /// Requests to send, cycling through the log (default: each message once)
+        #[arg(long)]
+        requests: Option<usize>,
+
+        /// User API key to authenticate with
+        #[arg(long, env = "ARES_API_KEY", hide_env_values = true)]
+        api_key: Option<String>,
+
+        /// JWT to authenticate with, instead of an API key
+        #[arg(long)]
+        token: Option<String>,
+
+        /// Agent to send the messages to (default: routed)
+        #[arg(long)]
+        agent: Option<String>,
+
+        /// Per-request timeout in seconds
+        #[arg(long, default_value = "120")]
+        timeout: u64,
+    },
+
+    /// Run declarative eval suites against a server
+    #[command(subcommand)]
+    Eval(EvalCommands),
+
+    /// Inspect and apply database schema migrations
+    ///
+    /// The server applies pending migrations at startup; run `db migrate`
+    /// to apply them ahead of an upgrade instead, and `db status` to see
+    /// which are applied.
+    #[command(subcommand)]
+    Db(DbCommands),
+
+    /// Check and repair the ares-vector database
+    #[cfg(feature = "ares-vector")]
+    #[command(subcommand)]
+    Vectors(VectorCommands),
 }
 
 /// Agent management subcommands
@@ -116,6 +219,169 @@ pub enum AgentCommands {
     },
 }
 
+/// GGUF model management subcommands
+#[derive(Subcommand, Debug)]
+pub enum ModelCommands {
+    /// Download a GGUF model from Hugging Face, resuming partial downloads
+    Pull {
+        /// Model reference: owner/repo[/file.gguf][@revision]
+        reference: String,
+
+        /// Models directory (defaults to ARES_MODELS_DIR or ./models)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+
+    /// List downloaded models
+    List {
+        /// Models directory (defaults to ARES_MODELS_DIR or ./models)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+
+    /// Delete a downloaded model
+    Remove {
+        /// Model reference: owner/repo/file.gguf
+        reference: String,
+
+        /// Models directory (defaults to ARES_MODELS_DIR or ./models)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+}
+
+/// Eval suite subcommands
+#[derive(Subcommand, Debug)]
+pub enum EvalCommands {
+    /// Run eval suites and report each case
+    ///
+    /// Each suite is a TOML file of conversations whose turns are sent to
+    /// the target's /api/chat and checked: the answering agent, text the
+    /// answer contains or matches, tools called and latency. Whether the
+    /// target answers with live models or the mock provider is up to its
+    /// configuration. Exits with an error when a case fails.
+    Run {
+        /// Suite files, or directories of them
+        #[arg(required = true)]
+        suites: Vec<PathBuf>,
+
+        /// Base URL of the server under test
+        #[arg(long, default_value = "http://127.0.0.1:3000")]
+        target: String,
+
+        /// User API key to authenticate with
+        #[arg(long, env = "ARES_API_KEY", hide_env_values = true)]
+        api_key: Option<String>,
+
+        /// JWT to authenticate with, instead of an API key
+        #[arg(long)]
+        token: Option<String>,
+
+        /// Write a JUnit XML report to this file
+        #[arg(long)]
+        junit: Option<PathBuf>,
+
+        /// Per-request timeout in seconds
+        #[arg(long, default_value = "120")]
+        timeout: u64,
+    },
+}
+
+/// Database subcommands
+#[derive(Subcommand, Debug)]
+pub enum DbCommands {
+    /// List the migrations and whether each is applied
+    ///
+    /// Exits with an error when a migration is pending, failed or was
+    /// modified after it was applied.
+    Status {
+        /// Database to inspect (default: DATABASE_URL, as the server)
+        #[arg(long)]
+        database_url: Option<String>,
+    },
+
+    /// Apply the pending migrations
+    Migrate {
+        /// Database to migrate (default: DATABASE_URL, as the server)
+        #[arg(long)]
+        database_url: Option<String>,
+    },
+
+    /// Write a checksummed archive of the database and vector store
+    ///
+    /// Safe to run while the server is running: tables are copied in one
+    /// consistent snapshot.
+    Backup {
+        /// Archive to create (a .tar.gz)
+        path: PathBuf,
+
+        /// Database to back up (default: DATABASE_URL, as the server)
+        #[arg(long)]
+        database_url: Option<String>,
+
+        /// Vector database directory (defaults to rag.vector_path from the config)
+        #[arg(long)]
+        vector_path: Option<PathBuf>,
+    },
+
+    /// Re-encrypt sensitive data under the current [encryption] key
+    ///
+    /// Seals memory facts, preferences, TOTP secrets and uploaded files
+    /// sealed with a previous key or stored before encryption was
+    /// configured. Running servers must already have the new key.
+    RotateKey {
+        /// Database to re-encrypt (default: DATABASE_URL, as the server)
+        #[arg(long)]
+        database_url: Option<String>,
+    },
+
+    /// Restore an archive written by `db backup`
+    ///
+    /// Checks every checksum before changing anything. Stop the server
+    /// first; the database and vector directory must be empty unless
+    /// --force is given.
+    Restore {
+        /// Archive to restore
+        path: PathBuf,
+
+        /// Database to restore into (default: DATABASE_URL, as the server)
+        #[arg(long)]
+        database_url: Option<String>,
+
+        /// Vector database directory (defaults to rag.vector_path from the config)
+        #[arg(long)]
+        vector_path: Option<PathBuf>,
+
+        /// Replace existing data instead of requiring an empty database
+        #[arg(long)]
+        force: bool,
+
+        /// Only check the archive's checksums; restore nothing
+        #[arg(long)]
+        verify_only: bool,
+    },
+}
+
+/// ares-vector database subcommands
+#[cfg(feature = "ares-vector")]
+#[derive(Subcommand, Debug)]
+pub enum VectorCommands {
+    /// Check collection files, ID mappings and HNSW graphs for corruption
+    ///
+    /// Exits with an error when problems remain. With --repair, collections
+    /// with a damaged graph or index, or invalid stored vectors, are rebuilt
+    /// from their stored vectors and rewritten.
+    Verify {
+        /// Vector database directory (defaults to rag.vector_path from the config)
+        #[arg(long)]
+        path: Option<PathBuf>,
+
+        /// Rebuild the collections that need it
+        #[arg(long)]
+        repair: bool,
+    },
+}
+
 impl Cli {
     /// Parse CLI arguments
     pub fn parse_args() -> Self {
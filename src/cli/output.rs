@@ -243,6 +243,71 @@ impl Output {
         }
     }
 
+    /// Prompt for a yes/no answer, returning `default` on empty input
+    pub fn toggle(&self, message: &str, default: bool) -> bool {
+        let hint = if default { "[Y/n]" } else { "[y/N]" };
+        match self.ask(message, hint).to_lowercase().as_str() {
+            "" => default,
+            answer => answer == "y" || answer == "yes",
+        }
+    }
+
+    /// Prompt for free text, returning `default` on empty input
+    pub fn prompt(&self, message: &str, default: &str) -> String {
+        let answer = self.ask(message, &format!("[{}]", default));
+        if answer.is_empty() {
+            default.to_string()
+        } else {
+            answer
+        }
+    }
+
+    /// Prompt to pick one of `options` by number, returning its index
+    ///
+    /// Empty or invalid input selects `default`.
+    pub fn select(&self, message: &str, options: &[String], default: usize) -> usize {
+        for (i, option) in options.iter().enumerate() {
+            let marker = if i == default { "*" } else { " " };
+            if self.colored {
+                println!(
+                    "    {} {} {}",
+                    marker.green(),
+                    format!("{})", i + 1).dimmed(),
+                    option
+                );
+            } else {
+                println!("    {} {}) {}", marker, i + 1, option);
+            }
+        }
+
+        self.ask(message, &format!("[{}]", default + 1))
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (1..=options.len()).contains(n))
+            .map(|n| n - 1)
+            .unwrap_or(default)
+    }
+
+    /// Print a question and read one trimmed line from stdin
+    fn ask(&self, message: &str, hint: &str) -> String {
+        if self.colored {
+            print!(
+                "  {} {} {}: ",
+                "?".bright_yellow().bold(),
+                message.bright_white(),
+                hint.dimmed()
+            );
+        } else {
+            print!("  [?] {} {}: ", message, hint);
+        }
+
+        io::stdout().flush().ok();
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).ok();
+        input.trim().to_string()
+    }
+
     /// Print a table header row
     pub fn table_header(&self, columns: &[&str]) {
         if self.colored {
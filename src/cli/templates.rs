@@ -0,0 +1,231 @@
+//! Project templates for the init command
+//!
+//! Each template scaffolds a set of agents and workflows (as TOON files)
+//! tuned for a particular kind of application.
+
+use crate::utils::toon_config::{ToonAgentConfig, ToonWorkflowConfig};
+use std::fmt;
+use std::str::FromStr;
+
+/// A project template selectable with `ares-server init --template <name>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectTemplate {
+    /// Chatbot that answers from ingested documents
+    RagChatbot,
+    /// Web research assistant with search and synthesis agents
+    ResearchAssistant,
+    /// Coding assistant with a coder and a reviewer agent
+    CodingAgent,
+}
+
+/// An agent scaffolded by a template
+pub struct TemplateAgent {
+    /// Agent name (also the TOON file stem)
+    pub name: &'static str,
+    /// Model reference (fast, balanced or powerful)
+    pub model: &'static str,
+    /// Tools the agent may call
+    pub tools: &'static [&'static str],
+    /// One-line description, used in the router prompt
+    pub description: &'static str,
+    /// System prompt
+    pub system_prompt: &'static str,
+}
+
+/// A workflow scaffolded by a template
+pub struct TemplateWorkflow {
+    /// Workflow name (also the TOON file stem)
+    pub name: &'static str,
+    /// Agent that receives the request first
+    pub entry_agent: &'static str,
+    /// Agent used when the entry agent cannot handle the request
+    pub fallback_agent: Option<&'static str>,
+    /// Whether subagents may run in parallel
+    pub parallel_subagents: bool,
+}
+
+impl ProjectTemplate {
+    /// All available templates, in display order
+    pub const ALL: [ProjectTemplate; 3] = [
+        ProjectTemplate::RagChatbot,
+        ProjectTemplate::ResearchAssistant,
+        ProjectTemplate::CodingAgent,
+    ];
+
+    /// Template name as used on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProjectTemplate::RagChatbot => "rag-chatbot",
+            ProjectTemplate::ResearchAssistant => "research-assistant",
+            ProjectTemplate::CodingAgent => "coding-agent",
+        }
+    }
+
+    /// Short human-readable description
+    pub fn description(&self) -> &'static str {
+        match self {
+            ProjectTemplate::RagChatbot => "Chatbot that answers from your ingested documents",
+            ProjectTemplate::ResearchAssistant => "Web research with search and synthesis agents",
+            ProjectTemplate::CodingAgent => "Coding assistant with coder and reviewer agents",
+        }
+    }
+
+    /// Whether the template needs the RAG configuration section
+    pub fn uses_rag(&self) -> bool {
+        matches!(self, ProjectTemplate::RagChatbot)
+    }
+
+    /// Agents scaffolded by this template
+    pub fn agents(&self) -> Vec<TemplateAgent> {
+        match self {
+            ProjectTemplate::RagChatbot => vec![TemplateAgent {
+                name: "knowledge",
+                model: "balanced",
+                tools: &[],
+                description: "Questions about the ingested documents and knowledge base",
+                system_prompt: "You are a knowledge base assistant.\n\nAnswer questions using the document excerpts provided in the conversation. Quote or cite the relevant passages. If the documents do not contain the answer, say so plainly instead of guessing.",
+            }],
+            ProjectTemplate::ResearchAssistant => vec![
+                TemplateAgent {
+                    name: "researcher",
+                    model: "powerful",
+                    tools: &["web_search"],
+                    description: "Finding and gathering information from the web",
+                    system_prompt: "You are a research agent.\n\nSearch the web to gather facts relevant to the question. Prefer primary and recent sources, note where each fact came from, and flag conflicting information.",
+                },
+                TemplateAgent {
+                    name: "synthesizer",
+                    model: "balanced",
+                    tools: &["calculator"],
+                    description: "Summarizing and comparing research findings",
+                    system_prompt: "You are a synthesis agent.\n\nCombine research findings into a clear, well-structured answer. Lead with the conclusion, support it with the key evidence, and list sources at the end.",
+                },
+            ],
+            ProjectTemplate::CodingAgent => vec![
+                TemplateAgent {
+                    name: "coder",
+                    model: "powerful",
+                    tools: &[],
+                    description: "Writing, explaining and debugging code",
+                    system_prompt: "You are a senior software engineer.\n\nWrite correct, idiomatic code with brief explanations. Ask for missing requirements when they change the design, and point out edge cases and trade-offs.",
+                },
+                TemplateAgent {
+                    name: "reviewer",
+                    model: "balanced",
+                    tools: &[],
+                    description: "Reviewing code for bugs, style and security issues",
+                    system_prompt: "You are a code reviewer.\n\nReview the given code for bugs, security issues, performance problems and readability. Be specific: reference the relevant lines and suggest concrete fixes.",
+                },
+            ],
+        }
+    }
+
+    /// Workflows scaffolded by this template
+    pub fn workflows(&self) -> Vec<TemplateWorkflow> {
+        match self {
+            ProjectTemplate::RagChatbot => vec![TemplateWorkflow {
+                name: "rag-chat",
+                entry_agent: "knowledge",
+                fallback_agent: Some("orchestrator"),
+                parallel_subagents: false,
+            }],
+            ProjectTemplate::ResearchAssistant => vec![TemplateWorkflow {
+                name: "deep-research",
+                entry_agent: "researcher",
+                fallback_agent: Some("synthesizer"),
+                parallel_subagents: true,
+            }],
+            ProjectTemplate::CodingAgent => vec![TemplateWorkflow {
+                name: "code-review",
+                entry_agent: "coder",
+                fallback_agent: Some("reviewer"),
+                parallel_subagents: false,
+            }],
+        }
+    }
+}
+
+impl fmt::Display for ProjectTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ProjectTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|t| t.name() == s)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown template '{}'. Available: {}",
+                    s,
+                    Self::ALL.map(|t| t.name()).join(", ")
+                )
+            })
+    }
+}
+
+impl TemplateAgent {
+    /// Build the TOON agent configuration for this agent
+    pub fn to_config(&self) -> ToonAgentConfig {
+        let mut config = ToonAgentConfig::new(self.name, self.model)
+            .with_system_prompt(self.system_prompt)
+            .with_tools(self.tools.iter().map(|t| t.to_string()).collect());
+        config.max_tool_iterations = if self.tools.is_empty() { 1 } else { 5 };
+        config
+    }
+}
+
+impl TemplateWorkflow {
+    /// Build the TOON workflow configuration for this workflow
+    pub fn to_config(&self) -> ToonWorkflowConfig {
+        let mut config = ToonWorkflowConfig::new(self.name, self.entry_agent);
+        config.fallback_agent = self.fallback_agent.map(String::from);
+        config.parallel_subagents = self.parallel_subagents;
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_from_str_roundtrip() {
+        for template in ProjectTemplate::ALL {
+            assert_eq!(template.name().parse::<ProjectTemplate>(), Ok(template));
+        }
+        let err = "chatbot".parse::<ProjectTemplate>().unwrap_err();
+        assert!(err.contains("rag-chatbot"));
+    }
+
+    #[test]
+    fn test_template_workflows_reference_agents() {
+        for template in ProjectTemplate::ALL {
+            let names: Vec<_> = template.agents().iter().map(|a| a.name).collect();
+            for workflow in template.workflows() {
+                assert!(names.contains(&workflow.entry_agent));
+            }
+        }
+    }
+
+    #[test]
+    fn test_template_toon_roundtrip() {
+        for template in ProjectTemplate::ALL {
+            for agent in template.agents() {
+                let config = agent.to_config();
+                let parsed = ToonAgentConfig::from_toon(&config.to_toon().unwrap()).unwrap();
+                assert_eq!(parsed.name, agent.name);
+                assert_eq!(parsed.tools.len(), agent.tools.len());
+            }
+            for workflow in template.workflows() {
+                let config = workflow.to_config();
+                let parsed = ToonWorkflowConfig::from_toon(&config.to_toon().unwrap()).unwrap();
+                assert_eq!(parsed, config);
+            }
+        }
+    }
+}
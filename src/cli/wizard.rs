@@ -0,0 +1,182 @@
+//! Interactive init wizard
+//!
+//! Walks the user through provider selection (with a connectivity test),
+//! model selection (from the live Ollama tag list when available), project
+//! template and feature toggles, and returns the resulting [`InitConfig`].
+
+use super::init::InitConfig;
+use super::output::Output;
+use super::templates::ProjectTemplate;
+use std::time::Duration;
+
+const PROVIDERS: [&str; 3] = ["ollama", "openai", "both"];
+const OPENAI_MODELS_URL: &str = "https://api.openai.com/v1/models";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Run the wizard, using `config` (built from CLI flags) as defaults
+pub async fn run(mut config: InitConfig, output: &Output) -> InitConfig {
+    output.header("Project Setup");
+
+    // Provider
+    output.subheader("LLM provider");
+    let options: Vec<String> = PROVIDERS.iter().map(|p| p.to_string()).collect();
+    let default = PROVIDERS
+        .iter()
+        .position(|p| *p == config.provider)
+        .unwrap_or(0);
+    config.provider = PROVIDERS[output.select("Provider", &options, default)].to_string();
+
+    let mut ollama_models = Vec::new();
+    if config.provider != "openai" {
+        config.ollama_url = output.prompt("Ollama URL", &config.ollama_url);
+        match fetch_ollama_models(&config.ollama_url).await {
+            Ok(models) => {
+                output.success(&format!(
+                    "Connected to Ollama ({} models available)",
+                    models.len()
+                ));
+                ollama_models = models;
+            }
+            Err(e) => {
+                output.warning(&format!("Could not reach Ollama: {}", e));
+                output.hint("Start it with `ollama serve`; the project can still be created");
+            }
+        }
+    }
+    if config.provider != "ollama" {
+        match check_openai().await {
+            Ok(()) => output.success("OpenAI API key is valid"),
+            Err(e) => output.warning(&format!("OpenAI check failed: {}", e)),
+        }
+    }
+
+    // Model
+    output.subheader("Model");
+    let current = config.model.clone().unwrap_or_else(|| {
+        if config.provider == "openai" {
+            "gpt-4o-mini".to_string()
+        } else {
+            "ministral-3:3b".to_string()
+        }
+    });
+    let model = if config.provider != "openai" && !ollama_models.is_empty() {
+        let default = ollama_models
+            .iter()
+            .position(|m| *m == current)
+            .unwrap_or(0);
+        ollama_models[output.select("Model", &ollama_models, default)].clone()
+    } else {
+        output.prompt("Model", &current)
+    };
+    config.model = Some(model);
+
+    // Template
+    output.subheader("Project template");
+    let mut options = vec!["none (router + orchestrator only)".to_string()];
+    options.extend(
+        ProjectTemplate::ALL
+            .iter()
+            .map(|t| format!("{} - {}", t.name(), t.description())),
+    );
+    let default = config
+        .template
+        .and_then(|t| ProjectTemplate::ALL.iter().position(|a| *a == t))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    config.template = match output.select("Template", &options, default) {
+        0 => None,
+        i => Some(ProjectTemplate::ALL[i - 1]),
+    };
+
+    // Features
+    output.subheader("Features");
+    let rag_default = config.features.rag || config.template.is_some_and(|t| t.uses_rag());
+    config.features.web_search =
+        output.toggle("Enable web search tool?", config.features.web_search);
+    config.features.calculator =
+        output.toggle("Enable calculator tool?", config.features.calculator);
+    config.features.rag = output.toggle("Include RAG configuration?", rag_default);
+
+    // Server
+    output.subheader("Server");
+    config.host = output.prompt("Host", &config.host);
+    let port = output.prompt("Port", &config.port.to_string());
+    config.port = port.parse().unwrap_or_else(|_| {
+        output.warning(&format!("Invalid port '{}', using {}", port, config.port));
+        config.port
+    });
+
+    config
+}
+
+/// Fetch the names of locally available models from Ollama's `/api/tags`
+pub async fn fetch_ollama_models(base_url: &str) -> Result<Vec<String>, String> {
+    let client = reqwest::Client::builder()
+        .timeout(CONNECT_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let url = format!("{}/api/tags", base_url.trim_end_matches('/'));
+    let body: serde_json::Value = client
+        .get(&url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(parse_ollama_tags(&body))
+}
+
+/// Extract model names from an Ollama `/api/tags` response
+pub fn parse_ollama_tags(body: &serde_json::Value) -> Vec<String> {
+    body["models"]
+        .as_array()
+        .map(|models| {
+            models
+                .iter()
+                .filter_map(|m| m["name"].as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Verify that `OPENAI_API_KEY` is set and accepted by the API
+async fn check_openai() -> Result<(), String> {
+    let key = std::env::var("OPENAI_API_KEY")
+        .map_err(|_| "OPENAI_API_KEY is not set (add it to .env)".to_string())?;
+
+    let client = reqwest::Client::builder()
+        .timeout(CONNECT_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    client
+        .get(OPENAI_MODELS_URL)
+        .bearer_auth(key)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ollama_tags() {
+        let body = serde_json::json!({
+            "models": [
+                {"name": "ministral-3:3b", "size": 1},
+                {"name": "qwen3:8b"},
+                {"size": 2}
+            ]
+        });
+        assert_eq!(parse_ollama_tags(&body), vec!["ministral-3:3b", "qwen3:8b"]);
+        assert!(parse_ollama_tags(&serde_json::json!({})).is_empty());
+    }
+}
@@ -22,6 +22,8 @@ pub struct AgentRun {
     pub duration_ms: i64,
     pub error: Option<String>,
     pub created_at: i64,
+    /// Sampling seed the run was generated with, for reproducing it
+    pub seed: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -65,13 +67,14 @@ pub async fn insert_agent_run(
     output_tokens: i64,
     duration_ms: i64,
     error: Option<&str>,
+    seed: Option<u32>,
 ) -> Result<String> {
     let id = uuid::Uuid::new_v4().to_string();
     let now = now_ts();
 
     sqlx::query(
-        "INSERT INTO agent_runs (id, tenant_id, agent_name, user_id, status, input_tokens, output_tokens, duration_ms, error, created_at)
-         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
+        "INSERT INTO agent_runs (id, tenant_id, agent_name, user_id, status, input_tokens, output_tokens, duration_ms, error, created_at, seed)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)"
     )
     .bind(&id)
     .bind(tenant_id)
@@ -83,6 +86,7 @@ pub async fn insert_agent_run(
     .bind(duration_ms)
     .bind(error)
     .bind(now)
+    .bind(seed.map(i64::from))
     .execute(pool)
     .await
     .map_err(|e| AppError::Database(e.to_string()))?;
@@ -99,7 +103,7 @@ pub async fn list_agent_runs(
 ) -> Result<Vec<AgentRun>> {
     let rows = if let Some(name) = agent_name {
         sqlx::query(
-            "SELECT id, tenant_id, agent_name, user_id, status, input_tokens, output_tokens, duration_ms, error, created_at
+            "SELECT id, tenant_id, agent_name, user_id, status, input_tokens, output_tokens, duration_ms, error, created_at, seed
              FROM agent_runs WHERE tenant_id = $1 AND agent_name = $2
              ORDER BY created_at DESC LIMIT $3 OFFSET $4"
         )
@@ -111,7 +115,7 @@ pub async fn list_agent_runs(
         .await
     } else {
         sqlx::query(
-            "SELECT id, tenant_id, agent_name, user_id, status, input_tokens, output_tokens, duration_ms, error, created_at
+            "SELECT id, tenant_id, agent_name, user_id, status, input_tokens, output_tokens, duration_ms, error, created_at, seed
              FROM agent_runs WHERE tenant_id = $1
              ORDER BY created_at DESC LIMIT $2 OFFSET $3"
         )
@@ -135,6 +139,7 @@ pub async fn list_agent_runs(
             duration_ms: row.get("duration_ms"),
             error: row.get("error"),
             created_at: row.get("created_at"),
+            seed: row.get("seed"),
         })
     }).collect()
 }
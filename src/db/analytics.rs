@@ -0,0 +1,294 @@
+//! Per-request analytics and the reports built from them.
+//!
+//! Every agent generation — a chat message, a streamed answer, a
+//! regeneration, a resumed or a scheduled run — records one
+//! [`RequestEvent`] with its agent, model, latency, estimated tokens and
+//! cost, and whether it failed. The reporting queries aggregate them for
+//! `GET /api/admin/analytics` and the dashboards built on it.
+
+use crate::types::{AppError, Result};
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+
+/// Answered
+pub const COMPLETED: &str = "completed";
+/// Paused for approval of a tool call
+pub const PAUSED: &str = "paused";
+/// Stopped by the user part way, keeping what was generated
+pub const STOPPED: &str = "stopped";
+/// Cancelled before any output
+pub const CANCELLED: &str = "cancelled";
+/// Failed with an error
+pub const FAILED: &str = "failed";
+
+/// One agent generation, as recorded.
+#[derive(Debug, Clone, Default)]
+pub struct RequestEvent {
+    /// Tenant the request was made in, if any
+    pub tenant_id: Option<String>,
+    /// User who made the request
+    pub user_id: Option<String>,
+    /// What asked for the generation: "chat", "stream", "regenerate",
+    /// "resume" or "schedule"
+    pub source: &'static str,
+    /// Agent that answered
+    pub agent_name: String,
+    /// Model that generated the answer
+    pub model: String,
+    /// One of [`COMPLETED`], [`PAUSED`], [`STOPPED`], [`CANCELLED`] or [`FAILED`]
+    pub status: &'static str,
+    /// Time to the full answer
+    pub latency_ms: i64,
+    /// Estimated input tokens
+    pub input_tokens: i64,
+    /// Estimated output tokens
+    pub output_tokens: i64,
+    /// Estimated cost in USD
+    pub cost_usd: f64,
+    /// Why the request failed
+    pub error: Option<String>,
+}
+
+/// Usage of one UTC day.
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyUsage {
+    /// Start of the day (Unix timestamp)
+    pub date: i64,
+    /// Requests made
+    pub requests: i64,
+    /// Requests that failed
+    pub failed: i64,
+    /// Estimated input tokens
+    pub input_tokens: i64,
+    /// Estimated output tokens
+    pub output_tokens: i64,
+    /// Estimated cost in USD
+    pub cost_usd: f64,
+    /// Average latency of the requests
+    pub avg_latency_ms: i64,
+}
+
+/// Usage of one agent.
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentUsage {
+    /// Agent name
+    pub agent_name: String,
+    /// Requests made
+    pub requests: i64,
+    /// Requests that failed
+    pub failed: i64,
+    /// Estimated input and output tokens
+    pub tokens: i64,
+    /// Estimated cost in USD
+    pub cost_usd: f64,
+    /// Average latency of the requests
+    pub avg_latency_ms: i64,
+    /// 95th percentile latency of the requests
+    pub p95_latency_ms: i64,
+}
+
+/// How often requests to an agent and model fail.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorRate {
+    /// Agent name
+    pub agent_name: String,
+    /// Model name
+    pub model: String,
+    /// Requests made
+    pub requests: i64,
+    /// Requests that failed
+    pub failed: i64,
+    /// Share of the requests that failed, from 0 to 1
+    pub error_rate: f64,
+    /// Error of the latest failed request
+    pub last_error: Option<String>,
+}
+
+/// Record one agent generation.
+pub async fn record(pool: &PgPool, event: &RequestEvent) -> Result<String> {
+    let id = uuid::Uuid::new_v4().to_string();
+
+    sqlx::query(
+        "INSERT INTO request_analytics (id, tenant_id, user_id, source, agent_name, model, status,
+             latency_ms, input_tokens, output_tokens, cost_usd, error, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)",
+    )
+    .bind(&id)
+    .bind(&event.tenant_id)
+    .bind(&event.user_id)
+    .bind(event.source)
+    .bind(&event.agent_name)
+    .bind(&event.model)
+    .bind(event.status)
+    .bind(event.latency_ms)
+    .bind(event.input_tokens)
+    .bind(event.output_tokens)
+    .bind(event.cost_usd)
+    .bind(&event.error)
+    .bind(Utc::now().timestamp())
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to record request analytics: {}", e)))?;
+
+    Ok(id)
+}
+
+/// Record one agent generation without waiting for it, logging a failure.
+pub fn record_in_background(pool: &PgPool, event: RequestEvent) {
+    let pool = pool.clone();
+    tokio::spawn(async move {
+        if let Err(e) = record(&pool, &event).await {
+            tracing::warn!("{}", e);
+        }
+    });
+}
+
+/// Usage per UTC day since `since`, oldest first, optionally of one tenant.
+pub async fn daily_usage(
+    pool: &PgPool,
+    since: i64,
+    tenant_id: Option<&str>,
+) -> Result<Vec<DailyUsage>> {
+    let rows = sqlx::query(
+        "SELECT (created_at / 86400) * 86400 as day_ts,
+            COUNT(*) as requests,
+            COUNT(*) FILTER (WHERE status = 'failed') as failed,
+            COALESCE(SUM(input_tokens), 0)::BIGINT as input_tokens,
+            COALESCE(SUM(output_tokens), 0)::BIGINT as output_tokens,
+            COALESCE(SUM(cost_usd), 0)::DOUBLE PRECISION as cost_usd,
+            COALESCE(AVG(latency_ms), 0)::BIGINT as avg_latency_ms
+         FROM request_analytics
+         WHERE created_at >= $1 AND ($2::TEXT IS NULL OR tenant_id = $2)
+         GROUP BY day_ts
+         ORDER BY day_ts",
+    )
+    .bind(since)
+    .bind(tenant_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get daily usage: {}", e)))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| DailyUsage {
+            date: row.get("day_ts"),
+            requests: row.get("requests"),
+            failed: row.get("failed"),
+            input_tokens: row.get("input_tokens"),
+            output_tokens: row.get("output_tokens"),
+            cost_usd: row.get("cost_usd"),
+            avg_latency_ms: row.get("avg_latency_ms"),
+        })
+        .collect())
+}
+
+/// The `limit` agents with the most requests since `since`, optionally of
+/// one tenant.
+pub async fn top_agents(
+    pool: &PgPool,
+    since: i64,
+    tenant_id: Option<&str>,
+    limit: i64,
+) -> Result<Vec<AgentUsage>> {
+    let rows = sqlx::query(
+        "SELECT agent_name,
+            COUNT(*) as requests,
+            COUNT(*) FILTER (WHERE status = 'failed') as failed,
+            COALESCE(SUM(input_tokens + output_tokens), 0)::BIGINT as tokens,
+            COALESCE(SUM(cost_usd), 0)::DOUBLE PRECISION as cost_usd,
+            COALESCE(AVG(latency_ms), 0)::BIGINT as avg_latency_ms,
+            COALESCE(PERCENTILE_CONT(0.95) WITHIN GROUP (ORDER BY latency_ms), 0)::BIGINT as p95_latency_ms
+         FROM request_analytics
+         WHERE created_at >= $1 AND ($2::TEXT IS NULL OR tenant_id = $2)
+         GROUP BY agent_name
+         ORDER BY requests DESC, agent_name
+         LIMIT $3",
+    )
+    .bind(since)
+    .bind(tenant_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get top agents: {}", e)))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| AgentUsage {
+            agent_name: row.get("agent_name"),
+            requests: row.get("requests"),
+            failed: row.get("failed"),
+            tokens: row.get("tokens"),
+            cost_usd: row.get("cost_usd"),
+            avg_latency_ms: row.get("avg_latency_ms"),
+            p95_latency_ms: row.get("p95_latency_ms"),
+        })
+        .collect())
+}
+
+/// Error rates per agent and model since `since`, highest first, of the
+/// pairs with any failed request, optionally of one tenant.
+pub async fn error_rates(
+    pool: &PgPool,
+    since: i64,
+    tenant_id: Option<&str>,
+) -> Result<Vec<ErrorRate>> {
+    let rows = sqlx::query(
+        "SELECT agent_name, model,
+            COUNT(*) as requests,
+            COUNT(*) FILTER (WHERE status = 'failed') as failed,
+            (ARRAY_AGG(error ORDER BY created_at DESC) FILTER (WHERE status = 'failed'))[1] as last_error
+         FROM request_analytics
+         WHERE created_at >= $1 AND ($2::TEXT IS NULL OR tenant_id = $2)
+         GROUP BY agent_name, model
+         HAVING COUNT(*) FILTER (WHERE status = 'failed') > 0",
+    )
+    .bind(since)
+    .bind(tenant_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get error rates: {}", e)))?;
+
+    let mut rates: Vec<ErrorRate> = rows
+        .iter()
+        .map(|row| {
+            let requests: i64 = row.get("requests");
+            let failed: i64 = row.get("failed");
+            ErrorRate {
+                agent_name: row.get("agent_name"),
+                model: row.get("model"),
+                requests,
+                failed,
+                error_rate: rate(failed, requests),
+                last_error: row.get("last_error"),
+            }
+        })
+        .collect();
+    rates.sort_by(|a, b| {
+        b.error_rate
+            .total_cmp(&a.error_rate)
+            .then(b.failed.cmp(&a.failed))
+    });
+    Ok(rates)
+}
+
+/// Share of `total` that `part` is, 0 when there is nothing to share.
+fn rate(part: i64, total: i64) -> f64 {
+    if total > 0 {
+        part as f64 / total as f64
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate() {
+        assert_eq!(rate(0, 0), 0.0);
+        assert_eq!(rate(1, 4), 0.25);
+        assert_eq!(rate(3, 3), 1.0);
+    }
+}
@@ -0,0 +1,157 @@
+//! Storage for agent runs paused until a user approves their tool calls.
+//!
+//! See [`crate::agents::approval`].
+
+use crate::agents::approval::{PausedRun, PendingApproval};
+use crate::types::{AppError, Result};
+use sqlx::PgPool;
+
+const COLUMNS: &str =
+    "id, user_id, conversation_id, agent, message, state, status, reason, created_at, decided_at";
+
+/// Status of an approval awaiting a decision
+pub const PENDING: &str = "pending";
+/// Status of an approval whose calls were approved
+pub const APPROVED: &str = "approved";
+/// Status of an approval whose calls were rejected
+pub const REJECTED: &str = "rejected";
+
+/// A paused agent run and the user's decision on it.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct RunApproval {
+    /// Approval ID
+    pub id: String,
+    /// User who must decide, and who owns the conversation
+    pub user_id: String,
+    /// Conversation the run answers in
+    pub conversation_id: String,
+    /// Agent whose run paused
+    pub agent: String,
+    /// User message the run answers
+    pub message: String,
+    /// The paused run as JSON
+    pub state: String,
+    /// "pending", "approved" or "rejected"
+    pub status: String,
+    /// Why the calls were rejected, if the user said
+    pub reason: Option<String>,
+    /// When the run paused (Unix timestamp)
+    pub created_at: i64,
+    /// When the user decided (Unix timestamp)
+    pub decided_at: Option<i64>,
+}
+
+impl RunApproval {
+    /// The paused run
+    pub fn paused(&self) -> Result<PausedRun> {
+        serde_json::from_str(&self.state).map_err(|e| {
+            AppError::Internal(format!(
+                "Approval '{}' has an invalid state: {}",
+                self.id, e
+            ))
+        })
+    }
+
+    /// The approval as shown to the user
+    pub fn pending(&self) -> Result<PendingApproval> {
+        Ok(PendingApproval {
+            id: self.id.clone(),
+            conversation_id: self.conversation_id.clone(),
+            agent: self.agent.clone(),
+            tool_calls: self.paused()?.pending_calls(),
+            created_at: self.created_at,
+        })
+    }
+}
+
+/// Store a paused run, returning the approval waiting on it.
+pub async fn insert_approval(
+    pool: &PgPool,
+    id: &str,
+    user_id: &str,
+    conversation_id: &str,
+    message: &str,
+    paused: &PausedRun,
+    now: i64,
+) -> Result<RunApproval> {
+    let state = serde_json::to_string(paused)
+        .map_err(|e| AppError::Internal(format!("Failed to encode paused run: {}", e)))?;
+    let approval = RunApproval {
+        id: id.to_string(),
+        user_id: user_id.to_string(),
+        conversation_id: conversation_id.to_string(),
+        agent: paused.agent.clone(),
+        message: message.to_string(),
+        state,
+        status: PENDING.to_string(),
+        reason: None,
+        created_at: now,
+        decided_at: None,
+    };
+    sqlx::query(
+        "INSERT INTO run_approvals (id, user_id, conversation_id, agent, message, state, status, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+    )
+    .bind(&approval.id)
+    .bind(&approval.user_id)
+    .bind(&approval.conversation_id)
+    .bind(&approval.agent)
+    .bind(&approval.message)
+    .bind(&approval.state)
+    .bind(&approval.status)
+    .bind(approval.created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to store approval: {}", e)))?;
+    Ok(approval)
+}
+
+/// List a user's approvals awaiting a decision, oldest first.
+pub async fn list_pending(pool: &PgPool, user_id: &str) -> Result<Vec<RunApproval>> {
+    sqlx::query_as::<_, RunApproval>(&format!(
+        "SELECT {} FROM run_approvals WHERE user_id = $1 AND status = $2 ORDER BY created_at ASC",
+        COLUMNS
+    ))
+    .bind(user_id)
+    .bind(PENDING)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list approvals: {}", e)))
+}
+
+/// Get one of a user's approvals.
+pub async fn get_approval(pool: &PgPool, id: &str, user_id: &str) -> Result<Option<RunApproval>> {
+    sqlx::query_as::<_, RunApproval>(&format!(
+        "SELECT {} FROM run_approvals WHERE id = $1 AND user_id = $2",
+        COLUMNS
+    ))
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get approval: {}", e)))
+}
+
+/// Record a decision on a pending approval.
+///
+/// Returns false if the approval was already decided.
+pub async fn decide(
+    pool: &PgPool,
+    id: &str,
+    status: &str,
+    reason: Option<&str>,
+    now: i64,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE run_approvals SET status = $1, reason = $2, decided_at = $3 WHERE id = $4 AND status = $5",
+    )
+    .bind(status)
+    .bind(reason)
+    .bind(now)
+    .bind(id)
+    .bind(PENDING)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to record approval decision: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
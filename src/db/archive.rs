@@ -0,0 +1,627 @@
+//! Conversation archival to cold storage.
+//!
+//! Archiving a conversation writes its messages and tool-call traces to a
+//! gzipped JSONL object, on disk or in S3, and deletes them from the
+//! database. The conversation row stays behind as a stub recording where the
+//! archive lives and how many messages it holds, so the conversation is still
+//! listed, and it is restored the next time it is opened or continued.
+//!
+//! Each archive holds one JSON record per line: a `conversation` header,
+//! the `message`s oldest first, then the `tool_call`s made for them.
+
+use crate::db::locks;
+use crate::rag::connectors::{DocumentSource, S3Source, SourceLocation, SourceScheme};
+use crate::types::{AppError, Result, ToolCallTrace};
+use crate::utils::toml_config::{ArchiveConfig, AresConfigManager};
+use chrono::Utc;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// Version of the archive format written
+const FORMAT_VERSION: u32 = 1;
+
+/// A conversation moved to cold storage.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ArchivedConversation {
+    /// Conversation identifier
+    pub conversation_id: String,
+    /// File or `s3://` URI of the archive
+    pub location: String,
+    /// Number of messages archived
+    pub message_count: usize,
+    /// Unix timestamp of archival
+    pub archived_at: i64,
+}
+
+/// One line of an archive
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ArchiveRecord {
+    Conversation {
+        version: u32,
+        id: String,
+        user_id: String,
+        title: Option<String>,
+        archived_at: i64,
+    },
+    Message(ArchivedMessage),
+    ToolCall(Box<ArchivedToolCall>),
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+struct ArchivedMessage {
+    id: String,
+    role: String,
+    content: String,
+    timestamp: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchivedToolCall {
+    message_id: String,
+    position: i32,
+    created_at: i64,
+    #[serde(flatten)]
+    trace: ToolCallTrace,
+}
+
+/// Archive a conversation's messages and tool-call traces
+///
+/// Fails if the conversation is already archived, has no messages, or
+/// receives a message while it is being archived.
+pub async fn archive_conversation(
+    pool: &PgPool,
+    config: &ArchiveConfig,
+    conversation_id: &str,
+) -> Result<ArchivedConversation> {
+    let (user_id, title, archived_at) = sqlx::query_as::<_, (String, Option<String>, Option<i64>)>(
+        "SELECT user_id, title, archived_at FROM conversations WHERE id = $1",
+    )
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to query conversation: {}", e)))?
+    .ok_or_else(|| AppError::NotFound("Conversation not found".to_string()))?;
+    if archived_at.is_some() {
+        return Err(AppError::InvalidInput(
+            "Conversation is already archived".to_string(),
+        ));
+    }
+
+    let messages = sqlx::query_as::<_, ArchivedMessage>(
+        "SELECT id, role, content, timestamp FROM messages WHERE conversation_id = $1 \
+         ORDER BY timestamp ASC",
+    )
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to query messages: {}", e)))?;
+    if messages.is_empty() {
+        return Err(AppError::InvalidInput(
+            "Conversation has no messages to archive".to_string(),
+        ));
+    }
+    let tool_calls = load_tool_calls(pool, conversation_id).await?;
+
+    let archived_at = Utc::now().timestamp();
+    let message_ids: Vec<String> = messages.iter().map(|m| m.id.clone()).collect();
+    let message_count = messages.len();
+    let records = std::iter::once(ArchiveRecord::Conversation {
+        version: FORMAT_VERSION,
+        id: conversation_id.to_string(),
+        user_id,
+        title,
+        archived_at,
+    })
+    .chain(messages.into_iter().map(ArchiveRecord::Message))
+    .chain(
+        tool_calls
+            .into_iter()
+            .map(|call| ArchiveRecord::ToolCall(Box::new(call))),
+    );
+    let bytes = encode(records)?;
+
+    let location = object_uri(&config.location, conversation_id);
+    ArchiveObject::open(&location, config)?.write(bytes).await?;
+
+    let db_err =
+        |e: sqlx::Error| AppError::Database(format!("Failed to archive conversation: {}", e));
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    let stubbed = sqlx::query(
+        "UPDATE conversations SET archived_at = $1, archive_key = $2, archived_message_count = $3 \
+         WHERE id = $4 AND archived_at IS NULL",
+    )
+    .bind(archived_at)
+    .bind(&location)
+    .bind(message_count as i32)
+    .bind(conversation_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_err)?;
+    if stubbed.rows_affected() == 0 {
+        return Err(AppError::InvalidInput(
+            "Conversation is already archived".to_string(),
+        ));
+    }
+    sqlx::query("DELETE FROM tool_call_traces WHERE conversation_id = $1")
+        .bind(conversation_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    sqlx::query("DELETE FROM messages WHERE conversation_id = $1 AND id = ANY($2)")
+        .bind(conversation_id)
+        .bind(&message_ids)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    // A message added since the archive was written would be stranded
+    let (remaining,): (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM messages WHERE conversation_id = $1")
+            .bind(conversation_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_err)?;
+    if remaining > 0 {
+        return Err(AppError::InvalidInput(
+            "Conversation received new messages while being archived".to_string(),
+        ));
+    }
+    tx.commit().await.map_err(db_err)?;
+
+    tracing::info!(
+        conversation_id = %conversation_id,
+        location = %location,
+        "Archived {} messages",
+        message_count
+    );
+    Ok(ArchivedConversation {
+        conversation_id: conversation_id.to_string(),
+        location,
+        message_count,
+        archived_at,
+    })
+}
+
+/// Restore an archived conversation's messages and tool-call traces
+///
+/// Returns false when the conversation is not archived.
+pub async fn restore_conversation(
+    pool: &PgPool,
+    config: &ArchiveConfig,
+    conversation_id: &str,
+) -> Result<bool> {
+    let Some(location) = archive_location(pool, conversation_id).await? else {
+        return Ok(false);
+    };
+    let object = ArchiveObject::open(&location, config)?;
+    let records = decode(&object.read().await?)?;
+    match records.first() {
+        Some(ArchiveRecord::Conversation { id, .. }) if id == conversation_id => {}
+        _ => {
+            return Err(AppError::Internal(format!(
+                "Archive {} does not hold conversation {}",
+                location, conversation_id
+            )))
+        }
+    }
+
+    let db_err =
+        |e: sqlx::Error| AppError::Database(format!("Failed to restore conversation: {}", e));
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    // Concurrent requests restore the conversation once
+    let locked: Option<(i32,)> = sqlx::query_as(
+        "SELECT 1 FROM conversations WHERE id = $1 AND archived_at IS NOT NULL FOR UPDATE",
+    )
+    .bind(conversation_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_err)?;
+    if locked.is_none() {
+        return Ok(false);
+    }
+
+    let mut message_count = 0;
+    for record in records {
+        match record {
+            ArchiveRecord::Conversation { .. } => {}
+            ArchiveRecord::Message(message) => {
+                sqlx::query(
+                    "INSERT INTO messages (id, conversation_id, role, content, timestamp) \
+                     VALUES ($1, $2, $3, $4, $5)",
+                )
+                .bind(&message.id)
+                .bind(conversation_id)
+                .bind(&message.role)
+                .bind(&message.content)
+                .bind(message.timestamp)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_err)?;
+                message_count += 1;
+            }
+            ArchiveRecord::ToolCall(call) => {
+                let trace = &call.trace;
+                sqlx::query(
+                    "INSERT INTO tool_call_traces (id, conversation_id, message_id, position, \
+                     agent, iteration, tool_call_id, tool_name, arguments, result, success, \
+                     duration_ms, error, created_at) \
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)",
+                )
+                .bind(uuid::Uuid::new_v4().to_string())
+                .bind(conversation_id)
+                .bind(&call.message_id)
+                .bind(call.position)
+                .bind(&trace.agent)
+                .bind(trace.iteration as i32)
+                .bind(&trace.tool_call_id)
+                .bind(&trace.tool)
+                .bind(trace.arguments.to_string())
+                .bind(trace.result.to_string())
+                .bind(trace.success)
+                .bind(trace.duration_ms as i64)
+                .bind(&trace.error)
+                .bind(call.created_at)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_err)?;
+            }
+        }
+    }
+    sqlx::query(
+        "UPDATE conversations SET archived_at = NULL, archive_key = NULL, \
+         archived_message_count = NULL WHERE id = $1",
+    )
+    .bind(conversation_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_err)?;
+    tx.commit().await.map_err(db_err)?;
+
+    if let Err(e) = object.delete().await {
+        tracing::warn!("Failed to delete restored archive {}: {}", location, e);
+    }
+    tracing::info!(
+        conversation_id = %conversation_id,
+        "Restored {} archived messages",
+        message_count
+    );
+    Ok(true)
+}
+
+/// Delete the archive of a conversation that is about to be deleted
+///
+/// Failures are logged rather than returned, so a conversation can be
+/// deleted while its cold storage is unreachable.
+pub async fn discard_archive(pool: &PgPool, config: &ArchiveConfig, conversation_id: &str) {
+    let discarded = async {
+        if let Some(location) = archive_location(pool, conversation_id).await? {
+            ArchiveObject::open(&location, config)?.delete().await?;
+        }
+        Ok::<_, AppError>(())
+    };
+    if let Err(e) = discarded.await {
+        tracing::warn!(
+            conversation_id = %conversation_id,
+            "Failed to delete conversation archive: {}",
+            e
+        );
+    }
+}
+
+/// Archive conversations without new messages for `after_days`
+///
+/// Archives at most `batch_size` conversations and returns how many were
+/// archived; conversations that fail are logged and skipped.
+pub async fn archive_inactive(pool: &PgPool, config: &ArchiveConfig) -> Result<usize> {
+    let cutoff = Utc::now().timestamp() - i64::from(config.after_days) * 86400;
+    let ids: Vec<(String,)> = sqlx::query_as(
+        "SELECT c.id FROM conversations c WHERE c.archived_at IS NULL \
+         AND (SELECT MAX(m.timestamp) FROM messages m WHERE m.conversation_id = c.id) < $1 \
+         ORDER BY c.id LIMIT $2",
+    )
+    .bind(cutoff)
+    .bind(i64::from(config.batch_size))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to query inactive conversations: {}", e)))?;
+
+    let mut archived = 0;
+    for (id,) in ids {
+        match archive_conversation(pool, config, &id).await {
+            Ok(_) => archived += 1,
+            Err(e) => {
+                tracing::warn!(conversation_id = %id, "Failed to archive conversation: {}", e)
+            }
+        }
+    }
+    Ok(archived)
+}
+
+/// Archive inactive conversations every `interval_secs` while `[archive]` is enabled
+///
+/// Only the leader archives, when several servers share the database.
+pub fn spawn_archiver(pool: PgPool, config_manager: Arc<AresConfigManager>) {
+    tokio::spawn(async move {
+        loop {
+            let config = config_manager.config();
+            if config.archive.enabled && locks::is_leader() {
+                match archive_inactive(&pool, &config.archive).await {
+                    Ok(0) => {}
+                    Ok(n) => tracing::info!("Archived {} inactive conversations", n),
+                    Err(e) => tracing::warn!("Conversation archival failed: {}", e),
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(config.archive.interval_secs)).await;
+        }
+    });
+}
+
+/// Location of a conversation's archive, if it is archived
+async fn archive_location(pool: &PgPool, conversation_id: &str) -> Result<Option<String>> {
+    let row: Option<(Option<String>,)> = sqlx::query_as(
+        "SELECT archive_key FROM conversations WHERE id = $1 AND archived_at IS NOT NULL",
+    )
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to query conversation archive: {}", e)))?;
+    Ok(row.and_then(|(key,)| key))
+}
+
+/// Tool calls of a conversation, in the order they were made
+async fn load_tool_calls(pool: &PgPool, conversation_id: &str) -> Result<Vec<ArchivedToolCall>> {
+    #[derive(sqlx::FromRow)]
+    struct TraceRow {
+        message_id: String,
+        position: i32,
+        agent: String,
+        iteration: i32,
+        tool_call_id: String,
+        tool_name: String,
+        arguments: String,
+        result: String,
+        success: bool,
+        duration_ms: i64,
+        error: Option<String>,
+        created_at: i64,
+    }
+    let rows = sqlx::query_as::<_, TraceRow>(
+        "SELECT message_id, position, agent, iteration, tool_call_id, tool_name, arguments, \
+         result, success, duration_ms, error, created_at FROM tool_call_traces \
+         WHERE conversation_id = $1 ORDER BY created_at ASC, position ASC",
+    )
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to query tool call traces: {}", e)))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ArchivedToolCall {
+            message_id: row.message_id,
+            position: row.position,
+            created_at: row.created_at,
+            trace: ToolCallTrace {
+                agent: row.agent,
+                iteration: row.iteration.max(0) as u32,
+                tool_call_id: row.tool_call_id,
+                tool: row.tool_name,
+                arguments: serde_json::from_str(&row.arguments).unwrap_or(serde_json::Value::Null),
+                result: serde_json::from_str(&row.result).unwrap_or(serde_json::Value::Null),
+                success: row.success,
+                duration_ms: row.duration_ms.max(0) as u64,
+                error: row.error,
+            },
+        })
+        .collect())
+}
+
+/// Gzipped JSONL of archive records
+fn encode(records: impl IntoIterator<Item = ArchiveRecord>) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    for record in records {
+        let line = serde_json::to_string(&record)
+            .map_err(|e| AppError::Internal(format!("Failed to encode archive: {}", e)))?;
+        writeln!(encoder, "{}", line)
+            .map_err(|e| AppError::Internal(format!("Failed to compress archive: {}", e)))?;
+    }
+    encoder
+        .finish()
+        .map_err(|e| AppError::Internal(format!("Failed to compress archive: {}", e)))
+}
+
+/// Archive records of gzipped JSONL
+fn decode(bytes: &[u8]) -> Result<Vec<ArchiveRecord>> {
+    let mut text = String::new();
+    GzDecoder::new(bytes)
+        .read_to_string(&mut text)
+        .map_err(|e| AppError::Internal(format!("Failed to decompress archive: {}", e)))?;
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .map_err(|e| AppError::Internal(format!("Invalid archive record: {}", e)))
+        })
+        .collect()
+}
+
+/// Archive URI of a conversation under `location`
+///
+/// Conversation IDs that aren't plain file names are hex-encoded.
+fn object_uri(location: &str, conversation_id: &str) -> String {
+    let plain = !conversation_id.is_empty()
+        && conversation_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    let name = if plain {
+        conversation_id.to_string()
+    } else {
+        hex::encode(conversation_id)
+    };
+    format!("{}/{}.jsonl.gz", location.trim_end_matches('/'), name)
+}
+
+/// An archive file or S3 object
+enum ArchiveObject {
+    File(PathBuf),
+    S3 { source: S3Source, key: String },
+}
+
+impl ArchiveObject {
+    /// Open an archive URI, with S3 credentials from `[archive.s3]`
+    fn open(uri: &str, config: &ArchiveConfig) -> Result<Self> {
+        if !uri.starts_with("s3://") {
+            return Ok(Self::File(PathBuf::from(uri)));
+        }
+        let location: SourceLocation = uri.parse()?;
+        if location.scheme != SourceScheme::S3 || location.prefix.is_empty() {
+            return Err(AppError::Configuration(format!(
+                "Invalid archive location {}",
+                uri
+            )));
+        }
+        Ok(Self::S3 {
+            source: S3Source::from_config(&config.s3, &location)?,
+            key: location.prefix,
+        })
+    }
+
+    async fn write(&self, bytes: Vec<u8>) -> Result<()> {
+        match self {
+            Self::File(path) => {
+                let io_err = |e: std::io::Error| {
+                    AppError::Internal(format!("Failed to write archive {}: {}", path.display(), e))
+                };
+                if let Some(dir) = path.parent() {
+                    tokio::fs::create_dir_all(dir).await.map_err(io_err)?;
+                }
+                // Write then rename, so a crash never leaves a partial archive
+                let partial = path.with_extension("gz.partial");
+                tokio::fs::write(&partial, bytes).await.map_err(io_err)?;
+                tokio::fs::rename(&partial, path).await.map_err(io_err)
+            }
+            Self::S3 { source, key } => source.put(key, bytes).await,
+        }
+    }
+
+    async fn read(&self) -> Result<Vec<u8>> {
+        match self {
+            Self::File(path) => tokio::fs::read(path).await.map_err(|e| {
+                AppError::Internal(format!("Failed to read archive {}: {}", path.display(), e))
+            }),
+            Self::S3 { source, key } => source.fetch(key).await,
+        }
+    }
+
+    async fn delete(&self) -> Result<()> {
+        match self {
+            Self::File(path) => match tokio::fs::remove_file(path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(AppError::Internal(
+                    format!("Failed to delete archive {}: {}", path.display(), e),
+                )),
+                _ => Ok(()),
+            },
+            Self::S3 { source, key } => source.delete(key).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<ArchiveRecord> {
+        vec![
+            ArchiveRecord::Conversation {
+                version: FORMAT_VERSION,
+                id: "conv-1".to_string(),
+                user_id: "user-1".to_string(),
+                title: Some("Trip".to_string()),
+                archived_at: 1_700_000_000,
+            },
+            ArchiveRecord::Message(ArchivedMessage {
+                id: "msg-1".to_string(),
+                role: "assistant".to_string(),
+                content: "It's sunny.\nTake a hat.".to_string(),
+                timestamp: 1_690_000_000,
+            }),
+            ArchiveRecord::ToolCall(Box::new(ArchivedToolCall {
+                message_id: "msg-1".to_string(),
+                position: 0,
+                created_at: 1_690_000_000,
+                trace: ToolCallTrace {
+                    agent: "travel".to_string(),
+                    iteration: 1,
+                    tool_call_id: "call-1".to_string(),
+                    tool: "weather".to_string(),
+                    arguments: serde_json::json!({"city": "Lisbon"}),
+                    result: serde_json::json!({"sky": "clear"}),
+                    success: true,
+                    duration_ms: 42,
+                    error: None,
+                },
+            })),
+        ]
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        let bytes = encode(records()).unwrap();
+        // Gzip magic number
+        assert_eq!(&bytes[..2], &[0x1f, 0x8b]);
+
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(decoded.len(), 3);
+        assert!(matches!(
+            &decoded[0],
+            ArchiveRecord::Conversation { id, version: 1, .. } if id == "conv-1"
+        ));
+        let ArchiveRecord::Message(message) = &decoded[1] else {
+            panic!("expected a message");
+        };
+        assert_eq!(message.content, "It's sunny.\nTake a hat.");
+        let ArchiveRecord::ToolCall(call) = &decoded[2] else {
+            panic!("expected a tool call");
+        };
+        assert_eq!(call.message_id, "msg-1");
+        assert_eq!(call.trace.tool, "weather");
+        assert_eq!(call.trace.arguments["city"], "Lisbon");
+        assert_eq!(call.trace.duration_ms, 42);
+
+        assert!(decode(b"not gzip").is_err());
+    }
+
+    #[test]
+    fn test_object_uri() {
+        assert_eq!(
+            object_uri("data/archive/", "0b6f-41c2"),
+            "data/archive/0b6f-41c2.jsonl.gz"
+        );
+        assert_eq!(
+            object_uri("s3://bucket/conversations", "../x"),
+            "s3://bucket/conversations/2e2e2f78.jsonl.gz"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_object_write_read_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().join("nested").to_string_lossy().to_string();
+        let object =
+            ArchiveObject::open(&object_uri(&location, "conv-1"), &ArchiveConfig::default())
+                .unwrap();
+
+        object.write(b"archive".to_vec()).await.unwrap();
+        assert_eq!(object.read().await.unwrap(), b"archive");
+        object.delete().await.unwrap();
+        assert!(object.read().await.is_err());
+        // Deleting twice is fine
+        object.delete().await.unwrap();
+    }
+}
@@ -9,6 +9,8 @@
 //! - **Embedded**: No separate server process required
 //! - **Persistent**: Optional disk persistence with efficient serialization
 //! - **Thread-safe**: Lock-free concurrent reads, synchronized writes
+//! - **Dimension-checked**: Vectors of another size than the collection's
+//!   are rejected instead of being stored or searched with
 //!
 //! # Example
 //!
@@ -19,14 +21,14 @@
 //! let results = store.search("documents", &embedding, 10, 0.5).await?;
 //! ```
 
-use crate::types::{AppError, Document, Result, SearchResult};
+use crate::types::{AppError, CollectionSettings, Document, Result, SearchResult};
 use async_trait::async_trait;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use super::vectorstore::{CollectionInfo, CollectionStats, VectorStore};
+use super::vectorstore::{matches_filters, CollectionInfo, CollectionStats, VectorStore};
 use ares_vector::{Config, DistanceMetric, VectorDb, VectorMetadata};
 
 // ============================================================================
@@ -47,6 +49,8 @@ pub struct AresVectorStore {
     path: Option<PathBuf>,
     /// Document storage (for full document retrieval)
     documents: Arc<RwLock<HashMap<String, HashMap<String, Document>>>>,
+    /// RAG settings stored per collection
+    settings: Arc<RwLock<HashMap<String, CollectionSettings>>>,
 }
 
 impl AresVectorStore {
@@ -79,11 +83,13 @@ impl AresVectorStore {
             db,
             path: path_buf,
             documents: Arc::new(RwLock::new(HashMap::new())),
+            settings: Arc::new(RwLock::new(HashMap::new())),
         };
 
-        // If persistent, try to load document metadata
+        // If persistent, try to load document metadata and collection settings
         if let Some(ref path) = store.path {
             store.load_documents(path).await?;
+            store.load_settings(path).await?;
         }
 
         Ok(store)
@@ -108,6 +114,80 @@ impl AresVectorStore {
         Ok(())
     }
 
+    /// Load collection settings from disk.
+    async fn load_settings(&self, path: &Path) -> Result<()> {
+        let settings_path = path.join("collections.json");
+        if settings_path.exists() {
+            let data = tokio::fs::read_to_string(&settings_path)
+                .await
+                .map_err(|e| {
+                    AppError::Configuration(format!(
+                        "Failed to read collection settings file: {}",
+                        e
+                    ))
+                })?;
+
+            let loaded: HashMap<String, CollectionSettings> =
+                serde_json::from_str(&data).map_err(|e| {
+                    AppError::Configuration(format!(
+                        "Failed to parse collection settings file: {}",
+                        e
+                    ))
+                })?;
+
+            *self.settings.write() = loaded;
+        }
+        Ok(())
+    }
+
+    /// Save collection settings to disk.
+    async fn save_settings(&self) -> Result<()> {
+        if let Some(ref path) = self.path {
+            let data = {
+                let settings = self.settings.read();
+                serde_json::to_string_pretty(&*settings).map_err(|e| {
+                    AppError::Internal(format!("Failed to serialize collection settings: {}", e))
+                })?
+            };
+
+            tokio::fs::create_dir_all(path).await.map_err(|e| {
+                AppError::Internal(format!("Failed to create data directory: {}", e))
+            })?;
+
+            tokio::fs::write(path.join("collections.json"), data)
+                .await
+                .map_err(|e| {
+                    AppError::Internal(format!("Failed to write collection settings file: {}", e))
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Check that a vector has the dimensions of the collection it is
+    /// stored in or searched against.
+    ///
+    /// A mismatch means the embedding model changed since the collection was
+    /// built; the collection has to be re-embedded with the new model.
+    fn check_dimensions(&self, collection: &str, id: &str, embedding: &[f32]) -> Result<()> {
+        let expected = self
+            .db
+            .get_collection(collection)
+            .map_err(|_| AppError::NotFound(format!("Collection '{}' not found", collection)))?
+            .stats()
+            .dimensions;
+        if embedding.len() != expected {
+            return Err(AppError::InvalidInput(format!(
+                "{} has {} dimensions but collection '{}' stores {}-dimensional vectors; \
+                 the embedding model has changed, re-embed the collection to use it",
+                id,
+                embedding.len(),
+                collection,
+                expected
+            )));
+        }
+        Ok(())
+    }
+
     /// Save document metadata to disk.
     async fn save_documents(&self) -> Result<()> {
         if let Some(ref path) = self.path {
@@ -174,15 +254,19 @@ impl VectorStore for AresVectorStore {
             .await
             .map_err(|e| AppError::Internal(format!("Failed to delete collection: {}", e)))?;
 
-        // Remove document storage
+        // Remove document storage and settings
         {
             let mut docs = self.documents.write();
             docs.remove(name);
         }
+        let had_settings = self.settings.write().remove(name).is_some();
 
         // Persist if configured
         if self.path.is_some() {
             self.save_documents().await?;
+            if had_settings {
+                self.save_settings().await?;
+            }
         }
 
         Ok(())
@@ -240,6 +324,14 @@ impl VectorStore for AresVectorStore {
             )));
         }
 
+        // Reject the whole batch before storing any of it
+        for doc in documents {
+            let embedding = doc.embedding.as_ref().ok_or_else(|| {
+                AppError::Internal(format!("Document '{}' missing embedding", doc.id))
+            })?;
+            self.check_dimensions(collection, &format!("Document '{}'", doc.id), embedding)?;
+        }
+
         let mut upserted = 0;
 
         for doc in documents {
@@ -290,36 +382,99 @@ impl VectorStore for AresVectorStore {
         limit: usize,
         threshold: f32,
     ) -> Result<Vec<SearchResult>> {
-        // Search in vector index
-        let vector_results = self
-            .db
-            .search(collection, embedding, limit * 2) // Fetch extra for threshold filtering
+        self.search_with_filters(collection, embedding, limit, threshold, &[])
             .await
-            .map_err(|e| AppError::Internal(format!("Search failed: {}", e)))?;
+    }
 
-        // Get full documents and filter by threshold
-        let docs = self.documents.read();
-        let collection_docs = docs
-            .get(collection)
-            .ok_or_else(|| AppError::NotFound(format!("Collection '{}' not found", collection)))?;
+    async fn search_with_filters(
+        &self,
+        collection: &str,
+        embedding: &[f32],
+        limit: usize,
+        threshold: f32,
+        filters: &[(String, String)],
+    ) -> Result<Vec<SearchResult>> {
+        self.check_dimensions(collection, "The query embedding", embedding)?;
+        let total = self.collection_stats(collection).await?.document_count;
 
+        // Fetch extra for threshold filtering, and keep widening the search
+        // while the metadata filters leave too few results
+        let mut fetch = limit * 2;
         let mut results = Vec::with_capacity(limit);
-        for result in vector_results {
-            // Use score directly (already converted from distance)
-            let similarity = result.score;
-
-            if similarity >= threshold {
-                if let Some(doc) = collection_docs.get(&result.id) {
-                    results.push(SearchResult {
-                        document: doc.clone(),
-                        score: similarity,
-                    });
-
-                    if results.len() >= limit {
-                        break;
+        loop {
+            let vector_results = self
+                .db
+                .search(collection, embedding, fetch)
+                .await
+                .map_err(|e| AppError::Internal(format!("Search failed: {}", e)))?;
+
+            // Get full documents and filter by threshold
+            let docs = self.documents.read();
+            let collection_docs = docs.get(collection).ok_or_else(|| {
+                AppError::NotFound(format!("Collection '{}' not found", collection))
+            })?;
+
+            results.clear();
+            for result in &vector_results {
+                // Use score directly (already converted from distance)
+                let similarity = result.score;
+
+                if similarity >= threshold {
+                    if let Some(doc) = collection_docs.get(&result.id) {
+                        if !matches_filters(&doc.metadata, filters) {
+                            continue;
+                        }
+                        results.push(SearchResult {
+                            document: doc.clone(),
+                            score: similarity,
+                        });
+
+                        if results.len() >= limit {
+                            break;
+                        }
                     }
                 }
             }
+
+            // Done once enough match, the collection is exhausted, or the
+            // remaining candidates already fall below the threshold
+            let below_threshold = vector_results
+                .last()
+                .is_some_and(|result| result.score < threshold);
+            if filters.is_empty() || results.len() >= limit || below_threshold {
+                break;
+            }
+            if fetch >= total {
+                // The index is approximate and can miss points even when
+                // asked for all of them, so score the matching documents
+                // directly instead, with the collection's metric
+                let metric = self
+                    .db
+                    .get_collection(collection)
+                    .map_err(|_| {
+                        AppError::NotFound(format!("Collection '{}' not found", collection))
+                    })?
+                    .metric();
+                results = collection_docs
+                    .values()
+                    .filter(|doc| matches_filters(&doc.metadata, filters))
+                    .filter_map(|doc| {
+                        let score = metric.similarity(embedding, doc.embedding.as_deref()?);
+                        (score >= threshold).then(|| SearchResult {
+                            document: doc.clone(),
+                            score,
+                        })
+                    })
+                    .collect();
+                results.sort_by(|a, b| {
+                    b.score
+                        .partial_cmp(&a.score)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+                results.truncate(limit);
+                break;
+            }
+            fetch *= 4;
         }
 
         // Sort by score descending
@@ -368,6 +523,44 @@ impl VectorStore for AresVectorStore {
 
         Ok(collection_docs.get(id).cloned())
     }
+
+    async fn documents(&self, collection: &str) -> Result<Vec<Document>> {
+        let docs = self.documents.read();
+        let collection_docs = docs
+            .get(collection)
+            .ok_or_else(|| AppError::NotFound(format!("Collection '{}' not found", collection)))?;
+        Ok(collection_docs.values().cloned().collect())
+    }
+
+    /// Write the vector indexes, documents and collection settings to disk.
+    ///
+    /// Does nothing for an in-memory store.
+    async fn persist(&self) -> Result<()> {
+        if self.path.is_none() {
+            return Ok(());
+        }
+        self.db
+            .persist()
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to persist vectors: {}", e)))?;
+        self.save_documents().await?;
+        self.save_settings().await
+    }
+
+    async fn collection_settings(&self, name: &str) -> Result<Option<CollectionSettings>> {
+        Ok(self.settings.read().get(name).cloned())
+    }
+
+    async fn set_collection_settings(
+        &self,
+        name: &str,
+        settings: &CollectionSettings,
+    ) -> Result<()> {
+        self.settings
+            .write()
+            .insert(name.to_string(), settings.clone());
+        self.save_settings().await
+    }
 }
 
 impl Default for AresVectorStore {
@@ -387,6 +580,7 @@ impl Default for AresVectorStore {
             db,
             path: None,
             documents: Arc::new(RwLock::new(HashMap::new())),
+            settings: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 }
@@ -442,6 +636,74 @@ mod tests {
         assert_eq!(results[0].document.id, "doc1");
     }
 
+    #[tokio::test]
+    async fn test_search_with_filters() {
+        let store = AresVectorStore::new(None).await.unwrap();
+        store.create_collection("test", 2).await.unwrap();
+
+        // The closest documents come from another source than the one asked for
+        let docs: Vec<Document> = (0..20)
+            .map(|i| Document {
+                id: format!("doc{}", i),
+                content: format!("Document {}", i),
+                metadata: DocumentMetadata {
+                    title: format!("Doc {}", i),
+                    source: if i == 19 { "wiki" } else { "mail" }.to_string(),
+                    created_at: Utc::now(),
+                    tags: vec![],
+                },
+                embedding: Some(vec![1.0, i as f32 / 10.0]),
+            })
+            .collect();
+        store.upsert("test", &docs).await.unwrap();
+
+        let filters = [("source".to_string(), "wiki".to_string())];
+        let results = store
+            .search_with_filters("test", &[1.0, 0.0], 1, 0.0, &filters)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].document.id, "doc19");
+    }
+
+    #[tokio::test]
+    async fn test_filtered_search_scores_with_the_collection_metric() {
+        let store = AresVectorStore::new(None).await.unwrap();
+        store.create_collection("test", 2).await.unwrap();
+        let docs: Vec<Document> = [("mail", [1.0, 0.0]), ("wiki", [10.0, 0.0])]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (source, embedding))| Document {
+                id: format!("doc{}", i),
+                content: format!("Document {}", i),
+                metadata: DocumentMetadata {
+                    title: format!("Doc {}", i),
+                    source: source.to_string(),
+                    created_at: Utc::now(),
+                    tags: vec![],
+                },
+                embedding: Some(embedding.to_vec()),
+            })
+            .collect();
+        store.upsert("test", &docs).await.unwrap();
+        store
+            .db
+            .convert_metric("test", DistanceMetric::Euclidean)
+            .await
+            .unwrap();
+
+        // Too few documents match the filter, so they are scored directly
+        let filters = [("source".to_string(), "wiki".to_string())];
+        let results = store
+            .search_with_filters("test", &[1.0, 0.0], 2, 0.0, &filters)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].document.id, "doc1");
+        // Cosine similarity would be 1.0
+        assert!((results[0].score - 0.1).abs() < 1e-6);
+    }
+
     #[tokio::test]
     async fn test_collection_operations() {
         let store = AresVectorStore::new(None).await.unwrap();
@@ -462,4 +724,35 @@ mod tests {
         store.delete_collection("col1").await.unwrap();
         assert!(!store.collection_exists("col1").await.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_mismatched_dimensions_are_rejected() {
+        let store = AresVectorStore::new(None).await.unwrap();
+        store.create_collection("test", 3).await.unwrap();
+
+        let document = |id: &str, embedding: Vec<f32>| Document {
+            id: id.to_string(),
+            content: "Hello world".to_string(),
+            metadata: DocumentMetadata {
+                title: "Test".to_string(),
+                source: "test".to_string(),
+                created_at: Utc::now(),
+                tags: vec![],
+            },
+            embedding: Some(embedding),
+        };
+        let docs = vec![
+            document("doc1", vec![1.0, 0.0, 0.0]),
+            document("doc2", vec![1.0, 0.0, 0.0, 0.0]),
+        ];
+
+        // Nothing from a batch with a mismatched vector is stored
+        let err = store.upsert("test", &docs).await.unwrap_err();
+        assert!(matches!(err, AppError::InvalidInput(ref msg) if msg.contains("doc2")));
+        assert!(store.documents("test").await.unwrap().is_empty());
+
+        store.upsert("test", &docs[..1]).await.unwrap();
+        assert_eq!(store.documents("test").await.unwrap().len(), 1);
+        assert!(store.search("test", &[1.0, 0.0], 10, 0.0).await.is_err());
+    }
 }
@@ -0,0 +1,109 @@
+//! Storage for files attached to conversations.
+//!
+//! The files' chunks are kept in a per-conversation vector collection; see
+//! [`crate::rag::attachments`].
+
+use crate::types::{AppError, Result};
+use serde::Serialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+const COLUMNS: &str = "id, conversation_id, user_id, filename, size_bytes, chunks, created_at";
+
+/// A file attached to a conversation.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Attachment {
+    /// Attachment ID
+    pub id: String,
+    /// Conversation the file is attached to
+    pub conversation_id: String,
+    /// Owner of the conversation
+    pub user_id: String,
+    /// File name
+    pub filename: String,
+    /// Size of the file's text in bytes
+    pub size_bytes: i64,
+    /// Chunks the file was split into for retrieval
+    pub chunks: i32,
+    /// When the file was attached (Unix timestamp)
+    pub created_at: i64,
+}
+
+/// Store an attachment.
+pub async fn insert_attachment(pool: &PgPool, attachment: &Attachment) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO conversation_attachments (id, conversation_id, user_id, filename, size_bytes, chunks, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(&attachment.id)
+    .bind(&attachment.conversation_id)
+    .bind(&attachment.user_id)
+    .bind(&attachment.filename)
+    .bind(attachment.size_bytes)
+    .bind(attachment.chunks)
+    .bind(attachment.created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to store attachment: {}", e)))?;
+    Ok(())
+}
+
+/// List a conversation's attachments, oldest first.
+pub async fn list_attachments(pool: &PgPool, conversation_id: &str) -> Result<Vec<Attachment>> {
+    sqlx::query_as::<_, Attachment>(&format!(
+        "SELECT {} FROM conversation_attachments WHERE conversation_id = $1 ORDER BY created_at ASC",
+        COLUMNS
+    ))
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list attachments: {}", e)))
+}
+
+/// Whether a conversation has any attachments.
+pub async fn has_attachments(pool: &PgPool, conversation_id: &str) -> Result<bool> {
+    sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM conversation_attachments WHERE conversation_id = $1)",
+    )
+    .bind(conversation_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to check attachments: {}", e)))
+}
+
+/// Get one of a conversation's attachments.
+pub async fn get_attachment(
+    pool: &PgPool,
+    id: &str,
+    conversation_id: &str,
+) -> Result<Option<Attachment>> {
+    sqlx::query_as::<_, Attachment>(&format!(
+        "SELECT {} FROM conversation_attachments WHERE id = $1 AND conversation_id = $2",
+        COLUMNS
+    ))
+    .bind(id)
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get attachment: {}", e)))
+}
+
+/// Delete an attachment.
+pub async fn delete_attachment(pool: &PgPool, id: &str) -> Result<()> {
+    sqlx::query("DELETE FROM conversation_attachments WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to delete attachment: {}", e)))?;
+    Ok(())
+}
+
+/// Delete all of a conversation's attachments, returning how many there were.
+pub async fn delete_conversation_attachments(pool: &PgPool, conversation_id: &str) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM conversation_attachments WHERE conversation_id = $1")
+        .bind(conversation_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to delete attachments: {}", e)))?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,727 @@
+//! Online backups of the database and the vector store.
+//!
+//! A backup is a gzipped tar archive holding:
+//!
+//! - `manifest.json`: format version, creation time, the latest migration
+//!   applied, and the size and SHA-256 checksum of every other entry
+//! - `postgres/<table>.csv`: each table, as written by `COPY ... (FORMAT csv,
+//!   HEADER)`
+//! - `vectors/...`: the files of the ares-vector directory
+//!
+//! Tables are copied in a single `REPEATABLE READ` read-only transaction, so
+//! they form one consistent snapshot while the server keeps writing. Vector
+//! files are replaced atomically by the store, and are read until a pass
+//! sees none of them change. BM25 and fuzzy indexes are built per search and
+//! have nothing on disk to back up.
+//!
+//! Restoring checks every checksum before changing anything, brings the
+//! schema up to date, then loads the tables in one transaction, referenced
+//! tables first. A backup restores with the version that made it or a newer
+//! one.
+
+use crate::db::migrations::MIGRATOR;
+use crate::types::{AppError, Result};
+use chrono::Utc;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Version of the archive format written
+const FORMAT_VERSION: u32 = 1;
+
+/// Passes over the vector files before giving up on a stable copy
+const VECTOR_COPY_ATTEMPTS: usize = 5;
+
+/// What a backup holds, stored as its `manifest.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Version of the archive format
+    pub format_version: u32,
+    /// Unix timestamp of the backup
+    pub created_at: i64,
+    /// Version of ares that made the backup
+    pub ares_version: String,
+    /// Latest migration applied to the database backed up
+    pub migration: i64,
+    /// Tables, in the order they are restored
+    pub tables: Vec<BackupTable>,
+    /// Files of the vector store, relative to its directory
+    pub vector_files: Vec<BackupFile>,
+}
+
+/// A table in a backup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupTable {
+    /// Table name
+    pub name: String,
+    /// Number of rows
+    pub rows: i64,
+    /// Size of the CSV entry in bytes
+    pub size: u64,
+    /// SHA-256 of the CSV entry, hex encoded
+    pub sha256: String,
+}
+
+/// A file of the vector store in a backup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFile {
+    /// Path relative to the vector store directory, `/`-separated
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+    /// SHA-256 of the file, hex encoded
+    pub sha256: String,
+}
+
+impl BackupManifest {
+    /// Total number of rows backed up
+    pub fn total_rows(&self) -> i64 {
+        self.tables.iter().map(|table| table.rows).sum()
+    }
+}
+
+/// Back up the database, and the vector store when `vector_path` is given,
+/// to a new archive at `archive`.
+///
+/// Safe to run against a live server. The archive is written next to its
+/// destination and renamed into place, so a failed backup leaves nothing
+/// behind.
+pub async fn backup(
+    pool: &PgPool,
+    vector_path: Option<&Path>,
+    archive: &Path,
+) -> Result<BackupManifest> {
+    let (migration, tables, table_data) = dump_tables(pool).await?;
+
+    let vector_data = match vector_path {
+        Some(path) if path.is_dir() => {
+            let path = path.to_path_buf();
+            tokio::task::spawn_blocking(move || read_vector_files(&path))
+                .await
+                .map_err(|e| AppError::Internal(format!("Vector copy panicked: {}", e)))??
+        }
+        _ => Vec::new(),
+    };
+
+    let manifest = BackupManifest {
+        format_version: FORMAT_VERSION,
+        created_at: Utc::now().timestamp(),
+        ares_version: env!("CARGO_PKG_VERSION").to_string(),
+        migration,
+        tables,
+        vector_files: vector_data
+            .iter()
+            .map(|(path, data)| BackupFile {
+                path: path.clone(),
+                size: data.len() as u64,
+                sha256: checksum(data),
+            })
+            .collect(),
+    };
+
+    let mut entries = vec![(
+        "manifest.json".to_string(),
+        serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| AppError::Internal(format!("Failed to encode manifest: {}", e)))?,
+    )];
+    entries.extend(table_data);
+    entries.extend(
+        vector_data
+            .into_iter()
+            .map(|(path, data)| (format!("vectors/{}", path), data)),
+    );
+
+    let archive = archive.to_path_buf();
+    tokio::task::spawn_blocking(move || write_archive(&archive, entries))
+        .await
+        .map_err(|e| AppError::Internal(format!("Backup write panicked: {}", e)))??;
+    Ok(manifest)
+}
+
+/// Read an archive and check it against its manifest, without restoring.
+pub async fn verify(archive: &Path) -> Result<BackupManifest> {
+    let archive = archive.to_path_buf();
+    let (manifest, _) = tokio::task::spawn_blocking(move || read_archive(&archive))
+        .await
+        .map_err(|e| AppError::Internal(format!("Backup read panicked: {}", e)))??;
+    Ok(manifest)
+}
+
+/// Restore an archive into the database, and into `vector_path` when given.
+///
+/// The database tables, and the vector directory, must be empty unless
+/// `force` is set, in which case their contents are replaced. The server
+/// should be stopped while restoring: it keeps the vector store in memory
+/// and would write it back on shutdown.
+pub async fn restore(
+    pool: &PgPool,
+    vector_path: Option<&Path>,
+    archive: &Path,
+    force: bool,
+) -> Result<BackupManifest> {
+    let path = archive.to_path_buf();
+    let (manifest, mut entries) = tokio::task::spawn_blocking(move || read_archive(&path))
+        .await
+        .map_err(|e| AppError::Internal(format!("Backup read panicked: {}", e)))??;
+
+    let latest = MIGRATOR
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or(0);
+    if manifest.migration > latest {
+        return Err(AppError::InvalidInput(format!(
+            "Backup was made at migration {:03}, newer than this version ({:03}); restore it with ares {} or later",
+            manifest.migration, latest, manifest.ares_version
+        )));
+    }
+
+    if let Some(path) = vector_path.filter(|_| !manifest.vector_files.is_empty()) {
+        if !force && has_entries(path)? {
+            return Err(AppError::InvalidInput(format!(
+                "Vector directory '{}' is not empty; pass --force to replace it",
+                path.display()
+            )));
+        }
+    }
+
+    MIGRATOR
+        .run(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to run migrations: {}", e)))?;
+    load_tables(pool, &manifest, &mut entries, force).await?;
+
+    if let Some(path) = vector_path.filter(|_| !manifest.vector_files.is_empty()) {
+        let files: Vec<(String, Vec<u8>)> = manifest
+            .vector_files
+            .iter()
+            .filter_map(|file| {
+                entries
+                    .remove(&format!("vectors/{}", file.path))
+                    .map(|data| (file.path.clone(), data))
+            })
+            .collect();
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || write_vector_files(&path, files))
+            .await
+            .map_err(|e| AppError::Internal(format!("Vector restore panicked: {}", e)))??;
+    }
+    Ok(manifest)
+}
+
+/// Copy every table in one snapshot, returning the latest migration, the
+/// tables in restore order and their CSV entries.
+async fn dump_tables(pool: &PgPool) -> Result<(i64, Vec<BackupTable>, Vec<(String, Vec<u8>)>)> {
+    let db_err = |e: sqlx::Error| AppError::Database(format!("Failed to back up database: {}", e));
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+
+    let migration: i64 =
+        sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success")
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_err)?;
+    let names = table_order(&mut tx).await?;
+
+    let mut tables = Vec::with_capacity(names.len());
+    let mut data = Vec::with_capacity(names.len());
+    for name in names {
+        let rows: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", quote(&name)))
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        let mut csv = Vec::new();
+        {
+            let mut stream = tx
+                .copy_out_raw(&format!(
+                    "COPY {} TO STDOUT (FORMAT csv, HEADER)",
+                    quote(&name)
+                ))
+                .await
+                .map_err(db_err)?;
+            while let Some(chunk) = stream.next().await {
+                csv.extend_from_slice(&chunk.map_err(db_err)?);
+            }
+        }
+        tables.push(BackupTable {
+            name: name.clone(),
+            rows,
+            size: csv.len() as u64,
+            sha256: checksum(&csv),
+        });
+        data.push((format!("postgres/{}.csv", name), csv));
+    }
+    tx.commit().await.map_err(db_err)?;
+    Ok((migration, tables, data))
+}
+
+/// Load the tables of a verified archive in one transaction.
+async fn load_tables(
+    pool: &PgPool,
+    manifest: &BackupManifest,
+    entries: &mut HashMap<String, Vec<u8>>,
+    force: bool,
+) -> Result<()> {
+    let db_err = |e: sqlx::Error| AppError::Database(format!("Failed to restore database: {}", e));
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    let existing = table_order(&mut tx).await?;
+
+    if force {
+        if !existing.is_empty() {
+            let list: Vec<String> = existing.iter().map(|name| quote(name)).collect();
+            sqlx::query(&format!("TRUNCATE {} CASCADE", list.join(", ")))
+                .execute(&mut *tx)
+                .await
+                .map_err(db_err)?;
+        }
+    } else {
+        for name in &existing {
+            let occupied: bool =
+                sqlx::query_scalar(&format!("SELECT EXISTS (SELECT 1 FROM {})", quote(name)))
+                    .fetch_one(&mut *tx)
+                    .await
+                    .map_err(db_err)?;
+            if occupied {
+                return Err(AppError::InvalidInput(format!(
+                    "Table '{}' is not empty; pass --force to replace the database contents",
+                    name
+                )));
+            }
+        }
+    }
+
+    for table in &manifest.tables {
+        if !existing.contains(&table.name) {
+            return Err(AppError::InvalidInput(format!(
+                "Table '{}' of the backup does not exist in this schema",
+                table.name
+            )));
+        }
+        let csv = entries
+            .remove(&format!("postgres/{}.csv", table.name))
+            .unwrap_or_default();
+        let columns = csv_header(&csv).ok_or_else(|| {
+            AppError::InvalidInput(format!("Table '{}' has no CSV header", table.name))
+        })?;
+        let columns: Vec<String> = columns.iter().map(|column| quote(column)).collect();
+        let mut copy = tx
+            .copy_in_raw(&format!(
+                "COPY {} ({}) FROM STDIN (FORMAT csv, HEADER)",
+                quote(&table.name),
+                columns.join(", ")
+            ))
+            .await
+            .map_err(db_err)?;
+        copy.send(csv).await.map_err(db_err)?;
+        let rows = copy.finish().await.map_err(db_err)?;
+        if rows as i64 != table.rows {
+            return Err(AppError::Database(format!(
+                "Restored {} rows into '{}', the backup holds {}",
+                rows, table.name, table.rows
+            )));
+        }
+    }
+    tx.commit().await.map_err(db_err)
+}
+
+/// The tables of the current schema, each after the tables it references.
+///
+/// The collection tables of the pgvector store are left out: they depend on
+/// the extension and are not created by migrations.
+async fn table_order(conn: &mut sqlx::PgConnection) -> Result<Vec<String>> {
+    let db_err = |e: sqlx::Error| AppError::Database(format!("Failed to list tables: {}", e));
+    let names: Vec<String> = sqlx::query_scalar(
+        "SELECT tablename::text FROM pg_tables \
+         WHERE schemaname = current_schema() AND tablename <> '_sqlx_migrations' \
+         AND tablename NOT LIKE 'ares\\_vector%' \
+         ORDER BY tablename",
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(db_err)?;
+    let references: Vec<(String, String)> = sqlx::query_as(
+        "SELECT c.conrelid::regclass::text, c.confrelid::regclass::text \
+         FROM pg_constraint c \
+         JOIN pg_namespace n ON n.oid = c.connamespace \
+         WHERE c.contype = 'f' AND n.nspname = current_schema()",
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(db_err)?;
+    Ok(dependency_order(names, &references))
+}
+
+/// Order tables so each comes after the tables it references.
+///
+/// Self references are ignored, and tables caught in a cycle keep their
+/// original order at the end.
+fn dependency_order(names: Vec<String>, references: &[(String, String)]) -> Vec<String> {
+    let mut pending = names;
+    let mut ordered: Vec<String> = Vec::with_capacity(pending.len());
+    loop {
+        let (ready, rest): (Vec<String>, Vec<String>) = pending.into_iter().partition(|name| {
+            references.iter().all(|(table, referenced)| {
+                table != name || referenced == name || ordered.contains(referenced)
+            })
+        });
+        if ready.is_empty() {
+            ordered.extend(rest);
+            return ordered;
+        }
+        ordered.extend(ready);
+        pending = rest;
+    }
+}
+
+/// Read every vector file, retrying until no file changes during a pass.
+fn read_vector_files(root: &Path) -> Result<Vec<(String, Vec<u8>)>> {
+    for _ in 0..VECTOR_COPY_ATTEMPTS {
+        let before = list_files(root)?;
+        let mut files = Vec::with_capacity(before.len());
+        for path in before.keys() {
+            match std::fs::read(root.join(path)) {
+                Ok(data) => files.push((path.clone(), data)),
+                // Replaced or removed while copying; try again
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+                Err(e) => {
+                    return Err(AppError::Internal(format!(
+                        "Failed to read vector file '{}': {}",
+                        path, e
+                    )))
+                }
+            }
+        }
+        if files.len() == before.len() && list_files(root)? == before {
+            return Ok(files);
+        }
+    }
+    Err(AppError::Internal(format!(
+        "Vector files in '{}' kept changing during {} attempts to copy them",
+        root.display(),
+        VECTOR_COPY_ATTEMPTS
+    )))
+}
+
+/// Files under `root` with their size and modification time, skipping the
+/// temporary files of in-progress writes.
+fn list_files(root: &Path) -> Result<BTreeMap<String, (u64, Option<SystemTime>)>> {
+    let io_err = |e: std::io::Error| {
+        AppError::Internal(format!(
+            "Failed to list vector directory '{}': {}",
+            root.display(),
+            e
+        ))
+    };
+    let mut files = BTreeMap::new();
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(root.join(&dir)).map_err(io_err)? {
+            let entry = entry.map_err(io_err)?;
+            let relative = dir.join(entry.file_name());
+            let metadata = entry.metadata().map_err(io_err)?;
+            if metadata.is_dir() {
+                dirs.push(relative);
+            } else if metadata.is_file() && relative.extension() != Some("tmp".as_ref()) {
+                let path = relative
+                    .components()
+                    .map(|part| part.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.insert(path, (metadata.len(), metadata.modified().ok()));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Replace the vector directory with the files of a backup.
+///
+/// The files are written to a sibling directory first, so the existing
+/// directory is only removed once the new one is complete.
+fn write_vector_files(root: &Path, files: Vec<(String, Vec<u8>)>) -> Result<()> {
+    let io_err =
+        |e: std::io::Error| AppError::Internal(format!("Failed to restore vector files: {}", e));
+    let mut staging = root.as_os_str().to_owned();
+    staging.push(".restoring");
+    let staging = PathBuf::from(staging);
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging).map_err(io_err)?;
+    }
+    std::fs::create_dir_all(&staging).map_err(io_err)?;
+    for (path, data) in files {
+        let target = staging.join(&path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(io_err)?;
+        }
+        std::fs::write(target, data).map_err(io_err)?;
+    }
+    if root.exists() {
+        std::fs::remove_dir_all(root).map_err(io_err)?;
+    }
+    std::fs::rename(&staging, root).map_err(io_err)
+}
+
+/// Whether a directory exists and holds anything
+fn has_entries(path: &Path) -> Result<bool> {
+    match std::fs::read_dir(path) {
+        Ok(mut entries) => Ok(entries.next().is_some()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(AppError::Internal(format!(
+            "Failed to read '{}': {}",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+/// Write the entries to a gzipped tar at `path`, via a temporary file.
+fn write_archive(path: &Path, entries: Vec<(String, Vec<u8>)>) -> Result<()> {
+    let io_err = |e: std::io::Error| {
+        AppError::Internal(format!(
+            "Failed to write backup '{}': {}",
+            path.display(),
+            e
+        ))
+    };
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    let file = std::fs::File::create(&partial).map_err(io_err)?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    let modified = Utc::now().timestamp() as u64;
+    let written = entries.into_iter().try_for_each(|(name, data)| {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(modified);
+        builder.append_data(&mut header, &name, data.as_slice())
+    });
+    let finished = written
+        .and_then(|_| builder.into_inner())
+        .and_then(|encoder| encoder.finish())
+        .and_then(|file| file.sync_all());
+    if let Err(e) = finished {
+        let _ = std::fs::remove_file(&partial);
+        return Err(io_err(e));
+    }
+    std::fs::rename(&partial, path).map_err(io_err)
+}
+
+/// Read a gzipped tar archive and check its entries against its manifest.
+fn read_archive(path: &Path) -> Result<(BackupManifest, HashMap<String, Vec<u8>>)> {
+    let io_err = |e: std::io::Error| {
+        AppError::InvalidInput(format!("Failed to read backup '{}': {}", path.display(), e))
+    };
+    let file = std::fs::File::open(path).map_err(io_err)?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut entries = HashMap::new();
+    for entry in archive.entries().map_err(io_err)? {
+        let mut entry = entry.map_err(io_err)?;
+        let name = entry.path().map_err(io_err)?.to_string_lossy().into_owned();
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data).map_err(io_err)?;
+        entries.insert(name, data);
+    }
+    let manifest = check_entries(&entries)?;
+    Ok((manifest, entries))
+}
+
+/// Parse the manifest and check the size and checksum of every entry it
+/// lists.
+fn check_entries(entries: &HashMap<String, Vec<u8>>) -> Result<BackupManifest> {
+    let manifest: BackupManifest = entries
+        .get("manifest.json")
+        .ok_or_else(|| AppError::InvalidInput("Backup has no manifest.json".to_string()))
+        .and_then(|data| {
+            serde_json::from_slice(data)
+                .map_err(|e| AppError::InvalidInput(format!("Invalid backup manifest: {}", e)))
+        })?;
+    if manifest.format_version != FORMAT_VERSION {
+        return Err(AppError::InvalidInput(format!(
+            "Unsupported backup format version {}",
+            manifest.format_version
+        )));
+    }
+
+    let expected = manifest
+        .tables
+        .iter()
+        .map(|table| {
+            (
+                format!("postgres/{}.csv", table.name),
+                table.size,
+                &table.sha256,
+            )
+        })
+        .chain(
+            manifest
+                .vector_files
+                .iter()
+                .map(|file| (format!("vectors/{}", file.path), file.size, &file.sha256)),
+        );
+    for (name, size, sha256) in expected {
+        let data = entries
+            .get(&name)
+            .ok_or_else(|| AppError::InvalidInput(format!("Backup is missing '{}'", name)))?;
+        if data.len() as u64 != size || &checksum(data) != sha256 {
+            return Err(AppError::InvalidInput(format!(
+                "Checksum mismatch for '{}'; the backup is corrupt",
+                name
+            )));
+        }
+    }
+    Ok(manifest)
+}
+
+/// Column names from the header line of a `COPY` CSV
+fn csv_header(csv: &[u8]) -> Option<Vec<String>> {
+    let end = csv.iter().position(|&byte| byte == b'\n')?;
+    let line = std::str::from_utf8(&csv[..end])
+        .ok()?
+        .trim_end_matches('\r');
+    let mut columns = Vec::new();
+    let mut column = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                column.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => columns.push(std::mem::take(&mut column)),
+            c => column.push(c),
+        }
+    }
+    columns.push(column);
+    Some(columns)
+}
+
+/// Quote an identifier for SQL
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// SHA-256 of `data`, hex encoded
+fn checksum(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dependency_order() {
+        let names = vec![
+            "agent_runs".to_string(),
+            "tenant_quotas".to_string(),
+            "tenants".to_string(),
+            "users".to_string(),
+        ];
+        let references = vec![
+            ("agent_runs".to_string(), "tenants".to_string()),
+            ("tenant_quotas".to_string(), "tenants".to_string()),
+            ("tenants".to_string(), "tenants".to_string()),
+        ];
+        assert_eq!(
+            dependency_order(names, &references),
+            vec!["tenants", "users", "agent_runs", "tenant_quotas"]
+        );
+    }
+
+    #[test]
+    fn test_csv_header() {
+        assert_eq!(
+            csv_header(b"id,\"odd,name\",\"say \"\"hi\"\"\"\n1,2,3\n"),
+            Some(vec![
+                "id".to_string(),
+                "odd,name".to_string(),
+                "say \"hi\"".to_string()
+            ])
+        );
+        assert_eq!(csv_header(b""), None);
+    }
+
+    #[test]
+    fn test_archive_round_trip_and_corruption() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backup.tar.gz");
+        let csv = b"id,name\n1,default\n".to_vec();
+        let vector = b"{\"dimensions\":3}".to_vec();
+        let manifest = BackupManifest {
+            format_version: FORMAT_VERSION,
+            created_at: 0,
+            ares_version: "test".to_string(),
+            migration: 33,
+            tables: vec![BackupTable {
+                name: "tenants".to_string(),
+                rows: 1,
+                size: csv.len() as u64,
+                sha256: checksum(&csv),
+            }],
+            vector_files: vec![BackupFile {
+                path: "docs/metadata.json".to_string(),
+                size: vector.len() as u64,
+                sha256: checksum(&vector),
+            }],
+        };
+        let entries = vec![
+            (
+                "manifest.json".to_string(),
+                serde_json::to_vec(&manifest).unwrap(),
+            ),
+            ("postgres/tenants.csv".to_string(), csv),
+            ("vectors/docs/metadata.json".to_string(), vector),
+        ];
+        write_archive(&path, entries).unwrap();
+
+        let (read, mut entries) = read_archive(&path).unwrap();
+        assert_eq!(read.migration, 33);
+        assert_eq!(read.total_rows(), 1);
+        assert!(!dir.path().join("backup.tar.gz.partial").exists());
+
+        entries.insert(
+            "postgres/tenants.csv".to_string(),
+            b"id,name\n1,tampered\n".to_vec(),
+        );
+        assert!(matches!(
+            check_entries(&entries),
+            Err(AppError::InvalidInput(_))
+        ));
+        entries.remove("vectors/docs/metadata.json");
+        assert!(check_entries(&entries).is_err());
+    }
+
+    #[test]
+    fn test_vector_files_skip_partial_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("docs/segments")).unwrap();
+        std::fs::write(dir.path().join("docs/metadata.json"), b"{}").unwrap();
+        std::fs::write(dir.path().join("docs/segments/0.jsonl"), b"[]").unwrap();
+        std::fs::write(dir.path().join("docs/segments/1.jsonl.tmp"), b"[").unwrap();
+
+        let files = read_vector_files(dir.path()).unwrap();
+        let paths: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, vec!["docs/metadata.json", "docs/segments/0.jsonl"]);
+
+        let target = dir.path().join("restored");
+        write_vector_files(&target, files).unwrap();
+        assert_eq!(
+            std::fs::read(target.join("docs/segments/0.jsonl")).unwrap(),
+            b"[]"
+        );
+    }
+}
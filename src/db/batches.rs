@@ -0,0 +1,222 @@
+//! Storage for batch chat.
+//!
+//! A batch (see [`crate::api::handlers::batches`]) is kept in
+//! `chat_batches`, and each of its messages, with the answer or error, in
+//! `chat_batch_items`.
+
+use crate::types::{AppError, Result};
+use sqlx::PgPool;
+
+const BATCH_COLUMNS: &str =
+    "id, user_id, workspace_id, agent_type, status, created_at, started_at, completed_at";
+
+const ITEM_COLUMNS: &str = "batch_id, position, custom_id, message, context_id, seed, status, agent, response, message_id, approval_id, error, started_at, completed_at";
+
+/// Status of a batch or message waiting to start
+pub const QUEUED: &str = "queued";
+/// Status of a batch or message being answered
+pub const IN_PROGRESS: &str = "in_progress";
+/// Status of a batch asked to stop
+pub const CANCELLING: &str = "cancelling";
+/// Status of a batch stopped on request, or a message it never answered
+pub const CANCELLED: &str = "cancelled";
+/// Status of a batch whose messages have all finished, or an answered message
+pub const COMPLETED: &str = "completed";
+/// Status of a message whose answer failed
+pub const FAILED: &str = "failed";
+/// Status of a message whose run waits for its tool calls to be approved
+pub const REQUIRES_ACTION: &str = "requires_action";
+
+/// Statuses of batches still running
+const ACTIVE: [&str; 3] = [QUEUED, IN_PROGRESS, CANCELLING];
+
+/// A batch of chat messages.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ChatBatch {
+    /// Batch ID
+    pub id: String,
+    /// User who sent the batch
+    pub user_id: String,
+    /// Workspace the batch was sent in, if any
+    pub workspace_id: Option<String>,
+    /// Agent answering; `None` when messages are routed
+    pub agent_type: Option<String>,
+    /// One of the status constants of this module
+    pub status: String,
+    /// When the batch was created (Unix timestamp)
+    pub created_at: i64,
+    /// When its first message started (Unix timestamp)
+    pub started_at: Option<i64>,
+    /// When the batch reached its final status (Unix timestamp)
+    pub completed_at: Option<i64>,
+}
+
+impl ChatBatch {
+    /// Whether the batch is still running
+    pub fn is_active(&self) -> bool {
+        ACTIVE.contains(&self.status.as_str())
+    }
+}
+
+/// A message of a batch and its outcome.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct BatchItem {
+    /// Batch the message belongs to
+    pub batch_id: String,
+    /// Position of the message in the batch, from 0
+    pub position: i32,
+    /// The client's ID for the message
+    pub custom_id: Option<String>,
+    /// Message text
+    pub message: String,
+    /// Conversation the message is answered in, once known
+    pub context_id: Option<String>,
+    /// Sampling seed
+    pub seed: Option<i64>,
+    /// One of the status constants of this module
+    pub status: String,
+    /// Agent that answered
+    pub agent: Option<String>,
+    /// The answer
+    pub response: Option<String>,
+    /// Stored answer message
+    pub message_id: Option<String>,
+    /// Approval the message's run waits for
+    pub approval_id: Option<String>,
+    /// Why the answer failed
+    pub error: Option<String>,
+    /// When answering started (Unix timestamp)
+    pub started_at: Option<i64>,
+    /// When the message reached its final status (Unix timestamp)
+    pub completed_at: Option<i64>,
+}
+
+/// Store a new batch with its messages.
+pub async fn insert_batch(pool: &PgPool, batch: &ChatBatch, items: &[BatchItem]) -> Result<()> {
+    let failed = |e: sqlx::Error| AppError::Database(format!("Failed to store batch: {}", e));
+    let mut tx = pool.begin().await.map_err(failed)?;
+    sqlx::query(&format!(
+        "INSERT INTO chat_batches ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        BATCH_COLUMNS
+    ))
+    .bind(&batch.id)
+    .bind(&batch.user_id)
+    .bind(&batch.workspace_id)
+    .bind(&batch.agent_type)
+    .bind(&batch.status)
+    .bind(batch.created_at)
+    .bind(batch.started_at)
+    .bind(batch.completed_at)
+    .execute(&mut *tx)
+    .await
+    .map_err(failed)?;
+    for item in items {
+        sqlx::query(
+            "INSERT INTO chat_batch_items (batch_id, position, custom_id, message, context_id, seed, status)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        )
+        .bind(&item.batch_id)
+        .bind(item.position)
+        .bind(&item.custom_id)
+        .bind(&item.message)
+        .bind(&item.context_id)
+        .bind(item.seed)
+        .bind(&item.status)
+        .execute(&mut *tx)
+        .await
+        .map_err(failed)?;
+    }
+    tx.commit().await.map_err(failed)
+}
+
+/// Save a batch's status.
+///
+/// A batch asked to stop stays `cancelling` until it reaches a final status.
+pub async fn update_batch(pool: &PgPool, batch: &ChatBatch) -> Result<()> {
+    sqlx::query(
+        "UPDATE chat_batches SET
+           status = CASE WHEN status = $2 AND $3 = $4 THEN status ELSE $3 END,
+           started_at = $5, completed_at = $6
+         WHERE id = $1",
+    )
+    .bind(&batch.id)
+    .bind(CANCELLING)
+    .bind(&batch.status)
+    .bind(IN_PROGRESS)
+    .bind(batch.started_at)
+    .bind(batch.completed_at)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to update batch: {}", e)))?;
+    Ok(())
+}
+
+/// Save a message's status and outcome.
+pub async fn update_item(pool: &PgPool, item: &BatchItem) -> Result<()> {
+    sqlx::query(
+        "UPDATE chat_batch_items SET
+           context_id = $3, status = $4, agent = $5, response = $6, message_id = $7,
+           approval_id = $8, error = $9, started_at = $10, completed_at = $11
+         WHERE batch_id = $1 AND position = $2",
+    )
+    .bind(&item.batch_id)
+    .bind(item.position)
+    .bind(&item.context_id)
+    .bind(&item.status)
+    .bind(&item.agent)
+    .bind(&item.response)
+    .bind(&item.message_id)
+    .bind(&item.approval_id)
+    .bind(&item.error)
+    .bind(item.started_at)
+    .bind(item.completed_at)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to update batch message: {}", e)))?;
+    Ok(())
+}
+
+/// Get a batch sent by a user in a workspace (`None`: their personal space).
+pub async fn get_batch(
+    pool: &PgPool,
+    id: &str,
+    user_id: &str,
+    workspace_id: Option<&str>,
+) -> Result<Option<ChatBatch>> {
+    sqlx::query_as::<_, ChatBatch>(&format!(
+        "SELECT {} FROM chat_batches
+         WHERE id = $1 AND user_id = $2 AND workspace_id IS NOT DISTINCT FROM $3",
+        BATCH_COLUMNS
+    ))
+    .bind(id)
+    .bind(user_id)
+    .bind(workspace_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get batch: {}", e)))
+}
+
+/// A batch's messages, in order.
+pub async fn list_items(pool: &PgPool, batch_id: &str) -> Result<Vec<BatchItem>> {
+    sqlx::query_as::<_, BatchItem>(&format!(
+        "SELECT {} FROM chat_batch_items WHERE batch_id = $1 ORDER BY position",
+        ITEM_COLUMNS
+    ))
+    .bind(batch_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list batch messages: {}", e)))
+}
+
+/// Mark a batch asked to stop, returning false if it isn't running.
+pub async fn request_cancel(pool: &PgPool, id: &str) -> Result<bool> {
+    let result =
+        sqlx::query("UPDATE chat_batches SET status = $2 WHERE id = $1 AND status = ANY($3)")
+            .bind(id)
+            .bind(CANCELLING)
+            .bind(&ACTIVE[..])
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to cancel batch: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,252 @@
+//! Storage for checkpoints of agent runs in progress.
+//!
+//! A run's checkpoint is kept while the run goes on and deleted when it
+//! ends. Each server stamps the checkpoints of its own runs every
+//! [`HEARTBEAT_INTERVAL`]; a running checkpoint left unstamped for
+//! [`STALE_AFTER_SECS`] belonged to a server that went away, and is marked
+//! interrupted. See [`crate::agents::checkpoint`].
+
+use crate::agents::approval::PausedRun;
+use crate::agents::checkpoint::{InterruptedRun, RunJournal};
+use crate::types::{AppError, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::PgPool;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::LazyLock;
+use std::time::Duration;
+use uuid::Uuid;
+
+const COLUMNS: &str =
+    "id, user_id, conversation_id, agent, message, input, state, status, created_at, updated_at";
+
+/// Status of a checkpoint whose run is going on
+pub const RUNNING: &str = "running";
+/// Status of a checkpoint whose server stopped before the run ended
+pub const INTERRUPTED: &str = "interrupted";
+
+/// How often a server stamps the checkpoints of its runs
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a running checkpoint may go unstamped before its run counts as
+/// interrupted
+pub const STALE_AFTER_SECS: i64 = 120;
+
+/// Identifies this server process in the checkpoints of its runs
+static INSTANCE: LazyLock<String> = LazyLock::new(|| Uuid::new_v4().to_string());
+
+/// A stored checkpoint of a run.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct RunCheckpoint {
+    /// Run ID
+    pub id: String,
+    /// User the run answers
+    pub user_id: String,
+    /// Conversation the run answers in
+    pub conversation_id: String,
+    /// Agent that was running at the checkpoint
+    pub agent: String,
+    /// User message to store with the run's answer, unless it already is
+    pub message: Option<String>,
+    /// Input the run was started with
+    pub input: String,
+    /// The checkpoint as JSON
+    pub state: String,
+    /// "running" or "interrupted"
+    pub status: String,
+    /// When the run started (Unix timestamp)
+    pub created_at: i64,
+    /// When the checkpoint was last saved or stamped (Unix timestamp)
+    pub updated_at: i64,
+}
+
+impl RunCheckpoint {
+    /// The run as of the checkpoint
+    pub fn checkpoint(&self) -> Result<PausedRun> {
+        serde_json::from_str(&self.state).map_err(|e| {
+            AppError::Internal(format!(
+                "Checkpoint of run '{}' has an invalid state: {}",
+                self.id, e
+            ))
+        })
+    }
+
+    /// The run as shown to the user
+    pub fn interrupted(&self) -> InterruptedRun {
+        InterruptedRun {
+            id: self.id.clone(),
+            conversation_id: self.conversation_id.clone(),
+            agent: self.agent.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+/// Journal of one run, keeping its latest checkpoint in `run_checkpoints`.
+///
+/// The checkpoint is deleted when the journal is dropped, which happens once
+/// the run ends, however it ends. A server that stops mid-run never drops
+/// it, leaving the checkpoint to be found interrupted.
+pub struct CheckpointJournal {
+    pool: PgPool,
+    id: String,
+    user_id: String,
+    conversation_id: String,
+    message: Option<String>,
+    input: String,
+    created_at: i64,
+    saved: AtomicBool,
+}
+
+impl CheckpointJournal {
+    /// Journal of a new run answering `input` in a conversation
+    ///
+    /// `message` is the user message to store if the run is interrupted, or
+    /// `None` if the conversation already has it.
+    pub fn new(
+        pool: PgPool,
+        user_id: &str,
+        conversation_id: &str,
+        message: Option<&str>,
+        input: &str,
+    ) -> Self {
+        Self {
+            pool,
+            id: Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            conversation_id: conversation_id.to_string(),
+            message: message.map(str::to_string),
+            input: input.to_string(),
+            created_at: Utc::now().timestamp(),
+            saved: AtomicBool::new(false),
+        }
+    }
+}
+
+#[async_trait]
+impl RunJournal for CheckpointJournal {
+    async fn save(&self, checkpoint: &PausedRun) -> Result<()> {
+        let state = serde_json::to_string(checkpoint)
+            .map_err(|e| AppError::Internal(format!("Failed to encode checkpoint: {}", e)))?;
+        sqlx::query(
+            "INSERT INTO run_checkpoints (id, user_id, conversation_id, agent, message, input, state, status, instance, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+             ON CONFLICT (id) DO UPDATE SET agent = EXCLUDED.agent, state = EXCLUDED.state, updated_at = EXCLUDED.updated_at",
+        )
+        .bind(&self.id)
+        .bind(&self.user_id)
+        .bind(&self.conversation_id)
+        .bind(&checkpoint.agent)
+        .bind(&self.message)
+        .bind(&self.input)
+        .bind(&state)
+        .bind(RUNNING)
+        .bind(INSTANCE.as_str())
+        .bind(self.created_at)
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to save checkpoint: {}", e)))?;
+        self.saved.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl Drop for CheckpointJournal {
+    fn drop(&mut self) {
+        if !self.saved.load(Ordering::Relaxed) {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let (pool, id) = (self.pool.clone(), std::mem::take(&mut self.id));
+        runtime.spawn(async move {
+            let deleted = sqlx::query("DELETE FROM run_checkpoints WHERE id = $1 AND status = $2")
+                .bind(&id)
+                .bind(RUNNING)
+                .execute(&pool)
+                .await;
+            if let Err(e) = deleted {
+                tracing::warn!("Failed to delete checkpoint of run {}: {}", id, e);
+            }
+        });
+    }
+}
+
+/// Stamp the running checkpoints of this server's runs.
+pub async fn heartbeat(pool: &PgPool, now: i64) -> Result<()> {
+    sqlx::query("UPDATE run_checkpoints SET updated_at = $1 WHERE instance = $2 AND status = $3")
+        .bind(now)
+        .bind(INSTANCE.as_str())
+        .bind(RUNNING)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to stamp checkpoints: {}", e)))?;
+    Ok(())
+}
+
+/// Mark running checkpoints last stamped before `stale_before` interrupted,
+/// returning them.
+///
+/// Each checkpoint is returned to only one caller, even with several servers.
+pub async fn mark_interrupted(pool: &PgPool, stale_before: i64) -> Result<Vec<RunCheckpoint>> {
+    sqlx::query_as::<_, RunCheckpoint>(&format!(
+        "UPDATE run_checkpoints SET status = $1 WHERE status = $2 AND updated_at < $3 RETURNING {}",
+        COLUMNS
+    ))
+    .bind(INTERRUPTED)
+    .bind(RUNNING)
+    .bind(stale_before)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to mark interrupted runs: {}", e)))
+}
+
+/// Mark the running checkpoints of this server's runs interrupted,
+/// returning them, when the server stops before the runs end.
+pub async fn interrupt_own(pool: &PgPool) -> Result<Vec<RunCheckpoint>> {
+    sqlx::query_as::<_, RunCheckpoint>(&format!(
+        "UPDATE run_checkpoints SET status = $1 WHERE status = $2 AND instance = $3 RETURNING {}",
+        COLUMNS
+    ))
+    .bind(INTERRUPTED)
+    .bind(RUNNING)
+    .bind(INSTANCE.as_str())
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to mark interrupted runs: {}", e)))
+}
+
+/// List a user's interrupted runs, oldest first.
+pub async fn list_interrupted(pool: &PgPool, user_id: &str) -> Result<Vec<RunCheckpoint>> {
+    sqlx::query_as::<_, RunCheckpoint>(&format!(
+        "SELECT {} FROM run_checkpoints WHERE user_id = $1 AND status = $2 ORDER BY created_at ASC",
+        COLUMNS
+    ))
+    .bind(user_id)
+    .bind(INTERRUPTED)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list interrupted runs: {}", e)))
+}
+
+/// Remove one of a user's interrupted runs, returning it.
+///
+/// Returns `None` if there is no such run, or it was already taken.
+pub async fn take_interrupted(
+    pool: &PgPool,
+    id: &str,
+    user_id: &str,
+) -> Result<Option<RunCheckpoint>> {
+    sqlx::query_as::<_, RunCheckpoint>(&format!(
+        "DELETE FROM run_checkpoints WHERE id = $1 AND user_id = $2 AND status = $3 RETURNING {}",
+        COLUMNS
+    ))
+    .bind(id)
+    .bind(user_id)
+    .bind(INTERRUPTED)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to take interrupted run: {}", e)))
+}
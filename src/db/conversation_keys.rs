@@ -0,0 +1,262 @@
+//! Keys of encrypted conversations.
+//!
+//! An encrypted conversation's messages are stored sealed with AES-256-GCM
+//! under a key derived (Argon2id) from a secret the user sends with every
+//! request, and a salt of the conversation's own. Only the salt and a
+//! check value, telling a wrong secret from a right one, are stored; the
+//! secret and the key live in memory for the request or run using them.
+//!
+//! Sealed messages read `enc:v1:` followed by the base64 of the nonce and
+//! ciphertext. The conversation ID is bound to each as associated data, so
+//! a message can't be moved to another conversation.
+
+use crate::types::{AppError, Message, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use sqlx::PgPool;
+
+/// Prefix of sealed content
+pub const PREFIX: &str = "enc:v1:";
+
+/// Shortest secret a conversation can be encrypted with
+pub const MIN_SECRET_CHARS: usize = 8;
+
+/// What the check value seals
+const CHECK: &str = "ares-conversation-key";
+
+const SALT_LEN: usize = 16;
+
+/// The key of one encrypted conversation.
+pub struct ConversationKey {
+    conversation_id: String,
+    key: LessSafeKey,
+}
+
+impl std::fmt::Debug for ConversationKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConversationKey")
+            .field("conversation_id", &self.conversation_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ConversationKey {
+    /// Derive the key of `conversation_id` from `secret` and its salt.
+    ///
+    /// Argon2 is slow by design; this runs on a blocking thread.
+    async fn derive(secret: &str, conversation_id: &str, salt: Vec<u8>) -> Result<Self> {
+        let secret = secret.to_string();
+        let mut bytes = tokio::task::spawn_blocking(move || {
+            let mut bytes = [0u8; 32];
+            argon2::Argon2::default()
+                .hash_password_into(secret.as_bytes(), &salt, &mut bytes)
+                .map(|_| bytes)
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("Key derivation failed: {}", e)))?
+        .map_err(|e| AppError::Internal(format!("Key derivation failed: {}", e)))?;
+        let key = UnboundKey::new(&AES_256_GCM, &bytes)
+            .map_err(|_| AppError::Internal("Invalid conversation key".to_string()))?;
+        bytes.fill(0);
+        Ok(Self {
+            conversation_id: conversation_id.to_string(),
+            key: LessSafeKey::new(key),
+        })
+    }
+
+    /// Seal `plaintext` for storage
+    pub fn seal(&self, plaintext: &str) -> Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| AppError::Internal("Failed to generate a nonce".to_string()))?;
+        let mut sealed = plaintext.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(self.conversation_id.as_bytes()),
+                &mut sealed,
+            )
+            .map_err(|_| AppError::Internal("Failed to encrypt message".to_string()))?;
+        let mut stored = nonce.to_vec();
+        stored.extend(sealed);
+        Ok(format!("{}{}", PREFIX, STANDARD.encode(stored)))
+    }
+
+    /// Open sealed content; content that isn't sealed is returned as is.
+    pub fn open(&self, content: &str) -> Result<String> {
+        let Some(encoded) = content.strip_prefix(PREFIX) else {
+            return Ok(content.to_string());
+        };
+        let failed = || AppError::Forbidden("Failed to decrypt message".to_string());
+        let mut bytes = STANDARD.decode(encoded).map_err(|_| failed())?;
+        if bytes.len() < NONCE_LEN {
+            return Err(failed());
+        }
+        let mut sealed = bytes.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&bytes).map_err(|_| failed())?;
+        let plaintext = self
+            .key
+            .open_in_place(
+                nonce,
+                Aad::from(self.conversation_id.as_bytes()),
+                &mut sealed,
+            )
+            .map_err(|_| failed())?;
+        String::from_utf8(plaintext.to_vec()).map_err(|_| failed())
+    }
+
+    /// Open the content of every message
+    pub fn open_messages(&self, messages: Vec<Message>) -> Result<Vec<Message>> {
+        messages
+            .into_iter()
+            .map(|message| {
+                Ok(Message {
+                    content: self.open(&message.content)?,
+                    ..message
+                })
+            })
+            .collect()
+    }
+}
+
+/// Seal `content` with `key`, or leave it as is without one
+pub fn seal(key: Option<&ConversationKey>, content: &str) -> Result<String> {
+    match key {
+        Some(key) => key.seal(content),
+        None => Ok(content.to_string()),
+    }
+}
+
+/// Open the messages of a conversation read with `key`, if any
+pub fn open_messages(
+    key: Option<&ConversationKey>,
+    messages: Vec<Message>,
+) -> Result<Vec<Message>> {
+    match key {
+        Some(key) => key.open_messages(messages),
+        None => Ok(messages),
+    }
+}
+
+/// Whether a conversation is encrypted
+pub async fn is_encrypted(pool: &PgPool, conversation_id: &str) -> Result<bool> {
+    Ok(stored(pool, conversation_id).await?.is_some())
+}
+
+/// The salt and check value of an encrypted conversation
+async fn stored(pool: &PgPool, conversation_id: &str) -> Result<Option<(String, String)>> {
+    let row: Option<(Option<String>, Option<String>)> =
+        sqlx::query_as("SELECT encryption_salt, encryption_check FROM conversations WHERE id = $1")
+            .bind(conversation_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| {
+                AppError::Database(format!("Failed to read conversation encryption: {}", e))
+            })?;
+    Ok(match row {
+        Some((Some(salt), Some(check))) => Some((salt, check)),
+        _ => None,
+    })
+}
+
+/// The key of an encrypted conversation, checking `secret` against it.
+///
+/// Returns `None` for a conversation that isn't encrypted, whatever the
+/// secret. Fails with [`AppError::Forbidden`] when the conversation is
+/// encrypted and the secret is missing or wrong.
+pub async fn unlock(
+    pool: &PgPool,
+    conversation_id: &str,
+    secret: Option<&str>,
+) -> Result<Option<ConversationKey>> {
+    let Some((salt, check)) = stored(pool, conversation_id).await? else {
+        return Ok(None);
+    };
+    let secret = secret.ok_or_else(|| {
+        AppError::Forbidden(
+            "This conversation is encrypted; send its secret in the X-Conversation-Secret header"
+                .to_string(),
+        )
+    })?;
+    let salt = STANDARD
+        .decode(salt)
+        .map_err(|e| AppError::Internal(format!("Invalid encryption salt: {}", e)))?;
+    let key = ConversationKey::derive(secret, conversation_id, salt).await?;
+    match key.open(&check) {
+        Ok(opened) if opened == CHECK => Ok(Some(key)),
+        _ => Err(AppError::Forbidden(
+            "Wrong secret for this encrypted conversation".to_string(),
+        )),
+    }
+}
+
+/// Encrypt a conversation that has no messages yet with `secret`.
+///
+/// Fails if the conversation has messages, archived or not, or is
+/// encrypted already: messages are never re-encrypted.
+pub async fn encrypt(
+    pool: &PgPool,
+    conversation_id: &str,
+    secret: &str,
+) -> Result<ConversationKey> {
+    if secret.chars().count() < MIN_SECRET_CHARS {
+        return Err(AppError::InvalidInput(format!(
+            "Conversation secrets must be at least {} characters",
+            MIN_SECRET_CHARS
+        )));
+    }
+    let mut salt = [0u8; SALT_LEN];
+    SystemRandom::new()
+        .fill(&mut salt)
+        .map_err(|_| AppError::Internal("Failed to generate a salt".to_string()))?;
+    let key = ConversationKey::derive(secret, conversation_id, salt.to_vec()).await?;
+    let result = sqlx::query(
+        "UPDATE conversations SET encryption_salt = $2, encryption_check = $3
+         WHERE id = $1 AND encryption_salt IS NULL AND archived_at IS NULL
+         AND NOT EXISTS (SELECT 1 FROM messages WHERE conversation_id = $1)",
+    )
+    .bind(conversation_id)
+    .bind(STANDARD.encode(salt))
+    .bind(key.seal(CHECK)?)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to encrypt conversation: {}", e)))?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::InvalidInput(format!(
+            "Conversation {} already has messages; start a new conversation to encrypt it",
+            conversation_id
+        )));
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sealed_messages_open_only_with_their_conversation_key() {
+        let salt = vec![7u8; SALT_LEN];
+        let key = ConversationKey::derive("correct horse", "conv-1", salt.clone())
+            .await
+            .unwrap();
+        let sealed = key.seal("What is RAG?").unwrap();
+        assert!(sealed.starts_with(PREFIX));
+        assert!(!sealed.contains("RAG"));
+        assert_eq!(key.open(&sealed).unwrap(), "What is RAG?");
+        // Messages stored before encryption read as they are
+        assert_eq!(key.open("plain").unwrap(), "plain");
+
+        let wrong_secret = ConversationKey::derive("battery staple", "conv-1", salt.clone())
+            .await
+            .unwrap();
+        assert!(wrong_secret.open(&sealed).is_err());
+        let other_conversation = ConversationKey::derive("correct horse", "conv-2", salt)
+            .await
+            .unwrap();
+        assert!(other_conversation.open(&sealed).is_err());
+    }
+}
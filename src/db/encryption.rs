@@ -0,0 +1,453 @@
+//! Encryption at rest of sensitive columns and uploaded files.
+//!
+//! With `[encryption]` configured, memory facts, preferences, TOTP secrets
+//! and the content of uploaded files are sealed with AES-256-GCM before
+//! they are stored, so a copy of the database, a backup or the file store
+//! doesn't give them away. The key comes from an environment variable or a
+//! command printing it, such as a KMS decrypt call, and never reaches the
+//! database.
+//!
+//! Sealed values read `enc:k1:`, the ID of the key, a colon, then the
+//! base64 of the nonce and ciphertext; sealed files start with
+//! `ARESENC1` and the key ID. The column a value belongs to (e.g.
+//! `memory_facts.fact_value`) is bound to it as associated data, so a
+//! value can't be moved to another column.
+//!
+//! Values stored before encryption was configured read as they are.
+//! Keys are rotated by making the new key current and listing the old one
+//! under `previous_keys_env`: values sealed with either open, and
+//! `ares-server db rotate-key` re-seals everything under the current key
+//! (sealing plaintext left from before), after which the old key can go.
+
+use crate::db::files;
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::{EncryptionConfig, FilesConfig};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::sync::OnceLock;
+
+/// Prefix of sealed column values
+pub const PREFIX: &str = "enc:k1:";
+
+/// Start of sealed file content
+const FILE_MAGIC: &[u8] = b"ARESENC1";
+
+/// Length of a key ID (hex of the first bytes of the key's SHA-256)
+const KEY_ID_LEN: usize = 8;
+
+/// Column of memory facts' values
+pub const MEMORY_FACT: &str = "memory_facts.fact_value";
+/// Column of learned preferences' values
+pub const PREFERENCE: &str = "preferences.value";
+/// Column of TOTP secrets
+pub const MFA_SECRET: &str = "user_mfa.secret";
+/// Content of uploaded files
+pub const FILE_CONTENT: &str = "files.content";
+
+/// Sealed columns: table, key column, sealed column and its name
+const COLUMNS: [(&str, &str, &str, &str); 3] = [
+    ("memory_facts", "id", "fact_value", MEMORY_FACT),
+    ("preferences", "id", "value", PREFERENCE),
+    ("user_mfa", "user_id", "secret", MFA_SECRET),
+];
+
+/// The keys this server was started with
+static KEYS: OnceLock<KeyRing> = OnceLock::new();
+
+struct Key {
+    id: String,
+    key: LessSafeKey,
+}
+
+impl Key {
+    fn new(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != 32 {
+            return Err(AppError::Configuration(
+                "Encryption keys must be 32 bytes, base64-encoded (e.g. `openssl rand -base64 32`)"
+                    .to_string(),
+            ));
+        }
+        let key = UnboundKey::new(&AES_256_GCM, bytes)
+            .map_err(|_| AppError::Configuration("Invalid encryption key".to_string()))?;
+        Ok(Self {
+            id: hex::encode(Sha256::digest(bytes))[..KEY_ID_LEN].to_string(),
+            key: LessSafeKey::new(key),
+        })
+    }
+
+    fn parse(encoded: &str) -> Result<Self> {
+        let bytes = STANDARD.decode(encoded.trim()).map_err(|e| {
+            AppError::Configuration(format!("Encryption key is not valid base64: {}", e))
+        })?;
+        Self::new(&bytes)
+    }
+}
+
+/// The current encryption key and the previous ones still read.
+pub struct KeyRing {
+    current: Key,
+    previous: Vec<Key>,
+}
+
+impl std::fmt::Debug for KeyRing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyRing")
+            .field("current", &self.current.id)
+            .field(
+                "previous",
+                &self.previous.iter().map(|k| &k.id).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl KeyRing {
+    /// A key ring sealing with `current` and also opening `previous`.
+    pub fn new(current: &[u8], previous: &[Vec<u8>]) -> Result<Self> {
+        Ok(Self {
+            current: Key::new(current)?,
+            previous: previous
+                .iter()
+                .map(|bytes| Key::new(bytes))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// The keys of `[encryption]`, or `None` when it sets no key.
+    pub fn from_config(config: &EncryptionConfig) -> Result<Option<Self>> {
+        let current = match (&config.key_env, &config.key_command) {
+            (Some(var), _) => std::env::var(var).map_err(|_| {
+                AppError::Configuration(format!(
+                    "encryption.key_env names {}, which is not set",
+                    var
+                ))
+            })?,
+            (None, Some(command)) => run_key_command(command)?,
+            (None, None) => return Ok(None),
+        };
+        let previous = match &config.previous_keys_env {
+            Some(var) => std::env::var(var).unwrap_or_default(),
+            None => String::new(),
+        };
+        Ok(Some(Self {
+            current: Key::parse(&current)?,
+            previous: previous
+                .split(',')
+                .filter(|key| !key.trim().is_empty())
+                .map(Key::parse)
+                .collect::<Result<_>>()?,
+        }))
+    }
+
+    /// ID of the key new values are sealed with
+    pub fn key_id(&self) -> &str {
+        &self.current.id
+    }
+
+    fn key(&self, id: &str) -> Result<&Key> {
+        std::iter::once(&self.current)
+            .chain(&self.previous)
+            .find(|key| key.id == id)
+            .ok_or_else(|| {
+                AppError::Internal(format!(
+                    "Data is sealed with encryption key {}, which is not configured; \
+                     list it under encryption.previous_keys_env",
+                    id
+                ))
+            })
+    }
+
+    fn seal_with_current(&self, column: &str, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| AppError::Internal("Failed to generate a nonce".to_string()))?;
+        let mut sealed = plaintext.to_vec();
+        self.current
+            .key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(column.as_bytes()),
+                &mut sealed,
+            )
+            .map_err(|_| AppError::Internal(format!("Failed to encrypt {}", column)))?;
+        let mut stored = nonce.to_vec();
+        stored.extend(sealed);
+        Ok(stored)
+    }
+
+    fn open_with(&self, key_id: &str, column: &str, mut stored: Vec<u8>) -> Result<Vec<u8>> {
+        let failed = || AppError::Internal(format!("Failed to decrypt {}", column));
+        let key = self.key(key_id)?;
+        if stored.len() < NONCE_LEN {
+            return Err(failed());
+        }
+        let mut sealed = stored.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&stored).map_err(|_| failed())?;
+        let plaintext = key
+            .key
+            .open_in_place(nonce, Aad::from(column.as_bytes()), &mut sealed)
+            .map_err(|_| failed())?;
+        Ok(plaintext.to_vec())
+    }
+
+    /// Seal a value of `column`
+    pub fn seal(&self, column: &str, plaintext: &str) -> Result<String> {
+        let sealed = self.seal_with_current(column, plaintext.as_bytes())?;
+        Ok(format!(
+            "{}{}:{}",
+            PREFIX,
+            self.current.id,
+            STANDARD.encode(sealed)
+        ))
+    }
+
+    /// Open a value of `column`; a value that isn't sealed is returned as is.
+    pub fn open(&self, column: &str, stored: &str) -> Result<String> {
+        let Some((key_id, encoded)) = split_sealed(stored) else {
+            return Ok(stored.to_string());
+        };
+        let bytes = STANDARD
+            .decode(encoded)
+            .map_err(|_| AppError::Internal(format!("Failed to decrypt {}", column)))?;
+        String::from_utf8(self.open_with(key_id, column, bytes)?)
+            .map_err(|_| AppError::Internal(format!("Failed to decrypt {}", column)))
+    }
+
+    /// Seal binary content of `column`
+    pub fn seal_bytes(&self, column: &str, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut stored = FILE_MAGIC.to_vec();
+        stored.extend(self.current.id.as_bytes());
+        stored.extend(self.seal_with_current(column, plaintext)?);
+        Ok(stored)
+    }
+
+    /// Open binary content of `column`; content that isn't sealed is
+    /// returned as is.
+    pub fn open_bytes(&self, column: &str, stored: Vec<u8>) -> Result<Vec<u8>> {
+        let Some(key_id) = sealed_bytes_key(&stored) else {
+            return Ok(stored);
+        };
+        let key_id = key_id.to_string();
+        let sealed = stored[FILE_MAGIC.len() + KEY_ID_LEN..].to_vec();
+        self.open_with(&key_id, column, sealed)
+    }
+
+    /// Whether a stored value is sealed with the current key
+    pub fn is_current(&self, stored: &str) -> bool {
+        split_sealed(stored).is_some_and(|(key_id, _)| key_id == self.current.id)
+    }
+
+    /// Whether stored binary content is sealed with the current key
+    pub fn is_current_bytes(&self, stored: &[u8]) -> bool {
+        sealed_bytes_key(stored) == Some(self.current.id.as_str())
+    }
+}
+
+/// Key ID and base64 of a sealed value
+fn split_sealed(stored: &str) -> Option<(&str, &str)> {
+    stored.strip_prefix(PREFIX)?.split_once(':')
+}
+
+/// Key ID of sealed binary content
+fn sealed_bytes_key(stored: &[u8]) -> Option<&str> {
+    let rest = stored.strip_prefix(FILE_MAGIC)?;
+    std::str::from_utf8(rest.get(..KEY_ID_LEN)?).ok()
+}
+
+/// Run `encryption.key_command`, returning what it prints.
+fn run_key_command(command: &[String]) -> Result<String> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| AppError::Configuration("encryption.key_command is empty".to_string()))?;
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| {
+            AppError::Configuration(format!("Failed to run encryption.key_command: {}", e))
+        })?;
+    if !output.status.success() {
+        return Err(AppError::Configuration(format!(
+            "encryption.key_command failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    String::from_utf8(output.stdout).map_err(|_| {
+        AppError::Configuration("encryption.key_command printed invalid UTF-8".to_string())
+    })
+}
+
+/// Seal with `keys` the values of this process from now on.
+///
+/// Returns false if keys were installed already; they stay as they were.
+pub fn install(keys: KeyRing) -> bool {
+    KEYS.set(keys).is_ok()
+}
+
+/// The keys installed, if encryption is configured
+pub fn keys() -> Option<&'static KeyRing> {
+    KEYS.get()
+}
+
+/// Seal a value of `column` for storage; stored as is without a key.
+pub fn seal(column: &str, plaintext: &str) -> Result<String> {
+    match keys() {
+        Some(keys) => keys.seal(column, plaintext),
+        None => Ok(plaintext.to_string()),
+    }
+}
+
+/// Open a stored value of `column`.
+///
+/// Values that aren't sealed are returned as is. Sealed values fail to open
+/// without `[encryption]`.
+pub fn open(column: &str, stored: &str) -> Result<String> {
+    match keys() {
+        Some(keys) => keys.open(column, stored),
+        None if split_sealed(stored).is_some() => Err(AppError::Configuration(format!(
+            "{} is encrypted, but no [encryption] key is configured",
+            column
+        ))),
+        None => Ok(stored.to_string()),
+    }
+}
+
+/// Seal binary content of `column` for storage; stored as is without a key.
+pub fn seal_bytes(column: &str, plaintext: Vec<u8>) -> Result<Vec<u8>> {
+    match keys() {
+        Some(keys) => keys.seal_bytes(column, &plaintext),
+        None => Ok(plaintext),
+    }
+}
+
+/// Open stored binary content of `column`, as [`open`] does values.
+pub fn open_bytes(column: &str, stored: Vec<u8>) -> Result<Vec<u8>> {
+    match keys() {
+        Some(keys) => keys.open_bytes(column, stored),
+        None if sealed_bytes_key(&stored).is_some() => Err(AppError::Configuration(format!(
+            "{} is encrypted, but no [encryption] key is configured",
+            column
+        ))),
+        None => Ok(stored),
+    }
+}
+
+/// What [`rotate`] re-sealed.
+#[derive(Debug, Clone, Default)]
+pub struct RotationReport {
+    /// Column values re-sealed under the current key, per column
+    pub columns: Vec<(&'static str, u64)>,
+    /// Uploaded files re-sealed under the current key
+    pub files: u64,
+}
+
+/// Re-seal under the current key every value and uploaded file sealed with
+/// a previous key or stored before encryption was configured.
+///
+/// Safe to run while servers are running, as long as they have the same
+/// keys: a value changed in the meantime is left for the next run.
+pub async fn rotate(pool: &PgPool, keys: &KeyRing, files: &FilesConfig) -> Result<RotationReport> {
+    let mut report = RotationReport::default();
+    for (table, id_column, column, name) in COLUMNS {
+        let rows: Vec<(String, String)> = sqlx::query_as(&format!(
+            "SELECT {id}, {col} FROM {table} WHERE {col} IS NOT NULL",
+            id = id_column,
+            col = column,
+            table = table
+        ))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to read {}: {}", name, e)))?;
+
+        let mut resealed = 0;
+        for (id, stored) in rows {
+            if keys.is_current(&stored) {
+                continue;
+            }
+            let sealed = keys.seal(name, &keys.open(name, &stored)?)?;
+            let result = sqlx::query(&format!(
+                "UPDATE {table} SET {col} = $1 WHERE {id} = $2 AND {col} = $3",
+                id = id_column,
+                col = column,
+                table = table
+            ))
+            .bind(sealed)
+            .bind(&id)
+            .bind(&stored)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to re-seal {}: {}", name, e)))?;
+            resealed += result.rows_affected();
+        }
+        report.columns.push((name, resealed));
+    }
+
+    for file in files::list_all_files(pool).await? {
+        if files::reseal_content(files, &file, keys).await? {
+            report.files += 1;
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sealed_values_open_only_in_their_column() {
+        let keys = KeyRing::new(&[7u8; 32], &[]).unwrap();
+        let sealed = keys.seal(MEMORY_FACT, "Prefers metric units").unwrap();
+        assert!(sealed.starts_with(PREFIX));
+        assert!(!sealed.contains("metric"));
+        assert!(keys.is_current(&sealed));
+        assert_eq!(
+            keys.open(MEMORY_FACT, &sealed).unwrap(),
+            "Prefers metric units"
+        );
+        assert!(keys.open(PREFERENCE, &sealed).is_err());
+        // Values stored before encryption read as they are
+        assert_eq!(keys.open(MEMORY_FACT, "plain").unwrap(), "plain");
+        assert!(!keys.is_current("plain"));
+
+        let content = keys.seal_bytes(FILE_CONTENT, b"# Handbook").unwrap();
+        assert!(keys.is_current_bytes(&content));
+        assert_eq!(
+            keys.open_bytes(FILE_CONTENT, content).unwrap(),
+            b"# Handbook"
+        );
+        assert_eq!(
+            keys.open_bytes(FILE_CONTENT, b"plain".to_vec()).unwrap(),
+            b"plain"
+        );
+    }
+
+    #[test]
+    fn test_previous_keys_open_until_rotated() {
+        let old = KeyRing::new(&[1u8; 32], &[]).unwrap();
+        let sealed = old.seal(MFA_SECRET, "JBSWY3DPEHPK3PXP").unwrap();
+
+        let rotated = KeyRing::new(&[2u8; 32], &[vec![1u8; 32]]).unwrap();
+        assert_ne!(rotated.key_id(), old.key_id());
+        assert!(!rotated.is_current(&sealed));
+        assert_eq!(
+            rotated.open(MFA_SECRET, &sealed).unwrap(),
+            "JBSWY3DPEHPK3PXP"
+        );
+
+        let dropped = KeyRing::new(&[2u8; 32], &[]).unwrap();
+        assert!(dropped.open(MFA_SECRET, &sealed).is_err());
+    }
+
+    #[test]
+    fn test_keys_must_be_32_bytes() {
+        assert!(KeyRing::new(&[0u8; 16], &[]).is_err());
+        assert!(Key::parse("not base64!").is_err());
+        assert!(Key::parse(&STANDARD.encode([3u8; 32])).is_ok());
+    }
+}
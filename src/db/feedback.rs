@@ -0,0 +1,347 @@
+//! Storage for users' feedback on assistant messages.
+//!
+//! Every assistant message has its [`Generation`] recorded: the agent, model
+//! and system prompt version that produced it. Feedback on a message is
+//! stored with its generation and a snapshot of the question and answer, so
+//! it stays usable as an evaluation dataset after the conversation changes
+//! or is deleted.
+
+use crate::types::{AppError, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+const COLUMNS: &str = "id, conversation_id, message_id, user_id, rating, comment, agent, model, \
+                       prompt_version, question, answer, created_at, updated_at";
+
+/// What generated an assistant message.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Generation {
+    /// Agent that answered (after any handoffs)
+    pub agent: String,
+    /// Model the agent answered with
+    pub model: String,
+    /// Version of the agent's system prompt (see
+    /// [`AgentConfig::prompt_version`](crate::utils::toml_config::AgentConfig::prompt_version))
+    pub prompt_version: String,
+}
+
+/// A user's rating of an assistant message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Rating {
+    /// Thumbs up
+    Up,
+    /// Thumbs down
+    Down,
+}
+
+impl Rating {
+    /// Name stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rating::Up => "up",
+            Rating::Down => "down",
+        }
+    }
+}
+
+/// Feedback on an assistant message.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct MessageFeedback {
+    /// Feedback ID
+    pub id: String,
+    /// Conversation the message is in
+    pub conversation_id: String,
+    /// The rated message
+    pub message_id: String,
+    /// User who gave the feedback
+    pub user_id: String,
+    /// "up" or "down"
+    pub rating: String,
+    /// Free-text comment
+    pub comment: Option<String>,
+    /// Agent that generated the message, when recorded
+    pub agent: Option<String>,
+    /// Model that generated the message, when recorded
+    pub model: Option<String>,
+    /// System prompt version the message was generated with, when recorded
+    pub prompt_version: Option<String>,
+    /// The user message the rated message answered
+    pub question: String,
+    /// The rated message
+    pub answer: String,
+    /// When the feedback was first given (Unix timestamp)
+    pub created_at: i64,
+    /// When the feedback was last changed (Unix timestamp)
+    pub updated_at: i64,
+}
+
+/// Which feedback to list.
+#[derive(Debug, Clone, Default)]
+pub struct FeedbackFilter {
+    /// Only feedback with this rating
+    pub rating: Option<Rating>,
+    /// Only feedback on messages generated by this agent
+    pub agent: Option<String>,
+    /// Feedback returned at most
+    pub limit: i64,
+    /// Feedback skipped before the first one returned
+    pub offset: i64,
+}
+
+/// Position in a feedback export: the last feedback exported.
+///
+/// Export pages continue strictly after it in `(created_at, id)` order, so
+/// feedback given while an export runs neither repeats nor hides exported
+/// rows the way shifting offsets would.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportCursor {
+    /// When the last exported feedback was first given
+    pub created_at: i64,
+    /// ID of the last exported feedback
+    pub id: String,
+}
+
+impl ExportCursor {
+    /// Cursor continuing after `feedback`
+    pub fn after(feedback: &MessageFeedback) -> Self {
+        Self {
+            created_at: feedback.created_at,
+            id: feedback.id.clone(),
+        }
+    }
+}
+
+/// Record what generated an assistant message, replacing any earlier record
+/// (a regenerated message keeps its ID).
+pub async fn record_generation(
+    pool: &PgPool,
+    conversation_id: &str,
+    message_id: &str,
+    generation: &Generation,
+    now: i64,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO message_generations
+             (message_id, conversation_id, agent, model, prompt_version, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         ON CONFLICT (message_id) DO UPDATE
+             SET agent = $3, model = $4, prompt_version = $5, created_at = $6",
+    )
+    .bind(message_id)
+    .bind(conversation_id)
+    .bind(&generation.agent)
+    .bind(&generation.model)
+    .bind(&generation.prompt_version)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to record message generation: {}", e)))?;
+    Ok(())
+}
+
+/// What generated a message, if it was recorded.
+pub async fn get_generation(
+    pool: &PgPool,
+    conversation_id: &str,
+    message_id: &str,
+) -> Result<Option<Generation>> {
+    sqlx::query_as::<_, Generation>(
+        "SELECT agent, model, prompt_version FROM message_generations
+         WHERE message_id = $1 AND conversation_id = $2",
+    )
+    .bind(message_id)
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get message generation: {}", e)))
+}
+
+/// Delete the generation records of a conversation's messages.
+///
+/// Feedback is kept: it holds its own snapshot of the exchange.
+pub async fn delete_conversation_generations(pool: &PgPool, conversation_id: &str) -> Result<()> {
+    sqlx::query("DELETE FROM message_generations WHERE conversation_id = $1")
+        .bind(conversation_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to delete message generations: {}", e)))?;
+    Ok(())
+}
+
+/// Store a user's feedback on a message, replacing their earlier feedback
+/// on it. Returns the stored feedback.
+pub async fn upsert_feedback(pool: &PgPool, feedback: &MessageFeedback) -> Result<MessageFeedback> {
+    sqlx::query_as::<_, MessageFeedback>(&format!(
+        "INSERT INTO message_feedback ({})
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+         ON CONFLICT (message_id, user_id) DO UPDATE SET rating = $5, comment = $6, agent = $7,
+             model = $8, prompt_version = $9, question = $10, answer = $11, updated_at = $13
+         RETURNING {}",
+        COLUMNS, COLUMNS
+    ))
+    .bind(&feedback.id)
+    .bind(&feedback.conversation_id)
+    .bind(&feedback.message_id)
+    .bind(&feedback.user_id)
+    .bind(&feedback.rating)
+    .bind(&feedback.comment)
+    .bind(&feedback.agent)
+    .bind(&feedback.model)
+    .bind(&feedback.prompt_version)
+    .bind(&feedback.question)
+    .bind(&feedback.answer)
+    .bind(feedback.created_at)
+    .bind(feedback.updated_at)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to store message feedback: {}", e)))
+}
+
+/// List feedback matching `filter`, newest first.
+pub async fn list_feedback(pool: &PgPool, filter: &FeedbackFilter) -> Result<Vec<MessageFeedback>> {
+    sqlx::query_as::<_, MessageFeedback>(&format!(
+        "SELECT {} FROM message_feedback
+         WHERE ($1::TEXT IS NULL OR rating = $1) AND ($2::TEXT IS NULL OR agent = $2)
+         ORDER BY created_at DESC, id LIMIT $3 OFFSET $4",
+        COLUMNS
+    ))
+    .bind(filter.rating.map(|r| r.as_str()))
+    .bind(&filter.agent)
+    .bind(filter.limit)
+    .bind(filter.offset)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list message feedback: {}", e)))
+}
+
+/// A page of feedback matching `filter` for export, newest first, starting
+/// after `after` (from the newest feedback when `None`).
+///
+/// `filter.offset` is ignored: pages follow on from their cursor.
+pub async fn export_feedback(
+    pool: &PgPool,
+    filter: &FeedbackFilter,
+    after: Option<&ExportCursor>,
+) -> Result<Vec<MessageFeedback>> {
+    sqlx::query_as::<_, MessageFeedback>(&format!(
+        "SELECT {} FROM message_feedback
+         WHERE ($1::TEXT IS NULL OR rating = $1) AND ($2::TEXT IS NULL OR agent = $2)
+             AND ($3::BIGINT IS NULL OR (created_at, id) < ($3, $4))
+         ORDER BY created_at DESC, id DESC LIMIT $5",
+        COLUMNS
+    ))
+    .bind(filter.rating.map(|r| r.as_str()))
+    .bind(&filter.agent)
+    .bind(after.map(|c| c.created_at))
+    .bind(after.map(|c| c.id.as_str()))
+    .bind(filter.limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to export message feedback: {}", e)))
+}
+
+/// The conversation a message is in, if it exists.
+///
+/// Assistant messages of archived conversations are found through their
+/// generation records.
+pub async fn conversation_of_message(pool: &PgPool, message_id: &str) -> Result<Option<String>> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT conversation_id FROM messages WHERE id = $1
+         UNION ALL
+         SELECT conversation_id FROM message_generations WHERE message_id = $1
+         LIMIT 1",
+    )
+    .bind(message_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to find message: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feedback(id: &str, rating: Rating, agent: &str, created_at: i64) -> MessageFeedback {
+        MessageFeedback {
+            id: id.to_string(),
+            conversation_id: "c1".to_string(),
+            message_id: format!("m-{}", id),
+            user_id: "alice".to_string(),
+            rating: rating.as_str().to_string(),
+            comment: None,
+            agent: Some(agent.to_string()),
+            model: None,
+            prompt_version: None,
+            question: "Where is my order?".to_string(),
+            answer: "I don't know.".to_string(),
+            created_at,
+            updated_at: created_at + 500,
+        }
+    }
+
+    #[test]
+    fn test_rating_filters_match_stored_names() {
+        let up: Rating = serde_json::from_str("\"up\"").unwrap();
+        let down: Rating = serde_json::from_str("\"down\"").unwrap();
+        assert_eq!(up.as_str(), "up");
+        assert_eq!(down.as_str(), "down");
+        assert!(serde_json::from_str::<Rating>("\"meh\"").is_err());
+    }
+
+    #[test]
+    fn test_export_cursor_follows_the_last_feedback() {
+        // Updating feedback keeps its place in the export
+        assert_eq!(
+            ExportCursor::after(&feedback("fb-2", Rating::Down, "support", 1_700_000_000)),
+            ExportCursor {
+                created_at: 1_700_000_000,
+                id: "fb-2".to_string(),
+            }
+        );
+    }
+
+    // Needs PostgreSQL with the ARES schema at `DATABASE_URL`; run with
+    // `cargo test -- --ignored`.
+    #[tokio::test]
+    #[ignore]
+    async fn test_export_filters_and_pages_by_keyset() {
+        let db = crate::db::PostgresClient::new_memory().await.unwrap();
+        let pool = &db.pool;
+        let agent = uuid::Uuid::new_v4().to_string();
+        let id = |name: &str| format!("{}-{}", agent, name);
+        for item in [
+            feedback(&id("a"), Rating::Up, &agent, 100),
+            feedback(&id("b"), Rating::Down, &agent, 200),
+            feedback(&id("c"), Rating::Down, &agent, 200),
+            feedback(&id("d"), Rating::Down, &agent, 300),
+            feedback(&id("other"), Rating::Down, "another-agent", 300),
+        ] {
+            upsert_feedback(pool, &item).await.unwrap();
+        }
+        let filter = FeedbackFilter {
+            rating: Some(Rating::Down),
+            agent: Some(agent.clone()),
+            limit: 2,
+            offset: 0,
+        };
+        let ids = |page: &[MessageFeedback]| page.iter().map(|f| f.id.clone()).collect::<Vec<_>>();
+
+        let first = export_feedback(pool, &filter, None).await.unwrap();
+        assert_eq!(ids(&first), [id("d"), id("c")]);
+        // Feedback given during the export doesn't shift later pages
+        upsert_feedback(pool, &feedback(&id("e"), Rating::Down, &agent, 400))
+            .await
+            .unwrap();
+        let cursor = ExportCursor::after(first.last().unwrap());
+        let second = export_feedback(pool, &filter, Some(&cursor)).await.unwrap();
+        assert_eq!(ids(&second), [id("b")]);
+
+        let up = FeedbackFilter {
+            rating: Some(Rating::Up),
+            ..filter
+        };
+        assert_eq!(ids(&list_feedback(pool, &up).await.unwrap()), [id("a")]);
+    }
+}
@@ -0,0 +1,269 @@
+//! Storage for uploaded files.
+//!
+//! A file's metadata is kept in the `files` table and its content in an
+//! object named after its ID under `[files] location`, a directory or an
+//! `s3://` prefix. Uploaded names are only stored as metadata, so they can
+//! never pick or escape the path a file is written to. With `[encryption]`
+//! configured, content is sealed before it is written (see
+//! [`crate::db::encryption`]).
+
+use crate::db::encryption::{self, KeyRing};
+use crate::rag::connectors::{DocumentSource, S3Source, SourceLocation, SourceScheme};
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::FilesConfig;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::path::PathBuf;
+use utoipa::ToSchema;
+
+const COLUMNS: &str =
+    "id, user_id, filename, content_type, size_bytes, sha256, location, created_at, project_id";
+
+/// An uploaded file.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct StoredFile {
+    /// File ID, used to reference the file in chat and RAG requests
+    pub id: String,
+    /// Owner of the file
+    pub user_id: String,
+    /// Name the file was uploaded with
+    pub filename: String,
+    /// Content type, e.g. "text/markdown"
+    pub content_type: String,
+    /// Size in bytes
+    pub size_bytes: i64,
+    /// SHA-256 of the content, hex-encoded
+    pub sha256: String,
+    /// File or `s3://` URI of the content
+    #[serde(skip)]
+    pub location: String,
+    /// When the file was uploaded (Unix timestamp)
+    pub created_at: i64,
+    /// Project the file belongs to, if any
+    pub project_id: Option<String>,
+}
+
+/// Store a file's metadata.
+pub async fn insert_file(pool: &PgPool, file: &StoredFile) -> Result<()> {
+    sqlx::query(&format!(
+        "INSERT INTO files ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        COLUMNS
+    ))
+    .bind(&file.id)
+    .bind(&file.user_id)
+    .bind(&file.filename)
+    .bind(&file.content_type)
+    .bind(file.size_bytes)
+    .bind(&file.sha256)
+    .bind(&file.location)
+    .bind(file.created_at)
+    .bind(&file.project_id)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to store file: {}", e)))?;
+    Ok(())
+}
+
+/// List a user's files, newest first, optionally only those of a project.
+pub async fn list_files(
+    pool: &PgPool,
+    user_id: &str,
+    project_id: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<StoredFile>> {
+    sqlx::query_as::<_, StoredFile>(&format!(
+        "SELECT {} FROM files WHERE user_id = $1 AND ($4::text IS NULL OR project_id = $4)
+         ORDER BY created_at DESC, id LIMIT $2 OFFSET $3",
+        COLUMNS
+    ))
+    .bind(user_id)
+    .bind(limit)
+    .bind(offset)
+    .bind(project_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list files: {}", e)))
+}
+
+/// Get one of a user's files.
+pub async fn get_file(pool: &PgPool, id: &str, user_id: &str) -> Result<Option<StoredFile>> {
+    sqlx::query_as::<_, StoredFile>(&format!(
+        "SELECT {} FROM files WHERE id = $1 AND user_id = $2",
+        COLUMNS
+    ))
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get file: {}", e)))
+}
+
+/// Every user's files, oldest first.
+pub async fn list_all_files(pool: &PgPool) -> Result<Vec<StoredFile>> {
+    sqlx::query_as::<_, StoredFile>(&format!(
+        "SELECT {} FROM files ORDER BY created_at, id",
+        COLUMNS
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list files: {}", e)))
+}
+
+/// Delete a file's metadata.
+pub async fn delete_file(pool: &PgPool, id: &str) -> Result<()> {
+    sqlx::query("DELETE FROM files WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to delete file: {}", e)))?;
+    Ok(())
+}
+
+/// URI of a file's content under `location`
+fn object_uri(location: &str, id: &str) -> String {
+    format!("{}/{}", location.trim_end_matches('/'), hex::encode(id))
+}
+
+/// Write a file's content under `[files] location`, returning its URI.
+///
+/// The content is sealed first when `[encryption]` is configured.
+pub async fn write_content(config: &FilesConfig, id: &str, bytes: Vec<u8>) -> Result<String> {
+    let uri = object_uri(config.location.trim(), id);
+    let sealed = encryption::seal_bytes(encryption::FILE_CONTENT, bytes)?;
+    FileObject::open(&uri, config)?.write(sealed).await?;
+    Ok(uri)
+}
+
+/// Read the content of a stored file.
+pub async fn read_content(config: &FilesConfig, file: &StoredFile) -> Result<Vec<u8>> {
+    let stored = FileObject::open(&file.location, config)?.read().await?;
+    encryption::open_bytes(encryption::FILE_CONTENT, stored)
+}
+
+/// Seal a stored file's content under the current key of `keys`, unless it
+/// is already, returning whether it was re-sealed.
+pub async fn reseal_content(
+    config: &FilesConfig,
+    file: &StoredFile,
+    keys: &KeyRing,
+) -> Result<bool> {
+    let object = FileObject::open(&file.location, config)?;
+    let stored = object.read().await?;
+    if keys.is_current_bytes(&stored) {
+        return Ok(false);
+    }
+    let content = keys.open_bytes(encryption::FILE_CONTENT, stored)?;
+    object
+        .write(keys.seal_bytes(encryption::FILE_CONTENT, &content)?)
+        .await?;
+    Ok(true)
+}
+
+/// Delete the content of a stored file.
+pub async fn delete_content(config: &FilesConfig, file: &StoredFile) -> Result<()> {
+    FileObject::open(&file.location, config)?.delete().await
+}
+
+/// A stored file's content on disk or in S3
+enum FileObject {
+    File(PathBuf),
+    S3 { source: S3Source, key: String },
+}
+
+impl FileObject {
+    /// Open a content URI, with S3 credentials from `[files.s3]`
+    fn open(uri: &str, config: &FilesConfig) -> Result<Self> {
+        if !uri.starts_with("s3://") {
+            return Ok(Self::File(PathBuf::from(uri)));
+        }
+        let location: SourceLocation = uri.parse()?;
+        if location.scheme != SourceScheme::S3 || location.prefix.is_empty() {
+            return Err(AppError::Configuration(format!(
+                "Invalid file location {}",
+                uri
+            )));
+        }
+        Ok(Self::S3 {
+            source: S3Source::from_config(&config.s3, &location)?,
+            key: location.prefix,
+        })
+    }
+
+    async fn write(&self, bytes: Vec<u8>) -> Result<()> {
+        match self {
+            Self::File(path) => {
+                let io_err = |e: std::io::Error| {
+                    AppError::Internal(format!("Failed to write file {}: {}", path.display(), e))
+                };
+                if let Some(dir) = path.parent() {
+                    tokio::fs::create_dir_all(dir).await.map_err(io_err)?;
+                }
+                // Write then rename, so a crash never leaves a partial file
+                let partial = path.with_extension("partial");
+                tokio::fs::write(&partial, bytes).await.map_err(io_err)?;
+                tokio::fs::rename(&partial, path).await.map_err(io_err)
+            }
+            Self::S3 { source, key } => source.put(key, bytes).await,
+        }
+    }
+
+    async fn read(&self) -> Result<Vec<u8>> {
+        match self {
+            Self::File(path) => tokio::fs::read(path).await.map_err(|e| {
+                AppError::Internal(format!("Failed to read file {}: {}", path.display(), e))
+            }),
+            Self::S3 { source, key } => source.fetch(key).await,
+        }
+    }
+
+    async fn delete(&self) -> Result<()> {
+        match self {
+            Self::File(path) => match tokio::fs::remove_file(path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(AppError::Internal(
+                    format!("Failed to delete file {}: {}", path.display(), e),
+                )),
+                _ => Ok(()),
+            },
+            Self::S3 { source, key } => source.delete(key).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_content_write_read_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = FilesConfig {
+            location: dir.path().join("files").to_string_lossy().to_string(),
+            ..Default::default()
+        };
+
+        let location = write_content(&config, "../../etc/passwd", b"notes".to_vec())
+            .await
+            .unwrap();
+        // IDs never leave the configured location
+        assert!(location.starts_with(&config.location));
+        assert!(!location[config.location.len()..].contains(".."));
+
+        let file = StoredFile {
+            id: "f1".to_string(),
+            user_id: "alice".to_string(),
+            filename: "notes.txt".to_string(),
+            content_type: "text/plain".to_string(),
+            size_bytes: 5,
+            sha256: String::new(),
+            location,
+            created_at: 0,
+            project_id: None,
+        };
+        assert_eq!(read_content(&config, &file).await.unwrap(), b"notes");
+        delete_content(&config, &file).await.unwrap();
+        assert!(read_content(&config, &file).await.is_err());
+        // Deleting twice is fine
+        delete_content(&config, &file).await.unwrap();
+    }
+}
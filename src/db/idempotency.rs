@@ -0,0 +1,169 @@
+//! Storage for idempotency keys.
+//!
+//! A chat or research request sent with an `Idempotency-Key` header claims
+//! the key for its caller (see [`crate::middleware::idempotency`]). Its
+//! response is kept under the key until it expires, so a retry gets the
+//! same response instead of running the request again.
+
+use crate::db::locks;
+use crate::types::{AppError, Result};
+use sqlx::PgPool;
+use std::time::Duration;
+
+/// How long a request may hold a key without finishing before a retry
+/// takes it over, in seconds (a server restart leaves claims unfinished)
+const ABANDONED_AFTER_SECS: i64 = 15 * 60;
+
+/// A response kept for replay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Content-Type header, if any
+    pub content_type: Option<String>,
+    /// Response body
+    pub body: Vec<u8>,
+}
+
+/// What a request finds when claiming its key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Claim {
+    /// The key is the request's; it should run
+    Claimed,
+    /// An earlier request with the key is still running
+    InProgress,
+    /// An earlier request with the key finished with this response
+    Completed(StoredResponse),
+    /// The key was used for a different request
+    Mismatch,
+}
+
+/// Claim `key` for `caller`'s request with `fingerprint`, for `ttl_secs`.
+///
+/// Expired keys, and keys held by requests that never finished, are taken
+/// over.
+pub async fn claim(
+    pool: &PgPool,
+    caller: &str,
+    key: &str,
+    fingerprint: &str,
+    ttl_secs: u64,
+) -> Result<Claim> {
+    let now = chrono::Utc::now().timestamp();
+    let claimed = sqlx::query(
+        "INSERT INTO idempotency_keys (caller, key, fingerprint, created_at, expires_at)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (caller, key) DO UPDATE SET
+             fingerprint = EXCLUDED.fingerprint, status_code = NULL, content_type = NULL,
+             body = NULL, created_at = EXCLUDED.created_at, expires_at = EXCLUDED.expires_at
+         WHERE idempotency_keys.expires_at <= $4
+            OR (idempotency_keys.status_code IS NULL AND idempotency_keys.created_at <= $6)",
+    )
+    .bind(caller)
+    .bind(key)
+    .bind(fingerprint)
+    .bind(now)
+    .bind(now.saturating_add(ttl_secs as i64))
+    .bind(now - ABANDONED_AFTER_SECS)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to claim idempotency key: {}", e)))?;
+    if claimed.rows_affected() > 0 {
+        return Ok(Claim::Claimed);
+    }
+
+    #[derive(sqlx::FromRow)]
+    struct KeyRow {
+        fingerprint: String,
+        status_code: Option<i32>,
+        content_type: Option<String>,
+        body: Option<Vec<u8>>,
+    }
+
+    let row = sqlx::query_as::<_, KeyRow>(
+        "SELECT fingerprint, status_code, content_type, body FROM idempotency_keys
+         WHERE caller = $1 AND key = $2",
+    )
+    .bind(caller)
+    .bind(key)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to read idempotency key: {}", e)))?;
+    Ok(match row {
+        // Released between the two queries; let the request run
+        None => Claim::Claimed,
+        Some(row) if row.fingerprint != fingerprint => Claim::Mismatch,
+        Some(KeyRow {
+            status_code: Some(status),
+            content_type,
+            body,
+            ..
+        }) => Claim::Completed(StoredResponse {
+            status: status as u16,
+            content_type,
+            body: body.unwrap_or_default(),
+        }),
+        Some(_) => Claim::InProgress,
+    })
+}
+
+/// Keep the response to the request holding `key`.
+pub async fn complete(
+    pool: &PgPool,
+    caller: &str,
+    key: &str,
+    response: &StoredResponse,
+) -> Result<()> {
+    sqlx::query(
+        "UPDATE idempotency_keys SET status_code = $3, content_type = $4, body = $5
+         WHERE caller = $1 AND key = $2",
+    )
+    .bind(caller)
+    .bind(key)
+    .bind(response.status as i32)
+    .bind(&response.content_type)
+    .bind(&response.body)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to store idempotent response: {}", e)))?;
+    Ok(())
+}
+
+/// Give up `key` without a response, so a retry runs the request again.
+pub async fn release(pool: &PgPool, caller: &str, key: &str) -> Result<()> {
+    sqlx::query("DELETE FROM idempotency_keys WHERE caller = $1 AND key = $2")
+        .bind(caller)
+        .bind(key)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to release idempotency key: {}", e)))?;
+    Ok(())
+}
+
+/// Delete expired keys, returning how many were deleted.
+pub async fn purge_expired(pool: &PgPool) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM idempotency_keys WHERE expires_at <= $1")
+        .bind(chrono::Utc::now().timestamp())
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to purge idempotency keys: {}", e)))?;
+    Ok(result.rows_affected())
+}
+
+/// Purge expired keys every hour, on the leader
+pub fn spawn_purger(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            if !locks::is_leader() {
+                continue;
+            }
+            match purge_expired(&pool).await {
+                Ok(0) => {}
+                Ok(n) => tracing::debug!("Purged {} expired idempotency keys", n),
+                Err(e) => tracing::warn!("Idempotency key purge failed: {}", e),
+            }
+        }
+    });
+}
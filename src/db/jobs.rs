@@ -0,0 +1,313 @@
+//! Storage for durable background jobs.
+//!
+//! Work that has to survive a restart is queued as a row in `jobs` rather
+//! than living only in a tokio task. Enqueueing takes any executor, so a job
+//! can be queued in the same transaction as the change that calls for it.
+//!
+//! Workers claim due jobs with `FOR UPDATE SKIP LOCKED` and hold them under
+//! a lease they extend while the job runs. A job whose worker stopped is
+//! claimed again once its lease expires. Failed jobs are retried later until
+//! they run out of attempts, then kept as dead letters until retried by hand.
+//!
+//! The workers themselves live in [`crate::api::jobs`].
+
+use crate::types::{AppError, Result};
+use serde::Serialize;
+use sqlx::{PgExecutor, PgPool};
+
+const COLUMNS: &str = "id, kind, payload, dedupe_key, status, attempts, max_attempts, run_at, \
+                       locked_by, locked_until, last_error, created_at, updated_at, finished_at";
+
+/// Waiting for its run time or a free worker
+pub const PENDING: &str = "pending";
+/// Claimed by a worker
+pub const RUNNING: &str = "running";
+/// Finished without error
+pub const SUCCEEDED: &str = "succeeded";
+/// Failed on every attempt; kept until retried or deleted
+pub const DEAD: &str = "dead";
+
+/// A queued unit of background work.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Job {
+    /// Job ID
+    pub id: String,
+    /// What the job does, which picks its handler
+    pub kind: String,
+    /// Arguments of the handler
+    pub payload: serde_json::Value,
+    /// Identifies the work, so it is queued once while pending or running
+    pub dedupe_key: Option<String>,
+    /// "pending", "running", "succeeded" or "dead"
+    pub status: String,
+    /// Attempts started so far, including the running one
+    pub attempts: i32,
+    /// Attempts before the job is dead-lettered
+    pub max_attempts: i32,
+    /// When the job is due (Unix timestamp)
+    pub run_at: i64,
+    /// Worker holding the job while it runs
+    pub locked_by: Option<String>,
+    /// End of the worker's lease (Unix timestamp)
+    pub locked_until: Option<i64>,
+    /// Why the latest attempt failed
+    pub last_error: Option<String>,
+    /// Creation time (Unix timestamp)
+    pub created_at: i64,
+    /// Last update time (Unix timestamp)
+    pub updated_at: i64,
+    /// When the job succeeded or was dead-lettered (Unix timestamp)
+    pub finished_at: Option<i64>,
+}
+
+/// Queue a job due at `run_at`, returning its ID.
+///
+/// If a pending or running job has the same `dedupe_key`, no job is added;
+/// the existing one is moved up to `run_at` if it was due later, and its ID
+/// is returned.
+pub async fn enqueue<'e>(
+    executor: impl PgExecutor<'e>,
+    kind: &str,
+    payload: &serde_json::Value,
+    dedupe_key: Option<&str>,
+    max_attempts: u32,
+    run_at: i64,
+) -> Result<String> {
+    let now = chrono::Utc::now().timestamp();
+    sqlx::query_scalar(
+        "INSERT INTO jobs (id, kind, payload, dedupe_key, max_attempts, run_at, created_at, updated_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+         ON CONFLICT (dedupe_key) WHERE dedupe_key IS NOT NULL AND status IN ('pending', 'running')
+         DO UPDATE SET run_at = LEAST(jobs.run_at, EXCLUDED.run_at), updated_at = EXCLUDED.updated_at
+         RETURNING id",
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(kind)
+    .bind(payload)
+    .bind(dedupe_key)
+    .bind(max_attempts.max(1) as i32)
+    .bind(run_at)
+    .bind(now)
+    .fetch_one(executor)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to enqueue job: {}", e)))
+}
+
+/// Queue a job unless one with `dedupe_key` was ever queued, returning
+/// whether it was.
+///
+/// For work that must happen once however many servers ask for it, such as
+/// a scheduled run every server notices is due.
+pub async fn enqueue_once(
+    pool: &PgPool,
+    kind: &str,
+    payload: &serde_json::Value,
+    dedupe_key: &str,
+    max_attempts: u32,
+    run_at: i64,
+) -> Result<bool> {
+    let now = chrono::Utc::now().timestamp();
+    let result = sqlx::query(
+        "INSERT INTO jobs (id, kind, payload, dedupe_key, max_attempts, run_at, created_at, updated_at)
+         SELECT $1, $2, $3, $4, $5, $6, $7, $7
+         WHERE NOT EXISTS (SELECT 1 FROM jobs WHERE dedupe_key = $4)
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(kind)
+    .bind(payload)
+    .bind(dedupe_key)
+    .bind(max_attempts.max(1) as i32)
+    .bind(run_at)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to enqueue job: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Claim up to `limit` due jobs for `worker` until `locked_until`.
+///
+/// Pending jobs due at `now` are claimed, and so are running jobs whose
+/// lease expired, because their worker stopped. Each claim starts a new
+/// attempt.
+pub async fn claim(
+    pool: &PgPool,
+    worker: &str,
+    now: i64,
+    locked_until: i64,
+    limit: i64,
+) -> Result<Vec<Job>> {
+    sqlx::query_as::<_, Job>(&format!(
+        "UPDATE jobs SET status = 'running', attempts = attempts + 1, locked_by = $1,
+             locked_until = $2, updated_at = $3
+         WHERE id IN (
+             SELECT id FROM jobs
+             WHERE (status = 'pending' AND run_at <= $3) OR (status = 'running' AND locked_until < $3)
+             ORDER BY run_at ASC
+             LIMIT $4
+             FOR UPDATE SKIP LOCKED
+         )
+         RETURNING {}",
+        COLUMNS
+    ))
+    .bind(worker)
+    .bind(locked_until)
+    .bind(now)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to claim jobs: {}", e)))
+}
+
+/// Extend `worker`'s lease on a running job.
+///
+/// Returns false if the worker lost the job, because its lease expired and
+/// another worker claimed it.
+pub async fn extend_lease(
+    pool: &PgPool,
+    id: &str,
+    worker: &str,
+    locked_until: i64,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE jobs SET locked_until = $1 WHERE id = $2 AND locked_by = $3 AND status = 'running'",
+    )
+    .bind(locked_until)
+    .bind(id)
+    .bind(worker)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to extend job lease: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Mark a job `worker` ran as succeeded.
+pub async fn complete(pool: &PgPool, id: &str, worker: &str, now: i64) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE jobs SET status = 'succeeded', locked_by = NULL, locked_until = NULL,
+             last_error = NULL, updated_at = $1, finished_at = $1
+         WHERE id = $2 AND locked_by = $3 AND status = 'running'",
+    )
+    .bind(now)
+    .bind(id)
+    .bind(worker)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to complete job: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Record a failed attempt of a job `worker` ran.
+///
+/// The job is due again at `retry_at`, or dead-lettered when `None`.
+pub async fn fail(
+    pool: &PgPool,
+    id: &str,
+    worker: &str,
+    error: &str,
+    now: i64,
+    retry_at: Option<i64>,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE jobs SET status = CASE WHEN $1::BIGINT IS NULL THEN 'dead' ELSE 'pending' END,
+             run_at = COALESCE($1, run_at), locked_by = NULL, locked_until = NULL,
+             last_error = $2, updated_at = $3,
+             finished_at = CASE WHEN $1::BIGINT IS NULL THEN $3 END
+         WHERE id = $4 AND locked_by = $5 AND status = 'running'",
+    )
+    .bind(retry_at)
+    .bind(error)
+    .bind(now)
+    .bind(id)
+    .bind(worker)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to record job failure: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get a job.
+pub async fn get_job(pool: &PgPool, id: &str) -> Result<Option<Job>> {
+    sqlx::query_as::<_, Job>(&format!("SELECT {} FROM jobs WHERE id = $1", COLUMNS))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to get job: {}", e)))
+}
+
+/// List jobs, newest first, optionally only those with a status or kind.
+pub async fn list_jobs(
+    pool: &PgPool,
+    status: Option<&str>,
+    kind: Option<&str>,
+    limit: i64,
+) -> Result<Vec<Job>> {
+    sqlx::query_as::<_, Job>(&format!(
+        "SELECT {} FROM jobs
+         WHERE ($1::TEXT IS NULL OR status = $1) AND ($2::TEXT IS NULL OR kind = $2)
+         ORDER BY created_at DESC
+         LIMIT $3",
+        COLUMNS
+    ))
+    .bind(status)
+    .bind(kind)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list jobs: {}", e)))
+}
+
+/// Queue a dead-lettered job again with a fresh set of attempts.
+///
+/// Returns false if the job is not dead, or an equal job is already
+/// pending or running.
+pub async fn retry_job(pool: &PgPool, id: &str, now: i64) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE jobs SET status = 'pending', attempts = 0, run_at = $1, updated_at = $1,
+             finished_at = NULL
+         WHERE id = $2 AND status = 'dead'
+           AND NOT EXISTS (
+               SELECT 1 FROM jobs active
+               WHERE active.dedupe_key = jobs.dedupe_key AND active.status IN ('pending', 'running')
+           )",
+    )
+    .bind(now)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to retry job: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete a job that is not running, returning whether it existed.
+pub async fn delete_job(pool: &PgPool, id: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM jobs WHERE id = $1 AND status <> 'running'")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to delete job: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Drop the pending job with a dedupe key, returning whether there was one.
+pub async fn cancel_pending(pool: &PgPool, dedupe_key: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM jobs WHERE dedupe_key = $1 AND status = 'pending'")
+        .bind(dedupe_key)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to cancel job: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete jobs that succeeded before `before`, returning how many.
+///
+/// Dead letters are kept until retried or deleted.
+pub async fn purge_succeeded(pool: &PgPool, before: i64) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM jobs WHERE status = 'succeeded' AND finished_at < $1")
+        .bind(before)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to purge jobs: {}", e)))?;
+    Ok(result.rows_affected())
+}
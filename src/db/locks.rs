@@ -0,0 +1,175 @@
+//! Coordination of servers sharing a database, through Postgres advisory
+//! locks.
+//!
+//! Several ARES servers can share one database. Work that must not run on
+//! two of them at once takes an advisory lock: [`try_lock`] for a task that
+//! is skipped or refused while another server has it, such as re-embedding
+//! a collection, and leadership for the periodic maintenance one server
+//! does for all of them, such as queueing scheduled runs, archival and
+//! purges.
+//!
+//! One server at a time is the leader. [`spawn_leader_election`] keeps
+//! trying to take the leader lock; when the leader stops, its database
+//! session ends, the lock is released and another server takes over within
+//! [`ELECTION_INTERVAL`]. A server that doesn't run the election, like one
+//! built for tests or a single-server deployment's CLI, counts as the
+//! leader.
+//!
+//! Locks are session-level: each is held on a connection of its own, taken
+//! out of the pool for as long as the lock is held.
+
+use crate::types::{AppError, Result};
+use sha2::{Digest, Sha256};
+use sqlx::pool::PoolConnection;
+use sqlx::{PgPool, Postgres};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// How often a server tries to become the leader, and the leader checks it
+/// still is
+pub const ELECTION_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Name of the lock the leader holds
+const LEADER_LOCK: &str = "leader";
+
+/// Whether this server is the leader
+static LEADER: AtomicBool = AtomicBool::new(true);
+
+/// Whether this server does the maintenance one server does for all.
+pub fn is_leader() -> bool {
+    LEADER.load(Ordering::SeqCst)
+}
+
+/// The advisory lock key of a lock name, the same on every server.
+pub fn lock_key(name: &str) -> i64 {
+    let digest = Sha256::digest(format!("ares:{}", name).as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    i64::from_be_bytes(bytes)
+}
+
+/// A held advisory lock.
+///
+/// Released by [`AdvisoryLock::release`], or when dropped, by closing its
+/// connection.
+pub struct AdvisoryLock {
+    name: String,
+    key: i64,
+    conn: Option<PoolConnection<Postgres>>,
+}
+
+impl std::fmt::Debug for AdvisoryLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdvisoryLock")
+            .field("name", &self.name)
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AdvisoryLock {
+    /// Name the lock was taken under
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the lock is still held, i.e. its connection is alive.
+    pub async fn is_held(&mut self) -> bool {
+        let Some(conn) = self.conn.as_mut() else {
+            return false;
+        };
+        sqlx::query("SELECT 1").execute(&mut **conn).await.is_ok()
+    }
+
+    /// Release the lock, returning its connection to the pool.
+    pub async fn release(mut self) -> Result<()> {
+        let Some(mut conn) = self.conn.take() else {
+            return Ok(());
+        };
+        let released = sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(self.key)
+            .execute(&mut *conn)
+            .await;
+        if let Err(e) = released {
+            // Closing the session releases the lock too
+            drop(conn.detach());
+            return Err(AppError::Database(format!(
+                "Failed to release lock '{}': {}",
+                self.name, e
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for AdvisoryLock {
+    fn drop(&mut self) {
+        // A connection returned to the pool would keep the lock
+        if let Some(conn) = self.conn.take() {
+            drop(conn.detach());
+        }
+    }
+}
+
+/// Take the lock `name` unless another server or task holds it.
+pub async fn try_lock(pool: &PgPool, name: &str) -> Result<Option<AdvisoryLock>> {
+    let key = lock_key(name);
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to take lock '{}': {}", name, e)))?;
+    let (locked,): (bool,) = sqlx::query_as("SELECT pg_try_advisory_lock($1)")
+        .bind(key)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to take lock '{}': {}", name, e)))?;
+    Ok(locked.then(|| AdvisoryLock {
+        name: name.to_string(),
+        key,
+        conn: Some(conn),
+    }))
+}
+
+/// Run for leadership among the servers sharing `pool`'s database.
+///
+/// This server stops counting as the leader until it takes the leader lock,
+/// and again if it loses its connection while holding it.
+pub fn spawn_leader_election(pool: PgPool) {
+    LEADER.store(false, Ordering::SeqCst);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ELECTION_INTERVAL);
+        let mut held: Option<AdvisoryLock> = None;
+        loop {
+            interval.tick().await;
+            match held.as_mut() {
+                Some(lock) => {
+                    if !lock.is_held().await {
+                        held = None;
+                        LEADER.store(false, Ordering::SeqCst);
+                        tracing::warn!("Lost the database connection holding leadership");
+                    }
+                }
+                None => match try_lock(&pool, LEADER_LOCK).await {
+                    Ok(Some(lock)) => {
+                        held = Some(lock);
+                        LEADER.store(true, Ordering::SeqCst);
+                        tracing::info!("This server is now the leader");
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!("Leader election failed: {}", e),
+                },
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_keys_are_stable_and_distinct() {
+        assert_eq!(lock_key("leader"), lock_key("leader"));
+        assert_ne!(lock_key("leader"), lock_key("reembed:docs"));
+    }
+}
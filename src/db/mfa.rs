@@ -0,0 +1,260 @@
+//! Storage for multi-factor authentication.
+//!
+//! Holds users' TOTP secrets (see [`crate::auth::totp`]), their recovery
+//! codes and the logins waiting for a second factor. Recovery codes and
+//! challenge tokens are stored as SHA-256 hashes only. Secrets are sealed
+//! at rest when `[encryption]` is configured.
+
+use crate::auth::totp;
+use crate::db::encryption;
+use crate::types::{AppError, Result};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+/// Failed codes a login challenge takes before it's dropped
+pub const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+
+/// A user's MFA settings.
+#[derive(Debug, Clone, Default, sqlx::FromRow)]
+pub struct UserMfa {
+    /// TOTP secret (base32); pending until `enabled`
+    pub secret: Option<String>,
+    /// Whether sign-ins need a code
+    pub enabled: bool,
+    /// Whether an admin requires MFA of the user
+    pub required: bool,
+    /// Last TOTP step used, so codes can't be replayed
+    pub last_step: i64,
+}
+
+fn hash(value: &str) -> String {
+    hex::encode(Sha256::digest(value.as_bytes()))
+}
+
+/// A user's MFA settings; defaults for users who never set them.
+pub async fn get(pool: &PgPool, user_id: &str) -> Result<UserMfa> {
+    let mfa = sqlx::query_as::<_, UserMfa>(
+        "SELECT secret, enabled, required, last_step FROM user_mfa WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get MFA settings: {}", e)))?;
+    let mut mfa = mfa.unwrap_or_default();
+    if let Some(secret) = &mfa.secret {
+        mfa.secret = Some(encryption::open(encryption::MFA_SECRET, secret)?);
+    }
+    Ok(mfa)
+}
+
+/// Store a new secret awaiting confirmation, replacing a pending one.
+pub async fn set_pending_secret(
+    pool: &PgPool,
+    user_id: &str,
+    secret: &str,
+    now: i64,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO user_mfa (user_id, secret, updated_at) VALUES ($1, $2, $3)
+         ON CONFLICT (user_id) DO UPDATE SET secret = $2, enabled = FALSE, last_step = 0,
+         updated_at = $3",
+    )
+    .bind(user_id)
+    .bind(encryption::seal(encryption::MFA_SECRET, secret)?)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to store MFA secret: {}", e)))?;
+    Ok(())
+}
+
+/// Turn MFA on with the pending secret, confirmed by a code of `step`, and
+/// replace the recovery codes.
+pub async fn enable(
+    pool: &PgPool,
+    user_id: &str,
+    step: i64,
+    recovery_codes: &[String],
+    now: i64,
+) -> Result<()> {
+    let db_err = |e: sqlx::Error| AppError::Database(format!("Failed to enable MFA: {}", e));
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    sqlx::query(
+        "UPDATE user_mfa SET enabled = TRUE, last_step = $2, updated_at = $3 WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .bind(step)
+    .bind(now)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_err)?;
+    insert_recovery_codes(&mut tx, user_id, recovery_codes)
+        .await
+        .map_err(db_err)?;
+    tx.commit().await.map_err(db_err)
+}
+
+/// Turn MFA off, dropping the secret and recovery codes. An admin's
+/// requirement stays.
+pub async fn disable(pool: &PgPool, user_id: &str, now: i64) -> Result<()> {
+    let db_err = |e: sqlx::Error| AppError::Database(format!("Failed to disable MFA: {}", e));
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    sqlx::query(
+        "UPDATE user_mfa SET secret = NULL, enabled = FALSE, last_step = 0, updated_at = $2
+         WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .bind(now)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_err)?;
+    sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    tx.commit().await.map_err(db_err)
+}
+
+/// Require MFA of a user, or stop requiring it.
+pub async fn set_required(pool: &PgPool, user_id: &str, required: bool, now: i64) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO user_mfa (user_id, required, updated_at) VALUES ($1, $2, $3)
+         ON CONFLICT (user_id) DO UPDATE SET required = $2, updated_at = $3",
+    )
+    .bind(user_id)
+    .bind(required)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to set MFA requirement: {}", e)))?;
+    Ok(())
+}
+
+/// Record the use of a code of `step`; false if a code of that step or a
+/// later one was used already.
+pub async fn use_step(pool: &PgPool, user_id: &str, step: i64) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE user_mfa SET last_step = $2 WHERE user_id = $1 AND enabled AND last_step < $2",
+    )
+    .bind(user_id)
+    .bind(step)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to record MFA code: {}", e)))?;
+    Ok(result.rows_affected() == 1)
+}
+
+async fn insert_recovery_codes(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: &str,
+    codes: &[String],
+) -> std::result::Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+    for code in codes {
+        sqlx::query(
+            "INSERT INTO mfa_recovery_codes (user_id, code_hash) VALUES ($1, $2)
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(user_id)
+        .bind(hash(&totp::normalize_recovery_code(code)))
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Replace a user's recovery codes.
+pub async fn replace_recovery_codes(pool: &PgPool, user_id: &str, codes: &[String]) -> Result<()> {
+    let db_err =
+        |e: sqlx::Error| AppError::Database(format!("Failed to store recovery codes: {}", e));
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    insert_recovery_codes(&mut tx, user_id, codes)
+        .await
+        .map_err(db_err)?;
+    tx.commit().await.map_err(db_err)
+}
+
+/// Use up a recovery code; false unless it's one of the user's unused ones.
+pub async fn use_recovery_code(pool: &PgPool, user_id: &str, code: &str, now: i64) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE mfa_recovery_codes SET used_at = $3
+         WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .bind(hash(&totp::normalize_recovery_code(code)))
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to use recovery code: {}", e)))?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Number of a user's unused recovery codes.
+pub async fn count_recovery_codes(pool: &PgPool, user_id: &str) -> Result<i64> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM mfa_recovery_codes WHERE user_id = $1 AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to count recovery codes: {}", e)))
+}
+
+/// Start a login challenge for a user, returning its token. Expired
+/// challenges are purged on the way.
+pub async fn create_challenge(pool: &PgPool, user_id: &str, now: i64, ttl: i64) -> Result<String> {
+    let db_err =
+        |e: sqlx::Error| AppError::Database(format!("Failed to create MFA challenge: {}", e));
+    sqlx::query("DELETE FROM mfa_challenges WHERE expires_at <= $1")
+        .bind(now)
+        .execute(pool)
+        .await
+        .map_err(db_err)?;
+
+    let bytes: Vec<u8> = (0..32).map(|_| rand::random::<u8>()).collect();
+    let token = hex::encode(bytes);
+    sqlx::query("INSERT INTO mfa_challenges (token_hash, user_id, expires_at) VALUES ($1, $2, $3)")
+        .bind(hash(&token))
+        .bind(user_id)
+        .bind(now + ttl)
+        .execute(pool)
+        .await
+        .map_err(db_err)?;
+    Ok(token)
+}
+
+/// The user of a live challenge, counting an attempt at it when `attempt`
+/// is set. Challenges out of attempts are gone.
+pub async fn challenge_user(
+    pool: &PgPool,
+    token: &str,
+    now: i64,
+    attempt: bool,
+) -> Result<Option<String>> {
+    sqlx::query_scalar(
+        "UPDATE mfa_challenges SET attempts = attempts + CASE WHEN $3 THEN 1 ELSE 0 END
+         WHERE token_hash = $1 AND expires_at > $2 AND attempts < $4
+         RETURNING user_id",
+    )
+    .bind(hash(token))
+    .bind(now)
+    .bind(attempt)
+    .bind(MAX_CHALLENGE_ATTEMPTS)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to check MFA challenge: {}", e)))
+}
+
+/// End a challenge once its login is complete.
+pub async fn delete_challenge(pool: &PgPool, token: &str) -> Result<()> {
+    sqlx::query("DELETE FROM mfa_challenges WHERE token_hash = $1")
+        .bind(hash(token))
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to delete MFA challenge: {}", e)))?;
+    Ok(())
+}
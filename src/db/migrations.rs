@@ -0,0 +1,209 @@
+//! Versioned schema migrations.
+//!
+//! The SQL files of `migrations/` are embedded in the binary and applied in
+//! order of their numeric prefix, each in its own transaction. Applied
+//! versions are recorded, with a checksum of their SQL, in the
+//! `_sqlx_migrations` table, so each runs once per database and an edited
+//! migration is caught instead of silently diverging.
+//!
+//! The server applies pending migrations at startup. `ares-server db status`
+//! lists them and `ares-server db migrate` applies them ahead of an upgrade.
+//! New schema changes go in a new file with the next number; applied files
+//! must not be edited.
+
+use crate::types::{AppError, Result};
+use sqlx::migrate::Migrator;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+/// The migrations of `migrations/`
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// State of a migration in a database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationState {
+    /// Not applied yet
+    Pending,
+    /// Applied as shipped
+    Applied {
+        /// When it was applied (Unix timestamp)
+        installed_on: i64,
+    },
+    /// Applied, but its SQL has changed since
+    Modified {
+        /// When it was applied (Unix timestamp)
+        installed_on: i64,
+    },
+    /// Applied but failed part way
+    Failed,
+}
+
+/// A migration and its state in a database.
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    /// Version (the file's numeric prefix)
+    pub version: i64,
+    /// Description (the rest of the file name)
+    pub description: String,
+    /// State in the database
+    pub state: MigrationState,
+}
+
+/// A migration recorded in `_sqlx_migrations`
+#[derive(sqlx::FromRow)]
+struct AppliedRow {
+    version: i64,
+    installed_on: chrono::DateTime<chrono::Utc>,
+    success: bool,
+    checksum: Vec<u8>,
+}
+
+/// State of each migration in the database, in order.
+///
+/// Reads the database without changing it, even when no migration was
+/// applied yet.
+pub async fn status(pool: &PgPool) -> Result<Vec<MigrationStatus>> {
+    let db_err = |e: sqlx::Error| AppError::Database(format!("Failed to read migrations: {}", e));
+    let tracked: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(pool)
+        .await
+        .map_err(db_err)?;
+    let applied: HashMap<i64, AppliedRow> = if tracked {
+        sqlx::query_as::<_, AppliedRow>(
+            "SELECT version, installed_on, success, checksum FROM _sqlx_migrations",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(db_err)?
+        .into_iter()
+        .map(|row| (row.version, row))
+        .collect()
+    } else {
+        HashMap::new()
+    };
+
+    Ok(states(&applied))
+}
+
+/// State of each migration given the ones recorded as applied, in order
+fn states(applied: &HashMap<i64, AppliedRow>) -> Vec<MigrationStatus> {
+    MIGRATOR
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| {
+            let state = match applied.get(&migration.version) {
+                None => MigrationState::Pending,
+                Some(row) if !row.success => MigrationState::Failed,
+                Some(row) if row.checksum != migration.checksum.as_ref() => {
+                    MigrationState::Modified {
+                        installed_on: row.installed_on.timestamp(),
+                    }
+                }
+                Some(row) => MigrationState::Applied {
+                    installed_on: row.installed_on.timestamp(),
+                },
+            };
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                state,
+            }
+        })
+        .collect()
+}
+
+/// Apply the pending migrations, returning the versions applied.
+///
+/// Fails on reaching an applied migration that was modified, and before
+/// applying anything when one failed part way.
+pub async fn migrate(pool: &PgPool) -> Result<Vec<i64>> {
+    let pending: Vec<i64> = status(pool)
+        .await?
+        .into_iter()
+        .filter(|migration| migration.state == MigrationState::Pending)
+        .map(|migration| migration.version)
+        .collect();
+    MIGRATOR
+        .run(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to run migrations: {}", e)))?;
+    Ok(pending)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn applied_row(version: i64, checksum: &[u8], success: bool) -> AppliedRow {
+        AppliedRow {
+            version,
+            installed_on: Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+            success,
+            checksum: checksum.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_states_against_embedded_migrations() {
+        let migrations: Vec<_> = MIGRATOR.iter().collect();
+        assert!(migrations.len() >= 4);
+
+        // A new database has every migration pending, in order
+        let fresh = states(&HashMap::new());
+        assert_eq!(fresh.len(), migrations.len());
+        assert!(fresh.windows(2).all(|w| w[0].version < w[1].version));
+        assert!(fresh.iter().all(|m| m.state == MigrationState::Pending));
+        assert_eq!(fresh[0].version, 0);
+        assert_eq!(fresh[0].description, "tenants");
+
+        let applied: HashMap<i64, AppliedRow> = [
+            applied_row(migrations[0].version, &migrations[0].checksum, true),
+            applied_row(migrations[1].version, b"edited", true),
+            applied_row(migrations[2].version, &migrations[2].checksum, false),
+        ]
+        .into_iter()
+        .map(|row| (row.version, row))
+        .collect();
+        let states = states(&applied);
+        assert_eq!(
+            states[0].state,
+            MigrationState::Applied {
+                installed_on: 1_700_000_000
+            }
+        );
+        assert_eq!(
+            states[1].state,
+            MigrationState::Modified {
+                installed_on: 1_700_000_000
+            }
+        );
+        assert_eq!(states[2].state, MigrationState::Failed);
+        assert!(states[3..]
+            .iter()
+            .all(|m| m.state == MigrationState::Pending));
+    }
+
+    // Needs PostgreSQL with the ARES schema at `DATABASE_URL`; run with
+    // `cargo test -- --ignored`.
+    #[tokio::test]
+    #[ignore]
+    async fn test_migrate_applies_pending_migrations_once() {
+        let db = crate::db::PostgresClient::new_memory().await.unwrap();
+        let pending: Vec<i64> = status(&db.pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|m| m.state == MigrationState::Pending)
+            .map(|m| m.version)
+            .collect();
+
+        assert_eq!(migrate(&db.pool).await.unwrap(), pending);
+        let migrated = status(&db.pool).await.unwrap();
+        assert_eq!(migrated.len(), MIGRATOR.iter().count());
+        assert!(migrated
+            .iter()
+            .all(|m| matches!(m.state, MigrationState::Applied { .. })));
+        assert!(migrate(&db.pool).await.unwrap().is_empty());
+    }
+}
@@ -74,6 +74,26 @@ pub mod agent_runs;
 pub mod alerts;
 /// Admin audit log (mutation tracking).
 pub mod audit_log;
+/// Estimated spend tracking and budget enforcement.
+pub mod spend;
+/// Conversation archival to cold storage.
+pub mod archive;
+/// Agent schedules created through the API.
+pub mod schedules;
+/// Agent runs paused for approval of their tool calls.
+pub mod approvals;
+/// Files attached to conversations.
+pub mod attachments;
+/// Feedback on assistant messages, and what generated them.
+pub mod feedback;
+/// Uploaded files and their storage.
+pub mod files;
+/// Checkpoints of agent runs in progress.
+pub mod checkpoints;
+/// RAG collections conversations are pinned to.
+pub mod rag_scopes;
+/// Workspaces and their members.
+pub mod workspaces;
 
 // Re-exports
 pub use vectorstore::{CollectionInfo, CollectionStats, VectorStore, VectorStoreProvider};
@@ -82,7 +102,45 @@ pub use vectorstore::{CollectionInfo, CollectionStats, VectorStore, VectorStoreP
 pub use ares_vector::AresVectorStore;
 #[cfg(feature = "lancedb")]
 pub use lancedb::LanceDBStore;
+#[cfg(feature = "pgvector")]
+pub use pgvector::PgVectorStore;
 #[cfg(feature = "qdrant")]
 pub use qdrant::QdrantVectorStore;
 pub use postgres::PostgresClient;
 pub use tenants::{TenantDb, UsageSummary};
+/// Projects grouping conversations, files and collections.
+pub mod projects;
+/// Assistants-style thread messages and runs.
+pub mod threads;
+/// Users' roles.
+pub mod roles;
+/// API keys users issue for machine clients.
+pub mod user_api_keys;
+/// Keys of encrypted conversations.
+pub mod conversation_keys;
+/// Idempotency keys and the responses kept under them.
+pub mod idempotency;
+/// Batches of chat messages and their outcomes.
+pub mod batches;
+/// Read-only share links to conversations.
+pub mod shares;
+/// Versioned schema migrations embedded from `migrations/`.
+pub mod migrations;
+/// Online backups of the database and vector store, and their restore.
+pub mod backup;
+/// Multi-factor authentication secrets, recovery codes and login challenges.
+pub mod mfa;
+/// Signed-in devices and their refresh-token sessions.
+pub mod sessions;
+/// Durable background jobs, their retries and dead letters.
+pub mod jobs;
+/// Per-request analytics and their reports.
+pub mod analytics;
+/// Encryption at rest of sensitive columns and uploaded files.
+pub mod encryption;
+/// Advisory locks and leader election among servers sharing the database.
+pub mod locks;
+
+/// Redis shared by the instances of a deployment
+#[cfg(feature = "redis")]
+pub mod redis;
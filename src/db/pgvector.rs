@@ -1,54 +1,524 @@
 //! PostgreSQL pgvector integration.
 //!
-//! This module provides vector similarity search using PostgreSQL with the pgvector extension.
+//! Stores collections in PostgreSQL with the
+//! [pgvector](https://github.com/pgvector/pgvector) extension, either in the
+//! server's own database or a separate one.
 //!
-//! # Status
-//!
-//! **Not yet implemented.** This is a placeholder for future development.
+//! Each collection is a table of documents with a `vector(n)` column and an
+//! HNSW index over cosine distance. The `ares_vector_collections` table maps
+//! collection names to their tables and dimensions, and
+//! `ares_vector_settings` holds the RAG settings of each collection. These
+//! tables, and the extension, are created on connect; the database user
+//! needs the privilege to create the extension unless it is installed.
 //!
 //! # Feature Flag
 //!
 //! Enable with `--features pgvector`
 //!
-//! # Future Implementation
-//!
-//! When implemented, this will support:
-//! - Creating vector collections as PostgreSQL tables
-//! - Storing embeddings with metadata
-//! - Similarity search using pgvector's IVFFlat and HNSW indexes
-//! - Filtering by metadata
-//!
-//! # Example (future API)
+//! # Example
 //!
 //! ```rust,ignore
 //! use ares::db::PgVectorStore;
 //!
 //! let store = PgVectorStore::new("postgres://localhost/vectors").await?;
 //! store.create_collection("documents", 384).await?;
-//! store.upsert("documents", &embedding, &metadata).await?;
-//! let results = store.search("documents", &query_embedding, 10).await?;
+//! store.upsert("documents", &documents).await?;
+//! let results = store.search("documents", &query_embedding, 10, 0.5).await?;
 //! ```
 
-use crate::types::{AppError, Result};
+use super::postgres::PostgresClient;
+use super::vectorstore::{CollectionInfo, CollectionStats, VectorStore};
+use crate::types::{
+    AppError, CollectionSettings, Document, DocumentMetadata, Result, SearchResult,
+};
+use crate::utils::toml_config::DatabaseConfig;
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
 
-/// PostgreSQL pgvector store (not yet implemented).
-///
-/// This struct will provide vector similarity search using PostgreSQL
-/// with the pgvector extension.
+/// Largest dimensions pgvector indexes with HNSW; larger collections are
+/// searched exactly
+const MAX_INDEXED_DIMENSIONS: usize = 2000;
+
+/// Vector store over PostgreSQL with the pgvector extension.
 pub struct PgVectorStore {
-    _private: (),
+    pool: PgPool,
 }
 
 impl PgVectorStore {
-    /// Create a new PgVectorStore.
+    /// Connect to a PostgreSQL database and prepare it for vector storage.
     ///
     /// # Errors
     ///
-    /// Currently always returns an error as this feature is not yet implemented.
-    pub async fn new(_connection_string: &str) -> Result<Self> {
-        Err(AppError::Configuration(
-            "PgVectorStore is not yet implemented. Use 'ares-vector' (default) or 'qdrant' instead. \
-             See https://github.com/dirmacs/ares for implementation status.".to_string()
+    /// Returns an error if the connection fails or the pgvector extension
+    /// cannot be enabled.
+    pub async fn new(connection_string: &str) -> Result<Self> {
+        let client = PostgresClient::connect(connection_string, &DatabaseConfig::default()).await?;
+        Self::from_pool(client.pool).await
+    }
+
+    /// Use an existing connection pool, preparing its database for vector
+    /// storage.
+    pub async fn from_pool(pool: PgPool) -> Result<Self> {
+        for statement in [
+            "CREATE EXTENSION IF NOT EXISTS vector",
+            "CREATE TABLE IF NOT EXISTS ares_vector_collections (
+                name TEXT PRIMARY KEY,
+                table_name TEXT NOT NULL UNIQUE,
+                dimensions INTEGER NOT NULL,
+                created_at BIGINT NOT NULL
+            )",
+            "CREATE TABLE IF NOT EXISTS ares_vector_settings (
+                name TEXT PRIMARY KEY,
+                settings JSONB NOT NULL
+            )",
+        ] {
+            sqlx::query(statement).execute(&pool).await.map_err(|e| {
+                AppError::Configuration(format!("Failed to initialize pgvector: {}", e))
+            })?;
+        }
+        Ok(Self { pool })
+    }
+
+    /// Table and dimensions of a collection
+    async fn collection(&self, name: &str) -> Result<(String, usize)> {
+        let row: Option<(String, i32)> = sqlx::query_as(
+            "SELECT table_name, dimensions FROM ares_vector_collections WHERE name = $1",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to get collection: {}", e)))?;
+        row.map(|(table, dimensions)| (table, dimensions as usize))
+            .ok_or_else(|| AppError::NotFound(format!("Collection '{}' not found", name)))
+    }
+
+    /// Number of documents in a collection table
+    async fn row_count(&self, table: &str) -> Result<usize> {
+        let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to count documents: {}", e)))?;
+        Ok(count as usize)
+    }
+
+    /// Check that a vector has the dimensions of its collection.
+    fn check_dimensions(
+        collection: &str,
+        expected: usize,
+        id: &str,
+        embedding: &[f32],
+    ) -> Result<()> {
+        if embedding.len() != expected {
+            return Err(AppError::InvalidInput(format!(
+                "{} has {} dimensions but collection '{}' stores {}-dimensional vectors; \
+                 the embedding model has changed, re-embed the collection to use it",
+                id,
+                embedding.len(),
+                collection,
+                expected
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Name of the table storing a collection.
+///
+/// Derived from a hash of the collection name, which may hold characters
+/// that are not valid in identifiers.
+fn table_name(collection: &str) -> String {
+    let digest = hex::encode(Sha256::digest(collection.as_bytes()));
+    format!("ares_vectors_{}", &digest[..16])
+}
+
+/// pgvector text representation of a vector
+fn vector_literal(embedding: &[f32]) -> String {
+    let values: Vec<String> = embedding.iter().map(|value| value.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
+/// Parse the pgvector text representation of a vector
+fn parse_vector(text: &str) -> Result<Vec<f32>> {
+    let inner = text.trim().trim_start_matches('[').trim_end_matches(']');
+    if inner.is_empty() {
+        return Ok(Vec::new());
+    }
+    inner
+        .split(',')
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|e| AppError::Database(format!("Invalid vector '{}': {}", text, e)))
+        })
+        .collect()
+}
+
+/// SQL condition for a metadata filter, matching
+/// [`matches_filters`](super::vectorstore::matches_filters).
+fn filter_condition(field: &str, param: usize) -> String {
+    match field {
+        "title" | "source" => format!("metadata->>'{}' = ${}", field, param),
+        "tags" => format!("metadata->'tags' ? ${}", param),
+        _ => "FALSE".to_string(),
+    }
+}
+
+/// Build a document from a row of `id, content, metadata` and an optional
+/// `embedding` text column.
+fn row_document(row: &PgRow, with_embedding: bool) -> Result<Document> {
+    let db_err = |e: sqlx::Error| AppError::Database(format!("Failed to read document: {}", e));
+    let metadata: serde_json::Value = row.try_get("metadata").map_err(db_err)?;
+    let embedding = if with_embedding {
+        let text: String = row.try_get("embedding").map_err(db_err)?;
+        Some(parse_vector(&text)?)
+    } else {
+        None
+    };
+    Ok(Document {
+        id: row.try_get("id").map_err(db_err)?,
+        content: row.try_get("content").map_err(db_err)?,
+        metadata: serde_json::from_value::<DocumentMetadata>(metadata)
+            .map_err(|e| AppError::Database(format!("Invalid document metadata: {}", e)))?,
+        embedding,
+    })
+}
+
+#[async_trait]
+impl VectorStore for PgVectorStore {
+    fn provider_name(&self) -> &'static str {
+        "pgvector"
+    }
+
+    async fn create_collection(&self, name: &str, dimensions: usize) -> Result<()> {
+        if self.collection_exists(name).await? {
+            return Err(AppError::InvalidInput(format!(
+                "Collection '{}' already exists",
+                name
+            )));
+        }
+
+        let db_err =
+            |e: sqlx::Error| AppError::Database(format!("Failed to create collection: {}", e));
+        let table = table_name(name);
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        sqlx::query(&format!(
+            "CREATE TABLE {} (
+                id TEXT PRIMARY KEY,
+                content TEXT NOT NULL,
+                metadata JSONB NOT NULL,
+                embedding vector({}) NOT NULL
+            )",
+            table, dimensions
         ))
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+        if dimensions <= MAX_INDEXED_DIMENSIONS {
+            sqlx::query(&format!(
+                "CREATE INDEX {}_embedding ON {} USING hnsw (embedding vector_cosine_ops)",
+                table, table
+            ))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        }
+        sqlx::query(
+            "INSERT INTO ares_vector_collections (name, table_name, dimensions, created_at)
+             VALUES ($1, $2, $3, $4)",
+        )
+        .bind(name)
+        .bind(&table)
+        .bind(dimensions as i32)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+        tx.commit().await.map_err(db_err)
+    }
+
+    async fn delete_collection(&self, name: &str) -> Result<()> {
+        let (table, _) = self.collection(name).await?;
+        let db_err =
+            |e: sqlx::Error| AppError::Database(format!("Failed to delete collection: {}", e));
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        sqlx::query(&format!("DROP TABLE IF EXISTS {}", table))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        sqlx::query("DELETE FROM ares_vector_collections WHERE name = $1")
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        sqlx::query("DELETE FROM ares_vector_settings WHERE name = $1")
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        tx.commit().await.map_err(db_err)
+    }
+
+    async fn list_collections(&self) -> Result<Vec<CollectionInfo>> {
+        let rows: Vec<(String, String, i32)> = sqlx::query_as(
+            "SELECT name, table_name, dimensions FROM ares_vector_collections ORDER BY name",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to list collections: {}", e)))?;
+
+        let mut infos = Vec::with_capacity(rows.len());
+        for (name, table, dimensions) in rows {
+            infos.push(CollectionInfo {
+                name,
+                document_count: self.row_count(&table).await?,
+                dimensions: dimensions as usize,
+            });
+        }
+        Ok(infos)
+    }
+
+    async fn collection_exists(&self, name: &str) -> Result<bool> {
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM ares_vector_collections WHERE name = $1)")
+            .bind(name)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to check collection: {}", e)))
+    }
+
+    async fn collection_stats(&self, name: &str) -> Result<CollectionStats> {
+        let (table, dimensions) = self.collection(name).await?;
+        let size: i64 = sqlx::query_scalar("SELECT pg_total_relation_size($1::regclass)")
+            .bind(&table)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to get collection size: {}", e)))?;
+
+        Ok(CollectionStats {
+            name: name.to_string(),
+            document_count: self.row_count(&table).await?,
+            dimensions,
+            index_size_bytes: Some(size as u64),
+            distance_metric: "cosine".to_string(),
+        })
+    }
+
+    async fn upsert(&self, collection: &str, documents: &[Document]) -> Result<usize> {
+        if documents.is_empty() {
+            return Ok(0);
+        }
+        let (table, dimensions) = self.collection(collection).await?;
+
+        // Reject the whole batch before storing any of it
+        for doc in documents {
+            let embedding = doc.embedding.as_ref().ok_or_else(|| {
+                AppError::InvalidInput(format!("Document '{}' is missing embedding", doc.id))
+            })?;
+            Self::check_dimensions(
+                collection,
+                dimensions,
+                &format!("Document '{}'", doc.id),
+                embedding,
+            )?;
+        }
+
+        let db_err = |e: sqlx::Error| AppError::Database(format!("Failed to upsert: {}", e));
+        let statement = format!(
+            "INSERT INTO {} (id, content, metadata, embedding) VALUES ($1, $2, $3, $4::vector)
+             ON CONFLICT (id) DO UPDATE SET
+                content = EXCLUDED.content,
+                metadata = EXCLUDED.metadata,
+                embedding = EXCLUDED.embedding",
+            table
+        );
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        for doc in documents {
+            let metadata = serde_json::to_value(&doc.metadata)
+                .map_err(|e| AppError::Internal(format!("Failed to encode metadata: {}", e)))?;
+            sqlx::query(&statement)
+                .bind(&doc.id)
+                .bind(&doc.content)
+                .bind(metadata)
+                .bind(vector_literal(doc.embedding.as_deref().unwrap_or_default()))
+                .execute(&mut *tx)
+                .await
+                .map_err(db_err)?;
+        }
+        tx.commit().await.map_err(db_err)?;
+        Ok(documents.len())
+    }
+
+    async fn search(
+        &self,
+        collection: &str,
+        embedding: &[f32],
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<SearchResult>> {
+        self.search_with_filters(collection, embedding, limit, threshold, &[])
+            .await
+    }
+
+    async fn search_with_filters(
+        &self,
+        collection: &str,
+        embedding: &[f32],
+        limit: usize,
+        threshold: f32,
+        filters: &[(String, String)],
+    ) -> Result<Vec<SearchResult>> {
+        let (table, dimensions) = self.collection(collection).await?;
+        Self::check_dimensions(collection, dimensions, "The query embedding", embedding)?;
+
+        let conditions: Vec<String> = filters
+            .iter()
+            .enumerate()
+            .map(|(i, (field, _))| filter_condition(field, i + 3))
+            .collect();
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let statement = format!(
+            "SELECT id, content, metadata, (1 - (embedding <=> $1::vector))::real AS score
+             FROM {} {} ORDER BY embedding <=> $1::vector LIMIT $2",
+            table, where_clause
+        );
+
+        let mut query = sqlx::query(&statement)
+            .bind(vector_literal(embedding))
+            .bind(limit as i64);
+        for (_, value) in filters {
+            query = query.bind(value);
+        }
+        let rows = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to search: {}", e)))?;
+
+        let mut results = Vec::with_capacity(rows.len());
+        for row in rows {
+            let score: f32 = row
+                .try_get("score")
+                .map_err(|e| AppError::Database(format!("Failed to read score: {}", e)))?;
+            if score < threshold {
+                break;
+            }
+            results.push(SearchResult {
+                document: row_document(&row, false)?,
+                score,
+            });
+        }
+        Ok(results)
+    }
+
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<usize> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let (table, _) = self.collection(collection).await?;
+        let result = sqlx::query(&format!("DELETE FROM {} WHERE id = ANY($1)", table))
+            .bind(ids)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to delete documents: {}", e)))?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    async fn get(&self, collection: &str, id: &str) -> Result<Option<Document>> {
+        let (table, _) = self.collection(collection).await?;
+        let row = sqlx::query(&format!(
+            "SELECT id, content, metadata, embedding::text AS embedding FROM {} WHERE id = $1",
+            table
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to get document: {}", e)))?;
+        row.map(|row| row_document(&row, true)).transpose()
+    }
+
+    async fn documents(&self, collection: &str) -> Result<Vec<Document>> {
+        let (table, _) = self.collection(collection).await?;
+        let rows = sqlx::query(&format!(
+            "SELECT id, content, metadata, embedding::text AS embedding FROM {}",
+            table
+        ))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to list documents: {}", e)))?;
+        rows.iter().map(|row| row_document(row, true)).collect()
+    }
+
+    async fn collection_settings(&self, name: &str) -> Result<Option<CollectionSettings>> {
+        let settings: Option<serde_json::Value> =
+            sqlx::query_scalar("SELECT settings FROM ares_vector_settings WHERE name = $1")
+                .bind(name)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| AppError::Database(format!("Failed to get settings: {}", e)))?;
+        settings
+            .map(|settings| {
+                serde_json::from_value(settings).map_err(|e| {
+                    AppError::Database(format!("Invalid settings for '{}': {}", name, e))
+                })
+            })
+            .transpose()
+    }
+
+    async fn set_collection_settings(
+        &self,
+        name: &str,
+        settings: &CollectionSettings,
+    ) -> Result<()> {
+        let settings = serde_json::to_value(settings)
+            .map_err(|e| AppError::Internal(format!("Failed to encode settings: {}", e)))?;
+        sqlx::query(
+            "INSERT INTO ares_vector_settings (name, settings) VALUES ($1, $2)
+             ON CONFLICT (name) DO UPDATE SET settings = EXCLUDED.settings",
+        )
+        .bind(name)
+        .bind(settings)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to save settings: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_literal_round_trip() {
+        let embedding = vec![0.5, -1.0, 0.125];
+        let literal = vector_literal(&embedding);
+        assert_eq!(literal, "[0.5,-1,0.125]");
+        assert_eq!(parse_vector(&literal).unwrap(), embedding);
+        assert!(parse_vector("[]").unwrap().is_empty());
+        assert!(parse_vector("[1,x]").is_err());
+    }
+
+    #[test]
+    fn test_table_name_is_a_safe_identifier() {
+        let name = table_name("user:42/Q&A \"docs\"");
+        assert!(name.starts_with("ares_vectors_"));
+        assert!(name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'));
+        assert_eq!(name, table_name("user:42/Q&A \"docs\""));
+        assert_ne!(name, table_name("docs"));
+    }
+
+    #[test]
+    fn test_filter_condition() {
+        assert_eq!(filter_condition("source", 3), "metadata->>'source' = $3");
+        assert_eq!(filter_condition("tags", 4), "metadata->'tags' ? $4");
+        // Unknown fields never match, as in the other stores
+        assert_eq!(filter_condition("title'; DROP TABLE x; --", 3), "FALSE");
     }
 }
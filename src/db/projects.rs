@@ -0,0 +1,280 @@
+//! Storage for projects.
+//!
+//! A project groups conversations, uploaded files and RAG collections, and
+//! holds default agent settings for its conversations. Projects are owned
+//! by a user, or by a workspace's owner key (see
+//! [`crate::api::workspace`]); conversations and files name the project
+//! they belong to in their `project_id` column.
+
+use crate::types::{AppError, ConversationOverrides, Result};
+use serde::Serialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+const COLUMNS: &str = "id, owner_id, name, description, agent, model, temperature, persona, collections, created_by, created_at, updated_at";
+
+/// A project.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Project {
+    /// Project ID
+    pub id: String,
+    /// Owner: a user, or a workspace's owner key
+    #[serde(skip)]
+    pub owner_id: String,
+    /// Display name
+    pub name: String,
+    /// What the project is about
+    pub description: Option<String>,
+    /// Agent, model, temperature and persona for the project's
+    /// conversations, where a conversation doesn't pin its own
+    pub defaults: ConversationOverrides,
+    /// RAG collections the project's conversations are answered from
+    pub collections: Vec<String>,
+    /// User who created the project
+    pub created_by: String,
+    /// When the project was created (Unix timestamp)
+    pub created_at: i64,
+    /// When the project was last changed (Unix timestamp)
+    pub updated_at: i64,
+}
+
+impl Project {
+    /// Fill the settings a conversation doesn't pin with the project's defaults
+    pub fn apply(&self, overrides: ConversationOverrides) -> ConversationOverrides {
+        ConversationOverrides {
+            model: overrides.model.or_else(|| self.defaults.model.clone()),
+            temperature: overrides.temperature.or(self.defaults.temperature),
+            agent: overrides.agent.or_else(|| self.defaults.agent.clone()),
+            persona: overrides.persona.or_else(|| self.defaults.persona.clone()),
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ProjectRow {
+    id: String,
+    owner_id: String,
+    name: String,
+    description: Option<String>,
+    agent: Option<String>,
+    model: Option<String>,
+    temperature: Option<f32>,
+    persona: Option<String>,
+    collections: String,
+    created_by: String,
+    created_at: i64,
+    updated_at: i64,
+}
+
+impl TryFrom<ProjectRow> for Project {
+    type Error = AppError;
+
+    fn try_from(row: ProjectRow) -> Result<Self> {
+        Ok(Project {
+            collections: serde_json::from_str(&row.collections).map_err(|e| {
+                AppError::Database(format!("Invalid project collections stored: {}", e))
+            })?,
+            id: row.id,
+            owner_id: row.owner_id,
+            name: row.name,
+            description: row.description,
+            defaults: ConversationOverrides {
+                model: row.model,
+                temperature: row.temperature,
+                agent: row.agent,
+                persona: row.persona,
+            },
+            created_by: row.created_by,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+fn encode_collections(collections: &[String]) -> Result<String> {
+    serde_json::to_string(collections)
+        .map_err(|e| AppError::Internal(format!("Failed to encode project collections: {}", e)))
+}
+
+/// Store a new project.
+pub async fn insert_project(pool: &PgPool, project: &Project) -> Result<()> {
+    sqlx::query(&format!(
+        "INSERT INTO projects ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+        COLUMNS
+    ))
+    .bind(&project.id)
+    .bind(&project.owner_id)
+    .bind(&project.name)
+    .bind(&project.description)
+    .bind(&project.defaults.agent)
+    .bind(&project.defaults.model)
+    .bind(project.defaults.temperature)
+    .bind(&project.defaults.persona)
+    .bind(encode_collections(&project.collections)?)
+    .bind(&project.created_by)
+    .bind(project.created_at)
+    .bind(project.updated_at)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to store project: {}", e)))?;
+    Ok(())
+}
+
+/// Replace a project's name, description, defaults and collections.
+pub async fn update_project(pool: &PgPool, project: &Project) -> Result<()> {
+    sqlx::query(
+        "UPDATE projects SET name = $3, description = $4, agent = $5, model = $6,
+         temperature = $7, persona = $8, collections = $9, updated_at = $10
+         WHERE id = $1 AND owner_id = $2",
+    )
+    .bind(&project.id)
+    .bind(&project.owner_id)
+    .bind(&project.name)
+    .bind(&project.description)
+    .bind(&project.defaults.agent)
+    .bind(&project.defaults.model)
+    .bind(project.defaults.temperature)
+    .bind(&project.defaults.persona)
+    .bind(encode_collections(&project.collections)?)
+    .bind(project.updated_at)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to update project: {}", e)))?;
+    Ok(())
+}
+
+/// Get one of an owner's projects.
+pub async fn get_project(pool: &PgPool, id: &str, owner_id: &str) -> Result<Option<Project>> {
+    sqlx::query_as::<_, ProjectRow>(&format!(
+        "SELECT {} FROM projects WHERE id = $1 AND owner_id = $2",
+        COLUMNS
+    ))
+    .bind(id)
+    .bind(owner_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get project: {}", e)))?
+    .map(Project::try_from)
+    .transpose()
+}
+
+/// List an owner's projects by name.
+pub async fn list_projects(pool: &PgPool, owner_id: &str) -> Result<Vec<Project>> {
+    sqlx::query_as::<_, ProjectRow>(&format!(
+        "SELECT {} FROM projects WHERE owner_id = $1 ORDER BY LOWER(name), id",
+        COLUMNS
+    ))
+    .bind(owner_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list projects: {}", e)))?
+    .into_iter()
+    .map(Project::try_from)
+    .collect()
+}
+
+/// Delete one of an owner's projects, returning whether it existed.
+///
+/// The project's conversations and files are kept, outside any project.
+pub async fn delete_project(pool: &PgPool, id: &str, owner_id: &str) -> Result<bool> {
+    let failed = |e: sqlx::Error| AppError::Database(format!("Failed to delete project: {}", e));
+    let mut tx = pool.begin().await.map_err(failed)?;
+    let result = sqlx::query("DELETE FROM projects WHERE id = $1 AND owner_id = $2")
+        .bind(id)
+        .bind(owner_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(failed)?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+    for table in ["conversations", "files"] {
+        sqlx::query(&format!(
+            "UPDATE {} SET project_id = NULL WHERE project_id = $1",
+            table
+        ))
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(failed)?;
+    }
+    tx.commit().await.map_err(failed)?;
+    Ok(true)
+}
+
+/// The project a conversation belongs to, if any.
+pub async fn conversation_project(pool: &PgPool, conversation_id: &str) -> Result<Option<Project>> {
+    sqlx::query_as::<_, ProjectRow>(&format!(
+        "SELECT {} FROM projects WHERE id = (SELECT project_id FROM conversations WHERE id = $1)",
+        COLUMNS
+    ))
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get conversation project: {}", e)))?
+    .map(Project::try_from)
+    .transpose()
+}
+
+/// Move a conversation into a project, or out of any with `None`.
+pub async fn set_conversation_project(
+    pool: &PgPool,
+    conversation_id: &str,
+    project_id: Option<&str>,
+) -> Result<()> {
+    sqlx::query("UPDATE conversations SET project_id = $2 WHERE id = $1")
+        .bind(conversation_id)
+        .bind(project_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to move conversation: {}", e)))?;
+    Ok(())
+}
+
+/// Move a file into a project, or out of any with `None`.
+pub async fn set_file_project(
+    pool: &PgPool,
+    file_id: &str,
+    project_id: Option<&str>,
+) -> Result<()> {
+    sqlx::query("UPDATE files SET project_id = $2 WHERE id = $1")
+        .bind(file_id)
+        .bind(project_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to move file: {}", e)))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversation_settings_take_precedence_over_project_defaults() {
+        let project = Project {
+            id: "p1".to_string(),
+            owner_id: "user-1".to_string(),
+            name: "Contracts".to_string(),
+            description: None,
+            defaults: ConversationOverrides {
+                model: Some("balanced".to_string()),
+                temperature: Some(0.2),
+                agent: Some("legal".to_string()),
+                persona: None,
+            },
+            collections: vec![],
+            created_by: "user-1".to_string(),
+            created_at: 0,
+            updated_at: 0,
+        };
+        let applied = project.apply(ConversationOverrides {
+            model: Some("fast".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(applied.model.as_deref(), Some("fast"));
+        assert_eq!(applied.temperature, Some(0.2));
+        assert_eq!(applied.agent.as_deref(), Some("legal"));
+        assert_eq!(applied.persona, None);
+    }
+}
@@ -1,17 +1,78 @@
-use crate::types::{AppError, Document, Result, SearchResult};
+//! Qdrant vector store.
+//!
+//! Stores each collection as a Qdrant collection of points with cosine
+//! distance. Qdrant only accepts unsigned integers and UUIDs as point IDs,
+//! so other document IDs are mapped to a UUID derived from them, and the
+//! original ID is kept in the `doc_id` payload field.
+//!
+//! Collections get keyword payload indexes on `title`, `source`, `tags` and
+//! `doc_id`, so filtered searches stay fast. RAG settings live in the
+//! `ares_collection_settings` collection, one point per collection.
+//!
+//! Besides the [`VectorStore`] operations, collections can be exported by
+//! scrolling through their points ([`QdrantVectorStore::export`]) and
+//! snapshotted on the Qdrant server.
+
+use crate::types::{
+    AppError, CollectionSettings, Document, DocumentMetadata, Result, SearchResult,
+};
 use async_trait::async_trait;
 use qdrant_client::{
     qdrant::{
-        condition::ConditionOneOf, r#match::MatchValue, Condition, CreateCollectionBuilder,
-        DeletePointsBuilder, Distance, FieldCondition, Filter, Match, PointId, PointStruct,
-        SearchPointsBuilder, UpsertPointsBuilder, VectorParamsBuilder,
+        point_id::PointIdOptions, vector_output::Vector, Condition, CountPointsBuilder,
+        CreateCollectionBuilder, CreateFieldIndexCollectionBuilder, DeletePointsBuilder,
+        DeleteSnapshotRequestBuilder, Distance, FieldType, Filter, GetPointsBuilder, PointId,
+        PointStruct, ScrollPointsBuilder, SearchPointsBuilder, SnapshotDescription,
+        UpsertPointsBuilder, Value, VectorParamsBuilder, VectorsOutput,
     },
     Qdrant,
 };
+use serde::Serialize;
 use std::collections::HashMap;
+use std::io::Write;
+use uuid::Uuid;
 
 use super::vectorstore::{CollectionInfo, CollectionStats, VectorStore};
 
+/// Collection holding the RAG settings of the other collections
+const SETTINGS_COLLECTION: &str = "ares_collection_settings";
+
+/// Payload fields indexed in every collection
+const INDEXED_FIELDS: [&str; 4] = ["doc_id", "title", "source", "tags"];
+
+/// Points sent per upsert request
+const UPSERT_CHUNK_SIZE: usize = 256;
+
+/// Points fetched per scroll request
+const SCROLL_PAGE_SIZE: u32 = 256;
+
+/// Namespace of the UUIDs derived from document IDs
+const ID_NAMESPACE: Uuid = Uuid::from_u128(0x6a1f_4c1e_8d3b_4f0a_9b2e_5c7d_0e8f_1a2b);
+
+/// A snapshot of a collection stored on the Qdrant server.
+#[derive(Debug, Clone, Serialize)]
+pub struct QdrantSnapshot {
+    /// Snapshot name, used to restore or delete it
+    pub name: String,
+    /// Unix timestamp of creation
+    pub created_at: Option<i64>,
+    /// Size in bytes
+    pub size: u64,
+    /// SHA-256 of the snapshot file, when Qdrant reports it
+    pub checksum: Option<String>,
+}
+
+impl From<SnapshotDescription> for QdrantSnapshot {
+    fn from(description: SnapshotDescription) -> Self {
+        Self {
+            name: description.name,
+            created_at: description.creation_time.map(|time| time.seconds),
+            size: description.size.max(0) as u64,
+            checksum: description.checksum,
+        }
+    }
+}
+
 /// Qdrant vector store implementation.
 ///
 /// Provides vector storage and similarity search using a Qdrant server.
@@ -21,6 +82,7 @@ pub struct QdrantVectorStore {
 }
 
 impl QdrantVectorStore {
+    /// Connect to a Qdrant server over gRPC (e.g. `http://localhost:6334`).
     pub async fn new(url: String, api_key: Option<String>) -> Result<Self> {
         let client = if let Some(key) = api_key {
             Qdrant::from_url(&url)
@@ -33,124 +95,272 @@ impl QdrantVectorStore {
                 .map_err(|e| AppError::Database(format!("Failed to create Qdrant client: {}", e)))?
         };
 
-        let qdrant = Self { client };
-        // qdrant.initialize_collections().await?;
+        Ok(Self { client })
+    }
 
-        Ok(qdrant)
+    /// Upsert a single document into the `documents` collection.
+    pub async fn upsert_document(&self, document: &Document) -> Result<()> {
+        self.upsert("documents", std::slice::from_ref(document))
+            .await?;
+        Ok(())
     }
 
+    /// Delete a single document from the `documents` collection.
     #[allow(dead_code)]
-    async fn initialize_collections(&self) -> Result<()> {
-        let collection_name = "documents";
+    pub async fn delete_document(&self, id: &str) -> Result<()> {
+        self.delete("documents", &[id.to_string()]).await?;
+        Ok(())
+    }
 
-        // Check if collection exists
-        let collections = self
-            .client
-            .list_collections()
+    /// Create a keyword payload index on a field of a collection, so
+    /// filters on it don't scan every point.
+    ///
+    /// `title`, `source`, `tags` and `doc_id` are indexed when a collection
+    /// is created.
+    pub async fn create_payload_index(&self, collection: &str, field: &str) -> Result<()> {
+        self.client
+            .create_field_index(
+                CreateFieldIndexCollectionBuilder::new(collection, field, FieldType::Keyword)
+                    .wait(true),
+            )
             .await
-            .map_err(|e| AppError::Database(format!("Failed to list collections: {}", e)))?;
-
-        let exists = collections
-            .collections
-            .iter()
-            .any(|c| c.name == collection_name);
-
-        if !exists {
-            // Create collection with 384-dimensional vectors (for BGE-small)
-            self.client
-                .create_collection(
-                    CreateCollectionBuilder::new(collection_name)
-                        .vectors_config(VectorParamsBuilder::new(384, Distance::Cosine)),
-                )
-                .await
-                .map_err(|e| AppError::Database(format!("Failed to create collection: {}", e)))?;
-        }
-
+            .map_err(|e| AppError::Database(format!("Failed to create payload index: {}", e)))?;
         Ok(())
     }
 
-    pub async fn upsert_document(&self, document: &Document) -> Result<()> {
-        let collection_name = "documents";
-
-        let embedding = document
-            .embedding
-            .as_ref()
-            .ok_or_else(|| AppError::Database("Document missing embedding".to_string()))?;
-
-        let mut payload: HashMap<String, qdrant_client::qdrant::Value> = HashMap::new();
-        payload.insert("content".to_string(), document.content.clone().into());
-        payload.insert("title".to_string(), document.metadata.title.clone().into());
-        payload.insert(
-            "source".to_string(),
-            document.metadata.source.clone().into(),
-        );
-        payload.insert(
-            "created_at".to_string(),
-            document.metadata.created_at.timestamp().into(),
-        );
-        payload.insert(
-            "tags".to_string(),
-            serde_json::to_value(&document.metadata.tags)
-                .unwrap_or(serde_json::Value::Null)
-                .into(),
-        );
-
-        let point = PointStruct::new(document.id.clone(), embedding.clone(), payload);
+    /// Snapshot a collection on the Qdrant server.
+    ///
+    /// The snapshot stays on the server, from where it can be downloaded
+    /// or restored with Qdrant's own API.
+    pub async fn create_snapshot(&self, collection: &str) -> Result<QdrantSnapshot> {
+        let response = self
+            .client
+            .create_snapshot(collection)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to create snapshot: {}", e)))?;
+        response
+            .snapshot_description
+            .map(QdrantSnapshot::from)
+            .ok_or_else(|| AppError::Database("Qdrant returned no snapshot".to_string()))
+    }
 
-        self.client
-            .upsert_points(UpsertPointsBuilder::new(collection_name, vec![point]).wait(true))
+    /// Snapshots of a collection, oldest first.
+    pub async fn list_snapshots(&self, collection: &str) -> Result<Vec<QdrantSnapshot>> {
+        let response = self
+            .client
+            .list_snapshots(collection)
             .await
-            .map_err(|e| AppError::Database(format!("Failed to upsert point: {}", e)))?;
+            .map_err(|e| AppError::Database(format!("Failed to list snapshots: {}", e)))?;
+        let mut snapshots: Vec<QdrantSnapshot> = response
+            .snapshot_descriptions
+            .into_iter()
+            .map(QdrantSnapshot::from)
+            .collect();
+        snapshots.sort_by_key(|snapshot| snapshot.created_at);
+        Ok(snapshots)
+    }
 
+    /// Delete a snapshot of a collection.
+    pub async fn delete_snapshot(&self, collection: &str, name: &str) -> Result<()> {
+        self.client
+            .delete_snapshot(DeleteSnapshotRequestBuilder::new(collection, name))
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to delete snapshot: {}", e)))?;
         Ok(())
     }
 
-    /// Parse search results from Qdrant response.
-    fn parse_search_results(
+    /// Write every document of a collection, with its embedding, to
+    /// `writer` as JSON lines, returning the number written.
+    ///
+    /// Scrolls through the collection a page at a time, so collections
+    /// larger than memory can be exported.
+    pub async fn export(
         &self,
-        search_result: qdrant_client::qdrant::SearchResponse,
-    ) -> Vec<SearchResult> {
-        search_result
+        collection: &str,
+        writer: &mut (impl Write + Send),
+    ) -> Result<usize> {
+        let mut written = 0;
+        let mut offset = None;
+        loop {
+            let (documents, next) = self.scroll_page(collection, offset).await?;
+            for document in &documents {
+                serde_json::to_writer(&mut *writer, document)
+                    .map_err(|e| AppError::Internal(format!("Failed to export document: {}", e)))?;
+                writer
+                    .write_all(b"\n")
+                    .map_err(|e| AppError::Internal(format!("Failed to export document: {}", e)))?;
+            }
+            written += documents.len();
+            match next {
+                Some(next) => offset = Some(next),
+                None => return Ok(written),
+            }
+        }
+    }
+
+    /// One page of documents with their embeddings, and the offset of the
+    /// next page.
+    async fn scroll_page(
+        &self,
+        collection: &str,
+        offset: Option<PointId>,
+    ) -> Result<(Vec<Document>, Option<PointId>)> {
+        let mut request = ScrollPointsBuilder::new(collection)
+            .limit(SCROLL_PAGE_SIZE)
+            .with_payload(true)
+            .with_vectors(true);
+        if let Some(offset) = offset {
+            request = request.offset(offset);
+        }
+        let response = self
+            .client
+            .scroll(request)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to scroll points: {}", e)))?;
+        let documents = response
             .result
             .into_iter()
-            .filter_map(|scored_point| {
-                let payload = scored_point.payload;
-                let content = payload.get("content")?.as_str()?.to_string();
-                let title = payload.get("title")?.as_str()?.to_string();
-                let source = payload.get("source")?.as_str()?.to_string();
-                let created_at_ts = payload.get("created_at")?.as_integer()?;
-                let tags: Vec<String> =
-                    serde_json::from_value(payload.get("tags")?.clone().into()).ok()?;
-
-                let id_str = match scored_point.id?.point_id_options? {
-                    qdrant_client::qdrant::point_id::PointIdOptions::Num(num) => num.to_string(),
-                    qdrant_client::qdrant::point_id::PointIdOptions::Uuid(uuid) => uuid,
-                };
-                Some(SearchResult {
-                    document: Document {
-                        id: id_str,
-                        content,
-                        metadata: crate::types::DocumentMetadata {
-                            title,
-                            source,
-                            created_at: chrono::DateTime::from_timestamp(created_at_ts, 0)?,
-                            tags,
-                        },
-                        embedding: None,
-                    },
-                    score: scored_point.score,
-                })
+            .filter_map(|point| payload_document(point.id, point.payload, point.vectors))
+            .collect();
+        Ok((documents, response.next_page_offset))
+    }
+
+    /// Dimensions of a collection's vectors
+    async fn dimensions(&self, name: &str) -> Result<usize> {
+        let info = self
+            .client
+            .collection_info(name)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to get collection info: {}", e)))?;
+
+        Ok(info
+            .result
+            .ok_or_else(|| AppError::NotFound(format!("Collection '{}' not found", name)))?
+            .config
+            .and_then(|c| c.params)
+            .and_then(|p| p.vectors_config)
+            .and_then(|v| match v.config {
+                Some(qdrant_client::qdrant::vectors_config::Config::Params(p)) => {
+                    Some(p.size as usize)
+                }
+                _ => None,
             })
-            .collect()
+            .unwrap_or(0))
     }
 
-    #[allow(dead_code)]
-    pub async fn delete_document(&self, id: &str) -> Result<()> {
-        self.delete("documents", &[id.to_string()]).await?;
-        Ok(())
+    /// Exact number of points in a collection
+    async fn point_count(&self, name: &str) -> Result<usize> {
+        let response = self
+            .client
+            .count(CountPointsBuilder::new(name).exact(true))
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to count points: {}", e)))?;
+        Ok(response.result.map(|result| result.count).unwrap_or(0) as usize)
     }
 }
 
+/// Qdrant point ID of a document ID.
+///
+/// Unsigned integers and UUIDs are used as they are; anything else maps to
+/// a UUID derived from it.
+fn point_id(id: &str) -> PointId {
+    if let Ok(num) = id.parse::<u64>() {
+        return PointId::from(num);
+    }
+    let uuid = Uuid::parse_str(id).unwrap_or_else(|_| Uuid::new_v5(&ID_NAMESPACE, id.as_bytes()));
+    PointId::from(uuid.to_string())
+}
+
+/// Payload stored with a document
+fn document_payload(document: &Document) -> HashMap<String, Value> {
+    let mut payload: HashMap<String, Value> = HashMap::new();
+    payload.insert("doc_id".to_string(), document.id.clone().into());
+    payload.insert("content".to_string(), document.content.clone().into());
+    payload.insert("title".to_string(), document.metadata.title.clone().into());
+    payload.insert(
+        "source".to_string(),
+        document.metadata.source.clone().into(),
+    );
+    payload.insert(
+        "created_at".to_string(),
+        document.metadata.created_at.timestamp().into(),
+    );
+    payload.insert(
+        "tags".to_string(),
+        serde_json::to_value(&document.metadata.tags)
+            .unwrap_or(serde_json::Value::Null)
+            .into(),
+    );
+    payload
+}
+
+/// Rebuild a document from a point.
+///
+/// Points written before `doc_id` was stored fall back to the point ID.
+fn payload_document(
+    id: Option<PointId>,
+    payload: HashMap<String, Value>,
+    vectors: Option<VectorsOutput>,
+) -> Option<Document> {
+    let text = |field: &str| {
+        payload
+            .get(field)
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_default()
+    };
+    let id = match payload.get("doc_id").and_then(|v| v.as_str()) {
+        Some(doc_id) => doc_id.to_string(),
+        None => match id?.point_id_options? {
+            PointIdOptions::Num(num) => num.to_string(),
+            PointIdOptions::Uuid(uuid) => uuid,
+        },
+    };
+    let created_at = payload
+        .get("created_at")
+        .and_then(|v| v.as_integer())
+        .unwrap_or(0);
+    let tags: Vec<String> = payload
+        .get("tags")
+        .and_then(|v| serde_json::from_value(v.clone().into()).ok())
+        .unwrap_or_default();
+    let embedding =
+        vectors
+            .and_then(|vectors| vectors.get_vector())
+            .and_then(|vector| match vector {
+                Vector::Dense(dense) => Some(dense.data),
+                _ => None,
+            });
+
+    Some(Document {
+        id,
+        content: text("content"),
+        metadata: DocumentMetadata {
+            title: text("title"),
+            source: text("source"),
+            created_at: chrono::DateTime::from_timestamp(created_at, 0)
+                .unwrap_or_else(chrono::Utc::now),
+            tags,
+        },
+        embedding,
+    })
+}
+
+/// Qdrant filter for metadata filters, matching
+/// [`matches_filters`](super::vectorstore::matches_filters).
+///
+/// `None` when a filter is on a field no document has, so nothing matches.
+fn metadata_filter(filters: &[(String, String)]) -> Option<Filter> {
+    let conditions = filters
+        .iter()
+        .map(|(field, value)| match field.as_str() {
+            "title" | "source" | "tags" => Some(Condition::matches(field.as_str(), value.clone())),
+            _ => None,
+        })
+        .collect::<Option<Vec<Condition>>>()?;
+    Some(Filter::must(conditions))
+}
+
 // ============================================================================
 // VectorStore Trait Implementation
 // ============================================================================
@@ -162,32 +372,49 @@ impl VectorStore for QdrantVectorStore {
     }
 
     async fn create_collection(&self, name: &str, dimensions: usize) -> Result<()> {
-        // Check if collection exists
-        let collections = self
-            .client
-            .list_collections()
-            .await
-            .map_err(|e| AppError::Database(format!("Failed to list collections: {}", e)))?;
-
-        let exists = collections.collections.iter().any(|c| c.name == name);
+        if self.collection_exists(name).await? {
+            return Err(AppError::InvalidInput(format!(
+                "Collection '{}' already exists",
+                name
+            )));
+        }
 
-        if !exists {
-            self.client
-                .create_collection(CreateCollectionBuilder::new(name).vectors_config(
-                    VectorParamsBuilder::new(dimensions as u64, Distance::Cosine),
-                ))
-                .await
-                .map_err(|e| AppError::Database(format!("Failed to create collection: {}", e)))?;
+        self.client
+            .create_collection(CreateCollectionBuilder::new(name).vectors_config(
+                VectorParamsBuilder::new(dimensions as u64, Distance::Cosine),
+            ))
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to create collection: {}", e)))?;
+        for field in INDEXED_FIELDS {
+            self.create_payload_index(name, field).await?;
         }
 
         Ok(())
     }
 
     async fn delete_collection(&self, name: &str) -> Result<()> {
+        if !self.collection_exists(name).await? {
+            return Err(AppError::NotFound(format!(
+                "Collection '{}' not found",
+                name
+            )));
+        }
         self.client
             .delete_collection(name)
             .await
             .map_err(|e| AppError::Database(format!("Failed to delete collection: {}", e)))?;
+
+        // Remove its settings along with it
+        if self.collection_exists(SETTINGS_COLLECTION).await? {
+            self.client
+                .delete_points(
+                    DeletePointsBuilder::new(SETTINGS_COLLECTION)
+                        .points(vec![point_id(name)])
+                        .wait(true),
+                )
+                .await
+                .map_err(|e| AppError::Database(format!("Failed to delete settings: {}", e)))?;
+        }
         Ok(())
     }
 
@@ -200,70 +427,32 @@ impl VectorStore for QdrantVectorStore {
 
         let mut result = Vec::new();
         for col in collections.collections {
-            // Get collection info for each
-            if let Ok(info) = self.client.collection_info(&col.name).await {
-                if let Some(collection_info) = info.result {
-                    let count = collection_info.points_count.unwrap_or(0) as usize;
-                    let dims = collection_info
-                        .config
-                        .and_then(|c| c.params)
-                        .and_then(|p| p.vectors_config)
-                        .and_then(|v| match v.config {
-                            Some(qdrant_client::qdrant::vectors_config::Config::Params(p)) => {
-                                Some(p.size as usize)
-                            }
-                            _ => None,
-                        })
-                        .unwrap_or(0);
-                    result.push(CollectionInfo {
-                        name: col.name,
-                        document_count: count,
-                        dimensions: dims,
-                    });
-                }
+            if col.name == SETTINGS_COLLECTION {
+                continue;
             }
+            result.push(CollectionInfo {
+                document_count: self.point_count(&col.name).await?,
+                dimensions: self.dimensions(&col.name).await?,
+                name: col.name,
+            });
         }
 
         Ok(result)
     }
 
     async fn collection_exists(&self, name: &str) -> Result<bool> {
-        let collections = self
-            .client
-            .list_collections()
+        self.client
+            .collection_exists(name)
             .await
-            .map_err(|e| AppError::Database(format!("Failed to list collections: {}", e)))?;
-
-        Ok(collections.collections.iter().any(|c| c.name == name))
+            .map_err(|e| AppError::Database(format!("Failed to check collection: {}", e)))
     }
 
     async fn collection_stats(&self, name: &str) -> Result<CollectionStats> {
-        let info = self
-            .client
-            .collection_info(name)
-            .await
-            .map_err(|e| AppError::Database(format!("Failed to get collection info: {}", e)))?;
-
-        let result = info
-            .result
-            .ok_or_else(|| AppError::Database("Collection not found".to_string()))?;
-
-        let document_count = result.points_count.unwrap_or(0) as usize;
-        let dimensions = result
-            .config
-            .and_then(|c| c.params)
-            .and_then(|p| p.vectors_config)
-            .and_then(|v| match v.config {
-                Some(qdrant_client::qdrant::vectors_config::Config::Params(p)) => {
-                    Some(p.size as usize)
-                }
-                _ => None,
-            })
-            .unwrap_or(0);
+        let dimensions = self.dimensions(name).await?;
 
         Ok(CollectionStats {
             name: name.to_string(),
-            document_count,
+            document_count: self.point_count(name).await?,
             dimensions,
             index_size_bytes: None,
             distance_metric: "cosine".to_string(),
@@ -271,42 +460,28 @@ impl VectorStore for QdrantVectorStore {
     }
 
     async fn upsert(&self, collection: &str, documents: &[Document]) -> Result<usize> {
-        let mut points = Vec::with_capacity(documents.len());
+        if documents.is_empty() {
+            return Ok(0);
+        }
 
+        let mut points = Vec::with_capacity(documents.len());
         for document in documents {
-            let embedding = document
-                .embedding
-                .as_ref()
-                .ok_or_else(|| AppError::Database("Document missing embedding".to_string()))?;
-
-            let mut payload: HashMap<String, qdrant_client::qdrant::Value> = HashMap::new();
-            payload.insert("content".to_string(), document.content.clone().into());
-            payload.insert("title".to_string(), document.metadata.title.clone().into());
-            payload.insert(
-                "source".to_string(),
-                document.metadata.source.clone().into(),
-            );
-            payload.insert(
-                "created_at".to_string(),
-                document.metadata.created_at.timestamp().into(),
-            );
-            payload.insert(
-                "tags".to_string(),
-                serde_json::to_value(&document.metadata.tags)
-                    .unwrap_or(serde_json::Value::Null)
-                    .into(),
-            );
-
+            let embedding = document.embedding.as_ref().ok_or_else(|| {
+                AppError::InvalidInput(format!("Document '{}' is missing embedding", document.id))
+            })?;
             points.push(PointStruct::new(
-                document.id.clone(),
+                point_id(&document.id),
                 embedding.clone(),
-                payload,
+                document_payload(document),
             ));
         }
 
         let count = points.len();
         self.client
-            .upsert_points(UpsertPointsBuilder::new(collection, points).wait(true))
+            .upsert_points_chunked(
+                UpsertPointsBuilder::new(collection, points).wait(true),
+                UPSERT_CHUNK_SIZE,
+            )
             .await
             .map_err(|e| AppError::Database(format!("Failed to upsert points: {}", e)))?;
 
@@ -320,16 +495,8 @@ impl VectorStore for QdrantVectorStore {
         limit: usize,
         threshold: f32,
     ) -> Result<Vec<SearchResult>> {
-        let search_builder = SearchPointsBuilder::new(collection, embedding.to_vec(), limit as u64)
-            .score_threshold(threshold);
-
-        let search_result = self
-            .client
-            .search_points(search_builder.with_payload(true))
+        self.search_with_filters(collection, embedding, limit, threshold, &[])
             .await
-            .map_err(|e| AppError::Database(format!("Failed to search: {}", e)))?;
-
-        Ok(self.parse_search_results(search_result))
     }
 
     async fn search_with_filters(
@@ -342,149 +509,209 @@ impl VectorStore for QdrantVectorStore {
     ) -> Result<Vec<SearchResult>> {
         let mut search_builder =
             SearchPointsBuilder::new(collection, embedding.to_vec(), limit as u64)
-                .score_threshold(threshold);
+                .score_threshold(threshold)
+                .with_payload(true);
 
         if !filters.is_empty() {
-            let conditions: Vec<Condition> = filters
-                .iter()
-                .map(|(field, value)| {
-                    let field_condition = FieldCondition {
-                        key: field.clone(),
-                        r#match: Some(Match {
-                            match_value: Some(MatchValue::Text(value.clone())),
-                        }),
-                        ..Default::default()
-                    };
-                    Condition {
-                        condition_one_of: Some(ConditionOneOf::Field(field_condition)),
-                    }
-                })
-                .collect();
-            search_builder = search_builder.filter(Filter::must(conditions));
+            match metadata_filter(filters) {
+                Some(filter) => search_builder = search_builder.filter(filter),
+                None => return Ok(Vec::new()),
+            }
         }
 
         let search_result = self
             .client
-            .search_points(search_builder.with_payload(true))
+            .search_points(search_builder)
             .await
             .map_err(|e| AppError::Database(format!("Failed to search: {}", e)))?;
 
-        Ok(self.parse_search_results(search_result))
+        Ok(search_result
+            .result
+            .into_iter()
+            .filter_map(|point| {
+                let score = point.score;
+                payload_document(point.id, point.payload, None)
+                    .map(|document| SearchResult { document, score })
+            })
+            .collect())
     }
 
     async fn delete(&self, collection: &str, ids: &[String]) -> Result<usize> {
-        use qdrant_client::qdrant::point_id::PointIdOptions;
-
-        let point_ids: Vec<PointId> = ids
-            .iter()
-            .map(|id| {
-                if let Ok(num) = id.parse::<u64>() {
-                    PointId {
-                        point_id_options: Some(PointIdOptions::Num(num)),
-                    }
-                } else {
-                    PointId {
-                        point_id_options: Some(PointIdOptions::Uuid(id.to_string())),
-                    }
-                }
-            })
-            .collect();
+        if ids.is_empty() {
+            return Ok(0);
+        }
 
-        let count = point_ids.len();
+        // Qdrant doesn't report how many points a delete removed
+        let before = self.point_count(collection).await?;
         self.client
             .delete_points(
                 DeletePointsBuilder::new(collection)
-                    .points(point_ids)
+                    .points(ids.iter().map(|id| point_id(id)).collect::<Vec<_>>())
                     .wait(true),
             )
             .await
             .map_err(|e| AppError::Database(format!("Failed to delete points: {}", e)))?;
 
-        Ok(count)
+        Ok(before.saturating_sub(self.point_count(collection).await?))
     }
 
     async fn get(&self, collection: &str, id: &str) -> Result<Option<Document>> {
-        use qdrant_client::qdrant::{point_id::PointIdOptions, GetPointsBuilder, PointId};
-
-        // Try to parse the ID as a numeric ID first, otherwise use UUID
-        let point_id = if let Ok(num) = id.parse::<u64>() {
-            PointId {
-                point_id_options: Some(PointIdOptions::Num(num)),
-            }
-        } else {
-            PointId {
-                point_id_options: Some(PointIdOptions::Uuid(id.to_string())),
-            }
-        };
-
         let result = self
             .client
             .get_points(
-                GetPointsBuilder::new(collection, vec![point_id])
+                GetPointsBuilder::new(collection, vec![point_id(id)])
                     .with_payload(true)
                     .with_vectors(true),
             )
             .await
             .map_err(|e| AppError::Database(format!("Failed to get point: {}", e)))?;
 
-        // Extract the first point if found
-        let point = match result.result.into_iter().next() {
-            Some(p) => p,
-            None => return Ok(None),
-        };
+        Ok(result
+            .result
+            .into_iter()
+            .next()
+            .and_then(|point| payload_document(point.id, point.payload, point.vectors)))
+    }
 
-        // Parse the payload
-        let payload = point.payload;
-        let content = payload
-            .get("content")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-        let title = payload
-            .get("title")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-        let source = payload
-            .get("source")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-        let created_at_ts = payload
-            .get("created_at")
-            .and_then(|v| v.as_integer())
-            .unwrap_or(0);
-        let tags: Vec<String> = payload
-            .get("tags")
-            .and_then(|v| serde_json::from_value(v.clone().into()).ok())
-            .unwrap_or_default();
-
-        // Get the ID string
-        let id_str = match point.id {
-            Some(pid) => match pid.point_id_options {
-                Some(PointIdOptions::Num(num)) => num.to_string(),
-                Some(PointIdOptions::Uuid(uuid)) => uuid,
-                None => return Ok(None),
+    async fn documents(&self, collection: &str) -> Result<Vec<Document>> {
+        let mut documents = Vec::new();
+        let mut offset = None;
+        loop {
+            let (page, next) = self.scroll_page(collection, offset).await?;
+            documents.extend(page);
+            match next {
+                Some(next) => offset = Some(next),
+                None => return Ok(documents),
+            }
+        }
+    }
+
+    async fn collection_settings(&self, name: &str) -> Result<Option<CollectionSettings>> {
+        if !self.collection_exists(SETTINGS_COLLECTION).await? {
+            return Ok(None);
+        }
+        let result = self
+            .client
+            .get_points(
+                GetPointsBuilder::new(SETTINGS_COLLECTION, vec![point_id(name)]).with_payload(true),
+            )
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to get settings: {}", e)))?;
+
+        result
+            .result
+            .into_iter()
+            .next()
+            .and_then(|point| {
+                point
+                    .payload
+                    .get("settings")
+                    .and_then(|v| v.as_str())
+                    .cloned()
+            })
+            .map(|settings| {
+                serde_json::from_str(&settings).map_err(|e| {
+                    AppError::Database(format!("Invalid settings for '{}': {}", name, e))
+                })
+            })
+            .transpose()
+    }
+
+    async fn set_collection_settings(
+        &self,
+        name: &str,
+        settings: &CollectionSettings,
+    ) -> Result<()> {
+        // Settings points carry a placeholder vector; only their payload is read
+        if !self.collection_exists(SETTINGS_COLLECTION).await? {
+            self.client
+                .create_collection(
+                    CreateCollectionBuilder::new(SETTINGS_COLLECTION)
+                        .vectors_config(VectorParamsBuilder::new(1, Distance::Dot)),
+                )
+                .await
+                .map_err(|e| {
+                    AppError::Database(format!("Failed to create settings collection: {}", e))
+                })?;
+        }
+
+        let settings = serde_json::to_string(settings)
+            .map_err(|e| AppError::Internal(format!("Failed to encode settings: {}", e)))?;
+        let mut payload: HashMap<String, Value> = HashMap::new();
+        payload.insert("name".to_string(), name.to_string().into());
+        payload.insert("settings".to_string(), settings.into());
+        self.client
+            .upsert_points(
+                UpsertPointsBuilder::new(
+                    SETTINGS_COLLECTION,
+                    vec![PointStruct::new(point_id(name), vec![0.0], payload)],
+                )
+                .wait(true),
+            )
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to save settings: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_ids() {
+        assert_eq!(point_id("42"), PointId::from(42u64));
+
+        let uuid = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        assert_eq!(point_id(uuid), PointId::from(uuid.to_string()));
+
+        // Other IDs map to a stable UUID of their own
+        let chunk = point_id("doc_1712_3");
+        assert_eq!(chunk, point_id("doc_1712_3"));
+        assert_ne!(chunk, point_id("doc_1712_4"));
+        match chunk.point_id_options {
+            Some(PointIdOptions::Uuid(uuid)) => assert!(Uuid::parse_str(&uuid).is_ok()),
+            other => panic!("expected a UUID, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_payload_round_trip() {
+        let document = Document {
+            id: "doc_1712_3".to_string(),
+            content: "Refunds are processed within 5 days".to_string(),
+            metadata: DocumentMetadata {
+                title: "Refunds".to_string(),
+                source: "handbook.md".to_string(),
+                created_at: chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+                tags: vec!["billing".to_string()],
             },
-            None => return Ok(None),
+            embedding: None,
         };
 
-        // Extract embedding if available
-        // Note: For simplicity, we don't return the embedding when getting by ID.
-        // If embeddings are needed, use the search methods instead.
-        let embedding = None;
-
-        Ok(Some(Document {
-            id: id_str,
-            content,
-            metadata: crate::types::DocumentMetadata {
-                title,
-                source,
-                created_at: chrono::DateTime::from_timestamp(created_at_ts, 0)
-                    .unwrap_or_else(chrono::Utc::now),
-                tags,
-            },
-            embedding,
-        }))
+        let restored = payload_document(
+            Some(point_id(&document.id)),
+            document_payload(&document),
+            None,
+        )
+        .unwrap();
+        assert_eq!(restored.id, document.id);
+        assert_eq!(restored.content, document.content);
+        assert_eq!(restored.metadata.source, "handbook.md");
+        assert_eq!(restored.metadata.created_at, document.metadata.created_at);
+        assert_eq!(restored.metadata.tags, vec!["billing"]);
+    }
+
+    #[test]
+    fn test_metadata_filter() {
+        let filters = [
+            ("source".to_string(), "handbook.md".to_string()),
+            ("tags".to_string(), "billing".to_string()),
+        ];
+        assert_eq!(metadata_filter(&filters).unwrap().must.len(), 2);
+
+        // Unknown fields match nothing, as in the other stores
+        let unknown = [("author".to_string(), "anyone".to_string())];
+        assert!(metadata_filter(&unknown).is_none());
     }
 }
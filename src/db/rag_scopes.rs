@@ -0,0 +1,87 @@
+//! Storage for the RAG collections conversations are pinned to.
+//!
+//! See [`crate::rag::scope`].
+
+use crate::types::{AppError, RagScope, Result};
+use chrono::Utc;
+use sqlx::PgPool;
+
+/// A conversation's stored scope, with the owner of the collections it names.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredScope {
+    /// Owner of the collections: a user, or a workspace's owner key
+    pub user_id: String,
+    /// The scope
+    pub scope: RagScope,
+}
+
+#[derive(sqlx::FromRow)]
+struct ScopeRow {
+    user_id: String,
+    collections: String,
+    tags: String,
+}
+
+fn decode(column: &str, json: &str) -> Result<Vec<String>> {
+    serde_json::from_str(json)
+        .map_err(|e| AppError::Database(format!("Invalid RAG scope {} stored: {}", column, e)))
+}
+
+/// Get a conversation's scope, if it has one.
+pub async fn get_scope(pool: &PgPool, conversation_id: &str) -> Result<Option<StoredScope>> {
+    let row = sqlx::query_as::<_, ScopeRow>(
+        "SELECT user_id, collections, tags FROM conversation_rag_scopes WHERE conversation_id = $1",
+    )
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get RAG scope: {}", e)))?;
+    row.map(|row| {
+        Ok(StoredScope {
+            user_id: row.user_id,
+            scope: RagScope {
+                collections: decode("collections", &row.collections)?,
+                tags: decode("tags", &row.tags)?,
+            },
+        })
+    })
+    .transpose()
+}
+
+/// Pin a conversation to a scope, replacing any it had.
+pub async fn set_scope(
+    pool: &PgPool,
+    conversation_id: &str,
+    user_id: &str,
+    scope: &RagScope,
+) -> Result<()> {
+    let encode = |values: &[String]| {
+        serde_json::to_string(values)
+            .map_err(|e| AppError::Internal(format!("Failed to encode RAG scope: {}", e)))
+    };
+    sqlx::query(
+        "INSERT INTO conversation_rag_scopes (conversation_id, user_id, collections, tags, updated_at)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (conversation_id) DO UPDATE
+         SET collections = EXCLUDED.collections, tags = EXCLUDED.tags, updated_at = EXCLUDED.updated_at",
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .bind(encode(&scope.collections)?)
+    .bind(encode(&scope.tags)?)
+    .bind(Utc::now().timestamp())
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to store RAG scope: {}", e)))?;
+    Ok(())
+}
+
+/// Remove a conversation's scope, returning whether it had one.
+pub async fn delete_scope(pool: &PgPool, conversation_id: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM conversation_rag_scopes WHERE conversation_id = $1")
+        .bind(conversation_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to delete RAG scope: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
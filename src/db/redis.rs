@@ -0,0 +1,603 @@
+//! Redis shared by the instances of a deployment (feature `redis`).
+//!
+//! With a `[redis]` section, instances behind a load balancer share what
+//! they would otherwise each keep to themselves:
+//!
+//! - **Rate limits**: the `[server.rate_limits]` token buckets, so a
+//!   caller's limit holds whichever instance serves them
+//! - **Configuration reloads**: an instance reloading `ares.toml` announces
+//!   it, and the others reload their copy
+//! - **LLM responses** (`response_cache_ttl_secs`): answers to identical
+//!   prompts without tools, reused until they expire
+//! - **Sessions** (`sessions = true`): login sessions and their refresh
+//!   tokens, instead of the `sessions` table
+//!
+//! ```toml
+//! [redis]
+//! url_env = "REDIS_URL"
+//! key_prefix = "ares"
+//! sessions = true
+//! response_cache_ttl_secs = 3600
+//! ```
+//!
+//! Every key and channel starts with `key_prefix`. A Redis error on a
+//! rate-limit check falls back to the instance's own buckets; other
+//! errors fail the request.
+
+use crate::db::locks;
+use crate::db::sessions::{Session, SessionClient};
+use crate::llm::client::LLMResponse;
+use crate::llm::middleware::{LLMMiddleware, LLMRequest};
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::{AresConfigManager, RedisConfig};
+use async_trait::async_trait;
+use futures::StreamExt;
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, Script};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Delay before resubscribing to configuration events after an error
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// How long the instance announcing a configuration reload keeps the other
+/// instances from announcing it again
+const RELOAD_ANNOUNCE_WINDOW: Duration = Duration::from_secs(5);
+
+/// Take a token from the bucket `KEYS[1]` holding up to `ARGV[1]` tokens
+/// refilled at `ARGV[2]` a second; returns 0, or the milliseconds until a
+/// token is available. Timed with the Redis clock, shared by all instances.
+const TAKE_TOKEN: &str = r#"
+local capacity = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated')
+local tokens = tonumber(bucket[1]) or capacity
+local updated = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - updated) / 1000 * rate)
+local wait = 0
+if tokens >= 1 then
+  tokens = tokens - 1
+else
+  wait = math.ceil((1 - tokens) / rate * 1000)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity / rate * 1000))
+return wait
+"#;
+
+/// Move session `ARGV[1]` from the refresh token `KEYS[1]` to `KEYS[3]`,
+/// updating the session `KEYS[2]` and extending its user's sessions
+/// `KEYS[4]`; 0 if `KEYS[1]` no longer holds it.
+const ROTATE_SESSION: &str = r#"
+if redis.call('GET', KEYS[1]) ~= ARGV[1] then
+  return 0
+end
+redis.call('DEL', KEYS[1])
+redis.call('HSET', KEYS[2], 'token_hash', ARGV[2], 'expires_at', ARGV[3], 'last_used_at', ARGV[4])
+if ARGV[5] ~= '' then
+  redis.call('HSET', KEYS[2], 'user_agent', ARGV[5])
+end
+if ARGV[6] ~= '' then
+  redis.call('HSET', KEYS[2], 'ip_address', ARGV[6])
+end
+redis.call('EXPIREAT', KEYS[2], ARGV[3])
+redis.call('SET', KEYS[3], ARGV[1], 'EXAT', ARGV[3])
+redis.call('EXPIREAT', KEYS[4], ARGV[3])
+return 1
+"#;
+
+fn redis_err(action: &str) -> impl Fn(redis::RedisError) -> AppError + '_ {
+    move |e| AppError::Database(format!("Failed to {}: {}", action, e))
+}
+
+/// Connection to the Redis of `[redis]`.
+///
+/// Cheap to clone; clones share the connection, which reconnects on its
+/// own after an error.
+#[derive(Clone)]
+pub struct RedisStore {
+    client: redis::Client,
+    conn: ConnectionManager,
+    prefix: String,
+    /// Identifies this instance in configuration events
+    instance_id: String,
+}
+
+impl RedisStore {
+    /// Connect to the Redis whose URL is in `[redis] url_env`
+    pub async fn connect(config: &RedisConfig) -> Result<Self> {
+        let url = std::env::var(&config.url_env).map_err(|_| {
+            AppError::Configuration(format!(
+                "Environment variable '{}' for the Redis URL is not set",
+                config.url_env
+            ))
+        })?;
+        let client = redis::Client::open(url)
+            .map_err(|e| AppError::Configuration(format!("Invalid Redis URL: {}", e)))?;
+        let conn = client
+            .get_connection_manager()
+            .await
+            .map_err(redis_err("connect to Redis"))?;
+        Ok(Self {
+            client,
+            conn,
+            prefix: config.key_prefix.clone(),
+            instance_id: uuid::Uuid::new_v4().to_string(),
+        })
+    }
+
+    fn key(&self, kind: &str, id: &str) -> String {
+        format!("{}:{}:{}", self.prefix, kind, id)
+    }
+
+    // ============= Rate limits =============
+
+    /// Take a token from the shared bucket `bucket`
+    ///
+    /// Returns how long until a token is available if the bucket is empty.
+    pub async fn take_token(
+        &self,
+        bucket: &str,
+        capacity: u32,
+        requests_per_minute: u32,
+    ) -> Result<std::result::Result<(), Duration>> {
+        let wait_ms: u64 = Script::new(TAKE_TOKEN)
+            .key(self.key("ratelimit", bucket))
+            .arg(capacity)
+            .arg(requests_per_minute as f64 / 60.0)
+            .invoke_async(&mut self.conn.clone())
+            .await
+            .map_err(redis_err("check rate limit"))?;
+        Ok(match wait_ms {
+            0 => Ok(()),
+            ms => Err(Duration::from_millis(ms)),
+        })
+    }
+
+    // ============= Configuration events =============
+
+    /// Announce this instance's configuration reloads, and reload when
+    /// another instance announces one
+    ///
+    /// Instances sharing their config files all reload at once; the first
+    /// to take the `config-reload` lock of `pool`'s database announces it,
+    /// and the others stay quiet for [`RELOAD_ANNOUNCE_WINDOW`].
+    pub fn spawn_config_sync(&self, config_manager: Arc<AresConfigManager>, pool: PgPool) {
+        let channel = self.key("events", "config");
+
+        let mut conn = self.conn.clone();
+        let mut reloads = config_manager.subscribe_reloads();
+        let (instance_id, publish_to) = (self.instance_id.clone(), channel.clone());
+        tokio::spawn(async move {
+            use tokio::sync::broadcast::error::RecvError;
+            loop {
+                match reloads.recv().await {
+                    Ok(()) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                }
+                let lock = match locks::try_lock(&pool, "config-reload").await {
+                    Ok(Some(lock)) => Some(lock),
+                    // Another instance is announcing the same reload
+                    Ok(None) => continue,
+                    Err(e) => {
+                        tracing::warn!("{}", e);
+                        None
+                    }
+                };
+                if let Err(e) = conn.publish::<_, _, ()>(&publish_to, &instance_id).await {
+                    tracing::warn!("Failed to announce configuration reload: {}", e);
+                }
+                if let Some(lock) = lock {
+                    tokio::time::sleep(RELOAD_ANNOUNCE_WINDOW).await;
+                    if let Err(e) = lock.release().await {
+                        tracing::warn!("{}", e);
+                    }
+                }
+            }
+        });
+
+        let client = self.client.clone();
+        let instance_id = self.instance_id.clone();
+        tokio::spawn(async move {
+            loop {
+                match follow_reloads(&client, &channel, &instance_id, &config_manager).await {
+                    Ok(()) => tracing::warn!("Configuration events disconnected"),
+                    Err(e) => tracing::warn!("Failed to follow configuration events: {}", e),
+                }
+                tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            }
+        });
+    }
+
+    // ============= LLM response cache =============
+
+    /// The cached response for `key`, if any
+    async fn cached_response(&self, key: &str) -> Result<Option<String>> {
+        self.conn
+            .clone()
+            .get(self.key("llm", key))
+            .await
+            .map_err(redis_err("read cached response"))
+    }
+
+    /// Cache a response for `key` for `ttl_secs`
+    async fn cache_response(&self, key: &str, value: &str, ttl_secs: u64) -> Result<()> {
+        self.conn
+            .clone()
+            .set_ex::<_, _, ()>(self.key("llm", key), value, ttl_secs)
+            .await
+            .map_err(redis_err("cache response"))
+    }
+
+    // ============= Sessions =============
+    //
+    // A session is a hash under `session:{id}`, found from its refresh
+    // token through `session_token:{hash}` and from its user through the
+    // set `user_sessions:{user_id}`. All three expire with the session;
+    // sessions share a lifetime, so a user's set lives as long as their
+    // newest session.
+
+    /// Start a session (see [`crate::db::sessions::SessionStore::create`])
+    pub async fn create_session(
+        &self,
+        id: &str,
+        user_id: &str,
+        token_hash: &str,
+        expires_at: i64,
+        client: &SessionClient,
+        now: i64,
+    ) -> Result<()> {
+        let session = self.key("session", id);
+        let mut fields = vec![
+            ("user_id", user_id.to_string()),
+            ("token_hash", token_hash.to_string()),
+            ("created_at", now.to_string()),
+            ("expires_at", expires_at.to_string()),
+        ];
+        if let Some(user_agent) = client.user_agent() {
+            fields.push(("user_agent", user_agent));
+        }
+        if let Some(ip_address) = &client.ip_address {
+            fields.push(("ip_address", ip_address.clone()));
+        }
+        redis::pipe()
+            .atomic()
+            .hset_multiple(&session, &fields)
+            .ignore()
+            .expire_at(&session, expires_at)
+            .ignore()
+            .cmd("SET")
+            .arg(self.key("session_token", token_hash))
+            .arg(id)
+            .arg("EXAT")
+            .arg(expires_at)
+            .ignore()
+            .sadd(self.key("user_sessions", user_id), id)
+            .ignore()
+            .expire_at(self.key("user_sessions", user_id), expires_at)
+            .ignore()
+            .query_async::<()>(&mut self.conn.clone())
+            .await
+            .map_err(redis_err("create session"))
+    }
+
+    /// The user of the live session holding `token_hash`, if any
+    pub async fn session_user(&self, token_hash: &str) -> Result<Option<String>> {
+        let mut conn = self.conn.clone();
+        let id: Option<String> = conn
+            .get(self.key("session_token", token_hash))
+            .await
+            .map_err(redis_err("validate session"))?;
+        let Some(id) = id else {
+            return Ok(None);
+        };
+        conn.hget(self.key("session", &id), "user_id")
+            .await
+            .map_err(redis_err("validate session"))
+    }
+
+    /// Replace a live session's refresh token (see
+    /// [`crate::db::sessions::rotate_session`])
+    pub async fn rotate_session(
+        &self,
+        token_hash: &str,
+        new_token_hash: &str,
+        expires_at: i64,
+        client: &SessionClient,
+        now: i64,
+    ) -> Result<bool> {
+        let mut conn = self.conn.clone();
+        let token = self.key("session_token", token_hash);
+        let id: Option<String> = conn
+            .get(&token)
+            .await
+            .map_err(redis_err("rotate session"))?;
+        let Some(id) = id else {
+            return Ok(false);
+        };
+        let session = self.key("session", &id);
+        let user_id: Option<String> = conn
+            .hget(&session, "user_id")
+            .await
+            .map_err(redis_err("rotate session"))?;
+        let Some(user_id) = user_id else {
+            return Ok(false);
+        };
+        let rotated: i64 = Script::new(ROTATE_SESSION)
+            .key(&token)
+            .key(&session)
+            .key(self.key("session_token", new_token_hash))
+            .key(self.key("user_sessions", &user_id))
+            .arg(&id)
+            .arg(new_token_hash)
+            .arg(expires_at)
+            .arg(now)
+            .arg(client.user_agent().unwrap_or_default())
+            .arg(client.ip_address.as_deref().unwrap_or_default())
+            .invoke_async(&mut conn)
+            .await
+            .map_err(redis_err("rotate session"))?;
+        Ok(rotated == 1)
+    }
+
+    /// End the session holding `token_hash`, if there is one
+    pub async fn end_session(&self, token_hash: &str) -> Result<()> {
+        let id: Option<String> = self
+            .conn
+            .clone()
+            .get(self.key("session_token", token_hash))
+            .await
+            .map_err(redis_err("delete session"))?;
+        if let Some(id) = id {
+            self.delete_session(&id).await?;
+        }
+        Ok(())
+    }
+
+    /// List a user's live sessions, most recently used first
+    pub async fn list_sessions(&self, user_id: &str) -> Result<Vec<Session>> {
+        let mut conn = self.conn.clone();
+        let user_sessions = self.key("user_sessions", user_id);
+        let ids: Vec<String> = conn
+            .smembers(&user_sessions)
+            .await
+            .map_err(redis_err("list sessions"))?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut pipe = redis::pipe();
+        for id in &ids {
+            pipe.hgetall(self.key("session", id));
+        }
+        let fields: Vec<HashMap<String, String>> = pipe
+            .query_async(&mut conn)
+            .await
+            .map_err(redis_err("list sessions"))?;
+
+        let mut sessions = Vec::new();
+        let mut expired = Vec::new();
+        for (id, fields) in ids.into_iter().zip(fields) {
+            let number = |field: &str| fields.get(field).and_then(|value| value.parse().ok());
+            match (number("created_at"), number("expires_at")) {
+                (Some(created_at), Some(expires_at)) => sessions.push(Session {
+                    id,
+                    created_at,
+                    last_used_at: number("last_used_at"),
+                    expires_at,
+                    user_agent: fields.get("user_agent").cloned(),
+                    ip_address: fields.get("ip_address").cloned(),
+                }),
+                _ => expired.push(id),
+            }
+        }
+        if !expired.is_empty() {
+            conn.srem::<_, _, ()>(&user_sessions, &expired)
+                .await
+                .map_err(redis_err("list sessions"))?;
+        }
+        sessions.sort_by(|a, b| {
+            b.last_used_at
+                .unwrap_or(b.created_at)
+                .cmp(&a.last_used_at.unwrap_or(a.created_at))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        Ok(sessions)
+    }
+
+    /// Revoke one of a user's sessions, returning whether it existed
+    pub async fn revoke_session(&self, id: &str, user_id: &str) -> Result<bool> {
+        let owner: Option<String> = self
+            .conn
+            .clone()
+            .hget(self.key("session", id), "user_id")
+            .await
+            .map_err(redis_err("revoke session"))?;
+        if owner.as_deref() != Some(user_id) {
+            return Ok(false);
+        }
+        self.delete_session(id).await?;
+        Ok(true)
+    }
+
+    async fn delete_session(&self, id: &str) -> Result<()> {
+        let mut conn = self.conn.clone();
+        let session = self.key("session", id);
+        let (user_id, token_hash): (Option<String>, Option<String>) = redis::cmd("HMGET")
+            .arg(&session)
+            .arg("user_id")
+            .arg("token_hash")
+            .query_async(&mut conn)
+            .await
+            .map_err(redis_err("delete session"))?;
+        let mut pipe = redis::pipe();
+        pipe.atomic().del(&session).ignore();
+        if let Some(token_hash) = token_hash {
+            pipe.del(self.key("session_token", &token_hash)).ignore();
+        }
+        if let Some(user_id) = user_id {
+            pipe.srem(self.key("user_sessions", &user_id), id).ignore();
+        }
+        pipe.query_async::<()>(&mut conn)
+            .await
+            .map_err(redis_err("delete session"))
+    }
+}
+
+/// Reload the configuration whenever another instance announces a reload
+/// on `channel`, until the subscription drops
+async fn follow_reloads(
+    client: &redis::Client,
+    channel: &str,
+    instance_id: &str,
+    config_manager: &AresConfigManager,
+) -> redis::RedisResult<()> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.subscribe(channel).await?;
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        let from: String = message.get_payload()?;
+        if from == instance_id {
+            continue;
+        }
+        tracing::info!("Another instance reloaded its configuration");
+        if let Err(e) = config_manager.reload() {
+            tracing::warn!("Failed to reload config: {}. Keeping previous config.", e);
+        }
+    }
+    Ok(())
+}
+
+/// A cached LLM response
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    content: String,
+    finish_reason: String,
+}
+
+/// Middleware answering prompts it has seen within `ttl_secs` with the
+/// response they got.
+///
+/// Only calls without tools are cached, and only responses that finished
+/// normally, streamed or not. Hits carry no token usage, so they cost nothing
+/// against `[budgets]`. The key covers the model and every prompt message;
+/// sampling parameters are not part of it.
+pub struct ResponseCache {
+    store: RedisStore,
+    ttl_secs: u64,
+}
+
+impl ResponseCache {
+    /// Cache responses in `store` for `ttl_secs`
+    pub fn new(store: RedisStore, ttl_secs: u64) -> Self {
+        Self { store, ttl_secs }
+    }
+
+    /// Key of a request, if its response may be cached
+    fn key(request: &LLMRequest) -> Option<String> {
+        if !request.tools.is_empty() {
+            return None;
+        }
+        let messages = serde_json::to_vec(&request.messages).ok()?;
+        let mut hasher = Sha256::new();
+        hasher.update(request.model.as_bytes());
+        hasher.update([0]);
+        hasher.update(&messages);
+        Some(hex::encode(hasher.finalize()))
+    }
+}
+
+#[async_trait]
+impl LLMMiddleware for ResponseCache {
+    fn name(&self) -> &str {
+        "redis_response_cache"
+    }
+
+    async fn before_request(&self, request: &mut LLMRequest) -> Result<Option<LLMResponse>> {
+        let Some(key) = Self::key(request) else {
+            return Ok(None);
+        };
+        let cached = match self.store.cached_response(&key).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                tracing::warn!("{}", e);
+                return Ok(None);
+            }
+        };
+        Ok(cached
+            .and_then(|json| serde_json::from_str::<CachedResponse>(&json).ok())
+            .map(|cached| LLMResponse {
+                content: cached.content,
+                tool_calls: Vec::new(),
+                finish_reason: cached.finish_reason,
+                usage: None,
+            }))
+    }
+
+    async fn after_response(&self, request: &LLMRequest, response: &mut LLMResponse) -> Result<()> {
+        if !response.tool_calls.is_empty()
+            || response.finish_reason != "stop"
+            || response.content.is_empty()
+        {
+            return Ok(());
+        }
+        let Some(key) = Self::key(request) else {
+            return Ok(());
+        };
+        let cached = CachedResponse {
+            content: response.content.clone(),
+            finish_reason: response.finish_reason.clone(),
+        };
+        let json = serde_json::to_string(&cached)
+            .map_err(|e| AppError::Internal(format!("Failed to encode response: {}", e)))?;
+        if let Err(e) = self.store.cache_response(&key, &json, self.ttl_secs).await {
+            tracing::warn!("{}", e);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::coordinator::ConversationMessage;
+
+    #[test]
+    fn test_response_cache_key() {
+        let request = |model: &str, prompt: &str| LLMRequest {
+            model: model.to_string(),
+            messages: vec![
+                ConversationMessage::system("Be brief."),
+                ConversationMessage::user(prompt),
+            ],
+            tools: Vec::new(),
+            stream: false,
+        };
+        let key = ResponseCache::key(&request("llama3", "Hi")).unwrap();
+        assert_eq!(key.len(), 64);
+
+        // Streaming doesn't change the answer
+        let mut streamed = request("llama3", "Hi");
+        streamed.stream = true;
+        assert_eq!(ResponseCache::key(&streamed), Some(key.clone()));
+        assert_ne!(
+            ResponseCache::key(&request("llama3", "Hello")),
+            Some(key.clone())
+        );
+        assert_ne!(ResponseCache::key(&request("mistral", "Hi")), Some(key));
+
+        // Calls offering tools aren't cached
+        let mut with_tools = request("llama3", "Hi");
+        with_tools.tools.push(crate::types::ToolDefinition {
+            name: "calculator".to_string(),
+            description: "Evaluate arithmetic".to_string(),
+            parameters: serde_json::json!({ "type": "object" }),
+        });
+        assert_eq!(ResponseCache::key(&with_tools), None);
+    }
+}
@@ -0,0 +1,104 @@
+//! Storage for users' roles.
+//!
+//! A user's [`Role`] is embedded in the JWTs issued to them, and enforced by
+//! [`crate::auth::middleware::require_role`]. Users without a stored role
+//! are members.
+
+use crate::types::{AppError, Result, Role};
+use serde::Serialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+/// A user and their role.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UserRole {
+    /// User ID
+    pub id: String,
+    /// User's email address
+    pub email: String,
+    /// User's display name
+    pub name: String,
+    /// What the user may do
+    pub role: Role,
+}
+
+#[derive(sqlx::FromRow)]
+struct UserRoleRow {
+    id: String,
+    email: String,
+    name: String,
+    role: Option<String>,
+}
+
+fn parse_role(stored: Option<&str>) -> Role {
+    stored.and_then(Role::parse).unwrap_or_default()
+}
+
+/// A user's role; members unless assigned another.
+pub async fn get_role(pool: &PgPool, user_id: &str) -> Result<Role> {
+    let stored: Option<String> =
+        sqlx::query_scalar("SELECT role FROM user_roles WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to get user role: {}", e)))?;
+    Ok(parse_role(stored.as_deref()))
+}
+
+/// Assign a role to a user.
+pub async fn set_role(pool: &PgPool, user_id: &str, role: Role, now: i64) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO user_roles (user_id, role, updated_at) VALUES ($1, $2, $3)
+         ON CONFLICT (user_id) DO UPDATE SET role = $2, updated_at = $3",
+    )
+    .bind(user_id)
+    .bind(role.as_str())
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to set user role: {}", e)))?;
+    Ok(())
+}
+
+/// List users with their roles, by email.
+pub async fn list_users(pool: &PgPool) -> Result<Vec<UserRole>> {
+    let rows = sqlx::query_as::<_, UserRoleRow>(
+        "SELECT u.id, u.email, u.name, r.role FROM users u
+         LEFT JOIN user_roles r ON r.user_id = u.id ORDER BY u.email",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list users: {}", e)))?;
+    Ok(rows
+        .into_iter()
+        .map(|row| UserRole {
+            role: parse_role(row.role.as_deref()),
+            id: row.id,
+            email: row.email,
+            name: row.name,
+        })
+        .collect())
+}
+
+/// Number of users assigned the admin role.
+pub async fn count_admins(pool: &PgPool) -> Result<i64> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM user_roles WHERE role = $1")
+        .bind(Role::Admin.as_str())
+        .fetch_one(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to count admins: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_users_without_a_known_role_are_members() {
+        assert_eq!(parse_role(None), Role::Member);
+        assert_eq!(parse_role(Some("superuser")), Role::Member);
+        assert_eq!(parse_role(Some("read_only")), Role::ReadOnly);
+        assert!(Role::Admin.allows(Role::Member));
+        assert!(!Role::ReadOnly.allows(Role::Member));
+    }
+}
@@ -0,0 +1,193 @@
+//! Storage for agent schedules users create through `/api/schedules`.
+//!
+//! Schedules from `[schedules]` in `ares.toml` are not stored; the
+//! scheduler keeps their next run time in memory.
+
+use crate::types::{AppError, Result};
+use serde::Serialize;
+use sqlx::{PgExecutor, PgPool};
+use utoipa::ToSchema;
+
+const COLUMNS: &str = "id, user_id, name, agent, prompt, cron, enabled, next_run_at, last_run_at, \
+                       last_status, last_conversation_id, last_error, created_at, updated_at";
+
+/// An agent run with a fixed prompt on a cron schedule.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct AgentSchedule {
+    /// Schedule ID
+    pub id: String,
+    /// Owner, who also owns the conversations the runs are stored in
+    pub user_id: String,
+    /// Name, used in the titles of the run conversations
+    pub name: String,
+    /// Agent that runs
+    pub agent: String,
+    /// Message the agent is given on every run
+    pub prompt: String,
+    /// Cron expression in UTC
+    pub cron: String,
+    /// Whether the schedule runs
+    pub enabled: bool,
+    /// Next run (Unix timestamp); `None` while disabled
+    pub next_run_at: Option<i64>,
+    /// Start of the latest run (Unix timestamp)
+    pub last_run_at: Option<i64>,
+    /// Outcome of the latest run: "completed" or "failed"
+    pub last_status: Option<String>,
+    /// Conversation holding the latest completed run
+    pub last_conversation_id: Option<String>,
+    /// Why the latest run failed
+    pub last_error: Option<String>,
+    /// Creation time (Unix timestamp)
+    pub created_at: i64,
+    /// Last update time (Unix timestamp)
+    pub updated_at: i64,
+}
+
+/// Store a new schedule.
+pub async fn insert_schedule(pool: &PgPool, schedule: &AgentSchedule) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO agent_schedules (id, user_id, name, agent, prompt, cron, enabled, next_run_at, created_at, updated_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $9)",
+    )
+    .bind(&schedule.id)
+    .bind(&schedule.user_id)
+    .bind(&schedule.name)
+    .bind(&schedule.agent)
+    .bind(&schedule.prompt)
+    .bind(&schedule.cron)
+    .bind(schedule.enabled)
+    .bind(schedule.next_run_at)
+    .bind(schedule.created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to create schedule: {}", e)))?;
+    Ok(())
+}
+
+/// Update a schedule's definition and next run.
+pub async fn update_schedule(pool: &PgPool, schedule: &AgentSchedule) -> Result<()> {
+    sqlx::query(
+        "UPDATE agent_schedules SET name = $1, agent = $2, prompt = $3, cron = $4, enabled = $5, next_run_at = $6, updated_at = $7
+         WHERE id = $8 AND user_id = $9",
+    )
+    .bind(&schedule.name)
+    .bind(&schedule.agent)
+    .bind(&schedule.prompt)
+    .bind(&schedule.cron)
+    .bind(schedule.enabled)
+    .bind(schedule.next_run_at)
+    .bind(schedule.updated_at)
+    .bind(&schedule.id)
+    .bind(&schedule.user_id)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to update schedule: {}", e)))?;
+    Ok(())
+}
+
+/// List a user's schedules, oldest first.
+pub async fn list_schedules(pool: &PgPool, user_id: &str) -> Result<Vec<AgentSchedule>> {
+    sqlx::query_as::<_, AgentSchedule>(&format!(
+        "SELECT {} FROM agent_schedules WHERE user_id = $1 ORDER BY created_at ASC",
+        COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list schedules: {}", e)))
+}
+
+/// Number of schedules a user has.
+pub async fn count_schedules(pool: &PgPool, user_id: &str) -> Result<i64> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM agent_schedules WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to count schedules: {}", e)))
+}
+
+/// Get one of a user's schedules.
+pub async fn get_schedule(pool: &PgPool, id: &str, user_id: &str) -> Result<Option<AgentSchedule>> {
+    sqlx::query_as::<_, AgentSchedule>(&format!(
+        "SELECT {} FROM agent_schedules WHERE id = $1 AND user_id = $2",
+        COLUMNS
+    ))
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get schedule: {}", e)))
+}
+
+/// Delete one of a user's schedules, returning whether it existed.
+pub async fn delete_schedule(pool: &PgPool, id: &str, user_id: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM agent_schedules WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to delete schedule: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Enabled schedules whose next run is at or before `now`.
+pub async fn due_schedules(pool: &PgPool, now: i64, limit: i64) -> Result<Vec<AgentSchedule>> {
+    sqlx::query_as::<_, AgentSchedule>(&format!(
+        "SELECT {} FROM agent_schedules WHERE enabled AND next_run_at <= $1 ORDER BY next_run_at ASC LIMIT $2",
+        COLUMNS
+    ))
+    .bind(now)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list due schedules: {}", e)))
+}
+
+/// Move a due schedule's next run from `due` to `next`.
+///
+/// Returns false if another server claimed the run first.
+pub async fn claim_run<'e>(
+    executor: impl PgExecutor<'e>,
+    id: &str,
+    due: i64,
+    next: Option<i64>,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE agent_schedules SET next_run_at = $1 WHERE id = $2 AND enabled AND next_run_at = $3",
+    )
+    .bind(next)
+    .bind(id)
+    .bind(due)
+    .execute(executor)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to claim schedule run: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Record the outcome of a run started at `ran_at`.
+pub async fn record_run(
+    pool: &PgPool,
+    id: &str,
+    ran_at: i64,
+    outcome: &Result<String>,
+) -> Result<()> {
+    let (status, conversation_id, error) = match outcome {
+        Ok(conversation_id) => ("completed", Some(conversation_id.as_str()), None),
+        Err(e) => ("failed", None, Some(e.to_string())),
+    };
+    sqlx::query(
+        "UPDATE agent_schedules SET last_run_at = $1, last_status = $2,
+             last_conversation_id = COALESCE($3, last_conversation_id), last_error = $4
+         WHERE id = $5",
+    )
+    .bind(ran_at)
+    .bind(status)
+    .bind(conversation_id)
+    .bind(error)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to record schedule run: {}", e)))?;
+    Ok(())
+}
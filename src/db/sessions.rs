@@ -0,0 +1,361 @@
+//! Devices signed in to user accounts.
+//!
+//! Every login starts a session holding the hash of its refresh token (see
+//! [`crate::api::handlers::auth`]), along with the device it came from. A
+//! refresh rotates the token within the same session, so a session lasts
+//! from login to logout, revocation or expiry, and revoking it signs the
+//! device out once its access token expires.
+//!
+//! Sessions live in the `sessions` table, or in Redis with `[redis]
+//! sessions = true` (see [`SessionStore`]).
+
+use crate::types::{AppError, Result};
+use serde::Serialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+/// Characters of a user agent that are kept
+const MAX_USER_AGENT_CHARS: usize = 512;
+
+/// A signed-in device, without its refresh token.
+#[derive(Debug, Clone, Serialize, ToSchema, sqlx::FromRow)]
+pub struct Session {
+    /// Session ID
+    pub id: String,
+    /// When the device logged in (Unix timestamp)
+    pub created_at: i64,
+    /// When the device last refreshed its tokens (Unix timestamp)
+    pub last_used_at: Option<i64>,
+    /// When the session ends unless refreshed (Unix timestamp)
+    pub expires_at: i64,
+    /// User agent of the device's last login or refresh
+    pub user_agent: Option<String>,
+    /// IP address of the device's last login or refresh
+    pub ip_address: Option<String>,
+}
+
+/// The device a login or refresh comes from.
+#[derive(Debug, Clone, Default)]
+pub struct SessionClient {
+    /// `User-Agent` header
+    pub user_agent: Option<String>,
+    /// Client IP address
+    pub ip_address: Option<String>,
+}
+
+impl SessionClient {
+    pub(crate) fn user_agent(&self) -> Option<String> {
+        self.user_agent
+            .as_deref()
+            .map(|agent| agent.chars().take(MAX_USER_AGENT_CHARS).collect())
+    }
+}
+
+/// Where sessions are kept.
+///
+/// Cheap to clone.
+#[derive(Clone)]
+pub enum SessionStore {
+    /// The `sessions` table
+    Postgres(PgPool),
+    /// Redis, shared by all instances
+    #[cfg(feature = "redis")]
+    Redis(Box<crate::db::redis::RedisStore>),
+}
+
+impl SessionStore {
+    /// Start a session for a user's device with the hash of its refresh token.
+    pub async fn create(
+        &self,
+        id: &str,
+        user_id: &str,
+        token_hash: &str,
+        expires_at: i64,
+        client: &SessionClient,
+        now: i64,
+    ) -> Result<()> {
+        match self {
+            Self::Postgres(pool) => {
+                create_session(pool, id, user_id, token_hash, expires_at, client, now).await
+            }
+            #[cfg(feature = "redis")]
+            Self::Redis(redis) => {
+                redis
+                    .create_session(id, user_id, token_hash, expires_at, client, now)
+                    .await
+            }
+        }
+    }
+
+    /// The user of the live session holding `token_hash`, if any
+    pub async fn user_of(&self, token_hash: &str, now: i64) -> Result<Option<String>> {
+        match self {
+            Self::Postgres(pool) => session_user(pool, token_hash, now).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(redis) => redis.session_user(token_hash).await,
+        }
+    }
+
+    /// Replace a live session's refresh token (see [`rotate_session`])
+    pub async fn rotate(
+        &self,
+        token_hash: &str,
+        new_token_hash: &str,
+        expires_at: i64,
+        client: &SessionClient,
+        now: i64,
+    ) -> Result<bool> {
+        match self {
+            Self::Postgres(pool) => {
+                rotate_session(pool, token_hash, new_token_hash, expires_at, client, now).await
+            }
+            #[cfg(feature = "redis")]
+            Self::Redis(redis) => {
+                redis
+                    .rotate_session(token_hash, new_token_hash, expires_at, client, now)
+                    .await
+            }
+        }
+    }
+
+    /// End the session holding `token_hash`, if there is one
+    pub async fn end(&self, token_hash: &str) -> Result<()> {
+        match self {
+            Self::Postgres(pool) => end_session(pool, token_hash).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(redis) => redis.end_session(token_hash).await,
+        }
+    }
+
+    /// List a user's live sessions, most recently used first.
+    pub async fn list(&self, user_id: &str, now: i64) -> Result<Vec<Session>> {
+        match self {
+            Self::Postgres(pool) => list_sessions(pool, user_id, now).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(redis) => redis.list_sessions(user_id).await,
+        }
+    }
+
+    /// Revoke one of a user's sessions, returning whether it existed.
+    pub async fn revoke(&self, id: &str, user_id: &str) -> Result<bool> {
+        match self {
+            Self::Postgres(pool) => revoke_session(pool, id, user_id).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(redis) => redis.revoke_session(id, user_id).await,
+        }
+    }
+}
+
+/// Start a session for a user's device with the hash of its refresh token.
+pub async fn create_session(
+    pool: &PgPool,
+    id: &str,
+    user_id: &str,
+    token_hash: &str,
+    expires_at: i64,
+    client: &SessionClient,
+    now: i64,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO sessions (id, user_id, token_hash, expires_at, created_at, user_agent, ip_address)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(token_hash)
+    .bind(expires_at)
+    .bind(now)
+    .bind(client.user_agent())
+    .bind(&client.ip_address)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to create session: {}", e)))?;
+    Ok(())
+}
+
+/// The user of the live session holding `token_hash`, if any
+pub async fn session_user(pool: &PgPool, token_hash: &str, now: i64) -> Result<Option<String>> {
+    sqlx::query_scalar("SELECT user_id FROM sessions WHERE token_hash = $1 AND expires_at > $2")
+        .bind(token_hash)
+        .bind(now)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to validate session: {}", e)))
+}
+
+/// End the session holding `token_hash`, if there is one
+pub async fn end_session(pool: &PgPool, token_hash: &str) -> Result<()> {
+    sqlx::query("DELETE FROM sessions WHERE token_hash = $1")
+        .bind(token_hash)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to delete session: {}", e)))?;
+    Ok(())
+}
+
+/// Replace a live session's refresh token, recording the device that
+/// refreshed it; false if no live session holds `token_hash`, as when it
+/// was rotated or revoked already.
+pub async fn rotate_session(
+    pool: &PgPool,
+    token_hash: &str,
+    new_token_hash: &str,
+    expires_at: i64,
+    client: &SessionClient,
+    now: i64,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE sessions SET token_hash = $2, expires_at = $3, last_used_at = $4,
+         user_agent = COALESCE($5, user_agent), ip_address = COALESCE($6, ip_address)
+         WHERE token_hash = $1 AND expires_at > $4",
+    )
+    .bind(token_hash)
+    .bind(new_token_hash)
+    .bind(expires_at)
+    .bind(now)
+    .bind(client.user_agent())
+    .bind(&client.ip_address)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to rotate session: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// List a user's live sessions, most recently used first.
+pub async fn list_sessions(pool: &PgPool, user_id: &str, now: i64) -> Result<Vec<Session>> {
+    sqlx::query_as::<_, Session>(
+        "SELECT id, created_at, last_used_at, expires_at, user_agent, ip_address FROM sessions
+         WHERE user_id = $1 AND expires_at > $2
+         ORDER BY COALESCE(last_used_at, created_at) DESC, id",
+    )
+    .bind(user_id)
+    .bind(now)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list sessions: {}", e)))
+}
+
+/// Revoke one of a user's sessions, returning whether it existed.
+pub async fn revoke_session(pool: &PgPool, id: &str, user_id: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM sessions WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to revoke session: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+// These tests need PostgreSQL with the ARES schema at `DATABASE_URL`; run
+// them with `cargo test -- --ignored`.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PostgresClient;
+
+    const DAY: i64 = 86_400;
+
+    /// A session store and a new user with a session holding `token_hash`,
+    /// expiring in a day
+    async fn store_with_session(token_hash: &str, now: i64) -> (SessionStore, String, String) {
+        let db = PostgresClient::new_memory().await.unwrap();
+        let user_id = uuid::Uuid::new_v4().to_string();
+        db.create_user(
+            &user_id,
+            &format!("{}@example.com", user_id),
+            "hash",
+            "Test User",
+        )
+        .await
+        .unwrap();
+        let store = SessionStore::Postgres(db.pool);
+        let session_id = uuid::Uuid::new_v4().to_string();
+        store
+            .create(
+                &session_id,
+                &user_id,
+                token_hash,
+                now + DAY,
+                &SessionClient::default(),
+                now,
+            )
+            .await
+            .unwrap();
+        (store, user_id, session_id)
+    }
+
+    fn token_hash() -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_refresh_after_revocation_is_rejected() {
+        let now = 1_700_000_000;
+        let token = token_hash();
+        let (store, user_id, session_id) = store_with_session(&token, now).await;
+        assert_eq!(
+            store.user_of(&token, now).await.unwrap(),
+            Some(user_id.clone())
+        );
+
+        assert!(store.revoke(&session_id, &user_id).await.unwrap());
+        assert_eq!(store.user_of(&token, now).await.unwrap(), None);
+        let client = SessionClient::default();
+        assert!(!store
+            .rotate(&token, &token_hash(), now + DAY, &client, now)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_refresh_after_expiry_is_rejected() {
+        let now = 1_700_000_000;
+        let token = token_hash();
+        let (store, user_id, _) = store_with_session(&token, now).await;
+
+        let later = now + DAY;
+        assert_eq!(store.user_of(&token, later).await.unwrap(), None);
+        let client = SessionClient::default();
+        assert!(!store
+            .rotate(&token, &token_hash(), later + DAY, &client, later)
+            .await
+            .unwrap());
+        assert!(store.list(&user_id, later).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_rotated_refresh_token_cannot_be_reused() {
+        let now = 1_700_000_000;
+        let token = token_hash();
+        let (store, user_id, session_id) = store_with_session(&token, now).await;
+        let client = SessionClient {
+            user_agent: Some("curl/8.0".to_string()),
+            ip_address: Some("203.0.113.7".to_string()),
+        };
+
+        let rotated = token_hash();
+        assert!(store
+            .rotate(&token, &rotated, now + 2 * DAY, &client, now + 60)
+            .await
+            .unwrap());
+        // The old token is spent; the new one keeps the session
+        assert_eq!(store.user_of(&token, now + 60).await.unwrap(), None);
+        assert!(!store
+            .rotate(&token, &token_hash(), now + 2 * DAY, &client, now + 120)
+            .await
+            .unwrap());
+        assert_eq!(
+            store.user_of(&rotated, now + 120).await.unwrap(),
+            Some(user_id.clone())
+        );
+
+        let sessions = store.list(&user_id, now + 120).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, session_id);
+        assert_eq!(sessions[0].last_used_at, Some(now + 60));
+        assert_eq!(sessions[0].user_agent.as_deref(), Some("curl/8.0"));
+    }
+}
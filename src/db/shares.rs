@@ -0,0 +1,125 @@
+//! Storage for conversation share links.
+//!
+//! A share link lets anyone holding its token read a conversation (see
+//! [`crate::api::handlers::shares`]). Only a SHA-256 hash of each token is
+//! stored.
+
+use crate::types::{AppError, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+const COLUMNS: &str = "id, conversation_id, user_id, views, created_at, expires_at, revoked_at";
+
+/// A share link, without its token.
+#[derive(Debug, Clone, Serialize, ToSchema, sqlx::FromRow)]
+pub struct ConversationShare {
+    /// Share link ID
+    pub id: String,
+    /// Conversation shared
+    pub conversation_id: String,
+    /// User who shared it
+    #[serde(skip)]
+    pub user_id: String,
+    /// Times the link was opened
+    pub views: i64,
+    /// When the link was created (Unix timestamp)
+    pub created_at: i64,
+    /// When the link stops working (Unix timestamp); `None` for never
+    pub expires_at: Option<i64>,
+    /// When the link was revoked (Unix timestamp)
+    pub revoked_at: Option<i64>,
+}
+
+/// Generate a new token, returning it with its hash
+pub fn generate_token() -> (String, String) {
+    let bytes: Vec<u8> = (0..32).map(|_| rand::random::<u8>()).collect();
+    let token = hex::encode(bytes);
+    (hash_token(&token), token)
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Store a new share link with the hash of its token.
+pub async fn insert_share(
+    pool: &PgPool,
+    share: &ConversationShare,
+    token_hash: &str,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO conversation_shares (id, conversation_id, user_id, token_hash, created_at, expires_at)
+         VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(&share.id)
+    .bind(&share.conversation_id)
+    .bind(&share.user_id)
+    .bind(token_hash)
+    .bind(share.created_at)
+    .bind(share.expires_at)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to store share link: {}", e)))?;
+    Ok(())
+}
+
+/// List a conversation's share links that haven't been revoked, newest first.
+pub async fn list_shares(pool: &PgPool, conversation_id: &str) -> Result<Vec<ConversationShare>> {
+    sqlx::query_as::<_, ConversationShare>(&format!(
+        "SELECT {} FROM conversation_shares WHERE conversation_id = $1 AND revoked_at IS NULL
+         ORDER BY created_at DESC, id",
+        COLUMNS
+    ))
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list share links: {}", e)))
+}
+
+/// Revoke one of a conversation's share links, returning whether it was live.
+pub async fn revoke_share(
+    pool: &PgPool,
+    id: &str,
+    conversation_id: &str,
+    now: i64,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE conversation_shares SET revoked_at = $3
+         WHERE id = $1 AND conversation_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(id)
+    .bind(conversation_id)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to revoke share link: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// The live share link a token opens, counting the view; `None` if the
+/// token is unknown, revoked or expired.
+pub async fn open_share(pool: &PgPool, token: &str, now: i64) -> Result<Option<ConversationShare>> {
+    sqlx::query_as::<_, ConversationShare>(&format!(
+        "UPDATE conversation_shares SET views = views + 1
+         WHERE token_hash = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > $2)
+         RETURNING {}",
+        COLUMNS
+    ))
+    .bind(hash_token(token))
+    .bind(now)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to open share link: {}", e)))
+}
+
+/// Delete every share link of a conversation.
+pub async fn delete_conversation_shares(pool: &PgPool, conversation_id: &str) -> Result<()> {
+    sqlx::query("DELETE FROM conversation_shares WHERE conversation_id = $1")
+        .bind(conversation_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to delete share links: {}", e)))?;
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::{BudgetLimit, BudgetsConfig};
+use chrono::{Datelike, TimeZone, Utc};
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+
+/// Spend accumulated over the current UTC day and month.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct SpendTotals {
+    /// Spend since the start of the UTC day.
+    pub daily_usd: f64,
+    /// Spend since the start of the UTC month.
+    pub monthly_usd: f64,
+}
+
+/// Month-to-date spend of a single agent.
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentSpend {
+    /// Agent name.
+    pub agent_name: String,
+    /// Estimated input tokens.
+    pub input_tokens: i64,
+    /// Estimated output tokens.
+    pub output_tokens: i64,
+    /// Estimated cost in USD.
+    pub cost_usd: f64,
+}
+
+/// Unix timestamp of the start of the UTC day containing `ts`.
+pub fn day_start(ts: i64) -> i64 {
+    ts - ts.rem_euclid(86400)
+}
+
+/// Unix timestamp of the start of the UTC month containing `ts`.
+pub fn month_start(ts: i64) -> i64 {
+    let dt = Utc.timestamp_opt(ts, 0).single().unwrap_or_else(Utc::now);
+    Utc.with_ymd_and_hms(dt.year(), dt.month(), 1, 0, 0, 0)
+        .single()
+        .map(|d| d.timestamp())
+        .unwrap_or_else(|| day_start(ts))
+}
+
+/// Record the estimated cost of one generation.
+pub async fn record_spend(
+    pool: &PgPool,
+    user_id: &str,
+    agent_name: &str,
+    model: &str,
+    input_tokens: i64,
+    output_tokens: i64,
+    cost_usd: f64,
+) -> Result<String> {
+    let id = uuid::Uuid::new_v4().to_string();
+
+    sqlx::query(
+        "INSERT INTO spend_events (id, user_id, agent_name, model, input_tokens, output_tokens, cost_usd, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+    )
+    .bind(&id)
+    .bind(user_id)
+    .bind(agent_name)
+    .bind(model)
+    .bind(input_tokens)
+    .bind(output_tokens)
+    .bind(cost_usd)
+    .bind(Utc::now().timestamp())
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(e.to_string()))?;
+
+    Ok(id)
+}
+
+/// Daily and monthly spend, optionally narrowed to a user and/or agent.
+pub async fn get_spend_totals(
+    pool: &PgPool,
+    user_id: Option<&str>,
+    agent_name: Option<&str>,
+) -> Result<SpendTotals> {
+    let now = Utc::now().timestamp();
+
+    let row = sqlx::query(
+        "SELECT
+            COALESCE(SUM(cost_usd) FILTER (WHERE created_at >= $1), 0)::DOUBLE PRECISION as daily_usd,
+            COALESCE(SUM(cost_usd), 0)::DOUBLE PRECISION as monthly_usd
+         FROM spend_events
+         WHERE created_at >= $2
+           AND ($3::TEXT IS NULL OR user_id = $3)
+           AND ($4::TEXT IS NULL OR agent_name = $4)"
+    )
+    .bind(day_start(now))
+    .bind(month_start(now))
+    .bind(user_id)
+    .bind(agent_name)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::Database(e.to_string()))?;
+
+    Ok(SpendTotals {
+        daily_usd: row.get("daily_usd"),
+        monthly_usd: row.get("monthly_usd"),
+    })
+}
+
+/// Month-to-date spend of a user, broken down by agent.
+pub async fn get_user_agent_spend(pool: &PgPool, user_id: &str) -> Result<Vec<AgentSpend>> {
+    let rows = sqlx::query(
+        "SELECT agent_name,
+            COALESCE(SUM(input_tokens), 0)::BIGINT as input_tokens,
+            COALESCE(SUM(output_tokens), 0)::BIGINT as output_tokens,
+            COALESCE(SUM(cost_usd), 0)::DOUBLE PRECISION as cost_usd
+         FROM spend_events
+         WHERE user_id = $1 AND created_at >= $2
+         GROUP BY agent_name
+         ORDER BY cost_usd DESC",
+    )
+    .bind(user_id)
+    .bind(month_start(Utc::now().timestamp()))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(e.to_string()))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| AgentSpend {
+            agent_name: row.get("agent_name"),
+            input_tokens: row.get("input_tokens"),
+            output_tokens: row.get("output_tokens"),
+            cost_usd: row.get("cost_usd"),
+        })
+        .collect())
+}
+
+/// Name of the first window (`"daily"` or `"monthly"`) whose limit `totals` has reached.
+pub fn exceeded_window(limit: &BudgetLimit, totals: &SpendTotals) -> Option<&'static str> {
+    if limit.daily_usd.is_some_and(|max| totals.daily_usd >= max) {
+        Some("daily")
+    } else if limit
+        .monthly_usd
+        .is_some_and(|max| totals.monthly_usd >= max)
+    {
+        Some("monthly")
+    } else {
+        None
+    }
+}
+
+/// Fail with [`AppError::BudgetExceeded`] if the global, user or agent budget is spent.
+///
+/// A no-op when budgets are disabled.
+pub async fn enforce_budgets(
+    pool: &PgPool,
+    budgets: &BudgetsConfig,
+    user_id: &str,
+    agent_name: &str,
+) -> Result<()> {
+    if !budgets.enabled {
+        return Ok(());
+    }
+
+    let checks = [
+        ("Global", budgets.global, None, None),
+        ("User", budgets.user_limit(user_id), Some(user_id), None),
+        (
+            "Agent",
+            budgets.agent_limit(agent_name),
+            None,
+            Some(agent_name),
+        ),
+    ];
+
+    for (scope, limit, user, agent) in checks {
+        if limit == BudgetLimit::default() {
+            continue;
+        }
+        let totals = get_spend_totals(pool, user, agent).await?;
+        if let Some(window) = exceeded_window(&limit, &totals) {
+            let subject = match (user, agent) {
+                (Some(u), _) => format!(" for user '{}'", u),
+                (_, Some(a)) => format!(" for agent '{}'", a),
+                _ => String::new(),
+            };
+            return Err(AppError::BudgetExceeded(format!(
+                "{} {} budget exceeded{}",
+                scope, window, subject
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_starts() {
+        // 2024-03-15T13:20:00Z
+        let ts = 1_710_508_800;
+        assert_eq!(day_start(ts), 1_710_460_800);
+        // 2024-03-01T00:00:00Z
+        assert_eq!(month_start(ts), 1_709_251_200);
+    }
+
+    #[test]
+    fn test_exceeded_window() {
+        let limit = BudgetLimit {
+            daily_usd: Some(1.0),
+            monthly_usd: Some(10.0),
+        };
+        let under = SpendTotals {
+            daily_usd: 0.5,
+            monthly_usd: 5.0,
+        };
+        let over_month = SpendTotals {
+            daily_usd: 0.5,
+            monthly_usd: 10.0,
+        };
+        assert_eq!(exceeded_window(&limit, &under), None);
+        assert_eq!(exceeded_window(&limit, &over_month), Some("monthly"));
+        assert_eq!(exceeded_window(&BudgetLimit::default(), &over_month), None);
+    }
+}
@@ -27,6 +27,12 @@ impl TenantDb {
         &self.postgres.pool
     }
 
+    /// Pool for reads that tolerate replication lag (see
+    /// [`PostgresClient::read_pool`])
+    pub fn read_pool(&self) -> &sqlx::PgPool {
+        self.postgres.read_pool()
+    }
+
     pub async fn create_tenant(&self, name: String, tier: TenantTier) -> Result<Tenant> {
         let id = uuid::Uuid::new_v4().to_string();
         let tenant = Tenant::new(id.clone(), name, tier);
@@ -0,0 +1,299 @@
+//! Storage for Assistants-style threads and runs.
+//!
+//! A thread is a conversation (see [`crate::api::handlers::threads`]).
+//! Messages added to it wait in `thread_messages` until a run answers them;
+//! runs and their outcome are kept in `thread_runs`.
+
+use crate::types::{AppError, Result};
+use sqlx::PgPool;
+
+const RUN_COLUMNS: &str = "id, thread_id, user_id, assistant_id, status, approval_id, message_id, last_error, incomplete_reason, prompt_tokens, completion_tokens, created_at, started_at, completed_at";
+
+/// Status of a run waiting to start
+pub const QUEUED: &str = "queued";
+/// Status of a run whose agent is answering
+pub const IN_PROGRESS: &str = "in_progress";
+/// Status of a run waiting for its tool calls to be approved
+pub const REQUIRES_ACTION: &str = "requires_action";
+/// Status of a run asked to stop
+pub const CANCELLING: &str = "cancelling";
+/// Status of a run stopped on request
+pub const CANCELLED: &str = "cancelled";
+/// Status of a run that failed with an error
+pub const FAILED: &str = "failed";
+/// Status of a run whose answer was stored
+pub const COMPLETED: &str = "completed";
+/// Status of a run cut short by a run limit, with a partial answer stored
+pub const INCOMPLETE: &str = "incomplete";
+
+/// Statuses of runs still running on a thread
+const ACTIVE: [&str; 3] = [QUEUED, IN_PROGRESS, CANCELLING];
+
+/// A message added to a thread that no run has answered yet.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PendingMessage {
+    /// Message ID
+    pub id: String,
+    /// Thread (conversation) the message was added to
+    pub thread_id: String,
+    /// Message text
+    pub content: String,
+    /// When the message was added (Unix timestamp)
+    pub created_at: i64,
+}
+
+/// A run of an agent on a thread.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ThreadRun {
+    /// Run ID
+    pub id: String,
+    /// Thread (conversation) the run answers in
+    pub thread_id: String,
+    /// User who started the run
+    pub user_id: String,
+    /// Agent answering; `None` when the message is routed
+    pub assistant_id: Option<String>,
+    /// One of the status constants of this module
+    pub status: String,
+    /// Approval the run waits for, when it requires action
+    pub approval_id: Option<String>,
+    /// Stored answer, once the run completed
+    pub message_id: Option<String>,
+    /// Why the run failed
+    pub last_error: Option<String>,
+    /// Run limit that cut the answer short
+    pub incomplete_reason: Option<String>,
+    /// Estimated input tokens
+    pub prompt_tokens: Option<i64>,
+    /// Estimated output tokens
+    pub completion_tokens: Option<i64>,
+    /// When the run was created (Unix timestamp)
+    pub created_at: i64,
+    /// When the agent started answering (Unix timestamp)
+    pub started_at: Option<i64>,
+    /// When the run reached its final status (Unix timestamp)
+    pub completed_at: Option<i64>,
+}
+
+impl ThreadRun {
+    /// A queued run
+    pub fn new(
+        id: &str,
+        thread_id: &str,
+        user_id: &str,
+        assistant_id: Option<String>,
+        now: i64,
+    ) -> Self {
+        Self {
+            id: id.to_string(),
+            thread_id: thread_id.to_string(),
+            user_id: user_id.to_string(),
+            assistant_id,
+            status: QUEUED.to_string(),
+            approval_id: None,
+            message_id: None,
+            last_error: None,
+            incomplete_reason: None,
+            prompt_tokens: None,
+            completion_tokens: None,
+            created_at: now,
+            started_at: None,
+            completed_at: None,
+        }
+    }
+
+    /// Whether the run is still running
+    pub fn is_active(&self) -> bool {
+        ACTIVE.contains(&self.status.as_str())
+    }
+}
+
+/// Add a message to a thread, to be answered by its next run.
+pub async fn add_message(pool: &PgPool, message: &PendingMessage) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO thread_messages (id, thread_id, content, created_at) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(&message.id)
+    .bind(&message.thread_id)
+    .bind(&message.content)
+    .bind(message.created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to add thread message: {}", e)))?;
+    Ok(())
+}
+
+/// A thread's messages no run has answered yet, oldest first.
+pub async fn pending_messages(pool: &PgPool, thread_id: &str) -> Result<Vec<PendingMessage>> {
+    sqlx::query_as::<_, PendingMessage>(
+        "SELECT id, thread_id, content, created_at FROM thread_messages
+         WHERE thread_id = $1 ORDER BY created_at, id",
+    )
+    .bind(thread_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list thread messages: {}", e)))
+}
+
+/// Remove pending messages a run has stored in the conversation.
+pub async fn remove_messages(pool: &PgPool, ids: &[String]) -> Result<()> {
+    sqlx::query("DELETE FROM thread_messages WHERE id = ANY($1)")
+        .bind(ids)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to remove thread messages: {}", e)))?;
+    Ok(())
+}
+
+/// Store a new run.
+pub async fn insert_run(pool: &PgPool, run: &ThreadRun) -> Result<()> {
+    sqlx::query(&format!(
+        "INSERT INTO thread_runs ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)",
+        RUN_COLUMNS
+    ))
+    .bind(&run.id)
+    .bind(&run.thread_id)
+    .bind(&run.user_id)
+    .bind(&run.assistant_id)
+    .bind(&run.status)
+    .bind(&run.approval_id)
+    .bind(&run.message_id)
+    .bind(&run.last_error)
+    .bind(&run.incomplete_reason)
+    .bind(run.prompt_tokens)
+    .bind(run.completion_tokens)
+    .bind(run.created_at)
+    .bind(run.started_at)
+    .bind(run.completed_at)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to store thread run: {}", e)))?;
+    Ok(())
+}
+
+/// Save a run's status and outcome.
+///
+/// A run asked to stop stays `cancelling` until it reaches a final status.
+pub async fn update_run(pool: &PgPool, run: &ThreadRun) -> Result<()> {
+    sqlx::query(
+        "UPDATE thread_runs SET
+           status = CASE WHEN status = $2 AND $3 = $4 THEN status ELSE $3 END,
+           approval_id = $5, message_id = $6, last_error = $7, incomplete_reason = $8,
+           prompt_tokens = $9, completion_tokens = $10, started_at = $11, completed_at = $12
+         WHERE id = $1",
+    )
+    .bind(&run.id)
+    .bind(CANCELLING)
+    .bind(&run.status)
+    .bind(IN_PROGRESS)
+    .bind(&run.approval_id)
+    .bind(&run.message_id)
+    .bind(&run.last_error)
+    .bind(&run.incomplete_reason)
+    .bind(run.prompt_tokens)
+    .bind(run.completion_tokens)
+    .bind(run.started_at)
+    .bind(run.completed_at)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to update thread run: {}", e)))?;
+    Ok(())
+}
+
+/// Get one of a thread's runs.
+pub async fn get_run(pool: &PgPool, id: &str, thread_id: &str) -> Result<Option<ThreadRun>> {
+    sqlx::query_as::<_, ThreadRun>(&format!(
+        "SELECT {} FROM thread_runs WHERE id = $1 AND thread_id = $2",
+        RUN_COLUMNS
+    ))
+    .bind(id)
+    .bind(thread_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get thread run: {}", e)))
+}
+
+/// List a thread's runs, oldest first.
+pub async fn list_runs(pool: &PgPool, thread_id: &str) -> Result<Vec<ThreadRun>> {
+    sqlx::query_as::<_, ThreadRun>(&format!(
+        "SELECT {} FROM thread_runs WHERE thread_id = $1 ORDER BY created_at, id",
+        RUN_COLUMNS
+    ))
+    .bind(thread_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list thread runs: {}", e)))
+}
+
+/// The run still running on a thread, if any.
+pub async fn active_run(pool: &PgPool, thread_id: &str) -> Result<Option<ThreadRun>> {
+    sqlx::query_as::<_, ThreadRun>(&format!(
+        "SELECT {} FROM thread_runs WHERE thread_id = $1 AND status = ANY($2) LIMIT 1",
+        RUN_COLUMNS
+    ))
+    .bind(thread_id)
+    .bind(&ACTIVE[..])
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get active thread run: {}", e)))
+}
+
+/// The run waiting for an approval, if any.
+pub async fn run_awaiting(pool: &PgPool, approval_id: &str) -> Result<Option<ThreadRun>> {
+    sqlx::query_as::<_, ThreadRun>(&format!(
+        "SELECT {} FROM thread_runs WHERE approval_id = $1 AND status = $2",
+        RUN_COLUMNS
+    ))
+    .bind(approval_id)
+    .bind(REQUIRES_ACTION)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get thread run: {}", e)))
+}
+
+/// Mark a run asked to stop, returning false if it isn't running.
+pub async fn request_cancel(pool: &PgPool, id: &str) -> Result<bool> {
+    let result =
+        sqlx::query("UPDATE thread_runs SET status = $2 WHERE id = $1 AND status = ANY($3)")
+            .bind(id)
+            .bind(CANCELLING)
+            .bind(&ACTIVE[..])
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to cancel thread run: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Fail the runs still running on a thread, e.g. after their server stopped.
+pub async fn fail_active_runs(
+    pool: &PgPool,
+    thread_id: &str,
+    error: &str,
+    now: i64,
+) -> Result<u64> {
+    let result = sqlx::query(
+        "UPDATE thread_runs SET status = $2, last_error = $3, completed_at = $4
+         WHERE thread_id = $1 AND status = ANY($5)",
+    )
+    .bind(thread_id)
+    .bind(FAILED)
+    .bind(error)
+    .bind(now)
+    .bind(&ACTIVE[..])
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to fail thread runs: {}", e)))?;
+    Ok(result.rows_affected())
+}
+
+/// Delete a thread's pending messages and runs, when its conversation is deleted.
+pub async fn delete_thread(pool: &PgPool, thread_id: &str) -> Result<()> {
+    for table in ["thread_messages", "thread_runs"] {
+        sqlx::query(&format!("DELETE FROM {} WHERE thread_id = $1", table))
+            .bind(thread_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to delete thread: {}", e)))?;
+    }
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use crate::types::{AppError, MemoryFact, Message, MessageRole, Preference, Result};
+use crate::types::{AppError, ChatPreferences, ConversationOverrides, MemoryFact, Message, MessageRole, Preference, Result, ToolCallTrace};
 use async_trait::async_trait;
 
 #[derive(Debug, Clone, Default)]
@@ -51,6 +51,46 @@ pub struct ConversationSummary {
     pub created_at: String,
     pub updated_at: String,
     pub message_count: i32,
+    /// Whether the messages are in cold storage
+    #[sqlx(default)]
+    pub archived: bool,
+    /// Project the conversation belongs to, if any
+    #[sqlx(default)]
+    pub project_id: Option<String>,
+}
+
+/// Order of listed conversations
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversationSort {
+    /// Most recently updated first
+    #[default]
+    Updated,
+    /// Most recently created first
+    Created,
+    /// Alphabetically by title
+    Title,
+    /// Best match for the search query first
+    Relevance,
+}
+
+/// Which of a user's conversations to list, and in which order
+#[derive(Debug, Clone, Default)]
+pub struct ConversationFilter {
+    /// Full-text query matched against titles and message content
+    pub query: Option<String>,
+    /// Sort order (relevance applies only to searches)
+    pub sort: ConversationSort,
+    /// Sort direction (default: descending, except for titles)
+    pub descending: Option<bool>,
+    /// Conversations returned at most (all when unset)
+    pub limit: Option<u32>,
+    /// Conversations skipped before the first one returned
+    pub offset: u32,
+    /// Workspace the conversations were started in (the personal space when unset)
+    pub workspace_id: Option<String>,
+    /// Project the conversations belong to (any or none when unset)
+    pub project_id: Option<String>,
 }
 
 #[async_trait]
@@ -62,20 +102,35 @@ pub trait DatabaseClient: Send + Sync {
     async fn validate_session(&self, token_hash: &str) -> Result<Option<String>>;
     async fn delete_session(&self, id: &str) -> Result<()>;
     async fn delete_session_by_token_hash(&self, token_hash: &str) -> Result<()>;
-    async fn create_conversation(&self, id: &str, user_id: &str, title: Option<&str>) -> Result<()>;
+    /// Create a conversation in a workspace, or the user's personal space when `None`
+    async fn create_conversation(&self, id: &str, user_id: &str, title: Option<&str>, workspace_id: Option<&str>) -> Result<()>;
     async fn conversation_exists(&self, conversation_id: &str) -> Result<bool>;
     async fn get_user_conversations(&self, user_id: &str) -> Result<Vec<ConversationSummary>>;
+    /// A user's conversations matching `filter`, in its order
+    async fn search_user_conversations(&self, user_id: &str, filter: &ConversationFilter) -> Result<Vec<ConversationSummary>>;
     async fn get_conversation(&self, conversation_id: &str) -> Result<super::postgres::Conversation>;
     async fn delete_conversation(&self, conversation_id: &str) -> Result<()>;
     async fn update_conversation_title(&self, conversation_id: &str, title: Option<&str>) -> Result<()>;
+    /// Replace the model, temperature, agent and persona pinned on a conversation
+    async fn update_conversation_overrides(&self, conversation_id: &str, overrides: &ConversationOverrides) -> Result<()>;
     async fn add_message(&self, id: &str, conversation_id: &str, role: MessageRole, content: &str) -> Result<()>;
     async fn get_conversation_history(&self, conversation_id: &str) -> Result<Vec<Message>>;
+    /// Replace the content of a conversation's latest assistant message
+    async fn update_last_assistant_message(&self, conversation_id: &str, content: &str) -> Result<()>;
+    /// Store the tool calls made for a message, replacing any stored before
+    async fn store_tool_call_traces(&self, conversation_id: &str, message_id: &str, traces: &[ToolCallTrace]) -> Result<()>;
+    /// Tool calls made for a message, in the order they were made
+    async fn get_tool_call_traces(&self, conversation_id: &str, message_id: &str) -> Result<Vec<ToolCallTrace>>;
     async fn store_memory_fact(&self, fact: &MemoryFact) -> Result<()>;
     async fn get_user_memory(&self, user_id: &str) -> Result<Vec<MemoryFact>>;
     async fn get_memory_by_category(&self, user_id: &str, category: &str) -> Result<Vec<MemoryFact>>;
     async fn store_preference(&self, user_id: &str, preference: &Preference) -> Result<()>;
     async fn get_user_preferences(&self, user_id: &str) -> Result<Vec<Preference>>;
     async fn get_preference(&self, user_id: &str, category: &str, key: &str) -> Result<Option<Preference>>;
+    /// A user's chat preferences; the defaults if they set none
+    async fn get_chat_preferences(&self, user_id: &str) -> Result<ChatPreferences>;
+    /// Replace a user's chat preferences
+    async fn set_chat_preferences(&self, user_id: &str, preferences: &ChatPreferences) -> Result<()>;
     async fn get_user_agent_by_name(&self, user_id: &str, name: &str) -> Result<Option<super::postgres::UserAgent>>;
     async fn get_public_agent_by_name(&self, name: &str) -> Result<Option<super::postgres::UserAgent>>;
     async fn list_user_agents(&self, user_id: &str) -> Result<Vec<super::postgres::UserAgent>>;
@@ -94,14 +149,16 @@ impl DatabaseClient for super::postgres::PostgresClient {
     async fn validate_session(&self, token_hash: &str) -> Result<Option<String>> { super::postgres::PostgresClient::validate_session(self, token_hash).await }
     async fn delete_session(&self, id: &str) -> Result<()> { super::postgres::PostgresClient::delete_session(self, id).await }
     async fn delete_session_by_token_hash(&self, token_hash: &str) -> Result<()> { super::postgres::PostgresClient::delete_session_by_token_hash(self, token_hash).await }
-    async fn create_conversation(&self, id: &str, user_id: &str, title: Option<&str>) -> Result<()> { super::postgres::PostgresClient::create_conversation(self, id, user_id, title).await }
+    async fn create_conversation(&self, id: &str, user_id: &str, title: Option<&str>, workspace_id: Option<&str>) -> Result<()> { super::postgres::PostgresClient::create_conversation(self, id, user_id, title, workspace_id).await }
     async fn conversation_exists(&self, conversation_id: &str) -> Result<bool> { super::postgres::PostgresClient::conversation_exists(self, conversation_id).await }
     async fn get_user_conversations(&self, user_id: &str) -> Result<Vec<ConversationSummary>> { super::postgres::PostgresClient::get_user_conversations(self, user_id).await }
+    async fn search_user_conversations(&self, user_id: &str, filter: &ConversationFilter) -> Result<Vec<ConversationSummary>> { super::postgres::PostgresClient::search_user_conversations(self, user_id, filter).await }
     async fn get_conversation(&self, conversation_id: &str) -> Result<super::postgres::Conversation> { 
-        let row = sqlx::query_as::<_, super::postgres::Conversation>("SELECT id, user_id, title, created_at, updated_at, 0 as message_count FROM conversations WHERE id = $1").bind(conversation_id).fetch_optional(&self.pool).await.map_err(|e| AppError::Database(e.to_string()))?;
+        let row = sqlx::query_as::<_, super::postgres::Conversation>("SELECT id, user_id, title, created_at, updated_at, 0 as message_count, model, temperature, agent, persona, workspace_id, project_id, encryption_salt IS NOT NULL as encrypted FROM conversations WHERE id = $1").bind(conversation_id).fetch_optional(&self.pool).await.map_err(|e| AppError::Database(e.to_string()))?;
         row.ok_or_else(|| AppError::NotFound("Conversation not found".into()))
     }
     async fn delete_conversation(&self, conversation_id: &str) -> Result<()> { 
+        sqlx::query("DELETE FROM tool_call_traces WHERE conversation_id = $1").bind(conversation_id).execute(&self.pool).await.map_err(|e| AppError::Database(e.to_string()))?;
         sqlx::query("DELETE FROM messages WHERE conversation_id = $1").bind(conversation_id).execute(&self.pool).await.map_err(|e| AppError::Database(e.to_string()))?;
         sqlx::query("DELETE FROM conversations WHERE id = $1").bind(conversation_id).execute(&self.pool).await.map_err(|e| AppError::Database(e.to_string()))?;
         Ok(())
@@ -111,8 +168,12 @@ impl DatabaseClient for super::postgres::PostgresClient {
         sqlx::query("UPDATE conversations SET title = $1, updated_at = $2 WHERE id = $3").bind(title).bind(now).bind(conversation_id).execute(&self.pool).await.map_err(|e| AppError::Database(e.to_string()))?;
         Ok(())
     }
+    async fn update_conversation_overrides(&self, conversation_id: &str, overrides: &ConversationOverrides) -> Result<()> { super::postgres::PostgresClient::update_conversation_overrides(self, conversation_id, overrides).await }
     async fn add_message(&self, id: &str, conversation_id: &str, role: MessageRole, content: &str) -> Result<()> { super::postgres::PostgresClient::add_message(self, id, conversation_id, role, content).await }
     async fn get_conversation_history(&self, conversation_id: &str) -> Result<Vec<Message>> { super::postgres::PostgresClient::get_conversation_history(self, conversation_id).await }
+    async fn update_last_assistant_message(&self, conversation_id: &str, content: &str) -> Result<()> { super::postgres::PostgresClient::update_last_assistant_message(self, conversation_id, content).await }
+    async fn store_tool_call_traces(&self, conversation_id: &str, message_id: &str, traces: &[ToolCallTrace]) -> Result<()> { super::postgres::PostgresClient::store_tool_call_traces(self, conversation_id, message_id, traces).await }
+    async fn get_tool_call_traces(&self, conversation_id: &str, message_id: &str) -> Result<Vec<ToolCallTrace>> { super::postgres::PostgresClient::get_tool_call_traces(self, conversation_id, message_id).await }
     async fn store_memory_fact(&self, fact: &MemoryFact) -> Result<()> { super::postgres::PostgresClient::store_memory_fact(self, fact).await }
     async fn get_user_memory(&self, user_id: &str) -> Result<Vec<MemoryFact>> { super::postgres::PostgresClient::get_user_memory(self, user_id).await }
     async fn get_memory_by_category(&self, user_id: &str, category: &str) -> Result<Vec<MemoryFact>> {
@@ -125,13 +186,13 @@ impl DatabaseClient for super::postgres::PostgresClient {
         let prefs = super::postgres::PostgresClient::get_user_preferences(self, user_id).await?;
         Ok(prefs.into_iter().find(|p| p.category == category && p.key == key))
     }
+    async fn get_chat_preferences(&self, user_id: &str) -> Result<ChatPreferences> { super::postgres::PostgresClient::get_chat_preferences(self, user_id).await }
+    async fn set_chat_preferences(&self, user_id: &str, preferences: &ChatPreferences) -> Result<()> { super::postgres::PostgresClient::set_chat_preferences(self, user_id, preferences).await }
     async fn get_user_agent_by_name(&self, user_id: &str, name: &str) -> Result<Option<super::postgres::UserAgent>> { super::postgres::PostgresClient::get_user_agent_by_name(self, user_id, name).await }
-    async fn get_public_agent_by_name(&self, name: &str) -> Result<Option<super::postgres::UserAgent>> { 
-        super::postgres::PostgresClient::get_user_agent_by_name(self, "", name).await 
-    }
-    async fn list_user_agents(&self, _user_id: &str) -> Result<Vec<super::postgres::UserAgent>> { Ok(vec![]) } 
-    async fn list_public_agents(&self, _limit: u32, _offset: u32) -> Result<Vec<super::postgres::UserAgent>> { Ok(vec![]) } 
-    async fn create_user_agent(&self, _agent: &super::postgres::UserAgent) -> Result<()> { Ok(()) } 
-    async fn update_user_agent(&self, _agent: &super::postgres::UserAgent) -> Result<()> { Ok(()) } 
-    async fn delete_user_agent(&self, _id: &str, _user_id: &str) -> Result<bool> { Ok(true) } 
+    async fn get_public_agent_by_name(&self, name: &str) -> Result<Option<super::postgres::UserAgent>> { super::postgres::PostgresClient::get_public_agent_by_name(self, name).await }
+    async fn list_user_agents(&self, user_id: &str) -> Result<Vec<super::postgres::UserAgent>> { super::postgres::PostgresClient::list_user_agents(self, user_id).await }
+    async fn list_public_agents(&self, limit: u32, offset: u32) -> Result<Vec<super::postgres::UserAgent>> { super::postgres::PostgresClient::list_public_agents(self, limit, offset).await }
+    async fn create_user_agent(&self, agent: &super::postgres::UserAgent) -> Result<()> { super::postgres::PostgresClient::create_user_agent(self, agent).await }
+    async fn update_user_agent(&self, agent: &super::postgres::UserAgent) -> Result<()> { super::postgres::PostgresClient::update_user_agent(self, agent).await }
+    async fn delete_user_agent(&self, id: &str, user_id: &str) -> Result<bool> { super::postgres::PostgresClient::delete_user_agent(self, id, user_id).await }
 }
@@ -0,0 +1,290 @@
+//! Storage for users' API keys.
+//!
+//! Machine clients send a key in the `X-API-Key` header instead of a JWT
+//! (see [`crate::auth::middleware::authenticate`]); the request acts as the
+//! key's user, limited to the key's [`ApiKeyScope`]s. Only a SHA-256 hash of
+//! each key is stored.
+
+use crate::types::{AppError, Result, Role};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+const COLUMNS: &str =
+    "id, user_id, name, key_prefix, scopes, request_count, last_used_at, created_at, rotated_at";
+
+/// Prefix of user API keys, telling them apart from tenant keys
+pub const KEY_PREFIX: &str = "ares_uk_";
+
+/// Routes an API key may call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyScope {
+    /// Chat, conversations, threads and AG-UI runs
+    Chat,
+    /// RAG ingestion, search and collections
+    Rag,
+}
+
+impl ApiKeyScope {
+    /// Route prefixes (below `/api`) the scope covers
+    fn prefixes(&self) -> &'static [&'static str] {
+        match self {
+            ApiKeyScope::Chat => &["/chat", "/conversations", "/messages", "/threads", "/agui"],
+            ApiKeyScope::Rag => &["/rag"],
+        }
+    }
+
+    /// Whether the scope covers a route, given its path below `/api`
+    pub fn covers(&self, path: &str) -> bool {
+        self.prefixes().iter().any(|prefix| {
+            path.strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+}
+
+/// An API key, without its secret.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UserApiKey {
+    /// Key ID
+    pub id: String,
+    /// User the key acts as
+    #[serde(skip)]
+    pub user_id: String,
+    /// What the key is for
+    pub name: String,
+    /// First characters of the key, to recognize it
+    pub key_prefix: String,
+    /// Routes the key may call
+    pub scopes: Vec<ApiKeyScope>,
+    /// Requests made with the key
+    pub request_count: i64,
+    /// When the key was last used (Unix timestamp)
+    pub last_used_at: Option<i64>,
+    /// When the key was issued (Unix timestamp)
+    pub created_at: i64,
+    /// When the key's secret was last replaced (Unix timestamp)
+    pub rotated_at: Option<i64>,
+}
+
+/// The user an API key authenticates, with their email and role.
+#[derive(Debug, Clone)]
+pub struct KeyOwner {
+    /// The key
+    pub key: UserApiKey,
+    /// User's email address
+    pub email: String,
+    /// User's role
+    pub role: Role,
+}
+
+#[derive(sqlx::FromRow)]
+struct KeyRow {
+    id: String,
+    user_id: String,
+    name: String,
+    key_prefix: String,
+    scopes: String,
+    request_count: i64,
+    last_used_at: Option<i64>,
+    created_at: i64,
+    rotated_at: Option<i64>,
+}
+
+impl TryFrom<KeyRow> for UserApiKey {
+    type Error = AppError;
+
+    fn try_from(row: KeyRow) -> Result<Self> {
+        Ok(UserApiKey {
+            scopes: serde_json::from_str(&row.scopes)
+                .map_err(|e| AppError::Database(format!("Invalid API key scopes stored: {}", e)))?,
+            id: row.id,
+            user_id: row.user_id,
+            name: row.name,
+            key_prefix: row.key_prefix,
+            request_count: row.request_count,
+            last_used_at: row.last_used_at,
+            created_at: row.created_at,
+            rotated_at: row.rotated_at,
+        })
+    }
+}
+
+/// Generate a new secret key, returning it with its hash and prefix
+pub fn generate_key() -> (String, String, String) {
+    let bytes: Vec<u8> = (0..32).map(|_| rand::random::<u8>()).collect();
+    let key = format!("{}{}", KEY_PREFIX, hex::encode(bytes));
+    let prefix = key[..KEY_PREFIX.len() + 8].to_string();
+    (hash_key(&key), prefix, key)
+}
+
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Store a new key with the hash of its secret.
+pub async fn insert_key(pool: &PgPool, key: &UserApiKey, key_hash: &str) -> Result<()> {
+    let scopes = serde_json::to_string(&key.scopes)
+        .map_err(|e| AppError::Internal(format!("Failed to encode API key scopes: {}", e)))?;
+    sqlx::query(
+        "INSERT INTO user_api_keys (id, user_id, name, key_hash, key_prefix, scopes, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(&key.id)
+    .bind(&key.user_id)
+    .bind(&key.name)
+    .bind(key_hash)
+    .bind(&key.key_prefix)
+    .bind(scopes)
+    .bind(key.created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to store API key: {}", e)))?;
+    Ok(())
+}
+
+/// List a user's keys that haven't been revoked, newest first.
+pub async fn list_keys(pool: &PgPool, user_id: &str) -> Result<Vec<UserApiKey>> {
+    sqlx::query_as::<_, KeyRow>(&format!(
+        "SELECT {} FROM user_api_keys WHERE user_id = $1 AND revoked_at IS NULL
+         ORDER BY created_at DESC, id",
+        COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list API keys: {}", e)))?
+    .into_iter()
+    .map(UserApiKey::try_from)
+    .collect()
+}
+
+/// Get one of a user's keys that hasn't been revoked.
+pub async fn get_key(pool: &PgPool, id: &str, user_id: &str) -> Result<Option<UserApiKey>> {
+    sqlx::query_as::<_, KeyRow>(&format!(
+        "SELECT {} FROM user_api_keys WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+        COLUMNS
+    ))
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get API key: {}", e)))?
+    .map(UserApiKey::try_from)
+    .transpose()
+}
+
+/// The key a secret belongs to, with its user, unless revoked.
+pub async fn authenticate(pool: &PgPool, secret: &str) -> Result<Option<KeyOwner>> {
+    #[derive(sqlx::FromRow)]
+    struct OwnerRow {
+        #[sqlx(flatten)]
+        key: KeyRow,
+        email: String,
+        role: Option<String>,
+    }
+
+    let row = sqlx::query_as::<_, OwnerRow>(
+        "SELECT k.id, k.user_id, k.name, k.key_prefix, k.scopes, k.request_count,
+                k.last_used_at, k.created_at, k.rotated_at, u.email, r.role
+         FROM user_api_keys k
+         JOIN users u ON u.id = k.user_id
+         LEFT JOIN user_roles r ON r.user_id = k.user_id
+         WHERE k.key_hash = $1 AND k.revoked_at IS NULL",
+    )
+    .bind(hash_key(secret))
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to verify API key: {}", e)))?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    Ok(Some(KeyOwner {
+        key: UserApiKey::try_from(row.key)?,
+        email: row.email,
+        role: row
+            .role
+            .as_deref()
+            .and_then(Role::parse)
+            .unwrap_or_default(),
+    }))
+}
+
+/// Count a request made with a key.
+pub async fn record_use(pool: &PgPool, id: &str, now: i64) -> Result<()> {
+    sqlx::query(
+        "UPDATE user_api_keys SET request_count = request_count + 1, last_used_at = $2
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to record API key use: {}", e)))?;
+    Ok(())
+}
+
+/// Replace the secret of one of a user's keys, returning whether it exists.
+pub async fn rotate_key(
+    pool: &PgPool,
+    id: &str,
+    user_id: &str,
+    key_hash: &str,
+    key_prefix: &str,
+    now: i64,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE user_api_keys SET key_hash = $3, key_prefix = $4, rotated_at = $5
+         WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(key_hash)
+    .bind(key_prefix)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to rotate API key: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Revoke one of a user's keys, returning whether it existed.
+pub async fn revoke_key(pool: &PgPool, id: &str, user_id: &str, now: i64) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE user_api_keys SET revoked_at = $3
+         WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to revoke API key: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scopes_cover_their_routes_only() {
+        assert!(ApiKeyScope::Chat.covers("/chat"));
+        assert!(ApiKeyScope::Chat.covers("/conversations/c1/messages"));
+        assert!(!ApiKeyScope::Chat.covers("/chatter"));
+        assert!(!ApiKeyScope::Chat.covers("/rag/search"));
+        assert!(ApiKeyScope::Rag.covers("/rag/search"));
+        assert!(!ApiKeyScope::Rag.covers("/api-keys"));
+    }
+
+    #[test]
+    fn test_generated_keys_are_recognizable() {
+        let (hash, prefix, key) = generate_key();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert!(key.starts_with(&prefix));
+        assert_eq!(hash, hash_key(&key));
+        assert_ne!(generate_key().2, key);
+    }
+}
@@ -40,7 +40,10 @@
 //! let results = store.search("documents", &query_embedding, 10, 0.5).await?;
 //! ```
 
-use crate::types::{AppError, Document, Result, SearchResult};
+use crate::types::{
+    AppError, CollectionSettings, Document, DocumentMetadata, Result, SearchResult,
+};
+use crate::utils::toml_config::RagConfig;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
@@ -186,6 +189,62 @@ impl VectorStoreProvider {
         }
     }
 
+    /// Create the provider selected by `[rag] vector_store`.
+    ///
+    /// ares-vector and lancedb store under `vector_path`; qdrant and pgvector
+    /// connect to the URL in `vector_url_env`, pgvector defaulting to the
+    /// server's DATABASE_URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider is unknown, its feature is not
+    /// enabled, or a variable it needs is not set.
+    pub fn from_rag_config(config: &RagConfig) -> Result<Self> {
+        match config.vector_store.as_str() {
+            #[cfg(feature = "ares-vector")]
+            "ares-vector" => Ok(VectorStoreProvider::AresVector {
+                path: Some(config.vector_path.clone()),
+            }),
+
+            #[cfg(feature = "lancedb")]
+            "lancedb" => Ok(VectorStoreProvider::LanceDB {
+                path: config.vector_path.clone(),
+            }),
+
+            #[cfg(feature = "qdrant")]
+            "qdrant" => Ok(VectorStoreProvider::Qdrant {
+                url: config
+                    .vector_url_env
+                    .as_deref()
+                    .map(env_var)
+                    .transpose()?
+                    .ok_or_else(|| {
+                        AppError::Configuration(
+                            "The qdrant vector store requires [rag] vector_url_env".into(),
+                        )
+                    })?,
+                api_key: config
+                    .vector_api_key_env
+                    .as_deref()
+                    .map(env_var)
+                    .transpose()?,
+            }),
+
+            #[cfg(feature = "pgvector")]
+            "pgvector" => Ok(VectorStoreProvider::PgVector {
+                connection_string: match config.vector_url_env.as_deref() {
+                    Some(name) => env_var(name)?,
+                    None => crate::db::postgres::local_url(),
+                },
+            }),
+
+            other => Err(AppError::Configuration(format!(
+                "Vector store '{}' is unknown or its feature is not enabled",
+                other
+            ))),
+        }
+    }
+
     /// Create a provider from environment variables.
     ///
     /// Checks for provider-specific environment variables in order:
@@ -245,6 +304,13 @@ impl VectorStoreProvider {
     }
 }
 
+/// Value of an environment variable a vector store connects with
+#[cfg(any(feature = "qdrant", feature = "pgvector"))]
+fn env_var(name: &str) -> Result<String> {
+    std::env::var(name)
+        .map_err(|_| AppError::Configuration(format!("Environment variable {} is not set", name)))
+}
+
 // ============================================================================
 // Collection Statistics
 // ============================================================================
@@ -373,7 +439,8 @@ pub trait VectorStore: Send + Sync {
     /// * `embedding` - Query vector.
     /// * `limit` - Maximum number of results.
     /// * `threshold` - Minimum similarity score.
-    /// * `filters` - Metadata filters to apply.
+    /// * `filters` - Metadata filters to apply, as `(field, value)` pairs that
+    ///   must all match (see [`matches_filters`]).
     ///
     /// # Default Implementation
     ///
@@ -420,6 +487,72 @@ pub trait VectorStore: Send + Sync {
         let stats = self.collection_stats(collection).await?;
         Ok(stats.document_count)
     }
+
+    /// All documents stored in a collection, with their embeddings.
+    ///
+    /// Used to re-embed a collection with another embedding model.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns an error; providers must override this to support re-embedding.
+    async fn documents(&self, _collection: &str) -> Result<Vec<Document>> {
+        Err(AppError::Configuration(format!(
+            "The {} vector store does not support listing documents",
+            self.provider_name()
+        )))
+    }
+
+    /// Write pending changes to durable storage.
+    ///
+    /// Called on shutdown. Server-backed providers write as they go.
+    ///
+    /// # Default Implementation
+    ///
+    /// Does nothing.
+    async fn persist(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Get the RAG settings stored with a collection.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns `None`; providers without settings storage use the global defaults.
+    async fn collection_settings(&self, _name: &str) -> Result<Option<CollectionSettings>> {
+        Ok(None)
+    }
+
+    /// Store RAG settings with a collection, replacing any previous settings.
+    ///
+    /// Settings may be stored before the collection is created; they are
+    /// removed when the collection is deleted.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns an error; providers must override this to support settings.
+    async fn set_collection_settings(
+        &self,
+        _name: &str,
+        _settings: &CollectionSettings,
+    ) -> Result<()> {
+        Err(AppError::Configuration(format!(
+            "The {} vector store does not support collection settings",
+            self.provider_name()
+        )))
+    }
+}
+
+/// Whether document metadata matches every `(field, value)` filter.
+///
+/// `title` and `source` match exactly, `tags` matches when the document has
+/// the tag. Filters on other fields never match.
+pub fn matches_filters(metadata: &DocumentMetadata, filters: &[(String, String)]) -> bool {
+    filters.iter().all(|(field, value)| match field.as_str() {
+        "title" => &metadata.title == value,
+        "source" => &metadata.source == value,
+        "tags" => metadata.tags.contains(value),
+        _ => false,
+    })
 }
 
 // ============================================================================
@@ -436,6 +569,7 @@ use std::sync::Arc;
 /// Uses cosine similarity for vector comparisons.
 pub struct InMemoryVectorStore {
     collections: Arc<RwLock<HashMap<String, InMemoryCollection>>>,
+    settings: Arc<RwLock<HashMap<String, CollectionSettings>>>,
 }
 
 struct InMemoryCollection {
@@ -448,6 +582,7 @@ impl InMemoryVectorStore {
     pub fn new() -> Self {
         Self {
             collections: Arc::new(RwLock::new(HashMap::new())),
+            settings: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -504,6 +639,7 @@ impl VectorStore for InMemoryVectorStore {
         collections
             .remove(name)
             .ok_or_else(|| AppError::NotFound(format!("Collection '{}' not found", name)))?;
+        self.settings.write().remove(name);
         Ok(())
     }
 
@@ -566,6 +702,18 @@ impl VectorStore for InMemoryVectorStore {
         embedding: &[f32],
         limit: usize,
         threshold: f32,
+    ) -> Result<Vec<SearchResult>> {
+        self.search_with_filters(collection, embedding, limit, threshold, &[])
+            .await
+    }
+
+    async fn search_with_filters(
+        &self,
+        collection: &str,
+        embedding: &[f32],
+        limit: usize,
+        threshold: f32,
+        filters: &[(String, String)],
     ) -> Result<Vec<SearchResult>> {
         let collections = self.collections.read();
         let col = collections
@@ -575,6 +723,7 @@ impl VectorStore for InMemoryVectorStore {
         let mut results: Vec<SearchResult> = col
             .documents
             .values()
+            .filter(|doc| matches_filters(&doc.metadata, filters))
             .filter_map(|doc| {
                 let doc_embedding = doc.embedding.as_ref()?;
                 let score = Self::cosine_similarity(embedding, doc_embedding);
@@ -631,6 +780,30 @@ impl VectorStore for InMemoryVectorStore {
 
         Ok(col.documents.get(id).cloned())
     }
+
+    async fn documents(&self, collection: &str) -> Result<Vec<Document>> {
+        let collections = self.collections.read();
+        let col = collections
+            .get(collection)
+            .ok_or_else(|| AppError::NotFound(format!("Collection '{}' not found", collection)))?;
+
+        Ok(col.documents.values().cloned().collect())
+    }
+
+    async fn collection_settings(&self, name: &str) -> Result<Option<CollectionSettings>> {
+        Ok(self.settings.read().get(name).cloned())
+    }
+
+    async fn set_collection_settings(
+        &self,
+        name: &str,
+        settings: &CollectionSettings,
+    ) -> Result<()> {
+        self.settings
+            .write()
+            .insert(name.to_string(), settings.clone());
+        Ok(())
+    }
 }
 
 // ============================================================================
@@ -640,7 +813,6 @@ impl VectorStore for InMemoryVectorStore {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::DocumentMetadata;
     use chrono::Utc;
 
     fn create_test_document(id: &str, content: &str, embedding: Vec<f32>) -> Document {
@@ -698,6 +870,41 @@ mod tests {
         assert_eq!(results[1].document.id, "doc3"); // Similar second
     }
 
+    #[tokio::test]
+    async fn test_inmemory_search_with_filters() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("test", 3).await.unwrap();
+
+        let mut doc1 = create_test_document("doc1", "Hello world", vec![1.0, 0.0, 0.0]);
+        doc1.metadata.source = "handbook.md".to_string();
+        let mut doc2 = create_test_document("doc2", "Hello there", vec![0.9, 0.1, 0.0]);
+        doc2.metadata.tags.push("hr".to_string());
+        store.upsert("test", &[doc1, doc2]).await.unwrap();
+
+        let by_source = [("source".to_string(), "handbook.md".to_string())];
+        let results = store
+            .search_with_filters("test", &[1.0, 0.0, 0.0], 10, 0.5, &by_source)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].document.id, "doc1");
+
+        let by_tag = [("tags".to_string(), "hr".to_string())];
+        let results = store
+            .search_with_filters("test", &[1.0, 0.0, 0.0], 10, 0.5, &by_tag)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].document.id, "doc2");
+
+        let unknown = [("author".to_string(), "anyone".to_string())];
+        assert!(store
+            .search_with_filters("test", &[1.0, 0.0, 0.0], 10, 0.5, &unknown)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
     #[tokio::test]
     async fn test_inmemory_delete() {
         let store = InMemoryVectorStore::new();
@@ -741,6 +948,32 @@ mod tests {
         assert_eq!(collections.len(), 2);
     }
 
+    #[tokio::test]
+    async fn test_inmemory_collection_settings() {
+        let store = InMemoryVectorStore::new();
+        assert!(store.collection_settings("docs").await.unwrap().is_none());
+
+        // Settings can be stored before the collection exists
+        let settings = CollectionSettings {
+            chunk_size: Some(100),
+            embedding_model: Some("bge-base-en-v1.5".to_string()),
+            ..Default::default()
+        };
+        store
+            .set_collection_settings("docs", &settings)
+            .await
+            .unwrap();
+        store.create_collection("docs", 768).await.unwrap();
+        assert_eq!(
+            store.collection_settings("docs").await.unwrap(),
+            Some(settings)
+        );
+
+        // Deleting the collection removes its settings
+        store.delete_collection("docs").await.unwrap();
+        assert!(store.collection_settings("docs").await.unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn test_cosine_similarity() {
         // Identical vectors
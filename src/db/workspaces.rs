@@ -0,0 +1,189 @@
+//! Storage for workspaces and their members.
+//!
+//! See [`crate::api::workspace`] for how requests act in a workspace.
+
+use crate::types::{AppError, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+/// A member's role in a workspace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceRole {
+    /// Created the workspace; can't be removed
+    Owner,
+    /// Manages the workspace's members
+    Admin,
+    /// Uses the workspace's agents, collections and memory
+    Member,
+}
+
+impl WorkspaceRole {
+    /// Name stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkspaceRole::Owner => "owner",
+            WorkspaceRole::Admin => "admin",
+            WorkspaceRole::Member => "member",
+        }
+    }
+
+    /// Parse a stored name; unknown names are `None`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "owner" => Some(WorkspaceRole::Owner),
+            "admin" => Some(WorkspaceRole::Admin),
+            "member" => Some(WorkspaceRole::Member),
+            _ => None,
+        }
+    }
+
+    /// Whether the role can add and remove members
+    pub fn manages_members(&self) -> bool {
+        matches!(self, WorkspaceRole::Owner | WorkspaceRole::Admin)
+    }
+}
+
+/// A workspace.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Workspace {
+    /// Workspace ID
+    pub id: String,
+    /// Display name
+    pub name: String,
+    /// User who created the workspace
+    pub created_by: String,
+    /// When the workspace was created (Unix timestamp)
+    pub created_at: i64,
+}
+
+/// A user's membership of a workspace.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct WorkspaceMember {
+    /// The workspace
+    pub workspace_id: String,
+    /// The member
+    pub user_id: String,
+    /// "owner", "admin" or "member"
+    pub role: String,
+    /// When the user joined (Unix timestamp)
+    pub created_at: i64,
+}
+
+/// Key a workspace's shared data is stored under, in place of a user ID
+pub fn owner_key(workspace_id: &str) -> String {
+    format!("workspace_{}", workspace_id)
+}
+
+/// Create a workspace with `workspace.created_by` as its owner.
+pub async fn create_workspace(pool: &PgPool, workspace: &Workspace) -> Result<()> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to create workspace: {}", e)))?;
+    sqlx::query(
+        "INSERT INTO workspaces (id, name, created_by, created_at) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(&workspace.id)
+    .bind(&workspace.name)
+    .bind(&workspace.created_by)
+    .bind(workspace.created_at)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to create workspace: {}", e)))?;
+    sqlx::query(
+        "INSERT INTO workspace_members (workspace_id, user_id, role, created_at) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(&workspace.id)
+    .bind(&workspace.created_by)
+    .bind(WorkspaceRole::Owner.as_str())
+    .bind(workspace.created_at)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to add workspace owner: {}", e)))?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to create workspace: {}", e)))
+}
+
+/// Get a workspace.
+pub async fn get_workspace(pool: &PgPool, id: &str) -> Result<Option<Workspace>> {
+    sqlx::query_as::<_, Workspace>(
+        "SELECT id, name, created_by, created_at FROM workspaces WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get workspace: {}", e)))
+}
+
+/// List the workspaces a user belongs to, oldest first.
+pub async fn list_user_workspaces(pool: &PgPool, user_id: &str) -> Result<Vec<Workspace>> {
+    sqlx::query_as::<_, Workspace>(
+        "SELECT w.id, w.name, w.created_by, w.created_at FROM workspaces w
+         JOIN workspace_members m ON m.workspace_id = w.id
+         WHERE m.user_id = $1 ORDER BY w.created_at ASC, w.id",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list workspaces: {}", e)))
+}
+
+/// A user's role in a workspace, if they are a member.
+pub async fn member_role(
+    pool: &PgPool,
+    workspace_id: &str,
+    user_id: &str,
+) -> Result<Option<WorkspaceRole>> {
+    let row: Option<(String,)> = sqlx::query_as(
+        "SELECT role FROM workspace_members WHERE workspace_id = $1 AND user_id = $2",
+    )
+    .bind(workspace_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to get workspace membership: {}", e)))?;
+    Ok(row.and_then(|(role,)| WorkspaceRole::parse(&role)))
+}
+
+/// List a workspace's members, in the order they joined.
+pub async fn list_members(pool: &PgPool, workspace_id: &str) -> Result<Vec<WorkspaceMember>> {
+    sqlx::query_as::<_, WorkspaceMember>(
+        "SELECT workspace_id, user_id, role, created_at FROM workspace_members
+         WHERE workspace_id = $1 ORDER BY created_at ASC, user_id",
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to list workspace members: {}", e)))
+}
+
+/// Add a member, or change the role of an existing one.
+pub async fn upsert_member(pool: &PgPool, member: &WorkspaceMember) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO workspace_members (workspace_id, user_id, role, created_at) VALUES ($1, $2, $3, $4)
+         ON CONFLICT (workspace_id, user_id) DO UPDATE SET role = EXCLUDED.role",
+    )
+    .bind(&member.workspace_id)
+    .bind(&member.user_id)
+    .bind(&member.role)
+    .bind(member.created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to add workspace member: {}", e)))?;
+    Ok(())
+}
+
+/// Remove a member, returning whether they were one.
+pub async fn remove_member(pool: &PgPool, workspace_id: &str, user_id: &str) -> Result<bool> {
+    let result =
+        sqlx::query("DELETE FROM workspace_members WHERE workspace_id = $1 AND user_id = $2")
+            .bind(workspace_id)
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to remove workspace member: {}", e)))?;
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,126 @@
+//! JUnit XML reports, read by CI servers.
+
+use super::runner::SuiteReport;
+use std::fmt::Write;
+
+/// A JUnit XML report of `suites`: a `<testsuite>` per suite and a
+/// `<testcase>` per case, with failed assertions as `<failure>` and cases
+/// that couldn't run to the end as `<error>`.
+pub fn report(suites: &[SuiteReport]) -> String {
+    let tests: usize = suites.iter().map(|s| s.cases.len()).sum();
+    let failures: usize = suites.iter().map(SuiteReport::failures).sum();
+    let errors: usize = suites.iter().map(SuiteReport::errors).sum();
+    let time: f64 = suites.iter().map(|s| s.elapsed.as_secs_f64()).sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"ares-eval\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">",
+        tests, failures, errors, time
+    );
+    for suite in suites {
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">",
+            escape(&suite.name),
+            suite.cases.len(),
+            suite.failures(),
+            suite.errors(),
+            suite.elapsed.as_secs_f64()
+        );
+        for case in &suite.cases {
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                escape(&case.name),
+                escape(&suite.name),
+                case.elapsed.as_secs_f64()
+            );
+            if case.passed() {
+                xml.push_str("/>\n");
+                continue;
+            }
+            xml.push_str(">\n");
+            if let Some(error) = &case.error {
+                let _ = writeln!(
+                    xml,
+                    "      <error message=\"{}\">{}</error>",
+                    escape(error),
+                    escape(&case.failures.join("\n"))
+                );
+            } else if let Some(first) = case.failures.first() {
+                let _ = writeln!(
+                    xml,
+                    "      <failure message=\"{}\">{}</failure>",
+                    escape(first),
+                    escape(&case.failures.join("\n"))
+                );
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Not allowed in XML 1.0
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::runner::CaseReport;
+    use std::time::Duration;
+
+    #[test]
+    fn test_junit_report() {
+        let case = |name: &str, failures: &[&str], error: Option<&str>| CaseReport {
+            name: name.to_string(),
+            failures: failures.iter().map(|f| f.to_string()).collect(),
+            error: error.map(str::to_string),
+            elapsed: Duration::from_millis(500),
+        };
+        let suite = SuiteReport {
+            name: "support".to_string(),
+            cases: vec![
+                case("greets", &[], None),
+                case(
+                    "refunds",
+                    &["turn 1: answer doesn't contain \"30 days\""],
+                    None,
+                ),
+                case(
+                    "handoff",
+                    &[],
+                    Some("turn 2: HTTP 503 <Service Unavailable>"),
+                ),
+            ],
+            elapsed: Duration::from_millis(1500),
+        };
+
+        let xml = report(&[suite]);
+        assert!(xml.contains(
+            r#"<testsuites name="ares-eval" tests="3" failures="1" errors="1" time="1.500">"#
+        ));
+        assert!(xml.contains(r#"<testcase name="greets" classname="support" time="0.500"/>"#));
+        assert!(xml.contains(
+            r#"<failure message="turn 1: answer doesn&apos;t contain &quot;30 days&quot;">"#
+        ));
+        assert!(xml.contains(r#"<error message="turn 2: HTTP 503 &lt;Service Unavailable&gt;">"#));
+    }
+}
@@ -0,0 +1,51 @@
+//! Declarative eval suites for end-to-end chat flows.
+//!
+//! A suite is a TOML file of conversations: user turns sent in order to a
+//! running server's `/api/chat`, each with assertions on its answer. Suites
+//! go through the whole stack (routing, agents, tools, storage), so the
+//! server can answer with the mock provider to test ARES itself, or with
+//! live models to test prompts and agents.
+//!
+//! ```toml
+//! name = "support"
+//! agent_type = "product"          # default: routed
+//!
+//! [[cases]]
+//! name = "refund policy"
+//!
+//! [[cases.turns]]
+//! user = "What's your refund policy?"
+//! agent = "product"               # agent that must answer
+//! contains = ["30 days"]          # ignoring case
+//! not_contains = ["I don't know"]
+//! matches = "(?i)refunds?"
+//! tools = ["search_docs"]         # must be called
+//! forbid_tools = ["web_search"]   # mustn't be called
+//! max_latency_ms = 10000
+//!
+//! [[cases.turns]]
+//! user = "Does that apply to digital goods?"
+//! ```
+//!
+//! `ares-server eval run <suite>...` runs suites and writes a JUnit report
+//! with `--junit`. Tests can run them through [`EvalRunner`]:
+//!
+//! ```rust,ignore
+//! use ares::eval::{EvalRunner, Suite};
+//!
+//! let suite = Suite::load(Path::new("evals/support.toml"))?;
+//! let runner = EvalRunner::new("http://127.0.0.1:3000", Duration::from_secs(120))?
+//!     .with_api_key(api_key);
+//! let report = runner.run(&suite).await;
+//! assert!(report.passed(), "{:#?}", report.cases);
+//! ```
+
+/// JUnit XML reports.
+pub mod junit;
+/// Running suites against a server.
+pub mod runner;
+/// Suite definitions and assertions.
+pub mod suite;
+
+pub use runner::{CaseReport, EvalRunner, SuiteReport};
+pub use suite::{Case, Suite, Turn, TurnOutcome};
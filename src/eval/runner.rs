@@ -0,0 +1,239 @@
+//! Running suites against a server over its HTTP API.
+
+use super::suite::{Case, Suite, Turn, TurnOutcome};
+use crate::types::{AppError, Result};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+/// Runs eval suites against a server.
+pub struct EvalRunner {
+    client: reqwest::Client,
+    target: String,
+    api_key: Option<String>,
+    token: Option<String>,
+}
+
+/// Outcome of one case.
+#[derive(Debug, Clone)]
+pub struct CaseReport {
+    /// Case name
+    pub name: String,
+    /// Failed assertions, prefixed by their turn
+    pub failures: Vec<String>,
+    /// Why the case couldn't be run to the end, if it couldn't
+    pub error: Option<String>,
+    /// Time taken by the case
+    pub elapsed: Duration,
+}
+
+impl CaseReport {
+    /// Whether every turn ran and passed its assertions
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty() && self.error.is_none()
+    }
+}
+
+/// Outcome of a suite.
+#[derive(Debug, Clone)]
+pub struct SuiteReport {
+    /// Suite name
+    pub name: String,
+    /// Outcome of each case, in order
+    pub cases: Vec<CaseReport>,
+    /// Time taken by the suite
+    pub elapsed: Duration,
+}
+
+impl SuiteReport {
+    /// Whether every case passed
+    pub fn passed(&self) -> bool {
+        self.cases.iter().all(CaseReport::passed)
+    }
+
+    /// Cases that ran but failed an assertion
+    pub fn failures(&self) -> usize {
+        self.cases
+            .iter()
+            .filter(|c| c.error.is_none() && !c.failures.is_empty())
+            .count()
+    }
+
+    /// Cases that couldn't be run to the end
+    pub fn errors(&self) -> usize {
+        self.cases.iter().filter(|c| c.error.is_some()).count()
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatReply {
+    response: String,
+    agent: String,
+    context_id: String,
+    message_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TraceReply {
+    tool_calls: Vec<TracedCall>,
+}
+
+#[derive(Deserialize)]
+struct TracedCall {
+    tool: String,
+}
+
+impl EvalRunner {
+    /// Create a runner for the server at `target`, giving up on a request
+    /// after `timeout`.
+    pub fn new(target: impl Into<String>, timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(Self {
+            client,
+            target: target.into().trim_end_matches('/').to_string(),
+            api_key: None,
+            token: None,
+        })
+    }
+
+    /// Authenticate with a user API key
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Authenticate with a JWT
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Run every case of `suite`, one after the other.
+    pub async fn run(&self, suite: &Suite) -> SuiteReport {
+        let started = Instant::now();
+        let mut cases = Vec::with_capacity(suite.cases.len());
+        for case in &suite.cases {
+            cases.push(self.run_case(suite, case).await);
+        }
+        SuiteReport {
+            name: suite.name.clone(),
+            cases,
+            elapsed: started.elapsed(),
+        }
+    }
+
+    /// Send a case's turns in one conversation, checking each answer.
+    ///
+    /// A turn the server doesn't answer ends the case with an error.
+    pub async fn run_case(&self, suite: &Suite, case: &Case) -> CaseReport {
+        let started = Instant::now();
+        let mut context_id = None;
+        let mut failures = Vec::new();
+        let mut error = None;
+        for (i, turn) in case.turns.iter().enumerate() {
+            match self
+                .send(turn, case.agent_type(suite), context_id.as_deref())
+                .await
+            {
+                Ok((outcome, context)) => {
+                    context_id = Some(context);
+                    failures.extend(
+                        turn.check(&outcome)
+                            .into_iter()
+                            .map(|failure| format!("turn {}: {}", i + 1, failure)),
+                    );
+                }
+                Err(e) => {
+                    error = Some(format!("turn {}: {}", i + 1, e));
+                    break;
+                }
+            }
+        }
+        CaseReport {
+            name: case.name.clone(),
+            failures,
+            error,
+            elapsed: started.elapsed(),
+        }
+    }
+
+    /// Send a turn, returning what the server did and the conversation ID
+    async fn send(
+        &self,
+        turn: &Turn,
+        agent_type: Option<&str>,
+        context_id: Option<&str>,
+    ) -> std::result::Result<(TurnOutcome, String), String> {
+        let mut body = json!({ "message": turn.user });
+        if let Some(agent) = agent_type {
+            body["agent_type"] = json!(agent);
+        }
+        if let Some(context_id) = context_id {
+            body["context_id"] = json!(context_id);
+        }
+
+        let started = Instant::now();
+        let reply: ChatReply = self
+            .request(
+                self.client
+                    .post(format!("{}/api/chat", self.target))
+                    .json(&body),
+            )
+            .await?;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        let tools = match (turn.checks_tools(), &reply.message_id) {
+            (false, _) => Vec::new(),
+            (true, Some(message_id)) => {
+                let url = format!(
+                    "{}/api/conversations/{}/messages/{}/trace",
+                    self.target, reply.context_id, message_id
+                );
+                let trace: TraceReply = self.request(self.client.get(url)).await?;
+                trace.tool_calls.into_iter().map(|call| call.tool).collect()
+            }
+            (true, None) => {
+                return Err("the answer has no message_id to look up its tool calls".to_string())
+            }
+        };
+
+        Ok((
+            TurnOutcome {
+                response: reply.response,
+                agent: reply.agent,
+                tools,
+                latency_ms,
+            },
+            reply.context_id,
+        ))
+    }
+
+    async fn request<T: serde::de::DeserializeOwned>(
+        &self,
+        mut request: reqwest::RequestBuilder,
+    ) -> std::result::Result<T, String> {
+        if let Some(key) = &self.api_key {
+            request = request.header("X-API-Key", key);
+        }
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("request failed: {}", e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let body: String = body.chars().take(500).collect();
+            return Err(format!("HTTP {}: {}", status, body));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| format!("unexpected response: {}", e))
+    }
+}
@@ -0,0 +1,278 @@
+//! Eval suite definitions, loaded from TOML.
+
+use crate::types::{AppError, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::path::Path;
+
+/// A named set of conversations checked against a server.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suite {
+    /// Suite name (default: the file name)
+    #[serde(default)]
+    pub name: String,
+    /// Agent the conversations are sent to (default: routed)
+    #[serde(default)]
+    pub agent_type: Option<String>,
+    /// The conversations
+    #[serde(default)]
+    pub cases: Vec<Case>,
+}
+
+/// One conversation: user turns sent in order, with what each answer must
+/// satisfy.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Case {
+    /// Case name, reported as the test case
+    pub name: String,
+    /// Agent the turns are sent to (default: the suite's)
+    #[serde(default)]
+    pub agent_type: Option<String>,
+    /// User turns, in order
+    pub turns: Vec<Turn>,
+}
+
+/// A user message and the assertions on its answer.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Turn {
+    /// Message sent as the user
+    pub user: String,
+    /// Agent that must answer
+    #[serde(default)]
+    pub agent: Option<String>,
+    /// Text the answer must contain, ignoring case
+    #[serde(default)]
+    pub contains: Vec<String>,
+    /// Text the answer must not contain, ignoring case
+    #[serde(default)]
+    pub not_contains: Vec<String>,
+    /// Regular expression the answer must match
+    #[serde(default)]
+    pub matches: Option<String>,
+    /// Tools that must be called while answering
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Tools that must not be called while answering
+    #[serde(default)]
+    pub forbid_tools: Vec<String>,
+    /// Longest the answer may take, in milliseconds
+    #[serde(default)]
+    pub max_latency_ms: Option<u64>,
+}
+
+/// What the server did with a turn.
+#[derive(Debug, Clone, Default)]
+pub struct TurnOutcome {
+    /// The answer
+    pub response: String,
+    /// Agent that answered
+    pub agent: String,
+    /// Tools called while answering, in order
+    pub tools: Vec<String>,
+    /// Time taken to answer, in milliseconds
+    pub latency_ms: u64,
+}
+
+impl Suite {
+    /// Parse a suite, naming it `default_name` unless it sets a name.
+    pub fn from_toml(source: &str, default_name: &str) -> Result<Self> {
+        let mut suite: Suite =
+            toml::from_str(source).map_err(|e| AppError::InvalidInput(e.to_string()))?;
+        if suite.name.trim().is_empty() {
+            suite.name = default_name.to_string();
+        }
+        suite.validate()?;
+        Ok(suite)
+    }
+
+    /// Load a suite from a TOML file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path).map_err(|e| {
+            AppError::InvalidInput(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let default_name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self::from_toml(&source, &default_name).map_err(|e| match e {
+            AppError::InvalidInput(msg) => {
+                AppError::InvalidInput(format!("{}: {}", path.display(), msg))
+            }
+            e => e,
+        })
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.cases.is_empty() {
+            return Err(AppError::InvalidInput(format!(
+                "Suite '{}' has no cases",
+                self.name
+            )));
+        }
+        for case in &self.cases {
+            if case.turns.is_empty() {
+                return Err(AppError::InvalidInput(format!(
+                    "Case '{}' has no turns",
+                    case.name
+                )));
+            }
+            for pattern in case.turns.iter().filter_map(|t| t.matches.as_deref()) {
+                Regex::new(pattern).map_err(|e| {
+                    AppError::InvalidInput(format!(
+                        "Case '{}' has an invalid pattern '{}': {}",
+                        case.name, pattern, e
+                    ))
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Case {
+    /// Agent the case's turns are sent to
+    pub fn agent_type<'a>(&'a self, suite: &'a Suite) -> Option<&'a str> {
+        self.agent_type.as_deref().or(suite.agent_type.as_deref())
+    }
+}
+
+impl Turn {
+    /// Whether checking the turn needs the tools called while answering
+    pub fn checks_tools(&self) -> bool {
+        !self.tools.is_empty() || !self.forbid_tools.is_empty()
+    }
+
+    /// The assertions `outcome` fails, as messages
+    pub fn check(&self, outcome: &TurnOutcome) -> Vec<String> {
+        let mut failures = Vec::new();
+        let response = outcome.response.to_lowercase();
+
+        if let Some(agent) = &self.agent {
+            if &outcome.agent != agent {
+                failures.push(format!(
+                    "answered by '{}', expected '{}'",
+                    outcome.agent, agent
+                ));
+            }
+        }
+        for text in &self.contains {
+            if !response.contains(&text.to_lowercase()) {
+                failures.push(format!("answer doesn't contain \"{}\"", text));
+            }
+        }
+        for text in &self.not_contains {
+            if response.contains(&text.to_lowercase()) {
+                failures.push(format!("answer contains \"{}\"", text));
+            }
+        }
+        if let Some(pattern) = &self.matches {
+            // Validated when the suite was loaded
+            if let Ok(regex) = Regex::new(pattern) {
+                if !regex.is_match(&outcome.response) {
+                    failures.push(format!("answer doesn't match /{}/", pattern));
+                }
+            }
+        }
+        for tool in &self.tools {
+            if !outcome.tools.contains(tool) {
+                failures.push(format!(
+                    "tool '{}' wasn't called (called: {})",
+                    tool,
+                    called(&outcome.tools)
+                ));
+            }
+        }
+        for tool in &self.forbid_tools {
+            if outcome.tools.contains(tool) {
+                failures.push(format!("tool '{}' was called", tool));
+            }
+        }
+        if let Some(max) = self.max_latency_ms {
+            if outcome.latency_ms > max {
+                failures.push(format!(
+                    "answered in {} ms, over {} ms",
+                    outcome.latency_ms, max
+                ));
+            }
+        }
+        failures
+    }
+}
+
+fn called(tools: &[String]) -> String {
+    match tools.is_empty() {
+        true => "none".to_string(),
+        false => tools.join(", "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUITE: &str = r#"
+agent_type = "product"
+
+[[cases]]
+name = "refund policy"
+
+[[cases.turns]]
+user = "What's your refund policy?"
+contains = ["30 days"]
+tools = ["search_docs"]
+
+[[cases.turns]]
+user = "And for digital goods?"
+not_contains = ["I don't know"]
+matches = "(?i)refund"
+"#;
+
+    #[test]
+    fn test_suite_parsing() {
+        let suite = Suite::from_toml(SUITE, "support").unwrap();
+        assert_eq!(suite.name, "support");
+        let case = &suite.cases[0];
+        assert_eq!(case.agent_type(&suite), Some("product"));
+        assert_eq!(case.turns.len(), 2);
+        assert!(case.turns[0].checks_tools());
+        assert!(!case.turns[1].checks_tools());
+
+        assert!(Suite::from_toml("name = \"empty\"", "x").is_err());
+        assert!(Suite::from_toml(&SUITE.replace("contains", "contain"), "x").is_err());
+        assert!(Suite::from_toml(&SUITE.replace("(?i)refund", "(refund"), "x").is_err());
+    }
+
+    #[test]
+    fn test_turn_assertions() {
+        let turn = Turn {
+            user: "What's your refund policy?".to_string(),
+            agent: Some("product".to_string()),
+            contains: vec!["30 Days".to_string()],
+            not_contains: vec!["sorry".to_string()],
+            matches: Some(r"\d+ days".to_string()),
+            tools: vec!["search_docs".to_string()],
+            forbid_tools: vec!["web_search".to_string()],
+            max_latency_ms: Some(1000),
+        };
+        let mut outcome = TurnOutcome {
+            response: "Refunds are accepted within 30 days.".to_string(),
+            agent: "product".to_string(),
+            tools: vec!["search_docs".to_string()],
+            latency_ms: 800,
+        };
+        assert!(turn.check(&outcome).is_empty());
+
+        outcome.response = "Sorry, no refunds.".to_string();
+        outcome.agent = "general".to_string();
+        outcome.tools = vec!["web_search".to_string()];
+        outcome.latency_ms = 1500;
+        let failures = turn.check(&outcome);
+        assert_eq!(failures.len(), 7, "{:?}", failures);
+        assert!(
+            failures.contains(&"tool 'search_docs' wasn't called (called: web_search)".to_string())
+        );
+    }
+}
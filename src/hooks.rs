@@ -0,0 +1,263 @@
+//! Conversation Hooks
+//!
+//! Hooks let library embedders inject custom logic (billing, analytics,
+//! custom retrieval, redaction) into the chat pipeline without forking the
+//! handlers. Register implementations of [`ConversationHook`] with
+//! [`AresBuilder::with_hook`](crate::AresBuilder::with_hook); they travel
+//! with each request in [`AgentContext::hooks`].
+//!
+//! Hooks run in registration order at four points:
+//!
+//! | Hook | When | May modify |
+//! |------|------|------------|
+//! | `on_message_received` | Before routing | The user message |
+//! | `on_before_llm` | Before an agent's generation | The prompt messages |
+//! | `on_tool_result` | After each tool call in a [`ToolCoordinator`](crate::llm::ToolCoordinator) | The tool result |
+//! | `on_response` | Before the response is stored and returned | The response text |
+//!
+//! Returning an error from a hook aborts the request with that error.
+//! For streamed chats, `on_response` sees the complete text after the last
+//! token has been sent; changes only affect the stored message.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use ares::hooks::ConversationHook;
+//! use ares::types::{AgentContext, Result};
+//!
+//! struct Analytics;
+//!
+//! #[async_trait::async_trait]
+//! impl ConversationHook for Analytics {
+//!     fn name(&self) -> &str {
+//!         "analytics"
+//!     }
+//!
+//!     async fn on_response(&self, ctx: &AgentContext, agent: &str, response: &mut String) -> Result<()> {
+//!         tracing::info!(user = %ctx.user_id, agent, chars = response.len(), "response");
+//!         Ok(())
+//!     }
+//! }
+//! ```
+
+use crate::llm::coordinator::ToolCallRecord;
+use crate::types::{AgentContext, Result};
+use async_trait::async_trait;
+use std::fmt;
+use std::sync::Arc;
+
+/// Custom logic invoked at fixed points of the chat pipeline.
+///
+/// Every method has a no-op default, so implementations only override the
+/// points they care about.
+#[async_trait]
+pub trait ConversationHook: Send + Sync {
+    /// Hook name, used in logs.
+    fn name(&self) -> &str;
+
+    /// Called with the user's message before it is routed to an agent.
+    async fn on_message_received(&self, _ctx: &AgentContext, _message: &mut String) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called with the `(role, content)` prompt messages before `agent` calls the LLM.
+    async fn on_before_llm(
+        &self,
+        _ctx: &AgentContext,
+        _agent: &str,
+        _messages: &mut Vec<(String, String)>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after each tool call, before its result is sent back to the model.
+    async fn on_tool_result(
+        &self,
+        _ctx: &AgentContext,
+        _record: &mut ToolCallRecord,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called with the final response of `agent` before it is stored and returned.
+    async fn on_response(
+        &self,
+        _ctx: &AgentContext,
+        _agent: &str,
+        _response: &mut String,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// An ordered set of [`ConversationHook`]s.
+#[derive(Clone, Default)]
+pub struct ConversationHooks {
+    hooks: Vec<Arc<dyn ConversationHook>>,
+}
+
+impl ConversationHooks {
+    /// Create an empty hook set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a hook; hooks run in the order they were registered
+    pub fn register(&mut self, hook: Arc<dyn ConversationHook>) {
+        self.hooks.push(hook);
+    }
+
+    /// Number of registered hooks
+    pub fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    /// Whether no hooks are registered
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Run every `on_message_received` hook
+    pub async fn message_received(&self, ctx: &AgentContext, message: &mut String) -> Result<()> {
+        for hook in &self.hooks {
+            hook.on_message_received(ctx, message).await?;
+        }
+        Ok(())
+    }
+
+    /// Run every `on_before_llm` hook
+    pub async fn before_llm(
+        &self,
+        ctx: &AgentContext,
+        agent: &str,
+        messages: &mut Vec<(String, String)>,
+    ) -> Result<()> {
+        for hook in &self.hooks {
+            hook.on_before_llm(ctx, agent, messages).await?;
+        }
+        Ok(())
+    }
+
+    /// Run every `on_tool_result` hook
+    pub async fn tool_result(&self, ctx: &AgentContext, record: &mut ToolCallRecord) -> Result<()> {
+        for hook in &self.hooks {
+            hook.on_tool_result(ctx, record).await?;
+        }
+        Ok(())
+    }
+
+    /// Run every `on_response` hook
+    pub async fn response(
+        &self,
+        ctx: &AgentContext,
+        agent: &str,
+        response: &mut String,
+    ) -> Result<()> {
+        for hook in &self.hooks {
+            hook.on_response(ctx, agent, response).await?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ConversationHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.hooks.iter().map(|h| h.name()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::cancellation::CancellationToken;
+    use crate::types::AppError;
+
+    struct Suffix(&'static str);
+
+    #[async_trait]
+    impl ConversationHook for Suffix {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        async fn on_message_received(
+            &self,
+            _ctx: &AgentContext,
+            message: &mut String,
+        ) -> Result<()> {
+            message.push_str(self.0);
+            Ok(())
+        }
+
+        async fn on_response(
+            &self,
+            _ctx: &AgentContext,
+            agent: &str,
+            response: &mut String,
+        ) -> Result<()> {
+            if agent == "blocked" {
+                return Err(AppError::InvalidInput(format!("{} rejected", self.0)));
+            }
+            response.push_str(self.0);
+            Ok(())
+        }
+    }
+
+    fn context(hooks: ConversationHooks) -> AgentContext {
+        AgentContext {
+            user_id: "user".to_string(),
+            session_id: "session".to_string(),
+            conversation_history: vec![],
+            user_memory: None,
+            cancellation: CancellationToken::new(),
+            hooks: Arc::new(hooks),
+            preferences: Default::default(),
+            tool_profile: None,
+            bus: None,
+            workspace_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hooks_run_in_registration_order() {
+        let mut hooks = ConversationHooks::new();
+        hooks.register(Arc::new(Suffix("-a")));
+        hooks.register(Arc::new(Suffix("-b")));
+        let ctx = context(hooks);
+
+        let mut message = "hi".to_string();
+        ctx.hooks
+            .message_received(&ctx, &mut message)
+            .await
+            .unwrap();
+        assert_eq!(message, "hi-a-b");
+
+        // Unimplemented points are no-ops
+        let mut messages = vec![("user".to_string(), "hi".to_string())];
+        ctx.hooks
+            .before_llm(&ctx, "product", &mut messages)
+            .await
+            .unwrap();
+        assert_eq!(messages.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_hook_error_aborts() {
+        let mut hooks = ConversationHooks::new();
+        hooks.register(Arc::new(Suffix("-a")));
+        hooks.register(Arc::new(Suffix("-b")));
+        let ctx = context(hooks);
+
+        let mut response = "ok".to_string();
+        let err = ctx
+            .hooks
+            .response(&ctx, "blocked", &mut response)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("-a rejected"));
+        assert_eq!(response, "ok");
+        assert_eq!(format!("{:?}", ctx.hooks), r#"["-a", "-b"]"#);
+    }
+}
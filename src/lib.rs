@@ -94,6 +94,24 @@
 //! );
 //! ```
 //!
+//! ### Embedding the Server
+//!
+//! ```rust,ignore
+//! use ares::AresBuilder;
+//!
+//! // Assemble registries, database and auth without ares.toml
+//! let ares = AresBuilder::new()
+//!     .with_provider("ollama", provider_config)
+//!     .with_model("default", model_config)
+//!     .with_agent("orchestrator", agent_config)
+//!     .with_database_url("postgres://localhost/ares")
+//!     .with_jwt_secret(jwt_secret)
+//!     .build()
+//!     .await?;
+//!
+//! let app = axum::Router::new().nest("/ares", ares.router());
+//! ```
+//!
 //! ## Feature Flags
 //!
 //! | Feature | Description |
@@ -110,7 +128,9 @@
 //! - [`agents`] - Agent framework for multi-agent orchestration
 //! - [`api`] - REST API handlers and routes
 //! - [`auth`] - JWT authentication and middleware
+//! - [`builder`] - Programmatic server construction
 //! - [`db`] - Database abstraction (PostgreSQL)
+//! - [`hooks`] - Conversation hooks for custom pipeline logic
 //! - [`llm`] - LLM client implementations
 //! - [`tools`] - Tool definitions and registry
 //! - [`workflows`] - Declarative workflow engine
@@ -135,10 +155,16 @@ pub mod agents;
 pub mod api;
 /// JWT authentication and middleware.
 pub mod auth;
+/// Programmatic server construction for embedding ARES.
+pub mod builder;
 /// Command-line interface and scaffolding.
 pub mod cli;
 /// Database clients (Turso/SQLite, Qdrant).
 pub mod db;
+/// Declarative eval suites for end-to-end chat flows.
+pub mod eval;
+/// Conversation hooks for injecting custom logic into the chat pipeline.
+pub mod hooks;
 /// LLM provider clients and abstractions.
 pub mod llm;
 /// Model Context Protocol (MCP) server integration.
@@ -164,9 +190,11 @@ pub mod utils;
 pub mod workflows;
 
 // Re-export commonly used types
-pub use agents::{AgentRegistry, AgentRegistryBuilder};
+pub use agents::{AgentHook, AgentHooks, AgentRegistry, AgentRegistryBuilder};
+pub use builder::{Ares, AresBuilder};
 pub use db::tenants::TenantDb;
 pub use db::PostgresClient;
+pub use hooks::{ConversationHook, ConversationHooks};
 pub use llm::client::LLMClientFactoryTrait;
 pub use llm::{
     ConfigBasedLLMFactory, LLMClient, LLMClientFactory, LLMResponse, Provider, ProviderRegistry,
@@ -207,4 +235,22 @@ pub struct AppState {
     pub mcp_registry: Option<Arc<crate::mcp::McpRegistry>>,
     /// Deploy registry for tracking deployment operations
     pub deploy_registry: crate::api::handlers::deploy::DeployRegistry,
+    /// Conversation hooks run by the chat pipeline
+    pub hooks: Arc<ConversationHooks>,
+    /// Loaders extracting the text of uploaded files
+    pub loaders: Arc<crate::rag::loaders::DocumentLoaders>,
+    /// In-flight generations, so they can be stopped by conversation id
+    pub generations: crate::llm::cancellation::ActiveGenerations,
+    /// Runtime maintenance mode switch
+    pub maintenance: crate::api::maintenance::Maintenance,
+    /// Token buckets of the per-route rate limits
+    pub rate_limits: crate::middleware::rate_limit::RateLimiter,
+    /// Progress of the startup model warm-up
+    pub warmup: crate::llm::warmup::Warmup,
+    /// Cached dependency checks of the readiness probe
+    pub health: crate::api::health::Health,
+    /// Signer of the tokens presented to called MCP servers and agents
+    pub service_tokens: Arc<crate::auth::service_tokens::ServiceTokens>,
+    /// Where login sessions and their refresh tokens are kept
+    pub sessions: crate::db::sessions::SessionStore,
 }
@@ -505,6 +505,7 @@ mod tests {
             top_p: Some(0.9),
             frequency_penalty: None,
             presence_penalty: None,
+            seed: None,
         };
 
         let client = AnthropicClient::with_params(
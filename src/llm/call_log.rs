@@ -0,0 +1,158 @@
+//! LLM call log
+//!
+//! [`CallLog`] is an [`LLMMiddleware`] appending every LLM call to a JSONL
+//! file, one [`CallRecord`] per line. It is enabled with `llm_call_log` in
+//! `[server]`, and the log is what `ares-server loadtest` replays.
+
+use crate::llm::client::LLMResponse;
+use crate::llm::coordinator::{ConversationMessage, MessageRole};
+use crate::llm::middleware::{LLMMiddleware, LLMRequest};
+use crate::types::{AppError, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// One logged LLM call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallRecord {
+    /// When the call finished (Unix timestamp)
+    pub timestamp: i64,
+    /// Model identifier of the client
+    pub model: String,
+    /// Whether the response was streamed
+    pub stream: bool,
+    /// Prompt messages, including any system prompt
+    pub messages: Vec<ConversationMessage>,
+    /// Names of the tools offered to the model
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+    /// Length of the response text, in characters
+    pub response_chars: usize,
+    /// Number of tool calls the model requested
+    #[serde(default)]
+    pub tool_calls: usize,
+}
+
+impl CallRecord {
+    /// The last user message of the call, if any
+    pub fn user_message(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|m| m.role == MessageRole::User)
+            .map(|m| m.content.as_str())
+    }
+}
+
+/// Middleware appending every LLM call to a JSONL file.
+pub struct CallLog {
+    file: Mutex<tokio::fs::File>,
+}
+
+impl CallLog {
+    /// Open `path` for appending, creating it and its directory if needed
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let failed = |e: std::io::Error| {
+            AppError::Configuration(format!(
+                "Failed to open LLM call log {}: {}",
+                path.display(),
+                e
+            ))
+        };
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir).await.map_err(failed)?;
+        }
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .map_err(failed)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+#[async_trait]
+impl LLMMiddleware for CallLog {
+    fn name(&self) -> &str {
+        "call_log"
+    }
+
+    async fn after_response(&self, request: &LLMRequest, response: &mut LLMResponse) -> Result<()> {
+        let record = CallRecord {
+            timestamp: Utc::now().timestamp(),
+            model: request.model.clone(),
+            stream: request.stream,
+            messages: request.messages.clone(),
+            tools: request.tools.iter().map(|t| t.name.clone()).collect(),
+            response_chars: response.content.chars().count(),
+            tool_calls: response.tool_calls.len(),
+        };
+        let mut line = serde_json::to_string(&record)
+            .map_err(|e| AppError::Internal(format!("Failed to encode LLM call: {}", e)))?;
+        line.push('\n');
+
+        // A failed write must not fail the call it logs
+        if let Err(e) = self.file.lock().await.write_all(line.as_bytes()).await {
+            tracing::warn!("Failed to write LLM call log: {}", e);
+        }
+        Ok(())
+    }
+}
+
+/// Read the calls of a call log, skipping lines that aren't records.
+pub fn read_records(path: impl AsRef<Path>) -> Result<Vec<CallRecord>> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        AppError::InvalidInput(format!(
+            "Failed to read LLM call log {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    Ok(parse_records(&content))
+}
+
+fn parse_records(content: &str) -> Vec<CallRecord> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_round_trip_and_skip_bad_lines() {
+        let record = CallRecord {
+            timestamp: 1,
+            model: "mock".to_string(),
+            stream: false,
+            messages: vec![
+                ConversationMessage::system("Be brief"),
+                ConversationMessage::user("What is RAG?"),
+                ConversationMessage::assistant("Retrieval.", vec![]),
+            ],
+            tools: vec![],
+            response_chars: 10,
+            tool_calls: 0,
+        };
+        let log = format!(
+            "{}\nnot json\n\n{}\n",
+            serde_json::to_string(&record).unwrap(),
+            serde_json::to_string(&record).unwrap()
+        );
+        let records = parse_records(&log);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].user_message(), Some("What is RAG?"));
+    }
+}
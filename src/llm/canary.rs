@@ -0,0 +1,683 @@
+//! Canary routing between models.
+//!
+//! A canary sends a share of a model's calls to another model, so an
+//! upgrade can be validated on live traffic before it replaces the model:
+//!
+//! ```toml
+//! [canaries.balanced]
+//! model = "balanced-next"   # Receives 10% of the calls for "balanced"
+//! percent = 10
+//! judge = "quality"         # Optional: score the answers of both arms
+//! ```
+//!
+//! The [`ProviderRegistry`](crate::llm::ProviderRegistry) picks an arm for every client it creates for a
+//! model with a canary, and measures the client's calls: requests, errors,
+//! latency, tokens and cost (from `[budgets.pricing]`), and with a judge
+//! the average score of the answers, judged in the background. Token counts
+//! are estimated when the provider does not report them. Both arms are
+//! measured the same way, so [`CanaryRouter::reports`] compares them like
+//! for like.
+
+use crate::llm::client::{LLMClient, LLMResponse};
+use crate::llm::coordinator::{ConversationMessage, MessageRole};
+use crate::llm::judge::Judge;
+use crate::memory::estimate_tokens;
+use crate::types::{Result, ToolDefinition};
+use crate::utils::toml_config::{CanaryConfig, JudgeConfig, ModelPricing};
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use tokio::sync::OnceCell;
+use utoipa::ToSchema;
+
+type TokenStream = Box<dyn Stream<Item = Result<String>> + Send + Unpin>;
+
+/// Arm of a canary a call was routed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CanaryArm {
+    /// The model the call was made for
+    Stable,
+    /// The canary model
+    Canary,
+}
+
+/// Running totals of an arm's calls.
+#[derive(Debug, Clone, Copy, Default)]
+struct ArmStats {
+    requests: u64,
+    errors: u64,
+    latency_ms: u64,
+    input_tokens: u64,
+    output_tokens: u64,
+    cost_usd: f64,
+    judged: u64,
+    score: f64,
+}
+
+/// Call metrics of canary arms, keyed by the model routed and the arm.
+#[derive(Debug, Default)]
+pub struct CanaryMetrics {
+    arms: Mutex<HashMap<(String, CanaryArm), ArmStats>>,
+}
+
+impl CanaryMetrics {
+    /// Metrics shared by every registry of the process, so clients created
+    /// by different registries are counted together.
+    pub fn shared() -> Arc<Self> {
+        static SHARED: OnceLock<Arc<CanaryMetrics>> = OnceLock::new();
+        Arc::clone(SHARED.get_or_init(|| Arc::new(Self::default())))
+    }
+
+    fn update(&self, model: &str, arm: CanaryArm, update: impl FnOnce(&mut ArmStats)) {
+        update(
+            self.arms
+                .lock()
+                .entry((model.to_string(), arm))
+                .or_default(),
+        );
+    }
+
+    fn stats(&self, model: &str, arm: CanaryArm) -> ArmStats {
+        self.arms
+            .lock()
+            .get(&(model.to_string(), arm))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Metrics of one arm of a canary.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ArmReport {
+    /// Model name from \[models\] serving the arm
+    pub model: String,
+    /// Configured percentage of calls routed to the arm
+    pub percent: f32,
+    /// Calls made
+    pub requests: u64,
+    /// Calls that failed
+    pub errors: u64,
+    /// Share of calls that failed, 0 to 1
+    pub error_rate: f64,
+    /// Average call duration in milliseconds
+    pub avg_latency_ms: f64,
+    /// Input tokens of the successful calls
+    pub input_tokens: u64,
+    /// Output tokens of the successful calls
+    pub output_tokens: u64,
+    /// Estimated spend of the arm in USD
+    pub cost_usd: f64,
+    /// Estimated spend per successful call in USD
+    pub avg_cost_usd: f64,
+    /// Answers scored by the canary's judge
+    pub judged: u64,
+    /// Average judge score of the answers, 0 to 10
+    pub avg_score: Option<f64>,
+}
+
+impl ArmReport {
+    fn new(model: &str, percent: f32, stats: ArmStats) -> Self {
+        let ratio = |total: f64, count: u64| match count {
+            0 => 0.0,
+            count => total / count as f64,
+        };
+        let succeeded = stats.requests - stats.errors;
+        Self {
+            model: model.to_string(),
+            percent,
+            requests: stats.requests,
+            errors: stats.errors,
+            error_rate: ratio(stats.errors as f64, stats.requests),
+            avg_latency_ms: ratio(stats.latency_ms as f64, stats.requests),
+            input_tokens: stats.input_tokens,
+            output_tokens: stats.output_tokens,
+            cost_usd: stats.cost_usd,
+            avg_cost_usd: ratio(stats.cost_usd, succeeded),
+            judged: stats.judged,
+            avg_score: (stats.judged > 0).then(|| ratio(stats.score, stats.judged)),
+        }
+    }
+}
+
+/// Side-by-side metrics of a model and its canary.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct CanaryReport {
+    /// Model name whose calls are split
+    pub model: String,
+    /// Calls served by the model itself
+    pub stable: ArmReport,
+    /// Calls served by the canary model
+    pub canary: ArmReport,
+    /// Judge scoring the answers, if any
+    pub judge: Option<String>,
+}
+
+/// Splits the calls of models with a canary between their arms.
+pub struct CanaryRouter {
+    canaries: HashMap<String, CanaryConfig>,
+    pricing: HashMap<String, ModelPricing>,
+    judges: HashMap<String, JudgeConfig>,
+    /// Judge of each canary, created on first use
+    judge_clients: HashMap<String, OnceCell<Arc<Judge>>>,
+    metrics: Arc<CanaryMetrics>,
+}
+
+impl CanaryRouter {
+    /// Route the canaries, pricing calls with `pricing` and scoring with the
+    /// canaries' `judges`.
+    pub fn new(
+        canaries: HashMap<String, CanaryConfig>,
+        pricing: HashMap<String, ModelPricing>,
+        judges: HashMap<String, JudgeConfig>,
+        metrics: Arc<CanaryMetrics>,
+    ) -> Self {
+        let judge_clients = canaries
+            .iter()
+            .filter(|(_, canary)| canary.judge.is_some())
+            .map(|(model, _)| (model.clone(), OnceCell::new()))
+            .collect();
+        Self {
+            canaries,
+            pricing,
+            judges,
+            judge_clients,
+            metrics,
+        }
+    }
+
+    /// The canary of `model`, if it has one
+    pub fn canary(&self, model: &str) -> Option<&CanaryConfig> {
+        self.canaries.get(model)
+    }
+
+    /// Pick the arm of a call for `model` from a roll between 0 and 100.
+    ///
+    /// Returns the arm and the model serving it, or `None` if `model` has no
+    /// canary.
+    pub fn pick(&self, model: &str, roll: f32) -> Option<(CanaryArm, &str)> {
+        let (model, canary) = self.canaries.get_key_value(model)?;
+        Some(if roll < canary.percent {
+            (CanaryArm::Canary, canary.model.as_str())
+        } else {
+            (CanaryArm::Stable, model.as_str())
+        })
+    }
+
+    /// Pick the arm of a call for `model` at random.
+    pub fn route(&self, model: &str) -> Option<(CanaryArm, &str)> {
+        self.pick(model, rand::random::<f32>() * 100.0)
+    }
+
+    /// Judge of `model`'s canary, made by `create` from its config on first
+    /// use.
+    ///
+    /// A judge that cannot be created is retried on the next call, and its
+    /// calls go unscored meanwhile.
+    pub async fn judge<F, Fut>(&self, model: &str, create: F) -> Option<Arc<Judge>>
+    where
+        F: FnOnce(JudgeConfig) -> Fut,
+        Fut: Future<Output = Result<Judge>>,
+    {
+        let cell = self.judge_clients.get(model)?;
+        let name = self.canaries.get(model)?.judge.as_ref()?;
+        let config = self.judges.get(name)?;
+        cell.get_or_try_init(|| async { create(config.clone()).await.map(Arc::new) })
+            .await
+            .inspect_err(|e| tracing::warn!("Canary judge '{}' unavailable: {}", name, e))
+            .ok()
+            .cloned()
+    }
+
+    /// Measure the calls of `client`, serving `arm` of `model`'s canary.
+    pub fn wrap(
+        &self,
+        model: &str,
+        arm: CanaryArm,
+        client: Box<dyn LLMClient>,
+        judge: Option<Arc<Judge>>,
+    ) -> Box<dyn LLMClient> {
+        let served_by = match (arm, self.canaries.get(model)) {
+            (CanaryArm::Canary, Some(canary)) => canary.model.as_str(),
+            _ => model,
+        };
+        Box::new(CanaryClient {
+            inner: client,
+            recorder: Recorder {
+                model: model.to_string(),
+                arm,
+                pricing: self.pricing.get(served_by).copied().unwrap_or_default(),
+                metrics: Arc::clone(&self.metrics),
+                judge,
+            },
+        })
+    }
+
+    /// Metrics of every canary, by model name.
+    pub fn reports(&self) -> Vec<CanaryReport> {
+        let mut reports = self
+            .canaries
+            .iter()
+            .map(|(model, canary)| CanaryReport {
+                model: model.clone(),
+                stable: ArmReport::new(
+                    model,
+                    100.0 - canary.percent,
+                    self.metrics.stats(model, CanaryArm::Stable),
+                ),
+                canary: ArmReport::new(
+                    &canary.model,
+                    canary.percent,
+                    self.metrics.stats(model, CanaryArm::Canary),
+                ),
+                judge: canary.judge.clone(),
+            })
+            .collect::<Vec<_>>();
+        reports.sort_by(|a, b| a.model.cmp(&b.model));
+        reports
+    }
+}
+
+impl Default for CanaryRouter {
+    fn default() -> Self {
+        Self::new(
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            Arc::new(CanaryMetrics::default()),
+        )
+    }
+}
+
+/// Records the calls of one canary arm.
+#[derive(Clone)]
+struct Recorder {
+    model: String,
+    arm: CanaryArm,
+    pricing: ModelPricing,
+    metrics: Arc<CanaryMetrics>,
+    judge: Option<Arc<Judge>>,
+}
+
+impl Recorder {
+    /// Record a call made for `input`, `prompt_tokens` long, that finished
+    /// with `response`, or failed.
+    fn record(
+        &self,
+        started: Instant,
+        input: &str,
+        prompt_tokens: usize,
+        response: Option<&LLMResponse>,
+    ) {
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let Some(response) = response else {
+            self.metrics.update(&self.model, self.arm, |stats| {
+                stats.requests += 1;
+                stats.errors += 1;
+                stats.latency_ms += latency_ms;
+            });
+            return;
+        };
+
+        let (input_tokens, output_tokens) = match &response.usage {
+            Some(usage) => (usage.prompt_tokens as u64, usage.completion_tokens as u64),
+            None => (
+                prompt_tokens as u64,
+                estimate_tokens(&response.content) as u64,
+            ),
+        };
+        let cost = (input_tokens as f64 / 1000.0) * self.pricing.input_per_1k
+            + (output_tokens as f64 / 1000.0) * self.pricing.output_per_1k;
+        self.metrics.update(&self.model, self.arm, |stats| {
+            stats.requests += 1;
+            stats.latency_ms += latency_ms;
+            stats.input_tokens += input_tokens;
+            stats.output_tokens += output_tokens;
+            stats.cost_usd += cost;
+        });
+
+        // Tool call requests are not answers to score
+        if let Some(judge) = &self.judge {
+            if response.tool_calls.is_empty() && !response.content.trim().is_empty() {
+                let judge = Arc::clone(judge);
+                let recorder = self.clone();
+                let (input, output) = (input.to_string(), response.content.clone());
+                tokio::spawn(async move {
+                    match judge.judge(&input, &output).await {
+                        Ok(judgment) => {
+                            recorder
+                                .metrics
+                                .update(&recorder.model, recorder.arm, |stats| {
+                                    stats.judged += 1;
+                                    stats.score += judgment.score as f64;
+                                })
+                        }
+                        Err(e) => tracing::warn!("Canary judge failed: {}", e),
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// An [`LLMClient`] measuring the calls of a canary arm.
+struct CanaryClient {
+    inner: Box<dyn LLMClient>,
+    recorder: Recorder,
+}
+
+impl CanaryClient {
+    /// Make a non-streaming call, recording it.
+    async fn call(
+        &self,
+        messages: &[ConversationMessage],
+        call: impl Future<Output = Result<LLMResponse>>,
+    ) -> Result<LLMResponse> {
+        let started = Instant::now();
+        let result = call.await;
+        self.recorder.record(
+            started,
+            last_user_message(messages),
+            prompt_tokens(messages),
+            result.as_ref().ok(),
+        );
+        result
+    }
+
+    /// Open a stream, recording the call once it has finished.
+    async fn call_stream(
+        &self,
+        messages: Vec<ConversationMessage>,
+        open: impl Future<Output = Result<TokenStream>>,
+    ) -> Result<TokenStream> {
+        let started = Instant::now();
+        let stream = match open.await {
+            Ok(stream) => stream,
+            Err(e) => {
+                self.recorder.record(started, "", 0, None);
+                return Err(e);
+            }
+        };
+
+        let recorder = self.recorder.clone();
+        let stream = async_stream::stream! {
+            let mut stream = stream;
+            let mut text = String::new();
+            let mut failed = false;
+            while let Some(chunk) = stream.next().await {
+                match &chunk {
+                    Ok(token) => text.push_str(token),
+                    Err(_) => failed = true,
+                }
+                yield chunk;
+                if failed {
+                    break;
+                }
+            }
+            let response = text_response(text);
+            recorder.record(
+                started,
+                last_user_message(&messages),
+                prompt_tokens(&messages),
+                (!failed).then_some(&response),
+            );
+        };
+        Ok(Box::new(Box::pin(stream)))
+    }
+}
+
+#[async_trait]
+impl LLMClient for CanaryClient {
+    async fn generate(&self, prompt: &str) -> Result<String> {
+        let messages = [ConversationMessage::user(prompt)];
+        let call = async { self.inner.generate(prompt).await.map(text_response) };
+        Ok(self.call(&messages, call).await?.content)
+    }
+
+    async fn generate_with_system(&self, system: &str, prompt: &str) -> Result<String> {
+        let messages = [
+            ConversationMessage::system(system),
+            ConversationMessage::user(prompt),
+        ];
+        let call = async {
+            self.inner
+                .generate_with_system(system, prompt)
+                .await
+                .map(text_response)
+        };
+        Ok(self.call(&messages, call).await?.content)
+    }
+
+    async fn generate_with_history(&self, messages: &[(String, String)]) -> Result<String> {
+        let call = async {
+            self.inner
+                .generate_with_history(messages)
+                .await
+                .map(text_response)
+        };
+        Ok(self.call(&history_messages(messages), call).await?.content)
+    }
+
+    async fn generate_structured(
+        &self,
+        messages: &[(String, String)],
+        schema: &serde_json::Value,
+    ) -> Result<String> {
+        let call = async {
+            self.inner
+                .generate_structured(messages, schema)
+                .await
+                .map(text_response)
+        };
+        Ok(self.call(&history_messages(messages), call).await?.content)
+    }
+
+    async fn generate_with_tools(
+        &self,
+        prompt: &str,
+        tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        let messages = [ConversationMessage::user(prompt)];
+        self.call(&messages, self.inner.generate_with_tools(prompt, tools))
+            .await
+    }
+
+    async fn generate_with_tools_and_history(
+        &self,
+        messages: &[ConversationMessage],
+        tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        self.call(
+            messages,
+            self.inner.generate_with_tools_and_history(messages, tools),
+        )
+        .await
+    }
+
+    async fn stream(&self, prompt: &str) -> Result<TokenStream> {
+        let messages = vec![ConversationMessage::user(prompt)];
+        self.call_stream(messages, self.inner.stream(prompt)).await
+    }
+
+    async fn stream_with_system(&self, system: &str, prompt: &str) -> Result<TokenStream> {
+        let messages = vec![
+            ConversationMessage::system(system),
+            ConversationMessage::user(prompt),
+        ];
+        self.call_stream(messages, self.inner.stream_with_system(system, prompt))
+            .await
+    }
+
+    async fn stream_with_history(&self, messages: &[(String, String)]) -> Result<TokenStream> {
+        self.call_stream(
+            history_messages(messages),
+            self.inner.stream_with_history(messages),
+        )
+        .await
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+}
+
+fn text_response(content: String) -> LLMResponse {
+    LLMResponse {
+        content,
+        tool_calls: Vec::new(),
+        finish_reason: "stop".to_string(),
+        usage: None,
+    }
+}
+
+fn history_messages(messages: &[(String, String)]) -> Vec<ConversationMessage> {
+    messages
+        .iter()
+        .map(|(role, content)| ConversationMessage::from_role_content(role, content.as_str()))
+        .collect()
+}
+
+/// The request a call answers, for judging its answer.
+fn last_user_message(messages: &[ConversationMessage]) -> &str {
+    messages
+        .iter()
+        .rev()
+        .find(|m| m.role == MessageRole::User)
+        .map_or("", |m| m.content.as_str())
+}
+
+fn prompt_tokens(messages: &[ConversationMessage]) -> usize {
+    messages.iter().map(|m| estimate_tokens(&m.content)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AppError;
+
+    /// Answers with a fixed reply, or fails
+    struct FixedClient(Option<&'static str>);
+
+    #[async_trait]
+    impl LLMClient for FixedClient {
+        async fn generate(&self, _: &str) -> Result<String> {
+            self.0
+                .map(str::to_string)
+                .ok_or_else(|| AppError::LLM("unavailable".to_string()))
+        }
+        async fn generate_with_system(&self, _: &str, prompt: &str) -> Result<String> {
+            self.generate(prompt).await
+        }
+        async fn generate_with_history(&self, _: &[(String, String)]) -> Result<String> {
+            unimplemented!()
+        }
+        async fn generate_with_tools(&self, _: &str, _: &[ToolDefinition]) -> Result<LLMResponse> {
+            unimplemented!()
+        }
+        async fn generate_with_tools_and_history(
+            &self,
+            _: &[ConversationMessage],
+            _: &[ToolDefinition],
+        ) -> Result<LLMResponse> {
+            unimplemented!()
+        }
+        async fn stream(&self, _: &str) -> Result<TokenStream> {
+            Ok(Box::new(futures::stream::iter([
+                Ok("Hello ".to_string()),
+                Ok("there".to_string()),
+            ])))
+        }
+        async fn stream_with_system(&self, _: &str, _: &str) -> Result<TokenStream> {
+            unimplemented!()
+        }
+        async fn stream_with_history(&self, _: &[(String, String)]) -> Result<TokenStream> {
+            unimplemented!()
+        }
+        fn model_name(&self) -> &str {
+            "fixed"
+        }
+    }
+
+    fn router() -> CanaryRouter {
+        let canary = CanaryConfig {
+            model: "balanced-next".to_string(),
+            percent: 10.0,
+            judge: None,
+        };
+        let pricing = ModelPricing {
+            input_per_1k: 1.0,
+            output_per_1k: 2.0,
+        };
+        CanaryRouter::new(
+            HashMap::from([("balanced".to_string(), canary)]),
+            HashMap::from([("balanced-next".to_string(), pricing)]),
+            HashMap::new(),
+            Arc::new(CanaryMetrics::default()),
+        )
+    }
+
+    #[test]
+    fn test_pick_splits_by_percent() {
+        let router = router();
+        assert_eq!(
+            router.pick("balanced", 5.0),
+            Some((CanaryArm::Canary, "balanced-next"))
+        );
+        assert_eq!(
+            router.pick("balanced", 10.0),
+            Some((CanaryArm::Stable, "balanced"))
+        );
+        assert_eq!(router.pick("fast", 5.0), None);
+
+        let canary_calls = (0..1000)
+            .filter(|_| router.route("balanced").unwrap().0 == CanaryArm::Canary)
+            .count();
+        assert!((30..=200).contains(&canary_calls));
+    }
+
+    #[tokio::test]
+    async fn test_calls_are_measured_per_arm() {
+        let router = router();
+        let canary = router.wrap(
+            "balanced",
+            CanaryArm::Canary,
+            Box::new(FixedClient(Some("four words of answer"))),
+            None,
+        );
+        let stable = router.wrap(
+            "balanced",
+            CanaryArm::Stable,
+            Box::new(FixedClient(None)),
+            None,
+        );
+
+        canary.generate("12345678").await.unwrap();
+        let mut stream = canary.stream("1234").await.unwrap();
+        while stream.next().await.is_some() {}
+        assert!(stable
+            .generate_with_system("system", "prompt")
+            .await
+            .is_err());
+
+        let report = router.reports().remove(0);
+        assert_eq!(report.model, "balanced");
+        assert_eq!(report.canary.model, "balanced-next");
+        assert_eq!(report.canary.requests, 2);
+        assert_eq!(report.canary.errors, 0);
+        // 2 + 1 input tokens, 5 + 3 output tokens
+        assert_eq!(report.canary.input_tokens, 3);
+        assert_eq!(report.canary.output_tokens, 8);
+        assert!((report.canary.cost_usd - 0.019).abs() < 1e-9);
+        assert_eq!(report.canary.avg_score, None);
+
+        assert_eq!(report.stable.percent, 90.0);
+        assert_eq!(report.stable.requests, 1);
+        assert_eq!(report.stable.error_rate, 1.0);
+        assert_eq!(report.stable.cost_usd, 0.0);
+    }
+}
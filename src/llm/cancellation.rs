@@ -0,0 +1,204 @@
+//! Generation Cancellation
+//!
+//! Aborting an in-flight generation in ARES means dropping its future: for
+//! HTTP providers (Ollama, OpenAI, Anthropic) that drops the underlying
+//! reqwest call or response stream, which closes the connection and frees
+//! the server slot. Work that cannot be dropped (llama.cpp inference on a
+//! blocking thread) polls a [`CancellationToken`] instead.
+//!
+//! Handlers create a token per request and hold a drop guard, so the token
+//! is cancelled as soon as Axum drops the handler (client disconnect). The
+//! token travels to agents in [`AgentContext::cancellation`](crate::types::AgentContext),
+//! where generations run through [`run_cancellable`].
+//!
+//! Generations are also registered per conversation in [`ActiveGenerations`],
+//! which lets `POST /api/chat/{context_id}/stop` cancel a run from a separate
+//! request.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use ares::llm::cancellation::{run_cancellable, CancellationToken};
+//!
+//! let token = CancellationToken::new();
+//! let _guard = token.clone().drop_guard();
+//!
+//! let response = run_cancellable(&token, llm.generate("Hello")).await?;
+//! ```
+
+use crate::types::{AppError, Result};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+pub use tokio_util::sync::{CancellationToken, DropGuard};
+
+/// Run `future` to completion unless `token` is cancelled first.
+///
+/// On cancellation the future is dropped, aborting any request it has in
+/// flight, and [`AppError::Cancelled`] is returned.
+pub async fn run_cancellable<T, F>(token: &CancellationToken, future: F) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(AppError::Cancelled("Generation cancelled".to_string())),
+        result = future => result,
+    }
+}
+
+/// In-flight generations keyed by conversation id.
+///
+/// Cheap to clone; all clones share the same table.
+#[derive(Clone, Default)]
+pub struct ActiveGenerations {
+    entries: Arc<Mutex<HashMap<String, ActiveGeneration>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+struct ActiveGeneration {
+    id: u64,
+    user_id: String,
+    token: CancellationToken,
+}
+
+impl ActiveGenerations {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `token` as the active generation for a conversation.
+    ///
+    /// A newer generation in the same conversation replaces the older entry.
+    /// The entry is removed when the returned guard is dropped.
+    pub fn register(
+        &self,
+        context_id: &str,
+        user_id: &str,
+        token: CancellationToken,
+    ) -> GenerationGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.entries.lock().insert(
+            context_id.to_string(),
+            ActiveGeneration {
+                id,
+                user_id: user_id.to_string(),
+                token,
+            },
+        );
+        GenerationGuard {
+            generations: self.clone(),
+            context_id: context_id.to_string(),
+            id,
+        }
+    }
+
+    /// Cancel the active generation in a conversation owned by `user_id`.
+    ///
+    /// Returns `false` if that user has nothing running in the conversation.
+    pub fn stop(&self, context_id: &str, user_id: &str) -> bool {
+        match self.entries.lock().get(context_id) {
+            Some(entry) if entry.user_id == user_id => {
+                entry.token.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a generation is running in the conversation.
+    pub fn is_active(&self, context_id: &str) -> bool {
+        self.entries.lock().contains_key(context_id)
+    }
+
+    /// Number of generations running.
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    /// Whether no generation is running.
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().is_empty()
+    }
+}
+
+/// Keeps a generation registered in [`ActiveGenerations`] until dropped.
+pub struct GenerationGuard {
+    generations: ActiveGenerations,
+    context_id: String,
+    id: u64,
+}
+
+impl Drop for GenerationGuard {
+    fn drop(&mut self) {
+        let mut entries = self.generations.entries.lock();
+        if entries
+            .get(&self.context_id)
+            .is_some_and(|entry| entry.id == self.id)
+        {
+            entries.remove(&self.context_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_run_cancellable_completes() {
+        let token = CancellationToken::new();
+        let result = run_cancellable(&token, async { Ok(42) }).await;
+        assert_eq!(result.unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn test_run_cancellable_aborts_on_cancel() {
+        let token = CancellationToken::new();
+        let child = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            child.cancel();
+        });
+
+        let result: Result<()> = run_cancellable(&token, async {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            Ok(())
+        })
+        .await;
+        assert!(matches!(result, Err(AppError::Cancelled(_))));
+    }
+
+    #[test]
+    fn test_active_generations_stop_and_unregister() {
+        let generations = ActiveGenerations::new();
+        let token = CancellationToken::new();
+        let guard = generations.register("conv-1", "alice", token.clone());
+
+        assert!(!generations.stop("conv-1", "bob"));
+        assert!(!token.is_cancelled());
+        assert!(generations.stop("conv-1", "alice"));
+        assert!(token.is_cancelled());
+
+        // A newer run replaces the entry; dropping the old guard leaves it alone
+        let newer = CancellationToken::new();
+        let newer_guard = generations.register("conv-1", "alice", newer.clone());
+        drop(guard);
+        assert!(generations.is_active("conv-1"));
+        drop(newer_guard);
+        assert!(!generations.is_active("conv-1"));
+        assert!(!generations.stop("conv-1", "alice"));
+    }
+
+    #[tokio::test]
+    async fn test_drop_guard_cancels_token() {
+        let token = CancellationToken::new();
+        drop(token.clone().drop_guard());
+        assert!(token.is_cancelled());
+    }
+}
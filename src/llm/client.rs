@@ -17,6 +17,19 @@ pub trait LLMClient: Send + Sync {
         messages: &[(String, String)], // (role, content) pairs
     ) -> Result<String>;
 
+    /// Generate a JSON response conforming to a JSON Schema
+    ///
+    /// Providers with native structured output constrain generation to the
+    /// schema. The default implementation relies on the messages describing
+    /// the expected JSON and calls [`generate_with_history`](LLMClient::generate_with_history).
+    async fn generate_structured(
+        &self,
+        messages: &[(String, String)], // (role, content) pairs
+        _schema: &serde_json::Value,
+    ) -> Result<String> {
+        self.generate_with_history(messages).await
+    }
+
     /// Generate with tool calling support
     async fn generate_with_tools(
         &self,
@@ -115,6 +128,8 @@ pub struct ModelParams {
     pub frequency_penalty: Option<f32>,
     /// Presence penalty (-2.0 to 2.0)
     pub presence_penalty: Option<f32>,
+    /// Sampling seed for reproducible generations (Ollama and OpenAI only)
+    pub seed: Option<u32>,
 }
 
 impl ModelParams {
@@ -126,8 +141,48 @@ impl ModelParams {
             top_p: config.top_p,
             frequency_penalty: config.frequency_penalty,
             presence_penalty: config.presence_penalty,
+            seed: None,
+        }
+    }
+}
+
+/// Per-request overrides of a model's configured sampling parameters
+///
+/// Unset fields keep the model's configuration.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Sampling {
+    /// Sampling temperature
+    pub temperature: Option<f32>,
+    /// Maximum tokens to generate
+    pub max_tokens: Option<u32>,
+    /// Nucleus sampling parameter
+    pub top_p: Option<f32>,
+    /// Sampling seed for reproducible generations
+    pub seed: Option<u32>,
+}
+
+impl Sampling {
+    /// Only fix the sampling seed
+    pub fn seeded(seed: Option<u32>) -> Self {
+        Self {
+            seed,
+            ..Self::default()
         }
     }
+
+    /// Override the parameters that are set
+    pub fn apply(&self, params: &mut ModelParams) {
+        if let Some(temperature) = self.temperature {
+            params.temperature = Some(temperature);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            params.max_tokens = Some(max_tokens);
+        }
+        if let Some(top_p) = self.top_p {
+            params.top_p = Some(top_p);
+        }
+        params.seed = self.seed;
+    }
 }
 
 /// LLM Provider configuration
@@ -180,6 +235,42 @@ pub enum Provider {
         /// Model inference parameters
         params: ModelParams,
     },
+
+    /// Mistral AI La Plateforme
+    #[cfg(feature = "mistral")]
+    Mistral {
+        /// API key for authentication
+        api_key: String,
+        /// Base URL for the API (default: <https://api.mistral.ai/v1>)
+        api_base: String,
+        /// Model identifier (e.g., "mistral-large-latest")
+        model: String,
+        /// Model inference parameters
+        params: ModelParams,
+    },
+
+    /// Cohere chat API
+    #[cfg(feature = "cohere")]
+    Cohere {
+        /// API key for authentication
+        api_key: String,
+        /// Base URL for the API (default: <https://api.cohere.com/v1>)
+        api_base: String,
+        /// Model identifier (e.g., "command-r-plus")
+        model: String,
+        /// Connector IDs to ground responses with (e.g., "web-search")
+        connectors: Vec<String>,
+        /// Model inference parameters
+        params: ModelParams,
+    },
+
+    /// Canned replies without a model, for load tests
+    Mock {
+        /// Model name reported by the client
+        model: String,
+        /// Delay before each reply, in milliseconds
+        latency_ms: u64,
+    },
 }
 
 impl Provider {
@@ -236,6 +327,39 @@ impl Provider {
                 model.clone(),
                 params.clone(),
             ))),
+
+            #[cfg(feature = "mistral")]
+            Provider::Mistral {
+                api_key,
+                api_base,
+                model,
+                params,
+            } => Ok(Box::new(super::mistral::MistralClient::with_params(
+                api_key.clone(),
+                api_base.clone(),
+                model.clone(),
+                params.clone(),
+            ))),
+
+            #[cfg(feature = "cohere")]
+            Provider::Cohere {
+                api_key,
+                api_base,
+                model,
+                connectors,
+                params,
+            } => Ok(Box::new(super::cohere::CohereClient::with_params(
+                api_key.clone(),
+                api_base.clone(),
+                model.clone(),
+                connectors.clone(),
+                params.clone(),
+            ))),
+
+            Provider::Mock { model, latency_ms } => Ok(Box::new(super::mock::MockClient::new(
+                model.clone(),
+                *latency_ms,
+            ))),
             _ => unreachable!("Provider variant not enabled"),
         }
     }
@@ -257,6 +381,10 @@ impl Provider {
     /// - `OPENAI_API_BASE` - Base URL (default: <https://api.openai.com/v1>)
     /// - `OPENAI_MODEL` - Model name (default: gpt-4)
     ///
+    /// ## Anthropic, Mistral, Cohere
+    /// - `ANTHROPIC_API_KEY` / `MISTRAL_API_KEY` / `COHERE_API_KEY` - API key (required)
+    /// - `ANTHROPIC_MODEL` / `MISTRAL_MODEL` / `COHERE_MODEL` - Model name
+    ///
     /// ## Ollama
     /// - `OLLAMA_BASE_URL` - Server URL (default: http://localhost:11434)
     /// - `OLLAMA_MODEL` - Model name (default: ministral-3:3b)
@@ -317,6 +445,37 @@ impl Provider {
             }
         }
 
+        // Check for Mistral (requires explicit API key configuration)
+        #[cfg(feature = "mistral")]
+        if let Ok(api_key) = std::env::var("MISTRAL_API_KEY") {
+            if !api_key.is_empty() {
+                let model = std::env::var("MISTRAL_MODEL")
+                    .unwrap_or_else(|_| "mistral-large-latest".into());
+                return Ok(Provider::Mistral {
+                    api_key,
+                    api_base: "https://api.mistral.ai/v1".into(),
+                    model,
+                    params: ModelParams::default(),
+                });
+            }
+        }
+
+        // Check for Cohere (requires explicit API key configuration)
+        #[cfg(feature = "cohere")]
+        if let Ok(api_key) = std::env::var("COHERE_API_KEY") {
+            if !api_key.is_empty() {
+                let model =
+                    std::env::var("COHERE_MODEL").unwrap_or_else(|_| "command-r-plus".into());
+                return Ok(Provider::Cohere {
+                    api_key,
+                    api_base: "https://api.cohere.com/v1".into(),
+                    model,
+                    connectors: Vec::new(),
+                    params: ModelParams::default(),
+                });
+            }
+        }
+
         // Ollama as default local inference (no API key required)
         #[cfg(feature = "ollama")]
         {
@@ -354,6 +513,14 @@ impl Provider {
 
             #[cfg(feature = "anthropic")]
             Provider::Anthropic { .. } => "anthropic",
+
+            #[cfg(feature = "mistral")]
+            Provider::Mistral { .. } => "mistral",
+
+            #[cfg(feature = "cohere")]
+            Provider::Cohere { .. } => "cohere",
+
+            Provider::Mock { .. } => "mock",
             _ => unreachable!("Provider variant not enabled"),
         }
     }
@@ -373,6 +540,14 @@ impl Provider {
 
             #[cfg(feature = "anthropic")]
             Provider::Anthropic { .. } => true,
+
+            #[cfg(feature = "mistral")]
+            Provider::Mistral { .. } => true,
+
+            #[cfg(feature = "cohere")]
+            Provider::Cohere { .. } => true,
+
+            Provider::Mock { .. } => false,
             _ => unreachable!("Provider variant not enabled"),
         }
     }
@@ -396,6 +571,18 @@ impl Provider {
 
             #[cfg(feature = "anthropic")]
             Provider::Anthropic { .. } => false,
+
+            #[cfg(feature = "mistral")]
+            Provider::Mistral { api_base, .. } => {
+                api_base.contains("localhost") || api_base.contains("127.0.0.1")
+            }
+
+            #[cfg(feature = "cohere")]
+            Provider::Cohere { api_base, .. } => {
+                api_base.contains("localhost") || api_base.contains("127.0.0.1")
+            }
+
+            Provider::Mock { .. } => true,
             _ => unreachable!("Provider variant not enabled"),
         }
     }
@@ -472,8 +659,16 @@ impl Provider {
             )),
 
             #[cfg(feature = "llamacpp")]
-            ProviderConfig::LlamaCpp { model_path, .. } => Ok(Provider::LlamaCpp {
-                model_path: model_path.clone(),
+            ProviderConfig::LlamaCpp {
+                model_path, model, ..
+            } => Ok(Provider::LlamaCpp {
+                model_path: match model {
+                    Some(reference) => super::gguf::ModelStore::from_env()
+                        .resolve(reference)?
+                        .to_string_lossy()
+                        .into_owned(),
+                    None => model_path.clone(),
+                },
                 params,
             }),
 
@@ -506,6 +701,72 @@ impl Provider {
             ProviderConfig::Anthropic { .. } => Err(AppError::Configuration(
                 "Anthropic provider configured but 'anthropic' feature is not enabled".into(),
             )),
+
+            #[cfg(feature = "mistral")]
+            ProviderConfig::Mistral {
+                api_key_env,
+                api_base,
+                default_model,
+            } => {
+                let api_key = std::env::var(api_key_env).map_err(|_| {
+                    AppError::Configuration(format!(
+                        "Mistral API key environment variable '{}' is not set",
+                        api_key_env
+                    ))
+                })?;
+                Ok(Provider::Mistral {
+                    api_key,
+                    api_base: api_base.clone(),
+                    model: model_override
+                        .map(String::from)
+                        .unwrap_or_else(|| default_model.clone()),
+                    params,
+                })
+            }
+
+            #[cfg(not(feature = "mistral"))]
+            ProviderConfig::Mistral { .. } => Err(AppError::Configuration(
+                "Mistral provider configured but 'mistral' feature is not enabled".into(),
+            )),
+
+            #[cfg(feature = "cohere")]
+            ProviderConfig::Cohere {
+                api_key_env,
+                api_base,
+                default_model,
+                connectors,
+            } => {
+                let api_key = std::env::var(api_key_env).map_err(|_| {
+                    AppError::Configuration(format!(
+                        "Cohere API key environment variable '{}' is not set",
+                        api_key_env
+                    ))
+                })?;
+                Ok(Provider::Cohere {
+                    api_key,
+                    api_base: api_base.clone(),
+                    model: model_override
+                        .map(String::from)
+                        .unwrap_or_else(|| default_model.clone()),
+                    connectors: connectors.clone(),
+                    params,
+                })
+            }
+
+            #[cfg(not(feature = "cohere"))]
+            ProviderConfig::Cohere { .. } => Err(AppError::Configuration(
+                "Cohere provider configured but 'cohere' feature is not enabled".into(),
+            )),
+
+            ProviderConfig::Mock {
+                default_model,
+                latency_ms,
+            } => Ok(Provider::Mock {
+                model: model_override
+                    .map(String::from)
+                    .unwrap_or_else(|| default_model.clone()),
+                latency_ms: *latency_ms,
+            }),
         }
     }
 
@@ -0,0 +1,612 @@
+//! Cohere LLM client implementation
+//!
+//! This module provides a native client for Cohere's chat API (v1), which
+//! is not OpenAI-compatible:
+//!
+//! - System prompts are sent as a `preamble` and prior turns as `chat_history`
+//! - Tools use `parameter_definitions` rather than JSON Schema
+//! - Tool calls have no IDs; results are matched back by call name and parameters
+//! - Connectors (e.g. `web-search`) let Cohere ground answers in external data
+//!
+//! # Features
+//!
+//! Enable with the `cohere` feature flag.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use ares::llm::{LLMClient, Provider};
+//!
+//! let provider = Provider::Cohere {
+//!     api_key: "...".to_string(),
+//!     api_base: "https://api.cohere.com/v1".to_string(),
+//!     model: "command-r-plus".to_string(),
+//!     connectors: vec!["web-search".to_string()],
+//!     params: ModelParams::default(),
+//! };
+//! let client = provider.create_client().await?;
+//! let response = client.generate("Hello!").await?;
+//! ```
+
+use crate::llm::client::{LLMClient, LLMResponse, ModelParams, TokenUsage};
+use crate::llm::coordinator::{ConversationMessage, MessageRole};
+use crate::types::{AppError, Result, ToolCall, ToolDefinition};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Cohere client for API-based inference
+pub struct CohereClient {
+    http: reqwest::Client,
+    api_key: String,
+    api_base: String,
+    model: String,
+    connectors: Vec<String>,
+    params: ModelParams,
+}
+
+impl CohereClient {
+    /// Create a new Cohere client against the public API
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - Cohere API key
+    /// * `model` - Model identifier (e.g., "command-r-plus")
+    pub fn new(api_key: String, model: String) -> Self {
+        Self::with_params(
+            api_key,
+            "https://api.cohere.com/v1".to_string(),
+            model,
+            Vec::new(),
+            ModelParams::default(),
+        )
+    }
+
+    /// Create a new Cohere client with connectors and model parameters
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - Cohere API key
+    /// * `api_base` - Base URL for the API (e.g., `https://api.cohere.com/v1`)
+    /// * `model` - Model identifier (e.g., "command-r-plus")
+    /// * `connectors` - Connector IDs to ground responses with (e.g., "web-search")
+    /// * `params` - Model inference parameters (temperature, max_tokens, etc.)
+    pub fn with_params(
+        api_key: String,
+        api_base: String,
+        model: String,
+        connectors: Vec<String>,
+        params: ModelParams,
+    ) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_key,
+            api_base: api_base.trim_end_matches('/').to_string(),
+            model,
+            connectors,
+            params,
+        }
+    }
+
+    /// Map a JSON Schema type to a Cohere parameter type
+    fn parameter_type(schema: &Value) -> &'static str {
+        match schema["type"].as_str() {
+            Some("string") => "str",
+            Some("integer") => "int",
+            Some("number") => "float",
+            Some("boolean") => "bool",
+            Some("array") => "list",
+            _ => "dict",
+        }
+    }
+
+    /// Convert a ToolDefinition's JSON Schema to Cohere parameter definitions
+    fn convert_tool(tool: &ToolDefinition) -> Value {
+        let required: Vec<&str> = tool.parameters["required"]
+            .as_array()
+            .map(|r| r.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        let definitions: Map<String, Value> = tool.parameters["properties"]
+            .as_object()
+            .map(|properties| {
+                properties
+                    .iter()
+                    .map(|(name, schema)| {
+                        let mut definition = json!({
+                            "type": Self::parameter_type(schema),
+                            "required": required.contains(&name.as_str()),
+                        });
+                        if let Some(description) = schema["description"].as_str() {
+                            definition["description"] = json!(description);
+                        }
+                        (name.clone(), definition)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        json!({
+            "name": tool.name,
+            "description": tool.description,
+            "parameter_definitions": definitions,
+        })
+    }
+
+    /// Convert a tool call to Cohere's `{name, parameters}` form
+    fn convert_call(call: &ToolCall) -> Value {
+        json!({"name": call.name, "parameters": call.arguments})
+    }
+
+    /// Convert a tool result message; outputs must be JSON objects
+    fn convert_tool_result(msg: &ConversationMessage, calls: &HashMap<&str, &ToolCall>) -> Value {
+        let call = msg
+            .tool_call_id
+            .as_deref()
+            .and_then(|id| calls.get(id))
+            .map(|call| Self::convert_call(call))
+            .unwrap_or_else(|| json!({"name": "", "parameters": {}}));
+
+        let output = match serde_json::from_str::<Value>(&msg.content) {
+            Ok(Value::Object(object)) => Value::Object(object),
+            Ok(other) => json!({"result": other}),
+            Err(_) => json!({"result": msg.content}),
+        };
+
+        json!({"call": call, "outputs": [output]})
+    }
+
+    /// Build a chat request body from conversation messages
+    ///
+    /// System messages become the preamble. A trailing user message becomes
+    /// `message`; trailing tool results become `tool_results`. Everything
+    /// else is sent as `chat_history`.
+    fn build_request(
+        &self,
+        messages: &[ConversationMessage],
+        tools: &[ToolDefinition],
+        stream: bool,
+    ) -> Value {
+        let calls: HashMap<&str, &ToolCall> = messages
+            .iter()
+            .flat_map(|m| &m.tool_calls)
+            .map(|tc| (tc.id.as_str(), tc))
+            .collect();
+
+        let preamble: Vec<&str> = messages
+            .iter()
+            .filter(|m| m.role == MessageRole::System)
+            .map(|m| m.content.as_str())
+            .collect();
+        let turns: Vec<&ConversationMessage> = messages
+            .iter()
+            .filter(|m| m.role != MessageRole::System)
+            .collect();
+
+        // Split off the current turn: the last user message or trailing tool results
+        let trailing_tools = turns
+            .iter()
+            .rev()
+            .take_while(|m| m.role == MessageRole::Tool)
+            .count();
+        let (history, message, tool_results) = if trailing_tools > 0 {
+            let split = turns.len() - trailing_tools;
+            let results: Vec<Value> = turns[split..]
+                .iter()
+                .map(|m| Self::convert_tool_result(m, &calls))
+                .collect();
+            (&turns[..split], String::new(), Some(results))
+        } else {
+            match turns.split_last() {
+                Some((last, rest)) if last.role == MessageRole::User => {
+                    (rest, last.content.clone(), None)
+                }
+                _ => (&turns[..], String::new(), None),
+            }
+        };
+
+        let mut chat_history: Vec<Value> = Vec::new();
+        for msg in history {
+            match msg.role {
+                MessageRole::User => {
+                    chat_history.push(json!({"role": "USER", "message": msg.content}))
+                }
+                MessageRole::Assistant => {
+                    let mut entry = json!({"role": "CHATBOT", "message": msg.content});
+                    if !msg.tool_calls.is_empty() {
+                        entry["tool_calls"] =
+                            Value::Array(msg.tool_calls.iter().map(Self::convert_call).collect());
+                    }
+                    chat_history.push(entry);
+                }
+                MessageRole::Tool => {
+                    let result = Self::convert_tool_result(msg, &calls);
+                    // Consecutive results share one TOOL turn
+                    match chat_history.last_mut() {
+                        Some(last) if last["role"] == "TOOL" => {
+                            if let Some(results) = last["tool_results"].as_array_mut() {
+                                results.push(result);
+                            }
+                        }
+                        _ => chat_history.push(json!({"role": "TOOL", "tool_results": [result]})),
+                    }
+                }
+                MessageRole::System => {}
+            }
+        }
+
+        let mut body = json!({
+            "model": self.model,
+            "message": message,
+            "stream": stream,
+        });
+
+        if !preamble.is_empty() {
+            body["preamble"] = json!(preamble.join("\n\n"));
+        }
+        if !chat_history.is_empty() {
+            body["chat_history"] = Value::Array(chat_history);
+        }
+        if let Some(results) = tool_results {
+            body["tool_results"] = Value::Array(results);
+        }
+        if !tools.is_empty() {
+            body["tools"] = Value::Array(tools.iter().map(Self::convert_tool).collect());
+        }
+        if !self.connectors.is_empty() {
+            body["connectors"] =
+                Value::Array(self.connectors.iter().map(|id| json!({"id": id})).collect());
+        }
+        if let Some(temperature) = self.params.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = self.params.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+        if let Some(top_p) = self.params.top_p {
+            body["p"] = json!(top_p);
+        }
+        if let Some(frequency_penalty) = self.params.frequency_penalty {
+            body["frequency_penalty"] = json!(frequency_penalty);
+        }
+        if let Some(presence_penalty) = self.params.presence_penalty {
+            body["presence_penalty"] = json!(presence_penalty);
+        }
+
+        body
+    }
+
+    /// Send a request, returning the HTTP response on success
+    async fn send(&self, body: &Value) -> Result<reqwest::Response> {
+        let response = self
+            .http
+            .post(format!("{}/chat", self.api_base))
+            .bearer_auth(&self.api_key)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| AppError::LLM(format!("Cohere API error: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(AppError::LLM(format!(
+                "Cohere API error ({}): {}",
+                status, text
+            )));
+        }
+        Ok(response)
+    }
+
+    /// Run a non-streaming chat request
+    async fn complete(
+        &self,
+        messages: &[ConversationMessage],
+        tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        let body = self.build_request(messages, tools, false);
+        let response: Value = self
+            .send(&body)
+            .await?
+            .json()
+            .await
+            .map_err(|e| AppError::LLM(format!("Invalid Cohere response: {}", e)))?;
+
+        Ok(Self::parse_response(&response))
+    }
+
+    /// Parse a chat response, assigning IDs to the returned tool calls
+    fn parse_response(response: &Value) -> LLMResponse {
+        let generation_id = response["generation_id"].as_str().unwrap_or("cohere");
+        let tool_calls: Vec<ToolCall> = response["tool_calls"]
+            .as_array()
+            .map(|calls| {
+                calls
+                    .iter()
+                    .enumerate()
+                    .map(|(i, tc)| ToolCall {
+                        id: format!("{}-{}", generation_id, i),
+                        name: tc["name"].as_str().unwrap_or_default().to_string(),
+                        arguments: tc["parameters"].clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let finish_reason = if !tool_calls.is_empty() {
+            "tool_calls".to_string()
+        } else {
+            match response["finish_reason"].as_str() {
+                Some("COMPLETE") | None => "stop".to_string(),
+                Some("MAX_TOKENS") => "length".to_string(),
+                Some(other) => other.to_lowercase(),
+            }
+        };
+
+        let billed = &response["meta"]["billed_units"];
+        let usage = billed.as_object().map(|_| {
+            TokenUsage::new(
+                billed["input_tokens"].as_u64().unwrap_or(0) as u32,
+                billed["output_tokens"].as_u64().unwrap_or(0) as u32,
+            )
+        });
+
+        LLMResponse {
+            content: response["text"].as_str().unwrap_or_default().to_string(),
+            tool_calls,
+            finish_reason,
+            usage,
+        }
+    }
+
+    /// Run a streaming chat request, yielding text deltas
+    ///
+    /// Cohere streams newline-delimited JSON events.
+    async fn complete_stream(
+        &self,
+        messages: &[ConversationMessage],
+    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+        let body = self.build_request(messages, &[], true);
+        let response = self.send(&body).await?;
+
+        let result_stream = async_stream::stream! {
+            let mut bytes = response.bytes_stream();
+            let mut buffer = String::new();
+            while let Some(chunk) = bytes.next().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        yield Err(AppError::LLM(format!("Stream error: {}", e)));
+                        return;
+                    }
+                };
+                buffer.push_str(&String::from_utf8_lossy(&chunk));
+
+                while let Some(pos) = buffer.find('\n') {
+                    let line: String = buffer.drain(..=pos).collect();
+                    let line = line.trim();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    let event: Value = match serde_json::from_str(line) {
+                        Ok(event) => event,
+                        Err(e) => {
+                            yield Err(AppError::LLM(format!("Invalid Cohere stream event: {}", e)));
+                            return;
+                        }
+                    };
+                    match event["event_type"].as_str() {
+                        Some("text-generation") => {
+                            if let Some(text) = event["text"].as_str() {
+                                yield Ok(text.to_string());
+                            }
+                        }
+                        Some("stream-end") => return,
+                        _ => {}
+                    }
+                }
+            }
+        };
+
+        Ok(Box::new(Box::pin(result_stream)))
+    }
+
+    fn history(messages: &[(String, String)]) -> Vec<ConversationMessage> {
+        messages
+            .iter()
+            .map(|(role, content)| ConversationMessage::from_role_content(role, content.as_str()))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl LLMClient for CohereClient {
+    async fn generate(&self, prompt: &str) -> Result<String> {
+        let messages = [ConversationMessage::user(prompt)];
+        Ok(self.complete(&messages, &[]).await?.content)
+    }
+
+    async fn generate_with_system(&self, system: &str, prompt: &str) -> Result<String> {
+        let messages = [
+            ConversationMessage::system(system),
+            ConversationMessage::user(prompt),
+        ];
+        Ok(self.complete(&messages, &[]).await?.content)
+    }
+
+    async fn generate_with_history(&self, messages: &[(String, String)]) -> Result<String> {
+        Ok(self.complete(&Self::history(messages), &[]).await?.content)
+    }
+
+    async fn generate_with_tools(
+        &self,
+        prompt: &str,
+        tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        self.complete(&[ConversationMessage::user(prompt)], tools)
+            .await
+    }
+
+    async fn generate_with_tools_and_history(
+        &self,
+        messages: &[ConversationMessage],
+        tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        self.complete(messages, tools).await
+    }
+
+    async fn stream(
+        &self,
+        prompt: &str,
+    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+        self.complete_stream(&[ConversationMessage::user(prompt)])
+            .await
+    }
+
+    async fn stream_with_system(
+        &self,
+        system: &str,
+        prompt: &str,
+    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+        let messages = [
+            ConversationMessage::system(system),
+            ConversationMessage::user(prompt),
+        ];
+        self.complete_stream(&messages).await
+    }
+
+    async fn stream_with_history(
+        &self,
+        messages: &[(String, String)],
+    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+        self.complete_stream(&Self::history(messages)).await
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client(api_base: String) -> CohereClient {
+        CohereClient::with_params(
+            "key".to_string(),
+            api_base,
+            "command-r".to_string(),
+            vec!["web-search".to_string()],
+            ModelParams::default(),
+        )
+    }
+
+    #[test]
+    fn test_tool_conversion() {
+        let tool = ToolDefinition {
+            name: "calculator".to_string(),
+            description: "Performs math operations".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "operation": {"type": "string", "description": "add or sub"},
+                    "a": {"type": "number"}
+                },
+                "required": ["operation"]
+            }),
+        };
+
+        let converted = CohereClient::convert_tool(&tool);
+        let definitions = &converted["parameter_definitions"];
+        assert_eq!(definitions["operation"]["type"], "str");
+        assert_eq!(definitions["operation"]["required"], true);
+        assert_eq!(definitions["operation"]["description"], "add or sub");
+        assert_eq!(definitions["a"]["type"], "float");
+        assert_eq!(definitions["a"]["required"], false);
+    }
+
+    #[test]
+    fn test_tool_loop_request() {
+        let call = ToolCall {
+            id: "gen-0".to_string(),
+            name: "calculator".to_string(),
+            arguments: json!({"a": 1}),
+        };
+        let messages = vec![
+            ConversationMessage::system("Be precise."),
+            ConversationMessage::user("1+1?"),
+            ConversationMessage::assistant("", vec![call]),
+            ConversationMessage::tool_result("gen-0", &json!(2)),
+        ];
+
+        let body = client("http://localhost".to_string()).build_request(&messages, &[], false);
+        assert_eq!(body["preamble"], "Be precise.");
+        assert_eq!(body["message"], "");
+        assert_eq!(body["chat_history"][0]["role"], "USER");
+        assert_eq!(
+            body["chat_history"][1]["tool_calls"][0]["name"],
+            "calculator"
+        );
+        assert_eq!(
+            body["tool_results"][0]["call"]["parameters"],
+            json!({"a": 1})
+        );
+        assert_eq!(body["tool_results"][0]["outputs"][0], json!({"result": 2}));
+        assert_eq!(body["connectors"][0]["id"], "web-search");
+    }
+
+    #[tokio::test]
+    async fn test_generate_with_tools() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat"))
+            .and(body_partial_json(json!({"message": "1+1?"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "text": "",
+                "generation_id": "gen",
+                "tool_calls": [{"name": "calculator", "parameters": {"a": 1}}],
+                "finish_reason": "COMPLETE",
+                "meta": {"billed_units": {"input_tokens": 12, "output_tokens": 3}}
+            })))
+            .mount(&server)
+            .await;
+
+        let response = client(format!("{}/v1", server.uri()))
+            .generate_with_tools("1+1?", &[])
+            .await
+            .unwrap();
+
+        assert_eq!(response.finish_reason, "tool_calls");
+        assert_eq!(response.tool_calls[0].id, "gen-0");
+        assert_eq!(response.tool_calls[0].arguments, json!({"a": 1}));
+        assert_eq!(response.usage, Some(TokenUsage::new(12, 3)));
+    }
+
+    #[tokio::test]
+    async fn test_stream() {
+        let server = MockServer::start().await;
+        let body = concat!(
+            "{\"is_finished\":false,\"event_type\":\"stream-start\",\"generation_id\":\"gen\"}\n",
+            "{\"is_finished\":false,\"event_type\":\"text-generation\",\"text\":\"Hel\"}\n",
+            "{\"is_finished\":false,\"event_type\":\"text-generation\",\"text\":\"lo\"}\n",
+            "{\"is_finished\":true,\"event_type\":\"stream-end\",\"finish_reason\":\"COMPLETE\"}\n",
+        );
+        Mock::given(method("POST"))
+            .and(path("/v1/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/stream+json"))
+            .mount(&server)
+            .await;
+
+        let tokens: Vec<String> = client(format!("{}/v1", server.uri()))
+            .stream("hi")
+            .await
+            .unwrap()
+            .map(|t| t.unwrap())
+            .collect()
+            .await;
+        assert_eq!(tokens, vec!["Hel", "lo"]);
+    }
+}
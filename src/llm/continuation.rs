@@ -0,0 +1,325 @@
+//! Continuation of replies cut off at the token limit.
+//!
+//! A reply that hits a model's `max_tokens` ends mid-sentence, or worse,
+//! halfway through a code block. [`ContinuationClient`] detects truncation
+//! from the finish reason the provider reports, asks the model to carry on
+//! where it stopped, and stitches the parts into a single reply, so callers
+//! never see the cut:
+//!
+//! ```toml
+//! [models.balanced]
+//! provider = "openai"
+//! model = "gpt-4o-mini"
+//! max_tokens = 1024
+//! max_continuations = 3   # default 2; 0 returns cut-off replies as they are
+//! ```
+//!
+//! Text the model repeats at the start of a continuation, and a code fence
+//! it reopens, are dropped. Streams are passed through unchanged, as
+//! streaming providers don't report why generation stopped.
+
+use crate::llm::client::{LLMClient, LLMResponse, TokenUsage};
+use crate::llm::coordinator::ConversationMessage;
+use crate::types::{Result, ToolDefinition};
+use async_trait::async_trait;
+use futures::Stream;
+
+type TokenStream = Box<dyn Stream<Item = Result<String>> + Send + Unpin>;
+
+/// Instruction sent after a reply that was cut off
+const CONTINUE_PROMPT: &str = "Your reply was cut off. Continue exactly where it stopped, \
+     without repeating anything and without any introduction.";
+
+/// Shortest repeated text recognized at the start of a continuation, in bytes
+const MIN_OVERLAP: usize = 12;
+
+/// Longest repeated text looked for at the start of a continuation, in bytes
+const MAX_OVERLAP: usize = 400;
+
+/// Whether a provider's finish reason means the reply hit the token limit
+///
+/// OpenAI, Mistral and Cohere report "length", Anthropic "max_tokens".
+pub fn is_truncated(finish_reason: &str) -> bool {
+    matches!(finish_reason, "length" | "max_tokens")
+}
+
+/// Continues replies cut off at the token limit, up to a set number of times.
+pub struct ContinuationClient {
+    inner: Box<dyn LLMClient>,
+    max_continuations: u32,
+}
+
+impl ContinuationClient {
+    /// Continue `client`'s cut-off replies up to `max_continuations` times
+    ///
+    /// Returns the client unchanged when `max_continuations` is 0.
+    pub fn wrap(client: Box<dyn LLMClient>, max_continuations: u32) -> Box<dyn LLMClient> {
+        if max_continuations == 0 {
+            return client;
+        }
+        Box::new(Self {
+            inner: client,
+            max_continuations,
+        })
+    }
+
+    /// Generate a reply to `messages`, continuing it while it is cut off
+    ///
+    /// Continuations are generated without tools; a reply that calls tools
+    /// is complete.
+    async fn complete(
+        &self,
+        messages: &[ConversationMessage],
+        tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        let mut response = self
+            .inner
+            .generate_with_tools_and_history(messages, tools)
+            .await?;
+        let mut history = messages.to_vec();
+        let mut continuations = 0;
+        while continuations < self.max_continuations
+            && response.tool_calls.is_empty()
+            && is_truncated(&response.finish_reason)
+        {
+            continuations += 1;
+            tracing::debug!(
+                "Reply of {} cut off at the token limit, continuing ({}/{})",
+                self.inner.model_name(),
+                continuations,
+                self.max_continuations
+            );
+            history.push(ConversationMessage::assistant(
+                &response.content,
+                Vec::new(),
+            ));
+            history.push(ConversationMessage::user(CONTINUE_PROMPT));
+            let next = self
+                .inner
+                .generate_with_tools_and_history(&history, &[])
+                .await?;
+            stitch(&mut response.content, &next.content);
+            response.finish_reason = next.finish_reason;
+            response.usage = match (response.usage, next.usage) {
+                (Some(a), Some(b)) => Some(TokenUsage::new(
+                    a.prompt_tokens + b.prompt_tokens,
+                    a.completion_tokens + b.completion_tokens,
+                )),
+                (a, b) => a.or(b),
+            };
+        }
+        Ok(response)
+    }
+}
+
+/// Append a continuation to a cut-off reply
+///
+/// Drops text the continuation repeats from the end of the reply, and a
+/// code fence it reopens while the reply is inside a code block.
+fn stitch(reply: &mut String, continuation: &str) {
+    let mut continuation = continuation;
+    let in_code_block = reply.matches("```").count() % 2 == 1;
+    if in_code_block && continuation.trim_start().starts_with("```") {
+        let reopened = continuation.trim_start();
+        continuation = reopened.split_once('\n').map_or("", |(_, rest)| rest);
+    }
+    let longest = continuation.len().min(reply.len()).min(MAX_OVERLAP);
+    let overlap = (MIN_OVERLAP..=longest)
+        .rev()
+        .filter(|&n| continuation.is_char_boundary(n))
+        .find(|&n| reply.ends_with(&continuation[..n]))
+        .unwrap_or(0);
+    reply.push_str(&continuation[overlap..]);
+}
+
+fn history_messages(messages: &[(String, String)]) -> Vec<ConversationMessage> {
+    messages
+        .iter()
+        .map(|(role, content)| ConversationMessage::from_role_content(role, content.as_str()))
+        .collect()
+}
+
+#[async_trait]
+impl LLMClient for ContinuationClient {
+    async fn generate(&self, prompt: &str) -> Result<String> {
+        let messages = [ConversationMessage::user(prompt)];
+        Ok(self.complete(&messages, &[]).await?.content)
+    }
+
+    async fn generate_with_system(&self, system: &str, prompt: &str) -> Result<String> {
+        let messages = [
+            ConversationMessage::system(system),
+            ConversationMessage::user(prompt),
+        ];
+        Ok(self.complete(&messages, &[]).await?.content)
+    }
+
+    async fn generate_with_history(&self, messages: &[(String, String)]) -> Result<String> {
+        Ok(self
+            .complete(&history_messages(messages), &[])
+            .await?
+            .content)
+    }
+
+    /// Structured output is not continued: half a JSON document can't be
+    /// stitched reliably, and schemas keep replies short
+    async fn generate_structured(
+        &self,
+        messages: &[(String, String)],
+        schema: &serde_json::Value,
+    ) -> Result<String> {
+        self.inner.generate_structured(messages, schema).await
+    }
+
+    async fn generate_with_tools(
+        &self,
+        prompt: &str,
+        tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        self.complete(&[ConversationMessage::user(prompt)], tools)
+            .await
+    }
+
+    async fn generate_with_tools_and_history(
+        &self,
+        messages: &[ConversationMessage],
+        tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        self.complete(messages, tools).await
+    }
+
+    async fn stream(&self, prompt: &str) -> Result<TokenStream> {
+        self.inner.stream(prompt).await
+    }
+
+    async fn stream_with_system(&self, system: &str, prompt: &str) -> Result<TokenStream> {
+        self.inner.stream_with_system(system, prompt).await
+    }
+
+    async fn stream_with_history(&self, messages: &[(String, String)]) -> Result<TokenStream> {
+        self.inner.stream_with_history(messages).await
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    /// Replies with the given parts in order, each but the last cut off,
+    /// recording the length of every conversation it is sent
+    struct Truncating {
+        parts: Mutex<Vec<&'static str>>,
+        calls: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl Truncating {
+        fn new(parts: &[&'static str]) -> (Self, Arc<Mutex<Vec<usize>>>) {
+            let calls = Arc::new(Mutex::new(Vec::new()));
+            let client = Self {
+                parts: Mutex::new(parts.iter().rev().copied().collect()),
+                calls: calls.clone(),
+            };
+            (client, calls)
+        }
+    }
+
+    #[async_trait]
+    impl LLMClient for Truncating {
+        async fn generate(&self, _: &str) -> Result<String> {
+            unreachable!()
+        }
+        async fn generate_with_system(&self, _: &str, _: &str) -> Result<String> {
+            unreachable!()
+        }
+        async fn generate_with_history(&self, _: &[(String, String)]) -> Result<String> {
+            unreachable!()
+        }
+        async fn generate_with_tools(&self, _: &str, _: &[ToolDefinition]) -> Result<LLMResponse> {
+            unreachable!()
+        }
+        async fn generate_with_tools_and_history(
+            &self,
+            messages: &[ConversationMessage],
+            _: &[ToolDefinition],
+        ) -> Result<LLMResponse> {
+            self.calls.lock().push(messages.len());
+            let mut parts = self.parts.lock();
+            let content = parts.pop().unwrap_or_default().to_string();
+            Ok(LLMResponse {
+                content,
+                tool_calls: Vec::new(),
+                finish_reason: if parts.is_empty() { "stop" } else { "length" }.to_string(),
+                usage: Some(TokenUsage::new(10, 5)),
+            })
+        }
+        async fn stream(&self, _: &str) -> Result<TokenStream> {
+            unreachable!()
+        }
+        async fn stream_with_system(&self, _: &str, _: &str) -> Result<TokenStream> {
+            unreachable!()
+        }
+        async fn stream_with_history(&self, _: &[(String, String)]) -> Result<TokenStream> {
+            unreachable!()
+        }
+        fn model_name(&self) -> &str {
+            "truncating"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cut_off_replies_are_continued_and_stitched() {
+        let (inner, calls) = Truncating::new(&[
+            "Here is the function:\n\n```rust\nfn add(a: i32, b: i32) -> i32 {\n",
+            "```rust\n    a + b\n}\n```\n\nIt adds two numbers",
+            "It adds two numbers together.",
+        ]);
+        let client = ContinuationClient::wrap(Box::new(inner), 2);
+        let response = client
+            .generate_with_tools_and_history(&[ConversationMessage::user("Write add")], &[])
+            .await
+            .unwrap();
+        assert_eq!(
+            response.content,
+            "Here is the function:\n\n```rust\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n```\
+             \n\nIt adds two numbers together."
+        );
+        assert_eq!(response.finish_reason, "stop");
+        assert_eq!(response.usage, Some(TokenUsage::new(30, 15)));
+        // Each continuation resends the conversation with the reply so far
+        assert_eq!(*calls.lock(), vec![1, 3, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_continuations_are_limited() {
+        let (inner, calls) = Truncating::new(&["one ", "two ", "three ", "four"]);
+        let client = ContinuationClient::wrap(Box::new(inner), 1);
+        let reply = client
+            .generate_with_history(&[("user".to_string(), "Count".to_string())])
+            .await
+            .unwrap();
+        assert_eq!(reply, "one two ");
+        assert_eq!(calls.lock().len(), 2);
+
+        let (inner, calls) = Truncating::new(&["one ", "two "]);
+        let client = ContinuationClient::wrap(Box::new(inner), 0);
+        let response = client
+            .generate_with_tools_and_history(&[ConversationMessage::user("Count")], &[])
+            .await
+            .unwrap();
+        assert_eq!((response.content.as_str(), calls.lock().len()), ("one ", 1));
+    }
+
+    #[test]
+    fn test_stitch_keeps_short_coincidental_overlaps() {
+        let mut reply = "The answer is".to_string();
+        stitch(&mut reply, " is 42.");
+        assert_eq!(reply, "The answer is is 42.");
+        assert!(is_truncated("max_tokens") && !is_truncated("stop"));
+    }
+}
@@ -31,7 +31,7 @@
 
 use crate::llm::client::{LLMClient, TokenUsage};
 use crate::tools::registry::ToolRegistry;
-use crate::types::{Result, ToolCall};
+use crate::types::{AgentContext, Result, ToolCall};
 use futures::future::join_all;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -94,6 +94,9 @@ pub struct ToolCallRecord {
     pub duration_ms: u64,
     /// Error message if the tool failed.
     pub error: Option<String>,
+    /// Tool-calling round the call was made in, starting at 1.
+    #[serde(default)]
+    pub iteration: usize,
 }
 
 /// Reason why a tool coordination session ended.
@@ -193,6 +196,15 @@ impl ConversationMessage {
         }
     }
 
+    /// Create a message from the simple (role, content) format; unknown roles become user messages.
+    pub fn from_role_content(role: &str, content: impl Into<String>) -> Self {
+        match role {
+            "system" => Self::system(content),
+            "assistant" => Self::assistant(content, Vec::new()),
+            _ => Self::user(content),
+        }
+    }
+
     /// Convert to the simple (role, content) format for LLMClient::generate_with_history.
     pub fn to_role_content(&self) -> (String, String) {
         let role = match self.role {
@@ -248,6 +260,7 @@ pub struct ToolCoordinator {
     client: Box<dyn LLMClient>,
     registry: Arc<ToolRegistry>,
     config: ToolCallingConfig,
+    context: Option<AgentContext>,
 }
 
 impl ToolCoordinator {
@@ -261,9 +274,16 @@ impl ToolCoordinator {
             client,
             registry,
             config,
+            context: None,
         }
     }
 
+    /// Run the context's `on_tool_result` hooks on every tool call.
+    pub fn with_context(mut self, context: AgentContext) -> Self {
+        self.context = Some(context);
+        self
+    }
+
     /// Create a new ToolCoordinator with default configuration.
     pub fn with_defaults(client: Box<dyn LLMClient>, registry: Arc<ToolRegistry>) -> Self {
         Self::new(client, registry, ToolCallingConfig::default())
@@ -348,7 +368,9 @@ impl ToolCoordinator {
             }
 
             // Execute tool calls
-            let tool_results = self.execute_tool_calls(&response.tool_calls).await?;
+            let tool_results = self
+                .execute_tool_calls(&response.tool_calls, iteration + 1)
+                .await?;
 
             // Record tool calls and add results to message history
             for record in tool_results {
@@ -373,17 +395,34 @@ impl ToolCoordinator {
     }
 
     /// Execute tool calls, either in parallel or sequentially based on config.
-    async fn execute_tool_calls(&self, calls: &[ToolCall]) -> Result<Vec<ToolCallRecord>> {
-        if self.config.parallel_execution {
-            self.execute_parallel(calls).await
+    async fn execute_tool_calls(
+        &self,
+        calls: &[ToolCall],
+        iteration: usize,
+    ) -> Result<Vec<ToolCallRecord>> {
+        let mut records = if self.config.parallel_execution {
+            self.execute_parallel(calls, iteration).await?
         } else {
-            self.execute_sequential(calls).await
+            self.execute_sequential(calls, iteration).await?
+        };
+
+        if let Some(ctx) = &self.context {
+            for record in &mut records {
+                ctx.hooks.tool_result(ctx, record).await?;
+            }
         }
+        Ok(records)
     }
 
     /// Execute tool calls in parallel.
-    async fn execute_parallel(&self, calls: &[ToolCall]) -> Result<Vec<ToolCallRecord>> {
-        let futures = calls.iter().map(|call| self.execute_single_tool(call));
+    async fn execute_parallel(
+        &self,
+        calls: &[ToolCall],
+        iteration: usize,
+    ) -> Result<Vec<ToolCallRecord>> {
+        let futures = calls
+            .iter()
+            .map(|call| self.execute_single_tool(call, iteration));
         let results = join_all(futures).await;
 
         let mut records = Vec::with_capacity(results.len());
@@ -401,6 +440,7 @@ impl ToolCoordinator {
                         success: false,
                         duration_ms: 0,
                         error: Some(e.to_string()),
+                        iteration,
                     });
                 }
             }
@@ -409,10 +449,14 @@ impl ToolCoordinator {
     }
 
     /// Execute tool calls sequentially.
-    async fn execute_sequential(&self, calls: &[ToolCall]) -> Result<Vec<ToolCallRecord>> {
+    async fn execute_sequential(
+        &self,
+        calls: &[ToolCall],
+        iteration: usize,
+    ) -> Result<Vec<ToolCallRecord>> {
         let mut records = Vec::with_capacity(calls.len());
         for call in calls {
-            match self.execute_single_tool(call).await {
+            match self.execute_single_tool(call, iteration).await {
                 Ok(record) => records.push(record),
                 Err(e) if self.config.stop_on_error => return Err(e),
                 Err(e) => {
@@ -424,6 +468,7 @@ impl ToolCoordinator {
                         success: false,
                         duration_ms: 0,
                         error: Some(e.to_string()),
+                        iteration,
                     });
                 }
             }
@@ -432,7 +477,11 @@ impl ToolCoordinator {
     }
 
     /// Execute a single tool call with timeout.
-    async fn execute_single_tool(&self, call: &ToolCall) -> Result<ToolCallRecord> {
+    async fn execute_single_tool(
+        &self,
+        call: &ToolCall,
+        iteration: usize,
+    ) -> Result<ToolCallRecord> {
         let start = Instant::now();
 
         let result = timeout(
@@ -452,6 +501,7 @@ impl ToolCoordinator {
                 success: true,
                 duration_ms,
                 error: None,
+                iteration,
             }),
             Ok(Err(e)) => Ok(ToolCallRecord {
                 id: call.id.clone(),
@@ -461,6 +511,7 @@ impl ToolCoordinator {
                 success: false,
                 duration_ms,
                 error: Some(e.to_string()),
+                iteration,
             }),
             Err(_) => Ok(ToolCallRecord {
                 id: call.id.clone(),
@@ -470,6 +521,7 @@ impl ToolCoordinator {
                 success: false,
                 duration_ms,
                 error: Some("Tool execution timed out".to_string()),
+                iteration,
             }),
         }
     }
@@ -566,6 +618,7 @@ mod tests {
             success: true,
             duration_ms: 100,
             error: None,
+            iteration: 1,
         };
 
         let json = serde_json::to_string(&record).unwrap();
@@ -0,0 +1,671 @@
+//! GGUF model downloads and management for the llama.cpp provider.
+//!
+//! Models are pulled from Hugging Face into a managed directory
+//! (`ARES_MODELS_DIR`, default `models/`) laid out as `<owner>/<repo>/<file>`,
+//! so a `ProviderConfig::LlamaCpp` can name a model as
+//! `owner/repo/file.gguf` instead of an absolute path.
+//!
+//! Downloads are written to a `.part` file next to the target and resumed with
+//! an HTTP range request if interrupted. The finished file is checked against
+//! the SHA-256 Hugging Face publishes for LFS objects before it is moved into
+//! place.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use ares::llm::gguf::{ModelRef, ModelStore};
+//!
+//! let store = ModelStore::from_env();
+//! let model: ModelRef = "Qwen/Qwen2.5-0.5B-Instruct-GGUF/qwen2.5-0.5b-instruct-q4_k_m.gguf".parse()?;
+//! let path = store.pull(&model).await?;
+//! ```
+
+use crate::types::{AppError, Result};
+use futures::StreamExt;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tokio::io::AsyncWriteExt;
+
+/// Directory models are stored in when `ARES_MODELS_DIR` is not set.
+pub const DEFAULT_MODELS_DIR: &str = "models";
+
+/// Hugging Face endpoint used when `HF_ENDPOINT` is not set.
+pub const DEFAULT_HF_ENDPOINT: &str = "https://huggingface.co";
+
+/// Reference to a GGUF file in a Hugging Face repository.
+///
+/// Written as `owner/repo[/path/to/file.gguf][@revision]`. The file may be
+/// omitted when pulling from a repository that contains a single GGUF file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelRef {
+    /// Repository id, e.g. `Qwen/Qwen2.5-0.5B-Instruct-GGUF`.
+    pub repo: String,
+    /// Path of the GGUF file within the repository.
+    pub file: Option<String>,
+    /// Branch, tag or commit to download from (default: `main`).
+    pub revision: String,
+}
+
+impl ModelRef {
+    /// Create a reference to a file in a repository at `main`.
+    pub fn new(repo: impl Into<String>, file: impl Into<String>) -> Self {
+        Self {
+            repo: repo.into(),
+            file: Some(file.into()),
+            revision: "main".to_string(),
+        }
+    }
+}
+
+impl FromStr for ModelRef {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (reference, revision) = match s.rsplit_once('@') {
+            Some((reference, revision)) if !revision.is_empty() && !revision.contains('/') => {
+                (reference, revision.to_string())
+            }
+            _ => (s, "main".to_string()),
+        };
+
+        let invalid = || {
+            AppError::InvalidInput(format!(
+                "Invalid model reference '{}': expected owner/repo[/file.gguf][@revision]",
+                s
+            ))
+        };
+
+        let mut parts = reference.trim_matches('/').splitn(3, '/');
+        let owner = parts.next().filter(|p| !p.is_empty()).ok_or_else(invalid)?;
+        let name = parts.next().filter(|p| !p.is_empty()).ok_or_else(invalid)?;
+        let file = parts.next().map(str::to_string);
+
+        let segments = std::iter::once(owner)
+            .chain(std::iter::once(name))
+            .chain(file.iter().flat_map(|f| f.split('/')));
+        for segment in segments {
+            if segment.is_empty() || segment == "." || segment == ".." || segment.contains('\\') {
+                return Err(invalid());
+            }
+        }
+        if let Some(file) = &file {
+            if !file.to_lowercase().ends_with(".gguf") {
+                return Err(AppError::InvalidInput(format!(
+                    "Invalid model reference '{}': '{}' is not a .gguf file",
+                    s, file
+                )));
+            }
+        }
+
+        Ok(Self {
+            repo: format!("{}/{}", owner, name),
+            file,
+            revision,
+        })
+    }
+}
+
+impl fmt::Display for ModelRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.repo)?;
+        if let Some(file) = &self.file {
+            write!(f, "/{}", file)?;
+        }
+        if self.revision != "main" {
+            write!(f, "@{}", self.revision)?;
+        }
+        Ok(())
+    }
+}
+
+/// A GGUF file present in the managed models directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalModel {
+    /// Reference the model can be configured with (`owner/repo/file.gguf`).
+    pub reference: String,
+    /// Absolute or root-relative path to the file on disk.
+    pub path: PathBuf,
+    /// File size in bytes.
+    pub size: u64,
+}
+
+/// Entry returned by the Hugging Face repository tree API.
+#[derive(Debug, Deserialize)]
+struct RemoteFile {
+    #[serde(rename = "type")]
+    kind: String,
+    path: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    lfs: Option<RemoteLfs>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoteLfs {
+    /// SHA-256 of the file contents
+    oid: String,
+    size: u64,
+}
+
+impl RemoteFile {
+    fn size(&self) -> u64 {
+        self.lfs.as_ref().map(|l| l.size).unwrap_or(self.size)
+    }
+
+    fn sha256(&self) -> Option<&str> {
+        self.lfs.as_ref().map(|l| l.oid.as_str())
+    }
+}
+
+/// Managed directory of GGUF models downloaded from Hugging Face.
+#[derive(Debug, Clone)]
+pub struct ModelStore {
+    root: PathBuf,
+    endpoint: String,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl ModelStore {
+    /// Create a store rooted at `root`, downloading from the public Hugging Face hub.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            endpoint: DEFAULT_HF_ENDPOINT.to_string(),
+            token: None,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Create a store from `ARES_MODELS_DIR`, `HF_ENDPOINT` and `HF_TOKEN`.
+    pub fn from_env() -> Self {
+        let root = std::env::var("ARES_MODELS_DIR")
+            .ok()
+            .filter(|d| !d.is_empty())
+            .unwrap_or_else(|| DEFAULT_MODELS_DIR.to_string());
+        let mut store = Self::new(root);
+        if let Ok(endpoint) = std::env::var("HF_ENDPOINT") {
+            if !endpoint.is_empty() {
+                store = store.with_endpoint(endpoint);
+            }
+        }
+        if let Ok(token) = std::env::var("HF_TOKEN") {
+            if !token.is_empty() {
+                store = store.with_token(token);
+            }
+        }
+        store
+    }
+
+    /// Store models under a different directory.
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    /// Download from a different Hugging Face compatible endpoint (e.g. a mirror).
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Authenticate downloads with a Hugging Face access token (needed for gated repos).
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Root of the managed models directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Location of a model within the store, whether or not it has been downloaded.
+    ///
+    /// Returns `None` if the reference does not name a file.
+    pub fn path_for(&self, model: &ModelRef) -> Option<PathBuf> {
+        let file = model.file.as_ref()?;
+        let mut path = self.root.join(&model.repo);
+        path.extend(file.split('/'));
+        Some(path)
+    }
+
+    /// Resolve a configured model reference to the downloaded file.
+    pub fn resolve(&self, reference: &str) -> Result<PathBuf> {
+        let model: ModelRef = reference.parse()?;
+        let path = self.path_for(&model).ok_or_else(|| {
+            AppError::Configuration(format!(
+                "Model reference '{}' must name a .gguf file",
+                reference
+            ))
+        })?;
+        if !path.exists() {
+            return Err(AppError::NotFound(format!(
+                "Model '{}' has not been downloaded to {} (run `ares-server models pull {}`)",
+                reference,
+                self.root.display(),
+                reference
+            )));
+        }
+        Ok(path)
+    }
+
+    /// List the GGUF models present in the store.
+    pub fn list(&self) -> Result<Vec<LocalModel>> {
+        let mut models = Vec::new();
+        if self.root.exists() {
+            collect_models(&self.root, &self.root, &mut models)?;
+        }
+        models.sort_by(|a, b| a.reference.cmp(&b.reference));
+        Ok(models)
+    }
+
+    /// Delete a downloaded model and any partial download of it.
+    pub fn remove(&self, model: &ModelRef) -> Result<()> {
+        let path = self.path_for(model).ok_or_else(|| {
+            AppError::InvalidInput(format!(
+                "Model reference '{}' must name a .gguf file",
+                model
+            ))
+        })?;
+        let part = part_path(&path);
+        if !path.exists() && !part.exists() {
+            return Err(AppError::NotFound(format!(
+                "Model '{}' is not downloaded",
+                model
+            )));
+        }
+        for file in [&path, &part] {
+            if file.exists() {
+                std::fs::remove_file(file).map_err(|e| {
+                    AppError::Internal(format!("Failed to remove {}: {}", file.display(), e))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Download a model into the store, returning its path.
+    ///
+    /// Already downloaded models are not fetched again.
+    pub async fn pull(&self, model: &ModelRef) -> Result<PathBuf> {
+        self.pull_with_progress(model, |_, _| {}).await
+    }
+
+    /// Download a model, calling `progress(downloaded, total)` as bytes arrive.
+    pub async fn pull_with_progress<F>(&self, model: &ModelRef, mut progress: F) -> Result<PathBuf>
+    where
+        F: FnMut(u64, u64),
+    {
+        let remote = self.remote_file(model).await?;
+        let resolved = ModelRef {
+            file: Some(remote.path.clone()),
+            ..model.clone()
+        };
+        let target = self
+            .path_for(&resolved)
+            .expect("resolved model reference names a file");
+        let total = remote.size();
+
+        if let Ok(meta) = tokio::fs::metadata(&target).await {
+            if meta.len() == total {
+                progress(total, total);
+                return Ok(target);
+            }
+        }
+
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|e| {
+                AppError::Internal(format!("Failed to create {}: {}", parent.display(), e))
+            })?;
+        }
+
+        let part = part_path(&target);
+        let mut downloaded = match tokio::fs::metadata(&part).await {
+            Ok(meta) if meta.len() <= total => meta.len(),
+            _ => 0,
+        };
+
+        if downloaded < total {
+            self.download(&resolved, &part, &mut downloaded, total, &mut progress)
+                .await?;
+        }
+        progress(downloaded, total);
+
+        if let Some(expected) = remote.sha256() {
+            let actual = sha256_file(&part).await?;
+            if !actual.eq_ignore_ascii_case(expected) {
+                let _ = tokio::fs::remove_file(&part).await;
+                return Err(AppError::External(format!(
+                    "Checksum mismatch for {}: expected {}, got {}",
+                    resolved, expected, actual
+                )));
+            }
+        } else {
+            tracing::warn!(
+                "No checksum published for {}, skipping verification",
+                resolved
+            );
+        }
+
+        tokio::fs::rename(&part, &target).await.map_err(|e| {
+            AppError::Internal(format!(
+                "Failed to move {} into place: {}",
+                target.display(),
+                e
+            ))
+        })?;
+        Ok(target)
+    }
+
+    /// Stream the file into `part`, resuming from `downloaded` bytes.
+    async fn download<F>(
+        &self,
+        model: &ModelRef,
+        part: &Path,
+        downloaded: &mut u64,
+        total: u64,
+        progress: &mut F,
+    ) -> Result<()>
+    where
+        F: FnMut(u64, u64),
+    {
+        let url = format!(
+            "{}/{}/resolve/{}/{}",
+            self.endpoint,
+            model.repo,
+            model.revision,
+            model.file.as_deref().unwrap_or_default()
+        );
+        let mut request = self.authorized(self.client.get(&url));
+        if *downloaded > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", downloaded));
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AppError::External(format!("Failed to download {}: {}", model, e)))?;
+
+        let mut file = match response.status() {
+            reqwest::StatusCode::PARTIAL_CONTENT => {
+                tokio::fs::OpenOptions::new().append(true).open(part).await
+            }
+            status if status.is_success() => {
+                // Server ignored the range request; start over
+                *downloaded = 0;
+                tokio::fs::File::create(part).await
+            }
+            status => {
+                return Err(AppError::External(format!(
+                    "Failed to download {}: HTTP {}",
+                    model, status
+                )))
+            }
+        }
+        .map_err(|e| AppError::Internal(format!("Failed to open {}: {}", part.display(), e)))?;
+
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| {
+                AppError::External(format!("Download of {} interrupted: {}", model, e))
+            })?;
+            file.write_all(&chunk).await.map_err(|e| {
+                AppError::Internal(format!("Failed to write {}: {}", part.display(), e))
+            })?;
+            *downloaded += chunk.len() as u64;
+            progress(*downloaded, total);
+        }
+        file.flush().await.map_err(|e| {
+            AppError::Internal(format!("Failed to write {}: {}", part.display(), e))
+        })?;
+
+        if *downloaded != total {
+            return Err(AppError::External(format!(
+                "Download of {} incomplete: got {} of {} bytes (run pull again to resume)",
+                model, downloaded, total
+            )));
+        }
+        Ok(())
+    }
+
+    /// Look up the file to download, picking the only GGUF file if none was named.
+    async fn remote_file(&self, model: &ModelRef) -> Result<RemoteFile> {
+        let url = format!(
+            "{}/api/models/{}/tree/{}?recursive=true",
+            self.endpoint, model.repo, model.revision
+        );
+        let response = self
+            .authorized(self.client.get(&url))
+            .send()
+            .await
+            .map_err(|e| AppError::External(format!("Failed to list {}: {}", model.repo, e)))?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(AppError::NotFound(format!(
+                "Repository '{}' (revision {}) not found",
+                model.repo, model.revision
+            )));
+        }
+        if !response.status().is_success() {
+            return Err(AppError::External(format!(
+                "Failed to list {}: HTTP {}",
+                model.repo,
+                response.status()
+            )));
+        }
+
+        let files: Vec<RemoteFile> = response.json().await.map_err(|e| {
+            AppError::External(format!("Invalid file listing for {}: {}", model.repo, e))
+        })?;
+        let mut ggufs: Vec<RemoteFile> = files
+            .into_iter()
+            .filter(|f| f.kind == "file" && f.path.to_lowercase().ends_with(".gguf"))
+            .collect();
+
+        match &model.file {
+            Some(file) => ggufs.into_iter().find(|f| &f.path == file).ok_or_else(|| {
+                AppError::NotFound(format!("'{}' not found in {}", file, model.repo))
+            }),
+            None if ggufs.len() == 1 => Ok(ggufs.remove(0)),
+            None if ggufs.is_empty() => Err(AppError::NotFound(format!(
+                "No GGUF files found in {}",
+                model.repo
+            ))),
+            None => Err(AppError::InvalidInput(format!(
+                "{} contains several GGUF files, pick one of: {}",
+                model.repo,
+                ggufs
+                    .iter()
+                    .map(|f| f.path.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    fn authorized(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+}
+
+impl Default for ModelStore {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+fn part_path(path: &Path) -> PathBuf {
+    let mut part = path.as_os_str().to_owned();
+    part.push(".part");
+    PathBuf::from(part)
+}
+
+fn collect_models(root: &Path, dir: &Path, models: &mut Vec<LocalModel>) -> Result<()> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| AppError::Internal(format!("Failed to read {}: {}", dir.display(), e)))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_models(root, &path, models)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("gguf"))
+        {
+            let reference = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            models.push(LocalModel {
+                reference,
+                path,
+                size,
+            });
+        }
+    }
+    Ok(())
+}
+
+async fn sha256_file(path: &Path) -> Result<String> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut file = std::fs::File::open(&path)
+            .map_err(|e| AppError::Internal(format!("Failed to open {}: {}", path.display(), e)))?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher)
+            .map_err(|e| AppError::Internal(format!("Failed to read {}: {}", path.display(), e)))?;
+        Ok(hex::encode(hasher.finalize()))
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("Checksum task failed: {}", e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const CONTENT: &[u8] = b"GGUF fake model weights";
+
+    fn sha(bytes: &[u8]) -> String {
+        hex::encode(Sha256::digest(bytes))
+    }
+
+    async fn mock_repo(server: &MockServer, checksum: &str) {
+        Mock::given(method("GET"))
+            .and(path("/api/models/acme/tiny-GGUF/tree/main"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {"type": "file", "path": "README.md", "size": 10},
+                {"type": "file", "path": "tiny-q4.gguf", "size": 133,
+                 "lfs": {"oid": checksum, "size": CONTENT.len(), "pointerSize": 133}}
+            ])))
+            .mount(server)
+            .await;
+    }
+
+    #[test]
+    fn test_parse_model_ref() {
+        let model: ModelRef = "acme/tiny-GGUF/sub/tiny-q4.gguf@v1".parse().unwrap();
+        assert_eq!(model.repo, "acme/tiny-GGUF");
+        assert_eq!(model.file.as_deref(), Some("sub/tiny-q4.gguf"));
+        assert_eq!(model.revision, "v1");
+        assert_eq!(model.to_string(), "acme/tiny-GGUF/sub/tiny-q4.gguf@v1");
+
+        let repo_only: ModelRef = "acme/tiny-GGUF".parse().unwrap();
+        assert_eq!(repo_only.file, None);
+        assert_eq!(repo_only.revision, "main");
+
+        assert!("acme".parse::<ModelRef>().is_err());
+        assert!("acme/tiny/../../etc.gguf".parse::<ModelRef>().is_err());
+        assert!("acme/tiny/weights.bin".parse::<ModelRef>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pull_verifies_and_lists_model() {
+        let server = MockServer::start().await;
+        mock_repo(&server, &sha(CONTENT)).await;
+        Mock::given(method("GET"))
+            .and(path("/acme/tiny-GGUF/resolve/main/tiny-q4.gguf"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(CONTENT))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = ModelStore::new(dir.path()).with_endpoint(server.uri());
+
+        // Repository with a single GGUF file doesn't need the file named
+        let path = store
+            .pull(&"acme/tiny-GGUF".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+        assert_eq!(store.resolve("acme/tiny-GGUF/tiny-q4.gguf").unwrap(), path);
+
+        let models = store.list().unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].reference, "acme/tiny-GGUF/tiny-q4.gguf");
+        assert_eq!(models[0].size, CONTENT.len() as u64);
+
+        store
+            .remove(&ModelRef::new("acme/tiny-GGUF", "tiny-q4.gguf"))
+            .unwrap();
+        assert!(store.resolve("acme/tiny-GGUF/tiny-q4.gguf").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pull_resumes_partial_download() {
+        let server = MockServer::start().await;
+        mock_repo(&server, &sha(CONTENT)).await;
+        Mock::given(method("GET"))
+            .and(path("/acme/tiny-GGUF/resolve/main/tiny-q4.gguf"))
+            .and(header("range", "bytes=5-"))
+            .respond_with(ResponseTemplate::new(206).set_body_bytes(&CONTENT[5..]))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = ModelStore::new(dir.path()).with_endpoint(server.uri());
+        let model = ModelRef::new("acme/tiny-GGUF", "tiny-q4.gguf");
+        let target = store.path_for(&model).unwrap();
+        std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+        std::fs::write(part_path(&target), &CONTENT[..5]).unwrap();
+
+        let path = store.pull(&model).await.unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), CONTENT);
+        assert!(!part_path(&target).exists());
+    }
+
+    #[tokio::test]
+    async fn test_pull_rejects_checksum_mismatch() {
+        let server = MockServer::start().await;
+        mock_repo(&server, &sha(b"something else")).await;
+        Mock::given(method("GET"))
+            .and(path("/acme/tiny-GGUF/resolve/main/tiny-q4.gguf"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(CONTENT))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = ModelStore::new(dir.path()).with_endpoint(server.uri());
+        let model = ModelRef::new("acme/tiny-GGUF", "tiny-q4.gguf");
+
+        let err = store.pull(&model).await.unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"));
+        let target = store.path_for(&model).unwrap();
+        assert!(!target.exists());
+        assert!(!part_path(&target).exists());
+    }
+}
@@ -0,0 +1,561 @@
+//! Judge-model scoring.
+//!
+//! A [`Judge`] asks a model to score an output from 0 to 10 against a
+//! [`Rubric`], one score per criterion. Every score is anchored to the same
+//! scale so judgments from different models and rubrics are comparable; the
+//! output's score is the average over its criteria and over `samples`
+//! separate judgments. Judgments are cached by model, rubric, input and
+//! output, so an unchanged output is never scored twice.
+//!
+//! Judges are configured under `[judges]` and used by agent reflection,
+//! workflows, the research critique pass and the LLM-judge reranker:
+//!
+//! ```toml
+//! [judges.quality]
+//! model = "fast"
+//! criteria = ["Accuracy: every claim is correct", "Completeness: every part of the request is addressed"]
+//! pass_score = 7.0
+//! ```
+
+use crate::llm::{LLMClient, ProviderRegistry};
+use crate::types::Result;
+use crate::utils::toml_config::JudgeConfig;
+use lru::LruCache;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// Highest score on the judging scale.
+pub const MAX_SCORE: f32 = 10.0;
+
+/// Judgments kept by the shared cache.
+const CACHE_CAPACITY: usize = 1024;
+
+/// How long a cached judgment is reused.
+const CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Anchors every judge scores against.
+const SCALE: &str = "Score each criterion from 0 to 10:\n\
+0-2: fails the criterion entirely\n\
+3-4: major problems\n\
+5-6: partly meets it\n\
+7-8: meets it, with minor problems\n\
+9-10: fully meets it, with nothing to improve\n\
+Use the whole scale and keep 9-10 for outputs you would not change.";
+
+/// Criteria an output is scored on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rubric {
+    criteria: Vec<String>,
+}
+
+impl Rubric {
+    /// Score on the given criteria, falling back to the default rubric if
+    /// there are none.
+    pub fn new(criteria: Vec<String>) -> Self {
+        if criteria.is_empty() {
+            return Self::default();
+        }
+        Self { criteria }
+    }
+
+    /// Score how well a document answers a search query.
+    pub fn relevance() -> Self {
+        Self::new(vec![
+            "Relevance: the output answers the input directly".to_string()
+        ])
+    }
+
+    /// The criteria, in order.
+    pub fn criteria(&self) -> &[String] {
+        &self.criteria
+    }
+}
+
+impl Default for Rubric {
+    /// General answer quality.
+    fn default() -> Self {
+        Self::new(vec![
+            "Accuracy: every claim is correct and supported".to_string(),
+            "Completeness: every part of the request is addressed".to_string(),
+            "Relevance: nothing is off-topic".to_string(),
+            "Clarity: the output is well organised and easy to follow".to_string(),
+        ])
+    }
+}
+
+/// Score of an output on one criterion.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CriterionScore {
+    /// The criterion as written in the rubric
+    pub criterion: String,
+    /// Score from 0 to 10
+    pub score: f32,
+}
+
+/// A judge's verdict on an output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Judgment {
+    /// Score from 0 to 10: the average of the criteria scores
+    pub score: f32,
+    /// Whether the score reaches the judge's pass score
+    pub passed: bool,
+    /// Score on each criterion the judge rated
+    #[serde(default)]
+    pub criteria: Vec<CriterionScore>,
+    /// What the judge would improve
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback: Option<String>,
+}
+
+impl Judgment {
+    /// The judgment as a critique to revise the output by.
+    pub fn critique(&self) -> String {
+        let mut critique = format!("Score: {:.1}/10", self.score);
+        for criterion in &self.criteria {
+            critique.push_str(&format!(
+                "\n- {}: {:.1}",
+                criterion.criterion, criterion.score
+            ));
+        }
+        if let Some(feedback) = &self.feedback {
+            critique.push_str(&format!("\n{}", feedback));
+        }
+        critique
+    }
+}
+
+/// Cache of judgments, with entries expiring after an hour.
+pub struct JudgmentCache {
+    entries: Mutex<LruCache<String, (Judgment, Instant)>>,
+    ttl: Duration,
+}
+
+impl JudgmentCache {
+    /// Create a cache holding up to `capacity` judgments for `ttl`.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl,
+        }
+    }
+
+    /// The cache shared by all judges of the process.
+    pub fn shared() -> Arc<JudgmentCache> {
+        static SHARED: OnceLock<Arc<JudgmentCache>> = OnceLock::new();
+        SHARED
+            .get_or_init(|| Arc::new(JudgmentCache::new(CACHE_CAPACITY, CACHE_TTL)))
+            .clone()
+    }
+
+    fn get(&self, key: &str) -> Option<Judgment> {
+        let mut entries = self.entries.lock();
+        match entries.get(key) {
+            Some((judgment, at)) if at.elapsed() < self.ttl => Some(judgment.clone()),
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn put(&self, key: String, judgment: Judgment) {
+        self.entries.lock().put(key, (judgment, Instant::now()));
+    }
+}
+
+/// Scores outputs against a rubric with a judge model.
+pub struct Judge {
+    llm: Box<dyn LLMClient>,
+    rubric: Rubric,
+    pass_score: f32,
+    samples: usize,
+    cache: Option<Arc<JudgmentCache>>,
+}
+
+impl Judge {
+    /// Score with `llm` against `rubric`, once per output, without caching.
+    pub fn new(llm: Box<dyn LLMClient>, rubric: Rubric) -> Self {
+        Self {
+            llm,
+            rubric,
+            pass_score: 7.0,
+            samples: 1,
+            cache: None,
+        }
+    }
+
+    /// Create a judge from its `[judges]` config, using the shared cache.
+    pub async fn from_config(config: &JudgeConfig, providers: &ProviderRegistry) -> Result<Self> {
+        let llm = providers.create_client_for_model(&config.model).await?;
+        Ok(Self::with_config(llm, config))
+    }
+
+    /// Create a judge from its `[judges]` config, scoring with `llm`, using
+    /// the shared cache.
+    pub fn with_config(llm: Box<dyn LLMClient>, config: &JudgeConfig) -> Self {
+        Self::new(llm, Rubric::new(config.criteria.clone()))
+            .with_pass_score(config.pass_score)
+            .with_samples(config.samples)
+            .with_cache(JudgmentCache::shared())
+    }
+
+    /// Set the lowest score an output passes with
+    pub fn with_pass_score(mut self, pass_score: f32) -> Self {
+        self.pass_score = pass_score.clamp(0.0, MAX_SCORE);
+        self
+    }
+
+    /// Average this many judgments per output
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    /// Reuse judgments from `cache`
+    pub fn with_cache(mut self, cache: Arc<JudgmentCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Lowest score an output passes with
+    pub fn pass_score(&self) -> f32 {
+        self.pass_score
+    }
+
+    /// Score `output`, produced for `input`.
+    ///
+    /// A reply without any score counts as 0, so an unreadable judgment
+    /// never passes an output.
+    pub async fn judge(&self, input: &str, output: &str) -> Result<Judgment> {
+        let key = self.cache_key(input, output);
+        if let Some(mut judgment) = self.cache.as_ref().and_then(|cache| cache.get(&key)) {
+            // Judges sharing a cache may pass outputs at different scores
+            judgment.passed = judgment.score >= self.pass_score;
+            return Ok(judgment);
+        }
+
+        let messages = judge_messages(&self.rubric, input, output);
+        let replies = futures::future::try_join_all(
+            (0..self.samples).map(|_| self.llm.generate_with_history(&messages)),
+        )
+        .await?;
+        let judgments: Vec<_> = replies
+            .iter()
+            .map(|reply| parse_judgment(&self.rubric, reply))
+            .collect();
+        let judgment = self.combine(judgments);
+
+        if let Some(cache) = &self.cache {
+            cache.put(key, judgment.clone());
+        }
+        Ok(judgment)
+    }
+
+    /// Average sampled judgments into one.
+    fn combine(&self, judgments: Vec<(f32, Vec<CriterionScore>, Option<String>)>) -> Judgment {
+        let count = judgments.len().max(1) as f32;
+        let score = judgments.iter().map(|(score, _, _)| score).sum::<f32>() / count;
+        let mut criteria: Vec<CriterionScore> = Vec::new();
+        for criterion in self.rubric.criteria() {
+            let scores: Vec<f32> = judgments
+                .iter()
+                .flat_map(|(_, scores, _)| scores)
+                .filter(|s| &s.criterion == criterion)
+                .map(|s| s.score)
+                .collect();
+            if !scores.is_empty() {
+                criteria.push(CriterionScore {
+                    criterion: criterion.clone(),
+                    score: round(scores.iter().sum::<f32>() / scores.len() as f32),
+                });
+            }
+        }
+        let feedback = judgments.into_iter().find_map(|(_, _, feedback)| feedback);
+        let score = round(score);
+        Judgment {
+            score,
+            passed: score >= self.pass_score,
+            criteria,
+            feedback,
+        }
+    }
+
+    fn cache_key(&self, input: &str, output: &str) -> String {
+        let mut hasher = Sha256::new();
+        for part in [
+            self.llm.model_name(),
+            &self.samples.to_string(),
+            input,
+            output,
+        ]
+        .into_iter()
+        .chain(self.rubric.criteria().iter().map(String::as_str))
+        {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// Prompt asking the judge to score `output`, produced for `input`.
+pub fn judge_messages(rubric: &Rubric, input: &str, output: &str) -> Vec<(String, String)> {
+    let criteria = rubric
+        .criteria()
+        .iter()
+        .enumerate()
+        .map(|(i, criterion)| format!("{}. {}", i + 1, criterion))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let system = format!(
+        "You are a strict, impartial judge scoring an output against these criteria:\n{}\n\n{}\n\n\
+         Reply with one line per criterion as `<number>: <score>`, then a line starting with \
+         `Feedback:` saying what would most improve the output, or `Feedback: none`.",
+        criteria, SCALE
+    );
+    vec![
+        ("system".to_string(), system),
+        (
+            "user".to_string(),
+            format!("Input:\n{}\n\nOutput to score:\n{}", input, output),
+        ),
+    ]
+}
+
+/// Read a judge's reply: the overall score, criteria scores and feedback.
+///
+/// Criteria without a score are left out of the average. A reply with no
+/// criteria scores is read as a single overall score.
+fn parse_judgment(rubric: &Rubric, reply: &str) -> (f32, Vec<CriterionScore>, Option<String>) {
+    let mut criteria: Vec<CriterionScore> = Vec::new();
+    let mut feedback = None;
+    let mut lines = reply.lines();
+    while let Some(line) = lines.next() {
+        let line = line.trim().trim_start_matches(['-', '*', ' ']);
+        if let Some(rest) = strip_prefix_ignore_case(line, "feedback:") {
+            let text = std::iter::once(rest)
+                .chain(lines.by_ref())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let text = text.trim();
+            if !text.is_empty() && !text.eq_ignore_ascii_case("none") {
+                feedback = Some(text.to_string());
+            }
+            break;
+        }
+        let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let Ok(number) = line[..digits].parse::<usize>() else {
+            continue;
+        };
+        let Some(criterion) = number.checked_sub(1).and_then(|i| rubric.criteria().get(i)) else {
+            continue;
+        };
+        // "1: 8", "1. Accuracy: 8/10"
+        let rest = &line[digits..];
+        let rest = rest.rsplit_once(':').map_or(rest, |(_, score)| score);
+        if let Some(score) = parse_score(rest) {
+            if !criteria.iter().any(|c| &c.criterion == criterion) {
+                criteria.push(CriterionScore {
+                    criterion: criterion.clone(),
+                    score,
+                });
+            }
+        }
+    }
+
+    let score = if criteria.is_empty() {
+        parse_score(reply.split_once(':').map_or(reply, |(_, rest)| rest)).unwrap_or(0.0)
+    } else {
+        criteria.iter().map(|c| c.score).sum::<f32>() / criteria.len() as f32
+    };
+    (score, criteria, feedback)
+}
+
+/// Extract the first number from text, clamped to the scoring scale.
+pub fn parse_score(text: &str) -> Option<f32> {
+    text.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .find_map(|token| token.trim_matches('.').parse::<f32>().ok())
+        .map(|score| score.clamp(0.0, MAX_SCORE))
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    text.get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &text[prefix.len()..])
+}
+
+fn round(score: f32) -> f32 {
+    (score * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Replies with the given judgments in turn, counting calls
+    struct ScriptedJudge {
+        replies: Vec<&'static str>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl LLMClient for ScriptedJudge {
+        async fn generate(&self, _: &str) -> Result<String> {
+            unimplemented!()
+        }
+        async fn generate_with_system(&self, _: &str, _: &str) -> Result<String> {
+            unimplemented!()
+        }
+        async fn generate_with_history(&self, _: &[(String, String)]) -> Result<String> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.replies[call % self.replies.len()].to_string())
+        }
+        async fn generate_with_tools(
+            &self,
+            _: &str,
+            _: &[crate::types::ToolDefinition],
+        ) -> Result<crate::llm::LLMResponse> {
+            unimplemented!()
+        }
+        async fn generate_with_tools_and_history(
+            &self,
+            _: &[crate::llm::coordinator::ConversationMessage],
+            _: &[crate::types::ToolDefinition],
+        ) -> Result<crate::llm::LLMResponse> {
+            unimplemented!()
+        }
+        async fn stream(
+            &self,
+            _: &str,
+        ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+            unimplemented!()
+        }
+        async fn stream_with_system(
+            &self,
+            _: &str,
+            _: &str,
+        ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+            unimplemented!()
+        }
+        async fn stream_with_history(
+            &self,
+            _: &[(String, String)],
+        ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+            unimplemented!()
+        }
+        fn model_name(&self) -> &str {
+            "judge"
+        }
+    }
+
+    fn rubric() -> Rubric {
+        Rubric::new(vec!["Accuracy".to_string(), "Clarity".to_string()])
+    }
+
+    #[test]
+    fn test_parse_score() {
+        assert_eq!(parse_score("8"), Some(8.0));
+        assert_eq!(parse_score("Rating: 7.5/10"), Some(7.5));
+        assert_eq!(parse_score("42"), Some(10.0));
+        assert_eq!(parse_score("not relevant"), None);
+    }
+
+    #[test]
+    fn test_parse_judgment() {
+        let reply =
+            "1. Accuracy: 8/10\n- 2: 5\n3: 9\nFeedback: Explain the second step.\nAdd an example.";
+        let (score, criteria, feedback) = parse_judgment(&rubric(), reply);
+        assert_eq!(score, 6.5);
+        assert_eq!(criteria.len(), 2);
+        assert_eq!(criteria[1].criterion, "Clarity");
+        assert_eq!(
+            feedback.as_deref(),
+            Some("Explain the second step.\nAdd an example.")
+        );
+
+        // A bare rating is the overall score; no rating at all scores 0
+        assert_eq!(parse_judgment(&Rubric::relevance(), "Rating: 7").0, 7.0);
+        assert_eq!(parse_judgment(&rubric(), "Looks good").0, 0.0);
+        assert!(parse_judgment(&rubric(), "1: 9\nFeedback: none")
+            .2
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_judge_averages_samples_and_caches() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let llm = ScriptedJudge {
+            replies: vec!["1: 8\n2: 6\nFeedback: Shorter", "1: 6\n2: 6"],
+            calls: calls.clone(),
+        };
+        let judge = Judge::new(Box::new(llm), rubric())
+            .with_samples(2)
+            .with_pass_score(7.0)
+            .with_cache(Arc::new(JudgmentCache::new(8, CACHE_TTL)));
+
+        let judgment = judge
+            .judge("Explain Rust", "Rust is a language.")
+            .await
+            .unwrap();
+        assert_eq!(judgment.score, 6.5);
+        assert!(!judgment.passed);
+        assert_eq!(judgment.criteria[0].score, 7.0);
+        assert_eq!(judgment.feedback.as_deref(), Some("Shorter"));
+        assert!(judgment
+            .critique()
+            .starts_with("Score: 6.5/10\n- Accuracy: 7.0"));
+
+        let again = judge
+            .judge("Explain Rust", "Rust is a language.")
+            .await
+            .unwrap();
+        assert_eq!(again, judgment);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        judge
+            .judge("Explain Rust", "A different answer")
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_shared_cache_applies_each_judges_pass_score() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cache = Arc::new(JudgmentCache::new(8, CACHE_TTL));
+        let judge = |pass_score| {
+            let llm = ScriptedJudge {
+                replies: vec!["1: 7\n2: 6"],
+                calls: calls.clone(),
+            };
+            Judge::new(Box::new(llm), rubric())
+                .with_pass_score(pass_score)
+                .with_cache(cache.clone())
+        };
+
+        let strict = judge(7.0).judge("Explain Rust", "Rust.").await.unwrap();
+        let lenient = judge(6.0).judge("Explain Rust", "Rust.").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(strict.score, lenient.score);
+        assert!(!strict.passed);
+        assert!(lenient.passed);
+    }
+
+    #[test]
+    fn test_judge_messages_list_criteria() {
+        let messages = judge_messages(&rubric(), "question", "answer");
+        assert!(messages[0].1.contains("1. Accuracy\n2. Clarity"));
+        assert!(messages[0].1.contains("9-10"));
+        assert!(messages[1].1.ends_with("Output to score:\nanswer"));
+        assert_eq!(Rubric::new(vec![]), Rubric::default());
+    }
+}
@@ -22,6 +22,7 @@
 //! let response = client.generate("Hello, world!").await?;
 //! ```
 
+use crate::llm::cancellation::CancellationToken;
 use crate::llm::client::{LLMClient, LLMResponse, ModelParams, TokenUsage};
 use crate::llm::coordinator::{ConversationMessage, MessageRole};
 use crate::types::{AppError, Result, ToolDefinition};
@@ -35,10 +36,48 @@ use llama_cpp_2::{
     model::{params::LlamaModelParams, AddBos, LlamaModel, Special},
     sampling::LlamaSampler,
 };
+use parking_lot::Mutex;
+use std::collections::HashMap;
 use std::num::NonZeroU32;
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock};
 use tokio::sync::mpsc;
 
+/// The llama.cpp backend, which can only be initialized once per process
+static BACKEND: Mutex<Option<Arc<LlamaBackend>>> = Mutex::new(None);
+
+/// Models loaded so far, by path, shared by every client using them
+static MODELS: LazyLock<Mutex<HashMap<String, Arc<LlamaModel>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The process's llama.cpp backend, initialized on first use
+fn shared_backend() -> Result<Arc<LlamaBackend>> {
+    let mut backend = BACKEND.lock();
+    if let Some(backend) = backend.as_ref() {
+        return Ok(Arc::clone(backend));
+    }
+    let initialized = Arc::new(
+        LlamaBackend::init()
+            .map_err(|e| AppError::LLM(format!("Failed to initialize llama backend: {}", e)))?,
+    );
+    *backend = Some(Arc::clone(&initialized));
+    Ok(initialized)
+}
+
+/// The model at `model_path`, loaded once and kept for later clients
+fn shared_model(backend: &LlamaBackend, model_path: &str) -> Result<Arc<LlamaModel>> {
+    // Held while loading, so a model requested twice at once is loaded once
+    let mut models = MODELS.lock();
+    if let Some(model) = models.get(model_path) {
+        return Ok(Arc::clone(model));
+    }
+    let model_params = LlamaModelParams::default();
+    let model = LlamaModel::load_from_file(backend, model_path, &model_params)
+        .map_err(|e| AppError::LLM(format!("Failed to load model from '{}': {}", model_path, e)))?;
+    let model = Arc::new(model);
+    models.insert(model_path.to_string(), Arc::clone(&model));
+    Ok(model)
+}
+
 /// LlamaCpp client for local GGUF model inference
 #[derive(Debug)]
 pub struct LlamaCppClient {
@@ -106,23 +145,15 @@ impl LlamaCppClient {
         temperature: f32,
         top_p: f32,
     ) -> Result<Self> {
-        // Initialize the backend (must be done once)
-        let backend = LlamaBackend::init()
-            .map_err(|e| AppError::LLM(format!("Failed to initialize llama backend: {}", e)))?;
-
-        // Set up model parameters
-        let model_params = LlamaModelParams::default();
-
-        // Load the model
-        let model =
-            LlamaModel::load_from_file(&backend, &model_path, &model_params).map_err(|e| {
-                AppError::LLM(format!("Failed to load model from '{}': {}", model_path, e))
-            })?;
+        // The backend and loaded models are shared by all clients, so only
+        // the first client of a model pays for loading it
+        let backend = shared_backend()?;
+        let model = shared_model(&backend, &model_path)?;
 
         Ok(Self {
             model_path,
-            model: Arc::new(model),
-            backend: Arc::new(backend),
+            model,
+            backend,
             n_ctx,
             n_threads,
             max_tokens,
@@ -161,6 +192,11 @@ impl LlamaCppClient {
         let top_p = self.top_p;
         let prompt = prompt.to_string();
 
+        // The blocking task outlives this future if it is dropped, so signal it
+        // to stop decoding and release the context
+        let cancellation = CancellationToken::new();
+        let _cancel_on_drop = cancellation.clone().drop_guard();
+
         // Run blocking llama operations in a spawn_blocking task
         tokio::task::spawn_blocking(move || {
             Self::generate_sync(
@@ -172,6 +208,7 @@ impl LlamaCppClient {
                 max_tokens,
                 temperature,
                 top_p,
+                &cancellation,
             )
         })
         .await
@@ -188,6 +225,7 @@ impl LlamaCppClient {
         max_tokens: u32,
         temperature: f32,
         top_p: f32,
+        cancellation: &CancellationToken,
     ) -> Result<String> {
         // Create context parameters
         let ctx_params = LlamaContextParams::default()
@@ -236,6 +274,11 @@ impl LlamaCppClient {
         let mut n_cur = tokens.len();
 
         for _ in 0..max_tokens {
+            // Stop as soon as the caller has gone away
+            if cancellation.is_cancelled() {
+                return Err(AppError::Cancelled("Generation cancelled".to_string()));
+            }
+
             // Sample the next token
             let new_token = sampler.sample(&ctx, -1);
 
@@ -377,6 +420,11 @@ impl LlamaCppClient {
         let mut n_cur = tokens.len();
 
         for _ in 0..max_tokens {
+            // Stop as soon as the stream has been dropped, even between pieces
+            if tx.is_closed() {
+                break;
+            }
+
             // Sample the next token
             let new_token = sampler.sample(&ctx, -1);
 
@@ -0,0 +1,487 @@
+//! LLM Call Middleware
+//!
+//! [`LLMMiddleware`] intercepts every call made through a client created by a
+//! [`ProviderRegistry`](crate::llm::ProviderRegistry) or
+//! [`ConfigBasedLLMFactory`](crate::llm::ConfigBasedLLMFactory), regardless of
+//! provider. Typical uses are logging, redaction, caching and prompt rewriting.
+//!
+//! Middleware run like an onion: `before_request` in registration order, the
+//! provider call, then `after_response` in reverse order. A middleware may
+//! answer a request itself (e.g. a cache hit) by returning a response from
+//! `before_request`; the provider and any later middleware are then skipped.
+//!
+//! For streaming calls, `after_response` receives the concatenated text once
+//! the stream has finished; changes to it are not seen by the consumer.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use ares::llm::middleware::{LLMMiddleware, LLMRequest};
+//! use ares::llm::LLMResponse;
+//!
+//! struct Logger;
+//!
+//! #[async_trait::async_trait]
+//! impl LLMMiddleware for Logger {
+//!     fn name(&self) -> &str {
+//!         "logger"
+//!     }
+//!
+//!     async fn after_response(&self, request: &LLMRequest, response: &mut LLMResponse) -> Result<()> {
+//!         tracing::info!(model = %request.model, chars = response.content.len(), "llm call");
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut factory = ConfigBasedLLMFactory::from_config(&config)?;
+//! factory.register_middleware(Arc::new(Logger));
+//! ```
+
+use crate::llm::client::{LLMClient, LLMResponse};
+use crate::llm::coordinator::{ConversationMessage, MessageRole};
+use crate::types::{Result, ToolDefinition};
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use std::sync::Arc;
+
+type TokenStream = Box<dyn Stream<Item = Result<String>> + Send + Unpin>;
+
+/// A provider-independent view of an LLM call.
+#[derive(Debug, Clone)]
+pub struct LLMRequest {
+    /// Model identifier of the underlying client
+    pub model: String,
+    /// Prompt messages, including any system prompt
+    pub messages: Vec<ConversationMessage>,
+    /// Tool definitions offered to the model (empty for plain generations)
+    pub tools: Vec<ToolDefinition>,
+    /// Whether the response is streamed
+    pub stream: bool,
+}
+
+/// Interceptor for LLM requests and responses.
+///
+/// Both methods have no-op defaults.
+#[async_trait]
+pub trait LLMMiddleware: Send + Sync {
+    /// Middleware name, used in logs.
+    fn name(&self) -> &str;
+
+    /// Inspect or rewrite a request before it reaches the provider.
+    ///
+    /// Returning `Some(response)` answers the request without calling the provider.
+    async fn before_request(&self, _request: &mut LLMRequest) -> Result<Option<LLMResponse>> {
+        Ok(None)
+    }
+
+    /// Inspect or rewrite the response to `request`.
+    async fn after_response(
+        &self,
+        _request: &LLMRequest,
+        _response: &mut LLMResponse,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// An [`LLMClient`] that runs a middleware chain around another client.
+pub struct MiddlewareClient {
+    inner: Box<dyn LLMClient>,
+    middleware: Arc<[Arc<dyn LLMMiddleware>]>,
+}
+
+impl MiddlewareClient {
+    /// Wrap `inner` with `middleware`, run in the given order
+    pub fn new(inner: Box<dyn LLMClient>, middleware: Arc<[Arc<dyn LLMMiddleware>]>) -> Self {
+        Self { inner, middleware }
+    }
+
+    /// Wrap `inner` only if there is middleware to run
+    pub fn wrap(
+        inner: Box<dyn LLMClient>,
+        middleware: &[Arc<dyn LLMMiddleware>],
+    ) -> Box<dyn LLMClient> {
+        if middleware.is_empty() {
+            inner
+        } else {
+            Box::new(Self::new(inner, middleware.into()))
+        }
+    }
+
+    fn request(
+        &self,
+        messages: Vec<ConversationMessage>,
+        tools: &[ToolDefinition],
+        stream: bool,
+    ) -> LLMRequest {
+        LLMRequest {
+            model: self.inner.model_name().to_string(),
+            messages,
+            tools: tools.to_vec(),
+            stream,
+        }
+    }
+
+    /// Run `before_request` hooks; returns the index of the middleware that
+    /// answered the request, with its response.
+    async fn before(&self, request: &mut LLMRequest) -> Result<Option<(usize, LLMResponse)>> {
+        for (i, middleware) in self.middleware.iter().enumerate() {
+            if let Some(response) = middleware.before_request(request).await? {
+                return Ok(Some((i, response)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Run a non-streaming call through the chain.
+    async fn call(&self, mut request: LLMRequest) -> Result<LLMResponse> {
+        let (ran, mut response) = match self.before(&mut request).await? {
+            Some(answered) => answered,
+            None => (self.middleware.len(), self.dispatch(&request).await?),
+        };
+        after(&self.middleware[..ran], &request, &mut response).await?;
+        Ok(response)
+    }
+
+    /// Call the inner client with the method matching the request's shape.
+    async fn dispatch(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        let messages = &request.messages;
+        if !request.tools.is_empty() {
+            return match messages.as_slice() {
+                [user] if user.role == MessageRole::User => {
+                    self.inner
+                        .generate_with_tools(&user.content, &request.tools)
+                        .await
+                }
+                _ => {
+                    self.inner
+                        .generate_with_tools_and_history(messages, &request.tools)
+                        .await
+                }
+            };
+        }
+
+        let content = match messages.as_slice() {
+            [user] if user.role == MessageRole::User => self.inner.generate(&user.content).await?,
+            [system, user]
+                if system.role == MessageRole::System && user.role == MessageRole::User =>
+            {
+                self.inner
+                    .generate_with_system(&system.content, &user.content)
+                    .await?
+            }
+            _ => {
+                self.inner
+                    .generate_with_history(&role_content(messages))
+                    .await?
+            }
+        };
+        Ok(text_response(content))
+    }
+
+    /// Run a streaming call through the chain.
+    async fn call_stream(&self, mut request: LLMRequest) -> Result<TokenStream> {
+        let (ran, stream): (usize, TokenStream) = match self.before(&mut request).await? {
+            Some((i, response)) => (i, Box::new(futures::stream::iter([Ok(response.content)]))),
+            None => {
+                let messages = &request.messages;
+                let stream = match messages.as_slice() {
+                    [user] if user.role == MessageRole::User => {
+                        self.inner.stream(&user.content).await?
+                    }
+                    [system, user]
+                        if system.role == MessageRole::System && user.role == MessageRole::User =>
+                    {
+                        self.inner
+                            .stream_with_system(&system.content, &user.content)
+                            .await?
+                    }
+                    _ => {
+                        self.inner
+                            .stream_with_history(&role_content(messages))
+                            .await?
+                    }
+                };
+                (self.middleware.len(), stream)
+            }
+        };
+
+        if ran == 0 {
+            return Ok(stream);
+        }
+
+        let middleware = Arc::clone(&self.middleware);
+        let stream = async_stream::stream! {
+            let mut stream = stream;
+            let mut text = String::new();
+            while let Some(chunk) = stream.next().await {
+                if let Ok(token) = &chunk {
+                    text.push_str(token);
+                }
+                let failed = chunk.is_err();
+                yield chunk;
+                if failed {
+                    return;
+                }
+            }
+            let mut response = text_response(text);
+            if let Err(e) = after(&middleware[..ran], &request, &mut response).await {
+                yield Err(e);
+            }
+        };
+        Ok(Box::new(Box::pin(stream)))
+    }
+}
+
+/// Run `after_response` hooks in reverse order.
+async fn after(
+    middleware: &[Arc<dyn LLMMiddleware>],
+    request: &LLMRequest,
+    response: &mut LLMResponse,
+) -> Result<()> {
+    for middleware in middleware.iter().rev() {
+        middleware.after_response(request, response).await?;
+    }
+    Ok(())
+}
+
+fn text_response(content: String) -> LLMResponse {
+    LLMResponse {
+        content,
+        tool_calls: Vec::new(),
+        finish_reason: "stop".to_string(),
+        usage: None,
+    }
+}
+
+fn history_messages(messages: &[(String, String)]) -> Vec<ConversationMessage> {
+    messages
+        .iter()
+        .map(|(role, content)| ConversationMessage::from_role_content(role, content.as_str()))
+        .collect()
+}
+
+fn role_content(messages: &[ConversationMessage]) -> Vec<(String, String)> {
+    messages.iter().map(|m| m.to_role_content()).collect()
+}
+
+#[async_trait]
+impl LLMClient for MiddlewareClient {
+    async fn generate(&self, prompt: &str) -> Result<String> {
+        let request = self.request(vec![ConversationMessage::user(prompt)], &[], false);
+        Ok(self.call(request).await?.content)
+    }
+
+    async fn generate_with_system(&self, system: &str, prompt: &str) -> Result<String> {
+        let messages = vec![
+            ConversationMessage::system(system),
+            ConversationMessage::user(prompt),
+        ];
+        Ok(self.call(self.request(messages, &[], false)).await?.content)
+    }
+
+    async fn generate_with_history(&self, messages: &[(String, String)]) -> Result<String> {
+        let request = self.request(history_messages(messages), &[], false);
+        Ok(self.call(request).await?.content)
+    }
+
+    async fn generate_with_tools(
+        &self,
+        prompt: &str,
+        tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        let request = self.request(vec![ConversationMessage::user(prompt)], tools, false);
+        self.call(request).await
+    }
+
+    async fn generate_with_tools_and_history(
+        &self,
+        messages: &[ConversationMessage],
+        tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        self.call(self.request(messages.to_vec(), tools, false))
+            .await
+    }
+
+    async fn stream(&self, prompt: &str) -> Result<TokenStream> {
+        let request = self.request(vec![ConversationMessage::user(prompt)], &[], true);
+        self.call_stream(request).await
+    }
+
+    async fn stream_with_system(&self, system: &str, prompt: &str) -> Result<TokenStream> {
+        let messages = vec![
+            ConversationMessage::system(system),
+            ConversationMessage::user(prompt),
+        ];
+        self.call_stream(self.request(messages, &[], true)).await
+    }
+
+    async fn stream_with_history(&self, messages: &[(String, String)]) -> Result<TokenStream> {
+        let request = self.request(history_messages(messages), &[], true);
+        self.call_stream(request).await
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AppError;
+    use std::sync::Mutex;
+
+    /// Echoes the last message, recording which client method was used
+    struct Echo(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Echo {
+        fn record(&self, method: &'static str, content: &str) -> Result<String> {
+            self.0.lock().unwrap().push(method);
+            Ok(content.to_string())
+        }
+    }
+
+    #[async_trait]
+    impl LLMClient for Echo {
+        async fn generate(&self, prompt: &str) -> Result<String> {
+            self.record("generate", prompt)
+        }
+        async fn generate_with_system(&self, _: &str, prompt: &str) -> Result<String> {
+            self.record("generate_with_system", prompt)
+        }
+        async fn generate_with_history(&self, messages: &[(String, String)]) -> Result<String> {
+            self.record("generate_with_history", &messages.last().unwrap().1)
+        }
+        async fn generate_with_tools(&self, _: &str, _: &[ToolDefinition]) -> Result<LLMResponse> {
+            unimplemented!()
+        }
+        async fn generate_with_tools_and_history(
+            &self,
+            _: &[ConversationMessage],
+            _: &[ToolDefinition],
+        ) -> Result<LLMResponse> {
+            unimplemented!()
+        }
+        async fn stream(&self, prompt: &str) -> Result<TokenStream> {
+            self.0.lock().unwrap().push("stream");
+            let tokens: Vec<Result<String>> =
+                prompt.split(' ').map(|t| Ok(t.to_string())).collect();
+            Ok(Box::new(futures::stream::iter(tokens)))
+        }
+        async fn stream_with_system(&self, _: &str, _: &str) -> Result<TokenStream> {
+            unimplemented!()
+        }
+        async fn stream_with_history(&self, _: &[(String, String)]) -> Result<TokenStream> {
+            unimplemented!()
+        }
+        fn model_name(&self) -> &str {
+            "echo"
+        }
+    }
+
+    /// Uppercases prompts and wraps responses in brackets
+    struct Shout;
+
+    #[async_trait]
+    impl LLMMiddleware for Shout {
+        fn name(&self) -> &str {
+            "shout"
+        }
+
+        async fn before_request(&self, request: &mut LLMRequest) -> Result<Option<LLMResponse>> {
+            for message in &mut request.messages {
+                message.content = message.content.to_uppercase();
+            }
+            Ok(None)
+        }
+
+        async fn after_response(&self, _: &LLMRequest, response: &mut LLMResponse) -> Result<()> {
+            response.content = format!("[{}]", response.content);
+            Ok(())
+        }
+    }
+
+    /// Answers prompts equal to "cached" without calling the provider
+    struct Cache;
+
+    #[async_trait]
+    impl LLMMiddleware for Cache {
+        fn name(&self) -> &str {
+            "cache"
+        }
+
+        async fn before_request(&self, request: &mut LLMRequest) -> Result<Option<LLMResponse>> {
+            if request.messages.last().map(|m| m.content.as_str()) == Some("CACHED") {
+                return Ok(Some(text_response("hit".to_string())));
+            }
+            Ok(None)
+        }
+
+        async fn after_response(&self, _: &LLMRequest, response: &mut LLMResponse) -> Result<()> {
+            if response.content == "hit" {
+                return Err(AppError::Internal("cache saw its own hit".into()));
+            }
+            Ok(())
+        }
+    }
+
+    fn client(calls: &Arc<Mutex<Vec<&'static str>>>) -> Box<dyn LLMClient> {
+        let middleware: Vec<Arc<dyn LLMMiddleware>> = vec![Arc::new(Shout), Arc::new(Cache)];
+        MiddlewareClient::wrap(Box::new(Echo(Arc::clone(calls))), &middleware)
+    }
+
+    #[tokio::test]
+    async fn test_middleware_rewrites_request_and_response() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let client = client(&calls);
+
+        assert_eq!(client.generate("hello").await.unwrap(), "[HELLO]");
+        assert_eq!(
+            client.generate_with_system("be brief", "hi").await.unwrap(),
+            "[HI]"
+        );
+        let history = vec![
+            ("user".to_string(), "a".to_string()),
+            ("assistant".to_string(), "b".to_string()),
+            ("user".to_string(), "c".to_string()),
+        ];
+        assert_eq!(client.generate_with_history(&history).await.unwrap(), "[C]");
+
+        // The provider method matching the original call is preserved
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec!["generate", "generate_with_system", "generate_with_history"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_middleware_can_answer_request() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let client = client(&calls);
+
+        assert_eq!(client.generate("cached").await.unwrap(), "[hit]");
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_middleware_sees_streamed_response() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let client = client(&calls);
+
+        let tokens: Vec<String> = client
+            .stream("a b")
+            .await
+            .unwrap()
+            .map(|t| t.unwrap())
+            .collect()
+            .await;
+        // Tokens pass through unchanged; the response hooks run once the stream ends
+        assert_eq!(tokens, vec!["A", "B"]);
+
+        let tokens: Vec<Result<String>> = client.stream("cached").await.unwrap().collect().await;
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].as_ref().unwrap(), "hit");
+        assert_eq!(*calls.lock().unwrap(), vec!["stream"]);
+    }
+}
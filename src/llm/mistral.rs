@@ -0,0 +1,502 @@
+//! Mistral AI LLM client implementation
+//!
+//! This module provides a native client for Mistral's La Plateforme chat
+//! completions API. The API is close to OpenAI's but differs in ways that
+//! break generic clients:
+//!
+//! - Tool call IDs must be exactly 9 alphanumeric characters
+//! - Tool result messages must carry the name of the function they answer
+//! - Function arguments may be returned as a JSON object instead of a string
+//!
+//! # Features
+//!
+//! Enable with the `mistral` feature flag.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use ares::llm::{LLMClient, Provider};
+//!
+//! let provider = Provider::Mistral {
+//!     api_key: "...".to_string(),
+//!     api_base: "https://api.mistral.ai/v1".to_string(),
+//!     model: "mistral-large-latest".to_string(),
+//!     params: ModelParams::default(),
+//! };
+//! let client = provider.create_client().await?;
+//! let response = client.generate("Hello!").await?;
+//! ```
+
+use crate::llm::client::{LLMClient, LLMResponse, ModelParams, TokenUsage};
+use crate::llm::coordinator::{ConversationMessage, MessageRole};
+use crate::types::{AppError, Result, ToolCall, ToolDefinition};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Length Mistral requires for tool call IDs
+const TOOL_CALL_ID_LEN: usize = 9;
+
+/// Mistral AI client for API-based inference
+pub struct MistralClient {
+    http: reqwest::Client,
+    api_key: String,
+    api_base: String,
+    model: String,
+    params: ModelParams,
+}
+
+impl MistralClient {
+    /// Create a new Mistral client against the public API
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - Mistral API key
+    /// * `model` - Model identifier (e.g., "mistral-large-latest")
+    pub fn new(api_key: String, model: String) -> Self {
+        Self::with_params(
+            api_key,
+            "https://api.mistral.ai/v1".to_string(),
+            model,
+            ModelParams::default(),
+        )
+    }
+
+    /// Create a new Mistral client with model parameters
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - Mistral API key
+    /// * `api_base` - Base URL for the API (e.g., `https://api.mistral.ai/v1`)
+    /// * `model` - Model identifier (e.g., "mistral-large-latest")
+    /// * `params` - Model inference parameters (temperature, max_tokens, etc.)
+    pub fn with_params(
+        api_key: String,
+        api_base: String,
+        model: String,
+        params: ModelParams,
+    ) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_key,
+            api_base: api_base.trim_end_matches('/').to_string(),
+            model,
+            params,
+        }
+    }
+
+    /// Normalize a tool call ID to the 9 alphanumeric characters Mistral accepts
+    ///
+    /// IDs issued by Mistral pass through unchanged; IDs from other sources are
+    /// mapped deterministically so calls and results still match up.
+    fn tool_call_id(id: &str) -> String {
+        let alnum: String = id.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+        if alnum.len() >= TOOL_CALL_ID_LEN {
+            alnum[alnum.len() - TOOL_CALL_ID_LEN..].to_string()
+        } else {
+            format!("{:0>width$}", alnum, width = TOOL_CALL_ID_LEN)
+        }
+    }
+
+    /// Convert a ToolDefinition to a Mistral function tool
+    fn convert_tool(tool: &ToolDefinition) -> Value {
+        json!({
+            "type": "function",
+            "function": {
+                "name": tool.name,
+                "description": tool.description,
+                "parameters": tool.parameters,
+            }
+        })
+    }
+
+    /// Convert conversation messages to Mistral's message format
+    fn convert_messages(messages: &[ConversationMessage]) -> Vec<Value> {
+        // Tool results must name their function; recover it from the calls
+        let tool_names: HashMap<&str, &str> = messages
+            .iter()
+            .flat_map(|m| &m.tool_calls)
+            .map(|tc| (tc.id.as_str(), tc.name.as_str()))
+            .collect();
+
+        messages
+            .iter()
+            .map(|msg| match msg.role {
+                MessageRole::System => json!({"role": "system", "content": msg.content}),
+                MessageRole::User => json!({"role": "user", "content": msg.content}),
+                MessageRole::Assistant if msg.tool_calls.is_empty() => {
+                    json!({"role": "assistant", "content": msg.content})
+                }
+                MessageRole::Assistant => {
+                    let tool_calls: Vec<Value> = msg
+                        .tool_calls
+                        .iter()
+                        .map(|tc| {
+                            json!({
+                                "id": Self::tool_call_id(&tc.id),
+                                "type": "function",
+                                "function": {
+                                    "name": tc.name,
+                                    "arguments": tc.arguments.to_string(),
+                                }
+                            })
+                        })
+                        .collect();
+                    json!({"role": "assistant", "content": msg.content, "tool_calls": tool_calls})
+                }
+                MessageRole::Tool => {
+                    let id = msg.tool_call_id.as_deref().unwrap_or_default();
+                    json!({
+                        "role": "tool",
+                        "name": tool_names.get(id).copied().unwrap_or_default(),
+                        "tool_call_id": Self::tool_call_id(id),
+                        "content": msg.content,
+                    })
+                }
+            })
+            .collect()
+    }
+
+    /// Build a chat completions request body
+    fn build_request(
+        &self,
+        messages: &[ConversationMessage],
+        tools: &[ToolDefinition],
+        stream: bool,
+    ) -> Value {
+        let mut body = json!({
+            "model": self.model,
+            "messages": Self::convert_messages(messages),
+            "stream": stream,
+        });
+
+        if let Some(temperature) = self.params.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = self.params.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+        if let Some(top_p) = self.params.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if let Some(frequency_penalty) = self.params.frequency_penalty {
+            body["frequency_penalty"] = json!(frequency_penalty);
+        }
+        if let Some(presence_penalty) = self.params.presence_penalty {
+            body["presence_penalty"] = json!(presence_penalty);
+        }
+        if !tools.is_empty() {
+            body["tools"] = Value::Array(tools.iter().map(Self::convert_tool).collect());
+            body["tool_choice"] = json!("auto");
+        }
+
+        body
+    }
+
+    /// Send a request, returning the HTTP response on success
+    async fn send(&self, body: &Value) -> Result<reqwest::Response> {
+        let response = self
+            .http
+            .post(format!("{}/chat/completions", self.api_base))
+            .bearer_auth(&self.api_key)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| AppError::LLM(format!("Mistral API error: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(AppError::LLM(format!(
+                "Mistral API error ({}): {}",
+                status, text
+            )));
+        }
+        Ok(response)
+    }
+
+    /// Run a non-streaming chat completion
+    async fn complete(
+        &self,
+        messages: &[ConversationMessage],
+        tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        let body = self.build_request(messages, tools, false);
+        let response: Value = self
+            .send(&body)
+            .await?
+            .json()
+            .await
+            .map_err(|e| AppError::LLM(format!("Invalid Mistral response: {}", e)))?;
+
+        Self::parse_response(&response)
+    }
+
+    /// Parse a chat completions response
+    fn parse_response(response: &Value) -> Result<LLMResponse> {
+        let choice = response["choices"]
+            .get(0)
+            .ok_or_else(|| AppError::LLM("Mistral response contained no choices".into()))?;
+        let message = &choice["message"];
+
+        let tool_calls = message["tool_calls"]
+            .as_array()
+            .map(|calls| {
+                calls
+                    .iter()
+                    .map(|tc| ToolCall {
+                        id: tc["id"].as_str().unwrap_or_default().to_string(),
+                        name: tc["function"]["name"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        arguments: Self::parse_arguments(&tc["function"]["arguments"]),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let usage = response["usage"].as_object().map(|u| {
+            TokenUsage::new(
+                u.get("prompt_tokens").and_then(Value::as_u64).unwrap_or(0) as u32,
+                u.get("completion_tokens")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(0) as u32,
+            )
+        });
+
+        Ok(LLMResponse {
+            content: message["content"].as_str().unwrap_or_default().to_string(),
+            tool_calls,
+            finish_reason: choice["finish_reason"]
+                .as_str()
+                .unwrap_or("stop")
+                .to_string(),
+            usage,
+        })
+    }
+
+    /// Function arguments arrive either as a JSON string or as an object
+    fn parse_arguments(arguments: &Value) -> Value {
+        match arguments {
+            Value::String(s) => serde_json::from_str(s).unwrap_or_else(|_| json!({})),
+            Value::Null => json!({}),
+            other => other.clone(),
+        }
+    }
+
+    /// Run a streaming chat completion, yielding content deltas
+    async fn complete_stream(
+        &self,
+        messages: &[ConversationMessage],
+    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+        let body = self.build_request(messages, &[], true);
+        let response = self.send(&body).await?;
+
+        let result_stream = async_stream::stream! {
+            let mut bytes = response.bytes_stream();
+            let mut buffer = String::new();
+            while let Some(chunk) = bytes.next().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        yield Err(AppError::LLM(format!("Stream error: {}", e)));
+                        return;
+                    }
+                };
+                buffer.push_str(&String::from_utf8_lossy(&chunk));
+
+                while let Some(pos) = buffer.find('\n') {
+                    let line: String = buffer.drain(..=pos).collect();
+                    let Some(data) = line.trim().strip_prefix("data:") else {
+                        continue;
+                    };
+                    let data = data.trim();
+                    if data == "[DONE]" {
+                        return;
+                    }
+                    match serde_json::from_str::<Value>(data) {
+                        Ok(event) => {
+                            if let Some(text) = event["choices"][0]["delta"]["content"].as_str() {
+                                if !text.is_empty() {
+                                    yield Ok(text.to_string());
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            yield Err(AppError::LLM(format!("Invalid Mistral stream event: {}", e)));
+                            return;
+                        }
+                    }
+                }
+            }
+        };
+
+        Ok(Box::new(Box::pin(result_stream)))
+    }
+
+    fn history(messages: &[(String, String)]) -> Vec<ConversationMessage> {
+        messages
+            .iter()
+            .map(|(role, content)| ConversationMessage::from_role_content(role, content.as_str()))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl LLMClient for MistralClient {
+    async fn generate(&self, prompt: &str) -> Result<String> {
+        let messages = [ConversationMessage::user(prompt)];
+        Ok(self.complete(&messages, &[]).await?.content)
+    }
+
+    async fn generate_with_system(&self, system: &str, prompt: &str) -> Result<String> {
+        let messages = [
+            ConversationMessage::system(system),
+            ConversationMessage::user(prompt),
+        ];
+        Ok(self.complete(&messages, &[]).await?.content)
+    }
+
+    async fn generate_with_history(&self, messages: &[(String, String)]) -> Result<String> {
+        Ok(self.complete(&Self::history(messages), &[]).await?.content)
+    }
+
+    async fn generate_with_tools(
+        &self,
+        prompt: &str,
+        tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        self.complete(&[ConversationMessage::user(prompt)], tools)
+            .await
+    }
+
+    async fn generate_with_tools_and_history(
+        &self,
+        messages: &[ConversationMessage],
+        tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        self.complete(messages, tools).await
+    }
+
+    async fn stream(
+        &self,
+        prompt: &str,
+    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+        self.complete_stream(&[ConversationMessage::user(prompt)])
+            .await
+    }
+
+    async fn stream_with_system(
+        &self,
+        system: &str,
+        prompt: &str,
+    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+        let messages = [
+            ConversationMessage::system(system),
+            ConversationMessage::user(prompt),
+        ];
+        self.complete_stream(&messages).await
+    }
+
+    async fn stream_with_history(
+        &self,
+        messages: &[(String, String)],
+    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+        self.complete_stream(&Self::history(messages)).await
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_tool_call_id_normalization() {
+        assert_eq!(MistralClient::tool_call_id("D681PevKs"), "D681PevKs");
+        assert_eq!(
+            MistralClient::tool_call_id("call_abc-123456789"),
+            "123456789"
+        );
+        assert_eq!(MistralClient::tool_call_id("t1"), "0000000t1");
+    }
+
+    #[test]
+    fn test_tool_results_carry_function_name() {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "calculator".to_string(),
+            arguments: json!({"a": 1}),
+        };
+        let messages = vec![
+            ConversationMessage::user("1+1?"),
+            ConversationMessage::assistant("", vec![call]),
+            ConversationMessage::tool_result("call_1", &json!({"result": 2})),
+        ];
+
+        let converted = MistralClient::convert_messages(&messages);
+        assert_eq!(converted[1]["tool_calls"][0]["id"], "0000call1");
+        assert_eq!(
+            converted[1]["tool_calls"][0]["function"]["arguments"],
+            r#"{"a":1}"#
+        );
+        assert_eq!(converted[2]["name"], "calculator");
+        assert_eq!(converted[2]["tool_call_id"], "0000call1");
+    }
+
+    #[test]
+    fn test_object_arguments_are_accepted() {
+        assert_eq!(
+            MistralClient::parse_arguments(&json!(r#"{"q":"x"}"#)),
+            json!({"q": "x"})
+        );
+        assert_eq!(
+            MistralClient::parse_arguments(&json!({"q": "x"})),
+            json!({"q": "x"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_with_tools() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(header("authorization", "Bearer key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{
+                    "message": {
+                        "role": "assistant",
+                        "content": "",
+                        "tool_calls": [{
+                            "id": "D681PevKs",
+                            "function": {"name": "calculator", "arguments": {"a": 1}}
+                        }]
+                    },
+                    "finish_reason": "tool_calls"
+                }],
+                "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+            })))
+            .mount(&server)
+            .await;
+
+        let client = MistralClient::with_params(
+            "key".to_string(),
+            format!("{}/v1", server.uri()),
+            "mistral-small-latest".to_string(),
+            ModelParams::default(),
+        );
+        let response = client.generate_with_tools("1+1?", &[]).await.unwrap();
+
+        assert_eq!(response.finish_reason, "tool_calls");
+        assert_eq!(response.tool_calls[0].id, "D681PevKs");
+        assert_eq!(response.tool_calls[0].arguments, json!({"a": 1}));
+        assert_eq!(response.usage, Some(TokenUsage::new(10, 5)));
+    }
+}
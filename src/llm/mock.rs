@@ -0,0 +1,175 @@
+//! Mock LLM client
+//!
+//! Answers every call with a canned reply after a fixed delay, without
+//! calling a model. Configure it as a `type = "mock"` provider to load-test
+//! a server (see `ares-server loadtest`) or run it without a model:
+//!
+//! ```toml
+//! [providers.mock]
+//! type = "mock"
+//! latency_ms = 200
+//! ```
+//!
+//! Replies echo the last user message; the mock never requests tool calls.
+
+use crate::llm::client::{LLMClient, LLMResponse, TokenUsage};
+use crate::llm::coordinator::{ConversationMessage, MessageRole};
+use crate::types::{Result, ToolDefinition};
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// Client answering without a model
+pub struct MockClient {
+    model: String,
+    latency: Duration,
+}
+
+impl MockClient {
+    /// Create a mock client replying after `latency_ms` milliseconds
+    pub fn new(model: String, latency_ms: u64) -> Self {
+        Self {
+            model,
+            latency: Duration::from_millis(latency_ms),
+        }
+    }
+
+    /// The reply to a prompt
+    fn reply(prompt: &str) -> String {
+        let prompt: String = prompt.chars().take(200).collect();
+        format!("Mock reply to: {}", prompt.trim())
+    }
+
+    async fn answer(&self, prompt: &str) -> String {
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+        Self::reply(prompt)
+    }
+
+    /// The last user message of a (role, content) history
+    fn last_user(messages: &[(String, String)]) -> &str {
+        messages
+            .iter()
+            .rev()
+            .find(|(role, _)| role == "user")
+            .map(|(_, content)| content.as_str())
+            .unwrap_or_default()
+    }
+
+    fn stream_of(reply: String) -> Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin> {
+        let chunks: Vec<Result<String>> = reply
+            .split_inclusive(' ')
+            .map(|chunk| Ok(chunk.to_string()))
+            .collect();
+        Box::new(futures::stream::iter(chunks))
+    }
+}
+
+#[async_trait]
+impl LLMClient for MockClient {
+    async fn generate(&self, prompt: &str) -> Result<String> {
+        Ok(self.answer(prompt).await)
+    }
+
+    async fn generate_with_system(&self, _system: &str, prompt: &str) -> Result<String> {
+        Ok(self.answer(prompt).await)
+    }
+
+    async fn generate_with_history(&self, messages: &[(String, String)]) -> Result<String> {
+        let prompt = Self::last_user(messages);
+        Ok(self.answer(prompt).await)
+    }
+
+    async fn generate_with_tools(
+        &self,
+        prompt: &str,
+        _tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        let content = self.answer(prompt).await;
+        Ok(response(prompt, content))
+    }
+
+    async fn generate_with_tools_and_history(
+        &self,
+        messages: &[ConversationMessage],
+        _tools: &[ToolDefinition],
+    ) -> Result<LLMResponse> {
+        let prompt = messages
+            .iter()
+            .rev()
+            .find(|m| m.role == MessageRole::User)
+            .map(|m| m.content.as_str())
+            .unwrap_or_default();
+        let content = self.answer(prompt).await;
+        Ok(response(prompt, content))
+    }
+
+    async fn stream(
+        &self,
+        prompt: &str,
+    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+        Ok(Self::stream_of(self.answer(prompt).await))
+    }
+
+    async fn stream_with_system(
+        &self,
+        _system: &str,
+        prompt: &str,
+    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+        Ok(Self::stream_of(self.answer(prompt).await))
+    }
+
+    async fn stream_with_history(
+        &self,
+        messages: &[(String, String)],
+    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Send + Unpin>> {
+        let prompt = Self::last_user(messages);
+        Ok(Self::stream_of(self.answer(prompt).await))
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+}
+
+/// A finished reply with usage estimated from word counts
+fn response(prompt: &str, content: String) -> LLMResponse {
+    let prompt_tokens = prompt.split_whitespace().count() as u32;
+    let completion_tokens = content.split_whitespace().count() as u32;
+    LLMResponse {
+        content,
+        tool_calls: vec![],
+        finish_reason: "stop".to_string(),
+        usage: Some(TokenUsage::new(prompt_tokens, completion_tokens)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_mock_replies_to_the_last_user_message() {
+        let client = MockClient::new("mock".to_string(), 0);
+        let messages = [
+            ConversationMessage::system("Be brief"),
+            ConversationMessage::user("What is RAG?"),
+        ];
+        let response = client
+            .generate_with_tools_and_history(&messages, &[])
+            .await
+            .unwrap();
+        assert_eq!(response.content, "Mock reply to: What is RAG?");
+        assert!(response.tool_calls.is_empty());
+
+        let streamed: Vec<String> = client
+            .stream("hello there")
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(streamed.concat(), "Mock reply to: hello there");
+    }
+}
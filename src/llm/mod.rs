@@ -11,8 +11,13 @@
 //! - [`LLMClientFactory`] - Factory trait for creating provider clients
 //! - [`ProviderRegistry`] - Registry for managing multiple providers
 //! - [`ConfigBasedLLMFactory`] - Creates clients based on `ares.toml` configuration
+//! - [`CanaryRouter`](crate::llm::canary::CanaryRouter) - Splits traffic between a model and its canary
+//! - [`ContinuationClient`](crate::llm::continuation::ContinuationClient) - Continues replies cut off at the token limit
 //! - [`ToolCoordinator`](crate::llm::coordinator::ToolCoordinator) - Generic multi-turn tool calling coordinator
 //! - [`ClientPool`](crate::llm::pool::ClientPool) - Connection pooling for efficient client reuse (DIR-44)
+//! - [`GuardrailPipeline`] - PII redaction, prompt-injection and topic guardrails around generations
+//! - [`Judge`] - Calibrated 0-10 scoring of outputs against a rubric, with cached judgments
+//! - [`LLMMiddleware`] - Interceptors run around every call (logging, redaction, caching)
 //!
 //! # Supported Providers
 //!
@@ -21,6 +26,8 @@
 //! - `anthropic` - Anthropic API (Claude 3, Claude 3.5, etc.)
 //! - `ollama` - Local Ollama server
 //! - `llamacpp` - llama.cpp server
+//! - `mistral` - Mistral AI La Plateforme (native function calling)
+//! - `cohere` - Cohere chat API (tools and connectors)
 //!
 //! # Example
 //!
@@ -65,16 +72,36 @@
 //! All providers support streaming responses via the `generate_stream` method,
 //! which returns a `Pin<Box<dyn Stream<Item = Result<String>>>>`.
 
+/// JSONL log of LLM calls, replayed by load tests.
+pub mod call_log;
+/// Canary routing of a share of a model's calls to another model.
+pub mod canary;
+/// Cancellation of in-flight generations.
+pub mod cancellation;
 /// Model capabilities and requirement matching (DIR-43).
 pub mod capabilities;
 /// Core LLM client trait and streaming response types.
 pub mod client;
+/// Continuation of replies cut off at the token limit.
+pub mod continuation;
 /// Generic tool coordinator for multi-turn tool calling.
 pub mod coordinator;
+/// GGUF model downloads from Hugging Face for the llama.cpp provider.
+pub mod gguf;
+/// Guardrail pre/post processors applied around agent generations.
+pub mod guardrails;
+/// Judge-model scoring of outputs against a rubric.
+pub mod judge;
+/// Middleware intercepting LLM requests and responses.
+pub mod middleware;
+/// Mock client answering without a model, for load tests.
+pub mod mock;
 /// Connection pooling for LLM clients (DIR-44).
 pub mod pool;
 /// Registry for managing multiple LLM provider instances.
 pub mod provider_registry;
+/// Pulling and loading of local models at startup.
+pub mod warmup;
 
 #[cfg(feature = "llamacpp")]
 pub mod llamacpp;
@@ -88,13 +115,23 @@ pub mod openai;
 #[cfg(feature = "anthropic")]
 pub mod anthropic;
 
+#[cfg(feature = "mistral")]
+pub mod mistral;
+
+#[cfg(feature = "cohere")]
+pub mod cohere;
+
+pub use cancellation::{run_cancellable, ActiveGenerations, CancellationToken};
 pub use capabilities::{
     CapabilityRequirements, CapabilityRequirementsBuilder, ModelCapabilities, ModelWithCapabilities,
 };
-pub use client::{LLMClient, LLMClientFactory, LLMResponse, Provider};
+pub use client::{LLMClient, LLMClientFactory, LLMResponse, Provider, Sampling};
 pub use coordinator::{
     ConversationMessage, CoordinatorResult, FinishReason, MessageRole, ToolCallRecord,
     ToolCallingConfig, ToolCoordinator,
 };
+pub use guardrails::{Guardrail, GuardrailPipeline, GuardrailStage, GuardrailVerdict};
+pub use judge::{Judge, Judgment, Rubric};
+pub use middleware::{LLMMiddleware, LLMRequest, MiddlewareClient};
 pub use pool::{ClientPool, ClientPoolBuilder, PoolConfig, PoolStats, PooledClientGuard};
 pub use provider_registry::{ConfigBasedLLMFactory, ProviderRegistry};
@@ -28,6 +28,7 @@ use async_trait::async_trait;
 use futures::{Stream, StreamExt};
 use ollama_rs::{
     generation::chat::{request::ChatMessageRequest, ChatMessage},
+    generation::parameters::{FormatType, JsonStructure},
     generation::tools::{ToolCall as OllamaToolCall, ToolFunctionInfo, ToolInfo, ToolType},
     models::ModelOptions,
     Ollama,
@@ -124,6 +125,10 @@ impl OllamaClient {
         if let Some(pres_penalty) = self.params.presence_penalty {
             options = options.repeat_penalty(pres_penalty);
         }
+        // Ollama takes a 32-bit signed seed; reinterpret the bits so every seed stays distinct
+        if let Some(seed) = self.params.seed {
+            options = options.seed(seed as i32);
+        }
         options
     }
 
@@ -229,6 +234,38 @@ impl LLMClient for OllamaClient {
         Ok(response.message.content)
     }
 
+    async fn generate_structured(
+        &self,
+        messages: &[(String, String)],
+        schema: &serde_json::Value,
+    ) -> Result<String> {
+        let chat_messages: Vec<ChatMessage> = messages
+            .iter()
+            .map(|(role, content)| match role.as_str() {
+                "system" => ChatMessage::system(content.clone()),
+                "assistant" => ChatMessage::assistant(content.clone()),
+                _ => ChatMessage::user(content.clone()),
+            })
+            .collect();
+
+        // Ollama constrains decoding to the schema (structured outputs)
+        let schema = Schema::try_from(schema.clone())
+            .map_err(|e| AppError::InvalidInput(format!("Invalid output schema: {}", e)))?;
+        let request = ChatMessageRequest::new(self.model.clone(), chat_messages)
+            .options(self.build_model_options())
+            .format(FormatType::StructuredJson(Box::new(
+                JsonStructure::new_for_schema(schema),
+            )));
+
+        let response = self
+            .client
+            .send_chat_messages(request)
+            .await
+            .map_err(|e| AppError::LLM(format!("Ollama error: {}", e)))?;
+
+        Ok(response.message.content)
+    }
+
     async fn generate_with_tools(
         &self,
         prompt: &str,
@@ -561,6 +598,26 @@ mod tests {
         assert_eq!(port, 8080);
     }
 
+    #[tokio::test]
+    async fn test_seed_in_model_options() {
+        let params = ModelParams {
+            seed: Some(42),
+            ..Default::default()
+        };
+        let client =
+            OllamaClient::with_params("localhost".to_string(), "llama3".to_string(), params)
+                .await
+                .unwrap();
+        let options = serde_json::to_value(client.build_model_options()).unwrap();
+        assert_eq!(options["seed"], 42);
+
+        let client = OllamaClient::new("localhost".to_string(), "llama3".to_string())
+            .await
+            .unwrap();
+        let options = serde_json::to_value(client.build_model_options()).unwrap();
+        assert!(options.get("seed").is_none());
+    }
+
     #[test]
     fn test_tool_definition_conversion() {
         let tool = ToolDefinition {
@@ -206,6 +206,9 @@ impl LLMClient for OpenAIClient {
         if let Some(pres_penalty) = self.params.presence_penalty {
             builder.presence_penalty(pres_penalty);
         }
+        if let Some(seed) = self.params.seed {
+            builder.seed(i64::from(seed));
+        }
 
         let request = builder
             .build()
@@ -259,6 +262,9 @@ impl LLMClient for OpenAIClient {
         if let Some(pres_penalty) = self.params.presence_penalty {
             builder.presence_penalty(pres_penalty);
         }
+        if let Some(seed) = self.params.seed {
+            builder.seed(i64::from(seed));
+        }
 
         let request = builder
             .build()
@@ -339,6 +345,9 @@ impl LLMClient for OpenAIClient {
         if let Some(pres_penalty) = self.params.presence_penalty {
             builder.presence_penalty(pres_penalty);
         }
+        if let Some(seed) = self.params.seed {
+            builder.seed(i64::from(seed));
+        }
 
         let request = builder
             .build()
@@ -391,6 +400,9 @@ impl LLMClient for OpenAIClient {
         if let Some(pres_penalty) = self.params.presence_penalty {
             builder.presence_penalty(pres_penalty);
         }
+        if let Some(seed) = self.params.seed {
+            builder.seed(i64::from(seed));
+        }
 
         let request = builder
             .build()
@@ -475,6 +487,9 @@ impl LLMClient for OpenAIClient {
         if let Some(pres_penalty) = self.params.presence_penalty {
             builder.presence_penalty(pres_penalty);
         }
+        if let Some(seed) = self.params.seed {
+            builder.seed(i64::from(seed));
+        }
 
         let request = builder
             .build()
@@ -549,6 +564,9 @@ impl LLMClient for OpenAIClient {
         if let Some(pres_penalty) = self.params.presence_penalty {
             builder.presence_penalty(pres_penalty);
         }
+        if let Some(seed) = self.params.seed {
+            builder.seed(i64::from(seed));
+        }
 
         let request = builder
             .build()
@@ -619,6 +637,9 @@ impl LLMClient for OpenAIClient {
         if let Some(pres_penalty) = self.params.presence_penalty {
             builder.presence_penalty(pres_penalty);
         }
+        if let Some(seed) = self.params.seed {
+            builder.seed(i64::from(seed));
+        }
 
         let request = builder
             .build()
@@ -715,6 +736,9 @@ impl LLMClient for OpenAIClient {
         if let Some(pres_penalty) = self.params.presence_penalty {
             builder.presence_penalty(pres_penalty);
         }
+        if let Some(seed) = self.params.seed {
+            builder.seed(i64::from(seed));
+        }
 
         let request = builder
             .build()
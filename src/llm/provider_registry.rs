@@ -19,9 +19,19 @@
 //! let model = registry.find_model(&requirements)?;
 //! let client = registry.create_client_for_model(&model.name).await?;
 //! ```
+//!
+//! # Canary Routing
+//!
+//! Clients created for a model with a canary under `[canaries]` are served
+//! by the canary model for the configured share of them, and their calls
+//! are measured per arm. See [`crate::llm::canary`].
 
+use crate::llm::canary::{CanaryMetrics, CanaryReport, CanaryRouter};
 use crate::llm::capabilities::{CapabilityRequirements, ModelCapabilities, ModelWithCapabilities};
-use crate::llm::client::{LLMClient, Provider};
+use crate::llm::client::{LLMClient, ModelParams, Provider, Sampling};
+use crate::llm::continuation::ContinuationClient;
+use crate::llm::judge::Judge;
+use crate::llm::middleware::{LLMMiddleware, MiddlewareClient};
 use crate::types::{AppError, Result};
 use crate::utils::toml_config::{AresConfig, ModelConfig, ProviderConfig};
 use std::collections::HashMap;
@@ -31,6 +41,7 @@ use std::sync::Arc;
 ///
 /// The ProviderRegistry holds references to provider configurations and allows
 /// creating LLM clients for specific models or providers by name.
+#[derive(Clone)]
 pub struct ProviderRegistry {
     /// Provider configurations keyed by name
     providers: HashMap<String, ProviderConfig>,
@@ -38,6 +49,10 @@ pub struct ProviderRegistry {
     models: HashMap<String, ModelConfig>,
     /// Default model name to use when none specified
     default_model: Option<String>,
+    /// Middleware wrapped around every created client
+    middleware: Vec<Arc<dyn LLMMiddleware>>,
+    /// Traffic splitting of models with a canary
+    canary: Arc<CanaryRouter>,
 }
 
 impl ProviderRegistry {
@@ -47,6 +62,8 @@ impl ProviderRegistry {
             providers: HashMap::new(),
             models: HashMap::new(),
             default_model: None,
+            middleware: Vec::new(),
+            canary: Arc::new(CanaryRouter::default()),
         }
     }
 
@@ -56,6 +73,13 @@ impl ProviderRegistry {
             providers: config.providers.clone(),
             models: config.models.clone(),
             default_model: config.models.keys().next().cloned(),
+            middleware: Vec::new(),
+            canary: Arc::new(CanaryRouter::new(
+                config.canaries.clone(),
+                config.budgets.pricing.clone(),
+                config.judges.clone(),
+                CanaryMetrics::shared(),
+            )),
         }
     }
 
@@ -74,6 +98,21 @@ impl ProviderRegistry {
         self.models.insert(name.to_string(), config);
     }
 
+    /// Register middleware run around every call of the clients this registry creates
+    pub fn register_middleware(&mut self, middleware: Arc<dyn LLMMiddleware>) {
+        self.middleware.push(middleware);
+    }
+
+    /// Split the calls of models with a canary with `router`
+    pub fn set_canary_router(&mut self, router: CanaryRouter) {
+        self.canary = Arc::new(router);
+    }
+
+    /// Metrics of every canary, comparing each model with its canary
+    pub fn canary_reports(&self) -> Vec<CanaryReport> {
+        self.canary.reports()
+    }
+
     /// Get a provider configuration by name
     pub fn get_provider(&self, name: &str) -> Option<&ProviderConfig> {
         self.providers.get(name)
@@ -98,6 +137,63 @@ impl ProviderRegistry {
     ///
     /// This resolves the model -> provider chain and creates the appropriate client.
     pub async fn create_client_for_model(&self, model_name: &str) -> Result<Box<dyn LLMClient>> {
+        self.create_client_for_model_with_temperature(model_name, None)
+            .await
+    }
+
+    /// Create an LLM client for a model, optionally overriding its configured temperature
+    pub async fn create_client_for_model_with_temperature(
+        &self,
+        model_name: &str,
+        temperature: Option<f32>,
+    ) -> Result<Box<dyn LLMClient>> {
+        let sampling = Sampling {
+            temperature,
+            ..Sampling::default()
+        };
+        self.create_client_for_model_with_sampling(model_name, sampling)
+            .await
+    }
+
+    /// Create an LLM client for a model, optionally overriding its configured
+    /// temperature, token limit and top_p, and fixing the sampling seed for
+    /// reproducible generations
+    ///
+    /// For a model with a canary, the client is served by either the model or
+    /// its canary, and its calls are measured.
+    pub async fn create_client_for_model_with_sampling(
+        &self,
+        model_name: &str,
+        sampling: Sampling,
+    ) -> Result<Box<dyn LLMClient>> {
+        let Some((arm, served_by)) = self.canary.route(model_name) else {
+            let client = self.create_model_client(model_name, sampling).await?;
+            return Ok(MiddlewareClient::wrap(client, &self.middleware));
+        };
+        let client = self.create_model_client(served_by, sampling).await?;
+        let judge = self
+            .canary
+            .judge(model_name, |config| async move {
+                // Judges don't use a model with a canary, so this isn't routed
+                let llm = self
+                    .create_model_client(&config.model, Sampling::default())
+                    .await?;
+                Ok(Judge::with_config(
+                    MiddlewareClient::wrap(llm, &self.middleware),
+                    &config,
+                ))
+            })
+            .await;
+        let client = self.canary.wrap(model_name, arm, client, judge);
+        Ok(MiddlewareClient::wrap(client, &self.middleware))
+    }
+
+    /// Create the provider client of a model, without middleware
+    async fn create_model_client(
+        &self,
+        model_name: &str,
+        sampling: Sampling,
+    ) -> Result<Box<dyn LLMClient>> {
         let model_config = self.get_model(model_name).ok_or_else(|| {
             AppError::Configuration(format!("Model '{}' not found in configuration", model_name))
         })?;
@@ -109,8 +205,20 @@ impl ProviderRegistry {
             ))
         })?;
 
-        let provider = Provider::from_model_config(model_config, provider_config)?;
-        provider.create_client().await
+        let mut params = ModelParams::from_model_config(model_config);
+        sampling.apply(&mut params);
+        let provider =
+            Provider::from_config_with_params(provider_config, Some(&model_config.model), params)?;
+        let client = provider.create_client().await?;
+        // Ollama and llama.cpp don't report replies cut off at the token limit
+        let reports_truncation = !matches!(
+            provider_config,
+            ProviderConfig::Ollama { .. } | ProviderConfig::LlamaCpp { .. }
+        );
+        Ok(match reports_truncation {
+            true => ContinuationClient::wrap(client, model_config.max_continuations),
+            false => client,
+        })
     }
 
     /// Create an LLM client for a specific provider by name
@@ -128,7 +236,8 @@ impl ProviderRegistry {
         })?;
 
         let provider = Provider::from_config(provider_config, None)?;
-        provider.create_client().await
+        let client = provider.create_client().await?;
+        Ok(MiddlewareClient::wrap(client, &self.middleware))
     }
 
     /// Create an LLM client using the default model
@@ -170,14 +279,16 @@ impl ProviderRegistry {
                 caps.is_local = true;
                 caps.cost_tier = "free".to_string();
             }
-            ProviderConfig::LlamaCpp { .. } => {
+            ProviderConfig::LlamaCpp { .. } | ProviderConfig::Mock { .. } => {
                 caps.is_local = true;
                 caps.cost_tier = "free".to_string();
             }
             ProviderConfig::OpenAI { .. } => {
                 caps.is_local = false;
             }
-            ProviderConfig::Anthropic { .. } => {
+            ProviderConfig::Anthropic { .. }
+            | ProviderConfig::Mistral { .. }
+            | ProviderConfig::Cohere { .. } => {
                 caps.is_local = false;
             }
         }
@@ -340,6 +451,13 @@ impl ConfigBasedLLMFactory {
         &self.registry
     }
 
+    /// Register middleware run around every call of the clients this factory creates
+    ///
+    /// If the registry is shared, the factory continues with its own copy.
+    pub fn register_middleware(&mut self, middleware: Arc<dyn LLMMiddleware>) {
+        Arc::make_mut(&mut self.registry).register_middleware(middleware);
+    }
+
     /// Create an LLM client for a specific model
     pub async fn create_for_model(&self, model_name: &str) -> Result<Box<dyn LLMClient>> {
         self.registry.create_client_for_model(model_name).await
@@ -410,6 +528,9 @@ mod tests {
                 top_p: None,
                 frequency_penalty: None,
                 presence_penalty: None,
+                context_window: None,
+                summarize_overflow: true,
+                max_continuations: 2,
             },
         );
 
@@ -459,6 +580,9 @@ mod tests {
                 top_p: None,
                 frequency_penalty: None,
                 presence_penalty: None,
+                context_window: None,
+                summarize_overflow: true,
+                max_continuations: 2,
             },
         );
 
@@ -472,6 +596,9 @@ mod tests {
                 top_p: None,
                 frequency_penalty: None,
                 presence_penalty: None,
+                context_window: None,
+                summarize_overflow: true,
+                max_continuations: 2,
             },
         );
 
@@ -485,6 +612,9 @@ mod tests {
                 top_p: None,
                 frequency_penalty: None,
                 presence_penalty: None,
+                context_window: None,
+                summarize_overflow: true,
+                max_continuations: 2,
             },
         );
 
@@ -498,6 +628,9 @@ mod tests {
                 top_p: None,
                 frequency_penalty: None,
                 presence_penalty: None,
+                context_window: None,
+                summarize_overflow: true,
+                max_continuations: 2,
             },
         );
 
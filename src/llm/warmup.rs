@@ -0,0 +1,339 @@
+//! Model warm-up at startup.
+//!
+//! Local models are loaded on their first request, which can keep the first
+//! user waiting for a minute or more. With `[warmup] enabled = true`, the
+//! server prepares them right after it starts instead, one model at a time:
+//!
+//! 1. Ollama models missing from the Ollama server are pulled, and GGUF
+//!    models configured by reference are downloaded to the model store
+//!    (`[warmup] pull`, default: true).
+//! 2. Each model answers a short test generation, which loads it: into
+//!    Ollama's memory, or into this process for llama.cpp, where loaded
+//!    models are kept for later clients.
+//!
+//! Until warm-up is done, requests that start LLM work get a 503
+//! (`[warmup] gate`, default: true) and `GET /health/ready` reports the
+//! server as not ready, so a load balancer holds traffic back. A model that
+//! fails to warm up is logged and reported by `/health/detailed`, but
+//! doesn't keep the server from becoming ready.
+//!
+//! Ollama unloads models left idle for its keep-alive (`OLLAMA_KEEP_ALIVE`,
+//! 5 minutes by default); raise it to keep warmed-up models loaded.
+
+use crate::llm::provider_registry::ProviderRegistry;
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::{AresConfig, ProviderConfig};
+use crate::AppState;
+use chrono::Utc;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Prompt of the test generation loading each model
+const TEST_PROMPT: &str = "Reply with OK.";
+
+/// Where a model is in its warm-up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelState {
+    /// Waiting for the models before it
+    Pending,
+    /// Being downloaded
+    Pulling,
+    /// Answering its test generation
+    Loading,
+    /// Loaded and answering
+    Ready,
+    /// Couldn't be pulled or loaded
+    Failed,
+}
+
+/// Warm-up of one model.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelWarmup {
+    /// Model name from `[models]`
+    pub model: String,
+    /// Provider serving the model
+    pub provider: String,
+    /// Where the model is in its warm-up
+    pub state: ModelState,
+    /// Why the model failed to warm up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Time taken to pull and load the model, in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+/// Progress of the warm-up, as reported by the health endpoints.
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmupStatus {
+    /// Whether requests are served: warm-up is done, or never started
+    pub ready: bool,
+    /// When warm-up started (Unix timestamp)
+    pub started_at: Option<i64>,
+    /// When warm-up finished (Unix timestamp)
+    pub completed_at: Option<i64>,
+    /// The models warmed up, in order
+    pub models: Vec<ModelWarmup>,
+}
+
+/// Progress of the startup warm-up.
+///
+/// Cheap to clone; all clones share the same progress.
+#[derive(Clone, Default)]
+pub struct Warmup {
+    progress: Arc<RwLock<Progress>>,
+}
+
+#[derive(Default)]
+struct Progress {
+    started_at: Option<i64>,
+    completed_at: Option<i64>,
+    models: Vec<ModelWarmup>,
+}
+
+impl Warmup {
+    /// Create a warm-up that hasn't started, which counts as ready.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether warm-up is done, or never started.
+    pub fn is_ready(&self) -> bool {
+        let progress = self.progress.read();
+        progress.started_at.is_none() || progress.completed_at.is_some()
+    }
+
+    /// The current progress.
+    pub fn status(&self) -> WarmupStatus {
+        let progress = self.progress.read();
+        WarmupStatus {
+            ready: progress.started_at.is_none() || progress.completed_at.is_some(),
+            started_at: progress.started_at,
+            completed_at: progress.completed_at,
+            models: progress.models.clone(),
+        }
+    }
+
+    /// Mark warm-up started for `models`, given as (model, provider) pairs.
+    fn start(&self, models: Vec<(String, String)>) {
+        *self.progress.write() = Progress {
+            started_at: Some(Utc::now().timestamp()),
+            completed_at: None,
+            models: models
+                .into_iter()
+                .map(|(model, provider)| ModelWarmup {
+                    model,
+                    provider,
+                    state: ModelState::Pending,
+                    error: None,
+                    duration_ms: None,
+                })
+                .collect(),
+        };
+    }
+
+    fn update(
+        &self,
+        model: &str,
+        state: ModelState,
+        error: Option<String>,
+        took: Option<Duration>,
+    ) {
+        let mut progress = self.progress.write();
+        if let Some(entry) = progress.models.iter_mut().find(|m| m.model == model) {
+            entry.state = state;
+            entry.error = error;
+            entry.duration_ms = took.map(|took| took.as_millis() as u64);
+        }
+    }
+
+    fn finish(&self) {
+        self.progress.write().completed_at = Some(Utc::now().timestamp());
+    }
+
+    /// Pull and load each model of [`models_to_warm`], one at a time.
+    pub async fn run(&self, config: &AresConfig, registry: &ProviderRegistry) {
+        let timeout = Duration::from_secs(config.warmup.timeout_secs);
+        for model in models_to_warm(config) {
+            let started = Instant::now();
+            let warmed = tokio::time::timeout(
+                timeout,
+                self.warm_model(&model, registry, config.warmup.pull),
+            )
+            .await
+            .unwrap_or_else(|_| {
+                Err(AppError::LLM(format!(
+                    "Timed out after {} seconds",
+                    timeout.as_secs()
+                )))
+            });
+            let took = started.elapsed();
+            match warmed {
+                Ok(()) => {
+                    tracing::info!("Model '{}' warmed up in {:.1}s", model, took.as_secs_f64());
+                    self.update(&model, ModelState::Ready, None, Some(took));
+                }
+                Err(e) => {
+                    tracing::warn!("Model '{}' failed to warm up: {}", model, e);
+                    self.update(&model, ModelState::Failed, Some(e.to_string()), Some(took));
+                }
+            }
+        }
+        self.finish();
+    }
+
+    async fn warm_model(&self, model: &str, registry: &ProviderRegistry, pull: bool) -> Result<()> {
+        let model_config = registry.get_model(model).ok_or_else(|| {
+            AppError::Configuration(format!("Model '{}' not found in configuration", model))
+        })?;
+        let provider = registry
+            .get_provider(&model_config.provider)
+            .ok_or_else(|| {
+                AppError::Configuration(format!(
+                    "Provider '{}' referenced by model '{}' not found",
+                    model_config.provider, model
+                ))
+            })?;
+        if pull {
+            self.update(model, ModelState::Pulling, None, None);
+            pull_model(provider, &model_config.model).await?;
+        }
+        self.update(model, ModelState::Loading, None, None);
+        let client = registry.create_client_for_model(model).await?;
+        client.generate(TEST_PROMPT).await?;
+        Ok(())
+    }
+}
+
+/// Models warmed up: `[warmup] models`, or every model of an Ollama or
+/// llama.cpp provider, by name.
+pub fn models_to_warm(config: &AresConfig) -> Vec<String> {
+    if !config.warmup.models.is_empty() {
+        return config.warmup.models.clone();
+    }
+    let mut models: Vec<String> = config
+        .models
+        .iter()
+        .filter(|(_, model)| {
+            matches!(
+                config.providers.get(&model.provider),
+                Some(ProviderConfig::Ollama { .. } | ProviderConfig::LlamaCpp { .. })
+            )
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    models.sort();
+    models
+}
+
+/// Download `model` if its provider keeps models locally and lacks it.
+async fn pull_model(provider: &ProviderConfig, model: &str) -> Result<()> {
+    match provider {
+        #[cfg(feature = "ollama")]
+        ProviderConfig::Ollama { base_url, .. } => {
+            let client =
+                crate::llm::ollama::OllamaClient::new(base_url.clone(), model.to_string()).await?;
+            let installed = client.list_models().await?;
+            if !installed.iter().any(|name| same_ollama_model(name, model)) {
+                tracing::info!("Pulling Ollama model '{}'", model);
+                client.pull_model(model).await?;
+            }
+            Ok(())
+        }
+        ProviderConfig::LlamaCpp {
+            model: Some(reference),
+            ..
+        } => {
+            // Already downloaded models aren't fetched again
+            crate::llm::gguf::ModelStore::from_env()
+                .pull(&reference.parse()?)
+                .await?;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether an installed Ollama model is the configured one, which may leave
+/// out the `:latest` tag
+fn same_ollama_model(installed: &str, configured: &str) -> bool {
+    installed == configured
+        || (!configured.contains(':') && installed.strip_suffix(":latest") == Some(configured))
+}
+
+/// Warm up the configured models in the background when `[warmup]` is
+/// enabled.
+///
+/// The warm-up counts as started when this returns, so the readiness gate is
+/// closed before the server accepts requests.
+pub fn spawn(state: &AppState) {
+    let config = state.config_manager.config();
+    if !config.warmup.enabled {
+        return;
+    }
+    let models: Vec<(String, String)> = models_to_warm(&config)
+        .into_iter()
+        .map(|model| {
+            let provider = config
+                .models
+                .get(&model)
+                .map(|m| m.provider.clone())
+                .unwrap_or_default();
+            (model, provider)
+        })
+        .collect();
+    if models.is_empty() {
+        return;
+    }
+    tracing::info!("Warming up {} models", models.len());
+    state.warmup.start(models);
+
+    let warmup = state.warmup.clone();
+    let registry = Arc::clone(&state.provider_registry);
+    tokio::spawn(async move {
+        let started = Instant::now();
+        warmup.run(&config, &registry).await;
+        tracing::info!(
+            "Model warm-up done in {:.1}s",
+            started.elapsed().as_secs_f64()
+        );
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ollama_model_names() {
+        assert!(same_ollama_model("llama3.2:latest", "llama3.2"));
+        assert!(same_ollama_model("llama3.2:3b", "llama3.2:3b"));
+        assert!(!same_ollama_model("llama3.2:3b", "llama3.2"));
+        assert!(!same_ollama_model("llama3.2:latest", "llama3.2:3b"));
+    }
+
+    #[test]
+    fn test_ready_until_started_and_after_finishing() {
+        let warmup = Warmup::new();
+        assert!(warmup.is_ready());
+
+        warmup.start(vec![("local".to_string(), "ollama".to_string())]);
+        assert!(!warmup.is_ready());
+        assert_eq!(warmup.status().models[0].state, ModelState::Pending);
+
+        warmup.update(
+            "local",
+            ModelState::Failed,
+            Some("Connection refused".to_string()),
+            Some(Duration::from_millis(1500)),
+        );
+        warmup.finish();
+        let status = warmup.status();
+        assert!(status.ready);
+        assert_eq!(status.models[0].state, ModelState::Failed);
+        assert_eq!(status.models[0].duration_ms, Some(1500));
+    }
+}
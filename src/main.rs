@@ -19,7 +19,10 @@
 use ares::{
     api,
     auth::jwt::AuthService,
-    cli::{init, output::Output, AgentCommands, Cli, Commands},
+    cli::{
+        eval, init, loadtest, output::Output, wizard, AgentCommands, Cli, Commands, DbCommands,
+        EvalCommands, ModelCommands,
+    },
     db::PostgresClient,
     utils::toml_config::AresConfig,
     AgentRegistry, AppState, AresConfigManager, ConfigBasedLLMFactory, DynamicConfigManager,
@@ -28,8 +31,9 @@ use ares::{
 #[cfg(feature = "mcp")]
 use ares::mcp::McpRegistry;
 use axum::{routing::get, Router};
+use std::future::IntoFuture;
 use std::sync::Arc;
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
+use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 #[cfg(feature = "swagger-ui")]
 use utoipa::OpenApi;
@@ -58,8 +62,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             provider,
             host,
             port,
+            template,
+            model,
+            ollama_url,
+            no_web_search,
+            no_calculator,
+            no_rag,
+            yes,
         }) => {
-            let config = init::InitConfig {
+            let mut config = init::InitConfig {
                 path,
                 force,
                 minimal,
@@ -67,8 +78,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 provider,
                 host,
                 port,
+                template,
+                model,
+                ollama_url,
+                features: init::InitFeatures {
+                    web_search: !no_web_search,
+                    calculator: !no_calculator,
+                    rag: !no_rag,
+                },
             };
 
+            output.banner();
+
+            // Prompt for the remaining choices when attached to a terminal
+            if !yes && std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+                config = wizard::run(config, &output).await;
+            }
+
             match init::run(config, &output) {
                 init::InitResult::Success => std::process::exit(0),
                 init::InitResult::AlreadyExists => std::process::exit(1),
@@ -86,6 +112,68 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             return Ok(());
         }
 
+        Some(Commands::Models(model_cmd)) => {
+            handle_models_command(model_cmd, &output).await?;
+            return Ok(());
+        }
+
+        Some(Commands::Loadtest {
+            log,
+            target,
+            concurrency,
+            requests,
+            api_key,
+            token,
+            agent,
+            timeout,
+        }) => {
+            output.banner();
+            let config = loadtest::LoadTestConfig {
+                log,
+                target,
+                concurrency,
+                requests,
+                api_key,
+                token,
+                agent,
+                timeout: std::time::Duration::from_secs(timeout),
+            };
+            loadtest::run(config, &output).await?;
+            return Ok(());
+        }
+
+        Some(Commands::Eval(EvalCommands::Run {
+            suites,
+            target,
+            api_key,
+            token,
+            junit,
+            timeout,
+        })) => {
+            output.banner();
+            let config = eval::EvalConfig {
+                suites,
+                target,
+                api_key,
+                token,
+                junit,
+                timeout: std::time::Duration::from_secs(timeout),
+            };
+            eval::run(config, &output).await?;
+            return Ok(());
+        }
+
+        Some(Commands::Db(db_cmd)) => {
+            handle_db_command(&cli.config, db_cmd, &output).await?;
+            return Ok(());
+        }
+
+        #[cfg(feature = "ares-vector")]
+        Some(Commands::Vectors(vector_cmd)) => {
+            handle_vectors_command(&cli.config, vector_cmd, &output).await?;
+            return Ok(());
+        }
+
         None => {
             // No subcommand - run the server
             #[cfg(feature = "mcp")]
@@ -244,6 +332,358 @@ fn handle_agent_command(
     Ok(())
 }
 
+/// Handle the models subcommand
+async fn handle_models_command(
+    cmd: ModelCommands,
+    output: &Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use ares::llm::gguf::{ModelRef, ModelStore};
+    use std::io::Write;
+
+    // Load .env for HF_TOKEN / ARES_MODELS_DIR
+    dotenvy::dotenv().ok();
+    output.banner();
+
+    let store_for = |dir: Option<std::path::PathBuf>| match dir {
+        Some(dir) => ModelStore::from_env().with_root(dir),
+        None => ModelStore::from_env(),
+    };
+
+    match cmd {
+        ModelCommands::Pull { reference, dir } => {
+            let store = store_for(dir);
+            let model: ModelRef = reference.parse()?;
+            output.info(&format!(
+                "Pulling {} into {}",
+                model,
+                store.root().display()
+            ));
+
+            let mut last_percent = None;
+            let path = store
+                .pull_with_progress(&model, |downloaded, total| {
+                    let percent = (downloaded * 100).checked_div(total).unwrap_or(100);
+                    if last_percent != Some(percent) {
+                        last_percent = Some(percent);
+                        print!(
+                            "\r  {:>3}% ({} / {} MiB)",
+                            percent,
+                            downloaded / (1024 * 1024),
+                            total / (1024 * 1024)
+                        );
+                        let _ = std::io::stdout().flush();
+                    }
+                })
+                .await;
+            println!();
+
+            let path = path?;
+            output.success(&format!("Downloaded {}", path.display()));
+            if let Some(file) = path.file_name().and_then(|f| f.to_str()) {
+                output.hint(&format!(
+                    "Reference it from ares.toml with model = \"{}/{}\"",
+                    model.repo, file
+                ));
+            }
+        }
+
+        ModelCommands::List { dir } => {
+            let store = store_for(dir);
+            let models = store.list()?;
+
+            output.header(&format!("Models in {}", store.root().display()));
+            output.newline();
+            if models.is_empty() {
+                output.info("No models downloaded");
+                output.hint("Use 'ares-server models pull <owner/repo/file.gguf>' to download one");
+            } else {
+                output.table_header(&["Model", "Size (MiB)"]);
+                for model in &models {
+                    let size = format!("{:.1}", model.size as f64 / (1024.0 * 1024.0));
+                    output.table_row(&[&model.reference, &size]);
+                }
+            }
+        }
+
+        ModelCommands::Remove { reference, dir } => {
+            let store = store_for(dir);
+            let model: ModelRef = reference.parse()?;
+            store.remove(&model)?;
+            output.success(&format!("Removed {}", model));
+        }
+    }
+
+    Ok(())
+}
+
+/// Handle the vectors subcommand
+/// Handle the db subcommands
+async fn handle_db_command(
+    config_path: &std::path::Path,
+    cmd: DbCommands,
+    output: &Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use ares::db::migrations::{self, MigrationState};
+
+    dotenvy::dotenv().ok();
+    output.banner();
+
+    if let DbCommands::Restore {
+        path,
+        verify_only: true,
+        ..
+    } = &cmd
+    {
+        let manifest = ares::db::backup::verify(path).await?;
+        output.success(&format!(
+            "Backup is intact: {} tables, {} rows, {} vector files, migration {:03}",
+            manifest.tables.len(),
+            manifest.total_rows(),
+            manifest.vector_files.len(),
+            manifest.migration
+        ));
+        return Ok(());
+    }
+
+    let database_url = match &cmd {
+        DbCommands::Status { database_url }
+        | DbCommands::Migrate { database_url }
+        | DbCommands::Backup { database_url, .. }
+        | DbCommands::RotateKey { database_url }
+        | DbCommands::Restore { database_url, .. } => database_url.clone(),
+    };
+    let db = match database_url {
+        Some(url) => PostgresClient::new_remote(url, String::new()).await?,
+        None => {
+            // The server connects to DATABASE_URL, whatever [database] says;
+            // migrations only need the primary
+            let mut database = AresConfig::load_unchecked(config_path)
+                .map(|config| config.database)
+                .unwrap_or_default();
+            database.replica_url_env = None;
+            init_postgres_db(&database).await?
+        }
+    };
+
+    // Backups include the vector store from the config unless another
+    // directory is given
+    let vector_dir = |vector_path: Option<std::path::PathBuf>| {
+        vector_path.or_else(|| {
+            AresConfig::load_unchecked(config_path)
+                .ok()
+                .map(|config| config.rag.vector_path.into())
+        })
+    };
+
+    match cmd {
+        DbCommands::Backup {
+            path, vector_path, ..
+        } => {
+            output.header("Backing up");
+            let manifest =
+                ares::db::backup::backup(&db.pool, vector_dir(vector_path).as_deref(), &path)
+                    .await?;
+            output.kv("Archive", &path.display().to_string());
+            output.kv("Migration", &format!("{:03}", manifest.migration));
+            output.kv(
+                "Tables",
+                &format!(
+                    "{} ({} rows)",
+                    manifest.tables.len(),
+                    manifest.total_rows()
+                ),
+            );
+            output.kv("Vector files", &manifest.vector_files.len().to_string());
+            output.newline();
+            output.success("Backup written");
+            return Ok(());
+        }
+        DbCommands::Restore {
+            path,
+            vector_path,
+            force,
+            ..
+        } => {
+            output.header("Restoring");
+            let manifest = ares::db::backup::restore(
+                &db.pool,
+                vector_dir(vector_path).as_deref(),
+                &path,
+                force,
+            )
+            .await?;
+            output.kv(
+                "Backup taken",
+                &chrono::DateTime::from_timestamp(manifest.created_at, 0)
+                    .map(|at| at.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default(),
+            );
+            output.kv(
+                "Tables",
+                &format!(
+                    "{} ({} rows)",
+                    manifest.tables.len(),
+                    manifest.total_rows()
+                ),
+            );
+            output.kv("Vector files", &manifest.vector_files.len().to_string());
+            output.newline();
+            output.success("Backup restored");
+            return Ok(());
+        }
+        DbCommands::RotateKey { .. } => {
+            let config = AresConfig::load_unchecked(config_path)?;
+            let keys = ares::db::encryption::KeyRing::from_config(&config.encryption)?
+                .ok_or("No [encryption] key is configured")?;
+            output.header("Re-encrypting");
+            output.kv("Key", keys.key_id());
+            let report = ares::db::encryption::rotate(&db.pool, &keys, &config.files).await?;
+            for (column, count) in &report.columns {
+                output.kv(column, &count.to_string());
+            }
+            output.kv("Files", &report.files.to_string());
+            output.newline();
+            output.success("Everything is sealed with the current key");
+            output.hint("Previous keys can be removed from encryption.previous_keys_env");
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    if let DbCommands::Migrate { .. } = cmd {
+        output.header("Applying migrations");
+        let applied = migrations::migrate(&db.pool).await?;
+        if applied.is_empty() {
+            output.success("Database is up to date");
+        }
+        for version in applied {
+            output.success(&format!("Applied migration {:03}", version));
+        }
+        return Ok(());
+    }
+
+    output.header("Migrations");
+    output.table_header(&["Version", "Description", "State"]);
+    let mut problems = 0;
+    for migration in migrations::status(&db.pool).await? {
+        let state = match migration.state {
+            MigrationState::Applied { installed_on } => format!(
+                "applied {}",
+                chrono::DateTime::from_timestamp(installed_on, 0)
+                    .map(|at| at.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default()
+            ),
+            MigrationState::Pending => {
+                problems += 1;
+                "pending".to_string()
+            }
+            MigrationState::Modified { .. } => {
+                problems += 1;
+                "modified after it was applied".to_string()
+            }
+            MigrationState::Failed => {
+                problems += 1;
+                "failed".to_string()
+            }
+        };
+        output.table_row(&[
+            &format!("{:03}", migration.version),
+            &migration.description,
+            &state,
+        ]);
+    }
+
+    output.newline();
+    if problems == 0 {
+        output.success("All migrations are applied");
+        return Ok(());
+    }
+    output.hint("Run `ares-server db migrate` to apply pending migrations");
+    Err(format!("{} migrations are not applied as shipped", problems).into())
+}
+
+#[cfg(feature = "ares-vector")]
+async fn handle_vectors_command(
+    config_path: &std::path::Path,
+    cmd: ares::cli::VectorCommands,
+    output: &Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use ares_vector::{Config, VectorDb, VerifyReport};
+
+    output.banner();
+
+    let ares::cli::VectorCommands::Verify { path, repair } = cmd;
+    let path = match path {
+        Some(path) => path,
+        None if config_path.exists() => AresConfig::load_unchecked(config_path)?
+            .rag
+            .vector_path
+            .into(),
+        None => {
+            output.error(&format!(
+                "Configuration file '{}' not found!",
+                config_path.display()
+            ));
+            output.hint("Pass --path to verify a vector database directly");
+            return Err("Config not found".into());
+        }
+    };
+    if !path.exists() {
+        output.error(&format!("No vector database at {}", path.display()));
+        return Err("Vector database not found".into());
+    }
+
+    let print_report = |report: &VerifyReport| {
+        for issue in &report.issues {
+            output.warning(&issue.detail);
+        }
+        for collection in &report.collections {
+            output.subheader(&format!(
+                "{} ({} vectors)",
+                collection.name, collection.vector_count
+            ));
+            if collection.is_ok() {
+                output.success("OK");
+            }
+            for issue in &collection.issues {
+                output.warning(&format!("{:?}: {}", issue.kind, issue.detail));
+            }
+        }
+    };
+
+    output.header(&format!("Verifying {}", path.display()));
+    let db = VectorDb::open(Config::persistent(&path)).await?;
+    let mut report = db.verify().await?;
+    print_report(&report);
+
+    let to_rebuild: Vec<String> = report
+        .needs_rebuild()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    if repair && !to_rebuild.is_empty() {
+        output.newline();
+        for name in &to_rebuild {
+            db.rebuild(name).await?;
+            output.success(&format!("Rebuilt collection '{}'", name));
+        }
+        report = db.verify().await?;
+        output.header("After repair");
+        print_report(&report);
+    }
+
+    output.newline();
+    if report.is_ok() {
+        output.success("Vector database is consistent");
+        return Ok(());
+    }
+    output.error(&format!("{} problems found", report.issue_count()));
+    if !repair && !report.needs_rebuild().is_empty() {
+        output.hint("Run with --repair to rebuild the damaged collections");
+    }
+    Err("Vector database verification failed".into())
+}
+
 /// Initialize tracing with the given log filter.
 /// Falls back to `log_filter` if RUST_LOG is not set.
 fn init_tracing(log_filter: &str) {
@@ -308,10 +748,52 @@ async fn run_server(
         config_path_str
     );
 
+    // =================================================================
+    // Connect to Redis (shared with other instances)
+    // =================================================================
+    #[cfg(feature = "redis")]
+    let redis = match &config.redis {
+        Some(redis) => {
+            let store = ares::db::redis::RedisStore::connect(redis).await?;
+            tracing::info!("Connected to Redis (key prefix '{}')", redis.key_prefix);
+            Some((store, redis.clone()))
+        }
+        None => None,
+    };
+    #[cfg(not(feature = "redis"))]
+    if config.redis.is_some() {
+        tracing::warn!(
+            "[redis] is configured but this build lacks the `redis` feature; ignoring it"
+        );
+    }
+
     // =================================================================
     // Initialize Provider Registry
     // =================================================================
-    let provider_registry = Arc::new(ProviderRegistry::from_config(&config));
+    let call_log: Option<Arc<dyn ares::llm::LLMMiddleware>> = match &config.server.llm_call_log {
+        Some(path) => {
+            tracing::info!("Logging LLM calls to {}", path);
+            Some(Arc::new(ares::llm::call_log::CallLog::open(path).await?))
+        }
+        None => None,
+    };
+    // Answer repeated prompts from Redis ([redis] response_cache_ttl_secs)
+    #[cfg(feature = "redis")]
+    let response_cache: Option<Arc<dyn ares::llm::LLMMiddleware>> = match &redis {
+        Some((store, redis)) if redis.response_cache_ttl_secs > 0 => Some(Arc::new(
+            ares::db::redis::ResponseCache::new(store.clone(), redis.response_cache_ttl_secs),
+        )),
+        _ => None,
+    };
+    let mut provider_registry = ProviderRegistry::from_config(&config);
+    if let Some(call_log) = &call_log {
+        provider_registry.register_middleware(Arc::clone(call_log));
+    }
+    #[cfg(feature = "redis")]
+    if let Some(response_cache) = &response_cache {
+        provider_registry.register_middleware(Arc::clone(response_cache));
+    }
+    let provider_registry = Arc::new(provider_registry);
     tracing::info!(
         "Provider registry initialized with {} providers, {} models",
         config.providers.len(),
@@ -321,10 +803,16 @@ async fn run_server(
     // =================================================================
     // Initialize LLM Factory
     // =================================================================
-    let llm_factory = Arc::new(
-        ConfigBasedLLMFactory::from_config(&config)
-            .expect("Failed to create LLM factory from config"),
-    );
+    let mut llm_factory = ConfigBasedLLMFactory::from_config(&config)
+        .expect("Failed to create LLM factory from config");
+    if let Some(call_log) = call_log {
+        llm_factory.register_middleware(call_log);
+    }
+    #[cfg(feature = "redis")]
+    if let Some(response_cache) = response_cache {
+        llm_factory.register_middleware(response_cache);
+    }
+    let llm_factory = Arc::new(llm_factory);
     tracing::info!(
         "LLM factory initialized with default model: {}",
         llm_factory.default_model()
@@ -333,13 +821,22 @@ async fn run_server(
     // =================================================================
     // Initialize Database
     // =================================================================
-    let db = init_postgres_db(&config.database.url).await?;
+    let db = init_postgres_db(&config.database).await?;
     tracing::info!("PostgreSQL database client initialized");
 
+    // Seal sensitive columns and uploaded files at rest ([encryption])
+    if let Some(keys) = ares::db::encryption::KeyRing::from_config(&config.encryption)? {
+        tracing::info!(
+            "Encrypting sensitive data at rest with key {}",
+            keys.key_id()
+        );
+        ares::db::encryption::install(keys);
+    }
+
     // =================================================================
     // Run Database Migrations
     // =================================================================
-    sqlx::migrate!("./migrations")
+    ares::db::migrations::MIGRATOR
         .run(&db.pool)
         .await
         .expect("Failed to run database migrations");
@@ -363,6 +860,10 @@ async fn run_server(
         config.auth.jwt_refresh_expiry,
     );
     tracing::info!("Auth service initialized");
+    let service_tokens = Arc::new(
+        ares::auth::service_tokens::ServiceTokens::from_config(&config.auth.service_tokens)
+            .expect("Invalid [auth.service_tokens] configuration"),
+    );
 
     // =================================================================
     // Initialize Tool Registry
@@ -414,15 +915,44 @@ async fn run_server(
     // =================================================================
     // Initialize Agent Registry (with TOON support)
     // =================================================================
-    let agent_registry = AgentRegistry::with_dynamic_config(
+    let mut agent_registry = AgentRegistry::with_dynamic_config(
         &config,
         Arc::clone(&provider_registry),
         Arc::clone(&tool_registry),
         Arc::clone(&dynamic_config),
     );
+    // Rank agent memory by embedding similarity when a remote embedder is configured
+    if let Some(provider) = config
+        .rag
+        .embedding_provider
+        .as_deref()
+        .and_then(|name| config.get_provider(name))
+    {
+        match ares::rag::remote_embeddings::RemoteEmbedder::from_provider(
+            provider,
+            &config.rag.embedding_model,
+        ) {
+            Ok(embedder) => agent_registry.set_memory_embedder(Arc::new(embedder)),
+            Err(e) => tracing::warn!("Agent memory will be ranked by word overlap: {}", e),
+        }
+    }
+    // Agents with a `rag` section search the user's RAG collections
+    agent_registry.set_knowledge(Arc::new(ares::api::handlers::rag::CollectionKnowledge::new(
+        Arc::clone(&config_manager),
+    )));
+    // Agents answered by external plugin processes
+    for plugin in ares::agents::plugin::PluginAgent::discover(&config.config.plugins_dir) {
+        tracing::info!("Registered agent plugin: {}", plugin.name());
+        agent_registry.register_custom(Arc::new(plugin));
+    }
+    // Agents hosted by other systems, reached over A2A
+    for remote in ares::agents::remote::RemoteAgent::from_config(&config, &service_tokens) {
+        tracing::info!("Registered remote agent: {}", remote.name());
+        agent_registry.register_custom(Arc::new(remote));
+    }
     let agent_registry = Arc::new(agent_registry);
     tracing::info!(
-        "Agent registry initialized with {} agents (TOML + TOON)",
+        "Agent registry initialized with {} agents (TOML + TOON + plugins + remote)",
         agent_registry.agent_names().len()
     );
 
@@ -430,7 +960,7 @@ async fn run_server(
     // Initialize MCP Registry (Eruka, etc.)
     // =================================================================
     #[cfg(feature = "mcp")]
-    let mcp_registry: Option<Arc<McpRegistry>> = match McpRegistry::from_dir(config.config.mcps_dir.to_string_lossy().as_ref()) {
+    let mcp_registry: Option<Arc<McpRegistry>> = match McpRegistry::from_dir(config.config.mcps_dir.to_string_lossy().as_ref(), Arc::clone(&service_tokens)) {
         Ok(registry) => {
             tracing::info!("MCP registry initialized with {} clients", registry.client_names().len());
             Some(Arc::new(registry))
@@ -443,6 +973,29 @@ async fn run_server(
     // =================================================================
     // Create Application State
     // =================================================================
+    let sessions = ares::db::sessions::SessionStore::Postgres(db.pool.clone());
+    let rate_limits = ares::middleware::rate_limit::RateLimiter::default();
+    #[cfg(feature = "redis")]
+    let (sessions, rate_limits) = match &redis {
+        Some((store, redis)) => {
+            if redis.config_events {
+                store.spawn_config_sync(Arc::clone(&config_manager), db.pool.clone());
+            }
+            (
+                if redis.sessions {
+                    ares::db::sessions::SessionStore::Redis(Box::new(store.clone()))
+                } else {
+                    sessions
+                },
+                if redis.rate_limits {
+                    ares::middleware::rate_limit::RateLimiter::shared(store.clone())
+                } else {
+                    rate_limits
+                },
+            )
+        }
+        None => (sessions, rate_limits),
+    };
     let db_arc = Arc::new(db);
     let tenant_db = Arc::new(ares::TenantDb::new(db_arc.clone()));
     
@@ -459,17 +1012,55 @@ async fn run_server(
         #[cfg(feature = "mcp")]
         mcp_registry,
         deploy_registry: ares::api::handlers::deploy::new_deploy_registry(),
+        hooks: Arc::new(ares::ConversationHooks::new()),
+        loaders: Default::default(),
+        generations: Default::default(),
+        maintenance: Default::default(),
+        rate_limits,
+        warmup: Default::default(),
+        health: Default::default(),
+        service_tokens,
+        sessions,
     };
 
+    // Share scheduled runs, archival and purges with the other servers on
+    // this database: only the leader does them
+    ares::db::locks::spawn_leader_election(state.tenant_db.pool().clone());
+
+    // Move inactive conversations to cold storage when [archive] is enabled
+    ares::db::archive::spawn_archiver(state.tenant_db.pool().clone(), Arc::clone(&config_manager));
+
+    // Pull and load local models before the first request needs them
+    ares::llm::warmup::spawn(&state);
+
+    // Drop responses kept for idempotency keys once they expire
+    ares::db::idempotency::spawn_purger(state.tenant_db.pool().clone());
+
+    // Run agents on their cron schedules
+    ares::agents::scheduler::spawn_scheduler(state.clone());
+
+    // Run queued background jobs (scheduled runs, ingest jobs)
+    ares::api::jobs::spawn_workers(state.clone());
+
+    // Keep this server's runs alive and report runs cut off by a restart
+    ares::api::handlers::runs::spawn_interruption_check(state.clone());
+
+    // Drained and saved on shutdown
+    let shutdown_state = state.clone();
+
+    // Re-sync ingest jobs that have a sync interval
+    #[cfg(feature = "ares-vector")]
+    ares::api::handlers::rag::spawn_ingest_scheduler(state.clone());
+
+    // Report collections whose vectors no longer match their embedding model
+    #[cfg(feature = "ares-vector")]
+    ares::api::handlers::rag::spawn_embedding_check(Arc::clone(&config_manager));
+
     // =================================================================
     // Build OpenAPI Documentation (only when swagger-ui is enabled)
     // =================================================================
-    // Version with RAG endpoints (requires both local-embeddings and ares-vector)
-    #[cfg(all(
-        feature = "swagger-ui",
-        feature = "local-embeddings",
-        feature = "ares-vector"
-    ))]
+    // Version with RAG endpoints (requires ares-vector)
+    #[cfg(all(feature = "swagger-ui", feature = "ares-vector"))]
     #[derive(OpenApi)]
     #[openapi(
         paths(
@@ -481,30 +1072,144 @@ async fn run_server(
             // Chat endpoints
             ares::api::handlers::chat::chat,
             ares::api::handlers::chat::chat_stream,
+            ares::api::handlers::chat::stop_generation,
+            ares::api::handlers::chat::regenerate,
             ares::api::handlers::chat::get_user_memory,
+            ares::api::handlers::batches::create_batch,
+            ares::api::handlers::batches::get_batch,
+            ares::api::handlers::batches::cancel_batch,
+            ares::api::handlers::mfa::get_status,
+            ares::api::handlers::mfa::enroll,
+            ares::api::handlers::mfa::confirm,
+            ares::api::handlers::mfa::verify,
+            ares::api::handlers::mfa::disable,
+            ares::api::handlers::mfa::regenerate_recovery_codes,
+            ares::api::handlers::sessions::list_sessions,
+            ares::api::handlers::sessions::revoke_session,
+            ares::api::handlers::auth::jwks,
+            ares::api::handlers::agui::run,
+            ares::api::handlers::agui::run_named,
+            // Usage endpoints
+            ares::api::handlers::usage::get_usage,
             // Research endpoints
             ares::api::handlers::research::deep_research,
             // Conversation endpoints
             ares::api::handlers::conversations::list_conversations,
             ares::api::handlers::conversations::get_conversation,
+            ares::api::handlers::conversations::get_message_trace,
+            ares::api::handlers::conversations::submit_message_feedback,
+            ares::api::handlers::conversations::submit_feedback,
             ares::api::handlers::conversations::update_conversation,
+            ares::api::handlers::conversations::update_conversation_overrides,
             ares::api::handlers::conversations::delete_conversation,
+            ares::api::handlers::conversations::archive_conversation,
+            ares::api::handlers::conversations::attach_file,
+            ares::api::handlers::conversations::list_attachments,
+            ares::api::handlers::conversations::delete_attachment,
+            ares::api::handlers::conversations::get_rag_scope,
+            ares::api::handlers::conversations::update_rag_scope,
+            ares::api::handlers::conversations::delete_rag_scope,
+            ares::api::handlers::conversations::update_conversation_project,
+            ares::api::handlers::shares::create_share,
+            ares::api::handlers::shares::list_shares,
+            ares::api::handlers::shares::revoke_share,
+            ares::api::handlers::shares::view_shared,
+            // File endpoints
+            ares::api::handlers::files::upload_file,
+            ares::api::handlers::files::list_files,
+            ares::api::handlers::files::get_file,
+            ares::api::handlers::files::download_file,
+            ares::api::handlers::files::delete_file,
+            ares::api::handlers::files::update_file_project,
+            // Project endpoints
+            ares::api::handlers::projects::list_projects,
+            ares::api::handlers::projects::create_project,
+            ares::api::handlers::projects::get_project,
+            ares::api::handlers::projects::update_project,
+            ares::api::handlers::projects::delete_project,
+            // API key endpoints
+            ares::api::handlers::api_keys::list_api_keys,
+            ares::api::handlers::api_keys::create_api_key,
+            ares::api::handlers::api_keys::rotate_api_key,
+            ares::api::handlers::api_keys::revoke_api_key,
+            // Thread endpoints
+            ares::api::handlers::threads::create_thread,
+            ares::api::handlers::threads::create_thread_and_run,
+            ares::api::handlers::threads::get_thread,
+            ares::api::handlers::threads::delete_thread,
+            ares::api::handlers::threads::list_messages,
+            ares::api::handlers::threads::create_message,
+            ares::api::handlers::threads::list_runs,
+            ares::api::handlers::threads::create_run,
+            ares::api::handlers::threads::get_run,
+            ares::api::handlers::threads::cancel_run,
+            // Workspace endpoints
+            ares::api::handlers::workspaces::create_workspace,
+            ares::api::handlers::workspaces::list_workspaces,
+            ares::api::handlers::workspaces::get_workspace,
+            ares::api::handlers::workspaces::add_member,
+            ares::api::handlers::workspaces::remove_member,
+            // Preference endpoints
+            ares::api::handlers::preferences::get_preferences,
+            ares::api::handlers::preferences::update_preferences,
+            // User agent endpoints
+            ares::api::handlers::user_agents::list_agents,
+            ares::api::handlers::user_agents::create_agent,
+            ares::api::handlers::user_agents::get_agent,
+            ares::api::handlers::user_agents::get_parameter_limits,
+            ares::api::handlers::user_agents::update_agent,
+            ares::api::handlers::user_agents::delete_agent,
+            ares::api::handlers::user_agents::import_agent_toon,
+            ares::api::handlers::user_agents::export_agent_toon,
+            // Schedule endpoints
+            ares::api::handlers::schedules::list_schedules,
+            ares::api::handlers::schedules::create_schedule,
+            ares::api::handlers::schedules::get_schedule,
+            ares::api::handlers::schedules::update_schedule,
+            ares::api::handlers::schedules::delete_schedule,
+            ares::api::handlers::schedules::run_schedule,
+            ares::api::handlers::approvals::list_approvals,
+            ares::api::handlers::approvals::decide_approval,
+            ares::api::handlers::runs::list_interrupted_runs,
+            ares::api::handlers::runs::resume_interrupted_run,
+            ares::api::handlers::runs::dismiss_interrupted_run,
             // RAG endpoints
             ares::api::handlers::rag::ingest,
             ares::api::handlers::rag::search,
+            ares::api::handlers::rag::start_ingest_job,
+            ares::api::handlers::rag::list_ingest_jobs,
+            ares::api::handlers::rag::get_ingest_job,
+            ares::api::handlers::rag::resume_ingest_job,
+            ares::api::handlers::rag::cancel_ingest_job,
+            ares::api::handlers::rag::github_webhook,
             ares::api::handlers::rag::delete_collection,
             ares::api::handlers::rag::list_collections,
+            ares::api::handlers::rag::get_collection_settings,
+            ares::api::handlers::rag::update_collection_settings,
+            ares::api::handlers::rag::reembed_collection,
+            ares::api::handlers::rag::chunk_feedback,
+            ares::api::handlers::rag::feedback_report,
         ),
         components(schemas(
             ares::types::ChatRequest,
+            ares::types::GenerationParameters,
+            ares::utils::toml_config::ParameterLimitsConfig,
+            ares::utils::toml_config::ParameterRange,
+            ares::types::RegenerateRequest,
             ares::types::ChatResponse,
             ares::types::ResearchRequest,
             ares::types::ResearchResponse,
+            ares::llm::judge::Judgment,
+            ares::llm::judge::CriterionScore,
             ares::types::LoginRequest,
             ares::types::RegisterRequest,
             ares::types::TokenResponse,
             ares::types::AgentType,
             ares::types::Source,
+            ares::agents::ReactStep,
+            ares::agents::limits::LimitExceeded,
+            ares::agents::limits::RunLimit,
+            ares::agents::limits::RunUsage,
             ares::api::handlers::auth::RefreshTokenRequest,
             ares::api::handlers::auth::LogoutRequest,
             ares::api::handlers::auth::LogoutResponse,
@@ -512,12 +1217,103 @@ async fn run_server(
             ares::api::handlers::conversations::ConversationDetails,
             ares::api::handlers::conversations::ConversationMessage,
             ares::api::handlers::conversations::UpdateConversationRequest,
+            ares::api::handlers::conversations::MessageTrace,
+            ares::db::traits::ConversationSort,
+            ares::api::handlers::conversations::MessageFeedbackRequest,
+            ares::db::feedback::MessageFeedback,
+            ares::db::feedback::Rating,
+            ares::api::handlers::conversations::AttachmentRequest,
+            ares::db::attachments::Attachment,
+            ares::db::files::StoredFile,
+            ares::db::archive::ArchivedConversation,
+            ares::types::ToolCallTrace,
+            ares::types::ConversationOverrides,
+            ares::types::RagScope,
+            ares::db::workspaces::Workspace,
+            ares::db::workspaces::WorkspaceMember,
+            ares::db::workspaces::WorkspaceRole,
+            ares::api::handlers::workspaces::CreateWorkspaceRequest,
+            ares::api::handlers::workspaces::AddMemberRequest,
+            ares::api::handlers::workspaces::WorkspaceDetails,
+            ares::db::projects::Project,
+            ares::api::handlers::projects::ProjectRequest,
+            ares::api::handlers::conversations::ProjectAssignment,
+            ares::db::user_api_keys::UserApiKey,
+            ares::db::user_api_keys::ApiKeyScope,
+            ares::api::handlers::api_keys::CreateApiKeyRequest,
+            ares::api::handlers::api_keys::IssuedApiKey,
+            ares::api::handlers::batches::BatchChatRequest,
+            ares::api::handlers::batches::BatchItemRequest,
+            ares::api::handlers::batches::Batch,
+            ares::api::handlers::batches::BatchCounts,
+            ares::api::handlers::batches::BatchItemResult,
+            ares::api::handlers::auth::LoginResponse,
+            ares::api::handlers::mfa::MfaStep,
+            ares::api::handlers::mfa::MfaChallenge,
+            ares::api::handlers::mfa::MfaStatus,
+            ares::api::handlers::mfa::EnrollRequest,
+            ares::api::handlers::mfa::MfaEnrollment,
+            ares::api::handlers::mfa::ConfirmRequest,
+            ares::api::handlers::mfa::MfaConfirmation,
+            ares::api::handlers::mfa::VerifyRequest,
+            ares::api::handlers::mfa::CodeRequest,
+            ares::api::handlers::mfa::RecoveryCodes,
+            ares::db::sessions::Session,
+            ares::api::handlers::agui::RunAgentInput,
+            ares::api::handlers::agui::AgUiMessage,
+            ares::api::handlers::agui::ForwardedProps,
+            ares::api::handlers::shares::CreateShareRequest,
+            ares::api::handlers::shares::IssuedShare,
+            ares::api::handlers::shares::SharedConversation,
+            ares::api::handlers::shares::SharedMessage,
+            ares::db::shares::ConversationShare,
+            ares::api::handlers::threads::Thread,
+            ares::api::handlers::threads::ThreadDeleted,
+            ares::api::handlers::threads::CreateThreadRequest,
+            ares::api::handlers::threads::CreateMessageRequest,
+            ares::api::handlers::threads::MessageInput,
+            ares::api::handlers::threads::TextPart,
+            ares::api::handlers::threads::ThreadMessage,
+            ares::api::handlers::threads::MessageContent,
+            ares::api::handlers::threads::MessageText,
+            ares::api::handlers::threads::MessageList,
+            ares::api::handlers::threads::CreateRunRequest,
+            ares::api::handlers::threads::CreateThreadAndRunRequest,
+            ares::api::handlers::threads::Run,
+            ares::api::handlers::threads::RequiredAction,
+            ares::api::handlers::threads::RunError,
+            ares::api::handlers::threads::IncompleteDetails,
+            ares::api::handlers::threads::RunTokens,
+            ares::api::handlers::threads::RunList,
+            ares::types::ChatPreferences,
+            ares::types::ResponseLength,
+            ares::api::handlers::usage::UsageReport,
+            ares::api::handlers::usage::BudgetUsage,
+            ares::api::handlers::usage::AgentUsage,
+            ares::db::schedules::AgentSchedule,
+            ares::api::handlers::schedules::ScheduleRequest,
+            ares::api::handlers::schedules::ScheduleRunResponse,
+            ares::agents::approval::PendingApproval,
+            ares::agents::approval::PendingToolCall,
+            ares::api::handlers::approvals::ApprovalRequest,
+            ares::agents::checkpoint::InterruptedRun,
         )),
         tags(
             (name = "auth", description = "Authentication endpoints"),
             (name = "chat", description = "Chat endpoints"),
             (name = "research", description = "Research endpoints"),
             (name = "conversations", description = "Conversation management endpoints"),
+            (name = "files", description = "File upload endpoints"),
+            (name = "workspaces", description = "Workspace and membership endpoints"),
+            (name = "projects", description = "Project endpoints"),
+            (name = "threads", description = "Assistants-style thread and run endpoints"),
+            (name = "api-keys", description = "API keys for machine clients"),
+            (name = "preferences", description = "User chat preference endpoints"),
+            (name = "agents", description = "User-defined agent endpoints"),
+            (name = "usage", description = "Spend and budget usage endpoints"),
+            (name = "schedules", description = "Scheduled agent run endpoints"),
+            (name = "approvals", description = "Tool call approval endpoints"),
+            (name = "runs", description = "Interrupted run endpoints"),
             (name = "rag", description = "RAG (Retrieval Augmented Generation) endpoints"),
         ),
         info(
@@ -528,11 +1324,8 @@ async fn run_server(
     )]
     struct ApiDoc;
 
-    // Version without RAG endpoints (when local-embeddings is not available)
-    #[cfg(all(
-        feature = "swagger-ui",
-        not(all(feature = "local-embeddings", feature = "ares-vector"))
-    ))]
+    // Version without RAG endpoints (when ares-vector is not available)
+    #[cfg(all(feature = "swagger-ui", not(feature = "ares-vector")))]
     #[derive(OpenApi)]
     #[openapi(
         paths(
@@ -544,25 +1337,128 @@ async fn run_server(
             // Chat endpoints
             ares::api::handlers::chat::chat,
             ares::api::handlers::chat::chat_stream,
+            ares::api::handlers::chat::stop_generation,
+            ares::api::handlers::chat::regenerate,
             ares::api::handlers::chat::get_user_memory,
+            ares::api::handlers::batches::create_batch,
+            ares::api::handlers::batches::get_batch,
+            ares::api::handlers::batches::cancel_batch,
+            ares::api::handlers::mfa::get_status,
+            ares::api::handlers::mfa::enroll,
+            ares::api::handlers::mfa::confirm,
+            ares::api::handlers::mfa::verify,
+            ares::api::handlers::mfa::disable,
+            ares::api::handlers::mfa::regenerate_recovery_codes,
+            ares::api::handlers::sessions::list_sessions,
+            ares::api::handlers::sessions::revoke_session,
+            ares::api::handlers::auth::jwks,
+            ares::api::handlers::agui::run,
+            ares::api::handlers::agui::run_named,
+            // Usage endpoints
+            ares::api::handlers::usage::get_usage,
             // Research endpoints
             ares::api::handlers::research::deep_research,
             // Conversation endpoints
             ares::api::handlers::conversations::list_conversations,
             ares::api::handlers::conversations::get_conversation,
+            ares::api::handlers::conversations::get_message_trace,
+            ares::api::handlers::conversations::submit_message_feedback,
+            ares::api::handlers::conversations::submit_feedback,
             ares::api::handlers::conversations::update_conversation,
+            ares::api::handlers::conversations::update_conversation_overrides,
             ares::api::handlers::conversations::delete_conversation,
+            ares::api::handlers::conversations::archive_conversation,
+            ares::api::handlers::conversations::attach_file,
+            ares::api::handlers::conversations::list_attachments,
+            ares::api::handlers::conversations::delete_attachment,
+            ares::api::handlers::conversations::get_rag_scope,
+            ares::api::handlers::conversations::update_rag_scope,
+            ares::api::handlers::conversations::delete_rag_scope,
+            ares::api::handlers::conversations::update_conversation_project,
+            ares::api::handlers::shares::create_share,
+            ares::api::handlers::shares::list_shares,
+            ares::api::handlers::shares::revoke_share,
+            ares::api::handlers::shares::view_shared,
+            // File endpoints
+            ares::api::handlers::files::upload_file,
+            ares::api::handlers::files::list_files,
+            ares::api::handlers::files::get_file,
+            ares::api::handlers::files::download_file,
+            ares::api::handlers::files::delete_file,
+            ares::api::handlers::files::update_file_project,
+            // Project endpoints
+            ares::api::handlers::projects::list_projects,
+            ares::api::handlers::projects::create_project,
+            ares::api::handlers::projects::get_project,
+            ares::api::handlers::projects::update_project,
+            ares::api::handlers::projects::delete_project,
+            // API key endpoints
+            ares::api::handlers::api_keys::list_api_keys,
+            ares::api::handlers::api_keys::create_api_key,
+            ares::api::handlers::api_keys::rotate_api_key,
+            ares::api::handlers::api_keys::revoke_api_key,
+            // Thread endpoints
+            ares::api::handlers::threads::create_thread,
+            ares::api::handlers::threads::create_thread_and_run,
+            ares::api::handlers::threads::get_thread,
+            ares::api::handlers::threads::delete_thread,
+            ares::api::handlers::threads::list_messages,
+            ares::api::handlers::threads::create_message,
+            ares::api::handlers::threads::list_runs,
+            ares::api::handlers::threads::create_run,
+            ares::api::handlers::threads::get_run,
+            ares::api::handlers::threads::cancel_run,
+            // Workspace endpoints
+            ares::api::handlers::workspaces::create_workspace,
+            ares::api::handlers::workspaces::list_workspaces,
+            ares::api::handlers::workspaces::get_workspace,
+            ares::api::handlers::workspaces::add_member,
+            ares::api::handlers::workspaces::remove_member,
+            // Preference endpoints
+            ares::api::handlers::preferences::get_preferences,
+            ares::api::handlers::preferences::update_preferences,
+            // User agent endpoints
+            ares::api::handlers::user_agents::list_agents,
+            ares::api::handlers::user_agents::create_agent,
+            ares::api::handlers::user_agents::get_agent,
+            ares::api::handlers::user_agents::get_parameter_limits,
+            ares::api::handlers::user_agents::update_agent,
+            ares::api::handlers::user_agents::delete_agent,
+            ares::api::handlers::user_agents::import_agent_toon,
+            ares::api::handlers::user_agents::export_agent_toon,
+            // Schedule endpoints
+            ares::api::handlers::schedules::list_schedules,
+            ares::api::handlers::schedules::create_schedule,
+            ares::api::handlers::schedules::get_schedule,
+            ares::api::handlers::schedules::update_schedule,
+            ares::api::handlers::schedules::delete_schedule,
+            ares::api::handlers::schedules::run_schedule,
+            ares::api::handlers::approvals::list_approvals,
+            ares::api::handlers::approvals::decide_approval,
+            ares::api::handlers::runs::list_interrupted_runs,
+            ares::api::handlers::runs::resume_interrupted_run,
+            ares::api::handlers::runs::dismiss_interrupted_run,
         ),
         components(schemas(
             ares::types::ChatRequest,
+            ares::types::GenerationParameters,
+            ares::utils::toml_config::ParameterLimitsConfig,
+            ares::utils::toml_config::ParameterRange,
+            ares::types::RegenerateRequest,
             ares::types::ChatResponse,
             ares::types::ResearchRequest,
             ares::types::ResearchResponse,
+            ares::llm::judge::Judgment,
+            ares::llm::judge::CriterionScore,
             ares::types::LoginRequest,
             ares::types::RegisterRequest,
             ares::types::TokenResponse,
             ares::types::AgentType,
             ares::types::Source,
+            ares::agents::ReactStep,
+            ares::agents::limits::LimitExceeded,
+            ares::agents::limits::RunLimit,
+            ares::agents::limits::RunUsage,
             ares::api::handlers::auth::RefreshTokenRequest,
             ares::api::handlers::auth::LogoutRequest,
             ares::api::handlers::auth::LogoutResponse,
@@ -570,12 +1466,103 @@ async fn run_server(
             ares::api::handlers::conversations::ConversationDetails,
             ares::api::handlers::conversations::ConversationMessage,
             ares::api::handlers::conversations::UpdateConversationRequest,
+            ares::api::handlers::conversations::MessageTrace,
+            ares::db::traits::ConversationSort,
+            ares::api::handlers::conversations::MessageFeedbackRequest,
+            ares::db::feedback::MessageFeedback,
+            ares::db::feedback::Rating,
+            ares::api::handlers::conversations::AttachmentRequest,
+            ares::db::attachments::Attachment,
+            ares::db::files::StoredFile,
+            ares::db::archive::ArchivedConversation,
+            ares::types::ToolCallTrace,
+            ares::types::ConversationOverrides,
+            ares::types::RagScope,
+            ares::db::workspaces::Workspace,
+            ares::db::workspaces::WorkspaceMember,
+            ares::db::workspaces::WorkspaceRole,
+            ares::api::handlers::workspaces::CreateWorkspaceRequest,
+            ares::api::handlers::workspaces::AddMemberRequest,
+            ares::api::handlers::workspaces::WorkspaceDetails,
+            ares::db::projects::Project,
+            ares::api::handlers::projects::ProjectRequest,
+            ares::api::handlers::conversations::ProjectAssignment,
+            ares::db::user_api_keys::UserApiKey,
+            ares::db::user_api_keys::ApiKeyScope,
+            ares::api::handlers::api_keys::CreateApiKeyRequest,
+            ares::api::handlers::api_keys::IssuedApiKey,
+            ares::api::handlers::batches::BatchChatRequest,
+            ares::api::handlers::batches::BatchItemRequest,
+            ares::api::handlers::batches::Batch,
+            ares::api::handlers::batches::BatchCounts,
+            ares::api::handlers::batches::BatchItemResult,
+            ares::api::handlers::auth::LoginResponse,
+            ares::api::handlers::mfa::MfaStep,
+            ares::api::handlers::mfa::MfaChallenge,
+            ares::api::handlers::mfa::MfaStatus,
+            ares::api::handlers::mfa::EnrollRequest,
+            ares::api::handlers::mfa::MfaEnrollment,
+            ares::api::handlers::mfa::ConfirmRequest,
+            ares::api::handlers::mfa::MfaConfirmation,
+            ares::api::handlers::mfa::VerifyRequest,
+            ares::api::handlers::mfa::CodeRequest,
+            ares::api::handlers::mfa::RecoveryCodes,
+            ares::db::sessions::Session,
+            ares::api::handlers::agui::RunAgentInput,
+            ares::api::handlers::agui::AgUiMessage,
+            ares::api::handlers::agui::ForwardedProps,
+            ares::api::handlers::shares::CreateShareRequest,
+            ares::api::handlers::shares::IssuedShare,
+            ares::api::handlers::shares::SharedConversation,
+            ares::api::handlers::shares::SharedMessage,
+            ares::db::shares::ConversationShare,
+            ares::api::handlers::threads::Thread,
+            ares::api::handlers::threads::ThreadDeleted,
+            ares::api::handlers::threads::CreateThreadRequest,
+            ares::api::handlers::threads::CreateMessageRequest,
+            ares::api::handlers::threads::MessageInput,
+            ares::api::handlers::threads::TextPart,
+            ares::api::handlers::threads::ThreadMessage,
+            ares::api::handlers::threads::MessageContent,
+            ares::api::handlers::threads::MessageText,
+            ares::api::handlers::threads::MessageList,
+            ares::api::handlers::threads::CreateRunRequest,
+            ares::api::handlers::threads::CreateThreadAndRunRequest,
+            ares::api::handlers::threads::Run,
+            ares::api::handlers::threads::RequiredAction,
+            ares::api::handlers::threads::RunError,
+            ares::api::handlers::threads::IncompleteDetails,
+            ares::api::handlers::threads::RunTokens,
+            ares::api::handlers::threads::RunList,
+            ares::types::ChatPreferences,
+            ares::types::ResponseLength,
+            ares::api::handlers::usage::UsageReport,
+            ares::api::handlers::usage::BudgetUsage,
+            ares::api::handlers::usage::AgentUsage,
+            ares::db::schedules::AgentSchedule,
+            ares::api::handlers::schedules::ScheduleRequest,
+            ares::api::handlers::schedules::ScheduleRunResponse,
+            ares::agents::approval::PendingApproval,
+            ares::agents::approval::PendingToolCall,
+            ares::api::handlers::approvals::ApprovalRequest,
+            ares::agents::checkpoint::InterruptedRun,
         )),
         tags(
             (name = "auth", description = "Authentication endpoints"),
             (name = "chat", description = "Chat endpoints"),
             (name = "research", description = "Research endpoints"),
             (name = "conversations", description = "Conversation management endpoints"),
+            (name = "files", description = "File upload endpoints"),
+            (name = "workspaces", description = "Workspace and membership endpoints"),
+            (name = "projects", description = "Project endpoints"),
+            (name = "threads", description = "Assistants-style thread and run endpoints"),
+            (name = "api-keys", description = "API keys for machine clients"),
+            (name = "preferences", description = "User chat preference endpoints"),
+            (name = "agents", description = "User-defined agent endpoints"),
+            (name = "usage", description = "Spend and budget usage endpoints"),
+            (name = "schedules", description = "Scheduled agent run endpoints"),
+            (name = "approvals", description = "Tool call approval endpoints"),
+            (name = "runs", description = "Interrupted run endpoints"),
         ),
         info(
             title = "A.R.E.S - Agentic Retrieval Enhanced Server API",
@@ -590,12 +1577,18 @@ async fn run_server(
     // =================================================================
     #[allow(unused_mut)]
     let mut app = Router::new()
-        // Health check (simple - returns "OK")
-        .route("/health", get(health_check))
+        // Liveness: 200 while the process answers
+        .route("/health", get(ares::api::health::live))
+        .route("/health/live", get(ares::api::health::live))
+        // Readiness: 503 unless the database, an LLM provider and the vector
+        // store are up and the startup model warm-up is done
+        .route("/health/ready", get(ares::api::health::ready))
         // Detailed health check with component status
         .route("/health/detailed", get(health_check_detailed))
         // Configuration info endpoint
         .route("/config/info", get(config_info))
+        // Key verifying the service tokens sent to MCP servers and agents
+        .route("/.well-known/jwks.json", get(api::handlers::auth::jwks))
         // API routes
         .nest(
             "/api",
@@ -622,8 +1615,41 @@ async fn run_server(
     // =================================================================
     // Add Middleware
     // =================================================================
-    // Build CORS layer from configuration
-    let cors = build_cors_layer(&config.server.cors_origins);
+    // Build CORS layer from configuration ([server.security] strict_cors)
+    let security = &config.server.security;
+    let cors = ares::middleware::security::cors_layer(&config.server);
+
+    // Security headers on every response ([server.security] headers)
+    let security_headers = ares::middleware::security::SecurityHeaders::from_config(security);
+    let app = if security_headers.is_empty() {
+        app
+    } else {
+        app.layer(axum::middleware::from_fn_with_state(
+            Arc::new(security_headers),
+            ares::middleware::security::security_headers,
+        ))
+    };
+
+    // Replay responses to retried chat and research requests (Idempotency-Key)
+    let app = app.layer(axum::middleware::from_fn_with_state(
+        state.clone(),
+        ares::middleware::idempotency::idempotency,
+    ));
+
+    // Per-user limits on chat, research and ingest routes ([server.rate_limits])
+    let app = app.layer(axum::middleware::from_fn_with_state(
+        state.clone(),
+        ares::middleware::rate_limit::rate_limit,
+    ));
+
+    // Clients of trusted reverse proxies, for the rate limits ([server] trusted_proxies)
+    let trusted_proxies =
+        ares::middleware::client_ip::TrustedProxies::parse(&config.server.trusted_proxies)
+            .map_err(ares::types::AppError::Configuration)?;
+    let client_ip = axum::middleware::from_fn_with_state(
+        Arc::new(trusted_proxies),
+        ares::middleware::client_ip::client_ip,
+    );
 
     // Build rate limiting layer if enabled (per-IP rate limiting using tower_governor)
     let app = if config.server.rate_limit_per_second > 0 {
@@ -665,12 +1691,14 @@ async fn run_server(
         );
 
         app.layer(GovernorLayer::new(governor_conf))
+            .layer(client_ip)
             .layer(cors)
             .layer(TraceLayer::new_for_http())
             .with_state(state)
     } else {
         tracing::warn!("Rate limiting is disabled - not recommended for production");
-        app.layer(cors)
+        app.layer(client_ip)
+            .layer(cors)
             .layer(TraceLayer::new_for_http())
             .with_state(state)
     };
@@ -679,56 +1707,59 @@ async fn run_server(
     // Start Server
     // =================================================================
     let addr = format!("{}:{}", config.server.host, config.server.port);
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    let scheme = if security.tls.is_some() {
+        "https"
+    } else {
+        "http"
+    };
 
-    tracing::info!("Server running on http://{}", addr);
-    tracing::info!("Swagger UI available at http://{}/swagger-ui/", addr);
+    tracing::info!("Server running on {}://{}", scheme, addr);
+    tracing::info!("Swagger UI available at {}://{}/swagger-ui/", scheme, addr);
     #[cfg(feature = "ui")]
-    tracing::info!("Web UI available at http://{}/", addr);
-
-    // Use graceful shutdown with signal handling
-    let server = axum::serve(
-        listener,
-        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
-    )
-    .with_graceful_shutdown(shutdown_signal());
-
-    server.await?;
+    tracing::info!("Web UI available at {}://{}/", scheme, addr);
+
+    // On SIGTERM/Ctrl+C stop accepting connections, drain open requests and
+    // agent runs up to the shutdown timeout, then save state and exit
+    let shutdown = tokio_util::sync::CancellationToken::new();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            ares::api::shutdown::signal().await;
+            shutdown.cancel();
+        }
+    });
+    let timeout = std::time::Duration::from_secs(config.server.shutdown_timeout_secs);
+    let app = app.into_make_service_with_connect_info::<std::net::SocketAddr>();
+
+    if let Some(tls) = &security.tls {
+        use axum::serve::ListenerExt;
+
+        // Tapping the listener also gives handlers the client's ConnectInfo
+        let listener = ares::api::tls::TlsListener::bind(&addr, tls)
+            .await?
+            .tap_io(|stream| {
+                if let Err(e) = stream.get_ref().0.set_nodelay(true) {
+                    tracing::trace!("Failed to set TCP_NODELAY: {}", e);
+                }
+            });
+        let server = axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown.clone().cancelled_owned())
+            .into_future();
+        ares::api::shutdown::serve_until_shutdown(server, shutdown, &shutdown_state, timeout)
+            .await?;
+    } else {
+        let listener = tokio::net::TcpListener::bind(&addr).await?;
+        let server = axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown.clone().cancelled_owned())
+            .into_future();
+        ares::api::shutdown::serve_until_shutdown(server, shutdown, &shutdown_state, timeout)
+            .await?;
+    }
 
     tracing::info!("Server shut down gracefully");
     Ok(())
 }
 
-/// Signal handler for graceful shutdown.
-/// Listens for Ctrl+C (SIGINT) and SIGTERM on Unix systems.
-async fn shutdown_signal() {
-    let ctrl_c = async {
-        tokio::signal::ctrl_c()
-            .await
-            .expect("failed to install Ctrl+C handler");
-    };
-
-    #[cfg(unix)]
-    let terminate = async {
-        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
-            .expect("failed to install SIGTERM handler")
-            .recv()
-            .await;
-    };
-
-    #[cfg(not(unix))]
-    let terminate = std::future::pending::<()>();
-
-    tokio::select! {
-        _ = ctrl_c => {
-            tracing::info!("Received Ctrl+C, initiating graceful shutdown...");
-        }
-        _ = terminate => {
-            tracing::info!("Received SIGTERM, initiating graceful shutdown...");
-        }
-    }
-}
-
 /// Run the A.R.E.S MCP server
 #[cfg(feature = "mcp")]
 async fn run_mcp_server(
@@ -745,7 +1776,7 @@ async fn run_mcp_server(
     let config = AresConfig::load_unchecked(config_path_str)?;
 
     // Initialize database
-    let db = init_postgres_db(&config.database.url).await?;
+    let db = init_postgres_db(&config.database).await?;
     let pool = db.pool.clone();
     let tenant_db = Arc::new(ares::TenantDb::new(Arc::new(db)));
 
@@ -771,56 +1802,16 @@ async fn run_mcp_server(
 }
 
 /// Initialize PostgreSQL database
-async fn init_postgres_db(url: &str) -> Result<PostgresClient, Box<dyn std::error::Error>> {
-    tracing::info!(database_url = %url, "Initializing PostgreSQL database");
-    Ok(PostgresClient::new_local(url).await?)
-}
-
-/// Build CORS layer from configuration
-fn build_cors_layer(origins: &[String]) -> CorsLayer {
-    use axum::http::{header, Method};
-    use tower_http::cors::AllowOrigin;
-
-    let (allow_origin, allow_credentials) = if origins.len() == 1 && origins[0] == "*" {
-        tracing::warn!(
-            "CORS is configured to allow all origins (*) - not recommended for production"
-        );
-        // Cannot use credentials with wildcard origin
-        (AllowOrigin::any(), false)
-    } else if origins.is_empty() {
-        tracing::warn!("No CORS origins configured, defaulting to allow all");
-        (AllowOrigin::any(), false)
-    } else {
-        tracing::info!("CORS configured for origins: {:?}", origins);
-        (
-            AllowOrigin::list(origins.iter().filter_map(|o| o.parse().ok())),
-            true,
-        )
-    };
-
-    CorsLayer::new()
-        .allow_origin(allow_origin)
-        .allow_methods([
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::DELETE,
-            Method::OPTIONS,
-            Method::PATCH,
-        ])
-        .allow_headers([
-            header::AUTHORIZATION,
-            header::CONTENT_TYPE,
-            header::ACCEPT,
-            header::ORIGIN,
-            axum::http::HeaderName::from_static("x-admin-secret"),
-        ])
-        .allow_credentials(allow_credentials)
-}
-
-/// Health check endpoint
-async fn health_check() -> &'static str {
-    "OK"
+async fn init_postgres_db(
+    config: &ares::utils::toml_config::DatabaseConfig,
+) -> Result<PostgresClient, Box<dyn std::error::Error>> {
+    tracing::info!(
+        database_url = %config.url,
+        max_connections = config.pool.max_connections,
+        replica = config.replica_url_env.is_some(),
+        "Initializing PostgreSQL database"
+    );
+    Ok(PostgresClient::from_config(config).await?)
 }
 
 /// Detailed health check endpoint with component status
@@ -832,7 +1823,15 @@ async fn health_check_detailed(
     let start = Instant::now();
 
     // Check database connectivity
-    let db_status = serde_json::json!({ "status": "healthy" });
+    let pool = state.tenant_db.pool();
+    let db_status = serde_json::json!({
+        "status": "healthy",
+        "pool": {
+            "size": pool.size(),
+            "idle": pool.num_idle(),
+            "max": pool.options().get_max_connections(),
+        },
+    });
     /* let db_status = match state.db.operation_conn().await {
         Ok(_) => serde_json::json!({ "status": "healthy" }),
         Err(e) => serde_json::json!({ "status": "unhealthy", "error": e.to_string() }),
@@ -865,6 +1864,10 @@ async fn health_check_detailed(
         .map(|s| s == "healthy")
         .unwrap_or(false);
     let overall_status = if db_healthy { "healthy" } else { "degraded" };
+    let maintenance = state.maintenance.status(
+        &state.config_manager.config().maintenance,
+        state.generations.len(),
+    );
 
     axum::Json(serde_json::json!({
         "status": overall_status,
@@ -872,6 +1875,8 @@ async fn health_check_detailed(
         "checks": {
             "database": db_status,
         },
+        "maintenance": maintenance,
+        "warmup": state.warmup.status(),
         "providers": providers,
         "agents": agents,
         "latency_ms": elapsed_ms,
@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::sync::Arc;
+
+use crate::auth::service_tokens::ServiceTokens;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct McpServerConfig {
@@ -11,11 +14,16 @@ pub struct McpServerConfig {
     pub endpoint: Option<String>,
     pub transport: Option<String>,
     pub api_key: Option<String>,
+    /// Scopes of a service token sent in place of `api_key`, with the
+    /// server's name as audience
+    #[serde(default)]
+    pub service_token_scopes: Option<Vec<String>>,
 }
 
 pub struct McpClient {
     config: McpServerConfig,
     http: reqwest::Client,
+    service_tokens: Option<Arc<ServiceTokens>>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -32,6 +40,9 @@ pub enum McpError {
     ServerDisabled,
     #[error("No endpoint configured")]
     NoEndpoint,
+    /// A service token for the server couldn't be minted
+    #[error("Service token error: {0}")]
+    ServiceToken(String),
 }
 
 impl McpClient {
@@ -43,7 +54,17 @@ impl McpClient {
             .build()
             .expect("Failed to create HTTP client");
 
-        Self { config, http }
+        Self {
+            config,
+            http,
+            service_tokens: None,
+        }
+    }
+
+    /// Sign the service tokens of `service_token_scopes` with `tokens`
+    pub fn with_service_tokens(mut self, tokens: Arc<ServiceTokens>) -> Self {
+        self.service_tokens = Some(tokens);
+        self
     }
 
     pub fn is_enabled(&self) -> bool {
@@ -59,9 +80,7 @@ impl McpClient {
         let url = format!("{}/api/v1/context?path={}", base_url, path);
         
         let mut request = self.http.get(&url);
-        if let Some(ref key) = self.config.api_key {
-            request = request.header("Authorization", format!("Bearer {}", key));
-        }
+        request = self.authorize(request)?;
         
         let response = request.send().await?;
         self.handle_response(response).await
@@ -77,9 +96,7 @@ impl McpClient {
         });
         
         let mut request = self.http.post(&url).json(&body);
-        if let Some(ref key) = self.config.api_key {
-            request = request.header("Authorization", format!("Bearer {}", key));
-        }
+        request = self.authorize(request)?;
         
         let response = request.send().await?;
         self.handle_response(response).await
@@ -100,9 +117,7 @@ impl McpClient {
         }
         
         let mut request = self.http.post(&url).json(&body);
-        if let Some(ref key) = self.config.api_key {
-            request = request.header("Authorization", format!("Bearer {}", key));
-        }
+        request = self.authorize(request)?;
         
         let response = request.send().await?;
         self.handle_response(response).await
@@ -114,9 +129,7 @@ impl McpClient {
         let url = format!("{}/api/v1/completeness/{}", base_url, scope_part);
         
         let mut request = self.http.get(&url);
-        if let Some(ref key) = self.config.api_key {
-            request = request.header("Authorization", format!("Bearer {}", key));
-        }
+        request = self.authorize(request)?;
         
         let response = request.send().await?;
         self.handle_response(response).await
@@ -127,9 +140,7 @@ impl McpClient {
         let url = format!("{}/api/v1/gaps", base_url);
         
         let mut request = self.http.get(&url);
-        if let Some(ref key) = self.config.api_key {
-            request = request.header("Authorization", format!("Bearer {}", key));
-        }
+        request = self.authorize(request)?;
         
         let response = request.send().await?;
         self.handle_response(response).await
@@ -146,14 +157,29 @@ impl McpClient {
         };
         
         let mut request = self.http.post(&url).json(&body);
-        if let Some(ref key) = self.config.api_key {
-            request = request.header("Authorization", format!("Bearer {}", key));
-        }
+        request = self.authorize(request)?;
         
         let response = request.send().await?;
         self.handle_response(response).await
     }
 
+    /// Add the service token, or else the API key, to a request
+    fn authorize(&self, request: reqwest::RequestBuilder) -> Result<reqwest::RequestBuilder, McpError> {
+        if let Some(scopes) = &self.config.service_token_scopes {
+            let tokens = self.service_tokens.as_ref().ok_or_else(|| {
+                McpError::ServiceToken("no service token signer configured".to_string())
+            })?;
+            let token = tokens
+                .mint(&self.config.name, scopes, None)
+                .map_err(|e| McpError::ServiceToken(e.to_string()))?;
+            return Ok(request.bearer_auth(token));
+        }
+        Ok(match &self.config.api_key {
+            Some(key) => request.header("Authorization", format!("Bearer {}", key)),
+            None => request,
+        })
+    }
+
     fn get_base_url(&self) -> Result<String, McpError> {
         self.config
             .endpoint
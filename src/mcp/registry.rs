@@ -1,13 +1,19 @@
 use std::{collections::HashMap, path::Path, sync::Arc};
 
 use super::client::{McpClient, McpServerConfig};
+use crate::auth::service_tokens::ServiceTokens;
 
 pub struct McpRegistry {
     clients: HashMap<String, Arc<McpClient>>,
 }
 
 impl McpRegistry {
-    pub fn from_dir(config_dir: &str) -> Result<Self, Box<dyn std::error::Error>> {
+    /// Load the clients of the `.toon` files in `config_dir`, signing their
+    /// service tokens with `service_tokens`
+    pub fn from_dir(
+        config_dir: &str,
+        service_tokens: Arc<ServiceTokens>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let mut clients = HashMap::new();
         let path = Path::new(config_dir);
 
@@ -25,7 +31,8 @@ impl McpRegistry {
                 let config: McpServerConfig = toml::from_str(&content)?;
 
                 if config.enabled {
-                    let client = McpClient::new(config);
+                    let client =
+                        McpClient::new(config).with_service_tokens(Arc::clone(&service_tokens));
                     let name = client.name().to_string();
                     tracing::info!("Registered MCP client: {}", name);
                     clients.insert(name, Arc::new(client));
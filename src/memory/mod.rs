@@ -8,6 +8,7 @@
 //! User memory facts and preferences are stored in the database (PostgresClient).
 //! This module provides utilities for working with that stored memory.
 
+use crate::llm::cancellation::CancellationToken;
 use crate::types::{AgentContext, MemoryFact, Message, Preference, UserMemory};
 
 /// Default number of recent messages to include in context.
@@ -163,6 +164,12 @@ pub fn build_context(
         session_id,
         conversation_history: truncated_history,
         user_memory: memory,
+        cancellation: CancellationToken::new(),
+        hooks: Default::default(),
+        preferences: Default::default(),
+        tool_profile: None,
+        bus: None,
+        workspace_id: None,
     }
 }
 
@@ -251,6 +258,7 @@ mod tests {
     fn test_truncate_history() {
         let history: Vec<Message> = (0..10)
             .map(|i| Message {
+                id: i.to_string(),
                 role: MessageRole::User,
                 content: format!("Message {}", i),
                 timestamp: Utc::now(),
@@ -294,6 +302,7 @@ mod tests {
     fn test_build_context() {
         let history: Vec<Message> = (0..20)
             .map(|i| Message {
+                id: i.to_string(),
                 role: MessageRole::User,
                 content: format!("Message {}", i),
                 timestamp: Utc::now(),
@@ -0,0 +1,176 @@
+//! Client addresses behind reverse proxies.
+//!
+//! Behind a proxy, every request comes from the proxy's address, so per-IP
+//! rate limits would count all clients as one. With the proxies listed in
+//! `[server] trusted_proxies`, a request from one of them is attributed to
+//! the address its `X-Forwarded-For` header reports instead:
+//!
+//! ```toml
+//! [server]
+//! trusted_proxies = ["10.0.0.0/8", "127.0.0.1"]
+//! ```
+//!
+//! The header is read from the right, skipping trusted proxies, so a client
+//! can't pass for another by sending its own `X-Forwarded-For`: the first
+//! untrusted hop is the client. Requests from other addresses keep their
+//! own address, whatever the header says.
+//!
+//! The client's address replaces the request's `ConnectInfo`, which the
+//! rate limits and everything after them read.
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+/// Header in which proxies list the addresses a request came through
+const FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Address ranges of the trusted reverse proxies.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedProxies {
+    ranges: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    /// Parse IP addresses and CIDR ranges such as `10.0.0.0/8`
+    pub fn parse(entries: &[String]) -> Result<Self, String> {
+        let ranges = entries
+            .iter()
+            .map(|entry| {
+                let invalid = || format!("Invalid trusted proxy '{}'", entry);
+                let (addr, prefix) = match entry.trim().split_once('/') {
+                    Some((addr, prefix)) => (addr, Some(prefix)),
+                    None => (entry.trim(), None),
+                };
+                let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+                let max = if addr.is_ipv4() { 32 } else { 128 };
+                let prefix = match prefix {
+                    Some(prefix) => prefix.parse().map_err(|_| invalid())?,
+                    None => max,
+                };
+                if prefix > max {
+                    return Err(invalid());
+                }
+                Ok((addr, prefix))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { ranges })
+    }
+
+    /// Whether no proxy is trusted
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Whether `ip` is a trusted proxy
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.ranges
+            .iter()
+            .any(|&(range, prefix)| match (range, ip) {
+                (IpAddr::V4(range), IpAddr::V4(ip)) => {
+                    let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+                    u32::from(range) & mask == u32::from(ip) & mask
+                }
+                (IpAddr::V6(range), IpAddr::V6(ip)) => {
+                    let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+                    u128::from(range) & mask == u128::from(ip) & mask
+                }
+                _ => false,
+            })
+    }
+
+    /// The client of a request from `peer` with `headers`
+    pub fn client(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let mut client = peer;
+        if !self.contains(peer) {
+            return client;
+        }
+        let hops: Vec<&str> = headers
+            .get_all(FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect();
+        for hop in hops.into_iter().rev() {
+            let hop = hop.trim();
+            // Some proxies add the client's port
+            let Some(ip) = hop
+                .parse::<IpAddr>()
+                .ok()
+                .or_else(|| hop.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+            else {
+                break;
+            };
+            client = ip;
+            if !self.contains(ip) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// Middleware attributing requests from trusted proxies to their client.
+pub async fn client_ip(
+    State(proxies): State<Arc<TrustedProxies>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !proxies.is_empty() {
+        if let Some(&ConnectInfo(peer)) = request.extensions().get::<ConnectInfo<SocketAddr>>() {
+            let client = proxies.client(peer.ip(), request.headers());
+            if client != peer.ip() {
+                request
+                    .extensions_mut()
+                    .insert(ConnectInfo(SocketAddr::new(client, 0)));
+            }
+        }
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(forwarded_for: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in forwarded_for {
+            headers.append(FORWARDED_FOR, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_client_behind_trusted_proxies() {
+        let proxies =
+            TrustedProxies::parse(&["10.0.0.0/8".to_string(), "::1".to_string()]).unwrap();
+        let proxy: IpAddr = "10.1.2.3".parse().unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+
+        assert!(proxies.contains("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(!proxies.contains("11.0.0.1".parse().unwrap()));
+        assert_eq!(proxies.client(proxy, &headers(&["203.0.113.7"])), client);
+        // A spoofed address left of the client is ignored, and so are proxies
+        assert_eq!(
+            proxies.client(proxy, &headers(&["1.1.1.1, 203.0.113.7", "10.0.0.2"])),
+            client
+        );
+        assert_eq!(
+            proxies.client(proxy, &headers(&["203.0.113.7:51000"])),
+            client
+        );
+        assert_eq!(proxies.client(proxy, &headers(&[])), proxy);
+        // Untrusted peers are their own client
+        assert_eq!(proxies.client(client, &headers(&["1.1.1.1"])), client);
+
+        assert!(TrustedProxies::parse(&["10.0.0.0/33".to_string()]).is_err());
+        assert!(TrustedProxies::parse(&["proxy.local".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+//! Idempotency keys for chat and research requests.
+//!
+//! A client retrying a request it never got the answer to (a dropped
+//! mobile connection, a proxy timeout) can send the same `Idempotency-Key`
+//! header with both attempts. The first runs the request; once it has
+//! finished, the retry gets its response again, with an
+//! `Idempotent-Replayed: true` header, instead of starting another LLM run.
+//! Keys belong to their caller and are kept for `[server]
+//! idempotency_ttl_secs` (default: a day).
+//!
+//! - A retry while the first request still runs gets a 409.
+//! - A key reused with a different request (path or body) gets a 422.
+//! - Responses a retry could change (401, 403, 408, 409, 429 and server
+//!   errors) aren't kept: the key is released and the retry runs.
+//!
+//! Covered routes are `POST /api/chat`, `/api/chat/batch`,
+//! `/api/chat/{id}/regenerate`, `/api/research` and their `/api/v1`
+//! equivalents, including `/api/v1/agents/{name}/run`. Streamed chat can't
+//! be replayed and ignores the header, as do requests with an
+//! `X-Conversation-Secret`, whose answers mustn't be stored in plaintext.
+
+use crate::db::idempotency::{self, Claim, StoredResponse};
+use crate::types::ErrorCode;
+use crate::AppState;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sha2::{Digest, Sha256};
+
+/// Header carrying the idempotency key
+pub const HEADER: &str = "idempotency-key";
+
+/// Header marking a replayed response
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Longest key accepted, in characters
+const MAX_KEY_CHARS: usize = 255;
+
+/// Largest request body fingerprinted, in bytes
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Whether a request to `path` honors idempotency keys
+pub fn covers(method: &Method, path: &str) -> bool {
+    if method != Method::POST {
+        return false;
+    }
+    let Some(path) = path.strip_prefix("/api") else {
+        return false;
+    };
+    let path = path.strip_prefix("/v1").unwrap_or(path);
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    matches!(
+        segments.as_slice(),
+        ["chat"]
+            | ["chat", "batch"]
+            | ["chat", _, "regenerate"]
+            | ["agents", _, "run"]
+            | ["research"]
+    )
+}
+
+/// Hash of what makes two requests the same: method, path and body
+fn fingerprint(method: &Method, path: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str());
+    hasher.update([0]);
+    hasher.update(path);
+    hasher.update([0]);
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+/// Whether a response is kept for retries with the same key
+fn is_final(status: StatusCode) -> bool {
+    !(status.is_server_error()
+        || matches!(
+            status,
+            StatusCode::UNAUTHORIZED
+                | StatusCode::FORBIDDEN
+                | StatusCode::REQUEST_TIMEOUT
+                | StatusCode::CONFLICT
+                | StatusCode::TOO_MANY_REQUESTS
+        ))
+}
+
+/// Replay the response to an earlier request with the same key, or run the
+/// request and keep its response.
+pub async fn idempotency(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let ttl_secs = state.config_manager.config().server.idempotency_ttl_secs;
+    if ttl_secs == 0
+        || !covers(req.method(), req.uri().path())
+        || req.headers().contains_key(crate::api::encryption::HEADER)
+    {
+        return next.run(req).await;
+    }
+    let Some(key) = req.headers().get(HEADER) else {
+        return next.run(req).await;
+    };
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.chars().count() <= MAX_KEY_CHARS => key.to_string(),
+        _ => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidInput,
+                format!(
+                    "Idempotency-Key must be 1 to {} visible ASCII characters",
+                    MAX_KEY_CHARS
+                ),
+            )
+        }
+    };
+
+    let caller = super::rate_limit::caller(&state, &req);
+    let (parts, body) = req.into_parts();
+    let body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(_) => {
+            return error(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ErrorCode::InvalidInput,
+                "Request body too large".to_string(),
+            )
+        }
+    };
+    let path = parts
+        .uri
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or_default();
+    let fingerprint = fingerprint(&parts.method, path, &body);
+
+    let pool = state.tenant_db.pool();
+    match idempotency::claim(pool, &caller, &key, &fingerprint, ttl_secs).await {
+        Ok(Claim::Claimed) => {}
+        Ok(Claim::Completed(stored)) => return replay(stored),
+        Ok(Claim::InProgress) => {
+            return error(
+                StatusCode::CONFLICT,
+                ErrorCode::IdempotencyConflict,
+                "A request with this Idempotency-Key is still running; retry once it has finished"
+                    .to_string(),
+            )
+        }
+        Ok(Claim::Mismatch) => {
+            return error(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::IdempotencyConflict,
+                "This Idempotency-Key was used for a different request".to_string(),
+            )
+        }
+        Err(e) => return e.into_response(),
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    let status = response.status();
+    if !is_final(status) {
+        if let Err(e) = idempotency::release(pool, &caller, &key).await {
+            tracing::warn!("{}", e);
+        }
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::warn!("Failed to read response for idempotency key: {}", e);
+            if let Err(e) = idempotency::release(pool, &caller, &key).await {
+                tracing::warn!("{}", e);
+            }
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let stored = StoredResponse {
+        status: status.as_u16(),
+        content_type: parts
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+        body: body.to_vec(),
+    };
+    if let Err(e) = idempotency::complete(pool, &caller, &key, &stored).await {
+        tracing::warn!("{}", e);
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// The kept response, marked as replayed
+fn replay(stored: StoredResponse) -> Response {
+    let status = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+    let mut response = (status, stored.body).into_response();
+    let headers = response.headers_mut();
+    headers.remove(header::CONTENT_TYPE);
+    if let Some(value) = stored
+        .content_type
+        .and_then(|value| HeaderValue::from_str(&value).ok())
+    {
+        headers.insert(header::CONTENT_TYPE, value);
+    }
+    headers.insert(
+        HeaderName::from_static(REPLAYED_HEADER),
+        HeaderValue::from_static("true"),
+    );
+    response
+}
+
+fn error(status: StatusCode, code: ErrorCode, message: String) -> Response {
+    let body = serde_json::json!({ "error": message, "code": code });
+    (status, Json(body)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_covered_routes() {
+        let post = Method::POST;
+        assert!(covers(&post, "/api/chat"));
+        assert!(covers(&post, "/api/v1/chat"));
+        assert!(covers(&post, "/api/chat/ctx_1/regenerate"));
+        assert!(covers(&post, "/api/v1/agents/support/run"));
+        assert!(covers(&post, "/api/research"));
+        assert!(covers(&post, "/api/chat/batch"));
+        assert!(!covers(&post, "/api/chat/batch/b1/cancel"));
+        // Streams can't be replayed
+        assert!(!covers(&post, "/api/chat/stream"));
+        assert!(!covers(&post, "/api/chat/ctx_1/stop"));
+        assert!(!covers(&Method::GET, "/api/chat"));
+    }
+
+    #[test]
+    fn test_fingerprint_tells_requests_apart() {
+        let post = Method::POST;
+        let first = fingerprint(&post, "/api/chat", br#"{"message":"hi"}"#);
+        assert_eq!(
+            first,
+            fingerprint(&post, "/api/chat", br#"{"message":"hi"}"#)
+        );
+        assert_ne!(
+            first,
+            fingerprint(&post, "/api/chat", br#"{"message":"bye"}"#)
+        );
+        assert_ne!(
+            first,
+            fingerprint(&post, "/api/research", br#"{"message":"hi"}"#)
+        );
+    }
+
+    #[test]
+    fn test_only_final_responses_are_kept() {
+        assert!(is_final(StatusCode::OK));
+        assert!(is_final(StatusCode::BAD_REQUEST));
+        assert!(is_final(StatusCode::UNPROCESSABLE_ENTITY));
+        assert!(!is_final(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_final(StatusCode::UNAUTHORIZED));
+        assert!(!is_final(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!is_final(StatusCode::GATEWAY_TIMEOUT));
+    }
+}
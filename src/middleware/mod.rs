@@ -1,5 +1,13 @@
 pub mod api_key_auth;
+/// Client addresses behind trusted reverse proxies.
+pub mod client_ip;
+/// Idempotency keys for chat and research requests.
+pub mod idempotency;
 pub mod usage;
+/// Per-route rate limiting.
+pub mod rate_limit;
+/// CORS and security headers.
+pub mod security;
 
 pub use api_key_auth::api_key_auth_middleware;
 pub use usage::track_usage as usage_tracking_middleware;
@@ -0,0 +1,313 @@
+//! Per-route rate limiting.
+//!
+//! Chat, research and RAG ingest requests are limited separately by
+//! `[server.rate_limits]` in `ares.toml` (hot-reloaded). Each caller has a
+//! token bucket per route group: its user when the request carries a valid
+//! JWT, its API key for `/api/v1` requests, or else its IP address. A request
+//! finding its bucket empty is rejected with a 429 and a `Retry-After`
+//! header:
+//!
+//! ```json
+//! {"error": "Too many chat requests; retry in 4 seconds", "code": "RATE_LIMITED"}
+//! ```
+//!
+//! This runs alongside the per-IP limit of `rate_limit_per_second`, which
+//! covers every route.
+//!
+//! Buckets are kept per instance, or in Redis when `[redis]` is configured
+//! (feature `redis`), so that the limits hold across instances.
+
+use crate::types::ErrorCode;
+use crate::utils::toml_config::{RateLimit, RateLimitsConfig};
+use crate::AppState;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Buckets kept before full ones are dropped
+const MAX_TRACKED: usize = 10_000;
+
+/// Routes that share a limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteGroup {
+    /// Chat, streamed chat, batches, regenerate and agent runs
+    Chat,
+    /// Deep research
+    Research,
+    /// RAG ingestion and ingest jobs
+    Ingest,
+}
+
+impl RouteGroup {
+    /// The group of a request to `path`, if its route is limited
+    pub fn of(method: &Method, path: &str) -> Option<Self> {
+        if method != Method::POST {
+            return None;
+        }
+        let path = path.strip_prefix("/api")?;
+        let path = path.strip_prefix("/v1").unwrap_or(path);
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match segments.as_slice() {
+            ["chat"]
+            | ["chat", "stream"]
+            | ["chat", "batch"]
+            | ["chat", _, "regenerate"]
+            | ["agents", _, "run"] => Some(Self::Chat),
+            ["research"] => Some(Self::Research),
+            ["rag", "ingest"]
+            | ["rag", "ingest", "jobs"]
+            | ["rag", "ingest", "jobs", _, "resume"] => Some(Self::Ingest),
+            _ => None,
+        }
+    }
+
+    /// The group's configured limit
+    pub fn limit(self, config: &RateLimitsConfig) -> Option<RateLimit> {
+        match self {
+            Self::Chat => config.chat,
+            Self::Research => config.research,
+            Self::Ingest => config.ingest,
+        }
+    }
+
+    /// Name of the group, as in `[server.rate_limits]`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Chat => "chat",
+            Self::Research => "research",
+            Self::Ingest => "ingest",
+        }
+    }
+}
+
+/// Token buckets of every caller.
+///
+/// Cheap to clone; all clones share the same buckets.
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<HashMap<(RouteGroup, String), Bucket>>>,
+    /// Buckets shared with other instances, used over `buckets` when set
+    #[cfg(feature = "redis")]
+    shared: Option<crate::db::redis::RedisStore>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    limit: RateLimit,
+}
+
+impl Bucket {
+    /// Tokens per second
+    fn rate(&self) -> f64 {
+        self.limit.requests_per_minute as f64 / 60.0
+    }
+
+    /// Add the tokens refilled since the last update
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate()).min(self.limit.capacity() as f64);
+        self.updated = now;
+    }
+
+    fn is_full(&self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens + elapsed * self.rate() >= self.limit.capacity() as f64
+    }
+}
+
+impl RateLimiter {
+    /// Keep buckets in Redis, falling back to this instance's own when
+    /// Redis fails
+    #[cfg(feature = "redis")]
+    pub fn shared(store: crate::db::redis::RedisStore) -> Self {
+        Self {
+            shared: Some(store),
+            ..Self::default()
+        }
+    }
+
+    /// Take a token from `caller`'s bucket for `group`, shared with other
+    /// instances if configured
+    pub async fn acquire(
+        &self,
+        group: RouteGroup,
+        caller: &str,
+        limit: RateLimit,
+    ) -> Result<(), Duration> {
+        #[cfg(feature = "redis")]
+        if let Some(store) = &self.shared {
+            let bucket = format!("{}:{}", group.name(), caller);
+            match store
+                .take_token(&bucket, limit.capacity(), limit.requests_per_minute)
+                .await
+            {
+                Ok(taken) => return taken,
+                Err(e) => tracing::warn!("{}; using local rate-limit buckets", e),
+            }
+        }
+        self.check(group, caller, limit, Instant::now())
+    }
+
+    /// Take a token from `caller`'s bucket for `group`
+    ///
+    /// Returns how long until a token is available if the bucket is empty.
+    /// A changed limit applies to existing buckets from the next request.
+    pub fn check(
+        &self,
+        group: RouteGroup,
+        caller: &str,
+        limit: RateLimit,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock();
+        if buckets.len() >= MAX_TRACKED {
+            buckets.retain(|_, bucket| !bucket.is_full(now));
+        }
+        let bucket = buckets
+            .entry((group, caller.to_string()))
+            .or_insert_with(|| Bucket {
+                tokens: limit.capacity() as f64,
+                updated: now,
+                limit,
+            });
+        bucket.limit = limit;
+        bucket.refill(now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64(
+            (1.0 - bucket.tokens) / bucket.rate(),
+        ))
+    }
+}
+
+/// Reject requests over their route group's limit with a 429.
+pub async fn rate_limit(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(group) = RouteGroup::of(req.method(), req.uri().path()) else {
+        return next.run(req).await;
+    };
+    let Some(limit) = group.limit(&state.config_manager.config().server.rate_limits) else {
+        return next.run(req).await;
+    };
+    let caller = caller(&state, &req);
+    if let Err(wait) = state.rate_limits.acquire(group, &caller, limit).await {
+        tracing::debug!("Rate limited {} request from {}", group.name(), caller);
+        return too_many_requests(group, wait);
+    }
+    next.run(req).await
+}
+
+/// Who a request counts against: its user, its API key, or its IP address
+///
+/// API keys are identified by a hash, so raw keys are never kept.
+pub(crate) fn caller(state: &AppState, req: &Request) -> String {
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if let Some(key) = req.headers().get("x-api-key").and_then(|v| v.to_str().ok()) {
+        return format!("key:{}", hex::encode(&Sha256::digest(key)[..16]));
+    }
+    if let Some(token) = token {
+        if token.starts_with("ares_") {
+            return format!("key:{}", hex::encode(&Sha256::digest(token)[..16]));
+        }
+        if let Ok(claims) = state.auth_service.verify_token(token) {
+            return format!("user:{}", claims.sub);
+        }
+    }
+    match req.extensions().get::<ConnectInfo<SocketAddr>>() {
+        Some(ConnectInfo(addr)) => format!("ip:{}", addr.ip()),
+        None => "ip:unknown".to_string(),
+    }
+}
+
+fn too_many_requests(group: RouteGroup, wait: Duration) -> Response {
+    let secs = wait.as_secs_f64().ceil().max(1.0) as u64;
+    let body = serde_json::json!({
+        "error": format!("Too many {} requests; retry in {} seconds", group.name(), secs),
+        "code": ErrorCode::RateLimited,
+    });
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, secs.to_string())],
+        Json(body),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_groups() {
+        let post = Method::POST;
+        assert_eq!(RouteGroup::of(&post, "/api/chat"), Some(RouteGroup::Chat));
+        assert_eq!(
+            RouteGroup::of(&post, "/api/v1/chat/stream"),
+            Some(RouteGroup::Chat)
+        );
+        assert_eq!(
+            RouteGroup::of(&post, "/api/chat/ctx_1/regenerate"),
+            Some(RouteGroup::Chat)
+        );
+        assert_eq!(
+            RouteGroup::of(&post, "/api/v1/agents/support/run"),
+            Some(RouteGroup::Chat)
+        );
+        assert_eq!(
+            RouteGroup::of(&post, "/api/research"),
+            Some(RouteGroup::Research)
+        );
+        assert_eq!(
+            RouteGroup::of(&post, "/api/rag/ingest/jobs/j1/resume"),
+            Some(RouteGroup::Ingest)
+        );
+        assert_eq!(
+            RouteGroup::of(&post, "/api/chat/batch"),
+            Some(RouteGroup::Chat)
+        );
+        assert_eq!(RouteGroup::of(&post, "/api/chat/ctx_1/stop"), None);
+        assert_eq!(RouteGroup::of(&post, "/api/rag/search"), None);
+        assert_eq!(RouteGroup::of(&Method::GET, "/api/rag/ingest/jobs"), None);
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::default();
+        let limit = RateLimit {
+            requests_per_minute: 60,
+            burst: Some(2),
+        };
+        let start = Instant::now();
+        let check =
+            |caller: &str, at: Duration| limiter.check(RouteGroup::Chat, caller, limit, start + at);
+
+        assert!(check("user:alice", Duration::ZERO).is_ok());
+        assert!(check("user:alice", Duration::ZERO).is_ok());
+        let wait = check("user:alice", Duration::ZERO).unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_secs(1));
+        // Other callers and groups have their own buckets
+        assert!(check("user:bob", Duration::ZERO).is_ok());
+        assert!(limiter
+            .check(RouteGroup::Research, "user:alice", limit, start)
+            .is_ok());
+        // One token a second refills
+        assert!(check("user:alice", Duration::from_secs(1)).is_ok());
+        assert!(check("user:alice", Duration::from_secs(1)).is_err());
+    }
+}
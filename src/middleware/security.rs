@@ -0,0 +1,197 @@
+//! CORS and security headers.
+//!
+//! Both follow `[server.security]` in `ares.toml`. Without `strict_cors`, a
+//! `cors_origins` of `["*"]` or `[]` allows any origin without credentials,
+//! which suits development only; with it, only the listed origins are
+//! allowed, with credentials, and the server refuses to start otherwise.
+//! `cors_methods` and `cors_headers` in `[server]` replace the methods and
+//! request headers allowed by default.
+//!
+//! With `headers` on, every response gets a Content-Security-Policy,
+//! Strict-Transport-Security, `X-Content-Type-Options: nosniff`,
+//! `X-Frame-Options: DENY` and `Referrer-Policy: no-referrer`, unless the
+//! handler already set them.
+
+use crate::utils::toml_config::{SecurityConfig, ServerConfig};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// How long browsers may cache a preflight response
+const PREFLIGHT_MAX_AGE: Duration = Duration::from_secs(600);
+
+/// Methods allowed in CORS requests unless `[server] cors_methods` is set
+const DEFAULT_METHODS: [Method; 6] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::DELETE,
+    Method::OPTIONS,
+    Method::PATCH,
+];
+
+/// Request headers allowed in CORS requests unless `[server] cors_headers`
+/// is set: the ones ARES clients send, including `Last-Event-ID` and
+/// `Cache-Control` for streaming.
+fn default_headers() -> Vec<HeaderName> {
+    vec![
+        header::AUTHORIZATION,
+        header::CONTENT_TYPE,
+        header::ACCEPT,
+        header::ORIGIN,
+        header::CACHE_CONTROL,
+        HeaderName::from_static("last-event-id"),
+        HeaderName::from_static("x-admin-secret"),
+        HeaderName::from_static("x-api-key"),
+        HeaderName::from_static(crate::auth::cookies::CSRF_HEADER),
+        HeaderName::from_static(crate::api::encryption::HEADER),
+        HeaderName::from_static(crate::middleware::idempotency::HEADER),
+    ]
+}
+
+/// Build the CORS layer for the origins, methods and headers of `server`.
+///
+/// The rate limit headers are exposed so browser clients can back off.
+pub fn cors_layer(server: &ServerConfig) -> CorsLayer {
+    let origins = &server.cors_origins;
+    let wildcard = origins.is_empty() || origins.iter().any(|o| o == "*");
+    let (allow_origin, allow_credentials) = if wildcard && !server.security.strict_cors {
+        tracing::warn!(
+            "CORS allows all origins - not recommended for production, set \
+             [server.security] strict_cors with exact cors_origins"
+        );
+        // Cannot use credentials with wildcard origin
+        (AllowOrigin::any(), false)
+    } else {
+        tracing::info!("CORS configured for origins: {:?}", origins);
+        (
+            AllowOrigin::list(origins.iter().filter_map(|o| o.parse().ok())),
+            true,
+        )
+    };
+    let methods: Vec<Method> = match &server.cors_methods {
+        Some(methods) => methods
+            .iter()
+            .filter_map(|m| Method::from_bytes(m.to_uppercase().as_bytes()).ok())
+            .collect(),
+        None => DEFAULT_METHODS.to_vec(),
+    };
+    let headers: Vec<HeaderName> = match &server.cors_headers {
+        Some(headers) => headers.iter().filter_map(|h| h.parse().ok()).collect(),
+        None => default_headers(),
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .expose_headers([
+            header::RETRY_AFTER,
+            HeaderName::from_static("x-ratelimit-limit"),
+            HeaderName::from_static("x-ratelimit-remaining"),
+            HeaderName::from_static("x-ratelimit-after"),
+            HeaderName::from_static(crate::middleware::idempotency::REPLAYED_HEADER),
+        ])
+        .allow_credentials(allow_credentials)
+        .max_age(PREFLIGHT_MAX_AGE)
+}
+
+/// Headers added to every response by [`security_headers`].
+#[derive(Debug, Clone, Default)]
+pub struct SecurityHeaders {
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl SecurityHeaders {
+    /// The headers `config` asks for; none unless `headers` is on.
+    pub fn from_config(config: &SecurityConfig) -> Self {
+        if !config.headers {
+            return Self::default();
+        }
+        let mut headers = vec![
+            (
+                header::X_CONTENT_TYPE_OPTIONS,
+                HeaderValue::from_static("nosniff"),
+            ),
+            (header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY")),
+            (
+                header::REFERRER_POLICY,
+                HeaderValue::from_static("no-referrer"),
+            ),
+        ];
+        match HeaderValue::from_str(&config.content_security_policy) {
+            Ok(csp) => headers.push((header::CONTENT_SECURITY_POLICY, csp)),
+            Err(_) => tracing::warn!("Invalid content_security_policy, not sending it"),
+        }
+        if config.hsts_max_age_secs > 0 {
+            let hsts = format!("max-age={}", config.hsts_max_age_secs);
+            if let Ok(hsts) = HeaderValue::from_str(&hsts) {
+                headers.push((header::STRICT_TRANSPORT_SECURITY, hsts));
+            }
+        }
+        Self { headers }
+    }
+
+    /// Whether there is nothing to add
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    fn apply(&self, headers: &mut HeaderMap) {
+        for (name, value) in &self.headers {
+            if !headers.contains_key(name) {
+                headers.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Middleware adding the security headers to responses.
+pub async fn security_headers(
+    State(policy): State<Arc<SecurityHeaders>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    policy.apply(response.headers_mut());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headers_are_added_unless_already_set() {
+        let config = SecurityConfig {
+            headers: true,
+            ..Default::default()
+        };
+        let policy = SecurityHeaders::from_config(&config);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::X_FRAME_OPTIONS,
+            HeaderValue::from_static("SAMEORIGIN"),
+        );
+        policy.apply(&mut headers);
+
+        assert_eq!(headers[header::X_FRAME_OPTIONS], "SAMEORIGIN");
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(
+            headers[header::STRICT_TRANSPORT_SECURITY],
+            "max-age=31536000"
+        );
+        assert!(headers[header::CONTENT_SECURITY_POLICY]
+            .to_str()
+            .unwrap()
+            .starts_with("default-src 'self'"));
+
+        assert!(SecurityHeaders::from_config(&SecurityConfig::default()).is_empty());
+    }
+}
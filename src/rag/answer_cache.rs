@@ -0,0 +1,288 @@
+//! Answer caching keyed on question similarity.
+//!
+//! Agents with `answer_cache` enabled store each first-turn answer, with its
+//! sources, in a vector collection keyed by the embedded question. A later
+//! question whose embedding is within the agent's similarity threshold of a
+//! stored one gets the stored answer back instead of a new generation, as
+//! long as the answer is younger than the agent's TTL. Stale entries are
+//! dropped when a lookup finds them.
+//!
+//! Each agent gets its own collection per embedding model, per user unless
+//! the agent shares its cache across users.
+
+use crate::db::VectorStore;
+use crate::rag::batcher::EmbeddingBatcher;
+use crate::types::{AppError, Document, DocumentMetadata, Result, Source};
+use crate::utils::toml_config::AnswerCacheConfig;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+/// Candidates checked per lookup, so a stale best match doesn't hide a
+/// fresh one just below it
+const LOOKUP_CANDIDATES: usize = 3;
+
+/// A stored answer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedAnswer {
+    /// Question the answer was generated for
+    pub question: String,
+    /// The answer
+    pub answer: String,
+    /// Sources cited by the answer
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<Source>,
+    /// When the answer was generated
+    pub cached_at: DateTime<Utc>,
+}
+
+/// Stores and finds answers in per-agent vector collections.
+#[derive(Clone)]
+pub struct AnswerCache {
+    store: Arc<dyn VectorStore>,
+    embedder: EmbeddingBatcher,
+    model: String,
+}
+
+impl AnswerCache {
+    /// Create a cache over a vector store, embedding questions with
+    /// `embedder`, which runs the embedding model `model`
+    pub fn new(
+        store: Arc<dyn VectorStore>,
+        embedder: EmbeddingBatcher,
+        model: impl Into<String>,
+    ) -> Self {
+        Self {
+            store,
+            embedder,
+            model: model.into(),
+        }
+    }
+
+    /// Collection holding an agent's answers for a user, or for everyone
+    /// when the cache is shared
+    ///
+    /// The embedding model is part of the name, so switching models starts
+    /// a new cache instead of mixing vector sizes.
+    pub fn collection(&self, agent: &str, user_id: &str, config: &AnswerCacheConfig) -> String {
+        let scope = if config.shared { "" } else { user_id };
+        let key = format!("{}\0{}\0{}", self.model, agent, scope);
+        let digest = hex::encode(Sha256::digest(key));
+        format!("answer_cache_{}", &digest[..32])
+    }
+
+    /// Embed a question for [`lookup`](Self::lookup) and [`store`](Self::store)
+    pub async fn embed(&self, question: &str) -> Result<Vec<f32>> {
+        self.embedder.embed(question).await
+    }
+
+    /// Find a fresh answer to a question similar to the embedded one
+    pub async fn lookup(
+        &self,
+        collection: &str,
+        embedding: &[f32],
+        config: &AnswerCacheConfig,
+    ) -> Result<Option<CachedAnswer>> {
+        if !self.store.collection_exists(collection).await? {
+            return Ok(None);
+        }
+        let results = self
+            .store
+            .search(
+                collection,
+                embedding,
+                LOOKUP_CANDIDATES,
+                config.similarity_threshold,
+            )
+            .await?;
+
+        let ttl = i64::try_from(config.ttl_secs)
+            .ok()
+            .and_then(Duration::try_seconds)
+            .unwrap_or(Duration::MAX);
+        let oldest = Utc::now()
+            .checked_sub_signed(ttl)
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let mut stale = Vec::new();
+        let mut hit = None;
+        for result in results {
+            match serde_json::from_str::<CachedAnswer>(&result.document.content) {
+                Ok(answer) if answer.cached_at >= oldest => {
+                    hit = Some(answer);
+                    break;
+                }
+                // Expired or unreadable
+                _ => stale.push(result.document.id),
+            }
+        }
+        if !stale.is_empty() {
+            self.store.delete(collection, &stale).await?;
+        }
+        Ok(hit)
+    }
+
+    /// Store an answer under the embedded question, replacing any earlier
+    /// answer to the same question
+    pub async fn store(
+        &self,
+        collection: &str,
+        embedding: Vec<f32>,
+        question: &str,
+        answer: &str,
+        sources: Vec<Source>,
+    ) -> Result<()> {
+        if !self.store.collection_exists(collection).await? {
+            self.store
+                .create_collection(collection, embedding.len())
+                .await?;
+        }
+
+        let cached = CachedAnswer {
+            question: question.to_string(),
+            answer: answer.to_string(),
+            sources,
+            cached_at: Utc::now(),
+        };
+        let content = serde_json::to_string(&cached)
+            .map_err(|e| AppError::Internal(format!("Failed to encode cached answer: {}", e)))?;
+        let normalized = question.split_whitespace().collect::<Vec<_>>().join(" ");
+        let document = Document {
+            id: hex::encode(Sha256::digest(normalized.to_lowercase())),
+            content,
+            metadata: DocumentMetadata {
+                title: question.to_string(),
+                created_at: cached.cached_at,
+                ..Default::default()
+            },
+            embedding: Some(embedding),
+        };
+        self.store.upsert(collection, &[document]).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::vectorstore::InMemoryVectorStore;
+    use crate::rag::batcher::{BatchConfig, BatchEmbedder};
+    use async_trait::async_trait;
+
+    /// Embeds questions about cats and dogs on separate axes
+    struct PetEmbedder;
+
+    #[async_trait]
+    impl BatchEmbedder for PetEmbedder {
+        async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let text = text.to_lowercase();
+                    vec![
+                        text.matches("cat").count() as f32,
+                        text.matches("dog").count() as f32,
+                        0.1,
+                    ]
+                })
+                .collect())
+        }
+    }
+
+    fn cache(store: Arc<InMemoryVectorStore>) -> AnswerCache {
+        let embedder = EmbeddingBatcher::new(
+            Arc::new(PetEmbedder),
+            BatchConfig {
+                max_batch_size: 8,
+                flush_interval: std::time::Duration::from_millis(1),
+            },
+        );
+        AnswerCache::new(store, embedder, "pets")
+    }
+
+    #[tokio::test]
+    async fn test_lookup_similar_questions() {
+        let cache = cache(Arc::new(InMemoryVectorStore::new()));
+        let config = AnswerCacheConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let collection = cache.collection("faq", "user-1", &config);
+
+        let question = "How long do cats sleep?";
+        let embedding = cache.embed(question).await.unwrap();
+        assert!(cache
+            .lookup(&collection, &embedding, &config)
+            .await
+            .unwrap()
+            .is_none());
+        let sources = vec![Source {
+            title: "Cat facts".to_string(),
+            url: Some("https://example.com/cats".to_string()),
+            relevance_score: 0.9,
+        }];
+        cache
+            .store(&collection, embedding, question, "About 15 hours.", sources)
+            .await
+            .unwrap();
+
+        let similar = cache.embed("how long does a cat sleep").await.unwrap();
+        let hit = cache
+            .lookup(&collection, &similar, &config)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(hit.answer, "About 15 hours.");
+        assert_eq!(hit.sources[0].title, "Cat facts");
+
+        let different = cache.embed("How long do dogs sleep?").await.unwrap();
+        assert!(cache
+            .lookup(&collection, &different, &config)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Other users and agents have their own caches
+        assert_ne!(collection, cache.collection("faq", "user-2", &config));
+        let shared = AnswerCacheConfig {
+            shared: true,
+            ..config.clone()
+        };
+        assert_eq!(
+            cache.collection("faq", "user-1", &shared),
+            cache.collection("faq", "user-2", &shared)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stale_answers_are_dropped() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        let cache = cache(store.clone());
+        let config = AnswerCacheConfig {
+            enabled: true,
+            ttl_secs: 0,
+            ..Default::default()
+        };
+        let collection = cache.collection("faq", "user-1", &config);
+
+        let embedding = cache.embed("Do cats purr?").await.unwrap();
+        cache
+            .store(
+                &collection,
+                embedding.clone(),
+                "Do cats purr?",
+                "Yes.",
+                vec![],
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+        assert!(cache
+            .lookup(&collection, &embedding, &config)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(store.count(&collection).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,284 @@
+//! Files attached to a conversation (document Q&A mode).
+//!
+//! Each conversation with attached files gets its own ephemeral vector
+//! collection holding their chunks. While a conversation has attachments,
+//! every message retrieves its most relevant passages from those files
+//! only, and the agent answers from them. The collection is deleted with
+//! the conversation, and never shows up among the user's RAG collections.
+
+use crate::db::VectorStore;
+use crate::rag::batcher::EmbeddingBatcher;
+use crate::rag::chunker::TextChunker;
+use crate::types::{AppError, Document, DocumentMetadata, Result, Source};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+/// Largest file that can be attached, in bytes of text
+pub const MAX_ATTACHMENT_BYTES: usize = 2 * 1024 * 1024;
+
+/// Most files a conversation can have attached
+pub const MAX_ATTACHMENTS: usize = 20;
+
+/// Passages retrieved for each message
+pub const PASSAGES_PER_MESSAGE: usize = 4;
+
+/// Words per chunk, and words shared by consecutive chunks
+const CHUNK_SIZE: usize = 200;
+const CHUNK_OVERLAP: usize = 50;
+
+/// A passage retrieved for a message, from an attached file or a scoped
+/// collection (see [`crate::rag::scope`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Passage {
+    /// Name of the file or document the passage is from
+    pub filename: String,
+    /// The passage text
+    pub content: String,
+    /// Similarity of the passage to the message
+    pub score: f32,
+}
+
+/// Stores and searches the chunks of conversations' attached files.
+#[derive(Clone)]
+pub struct ConversationAttachments {
+    store: Arc<dyn VectorStore>,
+    embedder: EmbeddingBatcher,
+}
+
+impl ConversationAttachments {
+    /// Store chunks in `store`, embedding them with `embedder`
+    pub fn new(store: Arc<dyn VectorStore>, embedder: EmbeddingBatcher) -> Self {
+        Self { store, embedder }
+    }
+
+    /// Collection holding the chunks of a conversation's attachments
+    pub fn collection(conversation_id: &str) -> String {
+        let digest = hex::encode(Sha256::digest(conversation_id));
+        format!("conversation_{}", &digest[..32])
+    }
+
+    /// Chunk, embed and store an attached file, returning its chunk count
+    ///
+    /// Chunk `i` is stored as `{attachment_id}_{i}`.
+    pub async fn add(
+        &self,
+        conversation_id: &str,
+        attachment_id: &str,
+        filename: &str,
+        content: &str,
+    ) -> Result<usize> {
+        let chunks = TextChunker::with_word_chunking(CHUNK_SIZE, CHUNK_OVERLAP).chunk(content);
+        if chunks.is_empty() {
+            return Err(AppError::InvalidInput(format!(
+                "'{}' has no text to attach",
+                filename
+            )));
+        }
+        let embeddings = self.embedder.embed_many(chunks.clone()).await?;
+
+        let collection = Self::collection(conversation_id);
+        let dimensions = embeddings.first().map(Vec::len).unwrap_or_default();
+        if !self.store.collection_exists(&collection).await? {
+            self.store
+                .create_collection(&collection, dimensions)
+                .await?;
+        }
+
+        let metadata = DocumentMetadata {
+            title: filename.to_string(),
+            source: attachment_id.to_string(),
+            created_at: Utc::now(),
+            tags: Vec::new(),
+        };
+        let documents = chunks
+            .into_iter()
+            .zip(embeddings)
+            .enumerate()
+            .map(|(i, (content, embedding))| Document {
+                id: format!("{}_{}", attachment_id, i),
+                content,
+                metadata: metadata.clone(),
+                embedding: Some(embedding),
+            })
+            .collect::<Vec<_>>();
+        self.store.upsert(&collection, &documents).await?;
+        Ok(documents.len())
+    }
+
+    /// Find the passages of a conversation's attachments most relevant to
+    /// `query`, best first
+    pub async fn search(
+        &self,
+        conversation_id: &str,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<Passage>> {
+        let collection = Self::collection(conversation_id);
+        if !self.store.collection_exists(&collection).await? {
+            return Ok(Vec::new());
+        }
+        let embedding = self.embedder.embed(query).await?;
+        let results = self
+            .store
+            .search(&collection, &embedding, limit, 0.0)
+            .await?;
+        Ok(results
+            .into_iter()
+            .map(|result| Passage {
+                filename: result.document.metadata.title,
+                content: result.document.content,
+                score: result.score,
+            })
+            .collect())
+    }
+
+    /// Remove the `chunks` chunks of one attached file
+    pub async fn remove(
+        &self,
+        conversation_id: &str,
+        attachment_id: &str,
+        chunks: usize,
+    ) -> Result<()> {
+        let collection = Self::collection(conversation_id);
+        if !self.store.collection_exists(&collection).await? {
+            return Ok(());
+        }
+        let ids = (0..chunks)
+            .map(|i| format!("{}_{}", attachment_id, i))
+            .collect::<Vec<_>>();
+        self.store.delete(&collection, &ids).await?;
+        Ok(())
+    }
+
+    /// Delete the collection of a conversation's attachments
+    pub async fn delete(&self, conversation_id: &str) -> Result<()> {
+        let collection = Self::collection(conversation_id);
+        if self.store.collection_exists(&collection).await? {
+            self.store.delete_collection(&collection).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Agent input answering `message` from the retrieved passages.
+pub fn prompt(passages: &[Passage], message: &str) -> String {
+    format!("{}\n\nQuestion: {}", context(passages), message)
+}
+
+/// Prompt section presenting the retrieved passages.
+pub fn context(passages: &[Passage]) -> String {
+    let passages = passages
+        .iter()
+        .map(|p| format!("[{}]\n{}", p.filename, p.content))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    format!(
+        "Answer using the following passages from this conversation's documents. \
+         Say so if they don't contain the answer.\n\n{}",
+        passages
+    )
+}
+
+/// The files the passages come from, as response sources.
+pub fn sources(passages: &[Passage]) -> Vec<Source> {
+    let mut sources: Vec<Source> = Vec::new();
+    for passage in passages {
+        match sources.iter_mut().find(|s| s.title == passage.filename) {
+            Some(source) => source.relevance_score = source.relevance_score.max(passage.score),
+            None => sources.push(Source {
+                title: passage.filename.clone(),
+                url: None,
+                relevance_score: passage.score,
+            }),
+        }
+    }
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::vectorstore::InMemoryVectorStore;
+    use crate::rag::batcher::{BatchConfig, BatchEmbedder};
+    use async_trait::async_trait;
+
+    /// Embeds texts about cats and dogs on separate axes
+    struct PetEmbedder;
+
+    #[async_trait]
+    impl BatchEmbedder for PetEmbedder {
+        async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let text = text.to_lowercase();
+                    vec![
+                        text.matches("cat").count() as f32,
+                        text.matches("dog").count() as f32,
+                        0.1,
+                    ]
+                })
+                .collect())
+        }
+    }
+
+    fn attachments(store: Arc<InMemoryVectorStore>) -> ConversationAttachments {
+        let embedder = EmbeddingBatcher::new(
+            Arc::new(PetEmbedder),
+            BatchConfig {
+                max_batch_size: 8,
+                flush_interval: std::time::Duration::from_millis(1),
+            },
+        );
+        ConversationAttachments::new(store, embedder)
+    }
+
+    #[tokio::test]
+    async fn test_search_is_scoped_to_the_conversation() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        let attachments = attachments(store.clone());
+        attachments
+            .add(
+                "conv-1",
+                "a1",
+                "cats.txt",
+                "Cats sleep about 15 hours a day.",
+            )
+            .await
+            .unwrap();
+        attachments
+            .add("conv-1", "a2", "dogs.txt", "Dogs need a daily walk.")
+            .await
+            .unwrap();
+        attachments
+            .add("conv-2", "a3", "other.txt", "Cats purr when content.")
+            .await
+            .unwrap();
+
+        let passages = attachments
+            .search("conv-1", "How long do cats sleep?", 1)
+            .await
+            .unwrap();
+        assert_eq!(passages.len(), 1);
+        assert_eq!(passages[0].filename, "cats.txt");
+        assert!(prompt(&passages, "How long?").contains("[cats.txt]\nCats sleep"));
+        assert_eq!(sources(&passages)[0].title, "cats.txt");
+
+        attachments.remove("conv-1", "a1", 1).await.unwrap();
+        let passages = attachments.search("conv-1", "cats", 5).await.unwrap();
+        assert_eq!(passages.len(), 1);
+        assert_eq!(passages[0].filename, "dogs.txt");
+
+        attachments.delete("conv-1").await.unwrap();
+        assert!(attachments
+            .search("conv-1", "dogs", 5)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            attachments.search("conv-2", "cats", 5).await.unwrap().len(),
+            1
+        );
+    }
+}
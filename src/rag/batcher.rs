@@ -0,0 +1,286 @@
+//! Embedding Request Batching
+//!
+//! Coalesces concurrent embedding requests into provider batch calls.
+//!
+//! Embedding models are far more efficient on batches than on single texts,
+//! but requests arrive independently: one per search query, one per ingested
+//! document. [`EmbeddingBatcher`] queues requests and flushes them to the
+//! underlying [`BatchEmbedder`] when either `max_batch_size` texts are
+//! pending or `flush_interval` has elapsed since the first pending request.
+//! Requests larger than `max_batch_size` are split across several calls.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use ares::rag::batcher::{BatchConfig, EmbeddingBatcher};
+//!
+//! let batcher = EmbeddingBatcher::new(Arc::new(embedding_service), BatchConfig::default());
+//!
+//! // Concurrent callers share provider calls
+//! let (a, b) = tokio::join!(batcher.embed("first"), batcher.embed("second"));
+//! ```
+
+use crate::types::{AppError, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+
+/// A backend that embeds a batch of texts in one call.
+#[async_trait]
+pub trait BatchEmbedder: Send + Sync + 'static {
+    /// Embed `texts`, returning one vector per text in the same order.
+    async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Batching behaviour for an [`EmbeddingBatcher`].
+#[derive(Debug, Clone, Copy)]
+pub struct BatchConfig {
+    /// Maximum number of texts per provider call.
+    pub max_batch_size: usize,
+    /// Maximum time to wait for more requests before flushing a partial batch.
+    pub flush_interval: Duration,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 64,
+            flush_interval: Duration::from_millis(5),
+        }
+    }
+}
+
+impl BatchConfig {
+    /// Build a batch configuration from the `[rag]` section of `ares.toml`.
+    pub fn from_rag_config(config: &crate::utils::toml_config::RagConfig) -> Self {
+        Self {
+            max_batch_size: config.embedding_batch_size.max(1),
+            flush_interval: Duration::from_millis(config.embedding_flush_interval_ms),
+        }
+    }
+}
+
+struct PendingRequest {
+    texts: Vec<String>,
+    respond: oneshot::Sender<Result<Vec<Vec<f32>>>>,
+}
+
+/// Coalesces concurrent embedding requests into batched provider calls.
+///
+/// Cloning is cheap; all clones feed the same background worker, which
+/// exits once every clone has been dropped.
+#[derive(Clone)]
+pub struct EmbeddingBatcher {
+    tx: mpsc::UnboundedSender<PendingRequest>,
+    config: BatchConfig,
+}
+
+impl EmbeddingBatcher {
+    /// Create a batcher over `embedder` and spawn its worker on the current runtime.
+    pub fn new<E: BatchEmbedder>(embedder: Arc<E>, config: BatchConfig) -> Self {
+        let config = BatchConfig {
+            max_batch_size: config.max_batch_size.max(1),
+            ..config
+        };
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run_worker(embedder, config, rx));
+        Self { tx, config }
+    }
+
+    /// Get the batching configuration
+    pub fn config(&self) -> BatchConfig {
+        self.config
+    }
+
+    /// Embed a single text.
+    pub async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_many(vec![text.to_string()])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| AppError::Internal("No embedding generated".to_string()))
+    }
+
+    /// Embed several texts, sharing provider calls with concurrent requests.
+    pub async fn embed_many(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let (respond, response) = oneshot::channel();
+        self.tx
+            .send(PendingRequest { texts, respond })
+            .map_err(|_| AppError::Internal("Embedding batcher has shut down".to_string()))?;
+
+        response
+            .await
+            .map_err(|_| AppError::Internal("Embedding batcher dropped request".to_string()))?
+    }
+}
+
+async fn run_worker<E: BatchEmbedder>(
+    embedder: Arc<E>,
+    config: BatchConfig,
+    mut rx: mpsc::UnboundedReceiver<PendingRequest>,
+) {
+    while let Some(first) = rx.recv().await {
+        let deadline = Instant::now() + config.flush_interval;
+        let mut pending_texts = first.texts.len();
+        let mut batch = vec![first];
+
+        // Gather more requests until the batch is full or the interval elapses
+        while pending_texts < config.max_batch_size {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(request)) => {
+                    pending_texts += request.texts.len();
+                    batch.push(request);
+                }
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        flush(embedder.as_ref(), config.max_batch_size, batch).await;
+    }
+}
+
+/// Embed all texts of `batch` in calls of at most `max_batch_size` and
+/// hand each request its slice of the results.
+async fn flush<E: BatchEmbedder>(embedder: &E, max_batch_size: usize, batch: Vec<PendingRequest>) {
+    let mut texts = Vec::new();
+    let mut responders = Vec::with_capacity(batch.len());
+    for request in batch {
+        responders.push((request.texts.len(), request.respond));
+        texts.extend(request.texts);
+    }
+
+    let total = texts.len();
+    let mut embeddings = Vec::with_capacity(total);
+    let mut texts = texts.into_iter();
+    let mut failure = None;
+
+    while embeddings.len() < total {
+        let chunk: Vec<String> = texts.by_ref().take(max_batch_size).collect();
+        let expected = chunk.len();
+        match embedder.embed_batch(chunk).await {
+            Ok(vectors) if vectors.len() == expected => embeddings.extend(vectors),
+            Ok(vectors) => {
+                failure = Some(format!(
+                    "Embedding provider returned {} vectors for {} texts",
+                    vectors.len(),
+                    expected
+                ));
+                break;
+            }
+            Err(e) => {
+                failure = Some(e.to_string());
+                break;
+            }
+        }
+    }
+
+    let mut embeddings = embeddings.into_iter();
+    for (count, respond) in responders {
+        let result = match failure {
+            Some(ref msg) => Err(AppError::Internal(format!(
+                "Batch embedding failed: {}",
+                msg
+            ))),
+            None => Ok(embeddings.by_ref().take(count).collect()),
+        };
+        // The caller may have gone away; nothing to do in that case
+        let _ = respond.send(result);
+    }
+}
+
+#[cfg(feature = "local-embeddings")]
+#[async_trait]
+impl BatchEmbedder for crate::rag::embeddings::EmbeddingService {
+    async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        self.embed_texts(texts.as_slice()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Embeds each text as `[len]` and records the size of every call.
+    #[derive(Default)]
+    struct RecordingEmbedder {
+        calls: Mutex<Vec<usize>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl BatchEmbedder for RecordingEmbedder {
+        async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            self.calls.lock().unwrap().push(texts.len());
+            if self.fail {
+                return Err(AppError::External("provider down".to_string()));
+            }
+            Ok(texts.iter().map(|t| vec![t.len() as f32]).collect())
+        }
+    }
+
+    fn config(max_batch_size: usize, flush_ms: u64) -> BatchConfig {
+        BatchConfig {
+            max_batch_size,
+            flush_interval: Duration::from_millis(flush_ms),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_are_coalesced() {
+        let embedder = Arc::new(RecordingEmbedder::default());
+        let batcher = EmbeddingBatcher::new(embedder.clone(), config(64, 50));
+
+        let (a, b, c) = tokio::join!(
+            batcher.embed("a"),
+            batcher.embed_many(vec!["bb".to_string(), "ccc".to_string()]),
+            batcher.embed("dddd"),
+        );
+
+        assert_eq!(a.unwrap(), vec![1.0]);
+        assert_eq!(b.unwrap(), vec![vec![2.0], vec![3.0]]);
+        assert_eq!(c.unwrap(), vec![4.0]);
+        assert_eq!(*embedder.calls.lock().unwrap(), vec![4]);
+    }
+
+    #[tokio::test]
+    async fn test_large_request_is_split() {
+        let embedder = Arc::new(RecordingEmbedder::default());
+        let batcher = EmbeddingBatcher::new(embedder.clone(), config(4, 1));
+
+        let texts: Vec<String> = (0..10).map(|i| "x".repeat(i)).collect();
+        let result = batcher.embed_many(texts).await.unwrap();
+
+        assert_eq!(result.len(), 10);
+        assert_eq!(result[9], vec![9.0]);
+        assert_eq!(*embedder.calls.lock().unwrap(), vec![4, 4, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_errors_reach_every_request() {
+        let embedder = Arc::new(RecordingEmbedder {
+            fail: true,
+            ..Default::default()
+        });
+        let batcher = EmbeddingBatcher::new(embedder, config(64, 20));
+
+        let (a, b) = tokio::join!(batcher.embed("a"), batcher.embed("b"));
+        assert!(a.unwrap_err().to_string().contains("provider down"));
+        assert!(b.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_empty_request_skips_provider() {
+        let embedder = Arc::new(RecordingEmbedder::default());
+        let batcher = EmbeddingBatcher::new(embedder.clone(), BatchConfig::default());
+
+        assert!(batcher.embed_many(vec![]).await.unwrap().is_empty());
+        assert!(embedder.calls.lock().unwrap().is_empty());
+    }
+}
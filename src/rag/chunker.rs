@@ -5,6 +5,8 @@
 //! - **Word-based**: Simple word count chunking with overlap
 //! - **Semantic**: Sentence/paragraph aware chunking using text-splitter
 //! - **Token-based**: Token-aware chunking for LLM context limits
+//! - **Code**: Keeps top-level declarations (functions, classes, impl blocks)
+//!   together, splitting at blank-line-separated, unindented boundaries
 
 use std::str::FromStr;
 
@@ -28,6 +30,8 @@ pub enum ChunkingStrategy {
     Semantic,
     /// Character-based chunking
     Character,
+    /// Source code chunking along top-level declaration boundaries
+    Code,
 }
 
 impl FromStr for ChunkingStrategy {
@@ -38,8 +42,9 @@ impl FromStr for ChunkingStrategy {
             "word" | "words" => Ok(Self::Word),
             "semantic" | "sentence" | "paragraph" => Ok(Self::Semantic),
             "character" | "char" | "chars" => Ok(Self::Character),
+            "code" | "source" => Ok(Self::Code),
             _ => Err(AppError::Internal(format!(
-                "Unknown chunking strategy: {}. Use: word, semantic, character",
+                "Unknown chunking strategy: {}. Use: word, semantic, character, code",
                 s
             ))),
         }
@@ -52,6 +57,7 @@ impl std::fmt::Display for ChunkingStrategy {
             Self::Word => "word",
             Self::Semantic => "semantic",
             Self::Character => "character",
+            Self::Code => "code",
         };
         write!(f, "{}", name)
     }
@@ -164,6 +170,19 @@ impl TextChunker {
         })
     }
 
+    /// Create with code chunking
+    ///
+    /// Chunks hold whole top-level blocks up to `max_chunk_size` characters;
+    /// larger blocks are split between lines.
+    pub fn with_code_chunking(max_chunk_size: usize) -> Self {
+        Self::new(ChunkerConfig {
+            strategy: ChunkingStrategy::Code,
+            chunk_size: max_chunk_size,
+            chunk_overlap: 0, // Not used for code
+            min_chunk_size: default_min_chunk_size(),
+        })
+    }
+
     /// Chunk text and return simple string vector (backward compatible)
     pub fn chunk(&self, text: &str) -> Vec<String> {
         self.chunk_with_metadata(text)
@@ -178,6 +197,7 @@ impl TextChunker {
             ChunkingStrategy::Word => self.chunk_by_words(text),
             ChunkingStrategy::Semantic => self.chunk_semantically(text),
             ChunkingStrategy::Character => self.chunk_by_characters(text),
+            ChunkingStrategy::Code => self.chunk_code(text),
         }
     }
 
@@ -289,6 +309,85 @@ impl TextChunker {
         chunks
     }
 
+    /// Code chunking along top-level declaration boundaries
+    ///
+    /// A block starts at an unindented line that follows a blank line or the
+    /// close of the previous block, so doc comments and attributes stay with
+    /// the declaration they precede. Blocks are packed into chunks of up to
+    /// `chunk_size` characters; a block too large for one chunk is split
+    /// between lines.
+    fn chunk_code(&self, text: &str) -> Vec<Chunk> {
+        let max = self.config.chunk_size.max(1);
+
+        // Byte ranges of the top-level blocks
+        let mut blocks: Vec<(usize, usize)> = Vec::new();
+        let mut block_start = 0;
+        let mut offset = 0;
+        let mut previous_ends_block = false;
+        for line in text.split_inclusive('\n') {
+            let content = line.trim_end();
+            let unindented = !content.is_empty() && !content.starts_with(char::is_whitespace);
+            let closes = unindented && content.starts_with(['}', ')', ']']);
+            if unindented && !closes && previous_ends_block && offset > block_start {
+                blocks.push((block_start, offset));
+                block_start = offset;
+            }
+            previous_ends_block = content.is_empty() || closes;
+            offset += line.len();
+        }
+        if offset > block_start {
+            blocks.push((block_start, offset));
+        }
+
+        // Pack blocks into chunks, splitting oversized blocks between lines
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        let mut current: Option<(usize, usize)> = None;
+        for (start, end) in blocks {
+            if let Some((chunk_start, chunk_end)) = current {
+                if text[chunk_start..end].chars().count() <= max {
+                    current = Some((chunk_start, end));
+                    continue;
+                }
+                ranges.push((chunk_start, chunk_end));
+            }
+            if text[start..end].chars().count() <= max {
+                current = Some((start, end));
+                continue;
+            }
+
+            let mut piece_start = start;
+            let mut line_start = start;
+            for line in text[start..end].split_inclusive('\n') {
+                let line_end = line_start + line.len();
+                if line_start > piece_start && text[piece_start..line_end].chars().count() > max {
+                    ranges.push((piece_start, line_start));
+                    piece_start = line_start;
+                }
+                line_start = line_end;
+            }
+            current = Some((piece_start, end));
+        }
+        ranges.extend(current);
+
+        ranges
+            .into_iter()
+            .filter_map(|(start, end)| {
+                let content = text[start..end].trim_end();
+                let leading = content.len() - content.trim_start().len();
+                let content = content.trim_start();
+                (content.len() >= self.config.min_chunk_size)
+                    .then(|| (start + leading, content.to_string()))
+            })
+            .enumerate()
+            .map(|(index, (start_offset, content))| Chunk {
+                index,
+                end_offset: start_offset + content.len(),
+                content,
+                start_offset,
+            })
+            .collect()
+    }
+
     /// Get the current configuration
     pub fn config(&self) -> &ChunkerConfig {
         &self.config
@@ -323,6 +422,49 @@ mod tests {
             "character".parse::<ChunkingStrategy>().unwrap(),
             ChunkingStrategy::Character
         );
+        assert_eq!(
+            "code".parse::<ChunkingStrategy>().unwrap(),
+            ChunkingStrategy::Code
+        );
+    }
+
+    #[test]
+    fn test_code_chunking_keeps_declarations_together() {
+        let source = "use std::fmt;\n\n\
+            /// Adds numbers\n\
+            fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\n\
+            fn sub(a: i32, b: i32) -> i32 {\n\n    a - b\n}\n";
+        let chunker = TextChunker::new(ChunkerConfig {
+            strategy: ChunkingStrategy::Code,
+            chunk_size: 80,
+            chunk_overlap: 0,
+            min_chunk_size: 1,
+        });
+        let chunks = chunker.chunk_with_metadata(source);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            chunks[0].content,
+            "use std::fmt;\n\n/// Adds numbers\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}"
+        );
+        assert_eq!(
+            chunks[1].content,
+            "fn sub(a: i32, b: i32) -> i32 {\n\n    a - b\n}"
+        );
+        assert_eq!(
+            &source[chunks[1].start_offset..chunks[1].end_offset],
+            chunks[1].content
+        );
+
+        // Blocks larger than a chunk are split between lines
+        let chunker = TextChunker::new(ChunkerConfig {
+            strategy: ChunkingStrategy::Code,
+            chunk_size: 24,
+            chunk_overlap: 0,
+            min_chunk_size: 1,
+        });
+        let chunks = chunker.chunk("fn long() {\n    one();\n    two();\n}\n");
+        assert_eq!(chunks, vec!["fn long() {\n    one();", "two();\n}"]);
     }
 
     #[test]
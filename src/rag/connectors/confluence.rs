@@ -0,0 +1,394 @@
+//! Confluence document source.
+//!
+//! Lists the current pages of a space through the Confluence REST API and
+//! converts their storage format (XHTML with Confluence macros) to text.
+//! A page's version number is its version, so only edited pages are
+//! re-ingested. Works with Confluence Cloud (email and API token) and
+//! Server/Data Center (personal access token).
+
+use super::{DocumentSource, SourceDocument, SourceLocation, SourceObject, SourcePage};
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::ConfluenceSourceConfig;
+use async_trait::async_trait;
+use scraper::{ElementRef, Html};
+use serde_json::Value;
+
+/// Pages requested per listing call
+const PAGE_SIZE: u64 = 50;
+
+/// How requests to Confluence are authorized.
+pub enum ConfluenceAuth {
+    /// Confluence Cloud: account email and API token
+    Basic {
+        /// Account email
+        email: String,
+        /// API token
+        token: String,
+    },
+    /// Server and Data Center: personal access token
+    Bearer(String),
+}
+
+/// Lists and fetches the pages of a Confluence space.
+pub struct ConfluenceSource {
+    http: reqwest::Client,
+    base_url: String,
+    space: String,
+    auth: ConfluenceAuth,
+}
+
+impl ConfluenceSource {
+    /// Create a source
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Site URL including any context path (e.g., `https://acme.atlassian.net/wiki`)
+    /// * `space` - Space key
+    /// * `auth` - How requests are authorized
+    pub fn new(base_url: &str, space: &str, auth: ConfluenceAuth) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            space: space.to_string(),
+            auth,
+        }
+    }
+
+    /// Create a source from `[rag.confluence]`
+    ///
+    /// Uses basic authentication when the email variable is set, otherwise
+    /// the token as a personal access token.
+    pub fn from_config(config: &ConfluenceSourceConfig, location: &SourceLocation) -> Result<Self> {
+        let base_url = config.base_url.as_deref().ok_or_else(|| {
+            AppError::Configuration("rag.confluence.base_url is not set".to_string())
+        })?;
+        let token = std::env::var(&config.token_env).map_err(|_| {
+            AppError::Configuration(format!(
+                "Confluence token variable {} is not set",
+                config.token_env
+            ))
+        })?;
+        let auth = match std::env::var(&config.email_env) {
+            Ok(email) => ConfluenceAuth::Basic { email, token },
+            Err(_) => ConfluenceAuth::Bearer(token),
+        };
+        Ok(Self::new(base_url, &location.bucket, auth))
+    }
+
+    /// Send an authorized GET request and parse the JSON response
+    async fn get(&self, path: &str, query: &[(&str, &str)]) -> Result<Value> {
+        let request = self
+            .http
+            .get(format!("{}{}", self.base_url, path))
+            .query(query);
+        let request = match &self.auth {
+            ConfluenceAuth::Basic { email, token } => request.basic_auth(email, Some(token)),
+            ConfluenceAuth::Bearer(token) => request.bearer_auth(token),
+        };
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AppError::External(format!("Confluence request failed: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(AppError::External(format!(
+                "Confluence request failed ({}): {}",
+                status, text
+            )));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| AppError::External(format!("Invalid Confluence response: {}", e)))
+    }
+}
+
+#[async_trait]
+impl DocumentSource for ConfluenceSource {
+    fn uri(&self) -> String {
+        format!("confluence://{}", self.space)
+    }
+
+    fn object_uri(&self, key: &str) -> String {
+        format!("{}/pages/viewpage.action?pageId={}", self.base_url, key)
+    }
+
+    /// The cursor is the offset of the next page of results
+    async fn list(&self, cursor: Option<&str>) -> Result<SourcePage> {
+        let start = cursor.unwrap_or("0");
+        let limit = PAGE_SIZE.to_string();
+        let response = self
+            .get(
+                "/rest/api/content",
+                &[
+                    ("spaceKey", self.space.as_str()),
+                    ("type", "page"),
+                    ("status", "current"),
+                    ("limit", limit.as_str()),
+                    ("start", start),
+                    ("expand", "version"),
+                ],
+            )
+            .await?;
+
+        let results = response["results"].as_array().cloned().unwrap_or_default();
+        let next_cursor =
+            (response["_links"]["next"].is_string() && !results.is_empty()).then(|| {
+                let start: u64 = start.parse().unwrap_or_default();
+                (start + results.len() as u64).to_string()
+            });
+        let objects = results
+            .iter()
+            .filter_map(|page| {
+                Some(SourceObject {
+                    key: page["id"].as_str()?.to_string(),
+                    version: page["version"]["number"].as_u64()?.to_string(),
+                    // Sizes are unknown until the body is fetched
+                    size: 0,
+                })
+            })
+            .collect();
+
+        Ok(SourcePage {
+            objects,
+            next_cursor,
+        })
+    }
+
+    async fn fetch(&self, key: &str) -> Result<Vec<u8>> {
+        Ok(self
+            .fetch_document(key)
+            .await?
+            .map(|document| document.text.into_bytes())
+            .unwrap_or_default())
+    }
+
+    async fn fetch_document(&self, key: &str) -> Result<Option<SourceDocument>> {
+        let page = self
+            .get(
+                &format!("/rest/api/content/{}", key),
+                &[("expand", "body.storage,ancestors,metadata.labels")],
+            )
+            .await?;
+
+        let body = storage_to_text(
+            page["body"]["storage"]["value"]
+                .as_str()
+                .unwrap_or_default(),
+        );
+        if body.is_empty() {
+            return Ok(None);
+        }
+        let title = page["title"].as_str().unwrap_or(key).to_string();
+
+        let mut tags = vec![format!("space:{}", self.space)];
+        let ancestors: Vec<&str> = page["ancestors"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|ancestor| ancestor["title"].as_str())
+            .collect();
+        if !ancestors.is_empty() {
+            tags.push(format!("path:{}", ancestors.join("/")));
+        }
+        tags.extend(
+            page["metadata"]["labels"]["results"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|label| label["name"].as_str())
+                .map(str::to_string),
+        );
+
+        let url = page["_links"]["webui"]
+            .as_str()
+            .map(|path| format!("{}{}", self.base_url, path))
+            .unwrap_or_else(|| self.object_uri(key));
+
+        Ok(Some(SourceDocument {
+            text: format!("# {}\n\n{}", title, body),
+            title,
+            url,
+            tags,
+        }))
+    }
+}
+
+/// Convert a page body in Confluence storage format to Markdown-like text
+///
+/// Headings and list items keep their markers, table cells are separated
+/// with `|`, and macro parameters are dropped.
+pub fn storage_to_text(storage: &str) -> String {
+    // HTML parsing drops CDATA, which is where code macros keep their bodies
+    let mut xhtml = String::with_capacity(storage.len());
+    let mut rest = storage;
+    while let Some(start) = rest.find("<![CDATA[") {
+        xhtml.push_str(&rest[..start]);
+        let cdata = &rest[start + 9..];
+        let end = cdata.find("]]>").unwrap_or(cdata.len());
+        xhtml.push_str(
+            &cdata[..end]
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;"),
+        );
+        rest = cdata.get(end + 3..).unwrap_or_default();
+    }
+    xhtml.push_str(rest);
+
+    let fragment = Html::parse_fragment(&xhtml);
+    let mut text = String::new();
+    render_element(fragment.root_element(), &mut text);
+
+    text.lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_element(element: ElementRef<'_>, out: &mut String) {
+    let name = element.value().name();
+    let block = matches!(
+        name,
+        "p" | "div"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "li"
+            | "tr"
+            | "pre"
+            | "blockquote"
+            | "table"
+            | "ul"
+            | "ol"
+            | "ac:structured-macro"
+            | "ac:plain-text-body"
+            | "ac:rich-text-body"
+    );
+    if block {
+        new_line(out);
+    }
+    match name {
+        "ac:parameter" => return,
+        "br" => out.push('\n'),
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = name[1..].parse().unwrap_or(1);
+            out.push_str(&"#".repeat(level));
+            out.push(' ');
+        }
+        "li" => out.push_str("- "),
+        "blockquote" => out.push_str("> "),
+        "td" | "th" if element.prev_siblings().any(|s| s.value().is_element()) => {
+            out.push_str(" | ")
+        }
+        _ => {}
+    }
+
+    for child in element.children() {
+        if let Some(child) = ElementRef::wrap(child) {
+            render_element(child, out);
+        } else if let Some(text) = child.value().as_text() {
+            out.push_str(text);
+        }
+    }
+    if block {
+        new_line(out);
+    }
+}
+
+/// Start a new line unless one was just started
+fn new_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_storage_to_text() {
+        let storage = concat!(
+            "<h1>Deploys</h1><p>Run the <strong>pipeline</strong>.</p>",
+            "<ul><li>Check CI</li><li>Tag the release</li></ul>",
+            "<table><tbody><tr><th>Env</th><th>URL</th></tr>",
+            "<tr><td>prod</td><td>example.com</td></tr></tbody></table>",
+            "<ac:structured-macro ac:name=\"code\"><ac:parameter ac:name=\"language\">bash</ac:parameter>",
+            "<ac:plain-text-body><![CDATA[make deploy <env>]]></ac:plain-text-body></ac:structured-macro>",
+        );
+        assert_eq!(
+            storage_to_text(storage),
+            "# Deploys\nRun the pipeline.\n- Check CI\n- Tag the release\n\
+             Env | URL\nprod | example.com\nmake deploy <env>"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_and_fetch_page() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/wiki/rest/api/content"))
+            .and(query_param("spaceKey", "ENG"))
+            .and(query_param("start", "50"))
+            .and(header("authorization", "Bearer pat-test"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [{"id": "101", "title": "Deploys", "version": {"number": 7}}],
+                "_links": {"next": "/rest/api/content?start=51"}
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/wiki/rest/api/content/101"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "101",
+                "title": "Deploys",
+                "body": {"storage": {"value": "<p>Run the pipeline.</p>"}},
+                "ancestors": [{"title": "Engineering"}, {"title": "Runbooks"}],
+                "metadata": {"labels": {"results": [{"name": "ops"}]}},
+                "_links": {"webui": "/spaces/ENG/pages/101/Deploys"}
+            })))
+            .mount(&server)
+            .await;
+
+        let base_url = format!("{}/wiki", server.uri());
+        let source = ConfluenceSource::new(
+            &base_url,
+            "ENG",
+            ConfluenceAuth::Bearer("pat-test".to_string()),
+        );
+        assert_eq!(source.uri(), "confluence://ENG");
+
+        let page = source.list(Some("50")).await.unwrap();
+        assert_eq!(page.next_cursor.as_deref(), Some("51"));
+        assert_eq!(
+            page.objects,
+            vec![SourceObject {
+                key: "101".to_string(),
+                version: "7".to_string(),
+                size: 0,
+            }]
+        );
+
+        let document = source.fetch_document("101").await.unwrap().unwrap();
+        assert_eq!(document.title, "Deploys");
+        assert_eq!(document.text, "# Deploys\n\nRun the pipeline.");
+        assert_eq!(
+            document.url,
+            format!("{}/spaces/ENG/pages/101/Deploys", base_url)
+        );
+        assert_eq!(
+            document.tags,
+            vec!["space:ENG", "path:Engineering/Runbooks", "ops"]
+        );
+    }
+}
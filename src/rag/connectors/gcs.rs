@@ -0,0 +1,339 @@
+//! Google Cloud Storage document source.
+//!
+//! Uses the Cloud Storage JSON API. Requests carry an OAuth access token,
+//! either supplied directly or exchanged for a signed service account JWT
+//! and refreshed before it expires.
+
+use super::{uri_encode, DocumentSource, SourceLocation, SourceObject, SourcePage};
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::GcsSourceConfig;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// OAuth scope for reading objects
+const READ_ONLY_SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_only";
+
+/// Lifetime requested for service account tokens
+const TOKEN_LIFETIME: Duration = Duration::from_secs(3600);
+
+/// Refresh tokens this long before they expire
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// How requests to Cloud Storage are authorized.
+pub enum GcsAuth {
+    /// A ready-made OAuth access token
+    Token(String),
+    /// A service account key; access tokens are requested as needed
+    ServiceAccount(ServiceAccountKey),
+}
+
+/// The fields of a service account key file used for token requests.
+#[derive(Clone, Deserialize)]
+pub struct ServiceAccountKey {
+    /// Service account email
+    pub client_email: String,
+    /// PEM-encoded RSA private key
+    pub private_key: String,
+    /// OAuth token endpoint
+    #[serde(default = "default_token_uri")]
+    pub token_uri: String,
+}
+
+fn default_token_uri() -> String {
+    "https://oauth2.googleapis.com/token".to_string()
+}
+
+/// Lists and fetches objects under a Cloud Storage prefix.
+pub struct GcsSource {
+    http: reqwest::Client,
+    endpoint: String,
+    bucket: String,
+    prefix: String,
+    auth: GcsAuth,
+    /// Current service account token and when it expires
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl GcsSource {
+    /// Create a source for a bucket prefix
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - Storage API endpoint (e.g., `https://storage.googleapis.com`)
+    /// * `bucket` - Bucket name
+    /// * `prefix` - Object name prefix to list under
+    /// * `auth` - How requests are authorized
+    pub fn new(endpoint: &str, bucket: &str, prefix: &str, auth: GcsAuth) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+            auth,
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Create a source from `[rag.gcs]`
+    ///
+    /// Uses the token in `access_token_env` when set, otherwise the service
+    /// account key file named by `credentials_env`.
+    pub fn from_config(config: &GcsSourceConfig, location: &SourceLocation) -> Result<Self> {
+        let token = config
+            .access_token_env
+            .as_deref()
+            .and_then(|env| std::env::var(env).ok());
+        let auth = match token {
+            Some(token) => GcsAuth::Token(token),
+            None => {
+                let path = std::env::var(&config.credentials_env).map_err(|_| {
+                    AppError::Configuration(format!(
+                        "GCS credentials variable {} is not set",
+                        config.credentials_env
+                    ))
+                })?;
+                let key = std::fs::read_to_string(&path).map_err(|e| {
+                    AppError::Configuration(format!(
+                        "Failed to read GCS credentials '{}': {}",
+                        path, e
+                    ))
+                })?;
+                GcsAuth::ServiceAccount(serde_json::from_str(&key).map_err(|e| {
+                    AppError::Configuration(format!("Invalid GCS credentials '{}': {}", path, e))
+                })?)
+            }
+        };
+        Ok(Self::new(
+            &config.endpoint,
+            &location.bucket,
+            &location.prefix,
+            auth,
+        ))
+    }
+
+    /// Get an access token, requesting a new one if needed
+    async fn access_token(&self) -> Result<String> {
+        let key = match &self.auth {
+            GcsAuth::Token(token) => return Ok(token.clone()),
+            GcsAuth::ServiceAccount(key) => key,
+        };
+
+        let mut cached = self.token.lock().await;
+        if let Some((token, expires_at)) = cached.as_ref() {
+            if Instant::now() + TOKEN_REFRESH_MARGIN < *expires_at {
+                return Ok(token.clone());
+            }
+        }
+
+        let (token, lifetime) = self.request_token(key).await?;
+        *cached = Some((token.clone(), Instant::now() + lifetime));
+        Ok(token)
+    }
+
+    /// Exchange a signed service account JWT for an access token
+    async fn request_token(&self, key: &ServiceAccountKey) -> Result<(String, Duration)> {
+        let now = chrono::Utc::now().timestamp();
+        let claims = serde_json::json!({
+            "iss": key.client_email,
+            "scope": READ_ONLY_SCOPE,
+            "aud": key.token_uri,
+            "iat": now,
+            "exp": now + TOKEN_LIFETIME.as_secs() as i64,
+        });
+        let signing_key = jsonwebtoken::EncodingKey::from_rsa_pem(key.private_key.as_bytes())
+            .map_err(|e| AppError::Configuration(format!("Invalid GCS private key: {}", e)))?;
+        let assertion = jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+            &claims,
+            &signing_key,
+        )
+        .map_err(|e| AppError::Internal(format!("Failed to sign GCS token request: {}", e)))?;
+
+        let body = format!(
+            "grant_type={}&assertion={}",
+            uri_encode("urn:ietf:params:oauth:grant-type:jwt-bearer", false),
+            assertion
+        );
+        let response = self
+            .http
+            .post(&key.token_uri)
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| AppError::External(format!("GCS token request failed: {}", e)))?;
+        let response: Value = check(response, "GCS token request")
+            .await?
+            .json()
+            .await
+            .map_err(|e| AppError::External(format!("Invalid GCS token response: {}", e)))?;
+
+        let token = response["access_token"].as_str().ok_or_else(|| {
+            AppError::External("GCS token response has no access_token".to_string())
+        })?;
+        let lifetime = response["expires_in"]
+            .as_u64()
+            .map_or(TOKEN_LIFETIME, Duration::from_secs);
+        Ok((token.to_string(), lifetime))
+    }
+
+    /// Send an authorized GET request
+    async fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<reqwest::Response> {
+        let response = self
+            .http
+            .get(url)
+            .query(query)
+            .bearer_auth(self.access_token().await?)
+            .send()
+            .await
+            .map_err(|e| AppError::External(format!("GCS request failed: {}", e)))?;
+        check(response, "GCS request").await
+    }
+}
+
+/// Turn an unsuccessful response into an error
+async fn check(response: reqwest::Response, what: &str) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let text = response.text().await.unwrap_or_default();
+    Err(AppError::External(format!(
+        "{} failed ({}): {}",
+        what, status, text
+    )))
+}
+
+#[async_trait]
+impl DocumentSource for GcsSource {
+    fn uri(&self) -> String {
+        format!("gs://{}/{}", self.bucket, self.prefix)
+    }
+
+    fn object_uri(&self, key: &str) -> String {
+        format!("gs://{}/{}", self.bucket, key)
+    }
+
+    async fn list(&self, cursor: Option<&str>) -> Result<SourcePage> {
+        let url = format!(
+            "{}/storage/v1/b/{}/o",
+            self.endpoint,
+            uri_encode(&self.bucket, false)
+        );
+        let mut query = vec![("prefix", self.prefix.as_str())];
+        if let Some(cursor) = cursor {
+            query.push(("pageToken", cursor));
+        }
+
+        let listing: ObjectList = self
+            .get(&url, &query)
+            .await?
+            .json()
+            .await
+            .map_err(|e| AppError::External(format!("Invalid GCS listing: {}", e)))?;
+
+        Ok(SourcePage {
+            objects: listing
+                .items
+                .into_iter()
+                .map(|o| SourceObject {
+                    key: o.name,
+                    version: o.generation,
+                    size: o.size.parse().unwrap_or_default(),
+                })
+                .collect(),
+            next_cursor: listing.next_page_token,
+        })
+    }
+
+    async fn fetch(&self, key: &str) -> Result<Vec<u8>> {
+        let url = format!(
+            "{}/storage/v1/b/{}/o/{}",
+            self.endpoint,
+            uri_encode(&self.bucket, false),
+            uri_encode(key, false)
+        );
+        let bytes = self
+            .get(&url, &[("alt", "media")])
+            .await?
+            .bytes()
+            .await
+            .map_err(|e| AppError::External(format!("GCS download of '{}' failed: {}", key, e)))?;
+        Ok(bytes.to_vec())
+    }
+}
+
+/// `objects.list` response body
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ObjectList {
+    #[serde(default)]
+    items: Vec<ListedObject>,
+    #[serde(default)]
+    next_page_token: Option<String>,
+}
+
+/// Sizes and generations are int64 values, which the JSON API sends as strings
+#[derive(Debug, Deserialize)]
+struct ListedObject {
+    name: String,
+    #[serde(default)]
+    generation: String,
+    #[serde(default)]
+    size: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_list_and_fetch_with_token() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/storage/v1/b/corpus/o"))
+            .and(query_param("prefix", "docs/"))
+            .and(query_param("pageToken", "page-2"))
+            .and(header("authorization", "Bearer ya29.test"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [
+                    {"name": "docs/guide.md", "generation": "1700000000000001", "size": "42"}
+                ]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/storage/v1/b/corpus/o/docs%2Fguide.md"))
+            .and(query_param("alt", "media"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("# Guide"))
+            .mount(&server)
+            .await;
+
+        let source = GcsSource::new(
+            &server.uri(),
+            "corpus",
+            "docs/",
+            GcsAuth::Token("ya29.test".to_string()),
+        );
+        assert_eq!(source.uri(), "gs://corpus/docs/");
+
+        let page = source.list(Some("page-2")).await.unwrap();
+        assert!(page.next_cursor.is_none());
+        assert_eq!(
+            page.objects,
+            vec![SourceObject {
+                key: "docs/guide.md".to_string(),
+                version: "1700000000000001".to_string(),
+                size: 42,
+            }]
+        );
+
+        assert_eq!(source.fetch("docs/guide.md").await.unwrap(), b"# Guide");
+    }
+}
@@ -0,0 +1,387 @@
+//! GitHub repository document source.
+//!
+//! Keeps a shallow bare clone of the repository under
+//! `rag.github.checkout_dir` and fetches the tracked ref before every full
+//! listing. Files are listed with `git ls-tree` and their blob SHAs are
+//! their versions, so after a push only the files that changed are
+//! re-ingested and deleted files are removed. Documents are tagged with
+//! their path, language and the commit they were read at.
+
+use super::{
+    object_text, DocumentSource, SourceDocument, SourceLocation, SourceObject, SourcePage,
+};
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::GitHubSourceConfig;
+use async_trait::async_trait;
+use base64::Engine;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+
+/// Locks serializing fetches into the same checkout, keyed by its path
+static CHECKOUT_LOCKS: LazyLock<Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn checkout_lock(path: &Path) -> Arc<tokio::sync::Mutex<()>> {
+    CHECKOUT_LOCKS
+        .lock()
+        .entry(path.to_path_buf())
+        .or_default()
+        .clone()
+}
+
+/// Lists and fetches the files of a GitHub repository.
+pub struct GitHubSource {
+    endpoint: String,
+    owner: String,
+    repo: String,
+    git_ref: Option<String>,
+    prefix: String,
+    checkout: PathBuf,
+    token: Option<String>,
+    /// Commit of the last listing; files are read at this commit
+    commit: Mutex<Option<String>>,
+}
+
+impl GitHubSource {
+    /// Create a source
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - Git host URL (e.g., `https://github.com`)
+    /// * `repository` - `owner/repo`, with `@ref` to track a branch or tag
+    ///   other than the default branch
+    /// * `prefix` - Path prefix to list under
+    /// * `checkout_dir` - Directory the clone is kept in
+    /// * `token` - Access token for private repositories
+    pub fn new(
+        endpoint: &str,
+        repository: &str,
+        prefix: &str,
+        checkout_dir: &Path,
+        token: Option<String>,
+    ) -> Result<Self> {
+        let (repository, git_ref) = match repository.split_once('@') {
+            Some((repository, git_ref)) => (repository, Some(git_ref.to_string())),
+            None => (repository, None),
+        };
+        let (owner, repo) = repository.split_once('/').ok_or_else(|| {
+            AppError::InvalidInput(format!("'{}' is not an owner/repo name", repository))
+        })?;
+        let checkout_name = match &git_ref {
+            Some(git_ref) => format!("{}@{}.git", repo, git_ref),
+            None => format!("{}.git", repo),
+        };
+        Ok(Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            git_ref,
+            prefix: prefix.to_string(),
+            checkout: checkout_dir.join(owner).join(checkout_name),
+            token,
+            commit: Mutex::new(None),
+        })
+    }
+
+    /// Create a source from `[rag.github]`
+    ///
+    /// The token is optional; without it only public repositories can be
+    /// cloned.
+    pub fn from_config(config: &GitHubSourceConfig, location: &SourceLocation) -> Result<Self> {
+        let token = std::env::var(&config.token_env)
+            .ok()
+            .filter(|token| !token.is_empty());
+        Self::new(
+            &config.endpoint,
+            &location.bucket,
+            &location.prefix,
+            Path::new(&config.checkout_dir),
+            token,
+        )
+    }
+
+    /// `owner/repo`, as in push webhook payloads
+    pub fn full_name(&self) -> String {
+        format!("{}/{}", self.owner, self.repo)
+    }
+
+    /// Run git, in the checkout unless `in_checkout` is unset, and return stdout
+    async fn git(&self, in_checkout: bool, args: &[&str]) -> Result<Vec<u8>> {
+        let mut command = tokio::process::Command::new("git");
+        if in_checkout {
+            command.arg("--git-dir").arg(&self.checkout);
+        }
+        command
+            .args(["-c", "gc.auto=0"])
+            .args(args)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .kill_on_drop(true);
+        if let Some(token) = &self.token {
+            // Passed through the environment to keep it out of the process list
+            let credentials = base64::engine::general_purpose::STANDARD
+                .encode(format!("x-access-token:{}", token));
+            command
+                .env("GIT_CONFIG_COUNT", "1")
+                .env("GIT_CONFIG_KEY_0", "http.extraHeader")
+                .env(
+                    "GIT_CONFIG_VALUE_0",
+                    format!("Authorization: Basic {}", credentials),
+                );
+        }
+
+        let output = command
+            .output()
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to run git: {}", e)))?;
+        if !output.status.success() {
+            return Err(AppError::External(format!(
+                "git {} failed for {}: {}",
+                args.first().copied().unwrap_or_default(),
+                self.full_name(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(output.stdout)
+    }
+
+    /// Clone the repository, or fetch the tracked ref into an existing
+    /// clone, and return the commit it points at
+    async fn sync(&self) -> Result<String> {
+        let lock = checkout_lock(&self.checkout);
+        let _guard = lock.lock().await;
+
+        let head = if self.checkout.join("HEAD").exists() {
+            let git_ref = self.git_ref.as_deref().unwrap_or("HEAD");
+            self.git(true, &["fetch", "--depth", "1", "origin", git_ref])
+                .await?;
+            "FETCH_HEAD"
+        } else {
+            if let Some(parent) = self.checkout.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let url = format!("{}/{}/{}.git", self.endpoint, self.owner, self.repo);
+            let checkout = self.checkout.to_string_lossy();
+            let mut args = vec!["clone", "--bare", "--depth", "1"];
+            if let Some(git_ref) = &self.git_ref {
+                args.extend(["--branch", git_ref.as_str()]);
+            }
+            args.extend(["--", url.as_str(), checkout.as_ref()]);
+            self.git(false, &args).await?;
+            "HEAD"
+        };
+
+        let commit = self.git(true, &["rev-parse", "--verify", head]).await?;
+        Ok(String::from_utf8_lossy(&commit).trim().to_string())
+    }
+
+    /// Commit files are read at, syncing if nothing has been listed yet
+    async fn commit(&self) -> Result<String> {
+        if let Some(commit) = self.commit.lock().clone() {
+            return Ok(commit);
+        }
+        let commit = self.sync().await?;
+        *self.commit.lock() = Some(commit.clone());
+        Ok(commit)
+    }
+}
+
+#[async_trait]
+impl DocumentSource for GitHubSource {
+    fn uri(&self) -> String {
+        let repository = match &self.git_ref {
+            Some(git_ref) => format!("{}@{}", self.full_name(), git_ref),
+            None => self.full_name(),
+        };
+        format!("github://{}/{}", repository, self.prefix)
+    }
+
+    fn object_uri(&self, key: &str) -> String {
+        let commit = self.commit.lock().clone();
+        let tree = commit
+            .as_deref()
+            .or(self.git_ref.as_deref())
+            .unwrap_or("HEAD");
+        format!(
+            "{}/{}/{}/blob/{}/{}",
+            self.endpoint, self.owner, self.repo, tree, key
+        )
+    }
+
+    /// Every file is listed on one page, fetched fresh from the remote
+    async fn list(&self, _cursor: Option<&str>) -> Result<SourcePage> {
+        let commit = self.sync().await?;
+        *self.commit.lock() = Some(commit.clone());
+
+        let listing = self
+            .git(true, &["ls-tree", "-r", "-l", "-z", &commit])
+            .await?;
+        let objects = listing
+            .split(|byte| *byte == 0)
+            .filter_map(|entry| {
+                // <mode> <type> <object> <size>\t<path>
+                let entry = std::str::from_utf8(entry).ok()?;
+                let (meta, path) = entry.split_once('\t')?;
+                let mut fields = meta.split_whitespace();
+                let mode = fields.next()?;
+                let (kind, blob, size) = (fields.next()?, fields.next()?, fields.next()?);
+                // Skip submodules and symlinks
+                if kind != "blob" || mode == "120000" || !path.starts_with(&self.prefix) {
+                    return None;
+                }
+                Some(SourceObject {
+                    key: path.to_string(),
+                    version: blob.to_string(),
+                    size: size.parse().ok()?,
+                })
+            })
+            .collect();
+
+        Ok(SourcePage {
+            objects,
+            next_cursor: None,
+        })
+    }
+
+    async fn fetch(&self, key: &str) -> Result<Vec<u8>> {
+        let commit = self.commit().await?;
+        self.git(true, &["cat-file", "blob", &format!("{}:{}", commit, key)])
+            .await
+    }
+
+    async fn fetch_document(&self, key: &str) -> Result<Option<SourceDocument>> {
+        let Some(text) = object_text(self.fetch(key).await?) else {
+            return Ok(None);
+        };
+        if text.trim().is_empty() {
+            return Ok(None);
+        }
+        let commit = self.commit().await?;
+
+        let mut tags = vec![format!("path:{}", key)];
+        if let Some(language) = language_for_path(key) {
+            tags.push(format!("language:{}", language));
+        }
+        tags.push(format!("commit:{}", commit));
+
+        Ok(Some(SourceDocument {
+            text,
+            title: key.to_string(),
+            url: self.object_uri(key),
+            tags,
+        }))
+    }
+}
+
+/// Language of a source file, from its extension or file name
+pub fn language_for_path(path: &str) -> Option<&'static str> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    match name {
+        "Dockerfile" | "Containerfile" => return Some("dockerfile"),
+        "Makefile" | "GNUmakefile" => return Some("makefile"),
+        _ => {}
+    }
+    let (_, extension) = name.rsplit_once('.')?;
+    let language = match extension.to_ascii_lowercase().as_str() {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" | "jsx" => "javascript",
+        "ts" | "mts" | "cts" | "tsx" => "typescript",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "scala" => "scala",
+        "swift" => "swift",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "ex" | "exs" => "elixir",
+        "hs" => "haskell",
+        "lua" => "lua",
+        "sh" | "bash" | "zsh" => "shell",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" | "scss" | "sass" => "css",
+        "md" | "markdown" => "markdown",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "json" => "json",
+        "proto" => "protobuf",
+        _ => return None,
+    };
+    Some(language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .current_dir(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn test_language_for_path() {
+        assert_eq!(language_for_path("src/main.rs"), Some("rust"));
+        assert_eq!(language_for_path("web/App.TSX"), Some("typescript"));
+        assert_eq!(language_for_path("deploy/Dockerfile"), Some("dockerfile"));
+        assert_eq!(language_for_path("LICENSE"), None);
+    }
+
+    #[tokio::test]
+    async fn test_list_and_fetch_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote/dirmacs/ares.git");
+        std::fs::create_dir_all(remote.join("src")).unwrap();
+        git(&remote, &["init", "-q", "-b", "main"]);
+        std::fs::write(remote.join("src/lib.rs"), "pub fn one() {}\n").unwrap();
+        std::fs::write(remote.join("src/util.py"), "def two():\n    pass\n").unwrap();
+        std::fs::write(remote.join("README.md"), "# Ares\n").unwrap();
+        git(&remote, &["add", "."]);
+        git(&remote, &["commit", "-q", "-m", "initial"]);
+
+        let endpoint = format!("file://{}", dir.path().join("remote").display());
+        let source = GitHubSource::new(
+            &endpoint,
+            "dirmacs/ares",
+            "src/",
+            &dir.path().join("checkouts"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(source.uri(), "github://dirmacs/ares/src/");
+
+        let first = source.list(None).await.unwrap();
+        assert!(first.next_cursor.is_none());
+        let keys: Vec<&str> = first.objects.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(keys, vec!["src/lib.rs", "src/util.py"]);
+        assert_eq!(first.objects[0].size, 16);
+
+        let document = source.fetch_document("src/lib.rs").await.unwrap().unwrap();
+        assert_eq!(document.text, "pub fn one() {}\n");
+        assert_eq!(document.title, "src/lib.rs");
+        assert_eq!(document.tags[..2], ["path:src/lib.rs", "language:rust"]);
+        assert!(document.tags[2].starts_with("commit:"));
+        assert!(document
+            .url
+            .starts_with(&format!("{}/dirmacs/ares/blob/", endpoint)));
+
+        // A push that touches one file only changes that file's version
+        std::fs::write(remote.join("src/lib.rs"), "pub fn one() -> u8 { 1 }\n").unwrap();
+        git(&remote, &["commit", "-q", "-am", "change"]);
+        let second = source.list(None).await.unwrap();
+        assert_ne!(first.objects[0].version, second.objects[0].version);
+        assert_eq!(first.objects[1].version, second.objects[1].version);
+        let document = source.fetch_document("src/lib.rs").await.unwrap().unwrap();
+        assert_eq!(document.text, "pub fn one() -> u8 { 1 }\n");
+    }
+}
@@ -0,0 +1,455 @@
+//! Document sources for bulk ingestion.
+//!
+//! A [`DocumentSource`] lists the objects under a location and fetches their
+//! contents, so large corpora can be ingested straight from where they live
+//! instead of being uploaded through the HTTP API. Sources are addressed by
+//! URI:
+//!
+//! - `s3://bucket/prefix` - Amazon S3 or an S3-compatible service ([`S3Source`])
+//! - `gs://bucket/prefix` - Google Cloud Storage ([`GcsSource`])
+//! - `notion://` or `notion://database_id` - Notion pages shared with the
+//!   integration, or the pages of one database ([`NotionSource`])
+//! - `confluence://SPACE` - the pages of a Confluence space ([`ConfluenceSource`])
+//! - `github://owner/repo[@ref]/prefix` - files of a GitHub repository
+//!   ([`GitHubSource`])
+//!
+//! Credentials and endpoints come from `[rag.s3]`, `[rag.gcs]`,
+//! `[rag.notion]`, `[rag.confluence]` and `[rag.github]`. For buckets and
+//! repositories, a [`KeyFilter`] narrows a listing down with a glob matched
+//! against keys relative to the prefix.
+
+mod confluence;
+mod gcs;
+mod github;
+mod notion;
+mod s3;
+
+pub use confluence::{storage_to_text, ConfluenceAuth, ConfluenceSource};
+pub use gcs::{GcsAuth, GcsSource, ServiceAccountKey};
+pub use github::{language_for_path, GitHubSource};
+pub use notion::NotionSource;
+pub use s3::{S3Credentials, S3Source};
+
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::RagConfig;
+use async_trait::async_trait;
+use globset::{Glob, GlobMatcher};
+use std::fmt;
+use std::str::FromStr;
+
+/// An object a source can fetch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceObject {
+    /// Object key, unique within the source
+    pub key: String,
+    /// Opaque version (ETag, generation); changes whenever the content does
+    pub version: String,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// One page of a source listing.
+#[derive(Debug, Clone, Default)]
+pub struct SourcePage {
+    /// Objects on this page
+    pub objects: Vec<SourceObject>,
+    /// Cursor for the next page; `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// A fetched object ready for ingestion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceDocument {
+    /// Document text
+    pub text: String,
+    /// Document title
+    pub title: String,
+    /// Where the document can be viewed
+    pub url: String,
+    /// Tags derived from the document's place in the source
+    pub tags: Vec<String>,
+}
+
+/// A location documents can be listed and fetched from.
+#[async_trait]
+pub trait DocumentSource: Send + Sync {
+    /// URI identifying the source (e.g., `s3://bucket/prefix`)
+    fn uri(&self) -> String;
+
+    /// URI of one object (e.g., `s3://bucket/docs/guide.md`)
+    fn object_uri(&self, key: &str) -> String;
+
+    /// List a page of objects, starting after `cursor` (the first page when `None`)
+    async fn list(&self, cursor: Option<&str>) -> Result<SourcePage>;
+
+    /// Fetch an object's content
+    async fn fetch(&self, key: &str) -> Result<Vec<u8>>;
+
+    /// Fetch an object as a document, or `None` if it holds no text
+    ///
+    /// The default decodes the fetched content and titles the document
+    /// with the last segment of its key.
+    async fn fetch_document(&self, key: &str) -> Result<Option<SourceDocument>> {
+        let Some(text) = object_text(self.fetch(key).await?) else {
+            return Ok(None);
+        };
+        Ok(Some(SourceDocument {
+            text,
+            title: key.rsplit('/').next().unwrap_or(key).to_string(),
+            url: self.object_uri(key),
+            tags: Vec::new(),
+        }))
+    }
+}
+
+// ============================================================================
+// Source Locations
+// ============================================================================
+
+/// Service a location lives in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceScheme {
+    /// Amazon S3 or an S3-compatible service
+    S3,
+    /// Google Cloud Storage
+    Gcs,
+    /// Notion workspace
+    Notion,
+    /// Confluence site
+    Confluence,
+    /// GitHub repository
+    GitHub,
+}
+
+/// A parsed source URI: scheme, bucket and key prefix.
+///
+/// For Notion the bucket is the database ID (empty for every shared page),
+/// and for Confluence the space key; neither has a prefix. For GitHub it is
+/// `owner/repo`, with `@ref` when a branch or tag is given (refs cannot
+/// contain `/`, which starts the prefix).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Service
+    pub scheme: SourceScheme,
+    /// Bucket name, Notion database ID, Confluence space key or GitHub repository
+    pub bucket: String,
+    /// Key prefix; empty for the whole bucket
+    pub prefix: String,
+}
+
+impl FromStr for SourceLocation {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (scheme, rest) = if let Some(rest) = s.strip_prefix("s3://") {
+            (SourceScheme::S3, rest)
+        } else if let Some(rest) = s.strip_prefix("gs://").or_else(|| s.strip_prefix("gcs://")) {
+            (SourceScheme::Gcs, rest)
+        } else if let Some(rest) = s.strip_prefix("notion://") {
+            (SourceScheme::Notion, rest)
+        } else if let Some(rest) = s.strip_prefix("confluence://") {
+            (SourceScheme::Confluence, rest)
+        } else if let Some(rest) = s.strip_prefix("github://") {
+            (SourceScheme::GitHub, rest)
+        } else {
+            return Err(AppError::InvalidInput(format!(
+                "Unsupported source '{}'. Use s3://bucket/prefix, gs://bucket/prefix, \
+                 notion://[database_id], confluence://SPACE or github://owner/repo[@ref]/prefix",
+                s
+            )));
+        };
+
+        if scheme == SourceScheme::GitHub {
+            let mut segments = rest.splitn(3, '/');
+            let owner = segments.next().unwrap_or_default();
+            let repo = segments.next().unwrap_or_default();
+            let prefix = segments.next().unwrap_or_default();
+            let (name, git_ref) = match repo.split_once('@') {
+                Some((name, git_ref)) => (name, Some(git_ref)),
+                None => (repo, None),
+            };
+            let valid_name = |part: &str| {
+                !part.is_empty()
+                    && !part.starts_with('.')
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            };
+            let valid_ref = |part: &str| {
+                !part.is_empty()
+                    && !part.starts_with('-')
+                    && !part.contains("..")
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            };
+            if !valid_name(owner) || !valid_name(name) || !git_ref.is_none_or(valid_ref) {
+                return Err(AppError::InvalidInput(format!(
+                    "Source '{}' is not a GitHub repository (github://owner/repo[@ref]/prefix)",
+                    s
+                )));
+            }
+            return Ok(Self {
+                scheme,
+                bucket: repo_bucket(owner, name, git_ref),
+                prefix: prefix.to_string(),
+            });
+        }
+
+        if !scheme.has_prefix() {
+            let id = rest.trim_end_matches('/');
+            if id.contains('/') {
+                return Err(AppError::InvalidInput(format!(
+                    "Source '{}' takes no path",
+                    s
+                )));
+            }
+            if id.is_empty() && scheme == SourceScheme::Confluence {
+                return Err(AppError::InvalidInput(format!(
+                    "Source '{}' has no space key",
+                    s
+                )));
+            }
+            return Ok(Self {
+                scheme,
+                bucket: id.to_string(),
+                prefix: String::new(),
+            });
+        }
+
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(AppError::InvalidInput(format!(
+                "Source '{}' has no bucket",
+                s
+            )));
+        }
+        Ok(Self {
+            scheme,
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+        })
+    }
+}
+
+impl SourceLocation {
+    /// URI of an object in the bucket (e.g., `s3://bucket/docs/guide.md`)
+    pub fn object_uri(&self, key: &str) -> String {
+        format!("{}://{}/{}", self.scheme.as_str(), self.bucket, key)
+    }
+}
+
+impl SourceScheme {
+    fn as_str(self) -> &'static str {
+        match self {
+            SourceScheme::S3 => "s3",
+            SourceScheme::Gcs => "gs",
+            SourceScheme::Notion => "notion",
+            SourceScheme::Confluence => "confluence",
+            SourceScheme::GitHub => "github",
+        }
+    }
+
+    /// Whether locations have a key prefix that globs are matched under
+    /// (buckets and repositories)
+    pub fn has_prefix(self) -> bool {
+        matches!(
+            self,
+            SourceScheme::S3 | SourceScheme::Gcs | SourceScheme::GitHub
+        )
+    }
+}
+
+/// Bucket of a GitHub location: `owner/repo`, plus `@ref` when given
+fn repo_bucket(owner: &str, repo: &str, git_ref: Option<&str>) -> String {
+    match git_ref {
+        Some(git_ref) => format!("{}/{}@{}", owner, repo, git_ref),
+        None => format!("{}/{}", owner, repo),
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.scheme.has_prefix() {
+            f.write_str(&self.object_uri(&self.prefix))
+        } else {
+            write!(f, "{}://{}", self.scheme.as_str(), self.bucket)
+        }
+    }
+}
+
+/// Create the source for a location, with credentials from its `[rag.*]` section
+pub fn create_source(
+    config: &RagConfig,
+    location: &SourceLocation,
+) -> Result<Box<dyn DocumentSource>> {
+    match location.scheme {
+        SourceScheme::S3 => Ok(Box::new(S3Source::from_config(&config.s3, location)?)),
+        SourceScheme::Gcs => Ok(Box::new(GcsSource::from_config(&config.gcs, location)?)),
+        SourceScheme::Notion => Ok(Box::new(NotionSource::from_config(
+            &config.notion,
+            location,
+        )?)),
+        SourceScheme::Confluence => Ok(Box::new(ConfluenceSource::from_config(
+            &config.confluence,
+            location,
+        )?)),
+        SourceScheme::GitHub => Ok(Box::new(GitHubSource::from_config(
+            &config.github,
+            location,
+        )?)),
+    }
+}
+
+// ============================================================================
+// Key Filtering
+// ============================================================================
+
+/// Selects the objects of a listing to ingest.
+///
+/// The glob is matched against keys relative to the source prefix, so with
+/// prefix `docs/` the glob `**/*.md` selects `docs/guides/setup.md`.
+#[derive(Debug, Clone)]
+pub struct KeyFilter {
+    prefix: String,
+    glob: Option<GlobMatcher>,
+}
+
+impl KeyFilter {
+    /// Create a filter for keys under `prefix`, optionally matching `glob`
+    pub fn new(prefix: &str, glob: Option<&str>) -> Result<Self> {
+        let glob = glob
+            .map(|pattern| {
+                Glob::new(pattern)
+                    .map(|g| g.compile_matcher())
+                    .map_err(|e| {
+                        AppError::InvalidInput(format!("Invalid glob '{}': {}", pattern, e))
+                    })
+            })
+            .transpose()?;
+        Ok(Self {
+            prefix: prefix.to_string(),
+            glob,
+        })
+    }
+
+    /// Whether an object should be ingested
+    pub fn matches(&self, key: &str) -> bool {
+        // Directory placeholders hold no content
+        if key.ends_with('/') {
+            return false;
+        }
+        let Some(relative) = key.strip_prefix(&self.prefix) else {
+            return false;
+        };
+        self.glob
+            .as_ref()
+            .is_none_or(|glob| glob.is_match(relative.trim_start_matches('/')))
+    }
+}
+
+/// Decode an object as text, or `None` if it is not UTF-8 text
+pub fn object_text(bytes: Vec<u8>) -> Option<String> {
+    let text = String::from_utf8(bytes).ok()?;
+    // NUL bytes mark binary formats that happen to decode
+    (!text.contains('\0')).then_some(text)
+}
+
+/// Percent-encode a string as in RFC 3986, leaving `/` when `keep_slash` is set
+pub(crate) fn uri_encode(s: &str, keep_slash: bool) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if keep_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_source_location() {
+        let location: SourceLocation = "s3://corpus/docs/2024/".parse().unwrap();
+        assert_eq!(location.scheme, SourceScheme::S3);
+        assert_eq!(location.bucket, "corpus");
+        assert_eq!(location.prefix, "docs/2024/");
+        assert_eq!(location.to_string(), "s3://corpus/docs/2024/");
+        assert_eq!(
+            location.object_uri("docs/2024/q1.md"),
+            "s3://corpus/docs/2024/q1.md"
+        );
+
+        let location: SourceLocation = "gs://corpus".parse().unwrap();
+        assert_eq!(location.scheme, SourceScheme::Gcs);
+        assert_eq!(location.prefix, "");
+
+        assert!("https://corpus/docs".parse::<SourceLocation>().is_err());
+        assert!("s3:///docs".parse::<SourceLocation>().is_err());
+    }
+
+    #[test]
+    fn test_parse_workspace_location() {
+        let location: SourceLocation = "notion://".parse().unwrap();
+        assert_eq!(location.scheme, SourceScheme::Notion);
+        assert_eq!(location.bucket, "");
+        assert_eq!(location.to_string(), "notion://");
+
+        let location: SourceLocation = "confluence://ENG/".parse().unwrap();
+        assert_eq!(location.scheme, SourceScheme::Confluence);
+        assert_eq!(location.bucket, "ENG");
+        assert_eq!(location.to_string(), "confluence://ENG");
+        assert!(!location.scheme.has_prefix());
+
+        assert!("confluence://".parse::<SourceLocation>().is_err());
+        assert!("notion://db/pages".parse::<SourceLocation>().is_err());
+    }
+
+    #[test]
+    fn test_parse_github_location() {
+        let location: SourceLocation = "github://dirmacs/ares@v1.2/src/".parse().unwrap();
+        assert_eq!(location.scheme, SourceScheme::GitHub);
+        assert_eq!(location.bucket, "dirmacs/ares@v1.2");
+        assert_eq!(location.prefix, "src/");
+        assert_eq!(location.to_string(), "github://dirmacs/ares@v1.2/src/");
+
+        let location: SourceLocation = "github://dirmacs/ares".parse().unwrap();
+        assert_eq!(location.bucket, "dirmacs/ares");
+        assert_eq!(location.prefix, "");
+
+        assert!("github://dirmacs".parse::<SourceLocation>().is_err());
+        assert!("github://dirmacs/../etc".parse::<SourceLocation>().is_err());
+        assert!("github://dirmacs/ares@--upload-pack"
+            .parse::<SourceLocation>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_key_filter_matches_relative_glob() {
+        let filter = KeyFilter::new("docs/", Some("**/*.md")).unwrap();
+        assert!(filter.matches("docs/guides/setup.md"));
+        assert!(filter.matches("docs/readme.md"));
+        assert!(!filter.matches("docs/logo.png"));
+        assert!(!filter.matches("other/readme.md"));
+        assert!(!filter.matches("docs/guides/"));
+
+        assert!(KeyFilter::new("", None).unwrap().matches("any/key.bin"));
+        assert!(KeyFilter::new("", Some("[")).is_err());
+    }
+
+    #[test]
+    fn test_object_text_and_uri_encode() {
+        assert_eq!(object_text(b"hello".to_vec()).as_deref(), Some("hello"));
+        assert!(object_text(vec![0xff, 0xfe]).is_none());
+        assert!(object_text(b"PK\0\x03".to_vec()).is_none());
+
+        assert_eq!(uri_encode("a b/c+d", true), "a%20b/c%2Bd");
+        assert_eq!(uri_encode("a/b", false), "a%2Fb");
+    }
+}
@@ -0,0 +1,291 @@
+//! Chunk-level relevance feedback.
+//!
+//! Users vote retrieved chunks up or down. Votes are kept per collection in
+//! a JSON file next to the vector data and feed back into search: after the
+//! strategy has ranked its candidates, each chunk's score is nudged by its
+//! feedback before the results are cut to the limit, so chunks that keep
+//! helping rise and chunks that keep missing sink. Chunks with consistently
+//! poor feedback are reported for curation.
+//!
+//! Feedback is reset when a chunk's object is re-ingested with new content
+//! or removed, since chunk IDs are reused across versions.
+
+use crate::types::{AppError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use utoipa::ToSchema;
+
+/// Votes a chunk needs before its feedback counts fully; fewer are damped
+const PRIOR_VOTES: f32 = 2.0;
+
+/// Queries kept per chunk from its most recent down votes
+const MAX_QUERIES: usize = 5;
+
+/// Serializes read-modify-write cycles on feedback files
+static FEEDBACK_LOCK: LazyLock<tokio::sync::Mutex<()>> = LazyLock::new(Default::default);
+
+/// A vote on a retrieved chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedbackVote {
+    /// The chunk helped answer the query
+    Up,
+    /// The chunk was irrelevant or wrong for the query
+    Down,
+}
+
+/// Votes recorded for one chunk.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChunkFeedback {
+    /// Up votes
+    pub up: u32,
+    /// Down votes
+    pub down: u32,
+    /// Queries of the most recent down votes, newest last
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub down_queries: Vec<String>,
+    /// When the last vote was cast
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl ChunkFeedback {
+    /// Net feedback from -1 (always down) to 1 (always up)
+    ///
+    /// Damped towards 0 while there are few votes, so a single vote only
+    /// moves a chunk a little.
+    pub fn score(&self) -> f32 {
+        let (up, down) = (self.up as f32, self.down as f32);
+        (up - down) / (up + down + PRIOR_VOTES)
+    }
+
+    /// Total votes
+    pub fn votes(&self) -> u32 {
+        self.up + self.down
+    }
+}
+
+/// Feedback for every voted chunk of a collection, keyed by chunk ID.
+pub type CollectionFeedback = BTreeMap<String, ChunkFeedback>;
+
+/// Nudge ranked `(chunk_id, score)` candidates by their feedback and re-sort
+///
+/// A chunk's score moves by up to `weight` of its magnitude: with the
+/// default 0.2, a chunk everyone votes down keeps 80% of its score. A
+/// weight of 0 leaves the ranking untouched.
+pub fn apply(
+    mut ranked: Vec<(String, f32)>,
+    feedback: &CollectionFeedback,
+    weight: f32,
+) -> Vec<(String, f32)> {
+    if weight == 0.0 || feedback.is_empty() {
+        return ranked;
+    }
+    for (id, score) in &mut ranked {
+        if let Some(chunk) = feedback.get(id) {
+            *score += score.abs() * weight * chunk.score();
+        }
+    }
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    ranked
+}
+
+/// Chunks whose feedback is consistently poor, worst first
+///
+/// A chunk qualifies with at least `min_votes` votes and a score at or
+/// below `max_score`.
+pub fn poorly_rated(
+    feedback: &CollectionFeedback,
+    min_votes: u32,
+    max_score: f32,
+) -> Vec<(&str, &ChunkFeedback)> {
+    let mut chunks: Vec<(&str, &ChunkFeedback)> = feedback
+        .iter()
+        .filter(|(_, chunk)| chunk.votes() >= min_votes && chunk.score() <= max_score)
+        .map(|(id, chunk)| (id.as_str(), chunk))
+        .collect();
+    chunks.sort_by(|(_, a), (_, b)| {
+        a.score()
+            .partial_cmp(&b.score())
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| b.down.cmp(&a.down))
+    });
+    chunks
+}
+
+/// Keeps each collection's feedback in a JSON file.
+pub struct FeedbackStore {
+    dir: PathBuf,
+}
+
+impl FeedbackStore {
+    /// Create a store keeping feedback files in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Collection names may hold any character; hash them into file names
+    fn path(&self, collection: &str) -> PathBuf {
+        let digest = hex::encode(Sha256::digest(collection.as_bytes()));
+        self.dir.join(format!("{}.json", &digest[..32]))
+    }
+
+    /// Load a collection's feedback (empty when nothing was recorded)
+    pub async fn load(&self, collection: &str) -> Result<CollectionFeedback> {
+        match tokio::fs::read(self.path(collection)).await {
+            Ok(json) => serde_json::from_slice(&json).map_err(|e| {
+                AppError::Internal(format!("Corrupt feedback for {}: {}", collection, e))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(CollectionFeedback::new()),
+            Err(e) => Err(AppError::Internal(format!(
+                "Failed to load feedback for {}: {}",
+                collection, e
+            ))),
+        }
+    }
+
+    async fn save(&self, collection: &str, feedback: &CollectionFeedback) -> Result<()> {
+        let io = |e: std::io::Error| AppError::Internal(format!("Failed to save feedback: {}", e));
+        let path = self.path(collection);
+        if feedback.is_empty() {
+            return match tokio::fs::remove_file(&path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(io(e)),
+                _ => Ok(()),
+            };
+        }
+        tokio::fs::create_dir_all(&self.dir).await.map_err(io)?;
+
+        let json = serde_json::to_vec_pretty(feedback)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize feedback: {}", e)))?;
+        // Write then rename so a crash never leaves a truncated file
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, json).await.map_err(io)?;
+        tokio::fs::rename(&tmp, path).await.map_err(io)
+    }
+
+    /// Record a vote on a chunk and return its updated feedback
+    pub async fn record(
+        &self,
+        collection: &str,
+        chunk_id: &str,
+        vote: FeedbackVote,
+        query: Option<&str>,
+    ) -> Result<ChunkFeedback> {
+        let _guard = FEEDBACK_LOCK.lock().await;
+        let mut feedback = self.load(collection).await?;
+        let chunk = feedback.entry(chunk_id.to_string()).or_default();
+        match vote {
+            FeedbackVote::Up => chunk.up += 1,
+            FeedbackVote::Down => {
+                chunk.down += 1;
+                if let Some(query) = query.map(str::trim).filter(|q| !q.is_empty()) {
+                    chunk.down_queries.push(query.to_string());
+                    let excess = chunk.down_queries.len().saturating_sub(MAX_QUERIES);
+                    chunk.down_queries.drain(..excess);
+                }
+            }
+        }
+        chunk.updated_at = Some(Utc::now());
+        let chunk = chunk.clone();
+        self.save(collection, &feedback).await?;
+        Ok(chunk)
+    }
+
+    /// Drop the feedback of chunks whose IDs start with `id_prefix`
+    pub async fn forget(&self, collection: &str, id_prefix: &str) -> Result<()> {
+        let _guard = FEEDBACK_LOCK.lock().await;
+        let mut feedback = self.load(collection).await?;
+        let before = feedback.len();
+        feedback.retain(|id, _| !id.starts_with(id_prefix));
+        if feedback.len() == before {
+            return Ok(());
+        }
+        self.save(collection, &feedback).await
+    }
+
+    /// Drop all of a collection's feedback
+    pub async fn clear(&self, collection: &str) -> Result<()> {
+        let _guard = FEEDBACK_LOCK.lock().await;
+        self.save(collection, &CollectionFeedback::new()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn votes(up: u32, down: u32) -> ChunkFeedback {
+        ChunkFeedback {
+            up,
+            down,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_apply_reorders_by_feedback() {
+        let ranked = vec![
+            ("a".to_string(), 0.80),
+            ("b".to_string(), 0.78),
+            ("c".to_string(), 0.50),
+        ];
+        let feedback: CollectionFeedback = [
+            ("a".to_string(), votes(0, 6)),
+            ("b".to_string(), votes(3, 0)),
+        ]
+        .into_iter()
+        .collect();
+
+        let adjusted = apply(ranked.clone(), &feedback, 0.2);
+        let ids: Vec<&str> = adjusted.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a", "c"]);
+        assert_eq!(apply(ranked.clone(), &feedback, 0.0), ranked);
+
+        // One vote barely moves a chunk
+        assert!((votes(0, 1).score() + 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_record_forget_and_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FeedbackStore::new(dir.path());
+        let collection = "user_1_docs";
+
+        for i in 0..7 {
+            let query = format!("query {}", i);
+            store
+                .record(collection, "doc_a_0", FeedbackVote::Down, Some(&query))
+                .await
+                .unwrap();
+        }
+        store
+            .record(collection, "doc_a_1", FeedbackVote::Up, None)
+            .await
+            .unwrap();
+        store
+            .record(collection, "doc_b_0", FeedbackVote::Down, None)
+            .await
+            .unwrap();
+
+        let feedback = store.load(collection).await.unwrap();
+        assert_eq!(feedback["doc_a_0"].down, 7);
+        assert_eq!(
+            feedback["doc_a_0"].down_queries,
+            vec!["query 2", "query 3", "query 4", "query 5", "query 6"]
+        );
+
+        let report = poorly_rated(&feedback, 3, -0.5);
+        let ids: Vec<&str> = report.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec!["doc_a_0"]);
+
+        store.forget(collection, "doc_a_").await.unwrap();
+        let feedback = store.load(collection).await.unwrap();
+        assert_eq!(feedback.keys().collect::<Vec<_>>(), vec!["doc_b_0"]);
+
+        store.clear(collection).await.unwrap();
+        assert!(store.load(collection).await.unwrap().is_empty());
+    }
+}
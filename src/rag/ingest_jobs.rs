@@ -0,0 +1,581 @@
+//! Background ingestion jobs.
+//!
+//! An ingestion job walks a [`DocumentSource`] page by page and ingests every
+//! matching text object into a collection. Progress is checkpointed to a
+//! JSON file after every object, recording the listing cursor and the
+//! version of each object ingested, so a job that is interrupted (server
+//! restart, cancellation, source error) can be resumed where it stopped.
+//! Resuming, or re-running over the same location, re-ingests only objects
+//! whose version changed; their previous chunks are replaced. A full pass
+//! over the source also removes the chunks of objects that disappeared from
+//! it, so jobs with a sync interval keep their collection in step with the
+//! source.
+
+use crate::llm::cancellation::CancellationToken;
+use crate::rag::connectors::{DocumentSource, KeyFilter, SourceDocument};
+use crate::types::{AppError, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use utoipa::ToSchema;
+
+/// Objects larger than this are skipped
+pub const MAX_OBJECT_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Most recent object errors kept on a job
+const MAX_ERRORS: usize = 20;
+
+/// State of an ingestion job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IngestJobStatus {
+    /// Waiting for a job worker to run it
+    Queued,
+    /// Listing and ingesting objects
+    Running,
+    /// Every matching object was processed
+    Completed,
+    /// Stopped by a source error; can be resumed
+    Failed,
+    /// Stopped on request; can be resumed
+    Cancelled,
+    /// Was running when the server stopped; can be resumed
+    Interrupted,
+}
+
+/// An object a job has ingested.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct IngestedObject {
+    /// Version of the object when it was ingested
+    pub version: String,
+    /// Number of chunks created from it
+    pub chunks: usize,
+}
+
+/// An ingestion job and its checkpoint.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IngestJob {
+    /// Job ID
+    pub id: String,
+    /// User who started the job
+    pub user_id: String,
+    /// Collection documents are ingested into
+    pub collection: String,
+    /// Source URI (e.g., `s3://bucket/prefix`)
+    pub source: String,
+    /// Glob selecting objects, relative to the source prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+    /// Chunking strategy override
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunking_strategy: Option<String>,
+    /// Tags added to every ingested document
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Seconds between scheduled re-runs; never re-run automatically when `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_interval_secs: Option<u64>,
+    /// Current state
+    pub status: IngestJobStatus,
+    /// Listing cursor of the page being processed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Objects ingested so far, by key
+    #[serde(default)]
+    pub objects: BTreeMap<String, IngestedObject>,
+    /// Keys listed so far in the current pass over the source
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub listed: BTreeSet<String>,
+    /// Objects ingested (or re-ingested after a change)
+    pub objects_ingested: usize,
+    /// Objects skipped because they were already ingested at the same version
+    pub objects_unchanged: usize,
+    /// Objects skipped because they are too large, not text, or empty
+    pub objects_skipped: usize,
+    /// Objects that could not be fetched or ingested
+    pub objects_failed: usize,
+    /// Objects removed because they disappeared from the source
+    #[serde(default)]
+    pub objects_removed: usize,
+    /// Chunks created
+    pub chunks_created: usize,
+    /// Most recent errors
+    #[serde(default)]
+    pub errors: Vec<String>,
+    /// When the job was created
+    pub created_at: DateTime<Utc>,
+    /// When the checkpoint was last written
+    pub updated_at: DateTime<Utc>,
+}
+
+impl IngestJob {
+    /// Create a job that has not started yet
+    pub fn new(user_id: &str, collection: &str, source: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            collection: collection.to_string(),
+            source: source.to_string(),
+            glob: None,
+            chunking_strategy: None,
+            tags: Vec::new(),
+            sync_interval_secs: None,
+            status: IngestJobStatus::Queued,
+            cursor: None,
+            objects: BTreeMap::new(),
+            listed: BTreeSet::new(),
+            objects_ingested: 0,
+            objects_unchanged: 0,
+            objects_skipped: 0,
+            objects_failed: 0,
+            objects_removed: 0,
+            chunks_created: 0,
+            errors: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Stable document ID prefix for an object's chunks
+    ///
+    /// Chunk `i` of the object is stored as `{prefix}_{i}`, so re-ingesting
+    /// the object replaces its chunks.
+    pub fn document_prefix(&self, key: &str) -> String {
+        let digest = Sha256::digest(format!("{}\n{}", self.source, key).as_bytes());
+        hex::encode(&digest[..12])
+    }
+
+    fn record_error(&mut self, key: &str, error: &AppError) {
+        self.objects_failed += 1;
+        self.errors.push(format!("{}: {}", key, error));
+        if self.errors.len() > MAX_ERRORS {
+            self.errors.remove(0);
+        }
+    }
+}
+
+// ============================================================================
+// Checkpoint Storage
+// ============================================================================
+
+/// Stores job checkpoints as JSON files in a directory.
+#[derive(Debug, Clone)]
+pub struct IngestJobStore {
+    dir: PathBuf,
+}
+
+impl IngestJobStore {
+    /// Create a store keeping checkpoints in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    /// Write a job's checkpoint
+    pub async fn save(&self, job: &IngestJob) -> Result<()> {
+        let io =
+            |e: std::io::Error| AppError::Internal(format!("Failed to save ingest job: {}", e));
+        tokio::fs::create_dir_all(&self.dir).await.map_err(io)?;
+
+        let json = serde_json::to_vec_pretty(job)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize ingest job: {}", e)))?;
+        // Write then rename so a crash never leaves a truncated checkpoint
+        let tmp = self.dir.join(format!("{}.json.tmp", job.id));
+        tokio::fs::write(&tmp, json).await.map_err(io)?;
+        tokio::fs::rename(&tmp, self.path(&job.id))
+            .await
+            .map_err(io)
+    }
+
+    /// Load a job by ID
+    pub async fn load(&self, id: &str) -> Result<Option<IngestJob>> {
+        // IDs are UUIDs; anything else could escape the directory
+        if uuid::Uuid::parse_str(id).is_err() {
+            return Ok(None);
+        }
+        match tokio::fs::read(self.path(id)).await {
+            Ok(json) => serde_json::from_slice(&json)
+                .map(Some)
+                .map_err(|e| AppError::Internal(format!("Corrupt ingest job {}: {}", id, e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(AppError::Internal(format!(
+                "Failed to load ingest job {}: {}",
+                id, e
+            ))),
+        }
+    }
+
+    /// List a user's jobs, newest first
+    pub async fn list(&self, user_id: &str) -> Result<Vec<IngestJob>> {
+        let mut jobs = self.list_all().await?;
+        jobs.retain(|job| job.user_id == user_id);
+        Ok(jobs)
+    }
+
+    /// List every user's jobs, newest first
+    pub async fn list_all(&self) -> Result<Vec<IngestJob>> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(AppError::Internal(format!(
+                    "Failed to list ingest jobs: {}",
+                    e
+                )))
+            }
+        };
+
+        let mut jobs = Vec::new();
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let Ok(json) = tokio::fs::read(&path).await else {
+                continue;
+            };
+            match serde_json::from_slice::<IngestJob>(&json) {
+                Ok(job) => jobs.push(job),
+                Err(e) => tracing::warn!("Skipping corrupt ingest job {:?}: {}", path, e),
+            }
+        }
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+        Ok(jobs)
+    }
+}
+
+// ============================================================================
+// Job Runner
+// ============================================================================
+
+/// Where a job's documents go.
+#[async_trait]
+pub trait IngestSink: Send + Sync {
+    /// Ingest an object's document, replacing the chunks of its `previous` version
+    ///
+    /// Returns the number of chunks created.
+    async fn ingest(
+        &self,
+        job: &IngestJob,
+        key: &str,
+        document: SourceDocument,
+        previous: Option<&IngestedObject>,
+    ) -> Result<usize>;
+
+    /// Remove the chunks of an object that is no longer in the source
+    async fn remove(&self, job: &IngestJob, key: &str, ingested: &IngestedObject) -> Result<()>;
+}
+
+/// Run a job until its source is exhausted, it fails or it is cancelled
+///
+/// Starts from the job's checkpoint, saving it after every object. Errors
+/// fetching or ingesting a single object are recorded on the job without
+/// stopping it; listing errors stop it as [`IngestJobStatus::Failed`].
+pub async fn run_job(
+    job: &mut IngestJob,
+    source: &dyn DocumentSource,
+    filter: &KeyFilter,
+    sink: &dyn IngestSink,
+    store: &IngestJobStore,
+    cancellation: &CancellationToken,
+) -> Result<()> {
+    job.status = IngestJobStatus::Running;
+    save(job, store).await?;
+
+    let status = match process(job, source, filter, sink, store, cancellation).await {
+        Ok(()) if cancellation.is_cancelled() => IngestJobStatus::Cancelled,
+        Ok(()) => IngestJobStatus::Completed,
+        Err(e) => {
+            tracing::warn!(job_id = %job.id, source = %job.source, "Ingest job failed: {}", e);
+            job.errors.push(e.to_string());
+            IngestJobStatus::Failed
+        }
+    };
+    job.status = status;
+    save(job, store).await
+}
+
+async fn process(
+    job: &mut IngestJob,
+    source: &dyn DocumentSource,
+    filter: &KeyFilter,
+    sink: &dyn IngestSink,
+    store: &IngestJobStore,
+    cancellation: &CancellationToken,
+) -> Result<()> {
+    if job.cursor.is_none() {
+        job.listed.clear();
+    }
+    loop {
+        let page = source.list(job.cursor.as_deref()).await?;
+
+        for object in page.objects {
+            if cancellation.is_cancelled() {
+                return Ok(());
+            }
+            if !filter.matches(&object.key) {
+                continue;
+            }
+            job.listed.insert(object.key.clone());
+
+            let previous = job.objects.get(&object.key).cloned();
+            if previous
+                .as_ref()
+                .is_some_and(|p| p.version == object.version)
+            {
+                job.objects_unchanged += 1;
+                continue;
+            }
+            if object.size > MAX_OBJECT_BYTES {
+                job.objects_skipped += 1;
+                continue;
+            }
+
+            let document = match source.fetch_document(&object.key).await {
+                Ok(document) => document.filter(|d| !d.text.trim().is_empty()),
+                Err(e) => {
+                    job.record_error(&object.key, &e);
+                    save(job, store).await?;
+                    continue;
+                }
+            };
+            let Some(document) = document else {
+                job.objects_skipped += 1;
+                continue;
+            };
+
+            match sink
+                .ingest(job, &object.key, document, previous.as_ref())
+                .await
+            {
+                // The sink removed any previous chunks
+                Ok(0) => {
+                    job.objects.remove(&object.key);
+                    job.objects_skipped += 1;
+                }
+                Ok(chunks) => {
+                    job.objects.insert(
+                        object.key,
+                        IngestedObject {
+                            version: object.version,
+                            chunks,
+                        },
+                    );
+                    job.objects_ingested += 1;
+                    job.chunks_created += chunks;
+                }
+                Err(e) => job.record_error(&object.key, &e),
+            }
+            save(job, store).await?;
+        }
+
+        job.cursor = page.next_cursor;
+        save(job, store).await?;
+        if job.cursor.is_none() {
+            return remove_unlisted(job, sink, store).await;
+        }
+    }
+}
+
+/// Remove the objects a completed pass over the source did not list
+async fn remove_unlisted(
+    job: &mut IngestJob,
+    sink: &dyn IngestSink,
+    store: &IngestJobStore,
+) -> Result<()> {
+    let gone: Vec<(String, IngestedObject)> = job
+        .objects
+        .iter()
+        .filter(|(key, _)| !job.listed.contains(*key))
+        .map(|(key, object)| (key.clone(), object.clone()))
+        .collect();
+    for (key, object) in gone {
+        match sink.remove(job, &key, &object).await {
+            Ok(()) => {
+                job.objects.remove(&key);
+                job.objects_removed += 1;
+            }
+            Err(e) => job.record_error(&key, &e),
+        }
+    }
+    job.listed.clear();
+    save(job, store).await
+}
+
+async fn save(job: &mut IngestJob, store: &IngestJobStore) -> Result<()> {
+    job.updated_at = Utc::now();
+    store.save(job).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::connectors::{SourceObject, SourcePage};
+    use parking_lot::Mutex;
+    use std::collections::HashMap;
+
+    /// Two pages of objects; fails listing the second page once if `flaky`
+    struct PagedSource {
+        objects: Mutex<HashMap<String, (String, String)>>,
+        flaky: Mutex<bool>,
+    }
+
+    impl PagedSource {
+        fn new() -> Self {
+            let objects = [
+                ("docs/a.md", "v1", "alpha"),
+                ("docs/b.md", "v1", "beta"),
+                ("docs/c.png", "v1", "\0png"),
+                ("docs/d.md", "v1", "delta"),
+            ]
+            .into_iter()
+            .map(|(k, v, c)| (k.to_string(), (v.to_string(), c.to_string())))
+            .collect();
+            Self {
+                objects: Mutex::new(objects),
+                flaky: Mutex::new(true),
+            }
+        }
+
+        fn page(&self, keys: &[&str], next: Option<&str>) -> SourcePage {
+            let objects = self.objects.lock();
+            SourcePage {
+                objects: keys
+                    .iter()
+                    .filter_map(|k| {
+                        let (version, content) = objects.get(*k)?;
+                        Some(SourceObject {
+                            key: k.to_string(),
+                            version: version.clone(),
+                            size: content.len() as u64,
+                        })
+                    })
+                    .collect(),
+                next_cursor: next.map(str::to_string),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl DocumentSource for PagedSource {
+        fn uri(&self) -> String {
+            "s3://corpus/docs/".to_string()
+        }
+
+        fn object_uri(&self, key: &str) -> String {
+            format!("s3://corpus/{}", key)
+        }
+
+        async fn list(&self, cursor: Option<&str>) -> Result<SourcePage> {
+            match cursor {
+                None => Ok(self.page(&["docs/a.md", "docs/b.md"], Some("2"))),
+                Some(_) if std::mem::take(&mut *self.flaky.lock()) => {
+                    Err(AppError::External("connection reset".to_string()))
+                }
+                Some(_) => Ok(self.page(&["docs/c.png", "docs/d.md"], None)),
+            }
+        }
+
+        async fn fetch(&self, key: &str) -> Result<Vec<u8>> {
+            Ok(self.objects.lock()[key].1.clone().into_bytes())
+        }
+    }
+
+    /// Records what was ingested and removed; one chunk per object
+    #[derive(Default)]
+    struct RecordingSink {
+        ingested: Mutex<Vec<(String, Option<IngestedObject>)>>,
+        removed: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl IngestSink for RecordingSink {
+        async fn ingest(
+            &self,
+            _job: &IngestJob,
+            key: &str,
+            _document: SourceDocument,
+            previous: Option<&IngestedObject>,
+        ) -> Result<usize> {
+            self.ingested
+                .lock()
+                .push((key.to_string(), previous.cloned()));
+            Ok(1)
+        }
+
+        async fn remove(
+            &self,
+            _job: &IngestJob,
+            key: &str,
+            _ingested: &IngestedObject,
+        ) -> Result<()> {
+            self.removed.lock().push(key.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_resumes_from_checkpoint_and_skips_unchanged() {
+        let dir = std::env::temp_dir().join(format!("ares-ingest-{}", uuid::Uuid::new_v4()));
+        let store = IngestJobStore::new(&dir);
+        let source = PagedSource::new();
+        let sink = RecordingSink::default();
+        let filter = KeyFilter::new("docs/", None).unwrap();
+        let token = CancellationToken::new();
+
+        // The second page fails: the first page is checkpointed
+        let mut job = IngestJob::new("user", "docs", &source.uri());
+        run_job(&mut job, &source, &filter, &sink, &store, &token)
+            .await
+            .unwrap();
+        assert_eq!(job.status, IngestJobStatus::Failed);
+        assert_eq!(job.cursor.as_deref(), Some("2"));
+        assert_eq!(job.objects_ingested, 2);
+
+        // Resume from the saved checkpoint
+        let mut job = store.load(&job.id).await.unwrap().unwrap();
+        run_job(&mut job, &source, &filter, &sink, &store, &token)
+            .await
+            .unwrap();
+        assert_eq!(job.status, IngestJobStatus::Completed);
+        assert_eq!(job.objects_ingested, 3);
+        assert_eq!(job.objects_skipped, 1); // the binary png
+        assert_eq!(sink.ingested.lock().len(), 3);
+
+        // Re-running only picks up the changed object
+        source.objects.lock().get_mut("docs/b.md").unwrap().0 = "v2".to_string();
+        job.cursor = None;
+        run_job(&mut job, &source, &filter, &sink, &store, &token)
+            .await
+            .unwrap();
+        assert_eq!(job.objects_unchanged, 2);
+        let ingested = sink.ingested.lock();
+        assert_eq!(ingested.len(), 4);
+        assert_eq!(ingested[3].0, "docs/b.md");
+        assert_eq!(ingested[3].1.as_ref().unwrap().version, "v1");
+        drop(ingested);
+        assert!(sink.removed.lock().is_empty());
+
+        // An object gone from the source is removed at the end of the pass
+        source.objects.lock().remove("docs/d.md");
+        run_job(&mut job, &source, &filter, &sink, &store, &token)
+            .await
+            .unwrap();
+        assert_eq!(job.objects_removed, 1);
+        assert_eq!(*sink.removed.lock(), vec!["docs/d.md".to_string()]);
+        assert!(!job.objects.contains_key("docs/d.md"));
+        assert!(job.listed.is_empty());
+
+        assert_eq!(store.list("user").await.unwrap().len(), 1);
+        assert!(store.list("other").await.unwrap().is_empty());
+        assert!(store.load("../etc/passwd").await.unwrap().is_none());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,225 @@
+//! Query intent classification.
+//!
+//! Most chat turns are greetings, thanks or other small talk that no document
+//! can answer. [`classify`] recognises them with cheap lexical rules, so
+//! callers can skip the embedding and vector search round-trip entirely:
+//!
+//! ```rust,ignore
+//! use ares::rag::intent;
+//!
+//! if intent::classify(&message).needs_retrieval() {
+//!     // embed, search, add context...
+//! }
+//! ```
+//!
+//! The rules err on the side of retrieval: anything that is not clearly
+//! small talk is classified as [`QueryIntent::Information`].
+
+use serde::{Deserialize, Serialize};
+
+/// Longest message, in words, that can still be classified as small talk
+const MAX_SMALL_TALK_WORDS: usize = 8;
+
+/// Words that open a greeting
+const GREETINGS: &[&str] = &[
+    "hi",
+    "hello",
+    "hey",
+    "heya",
+    "hiya",
+    "howdy",
+    "greetings",
+    "yo",
+    "morning",
+    "afternoon",
+    "evening",
+    "sup",
+];
+
+/// Words that make up small talk: thanks, acknowledgements, farewells and
+/// pleasantries
+const SMALL_TALK: &[&str] = &[
+    "thanks",
+    "thank",
+    "thx",
+    "ty",
+    "cheers",
+    "appreciate",
+    "it",
+    "that",
+    "ok",
+    "okay",
+    "k",
+    "cool",
+    "great",
+    "nice",
+    "awesome",
+    "perfect",
+    "sure",
+    "yes",
+    "yeah",
+    "yep",
+    "no",
+    "nope",
+    "bye",
+    "goodbye",
+    "later",
+    "see",
+    "ya",
+    "you",
+    "good",
+    "night",
+    "day",
+    "have",
+    "a",
+    "so",
+    "much",
+    "very",
+    "lol",
+    "haha",
+    "got",
+    "alright",
+    "np",
+    "welcome",
+    "and",
+    "again",
+    "there",
+    "all",
+    "everyone",
+    "how",
+    "are",
+    "is",
+    "going",
+    "doing",
+    "what's",
+    "whats",
+    "up",
+    "well",
+    "fine",
+    "too",
+    "i",
+    "i'm",
+    "im",
+    "am",
+    "hope",
+    "your",
+    "meet",
+    "to",
+    "pleased",
+];
+
+/// Words that mark a request for information even in a short message
+const INFORMATION_MARKERS: &[&str] = &[
+    "what",
+    "which",
+    "who",
+    "whom",
+    "whose",
+    "when",
+    "where",
+    "why",
+    "explain",
+    "describe",
+    "tell",
+    "show",
+    "find",
+    "list",
+    "define",
+    "compare",
+    "summarize",
+    "summarise",
+];
+
+/// What a message asks for, as far as retrieval is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryIntent {
+    /// A greeting ("hi", "good morning")
+    Greeting,
+    /// Thanks, acknowledgements, farewells and other pleasantries
+    SmallTalk,
+    /// A question or request that documents may answer
+    Information,
+}
+
+impl QueryIntent {
+    /// Whether answering the message may need retrieved documents
+    pub fn needs_retrieval(self) -> bool {
+        self == Self::Information
+    }
+}
+
+/// Classify a message by its words.
+pub fn classify(message: &str) -> QueryIntent {
+    let words: Vec<String> = message
+        .split_whitespace()
+        .map(|w| {
+            w.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'')
+                .to_lowercase()
+        })
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    // Punctuation or emoji alone carries nothing to search for
+    if words.is_empty() {
+        return QueryIntent::SmallTalk;
+    }
+    if words.len() > MAX_SMALL_TALK_WORDS
+        || words
+            .iter()
+            .any(|w| INFORMATION_MARKERS.contains(&w.as_str()))
+    {
+        return QueryIntent::Information;
+    }
+
+    let is_small_talk =
+        |w: &String| GREETINGS.contains(&w.as_str()) || SMALL_TALK.contains(&w.as_str());
+    if !words.iter().all(is_small_talk) {
+        return QueryIntent::Information;
+    }
+
+    if GREETINGS.contains(&words[0].as_str())
+        || (words[0] == "good"
+            && words
+                .get(1)
+                .is_some_and(|w| GREETINGS.contains(&w.as_str())))
+    {
+        QueryIntent::Greeting
+    } else {
+        QueryIntent::SmallTalk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_small_talk() {
+        assert_eq!(classify("Hi!"), QueryIntent::Greeting);
+        assert_eq!(classify("good morning"), QueryIntent::Greeting);
+        assert_eq!(classify("hey, how are you?"), QueryIntent::Greeting);
+        assert_eq!(classify("Thanks so much!"), QueryIntent::SmallTalk);
+        assert_eq!(classify("ok cool"), QueryIntent::SmallTalk);
+        assert_eq!(classify("👍"), QueryIntent::SmallTalk);
+    }
+
+    #[test]
+    fn test_classify_information() {
+        assert_eq!(
+            classify("What is our refund policy?"),
+            QueryIntent::Information
+        );
+        assert_eq!(
+            classify("hi, what's the VPN address?"),
+            QueryIntent::Information
+        );
+        assert_eq!(
+            classify("thanks, and the invoice total?"),
+            QueryIntent::Information
+        );
+        assert_eq!(classify("pricing"), QueryIntent::Information);
+        assert!(!classify("bye").needs_retrieval());
+        assert!(classify("tell me about onboarding").needs_retrieval());
+    }
+}
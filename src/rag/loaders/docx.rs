@@ -0,0 +1,188 @@
+//! Word documents.
+
+use super::{tidy, DocumentLoader, LoadedDocument};
+use crate::types::{AppError, Result};
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+/// Largest uncompressed document part read, against zip bombs
+const MAX_PART_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Reads the body of a DOCX document: one line per paragraph, with table
+/// cells separated with `|`. Headers, footers and comments are left out.
+///
+/// The title is the `dc:title` of the document's properties.
+pub struct DocxLoader;
+
+impl DocumentLoader for DocxLoader {
+    fn name(&self) -> &'static str {
+        "DOCX"
+    }
+
+    fn media_types(&self) -> &'static [&'static str] {
+        &["application/vnd.openxmlformats-officedocument.wordprocessingml.document"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["docx"]
+    }
+
+    /// A zip archive with a `word/document.xml` part
+    fn sniff(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(b"PK\x03\x04")
+            && ZipArchive::new(Cursor::new(bytes))
+                .is_ok_and(|archive| archive.index_for_name("word/document.xml").is_some())
+    }
+
+    fn load(&self, bytes: &[u8]) -> Result<LoadedDocument> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes))
+            .map_err(|e| AppError::InvalidInput(format!("it is not a zip archive: {}", e)))?;
+        let body = read_part(&mut archive, "word/document.xml")?
+            .ok_or_else(|| AppError::InvalidInput("it has no word/document.xml".into()))?;
+        let title = read_part(&mut archive, "docProps/core.xml")?
+            .and_then(|core| core_title(&core))
+            .filter(|title| !title.is_empty());
+        Ok(LoadedDocument {
+            text: tidy(&body_text(&body)?),
+            title,
+        })
+    }
+}
+
+/// Read a part of the archive as text, or `None` if it has no such part
+fn read_part(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<Option<String>> {
+    let Ok(part) = archive.by_name(name) else {
+        return Ok(None);
+    };
+    let mut xml = String::new();
+    part.take(MAX_PART_BYTES + 1)
+        .read_to_string(&mut xml)
+        .map_err(|e| AppError::InvalidInput(format!("can't read {}: {}", name, e)))?;
+    if xml.len() as u64 > MAX_PART_BYTES {
+        return Err(AppError::InvalidInput(format!(
+            "{} is larger than {} bytes",
+            name, MAX_PART_BYTES
+        )));
+    }
+    Ok(Some(xml))
+}
+
+/// Text of the runs of `word/document.xml`
+fn body_text(xml: &str) -> Result<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut out = String::new();
+    let mut in_text = false;
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| AppError::InvalidInput(format!("malformed document XML: {}", e)))?;
+        match event {
+            Event::Start(e) if e.local_name().as_ref() == b"t" => in_text = true,
+            Event::End(e) => match e.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"p" => out.push('\n'),
+                b"tc" => out.push_str(" | "),
+                b"tr" => {
+                    let row = out.trim_end_matches(" | ").len();
+                    out.truncate(row);
+                    out.push('\n');
+                }
+                _ => {}
+            },
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"tab" => out.push('\t'),
+                b"br" | b"cr" => out.push('\n'),
+                _ => {}
+            },
+            Event::Text(e) if in_text => out.push_str(&e.decode().unwrap_or_default()),
+            Event::GeneralRef(e) if in_text => {
+                if let Ok(Some(c)) = e.resolve_char_ref() {
+                    out.push(c);
+                } else if let Some(entity) = e
+                    .decode()
+                    .ok()
+                    .and_then(|name| resolve_predefined_entity(&name))
+                {
+                    out.push_str(entity);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    // Cells end with their paragraph's line break; keep rows on one line
+    Ok(out.replace("\n | ", " | "))
+}
+
+/// `dc:title` of `docProps/core.xml`
+fn core_title(xml: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut in_title = false;
+    let mut title = String::new();
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(e) if e.local_name().as_ref() == b"title" => in_title = true,
+            Event::End(e) if e.local_name().as_ref() == b"title" => {
+                return Some(title.trim().to_string())
+            }
+            Event::Text(e) if in_title => title.push_str(&e.decode().ok()?),
+            Event::GeneralRef(e) if in_title => {
+                let name = e.decode().ok()?;
+                title.push_str(resolve_predefined_entity(&name)?);
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn docx(document: &str, core: Option<&str>) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let mut parts = vec![("word/document.xml", document)];
+        parts.extend(core.map(|core| ("docProps/core.xml", core)));
+        for (name, content) in parts {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_paragraphs_tables_and_title() {
+        let document = concat!(
+            r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>"#,
+            r#"<w:p><w:r><w:t>Fish &amp; </w:t></w:r><w:r><w:t xml:space="preserve">chips</w:t></w:r></w:p>"#,
+            r#"<w:p><w:r><w:t>A</w:t><w:tab/><w:t>B</w:t><w:br/><w:t>C</w:t></w:r></w:p>"#,
+            r#"<w:tbl><w:tr><w:tc><w:p><w:r><w:t>Env</w:t></w:r></w:p></w:tc>"#,
+            r#"<w:tc><w:p><w:r><w:t>URL</w:t></w:r></w:p></w:tc></w:tr></w:tbl>"#,
+            r#"</w:body></w:document>"#,
+        );
+        let core = r#"<cp:coreProperties xmlns:cp="cp" xmlns:dc="dc"><dc:title>Menu</dc:title></cp:coreProperties>"#;
+        let bytes = docx(document, Some(core));
+
+        assert!(DocxLoader.sniff(&bytes));
+        assert!(!DocxLoader.sniff(b"PK\x03\x04not a zip"));
+        let loaded = DocxLoader.load(&bytes).unwrap();
+        assert_eq!(loaded.title.as_deref(), Some("Menu"));
+        assert_eq!(loaded.text, "Fish & chips\nA\tB\nC\nEnv | URL");
+    }
+
+    #[test]
+    fn test_rejects_archives_without_a_document() {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("other.txt", SimpleFileOptions::default())
+            .unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+        assert!(!DocxLoader.sniff(&bytes));
+        assert!(DocxLoader.load(&bytes).is_err());
+    }
+}
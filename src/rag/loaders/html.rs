@@ -0,0 +1,173 @@
+//! HTML pages.
+
+use super::{text, tidy, DocumentLoader, LoadedDocument};
+use crate::types::{AppError, Result};
+use scraper::{ElementRef, Html, Selector};
+
+/// Reads the visible text of an HTML page, with headings, list items and
+/// quotes marked as in Markdown and table cells separated with `|`.
+///
+/// Scripts, styles and navigation are dropped. The title is the page's
+/// `<title>`.
+pub struct HtmlLoader;
+
+impl DocumentLoader for HtmlLoader {
+    fn name(&self) -> &'static str {
+        "HTML"
+    }
+
+    fn media_types(&self) -> &'static [&'static str] {
+        &["text/html", "application/xhtml+xml"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["html", "htm", "xhtml"]
+    }
+
+    fn sniff(&self, bytes: &[u8]) -> bool {
+        let start = bytes
+            .strip_prefix(b"\xef\xbb\xbf")
+            .unwrap_or(bytes)
+            .trim_ascii_start();
+        let start = &start[..start.len().min(15)];
+        [b"<!doctype html".as_slice(), b"<html"]
+            .iter()
+            .any(|signature| {
+                start.len() >= signature.len()
+                    && start[..signature.len()].eq_ignore_ascii_case(signature)
+            })
+    }
+
+    fn load(&self, bytes: &[u8]) -> Result<LoadedDocument> {
+        let source = text::decode(bytes)
+            .ok_or_else(|| AppError::InvalidInput("it is not UTF-8 text".into()))?;
+        let document = Html::parse_document(&source);
+
+        let title = Selector::parse("title")
+            .ok()
+            .and_then(|selector| document.select(&selector).next())
+            .map(|title| title.text().collect::<String>().trim().to_string())
+            .filter(|title| !title.is_empty());
+
+        let mut out = String::new();
+        render_element(document.root_element(), &mut out);
+        Ok(LoadedDocument {
+            text: tidy(&out),
+            title,
+        })
+    }
+}
+
+fn render_element(element: ElementRef<'_>, out: &mut String) {
+    let name = element.value().name();
+    if matches!(
+        name,
+        "head" | "script" | "style" | "noscript" | "template" | "svg" | "nav" | "iframe"
+    ) {
+        return;
+    }
+    let block = matches!(
+        name,
+        "p" | "div"
+            | "section"
+            | "article"
+            | "main"
+            | "header"
+            | "footer"
+            | "aside"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "li"
+            | "tr"
+            | "pre"
+            | "blockquote"
+            | "table"
+            | "ul"
+            | "ol"
+            | "dl"
+            | "dt"
+            | "dd"
+            | "figcaption"
+    );
+    if block {
+        new_line(out);
+    }
+    match name {
+        "br" => out.push('\n'),
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = name[1..].parse().unwrap_or(1);
+            out.push_str(&"#".repeat(level));
+            out.push(' ');
+        }
+        "li" => out.push_str("- "),
+        "blockquote" => out.push_str("> "),
+        "td" | "th" if element.prev_siblings().any(|s| s.value().is_element()) => {
+            out.push_str(" | ")
+        }
+        _ => {}
+    }
+
+    for child in element.children() {
+        if let Some(child) = ElementRef::wrap(child) {
+            render_element(child, out);
+        } else if let Some(text) = child.value().as_text() {
+            if name == "pre" {
+                out.push_str(text);
+            } else {
+                push_collapsed(out, text);
+            }
+        }
+    }
+    if block {
+        new_line(out);
+    }
+}
+
+/// Append text with its whitespace collapsed, as a browser renders it
+fn push_collapsed(out: &mut String, text: &str) {
+    if text.starts_with(char::is_whitespace) && !out.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+    out.push_str(&text.split_whitespace().collect::<Vec<_>>().join(" "));
+    if text.ends_with(char::is_whitespace) && !text.trim().is_empty() {
+        out.push(' ');
+    }
+}
+
+/// Start a new line unless one was just started
+fn new_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_text_and_title() {
+        let page = concat!(
+            "<!DOCTYPE html><html><head><title> Release notes </title>",
+            "<style>p { color: red }</style></head><body>",
+            "<nav><a href=\"/\">Home</a></nav>",
+            "<h2>v2.0</h2><p>Faster\n   <em>search</em>.</p>",
+            "<ul><li>Hybrid</li><li>Filters</li></ul>",
+            "<table><tr><th>Env</th><th>URL</th></tr></table>",
+            "<script>track()</script></body></html>",
+        );
+        assert!(HtmlLoader.sniff(page.as_bytes()));
+        assert!(!HtmlLoader.sniff(b"# Not HTML"));
+
+        let document = HtmlLoader.load(page.as_bytes()).unwrap();
+        assert_eq!(document.title.as_deref(), Some("Release notes"));
+        assert_eq!(
+            document.text,
+            "## v2.0\nFaster search.\n- Hybrid\n- Filters\nEnv | URL"
+        );
+    }
+}
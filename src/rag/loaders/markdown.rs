@@ -0,0 +1,81 @@
+//! Markdown files.
+
+use super::{text, tidy, DocumentLoader, LoadedDocument};
+use crate::types::{AppError, Result};
+
+/// Keeps Markdown as is, headings and all, and drops YAML front matter.
+///
+/// The title is the front matter's `title`, or else the first heading.
+pub struct MarkdownLoader;
+
+impl DocumentLoader for MarkdownLoader {
+    fn name(&self) -> &'static str {
+        "Markdown"
+    }
+
+    fn media_types(&self) -> &'static [&'static str] {
+        &["text/markdown", "text/x-markdown"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["md", "markdown", "mdx"]
+    }
+
+    fn load(&self, bytes: &[u8]) -> Result<LoadedDocument> {
+        let source = text::decode(bytes)
+            .ok_or_else(|| AppError::InvalidInput("it is not UTF-8 text".into()))?;
+        let (front_matter, body) = split_front_matter(&source);
+
+        let title = front_matter
+            .lines()
+            .find_map(|line| line.strip_prefix("title:"))
+            .map(|title| title.trim().trim_matches(['"', '\'']).to_string())
+            .or_else(|| {
+                body.lines()
+                    .find_map(|line| line.trim_start().strip_prefix("# "))
+                    .map(|heading| heading.trim().to_string())
+            })
+            .filter(|title| !title.is_empty());
+
+        Ok(LoadedDocument {
+            text: tidy(body),
+            title,
+        })
+    }
+}
+
+/// Split a document into its front matter (empty when it has none) and body
+fn split_front_matter(source: &str) -> (&str, &str) {
+    let Some(rest) = source
+        .strip_prefix("---\n")
+        .or_else(|| source.strip_prefix("---\r\n"))
+    else {
+        return ("", source);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return (&rest[..offset], &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    ("", source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_front_matter_and_title() {
+        let document = MarkdownLoader
+            .load(b"---\ntitle: \"Runbook\"\ntags: [ops]\n---\n# Deploys\n\nRun it.\n")
+            .unwrap();
+        assert_eq!(document.title.as_deref(), Some("Runbook"));
+        assert_eq!(document.text, "# Deploys\n\nRun it.");
+
+        let document = MarkdownLoader.load(b"Intro\n\n# Setup\n---\n").unwrap();
+        assert_eq!(document.title.as_deref(), Some("Setup"));
+        assert_eq!(document.text, "Intro\n\n# Setup\n---");
+    }
+}
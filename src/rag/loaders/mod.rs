@@ -0,0 +1,274 @@
+//! Document loaders turning uploaded files into text for ingestion.
+//!
+//! A [`DocumentLoader`] reads one file format. [`DocumentLoaders`] holds the
+//! built-in ones and any registered by the embedding application (see
+//! [`crate::AresBuilder::with_document_loader`]), and picks the loader of a
+//! file by sniffing its content, then by its declared content type, then by
+//! its file name's extension:
+//!
+//! - PDF ([`PdfLoader`]) - text of text-based PDFs; scanned pages need OCR
+//!   first
+//! - DOCX ([`DocxLoader`]) - paragraphs and tables of Word documents
+//! - HTML ([`HtmlLoader`]) - visible text, with headings and list items
+//!   kept as Markdown
+//! - Markdown ([`MarkdownLoader`]) - kept as is, without front matter
+//! - CSV ([`CsvLoader`]) - one line per record, each value labelled with
+//!   its column
+//! - Plain text ([`TextLoader`]) - UTF-8 or UTF-16 with a byte order mark;
+//!   also the fallback for any other file that decodes as text
+
+mod csv;
+mod docx;
+mod html;
+mod markdown;
+mod pdf;
+mod text;
+
+pub use csv::CsvLoader;
+pub use docx::DocxLoader;
+pub use html::HtmlLoader;
+pub use markdown::MarkdownLoader;
+pub use pdf::PdfLoader;
+pub use text::TextLoader;
+
+use crate::types::{AppError, Result};
+use std::sync::Arc;
+
+/// Text extracted from a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedDocument {
+    /// Document text
+    pub text: String,
+    /// Title found in the document itself (HTML `<title>`, first Markdown
+    /// heading, DOCX properties), if any
+    pub title: Option<String>,
+}
+
+/// Extracts the text of one file format.
+pub trait DocumentLoader: Send + Sync {
+    /// Name of the format, for logs and errors (e.g., "PDF")
+    fn name(&self) -> &'static str;
+
+    /// Content types of the format, the usual one first
+    fn media_types(&self) -> &'static [&'static str];
+
+    /// File name extensions of the format, lowercase and without the dot
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// Whether `bytes` carry the format's signature, whatever their declared
+    /// type. The default recognizes nothing.
+    fn sniff(&self, _bytes: &[u8]) -> bool {
+        false
+    }
+
+    /// Extract the text of a file
+    fn load(&self, bytes: &[u8]) -> Result<LoadedDocument>;
+}
+
+/// The loaders files are read with.
+#[derive(Clone)]
+pub struct DocumentLoaders {
+    loaders: Vec<Arc<dyn DocumentLoader>>,
+}
+
+impl Default for DocumentLoaders {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DocumentLoaders {
+    /// The built-in loaders
+    pub fn new() -> Self {
+        Self {
+            loaders: vec![
+                Arc::new(PdfLoader),
+                Arc::new(DocxLoader),
+                Arc::new(HtmlLoader),
+                Arc::new(MarkdownLoader),
+                Arc::new(CsvLoader),
+                Arc::new(TextLoader),
+            ],
+        }
+    }
+
+    /// Add a loader, tried before the built-in ones and those added earlier
+    pub fn register(&mut self, loader: Arc<dyn DocumentLoader>) {
+        self.loaders.insert(0, loader);
+    }
+
+    /// The loader of a file, or `None` if none reads it
+    ///
+    /// A loader recognizing the content's signature wins over the declared
+    /// `content_type`, which wins over the extension of `filename`. Files
+    /// matching none of them are read as plain text if they decode as text.
+    pub fn detect(
+        &self,
+        filename: &str,
+        content_type: Option<&str>,
+        bytes: &[u8],
+    ) -> Option<&dyn DocumentLoader> {
+        if let Some(loader) = self.loaders.iter().find(|l| l.sniff(bytes)) {
+            return Some(loader.as_ref());
+        }
+
+        let declared = content_type
+            .and_then(|t| t.split(';').next())
+            .map(|t| t.trim().to_ascii_lowercase())
+            .filter(|t| !t.is_empty() && t != "application/octet-stream");
+        if let Some(declared) = declared {
+            if let Some(loader) = self
+                .loaders
+                .iter()
+                .find(|l| l.media_types().contains(&declared.as_str()))
+            {
+                return Some(loader.as_ref());
+            }
+        }
+
+        if let Some((_, extension)) = filename.rsplit_once('.') {
+            let extension = extension.to_ascii_lowercase();
+            if let Some(loader) = self
+                .loaders
+                .iter()
+                .find(|l| l.extensions().contains(&extension.as_str()))
+            {
+                return Some(loader.as_ref());
+            }
+        }
+
+        text::decode(bytes).map(|_| &TextLoader as &dyn DocumentLoader)
+    }
+
+    /// Content type of a file as detected by [`DocumentLoaders::detect`]
+    pub fn media_type(
+        &self,
+        filename: &str,
+        content_type: Option<&str>,
+        bytes: &[u8],
+    ) -> Option<&'static str> {
+        let loader = self.detect(filename, content_type, bytes)?;
+        loader.media_types().first().copied()
+    }
+
+    /// Extract the text of a file with its loader
+    ///
+    /// Fails when no loader reads the file, when it is malformed, and when
+    /// it holds no text.
+    pub fn load(
+        &self,
+        filename: &str,
+        content_type: Option<&str>,
+        bytes: &[u8],
+    ) -> Result<LoadedDocument> {
+        let loader = self.detect(filename, content_type, bytes).ok_or_else(|| {
+            AppError::InvalidInput(format!(
+                "{} ({}) is not a supported document",
+                filename,
+                content_type.unwrap_or("unknown type")
+            ))
+        })?;
+        let document = loader.load(bytes).map_err(|e| match e {
+            AppError::InvalidInput(reason) => AppError::InvalidInput(format!(
+                "Can't read {} as {}: {}",
+                filename,
+                loader.name(),
+                reason
+            )),
+            e => e,
+        })?;
+        if document.text.trim().is_empty() {
+            return Err(AppError::InvalidInput(format!(
+                "{} has no text to use",
+                filename
+            )));
+        }
+        Ok(document)
+    }
+}
+
+/// Collapse runs of blank lines and trailing whitespace left by extraction
+fn tidy(text: &str) -> String {
+    let mut tidied = String::with_capacity(text.len());
+    let mut blank = true;
+    for line in text.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            if !blank {
+                tidied.push('\n');
+            }
+            blank = true;
+            continue;
+        }
+        tidied.push_str(line);
+        tidied.push('\n');
+        blank = false;
+    }
+    tidied.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_prefers_signature_then_type_then_extension() {
+        let loaders = DocumentLoaders::new();
+        let detected = |filename, content_type, bytes| {
+            loaders
+                .detect(filename, content_type, bytes)
+                .map(|l| l.name())
+        };
+
+        assert_eq!(
+            detected("notes.txt", Some("text/plain"), b"%PDF-1.7\n"),
+            Some("PDF")
+        );
+        assert_eq!(
+            detected("page", Some("text/html; charset=utf-8"), b"hi"),
+            Some("HTML")
+        );
+        assert_eq!(
+            detected("data.csv", Some("application/octet-stream"), b"a,b\n1,2"),
+            Some("CSV")
+        );
+        assert_eq!(detected("README", None, b"plain words"), Some("plain text"));
+        assert_eq!(detected("blob.bin", None, b"\x00\x01\x02"), None);
+        assert_eq!(
+            loaders.media_type("x.md", None, b"# Title"),
+            Some("text/markdown")
+        );
+    }
+
+    #[test]
+    fn test_registered_loaders_come_first() {
+        struct Shouty;
+        impl DocumentLoader for Shouty {
+            fn name(&self) -> &'static str {
+                "shouty text"
+            }
+            fn media_types(&self) -> &'static [&'static str] {
+                &["text/plain"]
+            }
+            fn extensions(&self) -> &'static [&'static str] {
+                &["txt"]
+            }
+            fn load(&self, bytes: &[u8]) -> Result<LoadedDocument> {
+                Ok(LoadedDocument {
+                    text: String::from_utf8_lossy(bytes).to_uppercase(),
+                    title: None,
+                })
+            }
+        }
+
+        let mut loaders = DocumentLoaders::new();
+        loaders.register(Arc::new(Shouty));
+        let document = loaders.load("a.txt", None, b"quiet").unwrap();
+        assert_eq!(document.text, "QUIET");
+        assert!(loaders.load("a.txt", None, b"  \n").is_err());
+    }
+
+    #[test]
+    fn test_tidy() {
+        assert_eq!(tidy("\n\na  \n\n\n\nb\n \n"), "a\n\nb");
+    }
+}
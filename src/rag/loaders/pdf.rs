@@ -0,0 +1,1215 @@
+//! PDF documents.
+//!
+//! A small reader of the parts of PDF text extraction needs: objects
+//! (including those packed in object streams), the page tree, content
+//! streams compressed with Flate, fonts' `ToUnicode` maps and form
+//! XObjects. Pages are read in order; text is laid out a line per text line
+//! of the page, as far as the content stream's positioning tells.
+//!
+//! Encrypted PDFs, and pages that are images of text (scans), have no text
+//! to extract.
+
+use super::{tidy, DocumentLoader, LoadedDocument};
+use crate::types::{AppError, Result};
+use flate2::read::ZlibDecoder;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Read;
+use std::sync::Arc;
+
+/// Largest decompressed stream, against zip bombs
+const MAX_STREAM_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Deepest nesting of page tree nodes, form XObjects, and arrays and
+/// dictionaries within a value followed
+const MAX_DEPTH: usize = 32;
+
+/// Reads the text of text-based PDFs.
+///
+/// The title is the `Title` of the document information dictionary.
+pub struct PdfLoader;
+
+impl DocumentLoader for PdfLoader {
+    fn name(&self) -> &'static str {
+        "PDF"
+    }
+
+    fn media_types(&self) -> &'static [&'static str] {
+        &["application/pdf", "application/x-pdf"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["pdf"]
+    }
+
+    /// The `%PDF-` header, which may follow up to 1 KiB of junk
+    fn sniff(&self, bytes: &[u8]) -> bool {
+        find(&bytes[..bytes.len().min(1024)], b"%PDF-", 0).is_some()
+    }
+
+    fn load(&self, bytes: &[u8]) -> Result<LoadedDocument> {
+        let pdf = Pdf::parse(bytes);
+        if pdf.trailer_entry(b"Encrypt").is_some() {
+            return Err(AppError::InvalidInput("it is encrypted".into()));
+        }
+        let text = tidy(&pdf.text());
+        if text.is_empty() {
+            return Err(AppError::InvalidInput(
+                "it has no extractable text; scanned PDFs need OCR first".into(),
+            ));
+        }
+        let title = pdf
+            .trailer_entry(b"Info")
+            .and_then(|info| pdf.get(info, b"Title"))
+            .and_then(|title| match title {
+                Value::Str(bytes) => Some(text_string(bytes)),
+                _ => None,
+            })
+            .map(|title| title.trim().to_string())
+            .filter(|title| !title.is_empty());
+        Ok(LoadedDocument { text, title })
+    }
+}
+
+// ============================================================================
+// Objects
+// ============================================================================
+
+/// A PDF value, with indirect references left unresolved
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Num(f64),
+    Str(Vec<u8>),
+    Name(Vec<u8>),
+    Array(Vec<Value>),
+    Dict(HashMap<Vec<u8>, Value>),
+    Ref(u32),
+}
+
+impl Value {
+    fn entry(&self, key: &[u8]) -> Option<&Value> {
+        match self {
+            Value::Dict(dict) => dict.get(key),
+            _ => None,
+        }
+    }
+
+    fn is_name(&self, name: &[u8]) -> bool {
+        matches!(self, Value::Name(n) if n == name)
+    }
+}
+
+/// An indirect object: its value, and for a stream its raw data
+struct Object<'a> {
+    value: Value,
+    stream: Option<&'a [u8]>,
+}
+
+/// The objects of a PDF file and its trailer
+struct Pdf<'a> {
+    objects: HashMap<u32, Object<'a>>,
+    /// Objects unpacked from object streams, which have no streams
+    packed: HashMap<u32, Value>,
+    /// Trailer dictionaries (including cross-reference streams'), in file
+    /// order
+    trailers: Vec<Value>,
+}
+
+impl<'a> Pdf<'a> {
+    /// Read every object of a file, a later definition of an object
+    /// replacing an earlier one as incremental updates do
+    fn parse(bytes: &'a [u8]) -> Self {
+        let mut objects = HashMap::new();
+        let mut trailers = Vec::new();
+        let mut pos = 0;
+        while let Some(at) = find(bytes, b"obj", pos) {
+            pos = at + 3;
+            let keyword = at > 0
+                && is_whitespace(bytes[at - 1])
+                && bytes.get(at + 3).is_none_or(|&b| !is_regular(b));
+            let Some(number) = keyword.then(|| object_number(bytes, at)).flatten() else {
+                continue;
+            };
+
+            let mut parser = Parser::new(bytes, pos);
+            let value = parser.next_value().unwrap_or(Value::Null);
+            let mut stream = None;
+            if parser.peek_op() == Some(b"stream".as_slice()) {
+                // Peeking read the keyword, leaving the lexer at its end
+                let mut start = parser.lexer.pos;
+                start += match bytes.get(start..start + 2) {
+                    Some(b"\r\n") => 2,
+                    Some([b'\n' | b'\r', _]) => 1,
+                    _ => 0,
+                };
+                let (data, end) = stream_data(bytes, start, value.entry(b"Length"));
+                stream = Some(data);
+                pos = end;
+            } else {
+                pos = parser.lexer.pos;
+            }
+
+            if value.entry(b"Type").is_some_and(|t| t.is_name(b"XRef")) {
+                trailers.push(value.clone());
+            }
+            objects.insert(number, Object { value, stream });
+        }
+
+        let mut pos = 0;
+        while let Some(at) = find(bytes, b"trailer", pos) {
+            pos = at + "trailer".len();
+            if let Some(value @ Value::Dict(_)) = Parser::new(bytes, pos).next_value() {
+                trailers.push(value);
+            }
+        }
+
+        let mut pdf = Self {
+            objects,
+            packed: HashMap::new(),
+            trailers,
+        };
+        pdf.unpack_object_streams();
+        pdf
+    }
+
+    /// Parse the objects packed in object streams
+    fn unpack_object_streams(&mut self) {
+        let streams = self
+            .objects
+            .iter()
+            .filter(|(_, o)| o.value.entry(b"Type").is_some_and(|t| t.is_name(b"ObjStm")))
+            .map(|(&number, _)| number)
+            .collect::<Vec<_>>();
+        for number in streams {
+            let Some(data) = self.stream(number) else {
+                continue;
+            };
+            let value = &self.objects[&number].value;
+            let (Some(Value::Num(count)), Some(Value::Num(first))) = (
+                value.entry(b"N").map(|n| self.resolve(n)),
+                value.entry(b"First").map(|f| self.resolve(f)),
+            ) else {
+                continue;
+            };
+            let (count, first) = (*count as usize, *first as usize);
+
+            let mut header = Parser::new(&data[..first.min(data.len())], 0);
+            let mut offsets = Vec::with_capacity(count.min(4096));
+            for _ in 0..count {
+                match (header.next_value(), header.next_value()) {
+                    (Some(Value::Num(n)), Some(Value::Num(offset))) => {
+                        offsets.push((n as u32, first + offset as usize))
+                    }
+                    _ => break,
+                }
+            }
+            let mut unpacked = Vec::with_capacity(offsets.len());
+            for (n, offset) in offsets {
+                if offset < data.len() {
+                    if let Some(value) = Parser::new(&data, offset).next_value() {
+                        unpacked.push((n, value));
+                    }
+                }
+            }
+            for (n, value) in unpacked {
+                // A direct object is a later definition than a packed one
+                if !self.objects.contains_key(&n) {
+                    self.packed.entry(n).or_insert(value);
+                }
+            }
+        }
+    }
+
+    /// Follow references to the value they point to
+    fn resolve<'v>(&'v self, mut value: &'v Value) -> &'v Value {
+        for _ in 0..MAX_DEPTH {
+            let Value::Ref(number) = value else {
+                return value;
+            };
+            value = match self.objects.get(number) {
+                Some(object) => &object.value,
+                None => self.packed.get(number).unwrap_or(&Value::Null),
+            };
+        }
+        &Value::Null
+    }
+
+    /// A dictionary's entry, resolved
+    fn get<'v>(&'v self, dict: &'v Value, key: &[u8]) -> Option<&'v Value> {
+        let value = self.resolve(self.resolve(dict).entry(key)?);
+        (*value != Value::Null).then_some(value)
+    }
+
+    /// An entry of the latest trailer that has it, resolved
+    fn trailer_entry(&self, key: &[u8]) -> Option<&Value> {
+        self.trailers
+            .iter()
+            .rev()
+            .find_map(|trailer| trailer.entry(key))
+            .map(|value| self.resolve(value))
+    }
+
+    /// Decoded data of a stream object, or `None` if it uses an
+    /// unsupported filter
+    fn stream(&self, number: u32) -> Option<Vec<u8>> {
+        let object = self.objects.get(&number)?;
+        let mut data = object.stream?.to_vec();
+        let filters = match object.value.entry(b"Filter").map(|f| self.resolve(f)) {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::Array(filters)) => filters.iter().collect(),
+            Some(filter) => vec![filter],
+        };
+        for filter in filters {
+            data = match self.resolve(filter) {
+                Value::Name(name) if name == b"FlateDecode" || name == b"Fl" => inflate(&data),
+                Value::Name(name) if name == b"ASCIIHexDecode" || name == b"AHx" => {
+                    hex_bytes(&data)
+                }
+                _ => return None,
+            };
+        }
+        Some(data)
+    }
+
+    /// Decoded data of a stream, or of each stream of an array, joined
+    ///
+    /// Arrays of streams are walked without recursion, each referenced array
+    /// once, so arrays that refer to themselves end.
+    fn streams<'v>(&'v self, contents: &'v Value) -> Vec<u8> {
+        let mut seen = HashSet::new();
+        let mut pending = vec![contents];
+        let mut parts = Vec::new();
+        while let Some(value) = pending.pop() {
+            match value {
+                Value::Ref(number) => match self.resolve(value) {
+                    Value::Array(items) => {
+                        if seen.insert(*number) {
+                            pending.extend(items.iter().rev());
+                        }
+                    }
+                    _ => parts.push(self.stream(*number).unwrap_or_default()),
+                },
+                Value::Array(items) => pending.extend(items.iter().rev()),
+                _ => {}
+            }
+        }
+        parts.join(&b'\n')
+    }
+
+    // ========================================================================
+    // Text
+    // ========================================================================
+
+    /// Text of every page, in order
+    ///
+    /// A damaged file whose page tree can't be walked has its page objects
+    /// read in object order instead.
+    fn text(&self) -> String {
+        let mut out = String::new();
+        let mut fonts = HashMap::new();
+        let root = self.trailer_entry(b"Root").or_else(|| {
+            self.objects
+                .values()
+                .map(|o| &o.value)
+                .chain(self.packed.values())
+                .find(|v| v.entry(b"Type").is_some_and(|t| t.is_name(b"Catalog")))
+        });
+        if let Some(pages) = root.and_then(|root| root.entry(b"Pages")) {
+            self.page_tree_text(pages, None, 0, &mut fonts, &mut out);
+        }
+        if out.trim().is_empty() {
+            let mut pages = self
+                .objects
+                .iter()
+                .filter(|(_, o)| o.value.entry(b"Type").is_some_and(|t| t.is_name(b"Page")))
+                .map(|(&number, _)| number)
+                .collect::<Vec<_>>();
+            pages.sort_unstable();
+            for number in pages {
+                let page = Value::Ref(number);
+                let resources = self.inherited_resources(&page);
+                self.page_tree_text(&page, resources, 0, &mut fonts, &mut out);
+            }
+        }
+        out
+    }
+
+    /// Resources a page inherits from its ancestors
+    fn inherited_resources<'v>(&'v self, page: &'v Value) -> Option<&'v Value> {
+        let mut node = self.get(page, b"Parent")?;
+        for _ in 0..MAX_DEPTH {
+            if let Some(resources) = self.get(node, b"Resources") {
+                return Some(resources);
+            }
+            node = self.get(node, b"Parent")?;
+        }
+        None
+    }
+
+    fn page_tree_text(
+        &self,
+        node_ref: &Value,
+        inherited: Option<&Value>,
+        depth: usize,
+        fonts: &mut HashMap<u32, Arc<Font>>,
+        out: &mut String,
+    ) {
+        let node = self.resolve(node_ref);
+        if depth > MAX_DEPTH {
+            return;
+        }
+        let resources = self.get(node, b"Resources").or(inherited);
+        if let Some(Value::Array(kids)) = self.get(node, b"Kids") {
+            for kid in kids {
+                self.page_tree_text(kid, resources, depth + 1, fonts, out);
+            }
+            return;
+        }
+        if let Some(contents) = node.entry(b"Contents") {
+            let content = self.streams(contents);
+            self.content_text(&content, resources, 0, fonts, out);
+            out.push_str("\n\n");
+        }
+    }
+
+    /// Text of a content stream drawn with `resources`
+    fn content_text(
+        &self,
+        content: &[u8],
+        resources: Option<&Value>,
+        depth: usize,
+        fonts: &mut HashMap<u32, Arc<Font>>,
+        out: &mut String,
+    ) {
+        let font_dict = resources.and_then(|r| self.get(r, b"Font"));
+        let mut font: Option<Arc<Font>> = None;
+        let mut line_y: Option<f64> = None;
+        let mut operands: Vec<Value> = Vec::new();
+        let mut parser = Parser::new(content, 0);
+
+        while let Some(item) = parser.next_item() {
+            let op = match item {
+                Item::Value(value) => {
+                    operands.push(value);
+                    continue;
+                }
+                Item::Op(op) => op,
+            };
+            let number = |i: usize| match operands.get(i) {
+                Some(Value::Num(n)) => *n,
+                _ => 0.0,
+            };
+            match op {
+                b"Tf" => {
+                    font = match (operands.first(), font_dict) {
+                        (Some(Value::Name(name)), Some(dict)) => {
+                            dict.entry(name).map(|font_ref| self.font(font_ref, fonts))
+                        }
+                        _ => None,
+                    }
+                }
+                b"Tj" | b"'" | b"\"" => {
+                    if op != b"Tj" {
+                        new_line(out);
+                    }
+                    if let Some(Value::Str(s)) = operands.last() {
+                        out.push_str(&decode_shown(font.as_deref(), s));
+                    }
+                }
+                b"TJ" => {
+                    if let Some(Value::Array(parts)) = operands.last() {
+                        for part in parts {
+                            match part {
+                                Value::Str(s) => out.push_str(&decode_shown(font.as_deref(), s)),
+                                // A wide negative adjustment is a word gap
+                                Value::Num(n) if *n < -200.0 => space(out),
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                b"T*" => new_line(out),
+                b"Td" | b"TD" => {
+                    if number(1).abs() > 0.01 {
+                        new_line(out);
+                    } else if number(0) > 0.0 {
+                        space(out);
+                    }
+                }
+                b"Tm" => {
+                    let y = number(5);
+                    match line_y {
+                        Some(previous) if (previous - y).abs() > 0.01 => new_line(out),
+                        Some(_) => space(out),
+                        None => {}
+                    }
+                    line_y = Some(y);
+                }
+                b"Do" if depth < MAX_DEPTH => {
+                    let xobject = match (operands.first(), resources) {
+                        (Some(Value::Name(name)), Some(resources)) => self
+                            .get(resources, b"XObject")
+                            .and_then(|xobjects| xobjects.entry(name)),
+                        _ => None,
+                    };
+                    if let Some(Value::Ref(number)) = xobject {
+                        let form = self.objects.get(number).map(|o| &o.value);
+                        if form.is_some_and(|f| {
+                            f.entry(b"Subtype").is_some_and(|s| s.is_name(b"Form"))
+                        }) {
+                            let form_resources = form
+                                .and_then(|f| f.entry(b"Resources"))
+                                .map(|r| self.resolve(r))
+                                .or(resources);
+                            let data = self.stream(*number).unwrap_or_default();
+                            new_line(out);
+                            self.content_text(&data, form_resources, depth + 1, fonts, out);
+                            new_line(out);
+                        }
+                    }
+                }
+                b"ID" => parser.skip_inline_image(),
+                _ => {}
+            }
+            operands.clear();
+        }
+    }
+
+    /// The font a font resource refers to, built once per object
+    fn font(&self, font_ref: &Value, fonts: &mut HashMap<u32, Arc<Font>>) -> Arc<Font> {
+        let number = match font_ref {
+            Value::Ref(number) => Some(*number),
+            _ => None,
+        };
+        if let Some(font) = number.and_then(|n| fonts.get(&n)) {
+            return Arc::clone(font);
+        }
+        let dict = self.resolve(font_ref);
+        let composite = dict.entry(b"Subtype").is_some_and(|s| s.is_name(b"Type0"));
+        let cmap = match dict.entry(b"ToUnicode") {
+            Some(Value::Ref(number)) => self.stream(*number).map(|data| CMap::parse(&data)),
+            _ => None,
+        };
+        let font = Arc::new(Font {
+            code_bytes: cmap
+                .as_ref()
+                .and_then(|c| c.code_bytes)
+                .unwrap_or(if composite { 2 } else { 1 }),
+            cmap,
+        });
+        if let Some(number) = number {
+            fonts.insert(number, Arc::clone(&font));
+        }
+        font
+    }
+}
+
+/// Number of the object whose `obj` keyword is at `at`: `<number> <gen> obj`
+fn object_number(bytes: &[u8], at: usize) -> Option<u32> {
+    let before = &bytes[at.saturating_sub(32)..at];
+    let mut fields = before
+        .split(|&b| is_whitespace(b))
+        .filter(|f| !f.is_empty())
+        .rev();
+    let generation = fields.next()?;
+    let number = fields.next()?;
+    if !generation.iter().all(u8::is_ascii_digit) || !number.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(number).ok()?.parse().ok()
+}
+
+/// Raw data of a stream starting at `start`, and where its object ends
+///
+/// A direct `Length` is trusted when `endstream` follows it; otherwise the
+/// data runs to the next `endstream`.
+fn stream_data<'a>(bytes: &'a [u8], start: usize, length: Option<&Value>) -> (&'a [u8], usize) {
+    if let Some(Value::Num(length)) = length {
+        let end = start.saturating_add(*length as usize);
+        if let Some(rest) = bytes.get(end..) {
+            if rest.trim_ascii_start().starts_with(b"endstream") {
+                return (&bytes[start..end], end);
+            }
+        }
+    }
+    let start = start.min(bytes.len());
+    let end = find(bytes, b"endstream", start).unwrap_or(bytes.len());
+    let mut data = &bytes[start..end];
+    data = data.strip_suffix(b"\n").unwrap_or(data);
+    data = data.strip_suffix(b"\r").unwrap_or(data);
+    (data, end)
+}
+
+/// Decompress Flate data, keeping what decompresses of a truncated stream
+fn inflate(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let _ = ZlibDecoder::new(data)
+        .take(MAX_STREAM_BYTES)
+        .read_to_end(&mut out);
+    out
+}
+
+// ============================================================================
+// Fonts
+// ============================================================================
+
+/// How a font's character codes map to text
+struct Font {
+    /// Bytes per character code
+    code_bytes: usize,
+    cmap: Option<CMap>,
+}
+
+/// A `ToUnicode` CMap: character codes to the text they show
+#[derive(Debug, Default)]
+struct CMap {
+    code_bytes: Option<usize>,
+    map: HashMap<u32, String>,
+}
+
+impl CMap {
+    fn parse(data: &[u8]) -> Self {
+        let mut cmap = CMap::default();
+        let mut parser = Parser::new(data, 0);
+        let mut operands = Vec::new();
+        let mut section: Option<&[u8]> = None;
+        while let Some(item) = parser.next_item() {
+            let op = match item {
+                Item::Value(value) => {
+                    match section {
+                        Some(b"beginbfchar") if operands.len() == 1 => {
+                            cmap.add_char(&operands[0], &value);
+                            operands.clear();
+                        }
+                        Some(b"beginbfrange") if operands.len() == 2 => {
+                            cmap.add_range(&operands[0], &operands[1], &value);
+                            operands.clear();
+                        }
+                        _ => operands.push(value),
+                    }
+                    continue;
+                }
+                Item::Op(op) => op,
+            };
+            match op {
+                b"begincodespacerange" | b"beginbfchar" | b"beginbfrange" => section = Some(op),
+                b"endcodespacerange" => {
+                    if let Some(Value::Str(low)) = operands.first() {
+                        cmap.code_bytes = Some(low.len().clamp(1, 4));
+                    }
+                    section = None;
+                }
+                _ => section = section.filter(|_| !op.starts_with(b"end")),
+            }
+            operands.clear();
+        }
+        cmap
+    }
+
+    fn add_char(&mut self, code: &Value, text: &Value) {
+        if let (Value::Str(code), Value::Str(text)) = (code, text) {
+            self.map.insert(code_value(code), utf16_be(text));
+        }
+    }
+
+    fn add_range(&mut self, low: &Value, high: &Value, text: &Value) {
+        let (Value::Str(low), Value::Str(high)) = (low, high) else {
+            return;
+        };
+        let (low, high) = (code_value(low), code_value(high));
+        // Bound the range against malformed maps
+        for (i, code) in (low..=high.min(low.saturating_add(0xffff))).enumerate() {
+            let text = match text {
+                Value::Array(texts) => match texts.get(i) {
+                    Some(Value::Str(text)) => utf16_be(text),
+                    _ => break,
+                },
+                Value::Str(first) => {
+                    // The last code unit increments along the range
+                    let mut units = first
+                        .chunks(2)
+                        .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]))
+                        .collect::<Vec<_>>();
+                    if let Some(last) = units.last_mut() {
+                        *last = last.wrapping_add(i as u16);
+                    }
+                    String::from_utf16_lossy(&units)
+                }
+                _ => return,
+            };
+            self.map.insert(code, text);
+        }
+    }
+}
+
+/// Text shown by a string in `font`
+fn decode_shown(font: Option<&Font>, bytes: &[u8]) -> String {
+    let Some(font) = font else {
+        return bytes.iter().map(|&b| win_ansi(b)).collect();
+    };
+    bytes
+        .chunks(font.code_bytes)
+        .filter_map(|code| {
+            let value = code_value(code);
+            match &font.cmap {
+                Some(cmap) => cmap
+                    .map
+                    .get(&value)
+                    .cloned()
+                    .or_else(|| (font.code_bytes == 1).then(|| win_ansi(code[0]).to_string())),
+                // Composite font codes are glyph IDs, meaningless without a map
+                None if font.code_bytes > 1 => None,
+                None => Some(win_ansi(code[0]).to_string()),
+            }
+        })
+        .collect()
+}
+
+fn code_value(code: &[u8]) -> u32 {
+    code.iter().fold(0, |value, &b| (value << 8) | b as u32)
+}
+
+fn utf16_be(bytes: &[u8]) -> String {
+    let units = bytes
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]))
+        .collect::<Vec<_>>();
+    String::from_utf16_lossy(&units)
+}
+
+/// A text string (document information): UTF-16 or UTF-8 with a byte order
+/// mark, or PDFDocEncoding
+fn text_string(bytes: &[u8]) -> String {
+    if let Some(rest) = bytes.strip_prefix(b"\xfe\xff") {
+        utf16_be(rest)
+    } else if let Some(rest) = bytes.strip_prefix(b"\xef\xbb\xbf") {
+        String::from_utf8_lossy(rest).into_owned()
+    } else {
+        bytes.iter().map(|&b| win_ansi(b)).collect()
+    }
+}
+
+/// A byte of WinAnsiEncoding, which most simple fonts use; Latin-1 but for
+/// the punctuation at 0x80 to 0x9f
+fn win_ansi(byte: u8) -> char {
+    match byte {
+        0x80 => '€',
+        0x85 => '…',
+        0x91 => '‘',
+        0x92 => '’',
+        0x93 => '“',
+        0x94 => '”',
+        0x95 => '•',
+        0x96 => '–',
+        0x97 => '—',
+        0x99 => '™',
+        b => b as char,
+    }
+}
+
+fn new_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+fn space(out: &mut String) {
+    if !out.is_empty() && !out.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+}
+
+// ============================================================================
+// Syntax
+// ============================================================================
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b'\0' | b'\t' | b'\n' | b'\x0c' | b'\r' | b' ')
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(
+        b,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+fn is_regular(b: u8) -> bool {
+    !is_whitespace(b) && !is_delimiter(b)
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|i| from + i)
+}
+
+fn hex_bytes(hex: &[u8]) -> Vec<u8> {
+    let digits = hex
+        .iter()
+        .take_while(|&&b| b != b'>')
+        .filter_map(|&b| (b as char).to_digit(16))
+        .map(|d| d as u8)
+        .collect::<Vec<_>>();
+    digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    Num(f64),
+    Str(Vec<u8>),
+    Name(&'a [u8]),
+    ArrayStart,
+    ArrayEnd,
+    DictStart,
+    DictEnd,
+    Op(&'a [u8]),
+}
+
+struct Lexer<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let data = self.data;
+        loop {
+            let &b = data.get(self.pos)?;
+            if is_whitespace(b) {
+                self.pos += 1;
+            } else if b == b'%' {
+                while data
+                    .get(self.pos)
+                    .is_some_and(|&b| b != b'\n' && b != b'\r')
+                {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+
+        let start = self.pos;
+        self.pos += 1;
+        Some(match data[start] {
+            b'(' => Token::Str(self.literal_string()),
+            b'<' if data.get(self.pos) == Some(&b'<') => {
+                self.pos += 1;
+                Token::DictStart
+            }
+            b'<' => {
+                let end = find(data, b">", self.pos).unwrap_or(data.len());
+                let bytes = hex_bytes(&data[self.pos..end]);
+                self.pos = end + 1;
+                Token::Str(bytes)
+            }
+            b'>' => {
+                if data.get(self.pos) == Some(&b'>') {
+                    self.pos += 1;
+                }
+                Token::DictEnd
+            }
+            b'[' => Token::ArrayStart,
+            b']' => Token::ArrayEnd,
+            b'/' => {
+                let end = self.regular_end();
+                Token::Name(&data[start + 1..end])
+            }
+            b'{' | b'}' | b')' => Token::Op(&data[start..self.pos]),
+            _ => {
+                let end = self.regular_end();
+                let word = &data[start..end];
+                let numeric = matches!(word[0], b'0'..=b'9' | b'+' | b'-' | b'.');
+                match std::str::from_utf8(word).ok().and_then(|w| w.parse().ok()) {
+                    Some(n) if numeric => Token::Num(n),
+                    _ => Token::Op(word),
+                }
+            }
+        })
+    }
+}
+
+impl Lexer<'_> {
+    /// Move past the rest of a run of regular characters, returning its end
+    fn regular_end(&mut self) -> usize {
+        while self.data.get(self.pos).is_some_and(|&b| is_regular(b)) {
+            self.pos += 1;
+        }
+        self.pos
+    }
+
+    /// The rest of a literal string, whose `(` was just read
+    fn literal_string(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut depth = 0;
+        while let Some(&b) = self.data.get(self.pos) {
+            self.pos += 1;
+            match b {
+                b'(' => {
+                    depth += 1;
+                    out.push(b);
+                }
+                b')' if depth == 0 => break,
+                b')' => {
+                    depth -= 1;
+                    out.push(b);
+                }
+                b'\\' => {
+                    let Some(&escaped) = self.data.get(self.pos) else {
+                        break;
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'b' => out.push(b'\x08'),
+                        b'f' => out.push(b'\x0c'),
+                        b'0'..=b'7' => {
+                            let mut code = (escaped - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.data.get(self.pos) {
+                                    Some(&d @ b'0'..=b'7') => {
+                                        code = code * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            out.push(code as u8);
+                        }
+                        // A backslash before a line break continues the string
+                        b'\r' => {
+                            if self.data.get(self.pos) == Some(&b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => out.push(other),
+                    }
+                }
+                b => out.push(b),
+            }
+        }
+        out
+    }
+}
+
+/// An operand or an operator of a content stream
+enum Item<'a> {
+    Value(Value),
+    Op(&'a [u8]),
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    peeked: VecDeque<Token<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self {
+            lexer: Lexer { data, pos },
+            peeked: VecDeque::new(),
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        self.peeked.pop_front().or_else(|| self.lexer.next())
+    }
+
+    fn peek(&mut self, n: usize) -> Option<&Token<'a>> {
+        while self.peeked.len() <= n {
+            let token = self.lexer.next()?;
+            self.peeked.push_back(token);
+        }
+        self.peeked.get(n)
+    }
+
+    /// The next token if it is an operator, without consuming it
+    fn peek_op(&mut self) -> Option<&'a [u8]> {
+        match self.peek(0) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    /// The next value; null when an operator comes first
+    fn next_value(&mut self) -> Option<Value> {
+        match self.next_item()? {
+            Item::Value(value) => Some(value),
+            Item::Op(_) => Some(Value::Null),
+        }
+    }
+
+    fn next_item(&mut self) -> Option<Item<'a>> {
+        let token = self.next_token()?;
+        Some(match token {
+            Token::Op(op) => Item::Op(op),
+            token => Item::Value(self.value(token, 0)),
+        })
+    }
+
+    /// The value `token` starts, `depth` arrays and dictionaries deep; one
+    /// nested deeper than `MAX_DEPTH` is skipped and read as null
+    fn value(&mut self, token: Token<'a>, depth: usize) -> Value {
+        if depth >= MAX_DEPTH && matches!(token, Token::ArrayStart | Token::DictStart) {
+            self.skip_nested();
+            return Value::Null;
+        }
+        match token {
+            Token::Num(n) => {
+                let reference = matches!(self.peek(0), Some(Token::Num(_)))
+                    && matches!(self.peek(1), Some(Token::Op(b"R")));
+                if reference {
+                    self.next_token();
+                    self.next_token();
+                    Value::Ref(n as u32)
+                } else {
+                    Value::Num(n)
+                }
+            }
+            Token::Str(s) => Value::Str(s),
+            Token::Name(name) => Value::Name(name.to_vec()),
+            Token::ArrayStart => {
+                let mut items = Vec::new();
+                while let Some(token) = self.next_token() {
+                    match token {
+                        Token::ArrayEnd => break,
+                        Token::Op(_) | Token::DictEnd => items.push(Value::Null),
+                        token => items.push(self.value(token, depth + 1)),
+                    }
+                }
+                Value::Array(items)
+            }
+            Token::DictStart => {
+                let mut dict = HashMap::new();
+                while let Some(token) = self.next_token() {
+                    match token {
+                        Token::DictEnd => break,
+                        Token::Name(key) => {
+                            let value = match self.next_token() {
+                                Some(Token::DictEnd) => {
+                                    dict.insert(key.to_vec(), Value::Null);
+                                    break;
+                                }
+                                Some(Token::Op(_)) | None => Value::Null,
+                                Some(token) => self.value(token, depth + 1),
+                            };
+                            dict.insert(key.to_vec(), value);
+                        }
+                        _ => {}
+                    }
+                }
+                Value::Dict(dict)
+            }
+            Token::ArrayEnd | Token::DictEnd | Token::Op(_) => Value::Null,
+        }
+    }
+
+    /// Skip the rest of an array or dictionary whose start was just read,
+    /// along with everything nested in it
+    fn skip_nested(&mut self) {
+        let mut open = 1;
+        while let Some(token) = self.next_token() {
+            match token {
+                Token::ArrayStart | Token::DictStart => open += 1,
+                Token::ArrayEnd | Token::DictEnd => {
+                    open -= 1;
+                    if open == 0 {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Skip the data of an inline image, whose `ID` operator was just read,
+    /// up to its `EI` operator
+    fn skip_inline_image(&mut self) {
+        self.peeked.clear();
+        let data = self.lexer.data;
+        let mut pos = self.lexer.pos + 1;
+        while let Some(at) = find(data, b"EI", pos) {
+            let delimited =
+                is_whitespace(data[at - 1]) && data.get(at + 2).is_none_or(|&b| is_whitespace(b));
+            if delimited {
+                self.lexer.pos = at + 2;
+                return;
+            }
+            pos = at + 2;
+        }
+        self.lexer.pos = data.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// A PDF file of the given numbered objects, with a trailer
+    fn pdf(objects: &[(u32, Vec<u8>)], trailer: &str) -> Vec<u8> {
+        let mut out = b"%PDF-1.7\n".to_vec();
+        for (number, object) in objects {
+            out.extend(format!("{} 0 obj\n", number).as_bytes());
+            out.extend(object);
+            out.extend(b"\nendobj\n");
+        }
+        out.extend(format!("trailer\n{}\n%%EOF\n", trailer).as_bytes());
+        out
+    }
+
+    fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+        let mut out = format!("<< {} /Length {} >>\nstream\n", dict, data.len()).into_bytes();
+        out.extend(data);
+        out.extend(b"\nendstream");
+        out
+    }
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_simple_fonts_and_layout() {
+        let content =
+            b"BT /F1 12 Tf 72 720 Td [(Hello,) -250 (W) 30 (orld)] TJ 20 0 Td (again) Tj \
+                        0 -14 Td (Line \\(two\\)) Tj T* <416263> Tj ET";
+        let bytes = pdf(
+            &[
+                (1, b"<< /Type /Catalog /Pages 2 0 R >>".to_vec()),
+                (
+                    2,
+                    b"<< /Type /Pages /Kids [3 0 R] /Count 1 \
+                      /Resources << /Font << /F1 5 0 R >> >> >>"
+                        .to_vec(),
+                ),
+                (
+                    3,
+                    b"<< /Type /Page /Parent 2 0 R /Contents 4 0 R >>".to_vec(),
+                ),
+                (4, stream("/Filter /FlateDecode", &deflate(content))),
+                (
+                    5,
+                    b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_vec(),
+                ),
+                (6, b"<< /Title (Quarterly \\222report\\222) >>".to_vec()),
+            ],
+            "<< /Root 1 0 R /Info 6 0 R /Size 7 >>",
+        );
+
+        assert!(PdfLoader.sniff(&bytes));
+        let document = PdfLoader.load(&bytes).unwrap();
+        assert_eq!(document.text, "Hello, World again\nLine (two)\nAbc");
+        assert_eq!(document.title.as_deref(), Some("Quarterly ’report’"));
+    }
+
+    #[test]
+    fn test_composite_fonts_with_to_unicode_in_object_streams() {
+        let cmap = b"/CIDInit /ProcSet findresource begin 12 dict begin begincmap \
+                     1 begincodespacerange <0000> <FFFF> endcodespacerange \
+                     2 beginbfchar <0003> <0020> <0010> <00660069> endbfchar \
+                     1 beginbfrange <0020> <0022> <0061> endbfrange \
+                     endcmap end end";
+        let content = b"BT /C0 10 Tf 1 0 0 1 50 700 Tm <0010002000030021> Tj \
+                        1 0 0 1 50 680 Tm <0022> Tj ET";
+        // Objects 1 and 2 are packed in object stream 7
+        let mut objstm = b"1 0 2 34 ".to_vec();
+        objstm.extend(b"<< /Type /Catalog /Pages 2 0 R >> << /Type /Pages /Kids [3 0 R] >>");
+        let bytes = pdf(
+            &[
+                (
+                    3,
+                    b"<< /Type /Page /Parent 2 0 R /Contents [4 0 R] \
+                      /Resources << /Font << /C0 5 0 R >> >> >>"
+                        .to_vec(),
+                ),
+                (4, stream("", content)),
+                (
+                    5,
+                    b"<< /Type /Font /Subtype /Type0 /Encoding /Identity-H /ToUnicode 6 0 R >>"
+                        .to_vec(),
+                ),
+                (6, stream("/Filter /FlateDecode", &deflate(cmap))),
+                (
+                    7,
+                    stream(
+                        "/Type /ObjStm /N 2 /First 9 /Filter /FlateDecode",
+                        &deflate(&objstm),
+                    ),
+                ),
+            ],
+            "<< /Root 1 0 R /Size 8 >>",
+        );
+
+        let document = PdfLoader.load(&bytes).unwrap();
+        assert_eq!(document.text, "fia b\nc");
+        assert_eq!(document.title, None);
+    }
+
+    #[test]
+    fn test_rejects_encrypted_and_textless_pdfs() {
+        let encrypted = pdf(
+            &[
+                (1, b"<< /Type /Catalog >>".to_vec()),
+                (2, b"<< /Filter /Standard >>".to_vec()),
+            ],
+            "<< /Root 1 0 R /Encrypt 2 0 R >>",
+        );
+        assert!(PdfLoader.load(&encrypted).is_err());
+
+        let scanned = pdf(
+            &[
+                (1, b"<< /Type /Catalog /Pages 2 0 R >>".to_vec()),
+                (2, b"<< /Type /Pages /Kids [3 0 R] >>".to_vec()),
+                (3, b"<< /Type /Page /Contents 4 0 R >>".to_vec()),
+                (4, stream("", b"q 612 0 0 792 0 0 cm /Im0 Do Q")),
+            ],
+            "<< /Root 1 0 R >>",
+        );
+        assert!(PdfLoader.load(&scanned).is_err());
+    }
+
+    #[test]
+    fn test_survives_deep_nesting_and_reference_cycles() {
+        let bytes = pdf(
+            &[
+                (1, b"<< /Type /Catalog /Pages 2 0 R >>".to_vec()),
+                (
+                    2,
+                    b"<< /Type /Pages /Kids [3 0 R] \
+                      /Resources << /Font << /F1 5 0 R >> >> >>"
+                        .to_vec(),
+                ),
+                (3, b"<< /Type /Page /Contents 4 0 R >>".to_vec()),
+                // A contents array that contains itself
+                (4, b"[6 0 R 4 0 R]".to_vec()),
+                (
+                    5,
+                    b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_vec(),
+                ),
+                (6, stream("", b"BT /F1 12 Tf (Deep) Tj ET")),
+                (7, b"[".repeat(100_000)),
+                (8, b"<< /K ".repeat(100_000)),
+            ],
+            "<< /Root 1 0 R >>",
+        );
+
+        let document = PdfLoader.load(&bytes).unwrap();
+        assert_eq!(document.text, "Deep");
+    }
+}
@@ -0,0 +1,63 @@
+//! Plain text files.
+
+use super::{DocumentLoader, LoadedDocument};
+use crate::types::{AppError, Result};
+
+/// Reads UTF-8 text, and UTF-16 text starting with a byte order mark.
+pub struct TextLoader;
+
+impl DocumentLoader for TextLoader {
+    fn name(&self) -> &'static str {
+        "plain text"
+    }
+
+    fn media_types(&self) -> &'static [&'static str] {
+        &["text/plain", "application/json", "text/x-log"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["txt", "text", "log", "json"]
+    }
+
+    fn load(&self, bytes: &[u8]) -> Result<LoadedDocument> {
+        let text = decode(bytes)
+            .ok_or_else(|| AppError::InvalidInput("it is not UTF-8 or UTF-16 text".into()))?;
+        Ok(LoadedDocument { text, title: None })
+    }
+}
+
+/// Decode text, or `None` if `bytes` aren't text
+pub(super) fn decode(bytes: &[u8]) -> Option<String> {
+    let utf16 = |bytes: &[u8], from: fn([u8; 2]) -> u16| {
+        let units = bytes
+            .chunks_exact(2)
+            .map(|pair| from([pair[0], pair[1]]))
+            .collect::<Vec<_>>();
+        String::from_utf16(&units).ok()
+    };
+    let text = if let Some(rest) = bytes.strip_prefix(b"\xef\xbb\xbf") {
+        std::str::from_utf8(rest).ok()?.to_string()
+    } else if let Some(rest) = bytes.strip_prefix(b"\xff\xfe") {
+        utf16(rest, u16::from_le_bytes)?
+    } else if let Some(rest) = bytes.strip_prefix(b"\xfe\xff") {
+        utf16(rest, u16::from_be_bytes)?
+    } else {
+        std::str::from_utf8(bytes).ok()?.to_string()
+    };
+    // NUL characters mark binary formats that happen to decode
+    (!text.contains('\0')).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(b"\xef\xbb\xbfhi").as_deref(), Some("hi"));
+        assert_eq!(decode(b"\xff\xfeh\0i\0").as_deref(), Some("hi"));
+        assert_eq!(decode(b"\xfe\xff\0h\0i").as_deref(), Some("hi"));
+        assert!(decode(b"\xff\xd8\xff").is_none());
+        assert!(decode(b"PK\0\x03").is_none());
+    }
+}
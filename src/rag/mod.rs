@@ -7,9 +7,21 @@
 //!
 //! - `rag::embeddings` - Dense embedding models (fastembed, 38+ models) **[requires `local-embeddings` feature]**
 //! - [`rag::search`](crate::rag::search) - Search strategies (semantic, BM25, fuzzy, hybrid)
-//! - `rag::reranker` - Cross-encoder reranking for improved relevance **[requires `local-embeddings` feature]**
+//! - [`rag::pipeline`](crate::rag::pipeline) - Retriever running the strategies in parallel, with RRF fusion and reranking
+//! - [`rag::reranker`](crate::rag::reranker) - Reranking with local cross-encoders (**[requires `local-embeddings` feature]**), Cohere Rerank or an LLM judge
 //! - [`rag::chunker`](crate::rag::chunker) - Text chunking for document processing
+//! - [`rag::connectors`](crate::rag::connectors) - Document sources for bulk ingestion (S3, GCS, Notion, Confluence, GitHub)
+//! - [`rag::ingest_jobs`](crate::rag::ingest_jobs) - Checkpointed background ingestion from document sources
+//! - [`rag::loaders`](crate::rag::loaders) - Text extraction from uploaded PDF, DOCX, HTML, Markdown, CSV and text files
+//! - [`rag::parents`](crate::rag::parents) - Parent sections that matching chunks expand to (small-to-big retrieval)
+//! - [`rag::feedback`](crate::rag::feedback) - Chunk-level relevance feedback that tunes search ranking
+//! - [`rag::answer_cache`](crate::rag::answer_cache) - Agent answers reused for similar questions
+//! - [`rag::attachments`](crate::rag::attachments) - Per-conversation collections of attached files
+//! - [`rag::scope`](crate::rag::scope) - Collections a conversation's retrieval is pinned to
+//! - [`rag::intent`](crate::rag::intent) - Query intent classification for skipping retrieval on small talk
 //! - [`rag::cache`](crate::rag::cache) - Embedding cache for avoiding recomputation
+//! - [`rag::batcher`](crate::rag::batcher) - Coalesces concurrent embedding requests into batch calls
+//! - [`rag::remote_embeddings`](crate::rag::remote_embeddings) - Embeddings from OpenAI-compatible or Ollama APIs
 //!
 //! # Feature Flags
 //!
@@ -21,8 +33,9 @@
 //! in `ort-sys`. Use WSL, Linux, or macOS for local embeddings, or use remote embedding APIs.
 //!
 //! Without `local-embeddings`, you can still use:
-//! - Remote embedding APIs (OpenAI embeddings, Ollama embeddings, etc.)
+//! - Remote embedding APIs (OpenAI embeddings, Ollama embeddings, etc.) via `[rag] embedding_provider`
 //! - The chunker and search modules
+//! - Cohere and LLM-judge reranking
 //! - The cache module (if you have embeddings from elsewhere)
 //!
 //! # RAG Pipeline
@@ -32,7 +45,7 @@
 //! 1. **Ingestion** - Documents are chunked and embedded
 //! 2. **Storage** - Embeddings stored in vector database
 //! 3. **Retrieval** - Query embedded, similar chunks retrieved
-//! 4. **Reranking** - Cross-encoder reranks for relevance
+//! 4. **Reranking** - A reranker rescores results for relevance
 //! 5. **Generation** - LLM generates response with context
 //!
 //! # Example
@@ -74,10 +87,21 @@ compile_error!(
     4. Disable this feature: cargo build --no-default-features --features \"...\""
 );
 
+pub mod answer_cache;
+pub mod attachments;
+pub mod batcher;
 pub mod cache;
 pub mod chunker;
+pub mod connectors;
 #[cfg(feature = "local-embeddings")]
 pub mod embeddings;
-#[cfg(feature = "local-embeddings")]
+pub mod feedback;
+pub mod ingest_jobs;
+pub mod intent;
+pub mod loaders;
+pub mod parents;
+pub mod pipeline;
 pub mod reranker;
+pub mod remote_embeddings;
+pub mod scope;
 pub mod search;
@@ -0,0 +1,272 @@
+//! Parent-child chunk retrieval (small-to-big).
+//!
+//! Small chunks embed precisely but carry little context; large ones carry
+//! context but embed vaguely. A collection with a `parent_chunk_size` gets
+//! both: each document is first split into parent sections, and each
+//! section into the child chunks that are embedded and searched. A child
+//! that matches a query is then expanded to its parent section, plus
+//! `parent_window` neighbouring sections on each side, and that is the
+//! context handed to the LLM.
+//!
+//! Parent sections are kept per document in a JSON file next to the vector
+//! data, keyed by the document's chunk ID prefix (chunk `i` of a document is
+//! `{prefix}_{i}`), and replaced whenever the document is re-ingested.
+
+use crate::rag::chunker::TextChunker;
+use crate::types::{AppError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// A parent section of a document and the child chunks it was split into.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParentSection {
+    /// Index of the section's first child chunk
+    pub first_chunk: usize,
+    /// Number of child chunks
+    pub chunks: usize,
+    /// Section text
+    pub content: String,
+}
+
+/// Context a child chunk expands to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParentContext {
+    /// Chunk ID prefix of the document
+    pub document: String,
+    /// Index of the section holding the chunk
+    pub section: usize,
+    /// Text of the section and its neighbours within the window
+    pub content: String,
+}
+
+/// Split a document into parent sections of up to `parent_size` characters,
+/// breaking at paragraphs and sentences where possible, and each section into
+/// child chunks with `chunker`.
+///
+/// Returns the child chunks, numbered across sections, and the sections.
+pub fn split(
+    text: &str,
+    parent_size: usize,
+    chunker: &TextChunker,
+) -> (Vec<String>, Vec<ParentSection>) {
+    let mut parents = TextChunker::with_semantic_chunking(parent_size).chunk(text);
+    if parents.is_empty() && !text.trim().is_empty() {
+        parents.push(text.trim().to_string());
+    }
+
+    let mut children = Vec::new();
+    let sections = parents
+        .into_iter()
+        .map(|content| {
+            let first_chunk = children.len();
+            children.extend(chunker.chunk(&content));
+            ParentSection {
+                first_chunk,
+                chunks: children.len() - first_chunk,
+                content,
+            }
+        })
+        .collect();
+    (children, sections)
+}
+
+/// Split a chunk ID into its document's prefix and the chunk's index
+pub fn chunk_document(chunk_id: &str) -> Option<(&str, usize)> {
+    let (document, index) = chunk_id.rsplit_once('_')?;
+    Some((document, index.parse().ok()?))
+}
+
+/// Keeps the parent sections of each document in a JSON file.
+pub struct ParentStore {
+    dir: PathBuf,
+}
+
+impl ParentStore {
+    /// Create a store keeping parent sections in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Collection and document names may hold any character; hash them into
+    /// file names
+    fn hashed(name: &str) -> String {
+        let digest = hex::encode(Sha256::digest(name.as_bytes()));
+        digest[..32].to_string()
+    }
+
+    fn collection_dir(&self, collection: &str) -> PathBuf {
+        self.dir.join(Self::hashed(collection))
+    }
+
+    fn path(&self, collection: &str, document: &str) -> PathBuf {
+        self.collection_dir(collection)
+            .join(format!("{}.json", Self::hashed(document)))
+    }
+
+    /// Load a document's parent sections (empty when it has none)
+    pub async fn load(&self, collection: &str, document: &str) -> Result<Vec<ParentSection>> {
+        match tokio::fs::read(self.path(collection, document)).await {
+            Ok(json) => serde_json::from_slice(&json).map_err(|e| {
+                AppError::Internal(format!("Corrupt parent sections of {}: {}", document, e))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(AppError::Internal(format!(
+                "Failed to load parent sections of {}: {}",
+                document, e
+            ))),
+        }
+    }
+
+    /// Replace a document's parent sections; none removes them
+    pub async fn save(
+        &self,
+        collection: &str,
+        document: &str,
+        sections: &[ParentSection],
+    ) -> Result<()> {
+        let io = |e: std::io::Error| {
+            AppError::Internal(format!("Failed to save parent sections: {}", e))
+        };
+        let path = self.path(collection, document);
+        if sections.is_empty() {
+            return match tokio::fs::remove_file(&path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(io(e)),
+                _ => Ok(()),
+            };
+        }
+        tokio::fs::create_dir_all(self.collection_dir(collection))
+            .await
+            .map_err(io)?;
+
+        let json = serde_json::to_vec(sections).map_err(|e| {
+            AppError::Internal(format!("Failed to serialize parent sections: {}", e))
+        })?;
+        // Write then rename so a crash never leaves a truncated file
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, json).await.map_err(io)?;
+        tokio::fs::rename(&tmp, path).await.map_err(io)
+    }
+
+    /// Drop a document's parent sections
+    pub async fn remove(&self, collection: &str, document: &str) -> Result<()> {
+        self.save(collection, document, &[]).await
+    }
+
+    /// Drop the parent sections of every document of a collection
+    pub async fn clear(&self, collection: &str) -> Result<()> {
+        match tokio::fs::remove_dir_all(self.collection_dir(collection)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(AppError::Internal(format!(
+                "Failed to remove parent sections: {}",
+                e
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// The context each chunk expands to: its parent section and `window`
+    /// sections on each side
+    ///
+    /// Chunks of documents stored without parent sections have no context.
+    pub async fn contexts(
+        &self,
+        collection: &str,
+        chunk_ids: &[&str],
+        window: usize,
+    ) -> Result<Vec<Option<ParentContext>>> {
+        let mut documents: HashMap<&str, Vec<ParentSection>> = HashMap::new();
+        let mut contexts = Vec::with_capacity(chunk_ids.len());
+        for chunk_id in chunk_ids {
+            let Some((document, index)) = chunk_document(chunk_id) else {
+                contexts.push(None);
+                continue;
+            };
+            if !documents.contains_key(document) {
+                let sections = self.load(collection, document).await?;
+                documents.insert(document, sections);
+            }
+            let sections = &documents[document];
+            let Some(section) = sections
+                .iter()
+                .position(|s| (s.first_chunk..s.first_chunk + s.chunks).contains(&index))
+            else {
+                contexts.push(None);
+                continue;
+            };
+            let start = section.saturating_sub(window);
+            let end = (section + window + 1).min(sections.len());
+            let content = sections[start..end]
+                .iter()
+                .map(|s| s.content.as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            contexts.push(Some(ParentContext {
+                document: document.to_string(),
+                section,
+                content,
+            }));
+        }
+        Ok(contexts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_numbers_children_across_sections() {
+        let text = format!(
+            "{}\n\n{}",
+            "Alpha words fill the first section nicely. ".repeat(3),
+            "Beta words fill the second section too. ".repeat(3)
+        );
+        let (children, sections) = split(&text, 140, &TextChunker::with_word_chunking(10, 0));
+
+        assert_eq!(sections.len(), 2);
+        assert!(sections[0].content.starts_with("Alpha"));
+        assert!(sections[1].content.starts_with("Beta"));
+        assert_eq!(sections[1].first_chunk, sections[0].chunks);
+        assert_eq!(children.len(), sections[0].chunks + sections[1].chunks);
+        assert!(children[sections[1].first_chunk].starts_with("Beta"));
+
+        assert_eq!(chunk_document("job_a_b_12"), Some(("job_a_b", 12)));
+        assert_eq!(chunk_document("nounderscore"), None);
+    }
+
+    #[tokio::test]
+    async fn test_contexts_expand_to_the_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ParentStore::new(dir.path());
+        let collection = "user_1_docs";
+        let sections: Vec<ParentSection> = ["one", "two", "three"]
+            .iter()
+            .enumerate()
+            .map(|(i, content)| ParentSection {
+                first_chunk: i * 2,
+                chunks: 2,
+                content: content.to_string(),
+            })
+            .collect();
+        store.save(collection, "doc", &sections).await.unwrap();
+
+        let contexts = store
+            .contexts(collection, &["doc_3", "doc_9", "other_0"], 0)
+            .await
+            .unwrap();
+        assert_eq!(contexts[0].as_ref().unwrap().content, "two");
+        assert_eq!(contexts[0].as_ref().unwrap().section, 1);
+        assert!(contexts[1].is_none());
+        assert!(contexts[2].is_none());
+
+        let contexts = store.contexts(collection, &["doc_0"], 1).await.unwrap();
+        assert_eq!(contexts[0].as_ref().unwrap().content, "one\n\ntwo");
+
+        store.remove(collection, "doc").await.unwrap();
+        assert!(store.load(collection, "doc").await.unwrap().is_empty());
+        store.save(collection, "doc", &sections).await.unwrap();
+        store.clear(collection).await.unwrap();
+        assert!(store.load(collection, "doc").await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,439 @@
+//! Retrieval pipeline behind RAG search.
+//!
+//! A [`Retriever`] answers one query against one collection:
+//!
+//! 1. **Retrieve** - the strategies the request asks for run in parallel:
+//!    semantic search in the vector store, and BM25 and fuzzy search over a
+//!    lexical index of the collection
+//! 2. **Fuse** - hybrid searches merge the rankings with reciprocal rank
+//!    fusion (RRF), weighted by the request's [`HybridWeights`]
+//! 3. **Feedback** - chunk feedback nudges the ranking, if given
+//! 4. **Rerank** - a reranker rescores the top results, if given
+//!
+//! Each [`SearchResult`] lists the strategies that found it.
+//!
+//! Lexical indexes are built from a collection's documents and cached per
+//! collection. They are rebuilt when the collection's size changes, after
+//! [`invalidate`], and after [`LEXICAL_INDEX_TTL`]. Collections larger than
+//! [`MAX_LEXICAL_DOCUMENTS`], and collections of vector stores that cannot
+//! list their documents, are searched lexically over the semantic
+//! candidates instead.
+
+use crate::db::VectorStore;
+use crate::rag::feedback::{self, CollectionFeedback};
+use crate::rag::reranker::Reranker;
+use crate::rag::search::{
+    HybridWeights, RrfFusion, SearchEngine, SearchRequest, SearchResult, SearchStrategy,
+};
+use crate::types::{AppError, Document, Result};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+/// Largest collection searched lexically as a whole
+pub const MAX_LEXICAL_DOCUMENTS: usize = 10_000;
+
+/// Longest a lexical index is used before it is rebuilt
+pub const LEXICAL_INDEX_TTL: Duration = Duration::from_secs(300);
+
+/// Candidates each strategy returns per requested result, so feedback,
+/// fusion and reranking have more than the final cut to choose from
+const CANDIDATES_PER_RESULT: usize = 2;
+
+/// Lexical indexes keyed by collection
+static LEXICAL_INDEXES: LazyLock<parking_lot::Mutex<HashMap<String, Arc<LexicalIndex>>>> =
+    LazyLock::new(Default::default);
+
+/// Drop the cached lexical index of a collection after writing to it
+pub fn invalidate(collection: &str) {
+    LEXICAL_INDEXES.lock().remove(collection);
+}
+
+/// BM25 and fuzzy indexes over a set of documents.
+struct LexicalIndex {
+    engine: SearchEngine,
+    documents: HashMap<String, Document>,
+    built_at: Instant,
+}
+
+impl LexicalIndex {
+    fn build(documents: Vec<Document>) -> Self {
+        let mut engine = SearchEngine::new();
+        let documents = documents
+            .into_iter()
+            .map(|mut document| {
+                engine.index_document(&document);
+                document.embedding = None;
+                (document.id.clone(), document)
+            })
+            .collect();
+        Self {
+            engine,
+            documents,
+            built_at: Instant::now(),
+        }
+    }
+
+    /// BM25 and fuzzy rankings of `query`, as far as `strategy` uses them
+    async fn rank(
+        self: &Arc<Self>,
+        query: &str,
+        strategy: SearchStrategy,
+        limit: usize,
+    ) -> Result<(Vec<(String, f32)>, Vec<(String, f32)>)> {
+        let blocking = |search: fn(&SearchEngine, &str, usize) -> Vec<(String, f32)>| {
+            let (index, query) = (self.clone(), query.to_string());
+            tokio::task::spawn_blocking(move || search(&index.engine, &query, limit))
+        };
+        let bm25 = async {
+            match strategy {
+                SearchStrategy::Bm25 | SearchStrategy::Hybrid => {
+                    blocking(SearchEngine::search_bm25).await
+                }
+                _ => Ok(Vec::new()),
+            }
+        };
+        let fuzzy = async {
+            match strategy {
+                SearchStrategy::Fuzzy | SearchStrategy::Hybrid => {
+                    blocking(SearchEngine::search_fuzzy).await
+                }
+                _ => Ok(Vec::new()),
+            }
+        };
+        let (bm25, fuzzy) = tokio::join!(bm25, fuzzy);
+        let join =
+            |e: tokio::task::JoinError| AppError::Internal(format!("Lexical search failed: {}", e));
+        Ok((bm25.map_err(join)?, fuzzy.map_err(join)?))
+    }
+}
+
+/// Runs a collection's search strategies, fuses their rankings and reranks
+/// the results.
+pub struct Retriever {
+    store: Arc<dyn VectorStore>,
+    rrf: RrfFusion,
+    feedback: Option<(CollectionFeedback, f32)>,
+    reranker: Option<Arc<dyn Reranker>>,
+}
+
+impl Retriever {
+    /// Create a retriever searching `store`
+    pub fn new(store: Arc<dyn VectorStore>) -> Self {
+        Self {
+            store,
+            rrf: RrfFusion::default(),
+            feedback: None,
+            reranker: None,
+        }
+    }
+
+    /// Nudge rankings by chunk feedback (see [`feedback::apply`])
+    pub fn with_feedback(mut self, feedback: CollectionFeedback, weight: f32) -> Self {
+        self.feedback = Some((feedback, weight));
+        self
+    }
+
+    /// Rerank the results of requests with `rerank` set
+    pub fn with_reranker(mut self, reranker: Arc<dyn Reranker>) -> Self {
+        self.reranker = Some(reranker);
+        self
+    }
+
+    /// Search a collection: every candidate, then the cut and reranking
+    ///
+    /// `embedding` is the query's embedding with the collection's model.
+    pub async fn retrieve(
+        &self,
+        request: &SearchRequest,
+        embedding: &[f32],
+    ) -> Result<Vec<SearchResult>> {
+        let candidates = self.candidates(request, embedding).await?;
+        self.rerank(request, candidates).await
+    }
+
+    /// Every candidate of a search, best first, before the cut
+    ///
+    /// `min_score` filters semantic matches only; BM25 and fuzzy scores are
+    /// not similarities.
+    pub async fn candidates(
+        &self,
+        request: &SearchRequest,
+        embedding: &[f32],
+    ) -> Result<Vec<SearchResult>> {
+        let strategy = request.strategy;
+        let collection = request.collection.as_str();
+        let limit = request.top_k * CANDIDATES_PER_RESULT;
+
+        let semantic = async {
+            match strategy {
+                SearchStrategy::Semantic | SearchStrategy::Hybrid => {
+                    self.store
+                        .search(collection, embedding, limit, request.min_score)
+                        .await
+                }
+                _ => Ok(Vec::new()),
+            }
+        };
+        let lexical = async {
+            if strategy == SearchStrategy::Semantic {
+                return Ok(None);
+            }
+            let Some(index) = self.lexical_index(collection).await? else {
+                return Ok(None);
+            };
+            let (bm25, fuzzy) = index.rank(&request.query, strategy, limit).await?;
+            Ok(Some((index, bm25, fuzzy)))
+        };
+        let (mut semantic, lexical) = tokio::try_join!(semantic, lexical)?;
+
+        let (index, bm25, fuzzy) = match lexical {
+            Some((index, bm25, fuzzy)) => (Some(index), bm25, fuzzy),
+            None if strategy == SearchStrategy::Semantic => (None, Vec::new(), Vec::new()),
+            None => {
+                // No index of the whole collection; rank the semantic candidates
+                if semantic.is_empty() {
+                    semantic = self
+                        .store
+                        .search(collection, embedding, limit, request.min_score)
+                        .await?;
+                }
+                let index = Arc::new(LexicalIndex::build(
+                    semantic.iter().map(|r| r.document.clone()).collect(),
+                ));
+                let (bm25, fuzzy) = index.rank(&request.query, strategy, limit).await?;
+                if strategy != SearchStrategy::Hybrid {
+                    semantic.clear();
+                }
+                (Some(index), bm25, fuzzy)
+            }
+        };
+
+        let semantic_ranked: Vec<(String, f32)> = semantic
+            .iter()
+            .map(|r| (r.document.id.clone(), r.score))
+            .collect();
+        let ranked = match strategy {
+            SearchStrategy::Semantic => semantic_ranked.clone(),
+            SearchStrategy::Bm25 => bm25.clone(),
+            SearchStrategy::Fuzzy => fuzzy.clone(),
+            SearchStrategy::Hybrid => {
+                let weights: &HybridWeights = &request.hybrid_weights;
+                let mut fused = self.rrf.fuse(&[
+                    (&semantic_ranked, weights.semantic),
+                    (&bm25, weights.bm25),
+                    (&fuzzy, weights.fuzzy),
+                ]);
+                fused.truncate(limit);
+                fused
+            }
+        };
+        let ranked = match &self.feedback {
+            Some((feedback, weight)) => feedback::apply(ranked, feedback, *weight),
+            None => ranked,
+        };
+
+        // Which strategies found each document
+        let found_by = [
+            (SearchStrategy::Semantic, &semantic_ranked),
+            (SearchStrategy::Bm25, &bm25),
+            (SearchStrategy::Fuzzy, &fuzzy),
+        ]
+        .map(|(strategy, ranked)| {
+            let ids: HashSet<&str> = ranked.iter().map(|(id, _)| id.as_str()).collect();
+            (strategy, ids)
+        });
+        let documents: HashMap<&str, &Document> = index
+            .iter()
+            .flat_map(|index| index.documents.values())
+            .chain(semantic.iter().map(|r| &r.document))
+            .map(|document| (document.id.as_str(), document))
+            .collect();
+
+        Ok(ranked
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let document = documents.get(id.as_str())?;
+                let sources = found_by
+                    .iter()
+                    .filter(|(_, ids)| ids.contains(id.as_str()))
+                    .map(|(strategy, _)| *strategy)
+                    .collect();
+                Some(SearchResult {
+                    content: document.content.clone(),
+                    score,
+                    sources,
+                    metadata: serde_json::to_value(&document.metadata).ok(),
+                    id,
+                })
+            })
+            .collect())
+    }
+
+    /// Cut candidates to the request's `top_k` and rerank them if the
+    /// request asks to and a reranker is set
+    ///
+    /// Reranked scores are calibrated to 0-1, so `min_score` applies to them
+    /// as well.
+    pub async fn rerank(
+        &self,
+        request: &SearchRequest,
+        mut results: Vec<SearchResult>,
+    ) -> Result<Vec<SearchResult>> {
+        results.truncate(request.top_k);
+        let Some(reranker) = self
+            .reranker
+            .as_ref()
+            .filter(|_| request.rerank && !results.is_empty())
+        else {
+            return Ok(results);
+        };
+
+        let input: Vec<_> = results
+            .iter()
+            .map(|r| (r.id.clone(), r.content.clone(), r.score))
+            .collect();
+        let reranked = reranker
+            .rerank(&request.query, &input, Some(request.top_k))
+            .await?;
+
+        let mut results: HashMap<String, SearchResult> =
+            results.into_iter().map(|r| (r.id.clone(), r)).collect();
+        Ok(reranked
+            .into_iter()
+            .filter(|rr| rr.final_score >= request.min_score)
+            .filter_map(|rr| {
+                let mut result = results.remove(&rr.id)?;
+                result.score = rr.final_score;
+                Some(result)
+            })
+            .collect())
+    }
+
+    /// The collection's lexical index, or `None` when it is too large or the
+    /// store cannot list its documents
+    async fn lexical_index(&self, collection: &str) -> Result<Option<Arc<LexicalIndex>>> {
+        let size = self.store.count(collection).await?;
+        if size > MAX_LEXICAL_DOCUMENTS {
+            return Ok(None);
+        }
+        let cached = LEXICAL_INDEXES.lock().get(collection).cloned();
+        if let Some(index) = cached {
+            if index.documents.len() == size && index.built_at.elapsed() < LEXICAL_INDEX_TTL {
+                return Ok(Some(index));
+            }
+        }
+
+        let documents = match self.store.documents(collection).await {
+            Ok(documents) => documents,
+            Err(AppError::Configuration(e)) => {
+                tracing::debug!(collection = %collection, "No lexical index: {}", e);
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+        let index = tokio::task::spawn_blocking(move || LexicalIndex::build(documents))
+            .await
+            .map(Arc::new)
+            .map_err(|e| AppError::Internal(format!("Failed to build lexical index: {}", e)))?;
+        LEXICAL_INDEXES
+            .lock()
+            .insert(collection.to_string(), index.clone());
+        Ok(Some(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::vectorstore::InMemoryVectorStore;
+    use crate::types::DocumentMetadata;
+
+    fn request(collection: &str, query: &str, strategy: SearchStrategy) -> SearchRequest {
+        SearchRequest {
+            query: query.to_string(),
+            strategy,
+            top_k: 5,
+            min_score: 0.5,
+            rerank: false,
+            collection: collection.to_string(),
+            hybrid_weights: HybridWeights::default(),
+        }
+    }
+
+    /// A collection named for its test, as lexical indexes are cached by name
+    async fn store(collection: &str) -> Arc<dyn VectorStore> {
+        let store = InMemoryVectorStore::new();
+        store.create_collection(collection, 2).await.unwrap();
+        let documents = [
+            ("a", "Quarterly revenue grew by ten percent", [1.0, 0.0]),
+            (
+                "b",
+                "The ZX-81 error code means the disk is full",
+                [0.0, 1.0],
+            ),
+            ("c", "Revenue forecasts for next year", [0.9, 0.1]),
+        ]
+        .map(|(id, content, embedding)| Document {
+            id: id.to_string(),
+            content: content.to_string(),
+            metadata: DocumentMetadata {
+                title: id.to_uppercase(),
+                ..Default::default()
+            },
+            embedding: Some(embedding.to_vec()),
+        });
+        store.upsert(collection, &documents).await.unwrap();
+        Arc::new(store)
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_fuses_strategies_with_provenance() {
+        let retriever = Retriever::new(store("pipeline_hybrid").await);
+        let query = request("pipeline_hybrid", "ZX-81 revenue", SearchStrategy::Hybrid);
+        let results = retriever.retrieve(&query, &[1.0, 0.0]).await.unwrap();
+
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids[0], "a");
+        // Only the keyword finds b, far from the query's embedding
+        let b = results.iter().find(|r| r.id == "b").unwrap();
+        assert!(!b.sources.contains(&SearchStrategy::Semantic));
+        assert!(b.sources.contains(&SearchStrategy::Bm25));
+        assert!(results[0].sources.contains(&SearchStrategy::Semantic));
+        assert_eq!(results[0].metadata.as_ref().unwrap()["title"], "A");
+
+        let semantic = retriever
+            .retrieve(
+                &request("pipeline_hybrid", "ZX-81", SearchStrategy::Semantic),
+                &[1.0, 0.0],
+            )
+            .await
+            .unwrap();
+        assert!(semantic.iter().all(|r| r.id != "b"));
+        assert!(semantic
+            .iter()
+            .all(|r| r.sources == vec![SearchStrategy::Semantic]));
+    }
+
+    #[tokio::test]
+    async fn test_feedback_and_cut() {
+        let feedback: CollectionFeedback = [(
+            "a".to_string(),
+            crate::rag::feedback::ChunkFeedback {
+                down: 10,
+                ..Default::default()
+            },
+        )]
+        .into_iter()
+        .collect();
+        let retriever =
+            Retriever::new(store("pipeline_feedback").await).with_feedback(feedback, 1.0);
+        let mut query = request("pipeline_feedback", "revenue", SearchStrategy::Bm25);
+        query.top_k = 1;
+
+        let results = retriever.retrieve(&query, &[1.0, 0.0]).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "c");
+        assert_eq!(results[0].sources, vec![SearchStrategy::Bm25]);
+    }
+}
@@ -0,0 +1,260 @@
+//! Remote Embedding Providers
+//!
+//! Computes embeddings through a provider's HTTP API instead of a local ONNX
+//! model, so RAG works in builds without the `local-embeddings` feature.
+//!
+//! Two APIs are supported:
+//!
+//! - OpenAI-compatible `POST {api_base}/embeddings` (OpenAI, Mistral, and
+//!   most self-hosted gateways)
+//! - Ollama `POST {base_url}/api/embed`
+//!
+//! The provider is chosen with `[rag] embedding_provider`, which names an
+//! entry in `[providers]`; `[rag] embedding_model` selects the model:
+//!
+//! ```toml
+//! [rag]
+//! embedding_provider = "ollama-local"
+//! embedding_model = "nomic-embed-text"
+//! ```
+
+use crate::rag::batcher::BatchEmbedder;
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::ProviderConfig;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+/// HTTP API used to compute embeddings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteEmbeddingApi {
+    /// OpenAI-compatible `/embeddings` endpoint
+    OpenAI,
+    /// Ollama `/api/embed` endpoint
+    Ollama,
+}
+
+/// Embeds texts by calling a remote embedding API.
+pub struct RemoteEmbedder {
+    http: reqwest::Client,
+    api: RemoteEmbeddingApi,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+impl RemoteEmbedder {
+    /// Create an embedder for an OpenAI-compatible API
+    ///
+    /// # Arguments
+    ///
+    /// * `api_base` - Base URL of the API (e.g., `https://api.openai.com/v1`)
+    /// * `api_key` - Bearer token sent with every request
+    /// * `model` - Embedding model (e.g., "text-embedding-3-small")
+    pub fn openai(api_base: &str, api_key: String, model: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api: RemoteEmbeddingApi::OpenAI,
+            base_url: api_base.trim_end_matches('/').to_string(),
+            api_key: Some(api_key),
+            model: model.to_string(),
+        }
+    }
+
+    /// Create an embedder for an Ollama server
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Ollama server URL (e.g., "http://localhost:11434")
+    /// * `model` - Embedding model (e.g., "nomic-embed-text")
+    pub fn ollama(base_url: &str, model: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api: RemoteEmbeddingApi::Ollama,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: None,
+            model: model.to_string(),
+        }
+    }
+
+    /// Create an embedder from a `[providers]` entry
+    ///
+    /// API keys are read from the provider's `api_key_env` variable.
+    pub fn from_provider(provider: &ProviderConfig, model: &str) -> Result<Self> {
+        let api_key = |env: &str| {
+            std::env::var(env).map_err(|_| {
+                AppError::Configuration(format!(
+                    "Embedding provider API key variable {} is not set",
+                    env
+                ))
+            })
+        };
+
+        match provider {
+            ProviderConfig::OpenAI {
+                api_key_env,
+                api_base,
+                ..
+            }
+            | ProviderConfig::Mistral {
+                api_key_env,
+                api_base,
+                ..
+            } => Ok(Self::openai(api_base, api_key(api_key_env)?, model)),
+            ProviderConfig::Ollama { base_url, .. } => Ok(Self::ollama(base_url, model)),
+            _ => Err(AppError::Configuration(
+                "Embedding provider must be an openai, mistral or ollama provider".to_string(),
+            )),
+        }
+    }
+
+    /// Get the API this embedder calls
+    pub fn api(&self) -> RemoteEmbeddingApi {
+        self.api
+    }
+
+    /// Get the embedding model name
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Send a request, returning the parsed JSON body on success
+    async fn send(&self, path: &str, body: &Value) -> Result<Value> {
+        let mut request = self
+            .http
+            .post(format!("{}{}", self.base_url, path))
+            .json(body);
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AppError::External(format!("Embedding API error: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(AppError::External(format!(
+                "Embedding API error ({}): {}",
+                status, text
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| AppError::External(format!("Invalid embedding response: {}", e)))
+    }
+
+    /// Parse an OpenAI `/embeddings` response, ordered by input index
+    fn parse_openai(response: &Value) -> Result<Vec<Vec<f32>>> {
+        let mut data: Vec<(u64, Vec<f32>)> = response["data"]
+            .as_array()
+            .ok_or_else(|| AppError::External("Embedding response has no data".to_string()))?
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let index = item["index"].as_u64().unwrap_or(i as u64);
+                Ok((index, parse_vector(&item["embedding"])?))
+            })
+            .collect::<Result<_>>()?;
+        data.sort_by_key(|(index, _)| *index);
+        Ok(data.into_iter().map(|(_, vector)| vector).collect())
+    }
+
+    /// Parse an Ollama `/api/embed` response
+    fn parse_ollama(response: &Value) -> Result<Vec<Vec<f32>>> {
+        response["embeddings"]
+            .as_array()
+            .ok_or_else(|| AppError::External("Embedding response has no embeddings".to_string()))?
+            .iter()
+            .map(parse_vector)
+            .collect()
+    }
+}
+
+fn parse_vector(value: &Value) -> Result<Vec<f32>> {
+    value
+        .as_array()
+        .ok_or_else(|| AppError::External("Embedding is not an array".to_string()))?
+        .iter()
+        .map(|v| {
+            v.as_f64()
+                .map(|f| f as f32)
+                .ok_or_else(|| AppError::External("Embedding contains a non-number".to_string()))
+        })
+        .collect()
+}
+
+#[async_trait]
+impl BatchEmbedder for RemoteEmbedder {
+    async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let body = json!({"model": self.model, "input": texts});
+        match self.api {
+            RemoteEmbeddingApi::OpenAI => {
+                Self::parse_openai(&self.send("/embeddings", &body).await?)
+            }
+            RemoteEmbeddingApi::Ollama => {
+                Self::parse_ollama(&self.send("/api/embed", &body).await?)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_openai_embeddings_are_ordered_by_index() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/embeddings"))
+            .and(header("authorization", "Bearer sk-test"))
+            .and(body_partial_json(
+                json!({"model": "text-embedding-3-small", "input": ["a", "b"]}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    {"index": 1, "embedding": [0.0, 1.0]},
+                    {"index": 0, "embedding": [1.0, 0.0]}
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let embedder = RemoteEmbedder::openai(
+            &format!("{}/v1/", server.uri()),
+            "sk-test".to_string(),
+            "text-embedding-3-small",
+        );
+        let vectors = embedder
+            .embed_batch(vec!["a".to_string(), "b".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(vectors, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+    }
+
+    #[tokio::test]
+    async fn test_ollama_embeddings_and_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/embed"))
+            .and(body_partial_json(json!({"model": "nomic-embed-text"})))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"embeddings": [[0.5, 0.25]]})),
+            )
+            .mount(&server)
+            .await;
+
+        let embedder = RemoteEmbedder::ollama(&server.uri(), "nomic-embed-text");
+        let vectors = embedder.embed_batch(vec!["a".to_string()]).await.unwrap();
+        assert_eq!(vectors, vec![vec![0.5, 0.25]]);
+
+        let missing = RemoteEmbedder::ollama(&server.uri(), "unknown-model");
+        assert!(missing.embed_batch(vec!["a".to_string()]).await.is_err());
+    }
+}
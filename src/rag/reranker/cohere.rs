@@ -0,0 +1,137 @@
+//! Cohere Rerank API reranker.
+
+use super::Reranker;
+use crate::types::{AppError, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+/// Default Cohere rerank model
+const DEFAULT_MODEL: &str = "rerank-v3.5";
+
+/// Reranks documents with the Cohere Rerank API.
+///
+/// Cohere's relevance scores are already in the 0-1 range.
+pub struct CohereReranker {
+    http: reqwest::Client,
+    api_base: String,
+    api_key: String,
+    model: String,
+}
+
+impl CohereReranker {
+    /// Create a reranker using the default model
+    ///
+    /// # Arguments
+    ///
+    /// * `api_base` - Base URL of the API (e.g., `https://api.cohere.com/v1`)
+    /// * `api_key` - Cohere API key
+    pub fn new(api_base: &str, api_key: String) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_base: api_base.trim_end_matches('/').to_string(),
+            api_key,
+            model: DEFAULT_MODEL.to_string(),
+        }
+    }
+
+    /// Set the rerank model (e.g., "rerank-multilingual-v3.0")
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Get the rerank model
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+#[async_trait]
+impl Reranker for CohereReranker {
+    async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let response = self
+            .http
+            .post(format!("{}/rerank", self.api_base))
+            .bearer_auth(&self.api_key)
+            .json(&json!({
+                "model": self.model,
+                "query": query,
+                "documents": documents,
+                "top_n": documents.len(),
+            }))
+            .send()
+            .await
+            .map_err(|e| AppError::External(format!("Cohere rerank error: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(AppError::External(format!(
+                "Cohere rerank error ({}): {}",
+                status, text
+            )));
+        }
+
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AppError::External(format!("Invalid Cohere rerank response: {}", e)))?;
+        let results = body["results"].as_array().ok_or_else(|| {
+            AppError::External("Cohere rerank response has no results".to_string())
+        })?;
+
+        // Results come back sorted by relevance; put scores back in input order
+        let mut scores = vec![0.0; documents.len()];
+        for result in results {
+            let index = result["index"].as_u64().map(|i| i as usize);
+            let score = result["relevance_score"].as_f64();
+            if let (Some(index), Some(score)) = (index, score) {
+                if let Some(slot) = scores.get_mut(index) {
+                    *slot = (score as f32).clamp(0.0, 1.0);
+                }
+            }
+        }
+        Ok(scores)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_cohere_scores_in_input_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/rerank"))
+            .and(header("authorization", "Bearer co-test"))
+            .and(body_partial_json(
+                json!({"model": "rerank-v3.5", "query": "rust"}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [
+                    {"index": 1, "relevance_score": 0.92},
+                    {"index": 0, "relevance_score": 0.05}
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let reranker = CohereReranker::new(&format!("{}/v1", server.uri()), "co-test".to_string());
+        let results = vec![
+            ("a".to_string(), "Python is dynamic".to_string(), 0.9),
+            ("b".to_string(), "Rust is memory safe".to_string(), 0.8),
+        ];
+        let reranked = reranker.rerank("rust", &results, None).await.unwrap();
+
+        assert_eq!(reranked[0].id, "b");
+        assert!((reranked[0].rerank_score - 0.92).abs() < 1e-6);
+        assert!((reranked[1].rerank_score - 0.05).abs() < 1e-6);
+    }
+}
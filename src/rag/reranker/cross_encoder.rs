@@ -1,7 +1,8 @@
-//! Reranking for improving search result relevance.
+//! Local cross-encoder reranking.
 //!
-//! This module provides reranking capabilities using cross-encoder models
-//! to improve the quality of retrieved documents after initial retrieval.
+//! Scores query/document pairs with ONNX cross-encoder models. The models
+//! output relevance logits, which are calibrated into the 0-1 range with a
+//! sigmoid.
 //!
 //! # Feature Flag
 //!
@@ -17,10 +18,12 @@ use std::cmp::Ordering;
 use std::str::FromStr;
 use std::sync::Arc;
 
+use async_trait::async_trait;
 use fastembed::{RerankInitOptions, RerankerModel as FastEmbedRerankerModel, TextRerank};
 use serde::{Deserialize, Serialize};
 use tokio::sync::OnceCell;
 
+use super::{rank, sigmoid, RerankedResult, Reranker};
 use crate::types::{AppError, Result};
 
 // ============================================================================
@@ -143,40 +146,17 @@ impl Default for RerankerConfig {
     }
 }
 
-// ============================================================================
-// Reranked Result
-// ============================================================================
-
-/// A reranked search result
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RerankedResult {
-    /// Document ID
-    pub id: String,
-    /// Document content
-    pub content: String,
-    /// Original retrieval score
-    pub retrieval_score: f32,
-    /// Reranking score from cross-encoder
-    pub rerank_score: f32,
-    /// Final combined score (used for ranking)
-    pub final_score: f32,
-    /// Original rank before reranking
-    pub original_rank: usize,
-    /// New rank after reranking
-    pub new_rank: usize,
-}
-
 // ============================================================================
 // Reranker Service
 // ============================================================================
 
-/// Reranking service using cross-encoder models
-pub struct Reranker {
+/// Reranker using local cross-encoder models
+pub struct CrossEncoderReranker {
     config: RerankerConfig,
     model: OnceCell<Arc<tokio::sync::Mutex<TextRerank>>>,
 }
 
-impl Reranker {
+impl CrossEncoderReranker {
     /// Create a new reranker with the given configuration
     pub fn new(config: RerankerConfig) -> Self {
         Self {
@@ -210,76 +190,26 @@ impl Reranker {
             .map(Arc::clone)
     }
 
-    /// Rerank search results
-    ///
-    /// Takes a query and a list of (id, content, score) tuples and returns
-    /// reranked results sorted by relevance.
-    pub async fn rerank(
-        &self,
-        query: &str,
-        results: &[(String, String, f32)],
-        top_k: Option<usize>,
-    ) -> Result<Vec<RerankedResult>> {
-        if results.is_empty() {
-            return Ok(Vec::new());
-        }
-
+    /// Score documents with the cross-encoder, calibrated to 0-1
+    async fn calibrated_scores(&self, query: &str, documents: &[String]) -> Result<Vec<f32>> {
         let model = self.get_model().await?;
-        let documents: Vec<String> = results
-            .iter()
-            .map(|(_, content, _)| content.clone())
-            .collect();
-
+        let documents = documents.to_vec();
         let query = query.to_string();
         let rerank_scores = tokio::task::spawn_blocking(move || {
             let mut model = model.blocking_lock();
-            model.rerank(query, &documents, true, None)
+            model.rerank(query, &documents, false, None)
         })
         .await
         .map_err(|e| AppError::Internal(format!("Rerank task failed: {}", e)))?
         .map_err(|e| AppError::Internal(format!("Reranking failed: {}", e)))?;
 
-        // Combine with original results
-        let mut reranked: Vec<RerankedResult> = results
-            .iter()
-            .enumerate()
-            .map(|(idx, (id, content, retrieval_score))| {
-                let rerank_score = rerank_scores
-                    .iter()
-                    .find(|r| r.index == idx)
-                    .map(|r| r.score)
-                    .unwrap_or(0.0);
-
-                RerankedResult {
-                    id: id.clone(),
-                    content: content.clone(),
-                    retrieval_score: *retrieval_score,
-                    rerank_score,
-                    // Use rerank score as final score (could be combined differently)
-                    final_score: rerank_score,
-                    original_rank: idx + 1,
-                    new_rank: 0, // Will be set after sorting
-                }
-            })
-            .collect();
-
-        // Sort by rerank score (higher is better)
-        reranked.sort_by(|a, b| {
-            b.final_score
-                .partial_cmp(&a.final_score)
-                .unwrap_or(Ordering::Equal)
-        });
-
-        // Assign new ranks
-        for (idx, result) in reranked.iter_mut().enumerate() {
-            result.new_rank = idx + 1;
+        let mut scores = vec![0.0; rerank_scores.len()];
+        for result in rerank_scores {
+            if let Some(slot) = scores.get_mut(result.index) {
+                *slot = sigmoid(result.score);
+            }
         }
-
-        // Truncate to top_k
-        let top_k = top_k.unwrap_or(self.config.top_k);
-        reranked.truncate(top_k);
-
-        Ok(reranked)
+        Ok(scores)
     }
 
     /// Rerank with hybrid scoring
@@ -296,20 +226,11 @@ impl Reranker {
             return Ok(Vec::new());
         }
 
-        let model = self.get_model().await?;
         let documents: Vec<String> = results
             .iter()
             .map(|(_, content, _)| content.clone())
             .collect();
-
-        let query = query.to_string();
-        let rerank_scores = tokio::task::spawn_blocking(move || {
-            let mut model = model.blocking_lock();
-            model.rerank(query, &documents, true, None)
-        })
-        .await
-        .map_err(|e| AppError::Internal(format!("Rerank task failed: {}", e)))?
-        .map_err(|e| AppError::Internal(format!("Reranking failed: {}", e)))?;
+        let rerank_scores = self.calibrated_scores(query, &documents).await?;
 
         // Normalize retrieval scores to 0-1 range
         let max_retrieval = results
@@ -330,11 +251,7 @@ impl Reranker {
             .iter()
             .enumerate()
             .map(|(idx, (id, content, retrieval_score))| {
-                let rerank_score = rerank_scores
-                    .iter()
-                    .find(|r| r.index == idx)
-                    .map(|r| r.score)
-                    .unwrap_or(0.0);
+                let rerank_score = rerank_scores.get(idx).copied().unwrap_or(0.0);
 
                 // Normalize retrieval score
                 let normalized_retrieval = if retrieval_range > 0.0 {
@@ -384,6 +301,35 @@ impl Reranker {
     }
 }
 
+#[async_trait]
+impl Reranker for CrossEncoderReranker {
+    async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.calibrated_scores(query, documents).await
+    }
+
+    /// Rerank search results, keeping `top_k` (default: the configured `top_k`)
+    async fn rerank(
+        &self,
+        query: &str,
+        results: &[(String, String, f32)],
+        top_k: Option<usize>,
+    ) -> Result<Vec<RerankedResult>> {
+        if results.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let documents: Vec<String> = results
+            .iter()
+            .map(|(_, content, _)| content.clone())
+            .collect();
+        let scores = self.calibrated_scores(query, &documents).await?;
+        Ok(rank(results, &scores, top_k.or(Some(self.config.top_k))))
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -443,7 +389,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_rerank_empty() {
-        let reranker = Reranker::default_reranker();
+        let reranker = CrossEncoderReranker::default_reranker();
         let results = reranker.rerank("test query", &[], None).await.unwrap();
         assert!(results.is_empty());
     }
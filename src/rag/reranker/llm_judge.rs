@@ -0,0 +1,36 @@
+//! LLM-judge reranker.
+
+use super::Reranker;
+use crate::llm::judge::{JudgmentCache, MAX_SCORE};
+use crate::llm::{Judge, LLMClient, Rubric};
+use crate::types::Result;
+use async_trait::async_trait;
+
+/// Reranks documents by asking an LLM judge to score each one's relevance.
+///
+/// Scores from 0 to 10 are calibrated into the 0-1 range. Documents are
+/// scored concurrently, and judgments are shared with other judges through
+/// the judgment cache, so re-ranking the same documents costs nothing.
+pub struct LlmJudgeReranker {
+    judge: Judge,
+}
+
+impl LlmJudgeReranker {
+    /// Create a reranker judging with the given LLM client
+    pub fn new(llm: Box<dyn LLMClient>) -> Self {
+        Self {
+            judge: Judge::new(llm, Rubric::relevance()).with_cache(JudgmentCache::shared()),
+        }
+    }
+}
+
+#[async_trait]
+impl Reranker for LlmJudgeReranker {
+    async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>> {
+        futures::future::try_join_all(documents.iter().map(|doc| async move {
+            let judgment = self.judge.judge(query, doc).await?;
+            Ok(judgment.score / MAX_SCORE)
+        }))
+        .await
+    }
+}
@@ -0,0 +1,279 @@
+//! Reranking for improving search result relevance.
+//!
+//! Rerankers rescore retrieved documents against the query after initial
+//! retrieval. Three implementations are available:
+//!
+//! - [`CrossEncoderReranker`] - local ONNX cross-encoder models **[requires `local-embeddings` feature]**
+//! - [`CohereReranker`] - the Cohere Rerank API
+//! - [`LlmJudgeReranker`] - an LLM rates each document's relevance
+//!
+//! Every reranker calibrates its scores into the 0-1 range, so search
+//! thresholds apply to reranked results the same way they apply to
+//! similarity scores.
+//!
+//! The reranker is chosen with `[rag] reranker` and can be overridden per
+//! search request:
+//!
+//! ```toml
+//! [rag]
+//! reranker = "cohere"            # "cross-encoder" (default), "cohere" or "llm"
+//! reranker_provider = "cohere"   # [providers] entry holding the Cohere API key
+//! reranker_model = "rerank-v3.5"
+//! ```
+
+mod cohere;
+#[cfg(feature = "local-embeddings")]
+mod cross_encoder;
+mod llm_judge;
+
+pub use crate::utils::toml_config::RerankerKind;
+pub use cohere::CohereReranker;
+#[cfg(feature = "local-embeddings")]
+pub use cross_encoder::{CrossEncoderReranker, RerankerConfig, RerankerModelType};
+pub use llm_judge::LlmJudgeReranker;
+
+use crate::llm::ProviderRegistry;
+use crate::types::{AppError, Result};
+use crate::utils::toml_config::{AresConfig, ProviderConfig};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+// ============================================================================
+// Reranker Trait
+// ============================================================================
+
+/// Rescores documents by their relevance to a query.
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    /// Score each document's relevance to the query
+    ///
+    /// Returns one score per document, in input order, calibrated into the
+    /// 0-1 range.
+    async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>>;
+
+    /// Rerank search results
+    ///
+    /// Takes a query and a list of (id, content, score) tuples and returns
+    /// reranked results sorted by relevance, keeping at most `top_k`.
+    async fn rerank(
+        &self,
+        query: &str,
+        results: &[(String, String, f32)],
+        top_k: Option<usize>,
+    ) -> Result<Vec<RerankedResult>> {
+        if results.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let documents: Vec<String> = results
+            .iter()
+            .map(|(_, content, _)| content.clone())
+            .collect();
+        let scores = self.score(query, &documents).await?;
+        Ok(rank(results, &scores, top_k))
+    }
+}
+
+// ============================================================================
+// Reranked Result
+// ============================================================================
+
+/// A reranked search result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RerankedResult {
+    /// Document ID
+    pub id: String,
+    /// Document content
+    pub content: String,
+    /// Original retrieval score
+    pub retrieval_score: f32,
+    /// Calibrated reranking score (0-1)
+    pub rerank_score: f32,
+    /// Final combined score (used for ranking)
+    pub final_score: f32,
+    /// Original rank before reranking
+    pub original_rank: usize,
+    /// New rank after reranking
+    pub new_rank: usize,
+}
+
+/// Order results by their rerank scores, keeping at most `top_k`
+pub(crate) fn rank(
+    results: &[(String, String, f32)],
+    scores: &[f32],
+    top_k: Option<usize>,
+) -> Vec<RerankedResult> {
+    let mut reranked: Vec<RerankedResult> = results
+        .iter()
+        .enumerate()
+        .map(|(idx, (id, content, retrieval_score))| {
+            let rerank_score = scores.get(idx).copied().unwrap_or(0.0);
+            RerankedResult {
+                id: id.clone(),
+                content: content.clone(),
+                retrieval_score: *retrieval_score,
+                rerank_score,
+                final_score: rerank_score,
+                original_rank: idx + 1,
+                new_rank: 0, // Will be set after sorting
+            }
+        })
+        .collect();
+
+    // Sort by rerank score (higher is better)
+    reranked.sort_by(|a, b| {
+        b.final_score
+            .partial_cmp(&a.final_score)
+            .unwrap_or(Ordering::Equal)
+    });
+
+    // Assign new ranks
+    for (idx, result) in reranked.iter_mut().enumerate() {
+        result.new_rank = idx + 1;
+    }
+
+    if let Some(top_k) = top_k {
+        reranked.truncate(top_k);
+    }
+    reranked
+}
+
+/// Map an unbounded relevance logit into the 0-1 range
+pub fn sigmoid(logit: f32) -> f32 {
+    1.0 / (1.0 + (-logit).exp())
+}
+
+// ============================================================================
+// Reranker Selection
+// ============================================================================
+
+/// Create a reranker of the given kind
+///
+/// # Arguments
+///
+/// * `config` - Configuration providing `[rag]` reranker settings and providers
+/// * `providers` - Provider registry used to create the LLM judge's client
+/// * `kind` - Reranker implementation to use
+/// * `model` - Model override; defaults to `[rag] reranker_model` when `kind`
+///   is the configured reranker, else to the implementation's default
+pub async fn create_reranker(
+    config: &AresConfig,
+    providers: &ProviderRegistry,
+    kind: RerankerKind,
+    model: Option<&str>,
+) -> Result<Arc<dyn Reranker>> {
+    let model = model.map(str::to_string).or_else(|| {
+        (kind == config.rag.reranker)
+            .then(|| config.rag.reranker_model.clone())
+            .flatten()
+    });
+
+    match kind {
+        RerankerKind::CrossEncoder => cross_encoder_reranker(model.as_deref()),
+        RerankerKind::Cohere => {
+            let name = config.rag.reranker_provider.as_deref().ok_or_else(|| {
+                AppError::Configuration(
+                    "The cohere reranker requires [rag] reranker_provider".to_string(),
+                )
+            })?;
+            match config.get_provider(name) {
+                Some(ProviderConfig::Cohere {
+                    api_key_env,
+                    api_base,
+                    ..
+                }) => {
+                    let api_key = std::env::var(api_key_env).map_err(|_| {
+                        AppError::Configuration(format!(
+                            "Reranker API key variable {} is not set",
+                            api_key_env
+                        ))
+                    })?;
+                    let mut reranker = CohereReranker::new(api_base, api_key);
+                    if let Some(model) = model {
+                        reranker = reranker.with_model(model);
+                    }
+                    Ok(Arc::new(reranker))
+                }
+                _ => Err(AppError::Configuration(format!(
+                    "Reranker provider '{}' is not a cohere provider in [providers]",
+                    name
+                ))),
+            }
+        }
+        RerankerKind::Llm => {
+            let llm = match model {
+                Some(model) => providers.create_client_for_model(&model).await?,
+                None => providers.create_default_client().await?,
+            };
+            Ok(Arc::new(LlmJudgeReranker::new(llm)))
+        }
+    }
+}
+
+/// Create a local cross-encoder reranker.
+#[cfg(feature = "local-embeddings")]
+fn cross_encoder_reranker(model: Option<&str>) -> Result<Arc<dyn Reranker>> {
+    let model: RerankerModelType = model.map(str::parse).transpose()?.unwrap_or_default();
+    Ok(Arc::new(CrossEncoderReranker::new(RerankerConfig {
+        model,
+        ..Default::default()
+    })))
+}
+
+/// Cross-encoder models run locally and need the `local-embeddings` feature.
+#[cfg(not(feature = "local-embeddings"))]
+fn cross_encoder_reranker(_model: Option<&str>) -> Result<Arc<dyn Reranker>> {
+    Err(AppError::InvalidInput(
+        "The cross-encoder reranker requires the `local-embeddings` feature; \
+         use the cohere or llm reranker instead"
+            .to_string(),
+    ))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_orders_and_truncates() {
+        let results = vec![
+            ("a".to_string(), "first".to_string(), 0.9),
+            ("b".to_string(), "second".to_string(), 0.8),
+            ("c".to_string(), "third".to_string(), 0.7),
+        ];
+
+        let ranked = rank(&results, &[0.1, 0.8, 0.5], Some(2));
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].id, "b");
+        assert_eq!(ranked[0].original_rank, 2);
+        assert_eq!(ranked[0].new_rank, 1);
+        assert_eq!(ranked[1].id, "c");
+    }
+
+    #[test]
+    fn test_sigmoid_calibration() {
+        assert_eq!(sigmoid(0.0), 0.5);
+        assert!(sigmoid(8.0) > 0.99);
+        assert!(sigmoid(-8.0) < 0.01);
+    }
+
+    #[test]
+    fn test_reranker_kind_from_str() {
+        assert_eq!(
+            "cross-encoder".parse::<RerankerKind>().unwrap(),
+            RerankerKind::CrossEncoder
+        );
+        assert_eq!(
+            "cohere".parse::<RerankerKind>().unwrap(),
+            RerankerKind::Cohere
+        );
+        assert_eq!("llm".parse::<RerankerKind>().unwrap(), RerankerKind::Llm);
+        assert!("onnx-magic".parse::<RerankerKind>().is_err());
+    }
+}
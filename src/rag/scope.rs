@@ -0,0 +1,147 @@
+//! Collections and tags a conversation's retrieval is pinned to.
+//!
+//! A conversation can be scoped to some of its user's RAG collections
+//! ("only search the legal corpus"), optionally narrowed to the documents
+//! carrying given tags. Every message in a scoped conversation is then
+//! answered from passages of those collections only. Attached files take
+//! precedence: a conversation with attachments is answered from them (see
+//! [`crate::rag::attachments`]).
+//!
+//! The scope is set with `PUT /api/conversations/{id}/rag-scope`, or by
+//! sending a `/scope` command as a chat message:
+//!
+//! ```text
+//! /scope legal contracts #2024   search "legal" and "contracts", documents tagged 2024
+//! /scope                          show the conversation's scope
+//! /scope off                      stop searching collections
+//! ```
+
+use crate::types::RagScope;
+
+/// Chat message prefix of scope commands
+pub const COMMAND: &str = "/scope";
+
+/// Most collections a conversation can be scoped to.
+pub const MAX_COLLECTIONS: usize = 10;
+
+/// A `/scope` command sent as a chat message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScopeCommand {
+    /// Show the conversation's scope
+    Show,
+    /// Pin the conversation to a scope
+    Set(RagScope),
+    /// Remove the conversation's scope
+    Clear,
+}
+
+/// Parse a chat message as a scope command; other messages are `None`.
+///
+/// Words starting with `#` are tags, the others collection names; commas
+/// separate words like spaces do.
+pub fn parse_command(message: &str) -> Option<ScopeCommand> {
+    let rest = message.trim().strip_prefix(COMMAND)?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let words = rest
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+    match words.as_slice() {
+        [] => Some(ScopeCommand::Show),
+        ["off"] | ["clear"] => Some(ScopeCommand::Clear),
+        words => {
+            let mut scope = RagScope::default();
+            for word in words {
+                match word.strip_prefix('#') {
+                    Some(tag) => scope.tags.push(tag.to_string()),
+                    None => scope.collections.push(word.to_string()),
+                }
+            }
+            Some(ScopeCommand::Set(normalize(scope)))
+        }
+    }
+}
+
+/// Trim a scope's names and drop empty and repeated ones.
+pub fn normalize(scope: RagScope) -> RagScope {
+    let clean = |values: Vec<String>| {
+        let mut clean: Vec<String> = Vec::new();
+        for value in values {
+            let value = value.trim();
+            if !value.is_empty() && !clean.iter().any(|v| v == value) {
+                clean.push(value.to_string());
+            }
+        }
+        clean
+    };
+    RagScope {
+        collections: clean(scope.collections),
+        tags: clean(scope.tags),
+    }
+}
+
+/// Whether a document with `tags` is searched under a scope.
+pub fn matches(scope: &RagScope, tags: &[String]) -> bool {
+    scope.tags.iter().all(|tag| tags.contains(tag))
+}
+
+/// Describe a conversation's scope, as the reply to a scope command.
+pub fn describe(scope: Option<&RagScope>) -> String {
+    let Some(scope) = scope else {
+        return format!(
+            "This conversation doesn't search any collections. Send `{} <collection>...` to pin some.",
+            COMMAND
+        );
+    };
+    let mut description = format!(
+        "This conversation searches {}",
+        scope.collections.join(", ")
+    );
+    if !scope.tags.is_empty() {
+        description.push_str(&format!(
+            ", documents tagged {}",
+            scope
+                .tags
+                .iter()
+                .map(|tag| format!("#{}", tag))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ));
+    }
+    description.push('.');
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("/scope"), Some(ScopeCommand::Show));
+        assert_eq!(parse_command("  /scope off "), Some(ScopeCommand::Clear));
+        assert_eq!(
+            parse_command("/scope legal, contracts #2024 legal"),
+            Some(ScopeCommand::Set(RagScope {
+                collections: vec!["legal".to_string(), "contracts".to_string()],
+                tags: vec!["2024".to_string()],
+            }))
+        );
+        assert_eq!(parse_command("/scoped question"), None);
+        assert_eq!(parse_command("What does /scope do?"), None);
+    }
+
+    #[test]
+    fn test_matches_requires_every_tag() {
+        let scope = RagScope {
+            collections: vec!["legal".to_string()],
+            tags: vec!["2024".to_string(), "nda".to_string()],
+        };
+        let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        assert!(matches(&scope, &tags(&["nda", "2024", "signed"])));
+        assert!(!matches(&scope, &tags(&["nda"])));
+        assert!(matches(&RagScope::default(), &[]));
+    }
+}
@@ -25,6 +25,7 @@ use std::path::Path;
 use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use crate::types::{AppError, Document, Result};
 
@@ -33,7 +34,7 @@ use crate::types::{AppError, Document, Result};
 // ============================================================================
 
 /// Available search strategies
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default, ToSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum SearchStrategy {
     /// Semantic similarity using dense embeddings
@@ -162,7 +163,7 @@ impl Default for HybridWeights {
 /// BM25 search index for lexical matching
 ///
 /// This index supports persistence via `save()` and `load()` methods.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bm25Index {
     /// Document ID -> tokenized content
     documents: HashMap<String, Vec<String>>,
@@ -180,15 +181,25 @@ pub struct Bm25Index {
     b: f32,
 }
 
-impl Bm25Index {
-    /// Create a new BM25 index with default parameters
-    pub fn new() -> Self {
+impl Default for Bm25Index {
+    fn default() -> Self {
         Self {
+            documents: HashMap::new(),
+            inverted_index: HashMap::new(),
+            document_frequencies: HashMap::new(),
+            doc_count: 0,
+            avg_doc_length: 0.0,
             k1: 1.2,
             b: 0.75,
-            ..Default::default()
         }
     }
+}
+
+impl Bm25Index {
+    /// Create a new BM25 index with default parameters
+    pub fn new() -> Self {
+        Self::default()
+    }
 
     /// Create with custom BM25 parameters
     pub fn with_params(k1: f32, b: f32) -> Self {
@@ -1060,6 +1071,10 @@ mod tests {
         assert!(!bm25_results.is_empty());
         assert_eq!(bm25_results[0].0, "doc1");
 
+        // Documents matching only some query terms are found too
+        let partial_results = engine.search_bm25("Rust browsers", 10);
+        assert_eq!(partial_results.len(), 2);
+
         // Fuzzy search - test with exact word (fuzzy should handle it)
         let fuzzy_results = engine.search_fuzzy("rust", 10);
         // Fuzzy search should find "rust" with exact match
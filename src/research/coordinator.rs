@@ -1,5 +1,5 @@
 use crate::{
-    llm::LLMClient,
+    llm::{Judge, Judgment, LLMClient},
     types::{Result, Source},
 };
 use tokio::task::JoinSet;
@@ -7,11 +7,13 @@ use tokio::task::JoinSet;
 /// Coordinates multi-step research tasks across multiple queries.
 ///
 /// Decomposes research questions, executes parallel searches,
-/// and synthesizes findings into a coherent report.
+/// and synthesizes findings into a coherent report. With a judge, the
+/// report goes through a critique pass before it is returned.
 pub struct ResearchCoordinator {
     llm: Box<dyn LLMClient>,
     depth: u8,
     max_iterations: u8,
+    judge: Option<Judge>,
 }
 
 impl ResearchCoordinator {
@@ -21,11 +23,20 @@ impl ResearchCoordinator {
             llm,
             depth,
             max_iterations,
+            judge: None,
         }
     }
 
+    /// Score the report with `judge`, revising it once if it does not pass
+    pub fn with_judge(mut self, judge: Judge) -> Self {
+        self.judge = Some(judge);
+        self
+    }
+
     /// Execute deep research on a query
-    pub async fn research(&self, query: &str) -> Result<(String, Vec<Source>)> {
+    ///
+    /// Returns the report, its sources and, with a judge, the report's score.
+    pub async fn research(&self, query: &str) -> Result<(String, Vec<Source>, Option<Judgment>)> {
         let mut all_findings = Vec::new();
 
         // Generate initial research questions
@@ -61,11 +72,63 @@ impl ResearchCoordinator {
 
         // Synthesize findings
         let synthesis = self.synthesize_findings(query, &all_findings).await?;
+        let (synthesis, judgment) = self.critique(query, &all_findings, synthesis).await?;
 
         // Extract sources
         let all_sources = self.extract_sources(&all_findings);
 
-        Ok((synthesis, all_sources))
+        Ok((synthesis, all_sources, judgment))
+    }
+
+    /// Critique pass: score the report and revise it once if it falls short
+    ///
+    /// A judge that fails is logged and leaves the report unscored.
+    async fn critique(
+        &self,
+        query: &str,
+        findings: &[String],
+        synthesis: String,
+    ) -> Result<(String, Option<Judgment>)> {
+        let Some(judge) = &self.judge else {
+            return Ok((synthesis, None));
+        };
+        let judgment = match judge.judge(query, &synthesis).await {
+            Ok(judgment) => judgment,
+            Err(e) => {
+                tracing::warn!("Research critique failed: {}", e);
+                return Ok((synthesis, None));
+            }
+        };
+        if judgment.passed {
+            return Ok((synthesis, Some(judgment)));
+        }
+
+        let prompt = format!(
+            r#"Original query: {}
+
+      Research findings:
+      {}
+
+      Draft report:
+      {}
+
+      A reviewer scored the draft report:
+      {}
+
+      Rewrite the report to fix these problems. Reply with the complete revised report only."#,
+            query,
+            findings.join("\n\n"),
+            synthesis,
+            judgment.critique()
+        );
+        let revised = self.llm.generate(&prompt).await?;
+        match judge.judge(query, &revised).await {
+            Ok(judgment) => Ok((revised, Some(judgment))),
+            Err(e) => {
+                tracing::warn!("Research critique failed: {}", e);
+                Ok((revised, None))
+            }
+        }
     }
 
     async fn generate_research_questions(&self, query: &str) -> Result<Vec<String>> {
@@ -8,6 +8,7 @@
 //! - [`calculator`](crate::tools::calculator) - Mathematical expression evaluation
 //! - [`search`](crate::tools::search) - Web search integration (DuckDuckGo, Brave, etc.)
 //! - [`registry`](crate::tools::registry) - Tool registration and discovery
+//! - [`permissions`](crate::tools::permissions) - Per-agent limits on tool calls
 //!
 //! # Available Tools
 //!
@@ -42,6 +43,8 @@
 
 /// Calculator tool for arithmetic operations.
 pub mod calculator;
+/// Per-agent tool permissions and structured refusals.
+pub mod permissions;
 /// Tool registry for managing available tools.
 pub mod registry;
 /// Web search tool using DuckDuckGo.
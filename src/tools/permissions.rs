@@ -0,0 +1,506 @@
+//! Per-agent tool permissions.
+//!
+//! Agents can limit each tool (or every tool, under `"*"`) to a set of
+//! domains, a set of paths and a maximum cost per call. Before a tool runs,
+//! the tool describes what the call would reach through [`Tool::access`]
+//! and the call is checked against the agent's limits. A call that breaks
+//! a limit is not executed; the model receives a [`ToolRefusal`] naming the
+//! rule instead, so it can adjust the call or answer without the tool.
+//!
+//! Users' roles add a [`ToolProfile`] on top: tools the role may not use at
+//! all, and limits of the same kind as an agent's. Profiles apply to every
+//! agent the user talks to.
+//!
+//! [`Tool::access`]: crate::tools::registry::Tool::access
+
+use crate::tools::registry::ToolRegistry;
+use crate::types::ToolCall;
+use crate::utils::toml_config::{AresConfig, RoleConfig, ToolPermissionConfig};
+use globset::{Glob, GlobMatcher};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Permission key applying to every tool
+pub const ANY_TOOL: &str = "*";
+
+/// Argument keys read as URLs, hosts or domains
+const DOMAIN_KEYS: &[&str] = &["url", "urls", "uri", "domain", "host"];
+
+/// Argument keys read as file system paths
+const PATH_KEYS: &[&str] = &["path", "paths", "file", "file_path", "filename", "dir"];
+
+/// What a single tool call would reach.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolAccess {
+    /// Hosts the call would contact
+    pub domains: Vec<String>,
+    /// Whether the call may reach any host, such as an unscoped web search
+    pub any_domain: bool,
+    /// Paths the call would read or write
+    pub paths: Vec<String>,
+    /// Estimated cost of the call in USD
+    pub cost: f64,
+}
+
+impl ToolAccess {
+    /// Read domains and paths from the conventional argument keys
+    ///
+    /// Used by tools that don't describe their own access. URLs are reduced
+    /// to their host.
+    pub fn from_arguments(args: &Value) -> Self {
+        let strings = |keys: &[&str]| -> Vec<String> {
+            keys.iter()
+                .filter_map(|key| args.get(*key))
+                .flat_map(|value| match value {
+                    Value::String(s) => vec![s.clone()],
+                    Value::Array(items) => items
+                        .iter()
+                        .filter_map(|item| item.as_str().map(str::to_string))
+                        .collect(),
+                    _ => Vec::new(),
+                })
+                .collect()
+        };
+        Self {
+            domains: strings(DOMAIN_KEYS).iter().map(|s| host_of(s)).collect(),
+            paths: strings(PATH_KEYS),
+            ..Default::default()
+        }
+    }
+}
+
+/// Host of a URL, or the value itself when it is already a host
+fn host_of(value: &str) -> String {
+    let value = value.trim();
+    let rest = value.split_once("://").map_or(value, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// The limit a refused call broke.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionRule {
+    /// The call reaches a host outside `allowed_domains`
+    AllowedDomains,
+    /// The call touches a path outside `allowed_paths`
+    AllowedPaths,
+    /// The call costs more than `max_cost`
+    MaxCost,
+    /// The user's role may not use the tool
+    DeniedTools,
+    /// The call needs a user's approval, which it did not get
+    RequiresApproval,
+}
+
+/// A tool call refused by the agent's permissions, returned to the model
+/// in place of the tool's result.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolRefusal {
+    /// Always true, so the model can tell refusals from results
+    pub refused: bool,
+    /// Tool that was called
+    pub tool: String,
+    /// Limit the call broke
+    pub rule: PermissionRule,
+    /// Human-readable explanation
+    pub reason: String,
+    /// What the limit allows: domains, path patterns or the cost cap
+    pub allowed: Vec<String>,
+}
+
+impl ToolRefusal {
+    fn new(tool: &str, rule: PermissionRule, reason: String, allowed: Vec<String>) -> Self {
+        Self {
+            refused: true,
+            tool: tool.to_string(),
+            rule,
+            reason,
+            allowed,
+        }
+    }
+
+    /// Refusal of a call that needs a user's approval
+    pub(crate) fn approval(tool: &str, reason: String) -> Self {
+        Self::new(tool, PermissionRule::RequiresApproval, reason, Vec::new())
+    }
+
+    /// The refusal as a JSON tool result
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+/// One tool's limits, with its path globs compiled.
+#[derive(Debug, Clone)]
+struct Policy {
+    domains: Vec<String>,
+    path_patterns: Vec<String>,
+    /// Patterns that failed to compile are dropped here but stay in
+    /// `path_patterns`, so the allowlist still applies and matches less
+    paths: Vec<GlobMatcher>,
+    max_cost: Option<f64>,
+}
+
+impl Policy {
+    fn new(config: &ToolPermissionConfig) -> Self {
+        Self {
+            domains: config
+                .allowed_domains
+                .iter()
+                .map(|domain| domain.trim().trim_end_matches('.').to_ascii_lowercase())
+                .collect(),
+            path_patterns: config.allowed_paths.clone(),
+            paths: config
+                .allowed_paths
+                .iter()
+                .filter_map(|pattern| match Glob::new(pattern) {
+                    Ok(glob) => Some(glob.compile_matcher()),
+                    Err(e) => {
+                        tracing::warn!(
+                            "Ignoring invalid allowed_paths pattern '{}': {}",
+                            pattern,
+                            e
+                        );
+                        None
+                    }
+                })
+                .collect(),
+            max_cost: config.max_cost,
+        }
+    }
+
+    fn allows_domain(&self, host: &str) -> bool {
+        self.domains
+            .iter()
+            .any(|allowed| match allowed.strip_prefix("*.") {
+                Some(parent) => host == parent || host.ends_with(&format!(".{}", parent)),
+                None => host == allowed,
+            })
+    }
+
+    fn allows_path(&self, path: &str) -> bool {
+        // Traversal could escape any prefix the patterns anchor to
+        let traverses = path.split(['/', '\\']).any(|part| part == "..");
+        !traverses && self.paths.iter().any(|glob| glob.is_match(path))
+    }
+
+    fn check(&self, tool: &str, access: &ToolAccess) -> Option<ToolRefusal> {
+        if !self.domains.is_empty() {
+            if access.any_domain {
+                return Some(ToolRefusal::new(
+                    tool,
+                    PermissionRule::AllowedDomains,
+                    "The call is not limited to allowed domains".to_string(),
+                    self.domains.clone(),
+                ));
+            }
+            if let Some(host) = access.domains.iter().find(|d| !self.allows_domain(d)) {
+                return Some(ToolRefusal::new(
+                    tool,
+                    PermissionRule::AllowedDomains,
+                    format!("Domain '{}' is not allowed", host),
+                    self.domains.clone(),
+                ));
+            }
+        }
+
+        if !self.path_patterns.is_empty() {
+            if let Some(path) = access.paths.iter().find(|p| !self.allows_path(p)) {
+                return Some(ToolRefusal::new(
+                    tool,
+                    PermissionRule::AllowedPaths,
+                    format!("Path '{}' is not allowed", path),
+                    self.path_patterns.clone(),
+                ));
+            }
+        }
+
+        match self.max_cost {
+            Some(max_cost) if access.cost > max_cost => Some(ToolRefusal::new(
+                tool,
+                PermissionRule::MaxCost,
+                format!(
+                    "Estimated cost ${:.4} exceeds the ${:.4} limit per call",
+                    access.cost, max_cost
+                ),
+                vec![max_cost.to_string()],
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// An agent's tool permissions, checked before every tool call.
+#[derive(Debug, Clone, Default)]
+pub struct ToolPermissions {
+    policies: HashMap<String, Policy>,
+}
+
+impl ToolPermissions {
+    /// Compile an agent's `tool_permissions`
+    pub fn new(config: &HashMap<String, ToolPermissionConfig>) -> Self {
+        Self {
+            policies: config
+                .iter()
+                .map(|(tool, permission)| (tool.clone(), Policy::new(permission)))
+                .collect(),
+        }
+    }
+
+    /// Whether no limits are set
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    /// Check a call against the tool's own limits and the `"*"` limits
+    ///
+    /// The call is priced by [`call_access`].
+    pub fn check(&self, registry: &ToolRegistry, call: &ToolCall) -> Option<ToolRefusal> {
+        let policies: Vec<&Policy> = [call.name.as_str(), ANY_TOOL]
+            .iter()
+            .filter_map(|key| self.policies.get(*key))
+            .collect();
+        if policies.is_empty() {
+            return None;
+        }
+
+        let access = call_access(registry, &call.name, &call.arguments);
+        policies
+            .into_iter()
+            .find_map(|policy| policy.check(&call.name, &access))
+    }
+}
+
+/// What a tool call accesses
+///
+/// The call's cost is the tool's configured `cost_per_call`, falling back
+/// to the tool's own estimate.
+pub fn call_access(registry: &ToolRegistry, name: &str, arguments: &Value) -> ToolAccess {
+    let mut access = registry
+        .get(name)
+        .map(|tool| tool.access(arguments))
+        .unwrap_or_else(|| ToolAccess::from_arguments(arguments));
+    if let Some(cost) = registry
+        .get_config(name)
+        .and_then(|config| config.cost_per_call)
+    {
+        access.cost = cost;
+    }
+    access
+}
+
+/// The tool permission profile of a user's role.
+#[derive(Debug, Clone)]
+pub struct ToolProfile {
+    role: String,
+    denied: Vec<String>,
+    permissions: ToolPermissions,
+}
+
+impl ToolProfile {
+    /// Compile a role's `denied_tools` and `tool_permissions`
+    pub fn new(role: &str, config: &RoleConfig) -> Self {
+        Self {
+            role: role.to_string(),
+            denied: config.denied_tools.clone(),
+            permissions: ToolPermissions::new(&config.tool_permissions),
+        }
+    }
+
+    /// Profile of a user's role, if the user has one that limits tools
+    pub fn for_user(config: &AresConfig, user_id: &str, email: &str) -> Option<Arc<Self>> {
+        let (role, role_config) = config.role_of(user_id, email)?;
+        let profile = Self::new(role, role_config);
+        (!profile.denied.is_empty() || !profile.permissions.is_empty()).then(|| Arc::new(profile))
+    }
+
+    /// Name of the role
+    pub fn role(&self) -> &str {
+        &self.role
+    }
+
+    /// Check a call against the role's denied tools, then its limits
+    pub fn check(&self, registry: &ToolRegistry, call: &ToolCall) -> Option<ToolRefusal> {
+        if self
+            .denied
+            .iter()
+            .any(|tool| tool == &call.name || tool == ANY_TOOL)
+        {
+            return Some(ToolRefusal::new(
+                &call.name,
+                PermissionRule::DeniedTools,
+                format!("Role '{}' may not use tool '{}'", self.role, call.name),
+                Vec::new(),
+            ));
+        }
+        self.permissions.check(registry, call)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::search::WebSearch;
+    use crate::utils::toml_config::ToolConfig;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn call(name: &str, arguments: Value) -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            name: name.to_string(),
+            arguments,
+        }
+    }
+
+    fn permissions(entries: &[(&str, ToolPermissionConfig)]) -> ToolPermissions {
+        ToolPermissions::new(
+            &entries
+                .iter()
+                .map(|(tool, config)| (tool.to_string(), config.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_domains_and_paths() {
+        let registry = ToolRegistry::new();
+        let permissions = permissions(&[(
+            "fetch",
+            ToolPermissionConfig {
+                allowed_domains: vec!["docs.rs".to_string(), "*.rust-lang.org".to_string()],
+                allowed_paths: vec!["/srv/reports/**".to_string()],
+                max_cost: None,
+            },
+        )]);
+
+        let allowed = [
+            json!({"url": "https://docs.rs/serde"}),
+            json!({"url": "https://blog.rust-lang.org/2024/"}),
+            json!({"urls": ["rust-lang.org", "https://user@DOCS.RS:443/x"]}),
+            json!({"path": "/srv/reports/2024/q1.csv"}),
+        ];
+        for args in allowed {
+            assert!(permissions.check(&registry, &call("fetch", args)).is_none());
+        }
+
+        let refusal = permissions
+            .check(
+                &registry,
+                &call("fetch", json!({"url": "https://evil-docs.rs/"})),
+            )
+            .unwrap();
+        assert!(refusal.refused);
+        assert_eq!(refusal.rule, PermissionRule::AllowedDomains);
+        assert_eq!(refusal.to_value()["rule"], "allowed_domains");
+
+        for path in ["/etc/passwd", "/srv/reports/../../etc/passwd"] {
+            let refusal = permissions
+                .check(&registry, &call("fetch", json!({"file_path": path})))
+                .unwrap();
+            assert_eq!(refusal.rule, PermissionRule::AllowedPaths);
+        }
+
+        // Other tools are unrestricted
+        assert!(permissions
+            .check(&registry, &call("calculator", json!({"path": "/etc"})))
+            .is_none());
+    }
+
+    #[test]
+    fn test_web_search_scope_and_cost() {
+        let mut registry = ToolRegistry::new();
+        registry.register_with_config(
+            Arc::new(WebSearch::new()),
+            ToolConfig {
+                cost_per_call: Some(0.02),
+                ..Default::default()
+            },
+        );
+        let permissions = permissions(&[
+            (
+                "web_search",
+                ToolPermissionConfig {
+                    allowed_domains: vec!["docs.rs".to_string()],
+                    ..Default::default()
+                },
+            ),
+            (
+                ANY_TOOL,
+                ToolPermissionConfig {
+                    max_cost: Some(0.05),
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        let scoped = call("web_search", json!({"query": "serde derive site:docs.rs"}));
+        assert!(permissions.check(&registry, &scoped).is_none());
+
+        let unscoped = call("web_search", json!({"query": "serde derive"}));
+        let refusal = permissions.check(&registry, &unscoped).unwrap();
+        assert_eq!(refusal.rule, PermissionRule::AllowedDomains);
+
+        registry.set_config(
+            "web_search",
+            ToolConfig {
+                cost_per_call: Some(0.10),
+                ..Default::default()
+            },
+        );
+        let refusal = permissions.check(&registry, &scoped).unwrap();
+        assert_eq!(refusal.rule, PermissionRule::MaxCost);
+        assert_eq!(refusal.allowed, vec!["0.05"]);
+    }
+
+    #[test]
+    fn test_role_profile() {
+        let registry = ToolRegistry::new();
+        let profile = ToolProfile::new(
+            "viewer",
+            &RoleConfig {
+                denied_tools: vec!["web_fetch".to_string()],
+                tool_permissions: [(
+                    "fetch".to_string(),
+                    ToolPermissionConfig {
+                        allowed_domains: vec!["docs.rs".to_string()],
+                        ..Default::default()
+                    },
+                )]
+                .into(),
+                ..Default::default()
+            },
+        );
+
+        let refusal = profile
+            .check(
+                &registry,
+                &call("web_fetch", json!({"url": "https://docs.rs"})),
+            )
+            .unwrap();
+        assert_eq!(refusal.rule, PermissionRule::DeniedTools);
+        assert_eq!(refusal.to_value()["rule"], "denied_tools");
+        assert!(refusal.reason.contains("viewer"));
+
+        let refusal = profile
+            .check(
+                &registry,
+                &call("fetch", json!({"url": "https://evil.com"})),
+            )
+            .unwrap();
+        assert_eq!(refusal.rule, PermissionRule::AllowedDomains);
+        assert!(profile
+            .check(&registry, &call("calculator", json!({"expr": "2+2"})))
+            .is_none());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::tools::permissions::ToolAccess;
 use crate::types::{Result, ToolDefinition};
 use crate::utils::toml_config::{AresConfig, ToolConfig};
 use async_trait::async_trait;
@@ -19,6 +20,13 @@ pub trait Tool: Send + Sync {
     fn parameters_schema(&self) -> Value;
     /// Executes the tool with the given arguments.
     async fn execute(&self, args: Value) -> Result<Value>;
+    /// Describes what a call with these arguments would reach, for checking
+    /// against agent tool permissions before it runs.
+    ///
+    /// Defaults to reading URLs and paths from conventional argument keys.
+    fn access(&self, args: &Value) -> ToolAccess {
+        ToolAccess::from_arguments(args)
+    }
 }
 
 /// Registry for managing tools with configuration support
@@ -66,6 +74,11 @@ impl ToolRegistry {
         self.configs.get(name)
     }
 
+    /// Check if a user must approve calls to a tool
+    pub fn requires_approval(&self, name: &str) -> bool {
+        self.configs.get(name).is_some_and(|c| c.requires_approval)
+    }
+
     /// Check if a tool is enabled
     pub fn is_enabled(&self, name: &str) -> bool {
         self.configs.get(name).map(|c| c.enabled).unwrap_or(true) // Default to enabled if no config
@@ -181,6 +194,8 @@ mod tests {
                 enabled: false,
                 description: None,
                 timeout_secs: 30,
+                cost_per_call: None,
+                requires_approval: false,
                 extra: HashMap::new(),
             },
         );
@@ -196,6 +211,8 @@ mod tests {
                 enabled: true,
                 description: None,
                 timeout_secs: 60,
+                cost_per_call: None,
+                requires_approval: false,
                 extra: HashMap::new(),
             },
         );
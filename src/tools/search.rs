@@ -1,3 +1,4 @@
+use crate::tools::permissions::ToolAccess;
 use crate::tools::registry::Tool;
 use crate::types::Result;
 use async_trait::async_trait;
@@ -51,6 +52,23 @@ impl Tool for WebSearch {
         })
     }
 
+    /// Searches are scoped only by `site:` operators in the query; without
+    /// one, the search may return any domain.
+    fn access(&self, args: &Value) -> ToolAccess {
+        let domains: Vec<String> = args["query"]
+            .as_str()
+            .unwrap_or_default()
+            .split_whitespace()
+            .filter_map(|term| term.strip_prefix("site:"))
+            .map(|site| site.trim_end_matches('.').to_ascii_lowercase())
+            .collect();
+        ToolAccess {
+            any_domain: domains.is_empty(),
+            domains,
+            ..Default::default()
+        }
+    }
+
     async fn execute(&self, args: Value) -> Result<Value> {
         let query = args["query"]
             .as_str()
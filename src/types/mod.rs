@@ -11,6 +11,7 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 use utoipa::ToSchema;
 
 /// Default datetime for serde deserialization
@@ -25,12 +26,225 @@ fn default_datetime() -> DateTime<Utc> {
 pub struct ChatRequest {
     /// The user's message to send to the agent.
     pub message: String,
-    /// Optional agent type to handle the request. Defaults to router.
+    /// Optional agent type to handle the request. Defaults to router;
+    /// `debate` runs the debate workflow.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub agent_type: Option<AgentType>,
     /// Optional context ID for conversation continuity.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub context_id: Option<String>,
+    /// Sampling seed for a reproducible run. Passed to providers that
+    /// support seeding (Ollama, OpenAI) and recorded with the run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
+    /// Persona of the agent to answer in, one of the agent's `personas`.
+    /// The conversation keeps it for later messages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona: Option<String>,
+    /// IDs of uploaded files (`/api/files`) to attach to the conversation
+    /// before answering. Files already attached are skipped.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub file_ids: Vec<String>,
+    /// Project to start a new conversation in (`/api/projects`). Ignored
+    /// for an existing conversation, which stays in its project.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    /// Generation parameters for this message only, within the answering
+    /// agent's `parameter_limits`.
+    #[serde(default, skip_serializing_if = "GenerationParameters::is_empty")]
+    pub parameters: GenerationParameters,
+}
+
+/// Generation parameters a chat request sets instead of the model's.
+///
+/// Each must be within the bounds the answering agent sets in
+/// `parameter_limits`; parameters the agent has no bounds for can't be set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct GenerationParameters {
+    /// Sampling temperature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Maximum tokens to generate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Nucleus sampling parameter.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+}
+
+impl GenerationParameters {
+    /// Whether no parameter is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check the parameters are within an agent's limits, naming the first
+    /// that isn't.
+    pub fn check(
+        &self,
+        limits: &crate::utils::toml_config::ParameterLimitsConfig,
+    ) -> std::result::Result<(), String> {
+        for (name, value, range) in [
+            ("temperature", self.temperature, limits.temperature),
+            ("top_p", self.top_p, limits.top_p),
+        ] {
+            let Some(value) = value else { continue };
+            match range {
+                None => return Err(format!("The agent doesn't allow setting {}", name)),
+                Some(range) if !range.contains(value) => {
+                    return Err(format!(
+                        "{} must be between {} and {}",
+                        name, range.min, range.max
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+        match (self.max_tokens, limits.max_tokens) {
+            (None, _) => Ok(()),
+            (Some(_), None) => Err("The agent doesn't allow setting max_tokens".to_string()),
+            (Some(max_tokens), Some(limit)) if max_tokens == 0 || max_tokens > limit => {
+                Err(format!("max_tokens must be between 1 and {}", limit))
+            }
+            (Some(_), Some(_)) => Ok(()),
+        }
+    }
+}
+
+/// Request payload for regenerating the last assistant message.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct RegenerateRequest {
+    /// How the new answer should differ, e.g. "shorter" or "more formal".
+    /// Sent to the agent only; it is not stored in the conversation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feedback: Option<String>,
+    /// Agent to regenerate with. Defaults to the conversation's agent or the router.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_type: Option<AgentType>,
+    /// Return the new answer as a draft without replacing the stored one.
+    #[serde(default)]
+    pub draft: bool,
+    /// Sampling seed for a reproducible regeneration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
+}
+
+/// Model, temperature, agent and persona pinned on a conversation.
+///
+/// Subsequent messages in the conversation use these instead of the
+/// configured defaults; `None` keeps the default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ConversationOverrides {
+    /// Model to use instead of the agent's configured model.
+    pub model: Option<String>,
+    /// Sampling temperature to use instead of the model's configured one.
+    pub temperature: Option<f32>,
+    /// Agent to handle messages that don't specify an `agent_type`.
+    pub agent: Option<String>,
+    /// Persona for agents that define it; other agents ignore it.
+    #[serde(default)]
+    pub persona: Option<String>,
+}
+
+/// RAG collections a conversation's retrieval is pinned to.
+///
+/// While a conversation has a scope (and no attached files), each message
+/// is answered from passages of these collections only.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RagScope {
+    /// Collections to search, by the names they were ingested under.
+    pub collections: Vec<String>,
+    /// Tags a document must carry to be searched; empty searches every document.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// How long a user wants answers to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseLength {
+    /// Short, to-the-point answers.
+    Brief,
+    /// The agent's usual answer length.
+    Normal,
+    /// Thorough answers with explanations and examples.
+    Detailed,
+}
+
+impl ResponseLength {
+    /// Get the lowercase name, as stored and serialized
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResponseLength::Brief => "brief",
+            ResponseLength::Normal => "normal",
+            ResponseLength::Detailed => "detailed",
+        }
+    }
+
+    /// Parse a stored name; unknown names are `None`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "brief" => Some(ResponseLength::Brief),
+            "normal" => Some(ResponseLength::Normal),
+            "detailed" => Some(ResponseLength::Detailed),
+            _ => None,
+        }
+    }
+}
+
+/// Chat defaults a user sets for themselves.
+///
+/// Unlike memory preferences, which are extracted from conversations, these
+/// are set explicitly through `/api/preferences`. They apply to every chat;
+/// a request's `agent_type` and a conversation's overrides take precedence.
+/// `None` keeps the configured default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ChatPreferences {
+    /// Agent to handle messages when neither the request nor the
+    /// conversation picks one.
+    pub default_agent: Option<String>,
+    /// Model to use instead of the agent's configured model.
+    pub model: Option<String>,
+    /// Sampling temperature to use instead of the model's configured one.
+    pub temperature: Option<f32>,
+    /// How long answers should be.
+    pub response_length: Option<ResponseLength>,
+    /// Language to answer in, e.g. "German" or "pt-BR".
+    pub language: Option<String>,
+}
+
+impl ChatPreferences {
+    /// Fill the settings a conversation doesn't pin with the user's defaults
+    pub fn apply(&self, overrides: ConversationOverrides) -> ConversationOverrides {
+        ConversationOverrides {
+            model: overrides.model.or_else(|| self.model.clone()),
+            temperature: overrides.temperature.or(self.temperature),
+            agent: overrides.agent.or_else(|| self.default_agent.clone()),
+            persona: overrides.persona,
+        }
+    }
+
+    /// System prompt section asking for the preferred answer length and
+    /// language, if the user set either
+    pub fn instructions(&self) -> Option<String> {
+        let mut instructions = Vec::new();
+        match self.response_length {
+            Some(ResponseLength::Brief) => {
+                instructions.push("Keep your answers brief and to the point.".to_string())
+            }
+            Some(ResponseLength::Detailed) => instructions.push(
+                "Give detailed, thorough answers with explanations and examples.".to_string(),
+            ),
+            Some(ResponseLength::Normal) | None => {}
+        }
+        if let Some(language) = &self.language {
+            instructions.push(format!(
+                "Answer in {}, whatever language the user writes in.",
+                language
+            ));
+        }
+        (!instructions.is_empty()).then(|| instructions.join(" "))
+    }
 }
 
 /// Response from chat endpoints.
@@ -44,6 +258,30 @@ pub struct ChatResponse {
     pub context_id: String,
     /// Optional sources used to generate the response.
     pub sources: Option<Vec<Source>>,
+    /// Reasoning steps of agents using the ReAct strategy, for debugging.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<Vec<crate::agents::ReactStep>>,
+    /// Whether the response is an earlier answer to a similar question,
+    /// served from the agent's answer cache.
+    #[serde(default)]
+    pub cached: bool,
+    /// When a cached answer was generated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_at: Option<DateTime<Utc>>,
+    /// Sampling seed the response was generated with, if the request set one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
+    /// ID of the stored assistant message, for fetching its tool-call trace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    /// The run limit that cut the agent short, if one did. The response is
+    /// then the partial output produced before it stopped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit_exceeded: Option<crate::agents::limits::LimitExceeded>,
+    /// Tool calls the agent is waiting for the user to approve. The response
+    /// is then empty; `POST /api/approvals/{id}` continues the run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval: Option<crate::agents::approval::PendingApproval>,
 }
 
 /// A source reference used in responses.
@@ -77,6 +315,10 @@ pub struct ResearchResponse {
     pub sources: Vec<Source>,
     /// Time taken for the research in milliseconds.
     pub duration_ms: u64,
+    /// Score of the findings from the critique pass, if the research
+    /// workflow has a judge.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub judgment: Option<crate::llm::Judgment>,
 }
 
 // ============= RAG API Types =============
@@ -86,8 +328,13 @@ pub struct ResearchResponse {
 pub struct RagIngestRequest {
     /// Collection name to ingest into.
     pub collection: String,
-    /// The text content to ingest.
+    /// The text content to ingest. May be omitted when `file_id` is set.
+    #[serde(default)]
     pub content: String,
+    /// ID of an uploaded file (`/api/files`) whose text is ingested
+    /// instead of `content`: PDF, DOCX, HTML, Markdown, CSV or plain text.
+    #[serde(default)]
+    pub file_id: Option<String>,
     /// Optional document title.
     pub title: Option<String>,
     /// Optional source URL or path.
@@ -118,29 +365,33 @@ pub struct RagSearchRequest {
     pub collection: String,
     /// The search query.
     pub query: String,
-    /// Maximum results to return (default: 10).
-    #[serde(default = "default_search_limit")]
-    pub limit: usize,
-    /// Search strategy to use: semantic, bm25, fuzzy, hybrid.
+    /// Maximum results to return (default: the collection's setting, else 10).
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Search strategy to use: semantic, bm25, fuzzy, hybrid
+    /// (default: the collection's setting, else semantic).
     #[serde(default)]
     pub strategy: Option<String>,
-    /// Minimum similarity threshold (0.0 to 1.0).
-    #[serde(default = "default_search_threshold")]
-    pub threshold: f32,
-    /// Whether to enable reranking.
+    /// Minimum similarity threshold (0.0 to 1.0) (default: the collection's setting, else 0.0).
+    #[serde(default)]
+    pub threshold: Option<f32>,
+    /// Whether to enable reranking (default: the collection's setting, else false).
     #[serde(default)]
-    pub rerank: bool,
+    pub rerank: Option<bool>,
+    /// Reranker to use if reranking: cross-encoder, cohere, llm (default: `[rag] reranker`).
+    #[serde(default)]
+    pub reranker: Option<String>,
     /// Reranker model to use if reranking.
     #[serde(default)]
     pub reranker_model: Option<String>,
-}
-
-fn default_search_limit() -> usize {
-    10
-}
-
-fn default_search_threshold() -> f32 {
-    0.0
+    /// Whether to skip retrieval for greetings and small talk
+    /// (default: `[rag] intent_classification`).
+    #[serde(default)]
+    pub classify_intent: Option<bool>,
+    /// Neighbouring parent sections to add to each result's context
+    /// (default: the collection's setting, else 0).
+    #[serde(default)]
+    pub parent_window: Option<usize>,
 }
 
 /// Single search result.
@@ -154,6 +405,13 @@ pub struct RagSearchResult {
     pub score: f32,
     /// Document metadata.
     pub metadata: DocumentMetadata,
+    /// Parent section around the matching chunk, when the collection stores
+    /// parent sections; the context to give an LLM.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    /// Search strategies that found the chunk.
+    #[serde(default)]
+    pub sources: Vec<crate::rag::search::SearchStrategy>,
 }
 
 /// Response from RAG search.
@@ -167,10 +425,219 @@ pub struct RagSearchResponse {
     pub strategy: String,
     /// Whether reranking was applied.
     pub reranked: bool,
+    /// Whether retrieval was skipped because the query is small talk.
+    #[serde(default)]
+    pub retrieval_skipped: bool,
     /// Query processing time in milliseconds.
     pub duration_ms: u64,
 }
 
+/// Request to start ingesting a bucket prefix in the background.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RagIngestJobRequest {
+    /// Collection name to ingest into.
+    pub collection: String,
+    /// Source URI: `s3://bucket/prefix`, `gs://bucket/prefix`,
+    /// `notion://[database_id]` or `confluence://SPACE`.
+    pub source: String,
+    /// Glob selecting objects, relative to the prefix (e.g., `**/*.md`).
+    /// Bucket sources only.
+    #[serde(default)]
+    pub glob: Option<String>,
+    /// Chunking strategy to use.
+    #[serde(default)]
+    pub chunking_strategy: Option<String>,
+    /// Tags added to every ingested document.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Re-sync the source this many seconds after each run (minimum 300).
+    #[serde(default)]
+    pub sync_interval_secs: Option<u64>,
+}
+
+/// Progress of a background ingestion job.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RagIngestJobResponse {
+    /// Job ID.
+    pub id: String,
+    /// Collection name.
+    pub collection: String,
+    /// Source URI.
+    pub source: String,
+    /// Glob selecting objects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+    /// Seconds between scheduled re-syncs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_interval_secs: Option<u64>,
+    /// Current state.
+    pub status: crate::rag::ingest_jobs::IngestJobStatus,
+    /// Objects ingested (or re-ingested after a change).
+    pub objects_ingested: usize,
+    /// Objects skipped because they were already ingested at the same version.
+    pub objects_unchanged: usize,
+    /// Objects skipped because they are too large, not text, or empty.
+    pub objects_skipped: usize,
+    /// Objects that could not be fetched or ingested.
+    pub objects_failed: usize,
+    /// Objects removed because they disappeared from the source.
+    pub objects_removed: usize,
+    /// Chunks created.
+    pub chunks_created: usize,
+    /// Most recent errors.
+    pub errors: Vec<String>,
+    /// When the job was created.
+    pub created_at: DateTime<Utc>,
+    /// When the job last made progress.
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Result of a GitHub webhook delivery.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RagGitHubWebhookResponse {
+    /// Event named in the `X-GitHub-Event` header.
+    pub event: String,
+    /// Ingest jobs re-synced by a push.
+    pub triggered_jobs: Vec<String>,
+}
+
+/// RAG settings stored with a collection.
+///
+/// Unset fields fall back to the request, the `[rag]` configuration, or the
+/// built-in defaults. `embedding_model` is recorded on first ingest so later
+/// ingests and searches keep using the model the collection was built with.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CollectionSettings {
+    /// Chunking strategy: word, semantic, character.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunking_strategy: Option<String>,
+    /// Chunk size (words, or characters for semantic/character chunking).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_size: Option<usize>,
+    /// Overlap between consecutive chunks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_overlap: Option<usize>,
+    /// Embedding model used for the collection's documents and queries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+    /// Default search strategy: semantic, bm25, fuzzy, hybrid.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_strategy: Option<String>,
+    /// Default maximum number of search results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_limit: Option<usize>,
+    /// Default minimum similarity threshold.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_threshold: Option<f32>,
+    /// Whether searches rerank by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerank: Option<bool>,
+    /// How far chunk feedback moves search scores, from 0.0 (ignored) to 1.0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback_weight: Option<f32>,
+    /// Size of parent sections in characters. When set, documents are split
+    /// into parent sections before chunking, and matching chunks expand to
+    /// their section. Applies to documents ingested after it is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_chunk_size: Option<usize>,
+    /// Neighbouring parent sections added on each side of a match's section.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_window: Option<usize>,
+}
+
+/// Settings of a RAG collection.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RagCollectionSettingsResponse {
+    /// Collection name.
+    pub collection: String,
+    /// Settings stored with the collection.
+    pub settings: CollectionSettings,
+    /// Embedding model in effect for the collection.
+    pub embedding_model: String,
+}
+
+/// Request to re-embed a collection with another embedding model.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct RagReembedRequest {
+    /// Embedding model to re-embed with (default: the configured `[rag] embedding_model`).
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+}
+
+/// Result of re-embedding a collection.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RagReembedResponse {
+    /// Collection name.
+    pub collection: String,
+    /// Embedding model the collection now uses.
+    pub embedding_model: String,
+    /// Embedding model the collection used before.
+    pub previous_embedding_model: String,
+    /// Dimensions of the collection's vectors now.
+    pub dimensions: usize,
+    /// Dimensions of the collection's vectors before.
+    pub previous_dimensions: usize,
+    /// Number of chunks re-embedded.
+    pub documents_reembedded: usize,
+}
+
+/// A vote on a retrieved chunk.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RagChunkFeedbackRequest {
+    /// Collection the chunk was retrieved from.
+    pub collection: String,
+    /// Chunk ID, as returned in search results.
+    pub chunk_id: String,
+    /// Whether the chunk helped: up or down.
+    pub vote: crate::rag::feedback::FeedbackVote,
+    /// Query the chunk was retrieved for, kept with down votes for curation.
+    #[serde(default)]
+    pub query: Option<String>,
+}
+
+/// Feedback recorded for a chunk.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RagChunkFeedbackResponse {
+    /// Chunk ID.
+    pub chunk_id: String,
+    /// Up votes.
+    pub up: u32,
+    /// Down votes.
+    pub down: u32,
+    /// Net feedback from -1.0 (always down) to 1.0 (always up), damped while votes are few.
+    pub score: f32,
+}
+
+/// A chunk with consistently poor feedback.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RagFlaggedChunk {
+    /// Chunk ID.
+    pub chunk_id: String,
+    /// Up votes.
+    pub up: u32,
+    /// Down votes.
+    pub down: u32,
+    /// Net feedback score.
+    pub score: f32,
+    /// Queries of the most recent down votes.
+    pub down_queries: Vec<String>,
+    /// Chunk text, if the chunk still exists.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Metadata of the chunk's document, if the chunk still exists.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<DocumentMetadata>,
+}
+
+/// Chunks of a collection that need curation, worst first.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RagFeedbackReportResponse {
+    /// Collection name.
+    pub collection: String,
+    /// Chunks with consistently poor feedback.
+    pub chunks: Vec<RagFlaggedChunk>,
+}
+
 /// Request to delete a collection.
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct RagDeleteCollectionRequest {
@@ -226,6 +693,9 @@ pub enum AgentType {
     /// Handles HR and employee-related queries.
     #[serde(rename = "hr")]
     HR,
+    /// Runs the `debate` workflow: several agents answer and a judge agent
+    /// decides the final answer.
+    Debate,
     /// Custom user-defined agent type.
     /// The string contains the agent's unique identifier/name.
     #[serde(untagged)]
@@ -243,6 +713,7 @@ impl AgentType {
             AgentType::Sales => "sales",
             AgentType::Finance => "finance",
             AgentType::HR => "hr",
+            AgentType::Debate => "debate",
             AgentType::Custom(name) => name,
         }
     }
@@ -257,6 +728,7 @@ impl AgentType {
             "sales" => AgentType::Sales,
             "finance" => AgentType::Finance,
             "hr" => AgentType::HR,
+            "debate" => AgentType::Debate,
             _ => AgentType::Custom(s.to_string()),
         }
     }
@@ -284,11 +756,38 @@ pub struct AgentContext {
     pub conversation_history: Vec<Message>,
     /// User's stored memory and preferences.
     pub user_memory: Option<UserMemory>,
+    /// Cancelled when the client disconnects or the request times out.
+    pub cancellation: CancellationToken,
+    /// Conversation hooks to run for this request.
+    pub hooks: std::sync::Arc<crate::hooks::ConversationHooks>,
+    /// The user's chat preferences (answer length and language).
+    pub preferences: ChatPreferences,
+    /// Tool permissions of the user's role, enforced whichever agent runs.
+    pub tool_profile: Option<std::sync::Arc<crate::tools::permissions::ToolProfile>>,
+    /// Message bus of the workflow run the agent takes part in, if any.
+    pub bus: Option<std::sync::Arc<crate::agents::bus::AgentBus>>,
+    /// Workspace the request acts in; `None` for the user's personal space.
+    pub workspace_id: Option<String>,
+}
+
+impl AgentContext {
+    /// Key the custom agents and memory available to the run are stored
+    /// under: the user's ID, or their workspace's owner key (see
+    /// [`ActiveWorkspace::owner`](crate::api::workspace::ActiveWorkspace::owner)).
+    pub fn owner(&self) -> String {
+        match &self.workspace_id {
+            Some(id) => crate::db::workspaces::owner_key(id),
+            None => self.user_id.clone(),
+        }
+    }
 }
 
 /// A single message in a conversation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
+    /// Unique identifier for this message.
+    #[serde(default)]
+    pub id: String,
     /// The role of the message sender.
     pub role: MessageRole,
     /// The message content.
@@ -358,6 +857,47 @@ pub struct MemoryFact {
 
 // ============= Tool Types =============
 
+/// A tool call made while generating an assistant message.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ToolCallTrace {
+    /// Agent that made the call.
+    pub agent: String,
+    /// Tool-calling round the call was made in, starting at 1.
+    pub iteration: u32,
+    /// Tool call ID assigned by the model.
+    pub tool_call_id: String,
+    /// Name of the tool that was called.
+    pub tool: String,
+    /// Arguments the tool was called with.
+    pub arguments: serde_json::Value,
+    /// Result returned by the tool (or error object).
+    pub result: serde_json::Value,
+    /// Whether the call succeeded.
+    pub success: bool,
+    /// Time taken to run the tool in milliseconds.
+    pub duration_ms: u64,
+    /// Error message if the call failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ToolCallTrace {
+    /// Trace of a tool call made by `agent`
+    pub fn new(agent: impl Into<String>, record: crate::llm::coordinator::ToolCallRecord) -> Self {
+        Self {
+            agent: agent.into(),
+            iteration: u32::try_from(record.iteration).unwrap_or(u32::MAX),
+            tool_call_id: record.id,
+            tool: record.name,
+            arguments: record.arguments,
+            result: record.result,
+            success: record.success,
+            duration_ms: record.duration_ms,
+            error: record.error,
+        }
+    }
+}
+
 /// Definition of a tool that can be called by an LLM.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ToolDefinition {
@@ -479,12 +1019,55 @@ pub struct RegisterRequest {
 pub struct TokenResponse {
     /// JWT access token for API authentication.
     pub access_token: String,
-    /// Refresh token for obtaining new access tokens.
+    /// Refresh token for obtaining new access tokens. Empty, and left out,
+    /// when the server sends refresh tokens as cookies.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub refresh_token: String,
     /// Time in seconds until the access token expires.
     pub expires_in: i64,
 }
 
+/// What a user may do, from most to least privileged.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Manages users' roles and uses the admin endpoints.
+    Admin,
+    /// Uses the API, including RAG management and agent CRUD.
+    #[default]
+    Member,
+    /// Reads and chats, but can't manage RAG collections or agents.
+    ReadOnly,
+}
+
+impl Role {
+    /// Get the snake_case name, as stored and serialized
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Member => "member",
+            Role::ReadOnly => "read_only",
+        }
+    }
+
+    /// Parse a stored name; unknown names are `None`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "admin" => Some(Role::Admin),
+            "member" => Some(Role::Member),
+            "read_only" => Some(Role::ReadOnly),
+            _ => None,
+        }
+    }
+
+    /// Whether this role grants at least the privileges of `required`
+    pub fn allows(&self, required: Role) -> bool {
+        *self <= required
+    }
+}
+
 /// JWT claims embedded in access tokens.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
@@ -492,6 +1075,10 @@ pub struct Claims {
     pub sub: String,
     /// User's email address.
     pub email: String,
+    /// User's role when the token was issued; tokens issued before roles
+    /// existed are members.
+    #[serde(default)]
+    pub role: Role,
     /// Expiration time (Unix timestamp).
     pub exp: usize,
     /// Issued at time (Unix timestamp).
@@ -523,6 +1110,19 @@ pub enum ErrorCode {
     ExternalServiceError,
     /// Internal server error
     InternalError,
+    /// Request or response was blocked by a guardrail policy
+    GuardrailBlocked,
+    /// A spend budget has been exhausted
+    BudgetExceeded,
+    /// The operation was cancelled or timed out before completing
+    Cancelled,
+    /// The server is in maintenance mode and not accepting new work
+    ServiceUnavailable,
+    /// Too many requests to a rate-limited route
+    RateLimited,
+    /// An idempotency key is in use by a running request, or was used for
+    /// a different one
+    IdempotencyConflict,
 }
 
 /// Application-wide error type.
@@ -540,6 +1140,10 @@ pub enum AppError {
     #[error("Authentication error: {0}")]
     Auth(String),
 
+    /// The user's role doesn't allow the operation.
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     /// Requested resource was not found.
     #[error("Not found: {0}")]
     NotFound(String),
@@ -559,6 +1163,22 @@ pub enum AppError {
     /// Internal server error.
     #[error("Internal error: {0}")]
     Internal(String),
+
+    /// Input or output was blocked by a guardrail policy.
+    #[error("Blocked by guardrail: {0}")]
+    Guardrail(String),
+
+    /// A configured spend budget has been exhausted.
+    #[error("Budget exceeded: {0}")]
+    BudgetExceeded(String),
+
+    /// The operation was cancelled (client disconnect) or timed out.
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
+    /// The server is temporarily not accepting the request (maintenance mode).
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
 }
 
 impl AppError {
@@ -568,11 +1188,16 @@ impl AppError {
             AppError::Database(_) => ErrorCode::DatabaseError,
             AppError::LLM(_) => ErrorCode::LlmError,
             AppError::Auth(_) => ErrorCode::AuthenticationFailed,
+            AppError::Forbidden(_) => ErrorCode::AuthorizationFailed,
             AppError::NotFound(_) => ErrorCode::NotFound,
             AppError::InvalidInput(_) => ErrorCode::InvalidInput,
             AppError::Configuration(_) => ErrorCode::ConfigurationError,
             AppError::External(_) => ErrorCode::ExternalServiceError,
             AppError::Internal(_) => ErrorCode::InternalError,
+            AppError::Guardrail(_) => ErrorCode::GuardrailBlocked,
+            AppError::BudgetExceeded(_) => ErrorCode::BudgetExceeded,
+            AppError::Cancelled(_) => ErrorCode::Cancelled,
+            AppError::Unavailable(_) => ErrorCode::ServiceUnavailable,
         }
     }
 
@@ -613,6 +1238,7 @@ impl axum::response::IntoResponse for AppError {
             AppError::Database(msg) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
             AppError::LLM(msg) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
             AppError::Auth(msg) => (axum::http::StatusCode::UNAUTHORIZED, msg.clone()),
+            AppError::Forbidden(msg) => (axum::http::StatusCode::FORBIDDEN, msg.clone()),
             AppError::NotFound(msg) => (axum::http::StatusCode::NOT_FOUND, msg.clone()),
             AppError::InvalidInput(msg) => (axum::http::StatusCode::BAD_REQUEST, msg.clone()),
             AppError::Configuration(msg) => {
@@ -620,6 +1246,14 @@ impl axum::response::IntoResponse for AppError {
             }
             AppError::External(msg) => (axum::http::StatusCode::BAD_GATEWAY, msg.clone()),
             AppError::Internal(msg) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
+            AppError::Guardrail(msg) => (axum::http::StatusCode::UNPROCESSABLE_ENTITY, msg.clone()),
+            AppError::BudgetExceeded(msg) => {
+                (axum::http::StatusCode::TOO_MANY_REQUESTS, msg.clone())
+            }
+            AppError::Cancelled(msg) => (axum::http::StatusCode::GATEWAY_TIMEOUT, msg.clone()),
+            AppError::Unavailable(msg) => {
+                (axum::http::StatusCode::SERVICE_UNAVAILABLE, msg.clone())
+            }
         };
 
         let body = serde_json::json!({
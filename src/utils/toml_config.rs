@@ -17,8 +17,9 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc};
 use tracing::{error, info, warn};
+use utoipa::ToSchema;
 
 /// Root configuration structure loaded from ares.toml
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,6 +61,75 @@ pub struct AresConfig {
     #[serde(default)]
     pub rag: RagConfig,
 
+    /// Guardrail policies applied around agent generations
+    #[serde(default)]
+    pub guardrails: GuardrailsConfig,
+
+    /// Spend budgets and per-model token pricing
+    #[serde(default)]
+    pub budgets: BudgetsConfig,
+
+    /// Archival of inactive conversations to cold storage
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+
+    /// Agents run on a cron schedule, keyed by schedule name
+    #[serde(default)]
+    pub schedules: HashMap<String, ScheduleConfig>,
+
+    /// User roles and the tools they may use, keyed by role name
+    #[serde(default)]
+    pub roles: HashMap<String, RoleConfig>,
+
+    /// Judges that score outputs against a rubric, keyed by judge name
+    #[serde(default)]
+    pub judges: HashMap<String, JudgeConfig>,
+
+    /// Canary routing of model traffic, keyed by the name of the model from
+    /// \[models\] whose calls are split
+    #[serde(default)]
+    pub canaries: HashMap<String, CanaryConfig>,
+
+    /// Agents hosted by other systems, reached over A2A, keyed by agent name
+    #[serde(default)]
+    pub remote_agents: HashMap<String, RemoteAgentConfig>,
+
+    /// Automatic titling of new conversations
+    #[serde(default)]
+    pub titles: TitlesConfig,
+
+    /// Maintenance mode (rejects new generation requests)
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+
+    /// Uploaded files (`/api/files`)
+    #[serde(default)]
+    pub files: FilesConfig,
+
+    /// Batch chat (`/api/chat/batch`)
+    #[serde(default)]
+    pub batch: BatchConfig,
+
+    /// Model warm-up at startup
+    #[serde(default)]
+    pub warmup: WarmupConfig,
+
+    /// Readiness checks (`/health/ready`)
+    #[serde(default)]
+    pub health: HealthConfig,
+
+    /// Workers running the durable background job queue
+    #[serde(default)]
+    pub jobs: JobsConfig,
+
+    /// Encryption at rest of sensitive columns and uploaded files
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+
+    /// Redis shared by ARES instances (needs the `redis` feature)
+    #[serde(default)]
+    pub redis: Option<RedisConfig>,
+
     /// Dynamic configuration paths (TOON files)
     #[serde(default)]
     pub config: DynamicConfigPaths,
@@ -87,6 +157,23 @@ pub struct ServerConfig {
     #[serde(default = "default_cors_origins")]
     pub cors_origins: Vec<String>,
 
+    /// Methods allowed in CORS requests (default: GET, POST, PUT, DELETE,
+    /// OPTIONS and PATCH).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors_methods: Option<Vec<String>>,
+
+    /// Request headers allowed in CORS requests (default: the headers ARES
+    /// clients send, such as Authorization, Content-Type, X-API-Key and the
+    /// CSRF and idempotency headers).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors_headers: Option<Vec<String>>,
+
+    /// Reverse proxies trusted to report the client's address in
+    /// `X-Forwarded-For`, as IP addresses or CIDR ranges (default: none,
+    /// clients are identified by the address they connect from).
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+
     /// Rate limiting: requests per second per IP (default: 100, 0 = disabled).
     #[serde(default = "default_rate_limit")]
     pub rate_limit_per_second: u32,
@@ -94,6 +181,29 @@ pub struct ServerConfig {
     /// Rate limiting burst size (default: 10).
     #[serde(default = "default_rate_limit_burst")]
     pub rate_limit_burst: u32,
+
+    /// Per-user limits on expensive routes (default: none).
+    #[serde(default)]
+    pub rate_limits: RateLimitsConfig,
+
+    /// JSONL file every LLM call is appended to (default: none). The log
+    /// can be replayed against a server with `ares-server loadtest`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub llm_call_log: Option<String>,
+
+    /// How long a shutdown waits for open requests and agent runs to
+    /// finish before saving state and exiting, in seconds (default: 30).
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout_secs: u64,
+
+    /// How long responses to requests with an `Idempotency-Key` header are
+    /// kept for retries, in seconds (default: 86400, 0 = ignore the header).
+    #[serde(default = "default_idempotency_ttl")]
+    pub idempotency_ttl_secs: u64,
+
+    /// Production security profile (default: off).
+    #[serde(default)]
+    pub security: SecurityConfig,
 }
 
 fn default_host() -> String {
@@ -123,6 +233,14 @@ fn default_rate_limit_burst() -> u32 {
     10
 }
 
+fn default_shutdown_timeout() -> u64 {
+    30
+}
+
+fn default_idempotency_ttl() -> u64 {
+    86_400
+}
+
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
@@ -130,12 +248,144 @@ impl Default for ServerConfig {
             port: default_port(),
             log_level: default_log_level(),
             cors_origins: default_cors_origins(),
+            cors_methods: None,
+            cors_headers: None,
+            trusted_proxies: Vec::new(),
             rate_limit_per_second: default_rate_limit(),
             rate_limit_burst: default_rate_limit_burst(),
+            rate_limits: RateLimitsConfig::default(),
+            llm_call_log: None,
+            shutdown_timeout_secs: default_shutdown_timeout(),
+            idempotency_ttl_secs: default_idempotency_ttl(),
+            security: SecurityConfig::default(),
+        }
+    }
+}
+
+/// Hardening for a server exposed to browsers and the internet.
+///
+/// Everything is off by default:
+///
+/// ```toml
+/// [server.security]
+/// strict_cors = true            # only the listed cors_origins, never "*"
+/// headers = true                # CSP, HSTS, nosniff, frame and referrer policies
+/// cookie_refresh_tokens = true  # refresh tokens in HttpOnly cookies, CSRF-checked
+///
+/// [server.security.tls]
+/// cert_path = "/etc/ares/tls/fullchain.pem"
+/// key_path = "/etc/ares/tls/privkey.pem"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityConfig {
+    /// Refuse to start unless `cors_origins` lists exact origins (default: false).
+    #[serde(default)]
+    pub strict_cors: bool,
+
+    /// Add security headers to every response (default: false).
+    #[serde(default)]
+    pub headers: bool,
+
+    /// Content-Security-Policy sent with `headers` (default: same-origin
+    /// resources, plus the WebAssembly and inline styles the embedded UI needs).
+    #[serde(default = "default_content_security_policy")]
+    pub content_security_policy: String,
+
+    /// Strict-Transport-Security max-age sent with `headers`, in seconds
+    /// (default: one year, 0 = no HSTS header).
+    #[serde(default = "default_hsts_max_age")]
+    pub hsts_max_age_secs: u64,
+
+    /// Send refresh tokens as HttpOnly cookies instead of in response
+    /// bodies, and require a CSRF token when a cookie is used (default: false).
+    #[serde(default)]
+    pub cookie_refresh_tokens: bool,
+
+    /// Terminate TLS in the server with these certificates (default: none,
+    /// plain HTTP behind a proxy).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            strict_cors: false,
+            headers: false,
+            content_security_policy: default_content_security_policy(),
+            hsts_max_age_secs: default_hsts_max_age(),
+            cookie_refresh_tokens: false,
+            tls: None,
         }
     }
 }
 
+fn default_content_security_policy() -> String {
+    "default-src 'self'; script-src 'self' 'wasm-unsafe-eval'; style-src 'self' 'unsafe-inline'; \
+     img-src 'self' data:; connect-src 'self'; frame-ancestors 'none'; base-uri 'self'; \
+     form-action 'self'"
+        .to_string()
+}
+
+fn default_hsts_max_age() -> u64 {
+    31_536_000
+}
+
+/// Certificate and key for TLS termination, in PEM files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// Certificate chain, leaf first
+    pub cert_path: String,
+    /// Private key (PKCS#8, PKCS#1 or SEC1)
+    pub key_path: String,
+}
+
+/// Limits on how often each caller may use the expensive routes.
+///
+/// Callers are told apart by user (JWT), API key, or else IP address, and
+/// each has a token bucket per route group. Requests over the limit get a
+/// 429 with a `Retry-After` header. Groups without a limit are unlimited.
+///
+/// ```toml
+/// [server.rate_limits]
+/// chat = { requests_per_minute = 30, burst = 10 }   # chat, regenerate, agent runs
+/// research = { requests_per_minute = 5 }            # deep research
+/// ingest = { requests_per_minute = 10, burst = 20 } # RAG ingestion
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RateLimitsConfig {
+    /// Limit on chat, regenerate and agent run requests
+    #[serde(default)]
+    pub chat: Option<RateLimit>,
+
+    /// Limit on deep research requests
+    #[serde(default)]
+    pub research: Option<RateLimit>,
+
+    /// Limit on RAG ingest requests and ingest jobs
+    #[serde(default)]
+    pub ingest: Option<RateLimit>,
+}
+
+/// A token bucket: `burst` requests at once, refilled at
+/// `requests_per_minute`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Sustained requests allowed per minute
+    pub requests_per_minute: u32,
+
+    /// Requests allowed at once (default: `requests_per_minute`)
+    #[serde(default)]
+    pub burst: Option<u32>,
+}
+
+impl RateLimit {
+    /// Requests allowed at once
+    pub fn capacity(&self) -> u32 {
+        self.burst.unwrap_or(self.requests_per_minute)
+    }
+}
+
 // ============= Authentication Configuration =============
 
 /// Authentication configuration settings.
@@ -154,6 +404,14 @@ pub struct AuthConfig {
 
     /// Environment variable name containing the API key.
     pub api_key_env: String,
+
+    /// Multi-factor authentication with authenticator apps.
+    #[serde(default)]
+    pub mfa: MfaConfig,
+
+    /// Tokens ARES presents to the MCP servers and remote agents it calls.
+    #[serde(default)]
+    pub service_tokens: ServiceTokenConfig,
 }
 
 fn default_jwt_access_expiry() -> i64 {
@@ -171,6 +429,100 @@ impl Default for AuthConfig {
             jwt_access_expiry: default_jwt_access_expiry(),
             jwt_refresh_expiry: default_jwt_refresh_expiry(),
             api_key_env: "API_KEY".to_string(),
+            mfa: MfaConfig::default(),
+            service_tokens: ServiceTokenConfig::default(),
+        }
+    }
+}
+
+/// Multi-factor authentication with TOTP authenticator apps.
+///
+/// Users can always turn MFA on for themselves. With `required`, every
+/// user must, and signing in without it leads through enrollment first;
+/// admins can also require it of single users.
+///
+/// ```toml
+/// [auth.mfa]
+/// required = true
+/// issuer = "Acme ARES"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MfaConfig {
+    /// Require MFA of every user (default: false).
+    #[serde(default)]
+    pub required: bool,
+
+    /// Name authenticator apps show for the account (default: "ARES").
+    #[serde(default = "default_mfa_issuer")]
+    pub issuer: String,
+
+    /// Time a user has to enter their code after their password, in
+    /// seconds (default: 300).
+    #[serde(default = "default_mfa_challenge_ttl")]
+    pub challenge_ttl_secs: i64,
+}
+
+fn default_mfa_issuer() -> String {
+    "ARES".to_string()
+}
+
+fn default_mfa_challenge_ttl() -> i64 {
+    300
+}
+
+impl Default for MfaConfig {
+    fn default() -> Self {
+        Self {
+            required: false,
+            issuer: default_mfa_issuer(),
+            challenge_ttl_secs: default_mfa_challenge_ttl(),
+        }
+    }
+}
+
+/// Short-lived, scoped JWTs for outbound calls (see
+/// [`crate::auth::service_tokens`]).
+///
+/// Signed with an Ed25519 key whose public half is served at
+/// `/.well-known/jwks.json`, so called systems verify tokens without a
+/// shared secret.
+///
+/// ```toml
+/// [auth.service_tokens]
+/// private_key_env = "ARES_SERVICE_TOKEN_KEY"
+/// issuer = "https://ares.example.com"
+/// ttl_secs = 300
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceTokenConfig {
+    /// Environment variable holding the PKCS#8 Ed25519 signing key, PEM or
+    /// base64 DER. A key is generated at startup when unset.
+    #[serde(default)]
+    pub private_key_env: Option<String>,
+
+    /// Issuer (`iss`) of the tokens (default: "ares").
+    #[serde(default = "default_service_token_issuer")]
+    pub issuer: String,
+
+    /// Token lifetime in seconds, at most an hour (default: 300).
+    #[serde(default = "default_service_token_ttl")]
+    pub ttl_secs: i64,
+}
+
+fn default_service_token_issuer() -> String {
+    "ares".to_string()
+}
+
+fn default_service_token_ttl() -> i64 {
+    300
+}
+
+impl Default for ServiceTokenConfig {
+    fn default() -> Self {
+        Self {
+            private_key_env: None,
+            issuer: default_service_token_issuer(),
+            ttl_secs: default_service_token_ttl(),
         }
     }
 }
@@ -184,6 +536,16 @@ pub struct DatabaseConfig {
     #[serde(default = "default_database_url")]
     pub url: String,
 
+    /// Environment variable holding the URL of a read replica (optional).
+    /// Listings and searches that tolerate replication lag read from it;
+    /// everything else uses the primary.
+    #[serde(default)]
+    pub replica_url_env: Option<String>,
+
+    /// Connection pool settings, shared by the primary and the replica.
+    #[serde(default)]
+    pub pool: PoolConfig,
+
     /// Qdrant vector database configuration (optional).
     pub qdrant: Option<QdrantConfig>,
 }
@@ -196,11 +558,88 @@ impl Default for DatabaseConfig {
     fn default() -> Self {
         Self {
             url: default_database_url(),
+            replica_url_env: None,
+            pool: PoolConfig::default(),
             qdrant: None,
         }
     }
 }
 
+/// Database connection pool settings.
+///
+/// Connections are opened on demand up to `max_connections` and reused
+/// across requests; each caches up to `statement_cache_capacity` prepared
+/// statements, so repeated queries skip parsing and planning.
+///
+/// ```toml
+/// [database.pool]
+/// max_connections = 20
+/// min_connections = 2
+/// acquire_timeout_secs = 5
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolConfig {
+    /// Most connections open at once (default: 10).
+    #[serde(default = "default_pool_max_connections")]
+    pub max_connections: u32,
+
+    /// Connections kept open while idle (default: 0).
+    #[serde(default)]
+    pub min_connections: u32,
+
+    /// Seconds a query waits for a free connection before failing (default: 30).
+    #[serde(default = "default_pool_acquire_timeout")]
+    pub acquire_timeout_secs: u64,
+
+    /// Seconds before an idle connection above `min_connections` is closed;
+    /// 0 keeps them open (default: 600).
+    #[serde(default = "default_pool_idle_timeout")]
+    pub idle_timeout_secs: u64,
+
+    /// Seconds before a connection is replaced, whatever its use; 0 keeps it
+    /// open (default: 1800).
+    #[serde(default = "default_pool_max_lifetime")]
+    pub max_lifetime_secs: u64,
+
+    /// Prepared statements cached per connection; 0 disables the cache,
+    /// as needed behind PgBouncer in transaction mode (default: 100).
+    #[serde(default = "default_pool_statement_cache")]
+    pub statement_cache_capacity: usize,
+}
+
+fn default_pool_max_connections() -> u32 {
+    10
+}
+
+fn default_pool_acquire_timeout() -> u64 {
+    30
+}
+
+fn default_pool_idle_timeout() -> u64 {
+    600
+}
+
+fn default_pool_max_lifetime() -> u64 {
+    1800
+}
+
+fn default_pool_statement_cache() -> usize {
+    100
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: default_pool_max_connections(),
+            min_connections: 0,
+            acquire_timeout_secs: default_pool_acquire_timeout(),
+            idle_timeout_secs: default_pool_idle_timeout(),
+            max_lifetime_secs: default_pool_max_lifetime(),
+            statement_cache_capacity: default_pool_statement_cache(),
+        }
+    }
+}
+
 /// Qdrant vector database configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QdrantConfig {
@@ -225,6 +664,73 @@ impl Default for QdrantConfig {
     }
 }
 
+// ============= Redis Configuration =============
+
+/// Redis shared by the instances of a deployment (feature `redis`).
+///
+/// Instances then share their rate-limit counters, pick up each other's
+/// configuration reloads, and optionally share cached LLM responses and
+/// login sessions. Needs Redis 6.2 or later.
+///
+/// ```toml
+/// [redis]
+/// url_env = "REDIS_URL"
+/// key_prefix = "ares"
+/// sessions = true
+/// response_cache_ttl_secs = 3600
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedisConfig {
+    /// Environment variable holding the Redis URL (default: "REDIS_URL").
+    #[serde(default = "default_redis_url_env")]
+    pub url_env: String,
+
+    /// Prefix of every key and channel, to share a Redis between
+    /// deployments (default: "ares").
+    #[serde(default = "default_redis_key_prefix")]
+    pub key_prefix: String,
+
+    /// Share the `[server.rate_limits]` buckets across instances (default: true).
+    #[serde(default = "default_true")]
+    pub rate_limits: bool,
+
+    /// Reload `ares.toml` on every instance when one of them reloads it
+    /// (default: true).
+    #[serde(default = "default_true")]
+    pub config_events: bool,
+
+    /// Keep login sessions and refresh tokens in Redis instead of Postgres.
+    /// Sessions started before switching are signed out (default: false).
+    #[serde(default)]
+    pub sessions: bool,
+
+    /// Seconds LLM responses to identical prompts are reused; 0 disables
+    /// the cache (default: 0).
+    #[serde(default)]
+    pub response_cache_ttl_secs: u64,
+}
+
+fn default_redis_url_env() -> String {
+    "REDIS_URL".to_string()
+}
+
+fn default_redis_key_prefix() -> String {
+    "ares".to_string()
+}
+
+impl Default for RedisConfig {
+    fn default() -> Self {
+        Self {
+            url_env: default_redis_url_env(),
+            key_prefix: default_redis_key_prefix(),
+            rate_limits: true,
+            config_events: true,
+            sessions: false,
+            response_cache_ttl_secs: 0,
+        }
+    }
+}
+
 // ============= Provider Configuration =============
 
 /// LLM provider configuration. Tagged enum based on provider type.
@@ -252,7 +758,12 @@ pub enum ProviderConfig {
     /// LlamaCpp for direct GGUF model loading.
     LlamaCpp {
         /// Path to the GGUF model file.
+        #[serde(default)]
         model_path: String,
+        /// Managed model to load instead of `model_path`, as `owner/repo/file.gguf`
+        /// (downloaded with `ares-server models pull`).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
         /// Context window size (default: 4096).
         #[serde(default = "default_n_ctx")]
         n_ctx: u32,
@@ -270,6 +781,38 @@ pub enum ProviderConfig {
         /// Default model to use with this provider.
         default_model: String,
     },
+    /// Mistral AI La Plateforme.
+    Mistral {
+        /// Environment variable containing API key.
+        api_key_env: String,
+        /// API base URL (default: `https://api.mistral.ai/v1`).
+        #[serde(default = "default_mistral_base")]
+        api_base: String,
+        /// Default model to use with this provider.
+        default_model: String,
+    },
+    /// Cohere chat API.
+    Cohere {
+        /// Environment variable containing API key.
+        api_key_env: String,
+        /// API base URL (default: `https://api.cohere.com/v1`).
+        #[serde(default = "default_cohere_base")]
+        api_base: String,
+        /// Default model to use with this provider.
+        default_model: String,
+        /// Connector IDs to ground responses with (e.g., "web-search").
+        #[serde(default)]
+        connectors: Vec<String>,
+    },
+    /// Mock replies without a model, for load tests.
+    Mock {
+        /// Model name reported by the client (default: "mock").
+        #[serde(default = "default_mock_model")]
+        default_model: String,
+        /// Delay before each reply, in milliseconds (default: 0).
+        #[serde(default)]
+        latency_ms: u64,
+    },
 }
 
 fn default_ollama_url() -> String {
@@ -280,6 +823,18 @@ fn default_openai_base() -> String {
     "https://api.openai.com/v1".to_string()
 }
 
+fn default_mistral_base() -> String {
+    "https://api.mistral.ai/v1".to_string()
+}
+
+fn default_cohere_base() -> String {
+    "https://api.cohere.com/v1".to_string()
+}
+
+fn default_mock_model() -> String {
+    "mock".to_string()
+}
+
 fn default_n_ctx() -> u32 {
     4096
 }
@@ -319,8 +874,32 @@ pub struct ModelConfig {
 
     /// Optional presence penalty (-2.0 to 2.0).
     pub presence_penalty: Option<f32>,
+
+    /// Context window of the model in tokens. When set, agent prompts are
+    /// fitted into it, leaving `max_tokens` for the reply, by summarizing or
+    /// dropping the oldest conversation turns.
+    pub context_window: Option<u32>,
+
+    /// Whether conversation turns that don't fit the context window are
+    /// summarized rather than just dropped (default: true).
+    #[serde(default = "default_true")]
+    pub summarize_overflow: bool,
+
+    /// Times a reply cut off at `max_tokens` is continued, with the parts
+    /// stitched into one reply (default: 2, 0 to return it cut off).
+    /// Applies to providers that report truncation: OpenAI, Anthropic,
+    /// Mistral and Cohere.
+    #[serde(default = "default_max_continuations")]
+    pub max_continuations: u32,
 }
 
+fn default_max_continuations() -> u32 {
+    2
+}
+
+/// Most times a model's cut-off reply may be continued.
+pub const MAX_CONTINUATIONS: u32 = 10;
+
 fn default_temperature() -> f32 {
     0.7
 }
@@ -346,6 +925,15 @@ pub struct ToolConfig {
     #[serde(default = "default_tool_timeout")]
     pub timeout_secs: u64,
 
+    /// Estimated cost of one call in USD, checked against agents' `max_cost`.
+    #[serde(default)]
+    pub cost_per_call: Option<f64>,
+
+    /// Whether a user must approve each call before it runs, whichever
+    /// agent makes it.
+    #[serde(default)]
+    pub requires_approval: bool,
+
     /// Additional tool-specific configuration passed through.
     #[serde(flatten)]
     pub extra: HashMap<String, toml::Value>,
@@ -365,6 +953,8 @@ impl Default for ToolConfig {
             enabled: true,
             description: None,
             timeout_secs: default_tool_timeout(),
+            cost_per_call: None,
+            requires_approval: false,
             extra: HashMap::new(),
         }
     }
@@ -394,230 +984,2054 @@ pub struct AgentConfig {
     #[serde(default)]
     pub parallel_tools: bool,
 
-    /// Additional agent-specific configuration passed through.
-    #[serde(flatten)]
-    pub extra: HashMap<String, toml::Value>,
-}
+    /// Guardrail policy name from \[guardrails.policies\] (default: `guardrails.default_policy`).
+    #[serde(default)]
+    pub guardrail_policy: Option<String>,
 
-fn default_max_tool_iterations() -> usize {
-    10
-}
+    /// Agents this agent may hand the conversation off to.
+    #[serde(default)]
+    pub handoffs: Vec<String>,
 
-// ============= Workflow Configuration =============
+    /// How the agent works towards an answer (default: `direct`).
+    #[serde(default)]
+    pub strategy: AgentStrategy,
 
-/// Workflow configuration defining agent orchestration patterns.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WorkflowConfig {
-    /// Entry point agent that receives initial requests.
-    pub entry_agent: String,
+    /// JSON Schema the agent's output must conform to. Responses are
+    /// generated as JSON and validated, retrying on mismatch.
+    #[serde(default)]
+    pub output_schema: Option<serde_json::Value>,
 
-    /// Fallback agent if routing fails or no match is found.
-    pub fallback_agent: Option<String>,
+    /// User memory injected into the system prompt each turn.
+    #[serde(default)]
+    pub memory: AgentMemoryConfig,
 
-    /// Maximum depth for recursive/nested workflows (default: 3).
-    #[serde(default = "default_max_depth")]
-    pub max_depth: u8,
+    /// Limits on tool calls, keyed by tool name or `"*"` for every tool.
+    /// Calls that break a limit are refused instead of executed.
+    #[serde(default)]
+    pub tool_permissions: HashMap<String, ToolPermissionConfig>,
 
-    /// Maximum iterations for research/iterative workflows (default: 5).
-    #[serde(default = "default_max_iterations")]
-    pub max_iterations: u8,
+    /// Reuse of earlier answers to similar first-turn questions.
+    #[serde(default)]
+    pub answer_cache: AnswerCacheConfig,
 
-    /// Whether to execute sub-agent calls in parallel.
+    /// Critique and revision of draft answers before they are returned.
     #[serde(default)]
-    pub parallel_subagents: bool,
-}
+    pub reflection: ReflectionConfig,
 
-fn default_max_depth() -> u8 {
-    3
-}
+    /// Resource limits of a single run of the agent.
+    #[serde(default)]
+    pub limits: RunLimitsConfig,
 
-fn default_max_iterations() -> u8 {
-    5
+    /// Personas a chat request can select, keyed by name.
+    #[serde(default)]
+    pub personas: HashMap<String, PersonaConfig>,
+
+    /// Whether a user must approve each of the agent's tool calls before it
+    /// runs.
+    #[serde(default)]
+    pub requires_approval: bool,
+
+    /// Events the agent publishes to and reads from the message bus of the
+    /// workflows it runs in.
+    #[serde(default)]
+    pub bus: AgentBusConfig,
+
+    /// Bounds on the generation parameters chat requests may set.
+    #[serde(default)]
+    pub parameter_limits: ParameterLimitsConfig,
+
+    /// RAG collection whose passages are retrieved and injected each turn.
+    #[serde(default)]
+    pub rag: Option<AgentRagConfig>,
+
+    /// Additional agent-specific configuration passed through.
+    #[serde(flatten)]
+    pub extra: HashMap<String, toml::Value>,
+}
+
+impl AgentConfig {
+    /// Add a persona's prompt to the end of the system prompt.
+    ///
+    /// Returns false, leaving the config unchanged, if the agent has no
+    /// persona by that name.
+    pub fn apply_persona(&mut self, name: &str) -> bool {
+        let Some(persona) = self.personas.get(name) else {
+            return false;
+        };
+        self.system_prompt = Some(match self.system_prompt.take() {
+            Some(prompt) => format!("{}\n\n{}", prompt, persona.prompt),
+            None => persona.prompt.clone(),
+        });
+        true
+    }
+
+    /// Short fingerprint of the system prompt, identifying the version of
+    /// the prompt an answer was generated with
+    pub fn prompt_version(&self) -> String {
+        use sha2::{Digest, Sha256};
+        let digest = Sha256::digest(self.system_prompt.as_deref().unwrap_or_default());
+        hex::encode(digest)[..12].to_string()
+    }
+
+    /// Check the agent has a persona by that name.
+    pub fn check_persona(&self, name: &str) -> std::result::Result<(), String> {
+        if self.personas.contains_key(name) {
+            return Ok(());
+        }
+        let names = self.persona_names();
+        Err(if names.is_empty() {
+            format!("Unknown persona '{}': the agent has no personas", name)
+        } else {
+            format!(
+                "Unknown persona '{}': expected one of {}",
+                name,
+                names.join(", ")
+            )
+        })
+    }
+
+    /// Names of the agent's personas, sorted.
+    pub fn persona_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.personas.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Check every persona has a prompt, naming the first that doesn't.
+    pub fn validate_personas(&self) -> std::result::Result<(), String> {
+        match self
+            .persona_names()
+            .into_iter()
+            .find(|name| self.personas[*name].prompt.trim().is_empty())
+        {
+            Some(name) => Err(format!("personas.{}.prompt must not be empty", name)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn default_max_tool_iterations() -> usize {
+    10
+}
+
+/// A tone or role an agent can take on, chosen per chat request.
+///
+/// The selected persona's prompt is added to the agent's system prompt, and
+/// the conversation keeps using it until another is selected.
+///
+/// ```toml
+/// [agents.support.personas.formal]
+/// description = "Precise and formal"
+/// prompt = "Answer formally and precisely. Avoid contractions and humor."
+///
+/// [agents.support.personas.friendly]
+/// prompt = "Be warm and casual, and keep the jargon out."
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PersonaConfig {
+    /// What the persona is like, for clients offering a choice.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Instructions added to the system prompt.
+    pub prompt: String,
+}
+
+/// How an agent takes part in the message bus of a workflow run.
+///
+/// Agents publish events of the kinds in `publish` and are shown the events
+/// other agents published of the kinds in `subscribe`. See
+/// [`crate::agents::bus`].
+///
+/// ```toml
+/// [agents.research.bus]
+/// publish = ["finding", "artifact"]
+/// subscribe = ["warning"]
+/// topics = ["pricing"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentBusConfig {
+    /// Kinds of event the agent may publish.
+    #[serde(default)]
+    pub publish: Vec<BusEventKind>,
+
+    /// Kinds of event from other agents the agent is shown.
+    #[serde(default)]
+    pub subscribe: Vec<BusEventKind>,
+
+    /// Topics the agent is shown events on (default: all topics).
+    #[serde(default)]
+    pub topics: Vec<String>,
+}
+
+impl AgentBusConfig {
+    /// Whether the agent neither publishes nor subscribes to anything.
+    pub fn is_disabled(&self) -> bool {
+        self.publish.is_empty() && self.subscribe.is_empty()
+    }
+}
+
+/// Kind of event on the agent message bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BusEventKind {
+    /// Something the agent found out.
+    Finding,
+    /// A problem or risk other agents should take into account.
+    Warning,
+    /// A piece of work produced for other agents, such as a draft or a table.
+    Artifact,
+}
+
+impl BusEventKind {
+    /// Name of the kind as written in config and directives.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BusEventKind::Finding => "finding",
+            BusEventKind::Warning => "warning",
+            BusEventKind::Artifact => "artifact",
+        }
+    }
+}
+
+/// How an agent draws on the user's stored memory.
+///
+/// ```toml
+/// [agents.support]
+/// model = "balanced"
+/// memory = { enabled = true, max_facts = 8, strategy = "relevant" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentMemoryConfig {
+    /// Inject the user's preferences and the selected facts (default: false).
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Maximum facts injected per turn (default: 10).
+    #[serde(default = "default_memory_max_facts")]
+    pub max_facts: usize,
+
+    /// How facts are selected (default: `relevant`).
+    #[serde(default)]
+    pub strategy: MemoryStrategy,
+}
+
+impl Default for AgentMemoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_facts: default_memory_max_facts(),
+            strategy: MemoryStrategy::default(),
+        }
+    }
+}
+
+impl AgentMemoryConfig {
+    /// Whether memory injection is off.
+    pub fn is_disabled(&self) -> bool {
+        !self.enabled
+    }
+}
+
+fn default_memory_max_facts() -> usize {
+    10
+}
+
+/// Knowledge an agent retrieves from a RAG collection every turn.
+///
+/// The passages of the requesting user's collection most relevant to the
+/// message are added to the system prompt, numbered so the answer can cite
+/// them as `[1]`, `[2]`, and returned as the response's sources.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentRagConfig {
+    /// Collection to search, by the name it was ingested under.
+    pub collection: String,
+
+    /// Maximum passages injected per turn (default: 4).
+    #[serde(default = "default_rag_top_k")]
+    pub top_k: usize,
+
+    /// Minimum similarity of an injected passage (default: 0.0).
+    #[serde(default)]
+    pub min_score: f32,
+}
+
+fn default_rag_top_k() -> usize {
+    4
+}
+
+/// How an agent reuses its earlier answers.
+///
+/// Answers to the first question of a conversation are stored with their
+/// sources in a vector collection; a later question similar enough to a
+/// stored one gets the stored answer back, flagged as cached, until it is
+/// older than `ttl_secs`.
+///
+/// ```toml
+/// [agents.faq]
+/// model = "fast"
+/// answer_cache = { enabled = true, similarity_threshold = 0.95, ttl_secs = 86400 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnswerCacheConfig {
+    /// Serve and store cached answers (default: false).
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Minimum similarity between questions to reuse an answer, 0.0 to 1.0
+    /// (default: 0.95).
+    #[serde(default = "default_answer_cache_similarity")]
+    pub similarity_threshold: f32,
+
+    /// Seconds a cached answer stays fresh (default: 86400).
+    #[serde(default = "default_answer_cache_ttl")]
+    pub ttl_secs: u64,
+
+    /// Share answers across users instead of caching per user (default:
+    /// false). Only suitable for agents whose answers don't depend on the
+    /// user's memory or data.
+    #[serde(default)]
+    pub shared: bool,
+}
+
+impl Default for AnswerCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            similarity_threshold: default_answer_cache_similarity(),
+            ttl_secs: default_answer_cache_ttl(),
+            shared: false,
+        }
+    }
+}
+
+impl AnswerCacheConfig {
+    /// Whether answer caching is off.
+    pub fn is_disabled(&self) -> bool {
+        !self.enabled
+    }
+}
+
+fn default_answer_cache_similarity() -> f32 {
+    0.95
+}
+
+fn default_answer_cache_ttl() -> u64 {
+    86400
+}
+
+/// Most reflection rounds an agent may run per answer.
+pub const MAX_REFLECTION_ROUNDS: usize = 5;
+
+/// How an agent reviews its own answers.
+///
+/// Each round, a judge (the agent's own model unless `judge_model` names a
+/// cheaper one) critiques the draft against the user's message; the agent
+/// then revises the draft to address the critique. Rounds stop early once
+/// the judge approves. With `judge` naming one of the \[judges\], drafts
+/// are scored against its rubric instead and approved once they pass.
+///
+/// ```toml
+/// [agents.writer]
+/// model = "powerful"
+/// reflection = { enabled = true, max_rounds = 2, judge_model = "fast" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReflectionConfig {
+    /// Critique and revise drafts (default: false).
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Critique/revise rounds per answer at most, 1 to 5 (default: 1).
+    #[serde(default = "default_reflection_max_rounds")]
+    pub max_rounds: usize,
+
+    /// Model from \[models\] that writes the critiques (default: the agent's model).
+    #[serde(default)]
+    pub judge_model: Option<String>,
+
+    /// Judge from \[judges\] that scores drafts, revising those below its
+    /// pass score. Takes precedence over `judge_model`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub judge: Option<String>,
+}
+
+impl Default for ReflectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_rounds: default_reflection_max_rounds(),
+            judge_model: None,
+            judge: None,
+        }
+    }
+}
+
+impl ReflectionConfig {
+    /// Whether reflection is off.
+    pub fn is_disabled(&self) -> bool {
+        !self.enabled
+    }
+}
+
+fn default_reflection_max_rounds() -> usize {
+    1
+}
+
+/// Most judgments a judge averages per output.
+pub const MAX_JUDGE_SAMPLES: usize = 5;
+
+/// A judge that scores outputs from 0 to 10 against a rubric.
+///
+/// Agents' reflection, workflows and the research critique pass refer to
+/// judges by name. Each criterion is scored separately and the output's
+/// score is their average; judgments are cached, so the same output is
+/// not scored twice.
+///
+/// ```toml
+/// [judges.quality]
+/// model = "fast"
+/// criteria = [
+///     "Accuracy: every claim is correct",
+///     "Completeness: every part of the request is addressed",
+/// ]
+/// pass_score = 7.0
+/// samples = 2
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JudgeConfig {
+    /// Model from \[models\] that does the scoring.
+    pub model: String,
+
+    /// Criteria outputs are scored on (default: accuracy, completeness,
+    /// relevance and clarity).
+    #[serde(default)]
+    pub criteria: Vec<String>,
+
+    /// Lowest score, out of 10, an output passes with (default: 7).
+    #[serde(default = "default_judge_pass_score")]
+    pub pass_score: f32,
+
+    /// Judgments averaged per output, 1 to 5 (default: 1).
+    #[serde(default = "default_judge_samples")]
+    pub samples: usize,
+}
+
+fn default_judge_pass_score() -> f32 {
+    7.0
+}
+
+fn default_judge_samples() -> usize {
+    1
+}
+
+/// Sends a share of a model's calls to another model, to validate an
+/// upgrade on live traffic.
+///
+/// Keyed by the model being replaced. Calls for it go to `model` with
+/// probability `percent` and to the model itself otherwise, and the calls
+/// of both arms are measured so they can be compared:
+///
+/// ```toml
+/// [canaries.balanced]
+/// model = "balanced-next"
+/// percent = 10
+/// judge = "quality"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CanaryConfig {
+    /// Model from \[models\] receiving the canary share of the calls.
+    pub model: String,
+
+    /// Percentage of calls routed to the canary, 0 to 100.
+    pub percent: f32,
+
+    /// Judge from \[judges\] that scores the answers of both arms in the
+    /// background, for comparing their quality.
+    #[serde(default)]
+    pub judge: Option<String>,
+}
+
+/// An agent hosted by another system, called over the A2A protocol.
+///
+/// The agent is registered under its key like a local agent, so it can be
+/// chatted with, routed to and used as a workflow step:
+///
+/// ```toml
+/// [remote_agents.billing]
+/// url = "https://billing.example.com/a2a"
+/// description = "Answers questions about invoices and refunds"
+/// api_key_env = "BILLING_AGENT_KEY"
+/// streaming = true
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteAgentConfig {
+    /// JSON-RPC endpoint of the agent
+    pub url: String,
+
+    /// What the agent handles, for agent lists and routing
+    #[serde(default)]
+    pub description: String,
+
+    /// Environment variable holding the key sent with each request
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+
+    /// Header carrying the key. Sent as `Authorization: Bearer <key>` when
+    /// unset.
+    #[serde(default)]
+    pub api_key_header: Option<String>,
+
+    /// Time a run may take, including polling a task to completion
+    #[serde(default = "default_remote_agent_timeout")]
+    pub timeout_secs: u64,
+
+    /// Retries of a request failing to connect or with a 429 or 5xx status
+    #[serde(default = "default_remote_agent_retries")]
+    pub retries: u32,
+
+    /// Stream responses with `message/stream`, for agents supporting it
+    #[serde(default)]
+    pub streaming: bool,
+
+    /// Scopes of a service token sent as `Authorization: Bearer <token>`,
+    /// with the agent's name as audience and the user as subject. No token
+    /// is sent when empty.
+    #[serde(default)]
+    pub service_token_scopes: Vec<String>,
+}
+
+fn default_remote_agent_timeout() -> u64 {
+    120
+}
+
+fn default_remote_agent_retries() -> u32 {
+    2
+}
+
+/// Resource limits of a single agent run. Unset limits are unlimited.
+///
+/// A run that reaches a limit stops where it is and returns what it has so
+/// far, flagged as cut short, instead of running on.
+///
+/// ```toml
+/// [agents.research.limits]
+/// timeout_secs = 120
+/// max_tool_calls = 20
+/// max_llm_calls = 10
+/// max_cost = 0.25
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunLimitsConfig {
+    /// Wall-clock time of the run, in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+
+    /// Tool calls the run may make.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tool_calls: Option<usize>,
+
+    /// LLM generations the run may make, including reflection rounds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_llm_calls: Option<usize>,
+
+    /// Estimated spend of the run in USD, from `[budgets.pricing]` and the
+    /// tools' `cost_per_call`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
+}
+
+impl RunLimitsConfig {
+    /// Whether no limit is set.
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check the limits are usable, naming the first that isn't.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.timeout_secs == Some(0) {
+            return Err("limits.timeout_secs must be positive".to_string());
+        }
+        if self.max_llm_calls == Some(0) {
+            return Err("limits.max_llm_calls must be at least 1".to_string());
+        }
+        if self.max_cost.is_some_and(|cost| cost < 0.0) {
+            return Err("limits.max_cost must be non-negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Bounds on the generation parameters a chat request may set for an agent.
+///
+/// A request may only set the parameters the agent has bounds for, and only
+/// within them, so a client can offer e.g. a "creativity" slider without
+/// letting users push the model past what the agent was tuned for. Unset
+/// parameters can't be set by requests.
+///
+/// ```toml
+/// [agents.writer.parameter_limits]
+/// temperature = { min = 0.2, max = 1.2 }
+/// top_p = { min = 0.5, max = 1.0 }
+/// max_tokens = 2048
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ParameterLimitsConfig {
+    /// Range of `temperature`, within 0.0 to 2.0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<ParameterRange>,
+
+    /// Range of `top_p`, within 0.0 to 1.0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<ParameterRange>,
+
+    /// Largest `max_tokens`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+/// Inclusive range of a generation parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ParameterRange {
+    /// Lowest value accepted.
+    pub min: f32,
+    /// Highest value accepted.
+    pub max: f32,
+}
+
+impl ParameterRange {
+    /// Whether the range contains `value`.
+    pub fn contains(&self, value: f32) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+impl ParameterLimitsConfig {
+    /// Whether requests can't set any parameter.
+    pub fn is_unset(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check the bounds are usable, naming the first that isn't.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        for (name, range, ceiling) in [
+            ("temperature", self.temperature, 2.0),
+            ("top_p", self.top_p, 1.0),
+        ] {
+            let Some(range) = range else { continue };
+            if !(0.0..=ceiling).contains(&range.min)
+                || !(0.0..=ceiling).contains(&range.max)
+                || range.min > range.max
+            {
+                return Err(format!(
+                    "parameter_limits.{} must have 0.0 <= min <= max <= {:.1}",
+                    name, ceiling
+                ));
+            }
+        }
+        if self.max_tokens == Some(0) {
+            return Err("parameter_limits.max_tokens must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Limits on how an agent may call a tool.
+///
+/// ```toml
+/// [agents.researcher.tool_permissions.web_search]
+/// allowed_domains = ["docs.rs", "*.rust-lang.org"]
+///
+/// [agents.researcher.tool_permissions."*"]
+/// allowed_paths = ["/srv/reports/**"]
+/// max_cost = 0.05
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolPermissionConfig {
+    /// Hosts the tool may reach; `*.example.com` also matches its
+    /// subdomains. Empty allows any host.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_domains: Vec<String>,
+
+    /// Glob patterns of paths the tool may touch. Empty allows any path.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_paths: Vec<String>,
+
+    /// Highest estimated cost of a single call, in USD.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
+}
+
+/// How an agent picks which memory facts to inject.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryStrategy {
+    /// Facts most similar to the user's message, by embedding similarity
+    /// when an embedder is available and word overlap otherwise.
+    #[default]
+    Relevant,
+    /// Most recently updated facts.
+    Recent,
+    /// Facts with the highest confidence.
+    Confident,
+}
+
+/// How an agent works towards an answer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentStrategy {
+    /// Answer directly, calling tools natively if the agent has any.
+    #[default]
+    Direct,
+    /// ReAct planning loop: explicit Thought/Action/Observation steps, up to
+    /// `max_tool_iterations` steps, with the steps kept as a trace.
+    React,
+}
+
+impl AgentStrategy {
+    /// Whether this is the default `direct` strategy.
+    pub fn is_direct(&self) -> bool {
+        *self == Self::Direct
+    }
+}
+
+// ============= Workflow Configuration =============
+
+/// Workflow configuration defining agent orchestration patterns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowConfig {
+    /// Entry point agent that receives initial requests.
+    pub entry_agent: String,
+
+    /// Fallback agent if routing fails or no match is found.
+    pub fallback_agent: Option<String>,
+
+    /// Maximum depth for recursive/nested workflows (default: 3).
+    #[serde(default = "default_max_depth")]
+    pub max_depth: u8,
+
+    /// Maximum iterations for research/iterative workflows (default: 5).
+    #[serde(default = "default_max_iterations")]
+    pub max_iterations: u8,
+
+    /// Whether to execute sub-agent calls in parallel.
+    #[serde(default)]
+    pub parallel_subagents: bool,
+
+    /// Maximum seconds a single agent step may run before it is aborted (default: no limit).
+    #[serde(default)]
+    pub step_timeout_secs: Option<u64>,
+
+    /// Run the workflow as a debate: the listed agents answer the input
+    /// independently and the entry agent judges their answers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debate: Option<DebateConfig>,
+
+    /// Judge from \[judges\] that scores the final response. For the
+    /// `research` workflow, it also runs the critique pass of deep research.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub judge: Option<String>,
+}
+
+/// Panel of a debate workflow.
+///
+/// ```toml
+/// [workflows.debate]
+/// entry_agent = "orchestrator"   # the judge
+/// parallel_subagents = true
+/// debate = { agents = ["research", "finance", "sales"], mode = "synthesize" }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DebateConfig {
+    /// Agents that answer the input, at least two.
+    pub agents: Vec<String>,
+
+    /// How the judge reaches the final answer (default: synthesize).
+    #[serde(default)]
+    pub mode: DebateMode,
+}
+
+/// How the judge of a debate reaches the final answer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DebateMode {
+    /// Write a new answer from the best parts of the panel's answers.
+    #[default]
+    Synthesize,
+    /// Pick the best of the panel's answers, which is returned unchanged.
+    Select,
+}
+
+impl DebateMode {
+    /// Name of the mode as written in config.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DebateMode::Synthesize => "synthesize",
+            DebateMode::Select => "select",
+        }
+    }
+}
+
+fn default_max_depth() -> u8 {
+    3
+}
+
+fn default_max_iterations() -> u8 {
+    5
+}
+
+// ============= RAG Configuration =============
+
+/// RAG (Retrieval Augmented Generation) configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagConfig {
+    // =========== Vector Store ===========
+    /// Vector store provider: "ares-vector" (default), "qdrant", "lancedb", "pgvector".
+    /// Each but ares-vector needs its cargo feature.
+    #[serde(default = "default_vector_store")]
+    pub vector_store: String,
+
+    /// Path for persistent vector storage (default: "./data/vectors").
+    /// Also holds ingestion jobs and chunk feedback with any vector store.
+    #[serde(default = "default_vector_path")]
+    pub vector_path: String,
+
+    /// Environment variable with the URL of a qdrant server or pgvector
+    /// database. Required for qdrant; pgvector defaults to DATABASE_URL.
+    #[serde(default)]
+    pub vector_url_env: Option<String>,
+
+    /// Environment variable with the qdrant API key, if the server needs one.
+    #[serde(default)]
+    pub vector_api_key_env: Option<String>,
+
+    // =========== Embeddings ===========
+    /// Embedding model to use for vector embeddings (default: "bge-small-en-v1.5").
+    /// Available models: bge-small-en-v1.5, bge-base-en-v1.5, bge-large-en-v1.5,
+    /// all-minilm-l6-v2, all-minilm-l12-v2, nomic-embed-text-v1.5, etc.
+    /// With `embedding_provider` set, this is the provider's model name instead.
+    #[serde(default = "default_embedding_model")]
+    pub embedding_model: String,
+
+    /// Provider from \[providers\] that computes embeddings remotely (openai, mistral
+    /// or ollama). When unset, embeddings are computed locally, which requires the
+    /// `local-embeddings` feature.
+    #[serde(default)]
+    pub embedding_provider: Option<String>,
+
+    /// Enable sparse embeddings for hybrid search (default: false)
+    #[serde(default)]
+    pub sparse_embeddings: bool,
+
+    /// Sparse embedding model (default: "splade-pp-en-v1")
+    #[serde(default = "default_sparse_model")]
+    pub sparse_model: String,
+
+    /// Maximum number of texts per embedding provider call; concurrent
+    /// requests are coalesced up to this size (default: 64)
+    #[serde(default = "default_embedding_batch_size")]
+    pub embedding_batch_size: usize,
+
+    /// How long to wait for more requests before flushing a partial
+    /// embedding batch, in milliseconds (default: 5)
+    #[serde(default = "default_embedding_flush_interval_ms")]
+    pub embedding_flush_interval_ms: u64,
+
+    // =========== Chunking ===========
+    /// Chunking strategy: "word" (default), "semantic", "character"
+    #[serde(default = "default_chunking_strategy")]
+    pub chunking_strategy: String,
+
+    /// Size of text chunks for indexing (default: 200 words or 500 chars for semantic).
+    #[serde(default = "default_chunk_size")]
+    pub chunk_size: usize,
+
+    /// Overlap between consecutive chunks (default: 50).
+    #[serde(default = "default_chunk_overlap")]
+    pub chunk_overlap: usize,
+
+    /// Minimum chunk size to keep (default: 20 chars).
+    #[serde(default = "default_min_chunk_size")]
+    pub min_chunk_size: usize,
+
+    // =========== Search ===========
+    /// Default search strategy: "semantic" (default), "bm25", "fuzzy", "hybrid"
+    #[serde(default = "default_search_strategy")]
+    pub search_strategy: String,
+
+    /// Default number of results to return (default: 10)
+    #[serde(default = "default_search_limit")]
+    pub search_limit: usize,
+
+    /// Default similarity threshold (default: 0.0)
+    #[serde(default)]
+    pub search_threshold: f32,
+
+    /// Hybrid search weights (semantic, bm25, fuzzy) - sum should be 1.0
+    #[serde(default)]
+    pub hybrid_weights: Option<HybridWeightsConfig>,
+
+    /// Skip retrieval for greetings and small talk, returning no results
+    /// without embedding the query (default: true)
+    #[serde(default = "default_true")]
+    pub intent_classification: bool,
+
+    /// How far chunk feedback moves search scores, from 0.0 (ignored) to
+    /// 1.0; collections can override it (default: 0.2)
+    #[serde(default = "default_feedback_weight")]
+    pub feedback_weight: f32,
+
+    // =========== Reranking ===========
+    /// Enable reranking by default (default: false)
+    #[serde(default)]
+    pub rerank_enabled: bool,
+
+    /// Reranker implementation: "cross-encoder" (default), "cohere" or "llm"
+    #[serde(default)]
+    pub reranker: RerankerKind,
+
+    /// Reranker model. For `cross-encoder`: "bge-reranker-base" (default),
+    /// "bge-reranker-v2-m3", "jina-reranker-v1-turbo-en",
+    /// "jina-reranker-v2-base-multilingual". For `cohere`: a Cohere rerank
+    /// model (default: "rerank-v3.5"). For `llm`: a model name from \[models\]
+    /// (default: the default model).
+    #[serde(default)]
+    pub reranker_model: Option<String>,
+
+    /// Cohere provider from \[providers\] used by the `cohere` reranker.
+    #[serde(default)]
+    pub reranker_provider: Option<String>,
+
+    /// Weight for combining rerank and retrieval scores (default: 0.6)
+    #[serde(default = "default_rerank_weight")]
+    pub rerank_weight: f32,
+
+    // =========== Cloud Storage Ingestion ===========
+    /// S3 (and S3-compatible) buckets for `s3://` ingestion jobs
+    #[serde(default)]
+    pub s3: S3SourceConfig,
+
+    /// Google Cloud Storage buckets for `gs://` ingestion jobs
+    #[serde(default)]
+    pub gcs: GcsSourceConfig,
+
+    /// Notion workspace for `notion://` sync jobs
+    #[serde(default)]
+    pub notion: NotionSourceConfig,
+
+    /// Confluence site for `confluence://` sync jobs
+    #[serde(default)]
+    pub confluence: ConfluenceSourceConfig,
+
+    /// GitHub repositories for `github://` ingestion jobs
+    #[serde(default)]
+    pub github: GitHubSourceConfig,
+}
+
+/// Access to S3 buckets for ingestion jobs.
+///
+/// ```toml
+/// [rag.s3]
+/// region = "eu-west-1"
+/// endpoint = "http://localhost:9000"   # MinIO, R2, etc. (optional)
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3SourceConfig {
+    /// Bucket region (default: "us-east-1")
+    #[serde(default = "default_s3_region")]
+    pub region: String,
+
+    /// Endpoint of an S3-compatible service; buckets are then addressed
+    /// path-style (default: AWS)
+    #[serde(default)]
+    pub endpoint: Option<String>,
+
+    /// Environment variable holding the access key ID (default: "AWS_ACCESS_KEY_ID")
+    #[serde(default = "default_s3_access_key_env")]
+    pub access_key_env: String,
+
+    /// Environment variable holding the secret access key
+    /// (default: "AWS_SECRET_ACCESS_KEY")
+    #[serde(default = "default_s3_secret_key_env")]
+    pub secret_key_env: String,
+
+    /// Environment variable holding a session token for temporary
+    /// credentials, used when set (default: "AWS_SESSION_TOKEN")
+    #[serde(default = "default_s3_session_token_env")]
+    pub session_token_env: String,
+}
+
+impl Default for S3SourceConfig {
+    fn default() -> Self {
+        Self {
+            region: default_s3_region(),
+            endpoint: None,
+            access_key_env: default_s3_access_key_env(),
+            secret_key_env: default_s3_secret_key_env(),
+            session_token_env: default_s3_session_token_env(),
+        }
+    }
+}
+
+fn default_s3_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_s3_access_key_env() -> String {
+    "AWS_ACCESS_KEY_ID".to_string()
+}
+
+fn default_s3_secret_key_env() -> String {
+    "AWS_SECRET_ACCESS_KEY".to_string()
+}
+
+fn default_s3_session_token_env() -> String {
+    "AWS_SESSION_TOKEN".to_string()
+}
+
+/// Access to Google Cloud Storage buckets for ingestion jobs.
+///
+/// Requests are authorized with an OAuth access token from
+/// `access_token_env` when set, otherwise with the service account key file
+/// named by `credentials_env`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcsSourceConfig {
+    /// Storage API endpoint (default: "https://storage.googleapis.com")
+    #[serde(default = "default_gcs_endpoint")]
+    pub endpoint: String,
+
+    /// Environment variable holding the path of a service account key file
+    /// (default: "GOOGLE_APPLICATION_CREDENTIALS")
+    #[serde(default = "default_gcs_credentials_env")]
+    pub credentials_env: String,
+
+    /// Environment variable holding a ready-made OAuth access token
+    #[serde(default)]
+    pub access_token_env: Option<String>,
+}
+
+impl Default for GcsSourceConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: default_gcs_endpoint(),
+            credentials_env: default_gcs_credentials_env(),
+            access_token_env: None,
+        }
+    }
+}
+
+fn default_gcs_endpoint() -> String {
+    "https://storage.googleapis.com".to_string()
+}
+
+fn default_gcs_credentials_env() -> String {
+    "GOOGLE_APPLICATION_CREDENTIALS".to_string()
+}
+
+/// Access to a Notion workspace for sync jobs.
+///
+/// Uses an internal integration token; only pages shared with the
+/// integration are visible.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotionSourceConfig {
+    /// Notion API endpoint (default: "https://api.notion.com")
+    #[serde(default = "default_notion_endpoint")]
+    pub endpoint: String,
+
+    /// Environment variable holding the integration token (default: "NOTION_TOKEN")
+    #[serde(default = "default_notion_token_env")]
+    pub token_env: String,
+}
+
+impl Default for NotionSourceConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: default_notion_endpoint(),
+            token_env: default_notion_token_env(),
+        }
+    }
+}
+
+fn default_notion_endpoint() -> String {
+    "https://api.notion.com".to_string()
+}
+
+fn default_notion_token_env() -> String {
+    "NOTION_TOKEN".to_string()
+}
+
+/// Access to a Confluence site for sync jobs.
+///
+/// Confluence Cloud authenticates with an account email and API token;
+/// Server and Data Center with a personal access token alone, used when the
+/// email variable is not set.
+///
+/// ```toml
+/// [rag.confluence]
+/// base_url = "https://acme.atlassian.net/wiki"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfluenceSourceConfig {
+    /// Site URL including any context path (e.g., "https://acme.atlassian.net/wiki")
+    #[serde(default)]
+    pub base_url: Option<String>,
+
+    /// Environment variable holding the account email (default: "CONFLUENCE_EMAIL")
+    #[serde(default = "default_confluence_email_env")]
+    pub email_env: String,
+
+    /// Environment variable holding the API or personal access token
+    /// (default: "CONFLUENCE_API_TOKEN")
+    #[serde(default = "default_confluence_token_env")]
+    pub token_env: String,
+}
+
+impl Default for ConfluenceSourceConfig {
+    fn default() -> Self {
+        Self {
+            base_url: None,
+            email_env: default_confluence_email_env(),
+            token_env: default_confluence_token_env(),
+        }
+    }
+}
+
+fn default_confluence_email_env() -> String {
+    "CONFLUENCE_EMAIL".to_string()
+}
+
+fn default_confluence_token_env() -> String {
+    "CONFLUENCE_API_TOKEN".to_string()
+}
+
+/// Access to GitHub repositories for code ingestion.
+///
+/// Repositories are cloned with the `git` command line into
+/// `checkout_dir`; public repositories need no token.
+///
+/// ```toml
+/// [rag.github]
+/// checkout_dir = "./data/repos"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubSourceConfig {
+    /// Git host URL (default: "https://github.com"; set for GitHub Enterprise)
+    #[serde(default = "default_github_endpoint")]
+    pub endpoint: String,
+
+    /// Environment variable holding an access token for private repositories
+    /// (default: "GITHUB_TOKEN")
+    #[serde(default = "default_github_token_env")]
+    pub token_env: String,
+
+    /// Directory repositories are checked out into (default: "./data/repos")
+    #[serde(default = "default_github_checkout_dir")]
+    pub checkout_dir: String,
+
+    /// Environment variable holding the push webhook secret
+    /// (default: "GITHUB_WEBHOOK_SECRET"); webhooks are rejected while unset
+    #[serde(default = "default_github_webhook_secret_env")]
+    pub webhook_secret_env: String,
+}
+
+impl Default for GitHubSourceConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: default_github_endpoint(),
+            token_env: default_github_token_env(),
+            checkout_dir: default_github_checkout_dir(),
+            webhook_secret_env: default_github_webhook_secret_env(),
+        }
+    }
+}
+
+fn default_github_endpoint() -> String {
+    "https://github.com".to_string()
+}
+
+fn default_github_token_env() -> String {
+    "GITHUB_TOKEN".to_string()
+}
+
+fn default_github_checkout_dir() -> String {
+    "./data/repos".to_string()
+}
+
+fn default_github_webhook_secret_env() -> String {
+    "GITHUB_WEBHOOK_SECRET".to_string()
+}
+
+/// Reranker implementation used to rescore search results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RerankerKind {
+    /// Local ONNX cross-encoder model (requires `local-embeddings`).
+    #[default]
+    CrossEncoder,
+    /// Cohere Rerank API.
+    Cohere,
+    /// An LLM rates each result's relevance.
+    Llm,
+}
+
+impl std::str::FromStr for RerankerKind {
+    type Err = crate::types::AppError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cross-encoder" | "cross_encoder" | "onnx" => Ok(Self::CrossEncoder),
+            "cohere" => Ok(Self::Cohere),
+            "llm" | "llm-judge" => Ok(Self::Llm),
+            _ => Err(crate::types::AppError::InvalidInput(format!(
+                "Unknown reranker: {}. Use one of: cross-encoder, cohere, llm",
+                s
+            ))),
+        }
+    }
+}
+
+/// Hybrid search weight configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HybridWeightsConfig {
+    /// Weight for semantic search (default: 0.5)
+    #[serde(default = "default_semantic_weight")]
+    pub semantic: f32,
+    /// Weight for BM25 search (default: 0.3)
+    #[serde(default = "default_bm25_weight")]
+    pub bm25: f32,
+    /// Weight for fuzzy search (default: 0.2)
+    #[serde(default = "default_fuzzy_weight")]
+    pub fuzzy: f32,
+}
+
+impl Default for HybridWeightsConfig {
+    fn default() -> Self {
+        Self {
+            semantic: 0.5,
+            bm25: 0.3,
+            fuzzy: 0.2,
+        }
+    }
+}
+
+fn default_semantic_weight() -> f32 {
+    0.5
+}
+
+fn default_bm25_weight() -> f32 {
+    0.3
+}
+
+fn default_fuzzy_weight() -> f32 {
+    0.2
+}
+
+fn default_vector_store() -> String {
+    "ares-vector".to_string()
+}
+
+fn default_vector_path() -> String {
+    "./data/vectors".to_string()
+}
+
+fn default_embedding_model() -> String {
+    "bge-small-en-v1.5".to_string()
+}
+
+fn default_sparse_model() -> String {
+    "splade-pp-en-v1".to_string()
+}
+
+fn default_embedding_batch_size() -> usize {
+    64
+}
+
+fn default_embedding_flush_interval_ms() -> u64 {
+    5
+}
+
+fn default_chunking_strategy() -> String {
+    "word".to_string()
+}
+
+fn default_chunk_size() -> usize {
+    200
+}
+
+fn default_chunk_overlap() -> usize {
+    50
+}
+
+fn default_min_chunk_size() -> usize {
+    20
+}
+
+fn default_search_strategy() -> String {
+    "semantic".to_string()
+}
+
+fn default_search_limit() -> usize {
+    10
+}
+
+fn default_rerank_weight() -> f32 {
+    0.6
+}
+
+fn default_feedback_weight() -> f32 {
+    0.2
+}
+
+impl Default for RagConfig {
+    fn default() -> Self {
+        Self {
+            vector_store: default_vector_store(),
+            vector_path: default_vector_path(),
+            vector_url_env: None,
+            vector_api_key_env: None,
+            embedding_model: default_embedding_model(),
+            embedding_provider: None,
+            sparse_embeddings: false,
+            sparse_model: default_sparse_model(),
+            embedding_batch_size: default_embedding_batch_size(),
+            embedding_flush_interval_ms: default_embedding_flush_interval_ms(),
+            chunking_strategy: default_chunking_strategy(),
+            chunk_size: default_chunk_size(),
+            chunk_overlap: default_chunk_overlap(),
+            min_chunk_size: default_min_chunk_size(),
+            search_strategy: default_search_strategy(),
+            search_limit: default_search_limit(),
+            search_threshold: 0.0,
+            hybrid_weights: None,
+            intent_classification: true,
+            feedback_weight: default_feedback_weight(),
+            rerank_enabled: false,
+            reranker: RerankerKind::default(),
+            reranker_model: None,
+            reranker_provider: None,
+            rerank_weight: default_rerank_weight(),
+            s3: S3SourceConfig::default(),
+            gcs: GcsSourceConfig::default(),
+            notion: NotionSourceConfig::default(),
+            confluence: ConfluenceSourceConfig::default(),
+            github: GitHubSourceConfig::default(),
+        }
+    }
+}
+
+// ============= Guardrails Configuration =============
+
+/// Guardrail configuration: named policies of pre/post processors.
+///
+/// ```toml
+/// [guardrails]
+/// default_policy = "standard"
+///
+/// [guardrails.policies.standard]
+/// pii_redaction = true
+/// prompt_injection = true
+/// blocked_topics = ["medical advice"]
+/// judge_model = "fast"
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GuardrailsConfig {
+    /// Policy applied to agents that don't select one explicitly (default: none).
+    #[serde(default)]
+    pub default_policy: Option<String>,
+
+    /// Named guardrail policies, selected per agent via `guardrail_policy`.
+    #[serde(default)]
+    pub policies: HashMap<String, GuardrailPolicyConfig>,
+}
+
+/// A guardrail policy: which processors run around an agent generation.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GuardrailPolicyConfig {
+    /// Redact built-in PII (emails, phone numbers, card numbers, SSNs, IPs) from
+    /// inputs and outputs (default: false).
+    #[serde(default)]
+    pub pii_redaction: bool,
+
+    /// Additional redaction patterns as `label = "regex"` pairs.
+    #[serde(default)]
+    pub pii_patterns: HashMap<String, String>,
+
+    /// Block inputs that look like prompt-injection attempts (default: false).
+    #[serde(default)]
+    pub prompt_injection: bool,
+
+    /// Heuristic score at or above which an input is treated as an injection (default: 1.0).
+    #[serde(default = "default_injection_threshold")]
+    pub injection_threshold: f32,
+
+    /// Topics the agent must not engage with, classified by `judge_model`.
+    #[serde(default)]
+    pub blocked_topics: Vec<String>,
+
+    /// Reference to a model in \[models\] used to classify blocked topics.
+    #[serde(default)]
+    pub judge_model: Option<String>,
 }
 
-// ============= RAG Configuration =============
+fn default_injection_threshold() -> f32 {
+    1.0
+}
 
-/// RAG (Retrieval Augmented Generation) configuration.
+// ============= Budget Configuration =============
+
+/// Spend budgets, enforced against estimated cost of token usage.
+///
+/// ```toml
+/// [budgets]
+/// enabled = true
+/// global = { daily_usd = 50.0, monthly_usd = 1000.0 }
+/// per_user = { daily_usd = 1.0, monthly_usd = 20.0 }
+///
+/// [budgets.agents.research]
+/// monthly_usd = 100.0
+///
+/// [budgets.pricing.balanced]
+/// input_per_1k = 0.0005
+/// output_per_1k = 0.0015
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetsConfig {
+    /// Whether budgets are enforced (default: false). Spend is recorded either way.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Limits across all users and agents.
+    #[serde(default)]
+    pub global: BudgetLimit,
+
+    /// Limits applied to every user unless overridden in `users`.
+    #[serde(default)]
+    pub per_user: BudgetLimit,
+
+    /// Per-user overrides, keyed by user ID.
+    #[serde(default)]
+    pub users: HashMap<String, BudgetLimit>,
+
+    /// Per-agent limits, keyed by agent name.
+    #[serde(default)]
+    pub agents: HashMap<String, BudgetLimit>,
+
+    /// Token pricing keyed by model name from \[models\]. Unpriced models cost nothing.
+    #[serde(default)]
+    pub pricing: HashMap<String, ModelPricing>,
+}
+
+/// Daily and monthly spend limits in USD. Unset limits are unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BudgetLimit {
+    /// Maximum spend per UTC day.
+    #[serde(default)]
+    pub daily_usd: Option<f64>,
+
+    /// Maximum spend per UTC calendar month.
+    #[serde(default)]
+    pub monthly_usd: Option<f64>,
+}
+
+/// Price of a model's tokens in USD per 1,000 tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    /// Cost per 1,000 input (prompt) tokens.
+    #[serde(default)]
+    pub input_per_1k: f64,
+
+    /// Cost per 1,000 output (completion) tokens.
+    #[serde(default)]
+    pub output_per_1k: f64,
+}
+
+impl BudgetsConfig {
+    /// Estimated cost in USD of a generation on `model`.
+    pub fn estimate_cost(&self, model: &str, input_tokens: u64, output_tokens: u64) -> f64 {
+        self.pricing
+            .get(model)
+            .map(|p| {
+                (input_tokens as f64 / 1000.0) * p.input_per_1k
+                    + (output_tokens as f64 / 1000.0) * p.output_per_1k
+            })
+            .unwrap_or(0.0)
+    }
+
+    /// Effective limit for a user: explicit override, else `per_user`.
+    pub fn user_limit(&self, user_id: &str) -> BudgetLimit {
+        self.users.get(user_id).copied().unwrap_or(self.per_user)
+    }
+
+    /// Limit configured for an agent (unlimited if none).
+    pub fn agent_limit(&self, agent_name: &str) -> BudgetLimit {
+        self.agents.get(agent_name).copied().unwrap_or_default()
+    }
+}
+
+// ============= Archive Configuration =============
+
+/// Archival of inactive conversations to cold storage.
+///
+/// Conversations without new messages for `after_days` have their messages
+/// and tool-call traces moved to a gzipped JSONL file under `location`,
+/// leaving only the conversation row in the database. Opening or continuing
+/// an archived conversation restores it.
+///
+/// ```toml
+/// [archive]
+/// enabled = true
+/// after_days = 90
+/// location = "s3://ares-archive/conversations/"   # or a directory
+///
+/// [archive.s3]
+/// region = "eu-west-1"
+/// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RagConfig {
-    // =========== Vector Store ===========
-    /// Vector store provider: "ares-vector" (default), "qdrant", "lancedb", "pgvector"
-    #[serde(default = "default_vector_store")]
-    pub vector_store: String,
+pub struct ArchiveConfig {
+    /// Whether inactive conversations are archived in the background
+    /// (default: false). Archived conversations are restored either way.
+    #[serde(default)]
+    pub enabled: bool,
 
-    /// Path for persistent vector storage (default: "./data/vectors")
-    #[serde(default = "default_vector_path")]
-    pub vector_path: String,
+    /// Days without new messages after which a conversation is archived (default: 90)
+    #[serde(default = "default_archive_after_days")]
+    pub after_days: u32,
 
-    // =========== Embeddings ===========
-    /// Embedding model to use for vector embeddings (default: "bge-small-en-v1.5").
-    /// Available models: bge-small-en-v1.5, bge-base-en-v1.5, bge-large-en-v1.5,
-    /// all-minilm-l6-v2, all-minilm-l12-v2, nomic-embed-text-v1.5, etc.
-    #[serde(default = "default_embedding_model")]
-    pub embedding_model: String,
+    /// Seconds between archival runs (default: 3600)
+    #[serde(default = "default_archive_interval_secs")]
+    pub interval_secs: u64,
 
-    /// Enable sparse embeddings for hybrid search (default: false)
+    /// Conversations archived per run at most (default: 100)
+    #[serde(default = "default_archive_batch_size")]
+    pub batch_size: u32,
+
+    /// Directory or `s3://bucket/prefix` archives are written to
+    /// (default: "data/archive")
+    #[serde(default = "default_archive_location")]
+    pub location: String,
+
+    /// Region, endpoint and credentials for `s3://` locations
     #[serde(default)]
-    pub sparse_embeddings: bool,
+    pub s3: S3SourceConfig,
+}
 
-    /// Sparse embedding model (default: "splade-pp-en-v1")
-    #[serde(default = "default_sparse_model")]
-    pub sparse_model: String,
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            after_days: default_archive_after_days(),
+            interval_secs: default_archive_interval_secs(),
+            batch_size: default_archive_batch_size(),
+            location: default_archive_location(),
+            s3: S3SourceConfig::default(),
+        }
+    }
+}
 
-    // =========== Chunking ===========
-    /// Chunking strategy: "word" (default), "semantic", "character"
-    #[serde(default = "default_chunking_strategy")]
-    pub chunking_strategy: String,
+/// Automatic titling of new conversations.
+///
+/// After the first exchange of a conversation without a title, a short
+/// title is generated from it in the background. Titles set by the user
+/// are never replaced.
+///
+/// ```toml
+/// [titles]
+/// model = "fast"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TitlesConfig {
+    /// Whether new conversations are titled automatically (default: true)
+    #[serde(default = "default_true")]
+    pub enabled: bool,
 
-    /// Size of text chunks for indexing (default: 200 words or 500 chars for semantic).
-    #[serde(default = "default_chunk_size")]
-    pub chunk_size: usize,
+    /// Model from \[models\] that writes the titles (default: the router's
+    /// model, or "fast")
+    #[serde(default)]
+    pub model: Option<String>,
+}
 
-    /// Overlap between consecutive chunks (default: 50).
-    #[serde(default = "default_chunk_overlap")]
-    pub chunk_overlap: usize,
+impl Default for TitlesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            model: None,
+        }
+    }
+}
 
-    /// Minimum chunk size to keep (default: 20 chars).
-    #[serde(default = "default_min_chunk_size")]
-    pub min_chunk_size: usize,
+/// Maintenance mode.
+///
+/// While enabled, new chat, research, workflow and agent run requests are
+/// rejected with 503 Service Unavailable and scheduled runs are skipped.
+/// Generations already in progress finish. Admins can also switch it at
+/// runtime with `PUT /api/admin/maintenance`, which overrides this section
+/// until cleared. See [`crate::api::maintenance`].
+///
+/// ```toml
+/// [maintenance]
+/// enabled = true
+/// message = "Upgrading to the new models, back at 14:00 UTC."
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaintenanceConfig {
+    /// Whether the server is in maintenance mode (default: false)
+    #[serde(default)]
+    pub enabled: bool,
 
-    // =========== Search ===========
-    /// Default search strategy: "semantic" (default), "bm25", "fuzzy", "hybrid"
-    #[serde(default = "default_search_strategy")]
-    pub search_strategy: String,
+    /// Message returned to rejected requests (default: a generic notice)
+    #[serde(default)]
+    pub message: Option<String>,
+}
 
-    /// Default number of results to return (default: 10)
-    #[serde(default = "default_search_limit")]
-    pub search_limit: usize,
+/// Storage and limits of uploaded files.
+///
+/// Files uploaded to `/api/files` are stored under `location` by ID, never
+/// by the uploaded name, and can be referenced in chat requests and RAG
+/// ingestion. Uploads larger than `max_bytes`, of a type not in
+/// `allowed_types`, whose content doesn't match their type, or that look
+/// like executables are rejected. With `scan_command` set, every upload is
+/// piped to it and rejected unless it exits with status 0.
+///
+/// ```toml
+/// [files]
+/// location = "s3://ares-uploads/files/"   # or a directory
+/// max_bytes = 20971520
+/// scan_command = "clamdscan --no-summary -"
+///
+/// [files.s3]
+/// region = "eu-west-1"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilesConfig {
+    /// Directory or `s3://bucket/prefix` files are stored under
+    /// (default: "data/files")
+    #[serde(default = "default_files_location")]
+    pub location: String,
+
+    /// Largest upload accepted, in bytes (default: 10 MiB)
+    #[serde(default = "default_files_max_bytes")]
+    pub max_bytes: usize,
+
+    /// Content types accepted (default: plain text, Markdown, CSV, HTML,
+    /// JSON, PDF, DOCX, PNG and JPEG)
+    #[serde(default = "default_files_allowed_types")]
+    pub allowed_types: Vec<String>,
+
+    /// Virus scanner command run on every upload, with the file on stdin
+    /// (e.g. "clamdscan --no-summary -"); a non-zero exit rejects the file
+    #[serde(default)]
+    pub scan_command: Option<String>,
 
-    /// Default similarity threshold (default: 0.0)
+    /// Region, endpoint and credentials for `s3://` locations
     #[serde(default)]
-    pub search_threshold: f32,
+    pub s3: S3SourceConfig,
+}
 
-    /// Hybrid search weights (semantic, bm25, fuzzy) - sum should be 1.0
+impl Default for FilesConfig {
+    fn default() -> Self {
+        Self {
+            location: default_files_location(),
+            max_bytes: default_files_max_bytes(),
+            allowed_types: default_files_allowed_types(),
+            scan_command: None,
+            s3: S3SourceConfig::default(),
+        }
+    }
+}
+
+/// Batch chat: many messages answered by an agent at once.
+///
+/// Batches of up to `inline_items` messages are answered before the request
+/// returns; larger ones, or any asking for `background`, run as a job polled
+/// with `GET /api/chat/batch/{id}`.
+///
+/// ```toml
+/// [batch]
+/// max_items = 100     # most messages in one batch
+/// concurrency = 4     # messages of a batch answered at once
+/// inline_items = 10   # largest batch answered inline
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchConfig {
+    /// Most messages in one batch (default: 100)
+    #[serde(default = "default_batch_max_items")]
+    pub max_items: usize,
+
+    /// Messages of one batch answered at once (default: 4)
+    #[serde(default = "default_batch_concurrency")]
+    pub concurrency: usize,
+
+    /// Largest batch answered before the request returns (default: 10)
+    #[serde(default = "default_batch_inline_items")]
+    pub inline_items: usize,
+}
+
+fn default_batch_max_items() -> usize {
+    100
+}
+
+fn default_batch_concurrency() -> usize {
+    4
+}
+
+fn default_batch_inline_items() -> usize {
+    10
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_items: default_batch_max_items(),
+            concurrency: default_batch_concurrency(),
+            inline_items: default_batch_inline_items(),
+        }
+    }
+}
+
+/// Model warm-up at startup.
+///
+/// Local models take a while to load on their first request. When enabled,
+/// the server pulls the models missing from Ollama or the GGUF store, then
+/// loads each with a short test generation, rejecting chat requests with a
+/// 503 until it's done. See [`crate::llm::warmup`].
+///
+/// ```toml
+/// [warmup]
+/// enabled = true
+/// models = ["local", "fast"]   # default: every model of an Ollama or llama.cpp provider
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmupConfig {
+    /// Whether models are warmed up at startup (default: false)
     #[serde(default)]
-    pub hybrid_weights: Option<HybridWeightsConfig>,
+    pub enabled: bool,
 
-    // =========== Reranking ===========
-    /// Enable reranking by default (default: false)
+    /// Models from \[models\] to warm up (default: every model whose
+    /// provider is Ollama or llama.cpp)
     #[serde(default)]
-    pub rerank_enabled: bool,
+    pub models: Vec<String>,
 
-    /// Reranker model: "bge-reranker-base" (default), "bge-reranker-v2-m3",
-    /// "jina-reranker-v1-turbo-en", "jina-reranker-v2-base-multilingual"
-    #[serde(default = "default_reranker_model")]
-    pub reranker_model: String,
+    /// Whether models missing from Ollama or the GGUF store are downloaded
+    /// (default: true)
+    #[serde(default = "default_true")]
+    pub pull: bool,
 
-    /// Weight for combining rerank and retrieval scores (default: 0.6)
-    #[serde(default = "default_rerank_weight")]
-    pub rerank_weight: f32,
+    /// Whether requests that start LLM work get a 503 until warm-up is
+    /// done (default: true)
+    #[serde(default = "default_true")]
+    pub gate: bool,
+
+    /// Longest a model may take to pull and answer its test generation, in
+    /// seconds (default: 600)
+    #[serde(default = "default_warmup_timeout")]
+    pub timeout_secs: u64,
 }
 
-/// Hybrid search weight configuration
+fn default_warmup_timeout() -> u64 {
+    600
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            models: Vec::new(),
+            pull: true,
+            gate: true,
+            timeout_secs: default_warmup_timeout(),
+        }
+    }
+}
+
+/// Dependency checks of the readiness probe, `GET /health/ready`.
+///
+/// See [`crate::api::health`].
+///
+/// ```toml
+/// [health]
+/// timeout_ms = 2000
+/// provider_cache_secs = 30
+/// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HybridWeightsConfig {
-    /// Weight for semantic search (default: 0.5)
-    #[serde(default = "default_semantic_weight")]
-    pub semantic: f32,
-    /// Weight for BM25 search (default: 0.3)
-    #[serde(default = "default_bm25_weight")]
-    pub bm25: f32,
-    /// Weight for fuzzy search (default: 0.2)
-    #[serde(default = "default_fuzzy_weight")]
-    pub fuzzy: f32,
+pub struct HealthConfig {
+    /// Longest a dependency may take to answer its check, in milliseconds
+    /// (default: 2000)
+    #[serde(default = "default_health_timeout")]
+    pub timeout_ms: u64,
+
+    /// How long the LLM provider checks are reused, in seconds; 0 checks
+    /// them on every probe (default: 30)
+    #[serde(default = "default_provider_cache")]
+    pub provider_cache_secs: u64,
 }
 
-impl Default for HybridWeightsConfig {
+fn default_health_timeout() -> u64 {
+    2000
+}
+
+fn default_provider_cache() -> u64 {
+    30
+}
+
+impl Default for HealthConfig {
     fn default() -> Self {
         Self {
-            semantic: 0.5,
-            bm25: 0.3,
-            fuzzy: 0.2,
+            timeout_ms: default_health_timeout(),
+            provider_cache_secs: default_provider_cache(),
         }
     }
 }
 
-fn default_semantic_weight() -> f32 {
-    0.5
+/// Workers of the durable background job queue.
+///
+/// Scheduled agent runs and ingest jobs are queued in the database and run
+/// by these workers, so they survive restarts. Every server runs workers;
+/// a job is claimed by one of them at a time. Failed jobs are retried with
+/// exponential backoff, then kept as dead letters under
+/// `/api/admin/jobs` until retried by hand.
+///
+/// ```toml
+/// [jobs]
+/// concurrency = 4
+/// max_attempts = 5
+/// retry_base_secs = 30     # doubled on every failed attempt
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobsConfig {
+    /// Jobs a server runs at once (default: 4)
+    #[serde(default = "default_jobs_concurrency")]
+    pub concurrency: usize,
+
+    /// Seconds between looks for due jobs (default: 5)
+    #[serde(default = "default_jobs_poll_interval")]
+    pub poll_interval_secs: u64,
+
+    /// Attempts before a job is dead-lettered (default: 5)
+    #[serde(default = "default_jobs_max_attempts")]
+    pub max_attempts: u32,
+
+    /// Delay before the first retry, in seconds (default: 30)
+    #[serde(default = "default_jobs_retry_base")]
+    pub retry_base_secs: u64,
+
+    /// Longest delay between retries, in seconds (default: 3600)
+    #[serde(default = "default_jobs_retry_max")]
+    pub retry_max_secs: u64,
+
+    /// How long a worker holds a job without renewing its lease, in
+    /// seconds; a job is claimed again this long after its server stopped
+    /// (default: 300)
+    #[serde(default = "default_jobs_lease")]
+    pub lease_secs: u64,
+
+    /// Days succeeded jobs are kept (default: 7)
+    #[serde(default = "default_jobs_retention_days")]
+    pub retention_days: u32,
 }
 
-fn default_bm25_weight() -> f32 {
-    0.3
+fn default_jobs_concurrency() -> usize {
+    4
 }
 
-fn default_fuzzy_weight() -> f32 {
-    0.2
+fn default_jobs_poll_interval() -> u64 {
+    5
 }
 
-fn default_vector_store() -> String {
-    "ares-vector".to_string()
+fn default_jobs_max_attempts() -> u32 {
+    5
 }
 
-fn default_vector_path() -> String {
-    "./data/vectors".to_string()
+fn default_jobs_retry_base() -> u64 {
+    30
 }
 
-fn default_embedding_model() -> String {
-    "bge-small-en-v1.5".to_string()
+fn default_jobs_retry_max() -> u64 {
+    3600
+}
+
+fn default_jobs_lease() -> u64 {
+    300
+}
+
+fn default_jobs_retention_days() -> u32 {
+    7
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: default_jobs_concurrency(),
+            poll_interval_secs: default_jobs_poll_interval(),
+            max_attempts: default_jobs_max_attempts(),
+            retry_base_secs: default_jobs_retry_base(),
+            retry_max_secs: default_jobs_retry_max(),
+            lease_secs: default_jobs_lease(),
+            retention_days: default_jobs_retention_days(),
+        }
+    }
+}
+
+/// Encryption at rest of sensitive columns and uploaded files.
+///
+/// With a key set, memory facts, preferences, TOTP secrets and uploaded
+/// files are sealed with AES-256-GCM before they are stored (see
+/// [`crate::db::encryption`]). The key is read once at startup, from an
+/// environment variable or from what `key_command` prints, e.g. a KMS
+/// decrypt call; it must be 32 bytes, base64-encoded.
+///
+/// To rotate, make the new key current, list the old one in
+/// `previous_keys_env`, restart, and run `ares-server db rotate-key`.
+///
+/// ```toml
+/// [encryption]
+/// key_env = "ARES_ENCRYPTION_KEY"
+/// # key_command = ["aws", "kms", "decrypt", "--ciphertext-blob", "fileb://ares.key.enc",
+/// #                "--query", "Plaintext", "--output", "text"]
+/// previous_keys_env = "ARES_ENCRYPTION_PREVIOUS_KEYS"   # comma-separated
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    /// Environment variable holding the current key
+    #[serde(default)]
+    pub key_env: Option<String>,
+
+    /// Command printing the current key, when `key_env` isn't set
+    #[serde(default)]
+    pub key_command: Option<Vec<String>>,
+
+    /// Environment variable holding retired keys, comma-separated, still
+    /// used to read what they sealed until `db rotate-key` re-seals it
+    #[serde(default)]
+    pub previous_keys_env: Option<String>,
+}
+
+/// An agent run with a fixed prompt on a cron schedule.
+///
+/// Each run's prompt and answer are stored as a new conversation owned by
+/// `user_id`. Users schedule their own runs through `/api/schedules`.
+///
+/// ```toml
+/// [schedules.morning-digest]
+/// agent = "research"
+/// prompt = "Summarize the documents ingested yesterday."
+/// cron = "0 7 * * *"   # minute hour day-of-month month day-of-week, in UTC
+/// user_id = "ops-team"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+    /// Agent that runs
+    pub agent: String,
+
+    /// Message the agent is given on every run
+    pub prompt: String,
+
+    /// Cron expression in UTC, with an optional leading seconds field
+    pub cron: String,
+
+    /// Whether the schedule runs (default: true)
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// User whose conversations receive the runs (default: "system")
+    #[serde(default = "default_schedule_user")]
+    pub user_id: String,
 }
 
-fn default_sparse_model() -> String {
-    "splade-pp-en-v1".to_string()
-}
+fn default_schedule_user() -> String {
+    "system".to_string()
+}
+
+/// Role given to users no other role lists
+pub const DEFAULT_ROLE: &str = "default";
+
+/// A user role and the tool permission profile applied to its members.
+///
+/// The profile is enforced on every tool call the members' requests make,
+/// whichever agent handles them, on top of the agent's own
+/// `tool_permissions`. Users not listed in any role get the `default` role,
+/// if one is configured.
+///
+/// ```toml
+/// [roles.viewer]
+/// users = ["guest@example.com"]
+/// denied_tools = ["web_fetch", "email_send"]
+///
+/// [roles.viewer.tool_permissions."*"]
+/// max_cost = 0.01
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoleConfig {
+    /// Members, by user ID or email
+    #[serde(default)]
+    pub users: Vec<String>,
+
+    /// Tools members may not use at all; `"*"` denies every tool
+    #[serde(default)]
+    pub denied_tools: Vec<String>,
 
-fn default_chunking_strategy() -> String {
-    "word".to_string()
+    /// Limits on the tools members may use, keyed by tool name or `"*"`
+    #[serde(default)]
+    pub tool_permissions: HashMap<String, ToolPermissionConfig>,
 }
 
-fn default_chunk_size() -> usize {
-    200
+impl AresConfig {
+    /// The role of a user, by user ID or email
+    ///
+    /// If several roles list the user, the first by name applies.
+    pub fn role_of(&self, user_id: &str, email: &str) -> Option<(&str, &RoleConfig)> {
+        let mut roles: Vec<(&String, &RoleConfig)> = self.roles.iter().collect();
+        roles.sort_by_key(|(name, _)| name.as_str());
+        roles
+            .into_iter()
+            .find(|(_, role)| {
+                role.users.iter().any(|user| {
+                    user == user_id || (!email.is_empty() && user.eq_ignore_ascii_case(email))
+                })
+            })
+            .or_else(|| self.roles.get_key_value(DEFAULT_ROLE))
+            .map(|(name, role)| (name.as_str(), role))
+    }
 }
 
-fn default_chunk_overlap() -> usize {
-    50
+fn default_archive_after_days() -> u32 {
+    90
 }
 
-fn default_min_chunk_size() -> usize {
-    20
+fn default_archive_interval_secs() -> u64 {
+    3600
 }
 
-fn default_search_strategy() -> String {
-    "semantic".to_string()
+fn default_archive_batch_size() -> u32 {
+    100
 }
 
-fn default_search_limit() -> usize {
-    10
+fn default_archive_location() -> String {
+    "data/archive".to_string()
 }
 
-fn default_reranker_model() -> String {
-    "bge-reranker-base".to_string()
+fn default_files_location() -> String {
+    "data/files".to_string()
 }
 
-fn default_rerank_weight() -> f32 {
-    0.6
+fn default_files_max_bytes() -> usize {
+    10 * 1024 * 1024
 }
 
-impl Default for RagConfig {
-    fn default() -> Self {
-        Self {
-            vector_store: default_vector_store(),
-            vector_path: default_vector_path(),
-            embedding_model: default_embedding_model(),
-            sparse_embeddings: false,
-            sparse_model: default_sparse_model(),
-            chunking_strategy: default_chunking_strategy(),
-            chunk_size: default_chunk_size(),
-            chunk_overlap: default_chunk_overlap(),
-            min_chunk_size: default_min_chunk_size(),
-            search_strategy: default_search_strategy(),
-            search_limit: default_search_limit(),
-            search_threshold: 0.0,
-            hybrid_weights: None,
-            rerank_enabled: false,
-            reranker_model: default_reranker_model(),
-            rerank_weight: default_rerank_weight(),
-        }
-    }
+fn default_files_allowed_types() -> Vec<String> {
+    [
+        "text/plain",
+        "text/markdown",
+        "text/csv",
+        "text/html",
+        "application/json",
+        "application/pdf",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "image/png",
+        "image/jpeg",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
 }
 
 // ============= Dynamic Configuration Paths =============
@@ -649,6 +3063,10 @@ pub struct DynamicConfigPaths {
     #[serde(default = "default_mcps_dir")]
     pub mcps_dir: std::path::PathBuf,
 
+    /// Directory containing agent plugin TOON files, loaded at startup
+    #[serde(default = "default_plugins_dir")]
+    pub plugins_dir: std::path::PathBuf,
+
     /// Whether to watch for changes and hot-reload TOON configs
     #[serde(default = "default_hot_reload")]
     pub hot_reload: bool,
@@ -678,6 +3096,10 @@ fn default_mcps_dir() -> std::path::PathBuf {
     std::path::PathBuf::from("config/mcps")
 }
 
+fn default_plugins_dir() -> std::path::PathBuf {
+    std::path::PathBuf::from("config/plugins")
+}
+
 fn default_hot_reload() -> bool {
     true
 }
@@ -694,6 +3116,7 @@ impl Default for DynamicConfigPaths {
             models_dir: default_models_dir(),
             tools_dir: default_tools_dir(),
             mcps_dir: default_mcps_dir(),
+            plugins_dir: default_plugins_dir(),
             hot_reload: default_hot_reload(),
             watch_interval_ms: default_watch_interval(),
         }
@@ -828,6 +3251,106 @@ impl AresConfig {
         // Validate auth env vars exist
         self.validate_env_var(&self.auth.jwt_secret_env)?;
         self.validate_env_var(&self.auth.api_key_env)?;
+        if self.auth.mfa.challenge_ttl_secs <= 0 {
+            return Err(ConfigError::ValidationError(
+                "[auth.mfa] challenge_ttl_secs must be positive".to_string(),
+            ));
+        }
+        if !(1..=3600).contains(&self.auth.service_tokens.ttl_secs) {
+            return Err(ConfigError::ValidationError(
+                "[auth.service_tokens] ttl_secs must be between 1 and 3600".to_string(),
+            ));
+        }
+        if let Some(env) = &self.auth.service_tokens.private_key_env {
+            self.validate_env_var(env)?;
+        }
+
+        // Validate the database pool
+        let pool = &self.database.pool;
+        if pool.max_connections == 0 {
+            return Err(ConfigError::ValidationError(
+                "[database.pool] max_connections must be positive".to_string(),
+            ));
+        }
+        if pool.min_connections > pool.max_connections {
+            return Err(ConfigError::ValidationError(
+                "[database.pool] min_connections cannot exceed max_connections".to_string(),
+            ));
+        }
+        if pool.acquire_timeout_secs == 0 {
+            return Err(ConfigError::ValidationError(
+                "[database.pool] acquire_timeout_secs must be positive".to_string(),
+            ));
+        }
+        if let Some(env) = &self.database.replica_url_env {
+            self.validate_env_var(env)?;
+        }
+        if let Some(redis) = &self.redis {
+            if redis.key_prefix.is_empty() || redis.key_prefix.contains(char::is_whitespace) {
+                return Err(ConfigError::ValidationError(
+                    "[redis] key_prefix must be non-empty, without whitespace".to_string(),
+                ));
+            }
+            self.validate_env_var(&redis.url_env)?;
+        }
+
+        // Validate the security profile
+        let security = &self.server.security;
+        if security.strict_cors {
+            let origins = &self.server.cors_origins;
+            if origins.is_empty() || origins.iter().any(|o| o.trim() == "*") {
+                return Err(ConfigError::ValidationError(
+                    "[server.security] strict_cors requires server.cors_origins to list \
+                     exact origins, without \"*\""
+                        .to_string(),
+                ));
+            }
+            if let Some(origin) = origins
+                .iter()
+                .find(|o| o.parse::<axum::http::HeaderValue>().is_err() || o.ends_with('/'))
+            {
+                return Err(ConfigError::ValidationError(format!(
+                    "Invalid CORS origin '{}': use scheme://host[:port]",
+                    origin
+                )));
+            }
+        }
+        if let Some(method) = self
+            .server
+            .cors_methods
+            .iter()
+            .flatten()
+            .find(|m| axum::http::Method::from_bytes(m.as_bytes()).is_err())
+        {
+            return Err(ConfigError::ValidationError(format!(
+                "Invalid CORS method '{}'",
+                method
+            )));
+        }
+        if let Some(header) = self
+            .server
+            .cors_headers
+            .iter()
+            .flatten()
+            .find(|h| axum::http::HeaderName::from_bytes(h.as_bytes()).is_err())
+        {
+            return Err(ConfigError::ValidationError(format!(
+                "Invalid CORS header '{}'",
+                header
+            )));
+        }
+        crate::middleware::client_ip::TrustedProxies::parse(&self.server.trusted_proxies)
+            .map_err(ConfigError::ValidationError)?;
+        if let Some(ref tls) = security.tls {
+            for path in [&tls.cert_path, &tls.key_path] {
+                if !Path::new(path).exists() {
+                    return Err(ConfigError::ValidationError(format!(
+                        "TLS file does not exist: {}",
+                        path
+                    )));
+                }
+            }
+        }
 
         // Validate database env vars if specified
         if let Some(ref qdrant) = self.database.qdrant {
@@ -842,9 +3365,22 @@ impl AresConfig {
                 ProviderConfig::OpenAI { api_key_env, .. } => {
                     self.validate_env_var(api_key_env)?;
                 }
-                ProviderConfig::Anthropic { api_key_env, .. } => {
+                ProviderConfig::Anthropic { api_key_env, .. }
+                | ProviderConfig::Mistral { api_key_env, .. }
+                | ProviderConfig::Cohere { api_key_env, .. } => {
                     self.validate_env_var(api_key_env)?;
                 }
+                ProviderConfig::LlamaCpp {
+                    model: Some(reference),
+                    ..
+                } => {
+                    // Validate the managed model has been downloaded
+                    crate::llm::gguf::ModelStore::from_env()
+                        .resolve(reference)
+                        .map_err(|e| {
+                            ConfigError::ValidationError(format!("{} (provider: {})", e, name))
+                        })?;
+                }
                 ProviderConfig::LlamaCpp { model_path, .. } => {
                     // Validate model path exists
                     if !Path::new(model_path).exists() {
@@ -854,63 +3390,688 @@ impl AresConfig {
                         )));
                     }
                 }
-                ProviderConfig::Ollama { .. } => {
-                    // Ollama doesn't require validation - it's the default fallback
+                ProviderConfig::Ollama { .. } | ProviderConfig::Mock { .. } => {
+                    // Ollama doesn't require validation - it's the default fallback
+                }
+            }
+        }
+
+        // Validate model -> provider references, context windows and continuations
+        for (model_name, model_config) in &self.models {
+            if !self.providers.contains_key(&model_config.provider) {
+                return Err(ConfigError::MissingProvider(
+                    model_config.provider.clone(),
+                    model_name.clone(),
+                ));
+            }
+            if let Some(window) = model_config.context_window {
+                if window <= model_config.max_tokens {
+                    return Err(ConfigError::ValidationError(format!(
+                        "context_window of model '{}' must be larger than its max_tokens",
+                        model_name
+                    )));
+                }
+            }
+            if model_config.max_continuations > MAX_CONTINUATIONS {
+                return Err(ConfigError::ValidationError(format!(
+                    "max_continuations of model '{}' must be at most {}",
+                    model_name, MAX_CONTINUATIONS
+                )));
+            }
+        }
+
+        // Validate agent -> model and agent -> tools references
+        for (agent_name, agent_config) in &self.agents {
+            if !self.models.contains_key(&agent_config.model) {
+                return Err(ConfigError::MissingModel(
+                    agent_config.model.clone(),
+                    agent_name.clone(),
+                ));
+            }
+
+            for tool_name in &agent_config.tools {
+                if !self.tools.contains_key(tool_name) {
+                    return Err(ConfigError::MissingTool(
+                        tool_name.clone(),
+                        agent_name.clone(),
+                    ));
+                }
+            }
+
+            if let Some(ref schema) = agent_config.output_schema {
+                jsonschema::validator_for(schema).map_err(|e| {
+                    ConfigError::ValidationError(format!(
+                        "Invalid output_schema for agent '{}': {}",
+                        agent_name, e
+                    ))
+                })?;
+            }
+        }
+
+        // Validate the reranker's provider and model references
+        match self.rag.reranker {
+            RerankerKind::Cohere => match self.rag.reranker_provider.as_deref() {
+                Some(name) => match self.providers.get(name) {
+                    Some(ProviderConfig::Cohere { .. }) => {}
+                    _ => {
+                        return Err(ConfigError::ValidationError(format!(
+                            "RAG reranker provider '{}' must be a cohere provider in [providers]",
+                            name
+                        )))
+                    }
+                },
+                None => {
+                    return Err(ConfigError::ValidationError(
+                        "The cohere reranker requires [rag] reranker_provider".to_string(),
+                    ))
+                }
+            },
+            RerankerKind::Llm => {
+                if let Some(ref model) = self.rag.reranker_model {
+                    if !self.models.contains_key(model) {
+                        return Err(ConfigError::ValidationError(format!(
+                            "RAG reranker model '{}' is not defined in [models]",
+                            model
+                        )));
+                    }
+                }
+            }
+            RerankerKind::CrossEncoder => {}
+        }
+
+        // Validate the vector store and the variables it connects with
+        match self.rag.vector_store.as_str() {
+            "ares-vector" | "lancedb" | "pgvector" => {}
+            "qdrant" if self.rag.vector_url_env.is_none() => {
+                return Err(ConfigError::ValidationError(
+                    "The qdrant vector store requires [rag] vector_url_env".to_string(),
+                ))
+            }
+            "qdrant" => {}
+            other => return Err(ConfigError::ValidationError(format!(
+                "Unknown RAG vector store '{}'; expected ares-vector, qdrant, lancedb or pgvector",
+                other
+            ))),
+        }
+        if let Some(ref env) = self.rag.vector_url_env {
+            self.validate_env_var(env)?;
+        }
+        if let Some(ref env) = self.rag.vector_api_key_env {
+            self.validate_env_var(env)?;
+        }
+
+        // Validate the remote embedding provider reference
+        if let Some(ref name) = self.rag.embedding_provider {
+            match self.providers.get(name) {
+                Some(
+                    ProviderConfig::OpenAI { .. }
+                    | ProviderConfig::Mistral { .. }
+                    | ProviderConfig::Ollama { .. },
+                ) => {}
+                Some(_) => {
+                    return Err(ConfigError::ValidationError(format!(
+                        "RAG embedding provider '{}' must be an openai, mistral or ollama provider",
+                        name
+                    )))
+                }
+                None => {
+                    return Err(ConfigError::ValidationError(format!(
+                        "RAG embedding provider '{}' is not defined in [providers]",
+                        name
+                    )))
+                }
+            }
+        }
+
+        // Validate guardrail policy references
+        self.validate_guardrails()?;
+
+        // Validate budget limits and pricing
+        self.validate_budgets()?;
+
+        // Validate conversation archival
+        self.validate_archive()?;
+        self.validate_files()?;
+
+        // Validate the job workers
+        let jobs = &self.jobs;
+        if jobs.concurrency == 0
+            || jobs.poll_interval_secs == 0
+            || jobs.max_attempts == 0
+            || jobs.lease_secs == 0
+        {
+            return Err(ConfigError::ValidationError(
+                "jobs.concurrency, jobs.poll_interval_secs, jobs.max_attempts and \
+                 jobs.lease_secs must be greater than 0"
+                    .to_string(),
+            ));
+        }
+
+        // Validate the encryption key source
+        let encryption = &self.encryption;
+        if encryption.key_env.is_some() && encryption.key_command.is_some() {
+            return Err(ConfigError::ValidationError(
+                "encryption.key_env and encryption.key_command are mutually exclusive".to_string(),
+            ));
+        }
+        if encryption.key_command.as_ref().is_some_and(Vec::is_empty) {
+            return Err(ConfigError::ValidationError(
+                "encryption.key_command must name a program".to_string(),
+            ));
+        }
+
+        // Validate batch chat limits
+        if self.batch.max_items == 0 || self.batch.concurrency == 0 {
+            return Err(ConfigError::ValidationError(
+                "batch.max_items and batch.concurrency must be greater than 0".to_string(),
+            ));
+        }
+
+        // Validate per-route rate limits
+        self.validate_rate_limits()?;
+
+        // Validate the conversation title model
+        if let Some(model) = &self.titles.model {
+            if !self.models.contains_key(model) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Model '{}' referenced by titles.model does not exist",
+                    model
+                )));
+            }
+        }
+
+        // Validate the models warmed up at startup
+        for model in &self.warmup.models {
+            if !self.models.contains_key(model) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Model '{}' referenced by warmup.models does not exist",
+                    model
+                )));
+            }
+        }
+
+        // Validate scheduled agent runs
+        self.validate_schedules()?;
+
+        // Validate per-agent tool permissions
+        self.validate_tool_permissions()?;
+
+        // Validate answer cache thresholds
+        for (agent_name, agent_config) in &self.agents {
+            let threshold = agent_config.answer_cache.similarity_threshold;
+            if !(0.0..=1.0).contains(&threshold) {
+                return Err(ConfigError::ValidationError(format!(
+                    "answer_cache.similarity_threshold of agent '{}' must be between 0.0 and 1.0",
+                    agent_name
+                )));
+            }
+        }
+
+        // Validate reflection rounds and judge models
+        for (agent_name, agent_config) in &self.agents {
+            let reflection = &agent_config.reflection;
+            if !(1..=MAX_REFLECTION_ROUNDS).contains(&reflection.max_rounds) {
+                return Err(ConfigError::ValidationError(format!(
+                    "reflection.max_rounds of agent '{}' must be between 1 and {}",
+                    agent_name, MAX_REFLECTION_ROUNDS
+                )));
+            }
+            if let Some(judge) = &reflection.judge_model {
+                if !self.models.contains_key(judge) {
+                    return Err(ConfigError::MissingModel(judge.clone(), agent_name.clone()));
+                }
+            }
+            if let Some(judge) = &reflection.judge {
+                if !self.judges.contains_key(judge) {
+                    return Err(ConfigError::ValidationError(format!(
+                        "Judge '{}' referenced by agent '{}' does not exist",
+                        judge, agent_name
+                    )));
+                }
+            }
+        }
+
+        // Validate judges
+        self.validate_judges()?;
+
+        // Validate canary routing
+        self.validate_canaries()?;
+
+        // Validate remote agents
+        self.validate_remote_agents()?;
+
+        // Validate run limits
+        for (agent_name, agent_config) in &self.agents {
+            agent_config.limits.validate().map_err(|e| {
+                ConfigError::ValidationError(format!("{} (agent '{}')", e, agent_name))
+            })?;
+        }
+
+        // Validate personas
+        for (agent_name, agent_config) in &self.agents {
+            agent_config.validate_personas().map_err(|e| {
+                ConfigError::ValidationError(format!("{} (agent '{}')", e, agent_name))
+            })?;
+        }
+
+        // Validate request parameter limits
+        for (agent_name, agent_config) in &self.agents {
+            agent_config.parameter_limits.validate().map_err(|e| {
+                ConfigError::ValidationError(format!("{} (agent '{}')", e, agent_name))
+            })?;
+        }
+
+        // Validate workflow -> agent references
+        for (workflow_name, workflow_config) in &self.workflows {
+            if !self.has_agent(&workflow_config.entry_agent) {
+                return Err(ConfigError::MissingAgent(
+                    workflow_config.entry_agent.clone(),
+                    workflow_name.clone(),
+                ));
+            }
+
+            if let Some(ref fallback) = workflow_config.fallback_agent {
+                if !self.has_agent(fallback) {
+                    return Err(ConfigError::MissingAgent(
+                        fallback.clone(),
+                        workflow_name.clone(),
+                    ));
+                }
+            }
+
+            if let Some(ref debate) = workflow_config.debate {
+                if debate.agents.len() < 2 {
+                    return Err(ConfigError::ValidationError(format!(
+                        "Debate in workflow '{}' needs at least two agents",
+                        workflow_name
+                    )));
+                }
+                for (i, agent) in debate.agents.iter().enumerate() {
+                    if !self.has_agent(agent) {
+                        return Err(ConfigError::MissingAgent(
+                            agent.clone(),
+                            workflow_name.clone(),
+                        ));
+                    }
+                    if debate.agents[..i].contains(agent) {
+                        return Err(ConfigError::ValidationError(format!(
+                            "Debate in workflow '{}' lists agent '{}' twice",
+                            workflow_name, agent
+                        )));
+                    }
+                }
+            }
+
+            if let Some(ref judge) = workflow_config.judge {
+                if !self.judges.contains_key(judge) {
+                    return Err(ConfigError::ValidationError(format!(
+                        "Judge '{}' referenced by workflow '{}' does not exist",
+                        judge, workflow_name
+                    )));
+                }
+            }
+        }
+
+        // Check for circular references in workflows (entry_agent -> fallback cycles)
+        self.detect_circular_references()?;
+
+        Ok(())
+    }
+
+    /// Validate guardrail policies and the agent -> policy references
+    fn validate_guardrails(&self) -> Result<(), ConfigError> {
+        let policies = &self.guardrails.policies;
+
+        if let Some(ref default) = self.guardrails.default_policy {
+            if !policies.contains_key(default) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Default guardrail policy '{}' does not exist",
+                    default
+                )));
+            }
+        }
+
+        for (agent_name, agent_config) in &self.agents {
+            if let Some(ref policy) = agent_config.guardrail_policy {
+                if !policies.contains_key(policy) {
+                    return Err(ConfigError::ValidationError(format!(
+                        "Guardrail policy '{}' referenced by agent '{}' does not exist",
+                        policy, agent_name
+                    )));
+                }
+            }
+        }
+
+        for (policy_name, policy) in policies {
+            for (label, pattern) in &policy.pii_patterns {
+                if let Err(e) = regex::Regex::new(pattern) {
+                    return Err(ConfigError::ValidationError(format!(
+                        "Invalid PII pattern '{}' in guardrail policy '{}': {}",
+                        label, policy_name, e
+                    )));
+                }
+            }
+
+            if !policy.blocked_topics.is_empty() {
+                match policy.judge_model {
+                    Some(ref judge) if !self.models.contains_key(judge) => {
+                        return Err(ConfigError::ValidationError(format!(
+                            "Judge model '{}' referenced by guardrail policy '{}' does not exist",
+                            judge, policy_name
+                        )));
+                    }
+                    None => {
+                        return Err(ConfigError::ValidationError(format!(
+                            "Guardrail policy '{}' sets blocked_topics but no judge_model",
+                            policy_name
+                        )));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validate tool permission globs and cost limits
+    fn validate_tool_permissions(&self) -> Result<(), ConfigError> {
+        for (agent_name, agent_config) in &self.agents {
+            for (tool, permission) in &agent_config.tool_permissions {
+                for pattern in &permission.allowed_paths {
+                    if let Err(e) = globset::Glob::new(pattern) {
+                        return Err(ConfigError::ValidationError(format!(
+                            "Invalid allowed_paths pattern '{}' for tool '{}' of agent '{}': {}",
+                            pattern, tool, agent_name, e
+                        )));
+                    }
+                }
+                if permission.max_cost.is_some_and(|cost| cost < 0.0) {
+                    return Err(ConfigError::ValidationError(format!(
+                        "max_cost for tool '{}' of agent '{}' must be non-negative",
+                        tool, agent_name
+                    )));
+                }
+            }
+        }
+
+        for (role_name, role) in &self.roles {
+            for tool in &role.denied_tools {
+                if tool != crate::tools::permissions::ANY_TOOL && !self.tools.contains_key(tool) {
+                    return Err(ConfigError::ValidationError(format!(
+                        "Tool '{}' denied to role '{}' does not exist",
+                        tool, role_name
+                    )));
+                }
+            }
+            for (tool, permission) in &role.tool_permissions {
+                for pattern in &permission.allowed_paths {
+                    if let Err(e) = globset::Glob::new(pattern) {
+                        return Err(ConfigError::ValidationError(format!(
+                            "Invalid allowed_paths pattern '{}' for tool '{}' of role '{}': {}",
+                            pattern, tool, role_name, e
+                        )));
+                    }
+                }
+                if permission.max_cost.is_some_and(|cost| cost < 0.0) {
+                    return Err(ConfigError::ValidationError(format!(
+                        "max_cost for tool '{}' of role '{}' must be non-negative",
+                        tool, role_name
+                    )));
                 }
             }
         }
 
-        // Validate model -> provider references
-        for (model_name, model_config) in &self.models {
-            if !self.providers.contains_key(&model_config.provider) {
-                return Err(ConfigError::MissingProvider(
-                    model_config.provider.clone(),
-                    model_name.clone(),
-                ));
+        for (tool_name, tool_config) in &self.tools {
+            if tool_config.cost_per_call.is_some_and(|cost| cost < 0.0) {
+                return Err(ConfigError::ValidationError(format!(
+                    "cost_per_call for tool '{}' must be non-negative",
+                    tool_name
+                )));
             }
         }
 
-        // Validate agent -> model and agent -> tools references
-        for (agent_name, agent_config) in &self.agents {
-            if !self.models.contains_key(&agent_config.model) {
-                return Err(ConfigError::MissingModel(
-                    agent_config.model.clone(),
-                    agent_name.clone(),
-                ));
-            }
+        Ok(())
+    }
 
-            for tool_name in &agent_config.tools {
-                if !self.tools.contains_key(tool_name) {
-                    return Err(ConfigError::MissingTool(
-                        tool_name.clone(),
-                        agent_name.clone(),
-                    ));
+    /// Validate that budget limits and model prices are non-negative
+    fn validate_budgets(&self) -> Result<(), ConfigError> {
+        let budgets = &self.budgets;
+        let limits = std::iter::once(("global".to_string(), &budgets.global))
+            .chain(std::iter::once(("per_user".to_string(), &budgets.per_user)))
+            .chain(
+                budgets
+                    .users
+                    .iter()
+                    .map(|(k, v)| (format!("users.{}", k), v)),
+            )
+            .chain(
+                budgets
+                    .agents
+                    .iter()
+                    .map(|(k, v)| (format!("agents.{}", k), v)),
+            );
+
+        for (scope, limit) in limits {
+            for value in [limit.daily_usd, limit.monthly_usd].into_iter().flatten() {
+                if value < 0.0 {
+                    return Err(ConfigError::ValidationError(format!(
+                        "Budget limit for '{}' must not be negative",
+                        scope
+                    )));
                 }
             }
         }
 
-        // Validate workflow -> agent references
-        for (workflow_name, workflow_config) in &self.workflows {
-            if !self.agents.contains_key(&workflow_config.entry_agent) {
-                return Err(ConfigError::MissingAgent(
-                    workflow_config.entry_agent.clone(),
-                    workflow_name.clone(),
-                ));
+        for (model_name, pricing) in &budgets.pricing {
+            if pricing.input_per_1k < 0.0 || pricing.output_per_1k < 0.0 {
+                return Err(ConfigError::ValidationError(format!(
+                    "Pricing for model '{}' must not be negative",
+                    model_name
+                )));
             }
+        }
 
-            if let Some(ref fallback) = workflow_config.fallback_agent {
-                if !self.agents.contains_key(fallback) {
-                    return Err(ConfigError::MissingAgent(
-                        fallback.clone(),
-                        workflow_name.clone(),
-                    ));
+        Ok(())
+    }
+
+    fn validate_archive(&self) -> Result<(), ConfigError> {
+        let archive = &self.archive;
+        if archive.after_days == 0 || archive.interval_secs == 0 || archive.batch_size == 0 {
+            return Err(ConfigError::ValidationError(
+                "archive.after_days, archive.interval_secs and archive.batch_size must be \
+                 greater than 0"
+                    .to_string(),
+            ));
+        }
+        validate_storage_location("archive.location", &archive.location)
+    }
+
+    fn validate_files(&self) -> Result<(), ConfigError> {
+        let files = &self.files;
+        if files.max_bytes == 0 {
+            return Err(ConfigError::ValidationError(
+                "files.max_bytes must be greater than 0".to_string(),
+            ));
+        }
+        if files.allowed_types.iter().any(|t| !t.contains('/')) {
+            return Err(ConfigError::ValidationError(
+                "files.allowed_types must be content types such as \"text/plain\"".to_string(),
+            ));
+        }
+        if files
+            .scan_command
+            .as_ref()
+            .is_some_and(|command| command.trim().is_empty())
+        {
+            return Err(ConfigError::ValidationError(
+                "files.scan_command must not be empty".to_string(),
+            ));
+        }
+        validate_storage_location("files.location", &files.location)
+    }
+
+    fn validate_rate_limits(&self) -> Result<(), ConfigError> {
+        let limits = &self.server.rate_limits;
+        for (group, limit) in [
+            ("chat", limits.chat),
+            ("research", limits.research),
+            ("ingest", limits.ingest),
+        ] {
+            let Some(limit) = limit else {
+                continue;
+            };
+            if limit.requests_per_minute == 0 || limit.capacity() == 0 {
+                return Err(ConfigError::ValidationError(format!(
+                    "server.rate_limits.{} must allow at least one request",
+                    group
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_judges(&self) -> Result<(), ConfigError> {
+        for (name, judge) in &self.judges {
+            if !self.models.contains_key(&judge.model) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Model '{}' referenced by judge '{}' does not exist",
+                    judge.model, name
+                )));
+            }
+            if !(0.0..=10.0).contains(&judge.pass_score) {
+                return Err(ConfigError::ValidationError(format!(
+                    "judges.{}.pass_score must be between 0 and 10",
+                    name
+                )));
+            }
+            if !(1..=MAX_JUDGE_SAMPLES).contains(&judge.samples) {
+                return Err(ConfigError::ValidationError(format!(
+                    "judges.{}.samples must be between 1 and {}",
+                    name, MAX_JUDGE_SAMPLES
+                )));
+            }
+            if judge.criteria.iter().any(|c| c.trim().is_empty()) {
+                return Err(ConfigError::ValidationError(format!(
+                    "judges.{} has an empty criterion",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_canaries(&self) -> Result<(), ConfigError> {
+        for (name, canary) in &self.canaries {
+            for model in [name, &canary.model] {
+                if !self.models.contains_key(model) {
+                    return Err(ConfigError::ValidationError(format!(
+                        "Model '{}' referenced by canary '{}' does not exist",
+                        model, name
+                    )));
+                }
+            }
+            if canary.model == *name || self.canaries.contains_key(&canary.model) {
+                return Err(ConfigError::ValidationError(format!(
+                    "canaries.{}.model must be another model without a canary of its own",
+                    name
+                )));
+            }
+            if !(0.0..=100.0).contains(&canary.percent) {
+                return Err(ConfigError::ValidationError(format!(
+                    "canaries.{}.percent must be between 0 and 100",
+                    name
+                )));
+            }
+            if let Some(judge) = &canary.judge {
+                let Some(judge_config) = self.judges.get(judge) else {
+                    return Err(ConfigError::ValidationError(format!(
+                        "Judge '{}' referenced by canary '{}' does not exist",
+                        judge, name
+                    )));
+                };
+                // Scoring calls through a routed model would be scored in turn
+                if self.canaries.contains_key(&judge_config.model) {
+                    return Err(ConfigError::ValidationError(format!(
+                        "Judge '{}' of canary '{}' must not use a model with a canary",
+                        judge, name
+                    )));
                 }
             }
         }
+        Ok(())
+    }
 
-        // Check for circular references in workflows (entry_agent -> fallback cycles)
-        self.detect_circular_references()?;
+    /// Whether `name` is an agent from \[agents\] or \[remote_agents\]
+    fn has_agent(&self, name: &str) -> bool {
+        self.agents.contains_key(name) || self.remote_agents.contains_key(name)
+    }
+
+    fn validate_remote_agents(&self) -> Result<(), ConfigError> {
+        for (name, remote) in &self.remote_agents {
+            if self.agents.contains_key(name) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Remote agent '{}' has the name of an agent in [agents]",
+                    name
+                )));
+            }
+            if !remote.url.starts_with("http://") && !remote.url.starts_with("https://") {
+                return Err(ConfigError::ValidationError(format!(
+                    "remote_agents.{}.url must be an http or https URL",
+                    name
+                )));
+            }
+            if remote.timeout_secs == 0 {
+                return Err(ConfigError::ValidationError(format!(
+                    "remote_agents.{}.timeout_secs must be greater than 0",
+                    name
+                )));
+            }
+            if !remote.service_token_scopes.is_empty()
+                && remote.api_key_env.is_some()
+                && remote.api_key_header.is_none()
+            {
+                return Err(ConfigError::ValidationError(format!(
+                    "remote_agents.{} sends both its key and a service token as the \
+                     Authorization header; set api_key_header or drop one",
+                    name
+                )));
+            }
+            if let Some(scope) = remote
+                .service_token_scopes
+                .iter()
+                .find(|scope| scope.is_empty() || scope.contains(char::is_whitespace))
+            {
+                return Err(ConfigError::ValidationError(format!(
+                    "remote_agents.{}.service_token_scopes: invalid scope '{}'",
+                    name, scope
+                )));
+            }
+        }
+        Ok(())
+    }
 
+    fn validate_schedules(&self) -> Result<(), ConfigError> {
+        for (name, schedule) in &self.schedules {
+            if !self.agents.contains_key(&schedule.agent) || schedule.agent == "router" {
+                return Err(ConfigError::ValidationError(format!(
+                    "Agent '{}' referenced by schedule '{}' does not exist",
+                    schedule.agent, name
+                )));
+            }
+            if schedule.prompt.trim().is_empty() {
+                return Err(ConfigError::ValidationError(format!(
+                    "schedules.{}.prompt must not be empty",
+                    name
+                )));
+            }
+            if let Err(e) = crate::agents::scheduler::CronSchedule::parse(&schedule.cron) {
+                return Err(ConfigError::ValidationError(format!(
+                    "schedules.{}.cron: {}",
+                    name, e
+                )));
+            }
+        }
         Ok(())
     }
 
@@ -1081,7 +4242,7 @@ impl AresConfig {
     }
 
     /// Minimum length for JWT secret (256 bits = 32 bytes)
-    const JWT_SECRET_MIN_LENGTH: usize = 32;
+    pub(crate) const JWT_SECRET_MIN_LENGTH: usize = 32;
 
     /// Get the JWT secret from the environment
     ///
@@ -1137,6 +4298,11 @@ impl AresConfig {
         self.workflows.get(name)
     }
 
+    /// Get guardrail policy by name
+    pub fn get_guardrail_policy(&self, name: &str) -> Option<&GuardrailPolicyConfig> {
+        self.guardrails.policies.get(name)
+    }
+
     /// Get all enabled tools
     pub fn enabled_tools(&self) -> Vec<&str> {
         self.tools
@@ -1161,6 +4327,27 @@ impl AresConfig {
     }
 }
 
+/// Check that a storage location is a directory or `s3://bucket/prefix`.
+fn validate_storage_location(field: &str, location: &str) -> Result<(), ConfigError> {
+    let trimmed = location.trim();
+    let bucket = trimmed
+        .strip_prefix("s3://")
+        .map(|rest| rest.split('/').next());
+    if trimmed.is_empty() || bucket == Some(Some("")) {
+        return Err(ConfigError::ValidationError(format!(
+            "{} '{}' must be a directory or s3://bucket/prefix",
+            field, location
+        )));
+    }
+    if trimmed.contains("://") && bucket.is_none() {
+        return Err(ConfigError::ValidationError(format!(
+            "{} '{}' is not supported; use a directory or s3://bucket/prefix",
+            field, location
+        )));
+    }
+    Ok(())
+}
+
 // ============= Hot Reloading Configuration Manager =============
 
 /// Thread-safe configuration manager with hot reloading support
@@ -1169,6 +4356,7 @@ pub struct AresConfigManager {
     config_path: PathBuf,
     watcher: RwLock<Option<RecommendedWatcher>>,
     reload_tx: Option<mpsc::UnboundedSender<()>>,
+    reloaded: broadcast::Sender<()>,
 }
 
 impl AresConfigManager {
@@ -1195,6 +4383,7 @@ impl AresConfigManager {
             config_path: path,
             watcher: RwLock::new(None),
             reload_tx: None,
+            reloaded: broadcast::channel(16).0,
         })
     }
 
@@ -1203,6 +4392,12 @@ impl AresConfigManager {
         self.config.load_full()
     }
 
+    /// Notified each time the file watcher reloads the configuration; manual
+    /// reloads are not announced
+    pub fn subscribe_reloads(&self) -> broadcast::Receiver<()> {
+        self.reloaded.subscribe()
+    }
+
     /// Manually reload the configuration from disk
     pub fn reload(&self) -> Result<(), ConfigError> {
         info!("Reloading configuration from {:?}", self.config_path);
@@ -1221,6 +4416,7 @@ impl AresConfigManager {
 
         let config_path = self.config_path.clone();
         let config_arc = Arc::clone(&self.config);
+        let reloaded = self.reloaded.clone();
 
         // Create debounced file watcher
         let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
@@ -1263,6 +4459,7 @@ impl AresConfigManager {
                     Ok(new_config) => {
                         config_arc.store(Arc::new(new_config));
                         info!("Configuration hot-reloaded successfully");
+                        let _ = reloaded.send(());
                         last_reload = std::time::Instant::now();
                     }
                     Err(e) => {
@@ -1293,6 +4490,7 @@ impl Clone for AresConfigManager {
             config_path: self.config_path.clone(),
             watcher: RwLock::new(None), // Watcher is not cloned
             reload_tx: self.reload_tx.clone(),
+            reloaded: self.reloaded.clone(),
         }
     }
 }
@@ -1306,6 +4504,7 @@ impl AresConfigManager {
             config_path: PathBuf::from("test-config.toml"),
             watcher: RwLock::new(None),
             reload_tx: None,
+            reloaded: broadcast::channel(16).0,
         }
     }
 }
@@ -1382,7 +4581,258 @@ max_iterations = 5
     }
 
     #[test]
-    fn test_validation_missing_provider() {
+    fn test_validation_missing_provider() {
+        // SAFETY: Tests are run single-threaded for env var safety
+        unsafe {
+            std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
+            std::env::set_var("TEST_API_KEY", "test-key");
+        }
+
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+[models.test]
+provider = "nonexistent"
+model = "test"
+"#;
+
+        let config: AresConfig = toml::from_str(content).unwrap();
+        let result = config.validate();
+
+        assert!(matches!(result, Err(ConfigError::MissingProvider(_, _))));
+    }
+
+    #[test]
+    fn test_validation_missing_model() {
+        // SAFETY: Tests are run single-threaded for env var safety
+        unsafe {
+            std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
+            std::env::set_var("TEST_API_KEY", "test-key");
+        }
+
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+[providers.test]
+type = "ollama"
+default_model = "ministral-3:3b"
+[agents.test]
+model = "nonexistent"
+"#;
+
+        let config: AresConfig = toml::from_str(content).unwrap();
+        let result = config.validate();
+
+        assert!(matches!(result, Err(ConfigError::MissingModel(_, _))));
+    }
+
+    #[test]
+    fn test_validation_reflection() {
+        // SAFETY: Tests are run single-threaded for env var safety
+        unsafe {
+            std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
+            std::env::set_var("TEST_API_KEY", "test-key");
+        }
+
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+[providers.test]
+type = "ollama"
+default_model = "ministral-3:3b"
+[models.default]
+provider = "test"
+model = "ministral-3:3b"
+[agents.writer]
+model = "default"
+reflection = { enabled = true, max_rounds = 2, judge_model = "default" }
+"#;
+
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        let reflection = &config.agents["writer"].reflection;
+        assert!(reflection.enabled);
+        assert_eq!(reflection.max_rounds, 2);
+        assert!(config.validate().is_ok());
+
+        let writer = config.agents.get_mut("writer").unwrap();
+        writer.reflection.judge_model = Some("nonexistent".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::MissingModel(_, _))
+        ));
+
+        let writer = config.agents.get_mut("writer").unwrap();
+        writer.reflection.judge_model = None;
+        writer.reflection.max_rounds = MAX_REFLECTION_ROUNDS + 1;
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_validation_judges() {
+        // SAFETY: Tests are run single-threaded for env var safety
+        unsafe {
+            std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
+            std::env::set_var("TEST_API_KEY", "test-key");
+        }
+
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+[providers.test]
+type = "ollama"
+default_model = "ministral-3:3b"
+[models.default]
+provider = "test"
+model = "ministral-3:3b"
+[judges.quality]
+model = "default"
+criteria = ["Accuracy", "Clarity"]
+[agents.writer]
+model = "default"
+reflection = { enabled = true, judge = "quality" }
+[workflows.research]
+entry_agent = "writer"
+judge = "quality"
+"#;
+
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        let judge = &config.judges["quality"];
+        assert_eq!(judge.pass_score, 7.0);
+        assert_eq!(judge.samples, 1);
+        assert!(config.validate().is_ok());
+
+        config.judges.get_mut("quality").unwrap().samples = MAX_JUDGE_SAMPLES + 1;
+        assert!(config.validate().is_err());
+
+        config.judges.get_mut("quality").unwrap().samples = 1;
+        config.workflows.get_mut("research").unwrap().judge = Some("strict".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(msg)) if msg.contains("'strict'")
+        ));
+
+        config.workflows.get_mut("research").unwrap().judge = None;
+        config.judges.get_mut("quality").unwrap().model = "nonexistent".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_canaries() {
+        // SAFETY: Tests are run single-threaded for env var safety
+        unsafe {
+            std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
+            std::env::set_var("TEST_API_KEY", "test-key");
+        }
+
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+[providers.test]
+type = "ollama"
+default_model = "ministral-3:3b"
+[models.balanced]
+provider = "test"
+model = "ministral-3:3b"
+[models.next]
+provider = "test"
+model = "ministral-3:8b"
+[judges.quality]
+model = "next"
+[canaries.balanced]
+model = "next"
+percent = 10
+judge = "quality"
+"#;
+
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        assert_eq!(config.canaries["balanced"].percent, 10.0);
+        assert!(config.validate().is_ok());
+
+        config.canaries.get_mut("balanced").unwrap().percent = 120.0;
+        assert!(config.validate().is_err());
+
+        config.canaries.get_mut("balanced").unwrap().percent = 10.0;
+        config.canaries.get_mut("balanced").unwrap().model = "balanced".to_string();
+        assert!(config.validate().is_err());
+
+        // The judge's model is routed itself
+        config.canaries.get_mut("balanced").unwrap().model = "next".to_string();
+        config.judges.get_mut("quality").unwrap().model = "balanced".to_string();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(msg)) if msg.contains("must not use a model")
+        ));
+    }
+
+    #[test]
+    fn test_validation_schedules() {
+        // SAFETY: Tests are run single-threaded for env var safety
+        unsafe {
+            std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
+            std::env::set_var("TEST_API_KEY", "test-key");
+        }
+
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+[providers.test]
+type = "ollama"
+default_model = "ministral-3:3b"
+[models.default]
+provider = "test"
+model = "ministral-3:3b"
+[agents.research]
+model = "default"
+[schedules.digest]
+agent = "research"
+prompt = "Summarize the documents ingested yesterday."
+cron = "0 7 * * *"
+"#;
+
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        let digest = &config.schedules["digest"];
+        assert!(digest.enabled);
+        assert_eq!(digest.user_id, "system");
+        assert!(config.validate().is_ok());
+
+        config.schedules.get_mut("digest").unwrap().cron = "every morning".to_string();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(_))
+        ));
+
+        let digest = config.schedules.get_mut("digest").unwrap();
+        digest.cron = "0 7 * * *".to_string();
+        digest.agent = "nonexistent".to_string();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_validation_run_limits() {
         // SAFETY: Tests are run single-threaded for env var safety
         unsafe {
             std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
@@ -1395,19 +4845,97 @@ max_iterations = 5
 jwt_secret_env = "TEST_JWT_SECRET"
 api_key_env = "TEST_API_KEY"
 [database]
-[models.test]
-provider = "nonexistent"
-model = "test"
+[providers.test]
+type = "ollama"
+default_model = "ministral-3:3b"
+[models.default]
+provider = "test"
+model = "ministral-3:3b"
+[agents.research]
+model = "default"
+[agents.research.limits]
+timeout_secs = 120
+max_tool_calls = 20
+max_cost = 0.25
 "#;
 
-        let config: AresConfig = toml::from_str(content).unwrap();
-        let result = config.validate();
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        let limits = config.agents["research"].limits;
+        assert_eq!(limits.timeout_secs, Some(120));
+        assert_eq!(limits.max_llm_calls, None);
+        assert!(!limits.is_unlimited());
+        assert!(config.validate().is_ok());
 
-        assert!(matches!(result, Err(ConfigError::MissingProvider(_, _))));
+        config
+            .agents
+            .get_mut("research")
+            .unwrap()
+            .limits
+            .timeout_secs = Some(0);
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(_))
+        ));
+
+        let limits = &mut config.agents.get_mut("research").unwrap().limits;
+        limits.timeout_secs = None;
+        limits.max_cost = Some(-1.0);
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(_))
+        ));
     }
 
     #[test]
-    fn test_validation_missing_model() {
+    fn test_parameter_limits() {
+        use crate::types::GenerationParameters;
+
+        let limits: ParameterLimitsConfig = toml::from_str(
+            r#"
+temperature = { min = 0.2, max = 1.2 }
+max_tokens = 2048
+"#,
+        )
+        .unwrap();
+        assert!(limits.validate().is_ok());
+        assert!(!limits.is_unset());
+
+        let check = |temperature, max_tokens, top_p| {
+            GenerationParameters {
+                temperature,
+                max_tokens,
+                top_p,
+            }
+            .check(&limits)
+        };
+        assert!(check(None, None, None).is_ok());
+        assert!(check(Some(1.2), Some(2048), None).is_ok());
+        assert!(check(Some(1.5), None, None).is_err());
+        assert!(check(None, Some(4096), None).is_err());
+        assert!(check(None, Some(0), None).is_err());
+        // Parameters without bounds can't be set at all
+        assert!(check(None, None, Some(0.9)).is_err());
+        assert!(GenerationParameters {
+            temperature: Some(0.5),
+            ..Default::default()
+        }
+        .check(&ParameterLimitsConfig::default())
+        .is_err());
+
+        let inverted = ParameterLimitsConfig {
+            top_p: Some(ParameterRange { min: 0.9, max: 0.5 }),
+            ..Default::default()
+        };
+        assert!(inverted.validate().is_err());
+        let too_hot = ParameterLimitsConfig {
+            temperature: Some(ParameterRange { min: 0.0, max: 3.0 }),
+            ..Default::default()
+        };
+        assert!(too_hot.validate().is_err());
+    }
+
+    #[test]
+    fn test_roles() {
         // SAFETY: Tests are run single-threaded for env var safety
         unsafe {
             std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
@@ -1423,14 +4951,38 @@ api_key_env = "TEST_API_KEY"
 [providers.test]
 type = "ollama"
 default_model = "ministral-3:3b"
-[agents.test]
-model = "nonexistent"
+[models.default]
+provider = "test"
+model = "ministral-3:3b"
+[tools.web_search]
+[roles.viewer]
+users = ["guest@example.com", "user-2"]
+denied_tools = ["web_search"]
+[roles.default.tool_permissions."*"]
+max_cost = 0.05
 "#;
 
-        let config: AresConfig = toml::from_str(content).unwrap();
-        let result = config.validate();
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.role_of("user-1", "Guest@Example.com").unwrap().0,
+            "viewer"
+        );
+        assert_eq!(config.role_of("user-2", "").unwrap().0, "viewer");
+        assert_eq!(
+            config.role_of("user-3", "other@example.com").unwrap().0,
+            DEFAULT_ROLE
+        );
 
-        assert!(matches!(result, Err(ConfigError::MissingModel(_, _))));
+        config.roles.remove(DEFAULT_ROLE);
+        assert!(config.role_of("user-3", "other@example.com").is_none());
+
+        let viewer = config.roles.get_mut("viewer").unwrap();
+        viewer.denied_tools.push("email_send".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(_))
+        ));
     }
 
     #[test]
@@ -1488,6 +5040,123 @@ entry_agent = "nonexistent_agent"
         assert!(matches!(result, Err(ConfigError::MissingAgent(_, _))));
     }
 
+    #[test]
+    fn test_agent_personas() {
+        // SAFETY: Tests are run single-threaded for env var safety
+        unsafe {
+            std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
+            std::env::set_var("TEST_API_KEY", "test-key");
+        }
+
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+[providers.test]
+type = "ollama"
+default_model = "ministral-3:3b"
+[models.default]
+provider = "test"
+model = "ministral-3:3b"
+[agents.support]
+model = "default"
+system_prompt = "You help customers."
+[agents.support.personas.formal]
+description = "Precise and formal"
+prompt = "Answer formally."
+[agents.support.personas.friendly]
+prompt = "Be warm and casual."
+"#;
+
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+
+        let mut support = config.agents["support"].clone();
+        assert_eq!(support.persona_names(), vec!["formal", "friendly"]);
+        assert!(support.check_persona("formal").is_ok());
+        let err = support.check_persona("pirate").unwrap_err();
+        assert!(err.contains("formal, friendly"));
+
+        assert!(!support.apply_persona("pirate"));
+        assert!(support.apply_persona("friendly"));
+        assert_eq!(
+            support.system_prompt.as_deref(),
+            Some("You help customers.\n\nBe warm and casual.")
+        );
+
+        config
+            .agents
+            .get_mut("support")
+            .unwrap()
+            .personas
+            .get_mut("formal")
+            .unwrap()
+            .prompt = " ".to_string();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_validation_debate_workflow() {
+        // SAFETY: Tests are run single-threaded for env var safety
+        unsafe {
+            std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
+            std::env::set_var("TEST_API_KEY", "test-key");
+        }
+
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+[providers.test]
+type = "ollama"
+default_model = "ministral-3:3b"
+[models.default]
+provider = "test"
+model = "ministral-3:3b"
+[agents.judge]
+model = "default"
+[agents.optimist]
+model = "default"
+[agents.skeptic]
+model = "default"
+[workflows.debate]
+entry_agent = "judge"
+debate = { agents = ["optimist", "skeptic"] }
+"#;
+
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        let debate = config.workflows["debate"].debate.clone().unwrap();
+        assert_eq!(debate.mode, DebateMode::Synthesize);
+        assert!(config.validate().is_ok());
+
+        let workflow = config.workflows.get_mut("debate").unwrap();
+        workflow.debate = Some(DebateConfig {
+            agents: vec!["optimist".to_string(), "optimist".to_string()],
+            mode: DebateMode::Select,
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(_))
+        ));
+
+        let workflow = config.workflows.get_mut("debate").unwrap();
+        workflow.debate = Some(DebateConfig {
+            agents: vec!["optimist".to_string(), "pessimist".to_string()],
+            mode: DebateMode::Select,
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::MissingAgent(_, _))
+        ));
+    }
+
     #[test]
     fn test_get_provider() {
         let content = create_test_config();
@@ -1497,6 +5166,57 @@ entry_agent = "nonexistent_agent"
         assert!(config.get_provider("nonexistent").is_none());
     }
 
+    #[test]
+    fn test_parse_mistral_and_cohere_providers() {
+        let providers: HashMap<String, ProviderConfig> = toml::from_str(
+            r#"
+[mistral]
+type = "mistral"
+api_key_env = "MISTRAL_API_KEY"
+default_model = "mistral-large-latest"
+
+[cohere]
+type = "cohere"
+api_key_env = "COHERE_API_KEY"
+default_model = "command-r-plus"
+connectors = ["web-search"]
+"#,
+        )
+        .unwrap();
+
+        match &providers["mistral"] {
+            ProviderConfig::Mistral { api_base, .. } => {
+                assert_eq!(api_base, "https://api.mistral.ai/v1")
+            }
+            other => panic!("expected Mistral provider, got {:?}", other),
+        }
+        match &providers["cohere"] {
+            ProviderConfig::Cohere { connectors, .. } => assert_eq!(connectors, &["web-search"]),
+            other => panic!("expected Cohere provider, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_llamacpp_managed_model() {
+        let provider: ProviderConfig = toml::from_str(
+            r#"
+type = "llamacpp"
+model = "acme/tiny-GGUF/tiny-q4.gguf"
+"#,
+        )
+        .unwrap();
+
+        match provider {
+            ProviderConfig::LlamaCpp {
+                model_path, model, ..
+            } => {
+                assert!(model_path.is_empty());
+                assert_eq!(model.as_deref(), Some("acme/tiny-GGUF/tiny-q4.gguf"));
+            }
+            other => panic!("expected LlamaCpp provider, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_get_model() {
         let content = create_test_config();
@@ -1622,6 +5342,327 @@ fallback_agent = "agent_a"
         assert!(matches!(result, Err(ConfigError::CircularReference(_))));
     }
 
+    #[test]
+    fn test_validation_missing_guardrail_policy() {
+        // SAFETY: Tests are run single-threaded for env var safety
+        unsafe {
+            std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
+            std::env::set_var("TEST_API_KEY", "test-key");
+        }
+
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+[providers.test]
+type = "ollama"
+default_model = "ministral-3:3b"
+[models.default]
+provider = "test"
+model = "ministral-3:3b"
+[guardrails.policies.strict]
+pii_redaction = true
+blocked_topics = ["politics"]
+[agents.router]
+model = "default"
+guardrail_policy = "nonexistent"
+"#;
+
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(msg)) if msg.contains("nonexistent")
+        ));
+
+        // Blocked topics require a judge model
+        config.agents.get_mut("router").unwrap().guardrail_policy = Some("strict".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(msg)) if msg.contains("judge_model")
+        ));
+
+        config
+            .guardrails
+            .policies
+            .get_mut("strict")
+            .unwrap()
+            .judge_model = Some("default".to_string());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validation_rag_embedding_provider() {
+        // SAFETY: Tests are run single-threaded for env var safety
+        unsafe {
+            std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
+            std::env::set_var("TEST_API_KEY", "test-key");
+        }
+
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+[providers.local]
+type = "ollama"
+default_model = "ministral-3:3b"
+[rag]
+embedding_provider = "local"
+embedding_model = "nomic-embed-text"
+"#;
+
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.rag.embedding_model, "nomic-embed-text");
+
+        config.rag.embedding_provider = Some("missing".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(msg)) if msg.contains("missing")
+        ));
+    }
+
+    #[test]
+    fn test_validation_strict_cors() {
+        // SAFETY: Tests are run single-threaded for env var safety
+        unsafe {
+            std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
+            std::env::set_var("TEST_API_KEY", "test-key");
+        }
+
+        let content = r#"
+[server]
+cors_origins = ["*"]
+[server.security]
+strict_cors = true
+headers = true
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+[providers.local]
+type = "ollama"
+default_model = "ministral-3:3b"
+"#;
+
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        assert!(config.server.security.headers);
+        assert_eq!(config.server.security.hsts_max_age_secs, 31_536_000);
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(msg)) if msg.contains("strict_cors")
+        ));
+
+        config.server.cors_origins = vec!["https://app.example.com/".to_string()];
+        assert!(config.validate().is_err());
+
+        config.server.cors_origins = vec!["https://app.example.com".to_string()];
+        assert!(config.validate().is_ok());
+
+        config.server.cors_headers = Some(vec!["X Custom".to_string()]);
+        assert!(config.validate().is_err());
+        config.server.cors_headers = Some(vec!["x-custom".to_string()]);
+        config.server.trusted_proxies = vec!["10.0.0.0/40".to_string()];
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(msg)) if msg.contains("trusted proxy")
+        ));
+        config.server.trusted_proxies = vec!["10.0.0.0/8".to_string()];
+        assert!(config.validate().is_ok());
+
+        config.server.security.tls = Some(TlsConfig {
+            cert_path: "/nonexistent/cert.pem".to_string(),
+            key_path: "/nonexistent/key.pem".to_string(),
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(msg)) if msg.contains("TLS")
+        ));
+    }
+
+    #[test]
+    fn test_validation_database_pool() {
+        // SAFETY: Tests are run single-threaded for env var safety
+        unsafe {
+            std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
+            std::env::set_var("TEST_API_KEY", "test-key");
+        }
+
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database.pool]
+max_connections = 4
+min_connections = 8
+[providers.local]
+type = "ollama"
+default_model = "ministral-3:3b"
+"#;
+
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        assert_eq!(config.database.pool.acquire_timeout_secs, 30);
+        assert_eq!(config.database.pool.statement_cache_capacity, 100);
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(msg)) if msg.contains("min_connections")
+        ));
+
+        config.database.pool.min_connections = 2;
+        assert!(config.validate().is_ok());
+
+        config.database.replica_url_env = Some("TEST_UNSET_REPLICA_URL".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::MissingEnvVar(name)) if name == "TEST_UNSET_REPLICA_URL"
+        ));
+    }
+
+    #[test]
+    fn test_validation_rag_reranker() {
+        // SAFETY: Tests are run single-threaded for env var safety
+        unsafe {
+            std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
+            std::env::set_var("TEST_API_KEY", "test-key");
+            std::env::set_var("TEST_COHERE_KEY", "co-test");
+        }
+
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+[providers.local]
+type = "ollama"
+default_model = "ministral-3:3b"
+[providers.cohere]
+type = "cohere"
+api_key_env = "TEST_COHERE_KEY"
+default_model = "command-r"
+[rag]
+reranker = "cohere"
+reranker_provider = "cohere"
+reranker_model = "rerank-v3.5"
+"#;
+
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.rag.reranker, RerankerKind::Cohere);
+
+        config.rag.reranker_provider = Some("local".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(msg)) if msg.contains("cohere provider")
+        ));
+
+        config.rag.reranker = RerankerKind::Llm;
+        config.rag.reranker_model = Some("missing".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(msg)) if msg.contains("missing")
+        ));
+    }
+
+    #[test]
+    fn test_validation_agent_output_schema() {
+        // SAFETY: Tests are run single-threaded for env var safety
+        unsafe {
+            std::env::set_var("TEST_JWT_SECRET", "test-secret-at-least-32-characters-long");
+            std::env::set_var("TEST_API_KEY", "test-key");
+        }
+
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+[providers.local]
+type = "ollama"
+default_model = "ministral-3:3b"
+[models.fast]
+provider = "local"
+model = "ministral-3:3b"
+[agents.classifier]
+model = "fast"
+output_schema = { type = "object", required = ["label"], properties = { label = { type = "string" } } }
+"#;
+
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+        let schema = config.agents["classifier"].output_schema.clone().unwrap();
+        assert_eq!(schema["required"][0], "label");
+
+        config.agents.get_mut("classifier").unwrap().output_schema =
+            Some(serde_json::json!({"type": 5}));
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ValidationError(msg)) if msg.contains("classifier")
+        ));
+    }
+
+    #[test]
+    fn test_budget_cost_and_limits() {
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+[budgets]
+enabled = true
+per_user = { daily_usd = 1.0 }
+[budgets.users.alice]
+daily_usd = 5.0
+monthly_usd = 50.0
+[budgets.pricing.default]
+input_per_1k = 0.5
+output_per_1k = 1.5
+"#;
+
+        let config: AresConfig = toml::from_str(content).unwrap();
+        let budgets = &config.budgets;
+        assert!(budgets.enabled);
+        assert!((budgets.estimate_cost("default", 2000, 1000) - 2.5).abs() < 1e-9);
+        assert_eq!(budgets.estimate_cost("unpriced", 2000, 1000), 0.0);
+        assert_eq!(budgets.user_limit("alice").monthly_usd, Some(50.0));
+        assert_eq!(budgets.user_limit("bob").daily_usd, Some(1.0));
+        assert_eq!(budgets.agent_limit("research"), BudgetLimit::default());
+    }
+
+    #[test]
+    fn test_archive_config() {
+        let content = r#"
+[server]
+[auth]
+jwt_secret_env = "TEST_JWT_SECRET"
+api_key_env = "TEST_API_KEY"
+[database]
+"#;
+
+        let mut config: AresConfig = toml::from_str(content).unwrap();
+        assert!(!config.archive.enabled);
+        assert_eq!(config.archive.after_days, 90);
+        assert_eq!(config.archive.location, "data/archive");
+        assert!(config.validate_archive().is_ok());
+
+        config.archive.location = "s3://archive/conversations/".to_string();
+        assert!(config.validate_archive().is_ok());
+        for location in ["s3:///conversations", "gs://archive", ""] {
+            config.archive.location = location.to_string();
+            assert!(config.validate_archive().is_err(), "{}", location);
+        }
+
+        config.archive.location = "data/archive".to_string();
+        config.archive.after_days = 0;
+        assert!(config.validate_archive().is_err());
+    }
+
     #[test]
     fn test_unused_provider_warning() {
         // SAFETY: Tests are run single-threaded for env var safety
@@ -31,6 +31,10 @@
 //!   You are a routing agent...
 //! ```
 
+use crate::utils::toml_config::{
+    AgentBusConfig, AgentMemoryConfig, AgentRagConfig, AgentStrategy, AnswerCacheConfig,
+    ParameterLimitsConfig, PersonaConfig, ReflectionConfig, RunLimitsConfig, ToolPermissionConfig,
+};
 use arc_swap::ArcSwap;
 use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
@@ -74,6 +78,62 @@ pub struct ToonAgentConfig {
     #[serde(default)]
     pub parallel_tools: bool,
 
+    /// Guardrail policy name defined in `ares.toml` [guardrails.policies.*]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guardrail_policy: Option<String>,
+
+    /// Agents this agent may hand the conversation off to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub handoffs: Vec<String>,
+
+    /// How the agent works towards an answer (`direct` or `react`)
+    #[serde(default, skip_serializing_if = "AgentStrategy::is_direct")]
+    pub strategy: AgentStrategy,
+
+    /// JSON Schema the agent's output must conform to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+
+    /// User memory injected into the system prompt each turn
+    #[serde(default, skip_serializing_if = "AgentMemoryConfig::is_disabled")]
+    pub memory: AgentMemoryConfig,
+
+    /// Limits on tool calls, keyed by tool name or `"*"`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tool_permissions: HashMap<String, ToolPermissionConfig>,
+
+    /// Reuse of earlier answers to similar first-turn questions
+    #[serde(default, skip_serializing_if = "AnswerCacheConfig::is_disabled")]
+    pub answer_cache: AnswerCacheConfig,
+
+    /// Critique and revision of draft answers before they are returned
+    #[serde(default, skip_serializing_if = "ReflectionConfig::is_disabled")]
+    pub reflection: ReflectionConfig,
+
+    /// Resource limits of a single run
+    #[serde(default, skip_serializing_if = "RunLimitsConfig::is_unlimited")]
+    pub limits: RunLimitsConfig,
+
+    /// Personas a chat request can select, keyed by name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub personas: HashMap<String, PersonaConfig>,
+
+    /// Whether a user must approve each tool call before it runs
+    #[serde(default)]
+    pub requires_approval: bool,
+
+    /// Events published to and read from the workflow message bus
+    #[serde(default, skip_serializing_if = "AgentBusConfig::is_disabled")]
+    pub bus: AgentBusConfig,
+
+    /// Bounds on the generation parameters chat requests may set
+    #[serde(default, skip_serializing_if = "ParameterLimitsConfig::is_unset")]
+    pub parameter_limits: ParameterLimitsConfig,
+
+    /// RAG collection whose passages are injected each turn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rag: Option<AgentRagConfig>,
+
     /// Additional agent-specific configuration (extensible)
     #[serde(flatten)]
     pub extra: HashMap<String, serde_json::Value>,
@@ -93,6 +153,20 @@ impl ToonAgentConfig {
             tools: Vec::new(),
             max_tool_iterations: default_max_tool_iterations(),
             parallel_tools: false,
+            guardrail_policy: None,
+            handoffs: Vec::new(),
+            strategy: Default::default(),
+            output_schema: None,
+            memory: Default::default(),
+            tool_permissions: Default::default(),
+            answer_cache: Default::default(),
+            reflection: Default::default(),
+            limits: Default::default(),
+            personas: Default::default(),
+            requires_approval: false,
+            bus: Default::default(),
+            parameter_limits: Default::default(),
+            rag: None,
             extra: HashMap::new(),
         }
     }
@@ -109,6 +183,24 @@ impl ToonAgentConfig {
         self
     }
 
+    /// Allow handing conversations off to the given agents
+    pub fn with_handoffs(mut self, handoffs: Vec<String>) -> Self {
+        self.handoffs = handoffs;
+        self
+    }
+
+    /// Set how the agent works towards an answer
+    pub fn with_strategy(mut self, strategy: AgentStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Require the agent's output to conform to a JSON Schema
+    pub fn with_output_schema(mut self, schema: serde_json::Value) -> Self {
+        self.output_schema = Some(schema);
+        self
+    }
+
     /// Encode this config to TOON format
     pub fn to_toon(&self) -> Result<String, ToonConfigError> {
         encode_default(self).map_err(ToonConfigError::from)
@@ -372,6 +464,68 @@ impl ToonMcpConfig {
     }
 }
 
+// ============= Agent Plugin Configuration =============
+
+/// Configuration for an agent plugin: an external process that answers as
+/// an agent over JSON-RPC
+///
+/// See [`crate::agents::plugin`] for the protocol.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToonPluginConfig {
+    /// Name of the agent the plugin provides
+    pub name: String,
+
+    /// Whether this plugin is currently enabled
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// What the agent handles, shown in the agent list and to the router
+    #[serde(default)]
+    pub description: String,
+
+    /// Command to run the plugin (e.g., "python3", "./plugins/summarizer")
+    pub command: String,
+
+    /// Arguments to pass to the command
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Environment variables to set for the plugin
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+
+    /// Timeout in seconds for one call to the plugin
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+}
+
+impl ToonPluginConfig {
+    /// Create a new plugin config with required fields
+    pub fn new(name: impl Into<String>, command: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            enabled: default_true(),
+            description: String::new(),
+            command: command.into(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            timeout_secs: default_timeout(),
+        }
+    }
+
+    /// Parse a plugin config from TOON format
+    pub fn from_toon(toon: &str) -> Result<Self, ToonConfigError> {
+        decode_default(toon).map_err(ToonConfigError::from)
+    }
+}
+
+/// Load the plugin configs in a directory, keyed by name
+///
+/// Plugins are loaded once at startup; they are not hot-reloaded.
+pub fn load_plugins(dir: &Path) -> Result<HashMap<String, ToonPluginConfig>, ToonConfigError> {
+    load_configs_from_dir(dir, "plugins")
+}
+
 // ============= Dynamic Config Aggregate =============
 
 /// Aggregated dynamic configuration from all TOON files
@@ -588,6 +742,12 @@ impl HasName for ToonMcpConfig {
     }
 }
 
+impl HasName for ToonPluginConfig {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
 /// Load all .toon files from a directory into a HashMap keyed by name
 fn load_configs_from_dir<T>(
     dir: &Path,
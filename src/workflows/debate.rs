@@ -0,0 +1,98 @@
+//! Debate orchestration
+//!
+//! A debate workflow asks a panel of agents the same question. Each answers
+//! independently, without seeing the others' answers, and the workflow's
+//! entry agent then judges them: it either writes a final answer from the
+//! best parts of the panel's answers, or selects the best one, which is
+//! returned unchanged.
+//!
+//! ```toml
+//! [workflows.debate]
+//! entry_agent = "orchestrator"
+//! parallel_subagents = true
+//! debate = { agents = ["research", "finance", "sales"], mode = "select" }
+//! ```
+//!
+//! Chat requests with `agent_type: "debate"` run the workflow named
+//! [`DEBATE_WORKFLOW`].
+
+use crate::utils::toml_config::DebateMode;
+
+/// Workflow run for chat requests with `agent_type: "debate"`
+pub const DEBATE_WORKFLOW: &str = "debate";
+
+/// Input for the judge of a debate, holding the question and the panel's
+/// answers as `(agent, answer)` pairs
+pub fn judge_prompt(question: &str, answers: &[(String, String)], mode: DebateMode) -> String {
+    let mut prompt = format!(
+        "{} agents answered the question below independently.\n\nQuestion:\n{}\n",
+        answers.len(),
+        question
+    );
+    for (i, (agent, answer)) in answers.iter().enumerate() {
+        prompt.push_str(&format!(
+            "\nAnswer {} ({}):\n{}\n",
+            i + 1,
+            agent,
+            answer.trim()
+        ));
+    }
+    prompt.push('\n');
+    prompt.push_str(match mode {
+        DebateMode::Synthesize => {
+            "Compare the answers, resolve where they disagree, and write the single best \
+             answer to the question. Reply with that answer only."
+        }
+        DebateMode::Select => {
+            "Decide which answer best answers the question. Reply with its number on the \
+             first line, then one sentence explaining why."
+        }
+    });
+    prompt
+}
+
+/// Index of the answer a judge selected
+///
+/// Reads the first number in the verdict, which must name one of
+/// `answer_count` answers.
+pub fn parse_selection(verdict: &str, answer_count: usize) -> Option<usize> {
+    verdict
+        .split(|c: char| !c.is_ascii_digit())
+        .find(|part| !part.is_empty())
+        .and_then(|number| number.parse::<usize>().ok())
+        .filter(|number| (1..=answer_count).contains(number))
+        .map(|number| number - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answers() -> Vec<(String, String)> {
+        vec![
+            ("optimist".to_string(), "Revenue will grow. ".to_string()),
+            ("skeptic".to_string(), "Revenue will fall.".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_judge_prompt_lists_answers() {
+        let prompt = judge_prompt("Will revenue grow?", &answers(), DebateMode::Select);
+        assert!(prompt.contains("Will revenue grow?"));
+        assert!(prompt.contains("Answer 1 (optimist):\nRevenue will grow.\n"));
+        assert!(prompt.contains("Answer 2 (skeptic):\nRevenue will fall.\n"));
+        assert!(prompt.contains("number on the first line"));
+
+        let prompt = judge_prompt("Will revenue grow?", &answers(), DebateMode::Synthesize);
+        assert!(prompt.contains("single best answer"));
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(parse_selection("2\nIt cites the Q3 numbers.", 2), Some(1));
+        assert_eq!(parse_selection("Answer 1 is best.", 2), Some(0));
+        assert_eq!(parse_selection("Answer 3", 2), None);
+        assert_eq!(parse_selection("Answer 0", 2), None);
+        assert_eq!(parse_selection("Neither", 2), None);
+    }
+}
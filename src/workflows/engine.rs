@@ -3,13 +3,18 @@
 //! Executes declarative workflows by orchestrating agent execution based on
 //! TOML configuration.
 
-use crate::agents::Agent;
+use crate::agents::{router::RouterAgent, Agent, BusEvent};
 use crate::api::handlers::user_agents::resolve_agent;
+use crate::llm::cancellation::run_cancellable;
+use crate::llm::{Judge, Judgment};
 use crate::types::{AgentContext, AgentType, AppError, Result};
-use crate::utils::toml_config::{AgentConfig, WorkflowConfig};
+use crate::utils::toml_config::{AgentConfig, DebateConfig, DebateMode, WorkflowConfig};
+use crate::workflows::debate;
 use crate::AppState;
 use chrono::Utc;
+use futures::future::join_all;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use utoipa::ToSchema;
 
 /// Output from a workflow execution
@@ -17,12 +22,21 @@ use utoipa::ToSchema;
 pub struct WorkflowOutput {
     /// The final response from the workflow
     pub final_response: String,
+    /// The final response parsed as JSON, if the final agent has an output schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_response: Option<serde_json::Value>,
     /// Number of steps executed
     pub steps_executed: usize,
     /// List of agent names that were used
     pub agents_used: Vec<String>,
     /// Detailed reasoning path showing each step
     pub reasoning_path: Vec<WorkflowStep>,
+    /// Events the agents published on the run's message bus, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<BusEvent>,
+    /// Score of the final response, if the workflow has a judge
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub judgment: Option<Judgment>,
 }
 
 /// A single step in the workflow execution
@@ -34,6 +48,9 @@ pub struct WorkflowStep {
     pub input: String,
     /// The output from the agent
     pub output: String,
+    /// The output parsed as JSON, if the agent has an output schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_output: Option<serde_json::Value>,
     /// Unix timestamp when this step was executed
     pub timestamp: i64,
     /// Duration of this step in milliseconds
@@ -41,7 +58,7 @@ pub struct WorkflowStep {
 }
 
 /// Valid agent names for routing
-const VALID_AGENTS: &[&str] = &[
+pub(crate) const VALID_AGENTS: &[&str] = &[
     "product",
     "invoice",
     "sales",
@@ -130,76 +147,79 @@ impl WorkflowEngine {
         let mut current_agent_name = workflow.entry_agent.clone();
         let mut depth = 0;
 
+        // The run's agents share a message bus
+        let bus = context.bus.clone().unwrap_or_default();
+        let context = &AgentContext {
+            bus: Some(bus.clone()),
+            ..context.clone()
+        };
+
+        if let Some(ref debate) = workflow.debate {
+            let mut output = self
+                .execute_debate(workflow, debate, user_input, context)
+                .await?;
+            output.events = bus.events();
+            output.judgment = self.judge_response(workflow, user_input, &output).await;
+            return Ok(output);
+        }
+
         // Execute workflow with depth limiting
         while depth < workflow.max_depth {
-            let step_start = std::time::Instant::now();
-            let timestamp = Utc::now().timestamp();
-
-            // Resolve agent using the 3-tier hierarchy
-            let (user_agent, _source) =
-                match resolve_agent(&self.state, &context.user_id, current_agent_name.clone()).await {
-                    Ok(res) => res,
-                    Err(e) => {
-                        // Try fallback agent if available
-                        if let Some(ref fallback) = workflow.fallback_agent {
-                            tracing::warn!(
-                                "Failed to resolve agent '{}', using fallback '{}'",
-                                current_agent_name,
-                                fallback
-                            );
-                            current_agent_name = fallback.clone();
-                            resolve_agent(&self.state, &context.user_id, fallback.clone()).await?
-                        } else {
-                            return Err(e);
-                        }
+            let (step, agent_type) =
+                match self.state.agent_registry.custom_agent(&current_agent_name) {
+                    Some(agent) => {
+                        self.run_agent_step(
+                            workflow,
+                            &current_agent_name,
+                            agent.as_ref(),
+                            false,
+                            &current_input,
+                            context,
+                        )
+                        .await?
+                    }
+                    None => {
+                        let agent_config = self
+                            .resolve_step_agent(workflow, &mut current_agent_name, context)
+                            .await?;
+                        self.run_step(
+                            workflow,
+                            &current_agent_name,
+                            &agent_config,
+                            &current_input,
+                            context,
+                        )
+                        .await?
                     }
                 };
-
-            // Convert UserAgent to AgentConfig
-            let agent_config = AgentConfig {
-                model: user_agent.model.clone(),
-                system_prompt: user_agent.system_prompt.clone(),
-                tools: user_agent.tools_vec(),
-                max_tool_iterations: user_agent.max_tool_iterations as usize,
-                parallel_tools: user_agent.parallel_tools,
-                extra: std::collections::HashMap::new(),
-            };
-
-            // Create the agent
-            let agent = self
-                .state
-                .agent_registry
-                .create_agent_from_config(&current_agent_name, &agent_config)
-                .await?;
-
-            // Execute the agent
-            let output = agent.execute(&current_input, context).await?;
-            let duration_ms = step_start.elapsed().as_millis() as u64;
-
-            // Record this step
-            steps.push(WorkflowStep {
-                agent_name: current_agent_name.clone(),
-                input: current_input.clone(),
-                output: output.clone(),
-                timestamp,
-                duration_ms,
-            });
+            let output = step.output.clone();
+            steps.push(step);
 
             if !agents_used.contains(&current_agent_name) {
                 agents_used.push(current_agent_name.clone());
             }
 
             // Check if the agent is a router and needs to delegate
-            if agent.agent_type() == AgentType::Router {
+            if agent_type == AgentType::Router {
                 // Router's output should be an agent name
                 // Use robust parsing to handle various output formats
-                let next_agent = Self::parse_routing_decision(&output);
+                let custom: Vec<String> = self
+                    .state
+                    .agent_registry
+                    .custom_agents()
+                    .iter()
+                    .map(|agent| agent.agent_type().as_str().to_string())
+                    .collect();
+                let custom: Vec<&str> = custom.iter().map(String::as_str).collect();
+                let next_agent = RouterAgent::parse_custom_choice(&output, &custom)
+                    .or_else(|| Self::parse_routing_decision(&output));
 
                 if let Some(ref agent_name) = next_agent {
                     // Validate the routed agent exists (check hierarchy)
-                    if resolve_agent(&self.state, &context.user_id, agent_name.clone())
-                        .await
-                        .is_ok()
+                    if self.state.agent_registry.custom_agent(agent_name).is_some()
+                        || resolve_agent(&self.state, &context.owner(), agent_name.clone())
+                            .await
+                            .is_ok()
                     {
                         current_agent_name = agent_name.clone();
                         // Keep the original user input for the routed agent
@@ -234,12 +254,243 @@ impl WorkflowEngine {
             .last()
             .map(|s| s.output.clone())
             .unwrap_or_else(|| "No response generated".to_string());
+        let structured_response = steps.last().and_then(|s| s.structured_output.clone());
+
+        let mut output = WorkflowOutput {
+            final_response,
+            structured_response,
+            steps_executed: steps.len(),
+            agents_used,
+            reasoning_path: steps,
+            events: bus.events(),
+            judgment: None,
+        };
+        output.judgment = self.judge_response(workflow, user_input, &output).await;
+        Ok(output)
+    }
+
+    /// Score the final response with the workflow's judge, if it has one
+    ///
+    /// A judge that fails is logged and leaves the response unscored.
+    async fn judge_response(
+        &self,
+        workflow: &WorkflowConfig,
+        user_input: &str,
+        output: &WorkflowOutput,
+    ) -> Option<Judgment> {
+        let name = workflow.judge.as_ref()?;
+        let config = self.state.config_manager.config();
+        let judge_config = config.judges.get(name)?;
+        let judged = async {
+            Judge::from_config(judge_config, &self.state.provider_registry)
+                .await?
+                .judge(user_input, &output.final_response)
+                .await
+        };
+        match judged.await {
+            Ok(judgment) => Some(judgment),
+            Err(e) => {
+                tracing::warn!("Workflow judge '{}' failed: {}", name, e);
+                None
+            }
+        }
+    }
+
+    /// Resolve the agent named `agent_name` using the 3-tier hierarchy,
+    /// switching to the workflow's fallback agent if it can't be resolved
+    async fn resolve_step_agent(
+        &self,
+        workflow: &WorkflowConfig,
+        agent_name: &mut String,
+        context: &AgentContext,
+    ) -> Result<AgentConfig> {
+        match resolve_agent(&self.state, &context.owner(), agent_name.clone()).await {
+            Ok((config, _source)) => Ok(config),
+            Err(e) => {
+                // Try fallback agent if available
+                let Some(ref fallback) = workflow.fallback_agent else {
+                    return Err(e);
+                };
+                tracing::warn!(
+                    "Failed to resolve agent '{}', using fallback '{}'",
+                    agent_name,
+                    fallback
+                );
+                *agent_name = fallback.clone();
+                let (config, _source) =
+                    resolve_agent(&self.state, &context.owner(), fallback.clone()).await?;
+                Ok(config)
+            }
+        }
+    }
+
+    /// Run one configured agent as a step of `workflow`
+    async fn run_step(
+        &self,
+        workflow: &WorkflowConfig,
+        agent_name: &str,
+        agent_config: &AgentConfig,
+        input: &str,
+        context: &AgentContext,
+    ) -> Result<(WorkflowStep, AgentType)> {
+        // Create the agent
+        let agent = self
+            .state
+            .agent_registry
+            .create_agent_from_config(agent_name, agent_config)
+            .await?;
+        let structured = agent.output_schema().is_some();
+        self.run_agent_step(workflow, agent_name, &agent, structured, input, context)
+            .await
+    }
+
+    /// Run an agent as a step of `workflow`, parsing its output as JSON if
+    /// it is `structured`
+    async fn run_agent_step(
+        &self,
+        workflow: &WorkflowConfig,
+        agent_name: &str,
+        agent: &dyn Agent,
+        structured: bool,
+        input: &str,
+        context: &AgentContext,
+    ) -> Result<(WorkflowStep, AgentType)> {
+        let step_start = std::time::Instant::now();
+        let timestamp = Utc::now().timestamp();
+
+        // Execute the agent; dropping the call on cancellation or timeout
+        // aborts the in-flight provider request
+        let step = run_cancellable(&context.cancellation, agent.execute(input, context));
+        let output = match workflow.step_timeout_secs {
+            Some(secs) => tokio::time::timeout(Duration::from_secs(secs), step)
+                .await
+                .map_err(|_| {
+                    AppError::Cancelled(format!(
+                        "Workflow step '{}' timed out after {}s",
+                        agent_name, secs
+                    ))
+                })??,
+            None => step.await?,
+        };
+        let duration_ms = step_start.elapsed().as_millis() as u64;
+
+        // Agents with an output schema return validated JSON
+        let structured_output = structured
+            .then(|| serde_json::from_str(&output).ok())
+            .flatten();
+
+        let step = WorkflowStep {
+            agent_name: agent_name.to_string(),
+            input: input.to_string(),
+            output,
+            structured_output,
+            timestamp,
+            duration_ms,
+        };
+        Ok((step, agent.agent_type()))
+    }
+
+    /// Resolve an agent by name and run it as a step of `workflow`
+    async fn run_named_step(
+        &self,
+        workflow: &WorkflowConfig,
+        agent_name: &str,
+        input: &str,
+        context: &AgentContext,
+    ) -> Result<(WorkflowStep, AgentType)> {
+        if let Some(agent) = self.state.agent_registry.custom_agent(agent_name) {
+            return self
+                .run_agent_step(workflow, agent_name, agent.as_ref(), false, input, context)
+                .await;
+        }
+        let (agent_config, _source) =
+            resolve_agent(&self.state, &context.owner(), agent_name.to_string()).await?;
+        self.run_step(workflow, agent_name, &agent_config, input, context)
+            .await
+    }
+
+    /// Run a debate: the panel answers the input, then the entry agent
+    /// judges their answers
+    ///
+    /// Panel agents that fail are left out of the debate; it fails only if
+    /// none of them answers.
+    async fn execute_debate(
+        &self,
+        workflow: &WorkflowConfig,
+        debate: &DebateConfig,
+        user_input: &str,
+        context: &AgentContext,
+    ) -> Result<WorkflowOutput> {
+        let panel = debate
+            .agents
+            .iter()
+            .map(|agent_name| self.run_named_step(workflow, agent_name, user_input, context));
+        let results = if workflow.parallel_subagents {
+            join_all(panel).await
+        } else {
+            let mut results = Vec::with_capacity(debate.agents.len());
+            for answer in panel {
+                results.push(answer.await);
+            }
+            results
+        };
+
+        let mut steps = Vec::new();
+        let mut first_error = None;
+        for (agent_name, result) in debate.agents.iter().zip(results) {
+            match result {
+                Ok((step, _)) => steps.push(step),
+                Err(e) => {
+                    tracing::warn!("Debate agent '{}' failed: {}", agent_name, e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        if steps.is_empty() {
+            return Err(first_error
+                .unwrap_or_else(|| AppError::Configuration("Debate has no agents".to_string())));
+        }
+        let answers: Vec<(String, String)> = steps
+            .iter()
+            .map(|step| (step.agent_name.clone(), step.output.clone()))
+            .collect();
+
+        // The judge sees the question and every answer
+        let prompt = debate::judge_prompt(user_input, &answers, debate.mode);
+        let (verdict, _) = self
+            .run_named_step(workflow, &workflow.entry_agent, &prompt, context)
+            .await?;
+
+        let (final_response, structured_response) = match debate.mode {
+            DebateMode::Synthesize => (verdict.output.clone(), verdict.structured_output.clone()),
+            DebateMode::Select => match debate::parse_selection(&verdict.output, steps.len()) {
+                Some(selected) => (
+                    steps[selected].output.clone(),
+                    steps[selected].structured_output.clone(),
+                ),
+                None => {
+                    tracing::warn!(
+                        "Debate judge '{}' did not select an answer",
+                        workflow.entry_agent
+                    );
+                    (verdict.output.clone(), None)
+                }
+            },
+        };
+
+        steps.push(verdict);
+        let mut agents_used: Vec<String> = steps.iter().map(|s| s.agent_name.clone()).collect();
+        agents_used.dedup();
 
         Ok(WorkflowOutput {
             final_response,
+            structured_response,
             steps_executed: steps.len(),
             agents_used,
             reasoning_path: steps,
+            // Set by execute_workflow, which owns the run's bus
+            events: Vec::new(),
+            judgment: None,
         })
     }
 
@@ -307,6 +558,9 @@ mod tests {
                 top_p: None,
                 frequency_penalty: None,
                 presence_penalty: None,
+                context_window: None,
+                summarize_overflow: true,
+                max_continuations: 2,
             },
         );
 
@@ -319,6 +573,20 @@ mod tests {
                 tools: vec![],
                 max_tool_iterations: 1,
                 parallel_tools: false,
+                guardrail_policy: None,
+                handoffs: Vec::new(),
+                strategy: Default::default(),
+                output_schema: None,
+                memory: Default::default(),
+                tool_permissions: Default::default(),
+                answer_cache: Default::default(),
+                reflection: Default::default(),
+                limits: Default::default(),
+                personas: Default::default(),
+                requires_approval: false,
+                bus: Default::default(),
+                parameter_limits: Default::default(),
+                rag: None,
                 extra: HashMap::new(),
             },
         );
@@ -330,6 +598,20 @@ mod tests {
                 tools: vec![],
                 max_tool_iterations: 10,
                 parallel_tools: false,
+                guardrail_policy: None,
+                handoffs: Vec::new(),
+                strategy: Default::default(),
+                output_schema: None,
+                memory: Default::default(),
+                tool_permissions: Default::default(),
+                answer_cache: Default::default(),
+                reflection: Default::default(),
+                limits: Default::default(),
+                personas: Default::default(),
+                requires_approval: false,
+                bus: Default::default(),
+                parameter_limits: Default::default(),
+                rag: None,
                 extra: HashMap::new(),
             },
         );
@@ -341,6 +623,20 @@ mod tests {
                 tools: vec![],
                 max_tool_iterations: 5,
                 parallel_tools: false,
+                guardrail_policy: None,
+                handoffs: Vec::new(),
+                strategy: Default::default(),
+                output_schema: None,
+                memory: Default::default(),
+                tool_permissions: Default::default(),
+                answer_cache: Default::default(),
+                reflection: Default::default(),
+                limits: Default::default(),
+                personas: Default::default(),
+                requires_approval: false,
+                bus: Default::default(),
+                parameter_limits: Default::default(),
+                rag: None,
                 extra: HashMap::new(),
             },
         );
@@ -354,6 +650,9 @@ mod tests {
                 max_depth: 3,
                 max_iterations: 5,
                 parallel_subagents: false,
+                step_timeout_secs: None,
+                debate: None,
+                judge: None,
             },
         );
         workflows.insert(
@@ -364,6 +663,9 @@ mod tests {
                 max_depth: 3,
                 max_iterations: 10,
                 parallel_subagents: true,
+                step_timeout_secs: None,
+                debate: None,
+                judge: None,
             },
         );
 
@@ -378,6 +680,23 @@ mod tests {
             agents,
             workflows,
             rag: RagConfig::default(),
+            guardrails: Default::default(),
+            budgets: Default::default(),
+            archive: Default::default(),
+            schedules: Default::default(),
+            roles: Default::default(),
+            judges: Default::default(),
+            canaries: Default::default(),
+            remote_agents: Default::default(),
+            titles: Default::default(),
+            maintenance: Default::default(),
+            files: Default::default(),
+            batch: Default::default(),
+            warmup: Default::default(),
+            health: Default::default(),
+            jobs: Default::default(),
+            encryption: Default::default(),
+            redis: None,
         }
     }
 
@@ -424,8 +743,22 @@ mod tests {
                 900,
                 604800,
             )),
+            #[cfg(feature = "mcp")]
             mcp_registry: None,
             deploy_registry: crate::api::handlers::deploy::new_deploy_registry(),
+            hooks: Default::default(),
+            loaders: Default::default(),
+            generations: Default::default(),
+            maintenance: Default::default(),
+            rate_limits: Default::default(),
+            warmup: Default::default(),
+            health: Default::default(),
+            service_tokens: Default::default(),
+            sessions: crate::db::sessions::SessionStore::Postgres(
+                futures::executor::block_on(crate::db::PostgresClient::new_memory())
+                    .unwrap()
+                    .pool,
+            ),
         };
 
         let engine = WorkflowEngine::new(state);
@@ -478,8 +811,22 @@ mod tests {
                 900,
                 604800,
             )),
+            #[cfg(feature = "mcp")]
             mcp_registry: None,
             deploy_registry: crate::api::handlers::deploy::new_deploy_registry(),
+            hooks: Default::default(),
+            loaders: Default::default(),
+            generations: Default::default(),
+            maintenance: Default::default(),
+            rate_limits: Default::default(),
+            warmup: Default::default(),
+            health: Default::default(),
+            service_tokens: Default::default(),
+            sessions: crate::db::sessions::SessionStore::Postgres(
+                futures::executor::block_on(crate::db::PostgresClient::new_memory())
+                    .unwrap()
+                    .pool,
+            ),
         };
 
         let engine = WorkflowEngine::new(state);
@@ -532,8 +879,22 @@ mod tests {
                 900,
                 604800,
             )),
+            #[cfg(feature = "mcp")]
             mcp_registry: None,
             deploy_registry: crate::api::handlers::deploy::new_deploy_registry(),
+            hooks: Default::default(),
+            loaders: Default::default(),
+            generations: Default::default(),
+            maintenance: Default::default(),
+            rate_limits: Default::default(),
+            warmup: Default::default(),
+            health: Default::default(),
+            service_tokens: Default::default(),
+            sessions: crate::db::sessions::SessionStore::Postgres(
+                futures::executor::block_on(crate::db::PostgresClient::new_memory())
+                    .unwrap()
+                    .pool,
+            ),
         };
 
         let engine = WorkflowEngine::new(state);
@@ -555,6 +916,7 @@ mod tests {
     fn test_workflow_output_serialization() {
         let output = WorkflowOutput {
             final_response: "Test response".to_string(),
+            structured_response: None,
             steps_executed: 2,
             agents_used: vec!["router".to_string(), "product".to_string()],
             reasoning_path: vec![
@@ -562,6 +924,7 @@ mod tests {
                     agent_name: "router".to_string(),
                     input: "What products do we have?".to_string(),
                     output: "product".to_string(),
+                    structured_output: None,
                     timestamp: 1702500000,
                     duration_ms: 150,
                 },
@@ -569,10 +932,13 @@ mod tests {
                     agent_name: "product".to_string(),
                     input: "What products do we have?".to_string(),
                     output: "Test response".to_string(),
+                    structured_output: None,
                     timestamp: 1702500001,
                     duration_ms: 500,
                 },
             ],
+            events: Vec::new(),
+            judgment: None,
         };
 
         let json = serde_json::to_string(&output).unwrap();
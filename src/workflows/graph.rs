@@ -0,0 +1,366 @@
+//! Workflow graphs
+//!
+//! Renders a workflow definition, or a completed run of one, as a Mermaid
+//! flowchart or a Graphviz DOT digraph for documentation and debugging.
+//!
+//! A definition graph shows the agents a request can pass through: the
+//! entry agent, the agents a router can route to, the fallback agent, or a
+//! debate's panel and judge. A run graph shows the steps the run actually
+//! took, each annotated with its duration, with edges labelled by the output
+//! passed along and the judge's score, if any, on the output.
+
+use crate::types::AgentType;
+use crate::utils::toml_config::WorkflowConfig;
+use crate::workflows::engine::{WorkflowOutput, VALID_AGENTS};
+use serde::Deserialize;
+
+/// Characters of a step's output shown on the edge leaving it.
+const PREVIEW_CHARS: usize = 40;
+
+/// Text format a workflow graph is rendered in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    /// Mermaid flowchart
+    #[default]
+    Mermaid,
+    /// Graphviz DOT digraph
+    #[serde(alias = "graphviz")]
+    Dot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    /// Where the request enters or the response leaves
+    Terminal,
+    Agent,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    id: String,
+    label: String,
+    kind: NodeKind,
+}
+
+#[derive(Debug, Clone)]
+struct Edge {
+    from: String,
+    to: String,
+    label: Option<String>,
+    /// Taken only in some runs (routing, fallback)
+    dashed: bool,
+}
+
+/// Nodes and edges of a workflow definition or run.
+#[derive(Debug, Clone, Default)]
+pub struct WorkflowGraph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+impl WorkflowGraph {
+    /// Graph of the agents a request to `workflow` can pass through.
+    pub fn definition(workflow: &WorkflowConfig) -> Self {
+        let mut graph = Self::default();
+        graph.node("input", "Input", NodeKind::Terminal);
+        graph.node("output", "Output", NodeKind::Terminal);
+
+        if let Some(debate) = &workflow.debate {
+            let judge = format!("{} (judge, {})", workflow.entry_agent, debate.mode.as_str());
+            graph.node("judge", &judge, NodeKind::Agent);
+            for (i, agent) in debate.agents.iter().enumerate() {
+                let id = format!("panel_{}", i);
+                graph.node(&id, agent, NodeKind::Agent);
+                graph.edge("input", &id, None, false);
+                graph.edge(&id, "judge", Some("answer"), false);
+            }
+            graph.edge("judge", "output", None, false);
+            return graph;
+        }
+
+        let entry = agent_id(&workflow.entry_agent);
+        let label = format!("{} (entry)", workflow.entry_agent);
+        graph.node(&entry, &label, NodeKind::Agent);
+        graph.edge("input", &entry, None, false);
+
+        if AgentType::from_string(&workflow.entry_agent) == AgentType::Router {
+            for target in VALID_AGENTS.iter().filter(|a| **a != workflow.entry_agent) {
+                let id = agent_id(target);
+                graph.node(&id, target, NodeKind::Agent);
+                graph.edge(&entry, &id, Some("routes"), true);
+                graph.edge(&id, "output", None, false);
+            }
+        } else {
+            graph.edge(&entry, "output", None, false);
+        }
+
+        if let Some(fallback) = &workflow.fallback_agent {
+            let id = agent_id(fallback);
+            if !graph.has_node(&id) {
+                graph.node(&id, fallback, NodeKind::Agent);
+                graph.edge(&id, "output", None, false);
+            }
+            graph.edge(&entry, &id, Some("fallback"), true);
+        }
+        graph
+    }
+
+    /// Graph of the steps a completed run of `workflow` took.
+    pub fn run(workflow: &WorkflowConfig, output: &WorkflowOutput) -> Self {
+        let mut graph = Self::default();
+        let steps = &output.reasoning_path;
+        let total_ms: u64 = steps.iter().map(|step| step.duration_ms).sum();
+        graph.node("input", "Input", NodeKind::Terminal);
+        let mut label = format!("Output\n{} ms total", total_ms);
+        if let Some(judgment) = &output.judgment {
+            label.push_str(&format!("\nscore {:.1}/10", judgment.score));
+        }
+        graph.node("output", &label, NodeKind::Terminal);
+        for (i, step) in steps.iter().enumerate() {
+            let label = format!("{}\n{} ms", step.agent_name, step.duration_ms);
+            graph.node(&format!("step_{}", i), &label, NodeKind::Agent);
+        }
+
+        let Some(last) = steps.len().checked_sub(1) else {
+            graph.edge("input", "output", None, false);
+            return graph;
+        };
+        let judge = format!("step_{}", last);
+        if workflow.debate.is_some() && last > 0 {
+            // The panel answers the input and the last step judges the answers
+            for (i, step) in steps[..last].iter().enumerate() {
+                let id = format!("step_{}", i);
+                graph.edge("input", &id, None, false);
+                graph.edge(&id, &judge, Some(&preview(&step.output)), false);
+            }
+        } else {
+            graph.edge("input", "step_0", None, false);
+            for (i, step) in steps[..last].iter().enumerate() {
+                let (from, to) = (format!("step_{}", i), format!("step_{}", i + 1));
+                graph.edge(&from, &to, Some(&preview(&step.output)), false);
+            }
+        }
+        graph.edge(&judge, "output", Some(&preview(&steps[last].output)), false);
+        graph
+    }
+
+    /// Render the graph as text in `format`.
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Mermaid => self.to_mermaid(),
+            GraphFormat::Dot => self.to_dot(),
+        }
+    }
+
+    fn node(&mut self, id: &str, label: &str, kind: NodeKind) {
+        self.nodes.push(Node {
+            id: id.to_string(),
+            label: label.to_string(),
+            kind,
+        });
+    }
+
+    fn has_node(&self, id: &str) -> bool {
+        self.nodes.iter().any(|node| node.id == id)
+    }
+
+    fn edge(&mut self, from: &str, to: &str, label: Option<&str>, dashed: bool) {
+        self.edges.push(Edge {
+            from: from.to_string(),
+            to: to.to_string(),
+            label: label.filter(|l| !l.is_empty()).map(str::to_string),
+            dashed,
+        });
+    }
+
+    fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart TD\n");
+        for node in &self.nodes {
+            let label = mermaid_escape(&node.label);
+            match node.kind {
+                NodeKind::Terminal => out.push_str(&format!("    {}([\"{}\"])\n", node.id, label)),
+                NodeKind::Agent => out.push_str(&format!("    {}[\"{}\"]\n", node.id, label)),
+            }
+        }
+        for edge in &self.edges {
+            let arrow = if edge.dashed { "-.->" } else { "-->" };
+            match &edge.label {
+                Some(label) => out.push_str(&format!(
+                    "    {} {}|\"{}\"| {}\n",
+                    edge.from,
+                    arrow,
+                    mermaid_escape(label),
+                    edge.to
+                )),
+                None => out.push_str(&format!("    {} {} {}\n", edge.from, arrow, edge.to)),
+            }
+        }
+        out
+    }
+
+    fn to_dot(&self) -> String {
+        let mut out = String::from("digraph workflow {\n    rankdir=TB;\n");
+        for node in &self.nodes {
+            let shape = match node.kind {
+                NodeKind::Terminal => "ellipse",
+                NodeKind::Agent => "box",
+            };
+            out.push_str(&format!(
+                "    {} [label=\"{}\", shape={}];\n",
+                node.id,
+                dot_escape(&node.label),
+                shape
+            ));
+        }
+        for edge in &self.edges {
+            let mut attributes = Vec::new();
+            if let Some(label) = &edge.label {
+                attributes.push(format!("label=\"{}\"", dot_escape(label)));
+            }
+            if edge.dashed {
+                attributes.push("style=dashed".to_string());
+            }
+            match attributes.is_empty() {
+                true => out.push_str(&format!("    {} -> {};\n", edge.from, edge.to)),
+                false => out.push_str(&format!(
+                    "    {} -> {} [{}];\n",
+                    edge.from,
+                    edge.to,
+                    attributes.join(", ")
+                )),
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Node ID of an agent, safe to use unquoted in both formats.
+fn agent_id(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("agent_{}", name)
+}
+
+/// First line of a step's output, shortened for an edge label.
+fn preview(output: &str) -> String {
+    let line = output.trim().lines().next().unwrap_or_default();
+    if line.chars().count() <= PREVIEW_CHARS {
+        return line.to_string();
+    }
+    let mut preview: String = line.chars().take(PREVIEW_CHARS).collect();
+    preview.push('…');
+    preview
+}
+
+fn mermaid_escape(text: &str) -> String {
+    text.replace('"', "#quot;").replace('\n', "<br/>")
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::toml_config::{DebateConfig, DebateMode};
+    use crate::workflows::WorkflowStep;
+
+    fn workflow(entry: &str, fallback: Option<&str>) -> WorkflowConfig {
+        WorkflowConfig {
+            entry_agent: entry.to_string(),
+            fallback_agent: fallback.map(str::to_string),
+            max_depth: 3,
+            max_iterations: 5,
+            parallel_subagents: false,
+            step_timeout_secs: None,
+            debate: None,
+            judge: None,
+        }
+    }
+
+    fn step(agent: &str, output: &str, duration_ms: u64) -> WorkflowStep {
+        WorkflowStep {
+            agent_name: agent.to_string(),
+            input: "What do we sell?".to_string(),
+            output: output.to_string(),
+            structured_output: None,
+            timestamp: 0,
+            duration_ms,
+        }
+    }
+
+    #[test]
+    fn test_definition_graph_shows_routes_and_fallback() {
+        let graph = WorkflowGraph::definition(&workflow("router", Some("orchestrator")));
+
+        let mermaid = graph.render(GraphFormat::Mermaid);
+        assert!(mermaid.starts_with("flowchart TD\n"));
+        assert!(mermaid.contains("    agent_router[\"router (entry)\"]\n"));
+        assert!(mermaid.contains("    agent_router -.->|\"routes\"| agent_product\n"));
+        assert!(mermaid.contains("    agent_router -.->|\"fallback\"| agent_orchestrator\n"));
+        // The fallback is also a routing target, so it appears once
+        assert_eq!(mermaid.matches("agent_orchestrator[").count(), 1);
+
+        let dot = graph.render(GraphFormat::Dot);
+        assert!(dot.starts_with("digraph workflow {"));
+        assert!(
+            dot.contains("    agent_router -> agent_product [label=\"routes\", style=dashed];\n")
+        );
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn test_definition_graph_of_debate() {
+        let mut debate = workflow("orchestrator", None);
+        debate.debate = Some(DebateConfig {
+            agents: vec!["research".to_string(), "finance".to_string()],
+            mode: DebateMode::Select,
+        });
+
+        let mermaid = WorkflowGraph::definition(&debate).render(GraphFormat::Mermaid);
+        assert!(mermaid.contains("judge[\"orchestrator (judge, select)\"]"));
+        assert!(mermaid.contains("    panel_1 -->|\"answer\"| judge\n"));
+        assert!(mermaid.contains("    judge --> output\n"));
+    }
+
+    #[test]
+    fn test_run_graph_annotates_timings_and_outputs() {
+        let output = WorkflowOutput {
+            final_response: "We sell \"widgets\"".to_string(),
+            structured_response: None,
+            steps_executed: 2,
+            agents_used: vec!["router".to_string(), "product".to_string()],
+            reasoning_path: vec![
+                step("router", "product", 120),
+                step("product", "We sell \"widgets\"\nand more", 880),
+            ],
+            events: Vec::new(),
+            judgment: None,
+        };
+        let graph = WorkflowGraph::run(&workflow("router", None), &output);
+
+        let mermaid = graph.render(GraphFormat::Mermaid);
+        assert!(mermaid.contains("    step_0[\"router<br/>120 ms\"]\n"));
+        assert!(mermaid.contains("    output([\"Output<br/>1000 ms total\"])\n"));
+        assert!(mermaid.contains("    step_0 -->|\"product\"| step_1\n"));
+        assert!(mermaid.contains("    step_1 -->|\"We sell #quot;widgets#quot;\"| output\n"));
+
+        let dot = graph.render(GraphFormat::Dot);
+        assert!(dot.contains("    step_1 [label=\"product\\n880 ms\", shape=box];\n"));
+        assert!(dot.contains("    step_1 -> output [label=\"We sell \\\"widgets\\\"\"];\n"));
+    }
+
+    #[test]
+    fn test_preview_shortens_long_output() {
+        let long = "a".repeat(100);
+        assert_eq!(preview(&long).chars().count(), PREVIEW_CHARS + 1);
+        assert_eq!(preview("  short\nsecond line"), "short");
+    }
+}
@@ -10,6 +10,8 @@
 //! - Set execution limits (depth, iterations)
 //! - Enable parallel sub-agent execution
 //! - Configure fallback behaviors
+//! - Have several agents debate a question before a judge decides
+//! - Render a workflow, or a run of one, as a Mermaid or Graphviz graph
 //!
 //! # Configuration
 //!
@@ -27,6 +29,10 @@
 //! max_depth = 3
 //! max_iterations = 10
 //! parallel_subagents = true
+//!
+//! [workflows.debate]
+//! entry_agent = "orchestrator"
+//! debate = { agents = ["research", "finance"], mode = "synthesize" }
 //! ```
 //!
 //! # Usage
@@ -50,6 +56,9 @@
 //! - `steps` - Detailed log of each workflow step
 //! - `total_tokens` - Aggregate token usage
 
+pub mod debate;
 pub mod engine;
+pub mod graph;
 
 pub use engine::{WorkflowEngine, WorkflowOutput, WorkflowStep};
+pub use graph::{GraphFormat, WorkflowGraph};
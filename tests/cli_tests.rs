@@ -469,7 +469,7 @@ mod output_tests {
 
 #[cfg(test)]
 mod init_tests {
-    use ares::cli::init::{InitConfig, InitResult};
+    use ares::cli::init::{InitConfig, InitFeatures, InitResult};
     use std::path::PathBuf;
 
     #[test]
@@ -482,6 +482,10 @@ mod init_tests {
             provider: "ollama".to_string(),
             host: "127.0.0.1".to_string(),
             port: 3000,
+            template: None,
+            model: None,
+            ollama_url: "http://localhost:11434".to_string(),
+            features: InitFeatures::default(),
         };
 
         assert_eq!(config.path, PathBuf::from("/tmp/test"));
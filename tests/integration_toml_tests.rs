@@ -40,6 +40,9 @@ fn create_test_config() -> ares::utils::toml_config::AresConfig {
             top_p: None,
             frequency_penalty: None,
             presence_penalty: None,
+            context_window: None,
+            summarize_overflow: true,
+            max_continuations: 2,
         },
     );
 
@@ -50,6 +53,8 @@ fn create_test_config() -> ares::utils::toml_config::AresConfig {
             enabled: true,
             description: Some("Calculator tool".to_string()),
             timeout_secs: 10,
+            cost_per_call: None,
+            requires_approval: false,
             extra: HashMap::new(),
         },
     );
@@ -63,6 +68,20 @@ fn create_test_config() -> ares::utils::toml_config::AresConfig {
             system_prompt: Some("You are a test agent.".to_string()),
             max_tool_iterations: 10,
             parallel_tools: false,
+            guardrail_policy: None,
+            handoffs: Vec::new(),
+            strategy: Default::default(),
+            output_schema: None,
+            memory: Default::default(),
+            tool_permissions: Default::default(),
+            answer_cache: Default::default(),
+            reflection: Default::default(),
+            limits: Default::default(),
+            personas: Default::default(),
+            requires_approval: false,
+            bus: Default::default(),
+            parameter_limits: Default::default(),
+            rag: None,
             extra: HashMap::new(),
         },
     );
@@ -74,6 +93,20 @@ fn create_test_config() -> ares::utils::toml_config::AresConfig {
             system_prompt: Some("You are a fallback agent.".to_string()),
             max_tool_iterations: 10,
             parallel_tools: false,
+            guardrail_policy: None,
+            handoffs: Vec::new(),
+            strategy: Default::default(),
+            output_schema: None,
+            memory: Default::default(),
+            tool_permissions: Default::default(),
+            answer_cache: Default::default(),
+            reflection: Default::default(),
+            limits: Default::default(),
+            personas: Default::default(),
+            requires_approval: false,
+            bus: Default::default(),
+            parameter_limits: Default::default(),
+            rag: None,
             extra: HashMap::new(),
         },
     );
@@ -87,6 +120,9 @@ fn create_test_config() -> ares::utils::toml_config::AresConfig {
             max_depth: 5,
             max_iterations: 5,
             parallel_subagents: false,
+            step_timeout_secs: None,
+            debate: None,
+            judge: None,
         },
     );
 
@@ -97,6 +133,8 @@ fn create_test_config() -> ares::utils::toml_config::AresConfig {
             jwt_access_expiry: 900,
             jwt_refresh_expiry: 604800,
             api_key_env: "TEST_API_KEY".to_string(),
+            mfa: Default::default(),
+            service_tokens: Default::default(),
         },
         database: DatabaseConfig::default(),
         config: DynamicConfigPaths::default(),
@@ -106,6 +144,23 @@ fn create_test_config() -> ares::utils::toml_config::AresConfig {
         agents,
         workflows,
         rag: RagConfig::default(),
+        guardrails: Default::default(),
+        budgets: Default::default(),
+        archive: Default::default(),
+        schedules: Default::default(),
+        roles: Default::default(),
+        judges: Default::default(),
+        canaries: Default::default(),
+        remote_agents: Default::default(),
+        titles: Default::default(),
+        maintenance: Default::default(),
+        files: Default::default(),
+        batch: Default::default(),
+        warmup: Default::default(),
+        health: Default::default(),
+        jobs: Default::default(),
+        encryption: Default::default(),
+        redis: None,
     }
 }
 
@@ -264,6 +319,9 @@ fn test_circular_reference_rejected() {
             max_depth: 5,
             max_iterations: 5,
             parallel_subagents: false,
+            step_timeout_secs: None,
+            debate: None,
+            judge: None,
         },
     );
 
@@ -295,6 +353,20 @@ fn test_missing_reference_rejected() {
             system_prompt: None,
             max_tool_iterations: 10,
             parallel_tools: false,
+            guardrail_policy: None,
+            handoffs: Vec::new(),
+            strategy: Default::default(),
+            output_schema: None,
+            memory: Default::default(),
+            tool_permissions: Default::default(),
+            answer_cache: Default::default(),
+            reflection: Default::default(),
+            limits: Default::default(),
+            personas: Default::default(),
+            requires_approval: false,
+            bus: Default::default(),
+            parameter_limits: Default::default(),
+            rag: None,
             extra: HashMap::new(),
         },
     );
@@ -322,6 +394,20 @@ fn test_tool_filtering_in_agent() {
         system_prompt: None,
         max_tool_iterations: 10,
         parallel_tools: false,
+        guardrail_policy: None,
+        handoffs: Vec::new(),
+        strategy: Default::default(),
+        output_schema: None,
+        memory: Default::default(),
+        tool_permissions: Default::default(),
+        answer_cache: Default::default(),
+        reflection: Default::default(),
+        limits: Default::default(),
+        personas: Default::default(),
+        requires_approval: false,
+        bus: Default::default(),
+        parameter_limits: Default::default(),
+        rag: None,
         extra: HashMap::new(),
     };
 
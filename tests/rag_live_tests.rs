@@ -38,7 +38,7 @@ use ares::{
     rag::{
         chunker::TextChunker,
         embeddings::{EmbeddingModelType, EmbeddingService},
-        reranker::{Reranker, RerankerConfig, RerankerModelType},
+        reranker::{CrossEncoderReranker, Reranker, RerankerConfig, RerankerModelType},
         search::{HybridWeights, SearchEngine},
     },
     types::{Document, DocumentMetadata},
@@ -576,7 +576,7 @@ async fn test_live_reranking() {
         ..Default::default()
     };
 
-    let reranker = Reranker::new(config);
+    let reranker = CrossEncoderReranker::new(config);
 
     let query = "What programming language focuses on memory safety?";
 
@@ -638,7 +638,7 @@ async fn test_live_full_rag_pipeline() {
         show_download_progress: true,
         ..Default::default()
     };
-    let reranker = Reranker::new(reranker_config);
+    let reranker = CrossEncoderReranker::new(reranker_config);
 
     let collection = format!("test_pipeline_{}", uuid::Uuid::new_v4());
 
@@ -48,6 +48,20 @@ fn test_toon_agent_roundtrip() {
         tools: vec!["calculator".to_string(), "web_search".to_string()],
         max_tool_iterations: 10,
         parallel_tools: true,
+        guardrail_policy: None,
+        handoffs: Vec::new(),
+        strategy: Default::default(),
+        output_schema: None,
+        memory: Default::default(),
+        tool_permissions: Default::default(),
+        answer_cache: Default::default(),
+        reflection: Default::default(),
+        limits: Default::default(),
+        personas: Default::default(),
+        requires_approval: false,
+        bus: Default::default(),
+        parameter_limits: Default::default(),
+        rag: None,
         extra: std::collections::HashMap::new(),
     };
 
@@ -139,6 +153,20 @@ fn test_dynamic_config_manager_load_from_dirs() {
         tools: vec![],
         max_tool_iterations: 5,
         parallel_tools: false,
+        guardrail_policy: None,
+        handoffs: Vec::new(),
+        strategy: Default::default(),
+        output_schema: None,
+        memory: Default::default(),
+        tool_permissions: Default::default(),
+        answer_cache: Default::default(),
+        reflection: Default::default(),
+        limits: Default::default(),
+        personas: Default::default(),
+        requires_approval: false,
+        bus: Default::default(),
+        parameter_limits: Default::default(),
+        rag: None,
         extra: std::collections::HashMap::new(),
     };
     let agent_toon = encode_default(&agent).expect("Failed to encode agent");
@@ -196,6 +224,20 @@ fn test_dynamic_config_manager_list_methods() {
             tools: vec![],
             max_tool_iterations: 5,
             parallel_tools: false,
+            guardrail_policy: None,
+            handoffs: Vec::new(),
+            strategy: Default::default(),
+            output_schema: None,
+            memory: Default::default(),
+            tool_permissions: Default::default(),
+            answer_cache: Default::default(),
+            reflection: Default::default(),
+            limits: Default::default(),
+            personas: Default::default(),
+            requires_approval: false,
+            bus: Default::default(),
+            parameter_limits: Default::default(),
+            rag: None,
             extra: std::collections::HashMap::new(),
         };
         let toon = encode_default(&agent).expect("Failed to encode");
@@ -315,6 +357,20 @@ fn test_toon_config_with_extra_fields() {
             extra.insert("custom_number".to_string(), serde_json::json!(42));
             extra
         },
+        guardrail_policy: None,
+        handoffs: Vec::new(),
+        strategy: Default::default(),
+        output_schema: None,
+        memory: Default::default(),
+        tool_permissions: Default::default(),
+        answer_cache: Default::default(),
+        reflection: Default::default(),
+        limits: Default::default(),
+        personas: Default::default(),
+        requires_approval: false,
+        bus: Default::default(),
+        parameter_limits: Default::default(),
+        rag: None,
     };
 
     let toon = encode_default(&agent).expect("Failed to encode agent with extra fields");
@@ -385,6 +441,20 @@ fn test_dynamic_config_validation_with_workflows() {
         tools: vec![],
         max_tool_iterations: 5,
         parallel_tools: false,
+        guardrail_policy: None,
+        handoffs: Vec::new(),
+        strategy: Default::default(),
+        output_schema: None,
+        memory: Default::default(),
+        tool_permissions: Default::default(),
+        answer_cache: Default::default(),
+        reflection: Default::default(),
+        limits: Default::default(),
+        personas: Default::default(),
+        requires_approval: false,
+        bus: Default::default(),
+        parameter_limits: Default::default(),
+        rag: None,
         extra: std::collections::HashMap::new(),
     };
     std::fs::write(
@@ -123,6 +123,12 @@ pub async fn fetch_workflows(base_url: &str, token: &str) -> Result<Vec<Workflow
     fetch_with_auth(&url, Some(token.to_string())).await
 }
 
+/// Fetch the projects of the user's space (requires auth)
+pub async fn fetch_projects(base_url: &str, token: &str) -> Result<Vec<Project>, String> {
+    let url = format!("{}/api/projects", base_url);
+    fetch_with_auth(&url, Some(token.to_string())).await
+}
+
 /// Send a chat message
 pub async fn send_chat(
     base_url: &str,
@@ -130,12 +136,14 @@ pub async fn send_chat(
     message: &str,
     context_id: Option<String>,
     agent_type: Option<String>,
+    project_id: Option<String>,
 ) -> Result<ChatResponse, String> {
     let url = format!("{}/api/chat", base_url);
     let body = ChatRequest {
         message: message.to_string(),
         context_id,
         agent_type,
+        project_id,
     };
     post_with_auth::<_, ChatResponse>(&url, &body, Some(token.to_string())).await
 }
@@ -146,6 +154,25 @@ pub async fn fetch_memory(base_url: &str, token: &str) -> Result<UserMemory, Str
     fetch_with_auth(&url, Some(token.to_string())).await
 }
 
+/// Fetch the agent runs waiting for the user's approval
+pub async fn fetch_approvals(base_url: &str, token: &str) -> Result<Vec<PendingApproval>, String> {
+    let url = format!("{}/api/approvals", base_url);
+    fetch_with_auth(&url, Some(token.to_string())).await
+}
+
+/// Approve or reject a paused run's tool calls, returning the resumed run's response
+pub async fn decide_approval(
+    base_url: &str,
+    token: &str,
+    id: &str,
+    approve: bool,
+    reason: Option<String>,
+) -> Result<ChatResponse, String> {
+    let url = format!("{}/api/approvals/{}", base_url, id);
+    let body = ApprovalRequest { approve, reason };
+    post_with_auth::<_, ChatResponse>(&url, &body, Some(token.to_string())).await
+}
+
 /// Load agents into app state
 pub fn load_agents(state: AppState) {
     spawn_local(async move {
@@ -170,6 +197,32 @@ pub fn load_workflows(state: AppState) {
     });
 }
 
+/// Load projects into app state (requires auth)
+pub fn load_projects(state: AppState) {
+    spawn_local(async move {
+        let base = state.api_base.get_untracked();
+        if let Some(token) = state.token.get_untracked() {
+            match fetch_projects(&base, &token).await {
+                Ok(projects) => state.projects.set(projects),
+                Err(e) => tracing::error!("Failed to load projects: {}", e),
+            }
+        }
+    });
+}
+
+/// Load pending approvals into app state (requires auth)
+pub fn load_approvals(state: AppState) {
+    spawn_local(async move {
+        let base = state.api_base.get_untracked();
+        if let Some(token) = state.token.get_untracked() {
+            match fetch_approvals(&base, &token).await {
+                Ok(approvals) => state.approvals.set(approvals),
+                Err(e) => tracing::error!("Failed to load approvals: {}", e),
+            }
+        }
+    });
+}
+
 /// Stream chat response using fetch + ReadableStream
 /// This allows POST-based SSE streaming in WASM
 /// 
@@ -184,6 +237,7 @@ pub async fn stream_chat<F>(
     message: &str,
     context_id: Option<String>,
     agent_type: Option<String>,
+    project_id: Option<String>,
     mut on_event: F,
 ) -> Result<(), String>
 where
@@ -196,6 +250,7 @@ where
         message: message.to_string(),
         context_id,
         agent_type,
+        project_id,
     };
     let body_json = serde_json::to_string(&body)
         .map_err(|e| format!("Failed to serialize request: {}", e))?;
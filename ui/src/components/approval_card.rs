@@ -0,0 +1,116 @@
+//! Approval card component
+
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use crate::api::decide_approval;
+use crate::state::AppState;
+use crate::types::{Message, MessageRole, PendingApproval};
+
+/// A paused agent run with its tool calls, to approve or reject
+#[component]
+pub fn ApprovalCard(approval: PendingApproval) -> impl IntoView {
+    let state = expect_context::<AppState>();
+    let reason = RwSignal::new(String::new());
+    let is_deciding = RwSignal::new(false);
+    let id = approval.id.clone();
+
+    let decide = move |approve: bool| {
+        if is_deciding.get() {
+            return;
+        }
+        is_deciding.set(true);
+        let state = state.clone();
+        let id = id.clone();
+        let reason = Some(reason.get().trim().to_string()).filter(|r| !r.is_empty());
+
+        spawn_local(async move {
+            let base_url = state.api_base.get_untracked();
+            let token = state.token.get_untracked().unwrap_or_default();
+            match decide_approval(&base_url, &token, &id, approve, reason).await {
+                Ok(response) => {
+                    // The resumed run may pause again on another approval
+                    state.approvals.update(|approvals| {
+                        approvals.retain(|a| a.id != id);
+                        approvals.extend(response.approval.clone());
+                    });
+                    if response.approval.is_none()
+                        && state.conversation.get_untracked().id.as_deref()
+                            == Some(response.context_id.as_str())
+                    {
+                        let assistant_msg =
+                            Message::assistant(&response.response, Some(response.agent));
+                        state
+                            .conversation
+                            .update(|c| c.messages.push(assistant_msg));
+                    }
+                }
+                Err(e) => {
+                    state.conversation.update(|c| {
+                        c.messages.push(Message {
+                            id: uuid::Uuid::new_v4().to_string(),
+                            role: MessageRole::System,
+                            content: format!("Error: {}", e),
+                            timestamp: chrono::Utc::now(),
+                            agent_type: None,
+                            tool_calls: vec![],
+                            is_streaming: false,
+                        });
+                    });
+                    state.set_error(e);
+                }
+            }
+            is_deciding.set(false);
+        });
+    };
+    let approve = decide.clone();
+    let reject = decide;
+
+    view! {
+        <div class="card p-4 text-sm animate-fade-in border border-[var(--accent-warning)]">
+            <div class="flex items-center gap-2 mb-3">
+                <span class="text-[var(--accent-warning)]">"⏸"</span>
+                <span class="font-medium text-[var(--text-primary)]">
+                    {format!("{} is waiting for your approval", approval.agent)}
+                </span>
+            </div>
+            <div class="flex flex-col gap-2 mb-3">
+                {approval.tool_calls.into_iter().map(|call| view! {
+                    <div>
+                        <div class="flex items-center gap-2 mb-1">
+                            <span class="text-[var(--accent-warning)]">"🔧"</span>
+                            <span class="font-medium text-[var(--text-primary)]">{call.name}</span>
+                        </div>
+                        <div class="code-block">
+                            <div class="code-block-content text-xs">
+                                {serde_json::to_string_pretty(&call.arguments).unwrap_or_default()}
+                            </div>
+                        </div>
+                    </div>
+                }).collect::<Vec<_>>()}
+            </div>
+            <input
+                type="text"
+                class="input w-full mb-3"
+                placeholder="Reason for rejecting (optional)"
+                prop:value=move || reason.get()
+                on:input=move |ev| reason.set(event_target_value(&ev))
+            />
+            <div class="flex gap-2 justify-end">
+                <button
+                    on:click=move |_| reject(false)
+                    disabled=move || is_deciding.get()
+                    class="btn btn-ghost"
+                >
+                    "Reject"
+                </button>
+                <button
+                    on:click=move |_| approve(true)
+                    disabled=move || is_deciding.get()
+                    class="btn btn-primary"
+                >
+                    "Approve"
+                </button>
+            </div>
+        </div>
+    }
+}
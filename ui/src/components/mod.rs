@@ -1,5 +1,6 @@
 //! Reusable UI components
 
+pub mod approval_card;
 pub mod chat_input;
 pub mod chat_message;
 pub mod header;
@@ -7,6 +8,7 @@ pub mod loading;
 pub mod agent_selector;
 pub mod sidebar;
 
+pub use approval_card::ApprovalCard;
 pub use chat_input::ChatInput;
 pub use chat_message::ChatMessage;
 pub use header::Header;
@@ -3,7 +3,7 @@
 use leptos::prelude::*;
 use crate::state::AppState;
 
-/// Sidebar with agent list and settings
+/// Sidebar with agent and project lists and settings
 #[component]
 pub fn Sidebar(
     /// Whether sidebar is open (mobile)
@@ -20,6 +20,15 @@ pub fn Sidebar(
         });
     };
 
+    // Switching projects starts a new chat in the selected one
+    let switch_project = move |project: Option<String>| {
+        state.project.set(project);
+        state.conversation.update(|c| {
+            c.id = None;
+            c.messages.clear();
+        });
+    };
+
     view! {
         // Overlay for mobile
         <Show when=move || is_open.get()>
@@ -95,6 +104,39 @@ pub fn Sidebar(
                     </div>
                 </div>
                 
+                // Projects section
+                <div>
+                    <h3 class="text-xs font-semibold text-[var(--text-muted)] uppercase tracking-wider mb-3 px-2">
+                        "Projects"
+                    </h3>
+                    <div class="space-y-1">
+                        <AgentButton
+                            name="No project".to_string()
+                            emoji="💬".to_string()
+                            description="Conversations outside any project".to_string()
+                            is_selected=Signal::derive(move || state.project.get().is_none())
+                            on_click=move |_| switch_project(None)
+                        />
+
+                        {move || {
+                            let projects = state.projects.get();
+                            projects.into_iter().map(|project| {
+                                let id = project.id.clone();
+                                let id_clone = id.clone();
+                                view! {
+                                    <AgentButton
+                                        name=project.name.clone()
+                                        emoji="📁".to_string()
+                                        description=project.description.clone().unwrap_or_default()
+                                        is_selected=Signal::derive(move || state.project.get().as_deref() == Some(&id))
+                                        on_click=move |_| switch_project(Some(id_clone.clone()))
+                                    />
+                                }
+                            }).collect::<Vec<_>>()
+                        }}
+                    </div>
+                </div>
+
                 // Workflows section
                 <div>
                     <h3 class="text-xs font-semibold text-[var(--text-muted)] uppercase tracking-wider mb-3 px-2">
@@ -4,8 +4,8 @@ use leptos::prelude::*;
 use leptos::task::spawn_local;
 use leptos_router::hooks::use_navigate;
 use web_sys::{ScrollBehavior, ScrollIntoViewOptions};
-use crate::api::{load_agents, load_workflows, send_chat, stream_chat};
-use crate::components::{ChatInput, ChatMessage, Header, Sidebar, TypingIndicator};
+use crate::api::{load_agents, load_approvals, load_projects, load_workflows, send_chat, stream_chat};
+use crate::components::{ApprovalCard, ChatInput, ChatMessage, Header, Sidebar, TypingIndicator};
 use crate::state::AppState;
 use crate::types::{Message, MessageRole};
 
@@ -35,11 +35,13 @@ pub fn ChatPage() -> impl IntoView {
         }
     });
     
-    // Load agents and workflows on mount
+    // Load agents, workflows and projects on mount
     let state_for_load = state.clone();
     Effect::new(move |_| {
         load_agents(state_for_load.clone());
         load_workflows(state_for_load.clone());
+        load_projects(state_for_load.clone());
+        load_approvals(state_for_load.clone());
     });
     
     // Auto-scroll to bottom when new messages arrive
@@ -97,6 +99,7 @@ pub fn ChatPage() -> impl IntoView {
             let base_url = state.api_base.get_untracked();
             let token = state.token.get_untracked().unwrap_or_default();
             let context_id = state.conversation.get_untracked().id.clone();
+            let project_id = state.project.get_untracked();
             let msg_id_clone = msg_id.clone();
             
             // Try streaming first
@@ -106,6 +109,7 @@ pub fn ChatPage() -> impl IntoView {
                 &message_text,
                 context_id.clone(),
                 agent.clone(),
+                project_id.clone(),
                 move |event| {
                     match event.event.as_str() {
                         "start" => {
@@ -173,15 +177,20 @@ pub fn ChatPage() -> impl IntoView {
                 });
                 
                 // Use regular chat endpoint
-                match send_chat(&base_url, &token, &message_text, context_id, agent.clone()).await {
+                match send_chat(&base_url, &token, &message_text, context_id, agent.clone(), project_id).await {
                     Ok(response) => {
-                        // Add assistant response
-                        let assistant_msg = Message::assistant(&response.response, Some(response.agent));
-                        
                         state.conversation.update(|c| {
-                            c.id = Some(response.context_id);
-                            c.messages.push(assistant_msg);
+                            c.id = Some(response.context_id.clone());
                         });
+                        match response.approval {
+                            // The run waits for the user to approve its tool calls
+                            Some(approval) => state.approvals.update(|a| a.push(approval)),
+                            None => {
+                                // Add assistant response
+                                let assistant_msg = Message::assistant(&response.response, Some(response.agent));
+                                state.conversation.update(|c| c.messages.push(assistant_msg));
+                            }
+                        }
                     }
                     Err(e) => {
                         // Add error as system message
@@ -203,6 +212,7 @@ pub fn ChatPage() -> impl IntoView {
             
             is_sending.set(false);
             streaming_msg_id.set(None);
+            load_approvals(state.clone());
             scroll_to_bottom();
         });
     };
@@ -312,6 +322,18 @@ pub fn ChatPage() -> impl IntoView {
                             }
                         }
                         
+                        // Runs in this conversation waiting for approval
+                        {
+                            let state = state.clone();
+                            move || {
+                                let context_id = state.conversation.get().id;
+                                state.approvals.get().into_iter()
+                                    .filter(|a| context_id.as_deref() == Some(a.conversation_id.as_str()))
+                                    .map(|approval| view! { <ApprovalCard approval=approval /> })
+                                    .collect::<Vec<_>>()
+                            }
+                        }
+                        
                         // Typing indicator
                         <Show when=move || is_sending.get()>
                             <TypingIndicator agent_name=selected_agent.get().unwrap_or_else(|| "AI".to_string()) />
@@ -2,7 +2,7 @@
 
 use leptos::prelude::*;
 use gloo_storage::{LocalStorage, Storage};
-use crate::types::{AuthResponse, Conversation, AgentInfo, WorkflowInfo};
+use crate::types::{AuthResponse, Conversation, AgentInfo, PendingApproval, Project, WorkflowInfo};
 
 const STORAGE_KEY_TOKEN: &str = "ares_token";
 const STORAGE_KEY_REFRESH: &str = "ares_refresh_token";
@@ -18,8 +18,14 @@ pub struct AppState {
     pub agents: RwSignal<Vec<AgentInfo>>,
     /// Available workflows
     pub workflows: RwSignal<Vec<WorkflowInfo>>,
+    /// Projects of the user's space
+    pub projects: RwSignal<Vec<Project>>,
+    /// Project new conversations are started in
+    pub project: RwSignal<Option<String>>,
     /// Current conversation
     pub conversation: RwSignal<Conversation>,
+    /// Agent runs waiting for the user's approval
+    pub approvals: RwSignal<Vec<PendingApproval>>,
     /// Loading state
     pub is_loading: RwSignal<bool>,
     /// Error message
@@ -38,7 +44,10 @@ impl AppState {
             refresh_token: RwSignal::new(refresh),
             agents: RwSignal::new(vec![]),
             workflows: RwSignal::new(vec![]),
+            projects: RwSignal::new(vec![]),
+            project: RwSignal::new(None),
             conversation: RwSignal::new(Conversation::default()),
+            approvals: RwSignal::new(vec![]),
             is_loading: RwSignal::new(false),
             error: RwSignal::new(None),
             api_base: RwSignal::new("http://localhost:3000".to_string()),
@@ -53,10 +62,16 @@ impl AppState {
 
     pub fn save_auth(&self, auth: &AuthResponse) {
         let _ = LocalStorage::set(STORAGE_KEY_TOKEN, &auth.access_token);
-        let _ = LocalStorage::set(STORAGE_KEY_REFRESH, &auth.refresh_token);
-        
         self.token.set(Some(auth.access_token.clone()));
-        self.refresh_token.set(Some(auth.refresh_token.clone()));
+
+        // With cookie refresh tokens the server doesn't send it to scripts
+        if auth.refresh_token.is_empty() {
+            LocalStorage::delete(STORAGE_KEY_REFRESH);
+            self.refresh_token.set(None);
+        } else {
+            let _ = LocalStorage::set(STORAGE_KEY_REFRESH, &auth.refresh_token);
+            self.refresh_token.set(Some(auth.refresh_token.clone()));
+        }
     }
 
     pub fn clear_auth(&self) {
@@ -21,6 +21,8 @@ pub struct RegisterRequest {
 #[derive(Debug, Clone, Deserialize)]
 pub struct AuthResponse {
     pub access_token: String,
+    /// Empty when the server keeps the refresh token in a cookie
+    #[serde(default)]
     pub refresh_token: String,
     pub expires_in: i64,
 }
@@ -33,6 +35,9 @@ pub struct ChatRequest {
     pub context_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub agent_type: Option<String>,
+    /// Project to start a new conversation in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
 }
 
 /// Chat response
@@ -43,6 +48,9 @@ pub struct ChatResponse {
     pub agent: String,
     #[serde(default)]
     pub sources: Option<Vec<Source>>,
+    /// Set when the run paused until its tool calls are approved
+    #[serde(default)]
+    pub approval: Option<PendingApproval>,
 }
 
 /// Source reference in responses
@@ -62,6 +70,32 @@ pub struct ToolCallInfo {
     pub result: Option<String>,
 }
 
+/// Tool call waiting for approval
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct PendingToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: serde_json::Value,
+}
+
+/// Agent run paused until its tool calls are approved
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct PendingApproval {
+    pub id: String,
+    pub conversation_id: String,
+    pub agent: String,
+    pub tool_calls: Vec<PendingToolCall>,
+    pub created_at: i64,
+}
+
+/// Decision on a paused run's tool calls
+#[derive(Debug, Clone, Serialize)]
+pub struct ApprovalRequest {
+    pub approve: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
 /// Agent info from the API
 #[derive(Debug, Clone, Deserialize)]
 pub struct AgentInfo {
@@ -81,6 +115,15 @@ pub struct WorkflowInfo {
     pub parallel_subagents: bool,
 }
 
+/// Project grouping conversations, files and collections
+#[derive(Debug, Clone, Deserialize)]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
 /// User memory
 #[derive(Debug, Clone, Deserialize)]
 pub struct UserMemory {